@@ -0,0 +1,592 @@
+//! Local socket accepting admin commands, started by the [`EngineBuilder`] when enabled in the
+//! [`AdminConfig`] returned by [`NodeConfig::admin`], so that operators can drive a node from
+//! the command line without exposing an HTTP port, eg. `echo pause | socat - UNIX:admin.sock`.
+//!
+//! The socket is a Unix domain socket, only accessible to the user running the node,
+//! or a named pipe on Windows. Each connection carries a single command on one line,
+//! answered with `ok`, followed by its output if any, or with `error: <reason>`.
+//!
+//! - `dump_state` prints the state of consensus at the current height
+//! - `list_peers` prints the connected peers, one per line
+//! - `duplicate_senders [<count>]` prints the peers which sent the most duplicate messages,
+//!   10 of them unless given, with their number of duplicates
+//! - `dump_topology <path>` writes the local view of the graph of the network to a file
+//!   as JSON, on the host of the node, eg. to draw it offline
+//! - `ban_peer <peer id> [<seconds>]` bans a peer, for the given duration or until unbanned
+//! - `unban_peer <peer id>` lifts the ban of a peer
+//! - `export_address_book <path>` writes the peers known to discovery to a file, on the host
+//!   of the node, to seed other nodes from it
+//! - `import_address_book <path>` dials the peers of an address book read from a file,
+//!   eg. exported by another node
+//! - `pause` and `resume` stop and resume participating in consensus
+//! - `sync <from height> <to height>` fetches the values in the given range from the peers
+//! - `pause_sync` and `resume_sync` stop and resume requesting values from the peers
+//! - `set_log_filter <directives>` replaces the filter of the logs, eg. with
+//!   `info,malachitebft_discovery=debug`, and prints the previous one to restore it later
+//!
+//! The commands are mapped onto the same requests as the application can send on [`Channels`].
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use derive_where::derive_where;
+use eyre::WrapErr;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, Instrument};
+
+use malachitebft_app::types::core::{Context, Height};
+use malachitebft_app::types::PeerId;
+use malachitebft_engine::network::AddressBook;
+
+#[cfg(doc)]
+use crate::{
+    app::config::{AdminConfig, NodeConfig},
+    Channels, EngineBuilder,
+};
+use crate::{
+    ConsensusRequest, ConsensusRequestError, LogFilterError, NetworkMsg, NetworkRequest,
+    SyncRequest,
+};
+
+/// How long to wait for the engine to reply to the request serving a command
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of peers printed by `duplicate_senders` unless given
+const DUPLICATE_SENDERS: usize = 10;
+
+/// Longest command accepted, in bytes
+const MAX_COMMAND_LEN: u64 = 1024;
+
+#[derive_where(Clone)]
+struct AdminState<Ctx: Context> {
+    tx_request: mpsc::Sender<ConsensusRequest<Ctx>>,
+    tx_network: mpsc::Sender<NetworkMsg<Ctx>>,
+    tx_net_request: mpsc::Sender<NetworkRequest>,
+    tx_sync_request: mpsc::Sender<SyncRequest<Ctx>>,
+}
+
+/// Create the admin socket at the given path and serve the commands in the background.
+///
+/// Fails if the socket cannot be created, so that a misconfigured node does not start
+/// without its admin socket.
+pub(crate) async fn spawn_admin_server<Ctx: Context>(
+    socket_path: PathBuf,
+    tx_request: mpsc::Sender<ConsensusRequest<Ctx>>,
+    tx_network: mpsc::Sender<NetworkMsg<Ctx>>,
+    tx_net_request: mpsc::Sender<NetworkRequest>,
+    tx_sync_request: mpsc::Sender<SyncRequest<Ctx>>,
+) -> eyre::Result<JoinHandle<()>> {
+    let state = AdminState {
+        tx_request,
+        tx_network,
+        tx_net_request,
+        tx_sync_request,
+    };
+
+    let listener = listener::bind(&socket_path).wrap_err_with(|| {
+        format!(
+            "Failed to create the admin socket at {}",
+            socket_path.display()
+        )
+    })?;
+
+    let span = tracing::info_span!("admin");
+
+    let handle = tokio::spawn(
+        async move {
+            info!(path = %socket_path.display(), "Serving admin commands");
+
+            if let Err(e) = listener::serve(listener, &socket_path, state).await {
+                error!("Admin socket failed: {e}");
+            }
+        }
+        .instrument(span),
+    );
+
+    Ok(handle)
+}
+
+#[cfg(unix)]
+mod listener {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::path::Path;
+
+    use tokio::net::UnixListener;
+
+    use super::*;
+
+    pub fn bind(path: &Path) -> std::io::Result<UnixListener> {
+        // Remove the socket left over by a previous run, but nothing else
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+
+        Ok(listener)
+    }
+
+    pub async fn serve<Ctx: Context>(
+        listener: UnixListener,
+        _path: &Path,
+        state: AdminState<Ctx>,
+    ) -> std::io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            tokio::spawn(handle_connection(stream, state.clone()).in_current_span());
+        }
+    }
+}
+
+#[cfg(windows)]
+mod listener {
+    use std::path::Path;
+
+    use tokio::net::windows::named_pipe::{NamedPipeServer, ServerOptions};
+
+    use super::*;
+
+    pub fn bind(path: &Path) -> std::io::Result<NamedPipeServer> {
+        ServerOptions::new()
+            .first_pipe_instance(true)
+            .reject_remote_clients(true)
+            .create(path)
+    }
+
+    pub async fn serve<Ctx: Context>(
+        mut server: NamedPipeServer,
+        path: &Path,
+        state: AdminState<Ctx>,
+    ) -> std::io::Result<()> {
+        loop {
+            server.connect().await?;
+
+            // Create the next instance of the pipe before handling the connected one,
+            // so that clients always find the pipe
+            let connected = server;
+            server = ServerOptions::new()
+                .reject_remote_clients(true)
+                .create(path)?;
+
+            tokio::spawn(handle_connection(connected, state.clone()).in_current_span());
+        }
+    }
+}
+
+async fn handle_connection<Ctx: Context>(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    state: AdminState<Ctx>,
+) {
+    let (reader, mut writer) = tokio::io::split(stream);
+
+    let mut line = String::new();
+    let mut reader = BufReader::new(reader.take(MAX_COMMAND_LEN));
+
+    let reply = match reader.read_line(&mut line).await {
+        Ok(_) => {
+            debug!(command = line.trim(), "Received admin command");
+
+            match run_command(&state, line.trim()).await {
+                Ok(output) if output.is_empty() => "ok\n".to_string(),
+                Ok(output) => format!("ok\n{output}\n"),
+                Err(e) => format!("error: {e}\n"),
+            }
+        }
+        Err(e) => format!("error: failed to read the command: {e}\n"),
+    };
+
+    if let Err(e) = writer.write_all(reply.as_bytes()).await {
+        debug!("Failed to reply to admin command: {e}");
+    }
+
+    let _ = writer.shutdown().await;
+}
+
+/// Why a command could not be run
+#[derive(Debug, Error)]
+enum AdminError {
+    #[error("unknown command `{0}`, expected one of: dump_state, list_peers, duplicate_senders, dump_topology, ban_peer, unban_peer, export_address_book, import_address_book, pause, resume, sync, pause_sync, resume_sync, set_log_filter")]
+    UnknownCommand(String),
+
+    #[error("usage: {0}")]
+    Usage(&'static str),
+
+    #[error("invalid {0}: {1}")]
+    InvalidArgument(&'static str, String),
+
+    #[error("failed to write {0}: {1}")]
+    Write(String, std::io::Error),
+
+    #[error("{0} did not reply within {REPLY_TIMEOUT:?}")]
+    Timeout(&'static str),
+
+    #[error("failed to query {0}: {1}")]
+    Request(&'static str, ConsensusRequestError),
+
+    #[error("{0} is not running")]
+    NotRunning(&'static str),
+
+    #[error(transparent)]
+    LogFilter(LogFilterError),
+}
+
+/// Wait for the reply of a component of the engine to a request
+async fn query<T>(
+    component: &'static str,
+    request: impl Future<Output = Result<T, ConsensusRequestError>>,
+) -> Result<T, AdminError> {
+    match tokio::time::timeout(REPLY_TIMEOUT, request).await {
+        Ok(Ok(reply)) => Ok(reply),
+        Ok(Err(e)) => Err(AdminError::Request(component, e)),
+        Err(_) => Err(AdminError::Timeout(component)),
+    }
+}
+
+async fn run_command<Ctx: Context>(
+    state: &AdminState<Ctx>,
+    command: &str,
+) -> Result<String, AdminError> {
+    let mut args = command.split_whitespace();
+    let name = args.next().unwrap_or_default();
+    let args = args.collect::<Vec<_>>();
+
+    match (name, args.as_slice()) {
+        ("dump_state", []) => {
+            let dump = query("consensus", ConsensusRequest::dump_state(&state.tx_request))
+                .await?
+                .ok_or(AdminError::NotRunning("consensus"))?;
+
+            Ok(format!("{dump:#?}"))
+        }
+
+        ("list_peers", []) => {
+            let peers = query("network", NetworkRequest::list_peers(&state.tx_net_request))
+                .await?
+                .ok_or(AdminError::NotRunning("network"))?;
+
+            let lines = peers
+                .iter()
+                .map(|peer| {
+                    format!(
+                        "{} {} {}",
+                        peer.peer_id,
+                        peer.kind.as_str(),
+                        peer.moniker.as_deref().unwrap_or("-")
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            Ok(lines.join("\n"))
+        }
+
+        ("duplicate_senders", rest) if rest.len() <= 1 => {
+            let count = rest
+                .first()
+                .map(|count| {
+                    count
+                        .parse()
+                        .map_err(|e| AdminError::InvalidArgument("count", format!("{e}")))
+                })
+                .transpose()?
+                .unwrap_or(DUPLICATE_SENDERS);
+
+            let peers = query(
+                "network",
+                NetworkRequest::top_duplicate_senders(&state.tx_net_request, count),
+            )
+            .await?
+            .ok_or(AdminError::NotRunning("network"))?;
+
+            let lines = peers
+                .iter()
+                .map(|peer| {
+                    format!(
+                        "{} {} {}",
+                        peer.peer_id,
+                        peer.moniker.as_deref().unwrap_or("-"),
+                        peer.duplicate_messages
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            Ok(lines.join("\n"))
+        }
+        ("duplicate_senders", _) => Err(AdminError::Usage("duplicate_senders [<count>]")),
+
+        ("dump_topology", [path]) => {
+            let topology = query(
+                "network",
+                NetworkRequest::dump_topology(&state.tx_net_request),
+            )
+            .await?
+            .ok_or(AdminError::NotRunning("network"))?;
+
+            let json = serde_json::to_vec_pretty(&topology)
+                .map_err(|e| AdminError::Write(path.to_string(), e.into()))?;
+
+            std::fs::write(path, json).map_err(|e| AdminError::Write(path.to_string(), e))?;
+
+            let discovery = &topology.discovery;
+            let peers =
+                discovery.outbound.len() + discovery.inbound.len() + discovery.ephemeral.len();
+
+            Ok(format!("dumped the topology of {peers} connected peers"))
+        }
+        ("dump_topology", _) => Err(AdminError::Usage("dump_topology <path>")),
+
+        ("ban_peer", [peer_id, rest @ ..]) if rest.len() <= 1 => {
+            let peer_id = parse_peer_id(peer_id)?;
+            let duration = rest
+                .first()
+                .map(|secs| {
+                    secs.parse()
+                        .map(Duration::from_secs)
+                        .map_err(|e| AdminError::InvalidArgument("duration", format!("{e}")))
+                })
+                .transpose()?;
+
+            send(state, NetworkMsg::BanPeer(peer_id, duration)).await
+        }
+        ("ban_peer", _) => Err(AdminError::Usage("ban_peer <peer id> [<seconds>]")),
+
+        ("unban_peer", [peer_id]) => {
+            let peer_id = parse_peer_id(peer_id)?;
+            send(state, NetworkMsg::UnbanPeer(peer_id)).await
+        }
+        ("unban_peer", _) => Err(AdminError::Usage("unban_peer <peer id>")),
+
+        ("export_address_book", [path]) => {
+            let book = query(
+                "network",
+                NetworkRequest::export_address_book(&state.tx_net_request),
+            )
+            .await?
+            .ok_or(AdminError::NotRunning("network"))?;
+
+            book.save(Path::new(path))
+                .map_err(|e| AdminError::Write(path.to_string(), e))?;
+
+            Ok(format!("exported {} peers", book.len()))
+        }
+        ("export_address_book", _) => Err(AdminError::Usage("export_address_book <path>")),
+
+        ("import_address_book", [path]) => {
+            let book = AddressBook::load(Path::new(path))
+                .map_err(|e| AdminError::InvalidArgument("address book", format!("{e}")))?;
+
+            let dialed = query(
+                "network",
+                NetworkRequest::import_address_book(&state.tx_net_request, book),
+            )
+            .await?
+            .ok_or(AdminError::NotRunning("network"))?;
+
+            Ok(format!("dialing {dialed} peers"))
+        }
+        ("import_address_book", _) => Err(AdminError::Usage("import_address_book <path>")),
+
+        ("pause", []) => {
+            query("consensus", ConsensusRequest::pause(&state.tx_request)).await?;
+            Ok(String::new())
+        }
+
+        ("resume", []) => {
+            query("consensus", ConsensusRequest::resume(&state.tx_request)).await?;
+            Ok(String::new())
+        }
+
+        ("sync", [from, to]) => {
+            let from = parse_height::<Ctx>(from)?;
+            let to = parse_height::<Ctx>(to)?;
+
+            if from > to {
+                return Err(AdminError::InvalidArgument(
+                    "range",
+                    format!("{from} is above {to}"),
+                ));
+            }
+
+            let syncing = query(
+                "sync",
+                SyncRequest::backfill(&state.tx_sync_request, from..=to),
+            )
+            .await?;
+
+            sync_enabled(syncing)
+        }
+        ("sync", _) => Err(AdminError::Usage("sync <from height> <to height>")),
+
+        ("pause_sync", []) => {
+            let paused = query("sync", SyncRequest::pause(&state.tx_sync_request)).await?;
+            sync_enabled(paused)
+        }
+
+        ("resume_sync", []) => {
+            let resumed = query("sync", SyncRequest::resume(&state.tx_sync_request)).await?;
+            sync_enabled(resumed)
+        }
+
+        ("set_log_filter", [directives]) => {
+            let previous = query(
+                "network",
+                NetworkRequest::set_log_filter(&state.tx_net_request, *directives),
+            )
+            .await?
+            .map_err(AdminError::LogFilter)?;
+
+            Ok(format!("previous log filter: {previous}"))
+        }
+        ("set_log_filter", _) => Err(AdminError::Usage("set_log_filter <directives>")),
+
+        _ => Err(AdminError::UnknownCommand(command.to_string())),
+    }
+}
+
+/// Send a message to the network on behalf of the application
+async fn send<Ctx: Context>(
+    state: &AdminState<Ctx>,
+    msg: NetworkMsg<Ctx>,
+) -> Result<String, AdminError> {
+    state
+        .tx_network
+        .send(msg)
+        .await
+        .map_err(|_| AdminError::NotRunning("network"))?;
+
+    Ok(String::new())
+}
+
+fn sync_enabled(enabled: bool) -> Result<String, AdminError> {
+    if enabled {
+        Ok(String::new())
+    } else {
+        Err(AdminError::NotRunning("sync"))
+    }
+}
+
+fn parse_peer_id(peer_id: &str) -> Result<PeerId, AdminError> {
+    peer_id
+        .parse()
+        .map_err(|e| AdminError::InvalidArgument("peer id", format!("{e}")))
+}
+
+fn parse_height<Ctx: Context>(height: &str) -> Result<Ctx::Height, AdminError> {
+    height
+        .parse()
+        .map(|height| Ctx::Height::ZERO.increment_by(height))
+        .map_err(|e| AdminError::InvalidArgument("height", format!("{e}")))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use malachitebft_test::{Height as TestHeight, TestContext};
+    use tokio::net::UnixStream;
+
+    use super::*;
+    use crate::{Channels, MockEngine};
+
+    async fn serve(channels: &Channels<TestContext>, dir: &tempfile::TempDir) -> PathBuf {
+        let path = dir.path().join("admin.sock");
+
+        spawn_admin_server(
+            path.clone(),
+            channels.requests.clone(),
+            channels.network.clone(),
+            channels.net_requests.clone(),
+            channels.sync_requests.clone(),
+        )
+        .await
+        .unwrap();
+
+        path
+    }
+
+    async fn command(path: &PathBuf, command: &str) -> String {
+        let mut stream = UnixStream::connect(path).await.unwrap();
+        stream
+            .write_all(format!("{command}\n").as_bytes())
+            .await
+            .unwrap();
+
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).await.unwrap();
+        reply
+    }
+
+    #[tokio::test]
+    async fn ban_peer_is_sent_to_the_network() {
+        let (mut engine, channels) = MockEngine::<TestContext>::new();
+        let dir = tempfile::tempdir().unwrap();
+        let path = serve(&channels, &dir).await;
+
+        let peer_id = PeerId::random();
+
+        let reply = command(&path, &format!("ban_peer {peer_id} 60")).await;
+        assert_eq!(reply, "ok\n");
+
+        match engine.next_published().await.unwrap() {
+            NetworkMsg::BanPeer(banned, duration) => {
+                assert_eq!(banned, peer_id);
+                assert_eq!(duration, Some(Duration::from_secs(60)));
+            }
+            _ => panic!("expected the peer to be banned"),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_commands_are_rejected() {
+        let (_engine, channels) = MockEngine::<TestContext>::new();
+        let dir = tempfile::tempdir().unwrap();
+        let path = serve(&channels, &dir).await;
+
+        let reply = command(&path, "reboot").await;
+        assert!(
+            reply.starts_with("error: unknown command `reboot`"),
+            "{reply}"
+        );
+
+        let reply = command(&path, "ban_peer").await;
+        assert_eq!(reply, "error: usage: ban_peer <peer id> [<seconds>]\n");
+
+        let reply = command(&path, "dump_topology").await;
+        assert_eq!(reply, "error: usage: dump_topology <path>\n");
+
+        let reply = command(&path, "duplicate_senders many").await;
+        assert_eq!(
+            reply,
+            "error: invalid count: invalid digit found in string\n"
+        );
+
+        let book = dir.path().join("address_book");
+        std::fs::write(&book, "/ip4/10.0.0.1/tcp/27000\n").unwrap();
+
+        let reply = command(&path, &format!("import_address_book {}", book.display())).await;
+        assert_eq!(
+            reply,
+            "error: invalid address book: invalid address on line 1: address must end with /p2p/<peer id>\n"
+        );
+
+        let reply = command(&path, "set_log_filter debug").await;
+        assert_eq!(
+            reply,
+            "error: the log filter cannot be changed at runtime, no reloadable filter was installed\n"
+        );
+
+        let reply = command(&path, "sync 5 1").await;
+        assert_eq!(
+            reply,
+            format!(
+                "error: invalid range: {} is above {}\n",
+                TestHeight::new(5),
+                TestHeight::new(1)
+            )
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Capacity of the channels between the engine and the application,
+//! and what happens to a message sent on a channel which is full.
+
+use std::collections::VecDeque;
+
+use eyre::eyre;
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use malachitebft_app::metrics::prometheus as prometheus_client;
+use malachitebft_app::metrics::prometheus::encoding::EncodeLabelSet;
+use malachitebft_app::metrics::prometheus::metrics::counter::Counter;
+use malachitebft_app::metrics::prometheus::metrics::family::Family;
+use malachitebft_app::metrics::SharedRegistry;
+
+#[cfg(doc)]
+use crate::{Channels, ConsensusRequest};
+
+/// Capacity of the channel carrying messages from consensus to the application
+const DEFAULT_CONSENSUS_CAPACITY: usize = 128;
+
+/// Capacity of the channel carrying messages from the application to the network
+const DEFAULT_NETWORK_CAPACITY: usize = 1;
+
+/// What happens to a message sent on a channel which is full
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The sender waits until the receiver makes room for the message.
+    ///
+    /// Senders which must not wait, such as the network actor or the helpers
+    /// sending requests like [`ConsensusRequest::dump_state`], drop the message instead.
+    #[default]
+    Block,
+
+    /// The oldest message in the channel is dropped to make room for the new one,
+    /// and counted in the `malachitebft_app_channel_dropped_messages` metric.
+    ///
+    /// Dropping a message which expects a reply fails the request it is part of.
+    DropOldest,
+
+    /// Sending the message fails, and the sender handles the error
+    Error,
+}
+
+/// Capacity and overflow policy of a channel
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChannelConfig {
+    /// Number of messages the channel holds before it is full, at least 1
+    pub capacity: usize,
+    /// What happens to a message sent on the channel when it is full
+    pub overflow: OverflowPolicy,
+}
+
+impl ChannelConfig {
+    /// A blocking channel with the given capacity
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+
+    pub fn with_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+/// Configuration of every channel created by the [`EngineBuilder`](crate::EngineBuilder),
+/// named after the fields of [`Channels`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChannelsConfig {
+    /// Messages from consensus to the application
+    pub consensus: ChannelConfig,
+    /// Messages from the application to the network
+    pub network: ChannelConfig,
+    /// Requests from the application to consensus
+    pub requests: ChannelConfig,
+    /// Requests from the application to the network
+    pub net_requests: ChannelConfig,
+    /// Requests from the application to sync
+    pub sync_requests: ChannelConfig,
+    /// Messages from peers on the application protocols
+    pub peer_messages: ChannelConfig,
+    /// Transactions gossiped by the peers
+    pub mempool: ChannelConfig,
+    /// Connectivity events of the network
+    pub connectivity: ChannelConfig,
+}
+
+impl ChannelsConfig {
+    /// Blocking channels, with the given capacity for the requests and the network events
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            consensus: ChannelConfig::new(DEFAULT_CONSENSUS_CAPACITY),
+            network: ChannelConfig::new(DEFAULT_NETWORK_CAPACITY),
+            requests: ChannelConfig::new(capacity),
+            net_requests: ChannelConfig::new(capacity),
+            sync_requests: ChannelConfig::new(capacity),
+            peer_messages: ChannelConfig::new(capacity),
+            mempool: ChannelConfig::new(capacity),
+            connectivity: ChannelConfig::new(capacity),
+        }
+    }
+
+    pub(crate) fn validate(&self) -> eyre::Result<()> {
+        let channels = [
+            ("consensus", self.consensus),
+            ("network", self.network),
+            ("requests", self.requests),
+            ("net_requests", self.net_requests),
+            ("sync_requests", self.sync_requests),
+            ("peer_messages", self.peer_messages),
+            ("mempool", self.mempool),
+            ("connectivity", self.connectivity),
+        ];
+
+        for (name, config) in channels {
+            if config.capacity == 0 {
+                return Err(eyre!("Capacity of the `{name}` channel cannot be zero"));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Labels for the dropped messages metric
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ChannelLabels {
+    channel: String,
+}
+
+impl ChannelLabels {
+    fn new(channel: &str) -> Self {
+        Self {
+            channel: channel.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ChannelMetrics {
+    dropped_messages: Family<ChannelLabels, Counter>,
+}
+
+impl ChannelMetrics {
+    pub(crate) fn register(registry: &SharedRegistry) -> Self {
+        let dropped_messages = Family::<ChannelLabels, Counter>::default();
+
+        registry.with_prefix("malachitebft_app_channel", |registry| {
+            registry.register(
+                "dropped_messages",
+                "Number of messages dropped from a full channel, per channel",
+                dropped_messages.clone(),
+            );
+        });
+
+        Self { dropped_messages }
+    }
+}
+
+/// Create a channel with the given configuration.
+///
+/// Channels dropping their oldest messages are made of a relay task between the
+/// returned ends, which holds up to `capacity` messages not yet received.
+pub(crate) fn channel<T>(
+    name: &'static str,
+    config: ChannelConfig,
+    metrics: &ChannelMetrics,
+) -> (mpsc::Sender<T>, mpsc::Receiver<T>)
+where
+    T: Send + 'static,
+{
+    match config.overflow {
+        OverflowPolicy::Block | OverflowPolicy::Error => mpsc::channel(config.capacity),
+        OverflowPolicy::DropOldest => {
+            let (tx_in, rx_in) = mpsc::channel(config.capacity);
+            let (tx_out, rx_out) = mpsc::channel(1);
+
+            let dropped = metrics
+                .dropped_messages
+                .get_or_create(&ChannelLabels::new(name))
+                .clone();
+
+            tokio::spawn(relay(name, config.capacity, rx_in, tx_out, dropped));
+
+            (tx_in, rx_out)
+        }
+    }
+}
+
+/// Forward the messages as soon as the receiver has room for them,
+/// dropping the oldest ones when more than `capacity` are waiting
+async fn relay<T>(
+    name: &'static str,
+    capacity: usize,
+    mut rx_in: mpsc::Receiver<T>,
+    tx_out: mpsc::Sender<T>,
+    dropped: Counter,
+) {
+    let mut queue = VecDeque::with_capacity(capacity);
+
+    loop {
+        tokio::select! {
+            _ = tx_out.closed() => return,
+
+            msg = rx_in.recv() => {
+                let Some(msg) = msg else { break };
+
+                if queue.len() >= capacity {
+                    queue.pop_front();
+                    dropped.inc();
+                    debug!(channel = name, "Channel is full, dropped its oldest message");
+                }
+
+                queue.push_back(msg);
+            }
+
+            Ok(permit) = tx_out.reserve(), if !queue.is_empty() => {
+                if let Some(msg) = queue.pop_front() {
+                    permit.send(msg);
+                }
+            }
+        }
+    }
+
+    // The senders are gone, deliver the messages left
+    for msg in queue {
+        if tx_out.send(msg).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> ChannelMetrics {
+        ChannelMetrics {
+            dropped_messages: Family::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_latest_messages() {
+        let metrics = metrics();
+        let config = ChannelConfig::new(2).with_overflow(OverflowPolicy::DropOldest);
+        let (tx, mut rx) = channel("test", config, &metrics);
+
+        for i in 0..5 {
+            tx.send(i).await.unwrap();
+        }
+
+        drop(tx);
+
+        let mut received = Vec::new();
+        while let Some(i) = rx.recv().await {
+            received.push(i);
+        }
+
+        // At most one message handed over to the receiver, and the last two held by the relay
+        assert!(received.len() <= 3);
+        assert!(received.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(received.last(), Some(&4));
+
+        let dropped = metrics
+            .dropped_messages
+            .get_or_create(&ChannelLabels::new("test"))
+            .get();
+
+        assert_eq!(dropped as usize, 5 - received.len());
+    }
+
+    #[tokio::test]
+    async fn error_fails_on_full_channel() {
+        let config = ChannelConfig::new(1).with_overflow(OverflowPolicy::Error);
+        let (tx, _rx) = channel("test", config, &metrics());
+
+        tx.try_send(1).unwrap();
+        assert!(tx.try_send(2).is_err());
+    }
+}
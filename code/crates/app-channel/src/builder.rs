@@ -4,27 +4,37 @@
 //! at compile-time which actors have been configured. The `build()` method is
 //! only available when all required actors have been configured.
 
+use std::future::Future;
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use eyre::Result;
 use tokio::sync::mpsc::{self, Sender};
 
 use malachitebft_app::types::codec::HasEncodedLen;
-use malachitebft_engine::network::{NetworkIdentity, NetworkRef};
+use malachitebft_engine::consensus::ConsensusRef;
+use malachitebft_engine::host::HostRef;
+use malachitebft_engine::network::{NetworkIdentity, NetworkMsg as NetworkActorMsg, NetworkRef};
 use malachitebft_engine::sync::SyncRef;
+use malachitebft_engine::util::clock::{Clock, SystemClock};
 use malachitebft_engine::util::events::TxEvent;
 use malachitebft_engine::util::output_port::{OutputPort, OutputPortSubscriberTrait};
-use malachitebft_engine::wal::WalRef;
+use malachitebft_engine::wal::{WalBackendKind, WalRef};
 use malachitebft_signing::SigningProvider;
 
 use crate::app::config::NodeConfig;
 use crate::app::metrics::{Metrics, SharedRegistry};
+use crate::app::net::{AddressPolicy, EvictionPolicy};
 use crate::app::spawn::{
-    spawn_consensus_actor, spawn_node_actor, spawn_sync_actor, spawn_wal_actor,
+    spawn_consensus_actor, spawn_node_actor, spawn_shared_network_actors, spawn_sync_actor,
+    spawn_wal_actor, NetworkPolicies,
 };
 use crate::app::types::codec;
 use crate::app::types::core::Context;
+use crate::backpressure::{self, ChannelMetrics, ChannelsConfig};
+use crate::log_filter::LogFilter;
 use crate::msgs::NetworkMsg;
 use crate::spawn::{spawn_host_actor, spawn_network_actor};
 use crate::{Channels, EngineHandle};
@@ -53,11 +63,37 @@ impl<T> HasEncodedLen<T> for NoCodec {
 pub struct WalContext<Codec> {
     pub path: PathBuf,
     pub codec: Codec,
+    /// Storage backend for the WAL, an append-only file by default
+    pub backend: WalBackendKind,
+    /// Window within which flushes are batched into a single sync to disk, disabled by default
+    pub group_commit: Option<Duration>,
 }
 
 impl<Codec> WalContext<Codec> {
     pub fn new(path: PathBuf, codec: Codec) -> Self {
-        Self { path, codec }
+        Self {
+            path,
+            codec,
+            backend: WalBackendKind::default(),
+            group_commit: None,
+        }
+    }
+
+    /// Use another storage backend for the WAL, eg. to match the durability
+    /// and performance profile of the underlying storage
+    pub fn with_backend(mut self, backend: WalBackendKind) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Batch the flushes requested within the given window into a single sync to disk.
+    ///
+    /// Consensus then no longer waits for the WAL to be synced before moving on, only the
+    /// broadcast of its messages does, which trades up to `window` of latency per message
+    /// for far fewer syncs on storage where they are slow, eg. network-attached disks.
+    pub fn with_group_commit(mut self, window: Duration) -> Self {
+        self.group_commit = Some(window);
+        self
     }
 }
 
@@ -65,11 +101,31 @@ impl<Codec> WalContext<Codec> {
 pub struct NetworkContext<Codec> {
     pub identity: NetworkIdentity,
     pub codec: Codec,
+    /// Policies of the network implemented by the application
+    pub policies: NetworkPolicies,
 }
 
 impl<Codec> NetworkContext<Codec> {
     pub fn new(identity: NetworkIdentity, codec: Codec) -> Self {
-        Self { identity, codec }
+        Self {
+            identity,
+            codec,
+            policies: NetworkPolicies::default(),
+        }
+    }
+
+    /// Decide which of the addresses advertised by the peers are reachable with the given policy,
+    /// instead of the one following the reachability settings of the configuration
+    pub fn with_address_policy(mut self, address_policy: Arc<dyn AddressPolicy>) -> Self {
+        self.policies = self.policies.with_address_policy(address_policy);
+        self
+    }
+
+    /// Select the inbound peer to evict when the inbound peers limit is reached
+    /// with the given policy, instead of refusing the new peers
+    pub fn with_eviction_policy(mut self, eviction_policy: Arc<dyn EvictionPolicy>) -> Self {
+        self.policies = self.policies.with_eviction_policy(eviction_policy);
+        self
     }
 }
 
@@ -77,6 +133,9 @@ impl<Codec> NetworkContext<Codec> {
 pub struct ConsensusContext<Ctx: Context, Signer> {
     pub address: Ctx::Address,
     pub signing_provider: Signer,
+    /// Source of time of the timeouts, the clock of the operating system unless set,
+    /// whose offset is then monitored as configured in [`NodeConfig::clock`]
+    pub clock: Option<Arc<dyn Clock>>,
 }
 
 impl<Ctx: Context, Signer> ConsensusContext<Ctx, Signer> {
@@ -84,8 +143,15 @@ impl<Ctx: Context, Signer> ConsensusContext<Ctx, Signer> {
         Self {
             address,
             signing_provider,
+            clock: None,
         }
     }
+
+    /// Schedule the timeouts according to the given clock, eg. a simulated one
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
 }
 
 /// Context for spawning the Sync actor.
@@ -101,15 +167,138 @@ impl<Codec> SyncContext<Codec> {
 
 /// Context for request channels.
 pub struct RequestContext {
-    pub channel_size: usize,
+    /// Capacity and overflow policy of the channels between the engine and the application
+    pub channels: ChannelsConfig,
+    /// Filter of the logs changed by [`crate::NetworkRequest::set_log_filter`], if reloadable
+    pub log_filter: Option<LogFilter>,
 }
 
 impl RequestContext {
     pub fn new(channel_size: usize) -> Self {
-        Self { channel_size }
+        Self {
+            channels: ChannelsConfig::new(channel_size),
+            log_filter: None,
+        }
+    }
+
+    pub fn with_channels(mut self, channels: ChannelsConfig) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Let the filter of the logs be changed at runtime through the reloadable layer
+    /// installed on the subscriber, see [`LogFilter`]
+    pub fn with_log_filter(mut self, log_filter: LogFilter) -> Self {
+        self.log_filter = Some(log_filter);
+        self
+    }
+}
+
+/// Network service shared by the engines of several chains, eg. the shards of an application,
+/// which then share the same listen address, connections to the peers and discovery.
+///
+/// Each chain has its own pubsub topics and sync protocol, prefixed with `/<chain_id>`.
+/// Pass the network actor of a chain to [`EngineBuilder::with_shared_network`].
+///
+/// # Example
+/// ```rust,ignore
+/// let chain_ids = vec!["shard-1".to_string(), "shard-2".to_string()];
+/// let network = SharedNetwork::spawn(&config, identity, chain_ids, codec).await?;
+///
+/// let (channels, handle) = EngineBuilder::new(ctx, config)
+///     .with_default_wal(WalContext::new(path, codec))
+///     .with_shared_network(network.chain("shard-1").unwrap())
+///     // ...
+///     .build()
+///     .await?;
+/// ```
+pub struct SharedNetwork<Ctx: Context> {
+    chains: Vec<(String, NetworkRef<Ctx>)>,
+}
+
+impl<Ctx: Context> SharedNetwork<Ctx> {
+    /// Spawn the network service, along with one network actor per chain.
+    ///
+    /// The network is configured with the consensus and sync configuration of `config`,
+    /// and stops once the engines of all the chains are stopped.
+    pub async fn spawn<Config, Codec>(
+        config: &Config,
+        identity: NetworkIdentity,
+        chain_ids: Vec<String>,
+        codec: Codec,
+    ) -> Result<Self>
+    where
+        Config: NodeConfig,
+        Codec: codec::ConsensusCodec<Ctx> + codec::SyncCodec<Ctx> + Clone,
+    {
+        Self::spawn_with_policies(
+            config,
+            identity,
+            chain_ids,
+            codec,
+            NetworkPolicies::default(),
+        )
+        .await
+    }
+
+    /// Spawn the network service as [`SharedNetwork::spawn`] does,
+    /// with the given policies of the network implemented by the application.
+    pub async fn spawn_with_policies<Config, Codec>(
+        config: &Config,
+        identity: NetworkIdentity,
+        chain_ids: Vec<String>,
+        codec: Codec,
+        policies: NetworkPolicies,
+    ) -> Result<Self>
+    where
+        Config: NodeConfig,
+        Codec: codec::ConsensusCodec<Ctx> + codec::SyncCodec<Ctx> + Clone,
+    {
+        let registry = SharedRegistry::global().with_moniker(config.moniker());
+
+        let actors = spawn_shared_network_actors(
+            config.consensus(),
+            config.value_sync(),
+            identity,
+            chain_ids.clone(),
+            &registry,
+            codec,
+            policies,
+        )
+        .await?;
+
+        Ok(Self {
+            chains: chain_ids.into_iter().zip(actors).collect(),
+        })
+    }
+
+    /// Network actor of the given chain, `None` if the network does not serve it
+    pub fn chain(&self, chain_id: &str) -> Option<NetworkRef<Ctx>> {
+        self.chains
+            .iter()
+            .find(|(id, _)| id == chain_id)
+            .map(|(_, network)| network.clone())
     }
+
+    /// Identifiers of the chains served by the network
+    pub fn chain_ids(&self) -> impl Iterator<Item = &str> {
+        self.chains.iter().map(|(id, _)| id.as_str())
+    }
+}
+
+/// Actors spawned by the builder which a custom Sync actor may depend on.
+pub struct SyncDeps<Ctx: Context> {
+    pub network: NetworkRef<Ctx>,
+    pub host: HostRef<Ctx>,
+    pub consensus: ConsensusRef<Ctx>,
+    pub registry: SharedRegistry,
 }
 
+/// Spawns a custom Sync actor once the actors it depends on are running.
+pub type SyncSpawner<Ctx> = Box<
+    dyn FnOnce(SyncDeps<Ctx>) -> Pin<Box<dyn Future<Output = Result<SyncRef<Ctx>>> + Send>> + Send,
+>;
+
 /// Builder for the WAL actor - either default or custom.
 pub enum WalBuilder<Ctx: Context, Codec> {
     /// Use the default WAL actor with the given context.
@@ -125,6 +314,8 @@ pub enum NetworkBuilder<Ctx: Context, Codec> {
     Default(NetworkContext<Codec>),
     /// Use a custom Network actor reference and message sender.
     Custom((NetworkRef<Ctx>, Sender<NetworkMsg<Ctx>>)),
+    /// Use the Network actor of a chain served by a [`SharedNetwork`].
+    Shared(NetworkRef<Ctx>),
 }
 
 /// Builder for the Sync actor - either default, custom, or disabled.
@@ -133,6 +324,8 @@ pub enum SyncBuilder<Ctx: Context, Codec> {
     Default(SyncContext<Codec>),
     /// Use a custom Sync actor reference, or `None` to disable sync.
     Custom(Option<SyncRef<Ctx>>),
+    /// Spawn a custom Sync actor with the actors it depends on.
+    Spawn(SyncSpawner<Ctx>),
 }
 
 /// Builder for the Consensus actor.
@@ -620,6 +813,36 @@ where
             request: self.request,
         }
     }
+
+    /// Use the Network actor of a chain served by a [`SharedNetwork`],
+    /// see [`SharedNetwork::chain`].
+    #[must_use]
+    pub fn with_shared_network(
+        self,
+        network_ref: NetworkRef<Ctx>,
+    ) -> EngineBuilder<
+        Ctx,
+        Config,
+        Signer,
+        WalCodec,
+        NoCodec,
+        SyncCodec,
+        HAS_WAL,
+        true,
+        HAS_SYNC,
+        HAS_CONSENSUS,
+        HAS_REQUEST,
+    > {
+        EngineBuilder {
+            ctx: self.ctx,
+            config: self.config,
+            wal: self.wal,
+            network: Some(NetworkBuilder::Shared(network_ref)),
+            sync: self.sync,
+            consensus: self.consensus,
+            request: self.request,
+        }
+    }
 }
 
 // Implementation for custom Sync actor
@@ -679,6 +902,46 @@ where
         }
     }
 
+    /// Spawn a custom Sync actor with the network, host and consensus actors of the engine.
+    ///
+    /// Unlike [`with_custom_sync`](Self::with_custom_sync), which takes an actor spawned
+    /// beforehand, this lets the actor talk to the engine like the default one does.
+    /// It is subscribed to the decisions of consensus like the default actor,
+    /// and must otherwise handle the messages of the sync protocol.
+    #[must_use]
+    pub fn with_custom_sync_spawner<F, Fut>(
+        self,
+        spawn: F,
+    ) -> EngineBuilder<
+        Ctx,
+        Config,
+        Signer,
+        WalCodec,
+        NetCodec,
+        NoCodec,
+        HAS_WAL,
+        HAS_NETWORK,
+        true,
+        HAS_CONSENSUS,
+        HAS_REQUEST,
+    >
+    where
+        F: FnOnce(SyncDeps<Ctx>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<SyncRef<Ctx>>> + Send + 'static,
+    {
+        EngineBuilder {
+            ctx: self.ctx,
+            config: self.config,
+            wal: self.wal,
+            network: self.network,
+            sync: Some(SyncBuilder::Spawn(Box::new(move |deps| {
+                Box::pin(spawn(deps))
+            }))),
+            consensus: self.consensus,
+            request: self.request,
+        }
+    }
+
     /// Disable the Sync actor.
     #[must_use]
     pub fn with_no_sync(
@@ -731,7 +994,10 @@ where
     /// The build process will:
     /// 1. Spawn actors in dependency order (network → wal → host → consensus → sync → node)
     /// 2. Set up request handling tasks
-    /// 3. Return channels for the application and the engine handle
+    /// 3. Start the metrics and RPC servers, the admin socket and the decision log, if enabled in
+    ///    [`NodeConfig::metrics`], [`NodeConfig::rpc`], [`NodeConfig::admin`] and
+    ///    [`NodeConfig::decision_log`]
+    /// 4. Return channels for the application and the engine handle
     pub async fn build(self) -> Result<(Channels<Ctx>, EngineHandle)> {
         // SAFETY: All these unwrap() calls are safe because the const generic
         // constraints guarantee that all configurations are present.
@@ -744,10 +1010,23 @@ where
         // Set up metrics
         let registry = SharedRegistry::global().with_moniker(self.config.moniker());
         let metrics = Metrics::register(&registry);
+        let channels_config = request_ctx.channels;
+        channels_config.validate()?;
+
+        let channel_metrics = ChannelMetrics::register(&registry);
 
         // 1. Network actor (default or custom)
         let (network, tx_network) = match network_builder {
             NetworkBuilder::Custom(custom) => custom,
+            NetworkBuilder::Shared(network) => {
+                let tx_network = crate::spawn::forward_to_network(
+                    network.clone(),
+                    channels_config.network,
+                    &channel_metrics,
+                );
+
+                (network, tx_network)
+            }
             NetworkBuilder::Default(network_ctx) => {
                 spawn_network_actor(
                     network_ctx.identity,
@@ -755,26 +1034,51 @@ where
                     self.config.value_sync(),
                     &registry,
                     network_ctx.codec,
+                    network_ctx.policies,
+                    channels_config.network,
+                    &channel_metrics,
                 )
                 .await?
             }
         };
 
         // 2. WAL actor (default or custom)
-        let wal = match wal_builder {
-            WalBuilder::Custom(wal_ref) => wal_ref,
+        let (wal, wal_group_commit) = match wal_builder {
+            WalBuilder::Custom(wal_ref) => (wal_ref, false),
             WalBuilder::Default(wal_ctx) => {
-                spawn_wal_actor(&self.ctx, wal_ctx.codec, &wal_ctx.path, &registry).await?
+                let wal = spawn_wal_actor(
+                    &self.ctx,
+                    wal_ctx.codec,
+                    &wal_ctx.path,
+                    wal_ctx.backend,
+                    wal_ctx.group_commit,
+                    &registry,
+                )
+                .await?;
+
+                (wal, wal_ctx.group_commit.is_some())
             }
         };
 
         // 3. Host actor (use the default channel-based Connector)
-        let (connector, rx_consensus) = spawn_host_actor(metrics.clone()).await?;
+        let (connector, rx_consensus) =
+            spawn_host_actor(channels_config.consensus, metrics.clone(), &channel_metrics).await?;
 
         let tx_event = TxEvent::new();
         let sync_port = Arc::new(OutputPort::new());
 
-        // 4. Consensus actor (spawned before sync so sync can reference it)
+        // 4. Consensus actor (spawned before sync so sync can reference it),
+        // its timeouts elapsing according to the clock of the operating system unless set
+        let (clock, clock_skew) = match consensus_ctx.clock {
+            Some(clock) => (clock, None),
+            None => {
+                let clock = SystemClock::monitored(self.config.clock(), &registry);
+                let clock_skew = clock.skew();
+
+                (Arc::new(clock) as Arc<dyn Clock>, clock_skew)
+            }
+        };
+
         let consensus = spawn_consensus_actor(
             self.ctx.clone(),
             consensus_ctx.address,
@@ -783,15 +1087,27 @@ where
             network.clone(),
             connector.clone(),
             wal.clone(),
+            wal_group_commit,
             sync_port.clone(),
             metrics,
             tx_event.clone(),
+            clock,
+            &registry,
         )
         .await?;
 
         // 5. Sync actor (default or custom)
         let sync = match sync_builder {
             SyncBuilder::Custom(sync_ref) => sync_ref,
+            SyncBuilder::Spawn(spawn) => Some(
+                spawn(SyncDeps {
+                    network: network.clone(),
+                    host: connector.clone(),
+                    consensus: consensus.clone(),
+                    registry: registry.clone(),
+                })
+                .await?,
+            ),
             SyncBuilder::Default(sync_ctx) => {
                 spawn_sync_actor(
                     self.ctx.clone(),
@@ -816,18 +1132,127 @@ where
             self.ctx,
             network.clone(),
             consensus.clone(),
-            wal,
-            sync,
+            wal.clone(),
+            sync.clone(),
             connector,
         )
         .await?;
 
         // Spawn request handling tasks
-        let (tx_request, rx_request) = mpsc::channel(request_ctx.channel_size);
-        crate::run::spawn_consensus_request_task(rx_request, consensus);
+        let (tx_request, rx_request) =
+            backpressure::channel("requests", channels_config.requests, &channel_metrics);
+        crate::run::spawn_consensus_request_task(rx_request, consensus, node.clone());
+
+        let (tx_peer_message, rx_peer_message) = backpressure::channel(
+            "peer_messages",
+            channels_config.peer_messages,
+            &channel_metrics,
+        );
+        network.cast(NetworkActorMsg::SubscribePeerMessages(tx_peer_message))?;
+
+        let (tx_mempool, rx_mempool) =
+            backpressure::channel("mempool", channels_config.mempool, &channel_metrics);
+        network.cast(NetworkActorMsg::SubscribeMempool(tx_mempool))?;
+
+        let (tx_connectivity, rx_connectivity) = backpressure::channel(
+            "connectivity",
+            channels_config.connectivity,
+            &channel_metrics,
+        );
+        network.cast(NetworkActorMsg::SubscribeConnectivity(tx_connectivity))?;
+
+        let (tx_net_request, rx_net_request) = backpressure::channel(
+            "net_requests",
+            channels_config.net_requests,
+            &channel_metrics,
+        );
+        crate::run::spawn_network_request_task(
+            rx_net_request,
+            network.clone(),
+            request_ctx.log_filter,
+        );
+
+        let (tx_sync_request, rx_sync_request) = backpressure::channel(
+            "sync_requests",
+            channels_config.sync_requests,
+            &channel_metrics,
+        );
+        crate::run::spawn_sync_request_task(rx_sync_request, sync);
+
+        // Health checks, subscribed to the connectivity events on their own
+        let (tx_health_connectivity, rx_health_connectivity) =
+            mpsc::channel(channels_config.connectivity.capacity);
+        network.cast(NetworkActorMsg::SubscribeConnectivity(
+            tx_health_connectivity,
+        ))?;
+
+        let health = crate::health::spawn_health_task(
+            self.config.health(),
+            wal,
+            tx_request.clone(),
+            tx_sync_request.clone(),
+            rx_health_connectivity,
+            clock_skew,
+            &registry,
+        );
+
+        // Metrics server, serving the registry shared with the application
+        let metrics_config = self.config.metrics();
+        let metrics_server = if metrics_config.enabled {
+            let server =
+                crate::metrics::spawn_metrics_server(metrics_config.listen_addr, registry.clone())
+                    .await?;
+
+            Some(server)
+        } else {
+            None
+        };
 
-        let (tx_net_request, rx_net_request) = mpsc::channel(request_ctx.channel_size);
-        crate::run::spawn_network_request_task(rx_net_request, network);
+        // RPC server, serving the same requests as the application can send
+        let rpc_config = self.config.rpc();
+        let rpc_server = if rpc_config.enabled {
+            let server = crate::rpc::spawn_rpc_server(
+                rpc_config.listen_addr,
+                self.config.moniker().to_string(),
+                tx_request.clone(),
+                tx_net_request.clone(),
+                tx_sync_request.clone(),
+                health.clone(),
+            )
+            .await?;
+
+            Some(server)
+        } else {
+            None
+        };
+
+        // Admin socket, mapping the commands of operators onto the requests of the application
+        let admin_config = self.config.admin();
+        let admin_server = if admin_config.enabled {
+            let server = crate::admin::spawn_admin_server(
+                admin_config.socket_path,
+                tx_request.clone(),
+                tx_network.clone(),
+                tx_net_request.clone(),
+                tx_sync_request.clone(),
+            )
+            .await?;
+
+            Some(server)
+        } else {
+            None
+        };
+
+        // Audit log of the decided heights
+        let decision_log_config = self.config.decision_log();
+        let decision_log = if decision_log_config.enabled {
+            let writer =
+                crate::decision_log::spawn_decision_log(decision_log_config, &tx_event).await?;
+
+            Some(writer)
+        } else {
+            None
+        };
 
         // Build channels and handle
         let channels = Channels {
@@ -836,9 +1261,21 @@ where
             events: tx_event,
             requests: tx_request,
             net_requests: tx_net_request,
+            sync_requests: tx_sync_request,
+            peer_messages: rx_peer_message,
+            mempool: rx_mempool,
+            connectivity: rx_connectivity,
+            health,
+            metrics: registry,
         };
 
-        let handle = EngineHandle::new(node, handle);
+        let handle = EngineHandle::new(node, handle).with_servers(
+            metrics_server
+                .into_iter()
+                .chain(rpc_server)
+                .chain(admin_server)
+                .chain(decision_log),
+        );
 
         Ok((channels, handle))
     }
@@ -970,6 +1407,218 @@ mod tests {
             .await;
     }
 
+    struct MemoryConfig {
+        consensus: malachitebft_config::ConsensusConfig,
+        value_sync: malachitebft_config::ValueSyncConfig,
+    }
+
+    impl NodeConfig for MemoryConfig {
+        fn moniker(&self) -> &str {
+            "test-node"
+        }
+
+        fn consensus(&self) -> &malachitebft_config::ConsensusConfig {
+            &self.consensus
+        }
+
+        fn consensus_mut(&mut self) -> &mut malachitebft_config::ConsensusConfig {
+            &mut self.consensus
+        }
+
+        fn value_sync(&self) -> &malachitebft_config::ValueSyncConfig {
+            &self.value_sync
+        }
+
+        fn value_sync_mut(&mut self) -> &mut malachitebft_config::ValueSyncConfig {
+            &mut self.value_sync
+        }
+    }
+
+    fn memory_config() -> MemoryConfig {
+        let mut consensus = malachitebft_config::ConsensusConfig::default();
+        consensus.p2p.listen_addr = malachitebft_config::TransportProtocol::Memory
+            .multiaddr("", rand::random::<u16>() as usize);
+
+        MemoryConfig {
+            consensus,
+            value_sync: Default::default(),
+        }
+    }
+
+    // Two chains over a shared network, shutting down the engine of one of them
+    #[tokio::test]
+    async fn shared_network_outlives_one_of_its_chains() {
+        use malachitebft_test::{Address, PrivateKey};
+        use ractor::ActorStatus;
+
+        use crate::app::types::Keypair;
+
+        let config = memory_config();
+        let keypair = Keypair::ed25519_from_bytes([1; 32]).unwrap();
+        let identity = NetworkIdentity::new(config.moniker().to_string(), keypair, None);
+        let chain_ids = vec!["chain-1".to_string(), "chain-2".to_string()];
+
+        let network = SharedNetwork::<TestContext>::spawn(&config, identity, chain_ids, JsonCodec)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            network.chain_ids().collect::<Vec<_>>(),
+            vec!["chain-1", "chain-2"]
+        );
+        assert!(network.chain("chain-3").is_none());
+
+        let wal_dir = tempfile::tempdir().unwrap();
+        let private_key = PrivateKey::from([2; 32]);
+        let address = Address::from_public_key(&private_key.public_key());
+
+        let mut engines = Vec::new();
+
+        for chain_id in ["chain-1", "chain-2"] {
+            let engine = EngineBuilder::new(TestContext::default(), memory_config())
+                .with_default_wal(WalContext::new(
+                    wal_dir.path().join(chain_id),
+                    ProtobufCodec,
+                ))
+                .with_shared_network(network.chain(chain_id).unwrap())
+                .with_default_sync(SyncContext::new(JsonCodec))
+                .with_default_consensus(ConsensusContext::new(
+                    address,
+                    Ed25519Provider::new(private_key.clone()),
+                ))
+                .with_default_request(RequestContext::new(100))
+                .build()
+                .await
+                .unwrap();
+
+            engines.push(engine);
+        }
+
+        let (_channels_1, handle_1) = &engines[0];
+        let (_channels_2, handle_2) = &engines[1];
+
+        handle_1.shutdown().await.unwrap();
+
+        let chain_1 = network.chain("chain-1").unwrap();
+        let chain_2 = network.chain("chain-2").unwrap();
+
+        assert_eq!(chain_1.get_status(), ActorStatus::Stopped);
+        assert_eq!(chain_2.get_status(), ActorStatus::Running);
+
+        handle_2.shutdown().await.unwrap();
+
+        assert_eq!(chain_2.get_status(), ActorStatus::Stopped);
+    }
+
+    // Custom sync spawned with the actors of the engine, which stops along with it
+    #[tokio::test]
+    async fn custom_sync_is_spawned_with_the_engine_actors() {
+        use malachitebft_test::{Address, PrivateKey};
+        use ractor::ActorStatus;
+        use tokio::sync::oneshot;
+
+        use crate::app::types::Keypair;
+
+        let config = memory_config();
+        let keypair = Keypair::ed25519_from_bytes([1; 32]).unwrap();
+        let identity = NetworkIdentity::new(config.moniker().to_string(), keypair, None);
+
+        let wal_dir = tempfile::tempdir().unwrap();
+        let private_key = PrivateKey::from([2; 32]);
+        let address = Address::from_public_key(&private_key.public_key());
+
+        let (tx_sync, rx_sync) = oneshot::channel();
+
+        let (_channels, handle) = EngineBuilder::new(TestContext::default(), config)
+            .with_default_wal(WalContext::new(wal_dir.path().join("wal"), ProtobufCodec))
+            .with_default_network(NetworkContext::new(identity, JsonCodec))
+            .with_custom_sync_spawner(|deps: SyncDeps<TestContext>| async move {
+                // The actors the sync actor depends on are already running
+                assert_eq!(deps.network.get_status(), ActorStatus::Running);
+                assert_eq!(deps.host.get_status(), ActorStatus::Running);
+                assert_eq!(deps.consensus.get_status(), ActorStatus::Running);
+
+                let sync = spawn_sync_actor(
+                    TestContext::default(),
+                    deps.network,
+                    deps.host,
+                    deps.consensus,
+                    JsonCodec,
+                    &Default::default(),
+                    &deps.registry,
+                )
+                .await?
+                .unwrap();
+
+                tx_sync.send(sync.clone()).unwrap();
+                Ok(sync)
+            })
+            .with_default_consensus(ConsensusContext::new(
+                address,
+                Ed25519Provider::new(private_key),
+            ))
+            .with_default_request(RequestContext::new(100))
+            .build()
+            .await
+            .unwrap();
+
+        let sync = rx_sync.await.unwrap();
+        assert_eq!(sync.get_status(), ActorStatus::Running);
+
+        handle.shutdown().await.unwrap();
+
+        assert_eq!(sync.get_status(), ActorStatus::Stopped);
+    }
+
+    // Metrics registered by the application are exported along with those of the engine
+    #[tokio::test]
+    async fn application_metrics_are_exported_with_the_engine_metrics() {
+        use malachitebft_test::{Address, PrivateKey};
+
+        use crate::app::metrics::prometheus::metrics::counter::Counter;
+        use crate::app::types::Keypair;
+
+        let config = memory_config();
+        let keypair = Keypair::ed25519_from_bytes([1; 32]).unwrap();
+        let identity = NetworkIdentity::new(config.moniker().to_string(), keypair, None);
+
+        let wal_dir = tempfile::tempdir().unwrap();
+        let private_key = PrivateKey::from([2; 32]);
+        let address = Address::from_public_key(&private_key.public_key());
+
+        let (channels, handle) = EngineBuilder::new(TestContext::default(), config)
+            .with_default_wal(WalContext::new(wal_dir.path().join("wal"), ProtobufCodec))
+            .with_default_network(NetworkContext::new(identity, JsonCodec))
+            .with_default_sync(SyncContext::new(JsonCodec))
+            .with_default_consensus(ConsensusContext::new(
+                address,
+                Ed25519Provider::new(private_key),
+            ))
+            .with_default_request(RequestContext::new(100))
+            .build()
+            .await
+            .unwrap();
+
+        let served = Counter::<u64>::default();
+
+        channels.metrics.with_prefix("test_app", |registry| {
+            registry.register("requests_served", "Requests served", served.clone())
+        });
+
+        served.inc();
+
+        let mut exported = String::new();
+        channels.metrics.export(&mut exported);
+
+        assert!(exported.contains(r#"test_app_requests_served_total{moniker="test-node"} 1"#));
+        assert!(exported.lines().any(|line| {
+            line.starts_with("malachitebft_core_consensus")
+                && line.contains(r#"moniker="test-node""#)
+        }));
+
+        handle.shutdown().await.unwrap();
+    }
+
     // Mixed: custom WAL and network, default sync
     #[allow(dead_code)]
     async fn custom_wal_and_network_compiles() {
@@ -9,6 +9,7 @@ use malachitebft_engine::host::HostMsg;
 
 use crate::app::metrics::Metrics;
 use crate::app::types::core::Context;
+use crate::backpressure::OverflowPolicy;
 use crate::msgs::AppMsg;
 
 /// Actor for bridging consensus and the application via a set of channels.
@@ -20,6 +21,7 @@ where
     Ctx: Context,
 {
     sender: mpsc::Sender<AppMsg<Ctx>>,
+    overflow: OverflowPolicy,
 
     // TODO: add some metrics
     #[allow(dead_code)]
@@ -30,20 +32,40 @@ impl<Ctx> Connector<Ctx>
 where
     Ctx: Context,
 {
-    pub fn new(sender: mpsc::Sender<AppMsg<Ctx>>, metrics: Metrics) -> Self {
-        Connector { sender, metrics }
+    pub fn new(
+        sender: mpsc::Sender<AppMsg<Ctx>>,
+        overflow: OverflowPolicy,
+        metrics: Metrics,
+    ) -> Self {
+        Connector {
+            sender,
+            overflow,
+            metrics,
+        }
     }
 
     pub async fn spawn(
         sender: mpsc::Sender<AppMsg<Ctx>>,
+        overflow: OverflowPolicy,
         metrics: Metrics,
     ) -> Result<ActorRef<HostMsg<Ctx>>, SpawnErr>
     where
         Ctx: Context,
     {
-        let (actor_ref, _) = Actor::spawn(None, Self::new(sender, metrics), ()).await?;
+        let (actor_ref, _) = Actor::spawn(None, Self::new(sender, overflow, metrics), ()).await?;
         Ok(actor_ref)
     }
+
+    /// Send a message to the application, failing right away
+    /// if the channel is full and its overflow policy says so
+    async fn send(&self, msg: AppMsg<Ctx>) -> Result<(), ActorProcessingErr> {
+        match self.overflow {
+            OverflowPolicy::Error => self.sender.try_send(msg)?,
+            OverflowPolicy::Block | OverflowPolicy::DropOldest => self.sender.send(msg).await?,
+        }
+
+        Ok(())
+    }
 }
 
 impl<Ctx> Connector<Ctx>
@@ -56,10 +78,15 @@ where
         msg: HostMsg<Ctx>,
         _state: &mut (),
     ) -> Result<(), ActorProcessingErr> {
+        #[cfg(feature = "chaos")]
+        malachitebft_engine::chaos::toggles()
+            .delay_app_reply()
+            .await;
+
         match msg {
             HostMsg::ConsensusReady { reply_to } => {
                 let (reply, rx) = oneshot::channel();
-                self.sender.send(AppMsg::ConsensusReady { reply }).await?;
+                self.send(AppMsg::ConsensusReady { reply }).await?;
 
                 let (start_height, updates) = rx.await?;
                 reply_to.send((start_height, updates))?;
@@ -74,15 +101,14 @@ where
             } => {
                 let (reply_value, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::StartedRound {
-                        height,
-                        round,
-                        proposer,
-                        role,
-                        reply_value,
-                    })
-                    .await?;
+                self.send(AppMsg::StartedRound {
+                    height,
+                    round,
+                    proposer,
+                    role,
+                    reply_value,
+                })
+                .await?;
 
                 reply_to.send(rx.await?)?;
             }
@@ -95,14 +121,13 @@ where
             } => {
                 let (reply, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::GetValue {
-                        height,
-                        round,
-                        timeout,
-                        reply,
-                    })
-                    .await?;
+                self.send(AppMsg::GetValue {
+                    height,
+                    round,
+                    timeout,
+                    reply,
+                })
+                .await?;
 
                 reply_to.send(rx.await?)?;
             }
@@ -115,14 +140,13 @@ where
             } => {
                 let (reply, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::ExtendVote {
-                        height,
-                        round,
-                        value_id,
-                        reply,
-                    })
-                    .await?;
+                self.send(AppMsg::ExtendVote {
+                    height,
+                    round,
+                    value_id,
+                    reply,
+                })
+                .await?;
 
                 reply_to.send(rx.await?)?;
             }
@@ -136,15 +160,14 @@ where
             } => {
                 let (reply, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::VerifyVoteExtension {
-                        height,
-                        round,
-                        value_id,
-                        extension,
-                        reply,
-                    })
-                    .await?;
+                self.send(AppMsg::VerifyVoteExtension {
+                    height,
+                    round,
+                    value_id,
+                    extension,
+                    reply,
+                })
+                .await?;
 
                 reply_to.send(rx.await?)?;
             }
@@ -156,23 +179,20 @@ where
                 address,
                 value_id,
             } => {
-                self.sender
-                    .send(AppMsg::RestreamProposal {
-                        height,
-                        round,
-                        valid_round,
-                        address,
-                        value_id,
-                    })
-                    .await?
+                self.send(AppMsg::RestreamProposal {
+                    height,
+                    round,
+                    valid_round,
+                    address,
+                    value_id,
+                })
+                .await?
             }
 
             HostMsg::GetHistoryMinHeight { reply_to } => {
                 let (reply, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::GetHistoryMinHeight { reply })
-                    .await?;
+                self.send(AppMsg::GetHistoryMinHeight { reply }).await?;
 
                 reply_to.send(rx.await?)?;
             }
@@ -184,8 +204,7 @@ where
             } => {
                 let (reply, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::ReceivedProposalPart { from, part, reply })
+                self.send(AppMsg::ReceivedProposalPart { from, part, reply })
                     .await?;
 
                 if let Some(value) = rx.await? {
@@ -196,13 +215,14 @@ where
             HostMsg::Decided {
                 certificate,
                 extensions,
+                proof,
             } => {
-                self.sender
-                    .send(AppMsg::Decided {
-                        certificate,
-                        extensions,
-                    })
-                    .await?;
+                self.send(AppMsg::Decided {
+                    certificate,
+                    extensions,
+                    proof,
+                })
+                .await?;
             }
 
             HostMsg::Finalized {
@@ -213,14 +233,13 @@ where
             } => {
                 let (reply, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::Finalized {
-                        certificate,
-                        extensions,
-                        evidence,
-                        reply,
-                    })
-                    .await?;
+                self.send(AppMsg::Finalized {
+                    certificate,
+                    extensions,
+                    evidence,
+                    reply,
+                })
+                .await?;
 
                 // Do not block processing of other messages while waiting for the next height
                 tokio::spawn(async move {
@@ -235,9 +254,7 @@ where
             HostMsg::GetDecidedValues { range, reply_to } => {
                 let (reply, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::GetDecidedValues { range, reply })
-                    .await?;
+                self.send(AppMsg::GetDecidedValues { range, reply }).await?;
 
                 reply_to.send(rx.await?)?;
             }
@@ -251,15 +268,14 @@ where
             } => {
                 let (reply, rx) = oneshot::channel();
 
-                self.sender
-                    .send(AppMsg::ProcessSyncedValue {
-                        height,
-                        round,
-                        proposer,
-                        value_bytes,
-                        reply,
-                    })
-                    .await?;
+                self.send(AppMsg::ProcessSyncedValue {
+                    height,
+                    round,
+                    proposer,
+                    value_bytes,
+                    reply,
+                })
+                .await?;
 
                 if let Some(value) = rx.await? {
                     if let Err(e) = reply_to.send(value) {
@@ -269,6 +285,59 @@ where
                     warn!("Failed to decode synced value");
                 }
             }
+
+            HostMsg::GetSnapshots { reply_to } => {
+                let (reply, rx) = oneshot::channel();
+
+                self.send(AppMsg::GetSnapshots { reply }).await?;
+
+                reply_to.send(rx.await?)?;
+            }
+
+            HostMsg::GetSnapshotChunk {
+                height,
+                format,
+                index,
+                reply_to,
+            } => {
+                let (reply, rx) = oneshot::channel();
+
+                self.send(AppMsg::GetSnapshotChunk {
+                    height,
+                    format,
+                    index,
+                    reply,
+                })
+                .await?;
+
+                reply_to.send(rx.await?)?;
+            }
+
+            HostMsg::RestoreSnapshot {
+                snapshot,
+                chunks,
+                reply_to,
+            } => {
+                let (reply, rx) = oneshot::channel();
+
+                self.send(AppMsg::RestoreSnapshot {
+                    snapshot,
+                    chunks,
+                    reply,
+                })
+                .await?;
+
+                reply_to.send(rx.await?)?;
+            }
+
+            HostMsg::StoreBackfilledValues { values, reply_to } => {
+                let (reply, rx) = oneshot::channel();
+
+                self.send(AppMsg::StoreBackfilledValues { values, reply })
+                    .await?;
+
+                reply_to.send(rx.await?)?;
+            }
         };
 
         Ok(())
@@ -0,0 +1,361 @@
+//! Audit log of the decisions of the node, started by the [`EngineBuilder`] when enabled in the
+//! [`DecisionLogConfig`] returned by [`NodeConfig::decision_log`].
+//!
+//! A JSON line is appended for each decided height, with the round of the decision, its proposer,
+//! the id of the decided value, the validators whose precommits are in the commit certificate,
+//! the time of the decision and how long the height took. The log is rotated once it grows above
+//! `max_file_size`, keeping the `max_files` latest rotated files.
+//!
+//! The decisions are followed through the [`Event`]s of consensus, so that the log is independent
+//! of the application and of the format of the WAL. Should the log lag too far behind consensus,
+//! the decisions it missed are reported in the logs of the node rather than in the audit log.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use derive_where::derive_where;
+use eyre::WrapErr;
+use serde::Serialize;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{error, info, warn, Instrument};
+
+use malachitebft_app::config::DecisionLogConfig;
+use malachitebft_app::types::core::{CommitCertificate, Context, Height, Round};
+use malachitebft_engine::util::events::{Event, TxEvent};
+
+#[cfg(doc)]
+use crate::{app::config::NodeConfig, EngineBuilder};
+
+/// Open the log and append the decisions to it in the background.
+///
+/// Fails if the log cannot be opened, so that a misconfigured node does not start
+/// without its audit log.
+pub(crate) async fn spawn_decision_log<Ctx: Context>(
+    config: DecisionLogConfig,
+    events: &TxEvent<Ctx>,
+) -> eyre::Result<JoinHandle<()>> {
+    let mut file = RotatingFile::open(
+        config.path.clone(),
+        config.max_file_size.as_u64(),
+        config.max_files,
+    )
+    .await
+    .wrap_err_with(|| {
+        format!(
+            "Failed to open the decision log at {}",
+            config.path.display()
+        )
+    })?;
+
+    let mut rx_event = events.subscribe();
+    let mut tracker = Tracker::<Ctx>::default();
+
+    let span = tracing::info_span!("decision_log");
+
+    let handle = tokio::spawn(
+        async move {
+            info!(path = %config.path.display(), "Appending the decisions to the log");
+
+            loop {
+                let event = match rx_event.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Decision log lagged behind consensus, missed {missed} events");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                let Some(record) = tracker.on_event(event) else {
+                    continue;
+                };
+
+                let mut line = match serde_json::to_vec(&record) {
+                    Ok(line) => line,
+                    Err(e) => {
+                        error!(
+                            height = record.height,
+                            "Failed to serialize a decision: {e}"
+                        );
+                        continue;
+                    }
+                };
+                line.push(b'\n');
+
+                if let Err(e) = file.append(&line).await {
+                    error!(
+                        height = record.height,
+                        "Failed to append a decision to the log: {e}"
+                    );
+                }
+            }
+        }
+        .instrument(span),
+    );
+
+    Ok(handle)
+}
+
+/// Entry of the log for a decided height
+#[derive(Debug, Serialize)]
+struct DecisionRecord {
+    height: u64,
+    round: i64,
+    /// Proposer of the round of the decision, `None` if the round was not followed,
+    /// eg. when decided from values synced from the peers
+    proposer: Option<String>,
+    value_id: String,
+    /// Validators whose precommits are in the commit certificate
+    participants: Vec<String>,
+    /// Time of the decision, in milliseconds since the Unix epoch
+    decided_at_ms: u64,
+    /// Time from the start of the height to the decision, `None` if the start was not followed
+    height_duration_ms: Option<u64>,
+}
+
+/// Follows the height and rounds of consensus, to report the proposer and timing of a decision
+#[derive_where(Default)]
+struct Tracker<Ctx: Context> {
+    height: Option<Ctx::Height>,
+    started_at: Option<Instant>,
+    proposers: BTreeMap<Round, Ctx::Address>,
+}
+
+impl<Ctx: Context> Tracker<Ctx> {
+    fn on_event(&mut self, event: Event<Ctx>) -> Option<DecisionRecord> {
+        match event {
+            Event::StartedHeight(height, _) => {
+                self.height = Some(height);
+                self.started_at = Some(Instant::now());
+                self.proposers.clear();
+                None
+            }
+            Event::StartedRound(height, round, proposer, _) if self.height == Some(height) => {
+                self.proposers.insert(round, proposer);
+                None
+            }
+            Event::Decided { commit_certificate } => Some(self.record(&commit_certificate)),
+            _ => None,
+        }
+    }
+
+    fn record(&self, certificate: &CommitCertificate<Ctx>) -> DecisionRecord {
+        let current = self.height == Some(certificate.height);
+
+        DecisionRecord {
+            height: certificate.height.as_u64(),
+            round: certificate.round.as_i64(),
+            proposer: current
+                .then(|| self.proposers.get(&certificate.round))
+                .flatten()
+                .map(|address| address.to_string()),
+            value_id: certificate.value_id.to_string(),
+            participants: certificate
+                .commit_signatures
+                .iter()
+                .map(|signature| signature.address.to_string())
+                .collect(),
+            decided_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since_epoch| since_epoch.as_millis() as u64)
+                .unwrap_or_default(),
+            height_duration_ms: current
+                .then_some(self.started_at)
+                .flatten()
+                .map(|started_at| started_at.elapsed().as_millis() as u64),
+        }
+    }
+}
+
+/// File appended to, and rotated once it grows above a maximum size
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    async fn open(path: PathBuf, max_size: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+
+        let size = file.metadata().await?.len();
+
+        Ok(Self {
+            path,
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    async fn append(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate().await?;
+        }
+
+        self.file.write_all(line).await?;
+        self.file.flush().await?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    /// Shift the rotated files, dropping the oldest one, and start a new file
+    async fn rotate(&mut self) -> io::Result<()> {
+        self.file.sync_all().await?;
+
+        if self.max_files == 0 {
+            tokio::fs::remove_file(&self.path).await?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, index);
+
+                match tokio::fs::rename(&from, rotated_path(&self.path, index + 1)).await {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => (),
+                }
+            }
+
+            tokio::fs::rename(&self.path, rotated_path(&self.path, 1)).await?;
+        }
+
+        *self = Self::open(self.path.clone(), self.max_size, self.max_files).await?;
+
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    PathBuf::from(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytesize::ByteSize;
+    use malachitebft_app::consensus::Role;
+    use malachitebft_app::types::core::CommitSignature;
+    use malachitebft_test::{Address, Height as TestHeight, Signature, TestContext, ValueId};
+
+    use super::*;
+
+    fn certificate(height: u64, participants: &[Address]) -> CommitCertificate<TestContext> {
+        CommitCertificate {
+            height: TestHeight::new(height),
+            round: Round::new(0),
+            value_id: ValueId::new(height * 10),
+            commit_signatures: participants
+                .iter()
+                .map(|address| CommitSignature::new(*address, Signature::from_bytes([0; 64])))
+                .collect(),
+        }
+    }
+
+    /// Lines of the file, once they satisfy the given condition
+    async fn read_lines_until(
+        path: &Path,
+        done: impl Fn(&[serde_json::Value]) -> bool,
+    ) -> Vec<serde_json::Value> {
+        for _ in 0..100 {
+            let content = tokio::fs::read_to_string(path).await.unwrap_or_default();
+            let lines = content
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect::<Vec<_>>();
+
+            if done(&lines) {
+                return lines;
+            }
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        panic!("unexpected content of {}", path.display());
+    }
+
+    #[tokio::test]
+    async fn decisions_are_appended_with_their_proposer() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DecisionLogConfig {
+            enabled: true,
+            path: dir.path().join("decisions.jsonl"),
+            ..Default::default()
+        };
+
+        let events = TxEvent::<TestContext>::new();
+        spawn_decision_log(config.clone(), &events).await.unwrap();
+
+        let proposer = Address::new([1; 20]);
+        let validators = [proposer, Address::new([2; 20])];
+
+        events.send(|| Event::StartedHeight(TestHeight::new(1), false));
+        events.send(|| {
+            Event::StartedRound(TestHeight::new(1), Round::new(0), proposer, Role::Validator)
+        });
+        events.send(|| Event::Decided {
+            commit_certificate: certificate(1, &validators),
+        });
+
+        let lines = read_lines_until(&config.path, |lines| lines.len() == 1).await;
+        let record = &lines[0];
+
+        assert_eq!(record["height"], 1);
+        assert_eq!(record["round"], 0);
+        assert_eq!(record["proposer"], proposer.to_string());
+        assert_eq!(record["value_id"], ValueId::new(10).to_string());
+        assert_eq!(
+            record["participants"],
+            serde_json::json!([validators[0].to_string(), validators[1].to_string()])
+        );
+        assert!(record["height_duration_ms"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn log_is_rotated_above_its_maximum_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = DecisionLogConfig {
+            enabled: true,
+            path: dir.path().join("decisions.jsonl"),
+            max_file_size: ByteSize::b(1),
+            max_files: 1,
+        };
+
+        let events = TxEvent::<TestContext>::new();
+        spawn_decision_log(config.clone(), &events).await.unwrap();
+
+        for height in 1..=3 {
+            events.send(|| Event::Decided {
+                commit_certificate: certificate(height, &[Address::new([1; 20])]),
+            });
+
+            // Wait for the decision to be appended before the next one rotates the log
+            read_lines_until(&config.path, |lines| {
+                lines.len() == 1 && lines[0]["height"] == height
+            })
+            .await;
+        }
+
+        let rotated =
+            read_lines_until(&rotated_path(&config.path, 1), |lines| !lines.is_empty()).await;
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(rotated[0]["height"], 2);
+
+        assert!(!rotated_path(&config.path, 2).exists());
+    }
+}
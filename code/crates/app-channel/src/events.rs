@@ -0,0 +1,135 @@
+//! Stream of the progress made by consensus, for indexers and monitoring agents.
+//!
+//! Unlike the raw engine events available through [`Channels::events`], which also cover the WAL,
+//! the network and every message sent or received, this stream only carries the milestones of
+//! the consensus protocol at the current height.
+
+use derive_where::derive_where;
+use tokio::sync::broadcast::error::RecvError;
+
+use malachitebft_app::types::core::{
+    CommitCertificate, Context, NilOrVal, Round, SignedProposal, TimeoutKind, ValueId,
+};
+use malachitebft_app::types::SignedConsensusMsg;
+use malachitebft_engine::util::events::{Event, RxEvent};
+
+use crate::Channels;
+
+/// A milestone of the consensus protocol
+#[derive_where(Clone, Debug)]
+pub enum ConsensusEvent<Ctx: Context> {
+    /// A new round has started
+    NewRound {
+        /// Current height
+        height: Ctx::Height,
+        /// Round that was just started
+        round: Round,
+        /// Proposer for that round
+        proposer: Ctx::Address,
+    },
+
+    /// A proposal was received from the network
+    ProposalReceived {
+        /// The signed proposal, not verified yet
+        proposal: SignedProposal<Ctx>,
+    },
+
+    /// A quorum of prevotes was reached for a value or for nil
+    Polka {
+        /// Current height
+        height: Ctx::Height,
+        /// Round of the prevotes
+        round: Round,
+        /// The value the prevotes are for, or nil
+        value_id: NilOrVal<ValueId<Ctx>>,
+    },
+
+    /// A quorum of precommits was reached
+    PrecommitQuorum {
+        /// Current height
+        height: Ctx::Height,
+        /// Round of the precommits
+        round: Round,
+        /// The value the precommits are for, `None` if they are spread over several values or nil
+        value_id: Option<ValueId<Ctx>>,
+    },
+
+    /// A value was decided
+    Decided {
+        /// The certificate for the decided value
+        certificate: CommitCertificate<Ctx>,
+    },
+
+    /// A timeout has fired
+    TimeoutFired {
+        /// Current height
+        height: Ctx::Height,
+        /// Round of the timeout
+        round: Round,
+        /// Step the timeout was scheduled for
+        kind: TimeoutKind,
+    },
+}
+
+impl<Ctx: Context> ConsensusEvent<Ctx> {
+    fn from_event(event: Event<Ctx>) -> Option<Self> {
+        match event {
+            Event::StartedRound(height, round, proposer, _role) => Some(Self::NewRound {
+                height,
+                round,
+                proposer,
+            }),
+            Event::Received(SignedConsensusMsg::Proposal(proposal)) => {
+                Some(Self::ProposalReceived { proposal })
+            }
+            Event::PolkaReached(height, round, value_id) => Some(Self::Polka {
+                height,
+                round,
+                value_id,
+            }),
+            Event::PrecommitQuorumReached(height, round, value_id) => Some(Self::PrecommitQuorum {
+                height,
+                round,
+                value_id,
+            }),
+            Event::Decided { commit_certificate } => Some(Self::Decided {
+                certificate: commit_certificate,
+            }),
+            Event::TimeoutElapsed(height, timeout) => Some(Self::TimeoutFired {
+                height,
+                round: timeout.round,
+                kind: timeout.kind,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A subscription to the [`ConsensusEvent`]s, see [`Channels::subscribe_consensus_events`]
+pub struct ConsensusEvents<Ctx: Context> {
+    rx: RxEvent<Ctx>,
+}
+
+impl<Ctx: Context> ConsensusEvents<Ctx> {
+    /// Wait for the next consensus event.
+    ///
+    /// Fails with [`RecvError::Lagged`] if the subscriber fell behind and some events were dropped,
+    /// in which case the following calls resume from the oldest event still available,
+    /// and with [`RecvError::Closed`] once consensus has stopped.
+    pub async fn recv(&mut self) -> Result<ConsensusEvent<Ctx>, RecvError> {
+        loop {
+            if let Some(event) = ConsensusEvent::from_event(self.rx.recv().await?) {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+impl<Ctx: Context> Channels<Ctx> {
+    /// Subscribe to the progress made by consensus from now on
+    pub fn subscribe_consensus_events(&self) -> ConsensusEvents<Ctx> {
+        ConsensusEvents {
+            rx: self.events.subscribe(),
+        }
+    }
+}
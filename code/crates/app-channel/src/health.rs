@@ -0,0 +1,292 @@
+//! Health of the node, for liveness and readiness probes such as those of Kubernetes.
+//!
+//! The engine is checked at a regular interval, see [`HealthConfig`], and the outcome
+//! is both published on [`Channels::health`] and exported as Prometheus gauges.
+
+use std::time::Duration;
+
+use ractor::rpc::CallResult;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{debug, warn};
+
+use malachitebft_app::config::HealthConfig;
+use malachitebft_app::metrics::prometheus::metrics::gauge::Gauge;
+use malachitebft_app::metrics::SharedRegistry;
+use malachitebft_app::types::core::{Context, Height};
+use malachitebft_engine::network::ConnectivityEvent;
+use malachitebft_engine::util::clock::ClockSkew;
+use malachitebft_engine::wal::{Msg as WalMsg, WalRef};
+
+#[cfg(doc)]
+use crate::Channels;
+use crate::{ConsensusRequest, SyncRequest};
+
+/// Outcome of the latest health check of the node
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthStatus {
+    /// Whether the WAL could be synced to disk
+    pub wal_writable: bool,
+
+    /// Whether enough peers take part in consensus, see [`ConnectivityEvent::SufficientPeers`]
+    pub sufficient_peers: bool,
+
+    /// Whether consensus moved to a new height within `max_height_duration`.
+    /// Consensus is considered advancing while it is paused.
+    pub consensus_advancing: bool,
+
+    /// Number of heights between the tip of the node and the highest tip advertised
+    /// by its peers, `None` if sync is disabled or no peer advertised its tip yet
+    pub sync_lag: Option<u64>,
+
+    /// Whether the sync lag is at most `max_sync_lag`, or unknown
+    pub synced: bool,
+
+    /// Offset of the local clock to the NTP servers in milliseconds, positive if it is ahead,
+    /// `None` if the clock is not monitored or none of the servers could be reached
+    pub clock_offset_ms: Option<i64>,
+
+    /// Whether the clock offset is at most `max_skew`, or unknown
+    pub clock_synced: bool,
+}
+
+impl HealthStatus {
+    /// Status of a node found to be healthy on every check
+    pub fn healthy() -> Self {
+        Self {
+            wal_writable: true,
+            sufficient_peers: true,
+            consensus_advancing: true,
+            sync_lag: None,
+            synced: true,
+            clock_offset_ms: None,
+            clock_synced: true,
+        }
+    }
+
+    /// Whether the node is live, ie. it can persist its state and consensus makes progress.
+    /// A node which is not live may need to be restarted.
+    pub fn is_live(&self) -> bool {
+        self.wal_writable && self.consensus_advancing
+    }
+
+    /// Whether the node is ready to serve, ie. it is live, connected to enough peers,
+    /// caught up with them and its clock is not skewed
+    pub fn is_ready(&self) -> bool {
+        self.is_live() && self.sufficient_peers && self.synced && self.clock_synced
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Metrics {
+    wal_writable: Gauge,
+    sufficient_peers: Gauge,
+    consensus_advancing: Gauge,
+    sync_lag: Gauge,
+    clock_synced: Gauge,
+    live: Gauge,
+    ready: Gauge,
+}
+
+impl Metrics {
+    fn register(registry: &SharedRegistry) -> Self {
+        let metrics = Self {
+            wal_writable: Gauge::default(),
+            sufficient_peers: Gauge::default(),
+            consensus_advancing: Gauge::default(),
+            sync_lag: Gauge::default(),
+            clock_synced: Gauge::default(),
+            live: Gauge::default(),
+            ready: Gauge::default(),
+        };
+
+        registry.with_prefix("malachitebft_health", |registry| {
+            registry.register(
+                "wal_writable",
+                "Whether the WAL could be synced to disk (0 or 1)",
+                metrics.wal_writable.clone(),
+            );
+
+            registry.register(
+                "sufficient_peers",
+                "Whether enough peers take part in consensus (0 or 1)",
+                metrics.sufficient_peers.clone(),
+            );
+
+            registry.register(
+                "consensus_advancing",
+                "Whether consensus moved to a new height recently enough (0 or 1)",
+                metrics.consensus_advancing.clone(),
+            );
+
+            registry.register(
+                "sync_lag",
+                "Number of heights the node is behind the highest tip of its peers",
+                metrics.sync_lag.clone(),
+            );
+
+            registry.register(
+                "clock_synced",
+                "Whether the offset of the local clock is within the maximum skew (0 or 1)",
+                metrics.clock_synced.clone(),
+            );
+
+            registry.register(
+                "live",
+                "Whether the node is live (0 or 1)",
+                metrics.live.clone(),
+            );
+
+            registry.register(
+                "ready",
+                "Whether the node is ready to serve (0 or 1)",
+                metrics.ready.clone(),
+            );
+        });
+
+        metrics
+    }
+
+    fn observe(&self, status: &HealthStatus) {
+        self.wal_writable.set(i64::from(status.wal_writable));
+        self.sufficient_peers
+            .set(i64::from(status.sufficient_peers));
+        self.consensus_advancing
+            .set(i64::from(status.consensus_advancing));
+        self.sync_lag
+            .set(status.sync_lag.map_or(0, |lag| lag as i64));
+        self.clock_synced.set(i64::from(status.clock_synced));
+        self.live.set(i64::from(status.is_live()));
+        self.ready.set(i64::from(status.is_ready()));
+    }
+}
+
+/// Spawn the task checking the health of the engine, until the application
+/// drops the receiving end of the returned channel
+pub(crate) fn spawn_health_task<Ctx: Context>(
+    config: HealthConfig,
+    wal: WalRef<Ctx>,
+    tx_request: mpsc::Sender<ConsensusRequest<Ctx>>,
+    tx_sync_request: mpsc::Sender<SyncRequest<Ctx>>,
+    mut rx_connectivity: mpsc::Receiver<ConnectivityEvent>,
+    clock_skew: Option<watch::Receiver<ClockSkew>>,
+    registry: &SharedRegistry,
+) -> watch::Receiver<HealthStatus> {
+    let metrics = Metrics::register(registry);
+
+    // Not live until checked, so that probes do not succeed before the first check
+    let (tx_health, rx_health) = watch::channel(HealthStatus {
+        wal_writable: false,
+        sufficient_peers: false,
+        consensus_advancing: false,
+        sync_lag: None,
+        synced: false,
+        clock_offset_ms: None,
+        clock_synced: false,
+    });
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.check_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut sufficient_peers = false;
+        let mut last_height = None;
+        let mut last_progress = Instant::now();
+
+        loop {
+            tokio::select! {
+                _ = tx_health.closed() => break,
+
+                Some(event) = rx_connectivity.recv() => {
+                    match event {
+                        ConnectivityEvent::SufficientPeers { .. } => sufficient_peers = true,
+                        ConnectivityEvent::InsufficientPeers { .. } => sufficient_peers = false,
+                        _ => (),
+                    }
+                }
+
+                _ = interval.tick() => {
+                    let timeout = config.check_interval;
+
+                    let wal_writable = check_wal(&wal, timeout).await;
+
+                    let snapshot = ConsensusRequest::snapshot_queues(&tx_request);
+
+                    match tokio::time::timeout(timeout, snapshot).await {
+                        Ok(Ok(snapshot)) => {
+                            if snapshot.height != last_height || snapshot.phase == "paused" {
+                                last_height = snapshot.height;
+                                last_progress = Instant::now();
+                            }
+                        }
+                        Ok(Err(e)) => debug!("Failed to query consensus for the health check: {e}"),
+                        Err(_) => debug!("Consensus did not reply to the health check in time"),
+                    }
+
+                    let consensus_advancing = last_progress.elapsed() <= config.max_height_duration;
+
+                    let sync_status = SyncRequest::status(&tx_sync_request);
+
+                    let sync_lag = match tokio::time::timeout(timeout, sync_status).await {
+                        Ok(Ok(Some(status))) => status.network_tip_height.map(|network_tip| {
+                            network_tip.as_u64().saturating_sub(status.tip_height.as_u64())
+                        }),
+                        _ => None,
+                    };
+
+                    let skew = clock_skew
+                        .as_ref()
+                        .map(|skew| *skew.borrow())
+                        .unwrap_or_default();
+
+                    let status = HealthStatus {
+                        wal_writable,
+                        sufficient_peers,
+                        consensus_advancing,
+                        sync_lag,
+                        synced: sync_lag.is_none_or(|lag| lag <= config.max_sync_lag),
+                        clock_offset_ms: skew.offset_ms,
+                        clock_synced: !skew.exceeded,
+                    };
+
+                    if !status.clock_synced && tx_health.borrow().clock_synced {
+                        warn!(
+                            offset_ms = ?status.clock_offset_ms,
+                            "Node is not ready, its clock is skewed"
+                        );
+                    }
+
+                    if status.is_live() && !tx_health.borrow().is_live() {
+                        debug!(?status, "Node is live");
+                    } else if !status.is_live() {
+                        warn!(?status, "Node is not live");
+                    }
+
+                    metrics.observe(&status);
+                    tx_health.send_replace(status);
+                }
+            }
+        }
+    });
+
+    rx_health
+}
+
+/// Check that the WAL can be synced to disk
+async fn check_wal<Ctx: Context>(wal: &WalRef<Ctx>, timeout: Duration) -> bool {
+    match wal.call(WalMsg::Flush, Some(timeout)).await {
+        Ok(CallResult::Success(Ok(()))) => true,
+        Ok(CallResult::Success(Err(e))) => {
+            warn!("WAL is not writable: {e}");
+            false
+        }
+        Ok(CallResult::Timeout) => {
+            warn!("WAL did not sync to disk within {timeout:?}");
+            false
+        }
+        Ok(CallResult::SenderError) | Err(_) => {
+            debug!("WAL actor is not running");
+            false
+        }
+    }
+}
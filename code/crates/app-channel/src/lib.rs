@@ -12,19 +12,43 @@
 
 pub use malachitebft_app as app;
 
+mod admin;
+
+mod backpressure;
+pub use backpressure::{ChannelConfig, ChannelsConfig, OverflowPolicy};
+
 mod builder;
 mod connector;
 mod spawn;
 
+mod decision_log;
+
+mod events;
+pub use events::{ConsensusEvent, ConsensusEvents};
+
+mod health;
+pub use health::HealthStatus;
+
+mod log_filter;
+pub use log_filter::{LogFilter, LogFilterError};
+
+mod metrics;
+
 mod msgs;
 pub use msgs::{
     AppMsg, Channels, ConsensusMsg, ConsensusRequest, ConsensusRequestError, NetworkMsg,
-    NetworkRequest, Reply,
+    NetworkRequest, Reply, SyncRequest,
 };
 
+mod rpc;
+
 mod run;
 pub use run::*;
 
+pub mod mock;
+pub use mock::{MockEngine, MockError};
+
 pub use builder::{
-    ConsensusContext, EngineBuilder, NetworkContext, RequestContext, SyncContext, WalContext,
+    ConsensusContext, EngineBuilder, NetworkContext, RequestContext, SharedNetwork, SyncContext,
+    SyncDeps, SyncSpawner, WalContext,
 };
@@ -0,0 +1,116 @@
+//! Filter of the logs which can be changed while the node is running, eg. to bump
+//! `malachitebft_discovery=debug` while diagnosing a peering issue, without restarting it.
+//!
+//! The application installs the reloadable filter layer on its subscriber and passes the
+//! [`LogFilter`] controlling it to the engine with [`RequestContext::with_log_filter`].
+//! The filter is then changed with [`NetworkRequest::set_log_filter`] or the `set_log_filter`
+//! admin command.
+//!
+//! ```rust,ignore
+//! let (filter, log_filter) = LogFilter::layer("info")?;
+//! tracing_subscriber::registry().with(filter).with(fmt::layer()).init();
+//!
+//! let request_ctx = RequestContext::new(100).with_log_filter(log_filter);
+//! ```
+
+use std::sync::Arc;
+
+use thiserror::Error;
+use tracing_subscriber::{reload, EnvFilter};
+
+#[cfg(doc)]
+use crate::{NetworkRequest, RequestContext};
+
+type Reload = dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync;
+type Current = dyn Fn() -> Result<String, reload::Error> + Send + Sync;
+
+/// Handle on the reloadable filter layer of the subscriber, see the [module docs](self)
+#[derive(Clone)]
+pub struct LogFilter {
+    reload: Arc<Reload>,
+    current: Arc<Current>,
+}
+
+impl LogFilter {
+    /// Control the filter layer of the given reload handle
+    pub fn new<S: 'static>(handle: reload::Handle<EnvFilter, S>) -> Self {
+        let current = handle.clone();
+
+        Self {
+            reload: Arc::new(move |filter| handle.reload(filter)),
+            current: Arc::new(move || current.with_current(|filter| filter.to_string())),
+        }
+    }
+
+    /// Create a reloadable filter layer with the given directives, to install on the subscriber,
+    /// along with the [`LogFilter`] controlling it
+    pub fn layer<S: 'static>(
+        directives: &str,
+    ) -> Result<(reload::Layer<EnvFilter, S>, Self), LogFilterError> {
+        let (layer, handle) = reload::Layer::new(parse(directives)?);
+        Ok((layer, Self::new(handle)))
+    }
+
+    /// Directives of the filter currently applied
+    pub fn directives(&self) -> Result<String, LogFilterError> {
+        (self.current)().map_err(|e| LogFilterError::Reload(e.to_string()))
+    }
+
+    /// Replace the directives of the filter, eg. `info,malachitebft_discovery=debug`,
+    /// returning the previous ones, so that they can be restored once done
+    pub fn set(&self, directives: &str) -> Result<String, LogFilterError> {
+        let filter = parse(directives)?;
+        let previous = self.directives()?;
+
+        (self.reload)(filter).map_err(|e| LogFilterError::Reload(e.to_string()))?;
+
+        Ok(previous)
+    }
+}
+
+fn parse(directives: &str) -> Result<EnvFilter, LogFilterError> {
+    EnvFilter::builder()
+        .parse(directives)
+        .map_err(|e| LogFilterError::InvalidDirectives(directives.to_string(), e.to_string()))
+}
+
+/// Why the filter of the logs could not be changed
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum LogFilterError {
+    #[error("the log filter cannot be changed at runtime, no reloadable filter was installed")]
+    NotReloadable,
+
+    #[error("invalid log filter `{0}`: {1}")]
+    InvalidDirectives(String, String),
+
+    #[error("failed to reload the log filter: {0}")]
+    Reload(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::Registry;
+
+    use super::*;
+
+    #[test]
+    fn directives_are_replaced() {
+        let (layer, log_filter) = LogFilter::layer::<Registry>("info").unwrap();
+        let _subscriber = tracing_subscriber::registry().with(layer);
+
+        let previous = log_filter.set("info,malachitebft_discovery=debug").unwrap();
+        assert_eq!(previous, "info");
+
+        let current = log_filter.directives().unwrap();
+        assert!(
+            current.contains("malachitebft_discovery=debug"),
+            "{current}"
+        );
+
+        assert!(matches!(
+            log_filter.set("malachitebft_discovery=loud"),
+            Err(LogFilterError::InvalidDirectives(..))
+        ));
+    }
+}
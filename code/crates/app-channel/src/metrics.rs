@@ -0,0 +1,121 @@
+//! Server exporting the metrics of the engine and of the application in the Prometheus
+//! text format on `/metrics`, started by the [`EngineBuilder`] when enabled in the
+//! [`MetricsConfig`] returned by [`NodeConfig::metrics`].
+//!
+//! The server exports the whole shared registry, so the metrics the application registers
+//! in [`Channels::metrics`] are exported along with those of the engine.
+
+use std::net::SocketAddr;
+
+use axum::extract::State;
+use axum::routing::get;
+use axum::Router;
+use eyre::{Result, WrapErr};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::{error, info, Instrument};
+
+use malachitebft_app::metrics::SharedRegistry;
+
+#[cfg(doc)]
+use crate::{
+    app::config::{MetricsConfig, NodeConfig},
+    Channels, EngineBuilder,
+};
+
+/// Bind the metrics server to the given address and serve the registry in the background.
+///
+/// Fails if the address cannot be bound, so that a misconfigured node does not start
+/// without its metrics.
+pub(crate) async fn spawn_metrics_server(
+    listen_addr: SocketAddr,
+    registry: SharedRegistry,
+) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(listen_addr)
+        .await
+        .wrap_err_with(|| format!("Failed to bind the metrics server to {listen_addr}"))?;
+
+    let local_addr = listener.local_addr()?;
+
+    let app = Router::new()
+        .route("/metrics", get(get_metrics))
+        .with_state(registry);
+
+    let span = tracing::info_span!("metrics");
+
+    let handle = tokio::spawn(
+        async move {
+            info!(address = %local_addr, "Serving metrics");
+
+            if let Err(e) = axum::serve(listener, app).await {
+                error!("Metrics server failed: {e}");
+            }
+        }
+        .instrument(span),
+    );
+
+    Ok(handle)
+}
+
+async fn get_metrics(State(registry): State<SharedRegistry>) -> String {
+    let mut buf = String::new();
+    registry.export(&mut buf);
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use malachitebft_app::metrics::prometheus::metrics::counter::Counter;
+    use malachitebft_app::metrics::Registry;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_the_registry() {
+        let registry = SharedRegistry::new(Registry::default(), Some("node-1".to_string()));
+
+        let counter = Counter::<u64>::default();
+        counter.inc_by(3);
+
+        registry.with_prefix("test", |registry| {
+            registry.register("decisions", "Number of decisions", counter.clone())
+        });
+
+        // Bind to an ephemeral port first, to learn an address which is free
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let server = spawn_metrics_server(addr, registry).await.unwrap();
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(
+            response.contains(r#"test_decisions_total{moniker="node-1"} 3"#),
+            "{response}"
+        );
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn fails_if_the_address_is_in_use() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let registry = SharedRegistry::new(Registry::default(), None);
+        assert!(spawn_metrics_server(addr, registry).await.is_err());
+    }
+}
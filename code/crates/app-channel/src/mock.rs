@@ -0,0 +1,826 @@
+//! A mock of the consensus engine, for unit testing applications without running
+//! consensus and networking.
+//!
+//! The [`MockEngine`] hands out the same [`Channels`] as [`start_engine`](crate::start_engine),
+//! and lets the test drive the application by sending it the messages consensus would send,
+//! one at a time, and checking its replies.
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! let (mut engine, channels) = MockEngine::<TestContext>::new();
+//! tokio::spawn(app::run(state, channels));
+//!
+//! let (height, _params) = engine.start().await?;
+//! engine.start_round(Round::new(0), proposer, Role::Proposer).await?;
+//!
+//! let proposed = engine.get_value(Duration::from_secs(1)).await?;
+//! let next = engine.decide(certificate_for(&proposed)).await?;
+//! assert_eq!(engine.height(), Some(height.increment()));
+//! ```
+
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use bytes::Bytes;
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot, watch};
+
+use malachitebft_app::consensus::{Role, VoteExtensionError};
+use malachitebft_app::metrics::{Registry, SharedRegistry};
+use malachitebft_app::types::MisbehaviorEvidence;
+use malachitebft_engine::consensus::queue_snapshot::QueueSnapshot;
+use malachitebft_engine::host::{HeightParams, Next};
+use malachitebft_engine::network::{
+    ConfigUpdateError, ConnectivityEvent, MempoolMessage, NodeKeyError, PeerConnectionError,
+    PeerMessage, PeerMessageError,
+};
+use malachitebft_engine::util::events::TxEvent;
+
+use crate::app::types::core::{CommitCertificate, Context, Round, ValueId, VoteExtensions};
+use crate::app::types::streaming::StreamMessage;
+use crate::app::types::sync::RawDecidedValue;
+use crate::app::types::{LocallyProposedValue, PeerId, ProposedValue};
+use crate::log_filter::LogFilterError;
+use crate::msgs::{
+    AppMsg, Channels, ConsensusRequest, NetworkMsg, NetworkRequest, Reply, SyncRequest,
+};
+use crate::HealthStatus;
+
+/// Capacity of the channels handed out to the application
+const CHANNEL_CAPACITY: usize = 100;
+
+/// How long to wait for the application to reply, unless specified otherwise
+const DEFAULT_REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Errors returned by the [`MockEngine`] when the application does not reply as expected
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
+pub enum MockError {
+    /// The application did not reply in time
+    #[error("The application did not reply within {0:?}")]
+    Timeout(Duration),
+
+    /// The application dropped the reply channel without replying
+    #[error("The application dropped the reply channel")]
+    NoReply,
+
+    /// The application dropped its end of the channels
+    #[error("The application stopped receiving messages")]
+    Closed,
+
+    /// The message requires consensus to be started, see [`MockEngine::start`]
+    #[error("Consensus is not started")]
+    NotStarted,
+}
+
+/// Height and round of the mock engine, shared with the task serving the requests
+type Position<Ctx> = (Option<<Ctx as Context>::Height>, Round);
+
+/// A scriptable stand-in for the consensus engine.
+///
+/// Heights only advance when the test decides on a value with [`MockEngine::decide`]
+/// and the application replies with the next height to start. Requests sent by the
+/// application on the request channels are answered with canned responses:
+/// no state dumps, no peers, and an empty queue snapshot. As there are no peers,
+/// the messages sent to a peer fail to be delivered.
+pub struct MockEngine<Ctx: Context> {
+    tx_consensus: mpsc::Sender<AppMsg<Ctx>>,
+    rx_network: mpsc::Receiver<NetworkMsg<Ctx>>,
+    tx_peer_message: mpsc::Sender<PeerMessage>,
+    tx_mempool: mpsc::Sender<MempoolMessage>,
+    tx_connectivity: mpsc::Sender<ConnectivityEvent>,
+    tx_health: watch::Sender<HealthStatus>,
+    events: TxEvent<Ctx>,
+    reply_timeout: Duration,
+    params: Option<HeightParams<Ctx>>,
+    position: watch::Sender<Position<Ctx>>,
+}
+
+impl<Ctx: Context> MockEngine<Ctx> {
+    /// Create a mock engine along with the channels to hand to the application.
+    ///
+    /// Must be called within a Tokio runtime, as it spawns the task serving the requests.
+    pub fn new() -> (Self, Channels<Ctx>) {
+        let (tx_consensus, rx_consensus) = mpsc::channel(CHANNEL_CAPACITY);
+        let (tx_network, rx_network) = mpsc::channel(CHANNEL_CAPACITY);
+        let (tx_request, rx_request) = mpsc::channel(CHANNEL_CAPACITY);
+        let (tx_net_request, rx_net_request) = mpsc::channel(CHANNEL_CAPACITY);
+        let (tx_sync_request, rx_sync_request) = mpsc::channel(CHANNEL_CAPACITY);
+        let (tx_peer_message, rx_peer_message) = mpsc::channel(CHANNEL_CAPACITY);
+        let (tx_mempool, rx_mempool) = mpsc::channel(CHANNEL_CAPACITY);
+        let (tx_connectivity, rx_connectivity) = mpsc::channel(CHANNEL_CAPACITY);
+        let (tx_health, rx_health) = watch::channel(HealthStatus::healthy());
+        let (position, rx_position) = watch::channel((None, Round::Nil));
+
+        let events = TxEvent::new();
+
+        spawn_request_tasks(rx_request, rx_net_request, rx_sync_request, rx_position);
+
+        let engine = Self {
+            tx_consensus,
+            rx_network,
+            tx_peer_message,
+            tx_mempool,
+            tx_connectivity,
+            tx_health,
+            events: events.clone(),
+            reply_timeout: DEFAULT_REPLY_TIMEOUT,
+            params: None,
+            position,
+        };
+
+        let channels = Channels {
+            consensus: rx_consensus,
+            network: tx_network,
+            events,
+            requests: tx_request,
+            net_requests: tx_net_request,
+            sync_requests: tx_sync_request,
+            peer_messages: rx_peer_message,
+            mempool: rx_mempool,
+            connectivity: rx_connectivity,
+            health: rx_health,
+            // Not exported, so that the metrics of the tests do not mix with one another
+            metrics: SharedRegistry::new(Registry::default(), None),
+        };
+
+        (engine, channels)
+    }
+
+    /// Set how long to wait for the application to reply before failing with [`MockError::Timeout`]
+    pub fn with_reply_timeout(mut self, reply_timeout: Duration) -> Self {
+        self.reply_timeout = reply_timeout;
+        self
+    }
+
+    /// The current height, `None` until consensus is started
+    pub fn height(&self) -> Option<Ctx::Height> {
+        self.position.borrow().0
+    }
+
+    /// The current round, `Nil` until a round is started
+    pub fn round(&self) -> Round {
+        self.position.borrow().1
+    }
+
+    /// The parameters of the current height, `None` until consensus is started
+    pub fn params(&self) -> Option<&HeightParams<Ctx>> {
+        self.params.as_ref()
+    }
+
+    /// The events channel shared with the application, to emit events it subscribed to
+    pub fn events(&self) -> &TxEvent<Ctx> {
+        &self.events
+    }
+
+    /// Send `ConsensusReady` and start consensus at the height the application replies with
+    pub async fn start(&mut self) -> Result<(Ctx::Height, HeightParams<Ctx>), MockError> {
+        let (height, params) = self
+            .request(|reply| AppMsg::ConsensusReady { reply })
+            .await?;
+
+        self.start_height(height, params.clone());
+
+        Ok((height, params))
+    }
+
+    /// Send `StartedRound` for the given round of the current height,
+    /// returning the undecided values the application replied with
+    pub async fn start_round(
+        &mut self,
+        round: Round,
+        proposer: Ctx::Address,
+        role: Role,
+    ) -> Result<Vec<ProposedValue<Ctx>>, MockError> {
+        let height = self.current_height()?;
+        self.position.send_modify(|position| position.1 = round);
+
+        self.request(|reply_value| AppMsg::StartedRound {
+            height,
+            round,
+            proposer,
+            role,
+            reply_value,
+        })
+        .await
+    }
+
+    /// Ask the application for a value to propose at the current height and round.
+    ///
+    /// Fails with [`MockError::Timeout`] if the application does not reply within `timeout`,
+    /// as consensus would time out on the proposal.
+    pub async fn get_value(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<LocallyProposedValue<Ctx>, MockError> {
+        let height = self.current_height()?;
+        let round = self.round();
+
+        self.request_within(timeout, |reply| AppMsg::GetValue {
+            height,
+            round,
+            timeout,
+            reply,
+        })
+        .await
+    }
+
+    /// Deliver a proposal part as if it was received from `from`,
+    /// returning the full value if the application could complete it
+    pub async fn receive_proposal_part(
+        &mut self,
+        from: PeerId,
+        part: StreamMessage<Ctx::ProposalPart>,
+    ) -> Result<Option<ProposedValue<Ctx>>, MockError> {
+        self.request(|reply| AppMsg::ReceivedProposalPart { from, part, reply })
+            .await
+    }
+
+    /// Ask the application to extend its precommit for the given value
+    pub async fn extend_vote(
+        &mut self,
+        value_id: ValueId<Ctx>,
+    ) -> Result<Option<Ctx::Extension>, MockError> {
+        let height = self.current_height()?;
+        let round = self.round();
+
+        self.request(|reply| AppMsg::ExtendVote {
+            height,
+            round,
+            value_id,
+            reply,
+        })
+        .await
+    }
+
+    /// Ask the application to verify a vote extension for the given value
+    pub async fn verify_vote_extension(
+        &mut self,
+        value_id: ValueId<Ctx>,
+        extension: Ctx::Extension,
+    ) -> Result<Result<(), VoteExtensionError>, MockError> {
+        let height = self.current_height()?;
+        let round = self.round();
+
+        self.request(|reply| AppMsg::VerifyVoteExtension {
+            height,
+            round,
+            value_id,
+            extension,
+            reply,
+        })
+        .await
+    }
+
+    /// Ask the application to re-stream a proposal, the parts it publishes
+    /// can then be collected with [`MockEngine::published`]
+    pub async fn restream_proposal(
+        &mut self,
+        round: Round,
+        valid_round: Round,
+        address: Ctx::Address,
+        value_id: ValueId<Ctx>,
+    ) -> Result<(), MockError> {
+        let height = self.current_height()?;
+
+        self.send(AppMsg::RestreamProposal {
+            height,
+            round,
+            valid_round,
+            address,
+            value_id,
+        })
+        .await
+    }
+
+    /// Decide on the value certified by `certificate` and finalize the height,
+    /// then move on to the height the application replies with.
+    ///
+    /// Returns the reply of the application, starting or restarting a height.
+    pub async fn decide(
+        &mut self,
+        certificate: CommitCertificate<Ctx>,
+    ) -> Result<Next<Ctx>, MockError> {
+        self.decide_with_extensions(certificate, VoteExtensions::default())
+            .await
+    }
+
+    /// Same as [`MockEngine::decide`], along with the given vote extensions
+    pub async fn decide_with_extensions(
+        &mut self,
+        certificate: CommitCertificate<Ctx>,
+        extensions: VoteExtensions<Ctx>,
+    ) -> Result<Next<Ctx>, MockError> {
+        self.current_height()?;
+
+        self.send(AppMsg::Decided {
+            certificate: certificate.clone(),
+            extensions: extensions.clone(),
+            proof: None,
+        })
+        .await?;
+
+        let evidence = MisbehaviorEvidence {
+            proposals: Default::default(),
+            votes: Default::default(),
+        };
+
+        let next = self
+            .request(|reply| AppMsg::Finalized {
+                certificate,
+                extensions,
+                evidence,
+                reply,
+            })
+            .await?;
+
+        match &next {
+            Next::Start(height, params) | Next::Restart(height, params) => {
+                self.start_height(*height, params.clone())
+            }
+        }
+
+        Ok(next)
+    }
+
+    /// Ask the application for the decided values in the given range, as sync would
+    pub async fn get_decided_values(
+        &mut self,
+        range: RangeInclusive<Ctx::Height>,
+    ) -> Result<Vec<RawDecidedValue<Ctx>>, MockError> {
+        self.request(|reply| AppMsg::GetDecidedValues { range, reply })
+            .await
+    }
+
+    /// Ask the application for the earliest height in its history
+    pub async fn get_history_min_height(&mut self) -> Result<Ctx::Height, MockError> {
+        self.request(|reply| AppMsg::GetHistoryMinHeight { reply })
+            .await
+    }
+
+    /// Deliver a value synced from the network, returning the value the application decoded
+    pub async fn process_synced_value(
+        &mut self,
+        height: Ctx::Height,
+        round: Round,
+        proposer: Ctx::Address,
+        value_bytes: Bytes,
+    ) -> Result<Option<ProposedValue<Ctx>>, MockError> {
+        self.request(|reply| AppMsg::ProcessSyncedValue {
+            height,
+            round,
+            proposer,
+            value_bytes,
+            reply,
+        })
+        .await
+    }
+
+    /// The messages sent by the application to the network since the last call
+    pub fn published(&mut self) -> Vec<NetworkMsg<Ctx>> {
+        let mut msgs = Vec::new();
+        while let Ok(msg) = self.rx_network.try_recv() {
+            msgs.push(msg);
+        }
+        msgs
+    }
+
+    /// Wait for the next message sent by the application to the network
+    pub async fn next_published(&mut self) -> Result<NetworkMsg<Ctx>, MockError> {
+        match tokio::time::timeout(self.reply_timeout, self.rx_network.recv()).await {
+            Ok(Some(msg)) => Ok(msg),
+            Ok(None) => Err(MockError::Closed),
+            Err(_) => Err(MockError::Timeout(self.reply_timeout)),
+        }
+    }
+
+    /// Notify the application of a change in connectivity, eg. to test how it reacts
+    /// to losing its peers, as no peers connect to the mock engine on their own
+    pub async fn send_connectivity_event(&self, event: ConnectivityEvent) -> Result<(), MockError> {
+        self.tx_connectivity
+            .send(event)
+            .await
+            .map_err(|_| MockError::Closed)
+    }
+
+    /// Set the health status seen by the application, which is healthy until changed
+    pub fn set_health(&self, status: HealthStatus) {
+        self.tx_health.send_replace(status);
+    }
+
+    /// Send a message to the application as if sent directly by a peer, and wait for its reply
+    pub async fn send_peer_message(
+        &self,
+        peer_id: PeerId,
+        payload: Bytes,
+    ) -> Result<Bytes, MockError> {
+        let (reply, rx) = oneshot::channel();
+
+        let message = PeerMessage {
+            peer_id,
+            payload,
+            reply,
+        };
+
+        let result = tokio::time::timeout(self.reply_timeout, async {
+            self.tx_peer_message
+                .send(message)
+                .await
+                .map_err(|_| MockError::Closed)?;
+
+            rx.await.map_err(|_| MockError::NoReply)
+        })
+        .await;
+
+        result.unwrap_or(Err(MockError::Timeout(self.reply_timeout)))
+    }
+
+    /// Send transactions to the application as if gossiped by a peer on the mempool channel
+    pub async fn send_mempool_message(
+        &self,
+        peer_id: PeerId,
+        payload: Bytes,
+    ) -> Result<(), MockError> {
+        self.tx_mempool
+            .send(MempoolMessage { peer_id, payload })
+            .await
+            .map_err(|_| MockError::Closed)
+    }
+
+    fn current_height(&self) -> Result<Ctx::Height, MockError> {
+        self.height().ok_or(MockError::NotStarted)
+    }
+
+    fn start_height(&mut self, height: Ctx::Height, params: HeightParams<Ctx>) {
+        self.params = Some(params);
+        self.position.send_replace((Some(height), Round::Nil));
+    }
+
+    async fn send(&self, msg: AppMsg<Ctx>) -> Result<(), MockError> {
+        match tokio::time::timeout(self.reply_timeout, self.tx_consensus.send(msg)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(_)) => Err(MockError::Closed),
+            Err(_) => Err(MockError::Timeout(self.reply_timeout)),
+        }
+    }
+
+    async fn request<T>(&self, msg: impl FnOnce(Reply<T>) -> AppMsg<Ctx>) -> Result<T, MockError> {
+        self.request_within(self.reply_timeout, msg).await
+    }
+
+    async fn request_within<T>(
+        &self,
+        timeout: Duration,
+        msg: impl FnOnce(Reply<T>) -> AppMsg<Ctx>,
+    ) -> Result<T, MockError> {
+        let (tx, rx) = oneshot::channel();
+
+        let result = tokio::time::timeout(timeout, async {
+            self.tx_consensus
+                .send(msg(tx))
+                .await
+                .map_err(|_| MockError::Closed)?;
+
+            rx.await.map_err(|_| MockError::NoReply)
+        })
+        .await;
+
+        result.unwrap_or(Err(MockError::Timeout(timeout)))
+    }
+}
+
+/// Answer the requests of the application with canned responses
+fn spawn_request_tasks<Ctx: Context>(
+    mut rx_request: mpsc::Receiver<ConsensusRequest<Ctx>>,
+    mut rx_net_request: mpsc::Receiver<NetworkRequest>,
+    mut rx_sync_request: mpsc::Receiver<SyncRequest<Ctx>>,
+    position: watch::Receiver<Position<Ctx>>,
+) {
+    tokio::spawn(async move {
+        while let Some(msg) = rx_request.recv().await {
+            match msg {
+                ConsensusRequest::DumpState(reply) => {
+                    let _ = reply.send(None);
+                }
+                ConsensusRequest::SnapshotQueues(reply) => {
+                    let (height, round) = *position.borrow();
+
+                    let _ = reply.send(QueueSnapshot {
+                        height,
+                        round,
+                        phase: if height.is_some() {
+                            "running"
+                        } else {
+                            "unstarted"
+                        },
+                        input_queue: Vec::new(),
+                        msg_buffer: Vec::new(),
+                        ordered_msgs: Vec::new(),
+                    });
+                }
+                ConsensusRequest::UncommittedEvidence(reply) => {
+                    let _ = reply.send(Vec::new());
+                }
+                ConsensusRequest::SetSigningEnabled(enabled, reply) => {
+                    let _ = reply.send(enabled);
+                }
+                ConsensusRequest::RegisterSigningKey(_, _, reply) => {
+                    let _ = reply.send(true);
+                }
+                ConsensusRequest::UpdateConfig(update, reply) => {
+                    let _ = reply.send(update.validate().map(|()| update.outcome()));
+                }
+                ConsensusRequest::Pause(reply)
+                | ConsensusRequest::Resume(reply)
+                | ConsensusRequest::Shutdown(reply) => {
+                    let _ = reply.send(());
+                }
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        while let Some(msg) = rx_net_request.recv().await {
+            match msg {
+                NetworkRequest::DumpState(reply) => {
+                    let _ = reply.send(None);
+                }
+                NetworkRequest::DumpTopology(reply) => {
+                    let _ = reply.send(None);
+                }
+                NetworkRequest::DiscoveredPeers(reply) => {
+                    let _ = reply.send(None);
+                }
+                NetworkRequest::ListPeers(reply) => {
+                    let _ = reply.send(None);
+                }
+                NetworkRequest::ExportAddressBook(reply) => {
+                    let _ = reply.send(None);
+                }
+                NetworkRequest::ImportAddressBook(_, reply) => {
+                    let _ = reply.send(None);
+                }
+                NetworkRequest::DisconnectPeer(_, reply) | NetworkRequest::DialPeer(_, reply) => {
+                    let _ = reply.send(Err(PeerConnectionError::NetworkStopped));
+                }
+                NetworkRequest::UpdatePersistentPeers(_, reply) => {
+                    let _ = reply.send(Ok(()));
+                }
+                NetworkRequest::UpdateConfig(_, reply) => {
+                    let _ = reply.send(Err(ConfigUpdateError::NetworkStopped));
+                }
+                NetworkRequest::NodeKeyStatus(reply) => {
+                    let _ = reply.send(None);
+                }
+                NetworkRequest::RotateNodeKey(reply) => {
+                    let _ = reply.send(Err(NodeKeyError::NetworkStopped));
+                }
+                NetworkRequest::SendToPeer { reply, .. } => {
+                    let _ = reply.send(Err(PeerMessageError::DialFailure));
+                }
+                NetworkRequest::SetLogFilter(_, reply) => {
+                    let _ = reply.send(Err(LogFilterError::NotReloadable));
+                }
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        while let Some(msg) = rx_sync_request.recv().await {
+            match msg {
+                SyncRequest::DumpState(reply) => {
+                    let _ = reply.send(None);
+                }
+                SyncRequest::LowWatermark(reply) => {
+                    let _ = reply.send(None);
+                }
+                SyncRequest::Pause(reply) | SyncRequest::Resume(reply) => {
+                    let _ = reply.send(false);
+                }
+                SyncRequest::Status(reply) => {
+                    let _ = reply.send(None);
+                }
+                SyncRequest::Backfill(_, reply)
+                | SyncRequest::UpdateConfig(_, reply)
+                | SyncRequest::SetRetainHeight(_, reply) => {
+                    let _ = reply.send(false);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use malachitebft_test::utils::validators::make_validators;
+    use malachitebft_test::{Height, LinearTimeouts, TestContext, ValidatorSet, Value};
+
+    use crate::app::types::sync::ConfigUpdate;
+
+    use super::*;
+
+    /// A minimal application proposing its height as value and moving on to the next height
+    async fn run_app(mut channels: Channels<TestContext>, validator_set: ValidatorSet) {
+        let params = || HeightParams::new(validator_set.clone(), LinearTimeouts::default(), None);
+
+        while let Some(msg) = channels.consensus.recv().await {
+            match msg {
+                AppMsg::ConsensusReady { reply } => {
+                    let _ = reply.send((Height::new(1), params()));
+                }
+                AppMsg::StartedRound { reply_value, .. } => {
+                    let _ = reply_value.send(Vec::new());
+                }
+                AppMsg::GetValue {
+                    height,
+                    round,
+                    reply,
+                    ..
+                } => {
+                    // Take too long to propose at height 2, to exercise the proposal timeout
+                    if height == Height::new(2) {
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+
+                    let value = LocallyProposedValue::new(height, round, Value::new(42));
+                    let _ = reply.send(value);
+                }
+                AppMsg::Finalized {
+                    certificate, reply, ..
+                } => {
+                    let _ = reply.send(Next::Start(certificate.height.increment(), params()));
+                }
+                AppMsg::GetHistoryMinHeight { reply } => {
+                    let _ = reply.send(Height::new(1));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn heights_advance_on_decision() {
+        let [(validator, _)] = make_validators([1]);
+        let validator_set = ValidatorSet::new(vec![validator.clone()]);
+
+        let (mut engine, channels) = MockEngine::<TestContext>::new();
+        tokio::spawn(run_app(channels, validator_set));
+
+        assert_eq!(engine.height(), None);
+        assert_eq!(
+            engine.get_value(Duration::from_secs(1)).await.unwrap_err(),
+            MockError::NotStarted
+        );
+
+        let (height, _) = engine.start().await.unwrap();
+        assert_eq!(height, Height::new(1));
+
+        engine
+            .start_round(Round::new(0), validator.address, Role::Proposer)
+            .await
+            .unwrap();
+
+        let value = engine.get_value(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(value.value, Value::new(42));
+
+        let certificate = CommitCertificate::new(height, value.round, value.value.id(), Vec::new());
+
+        let next = engine.decide(certificate).await.unwrap();
+        assert!(matches!(next, Next::Start(height, _) if height == Height::new(2)));
+        assert_eq!(engine.height(), Some(Height::new(2)));
+        assert_eq!(engine.round(), Round::Nil);
+
+        assert_eq!(
+            engine.get_history_min_height().await.unwrap(),
+            Height::new(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn induced_proposal_timeout() {
+        let [(validator, _)] = make_validators([1]);
+        let validator_set = ValidatorSet::new(vec![validator.clone()]);
+
+        let (mut engine, channels) = MockEngine::<TestContext>::new();
+        tokio::spawn(run_app(channels, validator_set));
+
+        let (height, _) = engine.start().await.unwrap();
+        let certificate =
+            CommitCertificate::new(height, Round::new(0), Value::new(42).id(), Vec::new());
+        engine.decide(certificate).await.unwrap();
+
+        engine
+            .start_round(Round::new(0), validator.address, Role::Proposer)
+            .await
+            .unwrap();
+
+        let timeout = Duration::from_millis(50);
+        assert_eq!(
+            engine.get_value(timeout).await.unwrap_err(),
+            MockError::Timeout(timeout)
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_are_answered() {
+        let (engine, channels) = MockEngine::<TestContext>::new();
+
+        let snapshot = ConsensusRequest::snapshot_queues(&channels.requests)
+            .await
+            .unwrap();
+        assert_eq!(snapshot.height, engine.height());
+        assert_eq!(snapshot.phase, "unstarted");
+
+        assert!(
+            !ConsensusRequest::set_signing_enabled(&channels.requests, false)
+                .await
+                .unwrap()
+        );
+
+        assert!(NetworkRequest::dump_state(&channels.net_requests)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(SyncRequest::low_watermark(&channels.sync_requests)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(!SyncRequest::pause(&channels.sync_requests).await.unwrap());
+        assert!(
+            !SyncRequest::backfill(&channels.sync_requests, Height::new(1)..=Height::new(10))
+                .await
+                .unwrap()
+        );
+        assert!(!SyncRequest::update_config(
+            &channels.sync_requests,
+            ConfigUpdate::default().with_batch_size(10)
+        )
+        .await
+        .unwrap());
+        assert!(
+            !SyncRequest::set_retain_height(&channels.sync_requests, Height::new(5))
+                .await
+                .unwrap()
+        );
+        assert!(SyncRequest::status(&channels.sync_requests)
+            .await
+            .unwrap()
+            .is_none());
+
+        let peer_id = PeerId::random();
+        assert_eq!(
+            NetworkRequest::send_to_peer(&channels.net_requests, peer_id, Bytes::new())
+                .await
+                .unwrap(),
+            Err(PeerMessageError::DialFailure)
+        );
+    }
+
+    #[tokio::test]
+    async fn peer_messages_are_replied_to() {
+        let (engine, mut channels) = MockEngine::<TestContext>::new();
+
+        tokio::spawn(async move {
+            while let Some(message) = channels.peer_messages.recv().await {
+                let _ = message.reply.send(message.payload);
+            }
+        });
+
+        let peer_id = PeerId::random();
+        let reply = engine
+            .send_peer_message(peer_id, Bytes::from_static(b"ping"))
+            .await
+            .unwrap();
+        assert_eq!(reply.as_ref(), b"ping");
+    }
+
+    #[tokio::test]
+    async fn connectivity_events_are_delivered() {
+        let (engine, mut channels) = MockEngine::<TestContext>::new();
+
+        let event = ConnectivityEvent::InsufficientPeers {
+            peers: 0,
+            required: 4,
+        };
+
+        engine.send_connectivity_event(event.clone()).await.unwrap();
+        assert_eq!(channels.connectivity.recv().await, Some(event));
+    }
+
+    #[tokio::test]
+    async fn health_can_be_set() {
+        let (engine, channels) = MockEngine::<TestContext>::new();
+        assert!(channels.health.borrow().is_ready());
+
+        engine.set_health(HealthStatus {
+            sufficient_peers: false,
+            ..HealthStatus::healthy()
+        });
+
+        let status = channels.health.borrow().clone();
+        assert!(status.is_live());
+        assert!(!status.is_ready());
+    }
+}
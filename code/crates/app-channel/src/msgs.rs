@@ -1,4 +1,5 @@
 use std::ops::RangeInclusive;
+use std::sync::Arc;
 use std::time::Duration;
 
 use bytes::Bytes;
@@ -6,25 +7,39 @@ use derive_where::derive_where;
 use thiserror::Error;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
+use tokio::sync::watch;
 use tracing::error;
 
 use malachitebft_app::consensus::Role;
 use malachitebft_app::consensus::VoteExtensionError;
+use malachitebft_app::metrics::SharedRegistry;
 use malachitebft_app::types::core::ValueOrigin;
-use malachitebft_app::types::MisbehaviorEvidence;
+use malachitebft_app::types::{Evidence, MisbehaviorEvidence};
+use malachitebft_engine::consensus::config_update::ConfigUpdate as ConsensusConfigUpdate;
+use malachitebft_engine::consensus::queue_snapshot::QueueSnapshot;
 use malachitebft_engine::consensus::state_dump::StateDump;
 use malachitebft_engine::consensus::Msg as ConsensusActorMsg;
 use malachitebft_engine::host::{HeightParams, Next};
 use malachitebft_engine::network::Msg as NetworkActorMsg;
 use malachitebft_engine::network::{
-    Multiaddr, NetworkStateDump, PersistentPeerError, PersistentPeersOp,
+    AddressBook, ConfigUpdate as NetworkConfigUpdate, ConfigUpdateError, ConfigUpdateOutcome,
+    ConnectedPeer, ConnectivityEvent, DiscoveredPeer, LinkConditions, MempoolMessage, Multiaddr,
+    NetworkStateDump, NetworkTopology, NodeKeyError, NodeKeyRotation, NodeKeyStatus,
+    PeerConnectionError, PeerMessage, PeerMessageError, PersistentPeerError, PersistentPeersOp,
 };
+use malachitebft_engine::sync::state_dump::SyncStateDump;
+use malachitebft_engine::sync::status::SyncStatus;
 use malachitebft_engine::util::events::TxEvent;
+use malachitebft_signing::SigningProvider;
 
-use crate::app::types::core::{CommitCertificate, Context, Round, ValueId, VoteExtensions};
+use crate::app::types::core::{
+    CommitCertificate, Context, DecisionProof, Round, ValueId, VoteExtensions,
+};
 use crate::app::types::streaming::StreamMessage;
-use crate::app::types::sync::RawDecidedValue;
+use crate::app::types::sync::{ConfigUpdate, RawDecidedValue, Snapshot};
 use crate::app::types::{LocallyProposedValue, PeerId, ProposedValue};
+use crate::log_filter::LogFilterError;
+use crate::HealthStatus;
 
 pub type Reply<T> = oneshot::Sender<T>;
 
@@ -102,6 +117,25 @@ impl From<oneshot::error::RecvError> for ConsensusRequestError {
 pub enum ConsensusRequest<Ctx: Context> {
     /// Request a state dump from consensus
     DumpState(Reply<Option<StateDump<Ctx>>>),
+    /// Request a summary of the inputs and messages waiting in the queues of consensus
+    SnapshotQueues(Reply<QueueSnapshot<Ctx>>),
+    /// Request the evidence of equivocation not committed to by any decided value
+    UncommittedEvidence(Reply<Vec<Evidence<Ctx>>>),
+    /// Disable or re-enable signing at runtime
+    SetSigningEnabled(bool, Reply<bool>),
+    /// Sign with a new key from the given height on
+    RegisterSigningKey(Ctx::Height, Arc<dyn SigningProvider<Ctx>>, Reply<bool>),
+    /// Stop participating in consensus until resumed
+    Pause(Reply<()>),
+    /// Resume participating in consensus at the current height and round
+    Resume(Reply<()>),
+    /// Change the configuration of consensus without restarting the node
+    UpdateConfig(
+        ConsensusConfigUpdate<Ctx>,
+        Reply<Result<ConfigUpdateOutcome, ConfigUpdateError>>,
+    ),
+    /// Gracefully shut down the engine, see [`EngineHandle::shutdown`](crate::EngineHandle::shutdown)
+    Shutdown(Reply<()>),
 }
 
 impl<Ctx: Context> ConsensusRequest<Ctx> {
@@ -123,14 +157,225 @@ impl<Ctx: Context> ConsensusRequest<Ctx> {
 
         Ok(dump)
     }
+
+    /// Request a summary of the votes, proposals and other inputs waiting in the queues
+    /// of consensus, grouped by height, round and kind, with their count and the time at
+    /// which the oldest of them was queued. Useful to find out what consensus is waiting
+    /// on during a stall, as the request is served without pausing consensus,
+    /// even while the WAL is being replayed.
+    pub async fn snapshot_queues(
+        tx_request: &mpsc::Sender<ConsensusRequest<Ctx>>,
+    ) -> Result<QueueSnapshot<Ctx>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::SnapshotQueues(tx))
+            .inspect_err(|e| error!("Failed to send SnapshotQueues request to consensus: {e}"))?;
+
+        let snapshot = rx.await.inspect_err(|e| {
+            error!("Failed to receive SnapshotQueues response from consensus: {e}")
+        })?;
+
+        Ok(snapshot)
+    }
+
+    /// Request the evidence of equivocation detected by this node or gossiped by its peers
+    /// over the last heights, eg. to include it in the next value proposed by this node.
+    ///
+    /// This evidence is uncommitted: it differs from one node to another, depending on
+    /// the messages each of them received, and is not part of any decided value. It must
+    /// therefore not drive any state transition of the application, eg. slashing, unless
+    /// the application includes it in a value which is then decided.
+    pub async fn uncommitted_evidence(
+        tx_request: &mpsc::Sender<ConsensusRequest<Ctx>>,
+    ) -> Result<Vec<Evidence<Ctx>>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::UncommittedEvidence(tx))
+            .inspect_err(|e| {
+                error!("Failed to send UncommittedEvidence request to consensus: {e}")
+            })?;
+
+        let evidence = rx.await.inspect_err(|e| {
+            error!("Failed to receive UncommittedEvidence response from consensus: {e}")
+        })?;
+
+        Ok(evidence)
+    }
+
+    /// Disable or re-enable signing at runtime, eg. while migrating the validator key
+    /// to another machine. While signing is disabled, the node behaves as a follower:
+    /// it keeps following consensus but neither signs nor proposes.
+    ///
+    /// Once this returns, no signature will be produced until signing is enabled again.
+    /// Returns whether signing is enabled, which is always `false` for a node
+    /// configured as a follower.
+    pub async fn set_signing_enabled(
+        tx_request: &mpsc::Sender<ConsensusRequest<Ctx>>,
+        enabled: bool,
+    ) -> Result<bool, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::SetSigningEnabled(enabled, tx))
+            .inspect_err(|e| {
+                error!("Failed to send SetSigningEnabled request to consensus: {e}")
+            })?;
+
+        let signing_enabled = rx.await.inspect_err(|e| {
+            error!("Failed to receive SetSigningEnabled response from consensus: {e}")
+        })?;
+
+        Ok(signing_enabled)
+    }
+
+    /// Sign with the given key from the given height on, once the validator has registered
+    /// a new consensus key effective at that height, eg. when rotating its key.
+    ///
+    /// The validator set of that height and the following ones must carry the matching
+    /// public key. Returns whether the key was registered, which it is not if consensus
+    /// has already started the height. The key is not persisted by consensus, so it must
+    /// be registered again after a restart, unless the node is started with it.
+    pub async fn register_signing_key(
+        tx_request: &mpsc::Sender<ConsensusRequest<Ctx>>,
+        height: Ctx::Height,
+        signing_provider: Arc<dyn SigningProvider<Ctx>>,
+    ) -> Result<bool, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::RegisterSigningKey(height, signing_provider, tx))
+            .inspect_err(|e| {
+                error!("Failed to send RegisterSigningKey request to consensus: {e}")
+            })?;
+
+        let registered = rx.await.inspect_err(|e| {
+            error!("Failed to receive RegisterSigningKey response from consensus: {e}")
+        })?;
+
+        Ok(registered)
+    }
+
+    /// Stop participating in new rounds, eg. for maintenance or a coordinated upgrade,
+    /// without stopping the node. While paused, the node keeps serving the sync requests
+    /// of its peers, and the values it is asked to propose or receives are held until resumed.
+    pub async fn pause(
+        tx_request: &mpsc::Sender<ConsensusRequest<Ctx>>,
+    ) -> Result<(), ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::Pause(tx))
+            .inspect_err(|e| error!("Failed to send Pause request to consensus: {e}"))?;
+
+        rx.await
+            .inspect_err(|e| error!("Failed to receive Pause response from consensus: {e}"))?;
+
+        Ok(())
+    }
+
+    /// Resume participating in consensus at the current height and round, see [`Self::pause`]
+    pub async fn resume(
+        tx_request: &mpsc::Sender<ConsensusRequest<Ctx>>,
+    ) -> Result<(), ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::Resume(tx))
+            .inspect_err(|e| error!("Failed to send Resume request to consensus: {e}"))?;
+
+        rx.await
+            .inspect_err(|e| error!("Failed to receive Resume response from consensus: {e}"))?;
+
+        Ok(())
+    }
+
+    /// Change the timeouts or the vote limit of consensus without restarting the node,
+    /// the parameters left unset in `update` being unchanged. The timeouts set this way are
+    /// used instead of those provided by the application, until the node restarts.
+    ///
+    /// Returns the parameters applied and those only taking effect once the node is restarted
+    /// with them in its configuration, or an error if the update is invalid, in which case
+    /// none of them is applied. See [`NetworkRequest::update_config`] for the network
+    /// parameters, and [`SyncRequest::update_config`] for the sync batch size.
+    pub async fn update_config(
+        tx_request: &mpsc::Sender<ConsensusRequest<Ctx>>,
+        update: ConsensusConfigUpdate<Ctx>,
+    ) -> Result<Result<ConfigUpdateOutcome, ConfigUpdateError>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::UpdateConfig(update, tx))
+            .inspect_err(|e| error!("Failed to send UpdateConfig request to consensus: {e}"))?;
+
+        let result = rx.await.inspect_err(|e| {
+            error!("Failed to receive UpdateConfig response from consensus: {e}")
+        })?;
+
+        Ok(result)
+    }
+
+    /// Gracefully shut down the engine, see [`EngineHandle::shutdown`](crate::EngineHandle::shutdown).
+    ///
+    /// Returns once all the actors of the engine have stopped.
+    pub async fn shutdown(
+        tx_request: &mpsc::Sender<ConsensusRequest<Ctx>>,
+    ) -> Result<(), ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::Shutdown(tx))
+            .inspect_err(|e| error!("Failed to send Shutdown request to consensus: {e}"))?;
+
+        rx.await
+            .inspect_err(|e| error!("Failed to receive Shutdown response from consensus: {e}"))?;
+
+        Ok(())
+    }
 }
 
 /// Represents requests that can be sent to the network layer by the application.
 pub enum NetworkRequest {
     /// Request a state dump from the network
     DumpState(Reply<Option<NetworkStateDump>>),
+    /// Snapshot the local view of the graph of the network
+    DumpTopology(Reply<Option<NetworkTopology>>),
+    /// Query the peers currently known to discovery
+    DiscoveredPeers(Reply<Option<Vec<DiscoveredPeer>>>),
+    /// List the peers this node is connected to, with their direction, addresses,
+    /// protocols and scores
+    ListPeers(Reply<Option<Vec<ConnectedPeer>>>),
+    /// Export the peers known to discovery, to seed other nodes
+    ExportAddressBook(Reply<Option<AddressBook>>),
+    /// Dial the peers of an address book, eg. exported by another node
+    ImportAddressBook(AddressBook, Reply<Option<usize>>),
+    /// Close all the connections to a peer
+    DisconnectPeer(PeerId, Reply<Result<(), PeerConnectionError>>),
+    /// Dial a peer at the given address
+    DialPeer(Multiaddr, Reply<Result<(), PeerConnectionError>>),
     /// Add or remove a persistent peer at runtime
     UpdatePersistentPeers(PersistentPeersOp, Reply<Result<(), PersistentPeerError>>),
+    /// Change the peer limits and the inbound rate limits without restarting the node
+    UpdateConfig(
+        NetworkConfigUpdate,
+        Reply<Result<ConfigUpdateOutcome, ConfigUpdateError>>,
+    ),
+    /// Query the current peer ID of the node, and the one it takes once restarted
+    /// if its key was rotated
+    NodeKeyStatus(Reply<Option<NodeKeyStatus>>),
+    /// Rotate the node key, taking effect once the node is restarted
+    RotateNodeKey(Reply<Result<NodeKeyRotation, NodeKeyError>>),
+    /// Send a message directly to a peer, eg. to fetch transactions missing from the mempool.
+    /// The peer receives it on its `peer_messages` channel.
+    SendToPeer {
+        peer: PeerId,
+        payload: Bytes,
+        /// Channel for sending back the reply of the peer
+        reply: Reply<Result<Bytes, PeerMessageError>>,
+    },
+    /// Replace the directives of the filter of the logs, replying with the previous ones
+    SetLogFilter(String, Reply<Result<String, LogFilterError>>),
 }
 
 impl NetworkRequest {
@@ -151,6 +396,163 @@ impl NetworkRequest {
         Ok(dump)
     }
 
+    /// Snapshot the local view of the graph of the network: the gossipsub mesh of each topic,
+    /// the outbound, inbound and ephemeral peers, the relay circuits and the occupancy of the
+    /// Kademlia routing table. The snapshot serializes to JSON, eg. to draw the graph offline.
+    pub async fn dump_topology(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+    ) -> Result<Option<NetworkTopology>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request.try_send(Self::DumpTopology(tx)).inspect_err(
+            |error| error!(%error, "Failed to send DumpTopology request to network"),
+        )?;
+
+        let topology = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive DumpTopology response from network"),
+        )?;
+
+        Ok(topology)
+    }
+
+    /// Query the peers currently known to discovery, along with their addresses,
+    /// kind (outbound, inbound or ephemeral) and whether they are reached via a relay.
+    pub async fn discovered_peers(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+    ) -> Result<Option<Vec<DiscoveredPeer>>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request.try_send(Self::DiscoveredPeers(tx)).inspect_err(
+            |error| error!(%error, "Failed to send DiscoveredPeers request to network"),
+        )?;
+
+        let peers = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive DiscoveredPeers response from network"),
+        )?;
+
+        Ok(peers)
+    }
+
+    /// List the peers this node is connected to, with their direction, the addresses of
+    /// their connections, the protocols they support and their scores.
+    pub async fn list_peers(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+    ) -> Result<Option<Vec<ConnectedPeer>>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::ListPeers(tx))
+            .inspect_err(|error| error!(%error, "Failed to send ListPeers request to network"))?;
+
+        let peers = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive ListPeers response from network"),
+        )?;
+
+        Ok(peers)
+    }
+
+    /// List the connected peers which sent the most pubsub messages whose payload was already
+    /// received, most first and at most `limit` of them, to find a misconfigured mesh.
+    pub async fn top_duplicate_senders(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+        limit: usize,
+    ) -> Result<Option<Vec<ConnectedPeer>>, ConsensusRequestError> {
+        let peers = Self::list_peers(tx_request).await?;
+
+        Ok(peers.map(|mut peers| {
+            peers.retain(|peer| peer.duplicate_messages > 0);
+            peers.sort_by(|a, b| b.duplicate_messages.cmp(&a.duplicate_messages));
+            peers.truncate(limit);
+            peers
+        }))
+    }
+
+    /// Export the peers identified by discovery since the node started, most recently seen
+    /// first, eg. to save them with [`AddressBook::save`] and seed a new node from them.
+    pub async fn export_address_book(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+    ) -> Result<Option<AddressBook>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::ExportAddressBook(tx))
+            .inspect_err(
+                |error| error!(%error, "Failed to send ExportAddressBook request to network"),
+            )?;
+
+        let book = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive ExportAddressBook response from network"),
+        )?;
+
+        Ok(book)
+    }
+
+    /// Dial the peers of an address book, eg. exported by another node and read with
+    /// [`AddressBook::load`], returning the number of peers dialed. The banned peers and
+    /// the peers already connected are skipped. See `p2p.discovery.address_book` to import
+    /// an address book at startup.
+    pub async fn import_address_book(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+        book: AddressBook,
+    ) -> Result<Option<usize>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::ImportAddressBook(book, tx))
+            .inspect_err(
+                |error| error!(%error, "Failed to send ImportAddressBook request to network"),
+            )?;
+
+        let dialed = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive ImportAddressBook response from network"),
+        )?;
+
+        Ok(dialed)
+    }
+
+    /// Close all the connections to a peer.
+    ///
+    /// Discovery may connect to the peer again, eg. if it is a persistent peer,
+    /// see [`NetworkMsg::BanPeer`] to keep it disconnected.
+    pub async fn disconnect_peer(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+        peer: PeerId,
+    ) -> Result<Result<(), PeerConnectionError>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::DisconnectPeer(peer, tx))
+            .inspect_err(
+                |error| error!(%error, "Failed to send DisconnectPeer request to network"),
+            )?;
+
+        let result = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive DisconnectPeer response from network"),
+        )?;
+
+        Ok(result)
+    }
+
+    /// Dial a peer at the given address, returning once the dial is started.
+    ///
+    /// The peer is not added to the persistent peers, see [`NetworkRequest::add_persistent_peer`].
+    pub async fn dial_peer(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+        addr: Multiaddr,
+    ) -> Result<Result<(), PeerConnectionError>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::DialPeer(addr, tx))
+            .inspect_err(|error| error!(%error, "Failed to send DialPeer request to network"))?;
+
+        let result = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive DialPeer response from network"),
+        )?;
+
+        Ok(result)
+    }
+
     /// Add a persistent peer at runtime.
     pub async fn add_persistent_peer(
         tx_request: &mpsc::Sender<NetworkRequest>,
@@ -196,6 +598,319 @@ impl NetworkRequest {
 
         Ok(result)
     }
+
+    /// Change the number of peers, the maximum number of connections per IP address or
+    /// the inbound rate limits without restarting the node, the parameters left unset
+    /// in `update` being unchanged. The connections already established are kept.
+    ///
+    /// Returns the parameters applied and those only taking effect once the node is restarted
+    /// with them in its configuration, such as the GossipSub parameters, or an error if the
+    /// update is invalid, in which case none of them is applied.
+    pub async fn update_config(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+        update: NetworkConfigUpdate,
+    ) -> Result<Result<ConfigUpdateOutcome, ConfigUpdateError>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::UpdateConfig(update, tx))
+            .inspect_err(
+                |error| error!(%error, "Failed to send UpdateConfig request to network"),
+            )?;
+
+        let result = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive UpdateConfig response from network"),
+        )?;
+
+        Ok(result)
+    }
+
+    /// Query the current peer ID of the node, and the one it takes once restarted if its key
+    /// was rotated, or `None` if the network is not running.
+    pub async fn node_key_status(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+    ) -> Result<Option<NodeKeyStatus>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request.try_send(Self::NodeKeyStatus(tx)).inspect_err(
+            |error| error!(%error, "Failed to send NodeKeyStatus request to network"),
+        )?;
+
+        let status = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive NodeKeyStatus response from network"),
+        )?;
+
+        Ok(status)
+    }
+
+    /// Rotate the node key persisted in the file set in the `node_key_file` of the P2P
+    /// configuration, the previous key being kept next to it with the `.prev` extension.
+    ///
+    /// The new peer ID is announced to the connected persistent peers, which dial the node
+    /// with it from then on, and only taken by the node once restarted.
+    pub async fn rotate_node_key(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+    ) -> Result<Result<NodeKeyRotation, NodeKeyError>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request.try_send(Self::RotateNodeKey(tx)).inspect_err(
+            |error| error!(%error, "Failed to send RotateNodeKey request to network"),
+        )?;
+
+        let result = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive RotateNodeKey response from network"),
+        )?;
+
+        Ok(result)
+    }
+
+    /// Send a message directly to a peer and wait for its reply.
+    pub async fn send_to_peer(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+        peer: PeerId,
+        payload: Bytes,
+    ) -> Result<Result<Bytes, PeerMessageError>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::SendToPeer {
+                peer,
+                payload,
+                reply: tx,
+            })
+            .inspect_err(|error| error!(%error, "Failed to send SendToPeer request to network"))?;
+
+        let result = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive SendToPeer response from network"),
+        )?;
+
+        Ok(result)
+    }
+
+    /// Replace the directives of the filter of the logs at runtime, eg. to bump
+    /// `malachitebft_discovery=debug` while diagnosing a peering issue, returning the previous
+    /// directives so that they can be restored once done.
+    ///
+    /// Fails unless a reloadable filter was given with [`crate::RequestContext::with_log_filter`].
+    pub async fn set_log_filter(
+        tx_request: &mpsc::Sender<NetworkRequest>,
+        directives: impl Into<String>,
+    ) -> Result<Result<String, LogFilterError>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::SetLogFilter(directives.into(), tx))
+            .inspect_err(
+                |error| error!(%error, "Failed to send SetLogFilter request to network"),
+            )?;
+
+        let result = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive SetLogFilter response from network"),
+        )?;
+
+        Ok(result)
+    }
+}
+
+/// Represents requests that can be sent to the sync actor by the application.
+pub enum SyncRequest<Ctx: Context> {
+    /// Request a state dump from sync, `None` if sync is disabled
+    DumpState(Reply<Option<SyncStateDump<Ctx>>>),
+
+    /// Request the lowest height still needed by any of the connected peers,
+    /// `None` if sync is disabled or there are no peers
+    LowWatermark(Reply<Option<Ctx::Height>>),
+
+    /// Stop requesting values from the peers, eg. during maintenance of the node,
+    /// while still serving their requests. Replies `false` if sync is disabled.
+    Pause(Reply<bool>),
+
+    /// Resume requesting values from the peers. Replies `false` if sync is disabled.
+    Resume(Reply<bool>),
+
+    /// Request a summary of the progress of sync, `None` if sync is disabled
+    Status(Reply<Option<SyncStatus<Ctx>>>),
+
+    /// Fetch the values in the given range once caught up with the peers,
+    /// eg. the ones below the height the node started from. Replies `false` if sync is disabled.
+    Backfill(RangeInclusive<Ctx::Height>, Reply<bool>),
+
+    /// Change some parameters of sync on the running node, eg. to tune how fast it catches up.
+    /// Replies `false` if sync is disabled.
+    UpdateConfig(ConfigUpdate, Reply<bool>),
+
+    /// Stop serving and advertising the decided values below the given height to the peers,
+    /// before the application deletes them. Replies `false` if sync is disabled.
+    SetRetainHeight(Ctx::Height, Reply<bool>),
+}
+
+impl<Ctx: Context> SyncRequest<Ctx> {
+    /// Request a state dump from sync, including the per-peer history
+    /// of the requests sent and served over the audit window.
+    pub async fn dump_state(
+        tx_request: &mpsc::Sender<SyncRequest<Ctx>>,
+    ) -> Result<Option<SyncStateDump<Ctx>>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::DumpState(tx))
+            .inspect_err(|error| error!(%error, "Failed to send DumpState request to sync"))?;
+
+        let dump = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive DumpState response from sync"),
+        )?;
+
+        Ok(dump)
+    }
+
+    /// Request the lowest height still needed by any of the connected peers.
+    ///
+    /// Decided values below the low watermark can be pruned without cutting off
+    /// any syncing peer. Peers which do not advertise the minimum height they need
+    /// are assumed to need every height above their tip.
+    pub async fn low_watermark(
+        tx_request: &mpsc::Sender<SyncRequest<Ctx>>,
+    ) -> Result<Option<Ctx::Height>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::LowWatermark(tx))
+            .inspect_err(|error| error!(%error, "Failed to send LowWatermark request to sync"))?;
+
+        let low_watermark = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive LowWatermark response from sync"),
+        )?;
+
+        Ok(low_watermark)
+    }
+
+    /// Stop requesting values from the peers until [`SyncRequest::resume`] is called.
+    ///
+    /// Returns `false` if sync is disabled.
+    pub async fn pause(
+        tx_request: &mpsc::Sender<SyncRequest<Ctx>>,
+    ) -> Result<bool, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::Pause(tx))
+            .inspect_err(|error| error!(%error, "Failed to send Pause request to sync"))?;
+
+        let paused = rx
+            .await
+            .inspect_err(|error| error!(%error, "Failed to receive Pause response from sync"))?;
+
+        Ok(paused)
+    }
+
+    /// Resume requesting values from the peers.
+    ///
+    /// Returns `false` if sync is disabled.
+    pub async fn resume(
+        tx_request: &mpsc::Sender<SyncRequest<Ctx>>,
+    ) -> Result<bool, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::Resume(tx))
+            .inspect_err(|error| error!(%error, "Failed to send Resume request to sync"))?;
+
+        let resumed = rx
+            .await
+            .inspect_err(|error| error!(%error, "Failed to receive Resume response from sync"))?;
+
+        Ok(resumed)
+    }
+
+    /// Request the current sync height, the number of pending requests
+    /// and the estimated time until the tip of the peers is reached, along with
+    /// whether the node is still starting, see [`SyncStatus::node_status`].
+    pub async fn status(
+        tx_request: &mpsc::Sender<SyncRequest<Ctx>>,
+    ) -> Result<Option<SyncStatus<Ctx>>, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::Status(tx))
+            .inspect_err(|error| error!(%error, "Failed to send Status request to sync"))?;
+
+        let status = rx
+            .await
+            .inspect_err(|error| error!(%error, "Failed to receive Status response from sync"))?;
+
+        Ok(status)
+    }
+
+    /// Fetch the values in the given range from the peers once caught up with them, and hand
+    /// them to the application with [`AppMsg::StoreBackfilledValues`], replacing any range
+    /// being backfilled.
+    ///
+    /// Returns `false` if sync is disabled.
+    pub async fn backfill(
+        tx_request: &mpsc::Sender<SyncRequest<Ctx>>,
+        range: RangeInclusive<Ctx::Height>,
+    ) -> Result<bool, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::Backfill(range, tx))
+            .inspect_err(|error| error!(%error, "Failed to send Backfill request to sync"))?;
+
+        let backfilling = rx
+            .await
+            .inspect_err(|error| error!(%error, "Failed to receive Backfill response from sync"))?;
+
+        Ok(backfilling)
+    }
+
+    /// Change the batch size, the number of parallel requests or the timeout of the requests
+    /// of sync on the running node, the parameters left unset in `update` being unchanged.
+    ///
+    /// Returns `false` if sync is disabled.
+    pub async fn update_config(
+        tx_request: &mpsc::Sender<SyncRequest<Ctx>>,
+        update: ConfigUpdate,
+    ) -> Result<bool, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::UpdateConfig(update, tx))
+            .inspect_err(|error| error!(%error, "Failed to send UpdateConfig request to sync"))?;
+
+        let updated = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive UpdateConfig response from sync"),
+        )?;
+
+        Ok(updated)
+    }
+
+    /// Let sync know that the decided values and their certificates below `retain_height`
+    /// are about to be pruned from the store of the application.
+    ///
+    /// From then on, the peers are told that these values are no longer available,
+    /// and only the values of their requests from the retain height on are served,
+    /// so that the application can delete the others in the background.
+    /// The retain height never goes down, a lower one being ignored.
+    ///
+    /// Returns `true` once sync has applied the retain height, or `false` if sync is disabled.
+    pub async fn set_retain_height(
+        tx_request: &mpsc::Sender<SyncRequest<Ctx>>,
+        retain_height: Ctx::Height,
+    ) -> Result<bool, ConsensusRequestError> {
+        let (tx, rx) = oneshot::channel();
+
+        tx_request
+            .try_send(Self::SetRetainHeight(retain_height, tx))
+            .inspect_err(
+                |error| error!(%error, "Failed to send SetRetainHeight request to sync"),
+            )?;
+
+        let set = rx.await.inspect_err(
+            |error| error!(%error, "Failed to receive SetRetainHeight response from sync"),
+        )?;
+
+        Ok(set)
+    }
 }
 
 /// Channels created for application consumption
@@ -204,12 +919,33 @@ pub struct Channels<Ctx: Context> {
     pub consensus: mpsc::Receiver<AppMsg<Ctx>>,
     /// Channel for sending messages to the networking layer
     pub network: mpsc::Sender<NetworkMsg<Ctx>>,
-    /// Receiver of events, call `subscribe` to receive them
+    /// Receiver of events, call `subscribe` to receive them,
+    /// or see [`Channels::subscribe_consensus_events`] for the consensus progress only
     pub events: TxEvent<Ctx>,
     /// Channel for sending requests to consensus
     pub requests: mpsc::Sender<ConsensusRequest<Ctx>>,
     /// Channel for sending requests to the network
     pub net_requests: mpsc::Sender<NetworkRequest>,
+    /// Channel for sending requests to sync
+    pub sync_requests: mpsc::Sender<SyncRequest<Ctx>>,
+    /// Channel for receiving the messages sent directly by the peers,
+    /// see [`NetworkRequest::SendToPeer`]
+    pub peer_messages: mpsc::Receiver<PeerMessage>,
+    /// Channel for receiving the transactions gossiped by the peers on the mempool channel,
+    /// see [`NetworkMsg::PublishMempool`]. Messages are dropped if the channel is full.
+    pub mempool: mpsc::Receiver<MempoolMessage>,
+    /// Channel for receiving the peers connecting and disconnecting, and whether enough of them
+    /// take part in consensus, eg. to only report the node as ready once they do.
+    /// Starts with the peers already connected, events are dropped if the channel is full.
+    pub connectivity: mpsc::Receiver<ConnectivityEvent>,
+    /// Outcome of the latest health check of the node, eg. to serve liveness and readiness
+    /// probes with [`HealthStatus::is_live`] and [`HealthStatus::is_ready`].
+    /// Health checks stop once this is dropped.
+    pub health: watch::Receiver<HealthStatus>,
+    /// Registry of the engine metrics, labelled with the moniker of the node.
+    /// Metrics registered by the application with [`SharedRegistry::with_prefix`]
+    /// are exported along with those of the engine.
+    pub metrics: SharedRegistry,
 }
 
 /// Messages sent from consensus to the application.
@@ -327,13 +1063,17 @@ pub enum AppMsg<Ctx: Context> {
     /// This message includes a commit certificate containing the ID of
     /// the value that was decided on, the height and round at which it was decided,
     /// and the aggregated signatures of the validators that committed to it.
-    /// It also includes to the vote extensions received for that height.
+    /// It also includes to the vote extensions received for that height, and
+    /// if `consensus.decision_proof` is enabled, the data needed to build a proof of the decision.
     Decided {
         /// The certificate for the decided value
         certificate: CommitCertificate<Ctx>,
 
         /// The vote extensions received for that height
         extensions: VoteExtensions<Ctx>,
+
+        /// The round, proposer and signers of the decision, if enabled
+        proof: Option<DecisionProof<Ctx>>,
     },
 
     /// Notifies the application that a height has been finalized after collecting additional precommits.
@@ -356,7 +1096,10 @@ pub enum AppMsg<Ctx: Context> {
         /// The vote extensions received for that height (including additional ones)
         extensions: VoteExtensions<Ctx>,
 
-        /// Misbehavior evidence observed since last decide
+        /// Misbehavior evidence observed since last decide.
+        ///
+        /// This is the evidence observed by this node only, the evidence gossiped by
+        /// the peers is not included, see [`ConsensusRequest::uncommitted_evidence`].
         evidence: MisbehaviorEvidence<Ctx>,
 
         /// Channel for instructing consensus to start the next height, if desired
@@ -382,6 +1125,10 @@ pub enum AppMsg<Ctx: Context> {
     ///
     /// If a value can be decoded from the bytes provided, then the application MUST reply
     /// to this message with the decoded value. Otherwise, it MUST reply with `None`.
+    ///
+    /// When syncing certificates only (see `value_sync.certificates_only`), the value bytes
+    /// are empty and the application MUST reply with the value it knows for that height,
+    /// eg. restored from a snapshot, whose id must match the one in the commit certificate.
     ProcessSyncedValue {
         /// Height of the synced value
         height: Ctx::Height,
@@ -395,6 +1142,59 @@ pub enum AppMsg<Ctx: Context> {
         /// or `None` if the value could not be decoded
         reply: Reply<Option<ProposedValue<Ctx>>>,
     },
+
+    /// Requests the snapshots of the application state which can be served to the peers,
+    /// for them to restore their state from instead of syncing every value.
+    ///
+    /// The application MUST respond with the snapshots it has, or with an empty vector.
+    GetSnapshots {
+        /// Channel for sending back the snapshots
+        reply: Reply<Vec<Snapshot<Ctx>>>,
+    },
+
+    /// Requests a chunk of a snapshot of the application state, to be served to a peer.
+    ///
+    /// The application MUST respond with the chunk if it has it, or `None` otherwise.
+    GetSnapshotChunk {
+        /// Height of the snapshot
+        height: Ctx::Height,
+        /// Format of the snapshot
+        format: u32,
+        /// Index of the chunk
+        index: u32,
+        /// Channel for sending back the chunk
+        reply: Reply<Option<Bytes>>,
+    },
+
+    /// Requests the application to restore its state from a snapshot fetched from the peers,
+    /// when `value_sync.snapshot_sync` is enabled.
+    ///
+    /// The application MUST check the chunks against the hash of the snapshot. If the snapshot
+    /// is restored, it MUST respond with the parameters of the height following the snapshot,
+    /// at which consensus then starts. Otherwise, it MUST respond with `None`, in which case
+    /// the values are synced one height at a time instead.
+    RestoreSnapshot {
+        /// The snapshot to restore
+        snapshot: Snapshot<Ctx>,
+        /// The chunks of the snapshot, in order
+        chunks: Vec<Bytes>,
+        /// Channel for sending back the parameters of the height following the snapshot
+        reply: Reply<Option<HeightParams<Ctx>>>,
+    },
+
+    /// Requests the application to store decided values below the height it started from,
+    /// fetched from the peers when backfilling, eg. after restoring a snapshot
+    /// with `value_sync.backfill` enabled.
+    ///
+    /// The application MUST verify the commit certificates of the values before storing them,
+    /// and respond with `false` if any of them is invalid, in which case the values are
+    /// requested from another peer.
+    StoreBackfilledValues {
+        /// The decided values, in increasing order of height
+        values: Vec<RawDecidedValue<Ctx>>,
+        /// Channel for sending back whether the values were stored
+        reply: Reply<bool>,
+    },
 }
 
 /// Messages sent from the application to consensus.
@@ -431,12 +1231,58 @@ impl<Ctx: Context> From<ConsensusMsg<Ctx>> for ConsensusActorMsg<Ctx> {
 pub enum NetworkMsg<Ctx: Context> {
     /// Publish a proposal part to the network, within a stream.
     PublishProposalPart(StreamMessage<Ctx::ProposalPart>),
+
+    /// Add a delta to the GossipSub score of a peer, eg. a negative one to penalize a peer
+    /// which delivered invalid proposals or votes. A peer whose score becomes negative
+    /// is pruned from the mesh. Only effective when peer scoring is enabled.
+    ReportPeer(PeerId, f64),
+
+    /// Provide the peer IDs of the validators of the current validator set, eg. from a
+    /// registry of the application, so that discovery connects to them first and keeps
+    /// their connections open, and GossipSub protects them from mesh pruning when
+    /// `enable_explicit_validator_peering` is set. Replaces the previously provided peers.
+    UpdateValidatorPeers(Vec<PeerId>),
+
+    /// Disconnect a peer and refuse any connection to or from it, eg. a malicious peer,
+    /// for the given duration or until unbanned if `None`.
+    BanPeer(PeerId, Option<Duration>),
+
+    /// Lift the ban of a peer
+    UnbanPeer(PeerId),
+
+    /// Simulate latency, jitter and loss on the messages received from a peer, eg. to test
+    /// the timeouts of consensus under the conditions of a wide area network,
+    /// or stop simulating them if `None`
+    SetLinkConditions(PeerId, Option<LinkConditions>),
+
+    /// Start listening on an additional address without restarting the node,
+    /// eg. when moving to a new network interface. The peers are told about the new address.
+    AddListenAddr(Multiaddr),
+
+    /// Stop listening on an address
+    RemoveListenAddr(Multiaddr),
+
+    /// Gossip transactions, or a batch of them, on the mempool channel.
+    /// Dropped unless `p2p.mempool` is enabled, or if larger than its `max_msg_size`.
+    PublishMempool(Bytes),
 }
 
 impl<Ctx: Context> From<NetworkMsg<Ctx>> for NetworkActorMsg<Ctx> {
     fn from(msg: NetworkMsg<Ctx>) -> NetworkActorMsg<Ctx> {
         match msg {
             NetworkMsg::PublishProposalPart(part) => NetworkActorMsg::PublishProposalPart(part),
+            NetworkMsg::ReportPeer(peer_id, score_delta) => {
+                NetworkActorMsg::ReportPeer(peer_id, score_delta)
+            }
+            NetworkMsg::UpdateValidatorPeers(peers) => NetworkActorMsg::UpdateValidatorPeers(peers),
+            NetworkMsg::BanPeer(peer_id, duration) => NetworkActorMsg::BanPeer(peer_id, duration),
+            NetworkMsg::UnbanPeer(peer_id) => NetworkActorMsg::UnbanPeer(peer_id),
+            NetworkMsg::SetLinkConditions(peer_id, conditions) => {
+                NetworkActorMsg::SetLinkConditions(peer_id, conditions)
+            }
+            NetworkMsg::AddListenAddr(addr) => NetworkActorMsg::AddListenAddr(addr),
+            NetworkMsg::RemoveListenAddr(addr) => NetworkActorMsg::RemoveListenAddr(addr),
+            NetworkMsg::PublishMempool(payload) => NetworkActorMsg::PublishMempool(payload),
         }
     }
 }
@@ -0,0 +1,548 @@
+//! Server exposing the status of the node and introspection endpoints as JSON over HTTP,
+//! started by the [`EngineBuilder`] when enabled in the [`RpcConfig`] returned by [`NodeConfig::rpc`].
+//!
+//! - `GET /status` returns the peer ID of the node, whether it is still starting, the height,
+//!   round and phase of consensus, the sync lag and the number of peers
+//! - `GET /net_info` returns the peers known to discovery, with their kind and connections
+//! - `GET /topology` returns the local view of the graph of the network: the gossipsub mesh
+//!   of each topic, the peers by kind, the relay circuits and the Kademlia bucket occupancy
+//! - `GET /consensus_state` returns a summary of the state of consensus at the current height
+//! - `GET /health` returns the outcome of the latest health check, with status 503 if the node is not ready
+//!
+//! The endpoints are served with the same requests as the application can send on [`Channels`],
+//! and fail with status 503 if the engine does not reply in time.
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use derive_where::derive_where;
+use eyre::WrapErr;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tracing::{error, info, Instrument};
+
+use malachitebft_app::types::core::{Context, Height, Round, ValidatorSet, Value};
+use malachitebft_app::types::sync::NodeStatus;
+use malachitebft_engine::network::NetworkTopology;
+
+#[cfg(doc)]
+use crate::{
+    app::config::{NodeConfig, RpcConfig},
+    Channels, EngineBuilder,
+};
+use crate::{ConsensusRequest, ConsensusRequestError, HealthStatus, NetworkRequest, SyncRequest};
+
+/// How long to wait for the engine to reply to the request serving an endpoint
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive_where(Clone)]
+struct RpcState<Ctx: Context> {
+    moniker: String,
+    tx_request: mpsc::Sender<ConsensusRequest<Ctx>>,
+    tx_net_request: mpsc::Sender<NetworkRequest>,
+    tx_sync_request: mpsc::Sender<SyncRequest<Ctx>>,
+    health: watch::Receiver<HealthStatus>,
+}
+
+/// Bind the RPC server to the given address and serve the endpoints in the background.
+///
+/// Fails if the address cannot be bound, so that a misconfigured node does not start
+/// without its RPC endpoints.
+pub(crate) async fn spawn_rpc_server<Ctx: Context>(
+    listen_addr: SocketAddr,
+    moniker: String,
+    tx_request: mpsc::Sender<ConsensusRequest<Ctx>>,
+    tx_net_request: mpsc::Sender<NetworkRequest>,
+    tx_sync_request: mpsc::Sender<SyncRequest<Ctx>>,
+    health: watch::Receiver<HealthStatus>,
+) -> eyre::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(listen_addr)
+        .await
+        .wrap_err_with(|| format!("Failed to bind the RPC server to {listen_addr}"))?;
+
+    let local_addr = listener.local_addr()?;
+
+    let state = RpcState {
+        moniker,
+        tx_request,
+        tx_net_request,
+        tx_sync_request,
+        health,
+    };
+
+    let app = Router::new()
+        .route("/status", get(status::<Ctx>))
+        .route("/net_info", get(net_info::<Ctx>))
+        .route("/topology", get(topology::<Ctx>))
+        .route("/consensus_state", get(consensus_state::<Ctx>))
+        .route("/health", get(health::<Ctx>))
+        .with_state(state);
+
+    let span = tracing::info_span!("rpc");
+
+    let handle = tokio::spawn(
+        async move {
+            info!(address = %local_addr, "Serving RPC");
+
+            if let Err(e) = axum::serve(listener, app).await {
+                error!("RPC server failed: {e}");
+            }
+        }
+        .instrument(span),
+    );
+
+    Ok(handle)
+}
+
+/// Why an endpoint could not be served, reported with status 503
+#[derive(Debug, Error)]
+enum RpcError {
+    #[error("{0} did not reply within {REPLY_TIMEOUT:?}")]
+    Timeout(&'static str),
+
+    #[error("Failed to query {0}: {1}")]
+    Request(&'static str, ConsensusRequestError),
+
+    #[error("{0} is not running")]
+    NotRunning(&'static str),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl IntoResponse for RpcError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.to_string(),
+        };
+
+        (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response()
+    }
+}
+
+/// Wait for the reply of a component of the engine to a request
+async fn query<T>(
+    component: &'static str,
+    request: impl Future<Output = Result<T, ConsensusRequestError>>,
+) -> Result<T, RpcError> {
+    match tokio::time::timeout(REPLY_TIMEOUT, request).await {
+        Ok(Ok(reply)) => Ok(reply),
+        Ok(Err(e)) => Err(RpcError::Request(component, e)),
+        Err(_) => Err(RpcError::Timeout(component)),
+    }
+}
+
+#[derive(Serialize)]
+struct Status {
+    moniker: String,
+    /// Peer ID of the node, `None` if the network is not running
+    peer_id: Option<String>,
+    /// Peer ID the node takes once restarted, if its node key was rotated
+    next_peer_id: Option<String>,
+    /// `starting` until consensus is done recovering and has started its first height,
+    /// during which the node neither advertises its status nor serves values, `running` after
+    node_status: &'static str,
+    /// Height consensus is at, `None` until started
+    height: Option<u64>,
+    /// Round consensus is at, -1 until the first round starts
+    round: i64,
+    /// Phase of consensus, eg. `running` or `paused`
+    phase: &'static str,
+    /// Height of the last decided value, `None` if sync is disabled
+    tip_height: Option<u64>,
+    /// Highest tip height advertised by the peers, if any
+    network_tip_height: Option<u64>,
+    /// Number of heights the node is behind its peers, if known
+    sync_lag: Option<u64>,
+    /// Number of connected peers, `None` if the network is not running
+    peers: Option<usize>,
+}
+
+async fn status<Ctx: Context>(
+    State(state): State<RpcState<Ctx>>,
+) -> Result<Json<Status>, RpcError> {
+    let snapshot = query(
+        "consensus",
+        ConsensusRequest::snapshot_queues(&state.tx_request),
+    )
+    .await?;
+
+    // Sync and the network are optional, their status is only reported when they reply
+    let sync = query("sync", SyncRequest::status(&state.tx_sync_request))
+        .await
+        .ok()
+        .flatten();
+
+    let peers = query("network", NetworkRequest::list_peers(&state.tx_net_request))
+        .await
+        .ok()
+        .flatten();
+
+    let node_key = query(
+        "network",
+        NetworkRequest::node_key_status(&state.tx_net_request),
+    )
+    .await
+    .ok()
+    .flatten();
+
+    let tip_height = sync.as_ref().map(|sync| sync.tip_height.as_u64());
+    let network_tip_height = sync
+        .as_ref()
+        .and_then(|sync| sync.network_tip_height)
+        .map(|height| height.as_u64());
+
+    let sync_lag = tip_height
+        .zip(network_tip_height)
+        .map(|(tip, network_tip)| network_tip.saturating_sub(tip));
+
+    // Without sync, the node is done starting once consensus is running
+    let node_status = match &sync {
+        Some(sync) => sync.node_status,
+        None if matches!(snapshot.phase, "running" | "paused") => NodeStatus::Running,
+        None => NodeStatus::Starting,
+    };
+
+    Ok(Json(Status {
+        moniker: state.moniker,
+        peer_id: node_key.as_ref().map(|key| key.peer_id.to_string()),
+        next_peer_id: node_key
+            .and_then(|key| key.next_peer_id)
+            .map(|peer_id| peer_id.to_string()),
+        node_status: node_status.as_str(),
+        height: snapshot.height.map(|height| height.as_u64()),
+        round: snapshot.round.as_i64(),
+        phase: snapshot.phase,
+        tip_height,
+        network_tip_height,
+        sync_lag,
+        peers: peers.map(|peers| peers.len()),
+    }))
+}
+
+#[derive(Serialize)]
+struct NetInfo {
+    peers: Vec<NetPeer>,
+}
+
+#[derive(Serialize)]
+struct NetPeer {
+    peer_id: String,
+    /// `outbound`, `inbound` or `ephemeral`
+    kind: &'static str,
+    persistent: bool,
+    relayed: bool,
+    /// Agent version reported by the peer, `None` until it identified itself
+    agent_version: Option<String>,
+    listen_addrs: Vec<String>,
+    connections: Vec<NetConnection>,
+}
+
+#[derive(Serialize)]
+struct NetConnection {
+    /// `outbound` or `inbound`
+    direction: &'static str,
+    remote_addr: String,
+    relayed: bool,
+}
+
+async fn net_info<Ctx: Context>(
+    State(state): State<RpcState<Ctx>>,
+) -> Result<Json<NetInfo>, RpcError> {
+    let peers = query(
+        "network",
+        NetworkRequest::discovered_peers(&state.tx_net_request),
+    )
+    .await?
+    .ok_or(RpcError::NotRunning("network"))?;
+
+    let peers = peers
+        .into_iter()
+        .map(|peer| NetPeer {
+            peer_id: peer.peer_id.to_string(),
+            kind: peer.kind.as_str(),
+            persistent: peer.is_persistent,
+            relayed: peer.is_relayed(),
+            agent_version: peer.identity.map(|identity| identity.agent_version),
+            listen_addrs: peer.listen_addrs.iter().map(|a| a.to_string()).collect(),
+            connections: peer
+                .connections
+                .into_iter()
+                .map(|connection| NetConnection {
+                    direction: connection.direction.as_str(),
+                    remote_addr: connection.remote_addr.to_string(),
+                    relayed: connection.is_relayed,
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Json(NetInfo { peers }))
+}
+
+async fn topology<Ctx: Context>(
+    State(state): State<RpcState<Ctx>>,
+) -> Result<Json<NetworkTopology>, RpcError> {
+    let topology = query(
+        "network",
+        NetworkRequest::dump_topology(&state.tx_net_request),
+    )
+    .await?
+    .ok_or(RpcError::NotRunning("network"))?;
+
+    Ok(Json(topology))
+}
+
+#[derive(Serialize)]
+struct ConsensusState {
+    height: u64,
+    round: i64,
+    step: String,
+    address: String,
+    /// Proposer of the current round, `None` for round nil
+    proposer: Option<String>,
+    locked: Option<RoundValueId>,
+    valid: Option<RoundValueId>,
+    decision: Option<RoundValueId>,
+    validators: usize,
+    total_voting_power: u64,
+    /// Rounds of the current height in which votes were received
+    vote_rounds: Vec<i64>,
+    /// Rounds of the current height in which proposals were received
+    proposal_rounds: Vec<i64>,
+}
+
+#[derive(Serialize)]
+struct RoundValueId {
+    round: i64,
+    value_id: String,
+}
+
+impl RoundValueId {
+    fn new<V: Value>(round: Round, value: &V) -> Self {
+        Self {
+            round: round.as_i64(),
+            value_id: value.id().to_string(),
+        }
+    }
+}
+
+async fn consensus_state<Ctx: Context>(
+    State(state): State<RpcState<Ctx>>,
+) -> Result<Json<ConsensusState>, RpcError> {
+    let dump = query("consensus", ConsensusRequest::dump_state(&state.tx_request))
+        .await?
+        .ok_or(RpcError::NotRunning("consensus"))?;
+
+    let round_state = &dump.consensus;
+
+    Ok(Json(ConsensusState {
+        height: round_state.height.as_u64(),
+        round: round_state.round.as_i64(),
+        step: format!("{:?}", round_state.step),
+        address: dump.address.to_string(),
+        proposer: dump.proposer.as_ref().map(|address| address.to_string()),
+        locked: round_state
+            .locked
+            .as_ref()
+            .map(|rv| RoundValueId::new(rv.round, &rv.value)),
+        valid: round_state
+            .valid
+            .as_ref()
+            .map(|rv| RoundValueId::new(rv.round, &rv.value)),
+        decision: round_state
+            .decision
+            .as_ref()
+            .map(|rv| RoundValueId::new(rv.round, &rv.value)),
+        validators: dump.validator_set.count(),
+        total_voting_power: dump.validator_set.total_voting_power(),
+        vote_rounds: dump.vote_keeper.votes.keys().map(Round::as_i64).collect(),
+        proposal_rounds: dump
+            .proposal_keeper
+            .proposals
+            .keys()
+            .map(Round::as_i64)
+            .collect(),
+    }))
+}
+
+#[derive(Serialize)]
+struct Health {
+    live: bool,
+    ready: bool,
+    wal_writable: bool,
+    sufficient_peers: bool,
+    consensus_advancing: bool,
+    sync_lag: Option<u64>,
+    synced: bool,
+    clock_offset_ms: Option<i64>,
+    clock_synced: bool,
+}
+
+async fn health<Ctx: Context>(State(state): State<RpcState<Ctx>>) -> (StatusCode, Json<Health>) {
+    let status = state.health.borrow().clone();
+
+    let code = if status.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let health = Health {
+        live: status.is_live(),
+        ready: status.is_ready(),
+        wal_writable: status.wal_writable,
+        sufficient_peers: status.sufficient_peers,
+        consensus_advancing: status.consensus_advancing,
+        sync_lag: status.sync_lag,
+        synced: status.synced,
+        clock_offset_ms: status.clock_offset_ms,
+        clock_synced: status.clock_synced,
+    };
+
+    (code, Json(health))
+}
+
+#[cfg(test)]
+mod tests {
+    use malachitebft_engine::host::HeightParams;
+    use malachitebft_test::utils::validators::make_validators;
+    use malachitebft_test::{Height as TestHeight, LinearTimeouts, TestContext, ValidatorSet};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    use super::*;
+    use crate::{AppMsg, Channels, MockEngine};
+
+    async fn serve(channels: &Channels<TestContext>) -> (SocketAddr, JoinHandle<()>) {
+        // Bind to an ephemeral port first, to learn an address which is free
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let server = spawn_rpc_server(
+            addr,
+            "node-1".to_string(),
+            channels.requests.clone(),
+            channels.net_requests.clone(),
+            channels.sync_requests.clone(),
+            channels.health.clone(),
+        )
+        .await
+        .unwrap();
+
+        (addr, server)
+    }
+
+    /// Status line and body of the response to a `GET` request
+    async fn get(addr: SocketAddr, path: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status_line = head.lines().next().unwrap().to_string();
+
+        (status_line, body.to_string())
+    }
+
+    #[tokio::test]
+    async fn status_reports_the_height_of_consensus() {
+        let (mut engine, mut channels) = MockEngine::<TestContext>::new();
+        let (addr, server) = serve(&channels).await;
+
+        let (status, body) = get(addr, "/status").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(body.contains(r#""moniker":"node-1""#), "{body}");
+        assert!(body.contains(r#""peer_id":null"#), "{body}");
+        assert!(body.contains(r#""node_status":"starting""#), "{body}");
+        assert!(body.contains(r#""height":null"#), "{body}");
+        assert!(body.contains(r#""phase":"unstarted""#), "{body}");
+
+        // Reply to the consensus ready message of the mock engine
+        tokio::spawn(async move {
+            let [(validator, _)] = make_validators([1]);
+            let validator_set = ValidatorSet::new(vec![validator]);
+
+            while let Some(msg) = channels.consensus.recv().await {
+                if let AppMsg::ConsensusReady { reply } = msg {
+                    let params =
+                        HeightParams::new(validator_set.clone(), LinearTimeouts::default(), None);
+                    let _ = reply.send((TestHeight::new(1), params));
+                }
+            }
+        });
+
+        engine.start().await.unwrap();
+
+        let (status, body) = get(addr, "/status").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(body.contains(r#""node_status":"running""#), "{body}");
+        assert!(body.contains(r#""height":1"#), "{body}");
+        assert!(body.contains(r#""phase":"running""#), "{body}");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn unavailable_components_are_reported() {
+        let (_engine, channels) = MockEngine::<TestContext>::new();
+        let (addr, server) = serve(&channels).await;
+
+        // The mock engine has no network nor consensus state to dump
+        let (status, body) = get(addr, "/net_info").await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+        assert!(body.contains("network is not running"), "{body}");
+
+        let (status, _) = get(addr, "/topology").await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+
+        let (status, body) = get(addr, "/consensus_state").await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+        assert!(body.contains("consensus is not running"), "{body}");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn health_reflects_the_latest_check() {
+        let (engine, channels) = MockEngine::<TestContext>::new();
+        let (addr, server) = serve(&channels).await;
+
+        let (status, body) = get(addr, "/health").await;
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(body.contains(r#""ready":true"#), "{body}");
+
+        engine.set_health(HealthStatus {
+            synced: false,
+            sync_lag: Some(100),
+            ..HealthStatus::healthy()
+        });
+
+        let (status, body) = get(addr, "/health").await;
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+        assert!(body.contains(r#""live":true"#), "{body}");
+        assert!(body.contains(r#""ready":false"#), "{body}");
+        assert!(body.contains(r#""sync_lag":100"#), "{body}");
+
+        server.abort();
+    }
+}
@@ -1,6 +1,8 @@
 //! Run Malachite consensus with the given configuration and context.
 //! Provides the application with a channel for receiving messages from consensus.
 
+use std::sync::Arc;
+
 use tokio::sync::mpsc::Receiver;
 use tokio::task::JoinHandle;
 
@@ -8,11 +10,11 @@ use eyre::Result;
 
 use malachitebft_engine::consensus::{ConsensusMsg, ConsensusRef};
 use malachitebft_engine::network::{NetworkMsg, NetworkRef};
-use malachitebft_engine::node::NodeRef;
-use malachitebft_signing::SigningProvider;
+use malachitebft_engine::node::{Msg as NodeMsg, NodeRef};
+use malachitebft_engine::sync::{SyncMsg, SyncRef};
 
 pub use malachitebft_engine::network::NetworkIdentity;
-pub use malachitebft_signing::SigningProviderExt;
+pub use malachitebft_signing::{SigningProvider, SigningProviderExt};
 
 // Re-export context structs from builder module
 pub use crate::builder::{
@@ -22,18 +24,56 @@ pub use crate::builder::{
 use crate::app::config::NodeConfig;
 use crate::app::types::codec;
 use crate::app::types::core::Context;
-use crate::msgs::{ConsensusRequest, NetworkRequest};
+use crate::log_filter::{LogFilter, LogFilterError};
+use crate::msgs::{ConsensusRequest, NetworkRequest, SyncRequest};
 use crate::{Channels, EngineBuilder};
 
 pub struct EngineHandle {
     pub actor: NodeRef,
     pub handle: JoinHandle<()>,
+    servers: Vec<JoinHandle<()>>,
 }
 
 impl EngineHandle {
     pub fn new(actor: NodeRef, handle: JoinHandle<()>) -> Self {
-        Self { actor, handle }
+        Self {
+            actor,
+            handle,
+            servers: Vec::new(),
+        }
+    }
+
+    /// Stop the given servers, eg. the metrics server, along with the engine
+    pub(crate) fn with_servers(
+        mut self,
+        servers: impl IntoIterator<Item = JoinHandle<()>>,
+    ) -> Self {
+        self.servers.extend(servers);
+        self
     }
+
+    /// Gracefully shut down the engine, instead of aborting its task.
+    ///
+    /// Consensus stops first so that no new height is started, then the WAL is flushed
+    /// and synced to disk, and the connections to the peers are closed. Every actor gets
+    /// to process the messages already sent to it before it stops.
+    /// Returns once all the actors have stopped.
+    pub async fn shutdown(&self) -> Result<()> {
+        shutdown_node(&self.actor).await?;
+
+        for server in &self.servers {
+            server.abort();
+        }
+
+        Ok(())
+    }
+}
+
+/// Shut down the node actor, returning once it has stopped
+async fn shutdown_node(node: &NodeRef) -> Result<()> {
+    ractor::call!(node, NodeMsg::Shutdown)?;
+    node.stop_and_wait(None, None).await?;
+    Ok(())
 }
 
 /// Start the consensus engine with default actors.
@@ -89,6 +129,7 @@ where
 pub(crate) fn spawn_consensus_request_task<Ctx>(
     mut rx_request: Receiver<ConsensusRequest<Ctx>>,
     consensus: ConsensusRef<Ctx>,
+    node: NodeRef,
 ) where
     Ctx: Context,
 {
@@ -100,6 +141,56 @@ pub(crate) fn spawn_consensus_request_task<Ctx>(
                         tracing::error!("Failed to send state dump request: {e}");
                     }
                 }
+                ConsensusRequest::SnapshotQueues(reply) => {
+                    if let Err(e) = consensus.cast(ConsensusMsg::SnapshotQueues(reply.into())) {
+                        tracing::error!("Failed to send queue snapshot request: {e}");
+                    }
+                }
+                ConsensusRequest::UncommittedEvidence(reply) => {
+                    if let Err(e) =
+                        consensus.cast(ConsensusMsg::GetUncommittedEvidence(reply.into()))
+                    {
+                        tracing::error!("Failed to send uncommitted evidence request: {e}");
+                    }
+                }
+                ConsensusRequest::SetSigningEnabled(enabled, reply) => {
+                    if let Err(e) =
+                        consensus.cast(ConsensusMsg::SetSigningEnabled(enabled, reply.into()))
+                    {
+                        tracing::error!("Failed to send signing request: {e}");
+                    }
+                }
+                ConsensusRequest::RegisterSigningKey(height, signing_provider, reply) => {
+                    if let Err(e) = consensus.cast(ConsensusMsg::RegisterSigningKey(
+                        height,
+                        signing_provider,
+                        reply.into(),
+                    )) {
+                        tracing::error!("Failed to send signing key registration: {e}");
+                    }
+                }
+                ConsensusRequest::Pause(reply) => {
+                    if let Err(e) = consensus.cast(ConsensusMsg::Pause(reply.into())) {
+                        tracing::error!("Failed to send pause request: {e}");
+                    }
+                }
+                ConsensusRequest::Resume(reply) => {
+                    if let Err(e) = consensus.cast(ConsensusMsg::Resume(reply.into())) {
+                        tracing::error!("Failed to send resume request: {e}");
+                    }
+                }
+                ConsensusRequest::UpdateConfig(update, reply) => {
+                    if let Err(e) = consensus.cast(ConsensusMsg::UpdateConfig(update, reply.into()))
+                    {
+                        tracing::error!("Failed to send configuration update: {e}");
+                    }
+                }
+                ConsensusRequest::Shutdown(reply) => match shutdown_node(&node).await {
+                    Ok(()) => {
+                        let _ = reply.send(());
+                    }
+                    Err(e) => tracing::error!("Failed to shut down the engine: {e}"),
+                },
             }
         }
     });
@@ -108,6 +199,7 @@ pub(crate) fn spawn_consensus_request_task<Ctx>(
 pub(crate) fn spawn_network_request_task<Ctx>(
     mut rx_request: Receiver<NetworkRequest>,
     network: NetworkRef<Ctx>,
+    log_filter: Option<LogFilter>,
 ) where
     Ctx: Context,
 {
@@ -119,6 +211,44 @@ pub(crate) fn spawn_network_request_task<Ctx>(
                         tracing::error!(%error, "Failed to send network state dump request");
                     }
                 }
+                NetworkRequest::DumpTopology(reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::DumpTopology(reply.into())) {
+                        tracing::error!(%error, "Failed to send network topology request");
+                    }
+                }
+                NetworkRequest::DiscoveredPeers(reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::DiscoveredPeers(reply.into())) {
+                        tracing::error!(%error, "Failed to send discovered peers request");
+                    }
+                }
+                NetworkRequest::ListPeers(reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::ListPeers(reply.into())) {
+                        tracing::error!(%error, "Failed to send list peers request");
+                    }
+                }
+                NetworkRequest::ExportAddressBook(reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::ExportAddressBook(reply.into())) {
+                        tracing::error!(%error, "Failed to send export address book request");
+                    }
+                }
+                NetworkRequest::ImportAddressBook(book, reply) => {
+                    if let Err(error) =
+                        network.cast(NetworkMsg::ImportAddressBook(book, reply.into()))
+                    {
+                        tracing::error!(%error, "Failed to send import address book request");
+                    }
+                }
+                NetworkRequest::DisconnectPeer(peer, reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::DisconnectPeer(peer, reply.into()))
+                    {
+                        tracing::error!(%error, "Failed to send disconnect peer request");
+                    }
+                }
+                NetworkRequest::DialPeer(addr, reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::DialPeer(addr, reply.into())) {
+                        tracing::error!(%error, "Failed to send dial peer request");
+                    }
+                }
                 NetworkRequest::UpdatePersistentPeers(op, reply) => {
                     if let Err(error) =
                         network.cast(NetworkMsg::UpdatePersistentPeers(op, reply.into()))
@@ -126,6 +256,201 @@ pub(crate) fn spawn_network_request_task<Ctx>(
                         tracing::error!(%error, "Failed to send update persistent peers request");
                     }
                 }
+                NetworkRequest::UpdateConfig(update, reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::UpdateConfig(update, reply.into()))
+                    {
+                        tracing::error!(%error, "Failed to send network configuration update");
+                    }
+                }
+                NetworkRequest::NodeKeyStatus(reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::NodeKeyStatus(reply.into())) {
+                        tracing::error!(%error, "Failed to send node key status request");
+                    }
+                }
+                NetworkRequest::RotateNodeKey(reply) => {
+                    if let Err(error) = network.cast(NetworkMsg::RotateNodeKey(reply.into())) {
+                        tracing::error!(%error, "Failed to send node key rotation request");
+                    }
+                }
+                NetworkRequest::SendToPeer {
+                    peer,
+                    payload,
+                    reply,
+                } => {
+                    if let Err(error) =
+                        network.cast(NetworkMsg::SendToPeer(peer, payload, reply.into()))
+                    {
+                        tracing::error!(%error, "Failed to send message to peer");
+                    }
+                }
+                NetworkRequest::SetLogFilter(directives, reply) => {
+                    let result = match &log_filter {
+                        Some(log_filter) => log_filter.set(&directives),
+                        None => Err(LogFilterError::NotReloadable),
+                    };
+
+                    match &result {
+                        Ok(previous) => {
+                            tracing::info!(%directives, %previous, "Changed the log filter")
+                        }
+                        Err(error) => tracing::warn!(%error, "Failed to change the log filter"),
+                    }
+
+                    if reply.send(result).is_err() {
+                        tracing::error!("Failed to reply to log filter request");
+                    }
+                }
+            }
+        }
+    });
+}
+
+pub(crate) fn spawn_sync_request_task<Ctx>(
+    mut rx_request: Receiver<SyncRequest<Ctx>>,
+    sync: Option<SyncRef<Ctx>>,
+) where
+    Ctx: Context,
+{
+    tokio::spawn(async move {
+        while let Some(msg) = rx_request.recv().await {
+            match msg {
+                SyncRequest::DumpState(reply) => {
+                    let dump = match &sync {
+                        Some(sync) => {
+                            match ractor::call!(sync, |reply_to| SyncMsg::DumpState(Arc::new(
+                                reply_to
+                            ))) {
+                                Ok(dump) => Some(dump),
+                                Err(error) => {
+                                    tracing::error!(%error, "Failed to obtain sync state dump");
+                                    None
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+
+                    if reply.send(dump).is_err() {
+                        tracing::error!("Failed to reply with sync state dump");
+                    }
+                }
+                SyncRequest::LowWatermark(reply) => {
+                    let low_watermark = match &sync {
+                        Some(sync) => {
+                            match ractor::call!(sync, |reply_to| SyncMsg::GetLowWatermark(
+                                Arc::new(reply_to)
+                            )) {
+                                Ok(low_watermark) => low_watermark,
+                                Err(error) => {
+                                    tracing::error!(%error, "Failed to obtain sync low watermark");
+                                    None
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+
+                    if reply.send(low_watermark).is_err() {
+                        tracing::error!("Failed to reply with sync low watermark");
+                    }
+                }
+                SyncRequest::Pause(reply) => {
+                    let paused = match &sync {
+                        Some(sync) => sync
+                            .cast(SyncMsg::Pause)
+                            .inspect_err(|error| tracing::error!(%error, "Failed to pause sync"))
+                            .is_ok(),
+                        None => false,
+                    };
+
+                    if reply.send(paused).is_err() {
+                        tracing::error!("Failed to reply to sync pause request");
+                    }
+                }
+                SyncRequest::Resume(reply) => {
+                    let resumed = match &sync {
+                        Some(sync) => sync
+                            .cast(SyncMsg::Resume)
+                            .inspect_err(|error| tracing::error!(%error, "Failed to resume sync"))
+                            .is_ok(),
+                        None => false,
+                    };
+
+                    if reply.send(resumed).is_err() {
+                        tracing::error!("Failed to reply to sync resume request");
+                    }
+                }
+                SyncRequest::Backfill(range, reply) => {
+                    let backfilling = match &sync {
+                        Some(sync) => sync
+                            .cast(SyncMsg::Backfill(range))
+                            .inspect_err(
+                                |error| tracing::error!(%error, "Failed to start backfill"),
+                            )
+                            .is_ok(),
+                        None => false,
+                    };
+
+                    if reply.send(backfilling).is_err() {
+                        tracing::error!("Failed to reply to sync backfill request");
+                    }
+                }
+                SyncRequest::UpdateConfig(update, reply) => {
+                    let updated = match &sync {
+                        Some(sync) => sync
+                            .cast(SyncMsg::UpdateConfig(update))
+                            .inspect_err(
+                                |error| tracing::error!(%error, "Failed to update sync config"),
+                            )
+                            .is_ok(),
+                        None => false,
+                    };
+
+                    if reply.send(updated).is_err() {
+                        tracing::error!("Failed to reply to sync config update request");
+                    }
+                }
+                SyncRequest::SetRetainHeight(height, reply) => {
+                    let set = match &sync {
+                        Some(sync) => {
+                            match ractor::call!(sync, |reply_to| SyncMsg::SetRetainHeight(
+                                height,
+                                Arc::new(reply_to)
+                            )) {
+                                Ok(set) => set,
+                                Err(error) => {
+                                    tracing::error!(%error, "Failed to set retain height");
+                                    false
+                                }
+                            }
+                        }
+                        None => false,
+                    };
+
+                    if reply.send(set).is_err() {
+                        tracing::error!("Failed to reply to sync retain height request");
+                    }
+                }
+                SyncRequest::Status(reply) => {
+                    let status = match &sync {
+                        Some(sync) => {
+                            match ractor::call!(sync, |reply_to| SyncMsg::GetStatus(Arc::new(
+                                reply_to
+                            ))) {
+                                Ok(status) => Some(status),
+                                Err(error) => {
+                                    tracing::error!(%error, "Failed to obtain sync status");
+                                    None
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+
+                    if reply.send(status).is_err() {
+                        tracing::error!("Failed to reply with sync status");
+                    }
+                }
             }
         }
     });
@@ -13,18 +13,22 @@ use crate::app;
 use crate::app::config::ConsensusConfig;
 use crate::app::metrics::Metrics;
 use crate::app::metrics::SharedRegistry;
+use crate::app::spawn::NetworkPolicies;
 use crate::app::types::core::Context;
+use crate::backpressure::{self, ChannelConfig, ChannelMetrics};
 use crate::connector::Connector;
 use crate::{AppMsg, NetworkMsg};
 
 pub async fn spawn_host_actor<Ctx>(
+    channel: ChannelConfig,
     metrics: Metrics,
+    channel_metrics: &ChannelMetrics,
 ) -> Result<(HostRef<Ctx>, mpsc::Receiver<AppMsg<Ctx>>)>
 where
     Ctx: Context,
 {
-    let (tx, rx) = mpsc::channel(128);
-    let actor_ref = Connector::spawn(tx, metrics).await?;
+    let (tx, rx) = backpressure::channel("consensus", channel, channel_metrics);
+    let actor_ref = Connector::spawn(tx, channel.overflow, metrics).await?;
     Ok((actor_ref, rx))
 }
 
@@ -34,27 +38,43 @@ pub async fn spawn_network_actor<Ctx, Codec>(
     value_sync_cfg: &ValueSyncConfig,
     registry: &SharedRegistry,
     codec: Codec,
+    policies: NetworkPolicies,
+    channel: ChannelConfig,
+    channel_metrics: &ChannelMetrics,
 ) -> Result<(NetworkRef<Ctx>, mpsc::Sender<NetworkMsg<Ctx>>)>
 where
     Ctx: Context,
     Codec: ConsensusCodec<Ctx>,
     Codec: SyncCodec<Ctx>,
 {
-    let (tx, mut rx) = mpsc::channel::<NetworkMsg<Ctx>>(1);
-
     let actor_ref =
-        app::spawn::spawn_network_actor(cfg, value_sync_cfg, identity, registry, codec).await?;
-
-    tokio::spawn({
-        let actor_ref = actor_ref.clone();
-        async move {
-            while let Some(msg) = rx.recv().await {
-                if let Err(e) = actor_ref.cast(msg.into()) {
-                    tracing::error!("Failed to send message to network actor: {e}");
-                }
+        app::spawn::spawn_network_actor(cfg, value_sync_cfg, identity, registry, codec, policies)
+            .await?;
+
+    let tx = forward_to_network(actor_ref.clone(), channel, channel_metrics);
+
+    Ok((actor_ref, tx))
+}
+
+/// Create the channel through which the application sends messages to the network actor
+pub(crate) fn forward_to_network<Ctx>(
+    actor_ref: NetworkRef<Ctx>,
+    channel: ChannelConfig,
+    channel_metrics: &ChannelMetrics,
+) -> mpsc::Sender<NetworkMsg<Ctx>>
+where
+    Ctx: Context,
+{
+    let (tx, mut rx) =
+        backpressure::channel::<NetworkMsg<Ctx>>("network", channel, channel_metrics);
+
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let Err(e) = actor_ref.cast(msg.into()) {
+                tracing::error!("Failed to send message to network actor: {e}");
             }
         }
     });
 
-    Ok((actor_ref, tx))
+    tx
 }
@@ -0,0 +1,19 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let protos = &["proto/application.proto"];
+
+    for proto in protos {
+        println!("cargo:rerun-if-changed={proto}");
+    }
+
+    let fds = protox::compile(protos, ["proto"])?;
+
+    let mut config = prost_build::Config::new();
+    config.bytes(["."]);
+
+    tonic_build::configure()
+        .build_client(true)
+        .build_server(true)
+        .compile_fds_with_config(config, fds)?;
+
+    Ok(())
+}
@@ -0,0 +1,615 @@
+use std::future::Future;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::sync::mpsc;
+use tonic::transport::{Channel, Endpoint};
+use tracing::{debug, error, warn};
+
+use malachitebft_app_channel::app::consensus::Role;
+use malachitebft_app_channel::app::engine::host::{HeightParams, Next};
+use malachitebft_app_channel::app::streaming::{StreamContent, StreamId, StreamMessage};
+use malachitebft_app_channel::app::types::core::{Context, Height, Round, Validity};
+use malachitebft_app_channel::app::types::sync::RawDecidedValue;
+use malachitebft_app_channel::app::types::{LocallyProposedValue, ProposedValue};
+use malachitebft_app_channel::{AppMsg, Channels, NetworkMsg, Reply};
+use malachitebft_codec::Codec;
+
+use crate::proto;
+use crate::proto::application_client::ApplicationClient;
+use crate::{Error, GrpcCodec};
+
+/// Forwards the messages of the engine to an application serving the `Application`
+/// gRPC service, and its replies back to the engine, see the [crate documentation](crate).
+pub struct GrpcApp<Ctx: Context, C> {
+    client: ApplicationClient<Channel>,
+    codec: C,
+    timeouts: Ctx::Timeouts,
+}
+
+impl<Ctx, C> GrpcApp<Ctx, C>
+where
+    Ctx: Context,
+    C: GrpcCodec<Ctx>,
+{
+    /// Connect to the application at the given endpoint, eg. `http://127.0.0.1:26658`
+    pub async fn connect(endpoint: impl Into<String>, codec: C) -> Result<Self, Error> {
+        let channel = Endpoint::from_shared(endpoint.into())?.connect().await?;
+        Ok(Self::new(channel, codec))
+    }
+
+    /// Talk to the application over an existing gRPC channel
+    pub fn new(channel: Channel, codec: C) -> Self {
+        Self {
+            client: ApplicationClient::new(channel),
+            codec,
+            timeouts: Default::default(),
+        }
+    }
+
+    /// Timeouts of consensus at every height, the defaults of the context unless set
+    pub fn with_timeouts(mut self, timeouts: Ctx::Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Forward the messages of the engine to the application until the engine stops.
+    ///
+    /// A message the application fails to handle is logged and left unanswered,
+    /// as it would be by an in-process application failing to reply.
+    pub async fn run(mut self, channels: &mut Channels<Ctx>) -> Result<(), Error> {
+        while let Some(msg) = channels.consensus.recv().await {
+            match self.handle(msg, &channels.network).await {
+                Ok(()) => (),
+                Err(Error::EngineStopped) => return Err(Error::EngineStopped),
+                Err(e) => error!("{e}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle(
+        &mut self,
+        msg: AppMsg<Ctx>,
+        network: &mpsc::Sender<NetworkMsg<Ctx>>,
+    ) -> Result<(), Error> {
+        match msg {
+            AppMsg::ConsensusReady { reply } => {
+                let start = call(
+                    "ConsensusReady",
+                    self.client
+                        .consensus_ready(proto::ConsensusReadyRequest::default()),
+                )
+                .await?;
+
+                let params = self.decode_params("ConsensusReady", start.params)?;
+                send_reply("ConsensusReady", reply, (to_height(start.height), params));
+            }
+
+            AppMsg::StartedRound {
+                height,
+                round,
+                proposer,
+                role,
+                reply_value,
+            } => {
+                let request = proto::StartedRoundRequest {
+                    height: height.as_u64(),
+                    round: round.as_i64(),
+                    proposer: self.encode("proposer", &proposer)?,
+                    role: encode_role(role) as i32,
+                };
+
+                let response = call("StartedRound", self.client.started_round(request)).await?;
+
+                let values = response
+                    .values
+                    .into_iter()
+                    .map(|value| self.decode_proposed_value(value))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                send_reply("StartedRound", reply_value, values);
+            }
+
+            AppMsg::GetValue {
+                height,
+                round,
+                timeout,
+                reply,
+            } => {
+                let mut request = tonic::Request::new(proto::GetValueRequest {
+                    height: height.as_u64(),
+                    round: round.as_i64(),
+                    timeout_ms: timeout.as_millis() as u64,
+                });
+                request.set_timeout(timeout);
+
+                let response = call("GetValue", self.client.get_value(request)).await?;
+
+                let value = self.decode("value", response.value)?;
+                send_reply(
+                    "GetValue",
+                    reply,
+                    LocallyProposedValue::new(height, round, value),
+                );
+
+                self.stream_parts(network, height, round, response.parts)
+                    .await?;
+            }
+
+            AppMsg::RestreamProposal {
+                height,
+                round,
+                valid_round,
+                address,
+                value_id,
+            } => {
+                let request = proto::RestreamProposalRequest {
+                    height: height.as_u64(),
+                    round: round.as_i64(),
+                    valid_round: valid_round.as_i64(),
+                    proposer: self.encode("proposer", &address)?,
+                    value_id: value_id.to_string(),
+                };
+
+                let response =
+                    call("RestreamProposal", self.client.restream_proposal(request)).await?;
+
+                self.stream_parts(network, height, round, response.parts)
+                    .await?;
+            }
+
+            AppMsg::GetHistoryMinHeight { reply } => {
+                let response = call(
+                    "GetHistoryMinHeight",
+                    self.client
+                        .get_history_min_height(proto::GetHistoryMinHeightRequest::default()),
+                )
+                .await?;
+
+                send_reply("GetHistoryMinHeight", reply, to_height(response.height));
+            }
+
+            AppMsg::ReceivedProposalPart { from, part, reply } => {
+                let data = match &part.content {
+                    StreamContent::Data(data) => Some(self.encode("proposal part", data)?),
+                    StreamContent::Fin => None,
+                };
+
+                let request = proto::ReceivedProposalPartRequest {
+                    from: from.to_string(),
+                    stream_id: part.stream_id.to_bytes(),
+                    sequence: part.sequence,
+                    part: data,
+                };
+
+                let response = call(
+                    "ReceivedProposalPart",
+                    self.client.received_proposal_part(request),
+                )
+                .await?;
+
+                let value = response
+                    .value
+                    .map(|value| self.decode_proposed_value(value))
+                    .transpose()?;
+
+                send_reply("ReceivedProposalPart", reply, value);
+            }
+
+            AppMsg::Decided { certificate, .. } => {
+                let request = proto::DecidedRequest {
+                    certificate: self.encode("commit certificate", &certificate)?,
+                };
+
+                call("Decided", self.client.decided(request)).await?;
+            }
+
+            AppMsg::Finalized {
+                certificate,
+                evidence,
+                reply,
+                ..
+            } => {
+                if !evidence.is_empty() {
+                    warn!(
+                        ?evidence,
+                        "Misbehavior evidence is not forwarded to the application"
+                    );
+                }
+
+                let request = proto::FinalizedRequest {
+                    certificate: self.encode("commit certificate", &certificate)?,
+                };
+
+                let next = call("Finalized", self.client.finalized(request)).await?;
+
+                let params = self.decode_params("Finalized", next.params)?;
+                let next = if next.restart {
+                    Next::Restart(to_height(next.height), params)
+                } else {
+                    Next::Start(to_height(next.height), params)
+                };
+
+                send_reply("Finalized", reply, next);
+            }
+
+            AppMsg::GetDecidedValues { range, reply } => {
+                let request = proto::GetDecidedValuesRequest {
+                    from_height: range.start().as_u64(),
+                    to_height: range.end().as_u64(),
+                };
+
+                let response =
+                    call("GetDecidedValues", self.client.get_decided_values(request)).await?;
+
+                let values = response
+                    .values
+                    .into_iter()
+                    .map(|value| {
+                        let certificate = self.decode("commit certificate", value.certificate)?;
+                        Ok(RawDecidedValue::new(value.value, certificate))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                send_reply("GetDecidedValues", reply, values);
+            }
+
+            AppMsg::ProcessSyncedValue {
+                height,
+                round,
+                proposer,
+                value_bytes,
+                reply,
+            } => {
+                let request = proto::ProcessSyncedValueRequest {
+                    height: height.as_u64(),
+                    round: round.as_i64(),
+                    proposer: self.encode("proposer", &proposer)?,
+                    value: value_bytes,
+                };
+
+                let response = call(
+                    "ProcessSyncedValue",
+                    self.client.process_synced_value(request),
+                )
+                .await?;
+
+                let value = response
+                    .value
+                    .map(|value| self.decode_proposed_value(value))
+                    .transpose()?;
+
+                send_reply("ProcessSyncedValue", reply, value);
+            }
+
+            // Not exposed over gRPC yet, see the crate documentation
+            AppMsg::ExtendVote { reply, .. } => send_reply("ExtendVote", reply, None),
+            AppMsg::VerifyVoteExtension { reply, .. } => {
+                send_reply("VerifyVoteExtension", reply, Ok(()))
+            }
+            AppMsg::GetSnapshots { reply } => send_reply("GetSnapshots", reply, Vec::new()),
+            AppMsg::GetSnapshotChunk { reply, .. } => send_reply("GetSnapshotChunk", reply, None),
+            AppMsg::RestoreSnapshot { reply, .. } => send_reply("RestoreSnapshot", reply, None),
+            AppMsg::StoreBackfilledValues { reply, .. } => {
+                send_reply("StoreBackfilledValues", reply, false)
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publish the parts of a value, followed by the end of their stream
+    async fn stream_parts(
+        &self,
+        network: &mpsc::Sender<NetworkMsg<Ctx>>,
+        height: Ctx::Height,
+        round: Round,
+        parts: Vec<Bytes>,
+    ) -> Result<(), Error> {
+        let parts = parts
+            .into_iter()
+            .map(|part| self.decode::<Ctx::ProposalPart>("proposal part", part))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let stream_id = stream_id(height, round);
+        let fin_sequence = parts.len() as u64;
+
+        let messages = parts
+            .into_iter()
+            .map(StreamContent::Data)
+            .chain([StreamContent::Fin])
+            .zip(0..=fin_sequence)
+            .map(|(content, sequence)| StreamMessage::new(stream_id.clone(), sequence, content));
+
+        for message in messages {
+            debug!(%height, %round, sequence = message.sequence, "Streaming proposal part");
+
+            network
+                .send(NetworkMsg::PublishProposalPart(message))
+                .await
+                .map_err(|_| Error::EngineStopped)?;
+        }
+
+        Ok(())
+    }
+
+    fn decode_params(
+        &self,
+        call: &'static str,
+        params: Option<proto::HeightParams>,
+    ) -> Result<HeightParams<Ctx>, Error> {
+        let params = params.ok_or(Error::MissingField {
+            call,
+            field: "params",
+        })?;
+
+        Ok(HeightParams::new(
+            self.decode("validator set", params.validator_set)?,
+            self.timeouts,
+            params.target_time_ms.map(Duration::from_millis),
+        ))
+    }
+
+    fn decode_proposed_value(
+        &self,
+        value: proto::ProposedValue,
+    ) -> Result<ProposedValue<Ctx>, Error> {
+        Ok(ProposedValue {
+            height: to_height(value.height),
+            round: Round::from(value.round),
+            valid_round: Round::from(value.valid_round),
+            proposer: self.decode("proposer", value.proposer)?,
+            value: self.decode("value", value.value)?,
+            validity: if value.valid {
+                Validity::Valid
+            } else {
+                Validity::Invalid
+            },
+        })
+    }
+
+    fn encode<T>(&self, what: &'static str, value: &T) -> Result<Bytes, Error>
+    where
+        C: Codec<T>,
+    {
+        <C as Codec<T>>::encode(&self.codec, value).map_err(|e| Error::Encode {
+            what,
+            reason: e.to_string(),
+        })
+    }
+
+    fn decode<T>(&self, what: &'static str, bytes: Bytes) -> Result<T, Error>
+    where
+        C: Codec<T>,
+    {
+        <C as Codec<T>>::decode(&self.codec, bytes).map_err(|e| Error::Decode {
+            what,
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Wait for the reply of the application to a call
+async fn call<T>(
+    call: &'static str,
+    response: impl Future<Output = Result<tonic::Response<T>, tonic::Status>>,
+) -> Result<T, Error> {
+    response
+        .await
+        .map(tonic::Response::into_inner)
+        .map_err(|status| Error::Call { call, status })
+}
+
+fn send_reply<T>(call: &'static str, reply: Reply<T>, value: T) {
+    if reply.send(value).is_err() {
+        error!("Failed to send the reply to {call} to consensus");
+    }
+}
+
+fn to_height<H: Height>(height: u64) -> H {
+    H::ZERO.increment_by(height)
+}
+
+fn encode_role(role: Role) -> proto::Role {
+    match role {
+        Role::Proposer => proto::Role::Proposer,
+        Role::Validator => proto::Role::Validator,
+        Role::None => proto::Role::None,
+    }
+}
+
+/// Stream of the parts of the value proposed at a height and round
+fn stream_id<H: Height>(height: H, round: Round) -> StreamId {
+    let mut bytes = Vec::with_capacity(size_of::<u64>() + size_of::<i64>());
+    bytes.extend_from_slice(&height.as_u64().to_be_bytes());
+    bytes.extend_from_slice(&round.as_i64().to_be_bytes());
+    StreamId::new(bytes.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use malachitebft_app_channel::MockEngine;
+    use malachitebft_test::codec::json::JsonCodec;
+    use malachitebft_test::utils::validators::make_validators;
+    use malachitebft_test::{
+        Height as TestHeight, ProposalData, ProposalPart, TestContext, ValidatorSet, Value,
+    };
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{Request, Response, Status};
+
+    use super::*;
+    use crate::proto::application_server::{Application, ApplicationServer};
+
+    /// Application starting at height 1 and proposing the value 42 in two parts
+    struct MockApplication {
+        validator_set: ValidatorSet,
+    }
+
+    impl MockApplication {
+        fn params(&self) -> proto::HeightParams {
+            proto::HeightParams {
+                validator_set: JsonCodec.encode(&self.validator_set).unwrap(),
+                target_time_ms: None,
+            }
+        }
+    }
+
+    #[tonic::async_trait]
+    impl Application for MockApplication {
+        async fn consensus_ready(
+            &self,
+            _: Request<proto::ConsensusReadyRequest>,
+        ) -> Result<Response<proto::StartHeight>, Status> {
+            Ok(Response::new(proto::StartHeight {
+                height: 1,
+                params: Some(self.params()),
+            }))
+        }
+
+        async fn started_round(
+            &self,
+            _: Request<proto::StartedRoundRequest>,
+        ) -> Result<Response<proto::StartedRoundResponse>, Status> {
+            Ok(Response::new(proto::StartedRoundResponse::default()))
+        }
+
+        async fn get_value(
+            &self,
+            _: Request<proto::GetValueRequest>,
+        ) -> Result<Response<proto::GetValueResponse>, Status> {
+            let parts = [1, 2]
+                .into_iter()
+                .map(|factor| ProposalPart::Data(ProposalData::new(factor)))
+                .map(|part| JsonCodec.encode(&part).unwrap())
+                .collect();
+
+            Ok(Response::new(proto::GetValueResponse {
+                value: JsonCodec.encode(&Value::new(42)).unwrap(),
+                parts,
+            }))
+        }
+
+        async fn restream_proposal(
+            &self,
+            _: Request<proto::RestreamProposalRequest>,
+        ) -> Result<Response<proto::RestreamProposalResponse>, Status> {
+            Ok(Response::new(proto::RestreamProposalResponse::default()))
+        }
+
+        async fn received_proposal_part(
+            &self,
+            _: Request<proto::ReceivedProposalPartRequest>,
+        ) -> Result<Response<proto::ReceivedProposalPartResponse>, Status> {
+            Ok(Response::new(proto::ReceivedProposalPartResponse::default()))
+        }
+
+        async fn decided(
+            &self,
+            _: Request<proto::DecidedRequest>,
+        ) -> Result<Response<proto::DecidedResponse>, Status> {
+            Ok(Response::new(proto::DecidedResponse::default()))
+        }
+
+        async fn finalized(
+            &self,
+            _: Request<proto::FinalizedRequest>,
+        ) -> Result<Response<proto::Next>, Status> {
+            Err(Status::unimplemented("finalized"))
+        }
+
+        async fn get_history_min_height(
+            &self,
+            _: Request<proto::GetHistoryMinHeightRequest>,
+        ) -> Result<Response<proto::GetHistoryMinHeightResponse>, Status> {
+            Ok(Response::new(proto::GetHistoryMinHeightResponse {
+                height: 1,
+            }))
+        }
+
+        async fn get_decided_values(
+            &self,
+            _: Request<proto::GetDecidedValuesRequest>,
+        ) -> Result<Response<proto::GetDecidedValuesResponse>, Status> {
+            Ok(Response::new(proto::GetDecidedValuesResponse::default()))
+        }
+
+        async fn process_synced_value(
+            &self,
+            _: Request<proto::ProcessSyncedValueRequest>,
+        ) -> Result<Response<proto::ProcessSyncedValueResponse>, Status> {
+            Ok(Response::new(proto::ProcessSyncedValueResponse::default()))
+        }
+    }
+
+    /// Serve the mock application and bridge a mock engine to it
+    async fn setup(validator_set: ValidatorSet) -> MockEngine<TestContext> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let service = ApplicationServer::new(MockApplication { validator_set });
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let app = GrpcApp::<TestContext, _>::connect(format!("http://{addr}"), JsonCodec)
+            .await
+            .unwrap();
+
+        let (engine, mut channels) = MockEngine::new();
+        tokio::spawn(async move { app.run(&mut channels).await });
+
+        engine
+    }
+
+    fn validator_set() -> ValidatorSet {
+        let [(validator, _)] = make_validators([1]);
+        ValidatorSet::new([validator])
+    }
+
+    #[tokio::test]
+    async fn consensus_ready_starts_the_height_of_the_application() {
+        let mut engine = setup(validator_set()).await;
+
+        let (height, params) = engine.start().await.unwrap();
+
+        assert_eq!(height, TestHeight::new(1));
+        assert_eq!(params.validator_set, validator_set());
+        assert_eq!(params.target_time, None);
+    }
+
+    #[tokio::test]
+    async fn get_value_streams_the_parts_of_the_value() {
+        let mut engine = setup(validator_set()).await;
+        engine.start().await.unwrap();
+
+        let proposed = engine.get_value(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(proposed.height, TestHeight::new(1));
+        assert_eq!(proposed.value, Value::new(42));
+
+        let mut contents = Vec::new();
+        for sequence in 0..3 {
+            let NetworkMsg::PublishProposalPart(msg) = engine.next_published().await.unwrap()
+            else {
+                panic!("expected a proposal part");
+            };
+
+            assert_eq!(msg.sequence, sequence);
+            assert_eq!(msg.stream_id, stream_id(proposed.height, proposed.round));
+            contents.push(msg.content);
+        }
+
+        assert_eq!(
+            contents,
+            [
+                StreamContent::Data(ProposalPart::Data(ProposalData::new(1))),
+                StreamContent::Data(ProposalPart::Data(ProposalData::new(2))),
+                StreamContent::Fin,
+            ]
+        );
+    }
+}
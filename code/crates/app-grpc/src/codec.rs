@@ -0,0 +1,33 @@
+use malachitebft_app_channel::app::types::core::{CommitCertificate, Context};
+use malachitebft_codec::Codec;
+
+/// Codec for the types specific to the context of the application, which cross
+/// the gRPC interface as bytes. The application must decode and encode them in the same way.
+///
+/// This trait is automatically implemented for any type that implements:
+/// - [`Codec<Ctx::Value>`]
+/// - [`Codec<Ctx::ProposalPart>`]
+/// - [`Codec<Ctx::Address>`]
+/// - [`Codec<Ctx::ValidatorSet>`]
+/// - [`Codec<CommitCertificate<Ctx>>`]
+pub trait GrpcCodec<Ctx>
+where
+    Ctx: Context,
+    Self: Codec<Ctx::Value>,
+    Self: Codec<Ctx::ProposalPart>,
+    Self: Codec<Ctx::Address>,
+    Self: Codec<Ctx::ValidatorSet>,
+    Self: Codec<CommitCertificate<Ctx>>,
+{
+}
+
+impl<Ctx, C> GrpcCodec<Ctx> for C
+where
+    Ctx: Context,
+    C: Codec<Ctx::Value>,
+    C: Codec<Ctx::ProposalPart>,
+    C: Codec<Ctx::Address>,
+    C: Codec<Ctx::ValidatorSet>,
+    C: Codec<CommitCertificate<Ctx>>,
+{
+}
@@ -0,0 +1,35 @@
+use thiserror::Error;
+
+/// Errors of the [`GrpcApp`](crate::GrpcApp)
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The application could not be reached
+    #[error("Failed to connect to the application: {0}")]
+    Connect(#[from] tonic::transport::Error),
+
+    /// The application failed to handle a call
+    #[error("The application failed to handle {call}: {status}")]
+    Call {
+        call: &'static str,
+        status: tonic::Status,
+    },
+
+    /// A value of the engine could not be encoded for the application
+    #[error("Failed to encode {what}: {reason}")]
+    Encode { what: &'static str, reason: String },
+
+    /// A reply of the application could not be decoded
+    #[error("Failed to decode {what} sent by the application: {reason}")]
+    Decode { what: &'static str, reason: String },
+
+    /// A reply of the application lacks a mandatory field
+    #[error("The application replied to {call} without {field}")]
+    MissingField {
+        call: &'static str,
+        field: &'static str,
+    },
+
+    /// The engine stopped
+    #[error("The engine stopped")]
+    EngineStopped,
+}
@@ -0,0 +1,31 @@
+//! gRPC interface for driving the Malachite BFT consensus engine from an application running
+//! in another process, possibly written in another language, in the spirit of ABCI.
+//!
+//! The application implements the `Application` service defined in `proto/application.proto`,
+//! which mirrors the messages of the channel-based interface of `app-channel`: the application
+//! is asked to propose values, is given the proposal parts received from the peers to validate
+//! the values they make up, and is told about the decisions.
+//!
+//! The node starts the engine as any channel-based application does, and hands its
+//! [`Channels`](malachitebft_app_channel::Channels) to a [`GrpcApp`] connected to the application,
+//! which forwards each message of the engine to the application and its reply back to the engine.
+//! The types specific to the [`Context`](malachitebft_app_channel::app::types::core::Context)
+//! of the application cross the interface as bytes, encoded with a [`GrpcCodec`].
+//!
+//! Some parts of the channel-based interface are not exposed over gRPC yet. Vote extensions
+//! are neither produced nor checked, no snapshot is served nor restored, backfilled values are
+//! refused and misbehavior evidence is only logged.
+
+mod app;
+pub use app::GrpcApp;
+
+mod codec;
+pub use codec::GrpcCodec;
+
+mod error;
+pub use error::Error;
+
+/// Messages and service of the gRPC interface, generated from `proto/application.proto`
+pub mod proto {
+    tonic::include_proto!("malachitebft.app.v1");
+}
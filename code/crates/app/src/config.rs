@@ -8,4 +8,34 @@ pub trait NodeConfig {
 
     fn value_sync(&self) -> &ValueSyncConfig;
     fn value_sync_mut(&mut self) -> &mut ValueSyncConfig;
+
+    /// Thresholds of the health checks, the defaults unless overridden
+    fn health(&self) -> HealthConfig {
+        HealthConfig::default()
+    }
+
+    /// Monitoring of the offset of the local clock, disabled unless overridden
+    fn clock(&self) -> ClockConfig {
+        ClockConfig::default()
+    }
+
+    /// Server exporting the metrics of the node, disabled unless overridden
+    fn metrics(&self) -> MetricsConfig {
+        MetricsConfig::default()
+    }
+
+    /// Server exposing the status and introspection endpoints, disabled unless overridden
+    fn rpc(&self) -> RpcConfig {
+        RpcConfig::default()
+    }
+
+    /// Local socket accepting admin commands, disabled unless overridden
+    fn admin(&self) -> AdminConfig {
+        AdminConfig::default()
+    }
+
+    /// Audit log of the decided values, disabled unless overridden
+    fn decision_log(&self) -> DecisionLogConfig {
+        DecisionLogConfig::default()
+    }
 }
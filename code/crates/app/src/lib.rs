@@ -21,6 +21,10 @@ pub mod events {
 
 pub mod net {
     pub use libp2p::{Multiaddr, PeerId};
+    pub use malachitebft_network::{
+        AddrClass, AddressPolicy, DefaultAddressPolicy, EvictionPolicy, InboundPeer,
+        RefuseNewPeers, ValidatorAwareEvictionPolicy,
+    };
 }
 
 pub use malachitebft_core_consensus as consensus;
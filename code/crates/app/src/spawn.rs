@@ -6,18 +6,21 @@ use std::time::Duration;
 
 use eyre::{eyre, Result};
 use tokio::task::JoinHandle;
-use tracing::Span;
+use tracing::{info, Span};
 
 use malachitebft_engine::consensus::{Consensus, ConsensusCodec, ConsensusParams, ConsensusRef};
 use malachitebft_engine::host::HostRef;
 use malachitebft_engine::network::{Network, NetworkRef};
 use malachitebft_engine::node::{Node, NodeRef};
 use malachitebft_engine::sync::{Params as SyncParams, Sync, SyncCodec, SyncMsg, SyncRef};
+use malachitebft_engine::util::clock::Clock;
 use malachitebft_engine::util::events::TxEvent;
+use malachitebft_engine::util::mailbox::Mailbox;
 use malachitebft_engine::util::output_port::OutputPort;
-use malachitebft_engine::wal::{Wal, WalCodec, WalRef};
+use malachitebft_engine::wal::{Wal, WalBackendKind, WalCodec, WalRef};
 use malachitebft_network::{
-    ChannelNames, Config as NetworkConfig, DiscoveryConfig, GossipSubConfig, NetworkIdentity,
+    AddressPolicy, ChannelNames, Config as NetworkConfig, DiscoveryConfig, EvictionPolicy,
+    GossipSubConfig, NetworkIdentity,
 };
 use malachitebft_signing::SigningProvider;
 use malachitebft_sync as sync;
@@ -53,23 +56,113 @@ where
     Ok((actor_ref, handle))
 }
 
+/// Policies of the network implemented by the application, which cannot be set in the configuration
+#[derive(Clone, Default)]
+pub struct NetworkPolicies {
+    /// Policy deciding which of the addresses advertised by the peers are reachable,
+    /// the one following the reachability settings of the configuration if none is set
+    pub address_policy: Option<Arc<dyn AddressPolicy>>,
+
+    /// Policy selecting an inbound peer to evict when the inbound peers limit is reached,
+    /// new peers are refused if none is set
+    pub eviction_policy: Option<Arc<dyn EvictionPolicy>>,
+}
+
+impl NetworkPolicies {
+    pub fn with_address_policy(mut self, address_policy: Arc<dyn AddressPolicy>) -> Self {
+        self.address_policy = Some(address_policy);
+        self
+    }
+
+    pub fn with_eviction_policy(mut self, eviction_policy: Arc<dyn EvictionPolicy>) -> Self {
+        self.eviction_policy = Some(eviction_policy);
+        self
+    }
+}
+
 pub async fn spawn_network_actor<Ctx, Codec>(
     consensus_cfg: &ConsensusConfig,
     value_sync_cfg: &ValueSyncConfig,
     identity: NetworkIdentity,
     registry: &SharedRegistry,
     codec: Codec,
+    policies: NetworkPolicies,
 ) -> Result<NetworkRef<Ctx>>
 where
     Ctx: Context,
     Codec: ConsensusCodec<Ctx>,
     Codec: SyncCodec<Ctx>,
 {
-    let config = make_network_config(consensus_cfg, value_sync_cfg);
+    let config = make_network_config(consensus_cfg, value_sync_cfg, policies);
+    let max_value_size = consensus_cfg.max_value_size.as_u64() as usize;
+    let mailbox = Mailbox::new("network", consensus_cfg.p2p.mailbox, registry);
+
+    Network::spawn(
+        identity,
+        config,
+        registry.clone(),
+        codec,
+        max_value_size,
+        mailbox,
+        consensus_cfg.p2p.vote_batching,
+        Span::current(),
+    )
+    .await
+    .map_err(Into::into)
+}
+
+/// Spawn one network actor per chain listed in `chain_ids`, all of them sharing a single
+/// network service, ie. the same listen address, connections to the peers and discovery.
+///
+/// The pubsub topics and the sync protocol of each chain are prefixed with `/<chain_id>`,
+/// see [`malachitebft_network::spawn_chains`]. The actors are returned in the order of `chain_ids`.
+pub async fn spawn_shared_network_actors<Ctx, Codec>(
+    consensus_cfg: &ConsensusConfig,
+    value_sync_cfg: &ValueSyncConfig,
+    identity: NetworkIdentity,
+    chain_ids: Vec<String>,
+    registry: &SharedRegistry,
+    codec: Codec,
+    policies: NetworkPolicies,
+) -> Result<Vec<NetworkRef<Ctx>>>
+where
+    Ctx: Context,
+    Codec: ConsensusCodec<Ctx>,
+    Codec: SyncCodec<Ctx>,
+    Codec: Clone,
+{
+    if chain_ids.is_empty() {
+        return Err(eyre!("A shared network must serve at least one chain"));
+    }
+
+    let mut config = make_network_config(consensus_cfg, value_sync_cfg, policies);
+    config.chain_ids = chain_ids;
 
-    Network::spawn(identity, config, registry.clone(), codec, Span::current())
-        .await
-        .map_err(Into::into)
+    let max_value_size = consensus_cfg.max_value_size.as_u64() as usize;
+
+    let handles = malachitebft_network::spawn_chains(identity, config, registry.clone()).await?;
+
+    // The events of all the chains come from the same network service,
+    // so the actors share a mailbox bounding them altogether
+    let mailbox = Mailbox::new("network", consensus_cfg.p2p.mailbox, registry);
+
+    let mut actors = Vec::with_capacity(handles.len());
+
+    for handle in handles {
+        let actor = Network::spawn_with_handle(
+            handle,
+            codec.clone(),
+            max_value_size,
+            mailbox.clone(),
+            consensus_cfg.p2p.vote_batching,
+            Span::current(),
+        )
+        .await?;
+
+        actors.push(actor);
+    }
+
+    Ok(actors)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -81,9 +174,12 @@ pub async fn spawn_consensus_actor<Ctx>(
     network: NetworkRef<Ctx>,
     host: HostRef<Ctx>,
     wal: WalRef<Ctx>,
+    wal_group_commit: bool,
     sync: Arc<OutputPort<SyncMsg<Ctx>>>,
     metrics: Metrics,
     tx_event: TxEvent<Ctx>,
+    clock: Arc<dyn Clock>,
+    registry: &SharedRegistry,
 ) -> Result<ConsensusRef<Ctx>>
 where
     Ctx: Context,
@@ -96,13 +192,28 @@ where
         config::ValuePayload::ProposalAndParts => ValuePayload::ProposalAndParts,
     };
 
+    if cfg.follower && !cfg.enabled {
+        return Err(eyre!(
+            "Follower mode requires consensus to be enabled, set `consensus.enabled = true`"
+        ));
+    }
+
+    if cfg.follower {
+        info!("Running as a read-only follower, this node will not sign nor propose");
+    }
+
     let consensus_params = ConsensusParams {
         address,
         threshold_params: Default::default(),
         value_payload,
         enabled: cfg.enabled,
+        max_votes_per_validator_per_round: (cfg.max_votes_per_validator_per_round > 0)
+            .then_some(cfg.max_votes_per_validator_per_round),
+        follower: cfg.follower,
     };
 
+    let mailbox = Mailbox::new("consensus", cfg.mailbox, registry);
+
     Consensus::spawn(
         ctx,
         consensus_params,
@@ -111,9 +222,12 @@ where
         network,
         host,
         wal,
+        wal_group_commit,
         sync,
         metrics,
         tx_event,
+        clock,
+        mailbox,
         Span::current(),
     )
     .await
@@ -124,6 +238,8 @@ pub async fn spawn_wal_actor<Ctx, Codec>(
     ctx: &Ctx,
     codec: Codec,
     path: &Path,
+    backend: WalBackendKind,
+    group_commit: Option<Duration>,
     registry: &SharedRegistry,
 ) -> Result<WalRef<Ctx>>
 where
@@ -140,6 +256,8 @@ where
         ctx,
         codec,
         path.to_owned(),
+        backend,
+        group_commit,
         registry.clone(),
         Span::current(),
     )
@@ -171,6 +289,7 @@ where
     let params = SyncParams {
         status_update_interval: config.status_update_interval,
         request_timeout: config.request_timeout,
+        checkpoint_path: config.checkpoint_file.clone(),
     };
 
     let scoring_strategy = match config.scoring_strategy {
@@ -181,15 +300,33 @@ where
         enabled: config.enabled,
         max_request_size: config.max_request_size.as_u64() as usize,
         max_response_size: config.max_response_size.as_u64() as usize,
+        max_chunked_response_size: config.max_chunked_value_size(),
         request_timeout: config.request_timeout,
         parallel_requests: config.parallel_requests,
         scoring_strategy,
         inactive_threshold: (!config.inactive_threshold.is_zero())
             .then_some(config.inactive_threshold),
         batch_size: config.batch_size,
+        audit_window: config.audit_window,
+        advertise_min_needed_height: config.advertise_min_needed_height,
+        // Negotiated by the network layer, see `P2pConfig::compression`
+        compression: false,
+        max_in_flight_bytes: config.max_in_flight_bytes(),
+        max_inbound_requests: config.max_inbound_requests(),
+        max_inbound_requests_per_peer: config.max_inbound_requests_per_peer(),
+        max_served_bytes_per_sec: config.max_served_bytes_per_sec(),
+        certificates_only: config.certificates_only,
+        verification_queue_size: config.verification_queue_size,
+        snapshot_sync: config.snapshot_sync,
+        snapshot_min_lag: config.snapshot_min_lag,
+        snapshot_min_peers: config.snapshot_min_peers,
+        backfill: config.backfill,
+        proposal_parts_delay: config.proposal_parts_delay,
+        vote_sync: config.vote_sync,
     };
 
     let metrics = sync::Metrics::register(registry, params.status_update_interval);
+    let mailbox = Mailbox::new("sync", config.mailbox, registry);
 
     let actor_ref = Sync::spawn(
         ctx,
@@ -200,6 +337,7 @@ where
         sync_codec,
         sync_config,
         metrics,
+        mailbox,
         Span::current(),
     )
     .await?;
@@ -207,7 +345,11 @@ where
     Ok(Some(actor_ref))
 }
 
-fn make_network_config(cfg: &ConsensusConfig, value_sync_cfg: &ValueSyncConfig) -> NetworkConfig {
+fn make_network_config(
+    cfg: &ConsensusConfig,
+    value_sync_cfg: &ValueSyncConfig,
+    policies: NetworkPolicies,
+) -> NetworkConfig {
     use malachitebft_config as config;
     use malachitebft_network as network;
 
@@ -215,9 +357,13 @@ fn make_network_config(cfg: &ConsensusConfig, value_sync_cfg: &ValueSyncConfig)
         listen_addr: cfg.p2p.listen_addr.clone(),
         persistent_peers: cfg.p2p.persistent_peers.clone(),
         persistent_peers_only: cfg.p2p.persistent_peers_only,
+        explicit_peers: cfg.p2p.explicit_peers.clone(),
         discovery: DiscoveryConfig {
             enabled: cfg.p2p.discovery.enabled,
             persistent_peers_only: cfg.p2p.persistent_peers_only,
+            allowed_peers: (!cfg.p2p.allowed_peers.is_empty())
+                .then(|| cfg.p2p.allowed_peers.iter().copied().collect()),
+            persistent_peers: cfg.p2p.discovery.persistent_peers.clone(),
             bootstrap_protocol: match cfg.p2p.discovery.bootstrap_protocol {
                 config::BootstrapProtocol::Kademlia => network::BootstrapProtocol::Kademlia,
                 config::BootstrapProtocol::Full => network::BootstrapProtocol::Full,
@@ -225,15 +371,59 @@ fn make_network_config(cfg: &ConsensusConfig, value_sync_cfg: &ValueSyncConfig)
             selector: match cfg.p2p.discovery.selector {
                 config::Selector::Kademlia => network::Selector::Kademlia,
                 config::Selector::Random => network::Selector::Random,
+                config::Selector::Latency => network::Selector::Latency,
             },
             num_outbound_peers: cfg.p2p.discovery.num_outbound_peers,
             num_inbound_peers: cfg.p2p.discovery.num_inbound_peers,
             max_connections_per_ip: cfg.p2p.discovery.max_connections_per_ip,
             max_connections_per_peer: cfg.p2p.discovery.max_connections_per_peer,
             ephemeral_connection_timeout: cfg.p2p.discovery.ephemeral_connection_timeout,
+            bootstrap_timeout: cfg.p2p.discovery.bootstrap_timeout,
+            address_family_preference: match cfg.p2p.discovery.address_family_preference {
+                config::AddressFamilyPreference::Any => network::AddressFamilyPreference::Any,
+                config::AddressFamilyPreference::Ipv6 => network::AddressFamilyPreference::Ipv6,
+                config::AddressFamilyPreference::Ipv4 => network::AddressFamilyPreference::Ipv4,
+            },
+            happy_eyeballs_delay: cfg.p2p.discovery.happy_eyeballs_delay,
+            relay_rebalance_interval: cfg.p2p.discovery.relay_rebalance_interval,
+            auto_relay: cfg
+                .p2p
+                .discovery
+                .auto_relay
+                .map(|auto_relay| network::AutoRelayConfig {
+                    num_relays: auto_relay.num_relays,
+                    max_circuits: auto_relay.max_circuits,
+                }),
+            max_concurrent_dials: cfg.p2p.discovery.max_concurrent_dials,
+            dials_per_second: cfg.p2p.discovery.dials_per_second,
             dial_max_retries: cfg.p2p.discovery.dial_max_retries,
             request_max_retries: cfg.p2p.discovery.request_max_retries,
             connect_request_max_retries: cfg.p2p.discovery.connect_request_max_retries,
+            retry_backoff: network::BackoffConfig {
+                initial_delay: cfg.p2p.discovery.retry_backoff.initial_delay,
+                multiplier: cfg.p2p.discovery.retry_backoff.multiplier,
+                max_delay: cfg.p2p.discovery.retry_backoff.max_delay,
+                jitter: cfg.p2p.discovery.retry_backoff.jitter,
+            },
+            reconnect: network::ReconnectConfig {
+                spread_per_peer: cfg.p2p.discovery.reconnect.spread_per_peer,
+                max_spread: cfg.p2p.discovery.reconnect.max_spread,
+            },
+            reachability: network::ReachabilityConfig {
+                ipv4_subnet_prefix_len: cfg.p2p.discovery.reachability.ipv4_subnet_prefix_len,
+                ipv6_subnet_prefix_len: cfg.p2p.discovery.reachability.ipv6_subnet_prefix_len,
+                private_to_public: cfg.p2p.discovery.reachability.private_to_public,
+                public_to_private: cfg.p2p.discovery.reachability.public_to_private,
+                trust_advertised_addrs: cfg.p2p.discovery.reachability.trust_advertised_addrs,
+            },
+            address_policy: policies.address_policy,
+            eviction_policy: policies.eviction_policy,
+            max_message_size: cfg
+                .p2p
+                .max_message_sizes
+                .discovery
+                .map(|size| size.as_u64() as usize),
+            address_book: cfg.p2p.discovery.address_book.clone(),
         },
         idle_connection_timeout: Duration::from_secs(15 * 60),
         transport: network::TransportProtocol::from_multiaddr(&cfg.p2p.listen_addr).unwrap_or_else(
@@ -244,6 +434,48 @@ fn make_network_config(cfg: &ConsensusConfig, value_sync_cfg: &ValueSyncConfig)
                 )
             },
         ),
+        security: match cfg.p2p.security {
+            config::SecurityProtocol::Noise => network::SecurityProtocol::Noise,
+            config::SecurityProtocol::Tls => network::SecurityProtocol::Tls,
+            config::SecurityProtocol::NoiseAndTls => network::SecurityProtocol::NoiseAndTls,
+        },
+        dial_timeouts: network::DialTimeouts {
+            tcp_connect: cfg.p2p.dial_timeouts.tcp_connect,
+            relay_circuit: cfg.p2p.dial_timeouts.relay_circuit,
+            dcutr_upgrade: cfg.p2p.dial_timeouts.dcutr_upgrade,
+        },
+        websocket: network::WebSocketConfig {
+            tls: match (
+                &cfg.p2p.websocket.tls_cert_file,
+                &cfg.p2p.websocket.tls_key_file,
+            ) {
+                (Some(cert_file), Some(key_file)) => Some(network::WebSocketTlsConfig {
+                    cert_file: cert_file.clone(),
+                    key_file: key_file.clone(),
+                }),
+                (None, None) => None,
+                _ => panic!(
+                    "Both `tls_cert_file` and `tls_key_file` must be set for secure WebSocket"
+                ),
+            },
+            trusted_certs: cfg.p2p.websocket.trusted_certs.clone(),
+            advertised_addrs: cfg.p2p.websocket.advertised_addrs.clone(),
+        },
+        bandwidth: network::BandwidthConfig {
+            max_inbound_rate: cfg.p2p.bandwidth.max_inbound_rate.map(|rate| rate.as_u64()),
+            max_violations: cfg.p2p.bandwidth.max_violations,
+        },
+        liveness: network::LivenessConfig {
+            max_ping_failures: cfg.p2p.liveness.max_ping_failures,
+            max_ping_rtt: cfg.p2p.liveness.max_ping_rtt,
+        },
+        connection_limits: network::ConnectionLimitsConfig {
+            max_established_incoming: cfg.p2p.connection_limits.max_established_incoming,
+            max_established_outgoing: cfg.p2p.connection_limits.max_established_outgoing,
+            max_pending_incoming: cfg.p2p.connection_limits.max_pending_incoming,
+            max_pending_outgoing: cfg.p2p.connection_limits.max_pending_outgoing,
+            max_established_per_peer: cfg.p2p.connection_limits.max_established_per_peer,
+        },
         pubsub_protocol: match cfg.p2p.protocol {
             config::PubSubProtocol::GossipSub(_) => network::PubSubProtocol::GossipSub,
             config::PubSubProtocol::Broadcast => network::PubSubProtocol::Broadcast,
@@ -256,13 +488,86 @@ fn make_network_config(cfg: &ConsensusConfig, value_sync_cfg: &ValueSyncConfig)
                 mesh_outbound_min: config.mesh_outbound_min(),
                 enable_peer_scoring: config.enable_peer_scoring(),
                 enable_explicit_peering: config.enable_explicit_peering(),
+                enable_explicit_validator_peering: config.enable_explicit_validator_peering(),
                 enable_flood_publish: config.enable_flood_publish(),
+                flood_publish_votes: config.flood_publish_votes(),
+                peer_score: network::PeerScoreConfig {
+                    app_specific_weight: config.peer_score().app_specific_weight,
+                    ip_colocation_factor_weight: config.peer_score().ip_colocation_factor_weight,
+                    ip_colocation_factor_threshold: config
+                        .peer_score()
+                        .ip_colocation_factor_threshold,
+                    behaviour_penalty_weight: config.peer_score().behaviour_penalty_weight,
+                    behaviour_penalty_decay: config.peer_score().behaviour_penalty_decay,
+                    gossip_threshold: config.peer_score().gossip_threshold,
+                    publish_threshold: config.peer_score().publish_threshold,
+                    graylist_threshold: config.peer_score().graylist_threshold,
+                    opportunistic_graft_threshold: config
+                        .peer_score()
+                        .opportunistic_graft_threshold,
+                    reported_score_decay: config.peer_score().reported_score_decay,
+                },
+                validate_messages: config.validate_messages(),
+                history_length: config.history_length(),
+                history_gossip: config.history_gossip(),
+                duplicate_cache_time: config.duplicate_cache_time(),
             },
             config::PubSubProtocol::Broadcast => GossipSubConfig::default(),
         },
         channel_names: ChannelNames::default(),
         rpc_max_size: cfg.p2p.rpc_max_size.as_u64() as usize,
+        rpc_max_chunked_size: value_sync_cfg.max_chunked_value_size(),
         pubsub_max_size: cfg.p2p.pubsub_max_size.as_u64() as usize,
+        max_message_sizes: network::MaxMessageSizes {
+            votes: cfg
+                .p2p
+                .max_message_sizes
+                .votes
+                .map(|size| size.as_u64() as usize),
+            proposal_parts: cfg
+                .p2p
+                .max_message_sizes
+                .proposal_parts
+                .map(|size| size.as_u64() as usize),
+            sync_requests: cfg
+                .p2p
+                .max_message_sizes
+                .sync_requests
+                .map(|size| size.as_u64() as usize),
+            sync_responses: cfg
+                .p2p
+                .max_message_sizes
+                .sync_responses
+                .map(|size| size.as_u64() as usize),
+            discovery: cfg
+                .p2p
+                .max_message_sizes
+                .discovery
+                .map(|size| size.as_u64() as usize),
+        },
+        compression: network::CompressionConfig {
+            consensus: cfg.p2p.compression.consensus,
+            proposal_parts: cfg.p2p.compression.proposal_parts,
+            liveness: cfg.p2p.compression.liveness,
+            sync: cfg.p2p.compression.sync,
+        },
+        mempool: network::MempoolConfig {
+            enabled: cfg.p2p.mempool.enabled,
+            max_msg_size: cfg.p2p.mempool.max_msg_size.as_u64() as usize,
+            max_msgs_per_peer: cfg.p2p.mempool.max_msgs_per_peer,
+        },
+        hole_punch: network::HolePunchConfig {
+            enabled: cfg.p2p.hole_punch.enabled,
+            disabled_peers: cfg.p2p.hole_punch.disabled_peers.iter().copied().collect(),
+            disabled_networks: cfg
+                .p2p
+                .hole_punch
+                .disabled_networks
+                .iter()
+                .map(|subnet| network::IpSubnet::new(subnet.addr, subnet.prefix_len))
+                .collect(),
+            min_retry_interval: cfg.p2p.hole_punch.min_retry_interval,
+        },
         enable_consensus: cfg.enabled,
         enable_sync: value_sync_cfg.enabled,
         protocol_names: network::ProtocolNames {
@@ -271,6 +576,11 @@ fn make_network_config(cfg: &ConsensusConfig, value_sync_cfg: &ValueSyncConfig)
             discovery_regres: cfg.p2p.protocol_names.discovery_regres.clone(),
             sync: cfg.p2p.protocol_names.sync.clone(),
             validator_proof: cfg.p2p.protocol_names.validator_proof.clone(),
+            peer_message: cfg.p2p.protocol_names.peer_message.clone(),
+            node_key: cfg.p2p.protocol_names.node_key.clone(),
         },
+        // A single chain per node, see `spawn_shared_network_actors` to serve several ones
+        chain_ids: Vec::new(),
+        node_key_file: cfg.p2p.node_key_file.clone(),
     }
 }
@@ -3,7 +3,7 @@
 pub use libp2p_identity::Keypair;
 
 pub use malachitebft_core_consensus::{
-    ConsensusMsg, MisbehaviorEvidence, ProposedValue, SignedConsensusMsg, ValuePayload,
+    ConsensusMsg, Evidence, MisbehaviorEvidence, ProposedValue, SignedConsensusMsg, ValuePayload,
 };
 pub use malachitebft_engine::host::LocallyProposedValue;
 pub use malachitebft_peer::PeerId;
@@ -17,7 +17,9 @@ pub mod streaming {
 }
 
 pub mod sync {
-    pub use malachitebft_sync::{Metrics, RawDecidedValue, Request, Response, Status};
+    pub use malachitebft_sync::{
+        ConfigUpdate, Metrics, NodeStatus, RawDecidedValue, Request, Response, Snapshot, Status,
+    };
 }
 
 pub mod codec {
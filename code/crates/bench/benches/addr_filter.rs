@@ -0,0 +1,55 @@
+//! Filtering of the addresses advertised by the peers before dialing them,
+//! done for every peer record received during discovery.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use multiaddr::Multiaddr;
+
+use malachitebft_discovery::addr_filter::{filter_reachable_addresses, DefaultAddressPolicy};
+use malachitebft_discovery::config::ReachabilityConfig;
+
+use arc_malachitebft_bench::advertised_addrs;
+
+fn filter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("addr_filter");
+
+    let local_addrs: Vec<Multiaddr> = [
+        "/ip4/10.0.0.1/tcp/27000",
+        "/ip4/203.0.113.7/udp/27000/quic-v1",
+        "/ip6/fd00::1/tcp/27000",
+    ]
+    .iter()
+    .map(|addr| addr.parse().unwrap())
+    .collect();
+
+    let policies = [
+        ("default", DefaultAddressPolicy::default()),
+        (
+            "public_only",
+            DefaultAddressPolicy::new(ReachabilityConfig {
+                public_to_private: false,
+                private_to_public: false,
+                ..ReachabilityConfig::default()
+            }),
+        ),
+    ];
+
+    for count in [100, 1_000, 10_000] {
+        let remote_addrs = advertised_addrs(count);
+        group.throughput(Throughput::Elements(count as u64));
+
+        for (name, policy) in &policies {
+            group.bench_with_input(BenchmarkId::new(*name, count), &remote_addrs, |b, addrs| {
+                b.iter(|| {
+                    filter_reachable_addresses(policy, black_box(&local_addrs), addrs.clone())
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, filter);
+criterion_main!(benches);
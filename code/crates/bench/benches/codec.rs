@@ -0,0 +1,38 @@
+//! Encoding and decoding of the votes and proposals gossiped to the peers,
+//! done for every message published and received on the consensus channel.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use malachitebft_codec::Codec;
+use malachitebft_core_consensus::SignedConsensusMsg;
+use malachitebft_test::codec::proto::ProtobufCodec;
+use malachitebft_test::TestContext;
+
+use arc_malachitebft_bench::{proposal_msg, vote_msg};
+
+fn consensus_msgs(c: &mut Criterion) {
+    let codec = ProtobufCodec;
+    let mut group = c.benchmark_group("codec");
+    group.throughput(Throughput::Elements(1));
+
+    for (name, msg) in [("vote", vote_msg()), ("proposal", proposal_msg())] {
+        let encoded = Codec::<SignedConsensusMsg<TestContext>>::encode(&codec, &msg).unwrap();
+
+        group.bench_function(format!("encode_{name}"), |b| {
+            b.iter(|| Codec::<SignedConsensusMsg<TestContext>>::encode(&codec, black_box(&msg)))
+        });
+
+        group.bench_function(format!("decode_{name}"), |b| {
+            b.iter(|| {
+                Codec::<SignedConsensusMsg<TestContext>>::decode(&codec, black_box(encoded.clone()))
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, consensus_msgs);
+criterion_main!(benches);
@@ -0,0 +1,78 @@
+//! Bookkeeping of the value sync requests sent to the peers and not answered yet,
+//! looked up whenever a response arrives and whenever the next range of heights is requested.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+use malachitebft_core_types::Height as _;
+use malachitebft_peer::PeerId;
+use malachitebft_sync::OutboundRequestId;
+use malachitebft_test::Height;
+
+use arc_malachitebft_bench::pending_requests;
+
+/// Number of heights covered by each pending request
+const RANGE_SIZE: u64 = 10;
+
+fn operations(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pending_requests");
+
+    for count in [16, 256, 4096] {
+        let pending = pending_requests(count, RANGE_SIZE);
+        let last = Height::new(count as u64 * RANGE_SIZE);
+        let middle = Height::new(count as u64 * RANGE_SIZE / 2);
+        let peer_id = PeerId::random();
+
+        group.bench_with_input(BenchmarkId::new("insert_remove", count), &count, |b, _| {
+            b.iter_batched(
+                || pending.clone(),
+                |mut pending| {
+                    let request_id = OutboundRequestId::new("bench");
+                    let start = last.increment();
+                    let end = start.increment_by(RANGE_SIZE - 1);
+
+                    pending.insert(request_id.clone(), start..=end, peer_id);
+                    pending.remove(black_box(&request_id))
+                },
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.bench_with_input(BenchmarkId::new("covering", count), &count, |b, _| {
+            b.iter(|| pending.covering(black_box(middle)).is_some())
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("next_uncovered_height", count),
+            &count,
+            |b, _| b.iter(|| pending.next_uncovered_height(black_box(Height::new(1)))),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("next_uncovered_range_from", count),
+            &count,
+            |b, _| b.iter(|| pending.next_uncovered_range_from(black_box(last.increment()), 100)),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("remove_requests_up_to", count),
+            &count,
+            |b, _| {
+                b.iter_batched(
+                    || pending.clone(),
+                    |mut pending| {
+                        pending.remove_requests_up_to(black_box(middle));
+                        pending
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, operations);
+criterion_main!(benches);
@@ -0,0 +1,79 @@
+//! Appending the entries of consensus to the WAL, each synced to disk before the message
+//! it records is acted upon, or synced once per batch of entries with group commit.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tempfile::tempdir;
+
+use malachitebft_core_consensus::{SignedConsensusMsg, WalEntry};
+use malachitebft_engine::wal::encode_entry;
+use malachitebft_test::codec::proto::ProtobufCodec;
+use malachitebft_test::TestContext;
+use malachitebft_wal::Log;
+
+use arc_malachitebft_bench::{proposal_msg, signed_votes, vote_msg};
+
+fn encode(entry: &WalEntry<TestContext>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_entry(entry, &ProtobufCodec, &mut buf).unwrap();
+    buf
+}
+
+fn append_fsync(c: &mut Criterion) {
+    let dir = tempdir().unwrap();
+    let mut group = c.benchmark_group("wal_append_fsync");
+
+    let entries = [
+        ("vote", encode(&WalEntry::ConsensusMsg(vote_msg()))),
+        ("proposal", encode(&WalEntry::ConsensusMsg(proposal_msg()))),
+    ];
+
+    for (name, entry) in &entries {
+        let mut log = Log::open(dir.path().join(format!("{name}.wal"))).unwrap();
+
+        group.throughput(Throughput::Bytes(entry.len() as u64));
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                log.append(black_box(entry)).unwrap();
+                log.flush().unwrap();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn group_commit(c: &mut Criterion) {
+    let dir = tempdir().unwrap();
+    let mut group = c.benchmark_group("wal_group_commit");
+
+    for batch_size in [10, 100, 1000] {
+        let entries: Vec<Vec<u8>> = signed_votes(batch_size, 1)
+            .into_iter()
+            .map(|vote| encode(&WalEntry::ConsensusMsg(SignedConsensusMsg::Vote(vote))))
+            .collect();
+
+        let mut log = Log::open(dir.path().join(format!("votes_{batch_size}.wal"))).unwrap();
+
+        group.throughput(Throughput::Elements(batch_size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("votes", batch_size),
+            &entries,
+            |b, entries| {
+                b.iter(|| {
+                    for entry in entries {
+                        log.append(black_box(entry)).unwrap();
+                    }
+
+                    log.flush().unwrap();
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, append_fsync, group_commit);
+criterion_main!(benches);
@@ -0,0 +1,168 @@
+//! Benchmarks of the hot paths of the engine, run with `cargo bench -p arc-malachitebft-bench`,
+//! so that their regressions can be measured before a release:
+//!
+//! - `wal`: appending the entries of consensus to the WAL and syncing them to disk,
+//! - `codec`: encoding and decoding the votes and proposals gossiped to the peers,
+//! - `pending_requests`: bookkeeping of the value sync requests sent to the peers,
+//! - `addr_filter`: filtering of the addresses advertised by the peers before dialing them.
+//!
+//! This crate only holds the inputs shared by the benchmarks, found in `benches/`.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use multiaddr::{Multiaddr, Protocol};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use malachitebft_core_consensus::SignedConsensusMsg;
+use malachitebft_core_types::{Height as _, NilOrVal, Round, SignedMessage, SignedVote};
+use malachitebft_peer::PeerId;
+use malachitebft_sync::{OutboundRequestId, PendingRequests};
+use malachitebft_test::{Address, Height, PrivateKey, Proposal, TestContext, Value, ValueId, Vote};
+
+/// Seed of the inputs, so that all the runs measure the same work
+const SEED: u64 = 42;
+
+/// Signed votes of as many validators, half prevotes and half precommits for the same value
+pub fn signed_votes(validators: usize, height: u64) -> Vec<SignedVote<TestContext>> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let (height, round) = (Height::new(height), Round::new(0));
+    let value_id = NilOrVal::Val(ValueId::new(rng.gen()));
+
+    (0..validators)
+        .map(|i| {
+            let key = PrivateKey::generate(&mut rng);
+            let address = Address::from_public_key(&key.public_key());
+
+            let vote = if i % 2 == 0 {
+                Vote::new_prevote(height, round, value_id, address)
+            } else {
+                Vote::new_precommit(height, round, value_id, address)
+            };
+
+            let signature = key.sign(&vote.to_sign_bytes());
+            SignedMessage::new(vote, signature)
+        })
+        .collect()
+}
+
+/// A signed vote, as gossiped on the consensus channel
+pub fn vote_msg() -> SignedConsensusMsg<TestContext> {
+    let vote = signed_votes(1, 1).remove(0);
+    SignedConsensusMsg::Vote(vote)
+}
+
+/// A signed proposal, as gossiped on the consensus channel
+pub fn proposal_msg() -> SignedConsensusMsg<TestContext> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let key = PrivateKey::generate(&mut rng);
+    let address = Address::from_public_key(&key.public_key());
+
+    let proposal = Proposal::new(
+        Height::new(1),
+        Round::new(0),
+        Value::new(rng.gen()),
+        Round::Nil,
+        address,
+    );
+
+    let signature = key.sign(&proposal.to_sign_bytes());
+    SignedConsensusMsg::Proposal(SignedMessage::new(proposal, signature))
+}
+
+/// Pending requests for consecutive ranges of `range_size` heights from height 1,
+/// each sent to a different peer
+pub fn pending_requests(count: usize, range_size: u64) -> PendingRequests<TestContext> {
+    let mut pending = PendingRequests::new();
+    let mut start = Height::new(1);
+
+    for i in 0..count {
+        let end = start.increment_by(range_size - 1);
+        pending.insert(OutboundRequestId::new(i), start..=end, PeerId::random());
+        start = end.increment();
+    }
+
+    pending
+}
+
+/// Listen addresses as advertised by the peers, a mix of public, private and loopback
+/// IPv4 and IPv6 addresses over TCP and QUIC
+pub fn advertised_addrs(count: usize) -> Vec<Multiaddr> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+
+    (0..count)
+        .map(|_| {
+            let ip = match rng.gen_range(0..6) {
+                0 => Protocol::Ip4(Ipv4Addr::new(10, rng.gen(), rng.gen(), rng.gen())),
+                1 => Protocol::Ip4(Ipv4Addr::new(192, 168, rng.gen(), rng.gen())),
+                2 => Protocol::Ip4(Ipv4Addr::LOCALHOST),
+                3 => Protocol::Ip6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, rng.gen())),
+                4 => Protocol::Ip6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, rng.gen())),
+                _ => Protocol::Ip4(Ipv4Addr::new(
+                    rng.gen_range(11..100),
+                    rng.gen(),
+                    rng.gen(),
+                    rng.gen(),
+                )),
+            };
+
+            let port = rng.gen_range(1024..u16::MAX);
+
+            let addr = Multiaddr::empty().with(ip);
+
+            if rng.gen_bool(0.5) {
+                addr.with(Protocol::Tcp(port))
+            } else {
+                addr.with(Protocol::Udp(port)).with(Protocol::QuicV1)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use malachitebft_core_types::{Vote as _, VoteType};
+
+    use super::*;
+
+    #[test]
+    fn votes_are_the_same_across_runs() {
+        let votes = signed_votes(10, 1);
+
+        assert_eq!(votes, signed_votes(10, 1));
+
+        let prevotes = votes
+            .iter()
+            .filter(|vote| vote.vote_type() == VoteType::Prevote)
+            .count();
+        assert_eq!(prevotes, 5);
+
+        let validators: HashSet<_> = votes.iter().map(|vote| vote.validator_address()).collect();
+        assert_eq!(validators.len(), 10);
+    }
+
+    #[test]
+    fn pending_requests_cover_consecutive_ranges() {
+        let pending = pending_requests(4, 10);
+
+        assert_eq!(pending.len(), 4);
+        assert_eq!(
+            pending.next_uncovered_height(Height::new(1)),
+            Height::new(41)
+        );
+
+        for height in [1, 10, 11, 40] {
+            assert!(pending.covering(Height::new(height)).is_some());
+        }
+    }
+
+    #[test]
+    fn advertised_addrs_are_the_same_across_runs() {
+        let addrs = advertised_addrs(100);
+
+        assert_eq!(addrs, advertised_addrs(100));
+        assert!(addrs.iter().all(|addr| addr.iter().count() >= 2));
+    }
+}
@@ -1,9 +1,11 @@
 use core::fmt;
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
 
 use bytesize::ByteSize;
+use libp2p_identity::PeerId;
 use multiaddr::Multiaddr;
 use serde::{Deserialize, Serialize};
 
@@ -20,6 +22,22 @@ pub struct ProtocolNames {
     pub sync: String,
 
     pub validator_proof: String,
+
+    /// Protocol of the messages sent by the application directly to a peer
+    #[serde(default = "default_peer_message_protocol")]
+    pub peer_message: String,
+
+    /// Protocol of the announcements of the new peer ID of a node to its persistent peers
+    #[serde(default = "default_node_key_protocol")]
+    pub node_key: String,
+}
+
+fn default_peer_message_protocol() -> String {
+    "/malachitebft-peer-message/v1".to_string()
+}
+
+fn default_node_key_protocol() -> String {
+    "/malachitebft-node-key/v1".to_string()
 }
 
 impl Default for ProtocolNames {
@@ -30,6 +48,8 @@ impl Default for ProtocolNames {
             discovery_regres: "/malachitebft-discovery/reqres/v1beta1".to_string(),
             sync: "/malachitebft-sync/v1beta1".to_string(),
             validator_proof: "/malachitebft-validator-proof/v1".to_string(),
+            peer_message: default_peer_message_protocol(),
+            node_key: default_node_key_protocol(),
         }
     }
 }
@@ -47,10 +67,57 @@ pub struct P2pConfig {
     #[serde(default)]
     pub persistent_peers_only: bool,
 
+    /// File the node key is persisted in, generated on first start if missing,
+    /// so that the node keeps its peer ID across restarts and can rotate it
+    #[serde(default)]
+    pub node_key_file: Option<PathBuf>,
+
+    /// Only allow connections to/from these peers, if not empty.
+    /// Used by permissioned networks to reject unknown peers at the network layer.
+    #[serde(default)]
+    pub allowed_peers: Vec<PeerId>,
+
+    /// GossipSub only. Peers to which full messages are always sent and forwarded,
+    /// regardless of mesh membership. Should be configured on both sides.
+    #[serde(default)]
+    pub explicit_peers: Vec<PeerId>,
+
     /// Peer discovery
     #[serde(default)]
     pub discovery: DiscoveryConfig,
 
+    /// Security protocol of the TCP and WebSocket connections, QUIC connections always use TLS 1.3
+    #[serde(default)]
+    pub security: SecurityProtocol,
+
+    /// Timeouts of outbound TCP and WebSocket connection attempts
+    #[serde(default)]
+    pub dial_timeouts: DialTimeoutsConfig,
+
+    /// WebSocket transport, used when listening on a `/ws` or `/wss` address
+    #[serde(default)]
+    pub websocket: WebSocketConfig,
+
+    /// Per-peer bandwidth limits
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+
+    /// Closing of the connections to unresponsive peers
+    #[serde(default)]
+    pub liveness: LivenessConfig,
+
+    /// Caps on the number of connections
+    #[serde(default)]
+    pub connection_limits: ConnectionLimitsConfig,
+
+    /// Compression of the messages, per channel
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    /// Gossip of the transactions of the application on a dedicated channel
+    #[serde(default)]
+    pub mempool: MempoolGossipConfig,
+
     /// The type of pub-sub protocol to use for consensus
     pub protocol: PubSubProtocol,
 
@@ -60,6 +127,22 @@ pub struct P2pConfig {
     /// The maximum size of messages to send over RPC
     pub rpc_max_size: ByteSize,
 
+    /// Maximum size of the messages of each protocol, within `pubsub_max_size` and `rpc_max_size`
+    #[serde(default)]
+    pub max_message_sizes: MaxMessageSizesConfig,
+
+    /// Upgrade of the relayed connections into direct ones (DCUtR hole punching)
+    #[serde(default)]
+    pub hole_punch: HolePunchConfig,
+
+    /// Bound on the events of the network waiting to be handled by the network actor
+    #[serde(default)]
+    pub mailbox: MailboxConfig,
+
+    /// Batching of the votes published on the consensus channel
+    #[serde(default)]
+    pub vote_batching: VoteBatchingConfig,
+
     /// Protocol name configuration
     #[serde(default)]
     pub protocol_names: ProtocolNames,
@@ -71,17 +154,352 @@ impl Default for P2pConfig {
             listen_addr: Multiaddr::empty(),
             persistent_peers: vec![],
             persistent_peers_only: false,
+            node_key_file: None,
+            allowed_peers: vec![],
+            explicit_peers: vec![],
             discovery: Default::default(),
+            security: Default::default(),
+            dial_timeouts: Default::default(),
+            websocket: Default::default(),
+            bandwidth: Default::default(),
+            liveness: Default::default(),
+            connection_limits: Default::default(),
+            compression: Default::default(),
+            mempool: Default::default(),
             protocol: Default::default(),
             rpc_max_size: ByteSize::mib(10),
             pubsub_max_size: ByteSize::mib(4),
+            max_message_sizes: Default::default(),
+            hole_punch: Default::default(),
+            mailbox: Default::default(),
+            vote_batching: Default::default(),
             protocol_names: Default::default(),
         }
     }
 }
 
-/// Peer Discovery configuration options
+/// Security protocol of the TCP and WebSocket connections.
+///
+/// The TLS certificates are generated from the node key, operator-provided certificates
+/// are only used by secure WebSockets (`/wss`), see [`WebSocketConfig`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SecurityProtocol {
+    #[default]
+    Noise,
+    /// TLS 1.3
+    Tls,
+    /// Negotiate Noise or TLS 1.3 with each peer, preferring Noise
+    NoiseAndTls,
+}
+
+impl FromStr for SecurityProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "noise" => Ok(Self::Noise),
+            "tls" => Ok(Self::Tls),
+            "noise-and-tls" => Ok(Self::NoiseAndTls),
+            e => Err(format!(
+                "unknown security protocol: {e}, available: noise, tls, noise-and-tls"
+            )),
+        }
+    }
+}
+
+/// Timeouts of outbound connection attempts, including the security and multiplexing
+/// handshakes, depending on how the peer is reached
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DialTimeoutsConfig {
+    /// Direct dials over TCP or WebSocket
+    #[serde(with = "humantime_serde")]
+    pub tcp_connect: Duration,
+
+    /// Dials through a relay, ie. of a `/p2p-circuit` address
+    #[serde(with = "humantime_serde")]
+    pub relay_circuit: Duration,
+
+    /// Direct dials upgrading a relayed connection (DCUtR hole punching)
+    #[serde(with = "humantime_serde")]
+    pub dcutr_upgrade: Duration,
+}
+
+impl Default for DialTimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            tcp_connect: Duration::from_secs(10),
+            relay_circuit: Duration::from_secs(60),
+            dcutr_upgrade: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Per-peer bandwidth limits
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandwidthConfig {
+    /// Maximum number of bytes per second received from a single peer, unlimited if not set
+    pub max_inbound_rate: Option<ByteSize>,
+
+    /// Number of consecutive seconds a peer may exceed `max_inbound_rate`
+    /// before the connections to it are closed
+    pub max_violations: u32,
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        Self {
+            max_inbound_rate: None,
+            max_violations: 5,
+        }
+    }
+}
+
+/// Closing of the connections to the peers which stopped responding to pings,
+/// sent every 5 seconds, or respond too slowly, so that discovery replaces them
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LivenessConfig {
+    /// Number of consecutive failed pings after which the connection is closed.
+    /// Connections are never closed if not set.
+    pub max_ping_failures: Option<u32>,
+
+    /// Round-trip time above which a ping counts as failed, no limit if not set
+    #[serde(with = "humantime_serde")]
+    pub max_ping_rtt: Option<Duration>,
+}
+
+/// Caps on the number of connections, enforced before any protocol runs on them.
+/// The ones not set are derived from the discovery limits: four times the number of
+/// inbound and outbound peers, and `max_connections_per_peer` per peer.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionLimitsConfig {
+    /// Maximum number of established inbound connections
+    pub max_established_incoming: Option<u32>,
+
+    /// Maximum number of established outbound connections
+    pub max_established_outgoing: Option<u32>,
+
+    /// Maximum number of inbound connections still in the security and multiplexing handshakes
+    pub max_pending_incoming: Option<u32>,
+
+    /// Maximum number of dials in progress
+    pub max_pending_outgoing: Option<u32>,
+
+    /// Maximum number of established connections to a single peer
+    pub max_established_per_peer: Option<u32>,
+}
+
+/// Channels whose messages are compressed with LZ4, the `pubsub_max_size` and `rpc_max_size`
+/// limits applying to the uncompressed messages.
+///
+/// Compressed channels use a different topic, so all peers must agree on which
+/// channels are compressed. Sync requests and responses are only compressed
+/// when the peer enables it too.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub consensus: bool,
+    pub proposal_parts: bool,
+    pub liveness: bool,
+    /// Compress both the sync status messages and the sync requests and responses
+    pub sync: bool,
+}
+
+/// Gossip of the transactions of the application on the `/mempool` channel,
+/// over the same pub-sub protocol as consensus but with its own limits.
+/// All peers must enable it to exchange transactions.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MempoolGossipConfig {
+    /// Subscribe to the mempool channel
+    pub enabled: bool,
+
+    /// Maximum size of a message on the mempool channel,
+    /// must not be larger than `pubsub_max_size`
+    pub max_msg_size: ByteSize,
+
+    /// Maximum number of messages per second accepted from a single peer,
+    /// unlimited if not set
+    pub max_msgs_per_peer: Option<u32>,
+}
+
+impl Default for MempoolGossipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_msg_size: ByteSize::mib(1),
+            max_msgs_per_peer: Some(100),
+        }
+    }
+}
+
+/// Maximum size of the messages of each protocol, so that eg. votes cannot be as large as
+/// proposals. Messages over the limit of their protocol are dropped when received, and counted
+/// in the `oversized_messages` metric. Protocols without a limit are only bound by the
+/// `pubsub_max_size` and `rpc_max_size` limits.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaxMessageSizesConfig {
+    /// Votes, proposals and certificates, on the consensus and liveness channels.
+    /// Must fit the proposals carrying the full value, if any.
+    pub votes: Option<ByteSize>,
+
+    /// Proposal parts
+    pub proposal_parts: Option<ByteSize>,
+
+    /// Sync requests received from the peers
+    pub sync_requests: Option<ByteSize>,
+
+    /// Sync responses received from the peers, once their chunks are put back together
+    pub sync_responses: Option<ByteSize>,
+
+    /// Requests and responses of the discovery protocol
+    pub discovery: Option<ByteSize>,
+}
+
+/// Upgrade of the connections to the peers reached through a relay into direct ones
+/// (DCUtR hole punching). Attempts and their outcome are reported per peer in the
+/// `hole_punches` metric.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HolePunchConfig {
+    /// Upgrade the relayed connections, the peers staying on their relay circuits if disabled
+    pub enabled: bool,
+
+    /// Peers never dialed directly to upgrade their relayed connection
+    pub disabled_peers: Vec<PeerId>,
+
+    /// Networks never dialed directly to upgrade a relayed connection, eg. `10.0.0.0/8`
+    pub disabled_networks: Vec<IpSubnet>,
+
+    /// Minimum interval between two upgrade attempts to the same peer, unbounded if not set
+    #[serde(with = "humantime_serde")]
+    pub min_retry_interval: Option<Duration>,
+}
+
+impl Default for HolePunchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled_peers: vec![],
+            disabled_networks: vec![],
+            min_retry_interval: None,
+        }
+    }
+}
+
+/// Batching of the votes published on the consensus channel into a single pubsub message,
+/// reducing the overhead per message with large validator sets, where hundreds of votes
+/// are published at each step. The batches are always split back into votes on receipt,
+/// but must only be enabled once all the nodes of the network understand them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoteBatchingConfig {
+    /// Batch the votes, each vote being published on its own if disabled
+    pub enabled: bool,
+
+    /// How long to wait for more votes after the first vote of a batch before publishing it
+    #[serde(with = "humantime_serde")]
+    pub window: Duration,
+
+    /// Maximum number of votes in a batch, published as soon as it is full
+    pub max_votes: usize,
+
+    /// Maximum size of a batch, within `max_message_sizes.votes`
+    pub max_size: ByteSize,
+}
+
+impl Default for VoteBatchingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window: Duration::from_millis(5),
+            max_votes: 128,
+            max_size: ByteSize::kib(64),
+        }
+    }
+}
+
+/// Range of IP addresses sharing the same first `prefix_len` bits, written eg. `10.0.0.0/8`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpSubnet {
+    pub addr: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl FromStr for IpSubnet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = s
+            .split_once('/')
+            .ok_or_else(|| format!("invalid subnet: {s}, expected eg. 10.0.0.0/8"))?;
+
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|e| format!("invalid subnet address: {addr}: {e}"))?;
+
+        let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len
+            .parse::<u8>()
+            .ok()
+            .filter(|prefix_len| *prefix_len <= max_prefix_len)
+            .ok_or_else(|| format!("invalid subnet prefix length: {prefix_len}"))?;
+
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl TryFrom<String> for IpSubnet {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<IpSubnet> for String {
+    fn from(subnet: IpSubnet) -> Self {
+        subnet.to_string()
+    }
+}
+
+impl fmt::Display for IpSubnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// WebSocket transport configuration options
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WebSocketConfig {
+    /// PEM file with the certificate chain to accept secure WebSocket (`/wss`) connections with.
+    /// Must be set along with `tls_key_file`.
+    #[serde(default)]
+    pub tls_cert_file: Option<PathBuf>,
+
+    /// PEM file with the private key of the TLS certificate
+    #[serde(default)]
+    pub tls_key_file: Option<PathBuf>,
+
+    /// PEM files with additional certificates trusted when dialing `/wss` peers,
+    /// eg. to dial peers using self-signed certificates
+    #[serde(default)]
+    pub trusted_certs: Vec<PathBuf>,
+
+    /// Addresses advertised to peers in addition to the listen address,
+    /// eg. the public `/wss` address of a TLS-terminating reverse proxy
+    #[serde(default)]
+    pub advertised_addrs: Vec<Multiaddr>,
+}
+
+/// Peer Discovery configuration options
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DiscoveryConfig {
     /// Enable peer discovery
     #[serde(default)]
@@ -95,6 +513,12 @@ pub struct DiscoveryConfig {
     #[serde(default)]
     pub selector: Selector,
 
+    /// Peers always kept as outbound peers, independently of the discovery process.
+    /// Unlike `p2p.persistent_peers`, they are never closed as ephemeral and are re-dialed
+    /// forever, with backoff, when lost. The addresses must end with `/p2p/<peer id>`.
+    #[serde(default)]
+    pub persistent_peers: Vec<Multiaddr>,
+
     /// Number of outbound peers
     #[serde(default = "discovery::default_num_outbound_peers")]
     pub num_outbound_peers: usize,
@@ -118,6 +542,40 @@ pub struct DiscoveryConfig {
     #[serde(with = "humantime_serde")]
     pub ephemeral_connection_timeout: Duration,
 
+    /// Maximum duration of the initial discovery process, after which the node
+    /// carries on with the peers found so far. No timeout if not set.
+    #[serde(default, with = "humantime_serde")]
+    pub bootstrap_timeout: Option<Duration>,
+
+    /// IP family whose addresses are dialed first, when a peer advertises both
+    #[serde(default)]
+    pub address_family_preference: AddressFamilyPreference,
+
+    /// Delay after which the addresses of the other IP family are dialed, if the peer
+    /// is not connected yet through the preferred ones (happy eyeballs).
+    /// All the addresses of a peer are dialed at once if not set.
+    #[serde(default, with = "humantime_serde")]
+    pub happy_eyeballs_delay: Option<Duration>,
+
+    /// Interval at which the load of the relays in use is refreshed, and the peers reached
+    /// only through an overloaded relay are dialed through a less loaded one.
+    /// Relays are never rebalanced if not set.
+    #[serde(default, with = "humantime_serde")]
+    pub relay_rebalance_interval: Option<Duration>,
+
+    /// Automatic election of the relay servers among the publicly reachable nodes,
+    /// and selection of the relays by the nodes behind a NAT. Disabled if not set.
+    #[serde(default)]
+    pub auto_relay: Option<AutoRelayConfig>,
+
+    /// Maximum number of outbound dials in progress at the same time
+    #[serde(default = "discovery::default_max_concurrent_dials")]
+    pub max_concurrent_dials: usize,
+
+    /// Maximum number of outbound dials started per second, unlimited if not set
+    #[serde(default)]
+    pub dials_per_second: Option<u32>,
+
     #[serde(default = "discovery::default_dial_max_retries")]
     pub dial_max_retries: usize,
 
@@ -126,6 +584,24 @@ pub struct DiscoveryConfig {
 
     #[serde(default = "discovery::default_connect_request_max_retries")]
     pub connect_request_max_retries: usize,
+
+    /// Exponential backoff policy for dial and request retries
+    #[serde(default)]
+    pub retry_backoff: RetryBackoffConfig,
+
+    /// Spreading of the re-dials of the persistent peers and bootstrap nodes
+    #[serde(default)]
+    pub reconnect: ReconnectConfig,
+
+    /// Policy deciding which of the addresses advertised by peers are dialed
+    #[serde(default)]
+    pub reachability: ReachabilityConfig,
+
+    /// Address book whose peers are dialed at startup to seed discovery, eg. exported by
+    /// an existing node with the `export_address_book` admin command. One address ending
+    /// with `/p2p/<peer id>` per line.
+    #[serde(default)]
+    pub address_book: Option<PathBuf>,
 }
 
 impl Default for DiscoveryConfig {
@@ -134,14 +610,134 @@ impl Default for DiscoveryConfig {
             enabled: false,
             bootstrap_protocol: Default::default(),
             selector: Default::default(),
+            persistent_peers: vec![],
             num_outbound_peers: discovery::default_num_outbound_peers(),
             num_inbound_peers: discovery::default_num_inbound_peers(),
             max_connections_per_ip: discovery::default_num_inbound_peers(),
             max_connections_per_peer: discovery::default_max_connections_per_peer(),
             ephemeral_connection_timeout: Duration::from_secs(60),
+            bootstrap_timeout: None,
+            address_family_preference: AddressFamilyPreference::default(),
+            happy_eyeballs_delay: None,
+            relay_rebalance_interval: None,
+            auto_relay: None,
+            max_concurrent_dials: discovery::default_max_concurrent_dials(),
+            dials_per_second: None,
             dial_max_retries: discovery::default_dial_max_retries(),
             request_max_retries: discovery::default_request_max_retries(),
             connect_request_max_retries: discovery::default_connect_request_max_retries(),
+            retry_backoff: RetryBackoffConfig::default(),
+            reconnect: ReconnectConfig::default(),
+            reachability: ReachabilityConfig::default(),
+            address_book: None,
+        }
+    }
+}
+
+/// Exponential backoff with jitter for discovery retries
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryBackoffConfig {
+    /// Delay before the first retry
+    #[serde(with = "humantime_serde")]
+    pub initial_delay: Duration,
+
+    /// Factor by which the delay grows after each retry
+    pub multiplier: f64,
+
+    /// Upper bound on the delay between two retries
+    #[serde(with = "humantime_serde")]
+    pub max_delay: Duration,
+
+    /// Fraction of the delay (between 0 and 1) by which it is randomly
+    /// shortened or lengthened, to avoid synchronized retries
+    pub jitter: f64,
+}
+
+impl Default for RetryBackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(60),
+            jitter: 0.5,
+        }
+    }
+}
+
+/// Spreading of the re-dials of the persistent peers and bootstrap nodes
+/// becoming dialable at the same time, eg. after a network-wide restart
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReconnectConfig {
+    /// Spread added per peer re-dialed together, each dial being delayed
+    /// by a random duration up to the total spread
+    #[serde(with = "humantime_serde")]
+    pub spread_per_peer: Duration,
+
+    /// Upper bound on the spread of the dials started together
+    #[serde(with = "humantime_serde")]
+    pub max_spread: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            spread_per_peer: Duration::from_millis(100),
+            max_spread: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Automatic relay election: publicly reachable nodes run a relay server
+/// and advertise it, nodes behind a NAT pick the least loaded advertised relays
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoRelayConfig {
+    /// Number of relays selected by a node behind a NAT
+    pub num_relays: usize,
+
+    /// Maximum number of circuits relayed by a publicly reachable node
+    pub max_circuits: u32,
+}
+
+impl Default for AutoRelayConfig {
+    fn default() -> Self {
+        Self {
+            num_relays: 2,
+            max_circuits: 128,
+        }
+    }
+}
+
+/// Reachability policy applied to the addresses advertised by peers
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReachabilityConfig {
+    /// Prefix length of the IPv4 subnets within which private addresses can reach each other
+    pub ipv4_subnet_prefix_len: u8,
+
+    /// Prefix length of the IPv6 subnets within which private addresses can reach each other
+    pub ipv6_subnet_prefix_len: u8,
+
+    /// Whether a node with a private address can reach public addresses
+    pub private_to_public: bool,
+
+    /// Whether a node with a public address can reach private addresses
+    pub public_to_private: bool,
+
+    /// Dial all the addresses advertised by peers without filtering them
+    pub trust_advertised_addrs: bool,
+}
+
+impl Default for ReachabilityConfig {
+    fn default() -> Self {
+        Self {
+            ipv4_subnet_prefix_len: 16,
+            ipv6_subnet_prefix_len: 48,
+            private_to_public: true,
+            public_to_private: false,
+            trust_advertised_addrs: false,
         }
     }
 }
@@ -159,6 +755,10 @@ mod discovery {
         5
     }
 
+    pub fn default_max_concurrent_dials() -> usize {
+        20
+    }
+
     pub fn default_dial_max_retries() -> usize {
         5
     }
@@ -172,6 +772,16 @@ mod discovery {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamilyPreference {
+    /// Dial the addresses in the order advertised by the peer
+    #[default]
+    Any,
+    Ipv6,
+    Ipv4,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum BootstrapProtocol {
@@ -209,6 +819,7 @@ pub enum Selector {
     #[default]
     Kademlia,
     Random,
+    Latency,
 }
 
 impl Selector {
@@ -216,6 +827,7 @@ impl Selector {
         match self {
             Self::Kademlia => "kademlia",
             Self::Random => "random",
+            Self::Latency => "latency",
         }
     }
 }
@@ -227,8 +839,9 @@ impl FromStr for Selector {
         match s {
             "kademlia" => Ok(Self::Kademlia),
             "random" => Ok(Self::Random),
+            "latency" => Ok(Self::Latency),
             e => Err(format!(
-                "unknown selector: {e}, available: kademlia, random"
+                "unknown selector: {e}, available: kademlia, random, latency"
             )),
         }
     }
@@ -239,13 +852,19 @@ pub enum TransportProtocol {
     #[default]
     Tcp,
     Quic,
+    Ws,
+    /// In-memory transport, only reaching the nodes of the same process, eg. for tests
+    Memory,
 }
 
 impl TransportProtocol {
+    /// Address of the given host and port, the host being ignored by the in-memory transport
     pub fn multiaddr(&self, host: &str, port: usize) -> Multiaddr {
         match self {
             Self::Tcp => format!("/ip4/{host}/tcp/{port}").parse().unwrap(),
             Self::Quic => format!("/ip4/{host}/udp/{port}/quic-v1").parse().unwrap(),
+            Self::Ws => format!("/ip4/{host}/tcp/{port}/ws").parse().unwrap(),
+            Self::Memory => format!("/memory/{port}").parse().unwrap(),
         }
     }
 }
@@ -257,8 +876,10 @@ impl FromStr for TransportProtocol {
         match s {
             "tcp" => Ok(Self::Tcp),
             "quic" => Ok(Self::Quic),
+            "ws" => Ok(Self::Ws),
+            "memory" => Ok(Self::Memory),
             e => Err(format!(
-                "unknown transport protocol: {e}, available: tcp, quic"
+                "unknown transport protocol: {e}, available: tcp, quic, ws, memory"
             )),
         }
     }
@@ -308,9 +929,40 @@ pub struct GossipSubConfig {
     /// regardless of mesh membership.
     enable_explicit_peering: bool,
 
+    /// Enable explicit peering for validators.
+    /// When enabled, peers that proved they are in the current validator set are added
+    /// as explicit peers in GossipSub, and removed again when they leave the validator set.
+    enable_explicit_validator_peering: bool,
+
     /// Enable flood publishing.
     /// When enabled the publisher sends the messages to all known peers, not just mesh peers.
     enable_flood_publish: bool,
+
+    /// Publish the votes to all known peers rather than to the mesh peers only,
+    /// saving a hop on the critical path of consensus at the cost of bandwidth.
+    /// Implied by `enable_flood_publish`, which applies to all the messages.
+    flood_publish_votes: bool,
+
+    /// Peer scoring parameters and thresholds, used if `enable_peer_scoring` is set
+    peer_score: PeerScoreConfig,
+
+    /// Only forward the received messages to the other peers once they were validated
+    /// by consensus. The peers sending invalid messages get a negative score.
+    validate_messages: bool,
+
+    /// Number of heartbeats the messages are kept in the message cache for,
+    /// to be served to the peers asking for them
+    history_length: usize,
+
+    /// Number of heartbeats of the message cache advertised to the non-mesh peers.
+    /// Must be at most `history_length`, set to 3 or `history_length` if lower otherwise.
+    history_gossip: usize,
+
+    /// How long the ids of the messages received are remembered, the messages received again
+    /// within that time being dropped as duplicates. Messages taking longer than that to
+    /// propagate through the mesh are delivered again.
+    #[serde(with = "humantime_serde")]
+    duplicate_cache_time: Duration,
 }
 
 impl Default for GossipSubConfig {
@@ -322,6 +974,8 @@ impl Default for GossipSubConfig {
 
 impl GossipSubConfig {
     /// Create a new, valid GossipSub configuration.
+    ///
+    /// The other settings are set with the `with_*` methods.
     pub fn new(
         mesh_n: usize,
         mesh_n_high: usize,
@@ -338,13 +992,26 @@ impl GossipSubConfig {
             mesh_outbound_min,
             enable_peer_scoring,
             enable_explicit_peering,
+            enable_explicit_validator_peering: false,
             enable_flood_publish,
+            flood_publish_votes: false,
+            peer_score: PeerScoreConfig::default(),
+            validate_messages: false,
+            history_length: 5,
+            history_gossip: 3,
+            duplicate_cache_time: Duration::from_secs(60),
         };
 
         result.adjust();
         result
     }
 
+    /// Preset for small networks, eg. of less than 20 validators, where the mesh spans
+    /// most of the network and the published messages are sent to all known peers.
+    pub fn small_network() -> Self {
+        Self::new(8, 16, 6, 3, false, false, true)
+    }
+
     /// Adjust the configuration values.
     pub fn adjust(&mut self) {
         use std::cmp::{max, min};
@@ -368,6 +1035,18 @@ impl GossipSubConfig {
             self.mesh_outbound_min = max(1, min(self.mesh_n / 2, self.mesh_n_low - 1));
         }
 
+        if self.history_length == 0 {
+            self.history_length = 5;
+        }
+
+        if self.history_gossip == 0 || self.history_gossip > self.history_length {
+            self.history_gossip = min(3, self.history_length);
+        }
+
+        if self.duplicate_cache_time.is_zero() {
+            self.duplicate_cache_time = Duration::from_secs(60);
+        }
+
         // Both flood_publish and explicit_peering can be enabled together.
         // flood_publish sends to all known peers on publish, explicit peering ensures
         // a node always sends and forwards messages to its explicit peers,
@@ -390,20 +1069,166 @@ impl GossipSubConfig {
         self.mesh_outbound_min
     }
 
+    /// Set whether the peers are scored
+    pub fn with_peer_scoring(mut self, enable_peer_scoring: bool) -> Self {
+        self.enable_peer_scoring = enable_peer_scoring;
+        self
+    }
+
     pub fn enable_peer_scoring(&self) -> bool {
         self.enable_peer_scoring
     }
 
+    /// Set whether the persistent peers are explicit peers
+    pub fn with_explicit_peering(mut self, enable_explicit_peering: bool) -> Self {
+        self.enable_explicit_peering = enable_explicit_peering;
+        self
+    }
+
     pub fn enable_explicit_peering(&self) -> bool {
         self.enable_explicit_peering
     }
 
+    /// Set whether the validator peers are explicit peers
+    pub fn with_explicit_validator_peering(
+        mut self,
+        enable_explicit_validator_peering: bool,
+    ) -> Self {
+        self.enable_explicit_validator_peering = enable_explicit_validator_peering;
+        self
+    }
+
+    pub fn enable_explicit_validator_peering(&self) -> bool {
+        self.enable_explicit_validator_peering
+    }
+
+    /// Set whether the messages are published to all known peers
+    pub fn with_flood_publish(mut self, enable_flood_publish: bool) -> Self {
+        self.enable_flood_publish = enable_flood_publish;
+        self
+    }
+
     pub fn enable_flood_publish(&self) -> bool {
         self.enable_flood_publish
     }
+
+    /// Set whether the votes are published to all known peers
+    pub fn with_flood_publish_votes(mut self, flood_publish_votes: bool) -> Self {
+        self.flood_publish_votes = flood_publish_votes;
+        self
+    }
+
+    pub fn flood_publish_votes(&self) -> bool {
+        self.flood_publish_votes
+    }
+
+    /// Set the peer scoring parameters and thresholds
+    pub fn with_peer_score(mut self, peer_score: PeerScoreConfig) -> Self {
+        self.peer_score = peer_score;
+        self
+    }
+
+    pub fn peer_score(&self) -> &PeerScoreConfig {
+        &self.peer_score
+    }
+
+    /// Set whether the received messages are validated before being forwarded
+    pub fn with_validate_messages(mut self, validate_messages: bool) -> Self {
+        self.validate_messages = validate_messages;
+        self
+    }
+
+    pub fn validate_messages(&self) -> bool {
+        self.validate_messages
+    }
+
+    /// Set the number of heartbeats the messages are kept in the message cache for,
+    /// and the number of them advertised to the non-mesh peers
+    pub fn with_history(mut self, history_length: usize, history_gossip: usize) -> Self {
+        self.history_length = history_length;
+        self.history_gossip = history_gossip;
+        self.adjust();
+        self
+    }
+
+    pub fn history_length(&self) -> usize {
+        self.history_length
+    }
+
+    pub fn history_gossip(&self) -> usize {
+        self.history_gossip
+    }
+
+    /// Set how long the ids of the messages received are remembered to drop the duplicates
+    pub fn with_duplicate_cache_time(mut self, duplicate_cache_time: Duration) -> Self {
+        self.duplicate_cache_time = duplicate_cache_time;
+        self.adjust();
+        self
+    }
+
+    pub fn duplicate_cache_time(&self) -> Duration {
+        self.duplicate_cache_time
+    }
+}
+
+/// GossipSub v1.1 peer scoring parameters and thresholds
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerScoreConfig {
+    /// Weight multiplier for the application-specific scores, based on the peer type
+    /// and on the scores reported by the application
+    pub app_specific_weight: f64,
+
+    /// Weight of the penalty for too many peers sharing the same IP, should be negative
+    pub ip_colocation_factor_weight: f64,
+
+    /// Number of peers sharing the same IP above which the colocation penalty applies
+    pub ip_colocation_factor_threshold: f64,
+
+    /// Weight of the penalty for misbehaviour in the protocol, eg. broken gossip promises.
+    /// Should be negative.
+    pub behaviour_penalty_weight: f64,
+
+    /// Decay of the behaviour penalty at each decay interval
+    pub behaviour_penalty_decay: f64,
+
+    /// Peers below this score do not receive gossip
+    pub gossip_threshold: f64,
+
+    /// Peers below this score are not published to
+    pub publish_threshold: f64,
+
+    /// Messages from peers below this score are ignored
+    pub graylist_threshold: f64,
+
+    /// Opportunistic grafting is triggered when the median score of the mesh is below this value
+    pub opportunistic_graft_threshold: f64,
+
+    /// Factor by which the scores reported by the application, eg. for peers delivering
+    /// invalid proposals or votes, are multiplied every second. Between 0 and 1.
+    pub reported_score_decay: f64,
+}
+
+impl Default for PeerScoreConfig {
+    fn default() -> Self {
+        Self {
+            app_specific_weight: 100.0,
+            ip_colocation_factor_weight: -5.0,
+            ip_colocation_factor_threshold: 10.0,
+            behaviour_penalty_weight: -10.0,
+            behaviour_penalty_decay: 0.2,
+            gossip_threshold: -500.0,
+            publish_threshold: -1000.0,
+            graylist_threshold: -2000.0,
+            opportunistic_graft_threshold: 100_000.0,
+            reported_score_decay: 0.99,
+        }
+    }
 }
 
 mod gossipsub {
+    use std::time::Duration;
+
     use super::utils::bool_from_anything;
 
     fn default_enable_peer_scoring() -> bool {
@@ -414,12 +1239,35 @@ mod gossipsub {
         false
     }
 
+    fn default_enable_explicit_validator_peering() -> bool {
+        false
+    }
+
     fn default_enable_flood_publish() -> bool {
         true
     }
 
+    fn default_validate_messages() -> bool {
+        false
+    }
+
+    fn default_flood_publish_votes() -> bool {
+        false
+    }
+
+    /// Defaults of the mesh parameters not set
+    #[derive(Copy, Clone, Debug, Default, serde::Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    enum Preset {
+        #[default]
+        Default,
+        SmallNetwork,
+    }
+
     #[derive(serde::Deserialize)]
     pub struct RawConfig {
+        #[serde(default)]
+        preset: Preset,
         #[serde(default)]
         mesh_n: usize,
         #[serde(default)]
@@ -438,24 +1286,66 @@ mod gossipsub {
             deserialize_with = "bool_from_anything"
         )]
         enable_explicit_peering: bool,
+        #[serde(
+            default = "default_enable_explicit_validator_peering",
+            deserialize_with = "bool_from_anything"
+        )]
+        enable_explicit_validator_peering: bool,
         #[serde(
             default = "default_enable_flood_publish",
             deserialize_with = "bool_from_anything"
         )]
         enable_flood_publish: bool,
+        #[serde(
+            default = "default_flood_publish_votes",
+            deserialize_with = "bool_from_anything"
+        )]
+        flood_publish_votes: bool,
+        #[serde(default)]
+        peer_score: super::PeerScoreConfig,
+        #[serde(
+            default = "default_validate_messages",
+            deserialize_with = "bool_from_anything"
+        )]
+        validate_messages: bool,
+        #[serde(default)]
+        history_length: usize,
+        #[serde(default)]
+        history_gossip: usize,
+        #[serde(default, with = "humantime_serde")]
+        duplicate_cache_time: Option<Duration>,
     }
 
     impl From<RawConfig> for super::GossipSubConfig {
         fn from(raw: RawConfig) -> Self {
+            let preset = match raw.preset {
+                Preset::Default => super::GossipSubConfig::default(),
+                Preset::SmallNetwork => super::GossipSubConfig::small_network(),
+            };
+
+            let or_preset = |value: usize, preset: usize| if value == 0 { preset } else { value };
+
             super::GossipSubConfig::new(
-                raw.mesh_n,
-                raw.mesh_n_high,
-                raw.mesh_n_low,
-                raw.mesh_outbound_min,
+                or_preset(raw.mesh_n, preset.mesh_n),
+                or_preset(raw.mesh_n_high, preset.mesh_n_high),
+                or_preset(raw.mesh_n_low, preset.mesh_n_low),
+                or_preset(raw.mesh_outbound_min, preset.mesh_outbound_min),
                 raw.enable_peer_scoring,
                 raw.enable_explicit_peering,
                 raw.enable_flood_publish,
             )
+            .with_explicit_validator_peering(raw.enable_explicit_validator_peering)
+            .with_flood_publish_votes(raw.flood_publish_votes)
+            .with_peer_score(raw.peer_score)
+            .with_validate_messages(raw.validate_messages)
+            .with_history(
+                or_preset(raw.history_length, preset.history_length),
+                or_preset(raw.history_gossip, preset.history_gossip),
+            )
+            .with_duplicate_cache_time(
+                raw.duplicate_cache_time
+                    .unwrap_or(preset.duplicate_cache_time),
+            )
         }
     }
 }
@@ -561,7 +1451,7 @@ pub struct MempoolConfig {
 }
 
 /// ValueSync configuration options
-#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ValueSyncConfig {
     /// Enable ValueSync
     pub enabled: bool,
@@ -580,9 +1470,78 @@ pub struct ValueSyncConfig {
     /// Maximum size of a response
     pub max_response_size: ByteSize,
 
+    /// Maximum size of a single value sent in a sync response.
+    /// Values larger than `max_response_size` or `p2p.rpc_max_size` are sent alone
+    /// in a response split into chunks. Disabled if zero.
+    #[serde(default = "default_max_chunked_value_size")]
+    pub max_chunked_value_size: ByteSize,
+
     /// Maximum number of parallel requests to send
     pub parallel_requests: usize,
 
+    /// Number of answered requests whose values can wait to be verified by consensus
+    /// without counting against `parallel_requests`
+    #[serde(default)]
+    pub verification_queue_size: usize,
+
+    /// Maximum estimated size of the values requested and not received yet.
+    /// No new requests are sent above it, until responses arrive. Unlimited if zero.
+    #[serde(default)]
+    pub max_in_flight_bytes: ByteSize,
+
+    /// Maximum number of requests from all the peers served at the same time.
+    /// Unlimited if zero.
+    #[serde(default)]
+    pub max_inbound_requests: usize,
+
+    /// Maximum number of requests from a single peer served at the same time.
+    /// Unlimited if zero.
+    #[serde(default)]
+    pub max_inbound_requests_per_peer: usize,
+
+    /// Maximum number of bytes of values served per second. Unlimited if zero.
+    #[serde(default)]
+    pub max_served_bytes_per_sec: ByteSize,
+
+    /// Sync only the commit certificates of the decided values, for nodes which
+    /// do not need to re-execute the values, eg. light verification nodes
+    #[serde(default)]
+    pub certificates_only: bool,
+
+    /// File the sync progress and the scores of the peers are saved to, so that they
+    /// are restored after a restart. Not saved if unset.
+    #[serde(default)]
+    pub checkpoint_file: Option<PathBuf>,
+
+    /// Restore the application state from a snapshot served by the peers, instead of
+    /// syncing every value, when more than `snapshot_min_lag` heights behind them
+    #[serde(default)]
+    pub snapshot_sync: bool,
+
+    /// Minimum number of heights between the tip and a snapshot for it to be restored
+    #[serde(default = "default_snapshot_min_lag")]
+    pub snapshot_min_lag: u64,
+
+    /// Minimum number of peers which must offer the same snapshot, down to its hash,
+    /// for it to be restored, so that a single peer cannot make the node restore a forged one
+    #[serde(default = "default_snapshot_min_peers")]
+    pub snapshot_min_peers: usize,
+
+    /// After restoring a snapshot, fetch the values below its height once caught up
+    /// with the peers, and hand them to the application to store
+    #[serde(default)]
+    pub backfill: bool,
+
+    /// Delay after the last part of a proposal of the current height is received before
+    /// requesting the parts still missing from the peers, never requested if not set
+    #[serde(default, with = "humantime_serde")]
+    pub proposal_parts_delay: Option<Duration>,
+
+    /// Request the votes of the round consensus is stuck in from the peers deciding
+    /// the same height, instead of waiting for the decided value to be synced
+    #[serde(default)]
+    pub vote_sync: bool,
+
     /// Scoring strategy for peers
     #[serde(default)]
     pub scoring_strategy: ScoringStrategy,
@@ -593,6 +1552,19 @@ pub struct ValueSyncConfig {
 
     /// Maximum number of decided values to request in a single batch
     pub batch_size: usize,
+
+    /// How long the requests sent to and received from each peer are kept for the state dump
+    #[serde(default = "default_audit_window", with = "humantime_serde")]
+    pub audit_window: Duration,
+
+    /// Advertise the minimum height this node still needs in its status messages,
+    /// so that the peers serving it can compute a low watermark for pruning
+    #[serde(default)]
+    pub advertise_min_needed_height: bool,
+
+    /// Bound on the messages received from the network waiting to be handled by sync
+    #[serde(default)]
+    pub mailbox: MailboxConfig,
 }
 
 impl Default for ValueSyncConfig {
@@ -603,14 +1575,76 @@ impl Default for ValueSyncConfig {
             request_timeout: Duration::from_secs(10),
             max_request_size: ByteSize::mib(1),
             max_response_size: ByteSize::mib(10),
+            max_chunked_value_size: default_max_chunked_value_size(),
             parallel_requests: 5,
+            verification_queue_size: 0,
+            max_in_flight_bytes: ByteSize::b(0),
+            max_inbound_requests: 0,
+            max_inbound_requests_per_peer: 0,
+            max_served_bytes_per_sec: ByteSize::b(0),
+            certificates_only: false,
+            checkpoint_file: None,
+            snapshot_sync: false,
+            snapshot_min_lag: default_snapshot_min_lag(),
+            snapshot_min_peers: default_snapshot_min_peers(),
+            backfill: false,
+            proposal_parts_delay: None,
+            vote_sync: false,
             scoring_strategy: ScoringStrategy::default(),
             inactive_threshold: Duration::from_secs(60),
             batch_size: 5,
+            audit_window: default_audit_window(),
+            advertise_min_needed_height: false,
+            mailbox: MailboxConfig::default(),
         }
     }
 }
 
+impl ValueSyncConfig {
+    /// Maximum size of a response split into chunks, `None` if chunking is disabled
+    pub fn max_chunked_value_size(&self) -> Option<usize> {
+        (self.max_chunked_value_size.as_u64() > 0)
+            .then(|| self.max_chunked_value_size.as_u64() as usize)
+    }
+
+    /// Maximum estimated size of the in-flight requests, `None` if unlimited
+    pub fn max_in_flight_bytes(&self) -> Option<usize> {
+        (self.max_in_flight_bytes.as_u64() > 0).then(|| self.max_in_flight_bytes.as_u64() as usize)
+    }
+
+    /// Maximum number of requests served at the same time, `None` if unlimited
+    pub fn max_inbound_requests(&self) -> Option<usize> {
+        (self.max_inbound_requests > 0).then_some(self.max_inbound_requests)
+    }
+
+    /// Maximum number of requests from a single peer served at the same time, `None` if unlimited
+    pub fn max_inbound_requests_per_peer(&self) -> Option<usize> {
+        (self.max_inbound_requests_per_peer > 0).then_some(self.max_inbound_requests_per_peer)
+    }
+
+    /// Maximum number of bytes served per second, `None` if unlimited
+    pub fn max_served_bytes_per_sec(&self) -> Option<usize> {
+        (self.max_served_bytes_per_sec.as_u64() > 0)
+            .then(|| self.max_served_bytes_per_sec.as_u64() as usize)
+    }
+}
+
+fn default_audit_window() -> Duration {
+    Duration::from_secs(10 * 60)
+}
+
+fn default_max_chunked_value_size() -> ByteSize {
+    ByteSize::mib(64)
+}
+
+fn default_snapshot_min_lag() -> u64 {
+    1000
+}
+
+fn default_snapshot_min_peers() -> usize {
+    2
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ScoringStrategy {
@@ -645,6 +1679,18 @@ fn default_queue_capacity() -> usize {
     10
 }
 
+fn default_max_votes_per_validator_per_round() -> usize {
+    4
+}
+
+fn default_verification_batch_size() -> usize {
+    64
+}
+
+fn default_max_value_size() -> ByteSize {
+    ByteSize::mib(64)
+}
+
 /// Consensus configuration options
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ConsensusConfig {
@@ -667,6 +1713,76 @@ pub struct ConsensusConfig {
     /// Default: 10
     #[serde(default = "default_queue_capacity")]
     pub queue_capacity: usize,
+
+    /// Maximum number of distinct votes accepted from a single validator in a round.
+    /// Votes beyond this limit are dropped, protecting consensus from a validator
+    /// flooding equivocating votes. Set to 0 to disable the limit.
+    /// Default: 4
+    #[serde(default = "default_max_votes_per_validator_per_round")]
+    pub max_votes_per_validator_per_round: usize,
+
+    /// Run this node as a read-only follower
+    ///
+    /// A follower takes part in consensus gossip, verifies and decides on values
+    /// and serves them over sync, but never signs nor proposes anything,
+    /// even if its address is part of the validator set.
+    /// No validator signing key is needed in this mode.
+    #[serde(default)]
+    pub follower: bool,
+
+    /// Development mode, for local networks of one or two validators
+    ///
+    /// A single validator decides with more than a third of the voting power
+    /// instead of the default quorum. Two validators keep the default quorum,
+    /// so both of them must be up to make progress, but their timeouts are capped
+    /// so that they resume deciding quickly once they are.
+    /// Consensus is then not fault tolerant: never enable this in production.
+    #[serde(default)]
+    pub dev_mode: bool,
+
+    /// Process consensus messages in a deterministic order, for reproducible devnet runs
+    ///
+    /// If set, the consensus messages received from the network are held and processed
+    /// at every tick of the given interval, ordered by height, round, message type and
+    /// sender rather than by arrival order. This adds up to one interval of latency.
+    #[serde(default, with = "humantime_serde")]
+    pub deterministic_ordering: Option<Duration>,
+
+    /// Include the proposer and the bitmap of the validators which signed the commit
+    /// certificate in the decided notification sent to the application, so that it can
+    /// build proofs of the decision without querying the consensus state of every height
+    #[serde(default)]
+    pub decision_proof: bool,
+
+    /// Number of workers verifying the signatures of the votes, proposals and certificates
+    /// received from the network, off the consensus actor
+    ///
+    /// The messages are still processed by consensus in the order in which they were received.
+    /// Worth enabling with large validator sets, where verifying every signature on the
+    /// consensus actor becomes the bottleneck. Set to 0 to verify them on the consensus actor.
+    /// Default: 0
+    #[serde(default)]
+    pub verification_workers: usize,
+
+    /// Maximum number of messages verified at once by a signature verification worker,
+    /// see `verification_workers`. Votes in a batch are verified together when the
+    /// signing scheme supports batch verification.
+    /// Default: 64
+    #[serde(default = "default_verification_batch_size")]
+    pub verification_batch_size: usize,
+
+    /// Maximum total size of the proposal parts of a value
+    ///
+    /// The parts of a stream going over this size are dropped before being reassembled,
+    /// the peer sending them is penalized, and a nil prevote is cast if it is the proposer.
+    /// Set to 0 to disable the limit.
+    /// Default: 64 MiB
+    #[serde(default = "default_max_value_size")]
+    pub max_value_size: ByteSize,
+
+    /// Bound on the messages received from the network waiting to be handled by consensus
+    #[serde(default)]
+    pub mailbox: MailboxConfig,
 }
 
 impl Default for ConsensusConfig {
@@ -676,6 +1792,15 @@ impl Default for ConsensusConfig {
             p2p: P2pConfig::default(),
             value_payload: ValuePayload::default(),
             queue_capacity: default_queue_capacity(),
+            max_votes_per_validator_per_round: default_max_votes_per_validator_per_round(),
+            follower: false,
+            dev_mode: false,
+            deterministic_ordering: None,
+            decision_proof: false,
+            verification_workers: 0,
+            verification_batch_size: default_verification_batch_size(),
+            max_value_size: default_max_value_size(),
+            mailbox: MailboxConfig::default(),
         }
     }
 }
@@ -724,6 +1849,213 @@ impl Default for MetricsConfig {
     }
 }
 
+/// Server exposing the status of the node and introspection endpoints over HTTP,
+/// see `rpc` in the channel-based API
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RpcConfig {
+    /// Enable the RPC server
+    pub enabled: bool,
+
+    /// Address at which to serve the RPC endpoints
+    pub listen_addr: SocketAddr,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        RpcConfig {
+            enabled: false,
+            listen_addr: SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 26657),
+        }
+    }
+}
+
+/// Local socket accepting admin commands, a Unix domain socket or a Windows named pipe,
+/// see `admin` in the channel-based API
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AdminConfig {
+    /// Enable the admin socket
+    pub enabled: bool,
+
+    /// Path of the Unix domain socket, or name of the named pipe on Windows
+    pub socket_path: PathBuf,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        let socket_path = if cfg!(windows) {
+            PathBuf::from(r"\\.\pipe\malachite-admin")
+        } else {
+            PathBuf::from("admin.sock")
+        };
+
+        AdminConfig {
+            enabled: false,
+            socket_path,
+        }
+    }
+}
+
+/// Audit log of the decisions of the node, one JSON line per decided height,
+/// independent of the application and of the WAL, see `decision_log` in the channel-based API
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecisionLogConfig {
+    /// Append the decisions to the log
+    pub enabled: bool,
+
+    /// Path of the log, the rotated files having the same path suffixed with `.1`, `.2`, ...
+    pub path: PathBuf,
+
+    /// Size above which the log is rotated
+    pub max_file_size: ByteSize,
+
+    /// Number of rotated files kept besides the current one, the oldest ones being deleted
+    pub max_files: usize,
+}
+
+impl Default for DecisionLogConfig {
+    fn default() -> Self {
+        DecisionLogConfig {
+            enabled: false,
+            path: PathBuf::from("decisions.jsonl"),
+            max_file_size: ByteSize::mib(100),
+            max_files: 5,
+        }
+    }
+}
+
+/// Export of the traces of the node to an OpenTelemetry collector, eg. Jaeger
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Export the traces of the node over OTLP
+    pub enabled: bool,
+
+    /// Endpoint of the OTLP/HTTP collector the traces are exported to
+    pub otlp_endpoint: String,
+
+    /// Name of the service the traces are reported under, the moniker of the node if empty
+    pub service_name: String,
+
+    /// Fraction of the traces started by the node which are exported, between 0 and 1.
+    /// The traces started by a peer are exported if the peer exported them.
+    pub sample_ratio: f64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            enabled: false,
+            otlp_endpoint: "http://127.0.0.1:4318/v1/traces".to_string(),
+            service_name: String::new(),
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+/// Thresholds of the health checks of the node, see `HealthStatus` in the channel-based API
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthConfig {
+    /// Interval at which the health of the node is checked
+    #[serde(with = "humantime_serde")]
+    pub check_interval: Duration,
+
+    /// Maximum time consensus may stay at the same height before the node is reported
+    /// as not live, which should be well above the expected time to decide on a value
+    #[serde(with = "humantime_serde")]
+    pub max_height_duration: Duration,
+
+    /// Maximum number of heights the node may be behind the highest tip advertised
+    /// by its peers before it is reported as not ready
+    pub max_sync_lag: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(5),
+            max_height_duration: Duration::from_secs(60),
+            max_sync_lag: 10,
+        }
+    }
+}
+
+/// Monitoring of the offset of the local clock to NTP servers, a skewed clock silently
+/// degrading the performance of the rounds, see `SystemClock` in the engine
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// Monitor the offset of the local clock
+    pub enabled: bool,
+
+    /// NTP servers queried for the offset, as `host:port`
+    pub ntp_servers: Vec<String>,
+
+    /// Interval at which the NTP servers are queried
+    #[serde(with = "humantime_serde")]
+    pub check_interval: Duration,
+
+    /// Maximum offset of the local clock to the NTP servers, in either direction,
+    /// before the node is reported as not ready
+    #[serde(with = "humantime_serde")]
+    pub max_skew: Duration,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ntp_servers: vec!["pool.ntp.org:123".to_string()],
+            check_interval: Duration::from_secs(60),
+            max_skew: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Bound on the messages received from the network waiting in the mailbox of an actor
+/// of the engine, see `Mailbox` in the engine
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MailboxConfig {
+    /// Number of messages received from the network waiting to be handled by the actor
+    /// before the actor is overloaded
+    pub capacity: usize,
+
+    /// What happens to the messages received from the network while the actor is overloaded
+    pub overload: OverloadPolicy,
+}
+
+impl MailboxConfig {
+    /// A mailbox of the given capacity, shedding messages once overloaded
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            overload: OverloadPolicy::Shed,
+        }
+    }
+}
+
+impl Default for MailboxConfig {
+    fn default() -> Self {
+        Self::new(4096)
+    }
+}
+
+/// What happens to the messages received from the network while an actor is overloaded
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverloadPolicy {
+    /// Shed the low-priority messages, eg. stale votes and rebroadcast gossip, once the
+    /// mailbox is half full, and all the messages received from the network once it is full.
+    /// The changes of the connectivity of the node are never shed.
+    #[default]
+    Shed,
+
+    /// Never shed any message, the mailbox growing without bound, only its saturation
+    /// being measured
+    Unbounded,
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "flavor", rename_all = "snake_case")]
 pub enum RuntimeConfig {
@@ -918,6 +2250,7 @@ mod tests {
             protocol_names.validator_proof,
             "/malachitebft-validator-proof/v1"
         );
+        assert_eq!(protocol_names.peer_message, "/malachitebft-peer-message/v1");
     }
 
     #[test]
@@ -931,6 +2264,8 @@ mod tests {
             discovery_regres: "/custom-discovery/reqres/v1".to_string(),
             sync: "/custom-sync/v1".to_string(),
             validator_proof: "/custom-validator-proof/v1".to_string(),
+            peer_message: "/custom-peer-message/v1".to_string(),
+            node_key: "/custom-node-key/v1".to_string(),
         };
 
         let json = serde_json::to_string(&protocol_names).unwrap();
@@ -954,6 +2289,8 @@ mod tests {
             discovery_regres: "/test-network/discovery/reqres/v1".to_string(),
             sync: "/test-network/sync/v1".to_string(),
             validator_proof: "/test-network/validator-proof/v1".to_string(),
+            peer_message: "/test-network/peer-message/v1".to_string(),
+            node_key: "/test-network/node-key/v1".to_string(),
         };
 
         let config_with_custom = P2pConfig {
@@ -1012,6 +2349,11 @@ mod tests {
             config.p2p.protocol_names.validator_proof,
             "/custom-network/validator-proof/v2"
         );
+        // Missing from older configuration files
+        assert_eq!(
+            config.p2p.protocol_names.peer_message,
+            "/malachitebft-peer-message/v1"
+        );
     }
 
     #[test]
@@ -1042,6 +2384,86 @@ mod tests {
         assert_eq!(config.p2p.protocol_names, ProtocolNames::default());
     }
 
+    #[test]
+    fn max_message_sizes_toml() {
+        let toml_content = r#"
+        timeout_propose = "3s"
+        timeout_propose_delta = "500ms"
+        timeout_prevote = "1s"
+        timeout_prevote_delta = "500ms"
+        timeout_precommit = "1s"
+        timeout_precommit_delta = "500ms"
+        timeout_rebroadcast = "5s"
+        value_payload = "parts-only"
+
+        [p2p]
+        listen_addr = "/ip4/0.0.0.0/tcp/0"
+        persistent_peers = []
+        pubsub_max_size = "4 MiB"
+        rpc_max_size = "10 MiB"
+
+        [p2p.max_message_sizes]
+        votes = "4 KiB"
+        sync_requests = "1 KiB"
+
+        [p2p.protocol]
+        type = "gossipsub"
+        "#;
+
+        let config: ConsensusConfig = toml::from_str(toml_content).unwrap();
+        assert_eq!(
+            config.p2p.max_message_sizes,
+            MaxMessageSizesConfig {
+                votes: Some(ByteSize::kib(4)),
+                sync_requests: Some(ByteSize::kib(1)),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn hole_punch_toml() {
+        let toml_content = r#"
+        timeout_propose = "3s"
+        timeout_propose_delta = "500ms"
+        timeout_prevote = "1s"
+        timeout_prevote_delta = "500ms"
+        timeout_precommit = "1s"
+        timeout_precommit_delta = "500ms"
+        timeout_rebroadcast = "5s"
+        value_payload = "parts-only"
+
+        [p2p]
+        listen_addr = "/ip4/0.0.0.0/tcp/0"
+        persistent_peers = []
+        pubsub_max_size = "4 MiB"
+        rpc_max_size = "10 MiB"
+
+        [p2p.hole_punch]
+        disabled_networks = ["10.0.0.0/8", "fd00::/8"]
+        min_retry_interval = "1m"
+
+        [p2p.protocol]
+        type = "gossipsub"
+        "#;
+
+        let config: ConsensusConfig = toml::from_str(toml_content).unwrap();
+        let hole_punch = &config.p2p.hole_punch;
+
+        assert!(hole_punch.enabled);
+        assert_eq!(
+            hole_punch.disabled_networks,
+            vec![
+                "10.0.0.0/8".parse::<IpSubnet>().unwrap(),
+                "fd00::/8".parse::<IpSubnet>().unwrap()
+            ]
+        );
+        assert_eq!(hole_punch.min_retry_interval, Some(Duration::from_secs(60)));
+
+        assert!("10.0.0.0/33".parse::<IpSubnet>().is_err());
+        assert!("10.0.0.0".parse::<IpSubnet>().is_err());
+    }
+
     #[test]
     fn p2p_config_persistent_peers_only_default() {
         let config = P2pConfig::default();
@@ -1081,12 +2503,307 @@ mod tests {
         );
     }
 
+    #[test]
+    fn p2p_config_connection_limits_toml() {
+        let toml_content = r#"
+        timeout_propose = "3s"
+        timeout_propose_delta = "500ms"
+        timeout_prevote = "1s"
+        timeout_prevote_delta = "500ms"
+        timeout_precommit = "1s"
+        timeout_precommit_delta = "500ms"
+        timeout_rebroadcast = "5s"
+        value_payload = "parts-only"
+
+        [p2p]
+        listen_addr = "/ip4/0.0.0.0/tcp/0"
+        persistent_peers = []
+        pubsub_max_size = "4 MiB"
+        rpc_max_size = "10 MiB"
+
+        [p2p.connection_limits]
+        max_established_incoming = 100
+        max_pending_outgoing = 16
+
+        [p2p.protocol]
+        type = "gossipsub"
+        "#;
+
+        let config: ConsensusConfig = toml::from_str(toml_content).unwrap();
+        assert_eq!(
+            config.p2p.connection_limits,
+            ConnectionLimitsConfig {
+                max_established_incoming: Some(100),
+                max_pending_outgoing: Some(16),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn p2p_config_explicit_peers_toml() {
+        let peer_id: PeerId = "12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA"
+            .parse()
+            .unwrap();
+
+        let toml_content = format!(
+            r#"
+            timeout_propose = "3s"
+            timeout_propose_delta = "500ms"
+            timeout_prevote = "1s"
+            timeout_prevote_delta = "500ms"
+            timeout_precommit = "1s"
+            timeout_precommit_delta = "500ms"
+            timeout_rebroadcast = "5s"
+            value_payload = "parts-only"
+
+            [p2p]
+            listen_addr = "/ip4/0.0.0.0/tcp/0"
+            persistent_peers = []
+            explicit_peers = ["{peer_id}"]
+            pubsub_max_size = "4 MiB"
+            rpc_max_size = "10 MiB"
+
+            [p2p.protocol]
+            type = "gossipsub"
+            "#
+        );
+
+        let config: ConsensusConfig = toml::from_str(&toml_content).unwrap();
+        assert_eq!(config.p2p.explicit_peers, vec![peer_id]);
+    }
+
+    #[test]
+    fn gossipsub_validate_messages_deserialization() {
+        let toml_content = r#"
+            timeout_propose = "3s"
+            timeout_propose_delta = "500ms"
+            timeout_prevote = "1s"
+            timeout_prevote_delta = "500ms"
+            timeout_precommit = "1s"
+            timeout_precommit_delta = "500ms"
+            timeout_rebroadcast = "5s"
+            value_payload = "parts-only"
+            [p2p]
+            listen_addr = "/ip4/0.0.0.0/tcp/0"
+            persistent_peers = []
+            pubsub_max_size = "4 MiB"
+            rpc_max_size = "10 MiB"
+            [p2p.protocol]
+            type = "gossipsub"
+            validate_messages = true
+        "#;
+
+        let config: ConsensusConfig = toml::from_str(toml_content).unwrap();
+        let PubSubProtocol::GossipSub(gossipsub) = config.p2p.protocol else {
+            panic!("Expected GossipSub protocol");
+        };
+        assert!(gossipsub.validate_messages());
+        assert!(!GossipSubConfig::default().validate_messages());
+    }
+
+    #[test]
+    fn gossipsub_message_cache_deserialization() {
+        let toml_content = r#"
+            timeout_propose = "3s"
+            timeout_propose_delta = "500ms"
+            timeout_prevote = "1s"
+            timeout_prevote_delta = "500ms"
+            timeout_precommit = "1s"
+            timeout_precommit_delta = "500ms"
+            timeout_rebroadcast = "5s"
+            value_payload = "parts-only"
+            [p2p]
+            listen_addr = "/ip4/0.0.0.0/tcp/0"
+            persistent_peers = []
+            pubsub_max_size = "4 MiB"
+            rpc_max_size = "10 MiB"
+            [p2p.protocol]
+            type = "gossipsub"
+            history_length = 2
+            duplicate_cache_time = "2m"
+        "#;
+
+        let config: ConsensusConfig = toml::from_str(toml_content).unwrap();
+        let PubSubProtocol::GossipSub(gossipsub) = config.p2p.protocol else {
+            panic!("Expected GossipSub protocol");
+        };
+        assert_eq!(gossipsub.history_length(), 2);
+        assert_eq!(gossipsub.history_gossip(), 2);
+        assert_eq!(gossipsub.duplicate_cache_time(), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn mailbox_config_deserialization() {
+        let config: MailboxConfig = toml::from_str("capacity = 128").unwrap();
+        assert_eq!(config, MailboxConfig::new(128));
+
+        let config: MailboxConfig = toml::from_str(r#"overload = "unbounded""#).unwrap();
+        assert_eq!(config.capacity, MailboxConfig::default().capacity);
+        assert_eq!(config.overload, OverloadPolicy::Unbounded);
+    }
+
+    #[test]
+    fn vote_batching_deserialization() {
+        let config: VoteBatchingConfig = toml::from_str(
+            r#"
+            enabled = true
+            window = "10ms"
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.enabled);
+        assert_eq!(config.window, Duration::from_millis(10));
+        assert_eq!(config.max_votes, VoteBatchingConfig::default().max_votes);
+    }
+
+    #[test]
+    fn liveness_deserialization() {
+        let config: LivenessConfig = toml::from_str(
+            r#"
+            max_ping_failures = 3
+            max_ping_rtt = "2s"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.max_ping_failures, Some(3));
+        assert_eq!(config.max_ping_rtt, Some(Duration::from_secs(2)));
+        assert_eq!(
+            toml::from_str::<LivenessConfig>("").unwrap(),
+            LivenessConfig::default()
+        );
+    }
+
+    #[test]
+    fn value_sync_max_in_flight_bytes() {
+        let config = ValueSyncConfig::default();
+        assert_eq!(config.max_in_flight_bytes(), None);
+
+        let config = ValueSyncConfig {
+            max_in_flight_bytes: ByteSize::mib(4),
+            ..Default::default()
+        };
+        assert_eq!(config.max_in_flight_bytes(), Some(4 * 1024 * 1024));
+    }
+
+    #[test]
+    fn value_sync_max_chunked_value_size() {
+        let config = ValueSyncConfig::default();
+        assert_eq!(config.max_chunked_value_size(), Some(64 * 1024 * 1024));
+
+        let config = ValueSyncConfig {
+            max_chunked_value_size: ByteSize::b(0),
+            ..Default::default()
+        };
+        assert_eq!(config.max_chunked_value_size(), None);
+    }
+
+    #[test]
+    fn value_sync_serve_limits() {
+        let config = ValueSyncConfig::default();
+        assert_eq!(config.max_inbound_requests(), None);
+        assert_eq!(config.max_inbound_requests_per_peer(), None);
+        assert_eq!(config.max_served_bytes_per_sec(), None);
+
+        let config = ValueSyncConfig {
+            max_inbound_requests: 10,
+            max_inbound_requests_per_peer: 2,
+            max_served_bytes_per_sec: ByteSize::mib(50),
+            ..Default::default()
+        };
+        assert_eq!(config.max_inbound_requests(), Some(10));
+        assert_eq!(config.max_inbound_requests_per_peer(), Some(2));
+        assert_eq!(config.max_served_bytes_per_sec(), Some(50 * 1024 * 1024));
+    }
+
+    #[test]
+    fn security_protocol_deserialization() {
+        let toml_content = r#"
+            timeout_propose = "3s"
+            timeout_propose_delta = "500ms"
+            timeout_prevote = "1s"
+            timeout_prevote_delta = "500ms"
+            timeout_precommit = "1s"
+            timeout_precommit_delta = "500ms"
+            timeout_rebroadcast = "5s"
+            value_payload = "parts-only"
+            [p2p]
+            listen_addr = "/ip4/0.0.0.0/tcp/0"
+            persistent_peers = []
+            pubsub_max_size = "4 MiB"
+            rpc_max_size = "10 MiB"
+            security = "noise-and-tls"
+            [p2p.protocol]
+            type = "broadcast"
+        "#;
+
+        let config: ConsensusConfig = toml::from_str(toml_content).unwrap();
+        assert_eq!(config.p2p.security, SecurityProtocol::NoiseAndTls);
+        assert_eq!(P2pConfig::default().security, SecurityProtocol::Noise);
+        assert_eq!(SecurityProtocol::from_str("tls"), Ok(SecurityProtocol::Tls));
+        assert!(SecurityProtocol::from_str("ssl").is_err());
+    }
+
+    #[test]
+    fn gossipsub_small_network_preset_deserialization() {
+        let toml_content = r#"
+            timeout_propose = "3s"
+            timeout_propose_delta = "500ms"
+            timeout_prevote = "1s"
+            timeout_prevote_delta = "500ms"
+            timeout_precommit = "1s"
+            timeout_precommit_delta = "500ms"
+            timeout_rebroadcast = "5s"
+            value_payload = "parts-only"
+            [p2p]
+            listen_addr = "/ip4/0.0.0.0/tcp/0"
+            persistent_peers = []
+            pubsub_max_size = "4 MiB"
+            rpc_max_size = "10 MiB"
+            [p2p.protocol]
+            type = "gossipsub"
+            preset = "small-network"
+            mesh_n_high = 20
+            enable_flood_publish = false
+            flood_publish_votes = true
+        "#;
+
+        let config: ConsensusConfig = toml::from_str(toml_content).unwrap();
+        let PubSubProtocol::GossipSub(gossipsub) = config.p2p.protocol else {
+            panic!("Expected GossipSub protocol");
+        };
+
+        let preset = GossipSubConfig::small_network();
+        assert_eq!(gossipsub.mesh_n(), preset.mesh_n());
+        assert_eq!(gossipsub.mesh_n_low(), preset.mesh_n_low());
+        assert_eq!(gossipsub.mesh_outbound_min(), preset.mesh_outbound_min());
+        assert_eq!(gossipsub.mesh_n_high(), 20);
+        assert!(!gossipsub.enable_flood_publish());
+        assert!(gossipsub.flood_publish_votes());
+        assert!(!GossipSubConfig::default().flood_publish_votes());
+    }
+
     #[test]
     fn gossipsub_config_default_disables_peer_scoring() {
         let config = GossipSubConfig::default();
         assert!(!config.enable_peer_scoring());
     }
 
+    #[test]
+    fn gossipsub_config_setters() {
+        let config = GossipSubConfig::new(6, 12, 4, 2, false, false, true)
+            .with_peer_scoring(true)
+            .with_explicit_validator_peering(true)
+            .with_flood_publish(false);
+
+        assert!(config.enable_peer_scoring());
+        assert!(!config.enable_explicit_peering());
+        assert!(config.enable_explicit_validator_peering());
+        assert!(!config.enable_flood_publish());
+    }
+
     #[test]
     fn gossipsub_enable_peer_scoring_deserialization() {
         struct TestCase {
@@ -153,12 +153,14 @@ where
     /// the value that was decided on, the height and round at which it was decided,
     /// and the aggregated signatures of the validators that committed to it.
     ///
-    /// In addition, it includes the vote extensions that were received for this height.
+    /// In addition, it includes the vote extensions that were received for this height,
+    /// and the data needed to build a proof of the decision.
     ///
     /// Resume with: [`resume::Continue`]
     Decide(
         CommitCertificate<Ctx>,
         VoteExtensions<Ctx>,
+        DecisionProof<Ctx>,
         resume::Continue,
     ),
 
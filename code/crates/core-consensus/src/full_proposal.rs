@@ -3,7 +3,8 @@ use tracing::{error, warn};
 
 use derive_where::derive_where;
 
-use malachitebft_core_types::{Context, Proposal, Round, SignedProposal, Validity, Value, ValueId};
+use malachitebft_core_driver::StoredProposal;
+use malachitebft_core_types::{Context, Proposal, Round, Validity, Value, ValueId};
 
 use crate::ProposedValue;
 
@@ -14,15 +15,15 @@ pub struct FullProposal<Ctx: Context> {
     pub builder_value: Ctx::Value,
     /// Validity of the proposal
     pub validity: Validity,
-    /// Proposal consensus message
-    pub proposal: SignedProposal<Ctx>,
+    /// Proposal consensus message, unsigned in parts-only mode
+    pub proposal: StoredProposal<Ctx>,
 }
 
 impl<Ctx: Context> FullProposal<Ctx> {
     pub fn new(
         builder_value: Ctx::Value,
         validity: Validity,
-        proposal: SignedProposal<Ctx>,
+        proposal: StoredProposal<Ctx>,
     ) -> Self {
         Self {
             builder_value,
@@ -39,7 +40,7 @@ pub enum Entry<Ctx: Context> {
     Full(FullProposal<Ctx>),
 
     /// Only the proposal has been received.
-    ProposalOnly(SignedProposal<Ctx>),
+    ProposalOnly(StoredProposal<Ctx>),
 
     /// Only the value has been received.
     ValueOnly(Ctx::Value, Validity),
@@ -52,7 +53,7 @@ pub enum Entry<Ctx: Context> {
 }
 
 impl<Ctx: Context> Entry<Ctx> {
-    fn full(value: Ctx::Value, validity: Validity, proposal: SignedProposal<Ctx>) -> Self {
+    fn full(value: Ctx::Value, validity: Validity, proposal: StoredProposal<Ctx>) -> Self {
         Entry::Full(FullProposal::new(value, validity, proposal))
     }
 }
@@ -89,7 +90,7 @@ impl<Ctx: Context> Default for Entry<Ctx> {
 /// multiple complete proposals may form.
 ///
 /// Note: For `parts_only` mode there is no explicit proposal wire message, instead
-/// one is synthesized by the caller (`on_proposed_value` handler) before it invokes the `store_proposal` method.
+/// an unsigned one is synthesized by the caller (`on_proposed_value` handler) before it invokes the `store_proposal` method.
 #[derive_where(Clone, Debug, Default)]
 pub struct FullProposalKeeper<Ctx: Context> {
     keeper: BTreeMap<(Ctx::Height, Round), Vec<Entry<Ctx>>>,
@@ -117,7 +118,7 @@ impl<Ctx: Context> FullProposalKeeper<Ctx> {
     pub fn proposals_for_value(
         &self,
         proposed_value: &ProposedValue<Ctx>,
-    ) -> Vec<SignedProposal<Ctx>> {
+    ) -> Vec<StoredProposal<Ctx>> {
         let mut results = vec![];
 
         let first_key = &(proposed_value.height, proposed_value.round);
@@ -219,7 +220,7 @@ impl<Ctx: Context> FullProposalKeeper<Ctx> {
     // Determines a new entry for L28 vs L22, L36, L49.
     // Called when a proposal is received, only if an entry for new_proposal's round and/ or value
     // is not found.
-    fn new_entry(&self, new_proposal: SignedProposal<Ctx>) -> Entry<Ctx> {
+    fn new_entry(&self, new_proposal: StoredProposal<Ctx>) -> Entry<Ctx> {
         // L22, L36, L49
         if new_proposal.pol_round().is_nil() {
             return Entry::ProposalOnly(new_proposal);
@@ -241,7 +242,7 @@ impl<Ctx: Context> FullProposalKeeper<Ctx> {
         }
     }
 
-    pub fn store_proposal(&mut self, new_proposal: SignedProposal<Ctx>) {
+    pub fn store_proposal(&mut self, new_proposal: StoredProposal<Ctx>) {
         let key = (new_proposal.height(), new_proposal.round());
 
         match self.keeper.get_mut(&key) {
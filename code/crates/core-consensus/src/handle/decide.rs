@@ -64,6 +64,10 @@ where
         }
 
         metrics.block_end();
+        metrics.height_decided(
+            consensus_round.as_u32().unwrap_or(0) + 1,
+            state.is_proposer(),
+        );
         metrics
             .consensus_round
             .observe(consensus_round.as_i64() as f64);
@@ -72,9 +76,17 @@ where
             .observe(proposal_round.as_i64() as f64);
     }
 
+    let proposer = state.get_proposer(height, proposal_round).clone();
+    let proof = DecisionProof::new(&certificate, state.validator_set(), proposer);
+
     perform!(
         co,
-        Effect::Decide(certificate.clone(), extensions.clone(), Default::default())
+        Effect::Decide(
+            certificate.clone(),
+            extensions.clone(),
+            proof,
+            Default::default()
+        )
     );
 
     let Some(target_time) = state.target_time else {
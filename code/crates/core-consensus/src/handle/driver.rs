@@ -58,12 +58,12 @@ where
                 }
             }
 
-            let role = if state.address() == proposer {
+            let role = if !state.is_active_validator() {
+                Role::None
+            } else if state.address() == proposer {
                 Role::Proposer
-            } else if state.is_active_validator() {
-                Role::Validator
             } else {
-                Role::None
+                Role::Validator
             };
 
             info!(%height, %round, %proposer, ?role, "Starting new round");
@@ -101,6 +101,18 @@ where
             }
         }
 
+        DriverInput::ImplicitProposal(proposal, _validity) => {
+            if proposal.height() != state.driver.height() {
+                warn!(
+                    "Received implicit proposal for wrong height {}, current height: {}",
+                    proposal.height(),
+                    state.driver.height()
+                );
+
+                return Ok(());
+            }
+        }
+
         DriverInput::Vote(ref vote) => {
             if vote.height() != state.driver.height() {
                 warn!(
@@ -190,7 +202,7 @@ where
         #[cfg(feature = "metrics")]
         {
             metrics.step_end(prev_step);
-            metrics.step_start(new_step);
+            metrics.step_start(new_step, state.is_proposer());
         }
     }
 
@@ -335,27 +347,31 @@ where
                 state.driver.prune_votes_and_certificates(vote.round());
 
                 if state.driver.round() >= HIDDEN_LOCK_ROUND && state.is_active_validator() {
-                    if let Some((signed_proposal, Validity::Valid)) = state
+                    if let Some((proposal, Validity::Valid)) = state
                         .driver
                         .proposal_and_validity_for_round_and_value(vote.round(), value_id.clone())
                     {
                         perform!(
                             co,
                             Effect::RestreamProposal(
-                                signed_proposal.height(),
-                                signed_proposal.round(),
-                                signed_proposal.pol_round(),
-                                signed_proposal.validator_address().clone(),
-                                signed_proposal.value().id(),
+                                proposal.height(),
+                                proposal.round(),
+                                proposal.pol_round(),
+                                proposal.validator_address().clone(),
+                                proposal.value().id(),
                                 Default::default()
                             )
                         );
 
-                        if state.params.value_payload.include_proposal() {
+                        // Implicit proposals are unsigned and never published
+                        if let Some(signed_proposal) = proposal
+                            .signed()
+                            .filter(|_| state.params.value_payload.include_proposal())
+                        {
                             perform!(
                                 co,
                                 Effect::PublishConsensusMsg(
-                                    SignedConsensusMsg::Proposal(signed_proposal.clone()),
+                                    SignedConsensusMsg::Proposal(signed_proposal),
                                     Default::default()
                                 )
                             );
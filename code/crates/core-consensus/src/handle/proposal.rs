@@ -99,7 +99,7 @@ where
     );
 
     // Store the proposal in the full proposal keeper
-    state.store_proposal(signed_proposal.clone());
+    state.store_proposal(signed_proposal.clone().into());
 
     // If consensus runs in a mode where it publishes proposals over the network,
     // we need to persist in the Write-Ahead Log before we actually send it over the network.
@@ -138,7 +138,10 @@ where
             co,
             state,
             metrics,
-            DriverInput::Proposal(full_proposal.proposal.clone(), full_proposal.validity),
+            DriverInput::from_stored_proposal(
+                full_proposal.proposal.clone(),
+                full_proposal.validity,
+            ),
         )
         .await?;
     } else {
@@ -3,7 +3,6 @@ use crate::prelude::*;
 use crate::handle::driver::apply_driver_input;
 use crate::types::{ProposedValue, WalEntry};
 
-use super::signature::sign_proposal;
 use super::sync::maybe_sync_decision;
 
 /// Handles a proposed value that is not originated from the sync protocol.
@@ -11,7 +10,7 @@ use super::sync::maybe_sync_decision;
 /// This method looks for a matching (valid and signed) Proposal message to produce a
 /// Proposal driver's input, and applies it to the driver.
 ///
-/// For parts-only mode, generates and signs an internal Proposal message.
+/// For parts-only mode, generates an internal, unsigned Proposal message.
 /// Stores the value and applies any associated proposals to the driver.
 async fn process_proposal<Ctx>(
     co: &Co<Ctx>,
@@ -23,7 +22,9 @@ async fn process_proposal<Ctx>(
 where
     Ctx: Context,
 {
-    // For parts-only mode, we need to generate an internal Proposal message
+    // For parts-only mode, we need to generate an internal Proposal message.
+    // It is never published, so there is no need to sign it, which
+    // lets nodes without a signing key follow a parts-only network.
     if state.params.value_payload.parts_only() {
         let proposal = Ctx::new_proposal(
             &state.ctx,
@@ -34,21 +35,17 @@ where
             proposed_value.proposer.clone(),
         );
 
-        // TODO: Keep unsigned proposals in keeper.
-        // For now we keep all happy by signing all "implicit" proposals with this node's key
-        let signed_proposal = sign_proposal(co, proposal).await?;
-
-        state.store_proposal(signed_proposal);
+        state.store_proposal(StoredProposal::implicit(proposal));
     }
 
     // Get all proposals we have for this value.
     let proposals = state.proposals_for_value(&proposed_value);
 
     // Apply all proposals we have for this value, with the stored validity.
-    for signed_proposal in proposals {
+    for proposal in proposals {
         debug!(
-            proposal.height = %signed_proposal.height(),
-            proposal.round = %signed_proposal.round(),
+            proposal.height = %proposal.height(),
+            proposal.round = %proposal.round(),
             validity = ?validity,
             "We have a full proposal for this round, checking..."
         );
@@ -57,7 +54,7 @@ where
             co,
             state,
             metrics,
-            DriverInput::Proposal(signed_proposal, validity),
+            DriverInput::from_stored_proposal(proposal, validity),
         )
         .await?;
     }
@@ -76,7 +73,7 @@ where
 /// - Drops values from lower heights
 /// - Queues values from higher heights for later processing
 /// - If a commit certificate exists for this value, uses direct decision path
-/// - For parts-only mode, generates and signs internal Proposal messages
+/// - For parts-only mode, generates unsigned internal Proposal messages
 /// - Stores the value and appends it to the WAL if new
 /// - Applies any associated proposals to the driver
 ///
@@ -44,6 +44,19 @@ where
     let round = Round::new(0);
     info!(%height, "Starting new height");
 
+    if state.params.follower
+        && state
+            .validator_set()
+            .get_by_address(state.address())
+            .is_some()
+    {
+        warn!(
+            %height,
+            address = %state.address(),
+            "Node runs as a follower but is part of the validator set, it will not sign nor propose"
+        );
+    }
+
     #[cfg(feature = "metrics")]
     {
         metrics.block_start();
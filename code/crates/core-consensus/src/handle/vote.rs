@@ -79,6 +79,27 @@ where
         return Ok(());
     }
 
+    // Drop the vote if the validator already sent too many votes for this round,
+    // protecting the driver from a validator flooding us with equivocating votes.
+    if !state
+        .vote_limiter
+        .record(vote_round, validator_address.clone())
+    {
+        warn!(
+            consensus.height = %consensus_height,
+            consensus.round = %consensus_round,
+            vote.height = %vote_height,
+            vote.round = %vote_round,
+            validator = %validator_address,
+            "Validator exceeded the maximum number of votes per round, dropping vote"
+        );
+
+        #[cfg(feature = "metrics")]
+        metrics.flooded_votes.inc();
+
+        return Ok(());
+    }
+
     info!(
         consensus.height = %consensus_height,
         consensus.round = %consensus_round,
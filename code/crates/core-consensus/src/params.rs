@@ -22,4 +22,14 @@ pub struct Params<Ctx: Context> {
 
     /// Whether consensus is enabled for this node
     pub enabled: bool,
+
+    /// Maximum number of distinct votes accepted from a single validator in a round.
+    /// Further votes from that validator for that round are dropped.
+    /// `None` disables the limit.
+    pub max_votes_per_validator_per_round: Option<usize>,
+
+    /// Whether this node runs as a read-only follower.
+    /// A follower processes consensus messages and decides on values,
+    /// but never signs nor proposes, even if it is part of the validator set.
+    pub follower: bool,
 }
@@ -10,6 +10,7 @@ use crate::params::Params;
 use crate::prelude::*;
 use crate::types::ProposedValue;
 use crate::util::bounded_queue::BoundedQueue;
+use crate::util::vote_limiter::VoteLimiter;
 
 /// The state maintained by consensus for processing a [`Input`].
 pub struct State<Ctx>
@@ -28,6 +29,9 @@ where
     /// A queue of inputs that were received before the driver started.
     pub input_queue: BoundedQueue<Ctx::Height, Input<Ctx>>,
 
+    /// Per-validator accounting of the votes received at the current height
+    pub vote_limiter: VoteLimiter<Ctx::Address>,
+
     /// The proposals to decide on.
     pub full_proposal_keeper: FullProposalKeeper<Ctx>,
 
@@ -51,6 +55,12 @@ where
     /// It allows collecting additional precommits for the decided value after
     /// the decision is made in decide, which can be included in the commit certificate.
     pub finalization_period: bool,
+
+    /// Whether this node is allowed to sign.
+    ///
+    /// Signing can be disabled at runtime, eg. while migrating the validator key
+    /// to another machine, in which case the node behaves as a follower.
+    pub signing_enabled: bool,
 }
 
 impl<Ctx> State<Ctx>
@@ -72,17 +82,21 @@ where
             params.threshold_params,
         );
 
+        let vote_limiter = VoteLimiter::new(params.max_votes_per_validator_per_round);
+
         Self {
             ctx,
             driver,
             params,
             input_queue: BoundedQueue::new(queue_capacity),
+            vote_limiter,
             full_proposal_keeper: Default::default(),
             last_signed_prevote: None,
             last_signed_precommit: None,
             target_time: None,
             height_start_time: None,
             finalization_period: false,
+            signing_enabled: true,
         }
     }
 
@@ -102,6 +116,11 @@ where
         self.driver.validator_set()
     }
 
+    /// Whether this node is the proposer of the current round
+    pub fn is_proposer(&self) -> bool {
+        self.driver.proposer_address() == Some(self.address())
+    }
+
     pub fn get_proposer(&self, height: Ctx::Height, round: Round) -> &Ctx::Address {
         self.ctx
             .select_proposer(self.validator_set(), height, round)
@@ -177,12 +196,12 @@ where
     pub fn proposals_for_value(
         &self,
         proposed_value: &ProposedValue<Ctx>,
-    ) -> Vec<SignedProposal<Ctx>> {
+    ) -> Vec<StoredProposal<Ctx>> {
         self.full_proposal_keeper
             .proposals_for_value(proposed_value)
     }
 
-    pub fn store_proposal(&mut self, new_proposal: SignedProposal<Ctx>) {
+    pub fn store_proposal(&mut self, new_proposal: StoredProposal<Ctx>) {
         self.full_proposal_keeper.store_proposal(new_proposal)
     }
 
@@ -217,6 +236,13 @@ where
         validity
     }
 
+    /// Change the maximum number of distinct votes accepted from a single validator in a round,
+    /// `None` disabling the limit. The votes already recorded at this height still count.
+    pub fn set_max_votes_per_validator_per_round(&mut self, max_votes: Option<usize>) {
+        self.params.max_votes_per_validator_per_round = max_votes;
+        self.vote_limiter.set_max_votes_per_round(max_votes);
+    }
+
     pub fn reset_and_start_height(
         &mut self,
         height: Ctx::Height,
@@ -224,6 +250,7 @@ where
         target_time: Option<Duration>,
     ) {
         self.full_proposal_keeper.clear();
+        self.vote_limiter.clear();
         self.last_signed_prevote = None;
         self.last_signed_precommit = None;
         self.target_time = target_time;
@@ -317,9 +344,13 @@ where
     ///
     /// Returns true only if:
     /// - Consensus is enabled in the configuration, AND
+    /// - This node is not running as a follower, AND
+    /// - Signing has not been disabled at runtime, AND
     /// - This node is present in the current validator set
     pub fn is_active_validator(&self) -> bool {
         self.params.enabled
+            && !self.params.follower
+            && self.signing_enabled
             && self
                 .validator_set()
                 .get_by_address(self.address())
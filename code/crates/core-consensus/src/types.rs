@@ -3,7 +3,7 @@ use thiserror::Error;
 
 use malachitebft_core_types::{
     Context, PolkaCertificate, Proposal, Round, RoundCertificate, Signature, SignedProposal,
-    SignedVote, Timeout, Validity, Vote,
+    SignedVote, Timeout, Validity, Vote, VoteType,
 };
 
 pub use malachitebft_core_types::ValuePayload;
@@ -147,4 +147,131 @@ impl<Ctx: Context> MisbehaviorEvidence<Ctx> {
     pub fn is_empty(&self) -> bool {
         self.proposals.is_empty() && self.votes.is_empty()
     }
+
+    /// Add a single piece of evidence, eg. one received from a peer.
+    pub fn add(&mut self, evidence: Evidence<Ctx>) {
+        match evidence {
+            Evidence::DoubleProposal(existing, conflicting) => {
+                self.proposals.add(existing, conflicting)
+            }
+            Evidence::DoubleVote(existing, conflicting) => self.votes.add(existing, conflicting),
+        }
+    }
+
+    /// List every piece of evidence, one per pair of conflicting messages.
+    pub fn to_evidence(&self) -> Vec<Evidence<Ctx>> {
+        let proposals = self.proposals.iter().flat_map(|addr| {
+            self.proposals
+                .get(addr)
+                .into_iter()
+                .flatten()
+                .map(|(p1, p2)| Evidence::DoubleProposal(p1.clone(), p2.clone()))
+        });
+
+        let votes = self.votes.iter().flat_map(|addr| {
+            self.votes
+                .get(addr)
+                .into_iter()
+                .flatten()
+                .map(|(v1, v2)| Evidence::DoubleVote(v1.clone(), v2.clone()))
+        });
+
+        proposals.chain(votes).collect()
+    }
+}
+
+/// The kind of misbehavior a piece of [`Evidence`] is a proof of.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EvidenceKind {
+    DoubleProposal,
+    DoubleVote(VoteType),
+}
+
+/// Proof that a validator signed two conflicting messages for the same height and round.
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub enum Evidence<Ctx: Context> {
+    DoubleProposal(SignedProposal<Ctx>, SignedProposal<Ctx>),
+    DoubleVote(SignedVote<Ctx>, SignedVote<Ctx>),
+}
+
+impl<Ctx: Context> Evidence<Ctx> {
+    pub fn height(&self) -> Ctx::Height {
+        match self {
+            Evidence::DoubleProposal(p, _) => p.height(),
+            Evidence::DoubleVote(v, _) => v.height(),
+        }
+    }
+
+    pub fn round(&self) -> Round {
+        match self {
+            Evidence::DoubleProposal(p, _) => p.round(),
+            Evidence::DoubleVote(v, _) => v.round(),
+        }
+    }
+
+    pub fn validator_address(&self) -> &Ctx::Address {
+        match self {
+            Evidence::DoubleProposal(p, _) => p.validator_address(),
+            Evidence::DoubleVote(v, _) => v.validator_address(),
+        }
+    }
+
+    pub fn kind(&self) -> EvidenceKind {
+        match self {
+            Evidence::DoubleProposal(_, _) => EvidenceKind::DoubleProposal,
+            Evidence::DoubleVote(v, _) => EvidenceKind::DoubleVote(v.vote_type()),
+        }
+    }
+
+    /// The two signed messages, as consensus messages.
+    pub fn messages(&self) -> (SignedConsensusMsg<Ctx>, SignedConsensusMsg<Ctx>) {
+        match self {
+            Evidence::DoubleProposal(p1, p2) => (
+                SignedConsensusMsg::Proposal(p1.clone()),
+                SignedConsensusMsg::Proposal(p2.clone()),
+            ),
+            Evidence::DoubleVote(v1, v2) => (
+                SignedConsensusMsg::Vote(v1.clone()),
+                SignedConsensusMsg::Vote(v2.clone()),
+            ),
+        }
+    }
+
+    /// Build the evidence from two signed messages, `None` if they are not of the same type.
+    pub fn from_messages(
+        first: SignedConsensusMsg<Ctx>,
+        second: SignedConsensusMsg<Ctx>,
+    ) -> Option<Self> {
+        match (first, second) {
+            (SignedConsensusMsg::Proposal(p1), SignedConsensusMsg::Proposal(p2)) => {
+                Some(Evidence::DoubleProposal(p1, p2))
+            }
+            (SignedConsensusMsg::Vote(v1), SignedConsensusMsg::Vote(v2)) => {
+                Some(Evidence::DoubleVote(v1, v2))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the two messages were sent by the same validator for the same height, round
+    /// and step, but for different values, ie. whether this is actual proof of equivocation.
+    ///
+    /// The signatures of the messages are not checked.
+    pub fn is_conflicting(&self) -> bool {
+        match self {
+            Evidence::DoubleProposal(p1, p2) => {
+                p1.validator_address() == p2.validator_address()
+                    && p1.height() == p2.height()
+                    && p1.round() == p2.round()
+                    && p1.value() != p2.value()
+            }
+            Evidence::DoubleVote(v1, v2) => {
+                v1.validator_address() == v2.validator_address()
+                    && v1.height() == v2.height()
+                    && v1.round() == v2.round()
+                    && v1.vote_type() == v2.vote_type()
+                    && v1.value() != v2.value()
+            }
+        }
+    }
 }
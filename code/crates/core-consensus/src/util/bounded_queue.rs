@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use std::fmt::Display;
+use std::time::Instant;
 
 use tracing::{debug, trace};
 
@@ -12,6 +13,8 @@ use tracing::{debug, trace};
 pub struct BoundedQueue<I, T> {
     capacity: usize,
     queue: BTreeMap<I, Vec<T>>,
+    /// When the first value still queued at each index was pushed
+    since: BTreeMap<I, Instant>,
 }
 
 impl<I, T> BoundedQueue<I, T>
@@ -23,9 +26,18 @@ where
         Self {
             capacity,
             queue: BTreeMap::new(),
+            since: BTreeMap::new(),
         }
     }
 
+    /// Change the capacity of the queue.
+    ///
+    /// Entries already queued beyond a smaller capacity are kept, the queue
+    /// being full until enough of them have been taken.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
     /// Push a value into the queue associated with the given index.
     ///
     /// Returns `true` if the value was successfully added,
@@ -42,6 +54,7 @@ where
 
         // If the index does not exist, check if we can add a new entry.
         if !self.is_full() {
+            self.since.insert(index.clone(), Instant::now());
             self.queue.insert(index, vec![value]);
             return true;
         }
@@ -63,9 +76,11 @@ where
                 );
 
                 // Remove the entry at the highest index
-                max_entry.remove();
+                let (max_index, _) = max_entry.remove_entry();
+                self.since.remove(&max_index);
 
                 // Insert the new index with its value
+                self.since.insert(index.clone(), Instant::now());
                 self.queue.insert(index, vec![value]);
 
                 return true;
@@ -99,6 +114,7 @@ where
         let before = self.queue.len();
 
         self.queue.retain(|index, _| index >= min_index);
+        self.since.retain(|index, _| index >= min_index);
 
         let removed = before - self.queue.len();
         if removed > 0 {
@@ -113,6 +129,7 @@ where
 
     /// Take all entries with indices equal to `index` and return them.
     pub fn take(&mut self, index: &I) -> impl Iterator<Item = T> {
+        self.since.remove(index);
         self.queue
             .remove(index)
             .into_iter()
@@ -137,12 +154,24 @@ where
             removed += before - values.len();
             !values.is_empty()
         });
+        let queue = &self.queue;
+        self.since.retain(|index, _| queue.contains_key(index));
         removed
     }
 
     /// Remove all entries from the queue.
     pub fn clear(&mut self) {
         self.queue.clear();
+        self.since.clear();
+    }
+
+    /// Iterate over the indices in the queue in ascending order, with their values
+    /// and the time at which the oldest of these values was pushed.
+    pub fn iter(&self) -> impl Iterator<Item = (&I, &[T], Instant)> {
+        self.queue.iter().filter_map(|(index, values)| {
+            let since = self.since.get(index)?;
+            Some((index, values.as_slice(), *since))
+        })
     }
 
     /// Whether the queue is full
@@ -588,4 +617,30 @@ mod tests {
         assert!(queue.queue.contains_key(&30));
         assert_eq!(queue.len(), 2);
     }
+
+    #[test]
+    fn iter_reports_oldest_push_per_index() {
+        let mut queue = BoundedQueue::new(2);
+        queue.push(10, "a");
+        let first = queue.since[&10];
+        queue.push(10, "b");
+        queue.push(20, "c");
+
+        let entries: Vec<_> = queue.iter().collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, &10);
+        assert_eq!(entries[0].1, &["a", "b"]);
+        assert_eq!(entries[0].2, first);
+
+        // Evicting, taking and shifting forget the push times of the removed indices
+        queue.push(5, "d");
+        assert!(!queue.since.contains_key(&20));
+
+        let _ = queue.take(&10).count();
+        assert!(!queue.since.contains_key(&10));
+
+        queue.shift(&6);
+        assert!(queue.since.is_empty());
+        assert_eq!(queue.iter().count(), 0);
+    }
 }
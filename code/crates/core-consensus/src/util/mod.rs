@@ -1,2 +1,3 @@
 pub mod bounded_queue;
 pub mod pretty;
+pub mod vote_limiter;
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use malachitebft_core_types::Round;
+
+/// Per-validator accounting of the votes received at the current height.
+///
+/// An honest validator casts at most one prevote and one precommit per round.
+/// A compromised validator may instead flood us with equivocating votes, each of
+/// which would otherwise be verified, persisted to the WAL and fed to the driver.
+/// Once a validator exceeds the configured number of votes for a round,
+/// any further vote it sends for that round is rejected.
+#[derive(Clone, Debug)]
+pub struct VoteLimiter<A> {
+    /// Maximum number of distinct votes accepted per validator and round, `None` if unlimited
+    max_votes_per_round: Option<usize>,
+    counts: BTreeMap<(Round, A), usize>,
+}
+
+impl<A> VoteLimiter<A>
+where
+    A: Ord,
+{
+    /// Creates a new `VoteLimiter` with the specified limit.
+    pub fn new(max_votes_per_round: Option<usize>) -> Self {
+        Self {
+            max_votes_per_round,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Change the limit, keeping the votes already recorded.
+    pub fn set_max_votes_per_round(&mut self, max_votes_per_round: Option<usize>) {
+        self.max_votes_per_round = max_votes_per_round;
+    }
+
+    /// Record a vote from the given validator at the given round.
+    ///
+    /// Returns `true` if the vote is within the limit and should be processed,
+    /// or `false` if the validator already reached the limit for this round.
+    pub fn record(&mut self, round: Round, address: A) -> bool {
+        let Some(max_votes) = self.max_votes_per_round else {
+            return true;
+        };
+
+        let count = self.counts.entry((round, address)).or_insert(0);
+        if *count >= max_votes {
+            return false;
+        }
+
+        *count += 1;
+        true
+    }
+
+    /// Forget all recorded votes, eg. when moving to a new height.
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vote_limiter() {
+        let mut limiter = VoteLimiter::new(Some(2));
+
+        assert!(limiter.record(Round::new(0), "a"));
+        assert!(limiter.record(Round::new(0), "a"));
+        assert!(!limiter.record(Round::new(0), "a"));
+
+        // Limits are per validator and per round
+        assert!(limiter.record(Round::new(0), "b"));
+        assert!(limiter.record(Round::new(1), "a"));
+
+        limiter.clear();
+        assert!(limiter.record(Round::new(0), "a"));
+    }
+
+    #[test]
+    fn test_vote_limiter_set_limit() {
+        let mut limiter = VoteLimiter::new(None);
+
+        assert!(limiter.record(Round::new(0), "a"));
+        assert!(limiter.record(Round::new(0), "a"));
+
+        // Votes recorded before the limit was set still count
+        limiter.set_max_votes_per_round(Some(2));
+        assert!(!limiter.record(Round::new(0), "a"));
+        assert!(limiter.record(Round::new(0), "b"));
+
+        limiter.set_max_votes_per_round(None);
+        assert!(limiter.record(Round::new(0), "a"));
+    }
+
+    #[test]
+    fn test_vote_limiter_unlimited() {
+        let mut limiter = VoteLimiter::new(None);
+
+        for _ in 0..100 {
+            assert!(limiter.record(Round::new(0), "a"));
+        }
+    }
+}
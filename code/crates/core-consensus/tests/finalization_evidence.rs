@@ -25,6 +25,8 @@ fn make_state(validators: &[Validator], my_addr: Address) -> State<TestContext>
             threshold_params: Default::default(),
             value_payload: ValuePayload::ProposalOnly,
             enabled: true,
+            max_votes_per_validator_per_round: None,
+            follower: false,
         },
         1000,
     )
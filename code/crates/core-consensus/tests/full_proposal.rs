@@ -90,6 +90,9 @@ fn props_for_value(
     v: &ProposedValue<TestContext>,
 ) -> Vec<SignedProposal<TestContext>> {
     k.proposals_for_value(v)
+        .iter()
+        .filter_map(|p| p.signed())
+        .collect()
 }
 
 // Used for full proposer keeper testing:
@@ -276,7 +279,7 @@ fn full_proposal_keeper_tests() {
 
         for m in s.input {
             match m {
-                Input::Proposal(p) => keeper.store_proposal(p),
+                Input::Proposal(p) => keeper.store_proposal(p.into()),
                 Input::ProposedValue(v, _) => keeper.store_value(&v),
                 _ => continue,
             }
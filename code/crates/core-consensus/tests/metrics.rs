@@ -0,0 +1,145 @@
+use arc_malachitebft_core_consensus::{
+    process, Effect, Error, Input, LocallyProposedValue, Params, ProposedValue, Resume, State,
+};
+use malachitebft_core_types::{
+    NilOrVal, Round, SignedProposal, SignedVote, Validity, ValueOrigin, ValuePayload,
+};
+use malachitebft_metrics::{Metrics, Registry, SharedRegistry};
+use malachitebft_test::utils::validators::make_validators;
+use malachitebft_test::{
+    Address, Height, Proposal, Signature, TestContext, Validator, ValidatorSet, Value, Vote,
+};
+
+fn make_state(validators: &[Validator], my_addr: Address) -> State<TestContext> {
+    State::new(
+        TestContext::new(),
+        Height::new(1),
+        ValidatorSet::new(validators.to_vec()),
+        Params {
+            address: my_addr,
+            threshold_params: Default::default(),
+            value_payload: ValuePayload::ProposalOnly,
+            enabled: true,
+            max_votes_per_validator_per_round: None,
+            follower: false,
+        },
+        1000,
+    )
+}
+
+fn handle_effect(effect: Effect<TestContext>) -> Result<Resume<TestContext>, ()> {
+    use Effect::*;
+    Ok(match effect {
+        VerifySignature(_, _, r) => r.resume_with(true),
+        SignVote(vote, r) => r.resume_with(SignedVote::new(vote, Signature::test())),
+        SignProposal(proposal, r) => {
+            r.resume_with(SignedProposal::new(proposal, Signature::test()))
+        }
+        _ => Resume::Continue,
+    })
+}
+
+/// Decide height 1 at round 0, either as its proposer or not,
+/// returning the consensus metrics recorded meanwhile
+fn decide_height(as_proposer: bool) -> String {
+    let validators = make_validators([1, 1, 1, 1]).map(|(v, _)| v);
+    let proposer =
+        *make_state(&validators, validators[0].address).get_proposer(Height::new(1), Round::new(0));
+
+    let me = validators
+        .iter()
+        .find(|v| (v.address == proposer) == as_proposer)
+        .unwrap();
+
+    let registry = SharedRegistry::new(Registry::default(), None);
+    let metrics = Metrics::register(&registry);
+    let mut state = make_state(&validators, me.address);
+
+    let value = Value::new(42);
+    let height = Height::new(1);
+    let round = Round::new(0);
+
+    let mut inputs = vec![Input::StartHeight(
+        height,
+        ValidatorSet::new(validators.to_vec()),
+        false,
+        None,
+    )];
+
+    if as_proposer {
+        inputs.push(Input::Propose(LocallyProposedValue::new(
+            height,
+            round,
+            value.clone(),
+        )));
+    } else {
+        let proposal = Proposal::new(height, round, value.clone(), Round::Nil, proposer);
+
+        inputs.push(Input::Proposal(SignedProposal::new(
+            proposal,
+            Signature::test(),
+        )));
+        inputs.push(Input::ProposedValue(
+            ProposedValue {
+                height,
+                round,
+                valid_round: Round::Nil,
+                proposer,
+                value: value.clone(),
+                validity: Validity::Valid,
+            },
+            ValueOrigin::Consensus,
+        ));
+    }
+
+    for vote in [Vote::new_prevote, Vote::new_precommit] {
+        for v in &validators {
+            let vote = vote(height, round, NilOrVal::Val(value.id()), v.address);
+            inputs.push(Input::Vote(SignedVote::new(vote, Signature::test())));
+        }
+    }
+
+    for input in inputs {
+        let result: Result<(), Error<TestContext>> = process!(
+            input: input,
+            state: &mut state,
+            metrics: &metrics,
+            with: effect => handle_effect(effect)
+        );
+
+        drop(result);
+    }
+
+    assert!(state.driver.step_is_commit());
+
+    let mut exported = String::new();
+    registry.export(&mut exported);
+    exported
+}
+
+#[test]
+fn rounds_per_height_by_role() {
+    let as_proposer = decide_height(true);
+    assert!(as_proposer.contains(r#"rounds_per_height_count{role="Proposer"} 1"#));
+    assert!(!as_proposer.contains(r#"rounds_per_height_count{role="NonProposer"}"#));
+
+    let as_validator = decide_height(false);
+    assert!(as_validator.contains(r#"rounds_per_height_count{role="NonProposer"} 1"#));
+    assert!(!as_validator.contains(r#"rounds_per_height_count{role="Proposer"}"#));
+}
+
+#[test]
+fn step_timings_by_role() {
+    let as_proposer = decide_height(true);
+    let as_validator = decide_height(false);
+
+    let time_per_step = |exported: &str, role: &str| {
+        exported
+            .lines()
+            .any(|line| line.contains("time_per_step_count") && line.contains(role))
+    };
+
+    assert!(time_per_step(&as_proposer, r#"role="Proposer""#));
+    assert!(time_per_step(&as_validator, r#"role="NonProposer""#));
+    assert!(!time_per_step(&as_validator, r#"role="Proposer""#));
+}
@@ -0,0 +1,153 @@
+use arc_malachitebft_core_consensus::{
+    process, Effect, Error, Input, Params, ProposedValue, Resume, State,
+};
+use malachitebft_core_types::{
+    Round, SignedProposal, SignedVote, Validity, ValueOrigin, ValuePayload,
+};
+use malachitebft_metrics::Metrics;
+use malachitebft_test::utils::validators::make_validators;
+use malachitebft_test::{
+    Address, Height, Proposal, Signature, TestContext, Validator, ValidatorSet, Value,
+};
+
+const VALUE_PAYLOADS: [ValuePayload; 3] = [
+    ValuePayload::ProposalOnly,
+    ValuePayload::PartsOnly,
+    ValuePayload::ProposalAndParts,
+];
+
+fn make_state(
+    validators: &[Validator],
+    my_addr: Address,
+    value_payload: ValuePayload,
+) -> State<TestContext> {
+    State::new(
+        TestContext::new(),
+        Height::new(1),
+        ValidatorSet::new(validators.to_vec()),
+        Params {
+            address: my_addr,
+            threshold_params: Default::default(),
+            value_payload,
+            enabled: true,
+            max_votes_per_validator_per_round: None,
+            follower: false,
+        },
+        1000,
+    )
+}
+
+/// Process the input, returning the number of messages signed meanwhile
+fn signatures(
+    state: &mut State<TestContext>,
+    metrics: &Metrics,
+    input: Input<TestContext>,
+) -> usize {
+    let mut signed = 0;
+
+    let result: Result<(), Error<TestContext>> = process!(
+        input: input,
+        state: state,
+        metrics: metrics,
+        with: effect => {
+            match effect {
+                Effect::VerifySignature(_, _, r) => Ok::<_, ()>(r.resume_with(true)),
+                Effect::SignVote(vote, r) => {
+                    signed += 1;
+                    Ok(r.resume_with(SignedVote::new(vote, Signature::test())))
+                }
+                Effect::SignProposal(proposal, r) => {
+                    signed += 1;
+                    Ok(r.resume_with(SignedProposal::new(proposal, Signature::test())))
+                }
+                _ => Ok(Resume::Continue),
+            }
+        }
+    );
+
+    drop(result);
+    signed
+}
+
+/// Start height 1 as a validator which is not the proposer, and receive the proposal,
+/// returning the number of messages signed meanwhile.
+///
+/// In parts-only mode, there is no proposal message and the proposal is derived from the value.
+fn receive_proposal(value_payload: ValuePayload, signing_enabled: bool) -> usize {
+    let validators = make_validators([1, 1, 1, 1]).map(|(v, _)| v);
+    let proposer = *make_state(&validators, validators[0].address, value_payload)
+        .get_proposer(Height::new(1), Round::new(0));
+
+    let me = validators.iter().find(|v| v.address != proposer).unwrap();
+
+    let mut state = make_state(&validators, me.address, value_payload);
+    state.signing_enabled = signing_enabled;
+
+    let metrics = Metrics::new();
+    let value = Value::new(42);
+
+    let start = Input::StartHeight(
+        Height::new(1),
+        ValidatorSet::new(validators.to_vec()),
+        false,
+        None,
+    );
+
+    let proposal = Input::Proposal(SignedProposal::new(
+        Proposal::new(
+            Height::new(1),
+            Round::new(0),
+            value.clone(),
+            Round::Nil,
+            proposer,
+        ),
+        Signature::test(),
+    ));
+
+    let proposed_value = Input::ProposedValue(
+        ProposedValue {
+            height: Height::new(1),
+            round: Round::new(0),
+            valid_round: Round::Nil,
+            proposer,
+            value,
+            validity: Validity::Valid,
+        },
+        ValueOrigin::Consensus,
+    );
+
+    assert_eq!(state.is_active_validator(), signing_enabled);
+
+    let inputs = if value_payload.parts_only() {
+        vec![start, proposed_value]
+    } else {
+        vec![start, proposal, proposed_value]
+    };
+
+    inputs
+        .into_iter()
+        .map(|input| signatures(&mut state, &metrics, input))
+        .sum()
+}
+
+#[test]
+fn validator_prevotes_for_the_proposal() {
+    for value_payload in VALUE_PAYLOADS {
+        assert_eq!(
+            receive_proposal(value_payload, true),
+            1,
+            "{value_payload:?}"
+        );
+    }
+}
+
+#[test]
+fn validator_signs_nothing_while_signing_is_disabled() {
+    for value_payload in VALUE_PAYLOADS {
+        assert_eq!(
+            receive_proposal(value_payload, false),
+            0,
+            "{value_payload:?}"
+        );
+    }
+}
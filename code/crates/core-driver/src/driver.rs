@@ -9,16 +9,15 @@ use malachitebft_core_state_machine::state::{RoundValue, State as RoundState, St
 use malachitebft_core_state_machine::state_machine::Info;
 use malachitebft_core_types::{
     CommitCertificate, Context, EnterRoundCertificate, NilOrVal, PolkaCertificate, PolkaSignature,
-    Proposal, Round, RoundCertificate, RoundCertificateType, RoundSignature, SignedProposal,
-    SignedVote, Timeout, TimeoutKind, Validator, ValidatorSet, Validity, Value, ValueId, Vote,
-    VoteType,
+    Proposal, Round, RoundCertificate, RoundCertificateType, RoundSignature, SignedVote, Timeout,
+    TimeoutKind, Validator, ValidatorSet, Validity, Value, ValueId, Vote, VoteType,
 };
 use malachitebft_core_votekeeper::keeper::Output as VKOutput;
 use malachitebft_core_votekeeper::keeper::VoteKeeper;
 
 use crate::input::Input;
 use crate::output::Output;
-use crate::proposal_keeper::{self, ProposalKeeper};
+use crate::proposal_keeper::{self, ProposalKeeper, StoredProposal};
 use crate::Error;
 use crate::ThresholdParams;
 
@@ -311,7 +310,7 @@ where
         &self,
         round: Round,
         value_id: ValueId<Ctx>,
-    ) -> Option<&(SignedProposal<Ctx>, Validity)> {
+    ) -> Option<&(StoredProposal<Ctx>, Validity)> {
         self.proposal_keeper
             .get_proposal_and_validity_for_round_and_value(round, value_id)
     }
@@ -321,7 +320,7 @@ where
         &self,
         round: Round,
         value_id: ValueId<Ctx>,
-    ) -> Option<&SignedProposal<Ctx>> {
+    ) -> Option<&StoredProposal<Ctx>> {
         if let Some((proposal, validity)) =
             self.proposal_and_validity_for_round_and_value(round, value_id)
         {
@@ -336,7 +335,7 @@ where
     pub fn proposals_and_validities_for_round(
         &self,
         round: Round,
-    ) -> &[(SignedProposal<Ctx>, Validity)] {
+    ) -> &[(StoredProposal<Ctx>, Validity)] {
         self.proposal_keeper
             .get_proposals_and_validities_for_round(round)
     }
@@ -443,7 +442,10 @@ where
                 self.apply_new_round(height, round, proposer)
             }
             Input::ProposeValue(round, value) => self.apply_propose_value(round, value),
-            Input::Proposal(proposal, validity) => self.apply_proposal(proposal, validity),
+            Input::Proposal(proposal, validity) => self.apply_proposal(proposal.into(), validity),
+            Input::ImplicitProposal(proposal, validity) => {
+                self.apply_proposal(StoredProposal::implicit(proposal), validity)
+            }
             Input::Vote(vote) => self.apply_vote(vote),
             Input::TimeoutElapsed(timeout) => self.apply_timeout(timeout),
             Input::SyncDecision(proposal) => self.apply_decide_on_sync(proposal),
@@ -521,7 +523,7 @@ where
 
     fn apply_proposal(
         &mut self,
-        proposal: SignedProposal<Ctx>,
+        proposal: StoredProposal<Ctx>,
         validity: Validity,
     ) -> Result<Option<RoundOutput<Ctx>>, Error<Ctx>> {
         if self.height() != proposal.height() {
@@ -5,6 +5,8 @@ use malachitebft_core_types::{
 
 use derive_where::derive_where;
 
+use crate::proposal_keeper::StoredProposal;
+
 /// Events that can be received by the [`Driver`](crate::Driver).
 #[derive_where(Clone, Debug, PartialEq, Eq)]
 pub enum Input<Ctx>
@@ -20,6 +22,10 @@ where
     /// Receive a proposal, of the given validity
     Proposal(SignedProposal<Ctx>, Validity),
 
+    /// Receive an implicit, unsigned proposal of the given validity,
+    /// derived from the proposed value in parts-only mode
+    ImplicitProposal(Ctx::Proposal, Validity),
+
     /// Receive a vote
     Vote(SignedVote<Ctx>),
 
@@ -37,3 +43,18 @@ where
     /// Takes an unsigned proposal and goes through the state machine.
     SyncDecision(Ctx::Proposal),
 }
+
+impl<Ctx> Input<Ctx>
+where
+    Ctx: Context,
+{
+    /// Create the input for a stored proposal, which is implicit if it is not signed.
+    pub fn from_stored_proposal(proposal: StoredProposal<Ctx>, validity: Validity) -> Self {
+        match proposal.signature {
+            Some(signature) => {
+                Self::Proposal(SignedProposal::new(proposal.message, signature), validity)
+            }
+            None => Self::ImplicitProposal(proposal.message, validity),
+        }
+    }
+}
@@ -31,12 +31,13 @@ use alloc::vec::Vec;
 
 use malachitebft_core_state_machine::input::Input as RoundInput;
 use malachitebft_core_state_machine::state::Step;
-use malachitebft_core_types::{CommitCertificate, PolkaCertificate, SignedProposal};
+use malachitebft_core_types::{CommitCertificate, PolkaCertificate};
 use malachitebft_core_types::{Context, Proposal, Round, Validity, Value, ValueId, VoteType};
 use malachitebft_core_votekeeper::keeper::Output as VKOutput;
 use malachitebft_core_votekeeper::keeper::VoteKeeper;
 use malachitebft_core_votekeeper::Threshold;
 
+use crate::proposal_keeper::StoredProposal;
 use crate::Driver;
 
 impl<Ctx> Driver<Ctx>
@@ -186,17 +187,17 @@ where
 
     pub(crate) fn store_and_multiplex_proposal(
         &mut self,
-        signed_proposal: SignedProposal<Ctx>,
+        stored_proposal: StoredProposal<Ctx>,
         validity: Validity,
     ) -> Option<RoundInput<Ctx>> {
         // Should only receive proposals for our height.
-        assert_eq!(self.height(), signed_proposal.height());
+        assert_eq!(self.height(), stored_proposal.height());
 
-        let proposal = signed_proposal.message.clone();
+        let proposal = stored_proposal.message.clone();
 
         // Store the proposal and its validity
         self.proposal_keeper
-            .store_proposal(signed_proposal, validity);
+            .store_proposal(stored_proposal, validity);
 
         self.multiplex_proposal(proposal, validity)
     }
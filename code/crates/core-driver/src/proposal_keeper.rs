@@ -3,15 +3,76 @@
 use alloc::collections::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::ops::Deref;
 
 use derive_where::derive_where;
 use thiserror::Error;
 
 use malachitebft_core_types::{
-    Context, DoubleProposal, Proposal, Round, SignedProposal, Validity, Value, ValueId,
+    Context, DoubleProposal, Proposal, Round, Signature, SignedProposal, Validity, Value, ValueId,
 };
 use tracing::{error, warn};
 
+/// A proposal stored by the keepers, together with its signature if it has one.
+///
+/// In parts-only mode, there is no proposal message on the wire and the proposal is
+/// instead derived from the proposed value itself. Such an implicit proposal is never
+/// published and therefore stored without a signature.
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub struct StoredProposal<Ctx>
+where
+    Ctx: Context,
+{
+    /// The proposal
+    pub message: Ctx::Proposal,
+
+    /// The signature of the proposal, `None` for an implicit proposal
+    pub signature: Option<Signature<Ctx>>,
+}
+
+impl<Ctx> StoredProposal<Ctx>
+where
+    Ctx: Context,
+{
+    /// Create an implicit, unsigned proposal.
+    pub fn implicit(message: Ctx::Proposal) -> Self {
+        Self {
+            message,
+            signature: None,
+        }
+    }
+
+    /// The signed proposal, if the proposal is signed.
+    pub fn signed(&self) -> Option<SignedProposal<Ctx>> {
+        self.signature
+            .clone()
+            .map(|signature| SignedProposal::new(self.message.clone(), signature))
+    }
+}
+
+impl<Ctx> From<SignedProposal<Ctx>> for StoredProposal<Ctx>
+where
+    Ctx: Context,
+{
+    fn from(proposal: SignedProposal<Ctx>) -> Self {
+        Self {
+            message: proposal.message,
+            signature: Some(proposal.signature),
+        }
+    }
+}
+
+impl<Ctx> Deref for StoredProposal<Ctx>
+where
+    Ctx: Context,
+{
+    type Target = Ctx::Proposal;
+
+    fn deref(&self) -> &Self::Target {
+        &self.message
+    }
+}
+
 /// Errors can that be yielded when recording a proposal.
 #[derive_where(Debug)]
 #[derive(Error)]
@@ -20,12 +81,12 @@ where
     Ctx: Context,
 {
     /// Attempted to record a conflicting proposal.
-    #[error("Conflicting proposal: existing: {existing}, conflicting: {conflicting}")]
+    #[error("Conflicting proposal: existing: {existing:?}, conflicting: {conflicting:?}")]
     ConflictingProposal {
         /// The proposal already recorded for the same value.
-        existing: SignedProposal<Ctx>,
+        existing: StoredProposal<Ctx>,
         /// The conflicting proposal, from the same validator.
-        conflicting: SignedProposal<Ctx>,
+        conflicting: StoredProposal<Ctx>,
     },
 }
 
@@ -36,7 +97,7 @@ where
     Ctx: Context,
 {
     /// The proposals received in a given round (proposal.round) if any.
-    proposals: Vec<(SignedProposal<Ctx>, Validity)>,
+    proposals: Vec<(StoredProposal<Ctx>, Validity)>,
 }
 
 impl<Ctx> PerRound<Ctx>
@@ -47,19 +108,19 @@ where
     fn get_first_proposal_and_validity(
         &self,
         value_id: ValueId<Ctx>,
-    ) -> Option<&(SignedProposal<Ctx>, Validity)> {
+    ) -> Option<&(StoredProposal<Ctx>, Validity)> {
         self.proposals
             .iter()
             .find(|(proposal, _)| proposal.value().id() == value_id)
     }
 
     // /// Return the first proposal, if any, without validity.
-    fn get_first_proposal(&self) -> Option<&SignedProposal<Ctx>> {
+    fn get_first_proposal(&self) -> Option<&StoredProposal<Ctx>> {
         self.proposals.first().map(|(p, _)| p)
     }
 
     /// Returns all proposals and their validities.
-    pub fn get_proposals_and_validities(&self) -> &[(SignedProposal<Ctx>, Validity)] {
+    pub fn get_proposals_and_validities(&self) -> &[(StoredProposal<Ctx>, Validity)] {
         &self.proposals
     }
 
@@ -69,12 +130,12 @@ where
     /// If a proposal comes from a different validator than the first,
     /// this is considered a calling code bug and the function will panic.
     ///
-    /// - Stores each unique proposal once.
+    /// - Stores each unique proposal once, keeping its signature if any copy is signed.
     /// - Returns an error if equivocation is detected from the **same** validator.
     /// - Panics if proposals come from **different validators**.
     pub fn add(
         &mut self,
-        proposal: SignedProposal<Ctx>,
+        proposal: StoredProposal<Ctx>,
         validity: Validity,
     ) -> Result<(), RecordProposalError<Ctx>> {
         // Early return for exact duplicates
@@ -86,9 +147,13 @@ where
         self.verify_same_validator(&proposal);
 
         // Update existing proposal or add new one
-        match self.proposal_validity_mut(&proposal) {
-            Some(existing_validity) => {
+        match self.proposal_mut(&proposal) {
+            Some((existing, existing_validity)) => {
                 Self::update_validity(&proposal, existing_validity, validity);
+
+                if existing.signature.is_none() {
+                    existing.signature = proposal.signature.clone();
+                }
             }
             None => {
                 self.proposals.push((proposal.clone(), validity));
@@ -99,13 +164,13 @@ where
         self.check_equivocation(proposal)
     }
 
-    fn contains_exact(&self, proposal: &SignedProposal<Ctx>, validity: Validity) -> bool {
+    fn contains_exact(&self, proposal: &StoredProposal<Ctx>, validity: Validity) -> bool {
         self.proposals
             .iter()
             .any(|(p, v)| p == proposal && *v == validity)
     }
 
-    fn verify_same_validator(&self, proposal: &SignedProposal<Ctx>) {
+    fn verify_same_validator(&self, proposal: &StoredProposal<Ctx>) {
         if let Some(first) = self.get_first_proposal() {
             assert_eq!(
                 first.validator_address(),
@@ -118,14 +183,16 @@ where
         }
     }
 
-    fn proposal_validity_mut(&mut self, proposal: &SignedProposal<Ctx>) -> Option<&mut Validity> {
+    fn proposal_mut(
+        &mut self,
+        proposal: &StoredProposal<Ctx>,
+    ) -> Option<&mut (StoredProposal<Ctx>, Validity)> {
         self.proposals
             .iter_mut()
-            .find(|(p, _)| p == proposal)
-            .map(|(_, v)| v)
+            .find(|(p, _)| p.message == proposal.message)
     }
 
-    fn update_validity(proposal: &SignedProposal<Ctx>, current: &mut Validity, new: Validity) {
+    fn update_validity(proposal: &StoredProposal<Ctx>, current: &mut Validity, new: Validity) {
         use Validity::{Invalid, Valid};
 
         match (&current, &new) {
@@ -155,20 +222,20 @@ where
 
     fn check_equivocation(
         &self,
-        proposal: SignedProposal<Ctx>,
+        proposal: StoredProposal<Ctx>,
     ) -> Result<(), RecordProposalError<Ctx>> {
-        if self.proposals.len() > 1 {
-            let existing = self
-                .get_first_proposal()
-                .expect("at least one proposal should exist")
-                .clone();
+        let existing = self
+            .proposals
+            .iter()
+            .map(|(p, _)| p)
+            .find(|p| p.message != proposal.message);
 
-            Err(RecordProposalError::ConflictingProposal {
-                existing,
+        match existing {
+            Some(existing) => Err(RecordProposalError::ConflictingProposal {
+                existing: existing.clone(),
                 conflicting: proposal,
-            })
-        } else {
-            Ok(())
+            }),
+            None => Ok(()),
         }
     }
 }
@@ -200,7 +267,7 @@ where
         &self,
         round: Round,
         value_id: ValueId<Ctx>,
-    ) -> Option<&(SignedProposal<Ctx>, Validity)> {
+    ) -> Option<&(StoredProposal<Ctx>, Validity)> {
         self.per_round
             .get(&round)
             .and_then(|round_info| round_info.get_first_proposal_and_validity(value_id))
@@ -210,7 +277,7 @@ where
     pub fn get_proposals_and_validities_for_round(
         &self,
         round: Round,
-    ) -> &[(SignedProposal<Ctx>, Validity)] {
+    ) -> &[(StoredProposal<Ctx>, Validity)] {
         self.per_round
             .get(&round)
             .map(PerRound::get_proposals_and_validities)
@@ -233,7 +300,10 @@ where
     }
 
     /// Store a proposal, checking for conflicts and storing evidence of equivocation if necessary.
-    pub fn store_proposal(&mut self, proposal: SignedProposal<Ctx>, validity: Validity) {
+    ///
+    /// Evidence is only recorded when both proposals are signed, as an implicit proposal
+    /// does not prove anything about its proposer.
+    pub fn store_proposal(&mut self, proposal: StoredProposal<Ctx>, validity: Validity) {
         let per_round = self.per_round.entry(proposal.round()).or_default();
 
         match per_round.add(proposal, validity) {
@@ -248,7 +318,12 @@ where
                     "Received equivocating proposal {:?}, existing {:?}",
                     conflicting, existing
                 );
-                self.evidence.add(existing, conflicting);
+
+                if let (Some(existing), Some(conflicting)) =
+                    (existing.signed(), conflicting.signed())
+                {
+                    self.evidence.add(existing, conflicting);
+                }
             }
         }
     }
@@ -1,7 +1,7 @@
-use malachitebft_core_types::{Round, SignedProposal};
+use malachitebft_core_types::{Round, SignedProposal, Validity};
 use malachitebft_test::{Address, Height, PrivateKey, Proposal, TestContext, Value};
 
-use arc_malachitebft_core_driver::proposal_keeper::EvidenceMap;
+use arc_malachitebft_core_driver::proposal_keeper::{EvidenceMap, ProposalKeeper, StoredProposal};
 
 fn pk(id: &str) -> PrivateKey {
     let mut seed = [0u8; 32];
@@ -103,3 +103,26 @@ fn test_proposal_evidence_deduplication() {
         }
     }
 }
+
+#[test]
+fn implicit_proposals_are_not_evidence() {
+    let (p1, p2) = make_proposal_pair("Alice", 0, [100, 200]);
+    let mut keeper = ProposalKeeper::<TestContext>::new();
+
+    keeper.store_proposal(
+        StoredProposal::implicit(p1.message.clone()),
+        Validity::Valid,
+    );
+    keeper.store_proposal(p2.clone().into(), Validity::Valid);
+    assert!(keeper.evidence().is_empty());
+
+    // The signed proposal replaces the implicit one, which is now evidence of equivocation
+    keeper.store_proposal(p1.clone().into(), Validity::Valid);
+
+    let proposals = keeper.get_proposals_and_validities_for_round(Round::new(0));
+    assert_eq!(proposals.len(), 2);
+    assert_eq!(proposals[0].0.signed(), Some(p1.clone()));
+
+    let evidence = keeper.evidence().get(&addr("Alice")).unwrap();
+    assert_eq!(evidence, &vec![(p2, p1)]);
+}
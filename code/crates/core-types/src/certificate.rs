@@ -1,4 +1,6 @@
+use alloc::collections::BTreeSet;
 use alloc::string::ToString;
+use alloc::vec;
 use alloc::vec::Vec;
 use bytes::Bytes;
 use derive_where::derive_where;
@@ -6,7 +8,8 @@ use malachitebft_peer::PeerId;
 use thiserror::Error;
 
 use crate::{
-    BoxError, Context, NilOrVal, Round, Signature, SignedVote, ValueId, Vote, VoteType, VotingPower,
+    BoxError, Context, NilOrVal, Round, Signature, SignedVote, Validator, ValidatorSet, ValueId,
+    Vote, VoteType, VotingPower,
 };
 
 /// Represents a signature for a commit certificate, with the address of the validator that produced it.
@@ -72,6 +75,63 @@ impl<Ctx: Context> CommitCertificate<Ctx> {
     }
 }
 
+/// Data needed, along with a [`CommitCertificate`], to build a proof that a value was decided,
+/// without having to look up the consensus state of that height.
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub struct DecisionProof<Ctx: Context> {
+    /// The round at which the value was decided
+    pub round: Round,
+    /// The proposer of that round
+    pub proposer: Ctx::Address,
+    /// Bitmap of the validators which signed the certificate, in validator set order:
+    /// the `i`-th validator signed if bit `i % 8` of byte `i / 8` is set
+    pub signers: Vec<u8>,
+}
+
+impl<Ctx: Context> DecisionProof<Ctx> {
+    /// Build the proof material for the given certificate, decided with the given validator set
+    pub fn new(
+        certificate: &CommitCertificate<Ctx>,
+        validator_set: &Ctx::ValidatorSet,
+        proposer: Ctx::Address,
+    ) -> Self {
+        let signed: BTreeSet<&Ctx::Address> = certificate
+            .commit_signatures
+            .iter()
+            .map(|commit| &commit.address)
+            .collect();
+
+        let mut signers = vec![0u8; validator_set.count().div_ceil(8)];
+
+        for (index, validator) in validator_set.iter().enumerate() {
+            if signed.contains(validator.address()) {
+                signers[index / 8] |= 1 << (index % 8);
+            }
+        }
+
+        Self {
+            round: certificate.round,
+            proposer,
+            signers,
+        }
+    }
+
+    /// Whether the validator at the given index in the validator set signed the certificate
+    pub fn has_signed(&self, index: usize) -> bool {
+        self.signers
+            .get(index / 8)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    /// Number of validators which signed the certificate
+    pub fn num_signers(&self) -> usize {
+        self.signers
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+}
+
 /// Represents a signature for a polka certificate, with the address of the validator that produced it.
 #[derive_where(Clone, Debug, PartialEq, Eq)]
 pub struct PolkaSignature<Ctx: Context> {
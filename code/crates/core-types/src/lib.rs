@@ -64,8 +64,9 @@ pub type DoubleProposal<Ctx> = (SignedProposal<Ctx>, SignedProposal<Ctx>);
 pub type SignedExtension<Ctx> = SignedMessage<Ctx, <Ctx as Context>::Extension>;
 
 pub use certificate::{
-    CertificateError, CommitCertificate, CommitSignature, EnterRoundCertificate, PolkaCertificate,
-    PolkaSignature, RoundCertificate, RoundCertificateType, RoundSignature, ValueResponse,
+    CertificateError, CommitCertificate, CommitSignature, DecisionProof, EnterRoundCertificate,
+    PolkaCertificate, PolkaSignature, RoundCertificate, RoundCertificateType, RoundSignature,
+    ValueResponse,
 };
 pub use context::Context;
 pub use error::BoxError;
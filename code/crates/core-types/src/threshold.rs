@@ -30,6 +30,19 @@ pub struct ThresholdParams {
     pub honest: ThresholdParam,
 }
 
+impl ThresholdParams {
+    /// Thresholds for development networks of a single validator,
+    /// where more than a third of the voting power forms a quorum.
+    ///
+    /// These thresholds do not tolerate any faulty validator and must never be used
+    /// in production, nor with several validators, each of which could then form
+    /// a quorum on its own.
+    pub const DEV_MODE: Self = Self {
+        quorum: ThresholdParam::F_PLUS_ONE,
+        honest: ThresholdParam::F_PLUS_ONE,
+    };
+}
+
 impl Default for ThresholdParams {
     fn default() -> Self {
         Self {
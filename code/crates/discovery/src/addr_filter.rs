@@ -0,0 +1,285 @@
+//! Filtering of the addresses advertised by peers before dialing them.
+//!
+//! Peers share the listen addresses of other peers in their peer records,
+//! some of which cannot be reached from this node, eg. a private address
+//! in another subnet or the loopback address of a remote host. Dialing
+//! them only wastes dial attempts, so they are filtered out according to
+//! an [`AddressPolicy`]. Unless the application provides its own policy,
+//! the [`DefaultAddressPolicy`] is used, configured by [`ReachabilityConfig`].
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use libp2p::multiaddr::Protocol;
+use libp2p::Multiaddr;
+
+use crate::config::ReachabilityConfig;
+
+/// Coarse classification of an IP address, from the point of view of reachability
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AddrClass {
+    /// Only reachable from the same host
+    Loopback,
+    /// Only reachable from within the same private network
+    Private,
+    /// Reachable from anywhere
+    Public,
+}
+
+impl AddrClass {
+    pub fn of(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(ip) => Self::of_v4(ip),
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => Self::of_v4(ip),
+                None => Self::of_v6(ip),
+            },
+        }
+    }
+
+    fn of_v4(ip: Ipv4Addr) -> Self {
+        let [a, b, ..] = ip.octets();
+
+        if ip.is_loopback() || ip.is_unspecified() {
+            Self::Loopback
+        } else if ip.is_private()
+            || ip.is_link_local()
+            // Shared address space used by carrier-grade NATs, 100.64.0.0/10
+            || (a == 100 && (b & 0b1100_0000) == 64)
+        {
+            Self::Private
+        } else {
+            Self::Public
+        }
+    }
+
+    fn of_v6(ip: Ipv6Addr) -> Self {
+        let first = ip.segments()[0];
+
+        if ip.is_loopback() || ip.is_unspecified() {
+            Self::Loopback
+        } else if (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80 {
+            // Unique local, fc00::/7, and link-local unicast, fe80::/10
+            Self::Private
+        } else {
+            Self::Public
+        }
+    }
+}
+
+/// Policy classifying multiaddrs and deciding which of the addresses advertised
+/// by peers are expected to be reachable from this node.
+pub trait AddressPolicy: fmt::Debug + Send + Sync {
+    /// Classify the given address, or `None` if it cannot be classified,
+    /// eg. for DNS addresses or unspecified IP addresses.
+    fn classify(&self, addr: &Multiaddr) -> Option<AddrClass>;
+
+    /// Whether the remote address is expected to be reachable from the local one
+    fn is_reachable(&self, local: &Multiaddr, remote: &Multiaddr) -> bool;
+}
+
+/// Address policy based on the IP address found in the multiaddrs,
+/// see [`ReachabilityConfig`] for the rules it applies.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DefaultAddressPolicy {
+    config: ReachabilityConfig,
+}
+
+impl DefaultAddressPolicy {
+    pub fn new(config: ReachabilityConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl AddressPolicy for DefaultAddressPolicy {
+    fn classify(&self, addr: &Multiaddr) -> Option<AddrClass> {
+        ip_of(addr)
+            .filter(|ip| !ip.is_unspecified())
+            .map(AddrClass::of)
+    }
+
+    fn is_reachable(&self, local: &Multiaddr, remote: &Multiaddr) -> bool {
+        if self.config.trust_advertised_addrs {
+            return true;
+        }
+
+        match (ip_of(local), ip_of(remote)) {
+            (Some(local), Some(remote)) => is_ip_reachable(local, remote, &self.config),
+            _ => true,
+        }
+    }
+}
+
+/// Extract the IP address of a multiaddr, if it has one
+pub fn ip_of(addr: &Multiaddr) -> Option<IpAddr> {
+    addr.iter().find_map(|protocol| match protocol {
+        Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+        Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+        _ => None,
+    })
+}
+
+/// Whether both addresses belong to the same subnet, as defined by the configured prefix lengths
+pub fn same_subnet(a: IpAddr, b: IpAddr, config: &ReachabilityConfig) -> bool {
+    match (a, b) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let prefix_len = u32::from(config.ipv4_subnet_prefix_len.min(32));
+            let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+            u32::from(a) & mask == u32::from(b) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let prefix_len = u32::from(config.ipv6_subnet_prefix_len.min(128));
+            let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+            u128::from(a) & mask == u128::from(b) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Whether the remote IP address is expected to be reachable from the local one
+pub fn is_ip_reachable(local: IpAddr, remote: IpAddr, config: &ReachabilityConfig) -> bool {
+    match (AddrClass::of(local), AddrClass::of(remote)) {
+        (AddrClass::Loopback, AddrClass::Loopback) => true,
+        (AddrClass::Loopback, _) | (_, AddrClass::Loopback) => false,
+        (AddrClass::Private, AddrClass::Private) => same_subnet(local, remote, config),
+        (AddrClass::Private, AddrClass::Public) => config.private_to_public,
+        (AddrClass::Public, AddrClass::Private) => config.public_to_private,
+        (AddrClass::Public, AddrClass::Public) => true,
+    }
+}
+
+/// Keep only the remote addresses reachable from at least one of the local addresses,
+/// according to the given policy.
+///
+/// Nothing is filtered out if none of the local addresses can be classified by the policy.
+pub fn filter_reachable_addresses<'a>(
+    policy: &dyn AddressPolicy,
+    local_addrs: impl IntoIterator<Item = &'a Multiaddr>,
+    remote_addrs: Vec<Multiaddr>,
+) -> Vec<Multiaddr> {
+    let local_addrs: Vec<&Multiaddr> = local_addrs
+        .into_iter()
+        .filter(|addr| policy.classify(addr).is_some())
+        .collect();
+
+    if local_addrs.is_empty() {
+        return remote_addrs;
+    }
+
+    remote_addrs
+        .into_iter()
+        .filter(|remote| {
+            local_addrs
+                .iter()
+                .any(|local| policy.is_reachable(local, remote))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Multiaddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_classify() {
+        let class = |s: &str| AddrClass::of(s.parse().unwrap());
+
+        assert_eq!(class("127.0.0.1"), AddrClass::Loopback);
+        assert_eq!(class("10.1.2.3"), AddrClass::Private);
+        assert_eq!(class("192.168.1.1"), AddrClass::Private);
+        assert_eq!(class("100.64.0.1"), AddrClass::Private);
+        assert_eq!(class("8.8.8.8"), AddrClass::Public);
+        assert_eq!(class("::1"), AddrClass::Loopback);
+        assert_eq!(class("fd00::1"), AddrClass::Private);
+        assert_eq!(class("fe80::1"), AddrClass::Private);
+        assert_eq!(class("2001:db8::1"), AddrClass::Public);
+        assert_eq!(class("::ffff:10.0.0.1"), AddrClass::Private);
+    }
+
+    #[test]
+    fn test_subnet_prefix_len() {
+        let mut config = ReachabilityConfig::default();
+        let (a, b) = ("10.1.2.3".parse().unwrap(), "10.1.200.4".parse().unwrap());
+
+        config.ipv4_subnet_prefix_len = 16;
+        assert!(same_subnet(a, b, &config));
+
+        config.ipv4_subnet_prefix_len = 24;
+        assert!(!same_subnet(a, b, &config));
+
+        config.ipv4_subnet_prefix_len = 0;
+        assert!(same_subnet(a, "192.168.0.1".parse().unwrap(), &config));
+    }
+
+    #[test]
+    fn test_filter_reachable_addresses() {
+        let mut config = ReachabilityConfig::default();
+        let local = [
+            addr("/ip4/0.0.0.0/tcp/27000"),
+            addr("/ip4/10.1.0.1/tcp/27000"),
+        ];
+        let remote = vec![
+            addr("/ip4/127.0.0.1/tcp/27000"),
+            addr("/ip4/10.1.5.6/tcp/27000"),
+            addr("/ip4/10.2.5.6/tcp/27000"),
+            addr("/ip4/8.8.8.8/tcp/27000"),
+            addr("/dns/example.com/tcp/27000"),
+        ];
+
+        assert_eq!(
+            filter_reachable_addresses(&DefaultAddressPolicy::new(config), &local, remote.clone()),
+            vec![
+                addr("/ip4/10.1.5.6/tcp/27000"),
+                addr("/ip4/8.8.8.8/tcp/27000"),
+                addr("/dns/example.com/tcp/27000"),
+            ]
+        );
+
+        config.private_to_public = false;
+        assert_eq!(
+            filter_reachable_addresses(&DefaultAddressPolicy::new(config), &local, remote.clone()),
+            vec![
+                addr("/ip4/10.1.5.6/tcp/27000"),
+                addr("/dns/example.com/tcp/27000"),
+            ]
+        );
+
+        config.trust_advertised_addrs = true;
+        assert_eq!(
+            filter_reachable_addresses(&DefaultAddressPolicy::new(config), &local, remote.clone()),
+            remote
+        );
+    }
+
+    #[test]
+    fn test_custom_policy() {
+        /// Only allows dialing addresses reached through a relay
+        #[derive(Debug)]
+        struct RelayOnly;
+
+        impl AddressPolicy for RelayOnly {
+            fn classify(&self, _addr: &Multiaddr) -> Option<AddrClass> {
+                Some(AddrClass::Public)
+            }
+
+            fn is_reachable(&self, _local: &Multiaddr, remote: &Multiaddr) -> bool {
+                crate::is_relayed_addr(remote)
+            }
+        }
+
+        let local = [addr("/ip4/10.1.0.1/tcp/27000")];
+        let relayed = addr(
+            "/ip4/8.8.8.8/tcp/27000/p2p/12D3KooWD3eckifWpRn9wQpMG9R9hX3sD158z7EqHWmweQAJU5SA/p2p-circuit",
+        );
+        let remote = vec![addr("/ip4/10.1.5.6/tcp/27000"), relayed.clone()];
+
+        assert_eq!(
+            filter_reachable_addresses(&RelayOnly, &local, remote),
+            vec![relayed]
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Portable snapshot of the peers known to discovery, so that operators can seed a new node
+//! from the address book of an existing healthy node instead of relying solely on the
+//! bootstrap nodes.
+//!
+//! The file holds one address per line, ending with `/p2p/<peer id>` like the persistent
+//! peers in the configuration, blank lines and lines starting with `#` being ignored:
+//!
+//! ```text
+//! # Address book of node-1
+//! /ip4/10.0.0.1/tcp/27000/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN
+//! /ip6/fd00::1/tcp/27000/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN
+//! ```
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use libp2p::multiaddr::Protocol;
+use libp2p::{Multiaddr, PeerId, Swarm};
+use tracing::info;
+
+use crate::dial::DialData;
+use crate::{Discovery, DiscoveryClient};
+
+/// Peer of the address book, with the addresses it can be dialed at
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AddressBookEntry {
+    pub peer_id: PeerId,
+    pub listen_addrs: Vec<Multiaddr>,
+}
+
+/// Peers known to discovery, most recently seen first, see the [module docs](self)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AddressBook {
+    pub peers: Vec<AddressBookEntry>,
+}
+
+impl AddressBook {
+    /// Read an address book from a file
+    pub fn load(path: &Path) -> Result<Self, AddressBookError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Write the address book to a file, replacing it if it exists
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+}
+
+impl fmt::Display for AddressBook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.peers {
+            for addr in &entry.listen_addrs {
+                writeln!(f, "{}", addr.clone().with(Protocol::P2p(entry.peer_id)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for AddressBook {
+    type Err = AddressBookError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut book = AddressBook::default();
+
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |reason: String| AddressBookError::InvalidLine {
+                line: index + 1,
+                reason,
+            };
+
+            let mut addr = Multiaddr::from_str(line).map_err(|e| invalid(e.to_string()))?;
+
+            let Some(Protocol::P2p(peer_id)) = addr.pop() else {
+                return Err(invalid("address must end with /p2p/<peer id>".to_string()));
+            };
+
+            match book.peers.iter_mut().find(|entry| entry.peer_id == peer_id) {
+                Some(entry) => entry.listen_addrs.push(addr),
+                None => book.peers.push(AddressBookEntry {
+                    peer_id,
+                    listen_addrs: vec![addr],
+                }),
+            }
+        }
+
+        Ok(book)
+    }
+}
+
+/// Why an address book could not be read
+#[derive(Debug)]
+pub enum AddressBookError {
+    Io(std::io::Error),
+    InvalidLine { line: usize, reason: String },
+}
+
+impl fmt::Display for AddressBookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read the address book: {e}"),
+            Self::InvalidLine { line, reason } => {
+                write!(f, "invalid address on line {line}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AddressBookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::InvalidLine { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AddressBookError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    /// Export the peers identified since the node started, kept after they disconnect,
+    /// most recently seen first
+    pub fn address_book(&self) -> AddressBook {
+        let peers = self
+            .known_peers
+            .most_recent(usize::MAX)
+            .into_iter()
+            .map(|(peer_id, listen_addrs)| AddressBookEntry {
+                peer_id,
+                listen_addrs,
+            })
+            .collect();
+
+        AddressBook { peers }
+    }
+
+    /// Import the peers of an address book, eg. exported by another node, and dial them
+    /// to seed discovery. They are remembered as known peers, to reconnect to the network
+    /// if none of the bootstrap nodes can be reached.
+    ///
+    /// Returns the number of peers dialed, the banned peers, the peers already connected
+    /// and this node itself being skipped.
+    pub fn import_address_book(&mut self, swarm: &Swarm<C>, book: AddressBook) -> usize {
+        let mut dials = Vec::new();
+
+        // Import the least recently seen peers first, so that they are also forgotten first
+        for entry in book.peers.into_iter().rev() {
+            let dial_data = DialData::new(Some(entry.peer_id), entry.listen_addrs.clone());
+
+            if !self.should_dial(swarm, &dial_data, false) {
+                continue;
+            }
+
+            self.known_peers.record(entry.peer_id, entry.listen_addrs);
+
+            // The peer may have been dialed before, eg. if it was imported already
+            self.controller
+                .dial_clear_done_for_peer(entry.peer_id, &dial_data.listen_addrs());
+            self.controller.dial_register_done_on(&dial_data, false);
+            dials.push(dial_data);
+        }
+
+        info!(
+            "Dialing {} peers imported from an address book",
+            dials.len()
+        );
+
+        let dialed = dials.len();
+        self.queue_dials(dials, true);
+        self.known_peers_dialed = false;
+
+        dialed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let (a, b, relay) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let relayed: Multiaddr = format!("/ip4/10.0.0.3/tcp/27000/p2p/{relay}/p2p-circuit")
+            .parse()
+            .unwrap();
+
+        let book = AddressBook {
+            peers: vec![
+                AddressBookEntry {
+                    peer_id: b,
+                    listen_addrs: vec![
+                        "/ip4/10.0.0.1/tcp/27000".parse().unwrap(),
+                        "/ip6/fd00::1/udp/27000/quic-v1".parse().unwrap(),
+                    ],
+                },
+                AddressBookEntry {
+                    peer_id: a,
+                    listen_addrs: vec!["/ip4/10.0.0.2/tcp/27000".parse().unwrap(), relayed],
+                },
+            ],
+        };
+
+        let exported = format!("# Exported by node-1\n\n{book}");
+        assert_eq!(exported.parse::<AddressBook>().unwrap(), book);
+    }
+
+    #[test]
+    fn addresses_without_peer_id_are_rejected() {
+        let err = "/ip4/10.0.0.1/tcp/27000/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN\n/ip4/10.0.0.2/tcp/27000"
+            .parse::<AddressBook>()
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "invalid address on line 2: address must end with /p2p/<peer id>"
+        );
+    }
+}
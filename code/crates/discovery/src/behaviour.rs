@@ -5,7 +5,7 @@ use either::Either;
 use eyre::Result;
 use libp2p::identity::Keypair;
 use libp2p::kad::store::MemoryStore;
-use libp2p::kad::{Addresses, KBucketKey, KBucketRef, Mode, RoutingUpdate};
+use libp2p::kad::{Addresses, KBucketKey, KBucketRef, Mode, QueryId, RecordKey, RoutingUpdate};
 use libp2p::request_response::{self, OutboundRequestId, ProtocolSupport, ResponseChannel};
 use libp2p::swarm::behaviour::toggle::Toggle;
 use libp2p::swarm::NetworkBehaviour;
@@ -13,24 +13,88 @@ use libp2p::{kad, Multiaddr, PeerId, StreamProtocol};
 use serde::{Deserialize, Serialize};
 
 use crate::config::BootstrapProtocol;
-use crate::Config;
+use crate::{Config, PeerCapabilities};
 
 /// Protobuf-encoded signed peer record bytes.
 /// Use `SignedEnvelope::from_protobuf_encoding()` to decode.
 pub type SignedPeerRecordBytes = Vec<u8>;
 
+/// Reason given to a peer before intentionally closing the connections to it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisconnectReason {
+    /// The connection was not upgraded to an inbound or outbound connection in time
+    EphemeralTimeout,
+    /// The inbound peers limit is reached
+    PeerLimit,
+    /// The peer is not allowed to connect, eg. because it is not in the allowlist
+    NotAllowed,
+    /// The peer was evicted in favor of another inbound peer
+    Evicted,
+    /// The peer misbehaved, eg. by exceeding the peers request rate limit
+    Banned,
+    /// The node is shutting down
+    Shutdown,
+}
+
+impl DisconnectReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EphemeralTimeout => "ephemeral timeout",
+            Self::PeerLimit => "peer limit",
+            Self::NotAllowed => "not allowed",
+            Self::Evicted => "evicted",
+            Self::Banned => "banned",
+            Self::Shutdown => "shutdown",
+        }
+    }
+}
+
+/// Current load of a relay server, advertised to its peers so that the nodes
+/// behind a NAT can pick the least loaded relay
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayLoad {
+    /// Number of circuits currently relayed
+    pub circuits: u32,
+    /// Maximum number of circuits the relay accepts
+    pub max_circuits: u32,
+}
+
+impl RelayLoad {
+    /// Ratio of the relayed circuits to the capacity of the relay, a full relay being at `1.0`
+    pub fn utilization(&self) -> f64 {
+        if self.max_circuits == 0 {
+            return 1.0;
+        }
+
+        f64::from(self.circuits) / f64::from(self.max_circuits)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Request {
     /// Peer exchange with signed peer records, cryptographically verified
     Peers(Vec<SignedPeerRecordBytes>),
-    Connect(),
+    /// Ask the peer to upgrade the connection to an inbound peer, advertising our capabilities
+    Connect(PeerCapabilities),
+    /// Sent before intentionally closing the connections to the peer,
+    /// so that it can replace us right away instead of waiting for timeouts
+    Disconnect(DisconnectReason),
+    /// Sent periodically to the relays in use, to refresh their advertised load
+    RelayLoad(),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Response {
     /// Peer exchange with signed peer records, cryptographically verified
     Peers(Vec<SignedPeerRecordBytes>),
-    Connect(bool),
+    /// Whether the peer accepted the connect request, along with its capabilities
+    Connect(bool, PeerCapabilities),
+    /// Acknowledges a disconnect request, the connections are closed upon receipt
+    Disconnect(),
+    /// Peer exchange from a relay server, advertising its current load
+    PeersWithRelayLoad(Vec<SignedPeerRecordBytes>, RelayLoad),
+    /// Current load of the peer if it is a relay server
+    RelayLoad(Option<RelayLoad>),
 }
 
 #[derive(Debug)]
@@ -131,10 +195,24 @@ impl Behaviour {
             ),
         );
 
-        let request_response = request_response::cbor::Behaviour::new(
-            request_response_protocol(discovery_regres_protocol)?,
-            request_response_config(),
-        );
+        let request_response_protocol = request_response_protocol(discovery_regres_protocol)?;
+        let request_response = match config.max_message_size {
+            Some(max_size) => {
+                let codec = request_response::cbor::codec::Codec::default()
+                    .set_request_size_maximum(max_size as u64)
+                    .set_response_size_maximum(max_size as u64);
+
+                request_response::cbor::Behaviour::with_codec(
+                    codec,
+                    request_response_protocol,
+                    request_response_config(),
+                )
+            }
+            None => request_response::cbor::Behaviour::new(
+                request_response_protocol,
+                request_response_config(),
+            ),
+        };
 
         Ok(Self {
             kademlia,
@@ -148,6 +226,12 @@ pub trait DiscoveryClient: NetworkBehaviour {
 
     fn kbuckets(&mut self) -> impl Iterator<Item = KBucketRef<'_, KBucketKey<PeerId>, Addresses>>;
 
+    fn start_providing(&mut self, key: RecordKey) -> Result<QueryId, kad::store::Error>;
+
+    fn stop_providing(&mut self, key: &RecordKey);
+
+    fn get_providers(&mut self, key: RecordKey) -> QueryId;
+
     fn send_request(&mut self, peer_id: &PeerId, req: Request) -> OutboundRequestId;
 
     fn send_response(
@@ -0,0 +1,92 @@
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+use crate::{Discovery, DiscoveryClient, NodeRole};
+
+/// Capabilities and metadata exchanged along with the connect request and its response,
+/// and kept alongside the Identify info of the peer while it is connected, so that the
+/// peers can be selected with more information than just their addresses.
+///
+/// All fields are optional, a node only advertising what it knows about itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerCapabilities {
+    /// Role of the node, see [`Discovery::advertise_role`]
+    pub role: Option<NodeRole>,
+    /// Chain followed by the node. Peers following another chain are neither
+    /// upgraded to outbound or inbound peers nor selected as outbound candidates.
+    pub chain_id: Option<String>,
+    /// Lowest height whose decided value the node still serves to syncing peers
+    pub retained_height: Option<u64>,
+    /// Maximum number of circuits relayed by the node, if it runs a relay server
+    pub relay_capacity: Option<u32>,
+}
+
+impl PeerCapabilities {
+    /// Whether both nodes follow the same chain, or at least one of them does not tell
+    pub fn is_same_chain(&self, other: &PeerCapabilities) -> bool {
+        match (&self.chain_id, &other.chain_id) {
+            (Some(ours), Some(theirs)) => ours == theirs,
+            _ => true,
+        }
+    }
+}
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    /// Capabilities advertised by this node in its connect requests and responses
+    pub fn local_capabilities(&self) -> &PeerCapabilities {
+        &self.local_capabilities
+    }
+
+    /// Set the chain advertised to the peers in the connect requests and responses
+    pub fn set_chain_id(&mut self, chain_id: Option<String>) {
+        self.local_capabilities.chain_id = chain_id;
+    }
+
+    /// Set the lowest height whose decided value this node still serves,
+    /// advertised to the peers in the next connect requests and responses
+    pub fn set_retained_height(&mut self, retained_height: Option<u64>) {
+        self.local_capabilities.retained_height = retained_height;
+    }
+
+    /// Capabilities advertised by a connected peer, if it exchanged a connect request with us
+    pub fn peer_capabilities(&self, peer_id: &PeerId) -> Option<&PeerCapabilities> {
+        self.peer_capabilities.get(peer_id)
+    }
+
+    pub(crate) fn record_peer_capabilities(
+        &mut self,
+        peer_id: PeerId,
+        capabilities: PeerCapabilities,
+    ) {
+        self.peer_capabilities.insert(peer_id, capabilities);
+    }
+
+    /// Whether a peer is known to follow another chain than this node
+    pub(crate) fn is_on_other_chain(&self, peer_id: &PeerId) -> bool {
+        self.peer_capabilities
+            .get(peer_id)
+            .is_some_and(|capabilities| !self.local_capabilities.is_same_chain(capabilities))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peers_on_unknown_chains_are_compatible() {
+        let chain = |chain_id: Option<&str>| PeerCapabilities {
+            chain_id: chain_id.map(str::to_string),
+            ..Default::default()
+        };
+
+        assert!(chain(Some("main")).is_same_chain(&chain(Some("main"))));
+        assert!(!chain(Some("main")).is_same_chain(&chain(Some("test"))));
+        assert!(chain(Some("main")).is_same_chain(&chain(None)));
+        assert!(chain(None).is_same_chain(&chain(Some("test"))));
+    }
+}
@@ -1,5 +1,14 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
+use libp2p::{Multiaddr, PeerId};
+use rand::Rng;
+
+use crate::addr_filter::AddressPolicy;
+use crate::eviction::EvictionPolicy;
+
 const DEFAULT_NUM_OUTBOUND_PEERS: usize = 50;
 const DEFAULT_NUM_INBOUND_PEERS: usize = 50;
 
@@ -7,10 +16,26 @@ const DEFAULT_MAX_CONNECTIONS_PER_PEER: usize = 5;
 
 const DEFAULT_EPHEMERAL_CONNECTION_TIMEOUT: Duration = Duration::from_secs(15);
 
+const DEFAULT_MAX_CONCURRENT_DIALS: usize = 20;
+
 const DEFAULT_DIAL_MAX_RETRIES: usize = 5;
 const DEFAULT_PEERS_REQUEST_MAX_RETRIES: usize = 5;
 const DEFAULT_CONNECT_REQUEST_MAX_RETRIES: usize = 0;
 
+const DEFAULT_BACKOFF_INITIAL_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_BACKOFF_MULTIPLIER: f64 = 2.0;
+const DEFAULT_BACKOFF_MAX_DELAY: Duration = Duration::from_secs(60);
+const DEFAULT_BACKOFF_JITTER: f64 = 0.5;
+
+const DEFAULT_RECONNECT_SPREAD_PER_PEER: Duration = Duration::from_millis(100);
+const DEFAULT_RECONNECT_MAX_SPREAD: Duration = Duration::from_secs(10);
+
+const DEFAULT_IPV4_SUBNET_PREFIX_LEN: u8 = 16;
+const DEFAULT_IPV6_SUBNET_PREFIX_LEN: u8 = 48;
+
+const DEFAULT_AUTO_RELAY_NUM_RELAYS: usize = 2;
+const DEFAULT_AUTO_RELAY_MAX_CIRCUITS: u32 = 128;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
 pub enum BootstrapProtocol {
     #[default]
@@ -18,19 +43,193 @@ pub enum BootstrapProtocol {
     Full,
 }
 
+/// IP family whose addresses are dialed first, when a peer advertises addresses of both families
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum AddressFamilyPreference {
+    /// Dial the addresses in the order advertised by the peer
+    #[default]
+    Any,
+    Ipv6,
+    Ipv4,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
 pub enum Selector {
     #[default]
     Kademlia,
     Random,
+    Latency,
+}
+
+/// Exponential backoff policy used to space out dial and request retries.
+///
+/// The delay before retry `n` (starting at 1) is `initial_delay * multiplier^(n - 1)`,
+/// capped at `max_delay`, and then randomized by up to `jitter` (a fraction of the delay)
+/// in either direction so that nodes restarting together do not retry in lockstep.
+/// The maximum number of attempts is configured per action, see `dial_max_retries`,
+/// `request_max_retries` and `connect_request_max_retries` in [`Config`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BackoffConfig {
+    pub initial_delay: Duration,
+    pub multiplier: f64,
+    pub max_delay: Duration,
+    pub jitter: f64,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: DEFAULT_BACKOFF_INITIAL_DELAY,
+            multiplier: DEFAULT_BACKOFF_MULTIPLIER,
+            max_delay: DEFAULT_BACKOFF_MAX_DELAY,
+            jitter: DEFAULT_BACKOFF_JITTER,
+        }
+    }
+}
+
+impl BackoffConfig {
+    /// Delay before the given retry, without jitter.
+    pub fn delay(&self, retry: usize) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let factor = self.multiplier.max(1.0).powi(exponent);
+        let delay = self.initial_delay.as_secs_f64() * factor;
+
+        if !delay.is_finite() || delay >= self.max_delay.as_secs_f64() {
+            self.max_delay
+        } else {
+            Duration::from_secs_f64(delay)
+        }
+    }
+
+    /// Delay before the given retry, randomized by up to `jitter` in either direction.
+    pub fn jittered_delay(&self, retry: usize, rng: &mut impl Rng) -> Duration {
+        let delay = self.delay(retry);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+
+        if jitter == 0.0 {
+            return delay;
+        }
+
+        delay.mul_f64(rng.gen_range(1.0 - jitter..=1.0 + jitter))
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Spreads out the re-dials of the persistent peers and bootstrap nodes
+/// becoming dialable at the same time, eg. after a network-wide restart.
+///
+/// Each of the `n` peers re-dialed together is dialed after a random delay between zero
+/// and `spread_per_peer * n`, capped at `max_spread`, so that the nodes coming back
+/// together do not dial in lockstep and overwhelm the first nodes to come back.
+/// The first dials made when the node starts are not delayed, and failed dials
+/// are retried according to [`BackoffConfig`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReconnectConfig {
+    pub spread_per_peer: Duration,
+    pub max_spread: Duration,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            spread_per_peer: DEFAULT_RECONNECT_SPREAD_PER_PEER,
+            max_spread: DEFAULT_RECONNECT_MAX_SPREAD,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Window over which the given number of dials started together are spread.
+    pub fn spread(&self, num_dials: usize) -> Duration {
+        let num_dials = num_dials.min(u32::MAX as usize) as u32;
+
+        self.spread_per_peer
+            .saturating_mul(num_dials)
+            .min(self.max_spread)
+    }
+
+    /// Random delay before one of the given number of dials started together,
+    /// `None` if the dials are not spread out.
+    pub fn jittered_delay(&self, num_dials: usize, rng: &mut impl Rng) -> Option<Duration> {
+        let spread = self.spread(num_dials);
+
+        if spread.is_zero() {
+            return None;
+        }
+
+        Some(spread.mul_f64(rng.gen_range(0.0..=1.0)))
+    }
+}
+
+/// Reachability rules applied by the [`DefaultAddressPolicy`](crate::addr_filter::DefaultAddressPolicy)
+/// to decide which of the addresses advertised by peers are worth dialing.
+///
+/// Two private addresses are considered reachable from each other if they share
+/// the same subnet, as given by the prefix lengths. Loopback addresses are only
+/// reachable from loopback addresses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ReachabilityConfig {
+    pub ipv4_subnet_prefix_len: u8,
+    pub ipv6_subnet_prefix_len: u8,
+
+    /// Whether a node with a private address can reach public addresses, eg. through a NAT
+    pub private_to_public: bool,
+
+    /// Whether a node with a public address can reach private addresses
+    pub public_to_private: bool,
+
+    /// Dial all the addresses advertised by peers without filtering them,
+    /// eg. for overlay networks where the policy above does not hold
+    pub trust_advertised_addrs: bool,
+}
+
+impl Default for ReachabilityConfig {
+    fn default() -> Self {
+        Self {
+            ipv4_subnet_prefix_len: DEFAULT_IPV4_SUBNET_PREFIX_LEN,
+            ipv6_subnet_prefix_len: DEFAULT_IPV6_SUBNET_PREFIX_LEN,
+            private_to_public: true,
+            public_to_private: false,
+            trust_advertised_addrs: false,
+        }
+    }
+}
+
+/// Automatic relay election among the nodes, see
+/// [`Discovery::elect_relays`](crate::Discovery::elect_relays).
+///
+/// Publicly reachable nodes run a relay server accepting up to `max_circuits` circuits
+/// and advertise it to their peers, while the nodes behind a NAT pick the `num_relays`
+/// least loaded of the relays advertised by their peers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AutoRelayConfig {
+    pub num_relays: usize,
+    pub max_circuits: u32,
+}
+
+impl Default for AutoRelayConfig {
+    fn default() -> Self {
+        Self {
+            num_relays: DEFAULT_AUTO_RELAY_NUM_RELAYS,
+            max_circuits: DEFAULT_AUTO_RELAY_MAX_CIRCUITS,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Config {
     pub enabled: bool,
 
     pub persistent_peers_only: bool,
 
+    /// If set, only peers in this list are allowed to connect to or be dialed by this node.
+    /// Used by permissioned networks to reject unknown peers at the network layer.
+    pub allowed_peers: Option<HashSet<PeerId>>,
+
+    /// Peers always kept as outbound peers, independently of the discovery process.
+    /// Unlike bootstrap nodes, they are never closed as ephemeral and are re-dialed
+    /// forever, with backoff, when lost. The addresses must end with `/p2p/<peer id>`.
+    pub persistent_peers: Vec<Multiaddr>,
+
     pub bootstrap_protocol: BootstrapProtocol,
     pub selector: Selector,
 
@@ -43,9 +242,60 @@ pub struct Config {
 
     pub ephemeral_connection_timeout: Duration,
 
+    /// Maximum duration of the initial discovery process, after which the node
+    /// stops bootstrapping and carries on with the peers found so far.
+    /// No timeout if `None`.
+    pub bootstrap_timeout: Option<Duration>,
+
+    /// IP family whose addresses are dialed first, when a peer advertises both
+    pub address_family_preference: AddressFamilyPreference,
+
+    /// Delay after which the addresses of the other IP family are dialed, if the peer is not
+    /// connected yet through the addresses of the preferred family (happy eyeballs).
+    /// All the addresses of a peer are dialed at once if `None`.
+    pub happy_eyeballs_delay: Option<Duration>,
+
+    /// Interval at which the load of the relays in use is refreshed, and the peers reached
+    /// only through an overloaded relay are dialed through a less loaded one.
+    /// Relays are never rebalanced if `None`.
+    pub relay_rebalance_interval: Option<Duration>,
+
+    /// Automatic election of the relay servers among the publicly reachable nodes,
+    /// and selection of the relays by the nodes behind a NAT. Disabled if `None`.
+    pub auto_relay: Option<AutoRelayConfig>,
+
+    /// Maximum number of outbound dials in progress at the same time
+    pub max_concurrent_dials: usize,
+
+    /// Maximum number of outbound dials started per second, unlimited if `None`.
+    /// Dials over the limit stay queued until they can be started.
+    pub dials_per_second: Option<u32>,
+
     pub dial_max_retries: usize,
     pub request_max_retries: usize,
     pub connect_request_max_retries: usize,
+
+    pub retry_backoff: BackoffConfig,
+
+    /// Spreading of the re-dials of the persistent peers and bootstrap nodes
+    pub reconnect: ReconnectConfig,
+
+    pub reachability: ReachabilityConfig,
+
+    /// Custom address policy, replacing the default policy configured by `reachability`
+    pub address_policy: Option<Arc<dyn AddressPolicy>>,
+
+    /// Policy selecting an inbound peer to evict when the inbound peers limit is reached,
+    /// new peers are refused if none is set
+    pub eviction_policy: Option<Arc<dyn EvictionPolicy>>,
+
+    /// Maximum size of the requests and responses of the discovery protocol,
+    /// the default limits of the CBOR codec applying if `None`
+    pub max_message_size: Option<usize>,
+
+    /// Address book imported at startup to seed discovery, eg. exported by another node,
+    /// see [`AddressBook`](crate::AddressBook)
+    pub address_book: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -59,6 +309,10 @@ impl Default for Config {
 
             persistent_peers_only: false,
 
+            allowed_peers: None,
+
+            persistent_peers: Vec::new(),
+
             bootstrap_protocol: BootstrapProtocol::default(),
             selector: Selector::default(),
 
@@ -70,9 +324,31 @@ impl Default for Config {
 
             ephemeral_connection_timeout: DEFAULT_EPHEMERAL_CONNECTION_TIMEOUT,
 
+            bootstrap_timeout: None,
+
+            address_family_preference: AddressFamilyPreference::default(),
+            happy_eyeballs_delay: None,
+
+            relay_rebalance_interval: None,
+            auto_relay: None,
+
+            max_concurrent_dials: DEFAULT_MAX_CONCURRENT_DIALS,
+            dials_per_second: None,
+
             dial_max_retries: DEFAULT_DIAL_MAX_RETRIES,
             request_max_retries: DEFAULT_PEERS_REQUEST_MAX_RETRIES,
             connect_request_max_retries: DEFAULT_CONNECT_REQUEST_MAX_RETRIES,
+
+            retry_backoff: BackoffConfig::default(),
+            reconnect: ReconnectConfig::default(),
+
+            reachability: ReachabilityConfig::default(),
+            address_policy: None,
+            eviction_policy: None,
+
+            max_message_size: None,
+
+            address_book: None,
         }
     }
 }
@@ -94,6 +370,23 @@ impl Config {
         self.persistent_peers_only = persistent_peers_only;
     }
 
+    /// Set the peers always kept as outbound peers, see [`Config::persistent_peers`].
+    pub fn set_persistent_peers(&mut self, persistent_peers: Vec<Multiaddr>) {
+        self.persistent_peers = persistent_peers;
+    }
+
+    /// Restrict connections to the given peers, or allow all peers if `None`.
+    pub fn set_allowed_peers(&mut self, allowed_peers: Option<HashSet<PeerId>>) {
+        self.allowed_peers = allowed_peers;
+    }
+
+    /// Whether the peer is allowed by the allowlist, always true if there is no allowlist.
+    pub fn is_peer_allowed(&self, peer_id: &PeerId) -> bool {
+        self.allowed_peers
+            .as_ref()
+            .is_none_or(|allowed_peers| allowed_peers.contains(peer_id))
+    }
+
     pub fn set_bootstrap_protocol(&mut self, protocol: BootstrapProtocol) {
         self.bootstrap_protocol = protocol;
     }
@@ -118,4 +411,133 @@ impl Config {
     pub fn set_ephemeral_connection_timeout(&mut self, timeout: Duration) {
         self.ephemeral_connection_timeout = timeout;
     }
+
+    pub fn set_bootstrap_timeout(&mut self, timeout: Option<Duration>) {
+        self.bootstrap_timeout = timeout;
+    }
+
+    pub fn set_address_family_preference(
+        &mut self,
+        preference: AddressFamilyPreference,
+        happy_eyeballs_delay: Option<Duration>,
+    ) {
+        self.address_family_preference = preference;
+        self.happy_eyeballs_delay = happy_eyeballs_delay;
+    }
+
+    pub fn set_relay_rebalance_interval(&mut self, interval: Option<Duration>) {
+        self.relay_rebalance_interval = interval;
+    }
+
+    pub fn set_auto_relay(&mut self, auto_relay: Option<AutoRelayConfig>) {
+        self.auto_relay = auto_relay;
+    }
+
+    pub fn set_dial_limits(&mut self, max_concurrent_dials: usize, dials_per_second: Option<u32>) {
+        self.max_concurrent_dials = max_concurrent_dials;
+        self.dials_per_second = dials_per_second;
+    }
+
+    pub fn set_retry_backoff(&mut self, backoff: BackoffConfig) {
+        self.retry_backoff = backoff;
+    }
+
+    pub fn set_reconnect(&mut self, reconnect: ReconnectConfig) {
+        self.reconnect = reconnect;
+    }
+
+    pub fn set_reachability(&mut self, reachability: ReachabilityConfig) {
+        self.reachability = reachability;
+    }
+
+    pub fn set_address_policy(&mut self, address_policy: Arc<dyn AddressPolicy>) {
+        self.address_policy = Some(address_policy);
+    }
+
+    pub fn set_eviction_policy(&mut self, eviction_policy: Arc<dyn EvictionPolicy>) {
+        self.eviction_policy = Some(eviction_policy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = BackoffConfig {
+            initial_delay: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(3),
+            jitter: 0.0,
+        };
+
+        assert_eq!(backoff.delay(1), Duration::from_millis(500));
+        assert_eq!(backoff.delay(2), Duration::from_secs(1));
+        assert_eq!(backoff.delay(3), Duration::from_secs(2));
+        assert_eq!(backoff.delay(4), Duration::from_secs(3));
+        assert_eq!(backoff.delay(100), Duration::from_secs(3));
+        assert_eq!(backoff.delay(usize::MAX), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_backoff_jitter_bounds() {
+        let backoff = BackoffConfig {
+            jitter: 0.25,
+            ..BackoffConfig::default()
+        };
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for retry in 1..10 {
+            let base = backoff.delay(retry);
+            let delay = backoff.jittered_delay(retry, &mut rng);
+
+            assert!(delay >= base.mul_f64(0.75));
+            assert!(delay <= base.mul_f64(1.25));
+        }
+    }
+
+    #[test]
+    fn test_reconnect_spread() {
+        let reconnect = ReconnectConfig {
+            spread_per_peer: Duration::from_millis(100),
+            max_spread: Duration::from_secs(2),
+        };
+        let mut rng = StdRng::seed_from_u64(42);
+
+        assert_eq!(reconnect.spread(0), Duration::ZERO);
+        assert_eq!(reconnect.spread(5), Duration::from_millis(500));
+        assert_eq!(reconnect.spread(500), Duration::from_secs(2));
+        assert_eq!(reconnect.spread(usize::MAX), Duration::from_secs(2));
+
+        assert_eq!(reconnect.jittered_delay(0, &mut rng), None);
+
+        for _ in 0..100 {
+            let delay = reconnect.jittered_delay(500, &mut rng).unwrap();
+            assert!(delay <= Duration::from_secs(2));
+        }
+
+        let disabled = ReconnectConfig {
+            spread_per_peer: Duration::ZERO,
+            ..reconnect
+        };
+
+        assert_eq!(disabled.jittered_delay(500, &mut rng), None);
+    }
+
+    #[test]
+    fn test_is_peer_allowed() {
+        let (allowed, other) = (PeerId::random(), PeerId::random());
+        let mut config = Config::default();
+
+        assert!(config.is_peer_allowed(&allowed));
+        assert!(config.is_peer_allowed(&other));
+
+        config.set_allowed_peers(Some(HashSet::from([allowed])));
+
+        assert!(config.is_peer_allowed(&allowed));
+        assert!(!config.is_peer_allowed(&other));
+    }
 }
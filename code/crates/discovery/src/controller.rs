@@ -2,25 +2,62 @@
 
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use libp2p::{request_response::OutboundRequestId, swarm::ConnectionId, Multiaddr, PeerId};
 use tokio::sync::mpsc;
 use tracing::error;
 
-use crate::{request::RequestData, DialData};
+use crate::{request::RequestData, Config, DialData, DisconnectReason};
 
-const DEFAULT_DIAL_CONCURRENT_FACTOR: usize = 20;
 const DEFAULT_PEERS_REQUEST_CONCURRENT_FACTOR: usize = 20;
 const DEFAULT_CONNECT_REQUEST_CONCURRENT_FACTOR: usize = 100;
 const DEFAULT_CLOSE_CONCURRENT_FACTOR: usize = usize::MAX;
 
+/// Token bucket limiting how many times an action is performed per second,
+/// allowing bursts of up to `rate` actions.
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Throttle {
+    pub(crate) fn new(per_second: u32) -> Self {
+        let rate = f64::from(per_second.max(1));
+
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn tokens_at(&self, now: Instant) -> f64 {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        (self.tokens + elapsed * self.rate).min(self.rate)
+    }
+
+    pub(crate) fn is_available(&self, now: Instant) -> bool {
+        self.tokens_at(now) >= 1.0
+    }
+
+    pub(crate) fn acquire(&mut self, now: Instant) {
+        self.tokens = (self.tokens_at(now) - 1.0).max(0.0);
+        self.last_refill = now;
+    }
+}
+
 #[derive(Debug)]
 pub struct Action<T, U, V> {
     tx_queue: mpsc::UnboundedSender<V>,
     rx_queue: mpsc::UnboundedReceiver<V>,
     done_on: HashSet<T>,
     concurrent_factor: usize,
+    throttle: Option<Throttle>,
     in_progress: HashMap<U, V>,
 }
 
@@ -38,10 +75,18 @@ where
             rx_queue,
             done_on: HashSet::new(),
             concurrent_factor,
+            throttle: None,
             in_progress: HashMap::new(),
         }
     }
 
+    /// Limit the rate at which the action is performed, on top of the concurrency limit.
+    /// Queued values are not received while the rate is exceeded.
+    pub(crate) fn with_throttle(mut self, per_second: Option<u32>) -> Self {
+        self.throttle = per_second.map(Throttle::new);
+        self
+    }
+
     pub(crate) fn add_to_queue(&mut self, value: V, delay: Option<Duration>) {
         // Avoid spawning a new task if the delay is None
         if delay.is_none() {
@@ -84,9 +129,16 @@ where
 
     pub(crate) fn can_perform(&self) -> bool {
         self.in_progress.len() < self.concurrent_factor
+            && self
+                .throttle
+                .as_ref()
+                .is_none_or(|throttle| throttle.is_available(Instant::now()))
     }
 
     pub(crate) fn register_in_progress(&mut self, key: U, value: V) {
+        if let Some(throttle) = self.throttle.as_mut() {
+            throttle.acquire(Instant::now());
+        }
         self.in_progress.insert(key, value);
     }
 
@@ -122,13 +174,14 @@ pub struct Controller {
     pub dial: Action<PeerData, ConnectionId, DialData>,
     pub peers_request: Action<PeerId, OutboundRequestId, RequestData>,
     pub connect_request: Action<PeerId, OutboundRequestId, RequestData>,
-    pub close: Action<(), (), (PeerId, ConnectionId)>,
+    pub close: Action<(), (), (PeerId, ConnectionId, Option<DisconnectReason>)>,
 }
 
 impl Controller {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(config: &Config) -> Self {
         Controller {
-            dial: Action::new(DEFAULT_DIAL_CONCURRENT_FACTOR),
+            dial: Action::new(config.max_concurrent_dials.max(1))
+                .with_throttle(config.dials_per_second),
             peers_request: Action::new(DEFAULT_PEERS_REQUEST_CONCURRENT_FACTOR),
             connect_request: Action::new(DEFAULT_CONNECT_REQUEST_CONCURRENT_FACTOR),
             close: Action::new(DEFAULT_CLOSE_CONCURRENT_FACTOR),
@@ -252,11 +305,49 @@ mod tests {
         assert_eq!(action.remove_in_progress(&2), None);
     }
 
+    #[test]
+    fn test_throttle() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(2);
+
+        // Bursts of up to the rate are allowed
+        assert_eq!(throttle.is_available(start), true);
+        throttle.acquire(start);
+        assert_eq!(throttle.is_available(start), true);
+        throttle.acquire(start);
+        assert_eq!(throttle.is_available(start), false);
+
+        // Tokens are refilled over time
+        let later = start + Duration::from_millis(500);
+        assert_eq!(throttle.is_available(later), true);
+        throttle.acquire(later);
+        assert_eq!(throttle.is_available(later), false);
+
+        // Up to the rate
+        let much_later = later + Duration::from_secs(10);
+        throttle.acquire(much_later);
+        throttle.acquire(much_later);
+        assert_eq!(throttle.is_available(much_later), false);
+    }
+
+    #[test]
+    fn test_action_throttle() {
+        let mut action = Action::<PeerData, u32, u32>::new(10).with_throttle(Some(1));
+
+        assert_eq!(action.can_perform(), true);
+        action.register_in_progress(1, 1);
+        assert_eq!(action.can_perform(), false);
+
+        // Completing the action does not bypass the rate limit
+        action.remove_in_progress(&1);
+        assert_eq!(action.can_perform(), false);
+    }
+
     #[test]
     fn test_address_poisoning_prevented() {
         use crate::dial::DialData;
 
-        let mut controller = Controller::new();
+        let mut controller = Controller::new(&Config::default());
 
         // Attacker claims victim's address
         let attacker_peer_id = PeerId::random();
@@ -297,7 +388,7 @@ mod tests {
     fn test_bootstrap_addresses_registered() {
         use crate::dial::DialData;
 
-        let mut controller = Controller::new();
+        let mut controller = Controller::new(&Config::default());
 
         let bootstrap_peer_id = PeerId::random();
         let bootstrap_addr = Multiaddr::from_str("/ip4/10.0.0.1/tcp/8000").unwrap();
@@ -1,6 +1,22 @@
-use libp2p::{swarm::dial_opts::DialOpts, Multiaddr, PeerId};
+use libp2p::{multiaddr::Protocol, swarm::dial_opts::DialOpts, Multiaddr, PeerId};
+use tracing::{field, info_span, Span};
 
-use crate::util::Retry;
+use crate::config::AddressFamilyPreference;
+use crate::util::{relay_peer_id, Retry};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+fn address_family(addr: &Multiaddr) -> Option<AddressFamily> {
+    match addr.iter().next()? {
+        Protocol::Ip4(_) | Protocol::Dns4(_) => Some(AddressFamily::Ipv4),
+        Protocol::Ip6(_) | Protocol::Dns6(_) => Some(AddressFamily::Ipv6),
+        _ => None,
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct DialData {
@@ -10,6 +26,15 @@ pub struct DialData {
     /// Whether this dial originated from bootstrap/persistent peer configuration.
     /// Used to determine retry behavior, only bootstrap dials get unlimited retries.
     is_bootstrap: bool,
+    /// Whether this dial is for a peer from the discovery `persistent_peers`,
+    /// which is retried forever with backoff.
+    is_persistent: bool,
+    /// Whether this dial is for the addresses of the non-preferred IP family of a peer,
+    /// started after a delay while the preferred ones are still being dialed.
+    /// Such dials are never retried, the dial of the preferred addresses being retried instead.
+    is_fallback: bool,
+    /// Span of the dial and its retries, see [`DialData::start_span`]
+    span: Span,
 }
 
 impl DialData {
@@ -19,6 +44,9 @@ impl DialData {
             listen_addrs,
             retry: Retry::new(),
             is_bootstrap: false,
+            is_persistent: false,
+            is_fallback: false,
+            span: Span::none(),
         }
     }
 
@@ -29,6 +57,22 @@ impl DialData {
             listen_addrs,
             retry: Retry::new(),
             is_bootstrap: true,
+            is_persistent: false,
+            is_fallback: false,
+            span: Span::none(),
+        }
+    }
+
+    /// Create a DialData for a peer from the discovery `persistent_peers`
+    pub fn new_persistent(peer_id: PeerId, listen_addrs: Vec<Multiaddr>) -> Self {
+        Self {
+            peer_id: Some(peer_id),
+            listen_addrs,
+            retry: Retry::new(),
+            is_bootstrap: false,
+            is_persistent: true,
+            is_fallback: false,
+            span: Span::none(),
         }
     }
 
@@ -37,6 +81,105 @@ impl DialData {
         self.is_bootstrap
     }
 
+    /// Returns true if this dial is for a peer from the discovery `persistent_peers`
+    pub fn is_persistent(&self) -> bool {
+        self.is_persistent
+    }
+
+    /// Returns true if this dial is for the addresses of the non-preferred IP family of a peer
+    pub fn is_fallback(&self) -> bool {
+        self.is_fallback
+    }
+
+    /// Order the addresses so that the ones of the preferred IP family come first,
+    /// keeping the advertised order otherwise
+    pub fn prefer_address_family(&mut self, preference: AddressFamilyPreference) {
+        let preferred = match preference {
+            AddressFamilyPreference::Any => return,
+            AddressFamilyPreference::Ipv4 => AddressFamily::Ipv4,
+            AddressFamilyPreference::Ipv6 => AddressFamily::Ipv6,
+        };
+
+        self.listen_addrs
+            .sort_by_key(|addr| address_family(addr) != Some(preferred));
+    }
+
+    /// Only keep the relayed addresses going through the least loaded of the relays whose load
+    /// is known, instead of dialing the peer through all of its relays. The direct addresses
+    /// and the relayed addresses through relays of unknown load are kept.
+    pub fn keep_least_loaded_relay(&mut self, utilization: impl Fn(&PeerId) -> Option<f64>) {
+        let relay_utilization =
+            |addr: &Multiaddr| relay_peer_id(addr).and_then(|relay| utilization(&relay));
+
+        let Some(least) = self
+            .listen_addrs
+            .iter()
+            .filter_map(relay_utilization)
+            .min_by(f64::total_cmp)
+        else {
+            return;
+        };
+
+        self.listen_addrs
+            .retain(|addr| relay_utilization(addr).is_none_or(|u| u <= least));
+    }
+
+    /// Split the addresses between the ones of the IP family of the first address, to dial now,
+    /// and the other ones, to dial later if the peer is not connected yet.
+    ///
+    /// Returns `None` if the peer id is unknown, as only the first address is dialed then,
+    /// or if there are no addresses of another IP family.
+    pub fn split_fallback(&self) -> Option<(DialData, DialData)> {
+        self.peer_id?;
+
+        let preferred = address_family(self.listen_addrs.first()?);
+
+        let (primary, fallback): (Vec<_>, Vec<_>) = self
+            .listen_addrs
+            .iter()
+            .cloned()
+            .partition(|addr| address_family(addr) == preferred);
+
+        if fallback.is_empty() {
+            return None;
+        }
+
+        let primary = Self {
+            listen_addrs: primary,
+            ..self.clone()
+        };
+
+        let fallback = Self {
+            listen_addrs: fallback,
+            is_fallback: true,
+            ..self.clone()
+        };
+
+        Some((primary, fallback))
+    }
+
+    /// Start the span of the dial, unless started by a previous attempt,
+    /// which lasts until the dial and its retries are over
+    pub fn start_span(&mut self) -> &Span {
+        if self.span.is_none() {
+            self.span = info_span!(
+                parent: None,
+                "dial",
+                peer = ?self.peer_id,
+                addrs = ?self.listen_addrs,
+                retries = field::Empty,
+                connected = field::Empty,
+                error = field::Empty,
+            );
+        }
+
+        &self.span
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
     pub fn set_peer_id(&mut self, peer_id: PeerId) {
         self.peer_id = Some(peer_id);
     }
@@ -71,3 +214,81 @@ impl DialData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(addrs: &[&str]) -> Vec<Multiaddr> {
+        addrs.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    const IP4: &str = "/ip4/10.0.0.1/tcp/27000";
+    const IP6: &str = "/ip6/fd00::1/tcp/27000";
+    const DNS4: &str = "/dns4/node.example.com/tcp/27000";
+
+    #[test]
+    fn prefer_address_family() {
+        let mut dial_data = DialData::new(None, addrs(&[IP4, IP6, DNS4]));
+
+        dial_data.prefer_address_family(AddressFamilyPreference::Any);
+        assert_eq!(dial_data.listen_addrs(), addrs(&[IP4, IP6, DNS4]));
+
+        dial_data.prefer_address_family(AddressFamilyPreference::Ipv6);
+        assert_eq!(dial_data.listen_addrs(), addrs(&[IP6, IP4, DNS4]));
+
+        dial_data.prefer_address_family(AddressFamilyPreference::Ipv4);
+        assert_eq!(dial_data.listen_addrs(), addrs(&[IP4, DNS4, IP6]));
+    }
+
+    #[test]
+    fn keep_least_loaded_relay() {
+        let busy = PeerId::random();
+        let idle = PeerId::random();
+        let unknown = PeerId::random();
+        let target = PeerId::random();
+
+        let via = |relay: PeerId| format!("{IP4}/p2p/{relay}/p2p-circuit/p2p/{target}");
+        let (via_busy, via_idle, via_unknown) = (via(busy), via(idle), via(unknown));
+
+        let mut dial_data = DialData::new(
+            Some(target),
+            addrs(&[&via_busy, IP6, &via_idle, &via_unknown]),
+        );
+
+        dial_data.keep_least_loaded_relay(|relay| {
+            (*relay == busy)
+                .then_some(0.9)
+                .or((*relay == idle).then_some(0.1))
+        });
+
+        assert_eq!(
+            dial_data.listen_addrs(),
+            addrs(&[IP6, &via_idle, &via_unknown])
+        );
+
+        // No known load
+        let mut dial_data = DialData::new(Some(target), addrs(&[&via_busy, &via_idle]));
+        dial_data.keep_least_loaded_relay(|_| None);
+        assert_eq!(dial_data.listen_addrs(), addrs(&[&via_busy, &via_idle]));
+    }
+
+    #[test]
+    fn split_fallback() {
+        let dial_data = DialData::new(Some(PeerId::random()), addrs(&[IP6, IP4, DNS4]));
+
+        let (primary, fallback) = dial_data.split_fallback().unwrap();
+        assert_eq!(primary.listen_addrs(), addrs(&[IP6]));
+        assert_eq!(fallback.listen_addrs(), addrs(&[IP4, DNS4]));
+        assert!(!primary.is_fallback());
+        assert!(fallback.is_fallback());
+
+        // Single family
+        let dial_data = DialData::new(Some(PeerId::random()), addrs(&[IP4, DNS4]));
+        assert!(dial_data.split_fallback().is_none());
+
+        // Unknown peer id
+        let dial_data = DialData::new(None, addrs(&[IP6, IP4]));
+        assert!(dial_data.split_fallback().is_none());
+    }
+}
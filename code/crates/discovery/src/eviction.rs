@@ -0,0 +1,121 @@
+//! Eviction of inbound peers when the inbound peers limit is reached.
+//!
+//! By default, a new peer is refused once the node has as many inbound peers as
+//! allowed by `num_inbound_peers`. An [`EvictionPolicy`] provided by the application
+//! can instead select an existing inbound peer to disconnect in order to make room
+//! for the new one, eg. to favor the peers that are in the active validator set.
+//! The application labels the validators with [`Discovery::set_validator_peer`].
+//!
+//! [`Discovery::set_validator_peer`]: crate::Discovery::set_validator_peer
+
+use std::fmt;
+use std::time::Instant;
+
+use libp2p::PeerId;
+
+/// An inbound peer, or a peer which wants to become one
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InboundPeer {
+    pub peer_id: PeerId,
+    /// Whether the application labeled the peer as a validator
+    pub is_validator: bool,
+    /// Whether the peer is one of the persistent peers
+    pub is_persistent: bool,
+    /// When the peer became an inbound peer
+    pub since: Instant,
+}
+
+/// Policy deciding which inbound peer to evict, if any, when a new peer
+/// wants to become an inbound peer but the inbound peers limit is reached.
+pub trait EvictionPolicy: fmt::Debug + Send + Sync {
+    /// Select the inbound peer to evict in favor of the new peer,
+    /// or `None` to refuse the new peer instead.
+    fn select_evictee(
+        &self,
+        new_peer: &InboundPeer,
+        inbound_peers: &[InboundPeer],
+    ) -> Option<PeerId>;
+}
+
+/// Never evicts an inbound peer, new peers are refused once the limit is reached
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RefuseNewPeers;
+
+impl EvictionPolicy for RefuseNewPeers {
+    fn select_evictee(&self, _: &InboundPeer, _: &[InboundPeer]) -> Option<PeerId> {
+        None
+    }
+}
+
+/// Makes room for validators by evicting the oldest inbound peer that is neither
+/// a validator nor a persistent peer. Other new peers are refused.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ValidatorAwareEvictionPolicy;
+
+impl EvictionPolicy for ValidatorAwareEvictionPolicy {
+    fn select_evictee(
+        &self,
+        new_peer: &InboundPeer,
+        inbound_peers: &[InboundPeer],
+    ) -> Option<PeerId> {
+        if !new_peer.is_validator {
+            return None;
+        }
+
+        inbound_peers
+            .iter()
+            .filter(|peer| !peer.is_validator && !peer.is_persistent)
+            .min_by_key(|peer| peer.since)
+            .map(|peer| peer.peer_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn peer(is_validator: bool, is_persistent: bool, age_secs: u64) -> InboundPeer {
+        InboundPeer {
+            peer_id: PeerId::random(),
+            is_validator,
+            is_persistent,
+            since: Instant::now() - Duration::from_secs(age_secs),
+        }
+    }
+
+    #[test]
+    fn refuse_new_peers_never_evicts() {
+        let inbound = [peer(false, false, 10)];
+
+        assert_eq!(
+            RefuseNewPeers.select_evictee(&peer(true, false, 0), &inbound),
+            None
+        );
+    }
+
+    #[test]
+    fn validator_aware_evicts_oldest_non_validator() {
+        let validator = peer(true, false, 30);
+        let persistent = peer(false, true, 20);
+        let oldest = peer(false, false, 10);
+        let newest = peer(false, false, 5);
+        let inbound = [validator, persistent, newest, oldest];
+
+        let policy = ValidatorAwareEvictionPolicy;
+
+        assert_eq!(
+            policy.select_evictee(&peer(true, false, 0), &inbound),
+            Some(oldest.peer_id)
+        );
+        assert_eq!(
+            policy.select_evictee(&peer(false, false, 0), &inbound),
+            None
+        );
+        assert_eq!(
+            policy.select_evictee(&peer(true, false, 0), &[validator, persistent]),
+            None
+        );
+    }
+}
@@ -44,8 +44,15 @@ where
         }
     }
 
-    pub(crate) fn handle_failed_bootstrap(&mut self) {
-        if self.state == State::Bootstrapping {
+    pub(crate) fn handle_failed_bootstrap(&mut self, swarm: &mut swarm::Swarm<C>) {
+        if self.state != State::Bootstrapping {
+            return;
+        }
+
+        if self.active_connections.is_empty() && self.dial_known_peers(swarm) {
+            // Carry on discovery from the known peers we are reconnecting to
+            self.state = State::Extending(self.config.num_outbound_peers);
+        } else {
             self.state = State::Idle;
         }
     }
@@ -1,7 +1,7 @@
 use libp2p::{swarm::ConnectionId, PeerId, Swarm};
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
-use crate::{Discovery, DiscoveryClient, State};
+use crate::{is_relayed_addr, DisconnectReason, Discovery, DiscoveryClient, State};
 
 impl<C> Discovery<C>
 where
@@ -12,30 +12,101 @@ where
     }
 
     fn should_close(&self, peer_id: PeerId, connection_id: ConnectionId) -> bool {
+        let is_active = self
+            .active_connections
+            .get(&peer_id)
+            .is_some_and(|connection_ids| connection_ids.contains(&connection_id));
+
+        // Active connections to persistent peers, to the validator set
+        // and to the selected relays are never closed
+        if is_active
+            && (self.is_static_persistent_peer(&peer_id)
+                || self.validator_set.contains(&peer_id)
+                || self.is_selected_relay(&peer_id))
+        {
+            return false;
+        }
+
         // Only close ephemeral connections (i.e not inbound/outbound connections)
         // NOTE: a inbound or outbound connection can still be closed if it is not
         // part of the active connections to the peer. This is possible due to the
         // limit of the number of connections per peer.
-        (!self.outbound_peers.contains_key(&peer_id) && !self.inbound_peers.contains(&peer_id))
-            || self
-                .active_connections
-                .get(&peer_id)
-                .is_none_or(|connection_ids| !connection_ids.contains(&connection_id))
+        (!self.outbound_peers.contains_key(&peer_id) && !self.inbound_peers.contains_key(&peer_id))
+            || !is_active
     }
 
+    /// Close the connection, after telling the peer why if a reason is given
     pub fn close_connection(
         &mut self,
         swarm: &mut Swarm<C>,
         peer_id: PeerId,
         connection_id: ConnectionId,
+        reason: Option<DisconnectReason>,
     ) {
         if !self.should_close(peer_id, connection_id) {
             return;
         }
 
-        debug!("Closing connection {connection_id} to peer {peer_id}");
         // Close the connection even if it is not active
-        swarm.close_connection(connection_id);
+        match reason {
+            Some(reason) => {
+                self.close_connection_with_reason(swarm, peer_id, connection_id, reason)
+            }
+            None => {
+                debug!("Closing connection {connection_id} to peer {peer_id}");
+                swarm.close_connection(connection_id);
+            }
+        }
+    }
+
+    /// Close the relayed connections to a peer once a direct connection to it is established,
+    /// eg. after a successful hole punch, so that traffic stops flowing through the relay.
+    ///
+    /// The relayed connections are removed from the active connections right away, the peer
+    /// keeping its inbound or outbound status through the direct connection.
+    pub(crate) fn close_relayed_connections(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        peer_id: PeerId,
+        direct_connection_id: ConnectionId,
+    ) {
+        let is_direct = self
+            .connections
+            .get(&direct_connection_id)
+            .is_some_and(|info| !is_relayed_addr(&info.remote_addr));
+
+        if !is_direct {
+            return;
+        }
+
+        let Some(connection_ids) = self.active_connections.get_mut(&peer_id) else {
+            return;
+        };
+
+        let connections = &self.connections;
+        let (relayed, direct): (Vec<_>, Vec<_>) = connection_ids.iter().partition(|id| {
+            connections
+                .get(id)
+                .is_some_and(|info| is_relayed_addr(&info.remote_addr))
+        });
+
+        if relayed.is_empty() {
+            return;
+        }
+
+        *connection_ids = direct;
+
+        for connection_id in relayed {
+            info!(
+                peer = %peer_id, %connection_id, %direct_connection_id,
+                "Direct connection established, closing relayed connection"
+            );
+
+            self.connections.remove(&connection_id);
+            swarm.close_connection(connection_id);
+        }
+
+        self.metrics.increment_total_circuit_upgrades();
     }
 
     pub fn handle_closed_connection(
@@ -78,7 +149,7 @@ where
             if self.is_enabled() {
                 self.repair_outbound_peers(swarm);
             }
-        } else if self.inbound_peers.contains(&peer_id) {
+        } else if self.inbound_peers.contains_key(&peer_id) {
             warn!("Inbound connection {connection_id} to peer {peer_id} closed");
 
             if was_last_connection {
@@ -99,6 +170,7 @@ where
     /// Clean up peer state and dial history when the last connection to a peer is closed
     fn cleanup_peer_on_disconnect(&mut self, peer_id: PeerId) {
         let peer_info = self.discovered_peers.remove(&peer_id);
+        self.metrics.remove_peer_identity(&peer_id);
 
         // Remove signed peer record (no longer connected, record may be stale)
         self.signed_peer_records.remove(&peer_id);
@@ -106,6 +178,15 @@ where
         // Clear rate limiter state for this peer
         self.rate_limiter.remove_peer(&peer_id);
 
+        // Forget the validator label, it is set again on reconnection
+        self.validator_peers.remove(&peer_id);
+
+        // The round-trip times are measured again on reconnection
+        self.selector.forget_peer(&peer_id);
+
+        // The capabilities are exchanged again with the next connect request
+        self.peer_capabilities.remove(&peer_id);
+
         // Clear connect_request done_on to allow re-upgrading the peer on reconnection
         self.controller.connect_request.remove_done_on(&peer_id);
 
@@ -124,6 +205,15 @@ where
             }
         }
 
+        // Allow re-dialing the persistent peer, done by the periodic timer
+        if let Some(listen_addrs) = self.persistent_peers.get(&peer_id) {
+            info!("Last connection to persistent peer {peer_id} closed, will be re-dialed");
+
+            self.controller
+                .dial_clear_done_for_peer(peer_id, listen_addrs);
+            return;
+        }
+
         // Handle non-bootstrap peers when discovery is disabled
         if !self.is_enabled() {
             let addrs = peer_info.map(|info| info.listen_addrs).unwrap_or_default();
@@ -131,3 +221,136 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use libp2p::{identify, Multiaddr};
+    use malachitebft_metrics::Registry;
+
+    use super::*;
+    use crate::testkit::{offline_swarm, FixturePeer, TestClient};
+    use crate::{Config, ConnectionDirection, ConnectionInfo, Selection, Selector};
+
+    fn addr(s: &str) -> Multiaddr {
+        s.parse().unwrap()
+    }
+
+    /// Selector recording the peers it was told to forget
+    #[derive(Debug, Default)]
+    struct ForgetfulSelector {
+        forgotten: Arc<Mutex<Vec<PeerId>>>,
+    }
+
+    impl Selector<TestClient> for ForgetfulSelector {
+        fn try_select_n_outbound_candidates(
+            &mut self,
+            _swarm: &mut Swarm<TestClient>,
+            _discovered: &HashMap<PeerId, identify::Info>,
+            _excluded: Vec<PeerId>,
+            _n: usize,
+        ) -> Selection<PeerId> {
+            Selection::None
+        }
+
+        fn forget_peer(&mut self, peer_id: &PeerId) {
+            self.forgotten.lock().unwrap().push(*peer_id);
+        }
+    }
+
+    #[test]
+    fn selector_forgets_peer_once_last_connection_closed() {
+        let local = FixturePeer::new(0, addr("/ip4/10.0.0.1/tcp/27000"));
+        let peer = FixturePeer::new(1, addr("/ip4/10.0.0.2/tcp/27000")).peer_id;
+
+        let mut swarm = offline_swarm(&local.keypair);
+        let mut discovery =
+            Discovery::<TestClient>::new(Config::default(), vec![], &mut Registry::default());
+
+        let selector = ForgetfulSelector::default();
+        let forgotten = Arc::clone(&selector.forgotten);
+        discovery.set_selector(Box::new(selector));
+
+        let (first, second) = (
+            ConnectionId::new_unchecked(1),
+            ConnectionId::new_unchecked(2),
+        );
+
+        for connection_id in [first, second] {
+            discovery.connections.insert(
+                connection_id,
+                ConnectionInfo {
+                    direction: ConnectionDirection::Outbound,
+                    remote_addr: addr("/ip4/10.0.0.2/tcp/27000"),
+                },
+            );
+        }
+        discovery
+            .active_connections
+            .insert(peer, vec![first, second]);
+
+        // The peer is still connected through the other connection
+        discovery.handle_closed_connection(&mut swarm, peer, first);
+        assert!(forgotten.lock().unwrap().is_empty());
+
+        discovery.handle_closed_connection(&mut swarm, peer, second);
+        assert_eq!(*forgotten.lock().unwrap(), vec![peer]);
+        assert!(!discovery.active_connections.contains_key(&peer));
+    }
+
+    #[test]
+    fn relayed_connections_are_closed_once_direct() {
+        let local = FixturePeer::new(0, addr("/ip4/10.0.0.1/tcp/27000"));
+        let relay = FixturePeer::new(1, addr("/ip4/10.0.0.2/tcp/27000"));
+        let peer = FixturePeer::new(2, addr("/ip4/10.0.0.3/tcp/27000")).peer_id;
+
+        let mut swarm = offline_swarm(&local.keypair);
+        let mut discovery = Discovery::new(Config::default(), vec![], &mut Registry::default());
+
+        let relayed = ConnectionId::new_unchecked(1);
+        let direct = ConnectionId::new_unchecked(2);
+
+        let relayed_addr = addr(&format!(
+            "/ip4/10.0.0.2/tcp/27000/p2p/{}/p2p-circuit",
+            relay.peer_id
+        ));
+
+        discovery.connections.insert(
+            relayed,
+            ConnectionInfo {
+                direction: ConnectionDirection::Outbound,
+                remote_addr: relayed_addr,
+            },
+        );
+        discovery.active_connections.insert(peer, vec![relayed]);
+
+        // A relayed connection does not supersede the other ones
+        discovery.close_relayed_connections(&mut swarm, peer, relayed);
+        assert_eq!(discovery.active_connections[&peer], vec![relayed]);
+
+        discovery.connections.insert(
+            direct,
+            ConnectionInfo {
+                direction: ConnectionDirection::Inbound,
+                remote_addr: addr("/ip4/10.0.0.3/tcp/27000"),
+            },
+        );
+        discovery
+            .active_connections
+            .get_mut(&peer)
+            .unwrap()
+            .push(direct);
+
+        // The direct connection supersedes the relayed one, which is forgotten right away
+        discovery.close_relayed_connections(&mut swarm, peer, direct);
+        assert_eq!(discovery.active_connections[&peer], vec![direct]);
+        assert!(!discovery.connections.contains_key(&relayed));
+        assert!(discovery.connections.contains_key(&direct));
+
+        // Nothing left to close
+        discovery.close_relayed_connections(&mut swarm, peer, direct);
+        assert_eq!(discovery.active_connections[&peer], vec![direct]);
+    }
+}
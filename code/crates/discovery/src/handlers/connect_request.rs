@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use libp2p::{
     request_response::{OutboundRequestId, ResponseChannel},
     PeerId, Swarm,
@@ -7,7 +9,7 @@ use tracing::{debug, error, trace};
 use crate::{
     behaviour::{self, Response},
     request::RequestData,
-    Discovery, DiscoveryClient, OutboundState,
+    Discovery, DiscoveryClient, OutboundState, PeerCapabilities,
 };
 
 impl<C> Discovery<C>
@@ -47,9 +49,10 @@ where
             request_data.retry.count()
         );
 
-        let request_id = swarm
-            .behaviour_mut()
-            .send_request(&request_data.peer_id(), behaviour::Request::Connect());
+        let request_id = swarm.behaviour_mut().send_request(
+            &request_data.peer_id(),
+            behaviour::Request::Connect(self.local_capabilities.clone()),
+        );
 
         self.controller
             .connect_request
@@ -61,23 +64,34 @@ where
         swarm: &mut Swarm<C>,
         channel: ResponseChannel<Response>,
         peer: PeerId,
+        capabilities: PeerCapabilities,
     ) {
         let mut accepted: bool = false;
 
-        if self.config.persistent_peers_only && !self.is_persistent_peer(&peer) {
+        let same_chain = self.local_capabilities.is_same_chain(&capabilities);
+        self.record_peer_capabilities(peer, capabilities);
+
+        if !same_chain {
+            debug!("Rejecting upgrade of peer {peer} to inbound peer as it follows another chain");
+        } else if self.config.persistent_peers_only && !self.is_persistent_peer(&peer) {
             debug!("Rejecting upgrade of peer {peer} to inbound peer as it's non-persistent and persistent_peers_only mode is on");
         } else if self.outbound_peers.contains_key(&peer) {
             debug!("Peer {peer} is already an outbound peer");
 
             accepted = true;
-        } else if self.inbound_peers.contains(&peer) {
+        } else if self.inbound_peers.contains_key(&peer) {
             debug!("Peer {peer} is already an inbound peer");
 
             accepted = true;
         } else if self.inbound_peers.len() < self.config.num_inbound_peers {
             debug!("Upgrading peer {peer} to inbound peer");
 
-            self.inbound_peers.insert(peer);
+            self.inbound_peers.insert(peer, Instant::now());
+            accepted = true;
+        } else if self.evict_inbound_peer_for(peer) {
+            debug!("Upgrading peer {peer} to inbound peer after eviction");
+
+            self.inbound_peers.insert(peer, Instant::now());
             accepted = true;
         } else {
             debug!("Rejecting upgrade of peer {peer} to inbound peer as the limit is reached");
@@ -87,7 +101,10 @@ where
 
         if swarm
             .behaviour_mut()
-            .send_response(channel, behaviour::Response::Connect(accepted))
+            .send_response(
+                channel,
+                behaviour::Response::Connect(accepted, self.local_capabilities.clone()),
+            )
             .is_err()
         {
             error!("Error sending connect response to {peer}");
@@ -102,12 +119,20 @@ where
         request_id: OutboundRequestId,
         peer: PeerId,
         accepted: bool,
+        capabilities: PeerCapabilities,
     ) {
         self.controller
             .connect_request
             .remove_in_progress(&request_id);
 
-        if accepted {
+        let same_chain = self.local_capabilities.is_same_chain(&capabilities);
+        self.record_peer_capabilities(peer, capabilities);
+
+        if !same_chain {
+            debug!("Peer {peer} follows another chain, not upgrading it to outbound peer");
+
+            self.handle_connect_rejection(swarm, peer);
+        } else if accepted {
             debug!("Successfully upgraded peer {peer} to outbound peer");
 
             if let Some(state) = self.outbound_peers.get_mut(&peer) {
@@ -156,9 +181,11 @@ where
                 // Retry request after a delay
                 request_data.retry.inc_count();
 
+                let next_delay = request_data.retry.next_delay(&self.config.retry_backoff);
+
                 self.controller
                     .connect_request
-                    .add_to_queue(request_data.clone(), Some(request_data.retry.next_delay()));
+                    .add_to_queue(request_data.clone(), Some(next_delay));
             } else {
                 // No more trials left
                 error!(
@@ -3,7 +3,7 @@ use libp2p::{
     swarm::{ConnectionId, DialError},
     PeerId, Swarm,
 };
-use tracing::{debug, error, warn};
+use tracing::{debug, error, field, warn};
 
 use crate::{
     controller::PeerData, dial::DialData, ConnectionDirection, ConnectionInfo, Discovery,
@@ -18,7 +18,7 @@ where
         self.controller.dial.can_perform()
     }
 
-    fn should_dial(
+    pub(crate) fn should_dial(
         &self,
         swarm: &Swarm<C>,
         dial_data: &DialData,
@@ -29,6 +29,8 @@ where
             id != swarm.local_peer_id()
             // Is not already connected
             && !swarm.is_connected(id)
+            // Is not banned
+            && !self.is_banned_peer(id)
         })
             // Has not already dialed, or has dialed but retries are allowed
             && (!check_already_dialed || !self.controller.dial_is_done_on(dial_data) || dial_data.retry.count() != 0)
@@ -36,14 +38,35 @@ where
             && !swarm.listeners().any(|addr| dial_data.listen_addrs().contains(addr))
     }
 
-    pub fn dial_peer(&mut self, swarm: &mut Swarm<C>, dial_data: DialData) {
+    pub fn dial_peer(&mut self, swarm: &mut Swarm<C>, mut dial_data: DialData) {
         // Not checking if the peer was already dialed because it is done when
         // adding to the dial queue
         if !self.should_dial(swarm, &dial_data, false) {
             return;
         }
 
-        let Some(dial_opts) = dial_data.build_dial_opts() else {
+        dial_data
+            .start_span()
+            .record("retries", dial_data.retry.count());
+
+        dial_data.prefer_address_family(self.config.address_family_preference);
+        dial_data.keep_least_loaded_relay(|relay| self.relay_utilization(relay));
+
+        // Dial the addresses of the preferred IP family first, and the other ones after a delay
+        let staggered = self
+            .config
+            .happy_eyeballs_delay
+            .filter(|_| !dial_data.is_fallback())
+            .and_then(|delay| {
+                let (primary, fallback) = dial_data.split_fallback()?;
+                Some((primary, fallback, delay))
+            });
+
+        let dialed = staggered
+            .as_ref()
+            .map_or(&dial_data, |(primary, _, _)| primary);
+
+        let Some(dial_opts) = dialed.build_dial_opts() else {
             warn!(
                 "No addresses to dial for peer {:?}, skipping dial attempt",
                 dial_data.peer_id()
@@ -55,12 +78,15 @@ where
         // Register peer_id only, not addresses as they are untrusted
         self.controller.dial_register_done_on(&dial_data, false);
 
+        let dialed_addrs = dialed.listen_addrs();
+
+        // Keep all the addresses, so that a retry dials both IP families again
         self.controller
             .dial
             .register_in_progress(connection_id, dial_data.clone());
 
-        // Do not count retries as new interactions
-        if dial_data.retry.count() == 0 {
+        // Do not count retries nor fallback dials as new interactions
+        if dial_data.retry.count() == 0 && !dial_data.is_fallback() {
             self.metrics.increment_total_dials();
         }
 
@@ -68,16 +94,31 @@ where
             %connection_id,
             "Dialing peer {:?} at {:?}, retry #{}",
             dial_data.peer_id(),
-            dial_data.listen_addrs(),
+            dialed_addrs,
             dial_data.retry.count()
         );
 
-        if let Err(e) = swarm.dial(dial_opts) {
+        let result = swarm.dial(dial_opts);
+
+        if let Some((_, fallback, delay)) = staggered {
+            // No need to wait if the preferred addresses cannot be dialed at all
+            let delay = result.is_ok().then_some(delay);
+
+            debug!(
+                "Dialing peer {:?} at {:?} in {delay:?} if not connected by then",
+                fallback.peer_id(),
+                fallback.listen_addrs(),
+            );
+
+            self.controller.dial.add_to_queue(fallback, delay);
+        }
+
+        if let Err(e) = result {
             error!(
                 %connection_id,
                 "Error dialing peer {:?} at {:?}: {}",
                 dial_data.peer_id(),
-                dial_data.listen_addrs(),
+                dialed_addrs,
                 e
             );
 
@@ -109,6 +150,10 @@ where
                     },
                 );
 
+                if let Some(dial_data) = self.controller.dial.get_in_progress_mut(&connection_id) {
+                    dial_data.span().record("connected", true);
+                }
+
                 // Only register as "done" for connections that the node initiated
                 // This is needed in case the peer was dialed without knowing the peer id
                 self.controller
@@ -158,6 +203,48 @@ where
         error: DialError,
     ) {
         if let Some(mut dial_data) = self.controller.dial.remove_in_progress(&connection_id) {
+            dial_data.span().record("error", field::display(&error));
+
+            // Dialing a peer only reachable through a relay is an attempt to reach it directly
+            if dial_data
+                .peer_id()
+                .is_some_and(|peer_id| self.is_relayed_only(&peer_id))
+            {
+                warn!(
+                    "Failed to reach relayed peer {:?} directly: {error}",
+                    dial_data.peer_id()
+                );
+                self.metrics.increment_total_failed_circuit_upgrades();
+            }
+
+            // The dial of the preferred addresses is retried instead, with all the addresses
+            if dial_data.is_fallback() {
+                debug!(
+                    "Failed to dial peer {:?} at fallback addresses {:?}: {error}",
+                    dial_data.peer_id(),
+                    dial_data.listen_addrs(),
+                );
+                return;
+            }
+
+            // Persistent peers are retried forever, the backoff delay being capped
+            if dial_data.is_persistent() {
+                dial_data.retry.inc_count();
+
+                let next_delay = dial_data.retry.next_delay(&self.config.retry_backoff);
+
+                warn!(
+                    "Failed to dial persistent peer {:?}, retry #{} in {next_delay:?}: {error}",
+                    dial_data.peer_id(),
+                    dial_data.retry.count(),
+                );
+
+                self.controller
+                    .dial
+                    .add_to_queue(dial_data, Some(next_delay));
+                return;
+            }
+
             // Skip retrying for errors that will occur again
             if matches!(
                 error,
@@ -173,7 +260,7 @@ where
                 // Retry dialing after a delay
                 dial_data.retry.inc_count();
 
-                let next_delay = dial_data.retry.next_delay();
+                let next_delay = dial_data.retry.next_delay(&self.config.retry_backoff);
 
                 self.controller
                     .dial
@@ -221,12 +308,83 @@ where
         }
     }
 
+    /// Dial the persistent peers which are neither connected nor already being dialed.
+    /// Failed dials are retried forever with backoff, see `handle_failed_connection()`.
+    pub fn dial_persistent_peers(&mut self, swarm: &Swarm<C>) {
+        let mut dials = Vec::new();
+
+        for (peer_id, listen_addrs) in &self.persistent_peers.clone() {
+            // The done_on flag is only cleared when the last connection to the peer is closed
+            if self
+                .controller
+                .dial
+                .is_done_on(&crate::controller::PeerData::PeerId(*peer_id))
+            {
+                continue;
+            }
+
+            let dial_data = DialData::new_persistent(*peer_id, listen_addrs.clone());
+
+            if self.should_dial(swarm, &dial_data, false) {
+                debug!("Adding persistent peer {peer_id} to dial queue");
+
+                self.controller.dial_register_done_on(&dial_data, true);
+                dials.push(dial_data);
+            }
+        }
+
+        // Spread out the re-dials only, the node starts connecting to its peers right away
+        let spread = std::mem::replace(&mut self.persistent_peers_dialed, true);
+        self.queue_dials(dials, spread);
+    }
+
+    /// Dial the most recently seen peers which were identified since the node started,
+    /// when the node is isolated because none of the bootstrap nodes can be reached.
+    ///
+    /// The known peers are only dialed once until a connection is established again.
+    /// Returns whether any dial was queued.
+    pub(crate) fn dial_known_peers(&mut self, swarm: &Swarm<C>) -> bool {
+        if self.known_peers_dialed || self.known_peers.is_empty() {
+            return false;
+        }
+
+        self.known_peers_dialed = true;
+
+        let known_peers = self.known_peers.most_recent(self.config.num_outbound_peers);
+
+        warn!(
+            "No bootstrap node could be reached, falling back to {} previously known peers",
+            known_peers.len()
+        );
+
+        let mut dials = Vec::new();
+
+        for (peer_id, listen_addrs) in known_peers {
+            let dial_data = DialData::new(Some(peer_id), listen_addrs);
+
+            if self.should_dial(swarm, &dial_data, false) {
+                // The peer was already dialed when it was first discovered
+                self.controller
+                    .dial_clear_done_for_peer(peer_id, &dial_data.listen_addrs());
+                self.controller.dial_register_done_on(&dial_data, false);
+                dials.push(dial_data);
+            }
+        }
+
+        let dialed = !dials.is_empty();
+        self.queue_dials(dials, true);
+
+        dialed
+    }
+
     pub fn dial_bootstrap_nodes(&mut self, swarm: &Swarm<C>) {
+        let mut dials = Vec::new();
+
         for (peer_id, listen_addrs) in &self.bootstrap_nodes.clone() {
             // For bootstrap nodes, check if already attempted (done_on flag)
-            // This prevents overlapping Fibonacci retry sequences since done_on is only cleared
+            // This prevents overlapping backoff retry sequences since done_on is only cleared
             // after all retries are exhausted
-            // The Fibonacci retry sequence is started when a connection fails, see handle_failed_connection()
+            // The backoff retry sequence is started when a connection fails, see handle_failed_connection()
             // We check by address since bootstrap nodes may not have peer_id
             let already_attempted = listen_addrs.iter().any(|addr| {
                 self.controller
@@ -251,8 +409,27 @@ where
                 );
                 // For bootstrap nodes, register addresses too (trusted config)
                 self.controller.dial_register_done_on(&dial_data, true);
-                self.controller.dial.add_to_queue(dial_data, None);
+                dials.push(dial_data);
             }
         }
+
+        let spread = std::mem::replace(&mut self.bootstrap_nodes_dialed, true);
+        self.queue_dials(dials, spread);
+    }
+
+    /// Queue dials started together, eg. to the persistent peers lost in a network-wide outage.
+    /// If `spread` is set, each dial is queued after a random delay so that the nodes coming
+    /// back together do not dial in lockstep, see [`ReconnectConfig`](crate::config::ReconnectConfig).
+    pub(crate) fn queue_dials(&mut self, dials: Vec<DialData>, spread: bool) {
+        let num_dials = dials.len();
+        let mut rng = rand::thread_rng();
+
+        for dial_data in dials {
+            let delay = spread
+                .then(|| self.config.reconnect.jittered_delay(num_dials, &mut rng))
+                .flatten();
+
+            self.controller.dial.add_to_queue(dial_data, delay);
+        }
     }
 }
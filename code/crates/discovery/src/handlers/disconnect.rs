@@ -0,0 +1,353 @@
+use libp2p::{
+    request_response::{OutboundRequestId, ResponseChannel},
+    swarm::ConnectionId,
+    PeerId, Swarm,
+};
+use tracing::{debug, error, info, trace};
+
+use crate::{
+    behaviour::{self, DisconnectReason, Response},
+    Discovery, DiscoveryClient,
+};
+
+/// Connections to close once the peer acknowledged a disconnect request,
+/// or once the request failed
+#[derive(Debug)]
+pub(crate) enum PendingDisconnect {
+    Connections(PeerId, Vec<ConnectionId>),
+    Peer(PeerId),
+}
+
+impl PendingDisconnect {
+    fn peer_id(&self) -> PeerId {
+        match self {
+            Self::Connections(peer_id, _) | Self::Peer(peer_id) => *peer_id,
+        }
+    }
+}
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    /// Tell the peer why the connection is closed before closing it.
+    ///
+    /// If a disconnect request is already pending for the peer, the connection
+    /// is closed along with the others once the request completes.
+    pub(crate) fn close_connection_with_reason(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        reason: DisconnectReason,
+    ) {
+        if !self.is_enabled() {
+            swarm.close_connection(connection_id);
+            return;
+        }
+
+        let pending = self
+            .pending_disconnects
+            .values_mut()
+            .find(|pending| pending.peer_id() == peer_id);
+
+        match pending {
+            Some(PendingDisconnect::Connections(_, connection_ids)) => {
+                connection_ids.push(connection_id);
+            }
+            Some(PendingDisconnect::Peer(_)) => {}
+            None => self.send_disconnect_request(
+                swarm,
+                peer_id,
+                PendingDisconnect::Connections(peer_id, vec![connection_id]),
+                reason,
+            ),
+        }
+    }
+
+    /// Tell the peer why it is disconnected before closing all the connections to it
+    pub(crate) fn disconnect_peer_with_reason(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        peer_id: PeerId,
+        reason: DisconnectReason,
+    ) {
+        if !self.is_enabled() {
+            let _ = swarm.disconnect_peer_id(peer_id);
+            return;
+        }
+
+        // Supersedes the disconnect request already sent for some of the connections, if any
+        self.pending_disconnects
+            .retain(|_, pending| pending.peer_id() != peer_id);
+
+        self.send_disconnect_request(swarm, peer_id, PendingDisconnect::Peer(peer_id), reason);
+    }
+
+    /// Tell all the connected peers that the node is shutting down, closing the connections
+    /// to each of them once it acknowledged it, see [`Self::has_pending_disconnects`]
+    pub fn disconnect_all_on_shutdown(&mut self, swarm: &mut Swarm<C>) {
+        let peers = swarm.connected_peers().copied().collect::<Vec<_>>();
+
+        for peer_id in peers {
+            self.disconnect_peer_with_reason(swarm, peer_id, DisconnectReason::Shutdown);
+        }
+    }
+
+    /// Whether some peers have not acknowledged or failed a disconnect request yet
+    pub fn has_pending_disconnects(&self) -> bool {
+        !self.pending_disconnects.is_empty()
+    }
+
+    fn send_disconnect_request(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        peer_id: PeerId,
+        pending: PendingDisconnect,
+        reason: DisconnectReason,
+    ) {
+        debug!(%peer_id, reason = reason.as_str(), "Sending disconnect request");
+
+        let request_id = swarm
+            .behaviour_mut()
+            .send_request(&peer_id, behaviour::Request::Disconnect(reason));
+
+        self.pending_disconnects.insert(request_id, pending);
+    }
+
+    pub(crate) fn is_disconnect_request(&self, request_id: &OutboundRequestId) -> bool {
+        self.pending_disconnects.contains_key(request_id)
+    }
+
+    /// Close the connections once the disconnect request was acknowledged or failed
+    pub(crate) fn handle_disconnect_response(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        request_id: OutboundRequestId,
+    ) {
+        match self.pending_disconnects.remove(&request_id) {
+            Some(PendingDisconnect::Connections(peer_id, connection_ids)) => {
+                for connection_id in connection_ids {
+                    debug!("Closing connection {connection_id} to peer {peer_id}");
+                    swarm.close_connection(connection_id);
+                }
+            }
+            Some(PendingDisconnect::Peer(peer_id)) => {
+                debug!("Closing all connections to peer {peer_id}");
+                let _ = swarm.disconnect_peer_id(peer_id);
+            }
+            None => {}
+        }
+    }
+
+    /// The peer is about to close the connections to us: stop counting on it as an
+    /// outbound or inbound peer and look for a replacement right away.
+    pub(crate) fn handle_disconnect_request(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        channel: ResponseChannel<Response>,
+        peer: PeerId,
+        reason: DisconnectReason,
+    ) {
+        info!(%peer, reason = reason.as_str(), "Peer is disconnecting");
+
+        self.metrics.increment_total_disconnect_requests(reason);
+
+        if swarm
+            .behaviour_mut()
+            .send_response(channel, behaviour::Response::Disconnect())
+            .is_err()
+        {
+            error!("Error sending disconnect response to {peer}");
+        } else {
+            trace!("Sent disconnect response to {peer}");
+        }
+
+        self.inbound_peers.remove(&peer);
+
+        if self.outbound_peers.remove(&peer).is_some() {
+            // Do not select the peer again until it reconnects
+            self.controller.connect_request.register_done_on(peer);
+
+            self.repair_outbound_peers(swarm);
+        }
+
+        self.update_discovery_metrics();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::futures::executor::block_on;
+    use libp2p::futures::future::{select, Either};
+    use libp2p::futures::StreamExt;
+    use libp2p::swarm::SwarmEvent;
+    use malachitebft_metrics::Registry;
+
+    use super::*;
+    use crate::testkit::{memory_swarm, offline_swarm, FixturePeer};
+    use crate::Config;
+
+    fn pending_request(discovery: &Discovery<impl DiscoveryClient>) -> OutboundRequestId {
+        assert_eq!(discovery.pending_disconnects.len(), 1);
+        *discovery.pending_disconnects.keys().next().unwrap()
+    }
+
+    #[test]
+    fn connections_are_closed_once_the_disconnect_request_completes() {
+        let local = FixturePeer::new(0, "/ip4/10.0.0.1/tcp/27000".parse().unwrap());
+        let peer = FixturePeer::new(1, "/ip4/10.0.0.2/tcp/27000".parse().unwrap()).peer_id;
+
+        let mut swarm = offline_swarm(&local.keypair);
+        let mut discovery = Discovery::new(Config::default(), vec![], &mut Registry::default());
+
+        let (first, second) = (
+            ConnectionId::new_unchecked(1),
+            ConnectionId::new_unchecked(2),
+        );
+
+        // A single request is sent for the connections closed meanwhile
+        discovery.close_connection_with_reason(
+            &mut swarm,
+            peer,
+            first,
+            DisconnectReason::PeerLimit,
+        );
+        discovery.close_connection_with_reason(
+            &mut swarm,
+            peer,
+            second,
+            DisconnectReason::PeerLimit,
+        );
+
+        let request_id = pending_request(&discovery);
+        assert!(discovery.is_disconnect_request(&request_id));
+        assert!(matches!(
+            &discovery.pending_disconnects[&request_id],
+            PendingDisconnect::Connections(p, ids) if *p == peer && *ids == vec![first, second]
+        ));
+
+        // Disconnecting the peer supersedes the pending request
+        discovery.disconnect_peer_with_reason(&mut swarm, peer, DisconnectReason::Banned);
+
+        let superseding_id = pending_request(&discovery);
+        assert_ne!(superseding_id, request_id);
+        assert!(!discovery.is_disconnect_request(&request_id));
+        assert!(matches!(
+            discovery.pending_disconnects[&superseding_id],
+            PendingDisconnect::Peer(p) if p == peer
+        ));
+
+        // The connections closed meanwhile wait for the same request
+        discovery.close_connection_with_reason(&mut swarm, peer, first, DisconnectReason::Evicted);
+        assert_eq!(pending_request(&discovery), superseding_id);
+
+        discovery.handle_disconnect_response(&mut swarm, superseding_id);
+        assert!(discovery.pending_disconnects.is_empty());
+    }
+
+    #[test]
+    fn peers_are_told_the_node_is_shutting_down() {
+        let local = FixturePeer::new(0, "/ip4/10.0.0.1/tcp/27000".parse().unwrap());
+        let remote = FixturePeer::new(1, "/ip4/10.0.0.2/tcp/27000".parse().unwrap());
+
+        let mut local_swarm = memory_swarm(&local.keypair);
+        let mut remote_swarm = memory_swarm(&remote.keypair);
+
+        let mut local_discovery =
+            Discovery::new(Config::default(), vec![], &mut Registry::default());
+        let mut remote_discovery =
+            Discovery::new(Config::default(), vec![], &mut Registry::default());
+
+        block_on(async {
+            remote_swarm
+                .listen_on("/memory/0".parse().unwrap())
+                .unwrap();
+
+            let listen_addr = loop {
+                if let SwarmEvent::NewListenAddr { address, .. } =
+                    remote_swarm.select_next_some().await
+                {
+                    break address;
+                }
+            };
+
+            local_swarm.dial(listen_addr).unwrap();
+
+            // Drive both swarms until the connection is established on both ends,
+            // then until it is closed, routing the events to their discovery
+            let mut established = 0;
+            let mut closed = 0;
+
+            while closed < 2 {
+                let event = match select(
+                    local_swarm.select_next_some(),
+                    remote_swarm.select_next_some(),
+                )
+                .await
+                {
+                    Either::Left((event, _)) => Either::Left(event),
+                    Either::Right((event, _)) => Either::Right(event),
+                };
+
+                match event {
+                    Either::Left(SwarmEvent::Behaviour(event)) => {
+                        local_discovery.on_network_event(&mut local_swarm, event)
+                    }
+                    Either::Right(SwarmEvent::Behaviour(event)) => {
+                        remote_discovery.on_network_event(&mut remote_swarm, event)
+                    }
+                    Either::Left(SwarmEvent::ConnectionEstablished { .. })
+                    | Either::Right(SwarmEvent::ConnectionEstablished { .. }) => {
+                        established += 1;
+
+                        if established == 2 {
+                            local_discovery.disconnect_all_on_shutdown(&mut local_swarm);
+                            assert!(local_discovery.has_pending_disconnects());
+                        }
+                    }
+                    Either::Left(SwarmEvent::ConnectionClosed { .. })
+                    | Either::Right(SwarmEvent::ConnectionClosed { .. }) => {
+                        // The connection is only closed once the request is acknowledged
+                        assert!(!local_discovery.has_pending_disconnects());
+                        closed += 1;
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        assert_eq!(
+            remote_discovery
+                .metrics
+                .get_total_disconnect_requests(DisconnectReason::Shutdown),
+            1
+        );
+    }
+
+    #[test]
+    fn connections_are_closed_right_away_when_discovery_is_disabled() {
+        let local = FixturePeer::new(0, "/ip4/10.0.0.1/tcp/27000".parse().unwrap());
+        let peer = FixturePeer::new(1, "/ip4/10.0.0.2/tcp/27000".parse().unwrap()).peer_id;
+
+        let config = Config {
+            enabled: false,
+            ..Config::default()
+        };
+
+        let mut swarm = offline_swarm(&local.keypair);
+        let mut discovery = Discovery::new(config, vec![], &mut Registry::default());
+
+        let connection_id = ConnectionId::new_unchecked(1);
+
+        discovery.close_connection_with_reason(
+            &mut swarm,
+            peer,
+            connection_id,
+            DisconnectReason::NotAllowed,
+        );
+        discovery.disconnect_peer_with_reason(&mut swarm, peer, DisconnectReason::Banned);
+
+        assert!(discovery.pending_disconnects.is_empty());
+    }
+}
@@ -76,6 +76,11 @@ where
                     return;
                 } else {
                     warn!("No more peers to request peers from");
+
+                    // Isolated, eg. because the bootstrap nodes are down
+                    if self.active_connections.is_empty() && self.dial_known_peers(swarm) {
+                        return;
+                    }
                 }
             }
 
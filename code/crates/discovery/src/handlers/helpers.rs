@@ -1,6 +1,8 @@
+use libp2p::PeerId;
 use tracing::info;
 
-use crate::{ConnectionDirection, Discovery, DiscoveryClient};
+use crate::metrics::ConnectionPaths;
+use crate::{is_relayed_addr, ConnectionDirection, Discovery, DiscoveryClient};
 
 impl<C> Discovery<C>
 where
@@ -36,6 +38,42 @@ where
             .count()
     }
 
+    fn connection_paths(&self) -> ConnectionPaths {
+        let mut paths = ConnectionPaths::default();
+
+        for info in self
+            .active_connections
+            .values()
+            .flatten()
+            .filter_map(|connection_id| self.connections.get(connection_id))
+        {
+            let count = match (info.direction, is_relayed_addr(&info.remote_addr)) {
+                (ConnectionDirection::Outbound, false) => &mut paths.outbound_direct,
+                (ConnectionDirection::Outbound, true) => &mut paths.outbound_relayed,
+                (ConnectionDirection::Inbound, false) => &mut paths.inbound_direct,
+                (ConnectionDirection::Inbound, true) => &mut paths.inbound_relayed,
+            };
+
+            *count += 1;
+        }
+
+        paths
+    }
+
+    /// Whether all the active connections to the peer go through a relay
+    pub(crate) fn is_relayed_only(&self, peer_id: &PeerId) -> bool {
+        self.active_connections
+            .get(peer_id)
+            .is_some_and(|connection_ids| {
+                !connection_ids.is_empty()
+                    && connection_ids.iter().all(|connection_id| {
+                        self.connections
+                            .get(connection_id)
+                            .is_some_and(|info| is_relayed_addr(&info.remote_addr))
+                    })
+            })
+    }
+
     pub(crate) fn update_discovery_metrics(&mut self) {
         let num_active_peers = self.active_connections.len();
         let num_active_connections = self.total_active_connections_len();
@@ -46,7 +84,7 @@ where
         // This fixes the double-counting issue when peers dial each other simultaneously
         let num_only_inbound = self
             .inbound_peers
-            .iter()
+            .keys()
             .filter(|peer| !self.outbound_peers.contains_key(peer))
             .count();
 
@@ -92,5 +130,111 @@ where
             num_ephemeral_peers,
             num_ephemeral_connections,
         );
+
+        let num_relayed_only_peers = self
+            .active_connections
+            .keys()
+            .filter(|peer_id| self.is_relayed_only(peer_id))
+            .count();
+
+        self.metrics
+            .set_connection_paths(self.connection_paths(), num_relayed_only_peers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::swarm::ConnectionId;
+    use libp2p::Multiaddr;
+    use malachitebft_metrics::prometheus::encoding::text::encode;
+    use malachitebft_metrics::Registry;
+
+    use super::*;
+    use crate::testkit::{FixturePeer, TestClient};
+    use crate::{Config, ConnectionInfo};
+
+    fn connect(
+        discovery: &mut Discovery<TestClient>,
+        peer_id: PeerId,
+        connection_id: usize,
+        direction: ConnectionDirection,
+        remote_addr: &str,
+    ) {
+        let connection_id = ConnectionId::new_unchecked(connection_id);
+        let remote_addr: Multiaddr = remote_addr.parse().unwrap();
+
+        discovery.connections.insert(
+            connection_id,
+            ConnectionInfo {
+                direction,
+                remote_addr,
+            },
+        );
+        discovery
+            .active_connections
+            .entry(peer_id)
+            .or_default()
+            .push(connection_id);
+    }
+
+    #[test]
+    fn connections_are_counted_by_direction_and_path() {
+        let relay = FixturePeer::new(0, "/ip4/10.0.0.1/tcp/27000".parse().unwrap()).peer_id;
+        let relayed_only = FixturePeer::new(1, "/ip4/10.0.0.2/tcp/27000".parse().unwrap()).peer_id;
+        let upgraded = FixturePeer::new(2, "/ip4/10.0.0.3/tcp/27000".parse().unwrap()).peer_id;
+
+        let mut registry = Registry::default();
+        let mut discovery = Discovery::new(Config::default(), vec![], &mut registry);
+
+        let circuit = format!("/ip4/10.0.0.1/tcp/27000/p2p/{relay}/p2p-circuit");
+
+        connect(
+            &mut discovery,
+            relay,
+            1,
+            ConnectionDirection::Outbound,
+            "/ip4/10.0.0.1/tcp/27000",
+        );
+        connect(
+            &mut discovery,
+            relayed_only,
+            2,
+            ConnectionDirection::Outbound,
+            &circuit,
+        );
+        connect(
+            &mut discovery,
+            upgraded,
+            3,
+            ConnectionDirection::Inbound,
+            &circuit,
+        );
+        connect(
+            &mut discovery,
+            upgraded,
+            4,
+            ConnectionDirection::Inbound,
+            "/ip4/10.0.0.3/tcp/27000",
+        );
+
+        assert!(!discovery.is_relayed_only(&relay));
+        assert!(discovery.is_relayed_only(&relayed_only));
+        assert!(!discovery.is_relayed_only(&upgraded));
+
+        let paths = discovery.connection_paths();
+        assert_eq!(paths.outbound_direct, 1);
+        assert_eq!(paths.outbound_relayed, 1);
+        assert_eq!(paths.inbound_direct, 1);
+        assert_eq!(paths.inbound_relayed, 1);
+
+        discovery.update_discovery_metrics();
+
+        let mut metrics = String::new();
+        encode(&mut metrics, &registry).unwrap();
+
+        assert!(metrics.contains("num_relayed_only_peers 1"));
+        assert!(
+            metrics.contains(r#"num_connections_by_path{direction="outbound",path="relayed"} 1"#)
+        );
     }
 }
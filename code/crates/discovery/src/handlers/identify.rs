@@ -1,9 +1,12 @@
+use std::time::Instant;
+
 use libp2p::{identify, swarm::ConnectionId, PeerId, Swarm};
 use tracing::{debug, info, warn};
 
 use crate::{
-    config::BootstrapProtocol, request::RequestData, util::strip_peer_id_from_multiaddr, Discovery,
-    DiscoveryClient, OutboundState, State,
+    addr_filter::filter_reachable_addresses, config::BootstrapProtocol, request::RequestData,
+    util::strip_peer_id_from_multiaddr, DisconnectReason, Discovery, DiscoveryClient,
+    OutboundState, PeerIdentity, State,
 };
 
 impl<C> Discovery<C>
@@ -150,9 +153,24 @@ where
                 "Rejecting connection from non-persistent peer as persistent_peers_only mode is on"
             );
 
-            self.controller
-                .close
-                .add_to_queue((peer_id, connection_id), None);
+            self.controller.close.add_to_queue(
+                (peer_id, connection_id, Some(DisconnectReason::NotAllowed)),
+                None,
+            );
+
+            return is_already_connected;
+        }
+
+        if !self.config.is_peer_allowed(&peer_id) {
+            warn!(
+                peer = %peer_id, %connection_id,
+                "Rejecting connection from peer not in the allowlist"
+            );
+
+            self.controller.close.add_to_queue(
+                (peer_id, connection_id, Some(DisconnectReason::NotAllowed)),
+                None,
+            );
 
             return is_already_connected;
         }
@@ -170,6 +188,20 @@ where
             );
         }
 
+        self.metrics
+            .set_peer_identity(peer_id, &PeerIdentity::from(&info));
+
+        // Remember the peer in case the bootstrap nodes become unreachable
+        if !self.is_persistent_peer(&peer_id) {
+            let listen_addrs = filter_reachable_addresses(
+                self.address_policy.as_ref(),
+                swarm.listeners().chain(swarm.external_addresses()),
+                info.listen_addrs.clone(),
+            );
+            self.known_peers.record(peer_id, listen_addrs);
+        }
+        self.known_peers_dialed = false;
+
         match self.discovered_peers.insert(peer_id, info.clone()) {
             Some(_) => {
                 info!(
@@ -187,26 +219,19 @@ where
             }
         }
 
-        if let Some(connection_ids) = self.active_connections.get_mut(&peer_id) {
-            if connection_ids.len() >= self.config.max_connections_per_peer {
-                warn!(
-                    peer = %peer_id, %connection_id,
-                    "Peer has already reached the maximum number of connections ({}), closing connection",
-                    self.config.max_connections_per_peer
-                );
+        // A direct connection supersedes the relayed ones, which are closed to free the relay
+        self.close_relayed_connections(swarm, peer_id, connection_id);
 
-                self.controller
-                    .close
-                    .add_to_queue((peer_id, connection_id), None);
+        // A connection through a less loaded relay supersedes the ones through the previous relay
+        self.close_switched_relay_connections(swarm, peer_id, connection_id);
 
-                return is_already_connected;
-            } else {
-                debug!(
-                    peer = %peer_id, %connection_id,
-                    "Additional connection to peer, total connections: {}",
-                    connection_ids.len() + 1
-                );
-            }
+        // The number of connections per peer is capped by the connection limits of the swarm
+        if let Some(connection_ids) = self.active_connections.get_mut(&peer_id) {
+            debug!(
+                peer = %peer_id, %connection_id,
+                "Additional connection to peer, total connections: {}",
+                connection_ids.len() + 1
+            );
 
             connection_ids.push(connection_id);
         } else {
@@ -216,12 +241,23 @@ where
         }
 
         if self.is_enabled() {
-            if self.outbound_peers.contains_key(&peer_id) {
+            if self.is_static_persistent_peer(&peer_id) {
+                // Persistent peers are always outbound peers, whoever dialed
+                debug!(
+                    peer = %peer_id, %connection_id,
+                    "Connection is outbound (persistent peer)"
+                );
+
+                self.inbound_peers.remove(&peer_id);
+                self.outbound_peers
+                    .insert(peer_id, OutboundState::Confirmed);
+                self.controller.connect_request.register_done_on(peer_id);
+            } else if self.outbound_peers.contains_key(&peer_id) {
                 debug!(
                     peer = %peer_id, %connection_id,
                     "Connection is outbound"
                 );
-            } else if self.inbound_peers.contains(&peer_id) {
+            } else if self.inbound_peers.contains_key(&peer_id) {
                 debug!(
                     peer = %peer_id, %connection_id,
                     "Connection is inbound"
@@ -253,7 +289,11 @@ where
                 debug!(peer = %peer_id, %connection_id, "Connection is ephemeral");
 
                 self.controller.close.add_to_queue(
-                    (peer_id, connection_id),
+                    (
+                        peer_id,
+                        connection_id,
+                        Some(DisconnectReason::EphemeralTimeout),
+                    ),
                     Some(self.config.ephemeral_connection_timeout),
                 );
 
@@ -280,20 +320,24 @@ where
                     .dial
                     .is_done_on(&crate::controller::PeerData::PeerId(peer_id));
 
-            if we_dialed {
-                // Accept all peers we dial (no capacity check)
+            if we_dialed || self.is_static_persistent_peer(&peer_id) {
+                // Accept all peers we dial (no capacity check), and persistent peers
                 // When discovery is disabled, these are explicitly configured peers
                 debug!(peer = %peer_id, %connection_id, "Connection is outbound");
                 self.outbound_peers
                     .insert(peer_id, OutboundState::Confirmed);
             } else if self.inbound_peers.len() < self.config.num_inbound_peers {
                 debug!(peer = %peer_id, %connection_id, "Connection is inbound");
-                self.inbound_peers.insert(peer_id);
+                self.inbound_peers.insert(peer_id, Instant::now());
+            } else if self.evict_inbound_peer_for(peer_id) {
+                debug!(peer = %peer_id, %connection_id, "Connection is inbound, after eviction");
+                self.inbound_peers.insert(peer_id, Instant::now());
             } else {
                 warn!(peer = %peer_id, %connection_id, "Inbound peers limit reached, refusing connection");
-                self.controller
-                    .close
-                    .add_to_queue((peer_id, connection_id), None);
+                self.controller.close.add_to_queue(
+                    (peer_id, connection_id, Some(DisconnectReason::PeerLimit)),
+                    None,
+                );
                 is_already_connected = true;
             }
         }
@@ -4,8 +4,13 @@ pub mod bootstrap;
 pub mod close;
 pub mod connect_request;
 pub mod dial;
+pub mod disconnect;
 pub mod extension;
 pub mod helpers;
 pub mod identify;
 pub mod peers_management;
 pub mod peers_request;
+pub mod progress;
+pub mod relay;
+pub mod relay_election;
+pub mod role;
@@ -1,7 +1,12 @@
+use std::time::Instant;
+
 use libp2p::{swarm::ConnectionId, PeerId, Swarm};
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
-use crate::{request::RequestData, Discovery, DiscoveryClient, OutboundState};
+use crate::{
+    eviction::InboundPeer, request::RequestData, DisconnectReason, Discovery, DiscoveryClient,
+    OutboundState,
+};
 
 use super::selection::selector::Selection;
 
@@ -9,18 +14,76 @@ impl<C> Discovery<C>
 where
     C: DiscoveryClient,
 {
+    /// Select `n` outbound candidates, the discovered peers of the validator set provided
+    /// by the application coming first, then the ones advertising themselves as validators,
+    /// the selector picking the remaining candidates
+    fn select_n_outbound_candidates(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        n: usize,
+    ) -> Selection<PeerId> {
+        let mut excluded = self.get_excluded_peers();
+
+        let mut validators: Vec<PeerId> = self
+            .discovered_peers
+            .keys()
+            .filter(|peer_id| self.validator_set.contains(peer_id))
+            .filter(|peer_id| !excluded.contains(peer_id))
+            .take(n)
+            .cloned()
+            .collect();
+
+        validators.extend(
+            self.discovered_peers
+                .keys()
+                .filter(|peer_id| self.advertised_validators.contains(peer_id))
+                .filter(|peer_id| {
+                    !excluded.contains(peer_id) && !self.validator_set.contains(peer_id)
+                })
+                .take(n - validators.len())
+                .cloned()
+                .collect::<Vec<_>>(),
+        );
+
+        if validators.is_empty() {
+            return self.selector.try_select_n_outbound_candidates(
+                swarm,
+                &self.discovered_peers,
+                excluded,
+                n,
+            );
+        }
+
+        debug!(
+            "Selected {} peers in the validator set or advertising the validator role",
+            validators.len()
+        );
+
+        excluded.extend(validators.iter().cloned());
+
+        let remaining = self.selector.try_select_n_outbound_candidates(
+            swarm,
+            &self.discovered_peers,
+            excluded,
+            n - validators.len(),
+        );
+
+        validators.extend(remaining.as_slice().iter().cloned());
+
+        if validators.len() < n {
+            Selection::Only(validators)
+        } else {
+            Selection::Exactly(validators)
+        }
+    }
+
     fn select_outbound_peers(&mut self, swarm: &mut Swarm<C>) {
         let n = self
             .config
             .num_outbound_peers
             .saturating_sub(self.outbound_peers.len());
 
-        let peers = match self.selector.try_select_n_outbound_candidates(
-            swarm,
-            &self.discovered_peers,
-            self.get_excluded_peers(),
-            n,
-        ) {
+        let peers = match self.select_n_outbound_candidates(swarm, n) {
             Selection::Exactly(peers) => {
                 debug!("Selected exactly {} outbound candidates", peers.len());
                 peers
@@ -45,7 +108,7 @@ where
 
         // Safety check: make sure that the inbound peers are not part of the outbound peers
         self.inbound_peers
-            .retain(|peer_id| !self.outbound_peers.contains_key(peer_id));
+            .retain(|peer_id, _| !self.outbound_peers.contains_key(peer_id));
     }
 
     pub(crate) fn adjust_peers(&mut self, swarm: &mut Swarm<C>) {
@@ -63,7 +126,7 @@ where
             // Remove outbound peers
             .filter(|(peer_id, _)| !self.outbound_peers.contains_key(peer_id))
             // Remove inbound peers
-            .filter(|(peer_id, _)| !self.inbound_peers.contains(peer_id))
+            .filter(|(peer_id, _)| !self.inbound_peers.contains_key(peer_id))
             .map(|(peer_id, connection_ids)| (*peer_id, connection_ids.clone()))
             .collect();
 
@@ -75,13 +138,67 @@ where
         for (peer_id, connection_ids) in peers_to_disconnect {
             for connection_id in connection_ids {
                 self.controller.close.add_to_queue(
-                    (peer_id, connection_id),
+                    (
+                        peer_id,
+                        connection_id,
+                        Some(DisconnectReason::EphemeralTimeout),
+                    ),
                     Some(self.config.ephemeral_connection_timeout),
                 );
             }
         }
     }
 
+    /// Ask the eviction policy for an inbound peer to evict in favor of the given peer,
+    /// when the inbound peers limit is reached. The evicted peer is removed from the
+    /// inbound peers and its connections are closed.
+    ///
+    /// Returns whether a peer was evicted.
+    pub(crate) fn evict_inbound_peer_for(&mut self, peer_id: PeerId) -> bool {
+        let inbound_peer = |peer_id: PeerId, since: Instant| InboundPeer {
+            peer_id,
+            is_validator: self.validator_peers.contains(&peer_id)
+                || self.validator_set.contains(&peer_id),
+            is_persistent: self.is_persistent_peer(&peer_id),
+            since,
+        };
+
+        let new_peer = inbound_peer(peer_id, Instant::now());
+        let inbound_peers: Vec<InboundPeer> = self
+            .inbound_peers
+            .iter()
+            .map(|(peer_id, since)| inbound_peer(*peer_id, *since))
+            .collect();
+
+        let Some(evicted) = self
+            .eviction_policy
+            .select_evictee(&new_peer, &inbound_peers)
+        else {
+            return false;
+        };
+
+        if self.inbound_peers.remove(&evicted).is_none() {
+            warn!("Eviction policy selected peer {evicted} which is not an inbound peer");
+            return false;
+        }
+
+        info!("Evicting inbound peer {evicted} in favor of peer {peer_id}");
+
+        for connection_id in self
+            .active_connections
+            .get(&evicted)
+            .cloned()
+            .unwrap_or_default()
+        {
+            self.controller.close.add_to_queue(
+                (evicted, connection_id, Some(DisconnectReason::Evicted)),
+                None,
+            );
+        }
+
+        true
+    }
+
     pub(crate) fn repair_outbound_peers(&mut self, swarm: &mut Swarm<C>) {
         if !self.is_enabled() || self.outbound_peers.len() >= self.config.num_outbound_peers {
             return;
@@ -92,7 +209,7 @@ where
         // Upgrade any inbound peer to outbound if any is available
         if let Some(peer_id) = self
             .inbound_peers
-            .iter()
+            .keys()
             // Safety check: make sure that the inbound peer is not already an outbound peer
             .find(|peer_id| !self.outbound_peers.contains_key(peer_id))
             .cloned()
@@ -112,12 +229,7 @@ where
         }
 
         // If no inbound peers is available, then select a candidate
-        match self.selector.try_select_n_outbound_candidates(
-            swarm,
-            &self.discovered_peers,
-            self.get_excluded_peers(),
-            1,
-        ) {
+        match self.select_n_outbound_candidates(swarm, 1) {
             Selection::Exactly(peers) => {
                 if let Some(peer_id) = peers.first() {
                     debug!("Trying to connect to peer {peer_id} to repair outbound peers");
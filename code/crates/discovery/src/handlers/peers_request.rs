@@ -6,7 +6,8 @@ use libp2p::{
 use tracing::{debug, error, trace, warn};
 
 use crate::{
-    behaviour::{self, Response, SignedPeerRecordBytes},
+    addr_filter::filter_reachable_addresses,
+    behaviour::{self, DisconnectReason, Response, SignedPeerRecordBytes},
     dial::DialData,
     request::RequestData,
     Discovery, DiscoveryClient,
@@ -65,12 +66,18 @@ where
                 max_violations = self.rate_limiter.max_violations(),
                 "Disconnecting peer due to excessive peers request violations"
             );
-            let _ = swarm.disconnect_peer_id(*peer);
+            self.disconnect_peer_with_reason(swarm, *peer, DisconnectReason::Banned);
         }
 
         false
     }
 
+    /// Hold back the known peers from the peers responses while the node is starting,
+    /// so that the peers do not act upon the view of a node which is still recovering
+    pub fn set_starting(&mut self, starting: bool) {
+        self.starting = starting;
+    }
+
     pub fn peers_request_peer(&mut self, swarm: &mut Swarm<C>, request_data: RequestData) {
         if !self.is_enabled() || !self.should_peers_request(&request_data) {
             return;
@@ -131,6 +138,12 @@ where
         // Process incoming signed records
         self.process_signed_peer_records(swarm, signed_records);
 
+        if self.starting {
+            debug!(%peer, "Node is starting, not sharing the known peers yet");
+            self.send_peers_response(swarm, peer, channel, Vec::new());
+            return;
+        }
+
         // Send back only records they don't already have (the difference)
         let response_records: Vec<SignedPeerRecordBytes> = self
             .signed_peer_records
@@ -151,9 +164,16 @@ where
         records: Vec<SignedPeerRecordBytes>,
     ) {
         let count = records.len();
+
+        // Relay servers advertise their load along with the peers
+        let response = match self.local_relay_load {
+            Some(load) => behaviour::Response::PeersWithRelayLoad(records, load),
+            None => behaviour::Response::Peers(records),
+        };
+
         if swarm
             .behaviour_mut()
-            .send_response(channel, behaviour::Response::Peers(records))
+            .send_response(channel, response)
             .is_err()
         {
             error!(%peer, "Error sending peers response");
@@ -192,9 +212,11 @@ where
                 // Retry request after a delay
                 request_data.retry.inc_count();
 
+                let next_delay = request_data.retry.next_delay(&self.config.retry_backoff);
+
                 self.controller
                     .peers_request
-                    .add_to_queue(request_data.clone(), Some(request_data.retry.next_delay()));
+                    .add_to_queue(request_data.clone(), Some(next_delay));
             } else {
                 // No more trials left
                 error!(
@@ -237,6 +259,22 @@ where
                         continue;
                     }
 
+                    if !self.config.is_peer_allowed(&peer_id) {
+                        debug!(%peer_id, "Ignoring peer record of peer not in the allowlist");
+                        continue;
+                    }
+
+                    let addresses = filter_reachable_addresses(
+                        self.address_policy.as_ref(),
+                        swarm.listeners().chain(swarm.external_addresses()),
+                        addresses,
+                    );
+
+                    if addresses.is_empty() {
+                        debug!(%peer_id, "Ignoring peer record without any reachable address");
+                        continue;
+                    }
+
                     debug!(
                         %peer_id,
                         addr_count = addresses.len(),
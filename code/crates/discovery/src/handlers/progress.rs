@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+use libp2p::Swarm;
+use tracing::{info, warn};
+
+use crate::controller::PeerData;
+use crate::progress::{BootstrapPhase, BootstrapProgress};
+use crate::{Discovery, DiscoveryClient, State};
+
+/// Interval at which the progress is reported when it does not change,
+/// so that a node stuck in bootstrap keeps surfacing where it is stuck
+const PROGRESS_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    fn current_bootstrap_progress(&self) -> BootstrapProgress {
+        let phase = match self.state {
+            _ if self.bootstrap_timed_out => BootstrapPhase::TimedOut,
+            State::Bootstrapping => BootstrapPhase::Bootstrapping,
+            State::Extending(_) => BootstrapPhase::Extending,
+            State::Idle => BootstrapPhase::Done,
+        };
+
+        let dialed_bootstrap_nodes = self
+            .bootstrap_nodes
+            .iter()
+            .filter(|(peer_id, listen_addrs)| {
+                peer_id.is_some()
+                    || listen_addrs.iter().any(|addr| {
+                        self.controller
+                            .dial
+                            .is_done_on(&PeerData::Multiaddr(addr.clone()))
+                    })
+            })
+            .count();
+
+        let identified_bootstrap_nodes = self
+            .bootstrap_nodes
+            .iter()
+            .filter(|(peer_id, _)| peer_id.is_some())
+            .count();
+
+        BootstrapProgress {
+            phase,
+            elapsed: self.metrics.elapsed(),
+            bootstrap_nodes: self.bootstrap_nodes.len(),
+            dialed_bootstrap_nodes,
+            identified_bootstrap_nodes,
+            discovered_peers: self.discovered_peers.len(),
+            outbound_peers: self.outbound_peers.len(),
+            target_outbound_peers: self.config.num_outbound_peers,
+        }
+    }
+
+    /// Progress of the initial discovery process, returned when it changed since the
+    /// last call or at least every few seconds while discovery is in progress.
+    /// The final progress is returned once when initial discovery finishes,
+    /// `None` is returned from then on.
+    pub fn bootstrap_progress(&mut self) -> Option<BootstrapProgress> {
+        if !self.bootstrap_in_progress {
+            return None;
+        }
+
+        let progress = self.current_bootstrap_progress();
+
+        if progress.phase.is_finished() {
+            self.bootstrap_in_progress = false;
+        } else if let Some((last, reported_at)) = &self.last_bootstrap_progress {
+            if !progress.differs_from(last) && reported_at.elapsed() < PROGRESS_REPORT_INTERVAL {
+                return None;
+            }
+        }
+
+        self.last_bootstrap_progress = Some((progress.clone(), Instant::now()));
+
+        Some(progress)
+    }
+
+    /// Stop the initial discovery process if it did not finish within
+    /// [`Config::bootstrap_timeout`](crate::Config::bootstrap_timeout),
+    /// keeping the peers found so far.
+    pub fn check_bootstrap_timeout(&mut self, swarm: &mut Swarm<C>) {
+        let Some(timeout) = self.config.bootstrap_timeout else {
+            return;
+        };
+
+        if !self.bootstrap_in_progress
+            || self.state == State::Idle
+            || self.metrics.elapsed() < timeout
+        {
+            return;
+        }
+
+        warn!(
+            "Discovery bootstrap timed out after {}ms, {}",
+            timeout.as_millis(),
+            self.current_bootstrap_progress()
+        );
+
+        for (_, listen_addrs) in self
+            .bootstrap_nodes
+            .iter()
+            .filter(|(peer_id, _)| peer_id.is_none())
+        {
+            warn!("Bootstrap node {listen_addrs:?} could not be reached");
+        }
+
+        self.bootstrap_timed_out = true;
+
+        self.adjust_peers(swarm);
+
+        self.state = State::Idle;
+
+        info!("Discovery continues in the background with the peers found so far");
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::Instant;
+
+use libp2p::{
+    request_response::{OutboundRequestId, ResponseChannel},
+    swarm::{
+        dial_opts::{DialOpts, PeerCondition},
+        ConnectionId,
+    },
+    Multiaddr, PeerId, Swarm,
+};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    behaviour::{self, RelayLoad, Response},
+    is_relayed_addr,
+    util::relay_peer_id,
+    Discovery, DiscoveryClient,
+};
+
+/// Minimum difference of utilization between the relay a peer is reached through
+/// and another relay of the peer, for the peer to be dialed through the other relay
+const REBALANCE_MARGIN: f64 = 0.25;
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    /// Set the load advertised to the peers if this node runs a relay server,
+    /// or `None` if it does not relay circuits.
+    pub fn set_relay_load(&mut self, load: Option<RelayLoad>) {
+        self.local_relay_load = load;
+        self.local_capabilities.relay_capacity = load.map(|load| load.max_circuits);
+    }
+
+    /// Utilization of a relay, if it advertised its load
+    pub(crate) fn relay_utilization(&self, relay: &PeerId) -> Option<f64> {
+        self.relay_loads.get(relay).map(RelayLoad::utilization)
+    }
+
+    pub(crate) fn record_relay_load(&mut self, peer: PeerId, load: Option<RelayLoad>) {
+        match load {
+            Some(load) => {
+                debug!(%peer, circuits = load.circuits, max_circuits = load.max_circuits, "Recorded relay load");
+                self.relay_loads.insert(peer, load);
+            }
+            None => {
+                self.relay_loads.remove(&peer);
+            }
+        }
+    }
+
+    pub(crate) fn handle_relay_load_request(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        channel: ResponseChannel<Response>,
+        peer: PeerId,
+    ) {
+        if swarm
+            .behaviour_mut()
+            .send_response(channel, Response::RelayLoad(self.local_relay_load))
+            .is_err()
+        {
+            error!(%peer, "Error sending relay load response");
+        }
+    }
+
+    pub(crate) fn handle_relay_load_response(
+        &mut self,
+        request_id: OutboundRequestId,
+        peer: PeerId,
+        load: Option<RelayLoad>,
+    ) {
+        self.relay_load_requests.remove(&request_id);
+        self.record_relay_load(peer, load);
+    }
+
+    /// Whether the request is a relay load request in progress, which is then forgotten
+    pub(crate) fn take_relay_load_request(&mut self, request_id: &OutboundRequestId) -> bool {
+        self.relay_load_requests.remove(request_id)
+    }
+
+    /// Refresh the load of the connected relays, and dial the peers reached only through
+    /// a relay whose load is known through the least loaded of their other relays, if it is
+    /// less loaded by a margin. The connections through the previous relay are closed once
+    /// the peer is connected through the new one.
+    ///
+    /// Called periodically, does nothing until [`crate::Config::relay_rebalance_interval`]
+    /// has elapsed since the last rebalancing.
+    pub fn rebalance_relays(&mut self, swarm: &mut Swarm<C>) {
+        let Some(interval) = self.config.relay_rebalance_interval else {
+            return;
+        };
+
+        if !self.is_enabled() || self.last_relay_rebalance.elapsed() < interval {
+            return;
+        }
+
+        self.last_relay_rebalance = Instant::now();
+
+        // The previous switches either completed or failed by now
+        self.relay_switches.clear();
+
+        let relays: Vec<PeerId> = self
+            .relay_loads
+            .keys()
+            .filter(|relay| swarm.is_connected(relay))
+            .copied()
+            .collect();
+
+        for relay in relays {
+            let request_id = swarm
+                .behaviour_mut()
+                .send_request(&relay, behaviour::Request::RelayLoad());
+
+            self.relay_load_requests.insert(request_id);
+        }
+
+        let relayed_peers: Vec<PeerId> = self
+            .active_connections
+            .keys()
+            .filter(|peer_id| self.is_relayed_only(peer_id))
+            .copied()
+            .collect();
+
+        for peer_id in relayed_peers {
+            let Some((current_relay, current)) = self.most_loaded_relay_of(&peer_id) else {
+                continue;
+            };
+
+            let Some((addr, utilization)) = self.least_loaded_relayed_addr(&peer_id) else {
+                continue;
+            };
+
+            if utilization + REBALANCE_MARGIN > current {
+                continue;
+            }
+
+            info!(
+                peer = %peer_id, %current_relay, current, %addr, utilization,
+                "Dialing relayed peer through a less loaded relay"
+            );
+
+            let dial_opts = DialOpts::peer_id(peer_id)
+                .addresses(vec![addr])
+                .condition(PeerCondition::Always)
+                .build();
+
+            match swarm.dial(dial_opts) {
+                Ok(()) => {
+                    self.relay_switches.insert(peer_id, current_relay);
+                }
+                Err(e) => warn!(peer = %peer_id, "Failed to dial peer through another relay: {e}"),
+            }
+        }
+    }
+
+    /// The most loaded of the relays the peer is currently reached through, with its utilization
+    fn most_loaded_relay_of(&self, peer_id: &PeerId) -> Option<(PeerId, f64)> {
+        self.active_connections
+            .get(peer_id)?
+            .iter()
+            .filter_map(|connection_id| self.connections.get(connection_id))
+            .filter_map(|info| relay_peer_id(&info.remote_addr))
+            .filter_map(|relay| Some((relay, self.relay_utilization(&relay)?)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// The relayed address of the peer through its least loaded relay, with its utilization
+    fn least_loaded_relayed_addr(&self, peer_id: &PeerId) -> Option<(Multiaddr, f64)> {
+        self.discovered_peers
+            .get(peer_id)?
+            .listen_addrs
+            .iter()
+            .filter_map(|addr| {
+                let relay = relay_peer_id(addr)?;
+                Some((addr.clone(), self.relay_utilization(&relay)?))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Close the connections to a peer through the relay it was moved away from,
+    /// once the peer is connected through another relay, see [`Discovery::rebalance_relays`].
+    pub(crate) fn close_switched_relay_connections(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        peer_id: PeerId,
+        new_connection_id: ConnectionId,
+    ) {
+        let Some(previous_relay) = self.relay_switches.get(&peer_id).copied() else {
+            return;
+        };
+
+        let new_relay = self
+            .connections
+            .get(&new_connection_id)
+            .and_then(|info| relay_peer_id(&info.remote_addr));
+
+        // Direct connections are handled by `close_relayed_connections`
+        let is_relayed = self
+            .connections
+            .get(&new_connection_id)
+            .is_some_and(|info| is_relayed_addr(&info.remote_addr));
+
+        if !is_relayed || new_relay == Some(previous_relay) {
+            return;
+        }
+
+        self.relay_switches.remove(&peer_id);
+
+        let Some(connection_ids) = self.active_connections.get_mut(&peer_id) else {
+            return;
+        };
+
+        let connections = &self.connections;
+        let (previous, kept): (Vec<_>, Vec<_>) = connection_ids.iter().partition(|id| {
+            connections
+                .get(id)
+                .is_some_and(|info| relay_peer_id(&info.remote_addr) == Some(previous_relay))
+        });
+
+        *connection_ids = kept;
+
+        for connection_id in previous {
+            info!(
+                peer = %peer_id, %connection_id, %new_connection_id, %previous_relay,
+                "Connected through a less loaded relay, closing connection through the previous relay"
+            );
+
+            self.connections.remove(&connection_id);
+            swarm.close_connection(connection_id);
+        }
+    }
+}
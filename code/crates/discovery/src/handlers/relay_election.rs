@@ -0,0 +1,254 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use libp2p::{PeerId, Swarm};
+use tracing::{info, warn};
+
+use crate::{
+    addr_filter::{ip_of, AddrClass},
+    behaviour::RelayLoad,
+    role::NodeRole,
+    Discovery, DiscoveryClient,
+};
+
+/// Change of the relay setup of this node, see [`Discovery::elect_relays`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RelayElectionEvent {
+    /// This node is publicly reachable and should run a relay server for the peers
+    /// behind a NAT, relaying up to the given number of circuits
+    ServerEnabled { max_circuits: u32 },
+    /// This node is no longer publicly reachable and should stop its relay server
+    ServerDisabled,
+    /// Relays this node behind a NAT should reserve a circuit on and listen through,
+    /// replacing the previously selected ones
+    RelaysSelected(Vec<PeerId>),
+}
+
+impl fmt::Display for RelayElectionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ServerEnabled { max_circuits } => {
+                write!(f, "relay server enabled for up to {max_circuits} circuits")
+            }
+            Self::ServerDisabled => write!(f, "relay server disabled"),
+            Self::RelaysSelected(relays) => write!(f, "{} relays selected", relays.len()),
+        }
+    }
+}
+
+/// Relay advertised by a connected peer, ranked by [`rank_relays`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct RelayCandidate {
+    pub peer_id: PeerId,
+    /// Fraction of its circuits in use, if the relay advertised its load
+    pub utilization: Option<f64>,
+    pub max_circuits: u32,
+}
+
+/// Rank the relays best first: the least utilized first, the relays whose load is unknown
+/// after the ones with some spare capacity, and the largest ones first on a tie
+pub(crate) fn rank_relays(candidates: &mut [RelayCandidate]) {
+    candidates.sort_by(|a, b| {
+        let utilization = |candidate: &RelayCandidate| candidate.utilization.unwrap_or(1.0);
+
+        utilization(a)
+            .total_cmp(&utilization(b))
+            .then_with(|| b.max_circuits.cmp(&a.max_circuits))
+            .then_with(|| match (a.utilization, b.utilization) {
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                _ => Ordering::Equal,
+            })
+    });
+}
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    /// Whether one of the listen or external addresses of this node is a public IP address
+    fn is_publicly_reachable(&self, swarm: &Swarm<C>) -> bool {
+        swarm
+            .listeners()
+            .chain(swarm.external_addresses())
+            .filter_map(ip_of)
+            .any(|ip| !ip.is_unspecified() && AddrClass::of(ip) == AddrClass::Public)
+    }
+
+    /// Relays among the connected peers, advertised in the connect request exchange
+    /// or through their load, best first
+    fn relay_candidates(&self, swarm: &Swarm<C>) -> Vec<RelayCandidate> {
+        let mut candidates: Vec<RelayCandidate> = self
+            .active_connections
+            .keys()
+            .filter(|peer_id| swarm.is_connected(peer_id))
+            .filter(|peer_id| !self.is_banned_peer(peer_id) && !self.is_on_other_chain(peer_id))
+            .filter_map(|peer_id| {
+                let load = self.relay_loads.get(peer_id);
+                let max_circuits = load.map(|load| load.max_circuits).or_else(|| {
+                    self.peer_capabilities
+                        .get(peer_id)
+                        .and_then(|capabilities| capabilities.relay_capacity)
+                })?;
+
+                Some(RelayCandidate {
+                    peer_id: *peer_id,
+                    utilization: load.map(RelayLoad::utilization),
+                    max_circuits,
+                })
+            })
+            .filter(|candidate| candidate.max_circuits > 0)
+            .collect();
+
+        rank_relays(&mut candidates);
+        candidates
+    }
+
+    /// Relays currently selected by this node behind a NAT, see [`Discovery::elect_relays`]
+    pub fn selected_relays(&self) -> &[PeerId] {
+        &self.selected_relays
+    }
+
+    /// Whether the peer is one of the relays selected by this node, never closed while active
+    pub(crate) fn is_selected_relay(&self, peer_id: &PeerId) -> bool {
+        self.selected_relays.contains(peer_id)
+    }
+
+    /// Elect this node as a relay server if it is publicly reachable, advertising its relay
+    /// capacity in the connect request exchange and with a provider record, or otherwise
+    /// select the best relays advertised by the connected peers, instead of relying on
+    /// statically configured relays.
+    ///
+    /// Called periodically, does nothing unless [`crate::Config::auto_relay`] is set.
+    /// Returns the changes of the relay setup the relay server and client should apply.
+    pub fn elect_relays(&mut self, swarm: &mut Swarm<C>) -> Vec<RelayElectionEvent> {
+        let Some(auto_relay) = self.config.auto_relay else {
+            return Vec::new();
+        };
+
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+
+        let mut events = Vec::new();
+        let is_public = self.is_publicly_reachable(swarm);
+
+        if is_public && !self.relay_server_enabled {
+            info!(
+                max_circuits = auto_relay.max_circuits,
+                "Node is publicly reachable, enabling the relay server"
+            );
+
+            self.relay_server_enabled = true;
+            self.set_relay_load(Some(RelayLoad {
+                circuits: 0,
+                max_circuits: auto_relay.max_circuits,
+            }));
+            self.advertise_relay(swarm);
+
+            events.push(RelayElectionEvent::ServerEnabled {
+                max_circuits: auto_relay.max_circuits,
+            });
+        } else if !is_public && self.relay_server_enabled {
+            info!("Node is no longer publicly reachable, disabling the relay server");
+
+            self.relay_server_enabled = false;
+            self.set_relay_load(None);
+
+            if let Some(key) = self.relay_provider_key.take() {
+                swarm.behaviour_mut().stop_providing(&key);
+            }
+
+            events.push(RelayElectionEvent::ServerDisabled);
+        }
+
+        // Publicly reachable nodes are dialed directly and do not need any relay
+        let candidates = if is_public {
+            Vec::new()
+        } else {
+            self.relay_candidates(swarm)
+        };
+
+        // Keep the relays already selected while they are still available, to avoid moving
+        // the circuits around as the loads change, and replace the lost ones with the best
+        // of the other relays
+        let mut selected: Vec<PeerId> = self
+            .selected_relays
+            .iter()
+            .filter(|relay| {
+                candidates
+                    .iter()
+                    .any(|candidate| candidate.peer_id == **relay)
+            })
+            .take(auto_relay.num_relays)
+            .copied()
+            .collect();
+
+        for candidate in candidates {
+            if selected.len() >= auto_relay.num_relays {
+                break;
+            }
+
+            if !selected.contains(&candidate.peer_id) {
+                selected.push(candidate.peer_id);
+            }
+        }
+
+        if selected != self.selected_relays {
+            info!(relays = ?selected, "Selected relays");
+
+            self.selected_relays = selected.clone();
+            events.push(RelayElectionEvent::RelaysSelected(selected));
+        }
+
+        events
+    }
+
+    /// Advertise the relay role with a provider record, along with the role of the node,
+    /// if the provider records are used. Requires the role of this node to be advertised
+    /// first, for the consensus protocol name to be known.
+    fn advertise_relay(&mut self, swarm: &mut Swarm<C>) {
+        if !self.provider_records_enabled() || self.relay_provider_key.is_some() {
+            return;
+        }
+
+        let Some(consensus_protocol) = self.local_consensus_protocol() else {
+            return;
+        };
+
+        let key = NodeRole::Relay.record_key(consensus_protocol);
+
+        match swarm.behaviour_mut().start_providing(key.clone()) {
+            Ok(_) => {
+                info!("Advertising relay role");
+                self.relay_provider_key = Some(key);
+            }
+            Err(e) => warn!("Failed to advertise relay role: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_utilized_relays_first() {
+        let candidate = |utilization, max_circuits| RelayCandidate {
+            peer_id: PeerId::random(),
+            utilization,
+            max_circuits,
+        };
+
+        let idle = candidate(Some(0.0), 64);
+        let large_idle = candidate(Some(0.0), 128);
+        let busy = candidate(Some(0.9), 128);
+        let full = candidate(Some(1.0), 128);
+        let unknown = candidate(None, 128);
+
+        let mut candidates = vec![unknown, full, busy, idle, large_idle];
+        rank_relays(&mut candidates);
+
+        assert_eq!(candidates, vec![large_idle, idle, busy, full, unknown]);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use libp2p::{kad, PeerId, Swarm};
+use tracing::{debug, info, warn};
+
+use crate::{config::BootstrapProtocol, role::NodeRole, Discovery, DiscoveryClient};
+
+/// Role advertised by the local node, see [`Discovery::advertise_role`]
+#[derive(Debug)]
+pub(crate) struct LocalRole {
+    consensus_protocol: String,
+    role: NodeRole,
+    key: kad::RecordKey,
+}
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    pub(crate) fn provider_records_enabled(&self) -> bool {
+        self.is_enabled() && self.config.bootstrap_protocol == BootstrapProtocol::Kademlia
+    }
+
+    /// Advertise the role of this node in the Kademlia DHT, with a provider record keyed by
+    /// the consensus protocol name. The record of the previously advertised role, if any,
+    /// is withdrawn. The role is also advertised in the connect requests and responses,
+    /// the provider records only being used with the Kademlia bootstrap protocol.
+    pub fn advertise_role(
+        &mut self,
+        swarm: &mut Swarm<C>,
+        consensus_protocol: &str,
+        role: NodeRole,
+    ) {
+        self.local_capabilities.role = Some(role);
+
+        if !self.provider_records_enabled() {
+            return;
+        }
+
+        if let Some(local_role) = &self.local_role {
+            if local_role.role == role && local_role.consensus_protocol == consensus_protocol {
+                return;
+            }
+
+            swarm.behaviour_mut().stop_providing(&local_role.key);
+        }
+
+        let key = role.record_key(consensus_protocol);
+
+        match swarm.behaviour_mut().start_providing(key.clone()) {
+            Ok(_) => info!(role = role.as_str(), "Advertising node role"),
+            Err(e) => warn!(role = role.as_str(), "Failed to advertise node role: {e}"),
+        }
+
+        self.local_role = Some(LocalRole {
+            consensus_protocol: consensus_protocol.to_string(),
+            role,
+            key,
+        });
+    }
+
+    /// Consensus protocol name the role of this node is advertised for, if any
+    pub(crate) fn local_consensus_protocol(&self) -> Option<&str> {
+        self.local_role
+            .as_ref()
+            .map(|local_role| local_role.consensus_protocol.as_str())
+    }
+
+    /// Look up the peers advertising themselves as validators, to be dialed first when
+    /// selecting outbound peers. Requires the role of this node to be advertised first,
+    /// for the consensus protocol name to be known.
+    pub(crate) fn find_validator_providers(&mut self, swarm: &mut Swarm<C>) {
+        let Some(local_role) = &self.local_role else {
+            return;
+        };
+
+        let key = NodeRole::Validator.record_key(&local_role.consensus_protocol);
+
+        debug!("Looking up peers advertising the validator role");
+
+        self.advertised_validators.clear();
+        swarm.behaviour_mut().get_providers(key);
+    }
+
+    pub(crate) fn handle_found_providers(
+        &mut self,
+        swarm: &Swarm<C>,
+        key: kad::RecordKey,
+        providers: HashSet<PeerId>,
+    ) {
+        let Some(local_role) = &self.local_role else {
+            return;
+        };
+
+        if key != NodeRole::Validator.record_key(&local_role.consensus_protocol) {
+            return;
+        }
+
+        let local_peer_id = swarm.local_peer_id();
+
+        for peer_id in providers {
+            if peer_id != *local_peer_id && self.advertised_validators.insert(peer_id) {
+                debug!(%peer_id, "Found peer advertising the validator role");
+            }
+        }
+    }
+
+    /// Whether the peer advertises itself as a validator in the Kademlia DHT
+    pub fn is_advertised_validator(&self, peer_id: &PeerId) -> bool {
+        self.advertised_validators.contains(peer_id)
+    }
+}
@@ -8,7 +8,7 @@ use crate::DiscoveryClient;
 
 use super::selector::{Selection, Selector};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct KademliaSelector {}
 
 impl KademliaSelector {
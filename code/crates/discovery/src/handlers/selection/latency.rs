@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use libp2p::{identify, PeerId, Swarm};
+use rand::seq::SliceRandom;
+
+use crate::DiscoveryClient;
+
+use super::selector::{Selection, Selector};
+
+/// Weight given to a new RTT sample in the exponential moving average
+const RTT_SMOOTHING_FACTOR: f64 = 0.2;
+
+/// Selects the outbound candidates with the lowest observed round-trip time.
+///
+/// RTTs are fed from the `ping` behaviour and smoothed with an exponential
+/// moving average. Candidates for which no RTT has been observed yet are
+/// selected last, in random order.
+#[derive(Debug, Default)]
+pub struct LatencySelector {
+    rtts: HashMap<PeerId, Duration>,
+}
+
+impl LatencySelector {
+    pub fn new() -> Self {
+        LatencySelector::default()
+    }
+
+    /// Smoothed RTT of the given peer, if any was observed
+    pub fn rtt(&self, peer_id: &PeerId) -> Option<Duration> {
+        self.rtts.get(peer_id).copied()
+    }
+
+    fn update_rtt(&mut self, peer_id: PeerId, rtt: Duration) {
+        self.rtts
+            .entry(peer_id)
+            .and_modify(|smoothed| {
+                *smoothed = smoothed.mul_f64(1.0 - RTT_SMOOTHING_FACTOR)
+                    + rtt.mul_f64(RTT_SMOOTHING_FACTOR);
+            })
+            .or_insert(rtt);
+    }
+
+    /// Sort peers by increasing RTT, peers without RTT last
+    fn rank(&self, peers: &mut [PeerId]) {
+        // Shuffle first so that peers without RTT (and peers with equal RTT) are picked at random,
+        // the sort being stable.
+        let mut rng = rand::thread_rng();
+        peers.shuffle(&mut rng);
+        peers.sort_by_key(|peer_id| self.rtt(peer_id).unwrap_or(Duration::MAX));
+    }
+}
+
+impl<C> Selector<C> for LatencySelector
+where
+    C: DiscoveryClient,
+{
+    fn try_select_n_outbound_candidates(
+        &mut self,
+        _swarm: &mut Swarm<C>,
+        discovered: &HashMap<PeerId, identify::Info>,
+        excluded: Vec<PeerId>,
+        n: usize,
+    ) -> Selection<PeerId> {
+        if n == 0 {
+            return Selection::None;
+        }
+
+        let mut discovered_candidates: Vec<PeerId> = discovered
+            .keys()
+            .filter(|peer_id| !excluded.contains(peer_id))
+            .cloned()
+            .collect();
+
+        self.rank(&mut discovered_candidates);
+
+        let candidates: Vec<PeerId> = discovered_candidates.into_iter().take(n).collect();
+
+        match candidates.len() {
+            0 => Selection::None,
+            len if len < n => Selection::Only(candidates),
+            _ => Selection::Exactly(candidates),
+        }
+    }
+
+    fn record_rtt(&mut self, peer_id: PeerId, rtt: Duration) {
+        self.update_rtt(peer_id, rtt);
+    }
+
+    fn forget_peer(&mut self, peer_id: &PeerId) {
+        self.rtts.remove(peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::{SelectorHarness, Topology};
+
+    #[test]
+    fn test_rtt_smoothing() {
+        let mut selector = LatencySelector::new();
+        let peer = PeerId::random();
+
+        assert_eq!(selector.rtt(&peer), None);
+
+        selector.update_rtt(peer, Duration::from_millis(100));
+        assert_eq!(selector.rtt(&peer), Some(Duration::from_millis(100)));
+
+        selector.update_rtt(peer, Duration::from_millis(200));
+        assert_eq!(selector.rtt(&peer), Some(Duration::from_millis(120)));
+    }
+
+    #[test]
+    fn test_rank_by_rtt() {
+        let mut selector = LatencySelector::new();
+        let (fast, slow, unknown) = (PeerId::random(), PeerId::random(), PeerId::random());
+
+        selector.update_rtt(fast, Duration::from_millis(10));
+        selector.update_rtt(slow, Duration::from_millis(300));
+
+        let mut peers = vec![unknown, slow, fast];
+        selector.rank(&mut peers);
+
+        assert_eq!(peers, vec![fast, slow, unknown]);
+    }
+
+    #[test]
+    fn test_select_lowest_rtt_first() {
+        let topology = Topology::clusters(&[
+            (1, Duration::from_millis(40)),
+            (1, Duration::from_millis(10)),
+            (1, Duration::from_millis(30)),
+            (1, Duration::from_millis(20)),
+        ])
+        .exclude([1]);
+
+        let peer = |index| topology.peer_id(index);
+        let (slowest, slow, fast) = (peer(0), peer(2), peer(3));
+
+        let mut harness = SelectorHarness::new(LatencySelector::new(), topology);
+
+        // The fastest peer is excluded, the next fastest ones are selected by increasing RTT
+        let Selection::Exactly(selected) = harness.select(2) else {
+            panic!("expected exactly 2 peers");
+        };
+        assert_eq!(selected, vec![fast, slow]);
+
+        let Selection::Only(selected) = harness.select(4) else {
+            panic!("expected only 3 peers");
+        };
+        assert_eq!(selected, vec![fast, slow, slowest]);
+
+        // The slowest peer gets faster than the others
+        for _ in 0..20 {
+            harness
+                .selector_mut()
+                .update_rtt(slowest, Duration::from_millis(1));
+        }
+
+        let Selection::Exactly(selected) = harness.select(2) else {
+            panic!("expected exactly 2 peers");
+        };
+        assert_eq!(selected, vec![slowest, fast]);
+    }
+
+    #[test]
+    fn test_forget_peer() {
+        let mut selector = LatencySelector::new();
+        let (peer, other) = (PeerId::random(), PeerId::random());
+
+        selector.update_rtt(peer, Duration::from_millis(10));
+        selector.update_rtt(other, Duration::from_millis(20));
+
+        Selector::<crate::testkit::TestClient>::forget_peer(&mut selector, &peer);
+
+        assert_eq!(selector.rtt(&peer), None);
+        assert_eq!(selector.rtt(&other), Some(Duration::from_millis(20)));
+        assert_eq!(selector.rtts.len(), 1);
+    }
+}
@@ -1,3 +1,4 @@
 pub mod kademlia;
+pub mod latency;
 pub mod random;
 pub mod selector;
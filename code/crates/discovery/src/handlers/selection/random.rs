@@ -7,7 +7,7 @@ use crate::DiscoveryClient;
 
 use super::selector::{Selection, Selector};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct RandomSelector {}
 
 impl RandomSelector {
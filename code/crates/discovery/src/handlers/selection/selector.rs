@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{collections::HashMap, fmt::Debug, time::Duration};
 
 use libp2p::{identify, PeerId, Swarm};
 use tracing::info;
@@ -7,6 +7,7 @@ use crate::config;
 use crate::{Discovery, DiscoveryClient};
 
 use super::kademlia::KademliaSelector;
+use super::latency::LatencySelector;
 use super::random::RandomSelector;
 
 impl<C> Discovery<C>
@@ -38,29 +39,63 @@ where
                 info!("Using Random selector");
                 Box::new(RandomSelector::new())
             }
+
+            config::Selector::Latency => {
+                info!("Using Latency selector");
+                Box::new(LatencySelector::new())
+            }
         }
     }
 
+    /// Replace the selector of outbound candidates chosen by the configuration
+    pub fn set_selector(&mut self, selector: Box<dyn Selector<C>>) {
+        self.selector = selector;
+    }
+
+    /// Record a round-trip time measured to a peer, eg. by the `ping` protocol
+    pub fn record_peer_rtt(&mut self, peer_id: PeerId, rtt: Duration) {
+        self.selector.record_rtt(peer_id, rtt);
+    }
+
     /// Excluded peers are those that are already outbound or have already
-    /// been requested to be so.
+    /// been requested to be so, and those known to follow another chain.
     pub(crate) fn get_excluded_peers(&self) -> Vec<PeerId> {
         self.discovered_peers
             .keys()
             .filter(|peer_id| {
                 self.outbound_peers.contains_key(peer_id)
                     || self.controller.connect_request.is_done_on(peer_id)
+                    || self.is_on_other_chain(peer_id)
             })
             .cloned()
             .collect()
     }
 }
 
+/// Outbound candidates returned by a [`Selector`]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Selection<T> {
+    /// As many candidates as requested
     Exactly(Vec<T>),
+    /// Fewer candidates than requested
     Only(Vec<T>),
     None,
 }
 
+impl<T> Selection<T> {
+    /// The selected candidates, if any
+    pub fn as_slice(&self) -> &[T] {
+        match self {
+            Self::Exactly(candidates) | Self::Only(candidates) => candidates,
+            Self::None => &[],
+        }
+    }
+}
+
+/// Strategy picking the discovered peers to upgrade to outbound peers.
+///
+/// A custom selector can be installed with [`Discovery::set_selector`] and
+/// exercised offline with the `testkit` feature.
 pub trait Selector<C>: Debug + Send
 where
     C: DiscoveryClient,
@@ -74,4 +109,11 @@ where
         excluded: Vec<PeerId>,
         n: usize,
     ) -> Selection<PeerId>;
+
+    /// Record a round-trip time measured to a peer.
+    /// Selectors which do not rank peers by latency can ignore it.
+    fn record_rtt(&mut self, _peer_id: PeerId, _rtt: Duration) {}
+
+    /// Forget what was recorded about a peer, once the last connection to it is closed.
+    fn forget_peer(&mut self, _peer_id: &PeerId) {}
 }
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use libp2p::{Multiaddr, PeerId};
+
+/// Maximum number of peers remembered, the least recently seen peers being forgotten first
+const MAX_KNOWN_PEERS: usize = 256;
+
+#[derive(Debug)]
+struct KnownPeer {
+    listen_addrs: Vec<Multiaddr>,
+    last_seen: Instant,
+}
+
+/// Snapshot of the peers identified since the node started, kept after they disconnect.
+///
+/// Used as a fallback to reconnect to the network when none of the bootstrap nodes
+/// can be reached.
+#[derive(Debug, Default)]
+pub(crate) struct KnownPeers {
+    peers: HashMap<PeerId, KnownPeer>,
+}
+
+impl KnownPeers {
+    pub(crate) fn record(&mut self, peer_id: PeerId, listen_addrs: Vec<Multiaddr>) {
+        if listen_addrs.is_empty() {
+            return;
+        }
+
+        if self.peers.len() >= MAX_KNOWN_PEERS && !self.peers.contains_key(&peer_id) {
+            if let Some(oldest) = self
+                .peers
+                .iter()
+                .min_by_key(|(_, peer)| peer.last_seen)
+                .map(|(peer_id, _)| *peer_id)
+            {
+                self.peers.remove(&oldest);
+            }
+        }
+
+        self.peers.insert(
+            peer_id,
+            KnownPeer {
+                listen_addrs,
+                last_seen: Instant::now(),
+            },
+        );
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// The `n` most recently seen peers, most recent first
+    pub(crate) fn most_recent(&self, n: usize) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        let mut peers: Vec<(&PeerId, &KnownPeer)> = self.peers.iter().collect();
+        peers.sort_unstable_by_key(|(_, peer)| std::cmp::Reverse(peer.last_seen));
+
+        peers
+            .into_iter()
+            .take(n)
+            .map(|(peer_id, peer)| (*peer_id, peer.listen_addrs.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(i: usize) -> Multiaddr {
+        format!("/ip4/10.0.0.{}/tcp/27000", i % 250 + 1)
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn most_recent_first() {
+        let mut known = KnownPeers::default();
+        let (a, b) = (PeerId::random(), PeerId::random());
+
+        known.record(a, vec![addr(1)]);
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        known.record(b, vec![addr(2)]);
+
+        assert_eq!(known.most_recent(1), vec![(b, vec![addr(2)])]);
+        assert_eq!(known.most_recent(5).len(), 2);
+
+        // Peers without any address are not remembered
+        known.record(PeerId::random(), vec![]);
+        assert_eq!(known.most_recent(5).len(), 2);
+    }
+
+    #[test]
+    fn bounded() {
+        let mut known = KnownPeers::default();
+        let first = PeerId::random();
+
+        known.record(first, vec![addr(0)]);
+        for i in 1..=MAX_KNOWN_PEERS {
+            std::thread::sleep(std::time::Duration::from_micros(10));
+            known.record(PeerId::random(), vec![addr(i)]);
+        }
+
+        assert_eq!(known.peers.len(), MAX_KNOWN_PEERS);
+        assert!(!known.peers.contains_key(&first));
+    }
+}
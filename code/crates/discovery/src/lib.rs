@@ -1,18 +1,33 @@
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
 
 use tracing::{debug, error, info, warn};
 
 use malachitebft_metrics::Registry;
 
 use libp2p::core::SignedEnvelope;
+use libp2p::request_response::OutboundRequestId;
 use libp2p::{identify, kad, request_response, swarm::ConnectionId, Multiaddr, PeerId, Swarm};
 
+pub mod addr_filter;
+use addr_filter::{AddressPolicy, DefaultAddressPolicy};
+
+mod address_book;
+pub use address_book::{AddressBook, AddressBookEntry, AddressBookError};
+
 mod behaviour;
 pub use behaviour::*;
 
+mod capabilities;
+pub use capabilities::PeerCapabilities;
+
 mod dial;
 use dial::DialData;
 
+pub mod eviction;
+use eviction::{EvictionPolicy, RefuseNewPeers};
+
 pub mod config;
 pub use config::Config;
 
@@ -20,7 +35,13 @@ mod controller;
 use controller::Controller;
 
 mod handlers;
-use handlers::selection::selector::Selector;
+use handlers::disconnect::PendingDisconnect;
+pub use handlers::relay_election::RelayElectionEvent;
+use handlers::role::LocalRole;
+pub use handlers::selection::kademlia::KademliaSelector;
+pub use handlers::selection::latency::LatencySelector;
+pub use handlers::selection::random::RandomSelector;
+pub use handlers::selection::selector::{Selection, Selector};
 
 mod metrics;
 use metrics::Metrics;
@@ -28,10 +49,28 @@ use metrics::Metrics;
 mod rate_limiter;
 use rate_limiter::DiscoveryRateLimiter;
 
+mod progress;
+pub use progress::{BootstrapPhase, BootstrapProgress};
+
+mod known_peers;
+use known_peers::KnownPeers;
+
+mod query;
+pub use query::{is_relayed_addr, ConnectionSnapshot, DiscoveredPeer, PeerIdentity, PeerKind};
+
 mod request;
 
+mod role;
+pub use role::NodeRole;
+
+mod topology;
+pub use topology::{DiscoveryTopology, KBucketOccupancy, RelayCircuit};
+
 pub mod util;
 
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+
 #[derive(Debug, PartialEq)]
 enum State {
     Bootstrapping,
@@ -39,7 +78,8 @@ enum State {
     Idle,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ConnectionDirection {
     /// Outbound connection (we dialed the peer)
     Outbound,
@@ -78,8 +118,12 @@ where
     state: State,
 
     selector: Box<dyn Selector<C>>,
+    address_policy: Arc<dyn AddressPolicy>,
+    eviction_policy: Arc<dyn EvictionPolicy>,
 
     bootstrap_nodes: Vec<(Option<PeerId>, Vec<Multiaddr>)>,
+    /// Peers always kept as outbound peers, see [`Config::persistent_peers`]
+    persistent_peers: HashMap<PeerId, Vec<Multiaddr>>,
     discovered_peers: HashMap<PeerId, identify::Info>,
     /// Signed peer records received from peers (cryptographically verified)
     signed_peer_records: HashMap<PeerId, SignedEnvelope>,
@@ -87,11 +131,63 @@ where
     /// Track connection info (direction and remote address) per connection
     pub connections: HashMap<ConnectionId, ConnectionInfo>,
     outbound_peers: HashMap<PeerId, OutboundState>,
-    inbound_peers: HashSet<PeerId>,
+    /// Inbound peers, with the time at which they became inbound peers
+    inbound_peers: HashMap<PeerId, Instant>,
+    /// Peers labeled as validators by the application
+    validator_peers: HashSet<PeerId>,
+    /// Peers of the current validator set as provided by the application, connected or not
+    validator_set: HashSet<PeerId>,
+    /// Peers banned by the application or the operator, never dialed until unbanned
+    banned_peers: HashSet<PeerId>,
+    /// Role advertised by this node in the Kademlia DHT
+    local_role: Option<LocalRole>,
+    /// Peers advertising themselves as validators in the Kademlia DHT, dialed first
+    advertised_validators: HashSet<PeerId>,
+    /// Load advertised to the peers if this node runs a relay server
+    local_relay_load: Option<RelayLoad>,
+    /// Capabilities advertised to the peers in the connect requests and responses
+    local_capabilities: PeerCapabilities,
+    /// Capabilities advertised by the connected peers in their connect requests and responses
+    peer_capabilities: HashMap<PeerId, PeerCapabilities>,
+    /// Load advertised by the relay servers among the peers
+    relay_loads: HashMap<PeerId, RelayLoad>,
+    /// Relay being left by each peer dialed through a less loaded relay
+    relay_switches: HashMap<PeerId, PeerId>,
+    /// Relay load requests in progress
+    relay_load_requests: HashSet<OutboundRequestId>,
+    /// When the relays were last rebalanced
+    last_relay_rebalance: Instant,
+    /// Whether this node was elected as a relay server, see [`Discovery::elect_relays`]
+    relay_server_enabled: bool,
+    /// Key of the provider record advertising this node as a relay
+    relay_provider_key: Option<kad::RecordKey>,
+    /// Relays selected by this node behind a NAT
+    selected_relays: Vec<PeerId>,
+    /// Peers identified since the node started, dialed when no bootstrap node can be reached
+    known_peers: KnownPeers,
+    /// Whether the known peers were dialed since the last time a peer was identified
+    known_peers_dialed: bool,
+    /// Whether the bootstrap nodes were dialed once, their re-dials being spread out
+    bootstrap_nodes_dialed: bool,
+    /// Whether the persistent peers were dialed once, their re-dials being spread out
+    persistent_peers_dialed: bool,
 
     /// Rate limiter for peers requests
     rate_limiter: DiscoveryRateLimiter,
 
+    /// Connections to close once the disconnect request sent to the peer completes
+    pending_disconnects: HashMap<request_response::OutboundRequestId, PendingDisconnect>,
+
+    /// Whether the node is still starting, in which case the known peers are not shared
+    starting: bool,
+
+    /// Whether the initial discovery process is still to be reported as finished
+    bootstrap_in_progress: bool,
+    /// Whether the initial discovery process was stopped by the bootstrap timeout
+    bootstrap_timed_out: bool,
+    /// Last reported progress of the initial discovery process, and when it was reported
+    last_bootstrap_progress: Option<(BootstrapProgress, Instant)>,
+
     pub controller: Controller,
     metrics: Metrics,
 }
@@ -141,32 +237,83 @@ where
             State::Idle
         };
 
+        let selector =
+            Discovery::get_selector(config.enabled, config.bootstrap_protocol, config.selector);
+        let metrics = Metrics::new(registry, !config.enabled || bootstrap_nodes.is_empty());
+
+        let address_policy = config.address_policy.clone().unwrap_or_else(|| {
+            Arc::new(DefaultAddressPolicy::new(config.reachability)) as Arc<dyn AddressPolicy>
+        });
+
+        let mut persistent_peers: HashMap<PeerId, Vec<Multiaddr>> = HashMap::new();
+        for addr in &config.persistent_peers {
+            match util::peer_id_from_multiaddr(addr) {
+                Some(peer_id) => persistent_peers
+                    .entry(peer_id)
+                    .or_default()
+                    .push(addr.clone()),
+                None => warn!("Ignoring persistent peer address without peer ID: {addr}"),
+            }
+        }
+
+        let controller = Controller::new(&config);
+
+        let eviction_policy = config
+            .eviction_policy
+            .clone()
+            .unwrap_or_else(|| Arc::new(RefuseNewPeers) as Arc<dyn EvictionPolicy>);
+
         Self {
             config,
+            bootstrap_in_progress: state != State::Idle,
             state,
 
-            selector: Discovery::get_selector(
-                config.enabled,
-                config.bootstrap_protocol,
-                config.selector,
-            ),
+            selector,
+            address_policy,
+            eviction_policy,
 
             bootstrap_nodes: bootstrap_nodes
                 .clone()
                 .into_iter()
                 .map(|addr| (None, vec![addr]))
                 .collect(),
+            persistent_peers,
             discovered_peers: HashMap::new(),
             signed_peer_records: HashMap::new(),
             active_connections: HashMap::new(),
             connections: HashMap::new(),
             outbound_peers: HashMap::new(),
-            inbound_peers: HashSet::new(),
+            inbound_peers: HashMap::new(),
+            validator_peers: HashSet::new(),
+            validator_set: HashSet::new(),
+            banned_peers: HashSet::new(),
+            local_role: None,
+            advertised_validators: HashSet::new(),
+            local_relay_load: None,
+            local_capabilities: PeerCapabilities::default(),
+            peer_capabilities: HashMap::new(),
+            relay_loads: HashMap::new(),
+            relay_switches: HashMap::new(),
+            relay_load_requests: HashSet::new(),
+            last_relay_rebalance: Instant::now(),
+            relay_server_enabled: false,
+            relay_provider_key: None,
+            selected_relays: Vec::new(),
+            known_peers: KnownPeers::default(),
+            known_peers_dialed: false,
+            bootstrap_nodes_dialed: false,
+            persistent_peers_dialed: false,
 
             rate_limiter: DiscoveryRateLimiter::default(),
+            pending_disconnects: HashMap::new(),
 
-            controller: Controller::new(),
-            metrics: Metrics::new(registry, !config.enabled || bootstrap_nodes.is_empty()),
+            starting: false,
+
+            bootstrap_timed_out: false,
+            last_bootstrap_progress: None,
+
+            controller,
+            metrics,
         }
     }
 
@@ -174,6 +321,18 @@ where
         self.config.enabled
     }
 
+    /// Number of outbound and inbound peers to connect to
+    pub fn peers_bounds(&self) -> (usize, usize) {
+        (self.config.num_outbound_peers, self.config.num_inbound_peers)
+    }
+
+    /// Change the number of outbound and inbound peers to connect to, at runtime.
+    /// The peers already connected are kept, the new bounds applying to the next connections.
+    pub fn set_peers_bounds(&mut self, num_outbound_peers: usize, num_inbound_peers: usize) {
+        self.config
+            .set_peers_bounds(num_outbound_peers, num_inbound_peers);
+    }
+
     /// Check if a peer connection is outbound
     pub fn is_outbound_peer(&self, peer_id: &PeerId) -> bool {
         self.outbound_peers.contains_key(peer_id)
@@ -181,15 +340,76 @@ where
 
     /// Check if a peer connection is inbound
     pub fn is_inbound_peer(&self, peer_id: &PeerId) -> bool {
-        self.inbound_peers.contains(peer_id)
+        self.inbound_peers.contains_key(peer_id)
+    }
+
+    /// Label a peer as a validator or not, to be taken into account by the eviction policy
+    pub fn set_validator_peer(&mut self, peer_id: PeerId, is_validator: bool) {
+        if is_validator {
+            self.validator_peers.insert(peer_id);
+        } else {
+            self.validator_peers.remove(&peer_id);
+        }
     }
 
-    /// Check if a peer is a persistent peer (in the bootstrap_nodes list)
+    /// Replace the peers of the current validator set, as provided by the application.
+    /// They are selected first as outbound peers and their connections are never closed
+    /// to make room for other peers.
+    pub fn set_validator_set(&mut self, peers: HashSet<PeerId>) {
+        self.validator_set = peers;
+    }
+
+    /// Check if a peer is in the current validator set, see [`Discovery::set_validator_set`]
+    pub fn is_in_validator_set(&self, peer_id: &PeerId) -> bool {
+        self.validator_set.contains(peer_id)
+    }
+
+    /// Stop dialing a peer, including when it is a persistent peer or a bootstrap node,
+    /// until it is unbanned
+    pub fn ban_peer(&mut self, peer_id: PeerId) {
+        if self.banned_peers.insert(peer_id) {
+            self.controller
+                .dial
+                .remove_done_on(&controller::PeerData::PeerId(peer_id));
+        }
+    }
+
+    /// Allow dialing a banned peer again, persistent peers being re-dialed by the periodic timer
+    pub fn unban_peer(&mut self, peer_id: &PeerId) {
+        if !self.banned_peers.remove(peer_id) {
+            return;
+        }
+
+        // The dials skipped while the peer was banned are still registered as done
+        let bootstrap_addrs: Vec<_> = self
+            .bootstrap_nodes
+            .iter()
+            .filter(|(maybe_peer_id, _)| maybe_peer_id == &Some(*peer_id))
+            .flat_map(|(_, addrs)| addrs.iter().cloned())
+            .collect();
+
+        self.controller
+            .dial_clear_done_for_peer(*peer_id, &bootstrap_addrs);
+    }
+
+    /// Check if a peer is banned, see [`Discovery::ban_peer`]
+    pub fn is_banned_peer(&self, peer_id: &PeerId) -> bool {
+        self.banned_peers.contains(peer_id)
+    }
+
+    /// Check if a peer is a persistent peer (in the bootstrap_nodes or persistent_peers list)
     pub fn is_persistent_peer(&self, peer_id: &PeerId) -> bool {
         // XXX: The assumption here is bootstrap_nodes is a list of persistent peers.
-        self.bootstrap_nodes
-            .iter()
-            .any(|(maybe_peer_id, _)| maybe_peer_id == &Some(*peer_id))
+        self.is_static_persistent_peer(peer_id)
+            || self
+                .bootstrap_nodes
+                .iter()
+                .any(|(maybe_peer_id, _)| maybe_peer_id == &Some(*peer_id))
+    }
+
+    /// Check if a peer is in the persistent_peers list, see [`Config::persistent_peers`]
+    pub fn is_static_persistent_peer(&self, peer_id: &PeerId) -> bool {
+        self.persistent_peers.contains_key(peer_id)
     }
 
     pub fn on_network_event(
@@ -204,18 +424,29 @@ where
                 ..
             }) => match result {
                 kad::QueryResult::Bootstrap(Ok(_)) => {
-                    if step.last && self.state == State::Bootstrapping {
-                        debug!("Discovery bootstrap successful");
+                    if step.last {
+                        self.find_validator_providers(swarm);
+
+                        if self.state == State::Bootstrapping {
+                            debug!("Discovery bootstrap successful");
 
-                        self.handle_successful_bootstrap(swarm);
+                            self.handle_successful_bootstrap(swarm);
+                        }
                     }
                 }
 
+                kad::QueryResult::GetProviders(Ok(kad::GetProvidersOk::FoundProviders {
+                    key,
+                    providers,
+                })) => {
+                    self.handle_found_providers(swarm, key, providers);
+                }
+
                 kad::QueryResult::Bootstrap(Err(error)) => {
                     error!("Discovery bootstrap failed: {error}");
 
                     if self.state == State::Bootstrapping {
-                        self.handle_failed_bootstrap();
+                        self.handle_failed_bootstrap(swarm);
                     }
                 }
 
@@ -244,10 +475,22 @@ where
                             self.handle_peers_request(swarm, peer, channel, signed_records);
                         }
 
-                        behaviour::Request::Connect() => {
-                            debug!(peer_id = %peer, %connection_id, "Received connect request");
+                        behaviour::Request::Connect(capabilities) => {
+                            debug!(peer_id = %peer, %connection_id, ?capabilities, "Received connect request");
+
+                            self.handle_connect_request(swarm, channel, peer, capabilities);
+                        }
+
+                        behaviour::Request::Disconnect(reason) => {
+                            debug!(peer_id = %peer, %connection_id, reason = reason.as_str(), "Received disconnect request");
 
-                            self.handle_connect_request(swarm, channel, peer);
+                            self.handle_disconnect_request(swarm, channel, peer, reason);
+                        }
+
+                        behaviour::Request::RelayLoad() => {
+                            debug!(peer_id = %peer, %connection_id, "Received relay load request");
+
+                            self.handle_relay_load_request(swarm, channel, peer);
                         }
                     },
 
@@ -271,10 +514,41 @@ where
                             self.handle_peers_response(swarm, request_id, signed_records);
                         }
 
-                        behaviour::Response::Connect(accepted) => {
-                            debug!(%peer, %connection_id, accepted, "Received connect response");
+                        behaviour::Response::PeersWithRelayLoad(signed_records, load) => {
+                            debug!(
+                                %peer, %connection_id,
+                                count = signed_records.len(),
+                                circuits = load.circuits,
+                                max_circuits = load.max_circuits,
+                                "Received peers response from relay"
+                            );
+
+                            self.record_relay_load(peer, Some(load));
+                            self.handle_peers_response(swarm, request_id, signed_records);
+                        }
+
+                        behaviour::Response::Connect(accepted, capabilities) => {
+                            debug!(%peer, %connection_id, accepted, ?capabilities, "Received connect response");
+
+                            self.handle_connect_response(
+                                swarm,
+                                request_id,
+                                peer,
+                                accepted,
+                                capabilities,
+                            );
+                        }
+
+                        behaviour::Response::Disconnect() => {
+                            debug!(%peer, %connection_id, "Received disconnect response");
+
+                            self.handle_disconnect_response(swarm, request_id);
+                        }
+
+                        behaviour::Response::RelayLoad(load) => {
+                            debug!(%peer, %connection_id, ?load, "Received relay load response");
 
-                            self.handle_connect_response(swarm, request_id, peer, accepted);
+                            self.handle_relay_load_response(request_id, peer, load);
                         }
                     },
 
@@ -286,10 +560,15 @@ where
                     } => {
                         error!(%peer, %connection_id, "Outbound request to failed: {error}");
 
-                        if self.controller.peers_request.is_in_progress(&request_id) {
+                        if self.is_disconnect_request(&request_id) {
+                            // Close the connections anyway
+                            self.handle_disconnect_response(swarm, request_id);
+                        } else if self.controller.peers_request.is_in_progress(&request_id) {
                             self.handle_failed_peers_request(swarm, request_id);
                         } else if self.controller.connect_request.is_in_progress(&request_id) {
                             self.handle_failed_connect_request(swarm, request_id);
+                        } else if self.take_relay_load_request(&request_id) {
+                            // The load is refreshed again at the next rebalancing
                         } else {
                             // This should not happen
                             error!(%peer, %connection_id, "Unknown outbound request failure");
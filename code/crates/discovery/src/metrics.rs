@@ -1,10 +1,63 @@
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+use libp2p::PeerId;
+use malachitebft_metrics::prometheus::encoding::EncodeLabelSet;
 use malachitebft_metrics::prometheus::metrics::counter::Counter;
-
+use malachitebft_metrics::prometheus::metrics::family::Family;
 use malachitebft_metrics::prometheus::metrics::gauge::Gauge;
 use malachitebft_metrics::Registry;
 
+// Make prometheus_client available for the derive macro
+use malachitebft_metrics::prometheus as prometheus_client;
+
+use crate::{DisconnectReason, PeerIdentity};
+
+/// Labels for the Identify metadata of a peer
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub(crate) struct PeerIdentityLabels {
+    peer_id: String,
+    agent_version: String,
+    protocol_version: String,
+    /// Comma-separated list of the supported protocols
+    protocols: String,
+}
+
+impl PeerIdentityLabels {
+    fn new(peer_id: &PeerId, identity: &PeerIdentity) -> Self {
+        Self {
+            peer_id: peer_id.to_string(),
+            agent_version: identity.agent_version.clone(),
+            protocol_version: identity.protocol_version.clone(),
+            protocols: identity.protocols.join(","),
+        }
+    }
+}
+
+/// Labels partitioning the active connections by direction and path
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub(crate) struct ConnectionPathLabels {
+    /// Either `inbound` or `outbound`
+    direction: &'static str,
+    /// Either `direct` or `relayed`
+    path: &'static str,
+}
+
+/// Labels partitioning the disconnect requests received by reason
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub(crate) struct DisconnectReasonLabels {
+    reason: &'static str,
+}
+
+/// Number of active connections for each direction and path
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct ConnectionPaths {
+    pub(crate) outbound_direct: usize,
+    pub(crate) outbound_relayed: usize,
+    pub(crate) inbound_direct: usize,
+    pub(crate) inbound_relayed: usize,
+}
+
 #[derive(Debug)]
 pub(crate) struct Metrics {
     /// Time at which discovery started
@@ -34,6 +87,15 @@ pub(crate) struct Metrics {
     /// Number of ephemeral connections
     num_ephemeral_connections: Gauge,
 
+    /// Number of active connections, by direction and by whether they go through a relay
+    num_connections_by_path: Family<ConnectionPathLabels, Gauge>,
+    /// Number of active peers only reachable through relayed connections
+    num_relayed_only_peers: Gauge,
+    /// Total number of relayed connections superseded by a direct connection
+    total_circuit_upgrades: Counter,
+    /// Total number of failed attempts to reach a relayed peer directly
+    total_failed_circuit_upgrades: Counter,
+
     /// Total number of dial attempts
     total_dials: Counter,
     /// Total number of failed dial attempts
@@ -48,6 +110,13 @@ pub(crate) struct Metrics {
     total_failed_connect_requests: Counter,
     /// Total number of rejected connect request attempts
     total_rejected_connect_requests: Counter,
+    /// Number of disconnect requests received from the peers, by reason
+    total_disconnect_requests: Family<DisconnectReasonLabels, Counter>,
+
+    /// Identify metadata of the identified peers (gauge value is always 1)
+    peer_identity_info: Family<PeerIdentityLabels, Gauge>,
+    /// Labels currently set for each peer in `peer_identity_info`
+    peer_identity_labels: HashMap<PeerId, PeerIdentityLabels>,
 }
 
 impl Metrics {
@@ -70,6 +139,11 @@ impl Metrics {
             num_ephemeral_peers: Gauge::default(),
             num_ephemeral_connections: Gauge::default(),
 
+            num_connections_by_path: Family::default(),
+            num_relayed_only_peers: Gauge::default(),
+            total_circuit_upgrades: Counter::default(),
+            total_failed_circuit_upgrades: Counter::default(),
+
             total_dials: Counter::default(),
             total_failed_dials: Counter::default(),
             total_peer_requests: Counter::default(),
@@ -77,6 +151,10 @@ impl Metrics {
             total_connect_requests: Counter::default(),
             total_failed_connect_requests: Counter::default(),
             total_rejected_connect_requests: Counter::default(),
+            total_disconnect_requests: Family::default(),
+
+            peer_identity_info: Family::default(),
+            peer_identity_labels: HashMap::new(),
         };
 
         registry.register(
@@ -133,6 +211,30 @@ impl Metrics {
             this.num_ephemeral_connections.clone(),
         );
 
+        registry.register(
+            "num_connections_by_path",
+            "Number of active connections, by direction (inbound/outbound) and path (direct/relayed)",
+            this.num_connections_by_path.clone(),
+        );
+
+        registry.register(
+            "num_relayed_only_peers",
+            "Number of active peers only reachable through relayed connections",
+            this.num_relayed_only_peers.clone(),
+        );
+
+        registry.register(
+            "total_circuit_upgrades",
+            "Total number of relayed connections superseded by a direct connection",
+            this.total_circuit_upgrades.clone(),
+        );
+
+        registry.register(
+            "total_failed_circuit_upgrades",
+            "Total number of failed attempts to reach a relayed peer directly",
+            this.total_failed_circuit_upgrades.clone(),
+        );
+
         registry.register(
             "total_dials",
             "Total number of dial attempts",
@@ -175,6 +277,18 @@ impl Metrics {
             this.total_rejected_connect_requests.clone(),
         );
 
+        registry.register(
+            "total_disconnect_requests",
+            "Total number of disconnect requests received from the peers, by reason",
+            this.total_disconnect_requests.clone(),
+        );
+
+        registry.register(
+            "peer_identity_info",
+            "Agent version, protocol version and supported protocols reported by each identified peer",
+            this.peer_identity_info.clone(),
+        );
+
         this
     }
 
@@ -234,6 +348,34 @@ impl Metrics {
             .set(num_ephemeral_connections as i64);
     }
 
+    pub(crate) fn set_connection_paths(
+        &self,
+        paths: ConnectionPaths,
+        num_relayed_only_peers: usize,
+    ) {
+        for (direction, path, count) in [
+            ("outbound", "direct", paths.outbound_direct),
+            ("outbound", "relayed", paths.outbound_relayed),
+            ("inbound", "direct", paths.inbound_direct),
+            ("inbound", "relayed", paths.inbound_relayed),
+        ] {
+            self.num_connections_by_path
+                .get_or_create(&ConnectionPathLabels { direction, path })
+                .set(count as i64);
+        }
+
+        self.num_relayed_only_peers
+            .set(num_relayed_only_peers as i64);
+    }
+
+    pub(crate) fn increment_total_circuit_upgrades(&self) {
+        self.total_circuit_upgrades.inc();
+    }
+
+    pub(crate) fn increment_total_failed_circuit_upgrades(&self) {
+        self.total_failed_circuit_upgrades.inc();
+    }
+
     pub(crate) fn increment_total_dials(&self) {
         self.total_dials.inc();
     }
@@ -264,7 +406,44 @@ impl Metrics {
         self.total_rejected_connect_requests.inc();
     }
 
+    pub(crate) fn increment_total_disconnect_requests(&self, reason: DisconnectReason) {
+        self.total_disconnect_requests
+            .get_or_create(&DisconnectReasonLabels {
+                reason: reason.as_str(),
+            })
+            .inc();
+    }
+
+    pub(crate) fn set_peer_identity(&mut self, peer_id: PeerId, identity: &PeerIdentity) {
+        let labels = PeerIdentityLabels::new(&peer_id, identity);
+
+        if let Some(previous) = self.peer_identity_labels.insert(peer_id, labels.clone()) {
+            if previous == labels {
+                return;
+            }
+
+            self.peer_identity_info.remove(&previous);
+        }
+
+        self.peer_identity_info.get_or_create(&labels).set(1);
+    }
+
+    pub(crate) fn remove_peer_identity(&mut self, peer_id: &PeerId) {
+        if let Some(labels) = self.peer_identity_labels.remove(peer_id) {
+            self.peer_identity_info.remove(&labels);
+        }
+    }
+
     pub(crate) fn _get_total_rejected_connect_requests(&self) -> u64 {
         self.total_rejected_connect_requests.get()
     }
+
+    #[cfg(test)]
+    pub(crate) fn get_total_disconnect_requests(&self, reason: DisconnectReason) -> u64 {
+        self.total_disconnect_requests
+            .get_or_create(&DisconnectReasonLabels {
+                reason: reason.as_str(),
+            })
+            .get()
+    }
 }
@@ -0,0 +1,115 @@
+use core::fmt;
+use std::time::Duration;
+
+/// Phase of the initial discovery process
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BootstrapPhase {
+    /// Kademlia bootstrap query in progress
+    Bootstrapping,
+    /// Looking for more peers to reach the target number of outbound peers
+    Extending,
+    /// Initial discovery finished
+    Done,
+    /// Initial discovery was aborted after the bootstrap timeout expired
+    TimedOut,
+}
+
+impl BootstrapPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bootstrapping => "bootstrapping",
+            Self::Extending => "extending",
+            Self::Done => "done",
+            Self::TimedOut => "timed out",
+        }
+    }
+
+    /// Whether initial discovery is over, successfully or not
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::TimedOut)
+    }
+}
+
+/// Progress of the initial discovery process
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BootstrapProgress {
+    pub phase: BootstrapPhase,
+    /// Time since discovery started
+    pub elapsed: Duration,
+    /// Number of configured bootstrap nodes
+    pub bootstrap_nodes: usize,
+    /// Number of bootstrap nodes dialed at least once
+    pub dialed_bootstrap_nodes: usize,
+    /// Number of bootstrap nodes which identified themselves
+    pub identified_bootstrap_nodes: usize,
+    /// Number of discovered peers
+    pub discovered_peers: usize,
+    /// Number of outbound peers
+    pub outbound_peers: usize,
+    /// Target number of outbound peers
+    pub target_outbound_peers: usize,
+}
+
+impl BootstrapProgress {
+    /// Whether the progress differs from the given one, ignoring the elapsed time
+    pub(crate) fn differs_from(&self, other: &Self) -> bool {
+        Self {
+            elapsed: other.elapsed,
+            ..self.clone()
+        } != *other
+    }
+}
+
+impl fmt::Display for BootstrapProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "discovery {} after {}ms: dialed {}/{} bootstrap nodes, identified {}, \
+             discovered {} peers, reached {}/{} outbound peers",
+            self.phase.as_str(),
+            self.elapsed.as_millis(),
+            self.dialed_bootstrap_nodes,
+            self.bootstrap_nodes,
+            self.identified_bootstrap_nodes,
+            self.discovered_peers,
+            self.outbound_peers,
+            self.target_outbound_peers,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(phase: BootstrapPhase, elapsed: u64, outbound_peers: usize) -> BootstrapProgress {
+        BootstrapProgress {
+            phase,
+            elapsed: Duration::from_secs(elapsed),
+            bootstrap_nodes: 3,
+            dialed_bootstrap_nodes: 3,
+            identified_bootstrap_nodes: 1,
+            discovered_peers: 4,
+            outbound_peers,
+            target_outbound_peers: 10,
+        }
+    }
+
+    #[test]
+    fn differs_from_ignores_elapsed() {
+        let a = progress(BootstrapPhase::Extending, 1, 2);
+
+        assert!(!a.differs_from(&progress(BootstrapPhase::Extending, 5, 2)));
+        assert!(a.differs_from(&progress(BootstrapPhase::Extending, 1, 3)));
+        assert!(a.differs_from(&progress(BootstrapPhase::Done, 1, 2)));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            progress(BootstrapPhase::Extending, 2, 2).to_string(),
+            "discovery extending after 2000ms: dialed 3/3 bootstrap nodes, identified 1, \
+             discovered 4 peers, reached 2/10 outbound peers"
+        );
+    }
+}
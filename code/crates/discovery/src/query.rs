@@ -0,0 +1,217 @@
+use libp2p::{identify, multiaddr::Protocol, Multiaddr, PeerId};
+
+use crate::{ConnectionDirection, ConnectionInfo, Discovery, DiscoveryClient, PeerCapabilities};
+
+/// Role of a connected peer from the point of view of discovery
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerKind {
+    /// Peer selected by this node as an outbound peer
+    Outbound,
+    /// Peer that was accepted as an inbound peer
+    Inbound,
+    /// Temporary connection, neither outbound nor inbound
+    Ephemeral,
+}
+
+impl PeerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Outbound => "outbound",
+            Self::Inbound => "inbound",
+            Self::Ephemeral => "ephemeral",
+        }
+    }
+}
+
+/// Snapshot of a single active connection to a peer
+#[derive(Debug, Clone)]
+pub struct ConnectionSnapshot {
+    pub direction: ConnectionDirection,
+    pub remote_addr: Multiaddr,
+    /// Whether the connection goes through a relay (`/p2p-circuit`)
+    pub is_relayed: bool,
+}
+
+impl From<&ConnectionInfo> for ConnectionSnapshot {
+    fn from(info: &ConnectionInfo) -> Self {
+        Self {
+            direction: info.direction,
+            remote_addr: info.remote_addr.clone(),
+            is_relayed: is_relayed_addr(&info.remote_addr),
+        }
+    }
+}
+
+/// Metadata reported by a peer via Identify
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    pub agent_version: String,
+    pub protocol_version: String,
+    /// Protocols supported by the peer, sorted
+    pub protocols: Vec<String>,
+}
+
+impl From<&identify::Info> for PeerIdentity {
+    fn from(info: &identify::Info) -> Self {
+        let mut protocols: Vec<String> = info.protocols.iter().map(|p| p.to_string()).collect();
+        protocols.sort_unstable();
+
+        Self {
+            agent_version: info.agent_version.clone(),
+            protocol_version: info.protocol_version.clone(),
+            protocols,
+        }
+    }
+}
+
+/// Snapshot of a peer currently known to discovery
+#[derive(Debug, Clone)]
+pub struct DiscoveredPeer {
+    pub peer_id: PeerId,
+    /// Listen addresses advertised by the peer via Identify
+    pub listen_addrs: Vec<Multiaddr>,
+    /// Metadata reported by the peer via Identify, if it identified itself
+    pub identity: Option<PeerIdentity>,
+    pub kind: PeerKind,
+    /// Whether the peer is one of the configured persistent peers
+    pub is_persistent: bool,
+    /// Capabilities advertised by the peer, if it exchanged a connect request with us
+    pub capabilities: Option<PeerCapabilities>,
+    pub connections: Vec<ConnectionSnapshot>,
+}
+
+impl DiscoveredPeer {
+    /// Returns true if all active connections to the peer go through a relay
+    pub fn is_relayed(&self) -> bool {
+        !self.connections.is_empty() && self.connections.iter().all(|c| c.is_relayed)
+    }
+}
+
+/// Returns true if the address routes through a relay circuit
+pub fn is_relayed_addr(addr: &Multiaddr) -> bool {
+    addr.iter().any(|p| matches!(p, Protocol::P2pCircuit))
+}
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    /// Kind of a connected peer (outbound, inbound or ephemeral)
+    pub fn peer_kind(&self, peer_id: &PeerId) -> PeerKind {
+        if self.outbound_peers.contains_key(peer_id) {
+            PeerKind::Outbound
+        } else if self.inbound_peers.contains_key(peer_id) {
+            PeerKind::Inbound
+        } else {
+            PeerKind::Ephemeral
+        }
+    }
+
+    /// Metadata reported by a peer via Identify, if it identified itself
+    pub fn peer_identity(&self, peer_id: &PeerId) -> Option<PeerIdentity> {
+        self.discovered_peers.get(peer_id).map(PeerIdentity::from)
+    }
+
+    /// Returns a snapshot of all peers with at least one active connection,
+    /// sorted by peer ID.
+    pub fn discovered_peers(&self) -> Vec<DiscoveredPeer> {
+        let mut peers: Vec<DiscoveredPeer> = self
+            .active_connections
+            .iter()
+            .map(|(peer_id, connection_ids)| DiscoveredPeer {
+                peer_id: *peer_id,
+                listen_addrs: self
+                    .discovered_peers
+                    .get(peer_id)
+                    .map(|info| info.listen_addrs.clone())
+                    .unwrap_or_default(),
+                identity: self.peer_identity(peer_id),
+                kind: self.peer_kind(peer_id),
+                is_persistent: self.is_persistent_peer(peer_id),
+                capabilities: self.peer_capabilities(peer_id).cloned(),
+                connections: connection_ids
+                    .iter()
+                    .filter_map(|id| self.connections.get(id))
+                    .map(ConnectionSnapshot::from)
+                    .collect(),
+            })
+            .collect();
+
+        peers.sort_unstable_by_key(|peer| peer.peer_id);
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_is_relayed_addr() {
+        let direct = Multiaddr::from_str("/ip4/10.0.0.1/tcp/8000").unwrap();
+        let relayed = Multiaddr::from_str(
+            "/ip4/10.0.0.1/tcp/8000/p2p/12D3KooWDpJ7As7BWAwRMfu1VU2WCqNjvq387JEYKDBj4kx6nXTN/p2p-circuit",
+        )
+        .unwrap();
+
+        assert!(!is_relayed_addr(&direct));
+        assert!(is_relayed_addr(&relayed));
+    }
+
+    #[test]
+    fn test_peer_identity_from_identify_info() {
+        let info = identify::Info {
+            public_key: libp2p::identity::Keypair::generate_ed25519().public(),
+            protocol_version: "/malachitebft/1.0.0".to_string(),
+            agent_version: "malachite/0.5.0".to_string(),
+            listen_addrs: vec![],
+            protocols: vec![
+                libp2p::StreamProtocol::new("/meshsub/1.1.0"),
+                libp2p::StreamProtocol::new("/ipfs/id/1.0.0"),
+            ],
+            observed_addr: Multiaddr::empty(),
+            signed_peer_record: None,
+        };
+
+        assert_eq!(
+            PeerIdentity::from(&info),
+            PeerIdentity {
+                agent_version: "malachite/0.5.0".to_string(),
+                protocol_version: "/malachitebft/1.0.0".to_string(),
+                protocols: vec!["/ipfs/id/1.0.0".to_string(), "/meshsub/1.1.0".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_discovered_peer_is_relayed() {
+        let direct = ConnectionSnapshot {
+            direction: ConnectionDirection::Outbound,
+            remote_addr: Multiaddr::from_str("/ip4/10.0.0.1/tcp/8000").unwrap(),
+            is_relayed: false,
+        };
+        let relayed = ConnectionSnapshot {
+            is_relayed: true,
+            ..direct.clone()
+        };
+
+        let mut peer = DiscoveredPeer {
+            peer_id: PeerId::random(),
+            listen_addrs: vec![],
+            identity: None,
+            kind: PeerKind::Ephemeral,
+            is_persistent: false,
+            capabilities: None,
+            connections: vec![],
+        };
+        assert!(!peer.is_relayed());
+
+        peer.connections = vec![relayed.clone()];
+        assert!(peer.is_relayed());
+
+        peer.connections = vec![relayed, direct];
+        assert!(!peer.is_relayed());
+    }
+}
@@ -7,7 +7,7 @@ use libp2p::PeerId;
 const DEFAULT_RATE_WINDOW: Duration = Duration::from_secs(60);
 
 /// Maximum peers requests allowed per peer within the rate window.
-/// Set to 6 to allow initial request + 5 retries (Fibonacci backoff completes in ~12s)
+/// Set to 6 to allow initial request + 5 retries (default exponential backoff completes in ~31s)
 const DEFAULT_MAX_REQUESTS_PER_WINDOW: u32 = 6;
 
 /// Maximum violations before signaling disconnect.
@@ -45,7 +45,7 @@ impl RateLimitResult {
 /// Rate limiter for discovery peers requests.
 ///
 /// Uses a fixed window approach that allows burst requests (to accommodate
-/// retries with Fibonacci backoff) while still protecting against abuse.
+/// retries with exponential backoff) while still protecting against abuse.
 ///
 /// Each peer gets a window that tracks:
 /// - When the window started
@@ -0,0 +1,56 @@
+use libp2p::kad::RecordKey;
+use serde::{Deserialize, Serialize};
+
+/// Role advertised by a node in the Kademlia DHT, as a provider record
+/// keyed by the consensus protocol name and the role.
+///
+/// Used with the Kademlia bootstrap protocol to find the validators among
+/// the discovered peers and dial them first when selecting outbound peers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NodeRole {
+    /// The node is part of the current validator set
+    Validator,
+    /// The node follows consensus without being part of the validator set
+    FullNode,
+    /// The node relays connections on behalf of other peers
+    Relay,
+}
+
+impl NodeRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Validator => "validator",
+            Self::FullNode => "full-node",
+            Self::Relay => "relay",
+        }
+    }
+
+    /// Key of the provider record advertising this role for the given consensus protocol,
+    /// eg. `/malachitebft-core-consensus/v1beta1/role/validator`
+    pub fn record_key(&self, consensus_protocol: &str) -> RecordKey {
+        RecordKey::new(&format!("{consensus_protocol}/role/{}", self.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_keys_differ_per_role_and_protocol() {
+        let protocol = "/malachitebft-core-consensus/v1beta1";
+
+        assert_eq!(
+            NodeRole::Validator.record_key(protocol),
+            RecordKey::new(&"/malachitebft-core-consensus/v1beta1/role/validator")
+        );
+        assert_ne!(
+            NodeRole::Validator.record_key(protocol),
+            NodeRole::FullNode.record_key(protocol)
+        );
+        assert_ne!(
+            NodeRole::Validator.record_key(protocol),
+            NodeRole::Validator.record_key("/other-consensus/v1")
+        );
+    }
+}
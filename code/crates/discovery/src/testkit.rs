@@ -0,0 +1,372 @@
+//! Fixtures and a harness to exercise [`Selector`] implementations against canned
+//! topologies, without a live swarm.
+//!
+//! ```rust,ignore
+//! let topology = Topology::clusters(&[(5, Duration::from_millis(5)), (5, Duration::from_millis(80))]);
+//! let mut harness = SelectorHarness::new(LatencySelector::new(), topology);
+//!
+//! let selected = harness.select(5);
+//! assert!(selected.as_slice().iter().all(|p| harness.topology().cluster_of(p) == Some(0)));
+//! ```
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use libp2p::core::muxing::StreamMuxerBox;
+use libp2p::core::transport::{dummy::DummyTransport, MemoryTransport, Transport};
+use libp2p::core::upgrade;
+use libp2p::identity::Keypair;
+use libp2p::kad::{Addresses, KBucketKey, KBucketRef, QueryId, RecordKey, RoutingUpdate};
+use libp2p::request_response::{OutboundRequestId, ResponseChannel};
+use libp2p::swarm::{self, NetworkBehaviour};
+use libp2p::{identify, noise, yamux, Multiaddr, PeerId, StreamProtocol, Swarm};
+
+use crate::{Behaviour, Config, DiscoveryClient, NetworkEvent, Request, Response};
+use crate::{Selection, Selector};
+
+const AGENT_VERSION: &str = "malachitebft-testkit";
+const PROTOCOL_VERSION: &str = "/malachitebft-testkit/1.0.0";
+const LISTEN_PORT: u16 = 27000;
+
+/// A synthetic peer with a deterministic identity
+#[derive(Clone, Debug)]
+pub struct FixturePeer {
+    pub keypair: Keypair,
+    pub peer_id: PeerId,
+    pub listen_addrs: Vec<Multiaddr>,
+}
+
+impl FixturePeer {
+    /// Peer whose identity is derived from the given seed, listening on the given address
+    pub fn new(seed: u32, listen_addr: Multiaddr) -> Self {
+        let mut secret = [0x42; 32];
+        secret[..4].copy_from_slice(&seed.to_be_bytes());
+
+        let keypair = Keypair::ed25519_from_bytes(secret).expect("valid ed25519 secret key");
+
+        Self {
+            peer_id: keypair.public().to_peer_id(),
+            keypair,
+            listen_addrs: vec![listen_addr],
+        }
+    }
+
+    /// The Identify info the peer would send
+    pub fn identify_info(&self) -> identify::Info {
+        identify_info(&self.keypair, self.listen_addrs.clone())
+    }
+}
+
+/// Identify info for the given identity and listen addresses
+pub fn identify_info(keypair: &Keypair, listen_addrs: Vec<Multiaddr>) -> identify::Info {
+    identify::Info {
+        public_key: keypair.public(),
+        protocol_version: PROTOCOL_VERSION.to_string(),
+        agent_version: AGENT_VERSION.to_string(),
+        listen_addrs,
+        protocols: vec![StreamProtocol::new("/ipfs/id/1.0.0")],
+        observed_addr: Multiaddr::empty(),
+        signed_peer_record: None,
+    }
+}
+
+/// Address of the `host`-th peer of the `subnet`-th /16 private subnet
+fn fixture_addr(subnet: usize, host: usize) -> Multiaddr {
+    let [_, _, hi, lo] = (host as u32 + 1).to_be_bytes();
+    format!("/ip4/10.{subnet}.{hi}.{lo}/tcp/{LISTEN_PORT}")
+        .parse()
+        .expect("valid multiaddr")
+}
+
+/// A canned set of discovered peers, as seen by the node running the selector
+#[derive(Clone, Debug, Default)]
+pub struct Topology {
+    pub peers: Vec<FixturePeer>,
+    /// Cluster of each peer, by index in `peers`
+    pub clusters: Vec<usize>,
+    /// Peers which must not be selected, eg. because they already are outbound peers
+    pub excluded: Vec<PeerId>,
+    /// Round-trip times fed to the selector before selecting
+    pub rtts: Vec<(PeerId, Duration)>,
+    /// Whether the peers are also in the Kademlia routing table
+    pub in_routing_table: bool,
+}
+
+impl Topology {
+    /// `n` peers on the same subnet, without any observed round-trip time
+    pub fn flat(n: usize) -> Self {
+        Self::clusters(&[(n, Duration::ZERO)]).without_rtts()
+    }
+
+    /// Groups of peers, each group on its own subnet with the given round-trip time
+    pub fn clusters(clusters: &[(usize, Duration)]) -> Self {
+        let mut topology = Self::default();
+
+        for (cluster, &(size, rtt)) in clusters.iter().enumerate() {
+            for host in 0..size {
+                let seed = topology.peers.len() as u32;
+                let peer = FixturePeer::new(seed, fixture_addr(cluster, host));
+
+                topology.rtts.push((peer.peer_id, rtt));
+                topology.clusters.push(cluster);
+                topology.peers.push(peer);
+            }
+        }
+
+        topology
+    }
+
+    /// Exclude the peers with the given indices from the selection
+    pub fn exclude(mut self, indices: impl IntoIterator<Item = usize>) -> Self {
+        self.excluded
+            .extend(indices.into_iter().map(|i| self.peers[i].peer_id));
+        self
+    }
+
+    /// Forget all round-trip times
+    pub fn without_rtts(mut self) -> Self {
+        self.rtts.clear();
+        self
+    }
+
+    /// Also add the peers to the Kademlia routing table
+    pub fn with_routing_table(mut self) -> Self {
+        self.in_routing_table = true;
+        self
+    }
+
+    pub fn peer_id(&self, index: usize) -> PeerId {
+        self.peers[index].peer_id
+    }
+
+    /// Cluster of the given peer, if it is part of the topology
+    pub fn cluster_of(&self, peer_id: &PeerId) -> Option<usize> {
+        self.peers
+            .iter()
+            .position(|peer| peer.peer_id == *peer_id)
+            .map(|index| self.clusters[index])
+    }
+
+    /// The discovered peers, as passed to [`Selector::try_select_n_outbound_candidates`]
+    pub fn discovered(&self) -> HashMap<PeerId, identify::Info> {
+        self.peers
+            .iter()
+            .map(|peer| (peer.peer_id, peer.identify_info()))
+            .collect()
+    }
+}
+
+/// Discovery client of the test swarms, see [`offline_swarm`] and [`memory_swarm`]
+#[derive(NetworkBehaviour)]
+#[behaviour(to_swarm = "NetworkEvent")]
+pub struct TestClient {
+    inner: Behaviour,
+}
+
+impl DiscoveryClient for TestClient {
+    fn add_address(&mut self, peer: &PeerId, address: Multiaddr) -> RoutingUpdate {
+        self.kademlia().add_address(peer, address)
+    }
+
+    fn kbuckets(&mut self) -> impl Iterator<Item = KBucketRef<'_, KBucketKey<PeerId>, Addresses>> {
+        self.kademlia().kbuckets()
+    }
+
+    fn start_providing(&mut self, key: RecordKey) -> Result<QueryId, libp2p::kad::store::Error> {
+        self.kademlia().start_providing(key)
+    }
+
+    fn stop_providing(&mut self, key: &RecordKey) {
+        self.kademlia().stop_providing(key)
+    }
+
+    fn get_providers(&mut self, key: RecordKey) -> QueryId {
+        self.kademlia().get_providers(key)
+    }
+
+    fn send_request(&mut self, peer_id: &PeerId, req: Request) -> OutboundRequestId {
+        self.inner.request_response.send_request(peer_id, req)
+    }
+
+    fn send_response(
+        &mut self,
+        ch: ResponseChannel<Response>,
+        rs: Response,
+    ) -> Result<(), Response> {
+        self.inner.request_response.send_response(ch, rs)
+    }
+}
+
+impl TestClient {
+    fn kademlia(&mut self) -> &mut libp2p::kad::Behaviour<libp2p::kad::store::MemoryStore> {
+        self.inner
+            .kademlia
+            .as_mut()
+            .expect("Kademlia is enabled in the test client")
+    }
+}
+
+fn test_client(keypair: &Keypair) -> TestClient {
+    let behaviour = Behaviour::new(
+        keypair,
+        Config::default(),
+        "/malachitebft-testkit/kad".to_string(),
+        "/malachitebft-testkit/discovery".to_string(),
+    )
+    .expect("valid protocol names");
+
+    TestClient { inner: behaviour }
+}
+
+/// Build a swarm for the test client, which cannot dial nor accept any connection,
+/// to run selectors offline
+pub fn offline_swarm(keypair: &Keypair) -> Swarm<TestClient> {
+    Swarm::new(
+        DummyTransport::<(PeerId, StreamMuxerBox)>::new().boxed(),
+        test_client(keypair),
+        keypair.public().to_peer_id(),
+        swarm::Config::without_executor(),
+    )
+}
+
+/// Build a swarm for the test client communicating over an in-memory transport,
+/// to exchange requests with the other memory swarms of the same process
+pub fn memory_swarm(keypair: &Keypair) -> Swarm<TestClient> {
+    let transport = MemoryTransport::default()
+        .upgrade(upgrade::Version::V1)
+        .authenticate(noise::Config::new(keypair).expect("valid noise keypair"))
+        .multiplex(yamux::Config::default())
+        .boxed();
+
+    Swarm::new(
+        transport,
+        test_client(keypair),
+        keypair.public().to_peer_id(),
+        swarm::Config::without_executor().with_idle_connection_timeout(Duration::from_secs(60)),
+    )
+}
+
+/// Runs a selector against a topology
+pub struct SelectorHarness<S> {
+    selector: S,
+    swarm: Swarm<TestClient>,
+    topology: Topology,
+}
+
+impl<S> SelectorHarness<S>
+where
+    S: Selector<TestClient>,
+{
+    /// Set up the local swarm and feed the round-trip times of the topology to the selector
+    pub fn new(mut selector: S, topology: Topology) -> Self {
+        let local = FixturePeer::new(u32::MAX, fixture_addr(255, 0));
+        let mut swarm = offline_swarm(&local.keypair);
+
+        if topology.in_routing_table {
+            for peer in &topology.peers {
+                for addr in &peer.listen_addrs {
+                    swarm
+                        .behaviour_mut()
+                        .add_address(&peer.peer_id, addr.clone());
+                }
+            }
+        }
+
+        for (peer_id, rtt) in &topology.rtts {
+            selector.record_rtt(*peer_id, *rtt);
+        }
+
+        Self {
+            selector,
+            swarm,
+            topology,
+        }
+    }
+
+    /// Ask the selector for `n` outbound candidates among the topology peers
+    pub fn select(&mut self, n: usize) -> Selection<PeerId> {
+        let discovered = self.topology.discovered();
+
+        self.selector.try_select_n_outbound_candidates(
+            &mut self.swarm,
+            &discovered,
+            self.topology.excluded.clone(),
+            n,
+        )
+    }
+
+    pub fn selector(&self) -> &S {
+        &self.selector
+    }
+
+    pub fn selector_mut(&mut self) -> &mut S {
+        &mut self.selector
+    }
+
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{KademliaSelector, LatencySelector, RandomSelector};
+
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn fixtures_are_deterministic() {
+        let a = Topology::flat(3);
+        let b = Topology::flat(3);
+
+        assert_eq!(a.peer_id(2), b.peer_id(2));
+        assert_ne!(a.peer_id(0), a.peer_id(1));
+        assert_eq!(
+            a.discovered()[&a.peer_id(1)].listen_addrs,
+            a.peers[1].listen_addrs
+        );
+    }
+
+    #[test]
+    fn random_selector_skips_excluded_peers() {
+        let topology = Topology::flat(5).exclude([0, 1]);
+        let excluded = topology.excluded.clone();
+        let mut harness = SelectorHarness::new(RandomSelector::new(), topology);
+
+        let Selection::Exactly(selected) = harness.select(3) else {
+            panic!("expected exactly 3 peers");
+        };
+        assert!(selected.iter().all(|p| !excluded.contains(p)));
+
+        assert!(matches!(harness.select(4), Selection::Only(peers) if peers.len() == 3));
+    }
+
+    #[test]
+    fn latency_selector_prefers_close_cluster() {
+        let topology = Topology::clusters(&[(4, ms(120)), (3, ms(5))]);
+        let mut harness = SelectorHarness::new(LatencySelector::new(), topology);
+
+        let selected = harness.select(3);
+        assert_eq!(selected.as_slice().len(), 3);
+        assert!(selected
+            .as_slice()
+            .iter()
+            .all(|p| harness.topology().cluster_of(p) == Some(1)));
+    }
+
+    #[test]
+    fn kademlia_selector_uses_routing_table() {
+        let topology = Topology::flat(6).with_routing_table().exclude([0]);
+        let excluded = topology.peer_id(0);
+        let mut harness = SelectorHarness::new(KademliaSelector::new(), topology);
+
+        let Selection::Exactly(selected) = harness.select(4) else {
+            panic!("expected exactly 4 peers");
+        };
+        assert!(!selected.contains(&excluded));
+    }
+}
@@ -0,0 +1,189 @@
+use libp2p::{PeerId, Swarm};
+use serde::Serialize;
+
+use crate::util::relay_peer_id;
+use crate::{ConnectionDirection, Discovery, DiscoveryClient, RelayLoad};
+
+/// Local view of the overlay built by discovery, see [`Discovery::topology`]
+#[derive(Clone, Debug, Serialize)]
+pub struct DiscoveryTopology {
+    /// Peers selected by this node as outbound peers, sorted
+    pub outbound: Vec<PeerId>,
+    /// Peers accepted as inbound peers, sorted
+    pub inbound: Vec<PeerId>,
+    /// Connected peers which are neither outbound nor inbound peers, sorted
+    pub ephemeral: Vec<PeerId>,
+    /// Connections to the peers going through a relay
+    pub relay_circuits: Vec<RelayCircuit>,
+    /// Relays selected by this node behind a NAT
+    pub selected_relays: Vec<PeerId>,
+    /// Load of the relay server run by this node, if any
+    pub relay_server: Option<RelayLoad>,
+    /// Non-empty buckets of the Kademlia routing table, closest first
+    pub kbuckets: Vec<KBucketOccupancy>,
+}
+
+/// Connection to a peer going through a relay
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RelayCircuit {
+    pub peer_id: PeerId,
+    pub relay: PeerId,
+    pub direction: ConnectionDirection,
+}
+
+/// Number of peers in a bucket of the Kademlia routing table
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct KBucketOccupancy {
+    /// Index of the bucket, ie. the base-2 logarithm of the distance of its peers
+    pub index: u32,
+    pub peers: usize,
+}
+
+impl<C> Discovery<C>
+where
+    C: DiscoveryClient,
+{
+    /// Snapshot of the peers of this node by kind, of its relay circuits and of the occupancy
+    /// of its Kademlia routing table, eg. to draw the graph of the network offline
+    pub fn topology(&self, swarm: &mut Swarm<C>) -> DiscoveryTopology {
+        let mut outbound: Vec<PeerId> = self.outbound_peers.keys().copied().collect();
+        let mut inbound: Vec<PeerId> = self.inbound_peers.keys().copied().collect();
+        let mut ephemeral: Vec<PeerId> = self
+            .active_connections
+            .keys()
+            .filter(|peer_id| {
+                !self.outbound_peers.contains_key(peer_id)
+                    && !self.inbound_peers.contains_key(peer_id)
+            })
+            .copied()
+            .collect();
+
+        outbound.sort_unstable();
+        inbound.sort_unstable();
+        ephemeral.sort_unstable();
+
+        let mut relay_circuits: Vec<RelayCircuit> = self
+            .active_connections
+            .iter()
+            .flat_map(|(peer_id, connection_ids)| {
+                connection_ids
+                    .iter()
+                    .filter_map(|id| self.connections.get(id))
+                    .filter_map(move |info| {
+                        Some(RelayCircuit {
+                            peer_id: *peer_id,
+                            relay: relay_peer_id(&info.remote_addr)?,
+                            direction: info.direction,
+                        })
+                    })
+            })
+            .collect();
+
+        relay_circuits.sort_unstable_by_key(|circuit| (circuit.peer_id, circuit.relay));
+
+        let kbuckets = swarm
+            .behaviour_mut()
+            .kbuckets()
+            .map(|kbucket| KBucketOccupancy {
+                index: kbucket.range().0.ilog2().unwrap_or(0),
+                peers: kbucket.num_entries(),
+            })
+            .collect();
+
+        DiscoveryTopology {
+            outbound,
+            inbound,
+            ephemeral,
+            relay_circuits,
+            selected_relays: self.selected_relays.clone(),
+            relay_server: self.local_relay_load,
+            kbuckets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use libp2p::swarm::ConnectionId;
+    use libp2p::Multiaddr;
+    use malachitebft_metrics::Registry;
+
+    use super::*;
+    use crate::testkit::{offline_swarm, FixturePeer};
+    use crate::{Config, ConnectionInfo, OutboundState};
+
+    #[test]
+    fn peers_are_listed_by_kind_with_their_relay_circuits() {
+        let local = FixturePeer::new(0, "/ip4/10.0.0.1/tcp/27000".parse().unwrap());
+        let relay = FixturePeer::new(1, "/ip4/10.0.0.2/tcp/27000".parse().unwrap());
+        let inbound = FixturePeer::new(2, "/ip4/10.0.0.3/tcp/27000".parse().unwrap());
+        let relayed = FixturePeer::new(3, "/ip4/10.0.0.4/tcp/27000".parse().unwrap());
+
+        let mut swarm = offline_swarm(&local.keypair);
+        let mut discovery = Discovery::new(Config::default(), vec![], &mut Registry::default());
+
+        let circuit = format!("/ip4/10.0.0.2/tcp/27000/p2p/{}/p2p-circuit", relay.peer_id);
+
+        let connections = [
+            (
+                &relay,
+                ConnectionDirection::Outbound,
+                "/ip4/10.0.0.2/tcp/27000",
+            ),
+            (
+                &inbound,
+                ConnectionDirection::Inbound,
+                "/ip4/10.0.0.3/tcp/27000",
+            ),
+            (&relayed, ConnectionDirection::Inbound, circuit.as_str()),
+        ];
+
+        for (id, (peer, direction, remote_addr)) in connections.into_iter().enumerate() {
+            let connection_id = ConnectionId::new_unchecked(id);
+            let remote_addr: Multiaddr = remote_addr.parse().unwrap();
+
+            discovery.connections.insert(
+                connection_id,
+                ConnectionInfo {
+                    direction,
+                    remote_addr,
+                },
+            );
+            discovery
+                .active_connections
+                .entry(peer.peer_id)
+                .or_default()
+                .push(connection_id);
+
+            swarm
+                .behaviour_mut()
+                .add_address(&peer.peer_id, peer.listen_addrs[0].clone());
+        }
+
+        discovery
+            .outbound_peers
+            .insert(relay.peer_id, OutboundState::Confirmed);
+        discovery
+            .inbound_peers
+            .insert(inbound.peer_id, Instant::now());
+
+        let topology = discovery.topology(&mut swarm);
+
+        assert_eq!(topology.outbound, vec![relay.peer_id]);
+        assert_eq!(topology.inbound, vec![inbound.peer_id]);
+        assert_eq!(topology.ephemeral, vec![relayed.peer_id]);
+        assert_eq!(
+            topology.relay_circuits,
+            vec![RelayCircuit {
+                peer_id: relayed.peer_id,
+                relay: relay.peer_id,
+                direction: ConnectionDirection::Inbound,
+            }]
+        );
+
+        let peers: usize = topology.kbuckets.iter().map(|kbucket| kbucket.peers).sum();
+        assert_eq!(peers, 3);
+    }
+}
@@ -1,6 +1,8 @@
 use std::time::Duration;
 
-use libp2p::Multiaddr;
+use libp2p::{Multiaddr, PeerId};
+
+use crate::config::BackoffConfig;
 
 /// Strip /p2p/<peer_id> component from a Multiaddr for address comparison.
 /// This allows comparing addresses regardless of whether they include a peer ID.
@@ -16,47 +18,41 @@ pub fn strip_peer_id_from_multiaddr(addr: &Multiaddr) -> Multiaddr {
     result
 }
 
-#[derive(Debug, Clone)]
-struct FibonacciBackoff {
-    current: u64,
-    next: u64,
-}
+/// Extract the peer ID from the trailing /p2p/<peer_id> component of a Multiaddr, if any.
+pub fn peer_id_from_multiaddr(addr: &Multiaddr) -> Option<PeerId> {
+    use libp2p::multiaddr::Protocol;
 
-impl FibonacciBackoff {
-    pub fn new() -> Self {
-        // Start from 1 second
-        Self {
-            current: 1000,
-            next: 1000,
-        }
+    match addr.iter().last() {
+        Some(Protocol::P2p(peer_id)) => Some(peer_id),
+        _ => None,
     }
 }
 
-impl Iterator for FibonacciBackoff {
-    type Item = Duration;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let new_next = self.current + self.next;
-        self.current = self.next;
-        self.next = new_next;
+/// Extract the peer ID of the relay from a relayed address,
+/// ie. the `/p2p/<relay id>` component before `/p2p-circuit`.
+pub fn relay_peer_id(addr: &Multiaddr) -> Option<PeerId> {
+    use libp2p::multiaddr::Protocol;
 
-        Some(Duration::from_millis(self.current))
+    let mut relay = None;
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::P2p(peer_id) => relay = Some(peer_id),
+            Protocol::P2pCircuit => return relay,
+            _ => {}
+        }
     }
+    None
 }
 
 #[derive(Debug, Clone)]
 pub struct Retry {
     count: usize,
-    backoff: FibonacciBackoff,
 }
 
 impl Retry {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self {
-            count: 0,
-            backoff: FibonacciBackoff::new(),
-        }
+        Self { count: 0 }
     }
 
     pub fn count(&self) -> usize {
@@ -67,9 +63,8 @@ impl Retry {
         self.count += 1;
     }
 
-    pub fn next_delay(&mut self) -> Duration {
-        self.backoff
-            .next()
-            .expect("FibonacciBackoff is an infinite iterator")
+    /// Delay to wait before the current retry, according to the given backoff policy.
+    pub fn next_delay(&self, backoff: &BackoffConfig) -> Duration {
+        backoff.jittered_delay(self.count, &mut rand::thread_rng())
     }
 }
@@ -0,0 +1,145 @@
+//! Failure injection for staging environments.
+//!
+//! Only compiled in with the `chaos` feature. All toggles are off by default and can be
+//! changed at runtime on a live node through [`toggles`], eg. from an admin endpoint,
+//! so that operators can rehearse incident response against realistic failure modes:
+//!
+//! - delayed writes to the write-ahead log,
+//! - outbound gossip messages dropped before being published,
+//! - delayed replies from the application.
+
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::time::Duration;
+
+use rand::Rng;
+use tracing::warn;
+
+/// Current value of the chaos toggles
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChaosSettings {
+    /// Delay added before each write to the write-ahead log
+    pub wal_write_delay: Duration,
+    /// Percentage of the outbound consensus, liveness and proposal part messages
+    /// which are dropped instead of being published, between 0 and 100
+    pub gossip_drop_percent: u8,
+    /// Delay added before forwarding each request to the application,
+    /// and thus before the application reply reaches consensus
+    pub app_reply_delay: Duration,
+}
+
+impl ChaosSettings {
+    /// Whether any failure is currently injected
+    pub fn is_active(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Process-wide chaos toggles, shared by all the actors of the node
+#[derive(Debug)]
+pub struct ChaosToggles {
+    wal_write_delay_ms: AtomicU64,
+    gossip_drop_percent: AtomicU8,
+    app_reply_delay_ms: AtomicU64,
+}
+
+static TOGGLES: ChaosToggles = ChaosToggles {
+    wal_write_delay_ms: AtomicU64::new(0),
+    gossip_drop_percent: AtomicU8::new(0),
+    app_reply_delay_ms: AtomicU64::new(0),
+};
+
+/// The chaos toggles of this process
+pub fn toggles() -> &'static ChaosToggles {
+    &TOGGLES
+}
+
+impl ChaosToggles {
+    pub fn settings(&self) -> ChaosSettings {
+        ChaosSettings {
+            wal_write_delay: Duration::from_millis(self.wal_write_delay_ms.load(Ordering::Relaxed)),
+            gossip_drop_percent: self.gossip_drop_percent.load(Ordering::Relaxed),
+            app_reply_delay: Duration::from_millis(self.app_reply_delay_ms.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Replace all the toggles at once, the drop percentage being capped at 100
+    pub fn apply(&self, settings: ChaosSettings) {
+        if settings.is_active() {
+            warn!(?settings, "Chaos toggles enabled");
+        } else {
+            warn!("Chaos toggles disabled");
+        }
+
+        self.wal_write_delay_ms
+            .store(as_millis(settings.wal_write_delay), Ordering::Relaxed);
+        self.gossip_drop_percent
+            .store(settings.gossip_drop_percent.min(100), Ordering::Relaxed);
+        self.app_reply_delay_ms
+            .store(as_millis(settings.app_reply_delay), Ordering::Relaxed);
+    }
+
+    /// Turn off all the toggles
+    pub fn reset(&self) {
+        self.apply(ChaosSettings::default());
+    }
+
+    /// Block the current thread for the configured WAL write delay, if any
+    pub fn delay_wal_write(&self) {
+        let delay = self.wal_write_delay_ms.load(Ordering::Relaxed);
+
+        if delay > 0 {
+            std::thread::sleep(Duration::from_millis(delay));
+        }
+    }
+
+    /// Whether the next outbound gossip message should be dropped
+    pub fn drop_gossip(&self) -> bool {
+        let percent = self.gossip_drop_percent.load(Ordering::Relaxed);
+
+        percent > 0 && rand::thread_rng().gen_range(0..100) < percent
+    }
+
+    /// Wait for the configured application reply delay, if any
+    pub async fn delay_app_reply(&self) {
+        let delay = self.app_reply_delay_ms.load(Ordering::Relaxed);
+
+        if delay > 0 {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+    }
+}
+
+fn as_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_and_reset() {
+        let toggles = ChaosToggles {
+            wal_write_delay_ms: AtomicU64::new(0),
+            gossip_drop_percent: AtomicU8::new(0),
+            app_reply_delay_ms: AtomicU64::new(0),
+        };
+
+        assert!(!toggles.settings().is_active());
+        assert!(!toggles.drop_gossip());
+
+        toggles.apply(ChaosSettings {
+            wal_write_delay: Duration::from_millis(20),
+            gossip_drop_percent: 250,
+            app_reply_delay: Duration::ZERO,
+        });
+
+        let settings = toggles.settings();
+        assert_eq!(settings.wal_write_delay, Duration::from_millis(20));
+        assert_eq!(settings.gossip_drop_percent, 100);
+        assert!(toggles.drop_gossip());
+
+        toggles.reset();
+        assert_eq!(toggles.settings(), ChaosSettings::default());
+    }
+}
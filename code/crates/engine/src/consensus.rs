@@ -1,7 +1,8 @@
 use core::fmt;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::future::{pending, Future};
 use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -10,29 +11,38 @@ use async_trait::async_trait;
 use derive_where::derive_where;
 use eyre::eyre;
 use ractor::{Actor, ActorProcessingErr, ActorRef, RpcReplyPort};
+use tokio::task::JoinHandle;
 use tokio::time::Instant;
-use tracing::{debug, error, error_span, info, warn};
+use tracing::{debug, error, error_span, field, info, info_span, trace, warn, Instrument, Span};
 
 use malachitebft_codec as codec;
 use malachitebft_config::ConsensusConfig;
 use malachitebft_core_consensus::{
-    Effect, LivenessMsg, PeerId, Resumable, Resume, SignedConsensusMsg, VoteExtensionError,
+    Effect, Evidence, LivenessMsg, PeerId, Resumable, Resume, SignedConsensusMsg,
+    VoteExtensionError,
 };
 use malachitebft_core_types::{
-    Context, Proposal, Round, Timeout, TimeoutKind, Timeouts, ValidatorProof, ValidatorSet,
-    Validity, Value, ValueId, ValueOrigin, ValueResponse as CoreValueResponse, Vote,
+    Context, Height, NilOrVal, Proposal, Round, SignedVote, SigningScheme, ThresholdParams,
+    Timeout, TimeoutKind, Timeouts, Validator, ValidatorProof, ValidatorSet, Validity, Value,
+    ValueId, ValueOrigin, ValueResponse as CoreValueResponse, Vote,
 };
+use malachitebft_core_votekeeper::keeper::Output as VoteKeeperOutput;
 use malachitebft_metrics::Metrics;
 use malachitebft_signing::{SigningProvider, SigningProviderExt};
 use malachitebft_sync::HeightStartType;
 
+use crate::evidence::{EvidencePool, InvalidEvidence, ValidatorSetHistory};
 use crate::host::{HeightParams, HostMsg, HostRef, LocallyProposedValue, Next, ProposedValue};
 use crate::network::{NetworkEvent, NetworkMsg, NetworkRef};
 use crate::sync::Msg as SyncMsg;
+use crate::util::clock::Clock;
 use crate::util::events::{Event, TxEvent};
+use crate::util::mailbox::{Mailbox, MailboxSubscriber};
 use crate::util::msg_buffer::MessageBuffer;
+use crate::util::ordered_msgs::OrderedMessages;
 use crate::util::output_port::OutputPort;
-use crate::util::streaming::StreamMessage;
+use crate::util::streaming::{StreamId, StreamMessage};
+use crate::util::ticker::ticker;
 use crate::util::timers::{TimeoutElapsed, TimerScheduler};
 use crate::wal::{Msg as WalMsg, WalEntry, WalRef};
 
@@ -43,6 +53,21 @@ pub use malachitebft_core_consensus::State as ConsensusState;
 pub mod state_dump;
 use state_dump::StateDump;
 
+pub mod queue_snapshot;
+use queue_snapshot::QueueSnapshot;
+
+pub(crate) mod publisher;
+use publisher::DeferredPublisher;
+
+pub mod verifier;
+use verifier::{PreVerified, SignatureVerifier, VerifiedEvent};
+
+mod vote_filter;
+use vote_filter::{DroppedVote, VoteFilter};
+
+pub mod config_update;
+use config_update::{ConfigUpdate, ConfigUpdateError, ConfigUpdateOutcome};
+
 /// Codec for consensus messages.
 ///
 /// This trait is automatically implemented for any type that implements:
@@ -82,7 +107,7 @@ where
     ctx: Ctx,
     params: ConsensusParams<Ctx>,
     consensus_config: ConsensusConfig,
-    signing_provider: Box<dyn SigningProvider<Ctx>>,
+    signing_provider: Arc<dyn SigningProvider<Ctx>>,
     network: NetworkRef<Ctx>,
     host: HostRef<Ctx>,
     wal: WalRef<Ctx>,
@@ -90,6 +115,14 @@ where
     metrics: Metrics,
     tx_event: TxEvent<Ctx>,
     span: tracing::Span,
+    /// Publishes our messages once the WAL is synced, when the WAL uses group commit
+    publisher: Option<DeferredPublisher<Ctx>>,
+    /// Source of time the timeouts elapse according to
+    clock: Arc<dyn Clock>,
+    /// Admits the events received from the network into the mailbox of the actor
+    mailbox: Mailbox,
+    /// Height of consensus, 0 until started, to shed the stale messages first when overloaded
+    mailbox_height: Arc<AtomicU64>,
 }
 
 pub type ConsensusMsg<Ctx> = Msg<Ctx>;
@@ -127,6 +160,77 @@ pub enum Msg<Ctx: Context> {
 
     /// Request to dump the current consensus state
     DumpState(RpcReplyPort<Option<StateDump<Ctx>>>),
+
+    /// Request a summary of the inputs and messages waiting in the queues of consensus.
+    ///
+    /// Unlike [`Msg::DumpState`], this request is handled right away in every phase,
+    /// including while the WAL is being replayed.
+    SnapshotQueues(RpcReplyPort<QueueSnapshot<Ctx>>),
+
+    /// Request the evidence of equivocation detected locally or gossiped by the peers
+    /// over the last heights, which is not committed to by any decided value.
+    ///
+    /// This evidence differs from one node to another, depending on the messages each
+    /// of them received, so it must not drive any state transition of the application,
+    /// unless the application includes it in a value which is then decided.
+    GetUncommittedEvidence(RpcReplyPort<Vec<Evidence<Ctx>>>),
+
+    /// Disable or re-enable signing at runtime, eg. while migrating the validator key
+    /// to another machine. While signing is disabled, the node behaves as a follower.
+    ///
+    /// Replies with whether signing is enabled once the request has been applied.
+    /// Signing cannot be enabled on a node configured as a follower.
+    SetSigningEnabled(bool, RpcReplyPort<bool>),
+
+    /// Sign with the given key from the given height on, eg. once the validator has registered
+    /// a new consensus key with the application, effective at that height.
+    ///
+    /// Replies with whether the key was registered, which it is not if the height has started.
+    /// The key is not persisted, so it must be registered again after a restart,
+    /// unless the node is started with it.
+    RegisterSigningKey(
+        Ctx::Height,
+        #[derive_where(skip)] Arc<dyn SigningProvider<Ctx>>,
+        RpcReplyPort<bool>,
+    ),
+
+    /// Stop participating in consensus, eg. during maintenance or a coordinated upgrade.
+    ///
+    /// While paused, the votes, proposals and certificates received from the peers are dropped,
+    /// while the timeouts, the values from the application and the sync responses are held
+    /// until consensus is resumed. Only the sync responses are dropped once too many of them
+    /// are held, as they are requested again. Sync requests from the peers are still served.
+    Pause(RpcReplyPort<()>),
+
+    /// Resume participating in consensus at the current height and round,
+    /// processing the messages held while paused, see [`Msg::Pause`]
+    Resume(RpcReplyPort<()>),
+
+    /// Change the configuration of consensus without restarting the node, see [`ConfigUpdate`].
+    ///
+    /// Replies with the parameters applied and those requiring a restart,
+    /// or with an error if the update is invalid, in which case none of them is applied.
+    UpdateConfig(
+        ConfigUpdate<Ctx>,
+        RpcReplyPort<Result<ConfigUpdateOutcome, ConfigUpdateError>>,
+    ),
+
+    /// Process the consensus messages received since the last tick in a deterministic order,
+    /// see `consensus.deterministic_ordering`
+    OrderingTick,
+
+    /// Votes, proposals and certificates received from the network, in the order in which
+    /// they were received, whose signatures have been verified off the consensus actor,
+    /// see `consensus.verification_workers`
+    VerifiedEvents(Vec<VerifiedEvent<Ctx>>),
+
+    /// Request the votes received and cast in the given round of the current height,
+    /// to serve a peer stuck in that round. Empty if consensus is at another height.
+    GetVoteSet(Ctx::Height, Round, RpcReplyPort<Vec<SignedVote<Ctx>>>),
+
+    /// Votes of a round received from a peer in response to a vote set request,
+    /// handled as if they had been received on the gossip layer
+    ProcessVoteSet(PeerId, Vec<SignedVote<Ctx>>),
 }
 
 impl<Ctx: Context> fmt::Display for Msg<Ctx> {
@@ -175,6 +279,21 @@ impl<Ctx: Context> fmt::Display for Msg<Ctx> {
                 write!(f, "RestartHeight(height={height} params={params:?})")
             }
             Msg::DumpState(_) => write!(f, "DumpState"),
+            Msg::SnapshotQueues(_) => write!(f, "SnapshotQueues"),
+            Msg::GetUncommittedEvidence(_) => write!(f, "GetUncommittedEvidence"),
+            Msg::SetSigningEnabled(enabled, _) => write!(f, "SetSigningEnabled({enabled})"),
+            Msg::RegisterSigningKey(height, _, _) => write!(f, "RegisterSigningKey({height})"),
+            Msg::Pause(_) => write!(f, "Pause"),
+            Msg::Resume(_) => write!(f, "Resume"),
+            Msg::UpdateConfig(update, _) => write!(f, "UpdateConfig({update:?})"),
+            Msg::OrderingTick => write!(f, "OrderingTick"),
+            Msg::VerifiedEvents(events) => write!(f, "VerifiedEvents(count={})", events.len()),
+            Msg::GetVoteSet(height, round, _) => {
+                write!(f, "GetVoteSet(height={height} round={round})")
+            }
+            Msg::ProcessVoteSet(from, votes) => {
+                write!(f, "ProcessVoteSet(from={from} count={})", votes.len())
+            }
         }
     }
 }
@@ -195,6 +314,12 @@ impl<Ctx: Context> From<TimeoutElapsed<Timeout>> for Msg<Ctx> {
 
 type Timers = TimerScheduler<Timeout>;
 
+/// Outputs emitted by the vote keeper for each round of a height
+type ReachedQuorums<Ctx> = (
+    <Ctx as Context>::Height,
+    BTreeSet<(Round, VoteKeeperOutput<ValueId<Ctx>>)>,
+);
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum Phase {
     Unstarted,
@@ -203,10 +328,27 @@ enum Phase {
     Recovering,
 }
 
+impl Phase {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unstarted => "unstarted",
+            Self::Ready => "ready",
+            Self::Running => "running",
+            Self::Recovering => "recovering",
+        }
+    }
+}
+
 /// Maximum number of messages to buffer while consensus is
 /// in the `Unstarted` or `Recovering` phase
 const MAX_BUFFER_SIZE: usize = 1024;
 
+/// Upper bound on the timeouts of a network of two validators in development mode
+const DEV_MODE_MAX_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Score delta of a peer sending more stale votes than expected from an honest peer
+const REPLAYED_VOTE_PENALTY: f64 = -1.0;
+
 pub struct State<Ctx: Context> {
     /// Scheduler for timers
     timers: Timers,
@@ -214,6 +356,17 @@ pub struct State<Ctx: Context> {
     /// Timeouts for various consensus steps
     timeouts: Ctx::Timeouts,
 
+    /// Timeouts set at runtime, used instead of those provided by the application,
+    /// see [`Msg::UpdateConfig`]
+    timeouts_override: Option<Ctx::Timeouts>,
+
+    /// Upper bound on the duration of the timeouts, see [`Consensus::initial_params`]
+    max_timeout: Option<Duration>,
+
+    /// Vote limit set at runtime, applied once consensus is started if it is not yet,
+    /// see [`Msg::UpdateConfig`]
+    max_votes_override: Option<Option<usize>>,
+
     /// The state of the consensus state machine,
     /// or `None` if consensus has not been started yet.
     consensus: Option<ConsensusState<Ctx>>,
@@ -221,12 +374,69 @@ pub struct State<Ctx: Context> {
     /// The set of peers we are connected to.
     connected_peers: BTreeSet<PeerId>,
 
+    /// Encoded public keys of the validators behind the connected peers,
+    /// as proven by their validator proofs
+    validator_peers: BTreeMap<PeerId, Vec<u8>>,
+
+    /// Encoded public keys the validators behind the connected peers rotated from,
+    /// still matched until the validator set includes the key of their latest proof
+    rotated_validator_peers: BTreeMap<PeerId, Vec<u8>>,
+
     /// The current phase
     phase: Phase,
 
     /// A buffer of messages that were received while
     /// consensus was `Unstarted` or in the `Recovering` phase
     msg_buffer: MessageBuffer<Ctx>,
+
+    /// Whether signing is enabled, see [`Msg::SetSigningEnabled`]
+    signing_enabled: bool,
+
+    /// Key signing our votes, proposals and vote extensions at the current height
+    signing_key: Arc<dyn SigningProvider<Ctx>>,
+
+    /// Keys to sign with from a later height on, by height, see [`Msg::RegisterSigningKey`]
+    next_signing_keys: BTreeMap<Ctx::Height, Arc<dyn SigningProvider<Ctx>>>,
+
+    /// Whether consensus is paused, see [`Msg::Pause`]
+    paused: bool,
+
+    /// Messages driving consensus held while it is paused, which are never dropped
+    paused_control: MessageBuffer<Ctx>,
+
+    /// Sync responses held while consensus is paused, dropped once the buffer is full
+    paused_buffer: MessageBuffer<Ctx>,
+
+    /// Consensus messages waiting for the next [`Msg::OrderingTick`],
+    /// if deterministic ordering is enabled
+    ordered_msgs: Option<OrderedMessages<Ctx>>,
+
+    /// Task sending [`Msg::OrderingTick`] periodically, if deterministic ordering is enabled
+    ordering_ticker: Option<JoinHandle<()>>,
+
+    /// Evidence of equivocation detected locally or gossiped by the peers,
+    /// not committed to by any decided value
+    evidence: EvidencePool<Ctx>,
+
+    /// Validator sets of the heights the evidence received from the peers may be for
+    validator_sets: ValidatorSetHistory<Ctx>,
+
+    /// Pool of workers verifying the signatures of the messages received from the network,
+    /// if `consensus.verification_workers` is set
+    verifier: Option<SignatureVerifier<Ctx>>,
+
+    /// Outcome of the verification of the message being processed, if done by the workers
+    pre_verified: PreVerified<Ctx>,
+
+    /// Votes already received, to drop the duplicate and stale votes before processing them
+    vote_filter: VoteFilter<Ctx>,
+
+    /// Span of the current height, from its start to the start of the next height,
+    /// which starts a trace of its own when the traces are exported
+    height_span: Span,
+
+    /// Span of the current round, within the span of the current height
+    round_span: Span,
 }
 
 impl<Ctx> State<Ctx>
@@ -247,6 +457,41 @@ where
             .unwrap_or(Round::Nil)
     }
 
+    /// Switch to the latest signing key registered for the given height or a lower one, if any
+    /// Switch to the signing key registered for the given height, if any,
+    /// returning whether the key was switched
+    fn rotate_signing_key(&mut self, height: Ctx::Height) -> bool {
+        let mut rotated = false;
+
+        while let Some(entry) = self.next_signing_keys.first_entry() {
+            if *entry.key() > height {
+                break;
+            }
+
+            let (from, key) = entry.remove_entry();
+            info!(%height, registered_for = %from, "Switching to a new signing key");
+            self.signing_key = key;
+            rotated = true;
+        }
+
+        rotated
+    }
+
+    /// Forget the keys the validators rotated from once the validator set includes their new key
+    fn forget_rotated_validator_keys(&mut self, validator_set: &Ctx::ValidatorSet) {
+        let public_keys = validator_set
+            .iter()
+            .map(|v| Ctx::SigningScheme::encode_public_key(v.public_key()))
+            .collect::<BTreeSet<_>>();
+
+        let validator_peers = &self.validator_peers;
+        self.rotated_validator_peers.retain(|peer_id, _| {
+            validator_peers
+                .get(peer_id)
+                .is_some_and(|public_key| !public_keys.contains(public_key))
+        });
+    }
+
     fn set_phase(&mut self, phase: Phase) {
         if self.phase != phase {
             info!(prev = ?self.phase, new = ?phase, "Phase transition");
@@ -259,6 +504,18 @@ struct HandlerState<'a, Ctx: Context> {
     phase: Phase,
     timers: &'a mut Timers,
     timeouts: Ctx::Timeouts,
+    max_timeout: Option<Duration>,
+    signing_enabled: bool,
+    signing_key: Arc<dyn SigningProvider<Ctx>>,
+    evidence: &'a mut EvidencePool<Ctx>,
+    pre_verified: &'a mut PreVerified<Ctx>,
+}
+
+impl<Ctx: Context> HandlerState<'_, Ctx> {
+    fn timeout_duration(&self, timeout: Timeout) -> Duration {
+        let duration = self.timeouts.duration_for(timeout);
+        self.max_timeout.map_or(duration, |max| duration.min(max))
+    }
 }
 
 impl<Ctx> Consensus<Ctx>
@@ -274,16 +531,22 @@ where
         network: NetworkRef<Ctx>,
         host: HostRef<Ctx>,
         wal: WalRef<Ctx>,
+        wal_group_commit: bool,
         sync: Arc<OutputPort<SyncMsg<Ctx>>>,
         metrics: Metrics,
         tx_event: TxEvent<Ctx>,
+        clock: Arc<dyn Clock>,
+        mailbox: Mailbox,
         span: tracing::Span,
     ) -> Result<ActorRef<Msg<Ctx>>, ractor::SpawnErr> {
+        let publisher =
+            wal_group_commit.then(|| DeferredPublisher::spawn(network.clone(), tx_event.clone()));
+
         let node = Self {
             ctx,
             params,
             consensus_config,
-            signing_provider,
+            signing_provider: Arc::from(signing_provider),
             network,
             host,
             wal,
@@ -291,19 +554,66 @@ where
             metrics,
             tx_event,
             span,
+            publisher,
+            clock,
+            mailbox,
+            mailbox_height: Arc::new(AtomicU64::new(0)),
         };
 
         let (actor_ref, _) = Actor::spawn(None, node, ()).await?;
         Ok(actor_ref)
     }
 
+    /// Consensus parameters for the initial validator set.
+    ///
+    /// In development mode, a single validator uses the [`ThresholdParams::DEV_MODE`]
+    /// thresholds. Two validators keep the default quorum, as with a lower one each of them
+    /// could decide on its own, on a different value than the other one. Their timeouts are
+    /// capped at [`DEV_MODE_MAX_TIMEOUT`] instead, so that they quickly resume deciding
+    /// once both of them are up.
+    fn initial_params(
+        &self,
+        state: &mut State<Ctx>,
+        validator_set: &Ctx::ValidatorSet,
+    ) -> ConsensusParams<Ctx> {
+        let mut params = self.params.clone();
+        let count = validator_set.count();
+
+        if count > 2 {
+            if self.consensus_config.dev_mode {
+                warn!(%count, "Development mode has no effect with more than two validators");
+            }
+        } else if self.consensus_config.dev_mode {
+            warn!(
+                %count,
+                "Running in development mode, consensus is not fault tolerant, \
+                 do not use in production"
+            );
+
+            if count == 1 {
+                params.threshold_params = ThresholdParams::DEV_MODE;
+            } else {
+                state.max_timeout = Some(DEV_MODE_MAX_TIMEOUT);
+            }
+        } else if count == 2 {
+            warn!(
+                "With two validators, consensus cannot make progress if either of them is down, \
+                 consider enabling `consensus.dev_mode` for local development"
+            );
+        }
+
+        params
+    }
+
     async fn process_input(
         &self,
         myself: &ActorRef<Msg<Ctx>>,
         state: &mut State<Ctx>,
         input: ConsensusInput<Ctx>,
     ) -> Result<(), ConsensusError<Ctx>> {
-        malachitebft_core_consensus::process!(
+        let quorums_before = self.reached_quorums(state);
+
+        let result = malachitebft_core_consensus::process!(
             input: input,
             state: state.consensus.as_mut().expect("Consensus not started"),
             metrics: &self.metrics,
@@ -312,11 +622,78 @@ where
                     phase: state.phase,
                     timers: &mut state.timers,
                     timeouts: state.timeouts,
+                    max_timeout: state.max_timeout,
+                    signing_enabled: state.signing_enabled,
+                    signing_key: Arc::clone(&state.signing_key),
+                    evidence: &mut state.evidence,
+                    pre_verified: &mut state.pre_verified,
                 };
 
                 self.handle_effect(myself, handler_state, effect).await
             }
-        )
+        );
+
+        self.emit_reached_quorums(state, quorums_before);
+
+        result
+    }
+
+    /// The quorums of votes reached so far at the current height, as tracked by the vote keeper.
+    ///
+    /// Only computed when someone is subscribed to the events, as this is done for every input.
+    fn reached_quorums(&self, state: &State<Ctx>) -> Option<ReachedQuorums<Ctx>> {
+        if !self.tx_event.has_subscribers() {
+            return None;
+        }
+
+        let consensus = state.consensus.as_ref()?;
+
+        let outputs = consensus
+            .driver
+            .votes()
+            .all_rounds()
+            .iter()
+            .flat_map(|(round, per_round)| {
+                per_round
+                    .emitted_outputs()
+                    .iter()
+                    .map(|output| (*round, output.clone()))
+            })
+            .collect();
+
+        Some((consensus.height(), outputs))
+    }
+
+    /// Emit an event for every quorum of votes reached while processing an input
+    fn emit_reached_quorums(&self, state: &State<Ctx>, before: Option<ReachedQuorums<Ctx>>) {
+        let Some((height, after)) = self.reached_quorums(state) else {
+            return;
+        };
+
+        let before = match before {
+            Some((prev_height, outputs)) if prev_height == height => outputs,
+            _ => BTreeSet::new(),
+        };
+
+        for (round, output) in after.difference(&before) {
+            let round = *round;
+
+            let event = match output {
+                VoteKeeperOutput::PolkaValue(value_id) => {
+                    Event::PolkaReached(height, round, NilOrVal::Val(value_id.clone()))
+                }
+                VoteKeeperOutput::PolkaNil => Event::PolkaReached(height, round, NilOrVal::Nil),
+                VoteKeeperOutput::PrecommitAny => {
+                    Event::PrecommitQuorumReached(height, round, None)
+                }
+                VoteKeeperOutput::PrecommitValue(value_id) => {
+                    Event::PrecommitQuorumReached(height, round, Some(value_id.clone()))
+                }
+                VoteKeeperOutput::PolkaAny | VoteKeeperOutput::SkipRound(_) => continue,
+            };
+
+            self.tx_event.send(|| event);
+        }
     }
 
     #[async_recursion]
@@ -339,12 +716,210 @@ where
         while let Some(msg) = state.msg_buffer.pop() {
             debug!("Replaying buffered message: {msg}");
 
-            if let Err(e) = self.handle_msg(myself.clone(), state, msg).await {
+            if let Err(e) = self.verify_or_handle(myself.clone(), state, msg).await {
                 error!("Error when handling buffered message: {e:?}");
             }
         }
     }
 
+    /// Process the consensus messages received since the last tick,
+    /// ordered by height, round, message type and sender.
+    #[async_recursion]
+    async fn process_ordered_msgs(&self, myself: &ActorRef<Msg<Ctx>>, state: &mut State<Ctx>) {
+        if state.phase != Phase::Running || state.paused {
+            return;
+        }
+
+        let Some(ordered_msgs) = state.ordered_msgs.as_mut() else {
+            return;
+        };
+
+        if ordered_msgs.is_empty() {
+            return;
+        }
+
+        let events = ordered_msgs.take_sorted();
+        debug!(count = %events.len(), "Processing messages in deterministic order");
+
+        for event in events {
+            if let Err(e) = self
+                .verify_or_handle(myself.clone(), state, Msg::NetworkEvent(event))
+                .await
+            {
+                error!("Error when handling ordered message: {e:?}");
+            }
+        }
+    }
+
+    /// Process the messages whose signatures have been verified by the workers,
+    /// in the order in which they were received
+    async fn process_verified_events(
+        &self,
+        myself: &ActorRef<Msg<Ctx>>,
+        state: &mut State<Ctx>,
+        events: Vec<VerifiedEvent<Ctx>>,
+    ) {
+        let Some(generation) = state.verifier.as_ref().map(|v| v.generation()) else {
+            return;
+        };
+
+        for verified in events {
+            let (event, pre_verified) = verified.into_parts(generation);
+            state.pre_verified = pre_verified;
+
+            if let Err(e) = self
+                .handle_msg(myself.clone(), state, Msg::NetworkEvent(event))
+                .await
+            {
+                error!("Error when handling verified message: {e:?}");
+            }
+
+            // Discard the outcome if consensus did not need it
+            state.pre_verified = PreVerified::None;
+        }
+    }
+
+    /// Drop a vote received from a peer if it is a duplicate or for an already decided height,
+    /// penalizing the peers sending more stale votes than expected from an honest peer.
+    ///
+    /// Duplicates are not penalized, as the peer a vote is received from is not necessarily
+    /// its author, and honest peers forward the votes that validators rebroadcast.
+    fn admit_vote(&self, state: &mut State<Ctx>, from: PeerId, vote: &SignedVote<Ctx>) -> bool {
+        let height = state.consensus.as_ref().map(|consensus| consensus.height());
+
+        let Err(dropped) = state.vote_filter.check(height, vote) else {
+            return true;
+        };
+
+        match dropped {
+            DroppedVote::Duplicate => {
+                trace!(%from, height = %vote.height(), round = %vote.round(), "Dropping duplicate vote");
+                self.metrics.duplicate_vote(&from);
+                return false;
+            }
+            DroppedVote::Stale => {
+                trace!(%from, height = %vote.height(), round = %vote.round(), "Dropping stale vote");
+                self.metrics.stale_vote(&from);
+            }
+        }
+
+        if state.vote_filter.record_dropped(from) {
+            if let Err(e) = self
+                .network
+                .cast(NetworkMsg::ReportPeer(from, REPLAYED_VOTE_PENALTY))
+            {
+                error!(%from, "Failed to report peer replaying votes: {e}");
+            }
+        }
+
+        false
+    }
+
+    /// Handle the votes of a round received from a peer in response to a vote set request,
+    /// dropping the ones already received without penalizing the peer, since most of
+    /// the votes requested are expected to be known already
+    async fn process_vote_set(
+        &self,
+        myself: ActorRef<Msg<Ctx>>,
+        state: &mut State<Ctx>,
+        from: PeerId,
+        votes: Vec<SignedVote<Ctx>>,
+    ) {
+        let height = state.consensus.as_ref().map(|consensus| consensus.height());
+        let count = votes.len();
+        let mut fresh = 0;
+
+        for vote in votes {
+            if state.vote_filter.check(height, &vote).is_err() {
+                continue;
+            }
+
+            fresh += 1;
+
+            self.dispatch(
+                myself.clone(),
+                state,
+                Msg::NetworkEvent(NetworkEvent::Vote(from, vote)),
+            )
+            .await;
+        }
+
+        debug!(%from, count, fresh, "Processed the votes of the requested round");
+    }
+
+    /// Hand over the consensus messages from the peers to the signature verification workers
+    /// if enabled, or handle the message right away otherwise
+    async fn verify_or_handle(
+        &self,
+        myself: ActorRef<Msg<Ctx>>,
+        state: &mut State<Ctx>,
+        msg: Msg<Ctx>,
+    ) -> Result<(), ActorProcessingErr> {
+        if let Msg::VerifiedEvents(events) = msg {
+            self.process_verified_events(&myself, state, events).await;
+            return Ok(());
+        }
+
+        let msg = match (msg, state.verifier.as_ref(), state.consensus.as_ref()) {
+            (Msg::NetworkEvent(event), Some(verifier), Some(consensus))
+                if SignatureVerifier::queues(&event) =>
+            {
+                verifier.submit(consensus, event).await;
+                return Ok(());
+            }
+            (msg, _, _) => msg,
+        };
+
+        self.handle_msg(myself, state, msg).await
+    }
+
+    /// Hold, buffer or handle a message depending on whether consensus is paused,
+    /// its current phase and whether deterministic ordering is enabled
+    #[async_recursion]
+    async fn dispatch(&self, myself: ActorRef<Msg<Ctx>>, state: &mut State<Ctx>, msg: Msg<Ctx>) {
+        if state.paused {
+            match on_paused(&msg) {
+                OnPaused::Drop => {
+                    debug!("Consensus is paused, dropping message: {msg}");
+                    return;
+                }
+                OnPaused::Hold => {
+                    state.paused_control.buffer(msg);
+                    return;
+                }
+                OnPaused::HoldIfRoom => {
+                    state.paused_buffer.buffer(msg);
+                    return;
+                }
+                OnPaused::Handle => {}
+            }
+        }
+
+        if state.phase != Phase::Running && should_buffer(&msg) {
+            let _span = error_span!("buffer", phase = ?state.phase).entered();
+            state.msg_buffer.buffer(msg);
+            return;
+        }
+
+        // Hold consensus messages until the next tick if deterministic ordering is enabled
+        let height = state.height();
+        let msg = match (msg, state.ordered_msgs.as_mut()) {
+            (Msg::NetworkEvent(event), Some(ordered_msgs))
+                if OrderedMessages::is_ordered(&event) =>
+            {
+                if !ordered_msgs.push(event, height) {
+                    self.metrics.ordering_dropped_messages.inc();
+                }
+                return;
+            }
+            (msg, _) => msg,
+        };
+
+        if let Err(e) = self.verify_or_handle(myself, state, msg).await {
+            error!("Error when handling message: {e:?}");
+        }
+    }
+
     async fn handle_msg(
         &self,
         myself: ActorRef<Msg<Ctx>>,
@@ -360,17 +935,54 @@ where
                     return Err(eyre!("Validator set for height {height} is empty").into());
                 }
 
+                // Messages submitted for verification until now may have been verified
+                // against the validator set of the previous height
+                if let Some(verifier) = state.verifier.as_mut() {
+                    verifier.next_generation();
+                }
+
+                let rotated = state.rotate_signing_key(height);
+                state.validator_sets.record(height, &params.validator_set);
+                state.forget_rotated_validator_keys(&params.validator_set);
+
+                // The peers only know the validator proof signed with the previous key
+                if rotated {
+                    self.send_validator_proof(state, &params.validator_set)
+                        .await;
+                }
+
                 // Initialize consensus state if this is the first height we start
-                if state.consensus.is_none() {
-                    state.consensus = Some(ConsensusState::new(
+                let is_first_height = state.consensus.is_none();
+                if is_first_height {
+                    let initial_params = self.initial_params(state, &params.validator_set);
+
+                    let mut consensus = ConsensusState::new(
                         self.ctx.clone(),
                         height,
                         params.validator_set.clone(),
-                        self.params.clone(),
+                        initial_params,
                         self.consensus_config.queue_capacity,
-                    ));
+                    );
+
+                    consensus.signing_enabled = state.signing_enabled;
+
+                    if let Some(max_votes) = state.max_votes_override {
+                        consensus.set_max_votes_per_validator_per_round(max_votes);
+                    }
+
+                    state.consensus = Some(consensus);
                 }
 
+                // Replacing the spans of the previous height closes them
+                state.round_span = Span::none();
+                state.height_span = info_span!(
+                    parent: None,
+                    "height",
+                    %height,
+                    is_restart,
+                    decided_round = field::Empty,
+                );
+
                 self.tx_event
                     .send(|| Event::StartedHeight(height, is_restart));
 
@@ -382,6 +994,13 @@ where
                     error!(%height, "Error pushing validator set to network layer: {e}");
                 }
 
+                if let Err(e) = self
+                    .network
+                    .cast(NetworkMsg::StartedHeight(height.as_u64()))
+                {
+                    error!(%height, "Error sending the started height to network layer: {e}");
+                }
+
                 // Fetch entries from the WAL or reset the WAL if this is a restart
                 let wal_entries = if is_restart {
                     hang_on_failure(self.wal_reset(height), |e| {
@@ -404,8 +1023,8 @@ where
                     state.set_phase(Phase::Recovering);
                 }
 
-                // Update the timeouts
-                state.timeouts = params.timeouts;
+                // Update the timeouts, unless they were set at runtime
+                state.timeouts = state.timeouts_override.unwrap_or(params.timeouts);
 
                 // Start consensus for the given height
                 let result = self
@@ -436,6 +1055,13 @@ where
                 // Set the phase to `Running` now that we have replayed the WAL
                 state.set_phase(Phase::Running);
 
+                // Let the network share the known peers, now that we are done recovering
+                if is_first_height {
+                    if let Err(e) = self.network.cast(NetworkMsg::NodeStarted) {
+                        error!(%height, "Error notifying the network that the node started: {e}");
+                    }
+                }
+
                 // Notify the sync actor that we have started a new height.
                 // We want the sync actor to drain buffered values only after consensus is ready and running.
                 let start_type = HeightStartType::from_is_restart(is_restart);
@@ -498,6 +1124,21 @@ where
                         if state.connected_peers.remove(&peer_id) {
                             self.metrics.connected_peers.dec();
                         }
+
+                        state.validator_peers.remove(&peer_id);
+                        state.rotated_validator_peers.remove(&peer_id);
+                    }
+
+                    NetworkEvent::BootstrapProgress(progress) => {
+                        self.tx_event.send(|| Event::BootstrapProgress(progress));
+                    }
+
+                    NetworkEvent::HolePunch(event) => {
+                        self.tx_event.send(|| Event::HolePunch(event));
+                    }
+
+                    NetworkEvent::RelayElection(event) => {
+                        self.tx_event.send(|| Event::RelayElection(event));
                     }
 
                     NetworkEvent::Vote(from, vote) => {
@@ -505,10 +1146,13 @@ where
                             .send(|| Event::Received(SignedConsensusMsg::Vote(vote.clone())));
 
                         if let Err(e) = self
-                            .process_input(&myself, state, ConsensusInput::Vote(vote))
+                            .process_input(&myself, state, ConsensusInput::Vote(vote.clone()))
                             .await
                         {
                             error!(%from, "Error when processing vote: {e}");
+                        } else {
+                            let height = state.consensus.as_ref().map(|c| c.height());
+                            state.vote_filter.record(height, &vote);
                         }
                     }
 
@@ -580,6 +1224,19 @@ where
                             })?;
                     }
 
+                    NetworkEvent::ProposalTooLarge(from, stream_id) => {
+                        if let Err(e) = self
+                            .on_proposal_too_large(&myself, state, from, stream_id)
+                            .await
+                        {
+                            error!(%from, "Error when handling oversized proposal: {e}");
+                        }
+                    }
+
+                    NetworkEvent::Evidence(from, evidence) => {
+                        self.on_evidence(state, from, evidence).await;
+                    }
+
                     NetworkEvent::ValidatorProofReceived { peer_id, proof } => {
                         use malachitebft_network::validator_proof::ProofVerificationResult;
 
@@ -611,6 +1268,32 @@ where
                             }
                         };
 
+                        // A validator sends a proof for its new key when rotating its signing key,
+                        // which the validator set of the current height may not include yet:
+                        // keep matching the previous key until it does
+                        if let Some(public_key) = &public_key_bytes {
+                            let previous =
+                                state.validator_peers.insert(peer_id, public_key.clone());
+
+                            match previous {
+                                Some(previous) if previous != *public_key => {
+                                    let is_in_validator_set =
+                                        state.consensus.as_ref().is_some_and(|consensus| {
+                                            consensus.validator_set().iter().any(|v| {
+                                                Ctx::SigningScheme::encode_public_key(
+                                                    v.public_key(),
+                                                ) == *public_key
+                                            })
+                                        });
+
+                                    if !is_in_validator_set {
+                                        state.rotated_validator_peers.insert(peer_id, previous);
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+
                         // Send verification result to network layer
                         if let Err(e) = self.network.cast(NetworkMsg::ValidatorProofVerified {
                             peer_id,
@@ -699,6 +1382,175 @@ where
 
                 Ok(())
             }
+
+            Msg::GetVoteSet(height, round, reply_to) => {
+                let votes = state
+                    .consensus
+                    .as_ref()
+                    .filter(|consensus| consensus.height() == height)
+                    .and_then(|consensus| consensus.driver.votes().per_round(round))
+                    .map(|per_round| per_round.received_votes().clone())
+                    .unwrap_or_default();
+
+                if let Err(e) = reply_to.send(votes) {
+                    error!("Failed to reply with the votes of round {round}: {e}");
+                }
+
+                Ok(())
+            }
+
+            Msg::ProcessVoteSet(from, votes) => {
+                self.process_vote_set(myself, state, from, votes).await;
+                Ok(())
+            }
+
+            Msg::SnapshotQueues(reply_to) => {
+                let snapshot = QueueSnapshot::new(state);
+
+                info!(
+                    phase = snapshot.phase,
+                    queued = snapshot.total(),
+                    "Taking snapshot of consensus queues"
+                );
+
+                if let Err(e) = reply_to.send(snapshot) {
+                    error!("Failed to reply with queue snapshot: {e}");
+                }
+
+                Ok(())
+            }
+
+            Msg::GetUncommittedEvidence(reply_to) => {
+                let evidence = state.evidence.uncommitted().to_vec();
+
+                if let Err(e) = reply_to.send(evidence) {
+                    error!("Failed to reply with uncommitted evidence: {e}");
+                }
+
+                Ok(())
+            }
+
+            Msg::UpdateConfig(update, reply_to) => {
+                let result = update.validate().map(|()| {
+                    if let Some(timeouts) = update.timeouts {
+                        state.timeouts_override = Some(timeouts);
+                        state.timeouts = timeouts;
+                    }
+
+                    if let Some(max_votes) = update.max_votes_per_validator_per_round {
+                        let max_votes = (max_votes > 0).then_some(max_votes);
+                        state.max_votes_override = Some(max_votes);
+
+                        if let Some(consensus) = &mut state.consensus {
+                            consensus.set_max_votes_per_validator_per_round(max_votes);
+                        }
+                    }
+
+                    update.outcome()
+                });
+
+                match &result {
+                    Ok(outcome) => info!(
+                        applied = ?outcome.applied,
+                        requires_restart = ?outcome.requires_restart,
+                        "Updated consensus configuration"
+                    ),
+                    Err(e) => warn!("Rejected consensus configuration update: {e}"),
+                }
+
+                if let Err(e) = reply_to.send(result) {
+                    error!("Failed to reply to configuration update: {e}");
+                }
+
+                Ok(())
+            }
+
+            Msg::SetSigningEnabled(enabled, reply_to) => {
+                if enabled && self.params.follower {
+                    warn!("Cannot enable signing, node is configured as a follower");
+                } else if enabled != state.signing_enabled {
+                    if enabled {
+                        info!("Signing enabled");
+                    } else {
+                        warn!("Signing disabled, node now behaves as a follower");
+                    }
+
+                    state.signing_enabled = enabled;
+
+                    if let Some(consensus) = &mut state.consensus {
+                        consensus.signing_enabled = enabled;
+                    }
+                }
+
+                if let Err(e) = reply_to.send(state.signing_enabled) {
+                    error!("Failed to reply to signing request: {e}");
+                }
+
+                Ok(())
+            }
+
+            Msg::RegisterSigningKey(height, signing_key, reply_to) => {
+                let registered = state.consensus.is_none() || height > state.height();
+
+                if registered {
+                    info!(%height, "Registered a new signing key");
+                    state.next_signing_keys.insert(height, signing_key);
+                } else {
+                    warn!(%height, "Cannot register a signing key for a height which has started");
+                }
+
+                if let Err(e) = reply_to.send(registered) {
+                    error!("Failed to reply to signing key registration: {e}");
+                }
+
+                Ok(())
+            }
+
+            Msg::Pause(reply_to) => {
+                if !state.paused {
+                    info!("Pausing consensus");
+                    state.paused = true;
+                }
+
+                if let Err(e) = reply_to.send(()) {
+                    error!("Failed to reply to pause request: {e}");
+                }
+
+                Ok(())
+            }
+
+            Msg::Resume(reply_to) => {
+                if state.paused {
+                    let count = state.paused_control.len() + state.paused_buffer.len();
+                    info!(%count, "Resuming consensus");
+                    state.paused = false;
+
+                    // Start the heights and elapse the timeouts before processing
+                    // the sync responses, which may be for the heights held
+                    while let Some(msg) = state
+                        .paused_control
+                        .pop()
+                        .or_else(|| state.paused_buffer.pop())
+                    {
+                        debug!("Replaying message held while paused: {msg}");
+                        self.dispatch(myself.clone(), state, msg).await;
+                    }
+                }
+
+                if let Err(e) = reply_to.send(()) {
+                    error!("Failed to reply to resume request: {e}");
+                }
+
+                Ok(())
+            }
+
+            Msg::VerifiedEvents(_) => unreachable!("VerifiedEvents handled in verify_or_handle"),
+
+            Msg::OrderingTick => {
+                self.process_ordered_msgs(&myself, state).await;
+
+                Ok(())
+            }
         }
     }
 
@@ -723,6 +1575,18 @@ where
             });
         }
 
+        if let Some(consensus) = state.consensus.as_ref() {
+            let height = consensus.height();
+            self.tx_event
+                .send(|| Event::TimeoutElapsed(height, timeout));
+
+            // Still stuck in the round, request its votes in case some were lost by the gossip layer
+            if timeout.kind == TimeoutKind::Rebroadcast {
+                self.sync
+                    .send(SyncMsg::RequestVoteSet(height, consensus.round()));
+            }
+        }
+
         // Process the timeout event
         self.process_input(myself, state, ConsensusInput::TimeoutElapsed(timeout))
             .await?;
@@ -730,6 +1594,41 @@ where
         Ok(())
     }
 
+    /// Sign a validator proof for the public key of our validator in the given validator set
+    /// with the current signing key, and have the network send it to the peers
+    async fn send_validator_proof(&self, state: &State<Ctx>, validator_set: &Ctx::ValidatorSet) {
+        let Some(validator) = validator_set.get_by_address(&self.params.address) else {
+            return;
+        };
+
+        let public_key = Ctx::SigningScheme::encode_public_key(validator.public_key());
+
+        let peer_id = match ractor::call!(self.network, NetworkMsg::NodeKeyStatus) {
+            Ok(Some(status)) => status.peer_id,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Error requesting the peer ID from the network: {e}");
+                return;
+            }
+        };
+
+        let proof = match state
+            .signing_key
+            .sign_validator_proof(public_key, peer_id.to_bytes())
+            .await
+        {
+            Ok(proof) => proof,
+            Err(e) => {
+                error!("Failed to sign validator proof with the new signing key: {e}");
+                return;
+            }
+        };
+
+        if let Err(e) = self.network.cast(NetworkMsg::UpdateValidatorProof(proof)) {
+            error!("Error sending the validator proof to the network: {e}");
+        }
+    }
+
     async fn wal_reset(&self, height: Ctx::Height) -> Result<(), ActorProcessingErr> {
         let result = ractor::call!(self.wal, WalMsg::Reset, height);
 
@@ -993,6 +1892,137 @@ where
         Ok(())
     }
 
+    /// Prevote nil if the proposer of the current round sent a value over the maximum value size,
+    /// as the value would be dropped anyway, rather than waiting for the propose timeout.
+    ///
+    /// The stream is only attributed to the proposer if the peer proved to be its validator,
+    /// so that no other peer can get the round skipped.
+    async fn on_proposal_too_large(
+        &self,
+        myself: &ActorRef<Msg<Ctx>>,
+        state: &mut State<Ctx>,
+        from: PeerId,
+        stream_id: StreamId,
+    ) -> Result<(), ConsensusError<Ctx>> {
+        let Some(consensus) = state.consensus.as_ref() else {
+            return Ok(());
+        };
+
+        let is_proposer = consensus
+            .driver
+            .proposer_address()
+            .and_then(|address| consensus.validator_set().get_by_address(address))
+            .is_some_and(|proposer| {
+                let public_key = Ctx::SigningScheme::encode_public_key(proposer.public_key());
+
+                state.validator_peers.get(&from) == Some(&public_key)
+                    || state.rotated_validator_peers.get(&from) == Some(&public_key)
+            });
+
+        if !is_proposer || !consensus.driver.step_is_propose() {
+            debug!(%from, %stream_id, "Dropped oversized proposal stream");
+            return Ok(());
+        }
+
+        let round = consensus.round();
+        warn!(%from, %stream_id, %round, "Proposer sent a value over the maximum value size, prevoting nil");
+
+        self.timeout_elapsed(myself, state, Timeout::propose(round))
+            .await
+    }
+
+    /// Add the evidence gossiped by a peer to the pool, once checked that it is a valid proof
+    /// of equivocation by a validator of the current validator set.
+    async fn on_evidence(&self, state: &mut State<Ctx>, from: PeerId, evidence: Evidence<Ctx>) {
+        let height = evidence.height();
+        let address = evidence.validator_address().clone();
+
+        let Some(consensus) = &state.consensus else {
+            return;
+        };
+
+        if state.evidence.contains(&evidence) {
+            debug!(%from, %height, %address, "Ignoring duplicate evidence");
+            return;
+        }
+
+        if EvidencePool::<Ctx>::is_expired(height, consensus.height()) {
+            debug!(%from, %height, %address, "Ignoring expired evidence");
+            return;
+        }
+
+        let result = crate::evidence::verify(
+            self.signing_provider.as_ref(),
+            &state.validator_sets,
+            &evidence,
+        )
+        .await;
+
+        match result {
+            Ok(()) => {}
+            Err(e @ InvalidEvidence::UnknownHeight) => {
+                debug!(%from, %height, %address, "Ignoring evidence: {e}");
+                return;
+            }
+            Err(e @ InvalidEvidence::Verification(_)) => {
+                error!(%from, %height, %address, "Failed to verify evidence: {e}");
+                return;
+            }
+            Err(e) => {
+                warn!(%from, %height, %address, "Ignoring invalid evidence: {e}");
+                return;
+            }
+        }
+
+        info!(
+            %from, %height, %address, kind = ?evidence.kind(),
+            "Received evidence of equivocation"
+        );
+
+        if state.evidence.add(evidence) {
+            self.save_evidence(&state.evidence).await;
+        }
+    }
+
+    async fn save_evidence(&self, evidence: &EvidencePool<Ctx>) {
+        let result = ractor::call!(self.wal, WalMsg::SaveEvidence, evidence.snapshot());
+
+        match result {
+            Ok(Ok(())) => {
+                // Success
+            }
+            Ok(Err(e)) => {
+                error!("Failed to save evidence: {e}");
+            }
+            Err(e) => {
+                error!("Failed to send SaveEvidence command to WAL actor: {e}");
+            }
+        }
+    }
+
+    async fn load_evidence(&self) -> EvidencePool<Ctx> {
+        match ractor::call!(self.wal, WalMsg::LoadEvidence) {
+            Ok(Ok(snapshot)) => {
+                if !snapshot.evidence.is_empty() {
+                    info!(
+                        evidence = snapshot.evidence.len(),
+                        "Restored uncommitted evidence"
+                    );
+                }
+
+                EvidencePool::restore(snapshot)
+            }
+            Ok(Err(e)) => {
+                error!("Failed to load evidence, starting with an empty pool: {e}");
+                EvidencePool::new()
+            }
+            Err(e) => {
+                error!("Failed to send LoadEvidence command to WAL actor: {e}");
+                EvidencePool::new()
+            }
+        }
+    }
+
     async fn wal_flush(&self, phase: Phase) -> Result<(), ActorProcessingErr> {
         if phase == Phase::Recovering {
             return Ok(());
@@ -1033,7 +2063,7 @@ where
             }
 
             Effect::ScheduleTimeout(timeout, r) => {
-                let duration = state.timeouts.duration_for(timeout);
+                let duration = state.timeout_duration(timeout);
                 state.timers.start_timer(timeout, duration);
 
                 Ok(r.resume_with(()))
@@ -1055,6 +2085,16 @@ where
                     let _ = myself.cast(Msg::ReceivedProposedValue(value, ValueOrigin::Consensus));
                 }
 
+                self.sync.send(SyncMsg::StartedRound(height, round));
+
+                state.round_span = info_span!(
+                    parent: &state.height_span,
+                    "round",
+                    %round,
+                    %proposer,
+                    ?role,
+                );
+
                 self.tx_event
                     .send(|| Event::StartedRound(height, round, proposer, role));
 
@@ -1062,9 +2102,13 @@ where
             }
 
             Effect::SignProposal(proposal, r) => {
+                if !state.signing_enabled {
+                    return Err(eyre!("Refusing to sign a proposal, signing is disabled").into());
+                }
+
                 let start = Instant::now();
 
-                let signed_proposal = self.signing_provider.sign_proposal(proposal).await?;
+                let signed_proposal = state.signing_key.sign_proposal(proposal).await?;
 
                 self.metrics
                     .signature_signing_time
@@ -1074,9 +2118,13 @@ where
             }
 
             Effect::SignVote(vote, r) => {
+                if !state.signing_enabled {
+                    return Err(eyre!("Refusing to sign a vote, signing is disabled").into());
+                }
+
                 let start = Instant::now();
 
-                let signed_vote = self.signing_provider.sign_vote(vote).await?;
+                let signed_vote = state.signing_key.sign_vote(vote).await?;
 
                 self.metrics
                     .signature_signing_time
@@ -1088,6 +2136,10 @@ where
             Effect::VerifySignature(msg, pk, r) => {
                 use malachitebft_core_consensus::ConsensusMsg as Msg;
 
+                if let Some(valid) = state.pre_verified.signature(&msg, &pk) {
+                    return Ok(r.resume_with(valid));
+                }
+
                 let start = Instant::now();
 
                 let result = match msg.message {
@@ -1120,6 +2172,10 @@ where
             }
 
             Effect::VerifyPolkaCertificate(certificate, validator_set, thresholds, r) => {
+                if let Some(result) = state.pre_verified.polka_certificate(&certificate) {
+                    return Ok(r.resume_with(result));
+                }
+
                 let result = self
                     .signing_provider
                     .verify_polka_certificate(&self.ctx, &certificate, &validator_set, thresholds)
@@ -1129,6 +2185,10 @@ where
             }
 
             Effect::VerifyRoundCertificate(certificate, validator_set, thresholds, r) => {
+                if let Some(result) = state.pre_verified.round_certificate(&certificate) {
+                    return Ok(r.resume_with(result));
+                }
+
                 let result = self
                     .signing_provider
                     .verify_round_certificate(&self.ctx, &certificate, &validator_set, thresholds)
@@ -1139,8 +2199,8 @@ where
 
             Effect::ExtendVote(height, round, value_id, r) => {
                 if let Some(extension) = self.extend_vote(height, round, value_id).await? {
-                    let signed_extension = self
-                        .signing_provider
+                    let signed_extension = state
+                        .signing_key
                         .sign_vote_extension(extension)
                         .await
                         .inspect_err(|e| {
@@ -1176,6 +2236,15 @@ where
             }
 
             Effect::PublishConsensusMsg(msg, r) => {
+                // With group commit, consensus carries on while the WAL is synced to disk,
+                // only the broadcast of the message waits for it
+                if let Some(publisher) = &self.publisher {
+                    if state.phase != Phase::Recovering {
+                        publisher.publish_after_flush(&self.wal, msg).await?;
+                        return Ok(r.resume_with(()));
+                    }
+                }
+
                 // Sync the WAL to disk before we broadcast the message
                 // NOTE: The message has already been append to the WAL by the `WalAppend` effect.
                 self.wal_flush(state.phase).await?;
@@ -1240,7 +2309,7 @@ where
             }
 
             Effect::GetValue(height, round, timeout, r) => {
-                let timeout_duration = state.timeouts.duration_for(timeout);
+                let timeout_duration = state.timeout_duration(timeout);
 
                 self.get_value(myself, height, round, timeout_duration)
                     .map_err(|e| {
@@ -1264,7 +2333,7 @@ where
                 Ok(r.resume_with(()))
             }
 
-            Effect::Decide(certificate, extensions, r) => {
+            Effect::Decide(certificate, extensions, proof, r) => {
                 assert!(!certificate.commit_signatures.is_empty());
 
                 // Sync the WAL to disk before we decide the value
@@ -1277,12 +2346,19 @@ where
 
                 let height = certificate.height;
 
+                state
+                    .height_span
+                    .record("decided_round", field::display(certificate.round));
+
+                let proof = self.consensus_config.decision_proof.then_some(proof);
+
                 // Notify the host about the decided value
                 // Finalization will follow, so don't request a reply
                 self.host
                     .cast(HostMsg::Decided {
                         certificate,
                         extensions,
+                        proof,
                     })
                     .map_err(|e| eyre!("Error when casting decided value to host: {e:?}"))?;
 
@@ -1317,6 +2393,22 @@ where
                         .inc_by(vote_evidence_count as u64);
                 }
 
+                // Gossip the evidence detected during this height, so that the nodes which
+                // did not witness the equivocation can include it in a value they propose
+                for detected in evidence.to_evidence() {
+                    if state.evidence.add(detected.clone()) {
+                        if let Err(e) = self.network.cast(NetworkMsg::PublishEvidence(detected)) {
+                            error!("Error when publishing evidence: {e:?}");
+                        }
+                    }
+                }
+
+                // Only the evidence detected locally is delivered along with the finalized
+                // height, the evidence received from the peers is exposed separately as
+                // uncommitted evidence, see `Msg::GetUncommittedEvidence`
+                state.evidence.prune(certificate.height);
+                self.save_evidence(state.evidence).await;
+
                 // Notify any subscribers about the finalized value
                 self.tx_event.send(|| Event::Finalized {
                     commit_certificate: certificate.clone(),
@@ -1428,16 +2520,78 @@ where
     ) -> Result<State<Ctx>, ActorProcessingErr> {
         info!("Consensus is starting");
 
+        let height = Arc::clone(&self.mailbox_height);
+        let subscriber = MailboxSubscriber::new(
+            myself.clone(),
+            self.mailbox.clone(),
+            move |event: &NetworkEvent<Ctx>| {
+                let height = height.load(Ordering::Relaxed);
+                event.priority((height > 0).then_some(height))
+            },
+        );
+
         self.network
-            .cast(NetworkMsg::Subscribe(Box::new(myself.clone())))?;
+            .cast(NetworkMsg::Subscribe(Box::new(subscriber)))?;
+
+        let ordering_interval = self.consensus_config.deterministic_ordering;
+
+        let ordered_msgs = ordering_interval.map(|interval| {
+            info!(
+                ?interval,
+                "Processing consensus messages in deterministic order"
+            );
+            OrderedMessages::new(MAX_BUFFER_SIZE)
+        });
+
+        let ordering_ticker = ordering_interval.map(|interval| {
+            tokio::spawn(
+                ticker(interval, myself.clone(), 0.0, || Msg::OrderingTick).in_current_span(),
+            )
+        });
+
+        let verifier = (self.consensus_config.verification_workers > 0).then(|| {
+            let workers = self.consensus_config.verification_workers;
+            let batch_size = self.consensus_config.verification_batch_size.max(1);
+
+            info!(%workers, %batch_size, "Verifying signatures on a pool of workers");
+
+            SignatureVerifier::spawn(
+                self.ctx.clone(),
+                Arc::clone(&self.signing_provider),
+                workers,
+                batch_size,
+                self.metrics.clone(),
+                myself.clone(),
+            )
+        });
 
         Ok(State {
-            timers: Timers::new(Box::new(myself)),
+            timers: Timers::with_clock(Box::new(myself), Arc::clone(&self.clock)),
             timeouts: Ctx::Timeouts::default(),
+            timeouts_override: None,
+            max_timeout: None,
+            max_votes_override: None,
             consensus: None,
             connected_peers: BTreeSet::new(),
+            validator_peers: BTreeMap::new(),
+            rotated_validator_peers: BTreeMap::new(),
             phase: Phase::Unstarted,
             msg_buffer: MessageBuffer::new(MAX_BUFFER_SIZE),
+            signing_enabled: !self.params.follower,
+            signing_key: Arc::clone(&self.signing_provider),
+            next_signing_keys: BTreeMap::new(),
+            paused: false,
+            paused_control: MessageBuffer::new(usize::MAX),
+            paused_buffer: MessageBuffer::new(MAX_BUFFER_SIZE),
+            ordered_msgs,
+            ordering_ticker,
+            evidence: self.load_evidence().await,
+            validator_sets: ValidatorSetHistory::default(),
+            verifier,
+            pre_verified: PreVerified::None,
+            vote_filter: VoteFilter::default(),
+            height_span: Span::none(),
+            round_span: Span::none(),
         })
     }
 
@@ -1473,16 +2627,21 @@ where
         msg: Msg<Ctx>,
         state: &mut State<Ctx>,
     ) -> Result<(), ActorProcessingErr> {
-        if state.phase != Phase::Running && should_buffer(&msg) {
-            let _span = error_span!("buffer", phase = ?state.phase).entered();
-            state.msg_buffer.buffer(msg);
-            return Ok(());
-        }
+        if let Msg::NetworkEvent(event) = &msg {
+            self.mailbox.release();
 
-        if let Err(e) = self.handle_msg(myself.clone(), state, msg).await {
-            error!("Error when handling message: {e:?}");
+            if let NetworkEvent::Vote(from, vote) = event {
+                if !self.admit_vote(state, *from, vote) {
+                    return Ok(());
+                }
+            }
         }
 
+        self.dispatch(myself, state, msg).await;
+
+        self.mailbox_height
+            .store(state.height().as_u64(), Ordering::Relaxed);
+
         Ok(())
     }
 
@@ -1502,6 +2661,10 @@ where
     ) -> Result<(), ActorProcessingErr> {
         info!("Consensus has stopped");
         state.timers.cancel_all();
+
+        if let Some(ordering_ticker) = state.ordering_ticker.take() {
+            ordering_ticker.abort();
+        }
         Ok(())
     }
 }
@@ -1510,12 +2673,60 @@ fn should_buffer<Ctx: Context>(msg: &Msg<Ctx>) -> bool {
     !matches!(
         msg,
         Msg::StartHeight(..)
+            | Msg::SetSigningEnabled(..)
+            | Msg::UpdateConfig(..)
+            | Msg::RegisterSigningKey(..)
+            | Msg::Pause(..)
+            | Msg::Resume(..)
+            | Msg::SnapshotQueues(..)
+            | Msg::GetUncommittedEvidence(..)
+            | Msg::GetVoteSet(..)
+            | Msg::OrderingTick
             | Msg::NetworkEvent(NetworkEvent::Listening(..))
             | Msg::NetworkEvent(NetworkEvent::PeerConnected(..))
             | Msg::NetworkEvent(NetworkEvent::PeerDisconnected(..))
+            | Msg::NetworkEvent(NetworkEvent::BootstrapProgress(..))
+            | Msg::NetworkEvent(NetworkEvent::HolePunch(..))
+            | Msg::NetworkEvent(NetworkEvent::RelayElection(..))
     )
 }
 
+/// What to do with a message while consensus is paused
+enum OnPaused {
+    /// Drop the message, peers will rebroadcast what is still relevant once resumed
+    Drop,
+    /// Hold the message until consensus is resumed
+    Hold,
+    /// Hold the message until consensus is resumed, unless too many are held already
+    HoldIfRoom,
+    /// Handle the message right away
+    Handle,
+}
+
+fn on_paused<Ctx: Context>(msg: &Msg<Ctx>) -> OnPaused {
+    match msg {
+        Msg::NetworkEvent(
+            NetworkEvent::Vote(..)
+            | NetworkEvent::Proposal(..)
+            | NetworkEvent::ProposalPart(..)
+            | NetworkEvent::PolkaCertificate(..)
+            | NetworkEvent::RoundCertificate(..),
+        )
+        | Msg::VerifiedEvents(..)
+        | Msg::ProcessVoteSet(..) => OnPaused::Drop,
+
+        Msg::StartHeight(..)
+        | Msg::RestartHeight(..)
+        | Msg::TimeoutElapsed(..)
+        | Msg::ProposeValue(..)
+        | Msg::ReceivedProposedValue(..) => OnPaused::Hold,
+
+        Msg::ProcessSyncResponse(..) => OnPaused::HoldIfRoom,
+
+        _ => OnPaused::Handle,
+    }
+}
+
 /// Use the height we are about to start instead of the consensus state height
 /// for the tracing span of the Consensus actor when starting a new height.
 fn span_height<Ctx: Context>(height: Ctx::Height, msg: &Msg<Ctx>) -> Ctx::Height {
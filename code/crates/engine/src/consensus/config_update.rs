@@ -0,0 +1,58 @@
+use derive_where::derive_where;
+
+use malachitebft_config::ValuePayload;
+use malachitebft_core_types::Context;
+
+pub use malachitebft_network::{ConfigUpdateError, ConfigUpdateOutcome};
+
+/// Parameters of consensus which can be changed on a running node,
+/// the parameters left unset being unchanged.
+///
+/// The timeouts and the vote limit are applied right away, and kept until the node restarts.
+/// The other parameters only take effect once the node is restarted with them in its
+/// configuration. See [`malachitebft_network::ConfigUpdate`] for the network parameters.
+#[derive_where(Clone, Debug, Default)]
+pub struct ConfigUpdate<Ctx: Context> {
+    /// Timeouts of the consensus steps, used instead of those provided by the application
+    /// for every height from the current one on. The timeouts already scheduled are unchanged.
+    pub timeouts: Option<Ctx::Timeouts>,
+    /// Maximum number of distinct votes accepted from a single validator in a round,
+    /// 0 to disable the limit, see `consensus.max_votes_per_validator_per_round`
+    pub max_votes_per_validator_per_round: Option<usize>,
+    /// Message types that can carry values, see `consensus.value_payload`
+    pub value_payload: Option<ValuePayload>,
+    /// Size of the gossip input queue, at least 1, see `consensus.queue_capacity`
+    pub queue_capacity: Option<usize>,
+    /// Number of signature verification workers, see `consensus.verification_workers`
+    pub verification_workers: Option<usize>,
+}
+
+impl<Ctx: Context> ConfigUpdate<Ctx> {
+    /// Check the update as a whole, before any of its parameters is applied
+    pub fn validate(&self) -> Result<(), ConfigUpdateError> {
+        if self.queue_capacity == Some(0) {
+            return Err(ConfigUpdateError::invalid(
+                "queue_capacity",
+                "must be at least 1",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Which of the parameters set in this update are applied, and which require a restart
+    pub fn outcome(&self) -> ConfigUpdateOutcome {
+        let mut outcome = ConfigUpdateOutcome::default();
+
+        outcome.add_applied("timeouts", self.timeouts.is_some());
+        outcome.add_applied(
+            "max_votes_per_validator_per_round",
+            self.max_votes_per_validator_per_round.is_some(),
+        );
+        outcome.add_requires_restart("value_payload", self.value_payload.is_some());
+        outcome.add_requires_restart("queue_capacity", self.queue_capacity.is_some());
+        outcome.add_requires_restart("verification_workers", self.verification_workers.is_some());
+
+        outcome
+    }
+}
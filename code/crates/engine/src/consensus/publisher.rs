@@ -0,0 +1,78 @@
+//! Publication of the consensus messages once the WAL has been synced to disk,
+//! for when the WAL batches its flushes (group commit).
+//!
+//! Consensus must not broadcast a message before it has been persisted, otherwise it may
+//! equivocate after a crash. Instead of blocking consensus until the WAL is synced, the
+//! messages are handed over to a background task which publishes them, in order,
+//! as soon as the flush they wait for completes. A message whose flush failed is dropped.
+
+use eyre::eyre;
+use tokio::sync::{mpsc, oneshot};
+use tracing::error;
+
+use malachitebft_core_consensus::SignedConsensusMsg;
+use malachitebft_core_types::Context;
+
+use crate::network::{NetworkMsg, NetworkRef};
+use crate::util::events::{Event, TxEvent};
+use crate::wal::{Msg as WalMsg, WalRef};
+
+/// Maximum number of messages waiting for their flush to complete, past which
+/// consensus waits for the pending ones to be published before carrying on
+const MAX_PENDING_MESSAGES: usize = 1024;
+
+pub(crate) type Flushed = oneshot::Receiver<eyre::Result<()>>;
+
+pub struct DeferredPublisher<Ctx: Context> {
+    tx: mpsc::Sender<(Flushed, SignedConsensusMsg<Ctx>)>,
+}
+
+impl<Ctx: Context> DeferredPublisher<Ctx> {
+    /// Spawn the task publishing the messages, which stops once the publisher is dropped
+    pub fn spawn(network: NetworkRef<Ctx>, tx_event: TxEvent<Ctx>) -> Self {
+        let (tx, rx) = mpsc::channel(MAX_PENDING_MESSAGES);
+
+        tokio::spawn(publish_flushed(rx, move |msg| {
+            // Notify any subscribers that we are about to publish a message
+            tx_event.send(|| Event::Published(msg.clone()));
+
+            if let Err(e) = network.cast(NetworkMsg::PublishConsensusMsg(msg)) {
+                error!("Error when broadcasting consensus message: {e:?}");
+            }
+        }));
+
+        Self { tx }
+    }
+
+    /// Ask the WAL to sync to disk, and publish the message once it is done
+    pub async fn publish_after_flush(
+        &self,
+        wal: &WalRef<Ctx>,
+        msg: SignedConsensusMsg<Ctx>,
+    ) -> eyre::Result<()> {
+        let (reply, flushed) = oneshot::channel();
+
+        wal.cast(WalMsg::Flush(reply.into()))
+            .map_err(|e| eyre!("Failed to send Flush command to WAL: {e}"))?;
+
+        self.tx
+            .send((flushed, msg))
+            .await
+            .map_err(|_| eyre!("Publisher task has stopped"))
+    }
+}
+
+/// Publish the messages, in order, once the flush each of them waits for has completed,
+/// dropping those which could not be persisted
+pub(crate) async fn publish_flushed<M>(
+    mut rx: mpsc::Receiver<(Flushed, M)>,
+    mut publish: impl FnMut(M),
+) {
+    while let Some((flushed, msg)) = rx.recv().await {
+        match flushed.await {
+            Ok(Ok(())) => publish(msg),
+            Ok(Err(e)) => error!("Failed to flush WAL to disk, not publishing message: {e}"),
+            Err(_) => error!("WAL dropped the flush request, not publishing message"),
+        }
+    }
+}
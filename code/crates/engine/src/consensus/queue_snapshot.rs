@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use derive_where::derive_where;
+use malachitebft_core_consensus::Input as ConsensusInput;
+use malachitebft_core_types::{Context, Proposal, Round, Vote};
+
+use crate::network::NetworkEvent;
+
+use super::Msg;
+
+/// Kind of a consensus input or message waiting in one of the queues of the consensus actor
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QueuedKind {
+    Vote,
+    Proposal,
+    ProposalPart,
+    PolkaCertificate,
+    RoundCertificate,
+    LocallyProposedValue,
+    ProposedValue,
+    SyncValue,
+    Timeout,
+    StartHeight,
+    Other,
+}
+
+impl QueuedKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Vote => "vote",
+            Self::Proposal => "proposal",
+            Self::ProposalPart => "proposal_part",
+            Self::PolkaCertificate => "polka_certificate",
+            Self::RoundCertificate => "round_certificate",
+            Self::LocallyProposedValue => "locally_proposed_value",
+            Self::ProposedValue => "proposed_value",
+            Self::SyncValue => "sync_value",
+            Self::Timeout => "timeout",
+            Self::StartHeight => "start_height",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Inputs or messages of the same kind queued for the same height and round
+#[derive_where(Debug, Clone)]
+pub struct QueuedGroup<Ctx: Context> {
+    /// Height of the queued items, if known, eg. proposal parts do not carry their height
+    pub height: Option<Ctx::Height>,
+
+    /// Round of the queued items, if known
+    pub round: Option<Round>,
+
+    /// Kind of the queued items
+    pub kind: QueuedKind,
+
+    /// Number of queued items
+    pub count: usize,
+
+    /// When the oldest of the queued items was queued
+    pub oldest: SystemTime,
+
+    /// How long the oldest of the queued items has been waiting
+    pub oldest_age: Duration,
+}
+
+/// Summary of the queues of the consensus actor, to find out what consensus is waiting on.
+///
+/// Unlike [`super::state_dump::StateDump`], the queued items are not cloned,
+/// only counted, so that the snapshot can be taken cheaply while consensus is running.
+#[derive_where(Debug, Clone)]
+pub struct QueueSnapshot<Ctx: Context> {
+    /// The height consensus is at, if started
+    pub height: Option<Ctx::Height>,
+
+    /// The round consensus is at, if started
+    pub round: Round,
+
+    /// The phase of the consensus actor, ie. `unstarted`, `ready`, `running` or `recovering`,
+    /// or `paused` if consensus is paused
+    pub phase: &'static str,
+
+    /// Inputs for higher heights, buffered by consensus until it reaches their height.
+    ///
+    /// Queued items are timestamped per height, so the oldest time of a group is
+    /// the time at which the first input for that height was queued.
+    pub input_queue: Vec<QueuedGroup<Ctx>>,
+
+    /// Messages received while consensus was not started yet, replaying the WAL or paused
+    pub msg_buffer: Vec<QueuedGroup<Ctx>>,
+
+    /// Messages waiting for the next ordering tick, if deterministic ordering is enabled.
+    ///
+    /// The oldest time of each group is the time at which the first message since
+    /// the last tick was received.
+    pub ordered_msgs: Vec<QueuedGroup<Ctx>>,
+}
+
+impl<Ctx: Context> QueueSnapshot<Ctx> {
+    pub(super) fn new(state: &super::State<Ctx>) -> Self {
+        let consensus = state.consensus.as_ref();
+
+        let input_queue = consensus
+            .map(|consensus| {
+                group(
+                    consensus
+                        .input_queue
+                        .iter()
+                        .flat_map(|(height, inputs, since)| {
+                            inputs.iter().map(move |input| {
+                                let (round, kind) = classify_input(input);
+                                ((Some(*height), round, kind), since)
+                            })
+                        }),
+                )
+            })
+            .unwrap_or_default();
+
+        let msg_buffer = group(
+            state
+                .msg_buffer
+                .iter()
+                .chain(state.paused_control.iter())
+                .chain(state.paused_buffer.iter())
+                .map(|(since, msg)| (classify_msg(msg), since)),
+        );
+
+        let ordered_msgs = match state.ordered_msgs.as_ref().map(|o| o.pending()) {
+            Some((events, Some(since))) => {
+                group(events.map(|event| (classify_event(event), since)))
+            }
+            _ => Vec::new(),
+        };
+
+        Self {
+            height: consensus.map(|consensus| consensus.height()),
+            round: state.round(),
+            phase: if state.paused {
+                "paused"
+            } else {
+                state.phase.as_str()
+            },
+            input_queue,
+            msg_buffer,
+            ordered_msgs,
+        }
+    }
+
+    /// Total number of items waiting in the queues
+    pub fn total(&self) -> usize {
+        [&self.input_queue, &self.msg_buffer, &self.ordered_msgs]
+            .into_iter()
+            .flatten()
+            .map(|group| group.count)
+            .sum()
+    }
+}
+
+type GroupKey<Ctx> = (Option<<Ctx as Context>::Height>, Option<Round>, QueuedKind);
+
+/// Count the queued items per height, round and kind, keeping the oldest time of each group
+fn group<Ctx: Context>(
+    items: impl Iterator<Item = (GroupKey<Ctx>, Instant)>,
+) -> Vec<QueuedGroup<Ctx>> {
+    let mut groups: BTreeMap<GroupKey<Ctx>, (usize, Instant)> = BTreeMap::new();
+
+    for (key, since) in items {
+        let (count, oldest) = groups.entry(key).or_insert((0, since));
+        *count += 1;
+        *oldest = (*oldest).min(since);
+    }
+
+    let now = SystemTime::now();
+
+    groups
+        .into_iter()
+        .map(|((height, round, kind), (count, oldest))| {
+            let oldest_age = oldest.elapsed();
+
+            QueuedGroup {
+                height,
+                round,
+                kind,
+                count,
+                oldest: now.checked_sub(oldest_age).unwrap_or(now),
+                oldest_age,
+            }
+        })
+        .collect()
+}
+
+fn classify_input<Ctx: Context>(input: &ConsensusInput<Ctx>) -> (Option<Round>, QueuedKind) {
+    match input {
+        ConsensusInput::StartHeight(..) => (None, QueuedKind::StartHeight),
+        ConsensusInput::Vote(vote) => (Some(vote.round()), QueuedKind::Vote),
+        ConsensusInput::Proposal(proposal) => (Some(proposal.round()), QueuedKind::Proposal),
+        ConsensusInput::PolkaCertificate(certificate) => {
+            (Some(certificate.round), QueuedKind::PolkaCertificate)
+        }
+        ConsensusInput::RoundCertificate(certificate) => {
+            (Some(certificate.round), QueuedKind::RoundCertificate)
+        }
+        ConsensusInput::Propose(value) => (Some(value.round), QueuedKind::LocallyProposedValue),
+        ConsensusInput::TimeoutElapsed(timeout) => (Some(timeout.round), QueuedKind::Timeout),
+        ConsensusInput::ProposedValue(value, _) => (Some(value.round), QueuedKind::ProposedValue),
+        ConsensusInput::SyncValueResponse(response) => {
+            (Some(response.certificate.round), QueuedKind::SyncValue)
+        }
+    }
+}
+
+fn classify_event<Ctx: Context>(event: &NetworkEvent<Ctx>) -> GroupKey<Ctx> {
+    match event {
+        NetworkEvent::Vote(_, vote) => (Some(vote.height()), Some(vote.round()), QueuedKind::Vote),
+        NetworkEvent::Proposal(_, proposal) => (
+            Some(proposal.height()),
+            Some(proposal.round()),
+            QueuedKind::Proposal,
+        ),
+        NetworkEvent::ProposalPart(..) => (None, None, QueuedKind::ProposalPart),
+        NetworkEvent::PolkaCertificate(_, certificate) => (
+            Some(certificate.height),
+            Some(certificate.round),
+            QueuedKind::PolkaCertificate,
+        ),
+        NetworkEvent::RoundCertificate(_, certificate) => (
+            Some(certificate.height),
+            Some(certificate.round),
+            QueuedKind::RoundCertificate,
+        ),
+        _ => (None, None, QueuedKind::Other),
+    }
+}
+
+fn classify_msg<Ctx: Context>(msg: &Msg<Ctx>) -> GroupKey<Ctx> {
+    match msg {
+        Msg::NetworkEvent(event) => classify_event(event),
+        Msg::StartHeight(height, _) | Msg::RestartHeight(height, _) => {
+            (Some(*height), None, QueuedKind::StartHeight)
+        }
+        Msg::ProposeValue(value) => (
+            Some(value.height),
+            Some(value.round),
+            QueuedKind::LocallyProposedValue,
+        ),
+        Msg::ReceivedProposedValue(value, _) => (
+            Some(value.height),
+            Some(value.round),
+            QueuedKind::ProposedValue,
+        ),
+        Msg::ProcessSyncResponse(response) => (
+            Some(response.certificate.height),
+            Some(response.certificate.round),
+            QueuedKind::SyncValue,
+        ),
+        Msg::TimeoutElapsed(_) => (None, None, QueuedKind::Timeout),
+        _ => (None, None, QueuedKind::Other),
+    }
+}
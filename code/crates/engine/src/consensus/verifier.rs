@@ -0,0 +1,458 @@
+//! Verification of the signatures of the consensus messages received from the network
+//! on a pool of workers, off the consensus actor.
+//!
+//! With large validator sets, verifying the signature of every vote on the consensus actor
+//! becomes the bottleneck. Instead, the votes, proposals and certificates are handed over to
+//! a bounded pool of workers which verify them in batches. The messages are then sent back to
+//! consensus in the order in which they were received, along with the outcome of their
+//! verification, which consensus uses instead of verifying them again.
+//!
+//! The proposal parts go through the same queue, without being verified, so that they are
+//! not processed ahead of the proposals and votes received before them. Once too many
+//! messages are waiting for a worker, the new votes are dropped, peers rebroadcasting what
+//! is still relevant, while the other messages wait for room in the queue, holding back
+//! consensus until the workers catch up, as a lost proposal or part is never sent again.
+
+use std::sync::Arc;
+
+use derive_where::derive_where;
+use ractor::ActorRef;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{error, warn, Instrument};
+
+use malachitebft_core_consensus::{ConsensusMsg, State as ConsensusState};
+use malachitebft_core_types::{
+    CertificateError, Context, PolkaCertificate, Proposal, PublicKey, RoundCertificate,
+    SignedMessage, ThresholdParams, Validator, ValidatorSet, Vote,
+};
+use malachitebft_metrics::Metrics;
+use malachitebft_signing::{SigningProvider, SigningProviderExt};
+
+use super::Msg;
+use crate::network::NetworkEvent;
+
+/// Maximum number of messages waiting for a worker, past which new votes are dropped
+/// and the other messages wait for room in the queue
+const MAX_QUEUED_JOBS: usize = 8192;
+
+/// What to verify about a message
+enum Check<Ctx: Context> {
+    /// The signature of a vote or proposal, against the public key of its sender
+    Signature(PublicKey<Ctx>),
+
+    /// A polka certificate, against the validator set of its height
+    PolkaCertificate(Ctx::ValidatorSet, ThresholdParams),
+
+    /// A round certificate, against the validator set of its height
+    RoundCertificate(Ctx::ValidatorSet, ThresholdParams),
+}
+
+struct Job<Ctx: Context> {
+    event: NetworkEvent<Ctx>,
+    check: Option<Check<Ctx>>,
+    generation: u64,
+}
+
+/// The outcome of the verification of a message by a worker
+#[derive_where(Debug)]
+enum Outcome<Ctx: Context> {
+    Signature(PublicKey<Ctx>, bool),
+    Certificate(Result<(), CertificateError<Ctx>>),
+}
+
+/// A message received from the network, along with the outcome of its verification
+#[derive_where(Debug)]
+pub struct VerifiedEvent<Ctx: Context> {
+    event: NetworkEvent<Ctx>,
+    outcome: Option<Outcome<Ctx>>,
+    generation: u64,
+}
+
+impl<Ctx: Context> VerifiedEvent<Ctx> {
+    /// The message, along with the outcome of its verification.
+    ///
+    /// The outcome is discarded if consensus started a height since the message was submitted,
+    /// as the message may then have been verified against another validator set.
+    pub(crate) fn into_parts(self, generation: u64) -> (NetworkEvent<Ctx>, PreVerified<Ctx>) {
+        let Self {
+            event,
+            outcome,
+            generation: submitted,
+        } = self;
+
+        let outcome = outcome.filter(|_| submitted == generation);
+
+        let pre_verified = match (&event, outcome) {
+            (NetworkEvent::Vote(_, vote), Some(Outcome::Signature(public_key, valid))) => {
+                let msg = vote.clone().map(ConsensusMsg::Vote);
+                PreVerified::Signature(msg, public_key, valid)
+            }
+            (NetworkEvent::Proposal(_, proposal), Some(Outcome::Signature(public_key, valid))) => {
+                let msg = proposal.clone().map(ConsensusMsg::Proposal);
+                PreVerified::Signature(msg, public_key, valid)
+            }
+            (
+                NetworkEvent::PolkaCertificate(_, certificate),
+                Some(Outcome::Certificate(result)),
+            ) => PreVerified::PolkaCertificate(certificate.clone(), result),
+            (
+                NetworkEvent::RoundCertificate(_, certificate),
+                Some(Outcome::Certificate(result)),
+            ) => PreVerified::RoundCertificate(certificate.clone(), result),
+            _ => PreVerified::None,
+        };
+
+        (event, pre_verified)
+    }
+}
+
+/// The outcome of the verification of the message being processed by consensus, if any
+pub(crate) enum PreVerified<Ctx: Context> {
+    None,
+    Signature(SignedMessage<Ctx, ConsensusMsg<Ctx>>, PublicKey<Ctx>, bool),
+    PolkaCertificate(PolkaCertificate<Ctx>, Result<(), CertificateError<Ctx>>),
+    RoundCertificate(RoundCertificate<Ctx>, Result<(), CertificateError<Ctx>>),
+}
+
+impl<Ctx: Context> Default for PreVerified<Ctx> {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl<Ctx: Context> PreVerified<Ctx> {
+    /// Take the validity of the signature of the given message,
+    /// if it was verified against the given public key
+    pub fn signature(
+        &mut self,
+        msg: &SignedMessage<Ctx, ConsensusMsg<Ctx>>,
+        public_key: &PublicKey<Ctx>,
+    ) -> Option<bool> {
+        match std::mem::take(self) {
+            Self::Signature(m, pk, valid) if &m == msg && &pk == public_key => Some(valid),
+            other => {
+                *self = other;
+                None
+            }
+        }
+    }
+
+    /// Take the outcome of the verification of the given polka certificate, if it was verified
+    pub fn polka_certificate(
+        &mut self,
+        certificate: &PolkaCertificate<Ctx>,
+    ) -> Option<Result<(), CertificateError<Ctx>>> {
+        match std::mem::take(self) {
+            Self::PolkaCertificate(c, result) if &c == certificate => Some(result),
+            other => {
+                *self = other;
+                None
+            }
+        }
+    }
+
+    /// Take the outcome of the verification of the given round certificate, if it was verified
+    pub fn round_certificate(
+        &mut self,
+        certificate: &RoundCertificate<Ctx>,
+    ) -> Option<Result<(), CertificateError<Ctx>>> {
+        match std::mem::take(self) {
+            Self::RoundCertificate(c, result) if &c == certificate => Some(result),
+            other => {
+                *self = other;
+                None
+            }
+        }
+    }
+}
+
+pub(crate) struct SignatureVerifier<Ctx: Context> {
+    tx: mpsc::Sender<Job<Ctx>>,
+    generation: u64,
+    metrics: Metrics,
+}
+
+impl<Ctx: Context> SignatureVerifier<Ctx> {
+    /// Spawn the pool of workers, which stops once the verifier is dropped
+    pub fn spawn(
+        ctx: Ctx,
+        signing_provider: Arc<dyn SigningProvider<Ctx>>,
+        workers: usize,
+        batch_size: usize,
+        metrics: Metrics,
+        consensus: ActorRef<Msg<Ctx>>,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::channel::<Job<Ctx>>(MAX_QUEUED_JOBS);
+        let (tx_worker, mut rx_worker) = mpsc::channel::<JoinHandle<_>>(workers);
+
+        let permits = Arc::new(Semaphore::new(workers));
+        let worker_metrics = metrics.clone();
+
+        tokio::spawn(
+            async move {
+                while let Some(job) = rx.recv().await {
+                    let Ok(permit) = Arc::clone(&permits).acquire_owned().await else {
+                        break;
+                    };
+
+                    // Pick up the messages received while waiting for a worker to be available
+                    let mut batch = vec![job];
+                    while batch.len() < batch_size {
+                        match rx.try_recv() {
+                            Ok(job) => batch.push(job),
+                            Err(_) => break,
+                        }
+                    }
+
+                    let ctx = ctx.clone();
+                    let signing_provider = Arc::clone(&signing_provider);
+                    let metrics = worker_metrics.clone();
+
+                    let worker = tokio::spawn(async move {
+                        let verified = verify_batch(&ctx, &signing_provider, batch, &metrics).await;
+                        drop(permit);
+                        verified
+                    });
+
+                    if tx_worker.send(worker).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+
+        // Send the messages back to consensus in the order in which they were submitted
+        tokio::spawn(
+            async move {
+                while let Some(worker) = rx_worker.recv().await {
+                    match worker.await {
+                        Ok(verified) => {
+                            if consensus.cast(Msg::VerifiedEvents(verified)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => error!("Signature verification worker failed: {e}"),
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+
+        Self {
+            tx,
+            generation: 0,
+            metrics,
+        }
+    }
+
+    /// Whether the given message is handed over to the workers,
+    /// to be verified or only kept in order with the messages that are
+    pub fn queues(event: &NetworkEvent<Ctx>) -> bool {
+        matches!(
+            event,
+            NetworkEvent::Vote(..)
+                | NetworkEvent::Proposal(..)
+                | NetworkEvent::ProposalPart(..)
+                | NetworkEvent::PolkaCertificate(..)
+                | NetworkEvent::RoundCertificate(..)
+        )
+    }
+
+    /// Hand over a message to the workers, to be verified against the current validator set.
+    ///
+    /// Messages for another height are sent back unverified, for consensus to verify them
+    /// itself if it ever needs to. A vote is dropped if too many messages are waiting already,
+    /// peers rebroadcasting what is still relevant, any other message waiting for room.
+    pub async fn submit(&self, consensus: &ConsensusState<Ctx>, event: NetworkEvent<Ctx>) {
+        let kind = kind(&event);
+        let is_vote = matches!(event, NetworkEvent::Vote(..));
+
+        let job = Job {
+            check: check(consensus, &event),
+            event,
+            generation: self.generation,
+        };
+
+        let sent = if is_vote {
+            match self.tx.try_send(job) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!("Too many messages waiting for signature verification, dropping vote");
+                    false
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {
+                    error!("Signature verification workers have stopped");
+                    false
+                }
+            }
+        } else {
+            match self.tx.send(job).await {
+                Ok(()) => true,
+                Err(_) => {
+                    error!("Signature verification workers have stopped");
+                    false
+                }
+            }
+        };
+
+        if !sent {
+            self.metrics.verification_dropped_message(kind);
+        }
+    }
+
+    /// The generation of the messages submitted from now on
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Discard the outcome of the verification of the messages submitted so far,
+    /// to be called when consensus starts a height
+    pub fn next_generation(&mut self) {
+        self.generation += 1;
+    }
+}
+
+/// Kind of a message handed over to the workers, as counted in the metrics
+fn kind<Ctx: Context>(event: &NetworkEvent<Ctx>) -> &'static str {
+    match event {
+        NetworkEvent::Vote(..) => "vote",
+        NetworkEvent::Proposal(..) => "proposal",
+        NetworkEvent::ProposalPart(..) => "proposal_part",
+        NetworkEvent::PolkaCertificate(..) => "polka_certificate",
+        NetworkEvent::RoundCertificate(..) => "round_certificate",
+        _ => "other",
+    }
+}
+
+fn check<Ctx: Context>(
+    consensus: &ConsensusState<Ctx>,
+    event: &NetworkEvent<Ctx>,
+) -> Option<Check<Ctx>> {
+    let height = consensus.height();
+    let validator_set = consensus.validator_set();
+    let thresholds = consensus.params.threshold_params;
+
+    let signature = |address: &Ctx::Address| {
+        validator_set
+            .get_by_address(address)
+            .map(|validator| Check::Signature(validator.public_key().clone()))
+    };
+
+    match event {
+        NetworkEvent::Vote(_, vote) if vote.height() == height => {
+            signature(vote.validator_address())
+        }
+        NetworkEvent::Proposal(_, proposal) if proposal.height() == height => {
+            signature(proposal.validator_address())
+        }
+        NetworkEvent::PolkaCertificate(_, certificate) if certificate.height == height => {
+            Some(Check::PolkaCertificate(validator_set.clone(), thresholds))
+        }
+        NetworkEvent::RoundCertificate(_, certificate) if certificate.height == height => {
+            Some(Check::RoundCertificate(validator_set.clone(), thresholds))
+        }
+        _ => None,
+    }
+}
+
+async fn verify_batch<Ctx: Context>(
+    ctx: &Ctx,
+    signing_provider: &Arc<dyn SigningProvider<Ctx>>,
+    jobs: Vec<Job<Ctx>>,
+    metrics: &Metrics,
+) -> Vec<VerifiedEvent<Ctx>> {
+    // Verify all the votes at once, to benefit from batch verification if supported
+    let votes = jobs
+        .iter()
+        .filter_map(|job| match (&job.event, &job.check) {
+            (NetworkEvent::Vote(_, vote), Some(Check::Signature(public_key))) => {
+                Some((vote.clone(), public_key.clone()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+
+    let mut vote_results = match signing_provider.verify_signed_votes(&votes).await {
+        Ok(results) => results.into_iter(),
+        Err(e) => {
+            error!("Failed to verify the signatures of a batch of votes: {e}");
+            Vec::new().into_iter()
+        }
+    };
+
+    if !votes.is_empty() {
+        let per_vote = start.elapsed().as_secs_f64() / votes.len() as f64;
+
+        for _ in &votes {
+            metrics.signature_verification_time.observe(per_vote);
+        }
+    }
+
+    let mut verified = Vec::with_capacity(jobs.len());
+
+    for Job {
+        event,
+        check,
+        generation,
+    } in jobs
+    {
+        let outcome = match (&event, check) {
+            (NetworkEvent::Vote(..), Some(Check::Signature(public_key))) => vote_results
+                .next()
+                .map(|result| Outcome::Signature(public_key, result.is_valid())),
+
+            (NetworkEvent::Proposal(_, proposal), Some(Check::Signature(public_key))) => {
+                let start = Instant::now();
+
+                let result = signing_provider
+                    .verify_signed_proposal(&proposal.message, &proposal.signature, &public_key)
+                    .await;
+
+                metrics
+                    .signature_verification_time
+                    .observe(start.elapsed().as_secs_f64());
+
+                match result {
+                    Ok(result) => Some(Outcome::Signature(public_key, result.is_valid())),
+                    Err(e) => {
+                        error!("Failed to verify the signature of a proposal: {e}");
+                        None
+                    }
+                }
+            }
+
+            (
+                NetworkEvent::PolkaCertificate(_, certificate),
+                Some(Check::PolkaCertificate(validator_set, thresholds)),
+            ) => Some(Outcome::Certificate(
+                signing_provider
+                    .verify_polka_certificate(ctx, certificate, &validator_set, thresholds)
+                    .await,
+            )),
+
+            (
+                NetworkEvent::RoundCertificate(_, certificate),
+                Some(Check::RoundCertificate(validator_set, thresholds)),
+            ) => Some(Outcome::Certificate(
+                signing_provider
+                    .verify_round_certificate(ctx, certificate, &validator_set, thresholds)
+                    .await,
+            )),
+
+            _ => None,
+        };
+
+        verified.push(VerifiedEvent {
+            event,
+            outcome,
+            generation,
+        });
+    }
+
+    verified
+}
@@ -0,0 +1,223 @@
+//! Suppression of the duplicate and stale votes received from the network,
+//! in front of the consensus state machine.
+//!
+//! A peer replaying votes, eg. by republishing the votes it received, would otherwise
+//! have every one of them verified and processed by consensus again. Instead, the votes
+//! already seen are dropped before reaching consensus, as are the votes for a height
+//! already decided. Only the exact same vote, down to its signature, is dropped as
+//! a duplicate, so that a forged vote cannot shadow the genuine one and a conflicting
+//! vote still reaches consensus as evidence of equivocation.
+//!
+//! A vote is only remembered once consensus has processed it, see [`VoteFilter::record`],
+//! so that a vote dropped on its way to consensus, eg. while it is paused, is not
+//! dropped again as a duplicate when it is received anew.
+
+use std::collections::{BTreeMap, HashMap};
+
+use malachitebft_core_consensus::PeerId;
+use malachitebft_core_types::{Context, Round, Signature, SignedVote, Vote, VoteType};
+
+/// Maximum number of distinct votes remembered, the new votes are no longer remembered beyond that
+const MAX_SEEN_VOTES: usize = 100_000;
+
+/// Maximum number of distinct signatures remembered for the vote of a validator in a round
+const MAX_SIGNATURES_PER_VOTE: usize = 4;
+
+/// Number of stale votes of a peer dropped during a height before the peer is penalized,
+/// a few of them being expected from honest peers, eg. when they forward the votes
+/// of a validator arriving after the height is decided
+const DROPPED_VOTES_ALLOWANCE: usize = 100;
+
+type VoteKey<Ctx> = (
+    <Ctx as Context>::Height,
+    Round,
+    VoteType,
+    <Ctx as Context>::Address,
+);
+
+/// Why a vote is dropped, see [`VoteFilter::check`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DroppedVote {
+    /// The vote was already received
+    Duplicate,
+    /// The vote is for a height lower than the current one
+    Stale,
+}
+
+/// Votes seen at the current height and above, see the [module docs](self)
+pub struct VoteFilter<Ctx: Context> {
+    /// Height of consensus when the votes were last checked
+    height: Option<Ctx::Height>,
+    /// Signatures of the votes seen, by height, round, type and validator
+    seen: BTreeMap<VoteKey<Ctx>, Vec<Signature<Ctx>>>,
+    /// Number of distinct votes seen
+    seen_count: usize,
+    /// Number of votes of each peer dropped during the current height
+    dropped: HashMap<PeerId, usize>,
+}
+
+impl<Ctx: Context> Default for VoteFilter<Ctx> {
+    fn default() -> Self {
+        Self {
+            height: None,
+            seen: BTreeMap::new(),
+            seen_count: 0,
+            dropped: HashMap::new(),
+        }
+    }
+}
+
+impl<Ctx: Context> VoteFilter<Ctx> {
+    /// Check whether a vote received from the network should be dropped before reaching
+    /// consensus. Stale votes are only detected once consensus has started, ie. if `height`
+    /// is set.
+    pub fn check(
+        &mut self,
+        height: Option<Ctx::Height>,
+        vote: &SignedVote<Ctx>,
+    ) -> Result<(), DroppedVote> {
+        if height != self.height {
+            self.on_new_height(height);
+        }
+
+        if height.is_some_and(|height| vote.height() < height) {
+            return Err(DroppedVote::Stale);
+        }
+
+        if self
+            .seen
+            .get(&key_of(vote))
+            .is_some_and(|signatures| signatures.contains(&vote.signature))
+        {
+            return Err(DroppedVote::Duplicate);
+        }
+
+        Ok(())
+    }
+
+    /// Remember a vote once consensus has processed it, for its duplicates to be dropped
+    pub fn record(&mut self, height: Option<Ctx::Height>, vote: &SignedVote<Ctx>) {
+        if height != self.height {
+            self.on_new_height(height);
+        }
+
+        if height.is_some_and(|height| vote.height() < height) {
+            return;
+        }
+
+        if self.seen_count >= MAX_SEEN_VOTES {
+            return;
+        }
+
+        let signatures = self.seen.entry(key_of(vote)).or_default();
+
+        if signatures.len() < MAX_SIGNATURES_PER_VOTE && !signatures.contains(&vote.signature) {
+            signatures.push(vote.signature.clone());
+            self.seen_count += 1;
+        }
+    }
+
+    /// Count a stale vote of a peer dropped, returning whether the peer should be penalized
+    /// for having gone over its allowance for the current height
+    pub fn record_dropped(&mut self, peer_id: PeerId) -> bool {
+        let dropped = self.dropped.entry(peer_id).or_default();
+        *dropped += 1;
+
+        *dropped > DROPPED_VOTES_ALLOWANCE
+    }
+
+    /// Forget the votes for the heights below the new one, and the votes dropped so far
+    fn on_new_height(&mut self, height: Option<Ctx::Height>) {
+        self.height = height;
+        self.dropped.clear();
+
+        if let Some(height) = height {
+            self.seen
+                .retain(|(vote_height, ..), _| *vote_height >= height);
+            self.seen_count = self.seen.values().map(Vec::len).sum();
+        }
+    }
+}
+
+fn key_of<Ctx: Context>(vote: &SignedVote<Ctx>) -> VoteKey<Ctx> {
+    (
+        vote.height(),
+        vote.round(),
+        vote.vote_type(),
+        vote.validator_address().clone(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use malachitebft_core_types::NilOrVal;
+    use malachitebft_test::{Address, Height, PrivateKey, TestContext, ValueId, Vote};
+
+    use super::*;
+
+    fn vote(height: u64, value: u64) -> SignedVote<TestContext> {
+        let private_key = PrivateKey::from([1; 32]);
+        let address = Address::from_public_key(&private_key.public_key());
+
+        let vote = Vote::new_prevote(
+            Height::new(height),
+            Round::new(0),
+            NilOrVal::Val(ValueId::new(value)),
+            address,
+        );
+
+        SignedVote::new(vote.clone(), private_key.sign(&vote.to_sign_bytes()))
+    }
+
+    #[test]
+    fn duplicates_are_dropped_once_processed() {
+        let mut filter = VoteFilter::<TestContext>::default();
+        let height = Some(Height::new(1));
+        let prevote = vote(1, 1);
+
+        // Not processed by consensus yet, eg. dropped while paused
+        assert_eq!(filter.check(height, &prevote), Ok(()));
+        assert_eq!(filter.check(height, &prevote), Ok(()));
+
+        filter.record(height, &prevote);
+        assert_eq!(filter.check(height, &prevote), Err(DroppedVote::Duplicate));
+
+        // A conflicting vote of the same validator still reaches consensus
+        assert_eq!(filter.check(height, &vote(1, 2)), Ok(()));
+
+        // The votes of the decided heights are forgotten
+        let next = Some(Height::new(2));
+        assert_eq!(filter.check(next, &prevote), Err(DroppedVote::Stale));
+        assert!(filter.seen.is_empty());
+    }
+
+    #[test]
+    fn stale_votes_are_not_recorded() {
+        let mut filter = VoteFilter::<TestContext>::default();
+
+        // Before consensus starts, no vote is stale
+        assert_eq!(filter.check(None, &vote(1, 1)), Ok(()));
+
+        let height = Some(Height::new(3));
+        filter.record(height, &vote(2, 1));
+        assert_eq!(filter.seen_count, 0);
+        assert_eq!(filter.check(height, &vote(2, 1)), Err(DroppedVote::Stale));
+    }
+
+    #[test]
+    fn peers_are_penalized_past_their_allowance() {
+        let mut filter = VoteFilter::<TestContext>::default();
+        let peer = PeerId::random();
+
+        for _ in 0..DROPPED_VOTES_ALLOWANCE {
+            assert!(!filter.record_dropped(peer));
+        }
+
+        assert!(filter.record_dropped(peer));
+        assert!(!filter.record_dropped(PeerId::random()));
+
+        // The allowance is reset at every height
+        filter.check(Some(Height::new(1)), &vote(1, 1)).unwrap();
+        assert!(!filter.record_dropped(peer));
+    }
+}
@@ -0,0 +1,457 @@
+//! Pool of the evidence of equivocation, gossiped to the peers.
+//!
+//! Evidence is either detected locally when a height is finalized, or received from the peers
+//! on the evidence channel. As which evidence a node holds depends on what it witnessed and
+//! received, the pool is never merged into the evidence delivered along with a finalized height.
+//! Instead, it is exposed to the application as uncommitted evidence, which must not drive
+//! any state transition unless the application includes it in a decided value.
+//! Evidence is remembered for [`MAX_EVIDENCE_AGE`] heights so that it is not gossiped twice.
+//!
+//! The pool is persisted by the WAL actor, so that it survives restarts.
+//!
+//! Evidence received from the peers is verified against the validator set of its own height,
+//! see [`ValidatorSetHistory`], as the key of the validator may have been rotated since.
+
+use core::fmt;
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use derive_where::derive_where;
+
+use malachitebft_codec::Codec;
+use malachitebft_core_consensus::{Evidence, EvidenceKind, SignedConsensusMsg};
+use malachitebft_core_types::{Context, Round, Validator, ValidatorSet};
+use malachitebft_signing::SigningProvider;
+
+/// Number of heights after which evidence is considered too old to be acted upon,
+/// and is dropped from the pool
+pub const MAX_EVIDENCE_AGE: u64 = 100;
+
+/// The evidence held by the pool, as persisted by the WAL actor
+#[derive_where(Clone, Debug, Default)]
+pub struct EvidenceSnapshot<Ctx: Context> {
+    /// Evidence detected locally or received from the peers, not expired yet
+    pub evidence: Vec<Evidence<Ctx>>,
+}
+
+type EvidenceKey<Ctx> = (
+    <Ctx as Context>::Height,
+    Round,
+    <Ctx as Context>::Address,
+    EvidenceKind,
+);
+
+/// Two pieces of evidence for the same misbehavior, ie. the same validator, height,
+/// round and step, are considered duplicates even if they are made of other messages.
+fn key<Ctx: Context>(evidence: &Evidence<Ctx>) -> EvidenceKey<Ctx> {
+    (
+        evidence.height(),
+        evidence.round(),
+        evidence.validator_address().clone(),
+        evidence.kind(),
+    )
+}
+
+#[derive_where(Default)]
+pub struct EvidencePool<Ctx: Context> {
+    evidence: Vec<Evidence<Ctx>>,
+}
+
+impl<Ctx: Context> EvidencePool<Ctx> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn restore(snapshot: EvidenceSnapshot<Ctx>) -> Self {
+        Self {
+            evidence: snapshot.evidence,
+        }
+    }
+
+    pub fn snapshot(&self) -> EvidenceSnapshot<Ctx> {
+        EvidenceSnapshot {
+            evidence: self.evidence.clone(),
+        }
+    }
+
+    /// Number of pieces of evidence in the pool
+    pub fn len(&self) -> usize {
+        self.evidence.len()
+    }
+
+    /// Whether the pool holds no evidence
+    pub fn is_empty(&self) -> bool {
+        self.evidence.is_empty()
+    }
+
+    /// The evidence in the pool, which is not committed to by any decided value,
+    /// and differs from one node to another
+    pub fn uncommitted(&self) -> &[Evidence<Ctx>] {
+        &self.evidence
+    }
+
+    /// Whether evidence for the same misbehavior is already in the pool
+    pub fn contains(&self, evidence: &Evidence<Ctx>) -> bool {
+        let key = key(evidence);
+        self.evidence.iter().any(|e| self::key(e) == key)
+    }
+
+    /// Whether evidence for the given height is too old to be acted upon at the current height
+    pub fn is_expired(height: Ctx::Height, current_height: Ctx::Height) -> bool {
+        height.as_u64() + MAX_EVIDENCE_AGE < current_height.as_u64()
+    }
+
+    /// Add evidence to the pool, returns `false` if it is a duplicate
+    pub fn add(&mut self, evidence: Evidence<Ctx>) -> bool {
+        if self.contains(&evidence) {
+            return false;
+        }
+
+        self.evidence.push(evidence);
+        true
+    }
+
+    /// Drop the evidence which is too old to be acted upon at the given height
+    pub fn prune(&mut self, current_height: Ctx::Height) {
+        self.evidence
+            .retain(|e| !Self::is_expired(e.height(), current_height));
+    }
+}
+
+/// Validator sets of the heights evidence can be received for, ie. of the heights started
+/// by this node which are not expired yet, see [`EvidencePool::is_expired`]
+#[derive_where(Default)]
+pub struct ValidatorSetHistory<Ctx: Context> {
+    /// Validator set of each height it changed at
+    sets: BTreeMap<Ctx::Height, Ctx::ValidatorSet>,
+    /// Height started last
+    latest: Option<Ctx::Height>,
+}
+
+impl<Ctx: Context> ValidatorSetHistory<Ctx> {
+    /// Remember the validator set of the height being started,
+    /// forgetting the ones no longer needed at that height
+    pub fn record(&mut self, height: Ctx::Height, validator_set: &Ctx::ValidatorSet) {
+        // When restarting a lower height, the validator sets of the heights above are stale
+        self.sets.retain(|h, _| *h < height);
+
+        if self.sets.values().next_back() != Some(validator_set) {
+            self.sets.insert(height, validator_set.clone());
+        }
+
+        self.latest = Some(height);
+
+        // The first validator set is no longer needed once the next one is expired
+        while let Some(&next) = self.sets.keys().nth(1) {
+            if !EvidencePool::<Ctx>::is_expired(next, height) {
+                break;
+            }
+
+            self.sets.pop_first();
+        }
+    }
+
+    /// The validator set of the given height, if it is known
+    pub fn get(&self, height: Ctx::Height) -> Option<&Ctx::ValidatorSet> {
+        if self.latest.is_none_or(|latest| height > latest) {
+            return None;
+        }
+
+        self.sets
+            .range(..=height)
+            .next_back()
+            .map(|(_, validator_set)| validator_set)
+    }
+}
+
+/// Why evidence received from a peer is rejected, see [`verify`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidEvidence {
+    /// The validator set of the height of the evidence is not known
+    UnknownHeight,
+    /// The messages do not prove any equivocation
+    NotConflicting,
+    /// The validator is not in the validator set of the height of the evidence
+    UnknownValidator,
+    /// One of the messages was not signed by the validator
+    InvalidSignature,
+    /// The signatures could not be verified
+    Verification(String),
+}
+
+impl fmt::Display for InvalidEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownHeight => write!(f, "unknown validator set at the height of the evidence"),
+            Self::NotConflicting => write!(f, "non-conflicting messages"),
+            Self::UnknownValidator => write!(f, "unknown validator"),
+            Self::InvalidSignature => write!(f, "invalid signature"),
+            Self::Verification(e) => write!(f, "failed to verify signature: {e}"),
+        }
+    }
+}
+
+/// Verify that the evidence proves an equivocation of a validator of its height
+pub async fn verify<Ctx: Context>(
+    signing_provider: &dyn SigningProvider<Ctx>,
+    validator_sets: &ValidatorSetHistory<Ctx>,
+    evidence: &Evidence<Ctx>,
+) -> Result<(), InvalidEvidence> {
+    let validator_set = validator_sets
+        .get(evidence.height())
+        .ok_or(InvalidEvidence::UnknownHeight)?;
+
+    if !evidence.is_conflicting() {
+        return Err(InvalidEvidence::NotConflicting);
+    }
+
+    let validator = validator_set
+        .get_by_address(evidence.validator_address())
+        .ok_or(InvalidEvidence::UnknownValidator)?;
+
+    let public_key = validator.public_key();
+
+    let (first, second) = evidence.messages();
+    for msg in [first, second] {
+        let result = match msg {
+            SignedConsensusMsg::Vote(vote) => {
+                signing_provider
+                    .verify_signed_vote(&vote.message, &vote.signature, public_key)
+                    .await
+            }
+            SignedConsensusMsg::Proposal(proposal) => {
+                signing_provider
+                    .verify_signed_proposal(&proposal.message, &proposal.signature, public_key)
+                    .await
+            }
+        };
+
+        match result {
+            Ok(result) if result.is_valid() => {}
+            Ok(_) => return Err(InvalidEvidence::InvalidSignature),
+            Err(e) => return Err(InvalidEvidence::Verification(e.to_string())),
+        }
+    }
+
+    Ok(())
+}
+
+/// Encode evidence as its two conflicting messages, each prefixed with its encoded length
+pub fn encode_evidence<Ctx, C, W>(evidence: &Evidence<Ctx>, codec: &C, mut buf: W) -> io::Result<()>
+where
+    Ctx: Context,
+    C: Codec<SignedConsensusMsg<Ctx>>,
+    W: Write,
+{
+    let (first, second) = evidence.messages();
+
+    for msg in [first, second] {
+        let bytes = codec.encode(&msg).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("failed to encode evidence: {e}"),
+            )
+        })?;
+
+        buf.write_u64::<BE>(bytes.len() as u64)?;
+        buf.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+pub fn decode_evidence<Ctx, C, R>(codec: &C, mut buf: R) -> io::Result<Evidence<Ctx>>
+where
+    Ctx: Context,
+    C: Codec<SignedConsensusMsg<Ctx>>,
+    R: Read,
+{
+    let mut decode_msg = || -> io::Result<SignedConsensusMsg<Ctx>> {
+        let len = buf.read_u64::<BE>()?;
+
+        // Do not trust the length to allocate, as evidence is received from the peers
+        let mut bytes = Vec::new();
+        (&mut buf).take(len).read_to_end(&mut bytes)?;
+
+        if bytes.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        codec.decode(bytes.into()).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("failed to decode evidence: {e}"),
+            )
+        })
+    };
+
+    let first = decode_msg()?;
+    let second = decode_msg()?;
+
+    Evidence::from_messages(first, second).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "evidence made of a vote and a proposal",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use malachitebft_core_types::NilOrVal;
+    use malachitebft_test::codec::proto::ProtobufCodec;
+    use malachitebft_test::{
+        Address, Ed25519Verifier, Height, PrivateKey, TestContext, Validator as TestValidator,
+        ValidatorSet as TestValidatorSet, ValueId, Vote,
+    };
+
+    use super::*;
+
+    type SignedVote = malachitebft_core_types::SignedVote<TestContext>;
+
+    fn private_key(seed: u8) -> PrivateKey {
+        PrivateKey::from([seed; 32])
+    }
+
+    fn validator_set(seed: u8) -> TestValidatorSet {
+        TestValidatorSet::new([TestValidator::new(private_key(seed).public_key(), 1)])
+    }
+
+    fn prevote(signer: u8, author: u8, height: u64, value: u64) -> SignedVote {
+        let address = Address::from_public_key(&private_key(author).public_key());
+
+        let vote = Vote::new_prevote(
+            Height::new(height),
+            Round::new(0),
+            NilOrVal::Val(ValueId::new(value)),
+            address,
+        );
+
+        SignedVote::new(
+            vote.clone(),
+            private_key(signer).sign(&vote.to_sign_bytes()),
+        )
+    }
+
+    fn double_vote(height: u64) -> Evidence<TestContext> {
+        Evidence::DoubleVote(prevote(1, 1, height, 1), prevote(1, 1, height, 2))
+    }
+
+    #[tokio::test]
+    async fn evidence_is_verified_against_the_validator_set_of_its_height() {
+        let provider = Ed25519Verifier;
+        let mut validator_sets = ValidatorSetHistory::<TestContext>::default();
+
+        validator_sets.record(Height::new(1), &validator_set(1));
+        assert_eq!(
+            verify(&provider, &validator_sets, &double_vote(1)).await,
+            Ok(())
+        );
+
+        // The validator rotated its key at height 2
+        validator_sets.record(Height::new(2), &validator_set(2));
+        validator_sets.record(Height::new(3), &validator_set(2));
+        assert_eq!(
+            verify(&provider, &validator_sets, &double_vote(1)).await,
+            Ok(())
+        );
+
+        assert_eq!(
+            verify(&provider, &validator_sets, &double_vote(2)).await,
+            Err(InvalidEvidence::UnknownValidator)
+        );
+        assert_eq!(
+            verify(&provider, &validator_sets, &double_vote(4)).await,
+            Err(InvalidEvidence::UnknownHeight)
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_evidence_is_rejected() {
+        let provider = Ed25519Verifier;
+        let mut validator_sets = ValidatorSetHistory::<TestContext>::default();
+        validator_sets.record(Height::new(1), &validator_set(1));
+
+        let same = Evidence::DoubleVote(prevote(1, 1, 1, 1), prevote(1, 1, 1, 1));
+        assert_eq!(
+            verify(&provider, &validator_sets, &same).await,
+            Err(InvalidEvidence::NotConflicting)
+        );
+
+        // Second vote forged by another key
+        let forged = Evidence::DoubleVote(prevote(1, 1, 1, 1), prevote(2, 1, 1, 2));
+        assert_eq!(
+            verify(&provider, &validator_sets, &forged).await,
+            Err(InvalidEvidence::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn validator_sets_of_expired_heights_are_forgotten() {
+        let mut validator_sets = ValidatorSetHistory::<TestContext>::default();
+        assert_eq!(validator_sets.get(Height::new(1)), None);
+
+        validator_sets.record(Height::new(1), &validator_set(1));
+        validator_sets.record(Height::new(2), &validator_set(2));
+        assert_eq!(validator_sets.sets.len(), 2);
+
+        let current = Height::new(MAX_EVIDENCE_AGE + 3);
+        validator_sets.record(current, &validator_set(2));
+        assert_eq!(validator_sets.get(Height::new(1)), None);
+        assert_eq!(validator_sets.get(Height::new(2)), Some(&validator_set(2)));
+        assert_eq!(validator_sets.get(current), Some(&validator_set(2)));
+
+        // Restarting a lower height
+        validator_sets.record(Height::new(3), &validator_set(1));
+        assert_eq!(validator_sets.get(Height::new(3)), Some(&validator_set(1)));
+        assert_eq!(validator_sets.get(current), None);
+    }
+
+    #[test]
+    fn uncommitted_evidence_is_pruned_once_expired() {
+        let mut pool = EvidencePool::<TestContext>::new();
+        assert!(pool.add(double_vote(1)));
+        assert!(pool.add(double_vote(5)));
+        assert_eq!(pool.uncommitted(), &[double_vote(1), double_vote(5)]);
+
+        pool.prune(Height::new(MAX_EVIDENCE_AGE + 2));
+        assert_eq!(pool.uncommitted(), &[double_vote(5)]);
+
+        // Pruned evidence is not remembered anymore
+        assert!(!pool.contains(&double_vote(1)));
+    }
+
+    #[test]
+    fn pool_survives_persistence() {
+        let codec = ProtobufCodec;
+
+        let mut pool = EvidencePool::<TestContext>::new();
+        assert!(pool.add(double_vote(1)));
+        assert!(!pool.add(double_vote(1)));
+        assert!(pool.add(double_vote(2)));
+
+        let snapshot = pool.snapshot();
+
+        let mut persisted = Vec::new();
+        for evidence in &snapshot.evidence {
+            encode_evidence(evidence, &codec, &mut persisted).unwrap();
+        }
+
+        let mut buf = persisted.as_slice();
+        let evidence = (0..2)
+            .map(|_| decode_evidence(&codec, &mut buf).unwrap())
+            .collect::<Vec<_>>();
+        assert!(buf.is_empty());
+        assert_eq!(evidence, snapshot.evidence);
+
+        let restored = EvidencePool::restore(EvidenceSnapshot { evidence });
+        assert_eq!(restored.len(), 2);
+        assert!(restored.contains(&double_vote(2)));
+
+        // Truncated
+        let mut buf = &persisted[..persisted.len() - 1];
+        decode_evidence::<TestContext, _, _>(&codec, &mut buf).unwrap();
+        assert!(decode_evidence::<TestContext, _, _>(&codec, &mut buf).is_err());
+    }
+}
@@ -6,8 +6,10 @@ use derive_where::derive_where;
 use ractor::{ActorRef, RpcReplyPort};
 
 use malachitebft_core_consensus::{MisbehaviorEvidence, Role, VoteExtensionError};
-use malachitebft_core_types::{CommitCertificate, Context, Round, ValueId, VoteExtensions};
-use malachitebft_sync::{PeerId, RawDecidedValue};
+use malachitebft_core_types::{
+    CommitCertificate, Context, DecisionProof, Round, ValueId, VoteExtensions,
+};
+use malachitebft_sync::{PeerId, RawDecidedValue, Snapshot};
 
 use crate::util::streaming::StreamMessage;
 
@@ -152,6 +154,9 @@ pub enum HostMsg<Ctx: Context> {
 
         /// Vote extensions that were received for this height.
         extensions: VoteExtensions<Ctx>,
+
+        /// Data needed to build a proof of the decision, if enabled with `consensus.decision_proof`
+        proof: Option<DecisionProof<Ctx>>,
     },
 
     /// Notifies the application that consensus has finalized a height after collecting additional precommits.
@@ -175,6 +180,9 @@ pub enum HostMsg<Ctx: Context> {
         extensions: VoteExtensions<Ctx>,
 
         /// Misbehavior evidence collected since last height was decided.
+        ///
+        /// This is the evidence observed by this node only, the evidence gossiped by the peers
+        /// is not included, see [`crate::consensus::Msg::GetUncommittedEvidence`].
         evidence: MisbehaviorEvidence<Ctx>,
 
         /// Use this reply port to instruct consensus to start the next height.
@@ -200,6 +208,10 @@ pub enum HostMsg<Ctx: Context> {
     ///
     /// If a value can be decoded from the bytes provided, then the application MUST reply
     /// to this message with the decoded value. Otherwise, it MUST reply with `None`.
+    ///
+    /// When syncing certificates only (see `value_sync.certificates_only`), the value bytes
+    /// are empty and the application MUST reply with the value it knows for that height,
+    /// eg. restored from a snapshot, whose id must match the one in the commit certificate.
     ProcessSyncedValue {
         /// Height of the synced value
         height: Ctx::Height,
@@ -213,4 +225,57 @@ pub enum HostMsg<Ctx: Context> {
         /// or `None` if the value could not be decoded
         reply_to: RpcReplyPort<ProposedValue<Ctx>>,
     },
+
+    /// Requests the snapshots of the application state which can be served to the peers,
+    /// for them to restore their state from instead of syncing every value.
+    ///
+    /// The application MUST respond with the snapshots it has, or with an empty vector.
+    GetSnapshots {
+        /// Channel for sending back the snapshots
+        reply_to: RpcReplyPort<Vec<Snapshot<Ctx>>>,
+    },
+
+    /// Requests a chunk of a snapshot of the application state, to be served to a peer.
+    ///
+    /// The application MUST respond with the chunk if it has it, or `None` otherwise.
+    GetSnapshotChunk {
+        /// Height of the snapshot
+        height: Ctx::Height,
+        /// Format of the snapshot
+        format: u32,
+        /// Index of the chunk
+        index: u32,
+        /// Channel for sending back the chunk
+        reply_to: RpcReplyPort<Option<Bytes>>,
+    },
+
+    /// Requests the application to restore its state from a snapshot fetched from the peers,
+    /// when `value_sync.snapshot_sync` is enabled.
+    ///
+    /// The application MUST check the chunks against the hash of the snapshot. If the snapshot
+    /// is restored, it MUST respond with the parameters of the height following the snapshot,
+    /// at which consensus then starts. Otherwise, it MUST respond with `None`, in which case
+    /// the values are synced one height at a time instead.
+    RestoreSnapshot {
+        /// The snapshot to restore
+        snapshot: Snapshot<Ctx>,
+        /// The chunks of the snapshot, in order
+        chunks: Vec<Bytes>,
+        /// Channel for sending back the parameters of the height following the snapshot
+        reply_to: RpcReplyPort<Option<HeightParams<Ctx>>>,
+    },
+
+    /// Requests the application to store decided values below the height it started from,
+    /// fetched from the peers when backfilling, eg. after restoring a snapshot
+    /// with `value_sync.backfill` enabled.
+    ///
+    /// The application MUST verify the commit certificates of the values before storing them,
+    /// and respond with `false` if any of them is invalid, in which case the values are
+    /// requested from another peer.
+    StoreBackfilledValues {
+        /// The decided values, in increasing order of height
+        values: Vec<RawDecidedValue<Ctx>>,
+        /// Channel for sending back whether the values were stored
+        reply_to: RpcReplyPort<bool>,
+    },
 }
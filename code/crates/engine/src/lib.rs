@@ -1,4 +1,7 @@
+#[cfg(feature = "chaos")]
+pub mod chaos;
 pub mod consensus;
+pub mod evidence;
 pub mod host;
 pub mod network;
 pub mod node;
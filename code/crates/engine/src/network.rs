@@ -1,41 +1,71 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::marker::PhantomData;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use bytes::Bytes;
 use derive_where::derive_where;
 use eyre::eyre;
 use libp2p::request_response;
 use ractor::{Actor, ActorProcessingErr, ActorRef, RpcReplyPort};
+use tokio::sync::{mpsc, oneshot};
 use tokio::task::JoinHandle;
-use tracing::{debug, error, info, trace, warn};
+use tracing::{debug, error, info, info_span, trace, warn, Span};
 
 use malachitebft_codec as codec;
-use malachitebft_core_consensus::{LivenessMsg, SignedConsensusMsg};
+use malachitebft_config::VoteBatchingConfig;
+use malachitebft_core_consensus::{Evidence, LivenessMsg, SignedConsensusMsg};
 use malachitebft_core_types::{
-    Context, PolkaCertificate, RoundCertificate, SignedProposal, SignedVote, SigningScheme,
-    Validator, ValidatorProof, ValidatorSet,
+    Context, Height as _, PolkaCertificate, Proposal as _, RoundCertificate, SignedProposal,
+    SignedVote, SigningScheme, Validator, ValidatorProof, ValidatorSet, Vote as _,
 };
 use malachitebft_metrics::SharedRegistry;
-use malachitebft_network::handle::CtrlHandle;
+use malachitebft_network::handle::{CtrlHandle, Handle};
 use malachitebft_network::validator_proof::ProofVerificationResult;
-use malachitebft_network::{Channel, Config, Event, PeerId};
+use malachitebft_network::{Channel, Config, Event, MessageAcceptance, PeerId};
 
 pub use malachitebft_network::{
-    Multiaddr, NetworkIdentity, NetworkStateDump, PersistentPeerError, PersistentPeersOp,
+    AddressBook, AddressBookEntry, AddressBookError, BootstrapPhase, BootstrapProgress,
+    ConfigUpdate, ConfigUpdateError, ConfigUpdateOutcome, ConnectedPeer, DiscoveredConnection,
+    DiscoveredPeer, DiscoveredPeerIdentity, DiscoveredPeerKind, HolePunchDenial, HolePunchEvent,
+    LinkConditions, Multiaddr, NetworkIdentity, NetworkStateDump, NetworkTopology, NodeKeyError,
+    NodeKeyRotation, NodeKeyStatus, PeerCapabilities, PeerConnectionError, PeerMessageError,
+    PersistentPeerError, PersistentPeersOp, RelayElectionEvent,
 };
 
+pub use malachitebft_network::node_key;
+
 use malachitebft_sync::{
     self as sync, InboundRequestId, OutboundRequestId, RawMessage, Request, Response,
 };
 
 use crate::consensus::ConsensusCodec;
+use crate::evidence::{decode_evidence, encode_evidence};
 use crate::sync::SyncCodec;
+use crate::util::mailbox::{Mailbox, Priority};
 use crate::util::output_port::{OutputPort, OutputPortSubscriberTrait};
-use crate::util::streaming::StreamMessage;
+use crate::util::streaming::{StreamId, StreamMessage};
+use crate::util::trace_context::{self, TraceContext};
+use crate::util::vote_batch::{self, VoteBatch};
 
 pub type NetworkRef<Ctx> = ActorRef<Msg<Ctx>>;
 pub type NetworkMsg<Ctx> = Msg<Ctx>;
 
+/// Maximum number of proposal streams whose size is tracked at once,
+/// the oldest ones are forgotten beyond that
+const MAX_TRACKED_STREAMS: usize = 1024;
+
+/// Maximum number of proposal streams of a single peer whose size is tracked at once,
+/// the oldest ones of the peer are forgotten beyond that
+const MAX_TRACKED_STREAMS_PER_PEER: usize = 16;
+
+/// Maximum number of proposal streams whose parts are kept to be served to the peers,
+/// the oldest ones are forgotten beyond that
+const MAX_CACHED_STREAMS: usize = 16;
+
+/// Score delta of a peer sending a stream of proposal parts over the maximum value size
+const OVERSIZED_VALUE_PENALTY: f64 = -100.0;
+
 pub trait Subscriber<Msg>: OutputPortSubscriberTrait<Msg>
 where
     Msg: Clone + ractor::Message,
@@ -57,14 +87,30 @@ where
 
 pub struct Network<Ctx, Codec> {
     codec: Codec,
+    max_value_size: usize,
+    mailbox: Mailbox,
+    vote_batching: VoteBatchingConfig,
     span: tracing::Span,
     marker: PhantomData<Ctx>,
 }
 
 impl<Ctx, Codec> Network<Ctx, Codec> {
-    pub fn new(codec: Codec, span: tracing::Span) -> Self {
+    /// Create the actor, rejecting the streams of proposal parts larger than
+    /// `max_value_size` bytes in total, or accepting any size if `0`, admitting the
+    /// events received from the network into its mailbox through `mailbox`,
+    /// and publishing its votes in batches if `vote_batching` is enabled
+    pub fn new(
+        codec: Codec,
+        max_value_size: usize,
+        mailbox: Mailbox,
+        vote_batching: VoteBatchingConfig,
+        span: tracing::Span,
+    ) -> Self {
         Self {
             codec,
+            max_value_size,
+            mailbox,
+            vote_batching,
             span,
             marker: PhantomData,
         }
@@ -78,28 +124,56 @@ where
     Codec: SyncCodec<Ctx>,
     Codec: codec::HasEncodedLen<sync::Response<Ctx>>,
 {
+    #[allow(clippy::too_many_arguments)]
     pub async fn spawn(
         identity: NetworkIdentity,
         config: Config,
         metrics: SharedRegistry,
         codec: Codec,
+        max_value_size: usize,
+        mailbox: Mailbox,
+        vote_batching: VoteBatchingConfig,
         span: tracing::Span,
     ) -> Result<ActorRef<Msg<Ctx>>, ractor::SpawnErr> {
-        let args = Args {
+        let args = Args::Spawn {
             identity,
             config: config.clone(),
             metrics,
         };
 
-        let (actor_ref, _) = Actor::spawn(None, Self::new(codec, span), args).await?;
+        let actor = Self::new(codec, max_value_size, mailbox, vote_batching, span);
+        let (actor_ref, _) = Actor::spawn(None, actor, args).await?;
+        Ok(actor_ref)
+    }
+
+    /// Spawn the actor on the handle of one of the chains served by a shared network service,
+    /// see [`malachitebft_network::spawn_chains`]
+    pub async fn spawn_with_handle(
+        handle: Handle,
+        codec: Codec,
+        max_value_size: usize,
+        mailbox: Mailbox,
+        vote_batching: VoteBatchingConfig,
+        span: tracing::Span,
+    ) -> Result<ActorRef<Msg<Ctx>>, ractor::SpawnErr> {
+        let args = Args::Handle(handle);
+
+        let actor = Self::new(codec, max_value_size, mailbox, vote_batching, span);
+        let (actor_ref, _) = Actor::spawn(None, actor, args).await?;
         Ok(actor_ref)
     }
 }
 
-pub struct Args {
-    pub identity: NetworkIdentity,
-    pub config: Config,
-    pub metrics: SharedRegistry,
+#[allow(clippy::large_enum_variant)]
+pub enum Args {
+    /// Spawn a network service dedicated to this actor
+    Spawn {
+        identity: NetworkIdentity,
+        config: Config,
+        metrics: SharedRegistry,
+    },
+    /// Use the handle of a chain served by a shared network service
+    Handle(Handle),
 }
 
 #[derive_where(Clone, Debug, PartialEq, Eq)]
@@ -109,15 +183,31 @@ pub enum NetworkEvent<Ctx: Context> {
     PeerConnected(PeerId),
     PeerDisconnected(PeerId),
 
+    /// Progress of the initial peer discovery
+    BootstrapProgress(BootstrapProgress),
+
+    /// Attempt to upgrade the relayed connection to a peer into a direct one, or its outcome
+    HolePunch(HolePunchEvent),
+
+    /// Change of the relay setup of this node, see `DiscoveryConfig::auto_relay`
+    RelayElection(RelayElectionEvent),
+
     Vote(PeerId, SignedVote<Ctx>),
 
     Proposal(PeerId, SignedProposal<Ctx>),
     ProposalPart(PeerId, StreamMessage<Ctx::ProposalPart>),
 
+    /// A peer sent a stream of proposal parts over the maximum value size,
+    /// the parts received on it from then on are dropped
+    ProposalTooLarge(PeerId, StreamId),
+
     PolkaCertificate(PeerId, PolkaCertificate<Ctx>),
 
     RoundCertificate(PeerId, RoundCertificate<Ctx>),
 
+    /// Evidence of equivocation gossiped by a peer
+    Evidence(PeerId, Evidence<Ctx>),
+
     /// A validator proof received from a peer (one-way, no response expected).
     ValidatorProofReceived {
         peer_id: PeerId,
@@ -130,6 +220,43 @@ pub enum NetworkEvent<Ctx: Context> {
     SyncResponse(OutboundRequestId, PeerId, Option<Response<Ctx>>),
 }
 
+impl<Ctx: Context> NetworkEvent<Ctx> {
+    /// Priority of the event in the mailbox of a subscriber at the given height, if known,
+    /// the messages for the past heights being shed first.
+    ///
+    /// The status of the peers, which sync picks the peers to request values from after,
+    /// and the proposal parts, which are not sent again once lost, are never shed.
+    pub fn priority(&self, current_height: Option<u64>) -> Priority {
+        let stale = |height: Ctx::Height| current_height.is_some_and(|h| height.as_u64() < h);
+
+        match self {
+            Self::Vote(_, vote) if stale(vote.height()) => Priority::Low,
+            Self::Proposal(_, proposal) if stale(proposal.height()) => Priority::Low,
+            Self::PolkaCertificate(_, certificate) if stale(certificate.height) => Priority::Low,
+            Self::RoundCertificate(_, certificate) if stale(certificate.height) => Priority::Low,
+
+            Self::Vote(..)
+            | Self::Proposal(..)
+            | Self::PolkaCertificate(..)
+            | Self::RoundCertificate(..)
+            | Self::Evidence(..)
+            | Self::ValidatorProofReceived { .. }
+            | Self::SyncRequest(..)
+            | Self::SyncResponse(..) => Priority::Normal,
+
+            Self::Listening(_)
+            | Self::PeerConnected(_)
+            | Self::PeerDisconnected(_)
+            | Self::BootstrapProgress(_)
+            | Self::HolePunch(_)
+            | Self::RelayElection(_)
+            | Self::ProposalTooLarge(..)
+            | Self::Status(..)
+            | Self::ProposalPart(..) => Priority::High,
+        }
+    }
+}
+
 pub enum State<Ctx: Context> {
     Stopped,
     Running {
@@ -138,14 +265,257 @@ pub enum State<Ctx: Context> {
         output_port: OutputPort<NetworkEvent<Ctx>>,
         ctrl_handle: Box<CtrlHandle>,
         recv_task: JoinHandle<()>,
-        inbound_requests: HashMap<InboundRequestId, request_response::InboundRequestId>,
+        /// Requests received from peers, with the span in which they were received
+        inbound_requests: HashMap<InboundRequestId, (request_response::InboundRequestId, Span)>,
+        peer_messages: Option<mpsc::Sender<PeerMessage>>,
+        mempool: Option<mpsc::Sender<MempoolMessage>>,
+        connectivity: Vec<mpsc::Sender<ConnectivityEvent>>,
+        /// Latest of [`ConnectivityEvent::SufficientPeers`] and [`ConnectivityEvent::InsufficientPeers`]
+        sufficient_peers: Option<ConnectivityEvent>,
+        stream_sizes: StreamSizes,
+        stream_spans: StreamSpans,
+        proposal_parts: ProposalPartsCache,
+        /// Votes waiting to be published together, see [`VoteBatchingConfig`]
+        vote_batch: VoteBatch,
+    },
+}
+
+/// Total size of the proposal parts received so far on each stream.
+///
+/// The streams which went over the maximum value size are set apart until consensus is past
+/// their height, so that they stay rejected however many streams are tracked meanwhile.
+#[derive(Default)]
+pub struct StreamSizes {
+    sizes: HashMap<(PeerId, StreamId), usize>,
+    order: VecDeque<(PeerId, StreamId)>,
+    /// Streams over the maximum value size, with the height consensus was at when they went over it
+    exceeded: HashMap<(PeerId, StreamId), u64>,
+    /// Height consensus is at
+    height: u64,
+}
+
+impl StreamSizes {
+    /// Add the size of a part received on a stream, returning the total size of the stream.
+    ///
+    /// A new stream of a peer which has too many streams tracked replaces its oldest one,
+    /// so that a peer cannot get the streams of the other peers forgotten.
+    fn add(&mut self, key: (PeerId, StreamId), size: usize) -> usize {
+        if !self.sizes.contains_key(&key) {
+            let peer_streams = self.order.iter().filter(|(peer, _)| *peer == key.0).count();
+
+            let evicted = if peer_streams >= MAX_TRACKED_STREAMS_PER_PEER {
+                let oldest = self.order.iter().position(|(peer, _)| *peer == key.0);
+                oldest.and_then(|index| self.order.remove(index))
+            } else if self.order.len() >= MAX_TRACKED_STREAMS {
+                self.order.pop_front()
+            } else {
+                None
+            };
+
+            if let Some(evicted) = evicted {
+                self.sizes.remove(&evicted);
+            }
+
+            self.order.push_back(key.clone());
+        }
+
+        let total = self.sizes.entry(key).or_default();
+        *total = total.saturating_add(size);
+        *total
+    }
+
+    /// Whether the stream went over the maximum value size
+    fn is_exceeded(&self, key: &(PeerId, StreamId)) -> bool {
+        self.exceeded.contains_key(key)
+    }
+
+    /// Set apart a stream which went over the maximum value size
+    fn exceed(&mut self, key: (PeerId, StreamId)) {
+        self.remove(&key);
+        self.exceeded.insert(key, self.height);
+    }
+
+    /// Forget about a stream once complete
+    fn remove(&mut self, key: &(PeerId, StreamId)) {
+        if self.sizes.remove(key).is_some() {
+            self.order.retain(|k| k != key);
+        }
+    }
+
+    /// Forget about the streams over the maximum value size once consensus is past their height.
+    ///
+    /// A stream may be for the height after the one consensus was at when it went over the
+    /// maximum value size, so it is kept until consensus is past that height too.
+    fn set_height(&mut self, height: u64) {
+        self.height = height;
+        self.exceeded
+            .retain(|_, exceeded_at| exceeded_at.saturating_add(1) >= height);
+    }
+}
+
+/// Spans of the streams of proposal parts published by this node, from their first to their last part.
+///
+/// The parts of a stream carry the trace context of its span, so that their receipt
+/// by the peers is recorded in the same trace.
+#[derive(Default)]
+pub struct StreamSpans {
+    spans: HashMap<StreamId, Span>,
+    order: VecDeque<StreamId>,
+}
+
+impl StreamSpans {
+    /// Span of the stream a part is published on, started if the part is the first one seen
+    fn get_or_start<T>(&mut self, part: &StreamMessage<T>) -> Span {
+        if let Some(span) = self.spans.get(&part.stream_id) {
+            return span.clone();
+        }
+
+        if self.order.len() >= MAX_TRACKED_STREAMS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.spans.remove(&oldest);
+            }
+        }
+
+        let span = info_span!(parent: None, "publish_proposal", stream_id = %part.stream_id);
+
+        self.order.push_back(part.stream_id.clone());
+        self.spans.insert(part.stream_id.clone(), span.clone());
+
+        span
+    }
+
+    /// Close the span of a stream once its last part is published
+    fn finish(&mut self, stream_id: &StreamId) {
+        if self.spans.remove(stream_id).is_some() {
+            self.order.retain(|id| id != stream_id);
+        }
+    }
+}
+
+/// Encoded proposal parts of the latest streams received or published by this node,
+/// served to the peers which missed some of them, see [`Msg::GetProposalParts`]
+///
+/// The parts fetched from a peer are only tracked to not deliver them twice: they are not
+/// authenticated by the proposer as the gossiped ones are, so they are neither served to
+/// the other peers nor prevent the gossiped copies from being kept and delivered.
+#[derive(Default)]
+pub struct ProposalPartsCache {
+    parts: HashMap<(PeerId, StreamId), BTreeMap<u64, Bytes>>,
+    fetched: HashMap<(PeerId, StreamId), BTreeSet<u64>>,
+    order: VecDeque<(PeerId, StreamId)>,
+}
+
+impl ProposalPartsCache {
+    /// Keep a part of a stream, returning `false` if it was already kept
+    fn insert(&mut self, key: (PeerId, StreamId), sequence: u64, data: Bytes) -> bool {
+        self.track(&key);
+
+        let parts = self.parts.entry(key).or_default();
+        if parts.contains_key(&sequence) {
+            return false;
+        }
+
+        parts.insert(sequence, data);
+        true
+    }
+
+    /// Record a part of a stream fetched from a peer, returning `false`
+    /// if it was already received or fetched
+    fn insert_fetched(&mut self, key: (PeerId, StreamId), sequence: u64) -> bool {
+        self.track(&key);
+
+        if self
+            .parts
+            .get(&key)
+            .is_some_and(|parts| parts.contains_key(&sequence))
+        {
+            return false;
+        }
+
+        self.fetched.entry(key).or_default().insert(sequence)
+    }
+
+    /// Start tracking a stream if it is not yet, evicting the oldest one if full
+    fn track(&mut self, key: &(PeerId, StreamId)) {
+        if self.order.contains(key) {
+            return;
+        }
+
+        if self.order.len() >= MAX_CACHED_STREAMS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.parts.remove(&oldest);
+                self.fetched.remove(&oldest);
+            }
+        }
+
+        self.order.push_back(key.clone());
+    }
+
+    /// The parts of a stream with the given sequence numbers which are kept
+    fn get(&self, key: &(PeerId, StreamId), sequences: &[u64]) -> Vec<Bytes> {
+        let Some(parts) = self.parts.get(key) else {
+            return Vec::new();
+        };
+
+        sequences
+            .iter()
+            .filter_map(|sequence| parts.get(sequence).cloned())
+            .collect()
+    }
+}
+
+/// Outcome of the check of the size of a proposal stream, see [`check_value_size`]
+enum ValueSize {
+    /// The stream is within the maximum value size
+    Within,
+    /// The stream just went over the maximum value size
+    Exceeded(StreamId),
+    /// The stream was already over the maximum value size
+    AlreadyExceeded,
+}
+
+/// A change in the connectivity of the node, see [`Msg::SubscribeConnectivity`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectivityEvent {
+    PeerConnected(PeerId),
+    PeerDisconnected(PeerId),
+
+    /// Enough peers now take part in consensus: with GossipSub, at least `mesh_n_low` peers
+    /// are in the consensus mesh, or all the other validators if there are fewer of them
+    SufficientPeers {
+        peers: usize,
+        required: usize,
     },
+
+    /// Too few peers now take part in consensus, see [`ConnectivityEvent::SufficientPeers`]
+    InsufficientPeers {
+        peers: usize,
+        required: usize,
+    },
+}
+
+/// A message sent by a peer directly to the application
+#[derive(Debug)]
+pub struct PeerMessage {
+    pub peer_id: PeerId,
+    pub payload: Bytes,
+    /// Channel for sending back the reply to the peer,
+    /// dropping it lets the peer know that no reply is coming
+    pub reply: oneshot::Sender<Bytes>,
+}
+
+/// Transactions gossiped by a peer on the mempool channel
+#[derive(Clone, Debug)]
+pub struct MempoolMessage {
+    pub peer_id: PeerId,
+    pub payload: Bytes,
 }
 
 #[derive_where(Clone, Debug, PartialEq, Eq)]
 pub struct Status<Ctx: Context> {
     pub tip_height: Ctx::Height,
     pub history_min_height: Ctx::Height,
+    pub min_needed_height: Option<Ctx::Height>,
 }
 
 impl<Ctx: Context> Status<Ctx> {
@@ -153,8 +523,15 @@ impl<Ctx: Context> Status<Ctx> {
         Self {
             tip_height,
             history_min_height,
+            min_needed_height: None,
         }
     }
+
+    /// Advertise the minimum height still needed by the node
+    pub fn with_min_needed_height(mut self, min_needed_height: Option<Ctx::Height>) -> Self {
+        self.min_needed_height = min_needed_height;
+        self
+    }
 }
 
 pub enum Msg<Ctx: Context> {
@@ -170,6 +547,12 @@ pub enum Msg<Ctx: Context> {
     /// Publish a proposal part
     PublishProposalPart(StreamMessage<Ctx::ProposalPart>),
 
+    /// Publish evidence of equivocation
+    PublishEvidence(Evidence<Ctx>),
+
+    /// Gossip transactions of the application on the mempool channel
+    PublishMempool(Bytes),
+
     /// Broadcast status to all direct peers
     BroadcastStatus(Status<Ctx>),
 
@@ -179,15 +562,63 @@ pub enum Msg<Ctx: Context> {
     /// Send a response for a request to a peer
     OutgoingResponse(InboundRequestId, Response<Ctx>),
 
+    /// Request the encoded parts with the given sequence numbers of a stream
+    /// of the given proposer, which this node received or published
+    GetProposalParts(PeerId, StreamId, Vec<u64>, RpcReplyPort<Vec<Bytes>>),
+
+    /// Process the encoded parts of a stream of the given proposer fetched from a peer
+    /// in response to a request for the given sequence numbers, delivering to the
+    /// subscribers the requested ones not received yet.
+    ///
+    /// Unlike the gossiped parts, the fetched ones are not authenticated by the proposer,
+    /// the application must check them against the data signed by the proposer,
+    /// eg. the signature in the last part of the stream, before using the value.
+    ProcessProposalParts(PeerId, StreamId, Vec<u64>, Vec<Bytes>),
+
     /// Request to dump the current network state
     DumpState(RpcReplyPort<Option<NetworkStateDump>>),
 
+    /// Request a snapshot of the local view of the graph of the network
+    DumpTopology(RpcReplyPort<Option<NetworkTopology>>),
+
+    /// Request the list of peers currently known to discovery
+    DiscoveredPeers(RpcReplyPort<Option<Vec<DiscoveredPeer>>>),
+
+    /// Request the list of peers this node is connected to, with their connections and scores
+    ListPeers(RpcReplyPort<Option<Vec<ConnectedPeer>>>),
+
+    /// Export the peers known to discovery, to seed other nodes
+    ExportAddressBook(RpcReplyPort<Option<AddressBook>>),
+
+    /// Dial the peers of an address book, replying with the number of peers dialed
+    ImportAddressBook(AddressBook, RpcReplyPort<Option<usize>>),
+
+    /// Close all the connections to a peer, which discovery may later dial again
+    DisconnectPeer(PeerId, RpcReplyPort<Result<(), PeerConnectionError>>),
+
+    /// Dial a peer at the given address, replying once the dial is started
+    DialPeer(Multiaddr, RpcReplyPort<Result<(), PeerConnectionError>>),
+
     /// Add or remove a persistent peer at runtime
     UpdatePersistentPeers(
         PersistentPeersOp,
         RpcReplyPort<Result<(), PersistentPeerError>>,
     ),
 
+    /// Change the configuration of the running network
+    UpdateConfig(
+        ConfigUpdate,
+        RpcReplyPort<Result<ConfigUpdateOutcome, ConfigUpdateError>>,
+    ),
+
+    /// Request the current `PeerId` of the node, and the one it takes once restarted
+    /// if its key was rotated
+    NodeKeyStatus(RpcReplyPort<Option<NodeKeyStatus>>),
+
+    /// Rotate the node key, taking effect once the node is restarted,
+    /// and announce the new `PeerId` to the connected persistent peers
+    RotateNodeKey(RpcReplyPort<Result<NodeKeyRotation, NodeKeyError>>),
+
     /// Update the validator set for the current height
     UpdateValidatorSet(Ctx::ValidatorSet),
 
@@ -200,6 +631,69 @@ pub enum Msg<Ctx: Context> {
         public_key: Option<Vec<u8>>,
     },
 
+    /// Send the validator proof signed with the new consensus key to the peers,
+    /// once the signing key was rotated
+    UpdateValidatorProof(ValidatorProof<Ctx>),
+
+    /// Provide the peer IDs of the validators of the current validator set,
+    /// dialed first, kept connected and protected from GossipSub mesh pruning
+    UpdateValidatorPeers(Vec<PeerId>),
+
+    /// Advertise to the peers the lowest height whose decided value is still served by sync
+    SetRetainedHeight(u64),
+
+    /// Consensus is done recovering and started its first height,
+    /// until which the known peers are not shared with the peers
+    NodeStarted,
+
+    /// Consensus started the given height, before which the proposal streams
+    /// over the maximum value size are forgotten
+    StartedHeight(u64),
+
+    /// Add a delta to the score of a peer, eg. a negative one to penalize a peer
+    /// delivering invalid proposals or votes, which gets it pruned from the GossipSub mesh
+    ReportPeer(PeerId, f64),
+
+    /// Disconnect a peer and refuse any connection to or from it,
+    /// for the given duration or until unbanned if `None`
+    BanPeer(PeerId, Option<Duration>),
+
+    /// Lift the ban of a peer
+    UnbanPeer(PeerId),
+
+    /// Simulate the given conditions on the link to a peer, eg. latency in tests,
+    /// or stop simulating them if `None`
+    SetLinkConditions(PeerId, Option<LinkConditions>),
+
+    /// Start listening on an additional address, eg. a new network interface
+    AddListenAddr(Multiaddr),
+
+    /// Stop listening on an address
+    RemoveListenAddr(Multiaddr),
+
+    /// Send a message directly to a peer, replying with the reply of the peer
+    SendToPeer(PeerId, Bytes, RpcReplyPort<Result<Bytes, PeerMessageError>>),
+
+    /// Forward the messages sent directly by the peers to the given channel,
+    /// instead of dropping them without replying
+    SubscribePeerMessages(mpsc::Sender<PeerMessage>),
+
+    /// Forward the transactions gossiped by the peers on the mempool channel to the given channel,
+    /// instead of dropping them
+    SubscribeMempool(mpsc::Sender<MempoolMessage>),
+
+    /// Forward the changes in the connectivity of the node to the given channel,
+    /// starting with the peers currently connected and whether they are enough
+    SubscribeConnectivity(mpsc::Sender<ConnectivityEvent>),
+
+    /// Publish the votes waiting to be published together, if any
+    #[doc(hidden)]
+    FlushVoteBatch,
+
+    /// Reply to a message sent directly by a peer, or drop it without replying if `None`
+    #[doc(hidden)]
+    ReplyToPeer(request_response::InboundRequestId, Option<Bytes>),
+
     // Event emitted by the gossip layer
     #[doc(hidden)]
     NewEvent(Event),
@@ -226,12 +720,25 @@ where
         myself: ActorRef<Msg<Ctx>>,
         args: Args,
     ) -> Result<Self::State, ActorProcessingErr> {
-        let handle = malachitebft_network::spawn(args.identity, args.config, args.metrics).await?;
+        let handle = match args {
+            Args::Spawn {
+                identity,
+                config,
+                metrics,
+            } => malachitebft_network::spawn(identity, config, metrics).await?,
+            Args::Handle(handle) => handle,
+        };
 
         let (mut recv_handle, ctrl_handle) = handle.split();
 
+        let mailbox = self.mailbox.clone();
         let recv_task = tokio::spawn(async move {
             while let Some(event) = recv_handle.recv().await {
+                if !mailbox.admit(event_priority(&event)) {
+                    trace!("Network actor is overloaded, dropping event");
+                    continue;
+                }
+
                 if let Err(e) = myself.cast(Msg::NewEvent(event)) {
                     error!("Actor has died, stopping network: {e:?}");
                     break;
@@ -246,6 +753,14 @@ where
             ctrl_handle: Box::new(ctrl_handle),
             recv_task,
             inbound_requests: HashMap::new(),
+            peer_messages: None,
+            mempool: None,
+            connectivity: Vec::new(),
+            sufficient_peers: None,
+            stream_sizes: StreamSizes::default(),
+            stream_spans: StreamSpans::default(),
+            proposal_parts: ProposalPartsCache::default(),
+            vote_batch: VoteBatch::default(),
         })
     }
 
@@ -260,33 +775,110 @@ where
     #[tracing::instrument(name = "network", parent = &self.span, skip_all)]
     async fn handle(
         &self,
-        _myself: ActorRef<Msg<Ctx>>,
+        myself: ActorRef<Msg<Ctx>>,
         msg: Msg<Ctx>,
         state: &mut State<Ctx>,
     ) -> Result<(), ActorProcessingErr> {
+        if let Msg::NewEvent(_) = msg {
+            self.mailbox.release();
+        }
+
         // We need to handle before deconstructing `state` to always reply.
         if let Msg::DumpState(reply_to) = msg {
             handle_dump_state(state, reply_to).await;
             return Ok(());
         }
 
+        if let Msg::DumpTopology(reply_to) = msg {
+            handle_dump_topology(state, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::DiscoveredPeers(reply_to) = msg {
+            handle_discovered_peers(state, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::ListPeers(reply_to) = msg {
+            handle_list_peers(state, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::ExportAddressBook(reply_to) = msg {
+            handle_export_address_book(state, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::ImportAddressBook(book, reply_to) = msg {
+            handle_import_address_book(state, book, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::DisconnectPeer(peer_id, reply_to) = msg {
+            handle_disconnect_peer(state, peer_id, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::DialPeer(addr, reply_to) = msg {
+            handle_dial_peer(state, addr, reply_to).await;
+            return Ok(());
+        }
+
         if let Msg::UpdatePersistentPeers(op, reply_to) = msg {
             handle_update_persistent_peers(state, op, reply_to).await;
             return Ok(());
         }
 
+        if let Msg::UpdateConfig(update, reply_to) = msg {
+            handle_update_config(state, update, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::NodeKeyStatus(reply_to) = msg {
+            handle_node_key_status(state, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::RotateNodeKey(reply_to) = msg {
+            handle_rotate_node_key(state, reply_to).await;
+            return Ok(());
+        }
+
+        if let Msg::SendToPeer(peer_id, payload, reply_to) = msg {
+            handle_send_to_peer(state, peer_id, payload, reply_to).await;
+            return Ok(());
+        }
+
         let State::Running {
             listen_addrs,
             peers,
             output_port,
             ctrl_handle,
             inbound_requests,
+            peer_messages,
+            mempool,
+            connectivity,
+            sufficient_peers,
+            stream_sizes,
+            stream_spans,
+            proposal_parts,
+            vote_batch,
             ..
         } = state
         else {
             return Ok(());
         };
 
+        #[cfg(feature = "chaos")]
+        if matches!(
+            msg,
+            Msg::PublishConsensusMsg(_) | Msg::PublishLivenessMsg(_) | Msg::PublishProposalPart(_)
+        ) && crate::chaos::toggles().drop_gossip()
+        {
+            debug!("Chaos: dropping outbound gossip message");
+            return Ok(());
+        }
+
         match msg {
             Msg::Subscribe(subscriber) => {
                 for addr in listen_addrs.iter() {
@@ -300,15 +892,72 @@ where
                 subscriber.subscribe_to_port(output_port);
             }
 
-            Msg::PublishConsensusMsg(msg) => match self.codec.encode(&msg) {
-                Ok(data) => ctrl_handle.publish(Channel::Consensus, data).await?,
-                Err(e) => error!("Failed to encode consensus message: {e:?}"),
-            },
+            Msg::PublishConsensusMsg(msg) => {
+                let span = info_span!(
+                    parent: None,
+                    "publish_consensus_msg",
+                    height = %msg.height(),
+                    round = %msg.round(),
+                );
 
-            Msg::PublishLivenessMsg(msg) => match self.codec.encode(&msg) {
-                Ok(data) => ctrl_handle.publish(Channel::Liveness, data).await?,
-                Err(e) => error!("Failed to encode liveness message: {e:?}"),
-            },
+                let batched =
+                    self.vote_batching.enabled && matches!(msg, SignedConsensusMsg::Vote(_));
+
+                match self.codec.encode(&msg) {
+                    Ok(data) if batched => {
+                        let data = with_trace_context(&span, data);
+                        let max_size = self.vote_batching.max_size.as_u64() as usize;
+
+                        if vote_batch.would_exceed(&data, max_size) {
+                            publish_vote_batch(vote_batch, ctrl_handle).await?;
+                        }
+
+                        if vote_batch.is_empty() {
+                            myself.send_after(self.vote_batching.window, || Msg::FlushVoteBatch);
+                        }
+
+                        vote_batch.push(data);
+
+                        if vote_batch.len() >= self.vote_batching.max_votes
+                            || vote_batch.size() >= max_size
+                        {
+                            publish_vote_batch(vote_batch, ctrl_handle).await?;
+                        }
+                    }
+                    Ok(data) => {
+                        let data = with_trace_context(&span, data);
+                        ctrl_handle.publish(Channel::Consensus, data).await?
+                    }
+                    Err(e) => error!("Failed to encode consensus message: {e:?}"),
+                }
+            }
+
+            Msg::FlushVoteBatch => publish_vote_batch(vote_batch, ctrl_handle).await?,
+
+            Msg::PublishLivenessMsg(msg) => {
+                let span = info_span!(parent: None, "publish_liveness_msg");
+
+                match self.codec.encode(&msg) {
+                    Ok(data) => {
+                        let data = with_trace_context(&span, data);
+                        ctrl_handle.publish(Channel::Liveness, data).await?
+                    }
+                    Err(e) => error!("Failed to encode liveness message: {e:?}"),
+                }
+            }
+
+            Msg::PublishEvidence(evidence) => {
+                let mut data = Vec::new();
+
+                match encode_evidence(&evidence, &self.codec, &mut data) {
+                    Ok(()) => ctrl_handle.publish(Channel::Evidence, data.into()).await?,
+                    Err(e) => error!("Failed to encode evidence: {e:?}"),
+                }
+            }
+
+            Msg::PublishMempool(data) => {
+                ctrl_handle.publish(Channel::Mempool, data).await?;
+            }
 
             Msg::PublishProposalPart(msg) => {
                 trace!(
@@ -317,9 +966,21 @@ where
                     "Broadcasting proposal part"
                 );
 
+                let span = stream_spans.get_or_start(&msg);
+
+                if msg.is_fin() {
+                    stream_spans.finish(&msg.stream_id);
+                }
+
                 let data = self.codec.encode(&msg);
                 match data {
-                    Ok(data) => ctrl_handle.publish(Channel::ProposalParts, data).await?,
+                    Ok(data) => {
+                        let key = (ctrl_handle.peer_id(), msg.stream_id.clone());
+                        proposal_parts.insert(key, msg.sequence, data.clone());
+
+                        let data = with_trace_context(&span, data);
+                        ctrl_handle.publish(Channel::ProposalParts, data).await?
+                    }
                     Err(e) => error!("Failed to encode proposal part: {e:?}"),
                 }
             }
@@ -329,6 +990,7 @@ where
                     peer_id: ctrl_handle.peer_id(),
                     tip_height: status.tip_height,
                     history_min_height: status.history_min_height,
+                    min_needed_height: status.min_needed_height,
                 };
 
                 let data = self.codec.encode(&status);
@@ -339,10 +1001,12 @@ where
             }
 
             Msg::OutgoingRequest(peer_id, request, reply_to) => {
+                let span = info_span!(parent: None, "sync_request", peer = %peer_id);
                 let request = self.codec.encode(&request);
 
                 match request {
                     Ok(data) => {
+                        let data = with_trace_context(&span, data);
                         let p2p_request_id = ctrl_handle.sync_request(peer_id, data).await?;
                         reply_to.send(OutboundRequestId::new(p2p_request_id))?;
                     }
@@ -355,10 +1019,13 @@ where
 
                 match response {
                     Ok(data) => {
-                        let request_id = inbound_requests
+                        let (request_id, request_span) = inbound_requests
                             .remove(&request_id)
                             .ok_or_else(|| eyre!("Unknown inbound request ID: {request_id}"))?;
 
+                        let span = info_span!(parent: &request_span, "sync_response");
+                        let data = with_trace_context(&span, data);
+
                         ctrl_handle.sync_reply(request_id, data).await?
                     }
                     Err(e) => {
@@ -368,6 +1035,59 @@ where
                 };
             }
 
+            Msg::GetProposalParts(proposer, stream_id, sequences, reply_to) => {
+                let parts = proposal_parts.get(&(proposer, stream_id), &sequences);
+                reply_to.send(parts)?;
+            }
+
+            Msg::ProcessProposalParts(proposer, stream_id, sequences, parts) => {
+                for data in parts {
+                    let size = data.len();
+
+                    let part: StreamMessage<Ctx::ProposalPart> =
+                        match self.codec.decode(data.clone()) {
+                            Ok(part) => part,
+                            Err(e) => {
+                                error!(%proposer, "Failed to decode fetched proposal part: {e:?}");
+                                continue;
+                            }
+                        };
+
+                    if part.stream_id != stream_id {
+                        warn!(%proposer, %stream_id, "Fetched proposal part of another stream, ignoring");
+                        continue;
+                    }
+
+                    if !sequences.contains(&part.sequence) {
+                        warn!(
+                            %proposer, %stream_id, sequence = %part.sequence,
+                            "Fetched proposal part which was not requested, ignoring"
+                        );
+                        continue;
+                    }
+
+                    // Skip the parts received in the meantime on the proposal parts channel
+                    let key = (proposer, part.stream_id.clone());
+                    if !proposal_parts.insert_fetched(key, part.sequence) {
+                        continue;
+                    }
+
+                    debug!(
+                        %proposer,
+                        stream_id = %part.stream_id,
+                        sequence = %part.sequence,
+                        "Fetched missing proposal part"
+                    );
+
+                    let event = NetworkEvent::ProposalPart(proposer, part);
+                    if let ValueSize::Within =
+                        check_value_size(stream_sizes, self.max_value_size, &event, size)
+                    {
+                        output_port.send(event);
+                    }
+                }
+            }
+
             Msg::NewEvent(Event::Listening(addr)) => {
                 listen_addrs.push(addr.clone());
                 output_port.send(NetworkEvent::Listening(addr));
@@ -376,103 +1096,144 @@ where
             Msg::NewEvent(Event::PeerConnected(peer_id)) => {
                 peers.insert(peer_id);
                 output_port.send(NetworkEvent::PeerConnected(peer_id));
+                notify_connectivity(connectivity, ConnectivityEvent::PeerConnected(peer_id));
             }
 
             Msg::NewEvent(Event::PeerDisconnected(peer_id)) => {
                 peers.remove(&peer_id);
                 output_port.send(NetworkEvent::PeerDisconnected(peer_id));
+                notify_connectivity(connectivity, ConnectivityEvent::PeerDisconnected(peer_id));
             }
 
-            Msg::NewEvent(Event::LivenessMessage(Channel::Liveness, from, data)) => {
-                let msg = match self.codec.decode(data) {
-                    Ok(msg) => msg,
-                    Err(e) => {
-                        error!(%from, "Failed to decode liveness message: {e:?}");
-                        return Ok(());
-                    }
+            Msg::NewEvent(Event::SufficientPeers {
+                peers: count,
+                required,
+            }) => {
+                let event = ConnectivityEvent::SufficientPeers {
+                    peers: count,
+                    required,
                 };
+                notify_connectivity(connectivity, event.clone());
+                *sufficient_peers = Some(event);
+            }
 
-                let event = match msg {
-                    LivenessMsg::PolkaCertificate(polka_cert) => {
-                        NetworkEvent::PolkaCertificate(from, polka_cert)
-                    }
-                    LivenessMsg::SkipRoundCertificate(round_cert) => {
-                        NetworkEvent::RoundCertificate(from, round_cert)
-                    }
-                    LivenessMsg::Vote(vote) => NetworkEvent::Vote(from, vote),
+            Msg::NewEvent(Event::InsufficientPeers {
+                peers: count,
+                required,
+            }) => {
+                let event = ConnectivityEvent::InsufficientPeers {
+                    peers: count,
+                    required,
                 };
+                notify_connectivity(connectivity, event.clone());
+                *sufficient_peers = Some(event);
+            }
 
-                output_port.send(event);
+            Msg::NewEvent(Event::BootstrapProgress(progress)) => {
+                output_port.send(NetworkEvent::BootstrapProgress(progress));
             }
 
-            Msg::NewEvent(Event::LivenessMessage(channel, from, _)) => {
-                error!(%from, "Unexpected liveness message on {channel} channel");
-                return Ok(());
+            Msg::NewEvent(Event::HolePunch(event)) => {
+                output_port.send(NetworkEvent::HolePunch(event));
             }
 
-            Msg::NewEvent(Event::ConsensusMessage(Channel::Consensus, from, data)) => {
-                let msg = match self.codec.decode(data) {
-                    Ok(msg) => msg,
-                    Err(e) => {
-                        error!(%from, "Failed to decode consensus message: {e:?}");
-                        return Ok(());
-                    }
-                };
+            Msg::NewEvent(Event::RelayElection(event)) => {
+                output_port.send(NetworkEvent::RelayElection(event));
+            }
 
-                let event = match msg {
-                    SignedConsensusMsg::Vote(vote) => NetworkEvent::Vote(from, vote),
-                    SignedConsensusMsg::Proposal(proposal) => {
-                        NetworkEvent::Proposal(from, proposal)
-                    }
+            Msg::NewEvent(
+                Event::ConsensusMessage(channel, from, data)
+                | Event::LivenessMessage(channel, from, data),
+            ) => {
+                let Some(messages) = vote_batch::split(data) else {
+                    warn!(%from, %channel, "Received a malformed batch of votes, ignoring");
+                    return Ok(());
                 };
 
-                output_port.send(event);
-            }
+                for data in messages {
+                    let (span, data) = receive_traced(channel, from, data);
+                    let size = data.len();
 
-            Msg::NewEvent(Event::ConsensusMessage(Channel::ProposalParts, from, data)) => {
-                let msg: StreamMessage<Ctx::ProposalPart> = match self.codec.decode(data) {
-                    Ok(stream_msg) => stream_msg,
-                    Err(e) => {
-                        error!(%from, "Failed to decode stream message: {e:?}");
-                        return Ok(());
+                    let Some(event) =
+                        span.in_scope(|| decode_message(&self.codec, channel, from, data.clone()))
+                    else {
+                        continue;
+                    };
+
+                    if !keep_proposal_part(proposal_parts, &event, data) {
+                        continue;
                     }
-                };
 
-                trace!(
-                    %from,
-                    stream_id = %msg.stream_id,
-                    sequence = %msg.sequence,
-                    "Received proposal part"
-                );
+                    match check_value_size(stream_sizes, self.max_value_size, &event, size) {
+                        ValueSize::Within => output_port.send(event),
+                        ValueSize::Exceeded(stream_id) => {
+                            ctrl_handle
+                                .report_peer(from, OVERSIZED_VALUE_PENALTY)
+                                .await?;
 
-                output_port.send(NetworkEvent::ProposalPart(from, msg));
+                            output_port.send(NetworkEvent::ProposalTooLarge(from, stream_id));
+                        }
+                        ValueSize::AlreadyExceeded => (),
+                    }
+                }
             }
 
-            Msg::NewEvent(Event::ConsensusMessage(Channel::Sync, from, data)) => {
-                let status: sync::Status<Ctx> = match self.codec.decode(data) {
-                    Ok(status) => status,
-                    Err(e) => {
-                        error!(%from, "Failed to decode status message: {e:?}");
-                        return Ok(());
-                    }
-                };
+            Msg::NewEvent(Event::UnvalidatedMessage(message_id, channel, from, data)) => {
+                let Some(messages) = vote_batch::split(data) else {
+                    warn!(%from, %channel, "Received a malformed batch of votes, rejecting");
+
+                    ctrl_handle
+                        .validate_message(message_id, MessageAcceptance::Reject)
+                        .await?;
 
-                if from != status.peer_id {
-                    error!(%from, %status.peer_id, "Mismatched peer ID in status message");
                     return Ok(());
-                }
+                };
 
-                trace!(%from, tip_height = %status.tip_height, "Received status");
+                // Only the messages which can be decoded are forwarded to the other peers,
+                // the others are dropped and their sender penalized, as are the proposal parts
+                // taking their stream over the maximum value size. A batch of votes is only
+                // forwarded if all its votes can be decoded.
+                let mut acceptance = MessageAcceptance::Accept;
+                let mut events = Vec::with_capacity(messages.len());
 
-                output_port.send(NetworkEvent::Status(
-                    status.peer_id,
-                    Status::new(status.tip_height, status.history_min_height),
-                ));
-            }
+                for data in messages {
+                    let (span, data) = receive_traced(channel, from, data);
+                    let size = data.len();
+
+                    let event =
+                        span.in_scope(|| decode_message(&self.codec, channel, from, data.clone()));
+
+                    // Proposal parts already received are forwarded but not delivered again
+                    let is_new = event
+                        .as_ref()
+                        .is_some_and(|event| keep_proposal_part(proposal_parts, event, data));
+
+                    let value_size = match &event {
+                        Some(event) if is_new => {
+                            check_value_size(stream_sizes, self.max_value_size, event, size)
+                        }
+                        _ => ValueSize::Within,
+                    };
+
+                    match (event, value_size) {
+                        (Some(event), ValueSize::Within) => {
+                            if is_new {
+                                events.push(event);
+                            }
+                        }
+                        (_, ValueSize::Exceeded(stream_id)) => {
+                            acceptance = MessageAcceptance::Reject;
+                            events.push(NetworkEvent::ProposalTooLarge(from, stream_id));
+                        }
+                        _ => acceptance = MessageAcceptance::Reject,
+                    }
+                }
 
-            Msg::NewEvent(Event::ConsensusMessage(channel, from, _)) => {
-                error!(%from, "Unexpected consensus message on {channel} channel");
-                return Ok(());
+                ctrl_handle.validate_message(message_id, acceptance).await?;
+
+                for event in events {
+                    output_port.send(event);
+                }
             }
 
             Msg::NewEvent(Event::ValidatorProofReceived {
@@ -511,15 +1272,21 @@ where
                     peer,
                     body,
                 } => {
+                    let (context, body) = trace_context::unwrap(body);
+                    let span = continue_trace(
+                        context,
+                        || info_span!(parent: None, "sync_request_received", %peer),
+                    );
+
                     let request = match self.codec.decode(body) {
                         Ok(request) => request,
                         Err(e) => {
-                            error!(%peer, "Failed to decode sync request: {e:?}");
+                            span.in_scope(|| error!(%peer, "Failed to decode sync request: {e:?}"));
                             return Ok(());
                         }
                     };
 
-                    inbound_requests.insert(InboundRequestId::new(request_id), request_id);
+                    inbound_requests.insert(InboundRequestId::new(request_id), (request_id, span));
 
                     output_port.send(NetworkEvent::SyncRequest(
                         InboundRequestId::new(request_id),
@@ -533,10 +1300,18 @@ where
                     peer,
                     body,
                 } => {
+                    let (context, body) = trace_context::unwrap(body);
+                    let span = continue_trace(
+                        context,
+                        || info_span!(parent: None, "sync_response_received", %peer),
+                    );
+
                     let response = match self.codec.decode(body) {
                         Ok(response) => Some(response),
                         Err(e) => {
-                            error!(%peer, "Failed to decode sync response: {e:?}");
+                            span.in_scope(
+                                || error!(%peer, "Failed to decode sync response: {e:?}"),
+                            );
                             None
                         }
                     };
@@ -579,10 +1354,154 @@ where
                     .await?;
             }
 
+            Msg::UpdateValidatorProof(proof) => match self.codec.encode(&proof) {
+                Ok(proof_bytes) => ctrl_handle.update_validator_proof(proof_bytes).await?,
+                Err(e) => error!("Failed to encode validator proof: {e:?}"),
+            },
+
+            Msg::UpdateValidatorPeers(peers) => {
+                info!("Updating validator peers: {} peers", peers.len());
+                ctrl_handle.update_validator_peers(peers).await?;
+            }
+
+            Msg::SetRetainedHeight(height) => {
+                ctrl_handle.set_retained_height(height).await?;
+            }
+
+            Msg::NodeStarted => {
+                ctrl_handle.node_started().await?;
+            }
+
+            Msg::StartedHeight(height) => {
+                stream_sizes.set_height(height);
+            }
+
+            Msg::ReportPeer(peer_id, score_delta) => {
+                ctrl_handle.report_peer(peer_id, score_delta).await?;
+            }
+
+            Msg::BanPeer(peer_id, duration) => {
+                ctrl_handle.ban_peer(peer_id, duration).await?;
+            }
+
+            Msg::UnbanPeer(peer_id) => {
+                ctrl_handle.unban_peer(peer_id).await?;
+            }
+
+            Msg::SetLinkConditions(peer_id, conditions) => {
+                ctrl_handle.set_link_conditions(peer_id, conditions).await?;
+            }
+
+            Msg::AddListenAddr(addr) => {
+                ctrl_handle.add_listen_addr(addr).await?;
+            }
+
+            Msg::RemoveListenAddr(addr) => {
+                ctrl_handle.remove_listen_addr(addr).await?;
+            }
+
+            Msg::SubscribePeerMessages(tx_peer_message) => {
+                *peer_messages = Some(tx_peer_message);
+            }
+
+            Msg::SubscribeMempool(tx_mempool) => {
+                *mempool = Some(tx_mempool);
+            }
+
+            Msg::NewEvent(Event::MempoolMessage(peer_id, payload)) => {
+                let forwarded = match mempool {
+                    Some(tx_mempool) => tx_mempool
+                        .try_send(MempoolMessage { peer_id, payload })
+                        .is_ok(),
+                    None => false,
+                };
+
+                if !forwarded {
+                    debug!(%peer_id, "Dropping transactions from peer, no room for them in the application");
+                }
+            }
+
+            Msg::SubscribeConnectivity(tx_connectivity) => {
+                let current = peers
+                    .iter()
+                    .map(|peer_id| ConnectivityEvent::PeerConnected(*peer_id))
+                    .chain(sufficient_peers.clone());
+
+                for event in current {
+                    notify_connectivity(std::slice::from_ref(&tx_connectivity), event);
+                }
+
+                connectivity.retain(|tx| !tx.is_closed());
+                connectivity.push(tx_connectivity);
+            }
+
+            Msg::ReplyToPeer(request_id, payload) => {
+                ctrl_handle.reply_to_peer(request_id, payload).await?;
+            }
+
+            Msg::NewEvent(Event::PeerMessage {
+                request_id,
+                peer_id,
+                payload,
+            }) => {
+                let (reply, rx_reply) = oneshot::channel();
+
+                let message = PeerMessage {
+                    peer_id,
+                    payload,
+                    reply,
+                };
+
+                let forwarded = match peer_messages {
+                    Some(tx_peer_message) => tx_peer_message.try_send(message).is_ok(),
+                    None => false,
+                };
+
+                if !forwarded {
+                    debug!(%peer_id, "Dropping message from peer, no room for it in the application");
+                    ctrl_handle.reply_to_peer(request_id, None).await?;
+                    return Ok(());
+                }
+
+                tokio::spawn(async move {
+                    let payload = rx_reply.await.ok();
+                    if let Err(e) = myself.cast(Msg::ReplyToPeer(request_id, payload)) {
+                        error!(%peer_id, "Failed to reply to peer message: {e:?}");
+                    }
+                });
+            }
+
             Msg::DumpState(_) => unreachable!("DumpState handled above to ensure a reply"),
+            Msg::DumpTopology(_) => unreachable!("DumpTopology handled above to ensure a reply"),
+            Msg::DiscoveredPeers(_) => {
+                unreachable!("DiscoveredPeers handled above to ensure a reply")
+            }
+            Msg::ListPeers(_) => unreachable!("ListPeers handled above to ensure a reply"),
+            Msg::ExportAddressBook(_) => {
+                unreachable!("ExportAddressBook handled above to ensure a reply")
+            }
+            Msg::ImportAddressBook(_, _) => {
+                unreachable!("ImportAddressBook handled above to ensure a reply")
+            }
+            Msg::DisconnectPeer(_, _) => {
+                unreachable!("DisconnectPeer handled above to ensure a reply")
+            }
+            Msg::DialPeer(_, _) => unreachable!("DialPeer handled above to ensure a reply"),
             Msg::UpdatePersistentPeers(_, _) => {
                 unreachable!("UpdatePersistentPeers handled above to ensure a reply")
             }
+            Msg::UpdateConfig(_, _) => {
+                unreachable!("UpdateConfig handled above to ensure a reply")
+            }
+            Msg::NodeKeyStatus(_) => {
+                unreachable!("NodeKeyStatus handled above to ensure a reply")
+            }
+            Msg::RotateNodeKey(_) => {
+                unreachable!("RotateNodeKey handled above to ensure a reply")
+            }
+            Msg::SendToPeer(_, _, _) => {
+                unreachable!("SendToPeer handled above to ensure a reply")
+            }
         }
 
         Ok(())
@@ -609,6 +1528,260 @@ where
     }
 }
 
+/// Account for the size of a proposal part, checking that it does not take
+/// its stream over the maximum value size, unless `max_value_size` is `0`.
+///
+/// This bounds the memory taken by the parts of a value before they are reassembled,
+/// which a malicious proposer could otherwise exhaust with a never-ending stream.
+fn check_value_size<Ctx: Context>(
+    stream_sizes: &mut StreamSizes,
+    max_value_size: usize,
+    event: &NetworkEvent<Ctx>,
+    size: usize,
+) -> ValueSize {
+    let NetworkEvent::ProposalPart(from, part) = event else {
+        return ValueSize::Within;
+    };
+
+    if max_value_size == 0 {
+        return ValueSize::Within;
+    }
+
+    let key = (*from, part.stream_id.clone());
+
+    if stream_sizes.is_exceeded(&key) {
+        return ValueSize::AlreadyExceeded;
+    }
+
+    let total = stream_sizes.add(key.clone(), size);
+
+    if total > max_value_size {
+        warn!(
+            %from,
+            stream_id = %part.stream_id,
+            size = %total,
+            %max_value_size,
+            "Proposal stream exceeds the maximum value size, dropping it"
+        );
+
+        stream_sizes.exceed(key);
+        return ValueSize::Exceeded(part.stream_id.clone());
+    }
+
+    if part.is_fin() {
+        stream_sizes.remove(&key);
+    }
+
+    ValueSize::Within
+}
+
+/// Keep a proposal part received from a peer to serve it to the other peers,
+/// returning `false` if it was already received by gossip
+fn keep_proposal_part<Ctx: Context>(
+    proposal_parts: &mut ProposalPartsCache,
+    event: &NetworkEvent<Ctx>,
+    data: Bytes,
+) -> bool {
+    let NetworkEvent::ProposalPart(from, part) = event else {
+        return true;
+    };
+
+    proposal_parts.insert((*from, part.stream_id.clone()), part.sequence, data)
+}
+
+/// Publish the votes waiting to be published together, if any, as a single message
+async fn publish_vote_batch(
+    vote_batch: &mut VoteBatch,
+    ctrl_handle: &CtrlHandle,
+) -> Result<(), ActorProcessingErr> {
+    if let Some(data) = vote_batch.take() {
+        ctrl_handle.publish(Channel::Consensus, data).await?;
+    }
+
+    Ok(())
+}
+
+/// Priority of an event of the network service in the mailbox of the actor,
+/// the liveness messages and the transactions being shed first.
+///
+/// The proposal parts and the messages of the sync channel, ie. the status of the peers,
+/// are never shed, see [`NetworkEvent::priority`].
+fn event_priority(event: &Event) -> Priority {
+    match event {
+        Event::LivenessMessage(..) | Event::MempoolMessage(..) => Priority::Low,
+
+        Event::ConsensusMessage(Channel::ProposalParts | Channel::Sync, ..)
+        | Event::UnvalidatedMessage(_, Channel::ProposalParts | Channel::Sync, ..) => {
+            Priority::High
+        }
+
+        Event::ConsensusMessage(..)
+        | Event::UnvalidatedMessage(..)
+        | Event::Sync(_)
+        | Event::PeerMessage { .. }
+        | Event::ValidatorProofReceived { .. } => Priority::Normal,
+
+        Event::Listening(_)
+        | Event::PeerConnected(_)
+        | Event::PeerDisconnected(_)
+        | Event::BootstrapProgress(_)
+        | Event::SufficientPeers { .. }
+        | Event::InsufficientPeers { .. }
+        | Event::HolePunch(_)
+        | Event::RelayElection(_) => Priority::High,
+    }
+}
+
+/// Prepend the trace context of the span a message is sent in, if the traces are exported
+fn with_trace_context(span: &Span, data: Bytes) -> Bytes {
+    trace_context::wrap(TraceContext::of(span).as_ref(), data)
+}
+
+/// Strip the trace context from a message received on a pubsub channel, if it carries one,
+/// returning the span continuing the trace of the peer in which the message is processed
+fn receive_traced(channel: Channel, from: PeerId, data: Bytes) -> (Span, Bytes) {
+    // Evidence and status messages never carry a trace context
+    if !matches!(
+        channel,
+        Channel::Consensus | Channel::ProposalParts | Channel::Liveness
+    ) {
+        return (Span::none(), data);
+    }
+
+    let (context, data) = trace_context::unwrap(data);
+    let span = continue_trace(
+        context,
+        || info_span!(parent: None, "receive", %channel, peer = %from),
+    );
+
+    (span, data)
+}
+
+/// Continue the trace of a peer in the given span, if the message received from the peer
+/// carried a trace context, or in no span otherwise
+fn continue_trace(context: Option<TraceContext>, span: impl FnOnce() -> Span) -> Span {
+    let Some(context) = context else {
+        return Span::none();
+    };
+
+    let span = span();
+    context.set_as_parent_of(&span);
+    span
+}
+
+/// Decode a message received on a pubsub channel, returns `None` if it is invalid
+fn decode_message<Ctx, Codec>(
+    codec: &Codec,
+    channel: Channel,
+    from: PeerId,
+    data: Bytes,
+) -> Option<NetworkEvent<Ctx>>
+where
+    Ctx: Context,
+    Codec: codec::Codec<SignedConsensusMsg<Ctx>>,
+    Codec: codec::Codec<StreamMessage<Ctx::ProposalPart>>,
+    Codec: codec::Codec<LivenessMsg<Ctx>>,
+    Codec: codec::Codec<sync::Status<Ctx>>,
+{
+    match channel {
+        Channel::Liveness => {
+            let msg = match codec.decode(data) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    error!(%from, "Failed to decode liveness message: {e:?}");
+                    return None;
+                }
+            };
+
+            let event = match msg {
+                LivenessMsg::PolkaCertificate(polka_cert) => {
+                    NetworkEvent::PolkaCertificate(from, polka_cert)
+                }
+                LivenessMsg::SkipRoundCertificate(round_cert) => {
+                    NetworkEvent::RoundCertificate(from, round_cert)
+                }
+                LivenessMsg::Vote(vote) => NetworkEvent::Vote(from, vote),
+            };
+
+            Some(event)
+        }
+
+        Channel::Consensus => {
+            let msg = match codec.decode(data) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    error!(%from, "Failed to decode consensus message: {e:?}");
+                    return None;
+                }
+            };
+
+            let event = match msg {
+                SignedConsensusMsg::Vote(vote) => NetworkEvent::Vote(from, vote),
+                SignedConsensusMsg::Proposal(proposal) => NetworkEvent::Proposal(from, proposal),
+            };
+
+            Some(event)
+        }
+
+        Channel::Evidence => {
+            let evidence = match decode_evidence(codec, data.as_ref()) {
+                Ok(evidence) => evidence,
+                Err(e) => {
+                    error!(%from, "Failed to decode evidence: {e:?}");
+                    return None;
+                }
+            };
+
+            Some(NetworkEvent::Evidence(from, evidence))
+        }
+
+        // Delivered to the application as they are, see `Msg::SubscribeMempool`
+        Channel::Mempool => None,
+
+        Channel::ProposalParts => {
+            let msg: StreamMessage<Ctx::ProposalPart> = match codec.decode(data) {
+                Ok(stream_msg) => stream_msg,
+                Err(e) => {
+                    error!(%from, "Failed to decode stream message: {e:?}");
+                    return None;
+                }
+            };
+
+            trace!(
+                %from,
+                stream_id = %msg.stream_id,
+                sequence = %msg.sequence,
+                "Received proposal part"
+            );
+
+            Some(NetworkEvent::ProposalPart(from, msg))
+        }
+
+        Channel::Sync => {
+            let status: sync::Status<Ctx> = match codec.decode(data) {
+                Ok(status) => status,
+                Err(e) => {
+                    error!(%from, "Failed to decode status message: {e:?}");
+                    return None;
+                }
+            };
+
+            if from != status.peer_id {
+                error!(%from, %status.peer_id, "Mismatched peer ID in status message");
+                return None;
+            }
+
+            trace!(%from, tip_height = %status.tip_height, "Received status");
+
+            Some(NetworkEvent::Status(
+                status.peer_id,
+                Status::new(status.tip_height, status.history_min_height)
+                    .with_min_needed_height(status.min_needed_height),
+            ))
+        }
+    }
+}
+
 async fn handle_dump_state<Ctx>(
     state: &mut State<Ctx>,
     reply_to: RpcReplyPort<Option<NetworkStateDump>>,
@@ -634,6 +1807,226 @@ async fn handle_dump_state<Ctx>(
     }
 }
 
+async fn handle_dump_topology<Ctx>(
+    state: &mut State<Ctx>,
+    reply_to: RpcReplyPort<Option<NetworkTopology>>,
+) where
+    Ctx: Context,
+{
+    let topology = match state {
+        State::Stopped => {
+            info!("Dumping network topology: not started");
+            None
+        }
+        State::Running { ctrl_handle, .. } => match ctrl_handle.dump_topology().await {
+            Ok(topology) => Some(topology),
+            Err(error) => {
+                error!(%error, "Failed to obtain network topology");
+                None
+            }
+        },
+    };
+
+    if let Err(error) = reply_to.send(topology) {
+        error!(%error, "Failed to reply with network topology");
+    }
+}
+
+async fn handle_discovered_peers<Ctx>(
+    state: &mut State<Ctx>,
+    reply_to: RpcReplyPort<Option<Vec<DiscoveredPeer>>>,
+) where
+    Ctx: Context,
+{
+    let peers = match state {
+        State::Stopped => {
+            info!("Querying discovered peers: network not started");
+            None
+        }
+        State::Running { ctrl_handle, .. } => match ctrl_handle.discovered_peers().await {
+            Ok(peers) => Some(peers),
+            Err(error) => {
+                error!(%error, "Failed to query discovered peers");
+                None
+            }
+        },
+    };
+
+    if let Err(error) = reply_to.send(peers) {
+        error!(%error, "Failed to reply with discovered peers");
+    }
+}
+
+async fn handle_list_peers<Ctx>(
+    state: &mut State<Ctx>,
+    reply_to: RpcReplyPort<Option<Vec<ConnectedPeer>>>,
+) where
+    Ctx: Context,
+{
+    let peers = match state {
+        State::Stopped => {
+            info!("Listing peers: network not started");
+            None
+        }
+        State::Running { ctrl_handle, .. } => match ctrl_handle.list_peers().await {
+            Ok(peers) => Some(peers),
+            Err(error) => {
+                error!(%error, "Failed to list peers");
+                None
+            }
+        },
+    };
+
+    if let Err(error) = reply_to.send(peers) {
+        error!(%error, "Failed to reply with connected peers");
+    }
+}
+
+async fn handle_export_address_book<Ctx>(
+    state: &mut State<Ctx>,
+    reply_to: RpcReplyPort<Option<AddressBook>>,
+) where
+    Ctx: Context,
+{
+    let book = match state {
+        State::Stopped => {
+            info!("Exporting address book: network not started");
+            None
+        }
+        State::Running { ctrl_handle, .. } => match ctrl_handle.export_address_book().await {
+            Ok(book) => Some(book),
+            Err(error) => {
+                error!(%error, "Failed to export address book");
+                None
+            }
+        },
+    };
+
+    if let Err(error) = reply_to.send(book) {
+        error!(%error, "Failed to reply with address book");
+    }
+}
+
+async fn handle_import_address_book<Ctx>(
+    state: &mut State<Ctx>,
+    book: AddressBook,
+    reply_to: RpcReplyPort<Option<usize>>,
+) where
+    Ctx: Context,
+{
+    let dialed = match state {
+        State::Stopped => {
+            info!("Importing address book: network not started");
+            None
+        }
+        State::Running { ctrl_handle, .. } => match ctrl_handle.import_address_book(book).await {
+            Ok(dialed) => Some(dialed),
+            Err(error) => {
+                error!(%error, "Failed to import address book");
+                None
+            }
+        },
+    };
+
+    if let Err(error) = reply_to.send(dialed) {
+        error!(%error, "Failed to reply to ImportAddressBook");
+    }
+}
+
+async fn handle_disconnect_peer<Ctx>(
+    state: &mut State<Ctx>,
+    peer_id: PeerId,
+    reply_to: RpcReplyPort<Result<(), PeerConnectionError>>,
+) where
+    Ctx: Context,
+{
+    let result = match state {
+        State::Stopped => Err(PeerConnectionError::NetworkStopped),
+        State::Running { ctrl_handle, .. } => ctrl_handle
+            .disconnect_peer(peer_id)
+            .await
+            .unwrap_or_else(|error| {
+                error!(%error, %peer_id, "Internal error: failed to disconnect peer");
+                Err(PeerConnectionError::InternalError(error.to_string()))
+            }),
+    };
+
+    if let Err(error) = reply_to.send(result) {
+        error!(%error, "Failed to reply to DisconnectPeer");
+    }
+}
+
+async fn handle_dial_peer<Ctx>(
+    state: &mut State<Ctx>,
+    addr: Multiaddr,
+    reply_to: RpcReplyPort<Result<(), PeerConnectionError>>,
+) where
+    Ctx: Context,
+{
+    let result = match state {
+        State::Stopped => Err(PeerConnectionError::NetworkStopped),
+        State::Running { ctrl_handle, .. } => ctrl_handle
+            .dial_peer(addr.clone())
+            .await
+            .unwrap_or_else(|error| {
+                error!(%error, %addr, "Internal error: failed to dial peer");
+                Err(PeerConnectionError::InternalError(error.to_string()))
+            }),
+    };
+
+    if let Err(error) = reply_to.send(result) {
+        error!(%error, "Failed to reply to DialPeer");
+    }
+}
+
+async fn handle_send_to_peer<Ctx>(
+    state: &mut State<Ctx>,
+    peer_id: PeerId,
+    payload: Bytes,
+    reply_to: RpcReplyPort<Result<Bytes, PeerMessageError>>,
+) where
+    Ctx: Context,
+{
+    let rx_reply = match state {
+        State::Stopped => None,
+        State::Running { ctrl_handle, .. } => {
+            match ctrl_handle.send_to_peer(peer_id, payload).await {
+                Ok(rx_reply) => Some(rx_reply),
+                Err(error) => {
+                    error!(%error, %peer_id, "Failed to send message to peer");
+                    None
+                }
+            }
+        }
+    };
+
+    let Some(rx_reply) = rx_reply else {
+        let _ = reply_to.send(Err(PeerMessageError::NetworkStopped));
+        return;
+    };
+
+    // Do not block the actor while waiting for the reply of the peer
+    tokio::spawn(async move {
+        let result = rx_reply
+            .await
+            .unwrap_or(Err(PeerMessageError::NetworkStopped));
+
+        if let Err(error) = reply_to.send(result) {
+            error!(%error, %peer_id, "Failed to reply with the reply of the peer");
+        }
+    });
+}
+
+/// Forward a change in the connectivity of the node to the subscribers,
+/// dropping it for those lagging behind
+fn notify_connectivity(connectivity: &[mpsc::Sender<ConnectivityEvent>], event: ConnectivityEvent) {
+    for tx_connectivity in connectivity {
+        if let Err(e) = tx_connectivity.try_send(event.clone()) {
+            debug!("Dropping connectivity event, no room for it in the subscriber: {e}");
+        }
+    }
+}
+
 async fn handle_update_persistent_peers<Ctx>(
     state: &mut State<Ctx>,
     op: PersistentPeersOp,
@@ -683,3 +2076,303 @@ async fn handle_update_persistent_peers<Ctx>(
         error!(%error, "Failed to reply to UpdatePersistentPeers");
     }
 }
+
+async fn handle_node_key_status<Ctx>(
+    state: &mut State<Ctx>,
+    reply_to: RpcReplyPort<Option<NodeKeyStatus>>,
+) where
+    Ctx: Context,
+{
+    let status = match state {
+        State::Stopped => {
+            info!("Querying node key status: network not started");
+            None
+        }
+        State::Running { ctrl_handle, .. } => match ctrl_handle.node_key_status().await {
+            Ok(status) => Some(status),
+            Err(error) => {
+                error!(%error, "Failed to obtain node key status");
+                None
+            }
+        },
+    };
+
+    if let Err(error) = reply_to.send(status) {
+        error!(%error, "Failed to reply with node key status");
+    }
+}
+
+async fn handle_rotate_node_key<Ctx>(
+    state: &mut State<Ctx>,
+    reply_to: RpcReplyPort<Result<NodeKeyRotation, NodeKeyError>>,
+) where
+    Ctx: Context,
+{
+    let result = match state {
+        State::Stopped => {
+            warn!("Cannot rotate the node key: network not started");
+            Err(NodeKeyError::NetworkStopped)
+        }
+        State::Running { ctrl_handle, .. } => {
+            ctrl_handle.rotate_node_key().await.unwrap_or_else(|error| {
+                error!(%error, "Internal error: failed to rotate the node key");
+                Err(NodeKeyError::InternalError(error.to_string()))
+            })
+        }
+    };
+
+    if let Err(error) = reply_to.send(result) {
+        error!(%error, "Failed to reply to RotateNodeKey");
+    }
+}
+
+async fn handle_update_config<Ctx>(
+    state: &mut State<Ctx>,
+    update: ConfigUpdate,
+    reply_to: RpcReplyPort<Result<ConfigUpdateOutcome, ConfigUpdateError>>,
+) where
+    Ctx: Context,
+{
+    let result = match state {
+        State::Stopped => {
+            warn!("Cannot update network configuration: network not started");
+            Err(ConfigUpdateError::NetworkStopped)
+        }
+        State::Running { ctrl_handle, .. } => ctrl_handle
+            .update_config(update)
+            .await
+            .unwrap_or_else(|error| {
+                error!(%error, "Internal error: failed to update network configuration");
+                Err(ConfigUpdateError::InternalError(error.to_string()))
+            }),
+    };
+
+    if let Err(error) = reply_to.send(result) {
+        error!(%error, "Failed to reply to UpdateConfig");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::streaming::StreamContent;
+    use malachitebft_test::{ProposalData, ProposalPart, TestContext};
+
+    fn stream(id: u8) -> (PeerId, StreamId) {
+        (PeerId::random(), StreamId::new(Bytes::from(vec![id])))
+    }
+
+    #[test]
+    fn fetched_parts_are_not_served() {
+        let mut cache = ProposalPartsCache::default();
+        let key = stream(1);
+
+        assert!(cache.insert(key.clone(), 0, Bytes::from_static(b"gossiped")));
+        assert!(cache.insert_fetched(key.clone(), 1));
+
+        assert_eq!(
+            cache.get(&key, &[0, 1]),
+            vec![Bytes::from_static(b"gossiped")]
+        );
+    }
+
+    #[test]
+    fn fetched_parts_are_delivered_once() {
+        let mut cache = ProposalPartsCache::default();
+        let key = stream(1);
+
+        // A part already received by gossip is not delivered again when fetched
+        assert!(cache.insert(key.clone(), 0, Bytes::new()));
+        assert!(!cache.insert_fetched(key.clone(), 0));
+
+        assert!(cache.insert_fetched(key.clone(), 1));
+        assert!(!cache.insert_fetched(key, 1));
+    }
+
+    #[test]
+    fn fetched_parts_do_not_shadow_gossiped_ones() {
+        let mut cache = ProposalPartsCache::default();
+        let key = stream(1);
+
+        assert!(cache.insert_fetched(key.clone(), 0));
+        assert!(cache.insert(key.clone(), 0, Bytes::from_static(b"gossiped")));
+
+        assert_eq!(cache.get(&key, &[0]), vec![Bytes::from_static(b"gossiped")]);
+    }
+
+    fn proposal_part(key: &(PeerId, StreamId), sequence: u64) -> NetworkEvent<TestContext> {
+        let content = StreamContent::Data(ProposalPart::Data(ProposalData::new(sequence)));
+        let part = StreamMessage::new(key.1.clone(), sequence, content);
+        NetworkEvent::ProposalPart(key.0, part)
+    }
+
+    fn fin(key: &(PeerId, StreamId), sequence: u64) -> NetworkEvent<TestContext> {
+        let part = StreamMessage::new(key.1.clone(), sequence, StreamContent::Fin);
+        NetworkEvent::ProposalPart(key.0, part)
+    }
+
+    #[test]
+    fn streams_over_the_max_value_size_are_rejected() {
+        let mut sizes = StreamSizes::default();
+        let key = stream(1);
+
+        let check = |sizes: &mut StreamSizes, event: NetworkEvent<TestContext>, size| {
+            check_value_size(sizes, 100, &event, size)
+        };
+
+        assert!(matches!(
+            check(&mut sizes, proposal_part(&key, 0), 60),
+            ValueSize::Within
+        ));
+        assert!(matches!(
+            check(&mut sizes, proposal_part(&key, 1), 60),
+            ValueSize::Exceeded(stream_id) if stream_id == key.1
+        ));
+        assert!(matches!(
+            check(&mut sizes, proposal_part(&key, 2), 1),
+            ValueSize::AlreadyExceeded
+        ));
+        assert!(matches!(
+            check(&mut sizes, fin(&key, 3), 1),
+            ValueSize::AlreadyExceeded
+        ));
+
+        // The streams within the maximum value size are forgotten once complete
+        let key = stream(2);
+        assert!(matches!(
+            check(&mut sizes, proposal_part(&key, 0), 100),
+            ValueSize::Within
+        ));
+        assert!(matches!(
+            check(&mut sizes, fin(&key, 1), 0),
+            ValueSize::Within
+        ));
+        assert!(!sizes.sizes.contains_key(&key));
+
+        // Any size is accepted without a maximum value size
+        assert!(matches!(
+            check_value_size(&mut sizes, 0, &proposal_part(&stream(3), 0), usize::MAX),
+            ValueSize::Within
+        ));
+    }
+
+    #[test]
+    fn streams_over_the_max_value_size_are_kept_until_their_height_is_past() {
+        let mut sizes = StreamSizes::default();
+        sizes.set_height(5);
+
+        let key = stream(1);
+        assert!(matches!(
+            check_value_size(&mut sizes, 100, &proposal_part(&key, 0), 101),
+            ValueSize::Exceeded(_)
+        ));
+
+        // Tracking many other streams does not reset the size of the stream
+        for id in 0..MAX_TRACKED_STREAMS {
+            sizes.add(
+                (PeerId::random(), StreamId::new(Bytes::from(id.to_string()))),
+                1,
+            );
+        }
+
+        assert!(sizes.is_exceeded(&key));
+
+        // The stream may be for the next height
+        sizes.set_height(6);
+        assert!(sizes.is_exceeded(&key));
+
+        sizes.set_height(7);
+        assert!(!sizes.is_exceeded(&key));
+    }
+
+    #[test]
+    fn peers_only_evict_their_own_streams() {
+        let mut sizes = StreamSizes::default();
+        let other = stream(0);
+        sizes.add(other.clone(), 10);
+
+        let peer = PeerId::random();
+        let key = |id: usize| (peer, StreamId::new(Bytes::from(id.to_string())));
+
+        for id in 0..MAX_TRACKED_STREAMS {
+            sizes.add(key(id), 10);
+        }
+
+        assert_eq!(sizes.order.len(), MAX_TRACKED_STREAMS_PER_PEER + 1);
+        assert_eq!(sizes.sizes.get(&other), Some(&10));
+        assert!(!sizes.sizes.contains_key(&key(0)));
+        assert_eq!(sizes.sizes.get(&key(MAX_TRACKED_STREAMS - 1)), Some(&10));
+
+        // Beyond the limit of the streams of all the peers, the oldest one is forgotten
+        for _ in 0..MAX_TRACKED_STREAMS {
+            sizes.add(stream(1), 10);
+        }
+
+        assert_eq!(sizes.order.len(), MAX_TRACKED_STREAMS);
+        assert!(!sizes.sizes.contains_key(&other));
+    }
+
+    #[test]
+    fn oversized_value_penalty_graylists_all_but_validators() {
+        use malachitebft_network::peer_scoring::{
+            FULL_NODE_SCORE, PERSISTENT_PEER_SCORE, VALIDATOR_SCORE,
+        };
+        use malachitebft_network::PeerScoreConfig;
+
+        let config = PeerScoreConfig::default();
+        let score = |base: f64| (base + OVERSIZED_VALUE_PENALTY) * config.app_specific_weight;
+
+        assert!(score(FULL_NODE_SCORE) < config.graylist_threshold);
+        assert!(score(PERSISTENT_PEER_SCORE) > config.gossip_threshold);
+        assert!(score(VALIDATOR_SCORE) > config.gossip_threshold);
+    }
+
+    #[test]
+    fn oldest_streams_are_evicted() {
+        let mut cache = ProposalPartsCache::default();
+        let oldest = stream(0);
+
+        assert!(cache.insert_fetched(oldest.clone(), 0));
+
+        for id in 1..=MAX_CACHED_STREAMS as u8 {
+            cache.insert(stream(id), 0, Bytes::new());
+        }
+
+        assert!(cache.insert_fetched(oldest, 0));
+        assert_eq!(cache.order.len(), MAX_CACHED_STREAMS);
+    }
+
+    #[test]
+    fn status_and_proposal_parts_are_never_shed() {
+        use malachitebft_test::Height;
+
+        let peer_id = PeerId::random();
+        let status = sync::Status {
+            peer_id,
+            tip_height: Height::new(1),
+            history_min_height: Height::new(1),
+            min_needed_height: None,
+        };
+
+        assert_eq!(
+            NetworkEvent::<TestContext>::Status(peer_id, status).priority(Some(5)),
+            Priority::High
+        );
+        assert_eq!(
+            proposal_part(&stream(1), 0).priority(Some(5)),
+            Priority::High
+        );
+
+        let message = |channel| Event::ConsensusMessage(channel, peer_id, Bytes::new());
+
+        assert_eq!(event_priority(&message(Channel::Sync)), Priority::High);
+        assert_eq!(
+            event_priority(&message(Channel::ProposalParts)),
+            Priority::High
+        );
+        assert_eq!(
+            event_priority(&message(Channel::Consensus)),
+            Priority::Normal
+        );
+    }
+}
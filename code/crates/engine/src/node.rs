@@ -1,5 +1,7 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
-use ractor::{Actor, ActorProcessingErr, ActorRef, SupervisionEvent};
+use ractor::{Actor, ActorCell, ActorProcessingErr, ActorRef, RpcReplyPort, SupervisionEvent};
 use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
@@ -9,9 +11,19 @@ use crate::consensus::ConsensusRef;
 use crate::host::HostRef;
 use crate::network::NetworkRef;
 use crate::sync::SyncRef;
-use crate::wal::WalRef;
+use crate::wal::{Msg as WalMsg, WalRef};
+
+pub type NodeRef = ActorRef<Msg>;
+
+/// Maximum time to wait for an actor to process the messages already sent to it
+/// when shutting down, after which it is stopped right away
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
 
-pub type NodeRef = ActorRef<()>;
+pub enum Msg {
+    /// Gracefully stop the actors of the node, replying once they have all stopped.
+    /// The node actor itself is left running, for the caller to stop it.
+    Shutdown(RpcReplyPort<()>),
+}
 
 #[allow(dead_code)]
 pub struct Node<Ctx: Context> {
@@ -49,9 +61,47 @@ where
         }
     }
 
-    pub async fn spawn(self) -> Result<(ActorRef<()>, JoinHandle<()>), ractor::SpawnErr> {
+    pub async fn spawn(self) -> Result<(NodeRef, JoinHandle<()>), ractor::SpawnErr> {
         Actor::spawn(None, self, ()).await
     }
+
+    /// Stop the actors one after the other, each after it processed the messages
+    /// already sent to it:
+    /// - consensus first, so that no new height is started
+    /// - then sync and the host
+    /// - then the WAL, once flushed and synced to disk
+    /// - and finally the network, closing the connections to the peers
+    async fn shutdown(&self) {
+        info!("Shutting down");
+
+        drain(self.consensus.get_cell(), "consensus").await;
+
+        if let Some(sync) = &self.sync {
+            drain(sync.get_cell(), "sync").await;
+        }
+
+        drain(self.host.get_cell(), "host").await;
+
+        match ractor::call!(self.wal, WalMsg::Flush) {
+            Ok(Ok(())) => (),
+            Ok(Err(e)) => error!("Failed to flush the WAL: {e}"),
+            Err(e) => error!("Failed to send flush request to the WAL: {e}"),
+        }
+
+        drain(self.wal.get_cell(), "WAL").await;
+        drain(self.network.get_cell(), "network").await;
+
+        info!("Shut down");
+    }
+}
+
+/// Stop an actor once it processed the messages already sent to it,
+/// or right away if it does not manage to within [`DRAIN_TIMEOUT`]
+async fn drain(actor: ActorCell, name: &str) {
+    if let Err(e) = actor.drain_and_wait(Some(DRAIN_TIMEOUT)).await {
+        warn!("Failed to drain the {name} actor, stopping it: {e}");
+        actor.stop(Some("shutdown".to_string()));
+    }
 }
 
 #[async_trait]
@@ -59,7 +109,7 @@ impl<Ctx> Actor for Node<Ctx>
 where
     Ctx: Context,
 {
-    type Msg = ();
+    type Msg = Msg;
     type State = ();
     type Arguments = ();
 
@@ -85,9 +135,19 @@ where
     async fn handle(
         &self,
         _myself: ActorRef<Self::Msg>,
-        _msg: Self::Msg,
+        msg: Self::Msg,
         _state: &mut (),
     ) -> Result<(), ActorProcessingErr> {
+        match msg {
+            Msg::Shutdown(reply_to) => {
+                self.shutdown().await;
+
+                if let Err(e) = reply_to.send(()) {
+                    error!("Failed to reply to shutdown request: {e}");
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
@@ -8,7 +10,7 @@ use bytes::Bytes;
 use bytesize::ByteSize;
 use derive_where::derive_where;
 use eyre::eyre;
-use ractor::{Actor, ActorProcessingErr, ActorRef};
+use ractor::{Actor, ActorProcessingErr, ActorRef, RpcReplyPort};
 use rand::SeedableRng;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn, Instrument};
@@ -18,18 +20,27 @@ use malachitebft_core_consensus::util::bounded_queue::BoundedQueue;
 use malachitebft_core_consensus::PeerId;
 use malachitebft_core_types::utils::height::DisplayRange;
 use malachitebft_core_types::ValueResponse as CoreValueResponse;
-use malachitebft_core_types::{CommitCertificate, Context};
+use malachitebft_core_types::{CommitCertificate, Context, Height, Round};
 use malachitebft_sync::{
-    self as sync, HeightStartType, InboundRequestId, OutboundRequestId, RawDecidedValue, Request,
-    Response, Resumable,
+    self as sync, ConfigUpdate, HeightStartType, InboundRequestId, OutboundRequestId,
+    ProposalPartsRequest, ProposalPartsResponse, RawDecidedValue, Request, Response, Resumable,
+    Snapshot, SnapshotRequest, SnapshotResponse, ValueRequest, VoteSetRequest, VoteSetResponse,
 };
 
 use crate::consensus::{ConsensusMsg, ConsensusRef};
-use crate::host::{HostMsg, HostRef};
+use crate::host::{HeightParams, HostMsg, HostRef};
 use crate::network::{NetworkEvent, NetworkMsg, NetworkRef, Status};
+use crate::util::mailbox::{Mailbox, MailboxSubscriber};
+use crate::util::streaming::{StreamId, StreamMessage};
 use crate::util::ticker::ticker;
 use crate::util::timers::{TimeoutElapsed, TimerScheduler};
 
+pub mod state_dump;
+use state_dump::SyncStateDump;
+
+pub mod status;
+use status::SyncStatus;
+
 /// Codec for sync protocol messages
 ///
 /// This trait is automatically implemented for any type that implements:
@@ -59,8 +70,17 @@ where
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Timeout {
     Request(OutboundRequestId),
+
+    /// Request the missing parts of the stream of the given proposer
+    ProposalParts(PeerId, StreamId),
 }
 
+/// Maximum number of peers the missing parts of a proposal stream are requested from
+const MAX_PROPOSAL_PARTS_ATTEMPTS: usize = 3;
+
+/// Maximum number of proposal streams of the current height whose parts are tracked
+const MAX_PROPOSAL_STREAMS: usize = 16;
+
 type Timers = TimerScheduler<Timeout>;
 
 pub type SyncRef<Ctx> = ActorRef<Msg<Ctx>>;
@@ -98,10 +118,13 @@ pub enum Msg<Ctx: Context> {
     /// The second argument indicates whether this is a restart or not.
     StartedHeight(Ctx::Height, HeightStartType),
 
+    /// Consensus has started a new round of the current height
+    StartedRound(Ctx::Height, Round),
+
     /// Host has a response for the blocks request
     GotDecidedValues(
         InboundRequestId,
-        RangeInclusive<Ctx::Height>,
+        ValueRequest<Ctx>,
         Vec<RawDecidedValue<Ctx>>,
     ),
 
@@ -113,6 +136,59 @@ pub enum Msg<Ctx: Context> {
 
     /// An error occurred while processing a value
     ValueProcessingError(PeerId, Ctx::Height),
+
+    /// Request to dump the current sync state.
+    ///
+    /// The reply port is shared so that messages can be cloned when published on an output port.
+    DumpState(Arc<RpcReplyPort<SyncStateDump<Ctx>>>),
+
+    /// Request the lowest height still needed by any of the connected peers,
+    /// below which the decided values can be pruned without cutting off a syncing peer.
+    GetLowWatermark(Arc<RpcReplyPort<Option<Ctx::Height>>>),
+
+    /// Stop sending requests for values, eg. during maintenance of the node.
+    /// The values requested from the peers are still served.
+    Pause,
+
+    /// Resume sending requests for values
+    Resume,
+
+    /// Request a summary of the progress of sync.
+    ///
+    /// The reply port is shared so that messages can be cloned when published on an output port.
+    GetStatus(Arc<RpcReplyPort<SyncStatus<Ctx>>>),
+
+    /// Host has a response for a snapshot request from a peer
+    GotSnapshotResponse(InboundRequestId, SnapshotResponse<Ctx>),
+
+    /// Network has the proposal parts requested by a peer
+    GotProposalParts(InboundRequestId, ProposalPartsResponse<Ctx>),
+
+    /// Consensus is stuck in the given round of the current height,
+    /// the votes of the round are requested from a peer deciding the same height
+    RequestVoteSet(Ctx::Height, Round),
+
+    /// Consensus has the votes of the round requested by a peer
+    GotVoteSet(InboundRequestId, VoteSetResponse<Ctx>),
+
+    /// Host restored its state from a snapshot, with the parameters of the following height,
+    /// or failed to if `None`
+    SnapshotRestored(Snapshot<Ctx>, Option<HeightParams<Ctx>>),
+
+    /// Fetch the values in the given range once caught up with the peers,
+    /// eg. the ones below the height the node started from
+    Backfill(RangeInclusive<Ctx::Height>),
+
+    /// Host stored the values received in response to a backfill request, or rejected them
+    BackfillStored(OutboundRequestId, bool),
+
+    /// Change some parameters of the sync at runtime, eg. to tune how fast the node catches up
+    UpdateConfig(ConfigUpdate),
+
+    /// The host prunes the decided values below the given height,
+    /// which are then neither served nor advertised to the peers.
+    /// Replied to once the retain height is applied.
+    SetRetainHeight(Ctx::Height, Arc<RpcReplyPort<bool>>),
 }
 
 impl<Ctx: Context> From<NetworkEvent<Ctx>> for Msg<Ctx> {
@@ -137,6 +213,10 @@ pub struct Params {
     /// Timeout duration for sync requests
     /// Default: 10s
     pub request_timeout: Duration,
+
+    /// File the sync progress is saved to after each decision and restored from at startup
+    /// Default: None
+    pub checkpoint_path: Option<PathBuf>,
 }
 
 impl Default for Params {
@@ -144,6 +224,7 @@ impl Default for Params {
         Self {
             status_update_interval: Duration::from_secs(5),
             request_timeout: Duration::from_secs(10),
+            checkpoint_path: None,
         }
     }
 }
@@ -164,6 +245,30 @@ impl<Ctx: Context> BufferedValue<Ctx> {
 /// A queue of buffered sync values for heights ahead of consensus, keyed by height.
 type SyncQueue<Ctx> = BoundedQueue<<Ctx as Context>::Height, BufferedValue<Ctx>>;
 
+/// Parts received so far of a proposal stream of the current height
+#[derive(Default)]
+struct ProposalStream {
+    /// Sequence numbers of the parts received
+    received: BTreeSet<u64>,
+    /// Sequence number of the last part, once received
+    fin: Option<u64>,
+    /// Number of requests sent for the missing parts
+    attempts: usize,
+}
+
+impl ProposalStream {
+    /// Sequence numbers of the parts missing before the last one, once it is received
+    fn missing(&self) -> Vec<u64> {
+        let Some(fin) = self.fin else {
+            return Vec::new();
+        };
+
+        (0..fin)
+            .filter(|sequence| !self.received.contains(sequence))
+            .collect()
+    }
+}
+
 /// The mode for sending status updates
 enum StatusUpdateMode {
     /// Send status updates at regular intervals
@@ -188,6 +293,15 @@ pub struct State<Ctx: Context> {
 
     /// Status update mode
     status_update_mode: StatusUpdateMode,
+
+    /// Timeout of the requests sent, which can be changed at runtime
+    request_timeout: Duration,
+
+    /// Proposal streams of the current height, keyed by proposer and stream
+    proposal_streams: HashMap<(PeerId, StreamId), ProposalStream>,
+
+    /// Latest round started by consensus
+    round: Option<(Ctx::Height, Round)>,
 }
 
 struct HandlerState<'a, Ctx: Context> {
@@ -200,6 +314,10 @@ struct HandlerState<'a, Ctx: Context> {
     sync_queue: &'a mut SyncQueue<Ctx>,
     /// The current consensus height according to the last processed input.
     consensus_height: Ctx::Height,
+    /// Timeout of the requests sent.
+    request_timeout: Duration,
+    /// Height below which the decided values are pruned by the host, if any.
+    retain_height: Option<Ctx::Height>,
 }
 
 #[allow(dead_code)]
@@ -216,6 +334,7 @@ where
     sync_codec: Codec,
     sync_config: sync::Config,
     metrics: sync::Metrics,
+    mailbox: Mailbox,
     span: tracing::Span,
 }
 
@@ -234,6 +353,7 @@ where
         sync_codec: Codec,
         sync_config: sync::Config,
         metrics: sync::Metrics,
+        mailbox: Mailbox,
         span: tracing::Span,
     ) -> Self {
         Self {
@@ -245,6 +365,7 @@ where
             sync_codec,
             sync_config,
             metrics,
+            mailbox,
             span,
         }
     }
@@ -259,6 +380,7 @@ where
         sync_codec: Codec,
         sync_config: sync::Config,
         metrics: sync::Metrics,
+        mailbox: Mailbox,
         span: tracing::Span,
     ) -> Result<SyncRef<Ctx>, ractor::SpawnErr> {
         let actor = Self::new(
@@ -270,6 +392,7 @@ where
             sync_codec,
             sync_config,
             metrics,
+            mailbox,
             span,
         );
         let (actor_ref, _) = Actor::spawn(None, actor, ()).await?;
@@ -287,6 +410,8 @@ where
             inflight: &mut state.inflight,
             sync_queue: &mut state.sync_queue,
             consensus_height: state.sync.consensus_height,
+            request_timeout: state.request_timeout,
+            retain_height: state.sync.retain_height,
         };
 
         malachitebft_sync::process!(
@@ -319,50 +444,47 @@ where
         use sync::Effect;
 
         match effect {
-            Effect::BroadcastStatus(height, r) => {
+            Effect::BroadcastStatus(height, min_needed_height, r) => {
                 let history_min_height = self.get_history_min_height().await?;
 
-                self.network.cast(NetworkMsg::BroadcastStatus(Status::new(
-                    height,
-                    history_min_height,
-                )))?;
+                // The values below the retain height may not be deleted by the host yet
+                let history_min_height = state
+                    .retain_height
+                    .map_or(history_min_height, |retain_height| {
+                        history_min_height.max(retain_height)
+                    });
+
+                self.network.cast(NetworkMsg::BroadcastStatus(
+                    Status::new(height, history_min_height)
+                        .with_min_needed_height(min_needed_height),
+                ))?;
 
                 Ok(r.resume_with(()))
             }
 
             Effect::SendValueRequest(peer_id, value_request, r) => {
-                let request = Request::ValueRequest(value_request);
-                let result = ractor::call!(self.network, |reply_to| {
-                    NetworkMsg::OutgoingRequest(peer_id, request.clone(), reply_to)
-                });
-
-                match result {
-                    Ok(request_id) => {
-                        let request_id = OutboundRequestId::new(request_id);
-
-                        state.timers.start_timer(
-                            Timeout::Request(request_id.clone()),
-                            self.params.request_timeout,
-                        );
+                let trace_id = value_request.trace_id;
+                let request_id = self
+                    .send_request(state, peer_id, Request::ValueRequest(value_request))
+                    .await;
 
-                        state.inflight.insert(
-                            request_id.clone(),
-                            InflightRequest {
-                                peer_id,
-                                request_id: request_id.clone(),
-                                request,
-                            },
-                        );
+                if let Some(request_id) = &request_id {
+                    info!(%peer_id, %request_id, %trace_id, "Sent value request to peer");
+                }
+
+                Ok(r.resume_with(request_id))
+            }
 
-                        info!(%peer_id, %request_id, "Sent value request to peer");
+            Effect::SendSnapshotRequest(peer_id, snapshot_request, r) => {
+                let request_id = self
+                    .send_request(state, peer_id, Request::SnapshotRequest(snapshot_request))
+                    .await;
 
-                        Ok(r.resume_with(Some(request_id)))
-                    }
-                    Err(e) => {
-                        error!("Failed to send request to network layer: {e}");
-                        Ok(r.resume_with(None))
-                    }
+                if let Some(request_id) = &request_id {
+                    debug!(%peer_id, %request_id, "Sent snapshot request to peer");
                 }
+
+                Ok(r.resume_with(request_id))
             }
 
             Effect::SendValueResponse(request_id, value_response, r) => {
@@ -373,14 +495,14 @@ where
                 Ok(r.resume_with(()))
             }
 
-            Effect::GetDecidedValues(request_id, range, r) => {
+            Effect::GetDecidedValues(request_id, request, r) => {
                 self.host.call_and_forward(
                     {
-                        let range = range.clone();
+                        let range = request.range.clone();
                         |reply_to| HostMsg::GetDecidedValues { range, reply_to }
                     },
                     myself,
-                    |values| Msg::<Ctx>::GotDecidedValues(request_id, range, values),
+                    move |values| Msg::<Ctx>::GotDecidedValues(request_id, request, values),
                     None,
                 )?;
 
@@ -391,6 +513,129 @@ where
                 self.process_value_response(state, peer_id, request_id, response);
                 Ok(r.resume_with(()))
             }
+
+            Effect::GetSnapshotData(request_id, request, r) => {
+                match request {
+                    SnapshotRequest::List => self.host.call_and_forward(
+                        |reply_to| HostMsg::GetSnapshots { reply_to },
+                        myself,
+                        move |snapshots| {
+                            Msg::<Ctx>::GotSnapshotResponse(
+                                request_id,
+                                SnapshotResponse::List(snapshots),
+                            )
+                        },
+                        None,
+                    )?,
+
+                    SnapshotRequest::Chunk {
+                        height,
+                        format,
+                        index,
+                    } => self.host.call_and_forward(
+                        |reply_to| HostMsg::GetSnapshotChunk {
+                            height,
+                            format,
+                            index,
+                            reply_to,
+                        },
+                        myself,
+                        move |chunk| {
+                            Msg::<Ctx>::GotSnapshotResponse(
+                                request_id,
+                                SnapshotResponse::Chunk {
+                                    height,
+                                    format,
+                                    index,
+                                    chunk: chunk.unwrap_or_default(),
+                                },
+                            )
+                        },
+                        None,
+                    )?,
+                };
+
+                Ok(r.resume_with(()))
+            }
+
+            Effect::RestoreSnapshot(snapshot, chunks, r) => {
+                self.host.call_and_forward(
+                    {
+                        let snapshot = snapshot.clone();
+                        |reply_to| HostMsg::RestoreSnapshot {
+                            snapshot,
+                            chunks,
+                            reply_to,
+                        }
+                    },
+                    myself,
+                    move |params| Msg::<Ctx>::SnapshotRestored(snapshot, params),
+                    None,
+                )?;
+
+                Ok(r.resume_with(()))
+            }
+
+            Effect::StoreBackfilledValues(request_id, values, r) => {
+                self.host.call_and_forward(
+                    |reply_to| HostMsg::StoreBackfilledValues { values, reply_to },
+                    myself,
+                    move |stored| Msg::<Ctx>::BackfillStored(request_id, stored),
+                    None,
+                )?;
+
+                Ok(r.resume_with(()))
+            }
+        }
+    }
+
+    /// Send a request to a peer, tracking it until it is answered or times out
+    async fn send_request(
+        &self,
+        state: &mut HandlerState<'_, Ctx>,
+        peer_id: PeerId,
+        request: Request<Ctx>,
+    ) -> Option<OutboundRequestId> {
+        let result = ractor::call!(self.network, |reply_to| {
+            NetworkMsg::OutgoingRequest(peer_id, request.clone(), reply_to)
+        });
+
+        match result {
+            Ok(request_id) => {
+                let request_id = OutboundRequestId::new(request_id);
+
+                state
+                    .timers
+                    .start_timer(Timeout::Request(request_id.clone()), state.request_timeout);
+
+                state.inflight.insert(
+                    request_id.clone(),
+                    InflightRequest {
+                        peer_id,
+                        request_id: request_id.clone(),
+                        request,
+                    },
+                );
+
+                Some(request_id)
+            }
+            Err(e) => {
+                error!("Failed to send request to network layer: {e}");
+                None
+            }
+        }
+    }
+
+    fn save_checkpoint(&self, sync: &sync::State<Ctx>) {
+        // Nothing to resume from until consensus has started
+        if !sync.started {
+            return;
+        }
+
+        if let Some(path) = &self.params.checkpoint_path {
+            if let Err(e) = save_checkpoint(path, &sync.checkpoint()) {
+                warn!(path = %path.display(), "Failed to save sync checkpoint: {e}");
+            }
         }
     }
 
@@ -457,6 +702,344 @@ where
         }
     }
 
+    /// Serve the proposal parts requested by a peer which this node received or published,
+    /// if the peer is deciding the same height
+    fn on_proposal_parts_request(
+        &self,
+        myself: &ActorRef<Msg<Ctx>>,
+        state: &State<Ctx>,
+        request_id: InboundRequestId,
+        peer_id: PeerId,
+        request: ProposalPartsRequest<Ctx>,
+    ) -> Result<(), ActorProcessingErr> {
+        let height = request.height;
+
+        // Only the parts of the latest proposals are kept by the network
+        if height != state.sync.consensus_height {
+            debug!(%peer_id, %height, "Ignoring request for proposal parts of another height");
+
+            self.network.cast(NetworkMsg::OutgoingResponse(
+                request_id,
+                Response::ProposalPartsResponse(ProposalPartsResponse::new(height, Vec::new())),
+            ))?;
+
+            return Ok(());
+        }
+
+        debug!(
+            %peer_id, %request_id, %height, round = %request.round,
+            proposer = %request.proposer, sequences = ?request.sequences,
+            "Received request for proposal parts"
+        );
+
+        let max_response_size = self.sync_config.max_response_size;
+
+        self.network.call_and_forward(
+            |reply_to| {
+                NetworkMsg::GetProposalParts(
+                    request.proposer,
+                    StreamId::new(request.stream_id),
+                    request.sequences,
+                    reply_to,
+                )
+            },
+            myself,
+            move |mut parts| {
+                truncate_parts_to_size_limit(&mut parts, max_response_size);
+                Msg::<Ctx>::GotProposalParts(request_id, ProposalPartsResponse::new(height, parts))
+            },
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    /// Deliver the missing proposal parts received from a peer
+    fn on_proposal_parts_response(
+        &self,
+        state: &State<Ctx>,
+        peer_id: PeerId,
+        request: ProposalPartsRequest<Ctx>,
+        response: ProposalPartsResponse<Ctx>,
+    ) -> Result<(), ActorProcessingErr> {
+        if response.height != state.sync.consensus_height || response.parts.is_empty() {
+            debug!(%peer_id, height = %response.height, "Peer did not have the missing proposal parts");
+            return Ok(());
+        }
+
+        if response.parts.len() > request.sequences.len() {
+            warn!(
+                %peer_id, requested = request.sequences.len(), received = response.parts.len(),
+                "Peer sent more proposal parts than requested, ignoring"
+            );
+            return Ok(());
+        }
+
+        debug!(
+            %peer_id, proposer = %request.proposer, parts = response.parts.len(),
+            "Received missing proposal parts"
+        );
+
+        self.network.cast(NetworkMsg::ProcessProposalParts(
+            request.proposer,
+            StreamId::new(request.stream_id),
+            request.sequences,
+            response.parts,
+        ))?;
+
+        Ok(())
+    }
+
+    /// Track the parts received of the proposal streams of the current height, to request
+    /// the missing ones some time after the last part is received
+    fn on_proposal_part(
+        &self,
+        state: &mut State<Ctx>,
+        from: PeerId,
+        part: &StreamMessage<Ctx::ProposalPart>,
+    ) {
+        let Some(delay) = state.sync.config.proposal_parts_delay else {
+            return;
+        };
+
+        let key = (from, part.stream_id.clone());
+
+        if !state.proposal_streams.contains_key(&key)
+            && state.proposal_streams.len() >= MAX_PROPOSAL_STREAMS
+        {
+            return;
+        }
+
+        let stream = state.proposal_streams.entry(key).or_default();
+        stream.received.insert(part.sequence);
+
+        if part.is_fin() {
+            stream.fin = Some(part.sequence);
+        }
+
+        if stream.fin.is_none() {
+            return;
+        }
+
+        let timeout = Timeout::ProposalParts(from, part.stream_id.clone());
+
+        if stream.missing().is_empty() {
+            state.timers.cancel(&timeout);
+        } else if stream.attempts == 0 && !state.timers.is_timer_active(&timeout) {
+            state.timers.start_timer(timeout, delay);
+        }
+    }
+
+    /// Request the parts still missing of a proposal stream, from the proposer first
+    /// and then from the other peers in turn, until they are all received
+    async fn request_proposal_parts(
+        &self,
+        state: &mut State<Ctx>,
+        proposer: PeerId,
+        stream_id: StreamId,
+    ) {
+        let height = state.sync.consensus_height;
+
+        let Some(stream) = state
+            .proposal_streams
+            .get_mut(&(proposer, stream_id.clone()))
+        else {
+            return;
+        };
+
+        let sequences = stream.missing();
+        if sequences.is_empty() {
+            return;
+        }
+
+        if stream.attempts >= MAX_PROPOSAL_PARTS_ATTEMPTS {
+            warn!(
+                %proposer, %stream_id, missing = sequences.len(),
+                "Giving up on fetching the missing proposal parts"
+            );
+            return;
+        }
+
+        let mut peers: Vec<PeerId> = state
+            .sync
+            .peers
+            .keys()
+            .copied()
+            .filter(|peer_id| *peer_id != proposer)
+            .collect();
+
+        if state.sync.peers.contains_key(&proposer) {
+            peers.insert(0, proposer);
+        }
+
+        if peers.is_empty() {
+            debug!(%proposer, %stream_id, "No peer to request the missing proposal parts from");
+            return;
+        }
+
+        let peer_id = peers[stream.attempts % peers.len()];
+        stream.attempts += 1;
+
+        let round = match state.round {
+            Some((round_height, round)) if round_height == height => round,
+            _ => Round::Nil,
+        };
+
+        info!(
+            %peer_id, %proposer, %stream_id, missing = sequences.len(),
+            "Requesting missing proposal parts"
+        );
+
+        let request = ProposalPartsRequest {
+            height,
+            round,
+            proposer,
+            stream_id: stream_id.to_bytes(),
+            sequences,
+        };
+
+        let mut handler_state = HandlerState {
+            timers: &mut state.timers,
+            inflight: &mut state.inflight,
+            sync_queue: &mut state.sync_queue,
+            consensus_height: height,
+            request_timeout: state.request_timeout,
+            retain_height: state.sync.retain_height,
+        };
+
+        self.send_request(
+            &mut handler_state,
+            peer_id,
+            Request::ProposalPartsRequest(request),
+        )
+        .await;
+
+        // Request the parts still missing then from another peer
+        state.timers.start_timer(
+            Timeout::ProposalParts(proposer, stream_id),
+            state.request_timeout,
+        );
+    }
+
+    /// Serve the votes of a round requested by a peer stuck in that round,
+    /// if the peer is deciding the same height
+    fn on_vote_set_request(
+        &self,
+        myself: &ActorRef<Msg<Ctx>>,
+        state: &State<Ctx>,
+        request_id: InboundRequestId,
+        peer_id: PeerId,
+        request: VoteSetRequest<Ctx>,
+    ) -> Result<(), ActorProcessingErr> {
+        let VoteSetRequest { height, round } = request;
+
+        // Only the votes of the current height are kept by consensus
+        if height != state.sync.consensus_height {
+            debug!(%peer_id, %height, %round, "Ignoring request for the votes of another height");
+
+            self.network.cast(NetworkMsg::OutgoingResponse(
+                request_id,
+                Response::VoteSetResponse(VoteSetResponse::new(height, round, Vec::new())),
+            ))?;
+
+            return Ok(());
+        }
+
+        debug!(%peer_id, %request_id, %height, %round, "Received request for the votes of a round");
+
+        self.consensus.call_and_forward(
+            |reply_to| ConsensusMsg::GetVoteSet(height, round, reply_to),
+            myself,
+            move |votes| {
+                Msg::<Ctx>::GotVoteSet(request_id, VoteSetResponse::new(height, round, votes))
+            },
+            None,
+        )?;
+
+        Ok(())
+    }
+
+    /// Hand over to consensus the votes of the round received from a peer
+    fn on_vote_set_response(
+        &self,
+        state: &State<Ctx>,
+        peer_id: PeerId,
+        response: VoteSetResponse<Ctx>,
+    ) -> Result<(), ActorProcessingErr> {
+        let VoteSetResponse {
+            height,
+            round,
+            votes,
+        } = response;
+
+        if height != state.sync.consensus_height || votes.is_empty() {
+            debug!(%peer_id, %height, %round, "Peer did not have the votes of the round");
+            return Ok(());
+        }
+
+        debug!(%peer_id, %height, %round, votes = votes.len(), "Received the votes of the round");
+
+        self.consensus
+            .cast(ConsensusMsg::ProcessVoteSet(peer_id, votes))?;
+
+        Ok(())
+    }
+
+    /// Request the votes of the round consensus is stuck in from a peer deciding
+    /// the same height, unless they are already being requested
+    async fn request_vote_set(&self, state: &mut State<Ctx>, height: Ctx::Height, round: Round) {
+        if !state.sync.config.vote_sync || height != state.sync.consensus_height {
+            return;
+        }
+
+        let request = VoteSetRequest::new(height, round);
+
+        let already_requested = state.inflight.values().any(|inflight| {
+            matches!(&inflight.request, Request::VoteSetRequest(pending) if *pending == request)
+        });
+
+        if already_requested {
+            debug!(%height, %round, "Votes of the round already requested");
+            return;
+        }
+
+        // Peers which decided the previous height are deciding the same height as us
+        let peers: Vec<PeerId> = state
+            .sync
+            .peers
+            .iter()
+            .filter(|(_, status)| status.tip_height.increment() == height)
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+
+        let Some(peer_id) = state
+            .sync
+            .peer_scorer
+            .select_peer(&peers, &mut state.sync.rng)
+        else {
+            debug!(%height, %round, "No peer to request the votes of the round from");
+            return;
+        };
+
+        info!(%peer_id, %height, %round, "Requesting the votes of the round");
+
+        let mut handler_state = HandlerState {
+            timers: &mut state.timers,
+            inflight: &mut state.inflight,
+            sync_queue: &mut state.sync_queue,
+            consensus_height: height,
+            request_timeout: state.request_timeout,
+            retain_height: state.sync.retain_height,
+        };
+
+        self.send_request(
+            &mut handler_state,
+            peer_id,
+            Request::VoteSetRequest(request),
+        )
+        .await;
+    }
+
     async fn handle_msg(
         &self,
         myself: ActorRef<Msg<Ctx>>,
@@ -482,6 +1065,7 @@ where
                     peer_id,
                     tip_height: status.tip_height,
                     history_min_height: status.history_min_height,
+                    min_needed_height: status.min_needed_height,
                 };
 
                 self.process_input(&myself, state, sync::Input::Status(status))
@@ -498,6 +1082,32 @@ where
                         )
                         .await?;
                     }
+                    Request::SnapshotRequest(snapshot_request) => {
+                        self.process_input(
+                            &myself,
+                            state,
+                            sync::Input::SnapshotRequest(request_id, from, snapshot_request),
+                        )
+                        .await?;
+                    }
+                    Request::ProposalPartsRequest(parts_request) => {
+                        self.on_proposal_parts_request(
+                            &myself,
+                            state,
+                            request_id,
+                            from,
+                            parts_request,
+                        )?;
+                    }
+                    Request::VoteSetRequest(vote_set_request) => {
+                        self.on_vote_set_request(
+                            &myself,
+                            state,
+                            request_id,
+                            from,
+                            vote_set_request,
+                        )?;
+                    }
                 };
             }
 
@@ -506,25 +1116,64 @@ where
                 state.timers.cancel(&Timeout::Request(request_id.clone()));
 
                 // Remove the in-flight request
-                if state.inflight.remove(&request_id).is_none() {
+                let Some(inflight) = state.inflight.remove(&request_id) else {
                     debug!(%request_id, %peer, "Received response for unknown request");
 
                     // Ignore response for unknown request
                     // This can happen if the request timed out and was removed from in-flight requests
                     // in the meantime or if we receive a duplicate response.
                     return Ok(());
-                }
+                };
 
-                let response = response.map(|resp| match resp {
-                    Response::ValueResponse(value_response) => value_response,
-                });
+                // A response of another kind than the request is treated as invalid
+                let input = match inflight.request {
+                    Request::ValueRequest(_) => {
+                        let response = response.and_then(|resp| match resp {
+                            Response::ValueResponse(value_response) => Some(value_response),
+                            Response::SnapshotResponse(_)
+                            | Response::ProposalPartsResponse(_)
+                            | Response::VoteSetResponse(_) => None,
+                        });
+
+                        sync::Input::ValueResponse(request_id, peer, response)
+                    }
+                    Request::SnapshotRequest(_) => {
+                        let response = response.and_then(|resp| match resp {
+                            Response::SnapshotResponse(snapshot_response) => {
+                                Some(snapshot_response)
+                            }
+                            Response::ValueResponse(_)
+                            | Response::ProposalPartsResponse(_)
+                            | Response::VoteSetResponse(_) => None,
+                        });
+
+                        sync::Input::SnapshotResponse(request_id, peer, response)
+                    }
+                    // Not handled by the sync state machine, the parts being delivered
+                    // to consensus along with the ones received on the proposal parts channel
+                    Request::ProposalPartsRequest(parts_request) => {
+                        if let Some(Response::ProposalPartsResponse(response)) = response {
+                            self.on_proposal_parts_response(state, peer, parts_request, response)?;
+                        }
 
-                self.process_input(
-                    &myself,
-                    state,
-                    sync::Input::ValueResponse(request_id, peer, response),
-                )
-                .await?;
+                        return Ok(());
+                    }
+                    // Not handled by the sync state machine either, the votes being delivered
+                    // to consensus along with the ones received on the gossip layer
+                    Request::VoteSetRequest(_) => {
+                        if let Some(Response::VoteSetResponse(response)) = response {
+                            self.on_vote_set_response(state, peer, response)?;
+                        }
+
+                        return Ok(());
+                    }
+                };
+
+                self.process_input(&myself, state, input).await?;
+            }
+
+            Msg::NetworkEvent(NetworkEvent::ProposalPart(from, part)) => {
+                self.on_proposal_part(state, from, &part);
             }
 
             Msg::NetworkEvent(_) => {
@@ -533,6 +1182,14 @@ where
 
             // (Re)Started a new height
             Msg::StartedHeight(height, restart) => {
+                // The proposals of the previous height are no longer needed
+                for key in state.proposal_streams.keys() {
+                    state
+                        .timers
+                        .cancel(&Timeout::ProposalParts(key.0, key.1.clone()));
+                }
+                state.proposal_streams.clear();
+
                 if restart.is_restart() {
                     // Clear the sync queue
                     state.sync_queue.clear();
@@ -570,10 +1227,16 @@ where
                     .set(state.sync_queue.size() as i64);
             }
 
+            Msg::StartedRound(height, round) => {
+                state.round = Some((height, round));
+            }
+
             // Decided on a value
             Msg::Decided(height) => {
                 self.process_input(&myself, state, sync::Input::Decided(height))
                     .await?;
+
+                self.save_checkpoint(&state.sync);
             }
 
             // Received decided values from host
@@ -581,22 +1244,38 @@ where
             // We need to ensure that the total size of the response does not exceed the maximum allowed size.
             // If it does, we truncate the response accordingly.
             // This is to prevent sending overly large messages that could lead to network issues.
-            Msg::GotDecidedValues(request_id, range, mut values) => {
+            Msg::GotDecidedValues(request_id, request, mut values) => {
                 debug!(
-                    %request_id,
-                    range = %DisplayRange(&range),
+                    %request_id, trace_id = %request.trace_id,
+                    range = %DisplayRange(&request.range),
                     values_count = values.len(),
                     "Processing decided values from host"
                 );
 
+                // Only send the certificates, before fitting as many as possible in the response
+                if request.certificates_only {
+                    for value in &mut values {
+                        value.value_bytes = Bytes::new();
+                    }
+                }
+
                 // Filter values to respect maximum response size
                 let max_response_size = ByteSize::b(self.sync_config.max_response_size as u64);
-                truncate_values_to_size_limit(&mut values, max_response_size, &self.sync_codec);
+                let max_chunked_size = self
+                    .sync_config
+                    .max_chunked_response_size
+                    .map(|size| ByteSize::b(size as u64));
+                truncate_values_to_size_limit(
+                    &mut values,
+                    max_response_size,
+                    max_chunked_size,
+                    &self.sync_codec,
+                );
 
                 self.process_input(
                     &myself,
                     state,
-                    sync::Input::GotDecidedValues(request_id, range, values),
+                    sync::Input::GotDecidedValues(request_id, request, values),
                 )
                 .await?;
             }
@@ -631,6 +1310,162 @@ where
                 .await?
             }
 
+            Msg::DumpState(reply_to) => {
+                state.sync.audit.prune();
+
+                info!(tip_height = %state.sync.tip_height, "Dumping sync state");
+
+                let Some(reply_to) = Arc::into_inner(reply_to) else {
+                    error!("Failed to reply with sync state dump: reply port is shared");
+                    return Ok(());
+                };
+
+                if let Err(e) = reply_to.send(SyncStateDump::new(&state.sync)) {
+                    error!("Failed to reply with sync state dump: {e}");
+                }
+            }
+
+            Msg::GetLowWatermark(reply_to) => {
+                let Some(reply_to) = Arc::into_inner(reply_to) else {
+                    error!("Failed to reply with low watermark: reply port is shared");
+                    return Ok(());
+                };
+
+                if let Err(e) = reply_to.send(state.sync.low_watermark()) {
+                    error!("Failed to reply with low watermark: {e}");
+                }
+            }
+
+            Msg::Pause => {
+                self.process_input(&myself, state, sync::Input::Pause)
+                    .await?;
+            }
+
+            Msg::Resume => {
+                self.process_input(&myself, state, sync::Input::Resume)
+                    .await?;
+            }
+
+            Msg::GotSnapshotResponse(request_id, response) => {
+                self.network.cast(NetworkMsg::OutgoingResponse(
+                    request_id,
+                    Response::SnapshotResponse(response),
+                ))?;
+            }
+
+            Msg::GotProposalParts(request_id, response) => {
+                debug!(
+                    %request_id, height = %response.height, parts = response.parts.len(),
+                    "Sending the requested proposal parts"
+                );
+
+                self.network.cast(NetworkMsg::OutgoingResponse(
+                    request_id,
+                    Response::ProposalPartsResponse(response),
+                ))?;
+            }
+
+            Msg::RequestVoteSet(height, round) => {
+                self.request_vote_set(state, height, round).await;
+            }
+
+            Msg::GotVoteSet(request_id, response) => {
+                debug!(
+                    %request_id, height = %response.height, round = %response.round,
+                    votes = response.votes.len(), "Sending the requested votes of the round"
+                );
+
+                self.network.cast(NetworkMsg::OutgoingResponse(
+                    request_id,
+                    Response::VoteSetResponse(response),
+                ))?;
+            }
+
+            Msg::SnapshotRestored(snapshot, params) => {
+                let height = snapshot.height;
+                let restored = params.is_some();
+
+                self.process_input(
+                    &myself,
+                    state,
+                    sync::Input::SnapshotRestored(snapshot, restored),
+                )
+                .await?;
+
+                if let Some(params) = params {
+                    // Start consensus right after the snapshot, the values
+                    // buffered for the heights it covers being stale
+                    state.sync_queue.clear();
+                    self.metrics.sync_queue_updated(0, 0);
+
+                    self.consensus
+                        .cast(ConsensusMsg::StartHeight(height.increment(), params))?;
+
+                    self.save_checkpoint(&state.sync);
+                }
+            }
+
+            Msg::Backfill(range) => {
+                self.process_input(&myself, state, sync::Input::Backfill(range))
+                    .await?;
+            }
+
+            Msg::BackfillStored(request_id, stored) => {
+                self.process_input(
+                    &myself,
+                    state,
+                    sync::Input::BackfillStored(request_id, stored),
+                )
+                .await?;
+            }
+
+            Msg::SetRetainHeight(height, reply_to) => {
+                if state.sync.set_retain_height(height) {
+                    info!(retain_height = %height, "Pruning the decided values below the retain height");
+
+                    self.network
+                        .cast(NetworkMsg::SetRetainedHeight(height.as_u64()))?;
+
+                    // Let the peers know right away that the pruned values are no longer available
+                    self.process_input(&myself, state, sync::Input::SendStatusUpdate)
+                        .await?;
+                }
+
+                // The values below the retain height are no longer served from now on
+                match Arc::into_inner(reply_to) {
+                    Some(reply_to) => {
+                        if let Err(e) = reply_to.send(true) {
+                            error!("Failed to reply to retain height update: {e}");
+                        }
+                    }
+                    None => error!("Failed to reply to retain height update: reply port is shared"),
+                }
+            }
+
+            Msg::UpdateConfig(update) => {
+                self.process_input(&myself, state, sync::Input::UpdateConfig(update))
+                    .await?;
+
+                if let Some(request_timeout) = update.request_timeout {
+                    state.request_timeout = request_timeout;
+                }
+
+                state
+                    .sync_queue
+                    .set_capacity(queue_capacity(&state.sync.config));
+            }
+
+            Msg::GetStatus(reply_to) => {
+                let Some(reply_to) = Arc::into_inner(reply_to) else {
+                    error!("Failed to reply with sync status: reply port is shared");
+                    return Ok(());
+                };
+
+                if let Err(e) = reply_to.send(SyncStatus::new(&state.sync)) {
+                    error!("Failed to reply with sync status: {e}");
+                }
+            }
+
             Msg::TimeoutElapsed(elapsed) => {
                 let Some(timeout) = state.timers.intercept_timer_msg(elapsed) else {
                     // Timer was cancelled or already processed, ignore
@@ -656,6 +1491,11 @@ where
                             debug!(%request_id, "Timeout for unknown request");
                         }
                     }
+
+                    Timeout::ProposalParts(proposer, stream_id) => {
+                        self.request_proposal_parts(state, proposer, stream_id)
+                            .await;
+                    }
                 }
             }
         }
@@ -664,6 +1504,15 @@ where
     }
 }
 
+/// Capacity of the queue of the values received before consensus reaches their height.
+///
+/// NOTE: The queue capacity is set to accommodate all individual values for the
+/// maximum number of parallel requests and the answered requests waiting to be verified,
+/// with some additional buffer.
+fn queue_capacity(config: &sync::Config) -> usize {
+    2 * (config.parallel_requests + config.verification_queue_size) * config.batch_size
+}
+
 fn status_update_mode<Ctx, R>(
     interval: Duration,
     sync: &ActorRef<Msg<Ctx>>,
@@ -694,6 +1543,7 @@ where
 fn truncate_values_to_size_limit<Ctx, Codec>(
     values: &mut Vec<RawDecidedValue<Ctx>>,
     max_response_size: ByteSize,
+    max_chunked_size: Option<ByteSize>,
     codec: &Codec,
 ) where
     Ctx: Context,
@@ -716,6 +1566,18 @@ fn truncate_values_to_size_limit<Ctx, Codec>(
             }
         };
 
+        // A single value larger than the limit is sent alone, split into chunks by the network
+        let fits_chunked = max_chunked_size.is_some_and(|max| keep_count == 0 && value_size <= max);
+
+        if fits_chunked && value_size > max_response_size {
+            debug!(
+                %max_response_size, %value_size,
+                "Value exceeds maximum response size, sending it alone at height {height}"
+            );
+            keep_count = 1;
+            break;
+        }
+
         if current_size + value_size > max_response_size {
             warn!(
                 %max_response_size, %current_size, %value_size,
@@ -732,6 +1594,21 @@ fn truncate_values_to_size_limit<Ctx, Codec>(
     values.truncate(keep_count);
 }
 
+/// Drop the proposal parts past the maximum response size
+fn truncate_parts_to_size_limit(parts: &mut Vec<Bytes>, max_response_size: usize) {
+    let mut current_size = 0;
+
+    let keep_count = parts
+        .iter()
+        .take_while(|part| {
+            current_size += part.len();
+            current_size <= max_response_size
+        })
+        .count();
+
+    parts.truncate(keep_count);
+}
+
 #[async_trait]
 impl<Ctx, Codec> Actor for Sync<Ctx, Codec>
 where
@@ -747,24 +1624,46 @@ where
         myself: ActorRef<Self::Msg>,
         _args: Self::Arguments,
     ) -> Result<Self::State, ActorProcessingErr> {
+        let subscriber = MailboxSubscriber::new(myself.clone(), self.mailbox.clone(), |event| {
+            NetworkEvent::<Ctx>::priority(event, None)
+        });
+
         self.network
-            .cast(NetworkMsg::Subscribe(Box::new(myself.clone())))?;
+            .cast(NetworkMsg::Subscribe(Box::new(subscriber)))?;
 
         let mut rng = Box::new(rand::rngs::StdRng::from_entropy());
 
         let status_update_mode =
             status_update_mode(self.params.status_update_interval, &myself, &mut rng);
 
-        // NOTE: The queue capacity is set to accommodate all individual values for the
-        // maximum number of parallel requests and batch size, with some additional buffer.
-        let queue_capacity = 2 * self.sync_config.parallel_requests * self.sync_config.batch_size;
+        let queue_capacity = queue_capacity(&self.sync_config);
+
+        let mut sync = sync::State::new(rng, self.sync_config);
+
+        if let Some(path) = &self.params.checkpoint_path {
+            match load_checkpoint(path) {
+                Ok(Some(checkpoint)) => {
+                    info!(
+                        tip_height = checkpoint.tip_height,
+                        peers = checkpoint.peer_scores.len(),
+                        "Resuming sync from checkpoint"
+                    );
+                    sync.restore(&checkpoint);
+                }
+                Ok(None) => {}
+                Err(e) => warn!(path = %path.display(), "Failed to load sync checkpoint: {e}"),
+            }
+        }
 
         Ok(State {
-            sync: sync::State::new(rng, self.sync_config),
+            sync,
             timers: Timers::new(Box::new(myself.clone())),
             inflight: HashMap::new(),
             sync_queue: SyncQueue::new(queue_capacity),
             status_update_mode,
+            request_timeout: self.params.request_timeout,
+            proposal_streams: HashMap::new(),
+            round: None,
         })
     }
 
@@ -783,6 +1682,10 @@ where
         msg: Self::Msg,
         state: &mut Self::State,
     ) -> Result<(), ActorProcessingErr> {
+        if let Msg::NetworkEvent(_) = msg {
+            self.mailbox.release();
+        }
+
         if let Err(e) = self.handle_msg(myself, msg, state).await {
             error!("Error handling message: {e:?}");
         }
@@ -799,6 +1702,24 @@ where
             ticker.abort();
         }
 
+        self.save_checkpoint(&state.sync);
+
         Ok(())
     }
 }
+
+/// Load the checkpoint of the sync progress, `None` if none was saved yet
+fn load_checkpoint(path: &Path) -> eyre::Result<Option<sync::Checkpoint>> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => Ok(Some(sync::Checkpoint::decode(&data)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Save the checkpoint of the sync progress, replacing the previous one atomically
+fn save_checkpoint(path: &Path, checkpoint: &sync::Checkpoint) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, checkpoint.encode())?;
+    std::fs::rename(tmp_path, path)
+}
@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::RangeInclusive;
+
+use derive_where::derive_where;
+use malachitebft_core_consensus::PeerId;
+use malachitebft_core_types::Context;
+use malachitebft_sync::audit::AuditEntry;
+use malachitebft_sync::scoring::Score;
+use malachitebft_sync::{self as sync, NodeStatus, OutboundRequestId, Status};
+
+/// A dump of the current state of the sync engine.
+#[derive_where(Debug, Clone)]
+pub struct SyncStateDump<Ctx: Context> {
+    /// Whether the node is still starting, in which case no status is advertised
+    /// and no values are served to peers
+    pub node_status: NodeStatus,
+
+    /// The height that consensus is at, but has not decided yet
+    pub consensus_height: Ctx::Height,
+
+    /// Height of the last decided value
+    pub tip_height: Ctx::Height,
+
+    /// Next height to send a sync request for
+    pub sync_height: Ctx::Height,
+
+    /// The pending requests, with the requested range of heights and the peer
+    pub pending_requests: BTreeMap<OutboundRequestId, (RangeInclusive<Ctx::Height>, PeerId)>,
+
+    /// Ranges of heights above the tip requested from the peers, merged and sorted
+    pub covered_ranges: Vec<RangeInclusive<Ctx::Height>>,
+
+    /// Ranges of heights between the tip and the highest tip of the peers
+    /// which have not been requested from any peer
+    pub uncovered_ranges: Vec<RangeInclusive<Ctx::Height>>,
+
+    /// Ranges of heights requested from each peer and not decided yet
+    pub outstanding_ranges: BTreeMap<PeerId, Vec<RangeInclusive<Ctx::Height>>>,
+
+    /// The last status received from each peer
+    pub peers: BTreeMap<PeerId, Status<Ctx>>,
+
+    /// The score of each peer
+    pub scores: BTreeMap<PeerId, Score>,
+
+    /// The requests sent to and received from each peer over the audit window, oldest first
+    pub audit: BTreeMap<PeerId, VecDeque<AuditEntry<Ctx>>>,
+}
+
+impl<Ctx: Context> SyncStateDump<Ctx> {
+    pub fn new(state: &sync::State<Ctx>) -> Self {
+        Self {
+            node_status: state.node_status(),
+            consensus_height: state.consensus_height,
+            tip_height: state.tip_height,
+            sync_height: state.sync_height,
+            pending_requests: state.pending_requests.as_map().clone(),
+            covered_ranges: state.covered_ranges(),
+            uncovered_ranges: state.uncovered_ranges(),
+            outstanding_ranges: state.outstanding_ranges_by_peer(),
+            peers: state.peers.clone(),
+            scores: state
+                .peer_scorer
+                .get_scores()
+                .keys()
+                .map(|peer_id| (*peer_id, state.peer_scorer.get_score(peer_id)))
+                .collect(),
+            audit: state.audit.entries().clone(),
+        }
+    }
+}
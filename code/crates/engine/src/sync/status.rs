@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use derive_where::derive_where;
+use malachitebft_core_types::Context;
+use malachitebft_sync as sync;
+
+/// A summary of the progress of sync.
+#[derive_where(Debug, Clone, PartialEq)]
+pub struct SyncStatus<Ctx: Context> {
+    /// Whether the node is still starting, in which case no status is advertised
+    /// and no values are served to peers
+    pub node_status: sync::NodeStatus,
+
+    /// Whether sending requests for values is paused
+    pub paused: bool,
+
+    /// Height of the last decided value
+    pub tip_height: Ctx::Height,
+
+    /// Next height to send a sync request for
+    pub sync_height: Ctx::Height,
+
+    /// Number of ranges of heights requested from the peers and not decided yet
+    pub pending_requests: usize,
+
+    /// Highest tip height advertised by the peers, if any
+    pub network_tip_height: Option<Ctx::Height>,
+
+    /// Estimated time until the tip height of the peers is decided,
+    /// `None` if unknown
+    pub estimated_time_to_tip: Option<Duration>,
+}
+
+impl<Ctx: Context> SyncStatus<Ctx> {
+    pub fn new(state: &sync::State<Ctx>) -> Self {
+        Self {
+            node_status: state.node_status(),
+            paused: state.paused,
+            tip_height: state.tip_height,
+            sync_height: state.sync_height,
+            pending_requests: state.pending_requests.len(),
+            network_tip_height: state.network_tip_height(),
+            estimated_time_to_tip: state.estimated_time_to_tip(),
+        }
+    }
+}
@@ -0,0 +1,334 @@
+//! Source of time of the engine, used to schedule the timeouts of consensus and,
+//! in the future, to check the timestamps of the proposals (PBTS).
+//!
+//! The default [`SystemClock`] reads the clock of the operating system and can monitor
+//! its offset to NTP servers, see [`SystemClock::monitored`]. A skewed clock silently
+//! degrades the performance of the rounds, the timeouts of the validators expiring
+//! at different times, so the offset is exported as metrics and in the health of the node.
+
+use std::future::Future;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use malachitebft_config::ClockConfig;
+use malachitebft_metrics::prometheus::metrics::counter::Counter;
+use malachitebft_metrics::prometheus::metrics::gauge::Gauge;
+use malachitebft_metrics::SharedRegistry;
+
+/// How long to wait for the reply of an NTP server
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Size of the NTP packets, without any extension field
+const NTP_PACKET_SIZE: usize = 48;
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+const NTP_UNIX_OFFSET: i128 = 2_208_988_800;
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+
+/// Future returned by [`Clock::sleep`]
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Source of time of the engine, see the [module docs](self)
+pub trait Clock: Send + Sync + 'static {
+    /// Current wall-clock time
+    fn now(&self) -> SystemTime;
+
+    /// Complete once the given duration has elapsed, eg. for a timeout to expire
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+/// Offset of the local clock to the NTP servers, see [`SystemClock::monitored`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClockSkew {
+    /// Offset of the local clock in milliseconds, positive if it is ahead of the servers,
+    /// `None` until measured or while none of the servers can be reached
+    pub offset_ms: Option<i64>,
+
+    /// Whether the offset is above the configured `max_skew`
+    pub exceeded: bool,
+}
+
+/// Clock of the operating system
+#[derive(Clone, Debug, Default)]
+pub struct SystemClock {
+    skew: Option<watch::Receiver<ClockSkew>>,
+}
+
+impl SystemClock {
+    /// Clock of the operating system, whose offset is not monitored
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clock of the operating system whose offset to the configured NTP servers is checked
+    /// at a regular interval, exported as metrics and published on [`SystemClock::skew`].
+    /// The offset is not monitored unless enabled in the configuration.
+    pub fn monitored(config: ClockConfig, registry: &SharedRegistry) -> Self {
+        if !config.enabled || config.ntp_servers.is_empty() {
+            return Self::new();
+        }
+
+        let metrics = Metrics::register(registry);
+        let (tx_skew, rx_skew) = watch::channel(ClockSkew::default());
+
+        tokio::spawn(monitor(config, metrics, tx_skew));
+
+        Self {
+            skew: Some(rx_skew),
+        }
+    }
+
+    /// Latest offset of the clock to the NTP servers, if it is monitored
+    pub fn skew(&self) -> Option<watch::Receiver<ClockSkew>> {
+        self.skew.clone()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Metrics {
+    offset_ms: Gauge,
+    skew_exceeded: Gauge,
+    ntp_failures: Counter,
+}
+
+impl Metrics {
+    fn register(registry: &SharedRegistry) -> Self {
+        let metrics = Self {
+            offset_ms: Gauge::default(),
+            skew_exceeded: Gauge::default(),
+            ntp_failures: Counter::default(),
+        };
+
+        registry.with_prefix("malachitebft_clock", |registry| {
+            registry.register(
+                "offset_ms",
+                "Offset of the local clock to the NTP servers, in milliseconds, positive if ahead",
+                metrics.offset_ms.clone(),
+            );
+
+            registry.register(
+                "skew_exceeded",
+                "Whether the offset of the local clock is above the maximum skew (0 or 1)",
+                metrics.skew_exceeded.clone(),
+            );
+
+            registry.register(
+                "ntp_failures",
+                "Number of queries to the NTP servers which failed",
+                metrics.ntp_failures.clone(),
+            );
+        });
+
+        metrics
+    }
+}
+
+/// Check the offset of the local clock at a regular interval,
+/// until all the receivers of the offset are dropped
+async fn monitor(config: ClockConfig, metrics: Metrics, tx_skew: watch::Sender<ClockSkew>) {
+    let mut interval = tokio::time::interval(config.check_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = tx_skew.closed() => break,
+
+            _ = interval.tick() => {
+                let mut offsets = Vec::with_capacity(config.ntp_servers.len());
+
+                for server in &config.ntp_servers {
+                    match tokio::time::timeout(NTP_TIMEOUT, query_offset(server)).await {
+                        Ok(Ok(offset_ms)) => offsets.push(offset_ms),
+                        Ok(Err(e)) => {
+                            metrics.ntp_failures.inc();
+                            debug!(%server, "Failed to query NTP server: {e}");
+                        }
+                        Err(_) => {
+                            metrics.ntp_failures.inc();
+                            debug!(%server, "NTP server did not reply within {NTP_TIMEOUT:?}");
+                        }
+                    }
+                }
+
+                // The median is robust to a single server being off
+                offsets.sort_unstable();
+                let offset_ms = offsets.get(offsets.len() / 2).copied();
+
+                let exceeded = offset_ms.is_some_and(|offset_ms| {
+                    u128::from(offset_ms.unsigned_abs()) > config.max_skew.as_millis()
+                });
+
+                let was_exceeded = tx_skew.borrow().exceeded;
+
+                match offset_ms {
+                    None => warn!("No NTP server could be reached to check the local clock"),
+                    Some(offset_ms) if exceeded => warn!(
+                        %offset_ms,
+                        max_skew = ?config.max_skew,
+                        "Local clock is skewed, rounds may take longer to complete"
+                    ),
+                    Some(offset_ms) if was_exceeded => {
+                        info!(%offset_ms, "Local clock is back within the maximum skew")
+                    }
+                    Some(offset_ms) => debug!(%offset_ms, "Checked the offset of the local clock"),
+                }
+
+                metrics.offset_ms.set(offset_ms.unwrap_or(0));
+                metrics.skew_exceeded.set(i64::from(exceeded));
+
+                tx_skew.send_replace(ClockSkew {
+                    offset_ms,
+                    exceeded,
+                });
+            }
+        }
+    }
+}
+
+/// Query the offset of the local clock to an NTP server, with a single SNTP request
+async fn query_offset(server: &str) -> io::Result<i64> {
+    let addr = tokio::net::lookup_host(server)
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address for NTP server"))?;
+
+    let local_addr = match addr {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+
+    let socket = UdpSocket::bind(local_addr).await?;
+    socket.connect(addr).await?;
+
+    let sent = SystemTime::now();
+    let origin = to_ntp_timestamp(sent);
+
+    let mut request = [0; NTP_PACKET_SIZE];
+    request[0] = 0x1b; // No leap second warning, version 3, client mode
+    request[40..48].copy_from_slice(&origin.to_be_bytes());
+
+    socket.send(&request).await?;
+
+    let mut response = [0; NTP_PACKET_SIZE];
+    let len = socket.recv(&mut response).await?;
+    let received = SystemTime::now();
+
+    offset_of(&response[..len], origin, sent, received)
+}
+
+/// Offset of the local clock in milliseconds, positive if it is ahead of the server,
+/// from the reply of the server to a request sent and received at the given local times
+fn offset_of(
+    response: &[u8],
+    origin: u64,
+    sent: SystemTime,
+    received: SystemTime,
+) -> io::Result<i64> {
+    let invalid = |reason| Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+
+    if response.len() < NTP_PACKET_SIZE {
+        return invalid("truncated NTP reply");
+    }
+
+    if response[0] & 0x7 != 4 {
+        return invalid("not an NTP server reply");
+    }
+
+    if response[1] == 0 {
+        return invalid("NTP server asked to stop querying it");
+    }
+
+    let timestamp = |at: usize| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&response[at..at + 8]);
+        u64::from_be_bytes(bytes)
+    };
+
+    if timestamp(24) != origin {
+        return invalid("NTP reply to another request");
+    }
+
+    let server_received = from_ntp_timestamp(timestamp(32));
+    let server_sent = from_ntp_timestamp(timestamp(40));
+
+    // Offset of the server to the local clock, assuming a symmetric round trip
+    let server_offset =
+        ((server_received - unix_nanos(sent)) + (server_sent - unix_nanos(received))) / 2;
+
+    Ok((-server_offset / 1_000_000) as i64)
+}
+
+fn unix_nanos(time: SystemTime) -> i128 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_nanos() as i128,
+        Err(e) => -(e.duration().as_nanos() as i128),
+    }
+}
+
+/// NTP timestamp of a time, seconds since 1900 and fraction of a second on 32 bits each
+fn to_ntp_timestamp(time: SystemTime) -> u64 {
+    let nanos = unix_nanos(time) + NTP_UNIX_OFFSET * NANOS_PER_SEC;
+    let (secs, subsec_nanos) = (nanos / NANOS_PER_SEC, nanos % NANOS_PER_SEC);
+
+    ((secs as u64) << 32) | ((subsec_nanos << 32) / NANOS_PER_SEC) as u64
+}
+
+/// Nanoseconds since the Unix epoch of an NTP timestamp
+fn from_ntp_timestamp(timestamp: u64) -> i128 {
+    let secs = i128::from(timestamp >> 32) - NTP_UNIX_OFFSET;
+    let fraction = i128::from(timestamp & 0xffff_ffff);
+
+    secs * NANOS_PER_SEC + ((fraction * NANOS_PER_SEC) >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(origin: u64, server_received: SystemTime, server_sent: SystemTime) -> Vec<u8> {
+        let mut reply = vec![0; NTP_PACKET_SIZE];
+        reply[0] = 0x1c; // Version 3, server mode
+        reply[1] = 2;
+        reply[24..32].copy_from_slice(&origin.to_be_bytes());
+        reply[32..40].copy_from_slice(&to_ntp_timestamp(server_received).to_be_bytes());
+        reply[40..48].copy_from_slice(&to_ntp_timestamp(server_sent).to_be_bytes());
+        reply
+    }
+
+    #[test]
+    fn offset_of_a_skewed_clock() {
+        let sent = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let received = sent + Duration::from_millis(40);
+        let origin = to_ntp_timestamp(sent);
+
+        // The server is 300ms behind, and replies halfway through the round trip
+        let server_received = sent + Duration::from_millis(20) - Duration::from_millis(300);
+        let server_sent = server_received + Duration::from_millis(1);
+
+        let reply = reply(origin, server_received, server_sent);
+        let offset_ms = offset_of(&reply, origin, sent, received).unwrap();
+        assert!((299..=301).contains(&offset_ms), "{offset_ms}");
+
+        // Reply to another request
+        let error = offset_of(&reply, origin + 1, sent, received).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
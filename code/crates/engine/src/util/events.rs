@@ -10,9 +10,12 @@ use malachitebft_core_consensus::{
     SignedConsensusMsg, WalEntry,
 };
 use malachitebft_core_types::{
-    CommitCertificate, Context, PolkaCertificate, Round, RoundCertificate, SignedVote, ValueOrigin,
+    CommitCertificate, Context, NilOrVal, PolkaCertificate, Round, RoundCertificate, SignedVote,
+    Timeout, ValueId, ValueOrigin,
 };
 
+use crate::network::{BootstrapProgress, HolePunchEvent, RelayElectionEvent};
+
 pub type RxEvent<Ctx> = broadcast::Receiver<Event<Ctx>>;
 
 #[derive_where(Clone)]
@@ -30,6 +33,11 @@ impl<Ctx: Context> TxEvent<Ctx> {
         self.tx.subscribe()
     }
 
+    /// Whether there is at least one subscriber to the events
+    pub fn has_subscribers(&self) -> bool {
+        self.tx.receiver_count() > 0
+    }
+
     pub fn send(&self, event: impl FnOnce() -> Event<Ctx>) {
         if self.tx.receiver_count() > 0 {
             let _ = self.tx.send(event());
@@ -62,12 +70,20 @@ pub enum Event<Ctx: Context> {
     RebroadcastRoundCertificate(RoundCertificate<Ctx>),
     SkipRoundCertificate(RoundCertificate<Ctx>),
     PolkaCertificate(PolkaCertificate<Ctx>),
+    /// A quorum of prevotes was reached for a value or for nil
+    PolkaReached(Ctx::Height, Round, NilOrVal<ValueId<Ctx>>),
+    /// A quorum of precommits was reached, for a value if known
+    PrecommitQuorumReached(Ctx::Height, Round, Option<ValueId<Ctx>>),
+    TimeoutElapsed(Ctx::Height, Timeout),
     WalReplayBegin(Ctx::Height, usize),
     WalReplayEntry(WalEntry<Ctx>),
     WalReplayDone(Ctx::Height),
     WalReplayError(Arc<ConsensusError<Ctx>>),
     WalResetError(Arc<eyre::Report>),
     WalCorrupted(Arc<io::Error>),
+    BootstrapProgress(BootstrapProgress),
+    HolePunch(HolePunchEvent),
+    RelayElection(RelayElectionEvent),
 }
 
 impl<Ctx: Context> fmt::Display for Event<Ctx> {
@@ -130,6 +146,9 @@ impl<Ctx: Context> fmt::Display for Event<Ctx> {
             Event::WalReplayError(error) => write!(f, "WalReplayError({error})"),
             Event::WalResetError(error) => write!(f, "WalResetError({error})"),
             Event::WalCorrupted(error) => write!(f, "WalCorrupted(error: {error:?})"),
+            Event::BootstrapProgress(progress) => write!(f, "BootstrapProgress({progress})"),
+            Event::HolePunch(event) => write!(f, "HolePunch({event})"),
+            Event::RelayElection(event) => write!(f, "RelayElection({event})"),
 
             Event::PolkaCertificate(certificate) => {
                 write!(f, "PolkaCertificate: {certificate:?})")
@@ -137,6 +156,25 @@ impl<Ctx: Context> fmt::Display for Event<Ctx> {
             Event::SkipRoundCertificate(certificate) => {
                 write!(f, "SkipRoundCertificate: {certificate:?})")
             }
+            Event::PolkaReached(height, round, value_id) => {
+                write!(
+                    f,
+                    "PolkaReached(height: {height}, round: {round}, value: {value_id:?})"
+                )
+            }
+            Event::PrecommitQuorumReached(height, round, value_id) => {
+                write!(
+                    f,
+                    "PrecommitQuorumReached(height: {height}, round: {round}, value: {value_id:?})"
+                )
+            }
+            Event::TimeoutElapsed(height, timeout) => {
+                write!(
+                    f,
+                    "TimeoutElapsed(height: {height}, round: {}, kind: {:?})",
+                    timeout.round, timeout.kind
+                )
+            }
         }
     }
 }
@@ -0,0 +1,296 @@
+//! Bound on the messages received from the network waiting in the mailbox of an actor.
+//!
+//! The mailboxes of the actors are unbounded, so that a flood of messages from the network
+//! would grow them without limit, delaying the timeouts and the requests of the application
+//! queued behind the flood. The messages received from the network are therefore admitted
+//! into the mailboxes of the consensus, network and sync actors through a [`Mailbox`], which
+//! counts the messages waiting to be handled and, with [`OverloadPolicy::Shed`], sheds:
+//!
+//! - the [`Priority::Low`] messages, eg. stale votes or rebroadcast gossip,
+//!   once the mailbox is half full,
+//! - the [`Priority::Normal`] messages once the mailbox is full.
+//!
+//! The [`Priority::High`] messages, eg. the changes of the connectivity of the node, the status
+//! of the peers and the proposal parts, are never shed, nor are the timeouts, the requests of
+//! the application and the messages between actors, which do not go through the [`Mailbox`].
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ractor::{ActorRef, Message};
+use tracing::error;
+
+use malachitebft_config::{MailboxConfig, OverloadPolicy};
+use malachitebft_metrics::prometheus::encoding::EncodeLabelSet;
+use malachitebft_metrics::prometheus::metrics::counter::Counter;
+use malachitebft_metrics::prometheus::metrics::family::Family;
+use malachitebft_metrics::prometheus::metrics::gauge::Gauge;
+use malachitebft_metrics::SharedRegistry;
+
+// Make prometheus_client available for the derive macro
+use malachitebft_metrics::prometheus as prometheus_client;
+
+use crate::network::Subscriber;
+use crate::util::output_port::{OutputPort, OutputPortSubscriberTrait};
+
+/// How important a message received from the network is, see the [module docs](self)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Shed first, once the mailbox is half full
+    Low,
+    /// Shed once the mailbox is full
+    Normal,
+    /// Never shed
+    High,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Normal => "normal",
+            Self::High => "high",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct PriorityLabels {
+    priority: &'static str,
+}
+
+#[derive(Clone, Debug)]
+struct Metrics {
+    queued: Gauge,
+    capacity: Gauge,
+    shed: Family<PriorityLabels, Counter>,
+}
+
+impl Metrics {
+    fn register(actor: &str, config: MailboxConfig, registry: &SharedRegistry) -> Self {
+        let metrics = Self {
+            queued: Gauge::default(),
+            capacity: Gauge::default(),
+            shed: Family::default(),
+        };
+
+        metrics.capacity.set(config.capacity as i64);
+
+        registry.with_prefix("malachitebft_mailbox", |registry| {
+            let registry = registry
+                .sub_registry_with_label((Cow::Borrowed("actor"), Cow::Owned(actor.to_string())));
+
+            registry.register(
+                "queued_messages",
+                "Messages received from the network waiting to be handled by the actor",
+                metrics.queued.clone(),
+            );
+
+            registry.register(
+                "capacity",
+                "Number of messages waiting to be handled before the actor is overloaded",
+                metrics.capacity.clone(),
+            );
+
+            registry.register(
+                "shed_messages",
+                "Messages received from the network dropped while the actor was overloaded",
+                metrics.shed.clone(),
+            );
+        });
+
+        metrics
+    }
+}
+
+struct Inner {
+    config: MailboxConfig,
+    queued: AtomicUsize,
+    metrics: Option<Metrics>,
+}
+
+/// Counter of the messages received from the network waiting in the mailbox of an actor,
+/// shared by the senders admitting them and the actor handling them, see the [module docs](self)
+#[derive(Clone)]
+pub struct Mailbox {
+    inner: Arc<Inner>,
+}
+
+impl Mailbox {
+    /// Mailbox of the given actor, exporting its saturation as metrics
+    pub fn new(actor: &str, config: MailboxConfig, registry: &SharedRegistry) -> Self {
+        Self::with_metrics(config, Some(Metrics::register(actor, config, registry)))
+    }
+
+    /// Mailbox which never sheds any message nor exports any metrics
+    pub fn unbounded() -> Self {
+        let config = MailboxConfig {
+            overload: OverloadPolicy::Unbounded,
+            ..MailboxConfig::default()
+        };
+
+        Self::with_metrics(config, None)
+    }
+
+    fn with_metrics(config: MailboxConfig, metrics: Option<Metrics>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                queued: AtomicUsize::new(0),
+                metrics,
+            }),
+        }
+    }
+
+    /// Whether a message of the given priority can be sent to the actor, in which case it
+    /// is counted as waiting until [`Mailbox::release`] is called by the actor handling it
+    pub fn admit(&self, priority: Priority) -> bool {
+        let inner = &self.inner;
+
+        if inner.config.overload == OverloadPolicy::Shed {
+            let limit = match priority {
+                Priority::Low => inner.config.capacity / 2,
+                Priority::Normal => inner.config.capacity,
+                Priority::High => usize::MAX,
+            };
+
+            if inner.queued.load(Ordering::Relaxed) >= limit {
+                if let Some(metrics) = &inner.metrics {
+                    metrics
+                        .shed
+                        .get_or_create(&PriorityLabels {
+                            priority: priority.as_str(),
+                        })
+                        .inc();
+                }
+
+                return false;
+            }
+        }
+
+        let queued = inner.queued.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if let Some(metrics) = &inner.metrics {
+            metrics.queued.set(queued as i64);
+        }
+
+        true
+    }
+
+    /// Count a message admitted with [`Mailbox::admit`] as taken out of the mailbox,
+    /// to be called by the actor before handling it
+    pub fn release(&self) {
+        let inner = &self.inner;
+
+        let queued = inner
+            .queued
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |queued| {
+                Some(queued.saturating_sub(1))
+            })
+            .map_or(0, |queued| queued.saturating_sub(1));
+
+        if let Some(metrics) = &inner.metrics {
+            metrics.queued.set(queued as i64);
+        }
+    }
+
+    /// Number of messages waiting in the mailbox
+    pub fn len(&self) -> usize {
+        self.inner.queued.load(Ordering::Relaxed)
+    }
+
+    /// Whether no message is waiting in the mailbox
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+type Classifier<Msg> = Arc<dyn Fn(&Msg) -> Priority + Send + Sync>;
+
+/// Subscriber to an output port admitting the messages into the mailbox of an actor
+/// according to their priority, the ones shed never reaching the actor
+pub struct MailboxSubscriber<Msg, To>
+where
+    To: Message,
+{
+    actor: ActorRef<To>,
+    mailbox: Mailbox,
+    priority: Classifier<Msg>,
+}
+
+impl<Msg, To> MailboxSubscriber<Msg, To>
+where
+    To: Message,
+{
+    pub fn new(
+        actor: ActorRef<To>,
+        mailbox: Mailbox,
+        priority: impl Fn(&Msg) -> Priority + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            actor,
+            mailbox,
+            priority: Arc::new(priority),
+        }
+    }
+}
+
+impl<Msg, To> OutputPortSubscriberTrait<Msg> for MailboxSubscriber<Msg, To>
+where
+    Msg: Message + Clone,
+    To: Message + From<Msg>,
+{
+    fn subscribe_to_port(&self, port: &OutputPort<Msg>) {
+        let mailbox = self.mailbox.clone();
+        let priority = Arc::clone(&self.priority);
+
+        port.subscribe(self.actor.clone(), move |msg| {
+            mailbox.admit(priority(&msg)).then(|| To::from(msg))
+        });
+    }
+}
+
+impl<Msg, To> Subscriber<Msg> for MailboxSubscriber<Msg, To>
+where
+    Msg: Message + Clone,
+    To: Message + From<Msg>,
+{
+    fn send(&self, msg: Msg) {
+        if !self.mailbox.admit((self.priority)(&msg)) {
+            return;
+        }
+
+        if let Err(e) = self.actor.cast(To::from(msg)) {
+            error!("Failed to send message to subscriber: {e:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_priority_messages_are_shed_first() {
+        let mailbox = Mailbox::with_metrics(MailboxConfig::new(4), None);
+
+        assert!(mailbox.admit(Priority::Low));
+        assert!(mailbox.admit(Priority::Normal));
+        assert!(!mailbox.admit(Priority::Low));
+        assert!(mailbox.admit(Priority::Normal));
+        assert!(mailbox.admit(Priority::Normal));
+        assert!(!mailbox.admit(Priority::Normal));
+        assert!(mailbox.admit(Priority::High));
+        assert_eq!(mailbox.len(), 5);
+
+        for _ in 0..4 {
+            mailbox.release();
+        }
+
+        assert!(mailbox.admit(Priority::Low));
+
+        let unbounded = Mailbox::unbounded();
+        assert!((0..10_000).all(|_| unbounded.admit(Priority::Low)));
+    }
+}
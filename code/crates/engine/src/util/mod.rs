@@ -1,6 +1,11 @@
+pub mod clock;
 pub mod events;
+pub mod mailbox;
 pub mod msg_buffer;
+pub(crate) mod ordered_msgs;
 pub mod output_port;
 pub mod streaming;
 pub mod ticker;
 pub mod timers;
+pub mod trace_context;
+pub mod vote_batch;
@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::time::Instant;
 
 use malachitebft_core_types::Context;
 use tracing::{info, warn};
@@ -6,7 +7,8 @@ use tracing::{info, warn};
 use crate::consensus::ConsensusMsg;
 
 pub struct MessageBuffer<Ctx: Context> {
-    messages: VecDeque<ConsensusMsg<Ctx>>,
+    /// Buffered messages, with the time at which they were buffered
+    messages: VecDeque<(Instant, ConsensusMsg<Ctx>)>,
     max_size: usize,
 }
 
@@ -21,7 +23,7 @@ impl<Ctx: Context> MessageBuffer<Ctx> {
     pub fn buffer(&mut self, msg: ConsensusMsg<Ctx>) -> bool {
         if self.messages.len() < self.max_size {
             info!("Buffering message: {msg:?}");
-            self.messages.push_back(msg);
+            self.messages.push_back((Instant::now(), msg));
             true
         } else {
             warn!("Buffer is full, dropping message: {msg:?}");
@@ -30,7 +32,12 @@ impl<Ctx: Context> MessageBuffer<Ctx> {
     }
 
     pub fn pop(&mut self) -> Option<ConsensusMsg<Ctx>> {
-        self.messages.pop_front()
+        self.messages.pop_front().map(|(_, msg)| msg)
+    }
+
+    /// Iterate over the buffered messages, oldest first, with the time at which they were buffered
+    pub fn iter(&self) -> impl Iterator<Item = (Instant, &ConsensusMsg<Ctx>)> {
+        self.messages.iter().map(|(since, msg)| (*since, msg))
     }
 
     pub fn is_empty(&self) -> bool {
@@ -0,0 +1,280 @@
+use std::time::Instant;
+
+use malachitebft_core_consensus::PeerId;
+use malachitebft_core_types::{Context, Proposal, Round, Vote, VoteType};
+use tracing::warn;
+
+use crate::network::NetworkEvent;
+use crate::util::streaming::{Sequence, StreamId};
+
+/// Key by which consensus messages are ordered: height and round, message type, sender.
+///
+/// Proposal parts do not carry their height and round, they are keyed by the height consensus
+/// was at when they were received, and ordered by stream and position before the other
+/// messages of that height, whichever peer relayed them.
+type OrderKey<'a, Ctx> = (
+    Option<(<Ctx as Context>::Height, Round)>,
+    u8,
+    Option<&'a <Ctx as Context>::Address>,
+    Option<PeerId>,
+    Option<(&'a StreamId, Sequence)>,
+);
+
+/// Consensus messages received from the network during a tick, to be processed in
+/// a deterministic order rather than in arrival order, see `consensus.deterministic_ordering`.
+pub struct OrderedMessages<Ctx: Context> {
+    /// Messages received since the last tick, with the height consensus was at when they were
+    events: Vec<(Ctx::Height, NetworkEvent<Ctx>)>,
+    max_size: usize,
+    /// When the oldest message received since the last tick was pushed
+    since: Option<Instant>,
+}
+
+impl<Ctx: Context> OrderedMessages<Ctx> {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            events: Vec::new(),
+            max_size,
+            since: None,
+        }
+    }
+
+    /// Whether the event is a consensus message subject to ordering
+    pub fn is_ordered(event: &NetworkEvent<Ctx>) -> bool {
+        matches!(
+            event,
+            NetworkEvent::Vote(..)
+                | NetworkEvent::Proposal(..)
+                | NetworkEvent::ProposalPart(..)
+                | NetworkEvent::PolkaCertificate(..)
+                | NetworkEvent::RoundCertificate(..)
+        )
+    }
+
+    /// Hold a message received while consensus is at the given height until the next tick,
+    /// returning `false` if it was dropped because the buffer is full
+    pub fn push(&mut self, event: NetworkEvent<Ctx>, height: Ctx::Height) -> bool {
+        if self.events.len() < self.max_size {
+            self.since.get_or_insert_with(Instant::now);
+            self.events.push((height, event));
+            true
+        } else {
+            warn!(max_size = %self.max_size, "Ordering buffer is full, dropping message: {event:?}");
+            false
+        }
+    }
+
+    /// Take all the messages received since the last tick, in processing order
+    pub fn take_sorted(&mut self) -> Vec<NetworkEvent<Ctx>> {
+        self.since = None;
+        let mut events = std::mem::take(&mut self.events);
+        events.sort_by(|(a_height, a), (b_height, b)| {
+            order_key(a, a_height).cmp(&order_key(b, b_height))
+        });
+        events.into_iter().map(|(_, event)| event).collect()
+    }
+
+    /// The messages received since the last tick, in arrival order,
+    /// and the time at which the oldest of them was received
+    pub fn pending(&self) -> (impl Iterator<Item = &NetworkEvent<Ctx>>, Option<Instant>) {
+        (self.events.iter().map(|(_, event)| event), self.since)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+fn order_key<'a, Ctx: Context>(
+    event: &'a NetworkEvent<Ctx>,
+    received_at: &Ctx::Height,
+) -> OrderKey<'a, Ctx> {
+    match event {
+        NetworkEvent::ProposalPart(_, part) => (
+            Some((*received_at, Round::Nil)),
+            0,
+            None,
+            None,
+            Some((&part.stream_id, part.sequence)),
+        ),
+        NetworkEvent::Proposal(from, proposal) => (
+            Some((proposal.height(), proposal.round())),
+            1,
+            Some(proposal.validator_address()),
+            Some(*from),
+            None,
+        ),
+        NetworkEvent::Vote(from, vote) => {
+            let kind = match vote.vote_type() {
+                VoteType::Prevote => 2,
+                VoteType::Precommit => 3,
+            };
+
+            (
+                Some((vote.height(), vote.round())),
+                kind,
+                Some(vote.validator_address()),
+                Some(*from),
+                None,
+            )
+        }
+        NetworkEvent::PolkaCertificate(from, certificate) => (
+            Some((certificate.height, certificate.round)),
+            4,
+            None,
+            Some(*from),
+            None,
+        ),
+        NetworkEvent::RoundCertificate(from, certificate) => (
+            Some((certificate.height, certificate.round)),
+            5,
+            None,
+            Some(*from),
+            None,
+        ),
+        _ => (None, u8::MAX, None, None, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use malachitebft_core_types::{NilOrVal, SignedProposal, SignedVote};
+    use malachitebft_test::{Address, Height, Proposal, Signature, TestContext, Value, ValueId};
+
+    use super::*;
+    use crate::util::streaming::{StreamContent, StreamMessage};
+
+    type TestVote = malachitebft_test::Vote;
+
+    fn part(from: PeerId, stream: &'static str, sequence: u64) -> NetworkEvent<TestContext> {
+        NetworkEvent::ProposalPart(
+            from,
+            StreamMessage::new(
+                StreamId::new(Bytes::from_static(stream.as_bytes())),
+                sequence,
+                StreamContent::Fin,
+            ),
+        )
+    }
+
+    #[test]
+    fn messages_are_delivered_by_height_round_type_and_sender() {
+        let (a, b) = (Address::new([1; 20]), Address::new([2; 20]));
+        let (peer, proposer) = (PeerId::random(), PeerId::random());
+
+        let vote =
+            |vote: TestVote| NetworkEvent::Vote(peer, SignedVote::new(vote, Signature::test()));
+        let prevote = |height, round, address| {
+            vote(TestVote::new_prevote(
+                Height::new(height),
+                Round::new(round),
+                NilOrVal::Val(ValueId::new(1)),
+                address,
+            ))
+        };
+        let precommit = |height, round, address| {
+            vote(TestVote::new_precommit(
+                Height::new(height),
+                Round::new(round),
+                NilOrVal::Val(ValueId::new(1)),
+                address,
+            ))
+        };
+        let proposal = |height, round| {
+            let proposal = Proposal::new(
+                Height::new(height),
+                Round::new(round),
+                Value::new(1),
+                Round::Nil,
+                a,
+            );
+            NetworkEvent::Proposal(proposer, SignedProposal::new(proposal, Signature::test()))
+        };
+        let part = |stream, sequence| part(proposer, stream, sequence);
+
+        let mut ordered = OrderedMessages::<TestContext>::new(16);
+
+        // Messages received in arrival order, with the height consensus was at
+        let received = [
+            (precommit(1, 0, b), 1),
+            (part("first", 1), 1),
+            (prevote(1, 1, a), 1),
+            (prevote(1, 0, b), 1),
+            (proposal(1, 0), 1),
+            (prevote(2, 0, a), 1),
+            (prevote(1, 0, a), 1),
+            (part("second", 0), 2),
+            (part("first", 0), 1),
+        ];
+
+        for (event, height) in received {
+            assert!(ordered.push(event, Height::new(height)));
+        }
+
+        assert_eq!(
+            ordered.take_sorted(),
+            vec![
+                // The parts received at a height come first, by stream position
+                part("first", 0),
+                part("first", 1),
+                proposal(1, 0),
+                // Then the votes by round, type and validator
+                prevote(1, 0, a),
+                prevote(1, 0, b),
+                precommit(1, 0, b),
+                prevote(1, 1, a),
+                // The messages of the next height come last
+                part("second", 0),
+                prevote(2, 0, a),
+            ]
+        );
+
+        assert!(ordered.is_empty());
+    }
+
+    #[test]
+    fn parts_are_ordered_regardless_of_the_relaying_peer() {
+        let peers = [PeerId::random(), PeerId::random()];
+
+        // The same parts, relayed by different peers and received in different orders
+        let sorted = |received: [(usize, u64); 3]| {
+            let mut ordered = OrderedMessages::<TestContext>::new(3);
+
+            for (peer, sequence) in received {
+                assert!(ordered.push(part(peers[peer], "stream", sequence), Height::new(1)));
+            }
+
+            ordered
+                .take_sorted()
+                .into_iter()
+                .map(|event| match event {
+                    NetworkEvent::ProposalPart(_, part) => part.sequence,
+                    event => panic!("unexpected event: {event:?}"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted([(0, 2), (1, 0), (0, 1)]), vec![0, 1, 2]);
+        assert_eq!(sorted([(1, 2), (0, 1), (1, 0)]), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn messages_are_dropped_once_the_buffer_is_full() {
+        let mut ordered = OrderedMessages::<TestContext>::new(1);
+
+        let event = |value| {
+            let vote = TestVote::new_prevote(
+                Height::new(1),
+                Round::new(0),
+                NilOrVal::Val(ValueId::new(value)),
+                Address::new([1; 20]),
+            );
+            NetworkEvent::Vote(PeerId::random(), SignedVote::new(vote, Signature::test()))
+        };
+
+        assert!(ordered.push(event(1), Height::new(1)));
+        assert!(!ordered.push(event(2), Height::new(1)));
+        assert_eq!(ordered.pending().0.count(), 1);
+    }
+}
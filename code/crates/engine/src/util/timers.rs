@@ -10,6 +10,7 @@ use std::time::Duration;
 use tokio::task::JoinHandle;
 use tracing::trace;
 
+use super::clock::{Clock, SystemClock};
 use super::output_port::{OutputPort, OutputPortSubscriber};
 
 #[derive(Debug)]
@@ -47,6 +48,7 @@ where
     output_port: Arc<OutputPort<TimeoutElapsed<Key>>>,
     timers: HashMap<Key, Timer<Key>>,
     generations: RangeFrom<u64>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<Key> TimerScheduler<Key>
@@ -54,6 +56,14 @@ where
     Key: Clone + Eq + Hash + Send + 'static,
 {
     pub fn new(subscriber: OutputPortSubscriber<TimeoutElapsed<Key>>) -> Self {
+        Self::with_clock(subscriber, Arc::new(SystemClock::new()))
+    }
+
+    /// Scheduler whose timers elapse according to the given clock
+    pub fn with_clock(
+        subscriber: OutputPortSubscriber<TimeoutElapsed<Key>>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         let output_port = OutputPort::with_capacity(32);
         subscriber.subscribe_to_port(&output_port);
 
@@ -61,6 +71,7 @@ where
             output_port: Arc::new(output_port),
             timers: HashMap::new(),
             generations: 1..,
+            clock,
         }
     }
 
@@ -90,9 +101,10 @@ where
         let task = {
             let key = key.clone();
             let output_port = Arc::clone(&self.output_port);
+            let sleep = self.clock.sleep(timeout);
 
             tokio::spawn(async move {
-                tokio::time::sleep(timeout).await;
+                sleep.await;
                 output_port.send(TimeoutElapsed { key, generation })
             })
         };
@@ -0,0 +1,200 @@
+//! Propagation of the trace context in the messages sent to peers, so that the path of a message
+//! across nodes can be followed in a single trace when the traces are exported, see the `otel` feature.
+//!
+//! The W3C `traceparent` of the span a message is sent in is prepended to the message, behind
+//! a marker which no Protobuf encoding starts with, as Protobuf never encodes a field number of zero.
+//! Messages without it are accepted as is, so that the nodes which do not export their traces
+//! interoperate with the ones which do.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use tracing::Span;
+
+/// Marker of the messages carrying a trace context
+const MARKER: [u8; 3] = [0x00, b'T', b'C'];
+
+/// Length of a `traceparent` of version `00`, that is `00-<trace id>-<span id>-<flags>`
+const TRACEPARENT_LEN: usize = 55;
+
+/// Context of a span of a trace, as propagated to the peers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext(String);
+
+impl TraceContext {
+    /// Parse a W3C `traceparent` of version `00`
+    pub fn from_traceparent(traceparent: &str) -> Option<Self> {
+        let is_hex = |s: &str, len: usize| {
+            s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+
+        let is_zero = |s: &str| s.bytes().all(|b| b == b'0');
+
+        let mut parts = traceparent.split('-');
+
+        let (Some("00"), Some(trace_id), Some(span_id), Some(flags), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return None;
+        };
+
+        let valid = is_hex(trace_id, 32)
+            && is_hex(span_id, 16)
+            && is_hex(flags, 2)
+            && !is_zero(trace_id)
+            && !is_zero(span_id);
+
+        valid.then(|| Self(traceparent.to_string()))
+    }
+
+    pub fn traceparent(&self) -> &str {
+        &self.0
+    }
+
+    /// Context of the given span, if the traces are exported
+    #[cfg(feature = "otel")]
+    pub fn of(span: &Span) -> Option<Self> {
+        use opentelemetry::trace::TraceContextExt;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        let context = span.context();
+        let span_ref = context.span();
+        let span_context = span_ref.span_context();
+
+        if !span_context.is_valid() {
+            return None;
+        }
+
+        Some(Self(format!(
+            "00-{}-{}-{:02x}",
+            span_context.trace_id(),
+            span_context.span_id(),
+            span_context.trace_flags().to_u8()
+        )))
+    }
+
+    /// Context of the given span, if the traces are exported
+    #[cfg(not(feature = "otel"))]
+    pub fn of(_span: &Span) -> Option<Self> {
+        None
+    }
+
+    /// Make the given span a child of the remote span this is the context of,
+    /// if the traces are exported
+    #[cfg(feature = "otel")]
+    pub fn set_as_parent_of(&self, span: &Span) {
+        use opentelemetry::trace::{
+            SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+        };
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        // Validated when parsed
+        let parts = self.0.split('-').collect::<Vec<_>>();
+
+        let (Ok(trace_id), Ok(span_id), Ok(flags)) = (
+            TraceId::from_hex(parts[1]),
+            SpanId::from_hex(parts[2]),
+            u8::from_str_radix(parts[3], 16),
+        ) else {
+            return;
+        };
+
+        let remote = SpanContext::new(
+            trace_id,
+            span_id,
+            TraceFlags::new(flags),
+            true,
+            TraceState::default(),
+        );
+
+        span.set_parent(opentelemetry::Context::new().with_remote_span_context(remote));
+    }
+
+    /// Make the given span a child of the remote span this is the context of,
+    /// if the traces are exported
+    #[cfg(not(feature = "otel"))]
+    pub fn set_as_parent_of(&self, _span: &Span) {}
+}
+
+/// Prepend the trace context, if any, to a message sent to peers
+pub fn wrap(context: Option<&TraceContext>, data: Bytes) -> Bytes {
+    let Some(context) = context else {
+        return data;
+    };
+
+    let mut buf = BytesMut::with_capacity(MARKER.len() + TRACEPARENT_LEN + data.len());
+    buf.put_slice(&MARKER);
+    buf.put_slice(context.traceparent().as_bytes());
+    buf.put_slice(&data);
+    buf.freeze()
+}
+
+/// Split a message received from a peer into its trace context, if any, and the message itself
+pub fn unwrap(data: Bytes) -> (Option<TraceContext>, Bytes) {
+    let header_len = MARKER.len() + TRACEPARENT_LEN;
+
+    if data.len() < header_len || !data.starts_with(&MARKER) {
+        return (None, data);
+    }
+
+    let context = std::str::from_utf8(&data[MARKER.len()..header_len])
+        .ok()
+        .and_then(TraceContext::from_traceparent);
+
+    match context {
+        Some(context) => (Some(context), data.slice(header_len..)),
+        None => (None, data),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn parse_traceparent() {
+        assert!(TraceContext::from_traceparent(TRACEPARENT).is_some());
+
+        for invalid in [
+            "",
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-00",
+        ] {
+            assert_eq!(TraceContext::from_traceparent(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn wrap_and_unwrap() {
+        let context = TraceContext::from_traceparent(TRACEPARENT).unwrap();
+        let data = Bytes::from_static(b"\x0a\x03abc");
+
+        let wrapped = wrap(Some(&context), data.clone());
+        assert_eq!(wrapped.len(), MARKER.len() + TRACEPARENT_LEN + data.len());
+        assert_eq!(unwrap(wrapped), (Some(context), data.clone()));
+
+        assert_eq!(wrap(None, data.clone()), data);
+        assert_eq!(unwrap(data.clone()), (None, data));
+    }
+
+    #[test]
+    fn unwrap_without_valid_context() {
+        // Too short to carry a trace context
+        let data = Bytes::from_static(b"\x00TC\x0a");
+        assert_eq!(unwrap(data.clone()), (None, data));
+
+        // Marker followed by an invalid trace context
+        let mut buf = MARKER.to_vec();
+        buf.extend_from_slice(&[b'x'; TRACEPARENT_LEN + 4]);
+        let data = Bytes::from(buf);
+        assert_eq!(unwrap(data.clone()), (None, data));
+    }
+}
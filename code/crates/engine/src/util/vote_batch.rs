@@ -0,0 +1,148 @@
+//! Batching of the votes published on the consensus channel into a single pubsub message,
+//! see `VoteBatchingConfig`.
+//!
+//! A batch is made of the encoded votes, each prefixed with its length on 4 bytes (big-endian),
+//! behind a marker which no Protobuf encoding starts with, as Protobuf never encodes a field
+//! number of zero. Messages without it are single messages, accepted as is, so that the batches
+//! are split back into votes whether or not the receiving node batches its own votes.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// Marker of the batches of votes
+const MARKER: [u8; 3] = [0x00, b'V', b'B'];
+
+/// Size of the length prefixing each vote of a batch
+const LEN_SIZE: usize = 4;
+
+/// Votes waiting to be published together as a batch
+#[derive(Debug, Default)]
+pub struct VoteBatch {
+    votes: Vec<Bytes>,
+    size: usize,
+}
+
+impl VoteBatch {
+    /// Add an encoded vote to the batch
+    pub fn push(&mut self, vote: Bytes) {
+        self.size += LEN_SIZE + vote.len();
+        self.votes.push(vote);
+    }
+
+    pub fn len(&self) -> usize {
+        self.votes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.votes.is_empty()
+    }
+
+    /// Size of the batch once encoded
+    pub fn size(&self) -> usize {
+        MARKER.len() + self.size
+    }
+
+    /// Whether adding the given vote would take the batch, if not empty, over the given size
+    pub fn would_exceed(&self, vote: &Bytes, max_size: usize) -> bool {
+        !self.is_empty() && self.size() + LEN_SIZE + vote.len() > max_size
+    }
+
+    /// Take the votes of the batch, encoded as a single message, leaving it empty.
+    /// A single vote is left as is, to be received as a single message.
+    pub fn take(&mut self) -> Option<Bytes> {
+        let size = self.size();
+        let mut votes = std::mem::take(&mut self.votes);
+        self.size = 0;
+
+        if votes.len() <= 1 {
+            return votes.pop();
+        }
+
+        let mut buf = BytesMut::with_capacity(size);
+        buf.put_slice(&MARKER);
+
+        for vote in votes {
+            buf.put_u32(vote.len() as u32);
+            buf.put_slice(&vote);
+        }
+
+        Some(buf.freeze())
+    }
+}
+
+/// Split a message received from a peer into the messages it is made of, ie. the votes
+/// of a batch or the message itself if not a batch, or `None` if the batch is malformed
+pub fn split(data: Bytes) -> Option<Vec<Bytes>> {
+    if !data.starts_with(&MARKER) {
+        return Some(vec![data]);
+    }
+
+    let mut rest = data.slice(MARKER.len()..);
+    let mut messages = Vec::new();
+
+    while rest.has_remaining() {
+        if rest.remaining() < LEN_SIZE {
+            return None;
+        }
+
+        let len = rest.get_u32() as usize;
+
+        if len == 0 || rest.remaining() < len {
+            return None;
+        }
+
+        messages.push(rest.split_to(len));
+    }
+
+    Some(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_and_split() {
+        let votes = [
+            Bytes::from_static(b"\x0a\x03abc"),
+            Bytes::from_static(b"\x0a\x01d"),
+            Bytes::from_static(b"\x0a\x02ef"),
+        ];
+
+        let mut batch = VoteBatch::default();
+        assert_eq!(batch.take(), None);
+
+        batch.push(votes[0].clone());
+        assert_eq!(batch.take(), Some(votes[0].clone()));
+        assert!(batch.is_empty());
+
+        for vote in &votes {
+            batch.push(vote.clone());
+        }
+
+        assert!(!batch.would_exceed(&votes[0], batch.size() + LEN_SIZE + votes[0].len()));
+        assert!(batch.would_exceed(&votes[0], batch.size() + LEN_SIZE));
+
+        let size = batch.size();
+        let data = batch.take().unwrap();
+        assert_eq!(data.len(), size);
+        assert_eq!(batch.size(), MARKER.len());
+
+        assert_eq!(split(data), Some(votes.to_vec()));
+        assert_eq!(split(votes[0].clone()), Some(vec![votes[0].clone()]));
+    }
+
+    #[test]
+    fn split_malformed_batch() {
+        // Truncated length
+        assert_eq!(split(Bytes::from_static(b"\x00VB\x00\x00")), None);
+
+        // Truncated vote
+        assert_eq!(
+            split(Bytes::from_static(b"\x00VB\x00\x00\x00\x05abc")),
+            None
+        );
+
+        // Empty vote
+        assert_eq!(split(Bytes::from_static(b"\x00VB\x00\x00\x00\x00")), None);
+    }
+}
@@ -1,6 +1,7 @@
 use std::io;
 use std::marker::PhantomData;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use eyre::eyre;
 use ractor::{async_trait, Actor, ActorProcessingErr, ActorRef, RpcReplyPort, SpawnErr};
@@ -9,15 +10,23 @@ use tracing::{debug, error, info, warn};
 
 use malachitebft_core_types::{Context, Height};
 use malachitebft_metrics::SharedRegistry;
-use malachitebft_wal as wal;
 
+use crate::evidence::EvidenceSnapshot;
+
+mod backend;
 mod entry;
 mod iter;
+mod metrics;
 mod thread;
 
+pub mod replay;
+
+pub use backend::{BackendIter, MemoryBackend, OpenBackend, WalBackend, WalBackendKind};
 pub use entry::WalCodec;
 pub use entry::WalEntry;
+pub use entry::{decode_entry, encode_entry};
 pub use iter::log_entries;
+pub use metrics::Metrics as WalMetrics;
 
 pub type WalRef<Ctx> = ActorRef<Msg<Ctx>>;
 
@@ -42,10 +51,20 @@ where
         _ctx: &Ctx,
         codec: Codec,
         path: PathBuf,
-        _metrics: SharedRegistry,
+        backend: WalBackendKind,
+        group_commit: Option<Duration>,
+        metrics: SharedRegistry,
         span: tracing::Span,
     ) -> Result<WalRef<Ctx>, SpawnErr> {
-        let (actor_ref, _) = Actor::spawn(None, Self::new(span), Args { path, codec }).await?;
+        let args = Args {
+            path,
+            codec,
+            backend,
+            group_commit,
+            metrics: WalMetrics::register(&metrics),
+        };
+
+        let (actor_ref, _) = Actor::spawn(None, Self::new(span), args).await?;
         Ok(actor_ref)
     }
 }
@@ -58,15 +77,27 @@ pub enum Msg<Ctx: Context> {
     Append(Ctx::Height, WalEntry<Ctx>, WalReply<()>),
     Flush(WalReply<()>),
     Dump,
+
+    /// Load the evidence pool saved before the node was restarted
+    LoadEvidence(WalReply<EvidenceSnapshot<Ctx>>),
+
+    /// Save the evidence pool, replacing the one saved previously
+    SaveEvidence(EvidenceSnapshot<Ctx>, WalReply<()>),
 }
 
 pub struct Args<Codec> {
     pub path: PathBuf,
     pub codec: Codec,
+    pub backend: WalBackendKind,
+    /// Window within which flush requests are batched into a single sync to disk,
+    /// if group commit is enabled
+    pub group_commit: Option<Duration>,
+    pub metrics: WalMetrics,
 }
 
 pub struct State<Ctx: Context> {
     height: Ctx::Height,
+    group_commit: bool,
     wal_sender: mpsc::Sender<self::thread::WalMsg<Ctx>>,
     _handle: std::thread::JoinHandle<()>,
 }
@@ -123,6 +154,32 @@ where
             Msg::Dump => {
                 state.wal_sender.send(self::thread::WalMsg::Dump).await?;
             }
+
+            Msg::LoadEvidence(reply_to) => {
+                let (tx, rx) = oneshot::channel();
+
+                state
+                    .wal_sender
+                    .send(self::thread::WalMsg::LoadEvidence(tx))
+                    .await?;
+
+                reply_to
+                    .send(rx.await?)
+                    .map_err(|e| eyre!("Failed to send reply: {e}"))?;
+            }
+
+            Msg::SaveEvidence(snapshot, reply_to) => {
+                let (tx, rx) = oneshot::channel();
+
+                state
+                    .wal_sender
+                    .send(self::thread::WalMsg::SaveEvidence(snapshot, tx))
+                    .await?;
+
+                reply_to
+                    .send(rx.await?)
+                    .map_err(|e| eyre!("Failed to send reply: {e}"))?;
+            }
         }
 
         Ok(())
@@ -207,6 +264,19 @@ where
             .send(self::thread::WalMsg::Flush(tx))
             .await?;
 
+        // With group commit, the flush is only acknowledged once the batch it belongs to
+        // has been synced to disk. Wait for it in the background, so that the entries
+        // appended in the meantime can make it into the same batch.
+        if state.group_commit {
+            tokio::spawn(async move {
+                if let Ok(result) = rx.await {
+                    let _ = reply_to.send(result);
+                }
+            });
+
+            return Ok(());
+        }
+
         let result = rx.await?;
 
         reply_to
@@ -237,16 +307,34 @@ where
         _myself: WalRef<Ctx>,
         args: Self::Arguments,
     ) -> Result<Self::State, ActorProcessingErr> {
-        let log = wal::Log::open(&args.path)?;
-        info!("Opened WAL at {}", args.path.display());
+        let log = args.backend.open(&args.path)?;
+        info!(
+            backend = ?args.backend, group_commit = ?args.group_commit,
+            "Opened WAL at {}", args.path.display()
+        );
+
+        // The evidence pool is kept in its own log next to the WAL,
+        // as it must outlive the height the WAL is reset to
+        let evidence_path = args.path.with_extension("evidence.wal");
+        let evidence_log = args.backend.open(&evidence_path)?;
+        info!("Opened evidence log at {}", evidence_path.display());
 
         let (tx, rx) = mpsc::channel(100);
 
         // Spawn a system thread to perform blocking WAL operations.
-        let handle = self::thread::spawn(self.span.clone(), log, args.codec, rx);
+        let handle = self::thread::spawn(
+            self.span.clone(),
+            log,
+            evidence_log,
+            args.codec,
+            args.group_commit,
+            args.metrics,
+            rx,
+        );
 
         Ok(State {
             height: Ctx::Height::ZERO,
+            group_commit: args.group_commit.is_some(),
             wal_sender: tx,
             _handle: handle,
         })
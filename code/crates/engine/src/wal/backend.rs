@@ -0,0 +1,257 @@
+//! Storage backends for the Write-Ahead Log.
+//!
+//! The WAL actor only ever appends entries for the current height, reads them back
+//! on restart and resets the log when moving to the next height. Any storage able to
+//! perform these operations can be plugged in by implementing [`WalBackend`].
+
+use core::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use malachitebft_wal as wal;
+
+/// Iterator over the raw entries of a WAL backend, oldest first
+pub type BackendIter<'a> = Box<dyn Iterator<Item = io::Result<Vec<u8>>> + 'a>;
+
+/// Operations that a storage backend for the WAL must support
+pub trait WalBackend: Send + 'static {
+    /// The sequence number of the log, ie. the height its entries belong to
+    fn sequence(&self) -> u64;
+
+    /// Number of entries in the log
+    fn len(&self) -> usize;
+
+    /// Whether the log has no entries
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size of the log in bytes, as reported in the logs and metrics
+    fn size_bytes(&self) -> io::Result<u64>;
+
+    /// Remove all entries and start over with the given sequence number
+    fn reset(&mut self, sequence: u64) -> io::Result<()>;
+
+    /// Append an entry to the log.
+    ///
+    /// The entry is only guaranteed to be durable once [`WalBackend::flush`] returns.
+    fn append(&mut self, data: &[u8]) -> io::Result<()>;
+
+    /// Make all appended entries durable
+    fn flush(&mut self) -> io::Result<()>;
+
+    /// Remove all entries from the given index onwards
+    fn truncate(&mut self, from_entry: u64) -> io::Result<()>;
+
+    /// Iterate over the entries of the log, oldest first
+    fn entries(&mut self) -> io::Result<BackendIter<'_>>;
+}
+
+/// The default backend, an append-only file synced to disk on every flush
+impl WalBackend for wal::Log {
+    fn sequence(&self) -> u64 {
+        wal::Log::sequence(self)
+    }
+
+    fn len(&self) -> usize {
+        wal::Log::len(self)
+    }
+
+    fn size_bytes(&self) -> io::Result<u64> {
+        wal::Log::size_bytes(self)
+    }
+
+    fn reset(&mut self, sequence: u64) -> io::Result<()> {
+        wal::Log::reset(self, sequence)
+    }
+
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        wal::Log::append(self, data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        wal::Log::flush(self)
+    }
+
+    fn truncate(&mut self, from_entry: u64) -> io::Result<()> {
+        wal::Log::truncate(self, from_entry)
+    }
+
+    fn entries(&mut self) -> io::Result<BackendIter<'_>> {
+        Ok(Box::new(self.iter()?))
+    }
+}
+
+/// A log split into segments of bounded size, keeping the segments
+/// of the previous heights for a while, see [`wal::SegmentedLog`]
+impl WalBackend for wal::SegmentedLog {
+    fn sequence(&self) -> u64 {
+        wal::SegmentedLog::sequence(self)
+    }
+
+    fn len(&self) -> usize {
+        wal::SegmentedLog::len(self)
+    }
+
+    fn size_bytes(&self) -> io::Result<u64> {
+        wal::SegmentedLog::size_bytes(self)
+    }
+
+    fn reset(&mut self, sequence: u64) -> io::Result<()> {
+        wal::SegmentedLog::reset(self, sequence)
+    }
+
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        wal::SegmentedLog::append(self, data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        wal::SegmentedLog::flush(self)
+    }
+
+    fn truncate(&mut self, from_entry: u64) -> io::Result<()> {
+        wal::SegmentedLog::truncate(self, from_entry)
+    }
+
+    fn entries(&mut self) -> io::Result<BackendIter<'_>> {
+        Ok(Box::new(self.iter()))
+    }
+}
+
+/// A backend keeping the entries in memory only.
+///
+/// Nothing survives a restart, so a validator using this backend may equivocate
+/// if it crashes in the middle of a height. Only meant for nodes which do not sign,
+/// or for setups where durability is provided by other means, eg. a replicated
+/// block device on which fsync is prohibitively slow.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    sequence: u64,
+    entries: Vec<Vec<u8>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl WalBackend for MemoryBackend {
+    fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn size_bytes(&self) -> io::Result<u64> {
+        Ok(self.entries.iter().map(|e| e.len() as u64).sum())
+    }
+
+    fn reset(&mut self, sequence: u64) -> io::Result<()> {
+        self.sequence = sequence;
+        self.entries.clear();
+        Ok(())
+    }
+
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        self.entries.push(data.to_vec());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn truncate(&mut self, from_entry: u64) -> io::Result<()> {
+        self.entries
+            .truncate(usize::try_from(from_entry).unwrap_or(usize::MAX));
+        Ok(())
+    }
+
+    fn entries(&mut self) -> io::Result<BackendIter<'_>> {
+        Ok(Box::new(self.entries.iter().cloned().map(Ok)))
+    }
+}
+
+/// Opens a custom backend at the given path
+pub type OpenBackend = Arc<dyn Fn(&Path) -> io::Result<Box<dyn WalBackend>> + Send + Sync>;
+
+/// Which storage backend to use for the WAL
+#[derive(Clone, Default)]
+pub enum WalBackendKind {
+    /// Append-only file, synced to disk on every flush, see [`malachitebft_wal::Log`]
+    #[default]
+    File,
+
+    /// Append-only files rotated once they exceed a maximum size, with the files
+    /// of the previous heights deleted past a retention threshold
+    Segmented(wal::SegmentOptions),
+
+    /// Keep the entries in memory only, see [`MemoryBackend`]
+    Memory,
+
+    /// A backend provided by the application, eg. on top of an embedded database
+    Custom(OpenBackend),
+}
+
+impl WalBackendKind {
+    /// Use a backend provided by the application, opened with the given function
+    pub fn custom<F>(open: F) -> Self
+    where
+        F: Fn(&Path) -> io::Result<Box<dyn WalBackend>> + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(open))
+    }
+
+    /// Open the backend at the given path
+    pub fn open(&self, path: &Path) -> io::Result<Box<dyn WalBackend>> {
+        match self {
+            Self::File => Ok(Box::new(wal::Log::open(path)?)),
+            Self::Segmented(options) => Ok(Box::new(wal::SegmentedLog::open(path, *options)?)),
+            Self::Memory => Ok(Box::new(MemoryBackend::new())),
+            Self::Custom(open) => open(path),
+        }
+    }
+}
+
+impl fmt::Debug for WalBackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File => write!(f, "File"),
+            Self::Segmented(options) => write!(f, "Segmented({options:?})"),
+            Self::Memory => write!(f, "Memory"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_backend() {
+        let mut backend = MemoryBackend::new();
+        backend.reset(1).unwrap();
+
+        for entry in [b"a".as_slice(), b"bb", b"ccc"] {
+            backend.append(entry).unwrap();
+        }
+
+        assert_eq!(backend.sequence(), 1);
+        assert_eq!(backend.len(), 3);
+        assert_eq!(backend.size_bytes().unwrap(), 6);
+
+        backend.truncate(2).unwrap();
+
+        let entries = backend.entries().unwrap().collect::<io::Result<Vec<_>>>();
+        assert_eq!(entries.unwrap(), vec![b"a".to_vec(), b"bb".to_vec()]);
+
+        backend.reset(2).unwrap();
+        assert_eq!(backend.sequence(), 2);
+        assert!(backend.is_empty());
+    }
+}
@@ -100,9 +100,7 @@ where
     C: WalCodec<Ctx>,
     R: Read,
 {
-    let len = buf.read_u64::<BE>()?;
-    let mut bytes = vec![0; len as usize];
-    buf.read_exact(&mut bytes)?;
+    let bytes = read_bytes(buf)?;
 
     codec.decode(bytes.into()).map_err(|e| {
         io::Error::new(
@@ -112,6 +110,24 @@ where
     })
 }
 
+/// Read bytes prefixed with their length, without trusting the length
+/// to allocate the buffer, as the entry may be corrupted
+fn read_bytes(mut buf: impl Read) -> io::Result<Vec<u8>> {
+    let len = buf.read_u64::<BE>()?;
+
+    let mut bytes = Vec::new();
+    buf.take(len).read_to_end(&mut bytes)?;
+
+    if (bytes.len() as u64) < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("entry is {} bytes long, expected {len}", bytes.len()),
+        ));
+    }
+
+    Ok(bytes)
+}
+
 // Timeout helpers
 fn encode_timeout(tag: u8, timeout: &Timeout, mut buf: impl Write) -> io::Result<()> {
     use malachitebft_core_types::TimeoutKind;
@@ -227,9 +243,7 @@ where
     C: WalCodec<Ctx>,
     R: Read,
 {
-    let len = buf.read_u64::<BE>()?;
-    let mut bytes = vec![0; len as usize];
-    buf.read_exact(&mut bytes)?;
+    let bytes = read_bytes(buf)?;
 
     codec.decode(bytes.into()).map_err(|e| {
         io::Error::new(
@@ -238,3 +252,46 @@ where
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use malachitebft_test::codec::proto::ProtobufCodec;
+    use malachitebft_test::TestContext;
+
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> io::Result<WalEntry<TestContext>> {
+        decode_entry(&ProtobufCodec, bytes)
+    }
+
+    #[test]
+    fn timeout_entries_roundtrip() {
+        let timeout = Timeout::prevote(Round::new(1));
+
+        let mut bytes = Vec::new();
+        encode_entry::<TestContext, _, _>(&WalEntry::Timeout(timeout), &ProtobufCodec, &mut bytes)
+            .unwrap();
+
+        assert!(matches!(decode(&bytes), Ok(WalEntry::Timeout(t)) if t == timeout));
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn entries_longer_than_their_bytes_are_rejected() {
+        for tag in [TAG_CONSENSUS, TAG_PROPOSED_VALUE] {
+            // A corrupted length must not be trusted to allocate the buffer
+            let mut bytes = vec![tag];
+            bytes.extend_from_slice(&u64::MAX.to_be_bytes());
+            bytes.extend_from_slice(&[0; 16]);
+
+            let error = decode(&bytes).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn unknown_tags_are_rejected() {
+        let error = decode(&[0xff]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -2,30 +2,31 @@ use std::io;
 use std::marker::PhantomData;
 
 use malachitebft_core_types::Context;
-use malachitebft_wal as wal;
 
 use eyre::Result;
 
+use super::backend::{BackendIter, WalBackend};
 use super::entry::decode_entry;
 use super::{WalCodec, WalEntry};
 
-pub fn log_entries<'a, Ctx, Codec>(
-    log: &'a mut wal::Log,
+pub fn log_entries<'a, Ctx, Codec, B>(
+    log: &'a mut B,
     codec: &'a Codec,
 ) -> Result<WalIter<'a, Ctx, Codec>>
 where
     Ctx: Context,
     Codec: WalCodec<Ctx>,
+    B: WalBackend + ?Sized,
 {
     Ok(WalIter {
-        iter: log.iter()?,
+        iter: log.entries()?,
         codec,
         _marker: PhantomData,
     })
 }
 
 pub struct WalIter<'a, Ctx, Codec> {
-    iter: wal::LogIter<'a>,
+    iter: BackendIter<'a>,
     codec: &'a Codec,
     _marker: PhantomData<Ctx>,
 }
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use malachitebft_metrics::prometheus::metrics::histogram::{exponential_buckets, Histogram};
+use malachitebft_metrics::SharedRegistry;
+
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    /// Time taken to sync the WAL to disk
+    pub fsync_latency: Histogram,
+
+    /// Number of flush requests served by a single sync of the WAL to disk
+    pub batch_size: Histogram,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            fsync_latency: Histogram::new(exponential_buckets(0.0001, 2.0, 16)),
+            batch_size: Histogram::new(exponential_buckets(1.0, 2.0, 10)),
+        }
+    }
+
+    pub fn register(registry: &SharedRegistry) -> Self {
+        let metrics = Self::new();
+
+        registry.with_prefix("malachitebft_wal", |registry| {
+            registry.register(
+                "fsync_latency",
+                "Time taken to sync the WAL to disk, in seconds",
+                metrics.fsync_latency.clone(),
+            );
+
+            registry.register(
+                "batch_size",
+                "Number of flush requests served by a single sync of the WAL to disk",
+                metrics.batch_size.clone(),
+            );
+        });
+
+        metrics
+    }
+
+    pub fn observe_flush(&self, latency: Duration, batch_size: usize) {
+        self.fsync_latency.observe(latency.as_secs_f64());
+        self.batch_size.observe(batch_size as f64);
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
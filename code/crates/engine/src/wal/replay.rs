@@ -0,0 +1,394 @@
+//! Deterministic replay of the Write-Ahead Log, to debug divergence and crash-recovery issues.
+//!
+//! The entries written to the WAL for a height are fed, in order, to a fresh consensus driver,
+//! and the consensus state reached after each of them is recorded. The driver being
+//! deterministic, replaying a WAL always yields the same transitions, which can then be
+//! compared with the ones reconstructed from the WAL of another node for the same height
+//! with [`diff`], to find the first round at which both nodes disagree.
+//!
+//! Only the consensus state machine is replayed: nothing is verified, signed on behalf of the
+//! node, published or persisted, and the proposed values keep the validity recorded in the WAL.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use derive_where::derive_where;
+
+use malachitebft_core_consensus::{ProposedValue, SignedConsensusMsg};
+use malachitebft_core_driver::{Driver, Input, Output, Step, StoredProposal, ThresholdParams};
+use malachitebft_core_state_machine::state::RoundValue;
+use malachitebft_core_types::{Context, Proposal, Round, Validator, Value, ValueId, ValuePayload};
+use malachitebft_wal as wal;
+
+use super::{log_entries, WalCodec, WalEntry};
+
+/// The consensus state of a node at a height, as far as the other nodes are concerned
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub struct ConsensusStep<Ctx: Context> {
+    /// Current round
+    pub round: Round,
+    /// Current step within the round
+    pub step: Step,
+    /// Value we are locked on, along with the round at which we locked on it
+    pub locked: Option<(Round, ValueId<Ctx>)>,
+    /// Value for which we saw a polka, along with the round of the polka
+    pub valid: Option<(Round, ValueId<Ctx>)>,
+    /// Value decided on, along with the round of its proposal
+    pub decided: Option<(Round, ValueId<Ctx>)>,
+}
+
+impl<Ctx: Context> ConsensusStep<Ctx> {
+    fn from_driver(driver: &Driver<Ctx>) -> Self {
+        let state = driver.round_state();
+        let id = |rv: &RoundValue<Ctx::Value>| (rv.round, rv.value.id());
+
+        Self {
+            round: state.round,
+            step: state.step,
+            locked: state.locked.as_ref().map(id),
+            valid: state.valid.as_ref().map(id),
+            decided: state.decision.as_ref().map(id),
+        }
+    }
+}
+
+/// A change of the consensus state
+#[derive_where(Clone, Debug)]
+pub struct Transition<Ctx: Context> {
+    /// Index of the WAL entry which caused the transition, `None` when starting the height
+    pub entry: Option<usize>,
+    /// State reached once the entry was processed
+    pub state: ConsensusStep<Ctx>,
+    /// What the driver asked for while processing the entry, eg. to vote or schedule a timeout
+    pub outputs: Vec<Output<Ctx>>,
+}
+
+/// The outcome of replaying the WAL entries of a height
+#[derive_where(Clone, Debug)]
+pub struct ReplayedHeight<Ctx: Context> {
+    /// The height that was replayed
+    pub height: Ctx::Height,
+    /// Number of WAL entries that were replayed
+    pub entries: usize,
+    /// Transitions of the consensus state, in order
+    pub transitions: Vec<Transition<Ctx>>,
+    /// Entries which could not be read or were rejected by the driver, along with the reason,
+    /// with no entry index if starting the height failed
+    pub errors: Vec<(Option<usize>, String)>,
+}
+
+impl<Ctx: Context> ReplayedHeight<Ctx> {
+    /// The state reached at the end of the replay
+    pub fn final_state(&self) -> Option<&ConsensusStep<Ctx>> {
+        self.transitions.last().map(|t| &t.state)
+    }
+
+    /// Whether the height was decided by the end of the replay
+    pub fn is_decided(&self) -> bool {
+        self.final_state().is_some_and(|s| s.decided.is_some())
+    }
+
+    /// The last state reached in each round, the basis for comparing two replays
+    pub fn rounds(&self) -> BTreeMap<Round, &ConsensusStep<Ctx>> {
+        self.transitions
+            .iter()
+            .map(|t| (t.state.round, &t.state))
+            .collect()
+    }
+}
+
+/// The first round at which the states reconstructed from two WALs for the same height differ
+#[derive_where(Clone, Debug)]
+pub struct Divergence<Ctx: Context> {
+    /// The round at which the states differ
+    pub round: Round,
+    /// Last state reached in that round according to the first WAL, if it reached that round
+    pub left: Option<ConsensusStep<Ctx>>,
+    /// Last state reached in that round according to the second WAL, if it reached that round
+    pub right: Option<ConsensusStep<Ctx>>,
+}
+
+/// Compare the replays of the WALs of two nodes for the same height, round by round.
+///
+/// The order in which messages are received differs from one node to another, so only the last
+/// state reached in each round is compared. Returns `None` if both nodes went through the same
+/// rounds and ended them in the same state.
+pub fn diff<Ctx: Context>(
+    left: &ReplayedHeight<Ctx>,
+    right: &ReplayedHeight<Ctx>,
+) -> Option<Divergence<Ctx>> {
+    let left = left.rounds();
+    let right = right.rounds();
+
+    let mut rounds = left.keys().chain(right.keys()).copied().collect::<Vec<_>>();
+    rounds.sort();
+    rounds.dedup();
+
+    rounds.into_iter().find_map(|round| {
+        let (l, r) = (left.get(&round), right.get(&round));
+
+        (l != r).then(|| Divergence {
+            round,
+            left: l.map(|&s| s.clone()),
+            right: r.map(|&s| s.clone()),
+        })
+    })
+}
+
+/// Read the entries of the WAL at the given path, grouped by height.
+///
+/// If the WAL is made of segments, the entries of the heights retained
+/// alongside the current one are returned as well.
+pub fn read_heights<Ctx, Codec>(
+    path: &Path,
+    codec: &Codec,
+) -> eyre::Result<BTreeMap<u64, Vec<io::Result<WalEntry<Ctx>>>>>
+where
+    Ctx: Context,
+    Codec: WalCodec<Ctx>,
+{
+    let mut heights = BTreeMap::new();
+
+    if path.is_file() {
+        let mut log = wal::Log::open(path)?;
+        let entries = log_entries(&mut log, codec)?.collect();
+        heights.insert(log.sequence(), entries);
+
+        return Ok(heights);
+    }
+
+    // Do not let opening the log delete any of the retained segments
+    let options = wal::SegmentOptions {
+        retain_sequences: u64::MAX,
+        ..Default::default()
+    };
+
+    let mut log = wal::SegmentedLog::open(path, options)?;
+
+    for sequence in log.retained_sequences()? {
+        let mut entries = Vec::new();
+
+        for mut segment in log.open_retained(sequence)? {
+            entries.extend(log_entries(&mut segment, codec)?);
+        }
+
+        heights.insert(sequence, entries);
+    }
+
+    let entries = log_entries(&mut log, codec)?.collect();
+    heights.insert(log.sequence(), entries);
+
+    Ok(heights)
+}
+
+/// Replays the WAL entries of a height, see the [module docs](self)
+pub struct Replayer<Ctx: Context> {
+    ctx: Ctx,
+    address: Ctx::Address,
+    value_payload: ValuePayload,
+}
+
+impl<Ctx: Context> Replayer<Ctx> {
+    /// Create a replayer for the node with the given address.
+    ///
+    /// In parts-only mode, proposals are not written to the WAL and are rebuilt,
+    /// unsigned, from the proposed values instead, as consensus itself does.
+    pub fn new(ctx: Ctx, address: Ctx::Address, value_payload: ValuePayload) -> Self {
+        Self {
+            ctx,
+            address,
+            value_payload,
+        }
+    }
+
+    /// Replay the given WAL entries for a height, stopping at the first entry that cannot be read
+    pub async fn replay(
+        &self,
+        height: Ctx::Height,
+        validator_set: Ctx::ValidatorSet,
+        entries: impl IntoIterator<Item = io::Result<WalEntry<Ctx>>>,
+    ) -> ReplayedHeight<Ctx> {
+        let driver = Driver::new(
+            self.ctx.clone(),
+            height,
+            validator_set,
+            self.address.clone(),
+            ThresholdParams::default(),
+        );
+
+        let mut replay = HeightReplay {
+            driver,
+            proposals: Vec::new(),
+            values: Vec::new(),
+            result: ReplayedHeight {
+                height,
+                entries: 0,
+                transitions: Vec::new(),
+                errors: Vec::new(),
+            },
+        };
+
+        // Consensus always starts a height at round 0
+        let proposer = self.proposer(&replay.driver, height, Round::new(0));
+        let outcome = self.apply(
+            &mut replay.driver,
+            Input::NewRound(height, Round::new(0), proposer),
+        );
+        replay.record(None, outcome);
+
+        for (idx, entry) in entries.into_iter().enumerate() {
+            replay.result.entries += 1;
+
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    replay.result.errors.push((Some(idx), e.to_string()));
+                    break;
+                }
+            };
+
+            let outcome = self.replay_entry(&mut replay, entry).await;
+            replay.record(Some(idx), outcome);
+        }
+
+        replay.result
+    }
+
+    async fn replay_entry(
+        &self,
+        replay: &mut HeightReplay<Ctx>,
+        entry: WalEntry<Ctx>,
+    ) -> Result<Vec<Output<Ctx>>, String> {
+        match entry {
+            WalEntry::ConsensusMsg(SignedConsensusMsg::Vote(vote)) => {
+                self.apply(&mut replay.driver, Input::Vote(vote))
+            }
+
+            WalEntry::ConsensusMsg(SignedConsensusMsg::Proposal(proposal)) => {
+                let value = replay
+                    .values
+                    .iter()
+                    .find(|value| is_proposal_for(&proposal.message, value))
+                    .map(|value| value.validity);
+
+                replay.proposals.push(proposal.clone().into());
+
+                match value {
+                    Some(validity) => {
+                        self.apply(&mut replay.driver, Input::Proposal(proposal, validity))
+                    }
+                    // Wait for the value to be received
+                    None => Ok(Vec::new()),
+                }
+            }
+
+            WalEntry::ProposedValue(value) => {
+                if self.value_payload.parts_only() {
+                    let proposal = self.ctx.new_proposal(
+                        value.height,
+                        value.round,
+                        value.value.clone(),
+                        value.valid_round,
+                        value.proposer.clone(),
+                    );
+
+                    replay.proposals.push(StoredProposal::implicit(proposal));
+                }
+
+                let proposals = replay
+                    .proposals
+                    .iter()
+                    .filter(|proposal| is_proposal_for(&proposal.message, &value))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                let mut outputs = Vec::new();
+
+                for proposal in proposals {
+                    let input = Input::from_stored_proposal(proposal, value.validity);
+                    outputs.extend(self.apply(&mut replay.driver, input)?);
+                }
+
+                replay.values.push(value);
+
+                Ok(outputs)
+            }
+
+            WalEntry::Timeout(timeout) => {
+                self.apply(&mut replay.driver, Input::TimeoutElapsed(timeout))
+            }
+        }
+    }
+
+    /// Apply an input to the driver, as well as the inputs it leads to
+    /// without involving the network nor the application, ie. starting a new round
+    fn apply(
+        &self,
+        driver: &mut Driver<Ctx>,
+        input: Input<Ctx>,
+    ) -> Result<Vec<Output<Ctx>>, String> {
+        let mut inputs = vec![input];
+        let mut outputs = Vec::new();
+
+        while let Some(input) = inputs.pop() {
+            for output in driver.process(input).map_err(|e| e.to_string())? {
+                if let Output::NewRound(height, round) = &output {
+                    let proposer = self.proposer(driver, *height, *round);
+                    inputs.push(Input::NewRound(*height, *round, proposer));
+                }
+
+                outputs.push(output);
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    fn proposer(&self, driver: &Driver<Ctx>, height: Ctx::Height, round: Round) -> Ctx::Address {
+        self.ctx
+            .select_proposer(driver.validator_set(), height, round)
+            .address()
+            .clone()
+    }
+}
+
+struct HeightReplay<Ctx: Context> {
+    driver: Driver<Ctx>,
+    /// Proposals replayed so far, including the implicit ones in parts-only mode
+    proposals: Vec<StoredProposal<Ctx>>,
+    /// Values replayed so far
+    values: Vec<ProposedValue<Ctx>>,
+    result: ReplayedHeight<Ctx>,
+}
+
+impl<Ctx: Context> HeightReplay<Ctx> {
+    /// Record a transition if the entry changed the state or made the driver do anything
+    fn record(&mut self, entry: Option<usize>, outcome: Result<Vec<Output<Ctx>>, String>) {
+        let outputs = match outcome {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                self.result.errors.push((entry, e));
+                return;
+            }
+        };
+
+        let state = ConsensusStep::from_driver(&self.driver);
+
+        if outputs.is_empty() && self.result.final_state() == Some(&state) {
+            return;
+        }
+
+        self.result.transitions.push(Transition {
+            entry,
+            state,
+            outputs,
+        });
+    }
+}
+
+fn is_proposal_for<Ctx: Context>(proposal: &Ctx::Proposal, value: &ProposedValue<Ctx>) -> bool {
+    proposal.height() == value.height
+        && proposal.round() == value.round
+        && proposal.value().id() == value.value.id()
+}
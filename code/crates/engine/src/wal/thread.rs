@@ -1,17 +1,22 @@
 use std::ops::ControlFlow;
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use std::{io, thread};
 
 use eyre::{eyre, Result};
+use tokio::runtime::Handle;
 use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, error, info};
 
 use malachitebft_core_types::{Context, Height};
-use malachitebft_wal as wal;
 
+use crate::evidence::{decode_evidence, encode_evidence, EvidenceSnapshot};
+
+use super::backend::WalBackend;
 use super::entry::{decode_entry, encode_entry, WalCodec, WalEntry};
 use super::iter::log_entries;
+use super::metrics::Metrics;
 
 pub type ReplyTo<T> = oneshot::Sender<Result<T>>;
 
@@ -22,32 +27,83 @@ pub enum WalMsg<Ctx: Context> {
     Flush(ReplyTo<()>),
     Shutdown,
     Dump,
+    LoadEvidence(ReplyTo<EvidenceSnapshot<Ctx>>),
+    SaveEvidence(EvidenceSnapshot<Ctx>, ReplyTo<()>),
 }
 
+const TAG_EVIDENCE: u8 = 0x01;
+
 pub fn spawn<Ctx, Codec>(
     span: tracing::Span,
-    mut log: wal::Log,
+    mut log: Box<dyn WalBackend>,
+    mut evidence_log: Box<dyn WalBackend>,
     codec: Codec,
+    group_commit: Option<Duration>,
+    metrics: Metrics,
     mut rx: mpsc::Receiver<WalMsg<Ctx>>,
 ) -> JoinHandle<()>
 where
     Ctx: Context,
     Codec: WalCodec<Ctx>,
 {
+    // Used to wait for more flush requests with a timeout when batching them
+    let runtime = Handle::current();
+
     thread::spawn(move || {
         let result = catch_unwind(AssertUnwindSafe(|| {
-            while let Some(msg) = rx.blocking_recv() {
-                match process_msg(msg, &span, &mut log, &codec) {
-                    Ok(ControlFlow::Continue(())) => continue,
+            let mut next = rx.blocking_recv();
+
+            while let Some(msg) = next.take() {
+                let msg = match (msg, group_commit) {
+                    (WalMsg::Flush(reply), Some(window)) => {
+                        let (replies, pending) = collect_batch(
+                            reply,
+                            window,
+                            &runtime,
+                            &mut rx,
+                            &span,
+                            log.as_mut(),
+                            evidence_log.as_mut(),
+                            &codec,
+                            &metrics,
+                        );
+
+                        flush(log.as_mut(), replies, &metrics);
+
+                        match pending {
+                            Some(msg) => msg,
+                            None => {
+                                next = rx.blocking_recv();
+                                continue;
+                            }
+                        }
+                    }
+                    (msg, _) => msg,
+                };
+
+                let result = process_msg(
+                    msg,
+                    &span,
+                    log.as_mut(),
+                    evidence_log.as_mut(),
+                    &codec,
+                    &metrics,
+                );
+
+                match result {
+                    Ok(ControlFlow::Continue(())) => (),
                     Ok(ControlFlow::Break(())) => break,
                     Err(e) => error!("WAL task failed: {e}"),
                 }
+
+                next = rx.blocking_recv();
             }
 
             info!("WAL thread exiting");
 
             // Task finished normally, stop the thread
             drop(log);
+            drop(evidence_log);
         }));
 
         if let Err(e) = result {
@@ -56,6 +112,58 @@ where
     })
 }
 
+/// Group commit: collect the flush requests received within `window` of the first one,
+/// so that they can all be served by a single sync of the WAL to disk.
+///
+/// Entries appended in the meantime are written to the log right away, and will be
+/// synced along with the others. Any other message ends the batch early, and is
+/// returned to be processed once the batch has been flushed.
+#[allow(clippy::too_many_arguments)]
+fn collect_batch<Ctx, Codec>(
+    first: ReplyTo<()>,
+    window: Duration,
+    runtime: &Handle,
+    rx: &mut mpsc::Receiver<WalMsg<Ctx>>,
+    span: &tracing::Span,
+    log: &mut dyn WalBackend,
+    evidence_log: &mut dyn WalBackend,
+    codec: &Codec,
+    metrics: &Metrics,
+) -> (Vec<ReplyTo<()>>, Option<WalMsg<Ctx>>)
+where
+    Ctx: Context,
+    Codec: WalCodec<Ctx>,
+{
+    let deadline = Instant::now() + window;
+    let mut replies = vec![first];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+
+        if remaining.is_zero() {
+            return (replies, None);
+        }
+
+        let msg = match runtime.block_on(tokio::time::timeout(remaining, rx.recv())) {
+            Ok(Some(msg)) => msg,
+            // Window elapsed, or the WAL actor is gone
+            Ok(None) | Err(_) => return (replies, None),
+        };
+
+        match msg {
+            WalMsg::Flush(reply) => replies.push(reply),
+
+            msg @ WalMsg::Append(..) => {
+                if let Err(e) = process_msg(msg, span, log, evidence_log, codec, metrics) {
+                    error!("WAL task failed: {e}");
+                }
+            }
+
+            msg => return (replies, Some(msg)),
+        }
+    }
+}
+
 #[tracing::instrument(
     name = "wal",
     parent = span,
@@ -65,8 +173,10 @@ where
 fn process_msg<Ctx, Codec>(
     msg: WalMsg<Ctx>,
     span: &tracing::Span,
-    log: &mut wal::Log,
+    log: &mut dyn WalBackend,
+    evidence_log: &mut dyn WalBackend,
     codec: &Codec,
+    metrics: &Metrics,
 ) -> Result<ControlFlow<()>>
 where
     Ctx: Context,
@@ -117,6 +227,9 @@ where
             // Capture encoding result and always send a reply to prevent deadlock
             let result = encode_entry(&entry, codec, &mut buf)
                 .and_then(|_| {
+                    #[cfg(feature = "chaos")]
+                    crate::chaos::toggles().delay_wal_write();
+
                     if !buf.is_empty() {
                         log.append(&buf)
                     } else {
@@ -140,26 +253,36 @@ where
         }
 
         WalMsg::Flush(reply) => {
-            let result = log.flush().map_err(Into::into);
+            flush(log, vec![reply], metrics);
+        }
 
-            if let Err(e) = &result {
-                error!("ATTENTION: Failed to flush WAL to disk: {e}");
-            } else {
-                debug!(
-                    wal.entries = %log.len(),
-                    wal.size = %log.size_bytes().unwrap_or(0),
-                    "Flushed WAL to disk"
-                );
+        WalMsg::Dump => {
+            if let Err(e) = dump_entries(log, codec) {
+                error!("Failed to dump WAL: {e}");
+            }
+        }
+
+        WalMsg::LoadEvidence(reply) => {
+            let result = load_evidence(evidence_log, codec);
+
+            if let Ok(snapshot) = &result {
+                debug!(evidence = snapshot.evidence.len(), "Loaded evidence");
             }
 
             if reply.send(result).is_err() {
-                error!("Failed to send WAL flush reply");
+                error!("Failed to send evidence load reply");
             }
         }
 
-        WalMsg::Dump => {
-            if let Err(e) = dump_entries(log, codec) {
-                error!("Failed to dump WAL: {e}");
+        WalMsg::SaveEvidence(snapshot, reply) => {
+            let result = save_evidence(evidence_log, codec, &snapshot);
+
+            if let Err(e) = &result {
+                error!("ATTENTION: Failed to save evidence: {e}");
+            }
+
+            if reply.send(result).is_err() {
+                error!("Failed to send evidence save reply");
             }
         }
 
@@ -172,8 +295,39 @@ where
     Ok(ControlFlow::Continue(()))
 }
 
+/// Sync the WAL to disk, and notify all the callers waiting for it
+fn flush(log: &mut dyn WalBackend, replies: Vec<ReplyTo<()>>, metrics: &Metrics) {
+    let start = Instant::now();
+    let result = log.flush();
+    let latency = start.elapsed();
+
+    metrics.observe_flush(latency, replies.len());
+
+    match &result {
+        Err(e) => error!("ATTENTION: Failed to flush WAL to disk: {e}"),
+        Ok(()) => debug!(
+            wal.entries = %log.len(),
+            wal.size = %log.size_bytes().unwrap_or(0),
+            batch.size = %replies.len(),
+            latency = ?latency,
+            "Flushed WAL to disk"
+        ),
+    }
+
+    for reply in replies {
+        let result = result
+            .as_ref()
+            .map_err(|e| eyre!("Failed to flush WAL to disk: {e}"))
+            .copied();
+
+        if reply.send(result).is_err() {
+            error!("Failed to send WAL flush reply");
+        }
+    }
+}
+
 fn fetch_entries<Ctx, Codec>(
-    log: &mut wal::Log,
+    log: &mut dyn WalBackend,
     codec: &Codec,
 ) -> Result<Vec<io::Result<WalEntry<Ctx>>>>
 where
@@ -185,10 +339,11 @@ where
     }
 
     let iter = log
-        .iter()
+        .entries()
         .map_err(|e| eyre!("Failed to open WAL for reading entries: {e}"))?;
 
     let mut entries = Vec::new();
+    let mut corrupted_at = None;
 
     for (idx, result) in iter.enumerate() {
         match result {
@@ -199,19 +354,92 @@ where
             Err(e) => {
                 error!("Failed to read WAL entry {idx}: {e}");
                 entries.push(Err(e));
-
-                log.truncate(idx as u64).map_err(|e| {
-                    eyre!("Failed to truncate WAL after read error at entry {idx}: {e}")
-                })?;
-
+                corrupted_at = Some(idx);
                 break;
             }
         }
     }
 
+    if let Some(idx) = corrupted_at {
+        log.truncate(idx as u64)
+            .map_err(|e| eyre!("Failed to truncate WAL after read error at entry {idx}: {e}"))?;
+    }
+
     Ok(entries)
 }
 
+fn load_evidence<Ctx, Codec>(
+    evidence_log: &mut dyn WalBackend,
+    codec: &Codec,
+) -> Result<EvidenceSnapshot<Ctx>>
+where
+    Ctx: Context,
+    Codec: WalCodec<Ctx>,
+{
+    let mut snapshot = EvidenceSnapshot::default();
+
+    if evidence_log.is_empty() {
+        return Ok(snapshot);
+    }
+
+    let iter = evidence_log
+        .entries()
+        .map_err(|e| eyre!("Failed to open evidence log for reading: {e}"))?;
+
+    for (idx, result) in iter.enumerate() {
+        let bytes = result.map_err(|e| eyre!("Failed to read evidence entry {idx}: {e}"))?;
+
+        let Some((tag, data)) = bytes.split_first() else {
+            continue;
+        };
+
+        let evidence = decode_evidence(codec, io::Cursor::new(data))
+            .map_err(|e| eyre!("Failed to decode evidence entry {idx}: {e}"))?;
+
+        match *tag {
+            TAG_EVIDENCE => snapshot.evidence.push(evidence),
+            _ => return Err(eyre!("Invalid tag for evidence entry {idx}: {tag}")),
+        }
+    }
+
+    Ok(snapshot)
+}
+
+/// Replace the content of the evidence log with the given snapshot.
+///
+/// The snapshot is small and rarely changes, so it is rewritten as a whole
+/// rather than tracking the changes made to it.
+fn save_evidence<Ctx, Codec>(
+    evidence_log: &mut dyn WalBackend,
+    codec: &Codec,
+    snapshot: &EvidenceSnapshot<Ctx>,
+) -> Result<()>
+where
+    Ctx: Context,
+    Codec: WalCodec<Ctx>,
+{
+    let entries = snapshot.evidence.iter().map(|e| (TAG_EVIDENCE, e));
+
+    // Encode all entries before touching the log, so that it is left intact on failure
+    let mut encoded = Vec::new();
+
+    for (tag, evidence) in entries {
+        let mut buf = vec![tag];
+        encode_evidence(evidence, codec, &mut buf)?;
+        encoded.push(buf);
+    }
+
+    evidence_log.reset(0)?;
+
+    for buf in encoded {
+        evidence_log.append(&buf)?;
+    }
+
+    evidence_log.flush()?;
+
+    Ok(())
+}
+
 fn decode_result<Ctx, Codec>(
     idx: usize,
     result: io::Result<Vec<u8>>,
@@ -233,7 +461,7 @@ where
         })
 }
 
-fn dump_entries<'a, Ctx, Codec>(log: &'a mut wal::Log, codec: &'a Codec) -> Result<()>
+fn dump_entries<'a, Ctx, Codec>(log: &'a mut dyn WalBackend, codec: &'a Codec) -> Result<()>
 where
     Ctx: Context,
     Codec: WalCodec<Ctx>,
@@ -286,3 +514,89 @@ fn wal_entry_type<Ctx: Context>(entry: &WalEntry<Ctx>) -> &'static str {
         WalEntry::Timeout(_) => "Timeout",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::consensus::publisher::publish_flushed;
+    use crate::wal::{BackendIter, MemoryBackend};
+
+    /// Backend whose flushes fail after `fail_after` of them succeeded
+    struct FailingBackend {
+        inner: MemoryBackend,
+        fail_after: usize,
+    }
+
+    impl WalBackend for FailingBackend {
+        fn sequence(&self) -> u64 {
+            self.inner.sequence()
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+
+        fn size_bytes(&self) -> io::Result<u64> {
+            self.inner.size_bytes()
+        }
+
+        fn reset(&mut self, sequence: u64) -> io::Result<()> {
+            self.inner.reset(sequence)
+        }
+
+        fn append(&mut self, data: &[u8]) -> io::Result<()> {
+            self.inner.append(data)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            if self.fail_after == 0 {
+                return Err(io::Error::other("disk full"));
+            }
+
+            self.fail_after -= 1;
+            self.inner.flush()
+        }
+
+        fn truncate(&mut self, from_entry: u64) -> io::Result<()> {
+            self.inner.truncate(from_entry)
+        }
+
+        fn entries(&mut self) -> io::Result<BackendIter<'_>> {
+            self.inner.entries()
+        }
+    }
+
+    #[tokio::test]
+    async fn messages_are_not_published_when_flush_fails() {
+        let mut log = FailingBackend {
+            inner: MemoryBackend::new(),
+            fail_after: 1,
+        };
+
+        let metrics = Metrics::new();
+        let (tx, rx) = mpsc::channel(8);
+
+        // A first batch of two messages which is synced to disk, then one which is not
+        for msgs in [[1, 2].as_slice(), &[3]] {
+            let mut replies = Vec::new();
+
+            for &msg in msgs {
+                log.append(&[msg]).unwrap();
+
+                let (reply, flushed) = oneshot::channel();
+                replies.push(reply);
+                tx.send((flushed, msg)).await.unwrap();
+            }
+
+            flush(&mut log, replies, &metrics);
+        }
+
+        drop(tx);
+
+        let mut published = Vec::new();
+        publish_flushed(rx, |msg| published.push(msg)).await;
+
+        assert_eq!(published, vec![1, 2]);
+    }
+}
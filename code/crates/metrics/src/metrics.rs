@@ -1,4 +1,4 @@
-use std::fmt::Write;
+use std::fmt::{self, Write};
 use std::ops::Deref;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
@@ -21,16 +21,75 @@ impl Deref for Metrics {
     }
 }
 
+/// Whether the node was the proposer of the round a metric was recorded in
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, EncodeLabelValue)]
+pub enum Role {
+    Proposer,
+    NonProposer,
+}
+
+impl Role {
+    pub fn new(is_proposer: bool) -> Self {
+        if is_proposer {
+            Self::Proposer
+        } else {
+            Self::NonProposer
+        }
+    }
+}
+
 /// Label set for the `time_per_step` metric.
 #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
 pub struct TimePerStep {
     step: AsLabelValue<Step>,
+    role: Role,
 }
 
 impl TimePerStep {
-    pub fn new(step: Step) -> Self {
+    pub fn new(step: Step, role: Role) -> Self {
         Self {
             step: AsLabelValue(step),
+            role,
+        }
+    }
+}
+
+/// Label set for the `rounds_per_height` metric.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct RoundsPerHeight {
+    role: Role,
+}
+
+impl RoundsPerHeight {
+    pub fn new(role: Role) -> Self {
+        Self { role }
+    }
+}
+
+/// Label set for the metrics recorded per peer
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct PeerLabel {
+    peer_id: String,
+}
+
+impl PeerLabel {
+    pub fn new(peer_id: &impl fmt::Display) -> Self {
+        Self {
+            peer_id: peer_id.to_string(),
+        }
+    }
+}
+
+/// Label set for the metrics recorded per kind of consensus message
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct MessageKindLabel {
+    kind: String,
+}
+
+impl MessageKindLabel {
+    pub fn new(kind: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
         }
     }
 }
@@ -57,9 +116,14 @@ pub struct Inner {
     /// Time taken to finalize a block, in seconds
     pub time_per_block: Histogram,
 
-    /// Time taken for a step within a round, in secodns
+    /// Time taken for a step within a round, in seconds,
+    /// by step and by whether the node was the proposer of the round
     pub time_per_step: Family<TimePerStep, Histogram>,
 
+    /// Number of rounds it took to decide a height, by whether the node
+    /// was the proposer of the round in which the height was decided
+    pub rounds_per_height: Family<RoundsPerHeight, Histogram>,
+
     /// The consensus round in which the node was when it finalized a block
     pub consensus_round: Histogram,
 
@@ -99,6 +163,21 @@ pub struct Inner {
     /// Number of additional precommits received during finalization period
     pub additional_precommits: Counter,
 
+    /// Number of votes dropped because their validator exceeded the per-round vote limit
+    pub flooded_votes: Counter,
+
+    /// Number of votes already received dropped before reaching consensus, by peer
+    pub duplicate_votes: Family<PeerLabel, Counter>,
+
+    /// Number of votes for an already decided height dropped before reaching consensus, by peer
+    pub stale_votes: Family<PeerLabel, Counter>,
+
+    /// Number of consensus messages dropped because the deterministic ordering buffer was full
+    pub ordering_dropped_messages: Counter,
+
+    /// Number of consensus messages dropped before their signature was verified, by kind
+    pub verification_dropped_messages: Family<MessageKindLabel, Counter>,
+
     /// Internal state for measuring time taken for consensus
     instant_consensus_started: Arc<AtomicInstant>,
 
@@ -106,7 +185,7 @@ pub struct Inner {
     instant_block_started: Arc<AtomicInstant>,
 
     /// Internal state for measuring time taken for a step within a round
-    instant_step_started: Arc<Mutex<(Step, Instant)>>,
+    instant_step_started: Arc<Mutex<(Step, Role, Instant)>>,
 }
 
 impl Metrics {
@@ -117,6 +196,9 @@ impl Metrics {
             time_per_step: Family::new_with_constructor(|| {
                 Histogram::new(linear_buckets(0.0, 0.1, 20))
             }),
+            rounds_per_height: Family::new_with_constructor(|| {
+                Histogram::new(linear_buckets(1.0, 1.0, 20))
+            }),
             consensus_round: Histogram::new(linear_buckets(0.0, 1.0, 20)),
             proposal_round: Histogram::new(linear_buckets(0.0, 1.0, 20)),
             rebroadcast_timeouts: Counter::default(),
@@ -130,9 +212,18 @@ impl Metrics {
             equivocation_votes: Counter::default(),
             equivocation_proposals: Counter::default(),
             additional_precommits: Counter::default(),
+            flooded_votes: Counter::default(),
+            duplicate_votes: Family::default(),
+            stale_votes: Family::default(),
+            ordering_dropped_messages: Counter::default(),
+            verification_dropped_messages: Family::default(),
             instant_consensus_started: Arc::new(AtomicInstant::empty()),
             instant_block_started: Arc::new(AtomicInstant::empty()),
-            instant_step_started: Arc::new(Mutex::new((Step::Unstarted, Instant::now()))),
+            instant_step_started: Arc::new(Mutex::new((
+                Step::Unstarted,
+                Role::NonProposer,
+                Instant::now(),
+            ))),
         }))
     }
 
@@ -154,10 +245,16 @@ impl Metrics {
 
             registry.register(
                 "time_per_step",
-                "Time taken for a step in a round, in seconds",
+                "Time taken for a step in a round, in seconds, by step and by whether the node was the proposer",
                 metrics.time_per_step.clone(),
             );
 
+            registry.register(
+                "rounds_per_height",
+                "Number of rounds it took to decide a height, by whether the node was the proposer of the deciding round",
+                metrics.rounds_per_height.clone(),
+            );
+
             registry.register(
                 "consensus_round",
                 "The consensus round in which the node was when it finalized a block",
@@ -229,11 +326,62 @@ impl Metrics {
                 "Number of equivocating proposals",
                 metrics.equivocation_proposals.clone(),
             );
+
+            registry.register(
+                "flooded_votes",
+                "Number of votes dropped because their validator exceeded the per-round vote limit",
+                metrics.flooded_votes.clone(),
+            );
+
+            registry.register(
+                "duplicate_votes",
+                "Number of votes already received dropped before reaching consensus, by peer",
+                metrics.duplicate_votes.clone(),
+            );
+
+            registry.register(
+                "stale_votes",
+                "Number of votes for an already decided height dropped before reaching consensus, by peer",
+                metrics.stale_votes.clone(),
+            );
+
+            registry.register(
+                "ordering_dropped_messages",
+                "Number of consensus messages dropped because the deterministic ordering buffer was full",
+                metrics.ordering_dropped_messages.clone(),
+            );
+
+            registry.register(
+                "verification_dropped_messages",
+                "Number of consensus messages dropped before their signature was verified, by kind",
+                metrics.verification_dropped_messages.clone(),
+            );
         });
 
         metrics
     }
 
+    /// Count a vote of a peer dropped as a duplicate before reaching consensus
+    pub fn duplicate_vote(&self, peer_id: &impl fmt::Display) {
+        self.duplicate_votes
+            .get_or_create(&PeerLabel::new(peer_id))
+            .inc();
+    }
+
+    /// Count a vote of a peer dropped as stale before reaching consensus
+    pub fn stale_vote(&self, peer_id: &impl fmt::Display) {
+        self.stale_votes
+            .get_or_create(&PeerLabel::new(peer_id))
+            .inc();
+    }
+
+    /// Count a consensus message of the given kind dropped before its signature was verified
+    pub fn verification_dropped_message(&self, kind: &str) {
+        self.verification_dropped_messages
+            .get_or_create(&MessageKindLabel::new(kind))
+            .inc();
+    }
+
     pub fn consensus_start(&self) {
         self.instant_consensus_started.set_now();
     }
@@ -260,15 +408,15 @@ impl Metrics {
         }
     }
 
-    pub fn step_start(&self, step: Step) {
+    pub fn step_start(&self, step: Step, is_proposer: bool) {
         let mut guard = self.instant_step_started.lock().expect("poisoned mutex");
-        *guard = (step, Instant::now());
+        *guard = (step, Role::new(is_proposer), Instant::now());
     }
 
     pub fn step_end(&self, step: Step) {
         let mut guard = self.instant_step_started.lock().expect("poisoned mutex");
 
-        let (current_step, started) = *guard;
+        let (current_step, role, started) = *guard;
         debug_assert_eq!(current_step, step, "step_end called for wrong step");
 
         // If the step was never started, ignore
@@ -277,10 +425,16 @@ impl Metrics {
         }
 
         self.time_per_step
-            .get_or_create(&TimePerStep::new(step))
+            .get_or_create(&TimePerStep::new(step, role))
             .observe(started.elapsed().as_secs_f64());
 
-        *guard = (Step::Unstarted, Instant::now());
+        *guard = (Step::Unstarted, Role::NonProposer, Instant::now());
+    }
+
+    pub fn height_decided(&self, rounds: u32, is_proposer: bool) {
+        self.rounds_per_height
+            .get_or_create(&RoundsPerHeight::new(Role::new(is_proposer)))
+            .observe(f64::from(rounds));
     }
 }
 
@@ -43,6 +43,13 @@ impl SharedRegistry {
         }
     }
 
+    /// Encode the metrics of the registry in the Prometheus text format
+    pub fn export<W: core::fmt::Write>(&self, writer: &mut W) {
+        use prometheus_client::encoding::text::encode;
+
+        self.read(|registry| encode(writer, registry).unwrap())
+    }
+
     fn read<A>(&self, f: impl FnOnce(&Registry) -> A) -> A {
         f(&self.registry.read().expect("poisoned lock"))
     }
@@ -58,7 +65,5 @@ fn global_registry() -> &'static SharedRegistry {
 }
 
 pub fn export<W: core::fmt::Write>(writer: &mut W) {
-    use prometheus_client::encoding::text::encode;
-
-    SharedRegistry::global().read(|registry| encode(writer, registry).unwrap())
+    SharedRegistry::global().export(writer)
 }
@@ -0,0 +1,354 @@
+//! Detection of changes to the node's own IP address.
+//!
+//! A node can change IP address while running, e.g. after cloud re-provisioning
+//! or a DHCP lease change. Instead of waiting for peers to notice the stale
+//! address through dial failures, we track:
+//!
+//! - the addresses we are listening on, so that the listener can be re-created
+//!   when all of them expire, and
+//! - the IP address peers observe us at (reported via Identify), so that a change
+//!   of our public IP can be detected and announced to peers. A change is only taken
+//!   into account once observed by a majority of a quorum of distinct peers, the
+//!   validators being trusted over the other peers as soon as enough of them are
+//!   connected, so that a few peers cannot make the node announce a wrong address.
+
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use libp2p::multiaddr::Protocol;
+use libp2p::{Multiaddr, PeerId};
+
+use malachitebft_discovery::addr_filter::ip_of;
+
+/// Minimum number of distinct peers which must have observed us before
+/// the IP address observed by a majority of them is taken as our external IP
+const MIN_OBSERVERS: usize = 3;
+
+/// Our address as last observed by a connected peer
+#[derive(Debug)]
+struct Observation {
+    addr: Multiaddr,
+    ip: IpAddr,
+    /// Whether the peer is a validator
+    is_validator: bool,
+}
+
+/// Tracks the local listen addresses and the externally observed IP address
+#[derive(Debug, Default)]
+pub struct AddressMonitor {
+    /// Addresses the swarm is currently listening on
+    listen_addrs: HashSet<Multiaddr>,
+    /// Our address as last observed by each connected peer
+    observations: HashMap<PeerId, Observation>,
+    /// External IP address observed by a majority of peers
+    external_ip: Option<IpAddr>,
+    /// Whether the listener must be re-created
+    rebind_pending: bool,
+}
+
+impl AddressMonitor {
+    /// Record a new listen address.
+    ///
+    /// Returns true if the address was not already known.
+    pub fn on_new_listen_addr(&mut self, addr: Multiaddr) -> bool {
+        self.rebind_pending = false;
+        self.listen_addrs.insert(addr)
+    }
+
+    /// Record that a listen address expired, e.g. because the network interface went away.
+    ///
+    /// Schedules a rebind if there are no more listen addresses left.
+    pub fn on_expired_listen_addr(&mut self, addr: &Multiaddr) {
+        self.listen_addrs.remove(addr);
+
+        if self.listen_addrs.is_empty() {
+            self.rebind_pending = true;
+        }
+    }
+
+    /// Record that a listener was closed, removing all of its addresses, and schedule a rebind.
+    pub fn on_listener_closed(&mut self, addrs: &[Multiaddr]) {
+        for addr in addrs {
+            self.listen_addrs.remove(addr);
+        }
+
+        self.rebind_pending = true;
+    }
+
+    /// Record that a listener was removed on request, removing all of its addresses
+    /// without scheduling a rebind.
+    pub fn on_listener_removed(&mut self, addrs: &[Multiaddr]) {
+        for addr in addrs {
+            self.listen_addrs.remove(addr);
+        }
+    }
+
+    /// Whether the listener must be re-created
+    pub fn needs_rebind(&self) -> bool {
+        self.rebind_pending
+    }
+
+    /// Mark the listener as re-created
+    pub fn rebind_done(&mut self) {
+        self.rebind_pending = false;
+    }
+
+    /// Record the address a peer observed us at.
+    ///
+    /// Returns `Some((old_ip, new_addr))` if the external IP address observed by a majority
+    /// of the peers changed, along with the address, including its port, at which one of
+    /// them observed us. The first external IP address to be observed is not reported
+    /// as a change.
+    pub fn on_observed_addr(
+        &mut self,
+        peer_id: PeerId,
+        observed_addr: &Multiaddr,
+        is_validator: bool,
+    ) -> Option<(IpAddr, Multiaddr)> {
+        let ip = ip_of(observed_addr)?;
+
+        // Loopback and unspecified addresses say nothing about our public IP
+        if ip.is_loopback() || ip.is_unspecified() {
+            return None;
+        }
+
+        let observation = Observation {
+            addr: observed_addr.clone(),
+            ip,
+            is_validator,
+        };
+
+        self.observations.insert(peer_id, observation);
+        self.update_external_ip(&peer_id)
+    }
+
+    /// Forget the address observed by a disconnected peer
+    pub fn on_peer_disconnected(&mut self, peer_id: &PeerId) {
+        self.observations.remove(peer_id);
+    }
+
+    /// Update the external IP address to the one observed by a majority of the peers,
+    /// or of the validators if enough of them observed us, preferring the address
+    /// observed by the given peer
+    fn update_external_ip(&mut self, peer_id: &PeerId) -> Option<(IpAddr, Multiaddr)> {
+        let validators = self
+            .observations
+            .values()
+            .filter(|observation| observation.is_validator)
+            .count();
+
+        let voters: HashMap<&PeerId, &Observation> = self
+            .observations
+            .iter()
+            .filter(|(_, observation)| validators < MIN_OBSERVERS || observation.is_validator)
+            .collect();
+
+        if voters.len() < MIN_OBSERVERS {
+            return None;
+        }
+
+        let mut counts: HashMap<IpAddr, usize> = HashMap::new();
+        for observation in voters.values() {
+            *counts.entry(observation.ip).or_insert(0) += 1;
+        }
+
+        let (majority_ip, _) = counts
+            .into_iter()
+            .find(|(_, count)| *count * 2 > voters.len())?;
+
+        let old_ip = self.external_ip.replace(majority_ip)?;
+        if old_ip == majority_ip {
+            return None;
+        }
+
+        let observation = voters
+            .get(peer_id)
+            .filter(|observation| observation.ip == majority_ip)
+            .or_else(|| {
+                voters
+                    .values()
+                    .find(|observation| observation.ip == majority_ip)
+            })?;
+
+        Some((old_ip, observation.addr.clone()))
+    }
+}
+
+/// Replace the IP address in a multiaddr, keeping the rest of the address as is
+pub fn with_ip(addr: &Multiaddr, ip: IpAddr) -> Multiaddr {
+    addr.iter()
+        .map(|proto| match (proto, ip) {
+            (Protocol::Ip4(_) | Protocol::Ip6(_), IpAddr::V4(ip)) => Protocol::Ip4(ip),
+            (Protocol::Ip4(_) | Protocol::Ip6(_), IpAddr::V6(ip)) => Protocol::Ip6(ip),
+            (proto, _) => proto,
+        })
+        .collect()
+}
+
+/// Address to re-create a listener on once all of its addresses went away: the wildcard
+/// address with the same port and transport, unless listening on a loopback or wildcard
+/// address, which cannot go away
+pub fn rebind_addr(listen_addr: &Multiaddr) -> Multiaddr {
+    match ip_of(listen_addr) {
+        Some(ip) if ip.is_loopback() || ip.is_unspecified() => listen_addr.clone(),
+        Some(IpAddr::V4(_)) => with_ip(listen_addr, Ipv4Addr::UNSPECIFIED.into()),
+        Some(IpAddr::V6(_)) => with_ip(listen_addr, Ipv6Addr::UNSPECIFIED.into()),
+        None => listen_addr.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Multiaddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_external_ip_change() {
+        let mut monitor = AddressMonitor::default();
+        let peers: Vec<_> = (0..5).map(|_| PeerId::random()).collect();
+
+        // No external IP until enough peers observed us
+        assert_eq!(
+            monitor.on_observed_addr(peers[0], &addr("/ip4/1.2.3.4/tcp/1000"), false),
+            None
+        );
+        assert_eq!(
+            monitor.on_observed_addr(peers[1], &addr("/ip4/1.2.3.4/tcp/1000"), false),
+            None
+        );
+        assert_eq!(monitor.external_ip, None);
+
+        // Loopback addresses are ignored
+        assert_eq!(
+            monitor.on_observed_addr(peers[2], &addr("/ip4/127.0.0.1/tcp/1000"), false),
+            None
+        );
+        assert_eq!(monitor.external_ip, None);
+
+        // First external IP is not reported as a change
+        assert_eq!(
+            monitor.on_observed_addr(peers[2], &addr("/ip4/1.2.3.4/tcp/1000"), false),
+            None
+        );
+        assert_eq!(monitor.external_ip, Some("1.2.3.4".parse().unwrap()));
+
+        // Observations by the same peer are only counted once
+        for _ in 0..3 {
+            assert_eq!(
+                monitor.on_observed_addr(peers[0], &addr("/ip4/5.6.7.8/tcp/1000"), false),
+                None
+            );
+        }
+        assert_eq!(monitor.external_ip, Some("1.2.3.4".parse().unwrap()));
+
+        // Majority of peers now observe the new IP, reported with the observed port
+        assert_eq!(
+            monitor.on_observed_addr(peers[1], &addr("/ip4/5.6.7.8/tcp/2000"), false),
+            Some(("1.2.3.4".parse().unwrap(), addr("/ip4/5.6.7.8/tcp/2000")))
+        );
+
+        // Stale observation goes away on disconnect
+        monitor.on_peer_disconnected(&peers[2]);
+        assert_eq!(
+            monitor.on_observed_addr(peers[3], &addr("/ip4/5.6.7.8/tcp/1000"), false),
+            None
+        );
+        assert_eq!(monitor.external_ip, Some("5.6.7.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_validators_are_trusted_over_other_peers() {
+        let mut monitor = AddressMonitor::default();
+        let full_nodes: Vec<_> = (0..5).map(|_| PeerId::random()).collect();
+        let validators: Vec<_> = (0..3).map(|_| PeerId::random()).collect();
+
+        for peer_id in &validators {
+            monitor.on_observed_addr(*peer_id, &addr("/ip4/1.2.3.4/tcp/1000"), true);
+        }
+        assert_eq!(monitor.external_ip, Some("1.2.3.4".parse().unwrap()));
+
+        // A majority of full nodes cannot outvote the validators
+        for peer_id in &full_nodes {
+            assert_eq!(
+                monitor.on_observed_addr(*peer_id, &addr("/ip4/6.6.6.6/tcp/1000"), false),
+                None
+            );
+        }
+        assert_eq!(monitor.external_ip, Some("1.2.3.4".parse().unwrap()));
+
+        // Once too few validators are connected, all the peers are counted
+        monitor.on_peer_disconnected(&validators[0]);
+        assert_eq!(
+            monitor.on_observed_addr(full_nodes[0], &addr("/ip4/6.6.6.6/tcp/1000"), false),
+            Some(("1.2.3.4".parse().unwrap(), addr("/ip4/6.6.6.6/tcp/1000")))
+        );
+    }
+
+    #[test]
+    fn test_rebind() {
+        let mut monitor = AddressMonitor::default();
+        let addr1 = addr("/ip4/10.0.0.1/tcp/27000");
+        let addr2 = addr("/ip4/10.0.0.2/tcp/27000");
+
+        assert!(monitor.on_new_listen_addr(addr1.clone()));
+        assert!(monitor.on_new_listen_addr(addr2.clone()));
+        assert!(!monitor.on_new_listen_addr(addr2.clone()));
+
+        monitor.on_expired_listen_addr(&addr1);
+        assert!(!monitor.needs_rebind());
+
+        monitor.on_expired_listen_addr(&addr2);
+        assert!(monitor.needs_rebind());
+
+        monitor.rebind_done();
+        assert!(!monitor.needs_rebind());
+
+        monitor.on_listener_closed(&[]);
+        assert!(monitor.needs_rebind());
+        monitor.rebind_done();
+
+        // Removing a listener on request does not schedule a rebind
+        assert!(monitor.on_new_listen_addr(addr1.clone()));
+        monitor.on_listener_removed(std::slice::from_ref(&addr1));
+        assert!(!monitor.needs_rebind());
+        assert!(monitor.on_new_listen_addr(addr1));
+    }
+
+    #[test]
+    fn test_rebind_addr() {
+        assert_eq!(
+            rebind_addr(&addr("/ip4/10.0.0.1/tcp/27000")),
+            addr("/ip4/0.0.0.0/tcp/27000")
+        );
+        assert_eq!(
+            rebind_addr(&addr("/ip6/fd00::1/udp/27000/quic-v1")),
+            addr("/ip6/::/udp/27000/quic-v1")
+        );
+
+        for listen_addr in [
+            "/ip4/0.0.0.0/tcp/27000",
+            "/ip4/127.0.0.1/tcp/27000",
+            "/dns/localhost/tcp/27000",
+        ] {
+            assert_eq!(rebind_addr(&addr(listen_addr)), addr(listen_addr));
+        }
+    }
+
+    #[test]
+    fn test_with_ip() {
+        assert_eq!(
+            with_ip(&addr("/ip4/0.0.0.0/tcp/27000"), "1.2.3.4".parse().unwrap()),
+            addr("/ip4/1.2.3.4/tcp/27000")
+        );
+        assert_eq!(
+            with_ip(
+                &addr("/ip4/10.0.0.1/udp/27000/quic-v1"),
+                "::1".parse().unwrap()
+            ),
+            addr("/ip6/::1/udp/27000/quic-v1")
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use libp2p::PeerId;
+use malachitebft_metrics::prometheus::encoding::EncodeLabelSet;
+use malachitebft_metrics::prometheus::metrics::counter::Counter;
+use malachitebft_metrics::prometheus::metrics::family::Family;
+use malachitebft_metrics::Registry;
+
+// Make prometheus_client available for the derive macro
+use malachitebft_metrics::prometheus as prometheus_client;
+
+/// Per-peer bandwidth limits
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BandwidthConfig {
+    /// Maximum number of bytes per second received from a single peer, unlimited if `None`
+    pub max_inbound_rate: Option<u64>,
+
+    /// Number of consecutive seconds a peer may exceed `max_inbound_rate`
+    /// before the connections to it are closed
+    pub max_violations: u32,
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        Self {
+            max_inbound_rate: None,
+            max_violations: 5,
+        }
+    }
+}
+
+/// Protocol over which the metered bytes are exchanged
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Protocol {
+    /// GossipSub or Broadcast messages
+    PubSub,
+    Sync,
+    /// Peer exchange and the other discovery requests
+    Discovery,
+    /// Messages sent by the application directly to a peer, and their replies
+    PeerMessage,
+}
+
+impl Protocol {
+    const ALL: [Self; 4] = [Self::PubSub, Self::Sync, Self::Discovery, Self::PeerMessage];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::PubSub => "pubsub",
+            Self::Sync => "sync",
+            Self::Discovery => "discovery",
+            Self::PeerMessage => "peer_message",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    const ALL: [Self; 2] = [Self::Inbound, Self::Outbound];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Inbound => "inbound",
+            Self::Outbound => "outbound",
+        }
+    }
+}
+
+/// Labels for the per-peer bandwidth metric
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct PeerBandwidthLabels {
+    peer_id: String,
+    protocol: String,
+    direction: String,
+}
+
+impl PeerBandwidthLabels {
+    fn new(peer_id: &PeerId, protocol: Protocol, direction: Direction) -> Self {
+        Self {
+            peer_id: peer_id.to_string(),
+            protocol: protocol.as_str().to_string(),
+            direction: direction.as_str().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PeerBandwidth {
+    /// Bytes received since the last check of the rate limit
+    inbound: u64,
+    /// Number of consecutive checks in which the peer exceeded the rate limit
+    violations: u32,
+}
+
+/// Meters the bytes exchanged with each peer, per protocol and direction,
+/// and finds the peers persistently exceeding the inbound rate limit.
+///
+/// Bytes are counted at the message level, excluding the framing and the protocol overhead.
+/// Outbound pubsub messages are not attributed to peers, since the peers they are sent to
+/// are chosen by the pubsub protocol.
+pub(crate) struct BandwidthMeter {
+    config: BandwidthConfig,
+    peers: HashMap<PeerId, PeerBandwidth>,
+    last_check: Instant,
+    bytes: Family<PeerBandwidthLabels, Counter>,
+    rate_limited_peers: Counter,
+}
+
+impl std::fmt::Debug for BandwidthMeter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BandwidthMeter")
+            .field("config", &self.config)
+            .field("peers", &self.peers.len())
+            .finish()
+    }
+}
+
+impl BandwidthMeter {
+    pub(crate) fn new(config: BandwidthConfig, registry: &mut Registry) -> Self {
+        let bytes = Family::<PeerBandwidthLabels, Counter>::default();
+        let rate_limited_peers = Counter::default();
+
+        registry.register(
+            "peer_bytes",
+            "Bytes exchanged with each connected peer, per protocol and direction",
+            bytes.clone(),
+        );
+
+        registry.register(
+            "rate_limited_peers",
+            "Number of peers disconnected for exceeding the inbound rate limit",
+            rate_limited_peers.clone(),
+        );
+
+        Self {
+            config,
+            peers: HashMap::new(),
+            last_check: Instant::now(),
+            bytes,
+            rate_limited_peers,
+        }
+    }
+
+    pub(crate) fn config(&self) -> &BandwidthConfig {
+        &self.config
+    }
+
+    /// Change the inbound rate limits, starting from the next check
+    pub(crate) fn set_config(&mut self, config: BandwidthConfig) {
+        self.config = config;
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        peer_id: &PeerId,
+        protocol: Protocol,
+        direction: Direction,
+        bytes: usize,
+    ) {
+        let bytes = bytes as u64;
+
+        self.bytes
+            .get_or_create(&PeerBandwidthLabels::new(peer_id, protocol, direction))
+            .inc_by(bytes);
+
+        if direction == Direction::Inbound {
+            self.peers.entry(*peer_id).or_default().inbound += bytes;
+        }
+    }
+
+    /// Check the inbound rate of the peers since the previous check, and return
+    /// the peers which exceeded the rate limit for more than `max_violations` checks in a row.
+    pub(crate) fn check_rate_limits(&mut self) -> Vec<PeerId> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_check).as_secs_f64();
+        self.last_check = now;
+
+        let Some(max_rate) = self.config.max_inbound_rate else {
+            self.peers.clear();
+            return Vec::new();
+        };
+
+        let max_bytes = max_rate as f64 * elapsed;
+        let mut exceeded = Vec::new();
+
+        for (peer_id, peer) in &mut self.peers {
+            if peer.inbound as f64 > max_bytes {
+                peer.violations += 1;
+
+                if peer.violations > self.config.max_violations {
+                    exceeded.push(*peer_id);
+                }
+            } else {
+                peer.violations = 0;
+            }
+
+            peer.inbound = 0;
+        }
+
+        self.rate_limited_peers.inc_by(exceeded.len() as u64);
+
+        exceeded
+    }
+
+    /// Forget a disconnected peer, along with its metrics
+    pub(crate) fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+
+        for protocol in Protocol::ALL {
+            for direction in Direction::ALL {
+                self.bytes
+                    .remove(&PeerBandwidthLabels::new(peer_id, protocol, direction));
+            }
+        }
+    }
+}
+
+/// Size of the signed peer records carried by a discovery message,
+/// which make up most of the discovery traffic
+#[cfg(feature = "discovery")]
+pub(crate) fn discovery_request_len(request: &malachitebft_discovery::Request) -> usize {
+    use malachitebft_discovery::Request;
+
+    match request {
+        Request::Peers(records) => records.iter().map(Vec::len).sum(),
+        Request::Connect(_) | Request::Disconnect(_) | Request::RelayLoad() => 0,
+    }
+}
+
+/// Size of the signed peer records carried by a discovery response
+#[cfg(feature = "discovery")]
+pub(crate) fn discovery_response_len(response: &malachitebft_discovery::Response) -> usize {
+    use malachitebft_discovery::Response;
+
+    match response {
+        Response::Peers(records) | Response::PeersWithRelayLoad(records, _) => {
+            records.iter().map(Vec::len).sum()
+        }
+        Response::Connect(_, _) | Response::Disconnect() | Response::RelayLoad(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn meter(max_inbound_rate: Option<u64>, max_violations: u32) -> BandwidthMeter {
+        let config = BandwidthConfig {
+            max_inbound_rate,
+            max_violations,
+        };
+
+        BandwidthMeter::new(config, &mut Registry::default())
+    }
+
+    /// Pretend that a second elapsed since the previous check
+    fn elapse_second(meter: &mut BandwidthMeter) {
+        meter.last_check = Instant::now() - Duration::from_secs(1);
+    }
+
+    #[test]
+    fn peer_persistently_exceeding_rate_limit() {
+        let mut meter = meter(Some(1000), 2);
+        let peer = PeerId::random();
+
+        for _ in 0..2 {
+            elapse_second(&mut meter);
+            meter.record(&peer, Protocol::PubSub, Direction::Inbound, 2000);
+            assert!(meter.check_rate_limits().is_empty());
+        }
+
+        elapse_second(&mut meter);
+        meter.record(&peer, Protocol::Sync, Direction::Inbound, 2000);
+        assert_eq!(meter.check_rate_limits(), vec![peer]);
+    }
+
+    #[test]
+    fn violations_reset_under_rate_limit() {
+        let mut meter = meter(Some(1000), 1);
+        let peer = PeerId::random();
+
+        for bytes in [2000, 500, 2000, 500] {
+            elapse_second(&mut meter);
+            meter.record(&peer, Protocol::PubSub, Direction::Inbound, bytes);
+            assert!(meter.check_rate_limits().is_empty());
+        }
+    }
+
+    #[test]
+    fn outbound_bytes_not_rate_limited() {
+        let mut meter = meter(Some(1000), 0);
+        let peer = PeerId::random();
+
+        elapse_second(&mut meter);
+        meter.record(&peer, Protocol::Sync, Direction::Outbound, 10_000);
+        assert!(meter.check_rate_limits().is_empty());
+    }
+}
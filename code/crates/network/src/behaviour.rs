@@ -2,23 +2,33 @@ use std::convert::Infallible;
 use std::time::Duration;
 
 use eyre::Result;
-use libp2p::connection_limits;
+#[cfg(feature = "gossipsub")]
+use libp2p::gossipsub;
 pub use libp2p::identity::Keypair;
-use libp2p::kad::{Addresses, KBucketKey, KBucketRef};
+use libp2p::kad::{self, Addresses, KBucketKey, KBucketRef, QueryId, RecordKey};
 use libp2p::request_response::{OutboundRequestId, ResponseChannel};
 use libp2p::swarm::behaviour::toggle::Toggle;
-use libp2p::swarm::NetworkBehaviour;
-use libp2p::{gossipsub, identify, ping};
+use libp2p::swarm::{dummy, NetworkBehaviour};
+use libp2p::{allow_block_list, connection_limits};
+use libp2p::{identify, ping};
 pub use libp2p::{Multiaddr, PeerId};
 use libp2p_broadcast as broadcast;
 
 use malachitebft_discovery as discovery;
 use malachitebft_metrics::Registry;
+#[cfg(feature = "sync")]
 use malachitebft_sync as sync;
+#[cfg(feature = "gossipsub")]
 use tracing::info;
 
-use crate::validator_proof;
-use crate::{ip_limits, peer_scoring, Config, GossipSubConfig};
+use crate::custom::{Custom, CustomEvent};
+#[cfg(feature = "dcutr")]
+use crate::hole_punch::{self, HolePunchEvent};
+use crate::multi::Multi;
+use crate::{ip_limits, peer_allowlist, Config};
+use crate::{node_key, peer_message, validator_proof};
+#[cfg(feature = "gossipsub")]
+use crate::{peer_scoring, Channel, GossipSubConfig};
 
 /// Multiplier for connection limits.
 /// Connection limits are higher than discovery limits to allow headroom for ephemeral
@@ -26,14 +36,27 @@ use crate::{ip_limits, peer_scoring, Config, GossipSubConfig};
 const CONNECTION_LIMITS_MULTIPLIER: u32 = 4;
 
 /// Derive libp2p connection limits from network config.
-/// Uses 4x multiplier to provide headroom above discovery-level limits.
+/// Unless configured, uses 4x multiplier to provide headroom above discovery-level limits,
+/// the connections per peer being capped at the discovery limit.
 fn connection_limits(config: &Config) -> connection_limits::ConnectionLimits {
     let multiplier = CONNECTION_LIMITS_MULTIPLIER;
-    let max_pending_incoming = (config.discovery.num_inbound_peers as u32) * multiplier;
-    let max_pending_outgoing = (config.discovery.num_outbound_peers as u32) * multiplier;
-    let max_established_incoming = (config.discovery.num_inbound_peers as u32) * multiplier;
-    let max_established_outgoing = (config.discovery.num_outbound_peers as u32) * multiplier;
-    let max_established_per_peer = (config.discovery.max_connections_per_peer as u32) * multiplier;
+    let limits = config.connection_limits;
+
+    let max_pending_incoming = limits
+        .max_pending_incoming
+        .unwrap_or((config.discovery.num_inbound_peers as u32) * multiplier);
+    let max_pending_outgoing = limits
+        .max_pending_outgoing
+        .unwrap_or((config.discovery.num_outbound_peers as u32) * multiplier);
+    let max_established_incoming = limits
+        .max_established_incoming
+        .unwrap_or((config.discovery.num_inbound_peers as u32) * multiplier);
+    let max_established_outgoing = limits
+        .max_established_outgoing
+        .unwrap_or((config.discovery.num_outbound_peers as u32) * multiplier);
+    let max_established_per_peer = limits
+        .max_established_per_peer
+        .unwrap_or(config.discovery.max_connections_per_peer as u32);
 
     connection_limits::ConnectionLimits::default()
         .with_max_pending_incoming(Some(max_pending_incoming))
@@ -47,11 +70,24 @@ fn connection_limits(config: &Config) -> connection_limits::ConnectionLimits {
 pub enum NetworkEvent {
     Identify(Box<identify::Event>),
     Ping(ping::Event),
+    #[cfg(feature = "gossipsub")]
     GossipSub(gossipsub::Event),
     Broadcast(broadcast::Event),
-    Sync(sync::Event),
+    /// Event of the sync protocol of the chain with the given index
+    #[cfg(feature = "sync")]
+    Sync(usize, sync::Event),
+    #[cfg(feature = "discovery")]
     Discovery(Box<discovery::NetworkEvent>),
     ValidatorProof(validator_proof::Event),
+    /// Event of the direct messages protocol of the chain with the given index
+    PeerMessage(usize, peer_message::Event),
+    /// Announcement of a rotation of the node key of a peer
+    NodeKey(node_key::Event),
+    /// Upgrade of a relayed connection into a direct one
+    #[cfg(feature = "dcutr")]
+    HolePunch(HolePunchEvent),
+    /// Event of the behaviour provided by the application, see `Behaviour::custom`
+    Custom(CustomEvent),
 }
 
 impl From<identify::Event> for NetworkEvent {
@@ -66,6 +102,7 @@ impl From<ping::Event> for NetworkEvent {
     }
 }
 
+#[cfg(feature = "gossipsub")]
 impl From<gossipsub::Event> for NetworkEvent {
     fn from(event: gossipsub::Event) -> Self {
         Self::GossipSub(event)
@@ -78,24 +115,51 @@ impl From<broadcast::Event> for NetworkEvent {
     }
 }
 
-impl From<sync::Event> for NetworkEvent {
-    fn from(event: sync::Event) -> Self {
-        Self::Sync(event)
+#[cfg(feature = "sync")]
+impl From<(usize, sync::Event)> for NetworkEvent {
+    fn from((chain, event): (usize, sync::Event)) -> Self {
+        Self::Sync(chain, event)
     }
 }
 
+#[cfg(feature = "discovery")]
 impl From<discovery::NetworkEvent> for NetworkEvent {
     fn from(network_event: discovery::NetworkEvent) -> Self {
         Self::Discovery(Box::new(network_event))
     }
 }
 
+impl From<(usize, peer_message::Event)> for NetworkEvent {
+    fn from((chain, event): (usize, peer_message::Event)) -> Self {
+        Self::PeerMessage(chain, event)
+    }
+}
+
+impl From<node_key::Event> for NetworkEvent {
+    fn from(event: node_key::Event) -> Self {
+        Self::NodeKey(event)
+    }
+}
+
 impl From<validator_proof::Event> for NetworkEvent {
     fn from(event: validator_proof::Event) -> Self {
         Self::ValidatorProof(event)
     }
 }
 
+#[cfg(feature = "dcutr")]
+impl From<HolePunchEvent> for NetworkEvent {
+    fn from(event: HolePunchEvent) -> Self {
+        Self::HolePunch(event)
+    }
+}
+
+impl From<CustomEvent> for NetworkEvent {
+    fn from(event: CustomEvent) -> Self {
+        Self::Custom(event)
+    }
+}
+
 // connection_limits::Behaviour never emits events (uses Infallible),
 // but the NetworkBehaviour derive macro requires this implementation.
 impl From<Infallible> for NetworkEvent {
@@ -104,28 +168,72 @@ impl From<Infallible> for NetworkEvent {
     }
 }
 
+/// GossipSub behaviour, replaced by a dummy behaviour when the `gossipsub` feature is disabled,
+/// in which case the corresponding `Toggle` is always off.
+#[cfg(feature = "gossipsub")]
+type GossipSubBehaviour = gossipsub::Behaviour;
+#[cfg(not(feature = "gossipsub"))]
+type GossipSubBehaviour = libp2p::swarm::dummy::Behaviour;
+
+/// Sync behaviours, replaced by a dummy behaviour when the `sync` feature is disabled,
+/// in which case the corresponding `Toggle` is always off.
+#[cfg(feature = "sync")]
+type SyncBehaviour = Multi<sync::Behaviour>;
+#[cfg(not(feature = "sync"))]
+type SyncBehaviour = libp2p::swarm::dummy::Behaviour;
+
+/// Discovery behaviour, replaced by a dummy behaviour when the `discovery` feature is disabled,
+/// in which case the corresponding `Toggle` is always off.
+#[cfg(feature = "discovery")]
+type DiscoveryBehaviour = discovery::Behaviour;
+#[cfg(not(feature = "discovery"))]
+type DiscoveryBehaviour = libp2p::swarm::dummy::Behaviour;
+
+/// Hole punching behaviour, replaced by a dummy behaviour when the `dcutr` feature is disabled,
+/// in which case the upgrades of the relayed connections are neither reported nor denied.
+#[cfg(feature = "dcutr")]
+type HolePunchBehaviour = hole_punch::Behaviour;
+#[cfg(not(feature = "dcutr"))]
+type HolePunchBehaviour = libp2p::swarm::dummy::Behaviour;
+
+/// Behaviours of the network, along with an additional one `C` provided by the application
 #[derive(NetworkBehaviour)]
 #[behaviour(to_swarm = "NetworkEvent")]
-pub struct Behaviour {
+pub struct Behaviour<C: NetworkBehaviour> {
+    /// Peers banned at runtime, see `CtrlMsg::BanPeer`
+    pub banned_peers: allow_block_list::Behaviour<allow_block_list::BlockedPeers>,
     pub connection_limits: connection_limits::Behaviour,
     pub ip_limits: ip_limits::Behaviour,
+    pub peer_allowlist: Toggle<peer_allowlist::Behaviour>,
+    pub hole_punch: HolePunchBehaviour,
     pub identify: identify::Behaviour,
     pub ping: ping::Behaviour,
-    pub gossipsub: Toggle<gossipsub::Behaviour>,
+    pub gossipsub: Toggle<GossipSubBehaviour>,
     pub broadcast: Toggle<broadcast::Behaviour>,
-    pub sync: Toggle<sync::Behaviour>,
-    pub discovery: Toggle<discovery::Behaviour>,
+    /// One sync behaviour per chain, see `Config::chain_ids`
+    pub sync: Toggle<SyncBehaviour>,
+    pub discovery: Toggle<DiscoveryBehaviour>,
     pub validator_proof: Toggle<validator_proof::Behaviour>,
+    /// One direct messages behaviour per chain, see `Config::chain_ids`
+    pub peer_message: Multi<peer_message::Behaviour>,
+    /// Announcements of the new `PeerId` of the node to its persistent peers, see `node_key`
+    pub node_key: node_key::Behaviour,
+    /// Application-specific protocols sharing the swarm and the peers, eg. mempool gossip
+    pub custom: Custom<C>,
 }
 
+/// Behaviours run by the network service, without any behaviour provided by the application
+pub type DefaultBehaviour = Behaviour<dummy::Behaviour>;
+
 /// Dummy implementation of Debug for Behaviour.
-impl std::fmt::Debug for Behaviour {
+impl<C: NetworkBehaviour> std::fmt::Debug for Behaviour<C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Behaviour").finish()
     }
 }
 
-impl discovery::DiscoveryClient for Behaviour {
+#[cfg(feature = "discovery")]
+impl<C: NetworkBehaviour> discovery::DiscoveryClient for Behaviour<C> {
     fn add_address(&mut self, peer: &PeerId, address: Multiaddr) -> libp2p::kad::RoutingUpdate {
         self.discovery
             .as_mut()
@@ -146,6 +254,36 @@ impl discovery::DiscoveryClient for Behaviour {
             .kbuckets()
     }
 
+    fn start_providing(&mut self, key: RecordKey) -> Result<QueryId, kad::store::Error> {
+        self.discovery
+            .as_mut()
+            .expect("Discovery behaviour should be available")
+            .kademlia
+            .as_mut()
+            .expect("Kademlia behaviour should be available")
+            .start_providing(key)
+    }
+
+    fn stop_providing(&mut self, key: &RecordKey) {
+        self.discovery
+            .as_mut()
+            .expect("Discovery behaviour should be available")
+            .kademlia
+            .as_mut()
+            .expect("Kademlia behaviour should be available")
+            .stop_providing(key)
+    }
+
+    fn get_providers(&mut self, key: RecordKey) -> QueryId {
+        self.discovery
+            .as_mut()
+            .expect("Discovery behaviour should be available")
+            .kademlia
+            .as_mut()
+            .expect("Kademlia behaviour should be available")
+            .get_providers(key)
+    }
+
     fn send_request(&mut self, peer_id: &PeerId, req: discovery::Request) -> OutboundRequestId {
         self.discovery
             .as_mut()
@@ -167,6 +305,41 @@ impl discovery::DiscoveryClient for Behaviour {
     }
 }
 
+/// Without the `discovery` feature, discovery is never enabled and so never uses its behaviour
+#[cfg(not(feature = "discovery"))]
+impl<C: NetworkBehaviour> discovery::DiscoveryClient for Behaviour<C> {
+    fn add_address(&mut self, _peer: &PeerId, _address: Multiaddr) -> libp2p::kad::RoutingUpdate {
+        libp2p::kad::RoutingUpdate::Failed
+    }
+
+    fn kbuckets(&mut self) -> impl Iterator<Item = KBucketRef<'_, KBucketKey<PeerId>, Addresses>> {
+        std::iter::empty()
+    }
+
+    fn start_providing(&mut self, _key: RecordKey) -> Result<QueryId, kad::store::Error> {
+        unreachable!("Discovery is not supported by this build")
+    }
+
+    fn stop_providing(&mut self, _key: &RecordKey) {}
+
+    fn get_providers(&mut self, _key: RecordKey) -> QueryId {
+        unreachable!("Discovery is not supported by this build")
+    }
+
+    fn send_request(&mut self, _peer_id: &PeerId, _req: discovery::Request) -> OutboundRequestId {
+        unreachable!("Discovery is not supported by this build")
+    }
+
+    fn send_response(
+        &mut self,
+        _ch: ResponseChannel<discovery::Response>,
+        rs: discovery::Response,
+    ) -> Result<(), discovery::Response> {
+        Err(rs)
+    }
+}
+
+#[cfg(feature = "gossipsub")]
 fn message_id(message: &gossipsub::Message) -> gossipsub::MessageId {
     use seahash::SeaHasher;
     use std::hash::{Hash, Hasher};
@@ -176,15 +349,45 @@ fn message_id(message: &gossipsub::Message) -> gossipsub::MessageId {
     gossipsub::MessageId::new(hasher.finish().to_be_bytes().as_slice())
 }
 
-fn gossipsub_config(config: GossipSubConfig, max_transmit_size: usize) -> gossipsub::Config {
-    gossipsub::ConfigBuilder::default()
+/// Target mesh size of the topics whose messages are published to all the peers subscribed
+/// to them, above the number of peers of the small networks this is meant for, so that
+/// gossipsub tops up the recipients of the published messages with all the other peers
+#[cfg(feature = "gossipsub")]
+const FLOOD_PUBLISH_MESH_N: usize = 64;
+
+/// GossipSub configuration, where the messages published on the `flood_publish_topics`
+/// are sent to all the peers subscribed to them rather than to the mesh peers only
+#[cfg(feature = "gossipsub")]
+fn gossipsub_config(
+    config: GossipSubConfig,
+    max_transmit_size: usize,
+    flood_publish_topics: Vec<gossipsub::TopicHash>,
+) -> gossipsub::Config {
+    let mut builder = gossipsub::ConfigBuilder::default();
+
+    // The other mesh parameters of these topics do not default to the ones of the config
+    for topic in flood_publish_topics {
+        builder
+            .mesh_n_for_topic(FLOOD_PUBLISH_MESH_N, topic.clone())
+            .mesh_n_high_for_topic(FLOOD_PUBLISH_MESH_N, topic.clone())
+            .mesh_n_low_for_topic(config.mesh_n_low, topic.clone())
+            .mesh_outbound_min_for_topic(config.mesh_outbound_min, topic);
+    }
+
+    // Wait for the application to validate the messages before forwarding them
+    if config.validate_messages {
+        builder.validate_messages();
+    }
+
+    builder
         .max_transmit_size(max_transmit_size)
         .opportunistic_graft_ticks(peer_scoring::OPPORTUNISTIC_GRAFT_TICKS)
         .opportunistic_graft_peers(peer_scoring::OPPORTUNISTIC_GRAFT_PEERS)
         .heartbeat_interval(Duration::from_secs(1))
         .validation_mode(gossipsub::ValidationMode::Strict)
-        .history_gossip(3)
-        .history_length(5)
+        .history_gossip(config.history_gossip)
+        .history_length(config.history_length)
+        .duplicate_cache_time(config.duplicate_cache_time)
         .mesh_n_high(config.mesh_n_high)
         .mesh_n_low(config.mesh_n_low)
         .mesh_outbound_min(config.mesh_outbound_min)
@@ -195,11 +398,36 @@ fn gossipsub_config(config: GossipSubConfig, max_transmit_size: usize) -> gossip
         .unwrap()
 }
 
-impl Behaviour {
+/// Topics of the consensus channel of all the chains, if the votes are published to all the peers
+#[cfg(feature = "gossipsub")]
+fn flood_publish_topics(config: &Config) -> Vec<gossipsub::TopicHash> {
+    if !config.gossipsub.flood_publish_votes || config.gossipsub.enable_flood_publish {
+        return Vec::new();
+    }
+
+    (0..config.num_chains())
+        .map(|chain| {
+            Channel::Consensus
+                .to_gossipsub_topic(
+                    &config.namespace(chain),
+                    config.channel_names,
+                    config.compression,
+                )
+                .hash()
+        })
+        .collect()
+}
+
+impl<C: NetworkBehaviour> Behaviour<C> {
+    /// Create the behaviours of the network, with an additional behaviour provided by the
+    /// application whose events are emitted as `NetworkEvent::Custom`.
+    ///
+    /// Pass a `dummy::Behaviour` to only run the protocols of the network.
     pub fn new_with_metrics(
         config: &Config,
         identity: &crate::NetworkIdentity,
         registry: &mut Registry,
+        custom: C,
     ) -> Result<Self> {
         // Build agent_version for peer identification (moniker only)
         let agent_version = format!("moniker={}", identity.moniker);
@@ -215,16 +443,34 @@ impl Behaviour {
                 consensus_protocol.to_string(),
                 &identity.keypair,
             )
-            .with_agent_version(agent_version),
+            .with_agent_version(agent_version)
+            // Let connected peers know about new or expired listen addresses right away
+            .with_push_listen_addr_updates(true),
         );
 
         let ping = ping::Behaviour::new(ping::Config::new().with_interval(Duration::from_secs(5)));
 
         let enable_gossipsub = config.pubsub_protocol.is_gossipsub() && config.enable_consensus;
+
+        #[cfg(not(feature = "gossipsub"))]
+        if enable_gossipsub {
+            eyre::bail!(
+                "GossipSub is not supported by this build, enable the `gossipsub` feature or use the broadcast protocol"
+            );
+        }
+
+        #[cfg(not(feature = "gossipsub"))]
+        let gossipsub = None;
+
+        #[cfg(feature = "gossipsub")]
         let gossipsub = enable_gossipsub.then(|| {
             let mut behaviour = gossipsub::Behaviour::new(
                 gossipsub::MessageAuthenticity::Signed(identity.keypair.clone()),
-                gossipsub_config(config.gossipsub, config.pubsub_max_size),
+                gossipsub_config(
+                    config.gossipsub,
+                    config.compression.max_transmit_size(config.pubsub_max_size),
+                    flood_publish_topics(config),
+                ),
             )
             .unwrap();
 
@@ -233,8 +479,8 @@ impl Behaviour {
                 info!("Enabling peer scoring for GossipSub");
                 behaviour
                     .with_peer_score(
-                        peer_scoring::peer_score_params(),
-                        peer_scoring::peer_score_thresholds(),
+                        peer_scoring::peer_score_params(&config.gossipsub.peer_score),
+                        peer_scoring::peer_score_thresholds(&config.gossipsub.peer_score),
                     )
                     .expect("Failed to enable peer scoring");
             } else {
@@ -252,25 +498,64 @@ impl Behaviour {
         let broadcast = enable_broadcast.then(|| {
             broadcast::Behaviour::new_with_metrics(
                 broadcast::Config {
-                    max_buf_size: config.pubsub_max_size,
+                    max_buf_size: config.compression.max_transmit_size(config.pubsub_max_size),
                 },
                 registry.sub_registry_with_prefix("broadcast"),
             )
         });
 
+        #[cfg(not(feature = "sync"))]
+        if config.enable_sync {
+            eyre::bail!("Sync is not supported by this build, enable the `sync` feature");
+        }
+
+        #[cfg(not(feature = "sync"))]
+        let sync = None;
+
+        #[cfg(feature = "sync")]
         let sync = if config.enable_sync {
-            Some(sync::Behaviour::new(
-                sync::Config::default().with_max_response_size(config.rpc_max_size),
-                config.protocol_names.sync.clone(),
-            )?)
+            let sync_config = sync::Config::default()
+                .with_max_response_size(config.rpc_max_size)
+                .with_max_chunked_response_size(config.rpc_max_chunked_size)
+                .with_compression(config.compression.sync);
+
+            let behaviours = (0..config.num_chains())
+                .map(|chain| {
+                    sync::Behaviour::new(
+                        sync_config,
+                        format!("{}{}", config.namespace(chain), config.protocol_names.sync),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            Some(Multi::new(behaviours))
         } else {
             None
         };
 
+        #[cfg(not(feature = "discovery"))]
+        if config.discovery.enabled {
+            eyre::bail!("Discovery is not supported by this build, enable the `discovery` feature");
+        }
+
+        #[cfg(not(feature = "relay"))]
+        if config.discovery.auto_relay.is_some() {
+            eyre::bail!(
+                "Relay election is not supported by this build, enable the `relay` feature"
+            );
+        }
+
+        #[cfg(not(feature = "discovery"))]
+        let discovery = None;
+
+        #[cfg(feature = "discovery")]
         let discovery = if config.discovery.enabled {
             Some(discovery::Behaviour::new(
                 &identity.keypair,
-                config.discovery,
+                discovery::Config {
+                    max_message_size: config.max_message_sizes.discovery,
+                    ..config.discovery.clone()
+                },
                 config.protocol_names.discovery_kad.clone(),
                 config.protocol_names.discovery_regres.clone(),
             )?)
@@ -288,15 +573,49 @@ impl Behaviour {
             None
         };
 
+        let peer_message = (0..config.num_chains())
+            .map(|chain| {
+                let protocol = format!(
+                    "{}{}",
+                    config.namespace(chain),
+                    config.protocol_names.peer_message
+                );
+
+                libp2p::StreamProtocol::try_from_owned(protocol)
+                    .map(|protocol| peer_message::new_behaviour(protocol, config.rpc_max_size))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let node_key = node_key::new_behaviour(libp2p::StreamProtocol::try_from_owned(
+            config.protocol_names.node_key.clone(),
+        )?);
+
         // Limits for transport layer defense against connection attacks
         let connection_limits = connection_limits::Behaviour::new(connection_limits(config));
 
         // Per-IP connection limits to prevent DoS from multiple PeerIds on same IP
         let ip_limits = ip_limits::Behaviour::new(config.discovery.max_connections_per_ip);
 
+        // Deny connections to peers outside of the allowlist, if any
+        let peer_allowlist = config
+            .discovery
+            .allowed_peers
+            .clone()
+            .map(peer_allowlist::Behaviour::new);
+
+        // Report the upgrades of the relayed connections, denying the disabled ones
+        #[cfg(feature = "dcutr")]
+        let hole_punch = hole_punch::Behaviour::new(config.hole_punch.clone());
+
+        #[cfg(not(feature = "dcutr"))]
+        let hole_punch = dummy::Behaviour;
+
         Ok(Self {
+            banned_peers: Default::default(),
             connection_limits,
             ip_limits,
+            peer_allowlist: Toggle::from(peer_allowlist),
+            hole_punch,
             identify,
             ping,
             sync: Toggle::from(sync),
@@ -304,6 +623,40 @@ impl Behaviour {
             broadcast: Toggle::from(broadcast),
             discovery: Toggle::from(discovery),
             validator_proof: Toggle::from(validator_proof),
+            peer_message: Multi::new(peer_message),
+            node_key,
+            custom: Custom::new(custom),
         })
     }
 }
+
+#[cfg(all(test, feature = "gossipsub"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flood_publish_votes() {
+        let mut config = GossipSubConfig {
+            enable_flood_publish: false,
+            flood_publish_votes: true,
+            ..GossipSubConfig::default()
+        };
+
+        let topic = Channel::Consensus
+            .to_gossipsub_topic("", Default::default(), Default::default())
+            .hash();
+        let other = Channel::ProposalParts
+            .to_gossipsub_topic("", Default::default(), Default::default())
+            .hash();
+
+        let gossipsub = gossipsub_config(config, 1024, vec![topic.clone()]);
+        assert_eq!(gossipsub.mesh_n_for_topic(&topic), FLOOD_PUBLISH_MESH_N);
+        assert_eq!(gossipsub.mesh_n_low_for_topic(&topic), config.mesh_n_low);
+        assert_eq!(gossipsub.mesh_n_for_topic(&other), config.mesh_n);
+        assert!(!gossipsub.flood_publish());
+
+        config.enable_flood_publish = true;
+        let gossipsub = gossipsub_config(config, 1024, Vec::new());
+        assert!(gossipsub.flood_publish());
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(feature = "gossipsub")]
+use libp2p::gossipsub;
+use libp2p_broadcast as broadcast;
+use tokio::sync::mpsc;
+
+use crate::{Channel, Config, Event};
+
+/// Index of the first chain served by the network, in `Config::chain_ids`.
+///
+/// Its namespace also prefixes the protocols shared by all the chains, and it is the only one
+/// receiving the validator proofs and updating the validator set used to score the peers.
+pub(crate) const PRIMARY_CHAIN: usize = 0;
+
+/// Find the chain and the channel of a gossipsub topic
+#[cfg(feature = "gossipsub")]
+pub(crate) fn from_gossipsub_topic_hash(
+    config: &Config,
+    topic: &gossipsub::TopicHash,
+) -> Option<(usize, Channel)> {
+    (0..config.num_chains()).find_map(|chain| {
+        Channel::from_gossipsub_topic_hash(
+            topic,
+            &config.namespace(chain),
+            config.channel_names,
+            config.compression,
+        )
+        .map(|channel| (chain, channel))
+    })
+}
+
+/// Find the chain and the channel of a broadcast topic
+pub(crate) fn from_broadcast_topic(
+    config: &Config,
+    topic: &broadcast::Topic,
+) -> Option<(usize, Channel)> {
+    (0..config.num_chains()).find_map(|chain| {
+        Channel::from_broadcast_topic(
+            topic,
+            &config.namespace(chain),
+            config.channel_names,
+            config.compression,
+        )
+        .map(|channel| (chain, channel))
+    })
+}
+
+/// Senders of the events of the chains served by the network, indexed like `Config::chain_ids`
+pub(crate) struct EventSenders {
+    /// `None` once the chain has shut down
+    senders: Vec<Option<mpsc::Sender<Event>>>,
+}
+
+impl EventSenders {
+    pub fn new(senders: Vec<mpsc::Sender<Event>>) -> Self {
+        Self {
+            senders: senders.into_iter().map(Some).collect(),
+        }
+    }
+
+    /// Send an event to a chain, dropping it if the chain has shut down
+    pub async fn send(
+        &self,
+        chain: usize,
+        event: Event,
+    ) -> Result<(), mpsc::error::SendError<Event>> {
+        match self.senders.get(chain) {
+            Some(Some(tx_event)) => tx_event.send(event).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Sender of the events of a chain, `None` if the chain has shut down
+    pub fn sender(&self, chain: usize) -> Option<mpsc::Sender<Event>> {
+        self.senders.get(chain).cloned().flatten()
+    }
+
+    /// Send an event to all the chains which have not shut down
+    pub async fn send_all(&self, event: Event) -> Result<(), mpsc::error::SendError<Event>> {
+        for tx_event in self.senders.iter().flatten() {
+            tx_event.send(event.clone()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stop sending events to a chain, returns whether all the chains have shut down
+    pub fn shutdown(&mut self, chain: usize) -> bool {
+        if let Some(tx_event) = self.senders.get_mut(chain) {
+            *tx_event = None;
+        }
+
+        self.senders.iter().all(Option::is_none)
+    }
+}
@@ -1,15 +1,20 @@
 use core::fmt;
 
+#[cfg(feature = "gossipsub")]
 use libp2p::gossipsub;
 use libp2p_broadcast as broadcast;
 use serde::{Deserialize, Serialize};
 
+use crate::compression::{self, CompressionConfig};
+
 #[derive(Clone, Debug, Copy)]
 pub struct ChannelNames {
     pub consensus: &'static str,
     pub proposal_parts: &'static str,
     pub sync: &'static str,
     pub liveness: &'static str,
+    pub evidence: &'static str,
+    pub mempool: &'static str,
 }
 
 impl Default for ChannelNames {
@@ -19,6 +24,8 @@ impl Default for ChannelNames {
             proposal_parts: "/proposal_parts",
             sync: "/sync",
             liveness: "/liveness",
+            evidence: "/evidence",
+            mempool: "/mempool",
         }
     }
 }
@@ -29,6 +36,9 @@ pub enum Channel {
     Liveness,
     ProposalParts,
     Sync,
+    Evidence,
+    /// Transactions of the application, only subscribed to if the mempool is enabled
+    Mempool,
 }
 
 impl Channel {
@@ -38,6 +48,8 @@ impl Channel {
             Channel::ProposalParts,
             Channel::Sync,
             Channel::Liveness,
+            Channel::Evidence,
+            Channel::Mempool,
         ]
     }
 
@@ -46,15 +58,47 @@ impl Channel {
             Channel::Consensus,
             Channel::ProposalParts,
             Channel::Liveness,
+            Channel::Evidence,
         ]
     }
 
-    pub fn to_gossipsub_topic(self, channel_names: ChannelNames) -> gossipsub::IdentTopic {
-        gossipsub::IdentTopic::new(self.as_str(channel_names))
+    #[cfg(feature = "gossipsub")]
+    pub fn to_gossipsub_topic(
+        self,
+        namespace: &str,
+        channel_names: ChannelNames,
+        compression: CompressionConfig,
+    ) -> gossipsub::IdentTopic {
+        gossipsub::IdentTopic::new(self.topic_name(namespace, channel_names, compression))
     }
 
-    pub fn to_broadcast_topic(self, channel_names: ChannelNames) -> broadcast::Topic {
-        broadcast::Topic::new(self.as_str(channel_names).as_bytes())
+    pub fn to_broadcast_topic(
+        self,
+        namespace: &str,
+        channel_names: ChannelNames,
+        compression: CompressionConfig,
+    ) -> broadcast::Topic {
+        broadcast::Topic::new(
+            self.topic_name(namespace, channel_names, compression)
+                .as_bytes(),
+        )
+    }
+
+    /// Name of the pubsub topic of the channel, prefixed with the namespace of its chain
+    /// and suffixed if its messages are compressed
+    fn topic_name(
+        self,
+        namespace: &str,
+        channel_names: ChannelNames,
+        compression: CompressionConfig,
+    ) -> String {
+        let suffix = if compression.is_enabled(self) {
+            compression::TOPIC_SUFFIX
+        } else {
+            ""
+        };
+
+        format!("{namespace}{}{suffix}", self.as_str(channel_names))
     }
 
     pub fn as_str(&self, channel_names: ChannelNames) -> &'static str {
@@ -63,36 +107,80 @@ impl Channel {
             Channel::ProposalParts => channel_names.proposal_parts,
             Channel::Sync => channel_names.sync,
             Channel::Liveness => channel_names.liveness,
+            Channel::Evidence => channel_names.evidence,
+            Channel::Mempool => channel_names.mempool,
         }
     }
 
+    #[cfg(feature = "gossipsub")]
     pub fn has_gossipsub_topic(
         topic_hash: &gossipsub::TopicHash,
+        namespace: &str,
         channel_names: ChannelNames,
+        compression: CompressionConfig,
     ) -> bool {
-        Self::all()
-            .iter()
-            .any(|channel| &channel.to_gossipsub_topic(channel_names).hash() == topic_hash)
+        Self::all().iter().any(|channel| {
+            &channel
+                .to_gossipsub_topic(namespace, channel_names, compression)
+                .hash()
+                == topic_hash
+        })
     }
 
-    pub fn has_broadcast_topic(topic: &broadcast::Topic, channel_names: ChannelNames) -> bool {
-        Self::all()
-            .iter()
-            .any(|channel| &channel.to_broadcast_topic(channel_names) == topic)
+    pub fn has_broadcast_topic(
+        topic: &broadcast::Topic,
+        namespace: &str,
+        channel_names: ChannelNames,
+        compression: CompressionConfig,
+    ) -> bool {
+        Self::all().iter().any(|channel| {
+            &channel.to_broadcast_topic(namespace, channel_names, compression) == topic
+        })
     }
 
+    #[cfg(feature = "gossipsub")]
     pub fn from_gossipsub_topic_hash(
         topic: &gossipsub::TopicHash,
+        namespace: &str,
         channel_names: ChannelNames,
+        compression: CompressionConfig,
     ) -> Option<Self> {
-        if topic == &Self::Consensus.to_gossipsub_topic(channel_names).hash() {
+        if topic
+            == &Self::Consensus
+                .to_gossipsub_topic(namespace, channel_names, compression)
+                .hash()
+        {
             Some(Self::Consensus)
-        } else if topic == &Self::ProposalParts.to_gossipsub_topic(channel_names).hash() {
+        } else if topic
+            == &Self::ProposalParts
+                .to_gossipsub_topic(namespace, channel_names, compression)
+                .hash()
+        {
             Some(Self::ProposalParts)
-        } else if topic == &Self::Sync.to_gossipsub_topic(channel_names).hash() {
+        } else if topic
+            == &Self::Sync
+                .to_gossipsub_topic(namespace, channel_names, compression)
+                .hash()
+        {
             Some(Self::Sync)
-        } else if topic == &Self::Liveness.to_gossipsub_topic(channel_names).hash() {
+        } else if topic
+            == &Self::Liveness
+                .to_gossipsub_topic(namespace, channel_names, compression)
+                .hash()
+        {
             Some(Self::Liveness)
+        } else if topic
+            == &Self::Evidence
+                .to_gossipsub_topic(namespace, channel_names, compression)
+                .hash()
+        {
+            Some(Self::Evidence)
+        } else if topic
+            == &Self::Mempool
+                .to_gossipsub_topic(namespace, channel_names, compression)
+                .hash()
+        {
+            Some(Self::Mempool)
         } else {
             None
         }
@@ -100,16 +188,27 @@ impl Channel {
 
     pub fn from_broadcast_topic(
         topic: &broadcast::Topic,
+        namespace: &str,
         channel_names: ChannelNames,
+        compression: CompressionConfig,
     ) -> Option<Self> {
-        if topic == &Self::Consensus.to_broadcast_topic(channel_names) {
+        if topic == &Self::Consensus.to_broadcast_topic(namespace, channel_names, compression) {
             Some(Self::Consensus)
-        } else if topic == &Self::ProposalParts.to_broadcast_topic(channel_names) {
+        } else if topic
+            == &Self::ProposalParts.to_broadcast_topic(namespace, channel_names, compression)
+        {
             Some(Self::ProposalParts)
-        } else if topic == &Self::Sync.to_broadcast_topic(channel_names) {
+        } else if topic == &Self::Sync.to_broadcast_topic(namespace, channel_names, compression) {
             Some(Self::Sync)
-        } else if topic == &Self::Liveness.to_broadcast_topic(channel_names) {
+        } else if topic == &Self::Liveness.to_broadcast_topic(namespace, channel_names, compression)
+        {
             Some(Self::Liveness)
+        } else if topic == &Self::Evidence.to_broadcast_topic(namespace, channel_names, compression)
+        {
+            Some(Self::Evidence)
+        } else if topic == &Self::Mempool.to_broadcast_topic(namespace, channel_names, compression)
+        {
+            Some(Self::Mempool)
         } else {
             None
         }
@@ -0,0 +1,119 @@
+use bytes::Bytes;
+
+use crate::Channel;
+
+/// Suffix of the topics of the channels whose messages are compressed with LZ4,
+/// so that only the nodes compressing a channel the same way exchange messages on it
+pub(crate) const TOPIC_SUFFIX: &str = "+lz4";
+
+/// Length of the uncompressed size prepended to compressed messages
+const SIZE_PREFIX_LENGTH: usize = size_of::<u32>();
+
+/// Channels whose messages are compressed with LZ4.
+///
+/// The `pubsub_max_size` and `rpc_max_size` limits apply to the uncompressed messages.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompressionConfig {
+    pub consensus: bool,
+    pub proposal_parts: bool,
+    pub liveness: bool,
+    /// Compress both the sync status messages and the sync requests and responses
+    pub sync: bool,
+}
+
+impl CompressionConfig {
+    pub fn is_enabled(&self, channel: Channel) -> bool {
+        match channel {
+            Channel::Consensus => self.consensus,
+            Channel::ProposalParts => self.proposal_parts,
+            Channel::Liveness => self.liveness,
+            Channel::Sync => self.sync,
+            // Evidence is rare and small, so it is never compressed
+            Channel::Evidence => false,
+            // Transactions are opaque to the network, compressing them is up to the application
+            Channel::Mempool => false,
+        }
+    }
+
+    /// Maximum size of the pubsub messages sent on the wire, given the maximum size
+    /// of the uncompressed messages
+    pub(crate) fn max_transmit_size(&self, max_size: usize) -> usize {
+        if Channel::all()
+            .iter()
+            .any(|channel| self.is_enabled(*channel))
+        {
+            max_compressed_size(max_size)
+        } else {
+            max_size
+        }
+    }
+}
+
+/// Largest size a message of at most `max_size` bytes can be compressed to,
+/// including the prepended uncompressed size
+fn max_compressed_size(max_size: usize) -> usize {
+    lz4_flex::block::get_maximum_output_size(max_size) + SIZE_PREFIX_LENGTH
+}
+
+/// Compress a message of at most `max_size` bytes, prepending its uncompressed size
+pub(crate) fn compress(data: &[u8], max_size: usize) -> Result<Bytes, eyre::Report> {
+    if data.len() > max_size {
+        eyre::bail!(
+            "Message of {} bytes exceeds the maximum size of {max_size} bytes",
+            data.len()
+        );
+    }
+
+    Ok(Bytes::from(lz4_flex::compress_prepend_size(data)))
+}
+
+/// Decompress a message, rejecting it before decompressing it
+/// if its uncompressed size is above `max_size`
+pub(crate) fn decompress(data: &[u8], max_size: usize) -> Result<Bytes, eyre::Report> {
+    let (size, _) = lz4_flex::block::uncompressed_size(data)?;
+
+    if size > max_size {
+        eyre::bail!(
+            "Uncompressed message of {size} bytes exceeds the maximum size of {max_size} bytes"
+        );
+    }
+
+    Ok(Bytes::from(lz4_flex::decompress_size_prepended(data)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compress_roundtrip() {
+        let data = b"proposal part ".repeat(100);
+
+        let compressed = compress(&data, data.len()).unwrap();
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn size_limit_applies_to_uncompressed_message() {
+        let data = vec![0u8; 1000];
+
+        assert!(compress(&data, 999).is_err());
+
+        let compressed = compress(&data, 1000).unwrap();
+        assert!(compressed.len() <= 999);
+        assert!(decompress(&compressed, 999).is_err());
+    }
+
+    #[test]
+    fn max_transmit_size() {
+        let config = CompressionConfig::default();
+        assert_eq!(config.max_transmit_size(1000), 1000);
+
+        let config = CompressionConfig {
+            proposal_parts: true,
+            ..Default::default()
+        };
+        assert!(config.max_transmit_size(1000) > 1000);
+    }
+}
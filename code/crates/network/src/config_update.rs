@@ -0,0 +1,142 @@
+use libp2p::swarm;
+
+use crate::behaviour::DefaultBehaviour;
+use crate::state::State;
+use crate::GossipSubConfig;
+
+/// Parameters of the network which can be changed on a running node,
+/// the parameters left unset being unchanged.
+///
+/// The peer limits and the inbound rate limits are applied right away, to the connections
+/// made and the traffic received from then on. The GossipSub parameters cannot be changed
+/// on a running node, and only take effect once it is restarted with them in its configuration.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigUpdate {
+    /// Number of outbound peers discovery connects to
+    pub num_outbound_peers: Option<usize>,
+    /// Number of inbound peers discovery accepts, at least the number of outbound peers
+    pub num_inbound_peers: Option<usize>,
+    /// Maximum number of connections from a single IP address, at least one
+    pub max_connections_per_ip: Option<usize>,
+    /// Maximum number of bytes per second received from a single peer, unlimited if `Some(None)`
+    pub max_inbound_rate: Option<Option<u64>>,
+    /// Number of consecutive seconds a peer may exceed the inbound rate
+    pub max_rate_violations: Option<u32>,
+    /// GossipSub parameters
+    pub gossipsub: Option<GossipSubConfig>,
+}
+
+/// Outcome of an update of the configuration of a running node, by parameter name
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigUpdateOutcome {
+    /// Parameters applied to the running node
+    pub applied: Vec<&'static str>,
+    /// Parameters only taking effect once the node is restarted with them in its configuration
+    pub requires_restart: Vec<&'static str>,
+}
+
+impl ConfigUpdateOutcome {
+    /// Record a parameter as applied, if it was set in the update
+    pub fn add_applied(&mut self, name: &'static str, is_set: bool) {
+        if is_set {
+            self.applied.push(name);
+        }
+    }
+
+    /// Record a parameter as requiring a restart, if it was set in the update
+    pub fn add_requires_restart(&mut self, name: &'static str, is_set: bool) {
+        if is_set {
+            self.requires_restart.push(name);
+        }
+    }
+}
+
+/// Error rejecting a configuration update as a whole, none of its parameters being applied
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigUpdateError {
+    /// A parameter has an invalid value
+    #[error("Invalid value for `{name}`: {reason}")]
+    Invalid { name: &'static str, reason: String },
+
+    /// Network is not started
+    #[error("Network not started")]
+    NetworkStopped,
+
+    /// Internal error
+    #[error("Internal error: {0}")]
+    InternalError(String),
+}
+
+impl ConfigUpdateError {
+    pub fn invalid(name: &'static str, reason: impl Into<String>) -> Self {
+        Self::Invalid {
+            name,
+            reason: reason.into(),
+        }
+    }
+}
+
+pub(crate) fn apply(
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+    update: ConfigUpdate,
+) -> Result<ConfigUpdateOutcome, ConfigUpdateError> {
+    let (outbound, inbound) = state.discovery.peers_bounds();
+    let num_outbound_peers = update.num_outbound_peers.unwrap_or(outbound);
+    let num_inbound_peers = update.num_inbound_peers.unwrap_or(inbound);
+
+    if num_inbound_peers < num_outbound_peers {
+        return Err(ConfigUpdateError::invalid(
+            "num_inbound_peers",
+            format!("{num_inbound_peers} is less than the {num_outbound_peers} outbound peers"),
+        ));
+    }
+
+    if update.max_connections_per_ip == Some(0) {
+        return Err(ConfigUpdateError::invalid(
+            "max_connections_per_ip",
+            "must be at least 1",
+        ));
+    }
+
+    let mut outcome = ConfigUpdateOutcome::default();
+
+    if update.num_outbound_peers.is_some() || update.num_inbound_peers.is_some() {
+        state
+            .discovery
+            .set_peers_bounds(num_outbound_peers, num_inbound_peers);
+    }
+
+    outcome.add_applied("num_outbound_peers", update.num_outbound_peers.is_some());
+    outcome.add_applied("num_inbound_peers", update.num_inbound_peers.is_some());
+
+    if let Some(max_connections_per_ip) = update.max_connections_per_ip {
+        swarm
+            .behaviour_mut()
+            .ip_limits
+            .set_max_connections_per_ip(max_connections_per_ip);
+    }
+
+    outcome.add_applied(
+        "max_connections_per_ip",
+        update.max_connections_per_ip.is_some(),
+    );
+
+    let mut bandwidth = *state.bandwidth.config();
+
+    if let Some(max_inbound_rate) = update.max_inbound_rate {
+        bandwidth.max_inbound_rate = max_inbound_rate;
+    }
+
+    if let Some(max_violations) = update.max_rate_violations {
+        bandwidth.max_violations = max_violations;
+    }
+
+    state.bandwidth.set_config(bandwidth);
+
+    outcome.add_applied("max_inbound_rate", update.max_inbound_rate.is_some());
+    outcome.add_applied("max_rate_violations", update.max_rate_violations.is_some());
+    outcome.add_requires_restart("gossipsub", update.gossipsub.is_some());
+
+    Ok(outcome)
+}
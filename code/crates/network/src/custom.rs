@@ -0,0 +1,158 @@
+use std::any::Any;
+use std::fmt;
+use std::task::{Context, Poll};
+
+use libp2p::core::transport::PortUse;
+use libp2p::core::Endpoint;
+use libp2p::swarm::{
+    ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, PeerId};
+
+/// Behaviour provided by the application, eg. for mempool gossip or snapshot serving,
+/// sharing the swarm and the connections to the peers with the behaviours of the network.
+///
+/// Its events are type-erased into a [`CustomEvent`], see `NetworkEvent::Custom`.
+pub struct Custom<C> {
+    inner: C,
+}
+
+impl<C> Custom<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+}
+
+/// Event emitted by the behaviour provided by the application
+pub struct CustomEvent(Box<dyn Any + Send>);
+
+impl CustomEvent {
+    pub fn new<E: Any + Send>(event: E) -> Self {
+        Self(Box::new(event))
+    }
+
+    /// Recover the event of the behaviour provided by the application,
+    /// or get back the type-erased event if it is of another type
+    pub fn downcast<E: Any>(self) -> Result<E, Self> {
+        self.0.downcast().map(|event| *event).map_err(Self)
+    }
+
+    pub fn downcast_ref<E: Any>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+}
+
+impl fmt::Debug for CustomEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomEvent").finish_non_exhaustive()
+    }
+}
+
+impl<C: NetworkBehaviour> NetworkBehaviour for Custom<C> {
+    type ConnectionHandler = THandler<C>;
+    type ToSwarm = CustomEvent;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+        port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_outbound_connection(
+            connection_id,
+            peer,
+            addr,
+            role_override,
+            port_use,
+        )
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        self.inner.on_swarm_event(event);
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        self.inner
+            .poll(cx)
+            .map(|event| event.map_out(CustomEvent::new))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downcast() {
+        let event = CustomEvent::new(42_u64);
+        assert_eq!(event.downcast_ref::<u64>(), Some(&42));
+        assert!(event.downcast_ref::<u32>().is_none());
+
+        let event = event.downcast::<String>().unwrap_err();
+        assert_eq!(event.downcast::<u64>().unwrap(), 42);
+    }
+}
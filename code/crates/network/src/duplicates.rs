@@ -0,0 +1,183 @@
+//! Detection of the pubsub messages received more than once, per channel and per peer.
+//!
+//! GossipSub drops the messages whose id it already saw within `duplicate_cache_time`
+//! before delivering them, counting them in its `topic_msg_recv_counts_unfiltered` metric.
+//! The same payload published again under another message id, eg. republished by a peer
+//! or with a new sequence number, is delivered again though, as is any message received
+//! twice over Broadcast. Those duplicates are counted here, a peer sending many of them
+//! pointing at a misconfigured mesh or a misbehaving node.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use libp2p::PeerId;
+use malachitebft_metrics::prometheus::encoding::EncodeLabelSet;
+use malachitebft_metrics::prometheus::metrics::counter::Counter;
+use malachitebft_metrics::prometheus::metrics::family::Family;
+use malachitebft_metrics::Registry;
+use seahash::SeaHasher;
+
+// Make prometheus_client available for the derive macro
+use malachitebft_metrics::prometheus as prometheus_client;
+
+use crate::Channel;
+
+/// Maximum number of messages remembered, the oldest ones being forgotten first beyond that
+const MAX_SEEN_MESSAGES: usize = 100_000;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ChannelLabels {
+    channel: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct PeerLabels {
+    peer_id: String,
+}
+
+/// Payloads of the pubsub messages received within a time window, see the [module docs](self)
+pub(crate) struct DuplicateTracker {
+    window: Duration,
+    /// Hash of the channel and payload of the messages seen
+    seen: HashSet<u64>,
+    /// Messages seen, oldest first, to forget them once out of the window
+    expiry: VecDeque<(Instant, u64)>,
+    /// Number of duplicates received from each connected peer
+    peers: HashMap<PeerId, u64>,
+    by_channel: Family<ChannelLabels, Counter>,
+    by_peer: Family<PeerLabels, Counter>,
+}
+
+impl std::fmt::Debug for DuplicateTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DuplicateTracker")
+            .field("window", &self.window)
+            .field("seen", &self.seen.len())
+            .finish()
+    }
+}
+
+impl DuplicateTracker {
+    pub(crate) fn new(window: Duration, registry: &mut Registry) -> Self {
+        let by_channel = Family::<ChannelLabels, Counter>::default();
+        let by_peer = Family::<PeerLabels, Counter>::default();
+
+        registry.register(
+            "duplicate_messages",
+            "Pubsub messages received again within the duplicate cache time, per channel",
+            by_channel.clone(),
+        );
+
+        registry.register(
+            "peer_duplicate_messages",
+            "Pubsub messages received again within the duplicate cache time, per connected peer",
+            by_peer.clone(),
+        );
+
+        Self {
+            window,
+            seen: HashSet::new(),
+            expiry: VecDeque::new(),
+            peers: HashMap::new(),
+            by_channel,
+            by_peer,
+        }
+    }
+
+    /// Record a message received from a peer, returning whether
+    /// the same payload was already received on the channel within the window
+    pub(crate) fn record(
+        &mut self,
+        peer_id: &PeerId,
+        channel: Channel,
+        data: &[u8],
+        now: Instant,
+    ) -> bool {
+        self.expire(now);
+
+        let mut hasher = SeaHasher::new();
+        channel.hash(&mut hasher);
+        data.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        if !self.seen.insert(hash) {
+            *self.peers.entry(*peer_id).or_default() += 1;
+
+            self.by_channel
+                .get_or_create(&ChannelLabels {
+                    channel: channel.to_string(),
+                })
+                .inc();
+
+            self.by_peer
+                .get_or_create(&PeerLabels {
+                    peer_id: peer_id.to_string(),
+                })
+                .inc();
+
+            return true;
+        }
+
+        if self.expiry.len() >= MAX_SEEN_MESSAGES {
+            if let Some((_, oldest)) = self.expiry.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        self.expiry.push_back((now, hash));
+
+        false
+    }
+
+    /// Forget the messages first seen before the window, like the duplicate cache of GossipSub
+    fn expire(&mut self, now: Instant) {
+        while let Some(&(seen_at, hash)) = self.expiry.front() {
+            if now.duration_since(seen_at) <= self.window {
+                break;
+            }
+
+            self.expiry.pop_front();
+            self.seen.remove(&hash);
+        }
+    }
+
+    /// Number of duplicates received from a connected peer
+    pub(crate) fn duplicates_from(&self, peer_id: &PeerId) -> u64 {
+        self.peers.get(peer_id).copied().unwrap_or(0)
+    }
+
+    /// Forget a disconnected peer, along with its metrics
+    pub(crate) fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+        self.by_peer.remove(&PeerLabels {
+            peer_id: peer_id.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_are_counted_within_the_window() {
+        let mut tracker = DuplicateTracker::new(Duration::from_secs(60), &mut Registry::default());
+        let (alice, bob) = (PeerId::random(), PeerId::random());
+        let start = Instant::now();
+
+        assert!(!tracker.record(&alice, Channel::Consensus, b"vote", start));
+        assert!(!tracker.record(&alice, Channel::ProposalParts, b"vote", start));
+        assert!(tracker.record(&bob, Channel::Consensus, b"vote", start));
+        assert!(tracker.record(&bob, Channel::Consensus, b"vote", start));
+
+        assert_eq!(tracker.duplicates_from(&alice), 0);
+        assert_eq!(tracker.duplicates_from(&bob), 2);
+
+        let later = start + Duration::from_secs(121);
+        assert!(!tracker.record(&bob, Channel::Consensus, b"vote", later));
+
+        tracker.remove_peer(&bob);
+        assert_eq!(tracker.duplicates_from(&bob), 0);
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bytes::Bytes;
 use libp2p::request_response::{InboundRequestId, OutboundRequestId};
 use tokio::sync::{mpsc, oneshot};
@@ -6,7 +8,10 @@ use tokio::task;
 use malachitebft_peer::PeerId;
 
 use crate::{
-    validator_proof, Channel, CtrlMsg, Event, Multiaddr, PersistentPeerError, PersistentPeersOp,
+    validator_proof, AddressBook, Channel, ConfigUpdate, ConfigUpdateError, ConfigUpdateOutcome,
+    ConnectedPeer, CtrlMsg, DiscoveredPeer, Event, LinkConditions, MessageAcceptance, MessageId,
+    Multiaddr, NodeKeyError, NodeKeyRotation, NodeKeyStatus, PeerConnectionError, PeerMessageError,
+    PersistentPeerError, PersistentPeersOp, RelayLoad,
 };
 
 pub struct RecvHandle {
@@ -26,8 +31,11 @@ impl RecvHandle {
 
 pub struct CtrlHandle {
     peer_id: PeerId,
-    tx_ctrl: mpsc::Sender<CtrlMsg>,
-    task_handle: task::JoinHandle<()>,
+    /// Index of the chain served by the network the messages are sent for
+    chain: usize,
+    tx_ctrl: mpsc::Sender<(usize, CtrlMsg)>,
+    /// Only held by the handle of the first chain when the network serves several chains
+    task_handle: Option<task::JoinHandle<()>>,
 }
 
 impl CtrlHandle {
@@ -35,13 +43,18 @@ impl CtrlHandle {
         self.peer_id
     }
 
+    async fn send(&self, msg: CtrlMsg) -> Result<(), eyre::Report> {
+        self.tx_ctrl.send((self.chain, msg)).await?;
+        Ok(())
+    }
+
     pub async fn publish(&self, channel: Channel, data: Bytes) -> Result<(), eyre::Report> {
-        self.tx_ctrl.send(CtrlMsg::Publish(channel, data)).await?;
+        self.send(CtrlMsg::Publish(channel, data)).await?;
         Ok(())
     }
 
     pub async fn broadcast(&self, channel: Channel, data: Bytes) -> Result<(), eyre::Report> {
-        self.tx_ctrl.send(CtrlMsg::Broadcast(channel, data)).await?;
+        self.send(CtrlMsg::Broadcast(channel, data)).await?;
         Ok(())
     }
 
@@ -52,9 +65,7 @@ impl CtrlHandle {
     ) -> Result<OutboundRequestId, eyre::Report> {
         let (tx, rx) = oneshot::channel();
 
-        self.tx_ctrl
-            .send(CtrlMsg::SyncRequest(peer_id, data, tx))
-            .await?;
+        self.send(CtrlMsg::SyncRequest(peer_id, data, tx)).await?;
 
         Ok(rx.await?)
     }
@@ -64,18 +75,113 @@ impl CtrlHandle {
         request_id: InboundRequestId,
         data: Bytes,
     ) -> Result<(), eyre::Report> {
-        self.tx_ctrl
-            .send(CtrlMsg::SyncReply(request_id, data))
+        self.send(CtrlMsg::SyncReply(request_id, data)).await?;
+        Ok(())
+    }
+
+    /// Disconnect a peer and deny any connection to or from it for the given duration,
+    /// or until unbanned if `None`
+    pub async fn ban_peer(
+        &self,
+        peer_id: PeerId,
+        duration: Option<Duration>,
+    ) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::BanPeer(peer_id, duration)).await?;
+        Ok(())
+    }
+
+    /// Lift the ban of a peer
+    pub async fn unban_peer(&self, peer_id: PeerId) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::UnbanPeer(peer_id)).await?;
+        Ok(())
+    }
+
+    /// Simulate the given conditions on the link to a peer, or stop simulating them if `None`
+    pub async fn set_link_conditions(
+        &self,
+        peer_id: PeerId,
+        conditions: Option<LinkConditions>,
+    ) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::SetLinkConditions(peer_id, conditions))
             .await?;
         Ok(())
     }
 
+    /// Report the outcome of the validation of a message received through GossipSub
+    pub async fn validate_message(
+        &self,
+        message_id: MessageId,
+        acceptance: MessageAcceptance,
+    ) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::ValidateMessage(message_id, acceptance))
+            .await?;
+        Ok(())
+    }
+
+    /// Start listening on an additional address
+    pub async fn add_listen_addr(&self, addr: Multiaddr) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::AddListenAddr(addr)).await?;
+        Ok(())
+    }
+
+    /// Stop listening on an address
+    pub async fn remove_listen_addr(&self, addr: Multiaddr) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::RemoveListenAddr(addr)).await?;
+        Ok(())
+    }
+
+    /// Send a message directly to a peer, the returned receiver resolving with its reply
+    pub async fn send_to_peer(
+        &self,
+        peer_id: PeerId,
+        data: Bytes,
+    ) -> Result<oneshot::Receiver<Result<Bytes, PeerMessageError>>, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+        self.send(CtrlMsg::SendToPeer(peer_id, data, tx)).await?;
+        Ok(rx)
+    }
+
+    /// Reply to a message sent directly by a peer, or drop it without replying if `None`
+    pub async fn reply_to_peer(
+        &self,
+        request_id: InboundRequestId,
+        data: Option<Bytes>,
+    ) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::ReplyToPeer(request_id, data)).await?;
+        Ok(())
+    }
+
     pub async fn update_validator_set(
         &self,
         validators: Vec<crate::ValidatorInfo>,
     ) -> Result<(), eyre::Report> {
-        self.tx_ctrl
-            .send(CtrlMsg::UpdateValidatorSet(validators))
+        self.send(CtrlMsg::UpdateValidatorSet(validators)).await?;
+        Ok(())
+    }
+
+    /// Provide the peer IDs of the current validator set, to be dialed first by discovery,
+    /// kept connected and protected from mesh pruning in gossipsub
+    pub async fn update_validator_peers(&self, peers: Vec<PeerId>) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::UpdateValidatorPeers(peers)).await?;
+        Ok(())
+    }
+
+    /// Advertise the lowest height whose decided value is still served to syncing peers
+    pub async fn set_retained_height(&self, height: u64) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::SetRetainedHeight(height)).await?;
+        Ok(())
+    }
+
+    /// Let the peers know about the peers known to this node, once consensus is done recovering
+    pub async fn node_started(&self) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::NodeStarted).await?;
+        Ok(())
+    }
+
+    /// Send the validator proof signed by the new consensus key of this node
+    /// to the connected peers, and to the peers connecting from now on
+    pub async fn update_validator_proof(&self, proof_bytes: Bytes) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::UpdateValidatorProof(proof_bytes))
             .await?;
         Ok(())
     }
@@ -88,20 +194,89 @@ impl CtrlHandle {
         result: validator_proof::ProofVerificationResult,
         public_key: Option<Vec<u8>>,
     ) -> Result<(), eyre::Report> {
-        self.tx_ctrl
-            .send(CtrlMsg::ValidatorProofVerified {
-                peer_id,
-                result,
-                public_key,
-            })
-            .await?;
+        self.send(CtrlMsg::ValidatorProofVerified {
+            peer_id,
+            result,
+            public_key,
+        })
+        .await?;
         Ok(())
     }
 
     pub async fn dump_state(&self) -> Result<crate::NetworkStateDump, eyre::Report> {
         let (tx, rx) = oneshot::channel();
 
-        self.tx_ctrl.send(CtrlMsg::DumpState(tx)).await?;
+        self.send(CtrlMsg::DumpState(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Snapshot of the local view of the graph of the network
+    pub async fn dump_topology(&self) -> Result<crate::NetworkTopology, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::DumpTopology(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    pub async fn discovered_peers(&self) -> Result<Vec<DiscoveredPeer>, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::DiscoveredPeers(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// List the peers this node is connected to, with their connections and scores
+    pub async fn list_peers(&self) -> Result<Vec<ConnectedPeer>, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::ListPeers(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Export the peers known to discovery, most recently seen first, to seed other nodes
+    pub async fn export_address_book(&self) -> Result<AddressBook, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::ExportAddressBook(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Dial the peers of an address book, eg. exported by another node,
+    /// returning the number of peers dialed
+    pub async fn import_address_book(&self, book: AddressBook) -> Result<usize, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::ImportAddressBook(book, tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Close all the connections to a peer. Discovery may dial it again later,
+    /// see [`CtrlHandle::ban_peer`] to keep it disconnected.
+    pub async fn disconnect_peer(
+        &self,
+        peer_id: PeerId,
+    ) -> Result<Result<(), PeerConnectionError>, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::DisconnectPeer(peer_id, tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Dial a peer at the given address, returning once the dial is started
+    pub async fn dial_peer(
+        &self,
+        addr: Multiaddr,
+    ) -> Result<Result<(), PeerConnectionError>, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::DialPeer(addr, tx)).await?;
 
         Ok(rx.await?)
     }
@@ -112,12 +287,11 @@ impl CtrlHandle {
     ) -> Result<Result<(), PersistentPeerError>, eyre::Report> {
         let (tx, rx) = oneshot::channel();
 
-        self.tx_ctrl
-            .send(CtrlMsg::UpdatePersistentPeers(
-                PersistentPeersOp::Add(addr),
-                tx,
-            ))
-            .await?;
+        self.send(CtrlMsg::UpdatePersistentPeers(
+            PersistentPeersOp::Add(addr),
+            tx,
+        ))
+        .await?;
 
         Ok(rx.await?)
     }
@@ -128,29 +302,81 @@ impl CtrlHandle {
     ) -> Result<Result<(), PersistentPeerError>, eyre::Report> {
         let (tx, rx) = oneshot::channel();
 
-        self.tx_ctrl
-            .send(CtrlMsg::UpdatePersistentPeers(
-                PersistentPeersOp::Remove(addr),
-                tx,
-            ))
-            .await?;
+        self.send(CtrlMsg::UpdatePersistentPeers(
+            PersistentPeersOp::Remove(addr),
+            tx,
+        ))
+        .await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Change the peer limits and the inbound rate limits of the running network,
+    /// see [`ConfigUpdate`]
+    pub async fn update_config(
+        &self,
+        update: ConfigUpdate,
+    ) -> Result<Result<ConfigUpdateOutcome, ConfigUpdateError>, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::UpdateConfig(update, tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Current `PeerId` of the node, and the one it takes once restarted if its key was rotated
+    pub async fn node_key_status(&self) -> Result<NodeKeyStatus, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::NodeKeyStatus(tx)).await?;
+
+        Ok(rx.await?)
+    }
+
+    /// Rotate the node key, taking effect once the node is restarted,
+    /// and announce the new `PeerId` to the connected persistent peers
+    pub async fn rotate_node_key(
+        &self,
+    ) -> Result<Result<NodeKeyRotation, NodeKeyError>, eyre::Report> {
+        let (tx, rx) = oneshot::channel();
+
+        self.send(CtrlMsg::RotateNodeKey(tx)).await?;
 
         Ok(rx.await?)
     }
 
+    /// Set the load of the relay server run by this node, advertised to the peers
+    /// in peer exchange responses, or `None` if this node does not relay circuits.
+    pub async fn set_relay_load(&self, load: Option<RelayLoad>) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::SetRelayLoad(load)).await?;
+        Ok(())
+    }
+
+    /// Add a delta to the score of a peer in GossipSub, eg. a negative one to penalize
+    /// a peer delivering invalid proposals or votes, which prunes it from the mesh
+    /// once its score becomes negative. The reported score decays over time.
+    pub async fn report_peer(&self, peer_id: PeerId, score_delta: f64) -> Result<(), eyre::Report> {
+        self.send(CtrlMsg::ReportPeer(peer_id, score_delta)).await?;
+        Ok(())
+    }
+
     pub async fn wait_shutdown(self) -> Result<(), eyre::Report> {
         self.shutdown().await?;
         self.join().await?;
         Ok(())
     }
 
+    /// Shut down the chain of this handle, the network stopping once all its chains are shut down
     pub async fn shutdown(&self) -> Result<(), eyre::Report> {
-        self.tx_ctrl.send(CtrlMsg::Shutdown).await?;
+        self.send(CtrlMsg::Shutdown).await?;
         Ok(())
     }
 
+    /// Wait for the network to stop, returns immediately if this handle does not own its task
     pub async fn join(self) -> Result<(), eyre::Report> {
-        self.task_handle.await?;
+        if let Some(task_handle) = self.task_handle {
+            task_handle.await?;
+        }
         Ok(())
     }
 }
@@ -164,15 +390,17 @@ pub struct Handle {
 impl Handle {
     pub fn new(
         peer_id: PeerId,
-        tx_ctrl: mpsc::Sender<CtrlMsg>,
+        chain: usize,
+        tx_ctrl: mpsc::Sender<(usize, CtrlMsg)>,
         rx_event: mpsc::Receiver<Event>,
-        task_handle: task::JoinHandle<()>,
+        task_handle: Option<task::JoinHandle<()>>,
     ) -> Self {
         Self {
             peer_id,
             recv: RecvHandle { peer_id, rx_event },
             ctrl: CtrlHandle {
                 peer_id,
+                chain,
                 tx_ctrl,
                 task_handle,
             },
@@ -191,6 +419,36 @@ impl Handle {
         self.recv.recv().await
     }
 
+    pub async fn discovered_peers(&self) -> Result<Vec<DiscoveredPeer>, eyre::Report> {
+        self.ctrl.discovered_peers().await
+    }
+
+    pub async fn list_peers(&self) -> Result<Vec<ConnectedPeer>, eyre::Report> {
+        self.ctrl.list_peers().await
+    }
+
+    pub async fn export_address_book(&self) -> Result<AddressBook, eyre::Report> {
+        self.ctrl.export_address_book().await
+    }
+
+    pub async fn import_address_book(&self, book: AddressBook) -> Result<usize, eyre::Report> {
+        self.ctrl.import_address_book(book).await
+    }
+
+    pub async fn disconnect_peer(
+        &self,
+        peer_id: PeerId,
+    ) -> Result<Result<(), PeerConnectionError>, eyre::Report> {
+        self.ctrl.disconnect_peer(peer_id).await
+    }
+
+    pub async fn dial_peer(
+        &self,
+        addr: Multiaddr,
+    ) -> Result<Result<(), PeerConnectionError>, eyre::Report> {
+        self.ctrl.dial_peer(addr).await
+    }
+
     pub async fn add_persistent_peer(
         &self,
         addr: Multiaddr,
@@ -205,6 +463,57 @@ impl Handle {
         self.ctrl.remove_persistent_peer(addr).await
     }
 
+    pub async fn update_config(
+        &self,
+        update: ConfigUpdate,
+    ) -> Result<Result<ConfigUpdateOutcome, ConfigUpdateError>, eyre::Report> {
+        self.ctrl.update_config(update).await
+    }
+
+    pub async fn set_relay_load(&self, load: Option<RelayLoad>) -> Result<(), eyre::Report> {
+        self.ctrl.set_relay_load(load).await
+    }
+
+    pub async fn report_peer(&self, peer_id: PeerId, score_delta: f64) -> Result<(), eyre::Report> {
+        self.ctrl.report_peer(peer_id, score_delta).await
+    }
+
+    pub async fn ban_peer(
+        &self,
+        peer_id: PeerId,
+        duration: Option<Duration>,
+    ) -> Result<(), eyre::Report> {
+        self.ctrl.ban_peer(peer_id, duration).await
+    }
+
+    pub async fn unban_peer(&self, peer_id: PeerId) -> Result<(), eyre::Report> {
+        self.ctrl.unban_peer(peer_id).await
+    }
+
+    pub async fn set_link_conditions(
+        &self,
+        peer_id: PeerId,
+        conditions: Option<LinkConditions>,
+    ) -> Result<(), eyre::Report> {
+        self.ctrl.set_link_conditions(peer_id, conditions).await
+    }
+
+    pub async fn add_listen_addr(&self, addr: Multiaddr) -> Result<(), eyre::Report> {
+        self.ctrl.add_listen_addr(addr).await
+    }
+
+    pub async fn validate_message(
+        &self,
+        message_id: MessageId,
+        acceptance: MessageAcceptance,
+    ) -> Result<(), eyre::Report> {
+        self.ctrl.validate_message(message_id, acceptance).await
+    }
+
+    pub async fn remove_listen_addr(&self, addr: Multiaddr) -> Result<(), eyre::Report> {
+        self.ctrl.remove_listen_addr(addr).await
+    }
+
     pub async fn wait_shutdown(self) -> Result<(), eyre::Report> {
         self.ctrl.wait_shutdown().await
     }
@@ -0,0 +1,397 @@
+//! Hole punching (DCUtR) observability and control.
+//!
+//! The upgrades of the relayed connections into direct ones are told apart by their dials,
+//! made with the role of the listener of the connection (see [`DialTimeouts`]), whichever
+//! behaviour triggers them. This behaviour reports the attempts and their outcome per peer,
+//! and denies them for the peers and networks for which hole punching is disabled,
+//! or when retried too often, so that the peers stay on their relay circuits.
+//!
+//! [`DialTimeouts`]: crate::DialTimeouts
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use libp2p::core::transport::PortUse;
+use libp2p::core::Endpoint;
+use libp2p::swarm::{
+    ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::Multiaddr;
+use tracing::debug;
+
+use malachitebft_discovery::addr_filter::ip_of;
+
+use crate::PeerId;
+
+/// Hole punching of the connections to the peers reached through a relay
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HolePunchConfig {
+    /// Upgrade the relayed connections into direct ones, the peers staying on their
+    /// relay circuits if disabled
+    pub enabled: bool,
+
+    /// Peers never dialed directly to upgrade their relayed connection
+    pub disabled_peers: HashSet<libp2p::PeerId>,
+
+    /// Networks never dialed directly to upgrade a relayed connection,
+    /// eg. the ones known to be behind a symmetric NAT
+    pub disabled_networks: Vec<IpSubnet>,
+
+    /// Minimum interval between two upgrade attempts to the same peer,
+    /// the ones in between being denied. Unbounded if `None`.
+    pub min_retry_interval: Option<Duration>,
+}
+
+impl Default for HolePunchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled_peers: HashSet::new(),
+            disabled_networks: Vec::new(),
+            min_retry_interval: None,
+        }
+    }
+}
+
+/// Range of IP addresses sharing the same first `prefix_len` bits, eg. `10.0.0.0/8`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct IpSubnet {
+    pub addr: IpAddr,
+    pub prefix_len: u8,
+}
+
+impl IpSubnet {
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Self {
+        Self { addr, prefix_len }
+    }
+
+    /// Whether the address belongs to the subnet
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(subnet), IpAddr::V4(ip)) => {
+                let prefix_len = u32::from(self.prefix_len.min(32));
+                let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+                u32::from(subnet) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(subnet), IpAddr::V6(ip)) => {
+                let prefix_len = u32::from(self.prefix_len.min(128));
+                let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+                u128::from(subnet) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for IpSubnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Why an upgrade of a relayed connection was not attempted
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HolePunchDenial {
+    /// Hole punching is disabled
+    Disabled,
+    /// Hole punching is disabled for the peer
+    DisabledPeer,
+    /// One of the addresses of the peer is in a disabled network
+    DisabledNetwork(IpSubnet),
+    /// The previous attempt to the peer is more recent than the minimum retry interval
+    TooFrequent,
+}
+
+impl fmt::Display for HolePunchDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disabled => write!(f, "hole punching is disabled"),
+            Self::DisabledPeer => write!(f, "hole punching is disabled for the peer"),
+            Self::DisabledNetwork(subnet) => {
+                write!(f, "hole punching is disabled for network {subnet}")
+            }
+            Self::TooFrequent => write!(f, "retried before the minimum retry interval"),
+        }
+    }
+}
+
+impl std::error::Error for HolePunchDenial {}
+
+/// Attempt to upgrade the relayed connection to a peer into a direct one, and its outcome
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HolePunchEvent {
+    /// A direct connection to the peer is being dialed
+    Attempted(PeerId),
+    /// The direct connection to the peer was established
+    Succeeded(PeerId),
+    /// The direct dial failed, the peer staying on its relay circuit
+    Failed { peer_id: PeerId, error: String },
+    /// The direct dial was denied, the peer staying on its relay circuit
+    Denied {
+        peer_id: PeerId,
+        reason: HolePunchDenial,
+    },
+}
+
+impl HolePunchEvent {
+    pub fn peer_id(&self) -> &PeerId {
+        match self {
+            Self::Attempted(peer_id)
+            | Self::Succeeded(peer_id)
+            | Self::Failed { peer_id, .. }
+            | Self::Denied { peer_id, .. } => peer_id,
+        }
+    }
+
+    /// Label of the event in the metrics
+    pub(crate) fn outcome(&self) -> &'static str {
+        match self {
+            Self::Attempted(_) => "attempted",
+            Self::Succeeded(_) => "succeeded",
+            Self::Failed { .. } => "failed",
+            Self::Denied { .. } => "denied",
+        }
+    }
+}
+
+impl fmt::Display for HolePunchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Attempted(peer_id) => write!(f, "Attempted(peer: {peer_id})"),
+            Self::Succeeded(peer_id) => write!(f, "Succeeded(peer: {peer_id})"),
+            Self::Failed { peer_id, error } => {
+                write!(f, "Failed(peer: {peer_id}, error: {error})")
+            }
+            Self::Denied { peer_id, reason } => {
+                write!(f, "Denied(peer: {peer_id}, reason: {reason})")
+            }
+        }
+    }
+}
+
+/// Behaviour reporting and controlling the upgrades of the relayed connections
+pub struct Behaviour {
+    config: HolePunchConfig,
+    /// Time of the last attempt to each peer, within the minimum retry interval
+    last_attempts: HashMap<libp2p::PeerId, Instant>,
+    /// Peer of each upgrade in progress
+    pending: HashMap<ConnectionId, libp2p::PeerId>,
+    events: VecDeque<HolePunchEvent>,
+}
+
+impl Behaviour {
+    pub fn new(config: HolePunchConfig) -> Self {
+        Self {
+            config,
+            last_attempts: HashMap::new(),
+            pending: HashMap::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Check whether an upgrade to the peer through the given addresses is allowed,
+    /// recording it as the last attempt to the peer if so
+    fn check(
+        &mut self,
+        peer_id: &libp2p::PeerId,
+        addresses: &[Multiaddr],
+        now: Instant,
+    ) -> Result<(), HolePunchDenial> {
+        if !self.config.enabled {
+            return Err(HolePunchDenial::Disabled);
+        }
+
+        if self.config.disabled_peers.contains(peer_id) {
+            return Err(HolePunchDenial::DisabledPeer);
+        }
+
+        let disabled_network = addresses.iter().filter_map(ip_of).find_map(|ip| {
+            self.config
+                .disabled_networks
+                .iter()
+                .find(|subnet| subnet.contains(ip))
+        });
+
+        if let Some(subnet) = disabled_network {
+            return Err(HolePunchDenial::DisabledNetwork(*subnet));
+        }
+
+        if let Some(interval) = self.config.min_retry_interval {
+            self.last_attempts
+                .retain(|_, last_attempt| now.duration_since(*last_attempt) < interval);
+
+            if self.last_attempts.contains_key(peer_id) {
+                return Err(HolePunchDenial::TooFrequent);
+            }
+
+            self.last_attempts.insert(*peer_id, now);
+        }
+
+        Ok(())
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = libp2p::swarm::dummy::ConnectionHandler;
+    type ToSwarm = HolePunchEvent;
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<libp2p::PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        // Upgrades of a relayed connection are dialed as the listener of the connection
+        if effective_role != Endpoint::Listener {
+            return Ok(vec![]);
+        }
+
+        let Some(peer_id) = maybe_peer else {
+            return Ok(vec![]);
+        };
+
+        if let Err(reason) = self.check(&peer_id, addresses, Instant::now()) {
+            debug!(%peer_id, %reason, "Denying hole punching");
+
+            self.events.push_back(HolePunchEvent::Denied {
+                peer_id: PeerId::from_libp2p(&peer_id),
+                reason,
+            });
+
+            return Err(ConnectionDenied::new(reason));
+        }
+
+        self.pending.insert(connection_id, peer_id);
+        self.events
+            .push_back(HolePunchEvent::Attempted(PeerId::from_libp2p(&peer_id)));
+
+        Ok(vec![])
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: libp2p::PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(libp2p::swarm::dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: libp2p::PeerId,
+        _addr: &Multiaddr,
+        _role_override: Endpoint,
+        _port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        Ok(libp2p::swarm::dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm<'_>) {
+        match event {
+            FromSwarm::ConnectionEstablished(info) => {
+                if let Some(peer_id) = self.pending.remove(&info.connection_id) {
+                    self.events
+                        .push_back(HolePunchEvent::Succeeded(PeerId::from_libp2p(&peer_id)));
+                }
+            }
+            FromSwarm::DialFailure(info) => {
+                if let Some(peer_id) = self.pending.remove(&info.connection_id) {
+                    self.events.push_back(HolePunchEvent::Failed {
+                        peer_id: PeerId::from_libp2p(&peer_id),
+                        error: info.error.to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: libp2p::PeerId,
+        _connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        // dummy::ConnectionHandler produces no events
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        match self.events.pop_front() {
+            Some(event) => Poll::Ready(ToSwarm::GenerateEvent(event)),
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_are_denied_for_disabled_peers_and_networks() {
+        let disabled_peer = libp2p::PeerId::random();
+        let peer = libp2p::PeerId::random();
+
+        let mut behaviour = Behaviour::new(HolePunchConfig {
+            disabled_peers: HashSet::from([disabled_peer]),
+            disabled_networks: vec![IpSubnet::new("10.1.0.0".parse().unwrap(), 16)],
+            min_retry_interval: Some(Duration::from_secs(10)),
+            ..Default::default()
+        });
+
+        let now = Instant::now();
+        let addr = |s: &str| s.parse::<Multiaddr>().unwrap();
+        let public = [addr("/ip4/1.2.3.4/tcp/27000")];
+        let private = [
+            addr("/ip4/1.2.3.4/tcp/27000"),
+            addr("/ip4/10.1.2.3/tcp/27000"),
+        ];
+
+        assert_eq!(
+            behaviour.check(&disabled_peer, &public, now),
+            Err(HolePunchDenial::DisabledPeer)
+        );
+        assert_eq!(
+            behaviour.check(&peer, &private, now),
+            Err(HolePunchDenial::DisabledNetwork(IpSubnet::new(
+                "10.1.0.0".parse().unwrap(),
+                16
+            )))
+        );
+
+        // Denied attempts do not count towards the retry interval
+        assert_eq!(behaviour.check(&peer, &public, now), Ok(()));
+        assert_eq!(
+            behaviour.check(&peer, &public, now + Duration::from_secs(5)),
+            Err(HolePunchDenial::TooFrequent)
+        );
+        assert_eq!(
+            behaviour.check(&peer, &public, now + Duration::from_secs(10)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn subnet_contains_addresses_sharing_its_prefix() {
+        let subnet = IpSubnet::new("192.168.0.0".parse().unwrap(), 16);
+
+        assert!(subnet.contains("192.168.4.2".parse().unwrap()));
+        assert!(!subnet.contains("192.169.0.1".parse().unwrap()));
+        assert!(!subnet.contains("::1".parse().unwrap()));
+
+        let all = IpSubnet::new("0.0.0.0".parse().unwrap(), 0);
+        assert!(all.contains("8.8.8.8".parse().unwrap()));
+    }
+}
@@ -23,6 +23,8 @@ use libp2p::swarm::{
 use libp2p::{Multiaddr, PeerId};
 use tracing::debug;
 
+use malachitebft_discovery::addr_filter::ip_of;
+
 /// Behaviour that limits connections per IP address.
 ///
 /// Tracks pending inbound connections immediately (before handshake completes)
@@ -47,6 +49,12 @@ impl Behaviour {
         }
     }
 
+    /// Change the maximum number of connections per IP address,
+    /// the connections already established being kept
+    pub fn set_max_connections_per_ip(&mut self, max_connections_per_ip: usize) {
+        self.max_connections_per_ip = max_connections_per_ip;
+    }
+
     /// Increment connection count for an IP, tracking by connection ID.
     fn track_connection(&mut self, connection_id: ConnectionId, ip: IpAddr) {
         self.connection_ips.insert(connection_id, ip);
@@ -76,7 +84,7 @@ impl NetworkBehaviour for Behaviour {
         _local_addr: &Multiaddr,
         remote_addr: &Multiaddr,
     ) -> Result<(), ConnectionDenied> {
-        if let Some(ip) = extract_ip(remote_addr) {
+        if let Some(ip) = ip_of(remote_addr) {
             let count = self.connections_per_ip.get(&ip).copied().unwrap_or(0);
             if count >= self.max_connections_per_ip {
                 debug!(
@@ -159,16 +167,3 @@ impl std::fmt::Display for IpLimitExceeded {
 }
 
 impl std::error::Error for IpLimitExceeded {}
-
-/// Extract IP address from a multiaddr.
-fn extract_ip(addr: &Multiaddr) -> Option<IpAddr> {
-    use libp2p::multiaddr::Protocol;
-    for proto in addr.iter() {
-        match proto {
-            Protocol::Ip4(ip) => return Some(IpAddr::V4(ip)),
-            Protocol::Ip6(ip) => return Some(IpAddr::V6(ip)),
-            _ => continue,
-        }
-    }
-    None
-}
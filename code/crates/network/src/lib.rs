@@ -1,13 +1,16 @@
 use std::error::Error;
 use std::ops::ControlFlow;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use futures::StreamExt;
 use itertools::Itertools;
+#[cfg(feature = "gossipsub")]
+use libp2p::gossipsub;
 use libp2p::metrics::{Metrics, Recorder};
 use libp2p::request_response::{InboundRequestId, OutboundRequestId};
-use libp2p::swarm::{self, SwarmEvent};
-use libp2p::{gossipsub, identify, quic, SwarmBuilder};
+use libp2p::swarm::{self, dummy, SwarmEvent};
+use libp2p::{identify, quic, SwarmBuilder};
 use libp2p_broadcast as broadcast;
 use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, error, error_span, info, trace, warn, Instrument};
@@ -19,7 +22,13 @@ use malachitebft_sync::{self as sync};
 pub use malachitebft_peer::PeerId;
 
 pub use bytes::Bytes;
+#[cfg(feature = "gossipsub")]
 pub use libp2p::gossipsub::MessageId;
+
+/// Identifier of a GossipSub message, never emitted when the `gossipsub` feature is disabled
+#[cfg(not(feature = "gossipsub"))]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MessageId(pub Vec<u8>);
 pub use libp2p::identity::Keypair;
 pub use libp2p::Multiaddr;
 
@@ -37,23 +46,86 @@ mod peer_type;
 pub use peer_type::PeerType;
 
 pub mod peer_scoring;
+pub use peer_scoring::PeerScoreConfig;
 
 mod utils;
 
+mod transport;
+use transport::DialTimeout;
+pub use transport::DialTimeouts;
+
+mod security;
+pub use security::SecurityProtocol;
+
+mod websocket;
+pub use websocket::{WebSocketConfig, WebSocketTlsConfig};
+
+mod bandwidth;
+pub use bandwidth::BandwidthConfig;
+use bandwidth::{BandwidthMeter, Direction};
+
+mod duplicates;
+use duplicates::DuplicateTracker;
+
+mod liveness;
+pub use liveness::LivenessConfig;
+
+mod mempool;
+pub use mempool::MempoolConfig;
+use mempool::{Admission, MempoolLimiter};
+
+mod message_size;
+pub use message_size::MaxMessageSizes;
+use message_size::Protocol;
+
+mod link;
+pub use link::LinkConditions;
+
+mod compression;
+pub use compression::CompressionConfig;
+
+mod config_update;
+pub use config_update::{ConfigUpdate, ConfigUpdateError, ConfigUpdateOutcome};
+
+mod chain;
+use chain::{EventSenders, PRIMARY_CHAIN};
+
+mod multi;
+pub use multi::Multi;
+
+pub mod custom;
+pub use custom::CustomEvent;
+
+mod addr_monitor;
+#[cfg_attr(not(feature = "dcutr"), allow(dead_code))]
+mod hole_punch;
+pub use hole_punch::{HolePunchConfig, HolePunchDenial, HolePunchEvent, IpSubnet};
+
 mod ip_limits;
+mod peer_allowlist;
+pub mod peer_message;
+pub use peer_message::PeerMessageError;
+pub mod node_key;
+pub use node_key::{NodeKeyError, NodeKeyRotation, NodeKeyStatus};
 pub mod validator_proof;
 
 // Re-export state types for external use (e.g., RPC)
-pub use state::{LocalNodeInfo, PeerInfo, ValidatorInfo};
+pub use state::{ConnectedPeer, LocalNodeInfo, PeerInfo, ValidatorInfo};
 
 mod state;
-pub use state::NetworkStateDump;
 use state::State;
+pub use state::{NetworkStateDump, NetworkTopology};
 
-use behaviour::{Behaviour, NetworkEvent};
+use behaviour::{Behaviour, DefaultBehaviour, NetworkEvent};
 use handle::Handle;
 
 const METRICS_PREFIX: &str = "malachitebft_network";
+
+/// Maximum time to wait for the peers to acknowledge that the node is shutting down
+const SHUTDOWN_DISCONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Maximum time to wait for the connections to the peers to be closed when shutting down
+const CLOSE_CONNECTIONS_TIMEOUT: Duration = Duration::from_secs(2);
 const DISCOVERY_METRICS_PREFIX: &str = "malachitebft_discovery";
 
 #[derive(Clone, Debug, PartialEq)]
@@ -63,6 +135,27 @@ pub struct ProtocolNames {
     pub discovery_regres: String,
     pub sync: String,
     pub validator_proof: String,
+    /// Protocol of the messages sent by the application directly to a peer
+    pub peer_message: String,
+    /// Protocol of the announcements of the new `PeerId` of a node to its persistent peers
+    pub node_key: String,
+}
+
+impl ProtocolNames {
+    /// Prefix the names of the protocols shared by all the chains served by the network
+    /// with the given namespace, the sync and direct messages protocols being namespaced
+    /// per chain instead
+    fn with_namespace(self, namespace: &str) -> Self {
+        Self {
+            consensus: format!("{namespace}{}", self.consensus),
+            discovery_kad: format!("{namespace}{}", self.discovery_kad),
+            discovery_regres: format!("{namespace}{}", self.discovery_regres),
+            sync: self.sync,
+            validator_proof: format!("{namespace}{}", self.validator_proof),
+            peer_message: self.peer_message,
+            node_key: format!("{namespace}{}", self.node_key),
+        }
+    }
 }
 
 impl Default for ProtocolNames {
@@ -73,6 +166,8 @@ impl Default for ProtocolNames {
             discovery_regres: "/malachitebft-discovery/reqres/v1beta1".to_string(),
             sync: "/malachitebft-sync/v1beta1".to_string(),
             validator_proof: "/malachitebft-validator-proof/v1".to_string(),
+            peer_message: "/malachitebft-peer-message/v1".to_string(),
+            node_key: "/malachitebft-node-key/v1".to_string(),
         }
     }
 }
@@ -105,7 +200,33 @@ pub struct GossipSubConfig {
     pub mesh_outbound_min: usize,
     pub enable_peer_scoring: bool,
     pub enable_explicit_peering: bool,
+    pub enable_explicit_validator_peering: bool,
     pub enable_flood_publish: bool,
+    /// Publish the votes, ie. the messages of the consensus channel, to all the peers subscribed
+    /// to it rather than to the mesh peers only, saving a hop on the critical path of consensus
+    /// at the cost of bandwidth. Implied by `enable_flood_publish`, which applies to all channels.
+    pub flood_publish_votes: bool,
+    pub peer_score: PeerScoreConfig,
+    /// Only forward the received messages to the other peers once the application validated
+    /// them, see `Event::UnvalidatedMessage`
+    pub validate_messages: bool,
+    /// Number of heartbeats the published and forwarded messages are kept in the message cache
+    /// for, to be served to the peers asking for them
+    pub history_length: usize,
+    /// Number of heartbeats of the message cache advertised in the gossip to the non-mesh peers,
+    /// at most `history_length`
+    pub history_gossip: usize,
+    /// How long the ids of the messages received are remembered, the messages received again
+    /// within that time being dropped as duplicates
+    pub duplicate_cache_time: Duration,
+}
+
+impl GossipSubConfig {
+    /// Whether a peer of the given type should be added as an explicit peer in gossipsub
+    pub fn is_explicit_peer(&self, peer_type: PeerType) -> bool {
+        (self.enable_explicit_peering && peer_type.is_persistent())
+            || (self.enable_explicit_validator_peering && peer_type.is_validator())
+    }
 }
 
 impl Default for GossipSubConfig {
@@ -118,16 +239,58 @@ impl Default for GossipSubConfig {
             mesh_outbound_min: 2,
             enable_peer_scoring: false,
             enable_explicit_peering: false,
+            enable_explicit_validator_peering: false,
             enable_flood_publish: true,
+            flood_publish_votes: false,
+            peer_score: PeerScoreConfig::default(),
+            validate_messages: false,
+            history_length: 5,
+            history_gossip: 3,
+            duplicate_cache_time: Duration::from_secs(60),
         }
     }
 }
 
+/// Caps on the number of connections, enforced by the swarm before any protocol runs on them.
+/// The ones not set are derived from the discovery configuration.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionLimitsConfig {
+    pub max_established_incoming: Option<u32>,
+    pub max_established_outgoing: Option<u32>,
+    /// Maximum number of inbound connections still in the security and multiplexing handshakes
+    pub max_pending_incoming: Option<u32>,
+    /// Maximum number of dials in progress
+    pub max_pending_outgoing: Option<u32>,
+    pub max_established_per_peer: Option<u32>,
+}
+
 pub type BoxError = Box<dyn Error + Send + Sync + 'static>;
 
 pub type DiscoveryConfig = discovery::Config;
 pub type BootstrapProtocol = discovery::config::BootstrapProtocol;
 pub type Selector = discovery::config::Selector;
+pub type AddressFamilyPreference = discovery::config::AddressFamilyPreference;
+pub type BackoffConfig = discovery::config::BackoffConfig;
+pub type ReconnectConfig = discovery::config::ReconnectConfig;
+pub type ReachabilityConfig = discovery::config::ReachabilityConfig;
+pub type AutoRelayConfig = discovery::config::AutoRelayConfig;
+pub type DiscoveredPeer = discovery::DiscoveredPeer;
+pub type AddressBook = discovery::AddressBook;
+pub type AddressBookEntry = discovery::AddressBookEntry;
+pub type AddressBookError = discovery::AddressBookError;
+pub type DiscoveredPeerKind = discovery::PeerKind;
+pub type DiscoveredPeerIdentity = discovery::PeerIdentity;
+pub type DiscoveredConnection = discovery::ConnectionSnapshot;
+pub type BootstrapProgress = discovery::BootstrapProgress;
+pub type BootstrapPhase = discovery::BootstrapPhase;
+pub type RelayLoad = discovery::RelayLoad;
+pub type PeerCapabilities = discovery::PeerCapabilities;
+pub type RelayElectionEvent = discovery::RelayElectionEvent;
+
+pub use discovery::addr_filter::{AddrClass, AddressPolicy, DefaultAddressPolicy};
+pub use discovery::eviction::{
+    EvictionPolicy, InboundPeer, RefuseNewPeers, ValidatorAwareEvictionPolicy,
+};
 
 /// Node identity bundling all node-specific information.
 ///
@@ -211,20 +374,62 @@ pub struct Config {
     pub listen_addr: Multiaddr,
     pub persistent_peers: Vec<Multiaddr>,
     pub persistent_peers_only: bool,
+    /// Peers always sent and forwarded full messages in gossipsub, regardless of mesh membership
+    pub explicit_peers: Vec<libp2p::PeerId>,
     pub discovery: DiscoveryConfig,
     pub idle_connection_timeout: Duration,
     pub transport: TransportProtocol,
+    /// Security protocol of the TCP and WebSocket connections
+    pub security: SecurityProtocol,
+    /// Timeouts of the TCP and WebSocket dials, depending on how the peer is reached
+    pub dial_timeouts: DialTimeouts,
+    pub websocket: WebSocketConfig,
+    /// Per-peer inbound rate limits
+    pub bandwidth: BandwidthConfig,
+    /// Closing of the connections to the peers which stopped responding to pings
+    pub liveness: LivenessConfig,
+    pub connection_limits: ConnectionLimitsConfig,
     pub gossipsub: GossipSubConfig,
     pub pubsub_protocol: PubSubProtocol,
     pub channel_names: ChannelNames,
     pub rpc_max_size: usize,
+    /// Maximum size of a sync response split into chunks of at most `rpc_max_size` bytes,
+    /// responses are never chunked if `None`
+    pub rpc_max_chunked_size: Option<usize>,
     pub pubsub_max_size: usize,
+    /// Maximum size of the messages of each protocol, within `pubsub_max_size` and `rpc_max_size`
+    pub max_message_sizes: MaxMessageSizes,
+    /// Channels whose messages are compressed, the size limits applying to the uncompressed messages
+    pub compression: CompressionConfig,
+    /// Gossip of the transactions of the application on the mempool channel
+    pub mempool: MempoolConfig,
+    /// Upgrade of the relayed connections into direct ones (DCUtR hole punching)
+    pub hole_punch: HolePunchConfig,
     pub enable_consensus: bool,
     pub enable_sync: bool,
     pub protocol_names: ProtocolNames,
+    /// Chains served by the network, eg. several shards of a chain, each one with its own pubsub
+    /// topics and sync protocol prefixed with `/<chain_id>`, see [`spawn_chains`].
+    /// A single chain is served without any prefix if empty.
+    pub chain_ids: Vec<String>,
+    /// File the node key is persisted in, which cannot be rotated if `None`, see [`node_key`]
+    pub node_key_file: Option<PathBuf>,
 }
 
 impl Config {
+    /// Number of chains served by the network
+    pub fn num_chains(&self) -> usize {
+        self.chain_ids.len().max(1)
+    }
+
+    /// Prefix of the pubsub topics and protocol names of a chain
+    pub(crate) fn namespace(&self, chain: usize) -> String {
+        self.chain_ids
+            .get(chain)
+            .map(|chain_id| format!("/{chain_id}"))
+            .unwrap_or_default()
+    }
+
     fn apply_to_swarm(&self, cfg: swarm::Config) -> swarm::Config {
         cfg.with_idle_connection_timeout(self.idle_connection_timeout)
     }
@@ -243,14 +448,28 @@ impl Config {
 pub enum TransportProtocol {
     Tcp,
     Quic,
+    /// WebSocket over TCP, along with plain TCP for dialing
+    WebSocket,
+    /// In-memory transport on `/memory/<port>` addresses, only reaching the nodes
+    /// of the same process, eg. for tests
+    Memory,
 }
 
 impl TransportProtocol {
     pub fn from_multiaddr(multiaddr: &Multiaddr) -> Option<TransportProtocol> {
+        // WebSocket addresses also have a TCP component, eg. `/ip4/127.0.0.1/tcp/443/wss`
+        if multiaddr
+            .protocol_stack()
+            .any(|protocol| matches!(protocol, "ws" | "wss"))
+        {
+            return Some(TransportProtocol::WebSocket);
+        }
+
         for protocol in multiaddr.protocol_stack() {
             match protocol {
                 "tcp" => return Some(TransportProtocol::Tcp),
                 "quic" | "quic-v1" => return Some(TransportProtocol::Quic),
+                "memory" => return Some(TransportProtocol::Memory),
                 _ => {}
             }
         }
@@ -284,6 +503,23 @@ pub enum PersistentPeerError {
     InternalError(String),
 }
 
+/// Errors that can occur when connecting to or disconnecting from a peer
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PeerConnectionError {
+    /// The peer to disconnect from is not connected
+    #[error("Peer not connected")]
+    NotConnected,
+    /// The address could not be dialed, eg. because of an unsupported transport
+    #[error("Failed to dial peer: {0}")]
+    DialFailed(String),
+    /// Network is not started
+    #[error("Network not started")]
+    NetworkStopped,
+    /// Internal error
+    #[error("Internal error: {0}")]
+    InternalError(String),
+}
+
 /// sync event details:
 ///
 /// peer1: sync                  peer2: network                    peer2: sync              peer1: network
@@ -306,6 +542,60 @@ pub enum Event {
         peer_id: PeerId,
         proof_bytes: Bytes,
     },
+    /// Progress of the initial discovery process, emitted while it is
+    /// in progress and once when it finishes or times out.
+    BootstrapProgress(BootstrapProgress),
+    /// A message sent directly by a peer, to reply to with `CtrlMsg::ReplyToPeer`
+    PeerMessage {
+        request_id: InboundRequestId,
+        peer_id: PeerId,
+        payload: Bytes,
+    },
+    /// Enough peers now take part in consensus, see [`InsufficientPeers`](Event::InsufficientPeers)
+    SufficientPeers {
+        peers: usize,
+        required: usize,
+    },
+    /// Too few peers now take part in consensus: with GossipSub, fewer than `mesh_n_low`
+    /// peers are in the consensus mesh, or fewer than the other validators if there are less.
+    /// Emitted at the first periodic check of the peers, and then whenever that changes.
+    InsufficientPeers {
+        peers: usize,
+        required: usize,
+    },
+    /// A message received through GossipSub, only forwarded to the other peers once accepted
+    /// with `CtrlMsg::ValidateMessage`. Emitted instead of `ConsensusMessage` and
+    /// `LivenessMessage` if `GossipSubConfig::validate_messages` is set.
+    UnvalidatedMessage(MessageId, Channel, PeerId, Bytes),
+    /// Transactions gossiped by a peer on the mempool channel, within the limits of the channel.
+    /// Never emitted as `UnvalidatedMessage`, since their validation is up to the mempool.
+    MempoolMessage(PeerId, Bytes),
+    /// Attempt to upgrade the relayed connection to a peer into a direct one, or its outcome
+    HolePunch(HolePunchEvent),
+    /// Change of the relay setup of this node, see `DiscoveryConfig::auto_relay`
+    RelayElection(RelayElectionEvent),
+}
+
+/// Outcome of the validation of a message received through GossipSub
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageAcceptance {
+    /// The message is valid, forward it to the other peers
+    Accept,
+    /// The message is invalid, drop it and penalize the peer which sent it
+    Reject,
+    /// Drop the message without penalizing the peer, eg. a vote for a past height
+    Ignore,
+}
+
+#[cfg(feature = "gossipsub")]
+impl From<MessageAcceptance> for gossipsub::MessageAcceptance {
+    fn from(acceptance: MessageAcceptance) -> Self {
+        match acceptance {
+            MessageAcceptance::Accept => Self::Accept,
+            MessageAcceptance::Reject => Self::Reject,
+            MessageAcceptance::Ignore => Self::Ignore,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -315,6 +605,15 @@ pub enum CtrlMsg {
     SyncRequest(PeerId, Bytes, oneshot::Sender<OutboundRequestId>),
     SyncReply(InboundRequestId, Bytes),
     UpdateValidatorSet(Vec<ValidatorInfo>),
+    /// Peer IDs of the current validator set, as provided by the application.
+    /// These peers are prioritized by discovery and protected in gossipsub.
+    UpdateValidatorPeers(Vec<PeerId>),
+    /// Lowest height whose decided value is still served to syncing peers,
+    /// advertised to the peers in the connect request exchange
+    SetRetainedHeight(u64),
+    /// Consensus is done recovering and started its first height,
+    /// until which the known peers are not shared with the peers
+    NodeStarted,
     /// Validator proof verification result. If Valid, public_key should be Some.
     /// The public_key is stored and used to check validator set membership.
     ValidatorProofVerified {
@@ -322,19 +621,93 @@ pub enum CtrlMsg {
         result: validator_proof::ProofVerificationResult,
         public_key: Option<Vec<u8>>,
     },
+    /// Replace the validator proof of this node, signed by its new consensus key once rotated,
+    /// and send it to the connected peers
+    UpdateValidatorProof(Bytes),
     DumpState(oneshot::Sender<NetworkStateDump>),
+    /// Snapshot of the local view of the graph of the network
+    DumpTopology(oneshot::Sender<NetworkTopology>),
+    DiscoveredPeers(oneshot::Sender<Vec<DiscoveredPeer>>),
+    /// List the peers this node is connected to
+    ListPeers(oneshot::Sender<Vec<ConnectedPeer>>),
+    /// Export the peers known to discovery, to seed other nodes
+    ExportAddressBook(oneshot::Sender<AddressBook>),
+    /// Dial the peers of an address book, replying with the number of peers dialed
+    ImportAddressBook(AddressBook, oneshot::Sender<usize>),
+    /// Close all the connections to a peer, which discovery may later dial again
+    DisconnectPeer(PeerId, oneshot::Sender<Result<(), PeerConnectionError>>),
+    /// Dial a peer at the given address, replying once the dial is started
+    DialPeer(Multiaddr, oneshot::Sender<Result<(), PeerConnectionError>>),
     UpdatePersistentPeers(
         PersistentPeersOp,
         oneshot::Sender<Result<(), PersistentPeerError>>,
     ),
+    /// Change the configuration of the running network
+    UpdateConfig(
+        ConfigUpdate,
+        oneshot::Sender<Result<ConfigUpdateOutcome, ConfigUpdateError>>,
+    ),
+    /// Current `PeerId` of the node, and the one it takes once restarted if its key was rotated
+    NodeKeyStatus(oneshot::Sender<NodeKeyStatus>),
+    /// Rotate the node key, announcing the new `PeerId` to the connected persistent peers,
+    /// see [`node_key`]
+    RotateNodeKey(oneshot::Sender<Result<NodeKeyRotation, NodeKeyError>>),
+    /// Load of the relay server run by this node, advertised to the peers
+    SetRelayLoad(Option<RelayLoad>),
+    /// Add a delta to the score of a peer, eg. a negative one for delivering invalid messages
+    ReportPeer(PeerId, f64),
+    /// Send a message directly to a peer, replying with the reply of the peer
+    SendToPeer(
+        PeerId,
+        Bytes,
+        oneshot::Sender<Result<Bytes, PeerMessageError>>,
+    ),
+    /// Reply to a message sent directly by a peer, or drop it without replying if `None`
+    ReplyToPeer(InboundRequestId, Option<Bytes>),
+    /// Disconnect a peer and deny any connection to or from it for the given duration,
+    /// or until unbanned if `None`
+    BanPeer(PeerId, Option<Duration>),
+    /// Lift the ban of a peer
+    UnbanPeer(PeerId),
+    /// Simulate the given conditions on the link to a peer, or stop simulating them if `None`
+    SetLinkConditions(PeerId, Option<LinkConditions>),
+    /// Start listening on an additional address, announced to the peers through Identify
+    AddListenAddr(Multiaddr),
+    /// Stop listening on an address, either the configured one or one added at runtime
+    RemoveListenAddr(Multiaddr),
+    /// Outcome of the validation of a message, see `Event::UnvalidatedMessage`
+    ValidateMessage(MessageId, MessageAcceptance),
     Shutdown,
 }
 
+/// Spawn the network service of a single chain
 pub async fn spawn(
     identity: NetworkIdentity,
     config: Config,
     registry: SharedRegistry,
 ) -> Result<Handle, eyre::Report> {
+    if config.chain_ids.len() > 1 {
+        eyre::bail!("Cannot serve several chains with a single handle, use `spawn_chains` instead");
+    }
+
+    let mut handles = spawn_chains(identity, config, registry).await?;
+    Ok(handles.remove(PRIMARY_CHAIN))
+}
+
+/// Spawn a network service shared by the chains listed in `Config::chain_ids`,
+/// returning one handle per chain, in the same order.
+///
+/// The events of each chain are only sent to its handle, except for the peers connecting
+/// and disconnecting, which are sent to all of them. The network stops once all the chains
+/// are shut down.
+pub async fn spawn_chains(
+    identity: NetworkIdentity,
+    mut config: Config,
+    registry: SharedRegistry,
+) -> Result<Vec<Handle>, eyre::Report> {
+    let namespace = config.namespace(PRIMARY_CHAIN);
+    config.protocol_names = config.protocol_names.with_namespace(&namespace);
+
     let mut swarm =
         registry.with_prefix(METRICS_PREFIX, |registry| -> Result<_, eyre::Report> {
             // Pass the libp2p keypair to the behaviour, it is included in the Identify protocol
@@ -343,21 +716,67 @@ pub async fn spawn(
                 SwarmBuilder::with_existing_identity(identity.keypair.clone()).with_tokio();
             match config.transport {
                 TransportProtocol::Tcp => {
-                    let behaviour = Behaviour::new_with_metrics(&config, &identity, registry)?;
+                    let behaviour = Behaviour::new_with_metrics(
+                        &config,
+                        &identity,
+                        registry,
+                        dummy::Behaviour,
+                    )?;
+                    let tcp = transport::tcp(&identity.keypair, config.security)?;
                     Ok(builder
-                        .with_tcp(
-                            libp2p::tcp::Config::new().nodelay(true), // Disable Nagle's algorithm
-                            libp2p::noise::Config::new,
-                            libp2p::yamux::Config::default,
-                        )?
+                        .with_other_transport(|_| DialTimeout::new(tcp, config.dial_timeouts))?
                         .with_dns()?
                         .with_bandwidth_metrics(registry)
                         .with_behaviour(|_| behaviour)?
                         .with_swarm_config(|cfg| config.apply_to_swarm(cfg))
                         .build())
                 }
+                TransportProtocol::WebSocket => {
+                    let behaviour = Behaviour::new_with_metrics(
+                        &config,
+                        &identity,
+                        registry,
+                        dummy::Behaviour,
+                    )?;
+                    let tcp = transport::tcp(&identity.keypair, config.security)?;
+                    let websocket = websocket::transport(
+                        &identity.keypair,
+                        config.security,
+                        &config.websocket,
+                    )?;
+                    Ok(builder
+                        .with_other_transport(|_| DialTimeout::new(tcp, config.dial_timeouts))?
+                        .with_other_transport(|_| {
+                            DialTimeout::new(websocket, config.dial_timeouts)
+                        })?
+                        .with_dns()?
+                        .with_bandwidth_metrics(registry)
+                        .with_behaviour(|_| behaviour)?
+                        .with_swarm_config(|cfg| config.apply_to_swarm(cfg))
+                        .build())
+                }
+                TransportProtocol::Memory => {
+                    let behaviour = Behaviour::new_with_metrics(
+                        &config,
+                        &identity,
+                        registry,
+                        dummy::Behaviour,
+                    )?;
+                    let memory = transport::memory(&identity.keypair, config.security)?;
+                    Ok(builder
+                        .with_other_transport(|_| memory)?
+                        .with_bandwidth_metrics(registry)
+                        .with_behaviour(|_| behaviour)?
+                        .with_swarm_config(|cfg| config.apply_to_swarm(cfg))
+                        .build())
+                }
                 TransportProtocol::Quic => {
-                    let behaviour = Behaviour::new_with_metrics(&config, &identity, registry)?;
+                    let behaviour = Behaviour::new_with_metrics(
+                        &config,
+                        &identity,
+                        registry,
+                        dummy::Behaviour,
+                    )?;
                     Ok(builder
                         .with_quic_config(|cfg| config.apply_to_quic(cfg))
                         .with_dns()?
@@ -371,22 +790,48 @@ pub async fn spawn(
 
     let metrics = registry.with_prefix(METRICS_PREFIX, Metrics::new);
 
-    let (tx_event, rx_event) = mpsc::channel(32);
+    let (tx_events, rx_events): (Vec<_>, Vec<_>) =
+        (0..config.num_chains()).map(|_| mpsc::channel(32)).unzip();
     let (tx_ctrl, rx_ctrl) = mpsc::channel(32);
 
     let discovery = registry.with_prefix(DISCOVERY_METRICS_PREFIX, |reg| {
-        discovery::Discovery::new(config.discovery, config.persistent_peers.clone(), reg)
+        discovery::Discovery::new(
+            config.discovery.clone(),
+            config.persistent_peers.clone(),
+            reg,
+        )
     });
 
     let network_metrics = registry.with_prefix(METRICS_PREFIX, NetworkMetrics::new);
+    let bandwidth = registry.with_prefix(METRICS_PREFIX, |reg| {
+        BandwidthMeter::new(config.bandwidth, reg)
+    });
+    let duplicates = registry.with_prefix(METRICS_PREFIX, |reg| {
+        DuplicateTracker::new(config.gossipsub.duplicate_cache_time, reg)
+    });
 
     let peer_id = PeerId::from_libp2p(swarm.local_peer_id());
 
     // Create local node info with subscribed consensus topics
     let mut subscribed_topics = std::collections::HashSet::new();
     if config.enable_consensus {
-        for channel in Channel::consensus() {
-            subscribed_topics.insert(channel.as_str(config.channel_names).to_string());
+        for chain in 0..config.num_chains() {
+            for channel in Channel::consensus() {
+                subscribed_topics.insert(format!(
+                    "{}{}",
+                    config.namespace(chain),
+                    channel.as_str(config.channel_names)
+                ));
+            }
+        }
+    }
+    if config.mempool.enabled {
+        for chain in 0..config.num_chains() {
+            subscribed_topics.insert(format!(
+                "{}{}",
+                config.namespace(chain),
+                Channel::Mempool.as_str(config.channel_names)
+            ));
         }
     }
 
@@ -406,6 +851,14 @@ pub async fn spawn(
         }
     }
 
+    // Add the configured explicit peers to gossipsub, which dials them if not connected
+    #[cfg(feature = "gossipsub")]
+    if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+        for peer_id in &config.explicit_peers {
+            gossipsub.add_explicit_peer(peer_id);
+        }
+    }
+
     // Create local node info
     let local_node_info = LocalNodeInfo {
         moniker,
@@ -426,56 +879,131 @@ pub async fn spawn(
         config.persistent_peers.clone(),
         local_node_info,
         network_metrics,
+        bandwidth,
+        duplicates,
+        MempoolLimiter::new(config.mempool),
     );
 
     let span = error_span!("network");
 
     info!(parent: span.clone(), %peer_id, "Starting network service");
 
-    let task_handle =
-        tokio::task::spawn(run(config, metrics, state, swarm, rx_ctrl, tx_event).instrument(span));
+    let events = EventSenders::new(tx_events);
+    let mut task_handle = Some(tokio::task::spawn(
+        run(config, metrics, state, swarm, rx_ctrl, events).instrument(span),
+    ));
 
-    Ok(Handle::new(peer_id, tx_ctrl, rx_event, task_handle))
+    let handles = rx_events
+        .into_iter()
+        .enumerate()
+        .map(|(chain, rx_event)| {
+            Handle::new(
+                peer_id,
+                chain,
+                tx_ctrl.clone(),
+                rx_event,
+                task_handle.take(),
+            )
+        })
+        .collect();
+
+    Ok(handles)
 }
 
 async fn run(
     config: Config,
     metrics: Metrics,
     mut state: State,
-    mut swarm: swarm::Swarm<Behaviour>,
-    mut rx_ctrl: mpsc::Receiver<CtrlMsg>,
-    tx_event: mpsc::Sender<Event>,
+    mut swarm: swarm::Swarm<DefaultBehaviour>,
+    mut rx_ctrl: mpsc::Receiver<(usize, CtrlMsg)>,
+    mut events: EventSenders,
 ) {
     // The validator proof is already set on the behaviour before run() is called
     // (see set_proof above), so it will be sent on every ConnectionEstablished.
 
-    if let Err(e) = swarm.listen_on(config.listen_addr.clone()) {
-        error!("Error listening on {}: {e}", config.listen_addr);
-        return;
+    match swarm.listen_on(config.listen_addr.clone()) {
+        Ok(listener_id) => {
+            state
+                .listeners
+                .insert(listener_id, config.listen_addr.clone());
+        }
+        Err(e) => {
+            error!("Error listening on {}: {e}", config.listen_addr);
+            return;
+        }
     }
 
-    if config.enable_consensus {
-        if let Err(e) = pubsub::subscribe(
-            &mut swarm,
-            config.pubsub_protocol,
-            Channel::consensus(),
-            config.channel_names,
-        ) {
-            error!("Error subscribing to consensus channels: {e}");
-            return;
-        };
+    // Advertised through Identify and discovery along with the listen address
+    for addr in &config.websocket.advertised_addrs {
+        info!(%addr, "Advertising external address");
+        swarm.add_external_address(addr.clone());
     }
 
-    if config.enable_sync {
-        if let Err(e) = pubsub::subscribe(
-            &mut swarm,
-            PubSubProtocol::Broadcast,
-            &[Channel::Sync],
-            config.channel_names,
-        ) {
-            error!("Error subscribing to Sync channel: {e}");
-            return;
-        };
+    for chain in 0..config.num_chains() {
+        let namespace = config.namespace(chain);
+
+        if config.enable_consensus {
+            if let Err(e) = pubsub::subscribe(
+                &mut swarm,
+                config.pubsub_protocol,
+                Channel::consensus(),
+                &namespace,
+                config.channel_names,
+                config.compression,
+            ) {
+                error!("Error subscribing to consensus channels: {e}");
+                return;
+            };
+        }
+
+        if config.enable_sync {
+            if let Err(e) = pubsub::subscribe(
+                &mut swarm,
+                PubSubProtocol::Broadcast,
+                &[Channel::Sync],
+                &namespace,
+                config.channel_names,
+                config.compression,
+            ) {
+                error!("Error subscribing to Sync channel: {e}");
+                return;
+            };
+        }
+
+        if config.mempool.enabled {
+            if let Err(e) = pubsub::subscribe(
+                &mut swarm,
+                config.pubsub_protocol,
+                &[Channel::Mempool],
+                &namespace,
+                config.channel_names,
+                config.compression,
+            ) {
+                error!("Error subscribing to mempool channel: {e}");
+                return;
+            };
+        }
+    }
+
+    advertise_local_role(&mut swarm, &mut state, &config);
+
+    // Do not share the known peers until consensus is done recovering, see `CtrlMsg::NodeStarted`
+    state.discovery.set_starting(true);
+
+    // Let the peers on other chains know that we follow another chain in the connect requests
+    state
+        .discovery
+        .set_chain_id(config.chain_ids.get(PRIMARY_CHAIN).cloned());
+
+    // Seed discovery with the peers of the address book exported by another node, if any
+    if let Some(path) = &config.discovery.address_book {
+        match AddressBook::load(path) {
+            Ok(book) => {
+                let dialed = state.discovery.import_address_book(&swarm, book);
+                info!(path = %path.display(), "Imported {dialed} peers from the address book");
+            }
+            Err(e) => warn!(path = %path.display(), "Failed to import the address book: {e}"),
+        }
     }
 
     // Timer to perform periodic network operations (peer reconnection, metrics updates, etc.)
@@ -487,7 +1015,7 @@ async fn run(
     loop {
         let result = tokio::select! {
             event = swarm.select_next_some() => {
-                handle_swarm_event(event, &config, &metrics, &mut swarm, &mut state, &tx_event).await
+                handle_swarm_event(event, &config, &metrics, &mut swarm, &mut state, &events).await
             }
 
             Some(connection_data) = state.discovery.controller.dial.recv(), if state.discovery.can_dial() => {
@@ -505,28 +1033,102 @@ async fn run(
                 ControlFlow::Continue(())
             }
 
-            Some((peer_id, connection_id)) = state.discovery.controller.close.recv(), if state.discovery.can_close() => {
-                state.discovery.close_connection(&mut swarm, peer_id, connection_id);
+            Some((peer_id, connection_id, reason)) = state.discovery.controller.close.recv(), if state.discovery.can_close() => {
+                state.discovery.close_connection(&mut swarm, peer_id, connection_id, reason);
                 ControlFlow::Continue(())
             }
 
-            Some(ctrl) = rx_ctrl.recv() => {
-                handle_ctrl_msg(&mut swarm, &mut state, &config, ctrl).await
+            Some((chain, ctrl)) = rx_ctrl.recv() => {
+                handle_ctrl_msg(&mut swarm, &mut state, &config, &mut events, chain, ctrl).await
             }
 
             _ = periodic_timer.tick() => {
+                // Re-create the listener if all of its addresses went away
+                if state.addr_monitor.needs_rebind() {
+                    rebind_listener(&mut swarm, &mut state, &config);
+                }
+
                 // Attempt to dial bootstrap nodes
                 state.discovery.dial_bootstrap_nodes(&swarm);
 
+                // Re-dial lost persistent peers
+                state.discovery.dial_persistent_peers(&swarm);
+
+                // Give up on the initial discovery if it is taking too long
+                state.discovery.check_bootstrap_timeout(&mut swarm);
+
+                // Move relayed peers to less loaded relays
+                #[cfg(feature = "relay")]
+                state.discovery.rebalance_relays(&mut swarm);
+
+                // Run a relay server if publicly reachable, or pick the relays to listen through
+                #[cfg(feature = "relay")]
+                for event in state.discovery.elect_relays(&mut swarm) {
+                    if let Err(e) = events.send_all(Event::RelayElection(event)).await {
+                        error!("Error sending relay election event to handle: {e}");
+                        return;
+                    }
+                }
+
+                // Forgive the peers reported by the application over time
+                let decay = config.gossipsub.peer_score.reported_score_decay;
+                for (peer_id, score) in state.decay_reported_scores(decay) {
+                    set_peer_score(&mut swarm, peer_id, score);
+                }
+
+                // GossipSub has dropped the messages whose validation is still pending by now
+                state.expire_pending_validations();
+
+                // Lift the bans which expired
+                for peer_id in state.expire_bans() {
+                    info!(%peer_id, "Ban expired, unbanning peer");
+                    swarm.behaviour_mut().banned_peers.unblock_peer(peer_id);
+                }
+
+                // Disconnect the peers persistently exceeding the inbound rate limit
+                for peer_id in state.bandwidth.check_rate_limits() {
+                    warn!(%peer_id, "Peer exceeded the inbound rate limit, disconnecting");
+                    let _ = swarm.disconnect_peer_id(peer_id);
+                }
+
+                if let Some(progress) = state.discovery.bootstrap_progress() {
+                    match progress.phase {
+                        BootstrapPhase::TimedOut => warn!("Bootstrap progress: {progress}"),
+                        _ => info!("Bootstrap progress: {progress}"),
+                    }
+
+                    if let Err(e) = events.send_all(Event::BootstrapProgress(progress)).await {
+                        error!("Error sending bootstrap progress event to handle: {e}");
+                        return;
+                    }
+                }
+
                 // Update peer info in State and metrics (includes gossipsub scores and mesh membership)
+                #[cfg(feature = "gossipsub")]
                 if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
                     state.update_peer_info(
                         gossipsub,
                         Channel::consensus(),
+                        &config.namespace(PRIMARY_CHAIN),
                         config.channel_names,
+                        config.compression,
                     );
                 }
 
+                if let Some(event) = state.check_sufficient_peers(&config) {
+                    match &event {
+                        Event::SufficientPeers { peers, required } => {
+                            info!(%peers, %required, "Enough peers take part in consensus")
+                        }
+                        _ => warn!("Too few peers take part in consensus: {event:?}"),
+                    }
+
+                    if let Err(e) = events.send_all(event).await {
+                        error!("Error sending peer sufficiency event to handle: {e}");
+                        return;
+                    }
+                }
+
                 periodic_tick_count = periodic_tick_count.wrapping_add(1);
                 if periodic_tick_count.is_multiple_of(5) {
                     info!("Network peer state\n{}", state.format_peer_info());
@@ -544,19 +1146,41 @@ async fn run(
 }
 
 async fn handle_ctrl_msg(
-    swarm: &mut swarm::Swarm<Behaviour>,
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
     state: &mut State,
     config: &Config,
+    events: &mut EventSenders,
+    chain: usize,
     msg: CtrlMsg,
 ) -> ControlFlow<()> {
     match msg {
         CtrlMsg::Publish(channel, data) => {
             let msg_size = data.len();
+
+            if channel == Channel::Mempool {
+                if !config.mempool.enabled {
+                    error!("Cannot publish transactions: mempool not enabled");
+                    return ControlFlow::Continue(());
+                }
+
+                if msg_size > config.mempool.max_msg_size {
+                    error!(
+                        size = %msg_size,
+                        max_size = %config.mempool.max_msg_size,
+                        "Cannot publish transactions: message too large"
+                    );
+                    return ControlFlow::Continue(());
+                }
+            }
+
             let result = pubsub::publish(
                 swarm,
                 config.pubsub_protocol,
                 channel,
+                &config.namespace(chain),
                 config.channel_names,
+                config.compression,
+                config.pubsub_max_size,
                 data,
             );
 
@@ -579,7 +1203,10 @@ async fn handle_ctrl_msg(
                 swarm,
                 PubSubProtocol::Broadcast,
                 channel,
+                &config.namespace(chain),
                 config.channel_names,
+                config.compression,
+                config.pubsub_max_size,
                 data,
             );
 
@@ -591,14 +1218,28 @@ async fn handle_ctrl_msg(
             ControlFlow::Continue(())
         }
 
+        #[cfg(feature = "sync")]
         CtrlMsg::SyncRequest(peer_id, request, reply_to) => {
-            let Some(sync) = swarm.behaviour_mut().sync.as_mut() else {
+            let Some(sync) = swarm
+                .behaviour_mut()
+                .sync
+                .as_mut()
+                .and_then(|sync| sync.get_mut(chain))
+            else {
                 error!("Cannot request Sync from peer: Sync not enabled");
                 return ControlFlow::Continue(());
             };
 
+            let request_len = request.len();
             let request_id = sync.send_request(peer_id.to_libp2p(), request);
 
+            state.bandwidth.record(
+                &peer_id.to_libp2p(),
+                bandwidth::Protocol::Sync,
+                Direction::Outbound,
+                request_len,
+            );
+
             if let Err(e) = reply_to.send(request_id) {
                 error!(%peer_id, "Error sending Sync request: {e}");
             }
@@ -606,17 +1247,30 @@ async fn handle_ctrl_msg(
             ControlFlow::Continue(())
         }
 
+        #[cfg(feature = "sync")]
         CtrlMsg::SyncReply(request_id, data) => {
-            let Some(sync) = swarm.behaviour_mut().sync.as_mut() else {
+            let Some(sync) = swarm
+                .behaviour_mut()
+                .sync
+                .as_mut()
+                .and_then(|sync| sync.get_mut(chain))
+            else {
                 error!("Cannot send Sync response to peer: Sync not enabled");
                 return ControlFlow::Continue(());
             };
 
-            let Some(channel) = state.sync_channels.remove(&request_id) else {
+            let Some((peer_id, channel)) = state.sync_channels.remove(&(chain, request_id)) else {
                 error!(%request_id, "Received Sync reply for unknown request ID");
                 return ControlFlow::Continue(());
             };
 
+            state.bandwidth.record(
+                &peer_id,
+                bandwidth::Protocol::Sync,
+                Direction::Outbound,
+                data.len(),
+            );
+
             let result = sync.send_response(channel, data);
 
             match result {
@@ -627,16 +1281,95 @@ async fn handle_ctrl_msg(
             ControlFlow::Continue(())
         }
 
+        #[cfg(not(feature = "sync"))]
+        CtrlMsg::SyncRequest(..) | CtrlMsg::SyncReply(..) => {
+            error!("Cannot use Sync: not supported by this build, enable the `sync` feature");
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::UpdateValidatorSet(_) if chain != PRIMARY_CHAIN => {
+            trace!(%chain, "Ignoring validator set update: only the first chain scores the peers");
+            ControlFlow::Continue(())
+        }
+
         CtrlMsg::UpdateValidatorSet(validators) => {
             // Process the validator set update and get peers that need score updates
             let validator_set = validators.into_iter().collect();
             let changed_peers = state.process_validator_set_update(validator_set);
 
-            // Update GossipSub scores for peers whose type changed
+            // Update GossipSub scores and explicit peering for peers whose type changed
+            for (peer_id, new_score) in changed_peers {
+                set_peer_score(swarm, peer_id, new_score + state.reported_score(&peer_id));
+
+                #[cfg(feature = "gossipsub")]
+                update_explicit_peer_in_gossipsub(swarm, state, config, peer_id);
+            }
+
+            advertise_local_role(swarm, state, config);
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::SetRetainedHeight(_) if chain != PRIMARY_CHAIN => {
+            trace!(%chain, "Ignoring retained height: only the first chain is advertised to the peers");
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::SetRetainedHeight(height) => {
+            state.discovery.set_retained_height(Some(height));
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::NodeStarted if chain != PRIMARY_CHAIN => {
+            trace!(%chain, "Ignoring node start: only the first chain gates the peers responses");
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::NodeStarted => {
+            debug!("Node started, sharing the known peers");
+            state.discovery.set_starting(false);
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::UpdateValidatorPeers(_) if chain != PRIMARY_CHAIN => {
+            trace!(%chain, "Ignoring validator peers update: only the first chain scores the peers");
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::UpdateValidatorPeers(peers) => {
+            let peers = peers.iter().map(|peer_id| peer_id.to_libp2p()).collect();
+            let changed_peers = state.process_validator_peers_update(peers);
+
+            // Update GossipSub scores and explicit peering for peers whose type changed
             for (peer_id, new_score) in changed_peers {
-                set_peer_score(swarm, peer_id, new_score);
+                set_peer_score(swarm, peer_id, new_score + state.reported_score(&peer_id));
+
+                #[cfg(feature = "gossipsub")]
+                update_explicit_peer_in_gossipsub(swarm, state, config, peer_id);
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::UpdateValidatorProof(_) if chain != PRIMARY_CHAIN => {
+            trace!(%chain, "Ignoring validator proof update: only the first chain sends the proof");
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::UpdateValidatorProof(proof_bytes) => {
+            let peers: Vec<_> = swarm.connected_peers().copied().collect();
+
+            if let Some(vp) = swarm.behaviour_mut().validator_proof.as_mut() {
+                info!(
+                    peers = peers.len(),
+                    "Sending the new validator proof to the connected peers"
+                );
+                vp.update_proof(proof_bytes.clone(), peers);
             }
 
+            state.local_node.proof_bytes = Some(proof_bytes);
+            state.metrics.set_local_node_info(&state.local_node);
+
             ControlFlow::Continue(())
         }
 
@@ -657,7 +1390,11 @@ async fn handle_ctrl_msg(
             // If signature is valid, store the proof and check validator set membership
             if let Some(public_key) = public_key {
                 if let Some(new_score) = state.record_verified_proof(&libp2p_peer_id, public_key) {
+                    let new_score = new_score + state.reported_score(&libp2p_peer_id);
                     set_peer_score(swarm, libp2p_peer_id, new_score);
+
+                    #[cfg(feature = "gossipsub")]
+                    update_explicit_peer_in_gossipsub(swarm, state, config, libp2p_peer_id);
                 }
             }
 
@@ -682,6 +1419,7 @@ async fn handle_ctrl_msg(
                     .sorted_unstable()
                     .collect(),
                 persistent_peer_addrs: state.persistent_peer_addrs.clone(),
+                mesh: state.mesh_peers_by_topic(),
             };
 
             if let Err(_s) = reply_to.send(snapshot) {
@@ -691,81 +1429,681 @@ async fn handle_ctrl_msg(
             ControlFlow::Continue(())
         }
 
-        CtrlMsg::UpdatePersistentPeers(op, reply_to) => {
-            let result = match op {
-                PersistentPeersOp::Add(addr) => state.add_persistent_peer(addr, swarm),
-                PersistentPeersOp::Remove(addr) => state.remove_persistent_peer(addr, swarm),
+        CtrlMsg::DumpTopology(reply_to) => {
+            let topology = NetworkTopology {
+                peer_id: state.local_node.peer_id,
+                moniker: state.local_node.moniker.clone(),
+                mesh: state.mesh_peers_by_topic(),
+                discovery: state.discovery.topology(swarm),
             };
-            if reply_to.send(result).is_err() {
-                error!("Error replying to UpdatePersistentPeers");
+
+            if reply_to.send(topology).is_err() {
+                error!("Error replying to DumpTopology");
             }
+
             ControlFlow::Continue(())
         }
 
-        CtrlMsg::Shutdown => ControlFlow::Break(()),
-    }
-}
+        CtrlMsg::DiscoveredPeers(reply_to) => {
+            if reply_to.send(state.discovery.discovered_peers()).is_err() {
+                error!("Error replying to DiscoveredPeers");
+            }
 
-/// Set a default low score for a peer immediately upon connection
-/// This allows gossipsub to form an initial mesh before Identify completes
-fn set_default_peer_score(swarm: &mut swarm::Swarm<Behaviour>, peer_id: libp2p::PeerId) {
-    if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
-        let score = peer_scoring::get_default_score();
-        gossipsub.set_application_score(&peer_id, score);
-        trace!("Set default application score {score} for peer {peer_id} before Identify");
-    }
-}
+            ControlFlow::Continue(())
+        }
 
-fn set_peer_score(swarm: &mut swarm::Swarm<Behaviour>, peer_id: libp2p::PeerId, score: f64) {
-    // Set application-specific score in gossipsub if enabled
-    if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
-        if gossipsub.set_application_score(&peer_id, score) {
-            debug!("Upgraded application score to {score} for peer {peer_id}");
+        CtrlMsg::ListPeers(reply_to) => {
+            #[allow(unused_mut)]
+            let mut peers = state.connected_peers();
+
+            #[cfg(feature = "gossipsub")]
+            if let Some(gossipsub) = swarm.behaviour().gossipsub.as_ref() {
+                for peer in &mut peers {
+                    peer.gossipsub_score = gossipsub.peer_score(&peer.peer_id);
+                }
+            }
+
+            if reply_to.send(peers).is_err() {
+                error!("Error replying to ListPeers");
+            }
+
+            ControlFlow::Continue(())
         }
-    }
-}
 
-/// Add a persistent peer as an explicit peer in gossipsub (if explicit peering is enabled).
-/// A node always sends and forwards messages to its explicit peers, regardless of mesh membership.
-fn add_explicit_peer_to_gossipsub(
-    swarm: &mut swarm::Swarm<Behaviour>,
-    state: &mut State,
-    peer_id: libp2p::PeerId,
-) {
-    let Some(peer_info) = state.peer_info.get_mut(&peer_id) else {
-        return;
-    };
+        CtrlMsg::ExportAddressBook(reply_to) => {
+            if reply_to.send(state.discovery.address_book()).is_err() {
+                error!("Error replying to ExportAddressBook");
+            }
 
-    if peer_info.peer_type.is_persistent() {
-        if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
-            gossipsub.add_explicit_peer(&peer_id);
-            state
-                .metrics
-                .record_explicit_peer(&peer_id, &peer_info.moniker);
-            peer_info.is_explicit = true;
-            info!("Added persistent peer {peer_id} as explicit peer in gossipsub");
+            ControlFlow::Continue(())
         }
-    }
-}
 
-/// Remove a persistent peer from explicit peers in gossipsub and mark the metric stale.
-fn remove_explicit_peer_from_gossipsub(
-    swarm: &mut swarm::Swarm<Behaviour>,
-    state: &mut State,
-    peer_id: &libp2p::PeerId,
-) {
-    let Some(peer_info) = state.peer_info.get_mut(peer_id) else {
-        return;
-    };
+        CtrlMsg::ImportAddressBook(book, reply_to) => {
+            let dialed = state.discovery.import_address_book(swarm, book);
 
-    if peer_info.peer_type.is_persistent() {
+            if reply_to.send(dialed).is_err() {
+                error!("Error replying to ImportAddressBook");
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::DisconnectPeer(peer_id, reply_to) => {
+            let peer_id = peer_id.to_libp2p();
+
+            let result = swarm
+                .disconnect_peer_id(peer_id)
+                .map_err(|()| PeerConnectionError::NotConnected);
+
+            match &result {
+                Ok(()) => info!(%peer_id, "Disconnecting peer"),
+                Err(error) => warn!(%peer_id, %error, "Cannot disconnect peer"),
+            }
+
+            if reply_to.send(result).is_err() {
+                error!("Error replying to DisconnectPeer");
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::DialPeer(addr, reply_to) => {
+            let result = swarm
+                .dial(addr.clone())
+                .map_err(|e| PeerConnectionError::DialFailed(e.to_string()));
+
+            match &result {
+                Ok(()) => info!(%addr, "Dialing peer"),
+                Err(error) => warn!(%addr, %error, "Cannot dial peer"),
+            }
+
+            if reply_to.send(result).is_err() {
+                error!("Error replying to DialPeer");
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::UpdatePersistentPeers(op, reply_to) => {
+            let result = match op {
+                PersistentPeersOp::Add(addr) => state.add_persistent_peer(addr, swarm),
+                PersistentPeersOp::Remove(addr) => state.remove_persistent_peer(addr, swarm),
+            };
+            if reply_to.send(result).is_err() {
+                error!("Error replying to UpdatePersistentPeers");
+            }
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::UpdateConfig(update, reply_to) => {
+            let result = config_update::apply(swarm, state, update);
+
+            match &result {
+                Ok(outcome) => info!(
+                    applied = ?outcome.applied,
+                    requires_restart = ?outcome.requires_restart,
+                    "Updated network configuration"
+                ),
+                Err(error) => warn!(%error, "Rejected network configuration update"),
+            }
+
+            if reply_to.send(result).is_err() {
+                error!("Error replying to UpdateConfig");
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::NodeKeyStatus(reply_to) => {
+            let status = NodeKeyStatus {
+                peer_id: state.local_node.peer_id,
+                next_peer_id: state.next_peer_id,
+            };
+
+            if reply_to.send(status).is_err() {
+                error!("Error replying to NodeKeyStatus");
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::RotateNodeKey(reply_to) => {
+            let result = rotate_node_key(swarm, state, config);
+
+            match &result {
+                Ok(rotation) => info!(
+                    peer_id = %rotation.peer_id,
+                    next_peer_id = %rotation.next_peer_id,
+                    announced_to = rotation.announced_to.len(),
+                    "Rotated the node key, taking effect once the node is restarted"
+                ),
+                Err(error) => error!(%error, "Failed to rotate the node key"),
+            }
+
+            if reply_to.send(result).is_err() {
+                error!("Error replying to RotateNodeKey");
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::SetRelayLoad(load) => {
+            state.discovery.set_relay_load(load);
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::ValidateMessage(message_id, acceptance) => {
+            report_message_validation(swarm, state, &message_id, acceptance);
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::ReportPeer(peer_id, score_delta) => {
+            let peer_id = peer_id.to_libp2p();
+            debug!(%peer_id, %score_delta, "Peer reported");
+
+            let score = state.report_peer(peer_id, score_delta);
+            set_peer_score(swarm, peer_id, score);
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::BanPeer(peer_id, duration) => {
+            let peer_id = peer_id.to_libp2p();
+            warn!(%peer_id, ?duration, "Banning peer");
+
+            // Closes the connections to the peer and denies the new ones
+            swarm.behaviour_mut().banned_peers.block_peer(peer_id);
+            state.ban_peer(peer_id, duration);
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::UnbanPeer(peer_id) => {
+            let peer_id = peer_id.to_libp2p();
+
+            if state.unban_peer(&peer_id) {
+                info!(%peer_id, "Unbanning peer");
+                swarm.behaviour_mut().banned_peers.unblock_peer(peer_id);
+            } else {
+                debug!(%peer_id, "Ignoring unban of a peer which is not banned");
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::SetLinkConditions(peer_id, conditions) => {
+            let peer_id = peer_id.to_libp2p();
+
+            match conditions {
+                Some(conditions) => {
+                    info!(%peer_id, ?conditions, "Simulating conditions on the link to peer");
+                    state.link_conditions.insert(peer_id, conditions);
+                }
+                None => {
+                    info!(%peer_id, "No longer simulating conditions on the link to peer");
+                    state.link_conditions.remove(&peer_id);
+                }
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::AddListenAddr(addr) => {
+            if state
+                .listeners
+                .values()
+                .any(|listen_addr| listen_addr == &addr)
+            {
+                debug!(%addr, "Ignoring listen address which is already in use");
+                return ControlFlow::Continue(());
+            }
+
+            info!(%addr, "Adding listen address");
+
+            match swarm.listen_on(addr.clone()) {
+                Ok(listener_id) => {
+                    state.listeners.insert(listener_id, addr);
+                }
+                Err(e) => error!("Error listening on {addr}: {e}"),
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::RemoveListenAddr(addr) => {
+            let listener_ids: Vec<_> = state
+                .listeners
+                .iter()
+                .filter(|(_, listen_addr)| **listen_addr == addr)
+                .map(|(listener_id, _)| *listener_id)
+                .collect();
+
+            if listener_ids.is_empty() {
+                warn!(%addr, "Cannot remove listen address which is not in use");
+                return ControlFlow::Continue(());
+            }
+
+            info!(%addr, "Removing listen address");
+
+            for listener_id in listener_ids {
+                state.listeners.remove(&listener_id);
+                swarm.remove_listener(listener_id);
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::SendToPeer(peer_id, payload, reply_to) => {
+            let Some(behaviour) = swarm.behaviour_mut().peer_message.get_mut(chain) else {
+                let _ = reply_to.send(Err(PeerMessageError::NetworkStopped));
+                return ControlFlow::Continue(());
+            };
+
+            let peer_id = peer_id.to_libp2p();
+            let payload_len = payload.len();
+            let request_id = behaviour.send_request(&peer_id, payload);
+
+            state.bandwidth.record(
+                &peer_id,
+                bandwidth::Protocol::PeerMessage,
+                Direction::Outbound,
+                payload_len,
+            );
+
+            state
+                .peer_message_replies
+                .insert((chain, request_id), reply_to);
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::ReplyToPeer(request_id, payload) => {
+            let Some((peer_id, channel)) = state.peer_message_channels.remove(&(chain, request_id))
+            else {
+                error!(%request_id, "Received reply for unknown peer message");
+                return ControlFlow::Continue(());
+            };
+
+            // Dropping the channel lets the peer know right away that no reply is coming
+            let Some(payload) = payload else {
+                debug!(%request_id, %peer_id, "Dropped peer message without replying");
+                return ControlFlow::Continue(());
+            };
+
+            state.bandwidth.record(
+                &peer_id,
+                bandwidth::Protocol::PeerMessage,
+                Direction::Outbound,
+                payload.len(),
+            );
+
+            let Some(behaviour) = swarm.behaviour_mut().peer_message.get_mut(chain) else {
+                return ControlFlow::Continue(());
+            };
+
+            if behaviour.send_response(channel, payload).is_err() {
+                error!(%request_id, %peer_id, "Error replying to peer message");
+            }
+
+            ControlFlow::Continue(())
+        }
+
+        CtrlMsg::Shutdown => {
+            if events.shutdown(chain) {
+                close_connections(swarm, state).await;
+                ControlFlow::Break(())
+            } else {
+                debug!(%chain, "Chain shut down, still serving the other chains");
+                ControlFlow::Continue(())
+            }
+        }
+    }
+}
+
+/// Tell all peers that the node is shutting down, then close the connections to them
+/// and wait for them to be closed, so that the peers see the node leave instead of their
+/// connections timing out
+async fn close_connections(swarm: &mut swarm::Swarm<DefaultBehaviour>, state: &mut State) {
+    let count = swarm.connected_peers().count();
+    info!(%count, "Closing connections to peers");
+
+    // Without discovery, the connections are closed right away
+    state.discovery.disconnect_all_on_shutdown(swarm);
+
+    let acknowledged = tokio::time::timeout(SHUTDOWN_DISCONNECT_TIMEOUT, async {
+        while state.discovery.has_pending_disconnects() {
+            let event = swarm.select_next_some().await;
+
+            // Let discovery close the connections to the peers which acknowledged the request
+            #[cfg(feature = "discovery")]
+            if let SwarmEvent::Behaviour(NetworkEvent::Discovery(event)) = event {
+                state.discovery.on_network_event(swarm, *event);
+            }
+
+            #[cfg(not(feature = "discovery"))]
+            let _ = event;
+        }
+    })
+    .await;
+
+    if acknowledged.is_err() {
+        warn!("Timed out waiting for the peers to acknowledge the shutdown");
+    }
+
+    let peers = swarm.connected_peers().copied().collect::<Vec<_>>();
+
+    for peer_id in peers {
+        let _ = swarm.disconnect_peer_id(peer_id);
+    }
+
+    let closed = tokio::time::timeout(CLOSE_CONNECTIONS_TIMEOUT, async {
+        while swarm.connected_peers().next().is_some() {
+            swarm.select_next_some().await;
+        }
+    })
+    .await;
+
+    if closed.is_err() {
+        warn!("Timed out waiting for the connections to peers to be closed");
+    }
+}
+
+/// Re-create the listener once all of its addresses went away, on the wildcard address
+/// with the configured port if the configured IP address is not the wildcard one,
+/// as that address most likely went away with the network interface it belonged to
+fn rebind_listener(swarm: &mut swarm::Swarm<DefaultBehaviour>, state: &mut State, config: &Config) {
+    let listen_addr = addr_monitor::rebind_addr(&config.listen_addr);
+
+    info!(address = %listen_addr, "Re-creating listener");
+
+    match swarm.listen_on(listen_addr.clone()) {
+        Ok(listener_id) => {
+            state.listeners.insert(listener_id, listen_addr);
+            state.addr_monitor.rebind_done();
+        }
+        Err(e) => error!("Error listening on {listen_addr}: {e}"),
+    }
+}
+
+/// Announce our new external address after our IP changed.
+///
+/// Replaces the stale external address and pushes an Identify update to the connected
+/// persistent peers, instead of waiting for them to notice through dial failures.
+/// Other peers get the new address on their next Identify exchange.
+fn handle_external_ip_change(
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &State,
+    old_ip: std::net::IpAddr,
+    new_addr: Multiaddr,
+) {
+    warn!(%old_ip, %new_addr, "External IP address changed");
+
+    let stale_addrs: Vec<_> = swarm
+        .external_addresses()
+        .filter(|addr| discovery::addr_filter::ip_of(addr) == Some(old_ip))
+        .cloned()
+        .collect();
+
+    for addr in stale_addrs {
+        swarm.remove_external_address(&addr);
+    }
+
+    swarm.add_external_address(new_addr);
+
+    let persistent_peers: Vec<_> = state
+        .persistent_peer_ids
+        .iter()
+        .filter(|peer_id| swarm.is_connected(peer_id))
+        .copied()
+        .collect();
+
+    if !persistent_peers.is_empty() {
+        info!(
+            count = persistent_peers.len(),
+            "Pushing address update to persistent peers"
+        );
+
+        swarm.behaviour_mut().identify.push(persistent_peers);
+    }
+}
+
+/// Replace the node key in the node key file with a new one, see [`node_key`], and announce
+/// its `PeerId` to the connected persistent peers, so that they dial the node with it once
+/// the node is restarted
+fn rotate_node_key(
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+    config: &Config,
+) -> Result<NodeKeyRotation, NodeKeyError> {
+    let path = config
+        .node_key_file
+        .as_deref()
+        .ok_or(NodeKeyError::NotConfigured)?;
+
+    let peer_id = state.local_node.peer_id;
+    let next = node_key::rotate(path, &peer_id)?;
+    let next_peer_id = next.public().to_peer_id();
+    state.next_peer_id = Some(next_peer_id);
+
+    let announcement = node_key::announcement(&next, &peer_id)?;
+
+    let announced_to: Vec<_> = state
+        .persistent_peer_ids
+        .iter()
+        .filter(|peer_id| swarm.is_connected(peer_id))
+        .copied()
+        .collect();
+
+    for peer_id in &announced_to {
+        swarm
+            .behaviour_mut()
+            .node_key
+            .send_request(peer_id, announcement.clone());
+    }
+
+    Ok(NodeKeyRotation {
+        peer_id,
+        next_peer_id,
+        announced_to,
+    })
+}
+
+/// Record the new `PeerId` of a persistent peer announcing the rotation of its node key
+fn handle_node_key_event(
+    event: node_key::Event,
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+) {
+    use libp2p::request_response::{Event, Message};
+
+    match event {
+        Event::Message {
+            peer,
+            message: Message::Request {
+                request, channel, ..
+            },
+            ..
+        } => {
+            match node_key::verify_announcement(&peer, request) {
+                Some(next_peer_id) if state.persistent_peer_ids.contains(&peer) => {
+                    let addresses = state.replace_persistent_peer_id(&peer, next_peer_id);
+                    info!(%peer, %next_peer_id, addresses, "Persistent peer rotated its node key");
+                }
+                Some(_) => {
+                    debug!(%peer, "Ignoring the rotation of the node key of a non-persistent peer")
+                }
+                None => warn!(%peer, "Received an invalid announcement of a node key rotation"),
+            }
+
+            if swarm
+                .behaviour_mut()
+                .node_key
+                .send_response(channel, Bytes::new())
+                .is_err()
+            {
+                debug!(%peer, "Failed to acknowledge the rotation of the node key");
+            }
+        }
+
+        Event::Message {
+            peer,
+            message: Message::Response { .. },
+            ..
+        } => {
+            debug!(%peer, "Peer acknowledged the rotation of the node key");
+        }
+
+        Event::OutboundFailure { peer, error, .. } => {
+            warn!(%peer, "Failed to announce the rotation of the node key: {error}");
+        }
+
+        Event::InboundFailure { .. } | Event::ResponseSent { .. } => {}
+    }
+}
+
+/// Set a default low score for a peer immediately upon connection
+/// This allows gossipsub to form an initial mesh before Identify completes
+#[cfg_attr(not(feature = "gossipsub"), allow(unused_variables))]
+fn set_default_peer_score(swarm: &mut swarm::Swarm<DefaultBehaviour>, peer_id: libp2p::PeerId) {
+    #[cfg(feature = "gossipsub")]
+    if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+        let score = peer_scoring::get_default_score();
+        gossipsub.set_application_score(&peer_id, score);
+        trace!("Set default application score {score} for peer {peer_id} before Identify");
+    }
+}
+
+/// Advertise the role of the local node in the discovery DHT, validator or full node
+/// depending on its membership in the current validator set
+fn advertise_local_role(
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+    config: &Config,
+) {
+    let role = if state.local_node.is_validator {
+        discovery::NodeRole::Validator
+    } else {
+        discovery::NodeRole::FullNode
+    };
+
+    state
+        .discovery
+        .advertise_role(swarm, &config.protocol_names.consensus, role);
+}
+
+#[cfg_attr(not(feature = "gossipsub"), allow(unused_variables))]
+fn set_peer_score(swarm: &mut swarm::Swarm<DefaultBehaviour>, peer_id: libp2p::PeerId, score: f64) {
+    // Set application-specific score in gossipsub if enabled
+    #[cfg(feature = "gossipsub")]
+    if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+        if gossipsub.set_application_score(&peer_id, score) {
+            debug!("Upgraded application score to {score} for peer {peer_id}");
+        }
+    }
+}
+
+/// Report the outcome of the validation of a message to GossipSub, which forwards it
+/// to the other peers if accepted, and penalize the peer which sent it if rejected
+fn report_message_validation(
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+    message_id: &MessageId,
+    acceptance: MessageAcceptance,
+) {
+    let Some(propagation_source) = state.take_pending_validation(message_id) else {
+        debug!(
+            ?message_id,
+            "Ignoring validation of unknown or expired message"
+        );
+        return;
+    };
+
+    #[cfg(feature = "gossipsub")]
+    if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+        gossipsub.report_message_validation_result(
+            message_id,
+            &propagation_source,
+            acceptance.into(),
+        );
+    }
+
+    if acceptance == MessageAcceptance::Reject {
+        debug!(?message_id, peer_id = %propagation_source, "Peer sent an invalid message");
+
+        let score = state.report_peer(propagation_source, peer_scoring::INVALID_MESSAGE_SCORE);
+        set_peer_score(swarm, propagation_source, score);
+    }
+}
+
+/// Add or remove a peer from the explicit peers in gossipsub, depending on the configured
+/// explicit peers, its type and the explicit peering options. A node always sends and forwards
+/// messages to its explicit peers, regardless of mesh membership.
+#[cfg(feature = "gossipsub")]
+fn update_explicit_peer_in_gossipsub(
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+    config: &Config,
+    peer_id: libp2p::PeerId,
+) {
+    let Some(peer_info) = state.peer_info.get_mut(&peer_id) else {
+        return;
+    };
+
+    let should_be_explicit = config.explicit_peers.contains(&peer_id)
+        || config.gossipsub.is_explicit_peer(peer_info.peer_type);
+
+    if should_be_explicit == peer_info.is_explicit {
+        return;
+    }
+
+    if !should_be_explicit {
+        remove_explicit_peer_from_gossipsub(swarm, state, config, &peer_id);
+        return;
+    }
+
+    if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+        gossipsub.add_explicit_peer(&peer_id);
+        state
+            .metrics
+            .record_explicit_peer(&peer_id, &peer_info.moniker);
+        peer_info.is_explicit = true;
+        info!(
+            "Added {} {peer_id} as explicit peer in gossipsub",
+            peer_info.peer_type.primary_type_str()
+        );
+    }
+}
+
+/// Remove a peer from explicit peers in gossipsub and mark the metric stale.
+///
+/// Configured explicit peers are kept in gossipsub, which dials them again,
+/// only the metric is marked stale.
+#[cfg(feature = "gossipsub")]
+fn remove_explicit_peer_from_gossipsub(
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+    config: &Config,
+    peer_id: &libp2p::PeerId,
+) {
+    let Some(peer_info) = state.peer_info.get_mut(peer_id) else {
+        return;
+    };
+
+    if peer_info.is_explicit && config.explicit_peers.contains(peer_id) {
+        state
+            .metrics
+            .mark_explicit_peer_stale(peer_id, &peer_info.moniker);
+        peer_info.is_explicit = false;
+    } else if peer_info.is_explicit {
         if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
             gossipsub.remove_explicit_peer(peer_id);
             state
                 .metrics
                 .mark_explicit_peer_stale(peer_id, &peer_info.moniker);
             peer_info.is_explicit = false;
-            info!("Removed persistent peer {peer_id} from explicit peers in gossipsub");
+            info!("Removed {peer_id} from explicit peers in gossipsub");
         }
     }
 }
@@ -774,26 +2112,57 @@ async fn handle_swarm_event(
     event: SwarmEvent<NetworkEvent>,
     config: &Config,
     metrics: &Metrics,
-    swarm: &mut swarm::Swarm<Behaviour>,
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
     state: &mut State,
-    tx_event: &mpsc::Sender<Event>,
+    events: &EventSenders,
 ) -> ControlFlow<()> {
-    if let SwarmEvent::Behaviour(NetworkEvent::GossipSub(e)) = &event {
-        metrics.record(e);
-    } else if let SwarmEvent::Behaviour(NetworkEvent::Identify(e)) = &event {
-        metrics.record(e.as_ref());
+    match &event {
+        #[cfg(feature = "gossipsub")]
+        SwarmEvent::Behaviour(NetworkEvent::GossipSub(e)) => metrics.record(e),
+        SwarmEvent::Behaviour(NetworkEvent::Identify(e)) => metrics.record(e.as_ref()),
+        SwarmEvent::Behaviour(_) => {}
+        // Connections, dial errors and listeners, including the events handled below
+        swarm_event => metrics.record(swarm_event),
     }
 
     match event {
         SwarmEvent::NewListenAddr { address, .. } => {
             debug!(%address, "Node is listening");
 
-            if let Err(e) = tx_event.send(Event::Listening(address)).await {
+            state.addr_monitor.on_new_listen_addr(address.clone());
+
+            if let Err(e) = events.send_all(Event::Listening(address)).await {
                 error!("Error sending listening event to handle: {e}");
                 return ControlFlow::Break(());
             }
         }
 
+        SwarmEvent::ExpiredListenAddr { address, .. } => {
+            warn!(%address, "Listen address expired");
+
+            state.addr_monitor.on_expired_listen_addr(&address);
+        }
+
+        SwarmEvent::ListenerClosed {
+            listener_id,
+            addresses,
+            reason,
+        } => {
+            // Listeners removed on request are already forgotten and must not be re-created
+            if state.listeners.remove(&listener_id).is_none() {
+                info!(?addresses, "Stopped listening");
+                state.addr_monitor.on_listener_removed(&addresses);
+                return ControlFlow::Continue(());
+            }
+
+            match reason {
+                Ok(()) => warn!(?addresses, "Listener closed"),
+                Err(e) => error!(?addresses, "Listener closed with error: {e}"),
+            }
+
+            state.addr_monitor.on_listener_closed(&addresses);
+        }
+
         SwarmEvent::ConnectionEstablished {
             peer_id,
             connection_id,
@@ -847,20 +2216,24 @@ async fn handle_swarm_event(
             state
                 .discovery
                 .handle_closed_connection(swarm, peer_id, connection_id);
+            state.liveness.remove_connection(&connection_id);
 
             if num_established == 0 {
                 // Remove explicit peer before removing peer_info (needs peer_info to exist)
-                if config.gossipsub.enable_explicit_peering {
-                    remove_explicit_peer_from_gossipsub(swarm, state, &peer_id);
-                }
+                #[cfg(feature = "gossipsub")]
+                remove_explicit_peer_from_gossipsub(swarm, state, config, &peer_id);
                 if let Some(peer_info) = state.peer_info.remove(&peer_id) {
                     state.metrics.free_slot(&peer_id, &peer_info);
                 }
                 // Also clean up any pending proof (proof verified before Identify completed)
                 state.pending_verified_proofs.remove(&peer_id);
+                state.addr_monitor.on_peer_disconnected(&peer_id);
+                state.bandwidth.remove_peer(&peer_id);
+                state.duplicates.remove_peer(&peer_id);
+                state.mempool.remove_peer(&peer_id);
 
-                if let Err(e) = tx_event
-                    .send(Event::PeerDisconnected(PeerId::from_libp2p(&peer_id)))
+                if let Err(e) = events
+                    .send_all(Event::PeerDisconnected(PeerId::from_libp2p(&peer_id)))
                     .await
                 {
                     error!("Error sending peer disconnected event to handle: {e}");
@@ -899,16 +2272,28 @@ async fn handle_swarm_event(
 
                     // Update peer info in State and metrics, set peer score in gossipsub
                     let score = state.update_peer(peer_id, connection_id, &info);
-                    set_peer_score(swarm, peer_id, score);
+                    set_peer_score(swarm, peer_id, score + state.reported_score(&peer_id));
+
+                    // If enabled, add persistent peers and validators as explicit peers for guaranteed delivery
+                    #[cfg(feature = "gossipsub")]
+                    update_explicit_peer_in_gossipsub(swarm, state, config, peer_id);
+
+                    let is_validator = state
+                        .peer_info
+                        .get(&peer_id)
+                        .is_some_and(|info| info.peer_type.is_validator());
 
-                    // If enabled, add persistent peers as explicit peers for guaranteed delivery
-                    if config.gossipsub.enable_explicit_peering {
-                        add_explicit_peer_to_gossipsub(swarm, state, peer_id);
+                    if let Some((old_ip, new_addr)) = state.addr_monitor.on_observed_addr(
+                        peer_id,
+                        &info.observed_addr,
+                        is_validator,
+                    ) {
+                        handle_external_ip_change(swarm, state, old_ip, new_addr);
                     }
 
                     if !is_already_connected {
-                        if let Err(e) = tx_event
-                            .send(Event::PeerConnected(PeerId::from_libp2p(&peer_id)))
+                        if let Err(e) = events
+                            .send_all(Event::PeerConnected(PeerId::from_libp2p(&peer_id)))
                             .await
                         {
                             error!("Error sending peer connected event to handle: {e}");
@@ -931,55 +2316,95 @@ async fn handle_swarm_event(
             match &event.result {
                 Ok(rtt) => {
                     trace!("Received pong from {} in {rtt:?}", event.peer);
+
+                    state.discovery.record_peer_rtt(event.peer, *rtt);
                 }
                 Err(e) => {
                     trace!("Received pong from {} with error: {e}", event.peer);
                 }
             }
 
+            // Close the connection to an unresponsive peer, discovery replaces it once closed
+            let rtt = event.result.as_ref().ok().copied();
+            if state
+                .liveness
+                .record_ping(&config.liveness, event.connection, rtt)
+            {
+                warn!(peer_id = %event.peer, connection_id = %event.connection, "Peer is unresponsive, closing the connection");
+                swarm.close_connection(event.connection);
+            }
+
             // Record metric for round-trip time sending a ping and receiving a pong
             metrics.record(&event);
         }
 
+        #[cfg(feature = "gossipsub")]
         SwarmEvent::Behaviour(NetworkEvent::GossipSub(event)) => {
-            return handle_gossipsub_event(event, config, metrics, swarm, state, tx_event).await;
+            return handle_gossipsub_event(event, config, metrics, swarm, state, events).await;
         }
 
         SwarmEvent::Behaviour(NetworkEvent::Broadcast(event)) => {
-            return handle_broadcast_event(event, config, metrics, swarm, state, tx_event).await;
+            return handle_broadcast_event(event, config, metrics, swarm, state, events).await;
         }
 
-        SwarmEvent::Behaviour(NetworkEvent::Sync(event)) => {
-            return handle_sync_event(event, metrics, swarm, state, tx_event).await;
+        #[cfg(feature = "sync")]
+        SwarmEvent::Behaviour(NetworkEvent::Sync(chain, event)) => {
+            return handle_sync_event(chain, event, config, metrics, swarm, state, events).await;
         }
 
         SwarmEvent::Behaviour(NetworkEvent::ValidatorProof(event)) => {
-            return handle_validator_proof_event(event, tx_event).await;
+            return handle_validator_proof_event(event, events).await;
+        }
+
+        SwarmEvent::Behaviour(NetworkEvent::PeerMessage(chain, event)) => {
+            return handle_peer_message_event(chain, event, state, events).await;
+        }
+
+        SwarmEvent::Behaviour(NetworkEvent::NodeKey(event)) => {
+            handle_node_key_event(event, swarm, state);
         }
 
+        #[cfg(feature = "dcutr")]
+        SwarmEvent::Behaviour(NetworkEvent::HolePunch(event)) => {
+            match &event {
+                HolePunchEvent::Failed { .. } | HolePunchEvent::Denied { .. } => {
+                    info!("Hole punching: {event}")
+                }
+                _ => debug!("Hole punching: {event}"),
+            }
+
+            state.metrics.record_hole_punch(&event);
+
+            if let Err(e) = events.send_all(Event::HolePunch(event)).await {
+                error!("Error sending hole punching event to handle: {e}");
+                return ControlFlow::Break(());
+            }
+        }
+
+        #[cfg(feature = "discovery")]
         SwarmEvent::Behaviour(NetworkEvent::Discovery(network_event)) => {
+            record_discovery_bandwidth(state, &network_event);
             state.discovery.on_network_event(swarm, *network_event);
         }
 
-        swarm_event => {
-            metrics.record(&swarm_event);
-        }
+        _ => {}
     }
 
     ControlFlow::Continue(())
 }
 
+#[cfg(feature = "gossipsub")]
 async fn handle_gossipsub_event(
     event: gossipsub::Event,
     config: &Config,
     _metrics: &Metrics,
-    _swarm: &mut swarm::Swarm<Behaviour>,
-    _state: &mut State,
-    tx_event: &mpsc::Sender<Event>,
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+    events: &EventSenders,
 ) -> ControlFlow<()> {
     match event {
         gossipsub::Event::Subscribed { peer_id, topic } => {
-            if !Channel::has_gossipsub_topic(&topic, config.channel_names) {
+            if chain::from_gossipsub_topic_hash(config, &topic).is_none() {
                 trace!("Peer {peer_id} tried to subscribe to unknown topic: {topic}");
                 return ControlFlow::Continue(());
             }
@@ -988,7 +2413,7 @@ async fn handle_gossipsub_event(
         }
 
         gossipsub::Event::Unsubscribed { peer_id, topic } => {
-            if !Channel::has_gossipsub_topic(&topic, config.channel_names) {
+            if chain::from_gossipsub_topic_hash(config, &topic).is_none() {
                 trace!("Peer {peer_id} tried to unsubscribe from unknown topic: {topic}");
                 return ControlFlow::Continue(());
             }
@@ -997,39 +2422,155 @@ async fn handle_gossipsub_event(
         }
 
         gossipsub::Event::Message {
+            propagation_source,
             message_id,
             message,
-            ..
         } => {
+            state.bandwidth.record(
+                &propagation_source,
+                bandwidth::Protocol::PubSub,
+                Direction::Inbound,
+                message.data.len(),
+            );
+
+            let validate = config.gossipsub.validate_messages;
+            if validate {
+                state.add_pending_validation(message_id.clone(), propagation_source);
+            }
+
             let Some(peer_id) = message.source else {
+                if validate {
+                    report_message_validation(swarm, state, &message_id, MessageAcceptance::Ignore);
+                }
+
                 return ControlFlow::Continue(());
             };
 
-            let Some(channel) =
-                Channel::from_gossipsub_topic_hash(&message.topic, config.channel_names)
+            let Some(delay) = state.link_delay(&propagation_source) else {
+                trace!("Dropping message {message_id} from {propagation_source}, lost on the link");
+
+                if validate {
+                    report_message_validation(swarm, state, &message_id, MessageAcceptance::Ignore);
+                }
+
+                return ControlFlow::Continue(());
+            };
+
+            let Some((chain, channel)) = chain::from_gossipsub_topic_hash(config, &message.topic)
             else {
                 trace!(
                     "Received message {message_id} from {peer_id} on different channel: {}",
                     message.topic
                 );
 
+                if validate {
+                    report_message_validation(swarm, state, &message_id, MessageAcceptance::Ignore);
+                }
+
                 return ControlFlow::Continue(());
             };
 
+            if state
+                .duplicates
+                .record(&propagation_source, channel, &message.data, Instant::now())
+            {
+                trace!("Message {message_id} from {propagation_source} on channel {channel} is a duplicate");
+            }
+
             trace!(
                 "Received message {message_id} from {peer_id} on channel {channel} of {} bytes",
                 message.data.len()
             );
 
+            let data = if config.compression.is_enabled(channel) {
+                match compression::decompress(&message.data, config.pubsub_max_size) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        debug!("Dropping message {message_id} from {peer_id} on channel {channel}: {e}");
+
+                        if validate {
+                            report_message_validation(
+                                swarm,
+                                state,
+                                &message_id,
+                                MessageAcceptance::Reject,
+                            );
+                        }
+
+                        return ControlFlow::Continue(());
+                    }
+                }
+            } else {
+                Bytes::from(message.data)
+            };
+
+            if let Some((protocol, max_size)) = config.max_message_sizes.for_channel(channel) {
+                if data.len() > max_size {
+                    debug!(
+                        "Dropping message {message_id} from {peer_id} on channel {channel}: \
+                         {} bytes over the maximum of {max_size} bytes",
+                        data.len()
+                    );
+
+                    state.metrics.record_oversized_message(protocol);
+
+                    if validate {
+                        report_message_validation(
+                            swarm,
+                            state,
+                            &message_id,
+                            MessageAcceptance::Reject,
+                        );
+                    }
+
+                    return ControlFlow::Continue(());
+                }
+            }
+
             let peer_id = PeerId::from_libp2p(&peer_id);
 
-            let event = if channel == Channel::Liveness {
-                Event::LivenessMessage(channel, peer_id, Bytes::from(message.data))
+            if channel == Channel::Mempool {
+                let admission =
+                    state
+                        .mempool
+                        .admit(&propagation_source, data.len(), Instant::now());
+
+                // Transactions are validated by the mempool of the application, after having
+                // been forwarded, so only the limits of the channel are enforced here
+                if validate {
+                    let acceptance = match admission {
+                        Admission::Accepted => MessageAcceptance::Accept,
+                        Admission::TooLarge => MessageAcceptance::Reject,
+                        Admission::RateLimited => MessageAcceptance::Ignore,
+                    };
+
+                    report_message_validation(swarm, state, &message_id, acceptance);
+                }
+
+                if admission != Admission::Accepted {
+                    debug!("Dropping message {message_id} from {propagation_source} on the mempool channel: {admission:?}");
+                    return ControlFlow::Continue(());
+                }
+
+                if let Err(e) =
+                    send_after(events, chain, Event::MempoolMessage(peer_id, data), delay).await
+                {
+                    error!("Error sending transactions to handle: {e}");
+                    return ControlFlow::Break(());
+                }
+
+                return ControlFlow::Continue(());
+            }
+
+            let event = if validate {
+                Event::UnvalidatedMessage(message_id, channel, peer_id, data)
+            } else if channel == Channel::Liveness {
+                Event::LivenessMessage(channel, peer_id, data)
             } else {
-                Event::ConsensusMessage(channel, peer_id, Bytes::from(message.data))
+                Event::ConsensusMessage(channel, peer_id, data)
             };
 
-            if let Err(e) = tx_event.send(event).await {
+            if let Err(e) = send_after(events, chain, event, delay).await {
                 error!("Error sending message to handle: {e}");
                 return ControlFlow::Break(());
             }
@@ -1057,13 +2598,13 @@ async fn handle_broadcast_event(
     event: broadcast::Event,
     config: &Config,
     _metrics: &Metrics,
-    _swarm: &mut swarm::Swarm<Behaviour>,
-    _state: &mut State,
-    tx_event: &mpsc::Sender<Event>,
+    _swarm: &mut swarm::Swarm<DefaultBehaviour>,
+    state: &mut State,
+    events: &EventSenders,
 ) -> ControlFlow<()> {
     match event {
         broadcast::Event::Subscribed(peer_id, topic) => {
-            if !Channel::has_broadcast_topic(&topic, config.channel_names) {
+            if chain::from_broadcast_topic(config, &topic).is_none() {
                 trace!("Peer {peer_id} tried to subscribe to unknown topic: {topic:?}");
                 return ControlFlow::Continue(());
             }
@@ -1072,7 +2613,7 @@ async fn handle_broadcast_event(
         }
 
         broadcast::Event::Unsubscribed(peer_id, topic) => {
-            if !Channel::has_broadcast_topic(&topic, config.channel_names) {
+            if chain::from_broadcast_topic(config, &topic).is_none() {
                 trace!("Peer {peer_id} tried to unsubscribe from unknown topic: {topic:?}");
                 return ControlFlow::Continue(());
             }
@@ -1081,25 +2622,76 @@ async fn handle_broadcast_event(
         }
 
         broadcast::Event::Received(peer_id, topic, message) => {
-            let Some(channel) = Channel::from_broadcast_topic(&topic, config.channel_names) else {
+            state.bandwidth.record(
+                &peer_id,
+                bandwidth::Protocol::PubSub,
+                Direction::Inbound,
+                message.len(),
+            );
+
+            let Some((chain, channel)) = chain::from_broadcast_topic(config, &topic) else {
                 trace!("Received message from {peer_id} on different channel: {topic:?}");
                 return ControlFlow::Continue(());
             };
 
+            let Some(delay) = state.link_delay(&peer_id) else {
+                trace!("Dropping message from {peer_id} on channel {channel}, lost on the link");
+                return ControlFlow::Continue(());
+            };
+
+            if state
+                .duplicates
+                .record(&peer_id, channel, &message, Instant::now())
+            {
+                trace!("Message from {peer_id} on channel {channel} is a duplicate");
+            }
+
             trace!(
                 "Received message from {peer_id} on channel {channel} of {} bytes",
                 message.len()
             );
 
-            let peer_id = PeerId::from_libp2p(&peer_id);
+            let message = if config.compression.is_enabled(channel) {
+                match compression::decompress(&message, config.pubsub_max_size) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        debug!("Dropping message from {peer_id} on channel {channel}: {e}");
+                        return ControlFlow::Continue(());
+                    }
+                }
+            } else {
+                message
+            };
+
+            if let Some((protocol, max_size)) = config.max_message_sizes.for_channel(channel) {
+                if message.len() > max_size {
+                    debug!(
+                        "Dropping message from {peer_id} on channel {channel}: \
+                         {} bytes over the maximum of {max_size} bytes",
+                        message.len()
+                    );
 
-            let event = if channel == Channel::Liveness {
-                Event::LivenessMessage(channel, peer_id, message)
+                    state.metrics.record_oversized_message(protocol);
+                    return ControlFlow::Continue(());
+                }
+            }
+
+            let event = if channel == Channel::Mempool {
+                let admission = state.mempool.admit(&peer_id, message.len(), Instant::now());
+
+                if admission != Admission::Accepted {
+                    debug!("Dropping message from {peer_id} on the mempool channel: {admission:?}");
+                    return ControlFlow::Continue(());
+                }
+
+                Event::MempoolMessage(PeerId::from_libp2p(&peer_id), message)
+            } else if channel == Channel::Liveness {
+                Event::LivenessMessage(channel, PeerId::from_libp2p(&peer_id), message)
             } else {
-                Event::ConsensusMessage(channel, peer_id, message)
+                Event::ConsensusMessage(channel, PeerId::from_libp2p(&peer_id), message)
             };
 
-            if let Err(e) = tx_event.send(event).await {
+            if let Err(e) = send_after(events, chain, event, delay).await {
                 error!("Error sending message to handle: {e}");
                 return ControlFlow::Break(());
             }
@@ -1109,12 +2701,36 @@ async fn handle_broadcast_event(
     ControlFlow::Continue(())
 }
 
+/// Send an event to a chain after the given delay, without waiting for it to elapse
+async fn send_after(
+    events: &EventSenders,
+    chain: usize,
+    event: Event,
+    delay: Duration,
+) -> Result<(), mpsc::error::SendError<Event>> {
+    if delay.is_zero() {
+        return events.send(chain, event).await;
+    }
+
+    if let Some(tx_event) = events.sender(chain) {
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = tx_event.send(event).await;
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "sync")]
 async fn handle_sync_event(
+    chain: usize,
     event: sync::Event,
+    config: &Config,
     _metrics: &Metrics,
-    _swarm: &mut swarm::Swarm<Behaviour>,
+    _swarm: &mut swarm::Swarm<DefaultBehaviour>,
     state: &mut State,
-    tx_event: &mpsc::Sender<Event>,
+    events: &EventSenders,
 ) -> ControlFlow<()> {
     match event {
         sync::Event::Message { peer, message, .. } => {
@@ -1124,14 +2740,42 @@ async fn handle_sync_event(
                     request,
                     channel,
                 } => {
-                    state.sync_channels.insert(request_id, channel);
-
-                    let _ = tx_event
-                        .send(Event::Sync(sync::RawMessage::Request {
-                            request_id,
-                            peer: PeerId::from_libp2p(&peer),
-                            body: request.0,
-                        }))
+                    state.bandwidth.record(
+                        &peer,
+                        bandwidth::Protocol::Sync,
+                        Direction::Inbound,
+                        request.0.len(),
+                    );
+
+                    let max_sizes = &config.max_message_sizes;
+                    if let Some(max_size) =
+                        max_sizes.exceeded(Protocol::SyncRequests, request.0.len())
+                    {
+                        debug!(
+                            "Dropping sync request from {peer}: \
+                             {} bytes over the maximum of {max_size} bytes",
+                            request.0.len()
+                        );
+
+                        state
+                            .metrics
+                            .record_oversized_message(Protocol::SyncRequests);
+                        return ControlFlow::Continue(());
+                    }
+
+                    state
+                        .sync_channels
+                        .insert((chain, request_id), (peer, channel));
+
+                    let _ = events
+                        .send(
+                            chain,
+                            Event::Sync(sync::RawMessage::Request {
+                                request_id,
+                                peer: PeerId::from_libp2p(&peer),
+                                body: request.0,
+                            }),
+                        )
                         .await
                         .map_err(|e| {
                             error!("Error sending Sync request to handle: {e}");
@@ -1142,12 +2786,38 @@ async fn handle_sync_event(
                     request_id,
                     response,
                 } => {
-                    let _ = tx_event
-                        .send(Event::Sync(sync::RawMessage::Response {
-                            request_id,
-                            peer: PeerId::from_libp2p(&peer),
-                            body: response.0,
-                        }))
+                    state.bandwidth.record(
+                        &peer,
+                        bandwidth::Protocol::Sync,
+                        Direction::Inbound,
+                        response.0.len(),
+                    );
+
+                    let max_sizes = &config.max_message_sizes;
+                    if let Some(max_size) =
+                        max_sizes.exceeded(Protocol::SyncResponses, response.0.len())
+                    {
+                        debug!(
+                            "Dropping sync response from {peer}: \
+                             {} bytes over the maximum of {max_size} bytes",
+                            response.0.len()
+                        );
+
+                        state
+                            .metrics
+                            .record_oversized_message(Protocol::SyncResponses);
+                        return ControlFlow::Continue(());
+                    }
+
+                    let _ = events
+                        .send(
+                            chain,
+                            Event::Sync(sync::RawMessage::Response {
+                                request_id,
+                                peer: PeerId::from_libp2p(&peer),
+                                body: response.0,
+                            }),
+                        )
                         .await
                         .map_err(|e| {
                             error!("Error sending Sync response to handle: {e}");
@@ -1166,18 +2836,136 @@ async fn handle_sync_event(
     }
 }
 
+async fn handle_peer_message_event(
+    chain: usize,
+    event: peer_message::Event,
+    state: &mut State,
+    events: &EventSenders,
+) -> ControlFlow<()> {
+    use libp2p::request_response::{Event, Message};
+
+    match event {
+        Event::Message {
+            peer,
+            message:
+                Message::Request {
+                    request_id,
+                    request,
+                    channel,
+                },
+            ..
+        } => {
+            state.bandwidth.record(
+                &peer,
+                bandwidth::Protocol::PeerMessage,
+                Direction::Inbound,
+                request.len(),
+            );
+
+            state
+                .peer_message_channels
+                .insert((chain, request_id), (peer, channel));
+
+            let event = crate::Event::PeerMessage {
+                request_id,
+                peer_id: PeerId::from_libp2p(&peer),
+                payload: request,
+            };
+
+            if let Err(e) = events.send(chain, event).await {
+                error!("Error sending peer message to handle: {e}");
+                return ControlFlow::Break(());
+            }
+        }
+
+        Event::Message {
+            peer,
+            message:
+                Message::Response {
+                    request_id,
+                    response,
+                },
+            ..
+        } => {
+            state.bandwidth.record(
+                &peer,
+                bandwidth::Protocol::PeerMessage,
+                Direction::Inbound,
+                response.len(),
+            );
+
+            if let Some(reply_to) = state.peer_message_replies.remove(&(chain, request_id)) {
+                let _ = reply_to.send(Ok(response));
+            }
+        }
+
+        Event::OutboundFailure {
+            peer,
+            request_id,
+            error,
+            ..
+        } => {
+            debug!(%peer, %request_id, "Failed to send message to peer: {error}");
+
+            if let Some(reply_to) = state.peer_message_replies.remove(&(chain, request_id)) {
+                let _ = reply_to.send(Err(error.into()));
+            }
+        }
+
+        Event::InboundFailure {
+            peer,
+            request_id,
+            error,
+            ..
+        } => {
+            debug!(%peer, %request_id, "Failed to reply to message from peer: {error}");
+            state.peer_message_channels.remove(&(chain, request_id));
+        }
+
+        Event::ResponseSent { .. } => {}
+    }
+
+    ControlFlow::Continue(())
+}
+
+/// Record the peer records received through discovery requests and responses
+#[cfg(feature = "discovery")]
+fn record_discovery_bandwidth(state: &mut State, event: &discovery::NetworkEvent) {
+    use libp2p::request_response::{Event, Message};
+
+    let discovery::NetworkEvent::RequestResponse(Event::Message { peer, message, .. }) = event
+    else {
+        return;
+    };
+
+    let len = match message {
+        Message::Request { request, .. } => bandwidth::discovery_request_len(request),
+        Message::Response { response, .. } => bandwidth::discovery_response_len(response),
+    };
+
+    state.bandwidth.record(
+        peer,
+        bandwidth::Protocol::Discovery,
+        Direction::Inbound,
+        len,
+    );
+}
+
 async fn handle_validator_proof_event(
     event: validator_proof::Event,
-    tx_event: &mpsc::Sender<Event>,
+    events: &EventSenders,
 ) -> ControlFlow<()> {
     match event {
         validator_proof::Event::ProofReceived { peer, proof_bytes } => {
             // Forward to engine for verification
-            let _ = tx_event
-                .send(Event::ValidatorProofReceived {
-                    peer_id: PeerId::from_libp2p(&peer),
-                    proof_bytes,
-                })
+            let _ = events
+                .send(
+                    PRIMARY_CHAIN,
+                    Event::ValidatorProofReceived {
+                        peer_id: PeerId::from_libp2p(&peer),
+                        proof_bytes,
+                    },
+                )
                 .await
                 .map_err(|e| {
                     error!("Error sending ValidatorProofReceived to handle: {e}");
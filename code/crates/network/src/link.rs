@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Conditions of the link to a peer, simulated on the messages received from it over
+/// the pubsub channels, eg. to test consensus under the conditions of a wide area network.
+///
+/// The messages of a link with jitter may be delivered out of order.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LinkConditions {
+    /// Delay added to every message
+    pub latency: Duration,
+    /// Maximum deviation from the latency, drawn uniformly for every message
+    pub jitter: Duration,
+    /// Probability for a message to be dropped, between 0 and 1
+    pub loss: f64,
+}
+
+impl LinkConditions {
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        self.loss = loss.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay of a message on the link, `None` if the message is lost
+    pub(crate) fn sample(&self, rng: &mut impl Rng) -> Option<Duration> {
+        if self.loss > 0.0 && rng.gen_bool(self.loss.clamp(0.0, 1.0)) {
+            return None;
+        }
+
+        if self.jitter.is_zero() {
+            return Some(self.latency);
+        }
+
+        let min = self.latency.saturating_sub(self.jitter);
+        let max = self.latency + self.jitter;
+
+        Some(rng.gen_range(min..=max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn delay_is_within_jitter_of_latency() {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let conditions = LinkConditions::default()
+            .with_latency(Duration::from_millis(50))
+            .with_jitter(Duration::from_millis(80));
+
+        for _ in 0..100 {
+            let delay = conditions.sample(&mut rng).unwrap();
+            assert!(delay <= Duration::from_millis(130));
+        }
+    }
+
+    #[test]
+    fn lossy_link_drops_messages() {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let lossless = LinkConditions::default();
+        assert!((0..100).all(|_| lossless.sample(&mut rng).is_some()));
+
+        let lossy = LinkConditions::default().with_loss(1.0);
+        assert!((0..100).all(|_| lossy.sample(&mut rng).is_none()));
+    }
+}
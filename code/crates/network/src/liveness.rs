@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use libp2p::swarm::ConnectionId;
+
+/// Closing of the connections to the peers which stopped responding to pings, or respond
+/// too slowly, so that discovery replaces them
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LivenessConfig {
+    /// Number of consecutive failed pings after which the connection is closed,
+    /// connections are never closed if `None`
+    pub max_ping_failures: Option<u32>,
+
+    /// Round-trip time above which a ping counts as failed, no limit if `None`
+    pub max_ping_rtt: Option<Duration>,
+}
+
+/// Consecutive failed pings on each connection
+#[derive(Debug, Default)]
+pub(crate) struct Liveness {
+    failures: HashMap<ConnectionId, u32>,
+}
+
+impl Liveness {
+    /// Record the outcome of a ping, returns whether the connection should be closed
+    pub fn record_ping(
+        &mut self,
+        config: &LivenessConfig,
+        connection_id: ConnectionId,
+        rtt: Option<Duration>,
+    ) -> bool {
+        let Some(max_failures) = config.max_ping_failures else {
+            return false;
+        };
+
+        let failed = match rtt {
+            Some(rtt) => config.max_ping_rtt.is_some_and(|max_rtt| rtt > max_rtt),
+            None => true,
+        };
+
+        if !failed {
+            self.failures.remove(&connection_id);
+            return false;
+        }
+
+        let failures = self.failures.entry(connection_id).or_default();
+        *failures += 1;
+        *failures >= max_failures
+    }
+
+    pub fn remove_connection(&mut self, connection_id: &ConnectionId) {
+        self.failures.remove(connection_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_ping() {
+        let config = LivenessConfig {
+            max_ping_failures: Some(2),
+            max_ping_rtt: Some(Duration::from_millis(500)),
+        };
+
+        let mut liveness = Liveness::default();
+        let connection_id = ConnectionId::new_unchecked(1);
+        let fast = Some(Duration::from_millis(10));
+        let slow = Some(Duration::from_secs(1));
+
+        // Failures must be consecutive
+        assert!(!liveness.record_ping(&config, connection_id, None));
+        assert!(!liveness.record_ping(&config, connection_id, fast));
+        assert!(!liveness.record_ping(&config, connection_id, slow));
+        assert!(liveness.record_ping(&config, connection_id, None));
+
+        liveness.remove_connection(&connection_id);
+        assert!(!liveness.record_ping(&config, connection_id, slow));
+
+        // Disabled by default
+        let mut liveness = Liveness::default();
+        for _ in 0..10 {
+            assert!(!liveness.record_ping(&LivenessConfig::default(), connection_id, None));
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use libp2p::PeerId;
+
+/// Window over which the messages received from each peer on the mempool channel are counted
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Gossip of the transactions of the application on the mempool channel,
+/// with its own limits, separate from the ones of the consensus channels
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MempoolConfig {
+    /// Subscribe to the mempool channel, no transaction is sent or received if disabled
+    pub enabled: bool,
+
+    /// Maximum size of a message on the mempool channel, eg. a transaction or a batch of them
+    pub max_msg_size: usize,
+
+    /// Maximum number of messages per second accepted from a single peer,
+    /// the ones above being dropped, unlimited if `None`
+    pub max_msgs_per_peer: Option<u32>,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_msg_size: 1024 * 1024,
+            max_msgs_per_peer: Some(100),
+        }
+    }
+}
+
+/// Whether a message received on the mempool channel is within its limits
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Admission {
+    Accepted,
+    /// Larger than `max_msg_size`
+    TooLarge,
+    /// Above `max_msgs_per_peer` in the current window
+    RateLimited,
+}
+
+#[derive(Debug)]
+struct PeerWindow {
+    started_at: Instant,
+    count: u32,
+}
+
+/// Enforces the limits of the mempool channel on the messages received from each peer
+#[derive(Debug)]
+pub(crate) struct MempoolLimiter {
+    config: MempoolConfig,
+    peers: HashMap<PeerId, PeerWindow>,
+}
+
+impl MempoolLimiter {
+    pub(crate) fn new(config: MempoolConfig) -> Self {
+        Self {
+            config,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Count a message of the given size received from a peer, checking it against the limits
+    pub(crate) fn admit(&mut self, peer_id: &PeerId, size: usize, now: Instant) -> Admission {
+        if size > self.config.max_msg_size {
+            return Admission::TooLarge;
+        }
+
+        let Some(max_msgs) = self.config.max_msgs_per_peer else {
+            return Admission::Accepted;
+        };
+
+        let window = self.peers.entry(*peer_id).or_insert(PeerWindow {
+            started_at: now,
+            count: 0,
+        });
+
+        if now.duration_since(window.started_at) >= RATE_WINDOW {
+            window.started_at = now;
+            window.count = 0;
+        }
+
+        if window.count >= max_msgs {
+            return Admission::RateLimited;
+        }
+
+        window.count += 1;
+        Admission::Accepted
+    }
+
+    /// Forget a disconnected peer
+    pub(crate) fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_above_the_limits_are_refused() {
+        let mut limiter = MempoolLimiter::new(MempoolConfig {
+            enabled: true,
+            max_msg_size: 100,
+            max_msgs_per_peer: Some(2),
+        });
+
+        let peer = PeerId::random();
+        let other = PeerId::random();
+        let now = Instant::now();
+
+        assert_eq!(limiter.admit(&peer, 101, now), Admission::TooLarge);
+        assert_eq!(limiter.admit(&peer, 100, now), Admission::Accepted);
+        assert_eq!(limiter.admit(&peer, 10, now), Admission::Accepted);
+        assert_eq!(limiter.admit(&peer, 10, now), Admission::RateLimited);
+
+        // Each peer has its own budget
+        assert_eq!(limiter.admit(&other, 10, now), Admission::Accepted);
+
+        // Which is restored in the next window
+        let later = now + RATE_WINDOW;
+        assert_eq!(limiter.admit(&peer, 10, later), Admission::Accepted);
+    }
+}
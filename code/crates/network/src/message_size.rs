@@ -0,0 +1,98 @@
+use crate::Channel;
+
+/// Maximum size of the messages of each protocol, within the `pubsub_max_size` and
+/// `rpc_max_size` limits shared by all of them, so that eg. votes cannot be as large
+/// as proposals. Messages over the limit of their protocol are dropped when received,
+/// the protocols without a limit of their own only being bound by the shared ones.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MaxMessageSizes {
+    /// Messages on the consensus and liveness channels, ie. votes, signed proposals and
+    /// certificates. Must fit the proposals carrying the full value, if any.
+    pub votes: Option<usize>,
+
+    /// Messages on the proposal parts channel
+    pub proposal_parts: Option<usize>,
+
+    /// Sync requests received from the peers
+    pub sync_requests: Option<usize>,
+
+    /// Sync responses received from the peers, once their chunks are put back together
+    pub sync_responses: Option<usize>,
+
+    /// Requests and responses of the discovery protocol, enforced by its codec
+    pub discovery: Option<usize>,
+}
+
+impl MaxMessageSizes {
+    /// Protocol of the messages on the given channel, if it has a limit of its own
+    pub(crate) fn for_channel(&self, channel: Channel) -> Option<(Protocol, usize)> {
+        match channel {
+            Channel::Consensus | Channel::Liveness => self.votes.map(|max| (Protocol::Votes, max)),
+            Channel::ProposalParts => self
+                .proposal_parts
+                .map(|max| (Protocol::ProposalParts, max)),
+            Channel::Sync | Channel::Evidence | Channel::Mempool => None,
+        }
+    }
+
+    /// Check the size of a message of the given protocol,
+    /// returning the limit it is over, if any
+    pub(crate) fn exceeded(&self, protocol: Protocol, size: usize) -> Option<usize> {
+        let max = match protocol {
+            Protocol::Votes => self.votes,
+            Protocol::ProposalParts => self.proposal_parts,
+            Protocol::SyncRequests => self.sync_requests,
+            Protocol::SyncResponses => self.sync_responses,
+        }?;
+
+        (size > max).then_some(max)
+    }
+}
+
+/// Protocols whose oversized messages are counted in the metrics
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Protocol {
+    Votes,
+    ProposalParts,
+    #[cfg_attr(not(feature = "sync"), allow(dead_code))]
+    SyncRequests,
+    #[cfg_attr(not(feature = "sync"), allow(dead_code))]
+    SyncResponses,
+}
+
+impl Protocol {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Votes => "votes",
+            Protocol::ProposalParts => "proposal_parts",
+            Protocol::SyncRequests => "sync_requests",
+            Protocol::SyncResponses => "sync_responses",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_checked_against_the_limit_of_their_protocol() {
+        let sizes = MaxMessageSizes {
+            votes: Some(1024),
+            sync_requests: Some(512),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            sizes.for_channel(Channel::Liveness),
+            Some((Protocol::Votes, 1024))
+        );
+        assert_eq!(sizes.for_channel(Channel::ProposalParts), None);
+        assert_eq!(sizes.for_channel(Channel::Evidence), None);
+
+        assert_eq!(sizes.exceeded(Protocol::Votes, 1024), None);
+        assert_eq!(sizes.exceeded(Protocol::Votes, 1025), Some(1024));
+        assert_eq!(sizes.exceeded(Protocol::SyncRequests, 513), Some(512));
+        assert_eq!(sizes.exceeded(Protocol::SyncResponses, usize::MAX), None);
+    }
+}
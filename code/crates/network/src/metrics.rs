@@ -1,6 +1,8 @@
 use std::collections::HashSet;
+use std::sync::atomic::AtomicU64;
 
 use malachitebft_metrics::prometheus::encoding::EncodeLabelSet;
+use malachitebft_metrics::prometheus::metrics::counter::Counter;
 use malachitebft_metrics::prometheus::metrics::family::Family;
 use malachitebft_metrics::prometheus::metrics::gauge::Gauge;
 use malachitebft_metrics::Registry;
@@ -9,6 +11,9 @@ use tracing::{debug, warn};
 // Make prometheus_client available for the derive macro
 use malachitebft_metrics::prometheus as prometheus_client;
 
+#[cfg(feature = "dcutr")]
+use crate::hole_punch::HolePunchEvent;
+use crate::message_size::Protocol;
 use crate::state::{LocalNodeInfo, PeerInfo};
 use crate::utils::Slots;
 use crate::PeerType;
@@ -38,6 +43,25 @@ pub(crate) struct MeshMembershipLabels {
     topic: String, // "/consensus", "/liveness", "/proposal_parts"
 }
 
+/// Labels for per-topic mesh composition metrics
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub(crate) struct MeshTopicLabels {
+    topic: String,
+}
+
+/// Labels for oversized messages metric
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub(crate) struct ProtocolLabels {
+    protocol: String, // "votes", "proposal_parts", "sync_requests", "sync_responses"
+}
+
+/// Labels for hole punching metric
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub(crate) struct HolePunchLabels {
+    peer_id: String,
+    outcome: String, // "attempted", "succeeded", "failed", "denied"
+}
+
 /// Labels for explicit peer metric
 #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
 pub(crate) struct ExplicitPeerLabels {
@@ -91,7 +115,22 @@ pub(crate) struct Metrics {
     /// Per-peer, per-topic mesh membership (1 = in mesh, 0 = not in mesh)
     peer_mesh_membership: Family<MeshMembershipLabels, Gauge>,
     /// Explicit peers in gossipsub (1 = active, i64::MIN = disconnected/stale)
+    #[cfg_attr(not(feature = "gossipsub"), allow(dead_code))]
     explicit_peers: Family<ExplicitPeerLabels, Gauge>,
+    /// Per-topic number of peers in the gossipsub mesh
+    #[cfg_attr(not(feature = "gossipsub"), allow(dead_code))]
+    mesh_size: Family<MeshTopicLabels, Gauge>,
+    /// Per-topic number of peers that joined or left the gossipsub mesh
+    #[cfg_attr(not(feature = "gossipsub"), allow(dead_code))]
+    mesh_churn: Family<MeshTopicLabels, Counter>,
+    /// Per-topic fraction of mesh peers connected through outbound connections
+    #[cfg_attr(not(feature = "gossipsub"), allow(dead_code))]
+    mesh_outbound_fraction: Family<MeshTopicLabels, Gauge<f64, AtomicU64>>,
+    /// Per-protocol number of messages dropped for being over the maximum size of their protocol
+    oversized_messages: Family<ProtocolLabels, Counter>,
+    /// Per-peer number of upgrades of a relayed connection, by outcome
+    #[cfg_attr(not(feature = "dcutr"), allow(dead_code))]
+    hole_punches: Family<HolePunchLabels, Counter>,
     /// PeerId to slot number mapping
     peer_slots: Slots<PeerId>,
 }
@@ -111,6 +150,11 @@ impl Metrics {
         let peer_info = Family::<PeerInfoLabels, Gauge>::default();
         let mesh_membership = Family::<MeshMembershipLabels, Gauge>::default();
         let explicit_peers = Family::<ExplicitPeerLabels, Gauge>::default();
+        let mesh_size = Family::<MeshTopicLabels, Gauge>::default();
+        let mesh_churn = Family::<MeshTopicLabels, Counter>::default();
+        let mesh_outbound_fraction = Family::<MeshTopicLabels, Gauge<f64, AtomicU64>>::default();
+        let oversized_messages = Family::<ProtocolLabels, Counter>::default();
+        let hole_punches = Family::<HolePunchLabels, Counter>::default();
 
         registry.register(
             "local_node_info",
@@ -136,11 +180,46 @@ impl Metrics {
             explicit_peers.clone(),
         );
 
+        registry.register(
+            "mesh_size",
+            "Number of peers in the gossipsub mesh, per topic",
+            mesh_size.clone(),
+        );
+
+        registry.register(
+            "mesh_churn",
+            "Number of peers that joined or left the gossipsub mesh, per topic",
+            mesh_churn.clone(),
+        );
+
+        registry.register(
+            "mesh_outbound_fraction",
+            "Fraction of gossipsub mesh peers connected through outbound connections, per topic",
+            mesh_outbound_fraction.clone(),
+        );
+
+        registry.register(
+            "oversized_messages",
+            "Number of messages dropped for being larger than the maximum size of their protocol",
+            oversized_messages.clone(),
+        );
+
+        registry.register(
+            "hole_punches",
+            "Number of upgrades of a relayed connection into a direct one, per peer and outcome",
+            hole_punches.clone(),
+        );
+
         Self {
             local_node_info,
             discovered_peers: peer_info,
             peer_mesh_membership: mesh_membership,
             explicit_peers,
+            mesh_size,
+            mesh_churn,
+            mesh_outbound_fraction,
+            oversized_messages,
+            hole_punches,
             peer_slots: Slots::new(MAX_PEER_SLOTS),
         }
     }
@@ -164,6 +243,7 @@ impl Metrics {
     }
 
     /// Update a peer's score and mesh membership metrics
+    #[cfg_attr(not(feature = "gossipsub"), allow(dead_code))]
     pub(crate) fn update_peer_metrics(
         &mut self,
         peer_id: &PeerId,
@@ -236,7 +316,52 @@ impl Metrics {
         }
     }
 
+    /// Update the mesh composition metrics of a topic
+    #[cfg(feature = "gossipsub")]
+    pub(crate) fn update_mesh_metrics(
+        &self,
+        topic: &str,
+        size: usize,
+        outbound: usize,
+        churn: usize,
+    ) {
+        let labels = MeshTopicLabels {
+            topic: topic.to_string(),
+        };
+
+        let outbound_fraction = if size == 0 {
+            0.0
+        } else {
+            outbound as f64 / size as f64
+        };
+
+        self.mesh_size.get_or_create(&labels).set(size as i64);
+        self.mesh_churn.get_or_create(&labels).inc_by(churn as u64);
+        self.mesh_outbound_fraction
+            .get_or_create(&labels)
+            .set(outbound_fraction);
+    }
+
+    /// Record a message dropped for being over the maximum size of its protocol
+    pub(crate) fn record_oversized_message(&self, protocol: Protocol) {
+        let labels = ProtocolLabels {
+            protocol: protocol.as_str().to_string(),
+        };
+        self.oversized_messages.get_or_create(&labels).inc();
+    }
+
+    /// Record an attempt to upgrade the relayed connection to a peer, or its outcome
+    #[cfg(feature = "dcutr")]
+    pub(crate) fn record_hole_punch(&self, event: &HolePunchEvent) {
+        let labels = HolePunchLabels {
+            peer_id: event.peer_id().to_string(),
+            outcome: event.outcome().to_string(),
+        };
+        self.hole_punches.get_or_create(&labels).inc();
+    }
+
     /// Record a peer as an explicit peer in gossipsub
+    #[cfg(feature = "gossipsub")]
     pub(crate) fn record_explicit_peer(&self, peer_id: &PeerId, moniker: &str) {
         let labels = ExplicitPeerLabels {
             peer_id: peer_id.to_string(),
@@ -246,6 +371,7 @@ impl Metrics {
     }
 
     /// Mark an explicit peer as stale (disconnected)
+    #[cfg(feature = "gossipsub")]
     pub(crate) fn mark_explicit_peer_stale(&self, peer_id: &PeerId, moniker: &str) {
         let labels = ExplicitPeerLabels {
             peer_id: peer_id.to_string(),
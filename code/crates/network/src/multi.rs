@@ -0,0 +1,181 @@
+use std::task::{Context, Poll};
+
+use libp2p::core::transport::PortUse;
+use libp2p::core::Endpoint;
+use libp2p::swarm::handler::multi::MultiHandler;
+use libp2p::swarm::{
+    ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, PeerId};
+
+/// Several instances of the same behaviour, eg. one per chain served by the network,
+/// sharing the connections to the peers. Their events are tagged with their index.
+///
+/// The instances must use distinct protocol names.
+pub struct Multi<B> {
+    behaviours: Vec<B>,
+    /// Index of the instance polled first, rotated so that a busy one cannot starve the others
+    next_poll: usize,
+}
+
+impl<B> Multi<B> {
+    pub fn new(behaviours: Vec<B>) -> Self {
+        Self {
+            behaviours,
+            next_poll: 0,
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&B> {
+        self.behaviours.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut B> {
+        self.behaviours.get_mut(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.behaviours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.behaviours.is_empty()
+    }
+}
+
+impl<B> NetworkBehaviour for Multi<B>
+where
+    B: NetworkBehaviour,
+{
+    type ConnectionHandler = MultiHandler<usize, THandler<B>>;
+    type ToSwarm = (usize, B::ToSwarm);
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        for behaviour in &mut self.behaviours {
+            behaviour.handle_pending_inbound_connection(connection_id, local_addr, remote_addr)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handlers = self
+            .behaviours
+            .iter_mut()
+            .enumerate()
+            .map(|(index, behaviour)| {
+                behaviour
+                    .handle_established_inbound_connection(
+                        connection_id,
+                        peer,
+                        local_addr,
+                        remote_addr,
+                    )
+                    .map(|handler| (index, handler))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        MultiHandler::try_from_iter(handlers).map_err(ConnectionDenied::new)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        let mut extra_addresses = Vec::new();
+
+        for behaviour in &mut self.behaviours {
+            extra_addresses.extend(behaviour.handle_pending_outbound_connection(
+                connection_id,
+                maybe_peer,
+                addresses,
+                effective_role,
+            )?);
+        }
+
+        Ok(extra_addresses)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+        port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        let handlers = self
+            .behaviours
+            .iter_mut()
+            .enumerate()
+            .map(|(index, behaviour)| {
+                behaviour
+                    .handle_established_outbound_connection(
+                        connection_id,
+                        peer,
+                        addr,
+                        role_override,
+                        port_use,
+                    )
+                    .map(|handler| (index, handler))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        MultiHandler::try_from_iter(handlers).map_err(ConnectionDenied::new)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        for behaviour in &mut self.behaviours {
+            behaviour.on_swarm_event(event);
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        (index, event): THandlerOutEvent<Self>,
+    ) {
+        if let Some(behaviour) = self.behaviours.get_mut(index) {
+            behaviour.on_connection_handler_event(peer_id, connection_id, event);
+        }
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        let len = self.behaviours.len();
+
+        for offset in 0..len {
+            let index = (self.next_poll + offset) % len;
+
+            if let Poll::Ready(event) = self.behaviours[index].poll(cx) {
+                self.next_poll = (index + 1) % len;
+
+                return Poll::Ready(
+                    event
+                        .map_in(move |event| (index, event))
+                        .map_out(move |event| (index, event)),
+                );
+            }
+        }
+
+        Poll::Pending
+    }
+}
@@ -0,0 +1,269 @@
+//! Lifecycle of the key the node is identified with on the network, ie. of its `PeerId`.
+//!
+//! The key is generated on the first start of the node and persisted in the node key file,
+//! see `Config::node_key_file`, so that the node keeps its `PeerId` across restarts.
+//!
+//! It is rotated on request of the operator, see `CtrlMsg::RotateNodeKey`: the new key replaces
+//! the one in the file, the key of the running node being kept in the previous key file, and
+//! only takes effect once the node is restarted, as the `PeerId` of a running node cannot change.
+//! Meanwhile, the new `PeerId` is announced to the connected persistent peers, which replace the
+//! old one in the address they dial the node at, so that they reconnect to the node once it is
+//! restarted with its new key. The announcement is signed with the new key over the old `PeerId`,
+//! proving that the node holds the new key.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::{PeerId, StreamProtocol};
+use serde::Serialize;
+use tracing::info;
+
+use crate::peer_message;
+
+/// Maximum size of an announcement, well above the size of an Ed25519 or ECDSA public key
+/// along with its signature
+const MAX_ANNOUNCEMENT_SIZE: usize = 1024;
+
+/// Prefix of the bytes signed by an announcement, so that the signature cannot be replayed
+/// as the signature of another message
+const ANNOUNCEMENT_DOMAIN: &[u8] = b"malachitebft-node-key-rotation";
+
+/// Extension of the previous key file, appended to the name of the node key file
+const PREVIOUS_KEY_EXTENSION: &str = "prev";
+
+/// Announcements of a rotation of the node key, replied to with an empty message
+pub type Behaviour = peer_message::Behaviour;
+pub type Event = peer_message::Event;
+
+pub(crate) fn new_behaviour(protocol: StreamProtocol) -> Behaviour {
+    peer_message::new_behaviour(protocol, MAX_ANNOUNCEMENT_SIZE)
+}
+
+/// Errors that can occur when loading or rotating the node key
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NodeKeyError {
+    /// The node key is not persisted, so it cannot be rotated
+    #[error("No node key file configured")]
+    NotConfigured,
+    #[error("Failed to read the node key from {}: {reason}", .path.display())]
+    Read { path: PathBuf, reason: String },
+    #[error("Failed to write the node key to {}: {reason}", .path.display())]
+    Write { path: PathBuf, reason: String },
+    #[error("Invalid node key in {}: {reason}", .path.display())]
+    Invalid { path: PathBuf, reason: String },
+    /// Network is not started
+    #[error("Network not started")]
+    NetworkStopped,
+    /// Internal error
+    #[error("Internal error: {0}")]
+    InternalError(String),
+}
+
+/// `PeerId` of the node, along with the one it takes once restarted after a rotation of its key
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct NodeKeyStatus {
+    pub peer_id: PeerId,
+    /// `PeerId` of the rotated key, `None` if the key was not rotated since the node started
+    pub next_peer_id: Option<PeerId>,
+}
+
+/// Outcome of a rotation of the node key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeKeyRotation {
+    pub peer_id: PeerId,
+    /// `PeerId` of the new key, taking effect once the node is restarted
+    pub next_peer_id: PeerId,
+    /// Connected persistent peers the new `PeerId` was announced to
+    pub announced_to: Vec<PeerId>,
+}
+
+/// Load the node key from the given file, generating and persisting a new one on first start
+pub fn load_or_generate(path: &Path) -> Result<Keypair, NodeKeyError> {
+    match fs::read(path) {
+        Ok(bytes) => decode(path, &bytes),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let keypair = Keypair::generate_ed25519();
+            write(path, &keypair)?;
+
+            let peer_id = keypair.public().to_peer_id();
+            info!(%peer_id, path = %path.display(), "Generated the node key");
+
+            Ok(keypair)
+        }
+        Err(e) => Err(NodeKeyError::Read {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+/// Replace the node key in the given file with a new one, returned to be announced to the peers.
+///
+/// The key of the running node, with the given `PeerId`, is kept in the previous key file,
+/// see [`previous_key_file`], to restore it if need be. Rotating the key again before the
+/// node is restarted only replaces the new key.
+pub fn rotate(path: &Path, running: &PeerId) -> Result<Keypair, NodeKeyError> {
+    let bytes = fs::read(path).map_err(|e| NodeKeyError::Read {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+
+    let current = decode(path, &bytes)?;
+
+    if current.public().to_peer_id() == *running {
+        write(&previous_key_file(path), &current)?;
+    }
+
+    let next = Keypair::generate_ed25519();
+    write(path, &next)?;
+
+    Ok(next)
+}
+
+/// File keeping the key of the node before its last rotation, next to the node key file
+pub fn previous_key_file(path: &Path) -> PathBuf {
+    let mut file = path.as_os_str().to_owned();
+    file.push(".");
+    file.push(PREVIOUS_KEY_EXTENSION);
+    PathBuf::from(file)
+}
+
+fn decode(path: &Path, bytes: &[u8]) -> Result<Keypair, NodeKeyError> {
+    Keypair::from_protobuf_encoding(bytes).map_err(|e| NodeKeyError::Invalid {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })
+}
+
+/// Write the key to a temporary file only readable by its owner,
+/// then move it over the given file, which is thus never left half written
+fn write(path: &Path, keypair: &Keypair) -> Result<(), NodeKeyError> {
+    let write_err = |e: io::Error| NodeKeyError::Write {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    };
+
+    let bytes = keypair
+        .to_protobuf_encoding()
+        .map_err(|e| NodeKeyError::InternalError(e.to_string()))?;
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(write_err)?;
+    }
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&tmp).map_err(write_err)?;
+    file.write_all(&bytes).map_err(write_err)?;
+    file.sync_all().map_err(write_err)?;
+
+    fs::rename(&tmp, path).map_err(write_err)
+}
+
+/// Announcement of the `PeerId` of the given new key to the peers connected to the node
+/// with the given current `PeerId`: the new public key, prefixed with its length on 2 bytes
+/// (big-endian), followed by its signature over the current `PeerId`
+pub(crate) fn announcement(next: &Keypair, current: &PeerId) -> Result<Bytes, NodeKeyError> {
+    let public_key = next.public().encode_protobuf();
+    let signature = next
+        .sign(&signed_bytes(current))
+        .map_err(|e| NodeKeyError::InternalError(e.to_string()))?;
+
+    let mut buf = BytesMut::with_capacity(2 + public_key.len() + signature.len());
+    buf.put_u16(public_key.len() as u16);
+    buf.put_slice(&public_key);
+    buf.put_slice(&signature);
+
+    Ok(buf.freeze())
+}
+
+/// New `PeerId` announced by the peer with the given `PeerId`,
+/// or `None` if the announcement is malformed or its signature invalid
+pub(crate) fn verify_announcement(from: &PeerId, mut data: Bytes) -> Option<PeerId> {
+    if data.remaining() < 2 {
+        return None;
+    }
+
+    let len = data.get_u16() as usize;
+    if data.remaining() < len {
+        return None;
+    }
+
+    let public_key = PublicKey::try_decode_protobuf(&data.split_to(len)).ok()?;
+
+    if !public_key.verify(&signed_bytes(from), &data) {
+        return None;
+    }
+
+    let peer_id = public_key.to_peer_id();
+    (peer_id != *from).then_some(peer_id)
+}
+
+fn signed_bytes(peer_id: &PeerId) -> Vec<u8> {
+    [ANNOUNCEMENT_DOMAIN, &peer_id.to_bytes()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_persist_and_rotate() {
+        let dir = std::env::temp_dir().join(format!("node-key-{}", rand::random::<u64>()));
+        let path = dir.join("config").join("node_key");
+
+        let key = load_or_generate(&path).unwrap();
+        let peer_id = key.public().to_peer_id();
+        assert_eq!(load_or_generate(&path).unwrap().public(), key.public());
+
+        let next = rotate(&path, &peer_id).unwrap();
+        let next_peer_id = next.public().to_peer_id();
+        assert_ne!(next_peer_id, peer_id);
+        assert_eq!(load_or_generate(&path).unwrap().public(), next.public());
+
+        // Rotating again before restarting keeps the key of the running node
+        rotate(&path, &peer_id).unwrap();
+        let previous = fs::read(previous_key_file(&path)).unwrap();
+        assert_eq!(decode(&path, &previous).unwrap().public(), key.public());
+
+        fs::write(&path, b"not a key").unwrap();
+        assert!(matches!(
+            load_or_generate(&path),
+            Err(NodeKeyError::Invalid { .. })
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn announcement_is_signed_with_the_new_key() {
+        let (current, next) = (Keypair::generate_ed25519(), Keypair::generate_ed25519());
+        let (current, next_peer_id) = (current.public().to_peer_id(), next.public().to_peer_id());
+
+        let data = announcement(&next, &current).unwrap();
+        assert_eq!(
+            verify_announcement(&current, data.clone()),
+            Some(next_peer_id)
+        );
+
+        // Replayed by another peer
+        assert_eq!(verify_announcement(&PeerId::random(), data.clone()), None);
+
+        // Truncated
+        assert_eq!(verify_announcement(&current, data.slice(..10)), None);
+    }
+}
@@ -0,0 +1,118 @@
+//! Peer allowlist behaviour.
+//!
+//! In permissioned networks, only a known set of peers is allowed to take part
+//! in the network. This behaviour denies any connection, inbound or outbound,
+//! to a peer which is not in the allowlist as soon as its PeerId is known,
+//! ie. right after the security handshake, before any protocol runs on it.
+//!
+//! Outbound dials to a known PeerId that is not allowed are denied before dialing.
+
+use std::collections::HashSet;
+use std::task::{Context, Poll};
+
+use libp2p::core::transport::PortUse;
+use libp2p::core::Endpoint;
+use libp2p::swarm::{
+    ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, PeerId};
+use tracing::debug;
+
+/// Behaviour that denies connections to peers not in the allowlist.
+pub struct Behaviour {
+    allowed_peers: HashSet<PeerId>,
+}
+
+impl Behaviour {
+    /// Create a new allowlist behaviour.
+    pub fn new(allowed_peers: HashSet<PeerId>) -> Self {
+        Self { allowed_peers }
+    }
+
+    fn check(&self, peer_id: &PeerId) -> Result<(), ConnectionDenied> {
+        if self.allowed_peers.contains(peer_id) {
+            return Ok(());
+        }
+
+        debug!(%peer_id, "Denying connection: peer not in allowlist");
+        Err(ConnectionDenied::new(PeerNotAllowed { peer_id: *peer_id }))
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = libp2p::swarm::dummy::ConnectionHandler;
+    type ToSwarm = std::convert::Infallible;
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _addresses: &[Multiaddr],
+        _effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        // The PeerId is not always known before dialing, eg. for bootstrap nodes
+        // configured without one. Such connections are checked once established.
+        if let Some(peer_id) = maybe_peer {
+            self.check(&peer_id)?;
+        }
+
+        Ok(vec![])
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        peer: PeerId,
+        _local_addr: &Multiaddr,
+        _remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check(&peer)?;
+        Ok(libp2p::swarm::dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        peer: PeerId,
+        _addr: &Multiaddr,
+        _role_override: Endpoint,
+        _port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.check(&peer)?;
+        Ok(libp2p::swarm::dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, _event: FromSwarm<'_>) {}
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: PeerId,
+        _connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        // dummy::ConnectionHandler produces no events
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        Poll::Pending
+    }
+}
+
+/// Error returned when a peer is not in the allowlist.
+#[derive(Debug)]
+struct PeerNotAllowed {
+    peer_id: PeerId,
+}
+
+impl std::fmt::Display for PeerNotAllowed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "peer {} is not in the allowlist", self.peer_id)
+    }
+}
+
+impl std::error::Error for PeerNotAllowed {}
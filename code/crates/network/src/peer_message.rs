@@ -0,0 +1,135 @@
+//! Request/response protocol for the messages sent by the application directly to a peer,
+//! eg. to fetch transactions or data-availability samples, alongside the pubsub channels.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use libp2p::futures::{io, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use libp2p::request_response::{self as rpc, OutboundFailure, ProtocolSupport};
+use libp2p::StreamProtocol;
+
+pub type Behaviour = rpc::Behaviour<Codec>;
+pub type Event = rpc::Event<Bytes, Bytes>;
+
+pub fn new_behaviour(protocol: StreamProtocol, max_size: usize) -> Behaviour {
+    rpc::Behaviour::with_codec(
+        Codec { max_size },
+        [(protocol, ProtocolSupport::Full)],
+        rpc::Config::default(),
+    )
+}
+
+/// Errors returned to the sender of a message to a peer
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PeerMessageError {
+    #[error("Failed to dial the peer")]
+    DialFailure,
+    #[error("Timed out waiting for the reply of the peer")]
+    Timeout,
+    #[error("Connection to the peer closed before it replied")]
+    ConnectionClosed,
+    #[error("The peer does not support direct messages")]
+    UnsupportedProtocols,
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("Network not started")]
+    NetworkStopped,
+}
+
+impl From<OutboundFailure> for PeerMessageError {
+    fn from(failure: OutboundFailure) -> Self {
+        match failure {
+            OutboundFailure::DialFailure => Self::DialFailure,
+            OutboundFailure::Timeout => Self::Timeout,
+            OutboundFailure::ConnectionClosed => Self::ConnectionClosed,
+            OutboundFailure::UnsupportedProtocols => Self::UnsupportedProtocols,
+            OutboundFailure::Io(e) => Self::Io(e.to_string()),
+        }
+    }
+}
+
+/// Length-prefixed messages and replies of at most `max_size` bytes
+#[derive(Copy, Clone)]
+pub struct Codec {
+    max_size: usize,
+}
+
+#[async_trait]
+impl rpc::Codec for Codec {
+    type Protocol = StreamProtocol;
+    type Request = Bytes;
+    type Response = Bytes;
+
+    async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Bytes>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_length_prefixed(io, self.max_size).await
+    }
+
+    async fn read_response<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Bytes>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_length_prefixed(io, self.max_size).await
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+        req: Bytes,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, &req, self.max_size).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+        res: Bytes,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, &res, self.max_size).await
+    }
+}
+
+async fn write_length_prefixed<T>(dst: &mut T, data: &[u8], max_size: usize) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin + Send,
+{
+    if data.len() > max_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "message too large",
+        ));
+    }
+
+    dst.write_all(&(data.len() as u32).to_be_bytes()).await?;
+    dst.write_all(data).await?;
+    dst.flush().await
+}
+
+async fn read_length_prefixed<T>(src: &mut T, max_size: usize) -> io::Result<Bytes>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    let mut len_bytes = [0u8; size_of::<u32>()];
+    src.read_exact(&mut len_bytes).await?;
+
+    let len = u32::from_be_bytes(len_bytes) as usize;
+    if len > max_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too large",
+        ));
+    }
+
+    let mut data = vec![0u8; len];
+    src.read_exact(&mut data).await?;
+    Ok(Bytes::from(data))
+}
@@ -20,16 +20,18 @@
 //!
 //! The final peer score is: `app_specific_score × app_specific_weight`
 //!
-//! Note: We currently only use application-specific scoring based on peer type.
-//! Topic-specific behavioral scoring (penalties for invalid messages, mesh misbehavior, etc.)
-//! is not enabled.
-//! Enabling topic-specific scoring will be done in a future release. This requires passing message
-//! validation results from the application (malachite engine) layer to the network layer.
+//! Note: Topic-specific behavioral scoring (penalties for invalid messages, mesh misbehavior, etc.)
+//! is not enabled. Instead, the application (malachite engine) layer reports misbehaving peers,
+//! eg. peers delivering invalid proposals or votes, with a score delta added to their
+//! application-specific score (see `ReportPeer` below).
 //!
 //! ## Score Updates
 //!
 //! - On connection: Peer receives `UNKNOWN_PEER_SCORE = 0.0` to allow initial mesh formation
 //! - After Identify: Score upgraded based on peer type (currently persistent peer or full node)
+//! - On `ReportPeer`: The reported delta is added to the score of the peer. Reported deltas
+//!   decay towards zero by `reported_score_decay` every second, so that a peer is forgiven over time.
+//!   A peer whose score becomes negative is pruned from the mesh on the next heartbeat.
 //!
 //! ## Opportunistic Grafting
 //!
@@ -53,6 +55,7 @@
 //! Full nodes remain functional (can publish and receive gossip) but are aggressively replaced in
 //! the mesh by higher scored peers through continuous opportunistic grafting.
 
+#[cfg(feature = "gossipsub")]
 use libp2p::gossipsub;
 
 use crate::PeerType;
@@ -78,15 +81,19 @@ pub const FULL_NODE_SCORE: f64 = 4.0;
 /// Set to 0.0 to allow initial mesh formation without blocking on peer type detection.
 pub const UNKNOWN_PEER_SCORE: f64 = 0.0;
 
+/// Score delta reported for a peer sending a message rejected by the application,
+/// see `MessageAcceptance::Reject`.
+pub const INVALID_MESSAGE_SCORE: f64 = -10.0;
+
 /// Scoring Parameters
 ///
-/// Weight multiplier for application-specific scores.
+/// Default weight multiplier for application-specific scores.
 ///
 /// This amplifies the difference between nodes based on their type,
 /// ensuring clear prioritization in opportunistic grafting.
 const APP_SPECIFIC_WEIGHT: f64 = 100.0;
 
-/// Threshold for opportunistic grafting.
+/// Default threshold for opportunistic grafting.
 ///
 /// Opportunistic grafting triggers when the **median score** of all mesh peers falls below this
 /// threshold. It then grafts high-scoring peers (above the median) to improve mesh quality.
@@ -130,12 +137,61 @@ pub fn get_peer_score(peer_type: PeerType) -> f64 {
     }
 }
 
+/// GossipSub v1.1 peer scoring parameters and thresholds
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PeerScoreConfig {
+    /// Weight multiplier for application-specific scores
+    pub app_specific_weight: f64,
+    /// Weight of the penalty for too many peers sharing the same IP, should be negative
+    pub ip_colocation_factor_weight: f64,
+    /// Number of peers sharing the same IP above which the colocation penalty applies
+    pub ip_colocation_factor_threshold: f64,
+    /// Weight of the penalty for misbehaviour in the protocol (eg. broken gossip promises),
+    /// should be negative
+    pub behaviour_penalty_weight: f64,
+    /// Decay of the behaviour penalty at each decay interval
+    pub behaviour_penalty_decay: f64,
+    /// Peers below this score do not receive gossip
+    pub gossip_threshold: f64,
+    /// Peers below this score are not published to
+    pub publish_threshold: f64,
+    /// Messages from peers below this score are ignored
+    pub graylist_threshold: f64,
+    /// Opportunistic grafting is triggered when the median score of the mesh is below this value
+    pub opportunistic_graft_threshold: f64,
+    /// Factor by which the scores reported by the application are multiplied every second,
+    /// between 0 (forgotten right away) and 1 (never forgotten)
+    pub reported_score_decay: f64,
+}
+
+impl Default for PeerScoreConfig {
+    fn default() -> Self {
+        Self {
+            app_specific_weight: APP_SPECIFIC_WEIGHT,
+            ip_colocation_factor_weight: -5.0,
+            ip_colocation_factor_threshold: 10.0,
+            behaviour_penalty_weight: -10.0,
+            behaviour_penalty_decay: 0.2,
+            gossip_threshold: -500.0,
+            publish_threshold: -1000.0,
+            graylist_threshold: -2000.0,
+            opportunistic_graft_threshold: OPPORTUNISTIC_GRAFT_THRESHOLD,
+            reported_score_decay: 0.99,
+        }
+    }
+}
+
 /// Constructs the peer score parameters for GossipSub.
 ///
 /// Configures application-specific scoring with a weight multiplier to amplify score differences.
-pub fn peer_score_params() -> gossipsub::PeerScoreParams {
+#[cfg(feature = "gossipsub")]
+pub fn peer_score_params(config: &PeerScoreConfig) -> gossipsub::PeerScoreParams {
     gossipsub::PeerScoreParams {
-        app_specific_weight: APP_SPECIFIC_WEIGHT,
+        app_specific_weight: config.app_specific_weight,
+        ip_colocation_factor_weight: config.ip_colocation_factor_weight,
+        ip_colocation_factor_threshold: config.ip_colocation_factor_threshold,
+        behaviour_penalty_weight: config.behaviour_penalty_weight,
+        behaviour_penalty_decay: config.behaviour_penalty_decay,
         ..Default::default()
     }
 }
@@ -147,12 +203,13 @@ pub fn peer_score_params() -> gossipsub::PeerScoreParams {
 /// - `gossip_threshold`: Peers below this don't receive gossip
 /// - `publish_threshold`: Peers below this can't publish messages
 /// - `graylist_threshold`: Peers below this are completely ignored
-pub fn peer_score_thresholds() -> gossipsub::PeerScoreThresholds {
+#[cfg(feature = "gossipsub")]
+pub fn peer_score_thresholds(config: &PeerScoreConfig) -> gossipsub::PeerScoreThresholds {
     gossipsub::PeerScoreThresholds {
-        opportunistic_graft_threshold: OPPORTUNISTIC_GRAFT_THRESHOLD,
-        gossip_threshold: -500.0,
-        publish_threshold: -1000.0,
-        graylist_threshold: -2000.0,
+        opportunistic_graft_threshold: config.opportunistic_graft_threshold,
+        gossip_threshold: config.gossip_threshold,
+        publish_threshold: config.publish_threshold,
+        graylist_threshold: config.graylist_threshold,
         ..Default::default()
     }
 }
@@ -1,29 +1,47 @@
 use bytes::Bytes;
 use libp2p::swarm;
 
-use crate::behaviour::Behaviour;
-use crate::{Channel, ChannelNames, PeerIdExt, PubSubProtocol};
+use crate::behaviour::DefaultBehaviour;
+use crate::compression::{self, CompressionConfig};
+#[cfg(feature = "gossipsub")]
+use crate::PeerIdExt;
+use crate::{Channel, ChannelNames, PubSubProtocol};
 
 pub fn subscribe(
-    swarm: &mut swarm::Swarm<Behaviour>,
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
     protocol: PubSubProtocol,
     channels: &[Channel],
+    namespace: &str,
     channel_names: ChannelNames,
+    compression: CompressionConfig,
 ) -> Result<(), eyre::Report> {
     match protocol {
+        #[cfg(feature = "gossipsub")]
         PubSubProtocol::GossipSub => {
             if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
                 for channel in channels {
-                    gossipsub.subscribe(&channel.to_gossipsub_topic(channel_names))?;
+                    gossipsub.subscribe(&channel.to_gossipsub_topic(
+                        namespace,
+                        channel_names,
+                        compression,
+                    ))?;
                 }
             } else {
                 return Err(eyre::eyre!("GossipSub not enabled"));
             }
         }
+        #[cfg(not(feature = "gossipsub"))]
+        PubSubProtocol::GossipSub => {
+            return Err(eyre::eyre!("GossipSub not supported by this build"));
+        }
         PubSubProtocol::Broadcast => {
             if let Some(broadcast) = swarm.behaviour_mut().broadcast.as_mut() {
                 for channel in channels {
-                    broadcast.subscribe(channel.to_broadcast_topic(channel_names));
+                    broadcast.subscribe(channel.to_broadcast_topic(
+                        namespace,
+                        channel_names,
+                        compression,
+                    ));
                 }
             } else {
                 return Err(eyre::eyre!("Broadcast not enabled"));
@@ -34,24 +52,45 @@ pub fn subscribe(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn publish(
-    swarm: &mut swarm::Swarm<Behaviour>,
+    swarm: &mut swarm::Swarm<DefaultBehaviour>,
     protocol: PubSubProtocol,
     channel: Channel,
+    namespace: &str,
     channel_names: ChannelNames,
+    compression: CompressionConfig,
+    max_size: usize,
     data: Bytes,
 ) -> Result<(), eyre::Report> {
+    let data = if compression.is_enabled(channel) {
+        compression::compress(&data, max_size)?
+    } else {
+        data
+    };
+
     match protocol {
+        #[cfg(feature = "gossipsub")]
         PubSubProtocol::GossipSub => {
             if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
-                gossipsub.publish(channel.to_gossipsub_topic(channel_names), data)?;
+                gossipsub.publish(
+                    channel.to_gossipsub_topic(namespace, channel_names, compression),
+                    data,
+                )?;
             } else {
                 return Err(eyre::eyre!("GossipSub not enabled"));
             }
         }
+        #[cfg(not(feature = "gossipsub"))]
+        PubSubProtocol::GossipSub => {
+            return Err(eyre::eyre!("GossipSub not supported by this build"));
+        }
         PubSubProtocol::Broadcast => {
             if let Some(broadcast) = swarm.behaviour_mut().broadcast.as_mut() {
-                broadcast.broadcast(&channel.to_broadcast_topic(channel_names), data);
+                broadcast.broadcast(
+                    &channel.to_broadcast_topic(namespace, channel_names, compression),
+                    data,
+                );
             } else {
                 return Err(eyre::eyre!("Broadcast not enabled"));
             }
@@ -61,14 +100,17 @@ pub fn publish(
     Ok(())
 }
 
-/// Get the mesh peers for a specific channel
+/// Get the mesh peers for a specific channel of the chain with the given namespace
+#[cfg(feature = "gossipsub")]
 pub fn get_mesh_peers(
-    swarm: &swarm::Swarm<Behaviour>,
+    swarm: &swarm::Swarm<DefaultBehaviour>,
     channel: Channel,
+    namespace: &str,
     channel_names: ChannelNames,
+    compression: CompressionConfig,
 ) -> Vec<crate::PeerId> {
     if let Some(gossipsub) = swarm.behaviour().gossipsub.as_ref() {
-        let topic = channel.to_gossipsub_topic(channel_names);
+        let topic = channel.to_gossipsub_topic(namespace, channel_names, compression);
         let topic_hash = topic.hash();
         gossipsub
             .mesh_peers(&topic_hash)
@@ -78,3 +120,15 @@ pub fn get_mesh_peers(
         Vec::new()
     }
 }
+
+/// Get the mesh peers for a specific channel, always empty without GossipSub support
+#[cfg(not(feature = "gossipsub"))]
+pub fn get_mesh_peers(
+    _swarm: &swarm::Swarm<DefaultBehaviour>,
+    _channel: Channel,
+    _namespace: &str,
+    _channel_names: ChannelNames,
+    _compression: CompressionConfig,
+) -> Vec<crate::PeerId> {
+    Vec::new()
+}
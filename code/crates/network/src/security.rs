@@ -0,0 +1,154 @@
+use std::iter;
+
+use futures::future::{self, BoxFuture};
+use futures::{AsyncRead, AsyncWrite, FutureExt, TryFutureExt};
+use libp2p::core::upgrade::{InboundConnectionUpgrade, OutboundConnectionUpgrade, UpgradeInfo};
+use libp2p::{noise, tls, PeerId};
+
+use crate::Keypair;
+
+const NOISE_PROTOCOL: &str = "/noise";
+const TLS_PROTOCOL: &str = "/tls/1.0.0";
+
+/// Security protocol of the TCP and WebSocket connections.
+///
+/// QUIC connections are always secured with TLS 1.3.
+/// The TLS certificates are self-signed and bind the peer ID of the node, as required by libp2p,
+/// operator-provided certificates are only supported by the WebSocket transport (`/wss`).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SecurityProtocol {
+    #[default]
+    Noise,
+    /// TLS 1.3
+    Tls,
+    /// Both Noise and TLS 1.3, negotiated with each peer, Noise being preferred when dialing
+    NoiseAndTls,
+}
+
+/// Upgrade securing a connection with Noise, TLS 1.3 or either of them
+#[derive(Clone)]
+pub(crate) struct SecurityUpgrade {
+    noise: Option<noise::Config>,
+    tls: Option<tls::Config>,
+}
+
+impl SecurityUpgrade {
+    pub(crate) fn new(keypair: &Keypair, protocol: SecurityProtocol) -> Result<Self, eyre::Report> {
+        let noise = match protocol {
+            SecurityProtocol::Noise | SecurityProtocol::NoiseAndTls => {
+                Some(noise::Config::new(keypair)?)
+            }
+            SecurityProtocol::Tls => None,
+        };
+
+        let tls = match protocol {
+            SecurityProtocol::Tls | SecurityProtocol::NoiseAndTls => {
+                Some(tls::Config::new(keypair)?)
+            }
+            SecurityProtocol::Noise => None,
+        };
+
+        Ok(Self { noise, tls })
+    }
+}
+
+/// Connection secured with either Noise or TLS 1.3
+pub(crate) type SecureStream<C> = future::Either<noise::Output<C>, tls::TlsStream<C>>;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum SecurityError {
+    #[error("Noise handshake failed: {0}")]
+    Noise(#[from] noise::Error),
+    #[error("TLS handshake failed: {0}")]
+    Tls(#[from] tls::UpgradeError),
+    #[error("Unsupported security protocol: {0}")]
+    Unsupported(&'static str),
+}
+
+impl UpgradeInfo for SecurityUpgrade {
+    type Info = &'static str;
+    type InfoIter = iter::Flatten<std::array::IntoIter<Option<&'static str>, 2>>;
+
+    fn protocol_info(&self) -> Self::InfoIter {
+        [
+            self.noise.as_ref().map(|_| NOISE_PROTOCOL),
+            self.tls.as_ref().map(|_| TLS_PROTOCOL),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+impl<C> InboundConnectionUpgrade<C> for SecurityUpgrade
+where
+    C: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = (PeerId, SecureStream<C>);
+    type Error = SecurityError;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_inbound(self, socket: C, info: Self::Info) -> Self::Future {
+        match (info, self.noise, self.tls) {
+            (NOISE_PROTOCOL, Some(noise), _) => noise
+                .upgrade_inbound(socket, info)
+                .map_ok(|(peer_id, stream)| (peer_id, future::Either::Left(stream)))
+                .err_into()
+                .boxed(),
+            (TLS_PROTOCOL, _, Some(tls)) => tls
+                .upgrade_inbound(socket, info)
+                .map_ok(|(peer_id, stream)| (peer_id, future::Either::Right(stream)))
+                .err_into()
+                .boxed(),
+            _ => future::err(SecurityError::Unsupported(info)).boxed(),
+        }
+    }
+}
+
+impl<C> OutboundConnectionUpgrade<C> for SecurityUpgrade
+where
+    C: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = (PeerId, SecureStream<C>);
+    type Error = SecurityError;
+    type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn upgrade_outbound(self, socket: C, info: Self::Info) -> Self::Future {
+        match (info, self.noise, self.tls) {
+            (NOISE_PROTOCOL, Some(noise), _) => noise
+                .upgrade_outbound(socket, info)
+                .map_ok(|(peer_id, stream)| (peer_id, future::Either::Left(stream)))
+                .err_into()
+                .boxed(),
+            (TLS_PROTOCOL, _, Some(tls)) => tls
+                .upgrade_outbound(socket, info)
+                .map_ok(|(peer_id, stream)| (peer_id, future::Either::Right(stream)))
+                .err_into()
+                .boxed(),
+            _ => future::err(SecurityError::Unsupported(info)).boxed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_info() {
+        let keypair = Keypair::generate_ed25519();
+
+        let protocols = |protocol| {
+            SecurityUpgrade::new(&keypair, protocol)
+                .unwrap()
+                .protocol_info()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(protocols(SecurityProtocol::Noise), [NOISE_PROTOCOL]);
+        assert_eq!(protocols(SecurityProtocol::Tls), [TLS_PROTOCOL]);
+        assert_eq!(
+            protocols(SecurityProtocol::NoiseAndTls),
+            [NOISE_PROTOCOL, TLS_PROTOCOL]
+        );
+    }
+}
@@ -1,18 +1,31 @@
 //! Network state management
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
+use std::time::{Duration, Instant};
 
+use bytes::Bytes;
+use libp2p::core::transport::ListenerId;
 use libp2p::identify;
-use libp2p::request_response::InboundRequestId;
+use libp2p::request_response::{InboundRequestId, OutboundRequestId, ResponseChannel};
 use libp2p::Multiaddr;
 use malachitebft_discovery as discovery;
 use malachitebft_discovery::util::strip_peer_id_from_multiaddr;
 use malachitebft_sync as sync;
-
-use crate::behaviour::Behaviour;
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use crate::addr_monitor::AddressMonitor;
+use crate::bandwidth::BandwidthMeter;
+use crate::behaviour::DefaultBehaviour;
+use crate::duplicates::DuplicateTracker;
+use crate::link::LinkConditions;
+use crate::liveness::Liveness;
+use crate::mempool::MempoolLimiter;
 use crate::metrics::Metrics as NetworkMetrics;
-use crate::{Channel, ChannelNames, PeerType, PersistentPeerError};
+#[cfg(feature = "gossipsub")]
+use crate::{Channel, ChannelNames, CompressionConfig};
+use crate::{Config, Event, MessageId, PeerMessageError, PeerType, PersistentPeerError};
 use malachitebft_discovery::ConnectionDirection;
 
 /// Public network state dump for external consumers
@@ -23,6 +36,44 @@ pub struct NetworkStateDump {
     pub validator_set: Vec<ValidatorInfo>,
     pub persistent_peer_ids: Vec<libp2p::PeerId>,
     pub persistent_peer_addrs: Vec<Multiaddr>,
+    /// Peers in the gossipsub mesh, per topic
+    pub mesh: BTreeMap<String, Vec<libp2p::PeerId>>,
+}
+
+/// Local view of the graph of the network, serializable eg. to JSON to draw it offline
+#[derive(Clone, Debug, Serialize)]
+pub struct NetworkTopology {
+    pub peer_id: libp2p::PeerId,
+    pub moniker: String,
+    /// Peers in the gossipsub mesh, per topic
+    pub mesh: BTreeMap<String, Vec<libp2p::PeerId>>,
+    /// Peers by kind, relay circuits and Kademlia routing table, as seen by discovery
+    pub discovery: discovery::DiscoveryTopology,
+}
+
+/// Snapshot of a peer this node is connected to
+#[derive(Clone, Debug)]
+pub struct ConnectedPeer {
+    pub peer_id: libp2p::PeerId,
+    /// Moniker of the peer, `None` until it identified itself
+    pub moniker: Option<String>,
+    /// Whether the peer is an outbound, inbound or ephemeral peer
+    pub kind: discovery::PeerKind,
+    /// Remote addresses of the active connections to the peer
+    pub addresses: Vec<Multiaddr>,
+    /// Listen addresses advertised by the peer via Identify
+    pub listen_addrs: Vec<Multiaddr>,
+    /// Protocols supported by the peer, sorted, empty until it identified itself
+    pub protocols: Vec<String>,
+    /// Peer type (validator, persistent, full node), `None` until it identified itself
+    pub peer_type: Option<PeerType>,
+    /// Application-specific score of the peer, including the scores reported by the application
+    pub application_score: f64,
+    /// Overall GossipSub score of the peer, `None` if GossipSub is disabled
+    pub gossipsub_score: Option<f64>,
+    /// Number of pubsub messages received from the peer whose payload was already received,
+    /// many of them pointing at a misconfigured mesh
+    pub duplicate_messages: u64,
 }
 
 /// Validator information passed from consensus to network layer
@@ -112,6 +163,10 @@ pub struct PeerInfo {
     /// Set when a valid proof is received, regardless of validator set membership.
     /// Used to re-evaluate validator status when the validator set changes.
     pub consensus_public_key: Option<Vec<u8>>,
+    /// Public key replaced by the one of a newer proof, which the validator sends once it has
+    /// rotated its consensus key, before this node has the validator set including the new key.
+    /// Still matched against the validator set until the new key is in it.
+    pub previous_consensus_public_key: Option<Vec<u8>>,
     /// Peer type (validator, persistent, full node)
     pub peer_type: PeerType,
     /// Connection direction (outbound or inbound), None if ephemeral
@@ -148,10 +203,26 @@ impl PeerInfo {
     }
 }
 
+/// Reported scores closer to zero than this are forgotten
+const MIN_REPORTED_SCORE: f64 = 0.01;
+
+/// Messages not validated by the application within this delay are forgotten
+const MESSAGE_VALIDATION_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug)]
 pub struct State {
-    pub sync_channels: HashMap<InboundRequestId, sync::ResponseChannel>,
-    pub discovery: discovery::Discovery<Behaviour>,
+    /// Response channels of the inbound Sync requests, along with the requesting peer,
+    /// keyed by the index of the chain they were received for and their ID in that chain
+    pub sync_channels: HashMap<(usize, InboundRequestId), (libp2p::PeerId, sync::ResponseChannel)>,
+    /// Response channels of the inbound direct messages, along with the sending peer,
+    /// keyed like `sync_channels`
+    pub peer_message_channels:
+        HashMap<(usize, InboundRequestId), (libp2p::PeerId, ResponseChannel<Bytes>)>,
+    /// Senders of the replies to the outbound direct messages,
+    /// keyed by the index of the chain they were sent for and their ID in that chain
+    pub peer_message_replies:
+        HashMap<(usize, OutboundRequestId), oneshot::Sender<Result<Bytes, PeerMessageError>>>,
+    pub discovery: discovery::Discovery<DefaultBehaviour>,
     pub persistent_peer_ids: HashSet<libp2p::PeerId>,
     pub persistent_peer_addrs: Vec<Multiaddr>,
     /// Latest validator set from consensus
@@ -169,6 +240,32 @@ pub struct State {
     /// If proof verification completes before Identify, we buffer the public_key here
     /// and apply it when Identify completes and creates the PeerInfo.
     pub(crate) pending_verified_proofs: HashMap<libp2p::PeerId, Vec<u8>>,
+    /// Tracks changes to our own listen addresses and externally observed IP
+    pub(crate) addr_monitor: AddressMonitor,
+    /// Address each listener was created on, until it is removed on request
+    pub(crate) listeners: HashMap<ListenerId, Multiaddr>,
+    /// Bytes exchanged with each peer and inbound rate limits
+    pub(crate) bandwidth: BandwidthMeter,
+    /// Pubsub messages received more than once, per channel and per peer
+    pub(crate) duplicates: DuplicateTracker,
+    /// Limits on the messages received from each peer on the mempool channel
+    pub(crate) mempool: MempoolLimiter,
+    /// Consecutive failed pings on each connection
+    pub(crate) liveness: Liveness,
+    /// Score deltas reported by the application, added to the score of the peers
+    /// based on their type. Kept after the peer disconnects, until they decay to zero.
+    pub(crate) reported_scores: HashMap<libp2p::PeerId, f64>,
+    /// Peers banned at runtime, along with when their ban expires, if ever
+    pub(crate) banned_peers: HashMap<libp2p::PeerId, Option<Instant>>,
+    /// GossipSub messages waiting to be validated by the application,
+    /// along with the peer which sent them and when they were received
+    pub(crate) pending_validations: HashMap<MessageId, (libp2p::PeerId, Instant)>,
+    /// Whether enough peers took part in consensus when last checked, `None` if never checked
+    pub(crate) sufficient_peers: Option<bool>,
+    /// `PeerId` of the node once restarted, if its key was rotated, see `crate::node_key`
+    pub(crate) next_peer_id: Option<libp2p::PeerId>,
+    /// Conditions simulated on the links to some peers
+    pub(crate) link_conditions: HashMap<libp2p::PeerId, LinkConditions>,
 }
 
 impl State {
@@ -195,6 +292,23 @@ impl State {
         self.reclassify_peers()
     }
 
+    /// Process the peer IDs of the current validator set, as provided by the application.
+    ///
+    /// Discovery prioritizes these peers when selecting outbound peers and keeps their
+    /// connections open, and the connected ones are classified as validators even before
+    /// they send a proof.
+    ///
+    /// Returns a list of (peer_id, new_score) for peers whose type changed,
+    /// so the caller can update GossipSub scores and explicit peering.
+    pub(crate) fn process_validator_peers_update(
+        &mut self,
+        peers: HashSet<libp2p::PeerId>,
+    ) -> Vec<(libp2p::PeerId, f64)> {
+        self.discovery.set_validator_set(peers);
+
+        self.reclassify_peers()
+    }
+
     /// Re-classify the local node based on the current validator set.
     fn reclassify_local_node(&mut self) {
         let was_validator = self.local_node.is_validator;
@@ -225,23 +339,39 @@ impl State {
     /// by checking if their public key matches a validator in the new set.
     /// Updates consensus_address accordingly (set if in set, cleared if not).
     ///
+    /// Peers whose ID is among the validator peers provided by the application
+    /// are classified as validators, with or without a proof.
+    ///
     /// Returns a list of (peer_id, new_score) for peers whose type changed.
     fn reclassify_peers(&mut self) -> Vec<(libp2p::PeerId, f64)> {
         let mut changed_peers = Vec::new();
 
         for (peer_id, peer_info) in self.peer_info.iter_mut() {
-            // Only re-evaluate peers with verified proofs
-            let Some(public_key) = &peer_info.consensus_public_key else {
-                continue;
+            // Look up validator by public key of the verified proof, if any,
+            // to check membership and get address
+            let validator_address = peer_info
+                .consensus_public_key
+                .as_ref()
+                .and_then(|public_key| find_validator_address(&self.validator_set, public_key));
+
+            // Forget the key the peer rotated from once the validator set includes the new one,
+            // matching it until then
+            let validator_address = if validator_address.is_some() {
+                peer_info.previous_consensus_public_key = None;
+                validator_address
+            } else {
+                peer_info
+                    .previous_consensus_public_key
+                    .as_ref()
+                    .and_then(|public_key| find_validator_address(&self.validator_set, public_key))
             };
 
-            // Look up validator by public key to check membership and get address
-            let validator_address = self
-                .validator_set
-                .iter()
-                .find_map(|v| v.address_for_public_key(public_key));
+            // Label the peer for the inbound peers eviction policy
+            self.discovery
+                .set_validator_peer(*peer_id, validator_address.is_some());
 
-            let is_in_validator_set = validator_address.is_some();
+            let is_in_validator_set =
+                validator_address.is_some() || self.discovery.is_in_validator_set(peer_id);
 
             let new_type = peer_info
                 .peer_type
@@ -284,6 +414,32 @@ impl State {
         peer_id: &libp2p::PeerId,
         public_key: Vec<u8>,
     ) -> Option<f64> {
+        // Look up the validator by public key to get their address
+        let mut validator_address = find_validator_address(&self.validator_set, &public_key);
+
+        // A validator which rotated its consensus key sends a proof for the new key, which may
+        // not be in the validator set of this node yet: keep matching the previous key until then
+        let previous_public_key = self
+            .peer_info
+            .get(peer_id)
+            .and_then(|peer_info| peer_info.consensus_public_key.clone())
+            .filter(|previous| *previous != public_key);
+
+        let previous_public_key = match previous_public_key {
+            Some(previous) if validator_address.is_none() => {
+                validator_address = find_validator_address(&self.validator_set, &previous);
+                validator_address.is_some().then_some(previous)
+            }
+            _ => None,
+        };
+
+        // Label the peer for the inbound peers eviction policy, which may run before Identify
+        self.discovery
+            .set_validator_peer(*peer_id, validator_address.is_some());
+
+        let is_in_validator_set =
+            validator_address.is_some() || self.discovery.is_in_validator_set(peer_id);
+
         let Some(peer_info) = self.peer_info.get_mut(peer_id) else {
             // Peer not in peer_info yet (Identify not received).
             // Buffer the proof to apply when Identify completes.
@@ -291,14 +447,6 @@ impl State {
             return None;
         };
 
-        // Look up the validator by public key to get their address
-        let validator_address = self
-            .validator_set
-            .iter()
-            .find_map(|v| v.address_for_public_key(&public_key));
-
-        let is_in_validator_set = validator_address.is_some();
-
         let new_type = peer_info
             .peer_type
             .with_validator_status(is_in_validator_set);
@@ -308,6 +456,7 @@ impl State {
 
         // Store the public key from the verified proof
         peer_info.consensus_public_key = Some(public_key);
+        peer_info.previous_consensus_public_key = previous_public_key;
 
         // Set consensus_address only if in validator set (for display/metrics)
         peer_info.consensus_address = validator_address.map(|s| s.to_string());
@@ -322,10 +471,13 @@ impl State {
     }
 
     pub(crate) fn new(
-        discovery: discovery::Discovery<Behaviour>,
+        discovery: discovery::Discovery<DefaultBehaviour>,
         persistent_peer_addrs: Vec<Multiaddr>,
         local_node: LocalNodeInfo,
         metrics: NetworkMetrics,
+        bandwidth: BandwidthMeter,
+        duplicates: DuplicateTracker,
+        mempool: MempoolLimiter,
     ) -> Self {
         // Extract PeerIds from persistent peer Multiaddrs if they contain /p2p/<peer_id>
         let persistent_peer_ids = persistent_peer_addrs
@@ -335,6 +487,8 @@ impl State {
 
         Self {
             sync_channels: Default::default(),
+            peer_message_channels: Default::default(),
+            peer_message_replies: Default::default(),
             discovery,
             persistent_peer_ids,
             persistent_peer_addrs,
@@ -343,9 +497,165 @@ impl State {
             local_node,
             peer_info: HashMap::new(),
             pending_verified_proofs: HashMap::new(),
+            addr_monitor: AddressMonitor::default(),
+            listeners: HashMap::new(),
+            bandwidth,
+            duplicates,
+            mempool,
+            liveness: Liveness::default(),
+            reported_scores: HashMap::new(),
+            banned_peers: HashMap::new(),
+            pending_validations: HashMap::new(),
+            sufficient_peers: None,
+            next_peer_id: None,
+            link_conditions: HashMap::new(),
         }
     }
 
+    /// Score reported by the application for a peer, zero if never reported
+    pub(crate) fn reported_score(&self, peer_id: &libp2p::PeerId) -> f64 {
+        self.reported_scores.get(peer_id).copied().unwrap_or(0.0)
+    }
+
+    /// Application-specific score of a peer, based on its type and on the reported score
+    fn application_score(&self, peer_id: &libp2p::PeerId) -> f64 {
+        let type_score = self
+            .peer_info
+            .get(peer_id)
+            .map_or(crate::peer_scoring::get_default_score(), |peer_info| {
+                crate::peer_scoring::get_peer_score(peer_info.peer_type)
+            });
+
+        type_score + self.reported_score(peer_id)
+    }
+
+    /// The peers this node is connected to, sorted by peer ID, without their GossipSub score
+    pub(crate) fn connected_peers(&self) -> Vec<ConnectedPeer> {
+        self.discovery
+            .discovered_peers()
+            .into_iter()
+            .map(|peer| {
+                let peer_info = self.peer_info.get(&peer.peer_id);
+
+                ConnectedPeer {
+                    peer_id: peer.peer_id,
+                    moniker: peer_info.map(|info| info.moniker.clone()),
+                    kind: peer.kind,
+                    addresses: peer
+                        .connections
+                        .into_iter()
+                        .map(|connection| connection.remote_addr)
+                        .collect(),
+                    listen_addrs: peer.listen_addrs,
+                    protocols: peer
+                        .identity
+                        .map(|identity| identity.protocols)
+                        .unwrap_or_default(),
+                    peer_type: peer_info.map(|info| info.peer_type),
+                    application_score: self.application_score(&peer.peer_id),
+                    gossipsub_score: None,
+                    duplicate_messages: self.duplicates.duplicates_from(&peer.peer_id),
+                }
+            })
+            .collect()
+    }
+
+    /// Add a score delta reported by the application to a peer.
+    ///
+    /// Returns the new application-specific score of the peer, to set in GossipSub.
+    pub(crate) fn report_peer(&mut self, peer_id: libp2p::PeerId, score_delta: f64) -> f64 {
+        *self.reported_scores.entry(peer_id).or_default() += score_delta;
+        self.application_score(&peer_id)
+    }
+
+    /// Multiply the reported scores by `decay`, forgetting the ones close to zero.
+    ///
+    /// Returns the peers with a reported score along with their new application-specific score,
+    /// to set in GossipSub.
+    pub(crate) fn decay_reported_scores(&mut self, decay: f64) -> Vec<(libp2p::PeerId, f64)> {
+        for score in self.reported_scores.values_mut() {
+            *score *= decay;
+        }
+
+        let peers: Vec<_> = self.reported_scores.keys().copied().collect();
+
+        self.reported_scores
+            .retain(|_, score| score.abs() >= MIN_REPORTED_SCORE);
+
+        peers
+            .into_iter()
+            .map(|peer_id| (peer_id, self.application_score(&peer_id)))
+            .collect()
+    }
+
+    /// Ban a peer for the given duration, or until unbanned if `None`.
+    /// Banning an already banned peer replaces its ban.
+    pub(crate) fn ban_peer(&mut self, peer_id: libp2p::PeerId, duration: Option<Duration>) {
+        let expires_at = duration.map(|duration| Instant::now() + duration);
+        self.banned_peers.insert(peer_id, expires_at);
+        self.discovery.ban_peer(peer_id);
+    }
+
+    /// Delay of a message received from a peer, `None` if the message is lost on the link
+    pub(crate) fn link_delay(&self, peer_id: &libp2p::PeerId) -> Option<Duration> {
+        match self.link_conditions.get(peer_id) {
+            Some(conditions) => conditions.sample(&mut rand::thread_rng()),
+            None => Some(Duration::ZERO),
+        }
+    }
+
+    /// Unban a peer, returns whether it was banned
+    pub(crate) fn unban_peer(&mut self, peer_id: &libp2p::PeerId) -> bool {
+        self.discovery.unban_peer(peer_id);
+        self.banned_peers.remove(peer_id).is_some()
+    }
+
+    /// Unban the peers whose ban expired, returning them
+    pub(crate) fn expire_bans(&mut self) -> Vec<libp2p::PeerId> {
+        let now = Instant::now();
+
+        let expired: Vec<_> = self
+            .banned_peers
+            .iter()
+            .filter(|(_, expires_at)| expires_at.is_some_and(|expires_at| expires_at <= now))
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+
+        for peer_id in &expired {
+            self.unban_peer(peer_id);
+        }
+
+        expired
+    }
+
+    /// Record a GossipSub message waiting to be validated by the application
+    #[cfg(feature = "gossipsub")]
+    pub(crate) fn add_pending_validation(
+        &mut self,
+        message_id: MessageId,
+        propagation_source: libp2p::PeerId,
+    ) {
+        self.pending_validations
+            .insert(message_id, (propagation_source, Instant::now()));
+    }
+
+    /// Stop waiting for the validation of a message, returning the peer which sent it
+    pub(crate) fn take_pending_validation(
+        &mut self,
+        message_id: &MessageId,
+    ) -> Option<libp2p::PeerId> {
+        self.pending_validations
+            .remove(message_id)
+            .map(|(propagation_source, _)| propagation_source)
+    }
+
+    /// Forget the messages which waited for their validation for too long,
+    /// by which time GossipSub has dropped them from its cache
+    pub(crate) fn expire_pending_validations(&mut self) {
+        self.pending_validations
+            .retain(|_, (_, received_at)| received_at.elapsed() < MESSAGE_VALIDATION_TIMEOUT);
+    }
+
     /// Check if a peer is persistent, by PeerId or by connection address.
     fn is_persistent_peer(
         &self,
@@ -353,6 +663,7 @@ impl State {
         connection_id: libp2p::swarm::ConnectionId,
     ) -> bool {
         self.persistent_peer_ids.contains(peer_id)
+            || self.discovery.is_static_persistent_peer(peer_id)
             || self.is_persistent_peer_by_address(connection_id)
     }
 
@@ -383,17 +694,22 @@ impl State {
 
     /// Update peer information from gossipsub (scores and mesh membership)
     /// Also updates metrics based on the updated State
+    ///
+    /// The mesh membership is that of the topics of the chain with the given namespace
+    #[cfg(feature = "gossipsub")]
     pub(crate) fn update_peer_info(
         &mut self,
         gossipsub: &libp2p_gossipsub::Behaviour,
         channels: &[Channel],
+        namespace: &str,
         channel_names: ChannelNames,
+        compression: CompressionConfig,
     ) {
         // Build a map of peer_id to the set of topics they're in
         let mut peer_topics: HashMap<libp2p::PeerId, HashSet<String>> = HashMap::new();
 
         for channel in channels {
-            let topic = channel.to_gossipsub_topic(channel_names);
+            let topic = channel.to_gossipsub_topic(namespace, channel_names, compression);
             let topic_hash = topic.hash();
             let topic_str = channel.as_str(channel_names).to_string();
 
@@ -405,6 +721,8 @@ impl State {
             }
         }
 
+        self.update_mesh_metrics(&peer_topics, channels, channel_names);
+
         // Update score and topics for all peers in State
         for (peer_id, peer_info) in self.peer_info.iter_mut() {
             // Use GossipSub score if available, otherwise use internal score based on peer type
@@ -426,6 +744,118 @@ impl State {
         }
     }
 
+    /// Update the per-topic mesh size, churn and outbound fraction metrics.
+    /// Must be called before the peers' topics are updated, to compute the churn.
+    #[cfg(feature = "gossipsub")]
+    fn update_mesh_metrics(
+        &self,
+        peer_topics: &HashMap<libp2p::PeerId, HashSet<String>>,
+        channels: &[Channel],
+        channel_names: ChannelNames,
+    ) {
+        for channel in channels {
+            let topic = channel.as_str(channel_names);
+
+            let mut size = 0;
+            let mut outbound = 0;
+            let mut joined = 0;
+
+            for (peer_id, topics) in peer_topics {
+                if !topics.contains(topic) {
+                    continue;
+                }
+
+                size += 1;
+
+                let peer_info = self.peer_info.get(peer_id);
+
+                if peer_info.and_then(|p| p.connection_direction)
+                    == Some(ConnectionDirection::Outbound)
+                {
+                    outbound += 1;
+                }
+
+                if !peer_info.is_some_and(|p| p.topics.contains(topic)) {
+                    joined += 1;
+                }
+            }
+
+            let left = self
+                .peer_info
+                .iter()
+                .filter(|(peer_id, peer_info)| {
+                    peer_info.topics.contains(topic)
+                        && !peer_topics.get(peer_id).is_some_and(|t| t.contains(topic))
+                })
+                .count();
+
+            self.metrics
+                .update_mesh_metrics(topic, size, outbound, joined + left);
+        }
+    }
+
+    /// Check whether enough peers take part in consensus, returning
+    /// [`Event::SufficientPeers`] or [`Event::InsufficientPeers`] if that changed since the last check.
+    ///
+    /// With GossipSub, the peers counted are those in the consensus mesh, as last observed,
+    /// of which `mesh_n_low` are required. Otherwise, a single connected peer is enough.
+    /// Fewer peers are required if there are fewer other validators in the validator set.
+    pub(crate) fn check_sufficient_peers(&mut self, config: &Config) -> Option<Event> {
+        let (peers, min_peers) = if config.pubsub_protocol.is_gossipsub() {
+            let topic = crate::Channel::Consensus.as_str(config.channel_names);
+            let peers = self
+                .peer_info
+                .values()
+                .filter(|peer_info| peer_info.topics.contains(topic))
+                .count();
+
+            (peers, config.gossipsub.mesh_n_low)
+        } else {
+            (self.peer_info.len(), 1)
+        };
+
+        let other_validators = self
+            .validator_set
+            .len()
+            .saturating_sub(usize::from(self.local_node.is_validator));
+
+        let required = match other_validators {
+            0 => min_peers,
+            n => min_peers.min(n),
+        };
+
+        let sufficient = peers >= required;
+
+        if self.sufficient_peers == Some(sufficient) {
+            return None;
+        }
+
+        self.sufficient_peers = Some(sufficient);
+
+        Some(if sufficient {
+            Event::SufficientPeers { peers, required }
+        } else {
+            Event::InsufficientPeers { peers, required }
+        })
+    }
+
+    /// Per-topic mesh peers, as last observed from gossipsub
+    pub(crate) fn mesh_peers_by_topic(&self) -> BTreeMap<String, Vec<libp2p::PeerId>> {
+        let mut mesh: BTreeMap<String, Vec<libp2p::PeerId>> = BTreeMap::new();
+
+        for (peer_id, peer_info) in &self.peer_info {
+            for topic in &peer_info.topics {
+                mesh.entry(topic.clone()).or_default().push(*peer_id);
+            }
+        }
+
+        for peers in mesh.values_mut() {
+            peers.sort_unstable();
+        }
+
+        mesh
+    }
+
     /// Update the peer information after Identify completes and compute peer score.
     ///
     /// This method:
@@ -495,12 +925,14 @@ impl State {
             return existing.score;
         }
 
-        // New peer - create entry (validator status starts as false, set by proof protocol)
-        let peer_type = PeerType::new(is_persistent, false);
+        // New peer - create entry (validator status starts as false unless the peer is among
+        // the validator peers provided by the application, set by proof protocol)
+        let peer_type = PeerType::new(is_persistent, self.discovery.is_in_validator_set(&peer_id));
         let mut score = crate::peer_scoring::get_peer_score(peer_type);
         let peer_info = PeerInfo {
             address,
             consensus_public_key: None,
+            previous_consensus_public_key: None,
             consensus_address: None,
             moniker: agent_info.moniker,
             peer_type,
@@ -550,11 +982,13 @@ impl State {
     }
 
     /// Update peer's persistent status, recalculate score, and update GossipSub
+    #[cfg_attr(not(feature = "gossipsub"), allow(unused_variables))]
     fn update_peer_persistent_status(
         peer_id: libp2p::PeerId,
         peer_info: Option<&mut PeerInfo>,
         is_persistent: bool,
-        swarm: &mut libp2p::Swarm<Behaviour>,
+        reported_score: f64,
+        swarm: &mut libp2p::Swarm<DefaultBehaviour>,
     ) {
         let Some(peer_info) = peer_info else {
             return;
@@ -567,8 +1001,9 @@ impl State {
         peer_info.score = new_score;
 
         // Update GossipSub score
+        #[cfg(feature = "gossipsub")]
         if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
-            gossipsub.set_application_score(&peer_id, new_score);
+            gossipsub.set_application_score(&peer_id, new_score + reported_score);
         }
 
         tracing::debug!(
@@ -583,7 +1018,7 @@ impl State {
     pub(crate) fn add_persistent_peer(
         &mut self,
         addr: Multiaddr,
-        swarm: &mut libp2p::Swarm<Behaviour>,
+        swarm: &mut libp2p::Swarm<DefaultBehaviour>,
     ) -> Result<(), PersistentPeerError> {
         // Check if already exists
         if self.persistent_peer_addrs.contains(&addr) {
@@ -595,10 +1030,12 @@ impl State {
             self.persistent_peer_ids.insert(peer_id);
 
             // Update peer type and score if already connected
+            let reported_score = self.reported_score(&peer_id);
             Self::update_peer_persistent_status(
                 peer_id,
                 self.peer_info.get_mut(&peer_id),
                 true,
+                reported_score,
                 swarm,
             );
         }
@@ -626,7 +1063,7 @@ impl State {
     pub(crate) fn remove_persistent_peer(
         &mut self,
         addr: Multiaddr,
-        swarm: &mut libp2p::Swarm<Behaviour>,
+        swarm: &mut libp2p::Swarm<DefaultBehaviour>,
     ) -> Result<(), PersistentPeerError> {
         // Check if exists and remove from persistent peer list
         let Some(pos) = self.persistent_peer_addrs.iter().position(|a| a == &addr) else {
@@ -643,10 +1080,12 @@ impl State {
             self.persistent_peer_ids.remove(&peer_id);
 
             // Update peer type and score if connected
+            let reported_score = self.reported_score(&peer_id);
             Self::update_peer_persistent_status(
                 peer_id,
                 self.peer_info.get_mut(&peer_id),
                 false,
+                reported_score,
                 swarm,
             );
 
@@ -671,6 +1110,39 @@ impl State {
 
         Ok(())
     }
+
+    /// Replace the `PeerId` of a persistent peer which rotated its node key in the addresses
+    /// it is dialed at, returning the number of addresses updated. The persistent peers
+    /// configured without their `PeerId` are recognized by their address and left as is.
+    ///
+    /// The old `PeerId` is still recognized as persistent, until the peer restarts with its new key.
+    pub(crate) fn replace_persistent_peer_id(
+        &mut self,
+        old: &libp2p::PeerId,
+        new: libp2p::PeerId,
+    ) -> usize {
+        use libp2p::multiaddr::Protocol;
+
+        let mut replaced = Vec::new();
+
+        for addr in &mut self.persistent_peer_addrs {
+            if extract_peer_id_from_multiaddr(addr).as_ref() == Some(old) {
+                let new_addr = strip_peer_id_from_multiaddr(addr).with(Protocol::P2p(new));
+                replaced.push((std::mem::replace(addr, new_addr.clone()), new_addr));
+            }
+        }
+
+        for (old_addr, new_addr) in &replaced {
+            self.discovery.remove_bootstrap_node(old_addr);
+            self.discovery.add_bootstrap_node(new_addr.clone());
+        }
+
+        if !replaced.is_empty() {
+            self.persistent_peer_ids.insert(new);
+        }
+
+        replaced.len()
+    }
 }
 
 /// Extract PeerId from a Multiaddr if it contains a /p2p/<peer_id> component
@@ -685,6 +1157,16 @@ fn extract_peer_id_from_multiaddr(addr: &Multiaddr) -> Option<libp2p::PeerId> {
     None
 }
 
+/// Returns the address of the validator with the given public key, if any.
+fn find_validator_address<'a>(
+    validator_set: &'a HashSet<ValidatorInfo>,
+    public_key: &[u8],
+) -> Option<&'a str> {
+    validator_set
+        .iter()
+        .find_map(|v| v.address_for_public_key(public_key))
+}
+
 /// Helper to apply a peer type change, updating score and metrics.
 ///
 /// Takes old_peer_info for stale metric labels (before any modifications)
@@ -722,9 +1204,14 @@ mod tests {
     /// Create a minimal `State` with disabled discovery and an optional local consensus address.
     fn test_state_with_local_addr(consensus_address: Option<&str>) -> State {
         let mut registry = malachitebft_metrics::Registry::default();
-        let discovery =
-            discovery::Discovery::<Behaviour>::new(Config::new(false), vec![], &mut registry);
+        let discovery = discovery::Discovery::<DefaultBehaviour>::new(
+            Config::new(false),
+            vec![],
+            &mut registry,
+        );
         let metrics = NetworkMetrics::new(&mut registry);
+        let bandwidth = BandwidthMeter::new(Default::default(), &mut registry);
+        let duplicates = DuplicateTracker::new(Duration::from_secs(60), &mut registry);
 
         let local_node = LocalNodeInfo {
             moniker: "test-node".to_string(),
@@ -737,7 +1224,15 @@ mod tests {
             subscribed_topics: HashSet::new(),
         };
 
-        State::new(discovery, vec![], local_node, metrics)
+        State::new(
+            discovery,
+            vec![],
+            local_node,
+            metrics,
+            bandwidth,
+            duplicates,
+            MempoolLimiter::new(Default::default()),
+        )
     }
 
     /// Create default full-node peer info.
@@ -747,6 +1242,7 @@ mod tests {
             address: "/ip4/10.0.0.1/tcp/26656".parse().unwrap(),
             consensus_address: None,
             consensus_public_key: None,
+            previous_consensus_public_key: None,
             peer_type: PeerType::new(false, false),
             connection_direction: None,
             score: FULL_NODE_SCORE,
@@ -828,6 +1324,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn record_proof_rotated_key_keeps_validator_until_set_updated() {
+        let mut state = test_state();
+        let peer_id = libp2p::PeerId::random();
+        let old_key = vec![20, 21, 22];
+        let new_key = vec![23, 24, 25];
+
+        insert_peer(&mut state, peer_id, test_peer_info());
+        state.validator_set.insert(ValidatorInfo {
+            address: "rotating_addr".to_string(),
+            public_key: old_key.clone(),
+            voting_power: 100,
+        });
+        state.record_verified_proof(&peer_id, old_key.clone());
+
+        // The validator rotated its key, the validator set still has the old one
+        state.record_verified_proof(&peer_id, new_key.clone());
+
+        let info = &state.peer_info[&peer_id];
+        assert!(info.peer_type.is_validator());
+        assert_eq!(info.consensus_address.as_deref(), Some("rotating_addr"));
+        assert_eq!(info.consensus_public_key.as_deref(), Some(&new_key[..]));
+        assert_eq!(
+            info.previous_consensus_public_key.as_deref(),
+            Some(&old_key[..])
+        );
+
+        // The validator set now has the new key
+        let mut validators = HashSet::new();
+        validators.insert(ValidatorInfo {
+            address: "rotating_addr".to_string(),
+            public_key: new_key.clone(),
+            voting_power: 100,
+        });
+        state.process_validator_set_update(validators);
+
+        let info = &state.peer_info[&peer_id];
+        assert!(info.peer_type.is_validator());
+        assert!(info.previous_consensus_public_key.is_none());
+
+        // The old key is no longer matched
+        let mut validators = HashSet::new();
+        validators.insert(ValidatorInfo {
+            address: "rotating_addr".to_string(),
+            public_key: old_key,
+            voting_power: 100,
+        });
+        state.process_validator_set_update(validators);
+
+        assert!(!state.peer_info[&peer_id].peer_type.is_validator());
+    }
+
     // ── reclassify_peers (via process_validator_set_update) ──────────
 
     #[test]
@@ -903,6 +1451,30 @@ mod tests {
         assert!(!state.peer_info[&peer_id].peer_type.is_validator());
     }
 
+    #[test]
+    fn reclassify_validator_peers_provided_by_app() {
+        let mut state = test_state();
+        let peer_id = libp2p::PeerId::random();
+
+        // Peer without consensus_public_key
+        insert_peer(&mut state, peer_id, test_peer_info());
+
+        let changed = state.process_validator_peers_update(HashSet::from([peer_id]));
+
+        assert_eq!(changed, vec![(peer_id, VALIDATOR_SCORE)]);
+        assert!(state.peer_info[&peer_id].peer_type.is_validator());
+        assert!(state.discovery.is_in_validator_set(&peer_id));
+
+        // Validator set update without a matching proof keeps the peer a validator
+        let changed = state.process_validator_set_update(HashSet::new());
+        assert!(changed.is_empty());
+
+        let changed = state.process_validator_peers_update(HashSet::new());
+
+        assert_eq!(changed, vec![(peer_id, FULL_NODE_SCORE)]);
+        assert!(!state.peer_info[&peer_id].peer_type.is_validator());
+    }
+
     // ── Local node reclassification ──────────────────────────────────
 
     #[test]
@@ -1002,4 +1574,65 @@ mod tests {
         assert!(info.peer_type.is_validator());
         assert_eq!(info.consensus_address.as_deref(), Some("persistent_val"));
     }
+
+    // ── mesh_peers_by_topic ──────────────────────────────────────────
+
+    #[test]
+    fn mesh_peers_grouped_by_topic() {
+        let mut state = test_state();
+        let peer_a = libp2p::PeerId::random();
+        let peer_b = libp2p::PeerId::random();
+        let peer_c = libp2p::PeerId::random();
+
+        let mut info = test_peer_info();
+        info.topics = HashSet::from(["/consensus".to_string(), "/liveness".to_string()]);
+        insert_peer(&mut state, peer_a, info);
+
+        let mut info = test_peer_info();
+        info.topics = HashSet::from(["/consensus".to_string()]);
+        insert_peer(&mut state, peer_b, info);
+
+        // Not in any mesh
+        insert_peer(&mut state, peer_c, test_peer_info());
+
+        let mesh = state.mesh_peers_by_topic();
+
+        let mut consensus = vec![peer_a, peer_b];
+        consensus.sort_unstable();
+
+        assert_eq!(mesh.len(), 2);
+        assert_eq!(mesh["/consensus"], consensus);
+        assert_eq!(mesh["/liveness"], vec![peer_a]);
+    }
+
+    // ── reported scores ──────────────────────────────────────────────
+
+    #[test]
+    fn reported_score_added_to_peer_type_score() {
+        let mut state = test_state();
+        let peer_id = libp2p::PeerId::random();
+        insert_peer(&mut state, peer_id, test_peer_info());
+
+        assert_eq!(state.report_peer(peer_id, -10.0), FULL_NODE_SCORE - 10.0);
+        assert_eq!(state.report_peer(peer_id, -10.0), FULL_NODE_SCORE - 20.0);
+        assert_eq!(state.reported_score(&peer_id), -20.0);
+    }
+
+    #[test]
+    fn reported_score_decays_to_zero() {
+        let mut state = test_state();
+        let peer_id = libp2p::PeerId::random();
+        insert_peer(&mut state, peer_id, test_peer_info());
+
+        state.report_peer(peer_id, -10.0);
+
+        let scores = state.decay_reported_scores(0.5);
+        assert_eq!(scores, vec![(peer_id, FULL_NODE_SCORE - 5.0)]);
+
+        // Forgotten once close to zero, the peer getting its type score back
+        let scores = state.decay_reported_scores(0.0001);
+        assert_eq!(scores, vec![(peer_id, FULL_NODE_SCORE)]);
+        assert!(state.reported_scores.is_empty());
+        assert!(state.decay_reported_scores(0.5).is_empty());
+    }
 }
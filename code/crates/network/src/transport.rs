@@ -0,0 +1,187 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use libp2p::core::muxing::StreamMuxerBox;
+use libp2p::core::transport::{
+    Boxed, DialOpts, ListenerId, MemoryTransport, TransportError, TransportEvent,
+};
+use libp2p::core::{upgrade, Endpoint};
+use libp2p::{tcp, yamux, Multiaddr, PeerId, Transport};
+
+use malachitebft_discovery::is_relayed_addr;
+
+use crate::security::{SecurityProtocol, SecurityUpgrade};
+use crate::Keypair;
+
+/// Maximum duration of an outbound connection attempt, including the security
+/// and multiplexing handshakes, depending on how the peer is reached.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DialTimeouts {
+    /// Direct dials over TCP or WebSocket
+    pub tcp_connect: Duration,
+
+    /// Dials of a `/p2p-circuit` address, through a relay
+    pub relay_circuit: Duration,
+
+    /// Direct dials upgrading a relayed connection (DCUtR hole punching),
+    /// where the dialer acts as the listener of the connection
+    pub dcutr_upgrade: Duration,
+}
+
+impl Default for DialTimeouts {
+    fn default() -> Self {
+        Self {
+            tcp_connect: Duration::from_secs(10),
+            relay_circuit: Duration::from_secs(60),
+            dcutr_upgrade: Duration::from_secs(30),
+        }
+    }
+}
+
+impl DialTimeouts {
+    /// Timeout applied to a dial of the given address
+    pub fn for_dial(&self, addr: &Multiaddr, opts: &DialOpts) -> Duration {
+        if is_relayed_addr(addr) {
+            self.relay_circuit
+        } else if opts.role == Endpoint::Listener {
+            self.dcutr_upgrade
+        } else {
+            self.tcp_connect
+        }
+    }
+}
+
+/// Transport failing the dials which do not complete within their timeout.
+///
+/// Unlike `TransportTimeout` from libp2p, the timeout depends on the dialed address,
+/// so that dials through a relay are given a longer budget than direct ones.
+/// Inbound connections are not subject to a timeout.
+pub(crate) struct DialTimeout {
+    inner: Boxed<(PeerId, StreamMuxerBox)>,
+    timeouts: DialTimeouts,
+}
+
+impl DialTimeout {
+    pub(crate) fn new(inner: Boxed<(PeerId, StreamMuxerBox)>, timeouts: DialTimeouts) -> Self {
+        Self { inner, timeouts }
+    }
+}
+
+impl Transport for DialTimeout {
+    type Output = (PeerId, StreamMuxerBox);
+    type Error = io::Error;
+    type ListenerUpgrade = <Boxed<(PeerId, StreamMuxerBox)> as Transport>::ListenerUpgrade;
+    type Dial = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+    fn listen_on(
+        &mut self,
+        id: ListenerId,
+        addr: Multiaddr,
+    ) -> Result<(), TransportError<Self::Error>> {
+        self.inner.listen_on(id, addr)
+    }
+
+    fn remove_listener(&mut self, id: ListenerId) -> bool {
+        self.inner.remove_listener(id)
+    }
+
+    fn dial(
+        &mut self,
+        addr: Multiaddr,
+        opts: DialOpts,
+    ) -> Result<Self::Dial, TransportError<Self::Error>> {
+        let timeout = self.timeouts.for_dial(&addr, &opts);
+        let dial = self.inner.dial(addr.clone(), opts)?;
+
+        Ok(async move {
+            tokio::time::timeout(timeout, dial).await.map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("Dial to {addr} timed out after {timeout:?}"),
+                )
+            })?
+        }
+        .boxed())
+    }
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<TransportEvent<Self::ListenerUpgrade, Self::Error>> {
+        Pin::new(&mut self.inner).poll(cx)
+    }
+}
+
+/// TCP transport with Noise and/or TLS encryption and Yamux multiplexing
+pub(crate) fn tcp(
+    keypair: &Keypair,
+    security: SecurityProtocol,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, eyre::Report> {
+    let tcp = tcp::tokio::Transport::new(tcp::Config::new().nodelay(true)); // Disable Nagle's algorithm
+
+    Ok(tcp
+        .upgrade(upgrade::Version::V1Lazy)
+        .authenticate(SecurityUpgrade::new(keypair, security)?)
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed())
+}
+
+/// In-memory transport, to run many nodes in one process without binding any socket,
+/// with the same security and multiplexing as the TCP transport
+pub(crate) fn memory(
+    keypair: &Keypair,
+    security: SecurityProtocol,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, eyre::Report> {
+    Ok(MemoryTransport::default()
+        .upgrade(upgrade::Version::V1Lazy)
+        .authenticate(SecurityUpgrade::new(keypair, security)?)
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed())
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::core::transport::PortUse;
+
+    use super::*;
+
+    fn dial_opts(role: Endpoint) -> DialOpts {
+        DialOpts {
+            role,
+            port_use: PortUse::Reuse,
+        }
+    }
+
+    #[test]
+    fn timeout_depends_on_dialed_address() {
+        let timeouts = DialTimeouts {
+            tcp_connect: Duration::from_secs(1),
+            relay_circuit: Duration::from_secs(2),
+            dcutr_upgrade: Duration::from_secs(3),
+        };
+
+        let direct: Multiaddr = "/ip4/10.0.0.1/tcp/27000".parse().unwrap();
+        let relayed: Multiaddr = "/ip4/10.0.0.2/tcp/27000/p2p/12D3KooWAP3H2MR5fFMJmubFLkaZiqArZdHskoLz4bADaNeVPWUH/p2p-circuit"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            timeouts.for_dial(&direct, &dial_opts(Endpoint::Dialer)),
+            timeouts.tcp_connect
+        );
+        assert_eq!(
+            timeouts.for_dial(&direct, &dial_opts(Endpoint::Listener)),
+            timeouts.dcutr_upgrade
+        );
+        assert_eq!(
+            timeouts.for_dial(&relayed, &dial_opts(Endpoint::Dialer)),
+            timeouts.relay_circuit
+        );
+    }
+}
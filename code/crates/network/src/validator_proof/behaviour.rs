@@ -3,7 +3,7 @@
 //! This is a one-way protocol where validators send their proof to peers.
 //! No response is expected - the receiver just stores the proof.
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::task::{self, Poll};
 
 use bytes::Bytes;
@@ -19,6 +19,10 @@ use tracing::{debug, trace, warn};
 
 use super::protocol;
 
+/// Maximum number of proofs a peer may send over a session, eg. a validator
+/// rotating its consensus key, beyond which the connection is closed (anti-spam)
+const MAX_PROOFS_PER_SESSION: usize = 4;
+
 /// Events emitted by the Validator Proof behaviour.
 #[derive(Debug)]
 pub enum Event {
@@ -56,9 +60,10 @@ pub struct Behaviour {
     events_rx: mpsc::UnboundedReceiver<Event>,
     events_tx: mpsc::UnboundedSender<Event>,
 
-    /// Track peers we've received proofs from (anti-spam: one proof per peer per session).
+    /// Track peers we've received proofs from (anti-spam: the same proof is received once
+    /// per peer per session, and only a few different ones, see [`MAX_PROOFS_PER_SESSION`]).
     /// Cleared when the last connection to a peer closes.
-    proofs_received: HashSet<PeerId>,
+    proofs_received: HashMap<PeerId, ReceivedProofs>,
 
     /// Whether we're listening for incoming streams.
     listening: bool,
@@ -75,7 +80,7 @@ impl Behaviour {
             proof_bytes: None,
             events_rx,
             events_tx,
-            proofs_received: HashSet::new(),
+            proofs_received: HashMap::new(),
             listening: false,
         }
     }
@@ -93,6 +98,18 @@ impl Behaviour {
         self.proof_bytes = Some(proof_bytes);
     }
 
+    /// Replace the proof once the consensus key is rotated, and send the new one
+    /// to the given peers, which already received the previous one.
+    pub fn update_proof(&mut self, proof_bytes: Bytes, peers: impl IntoIterator<Item = PeerId>) {
+        self.proof_bytes = Some(proof_bytes);
+
+        for peer_id in peers {
+            if self.send_proof(peer_id) {
+                debug!(%peer_id, "Sending new validator proof");
+            }
+        }
+    }
+
     /// Check if we have a proof to send.
     pub fn has_proof(&self) -> bool {
         self.proof_bytes.is_some()
@@ -171,6 +188,29 @@ impl Default for Behaviour {
     }
 }
 
+/// Proofs received from a peer over a session
+#[derive(Debug, Default)]
+struct ReceivedProofs {
+    /// Latest proof received
+    latest: Option<Bytes>,
+    /// Number of proofs received
+    count: usize,
+}
+
+impl ReceivedProofs {
+    /// Record a proof, returning `false` if it is the same as the latest one,
+    /// or if the peer already sent too many proofs
+    fn accept(&mut self, proof_bytes: &Bytes) -> bool {
+        if self.latest.as_ref() == Some(proof_bytes) || self.count >= MAX_PROOFS_PER_SESSION {
+            return false;
+        }
+
+        self.latest = Some(proof_bytes.clone());
+        self.count += 1;
+        true
+    }
+}
+
 impl NetworkBehaviour for Behaviour {
     type ConnectionHandler = <stream::Behaviour as NetworkBehaviour>::ConnectionHandler;
     type ToSwarm = Event;
@@ -253,15 +293,17 @@ impl NetworkBehaviour for Behaviour {
                     });
                 }
                 // On proof received, check for duplicate (anti-spam)
-                Event::ProofReceived { peer, .. } => {
-                    if self.proofs_received.contains(peer) {
+                Event::ProofReceived { peer, proof_bytes } => {
+                    let received = self.proofs_received.entry(*peer).or_default();
+
+                    if !received.accept(proof_bytes) {
                         warn!(%peer, "Duplicate validator proof received, closing connection (anti-spam)");
                         return Poll::Ready(ToSwarm::CloseConnection {
                             peer_id: *peer,
                             connection: CloseConnection::All,
                         });
                     }
-                    self.proofs_received.insert(*peer);
+
                     return Poll::Ready(ToSwarm::GenerateEvent(event));
                 }
                 // Forward other events to swarm
@@ -375,7 +417,7 @@ mod tests {
             }
             other => panic!("expected GenerateEvent(ProofReceived), got {other:?}"),
         }
-        assert!(b.proofs_received.contains(&peer));
+        assert!(b.proofs_received.contains_key(&peer));
     }
 
     #[test]
@@ -392,11 +434,11 @@ mod tests {
             .unwrap();
         let _ = poll_behaviour(&mut b);
 
-        // Second proof triggers disconnect
+        // Same proof again triggers disconnect
         b.events_tx
             .send(Event::ProofReceived {
                 peer,
-                proof_bytes: Bytes::from_static(b"proof2"),
+                proof_bytes: Bytes::from_static(b"proof"),
             })
             .unwrap();
 
@@ -412,6 +454,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn poll_new_proof_accepted_until_limit() {
+        let mut b = Behaviour::with_default_protocol();
+        let peer = PeerId::random();
+
+        let mut receive = |proof: usize| {
+            b.events_tx
+                .send(Event::ProofReceived {
+                    peer,
+                    proof_bytes: Bytes::from(proof.to_string()),
+                })
+                .unwrap();
+
+            poll_behaviour(&mut b)
+        };
+
+        // New proofs, eg. once the consensus key is rotated, are accepted
+        for proof in 0..MAX_PROOFS_PER_SESSION {
+            assert!(matches!(
+                receive(proof),
+                Poll::Ready(ToSwarm::GenerateEvent(Event::ProofReceived { .. }))
+            ));
+        }
+
+        // Up to a limit
+        assert!(matches!(
+            receive(MAX_PROOFS_PER_SESSION),
+            Poll::Ready(ToSwarm::CloseConnection { peer_id, .. }) if peer_id == peer
+        ));
+    }
+
     #[test]
     fn poll_different_peers_both_accepted() {
         let mut b = Behaviour::with_default_protocol();
@@ -441,8 +514,8 @@ mod tests {
             Poll::Ready(ToSwarm::GenerateEvent(Event::ProofReceived { .. }))
         ));
 
-        assert!(b.proofs_received.contains(&peer_a));
-        assert!(b.proofs_received.contains(&peer_b));
+        assert!(b.proofs_received.contains_key(&peer_a));
+        assert!(b.proofs_received.contains_key(&peer_b));
     }
 
     #[test]
@@ -519,11 +592,11 @@ mod tests {
         let conn = ConnectionId::new_unchecked(1);
 
         establish_connection(&mut b, peer, conn, 0);
-        b.proofs_received.insert(peer);
+        b.proofs_received.insert(peer, ReceivedProofs::default());
 
         close_connection(&mut b, peer, conn, 0);
 
-        assert!(!b.proofs_received.contains(&peer));
+        assert!(!b.proofs_received.contains_key(&peer));
     }
 
     #[test]
@@ -535,12 +608,12 @@ mod tests {
 
         establish_connection(&mut b, peer, conn1, 0);
         establish_connection(&mut b, peer, conn2, 1);
-        b.proofs_received.insert(peer);
+        b.proofs_received.insert(peer, ReceivedProofs::default());
 
         // Close one, one remains
         close_connection(&mut b, peer, conn1, 1);
 
-        assert!(b.proofs_received.contains(&peer));
+        assert!(b.proofs_received.contains_key(&peer));
     }
 
     #[test]
@@ -559,11 +632,11 @@ mod tests {
             })
             .unwrap();
         let _ = poll_behaviour(&mut b);
-        assert!(b.proofs_received.contains(&peer));
+        assert!(b.proofs_received.contains_key(&peer));
 
         // Fully disconnect
         close_connection(&mut b, peer, conn1, 0);
-        assert!(!b.proofs_received.contains(&peer));
+        assert!(!b.proofs_received.contains_key(&peer));
 
         // Reconnect → proof should be accepted again
         establish_connection(&mut b, peer, conn2, 0);
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+
+use eyre::WrapErr;
+use libp2p::core::muxing::StreamMuxerBox;
+use libp2p::core::transport::Boxed;
+use libp2p::core::upgrade;
+use libp2p::websocket::tls;
+use libp2p::{dns, tcp, yamux, Multiaddr, Transport};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+
+use crate::security::{SecurityProtocol, SecurityUpgrade};
+use crate::Keypair;
+
+/// WebSocket transport configuration, used when listening on a `/ws` or `/wss` address,
+/// for nodes to be reachable from environments where only HTTP(S) egress is allowed.
+#[derive(Clone, Debug, Default)]
+pub struct WebSocketConfig {
+    /// Certificate and private key to accept secure WebSocket (`/wss`) connections with
+    pub tls: Option<WebSocketTlsConfig>,
+
+    /// PEM files with certificates trusted when dialing `/wss` peers, in addition to the
+    /// Mozilla root certificates, eg. to dial peers using self-signed certificates
+    pub trusted_certs: Vec<PathBuf>,
+
+    /// Addresses advertised to peers through Identify and discovery in addition to the listen
+    /// address, eg. the public `/dns/example.com/tcp/443/wss` address of a TLS-terminating proxy
+    pub advertised_addrs: Vec<Multiaddr>,
+}
+
+/// Certificate and private key of a secure WebSocket listener
+#[derive(Clone, Debug)]
+pub struct WebSocketTlsConfig {
+    /// PEM file with the certificate chain, starting with the certificate of this node
+    pub cert_file: PathBuf,
+
+    /// PEM file with the private key of the certificate, in PKCS#8, PKCS#1 or SEC1 format
+    pub key_file: PathBuf,
+}
+
+impl WebSocketConfig {
+    fn tls_config(&self) -> Result<Option<tls::Config>, eyre::Report> {
+        if self.tls.is_none() && self.trusted_certs.is_empty() {
+            return Ok(None);
+        }
+
+        let mut builder = tls::Config::builder();
+
+        if let Some(tls) = &self.tls {
+            let key = PrivateKeyDer::from_pem_file(&tls.key_file).wrap_err_with(|| {
+                format!("Failed to read TLS key from {}", tls.key_file.display())
+            })?;
+
+            let certs = read_certs(&tls.cert_file)?;
+
+            builder.server(tls::PrivateKey::new(key.secret_der().to_vec()), certs)?;
+        }
+
+        for path in &self.trusted_certs {
+            for cert in read_certs(path)? {
+                builder.add_trust(&cert)?;
+            }
+        }
+
+        Ok(Some(builder.finish()))
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<tls::Certificate>, eyre::Report> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map(|certs| {
+            certs
+                .into_iter()
+                .map(|cert| tls::Certificate::new(cert.to_vec()))
+                .collect()
+        })
+        .wrap_err_with(|| format!("Failed to read TLS certificates from {}", path.display()))
+}
+
+/// WebSocket transport over TCP, with DNS resolution, Noise and/or TLS encryption and Yamux multiplexing.
+///
+/// Used alongside the plain TCP transport, so that nodes listening on a WebSocket address
+/// can still dial the peers listening on a TCP address.
+pub(crate) fn transport(
+    keypair: &Keypair,
+    security: SecurityProtocol,
+    config: &WebSocketConfig,
+) -> Result<Boxed<(libp2p::PeerId, StreamMuxerBox)>, eyre::Report> {
+    let tcp = tcp::tokio::Transport::new(tcp::Config::new().nodelay(true));
+    let mut ws = libp2p::websocket::Config::new(dns::tokio::Transport::system(tcp)?);
+
+    if let Some(tls) = config.tls_config()? {
+        ws.set_tls_config(tls);
+    }
+
+    Ok(ws
+        .upgrade(upgrade::Version::V1Lazy)
+        .authenticate(SecurityUpgrade::new(keypair, security)?)
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed())
+}
@@ -4,10 +4,13 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use libp2p_identity::PeerId;
 use malachitebft_config::TransportProtocol;
 use malachitebft_metrics::SharedRegistry;
-use malachitebft_network::{spawn, Config, DiscoveryConfig, Keypair, PeerIdExt, ProtocolNames};
+use malachitebft_network::handle::RecvHandle;
+use malachitebft_network::{
+    spawn, Config, DiscoveryConfig, Event, Keypair, PeerIdExt, ProtocolNames,
+};
 use malachitebft_starknet_host::types::PrivateKey;
 use rand::{rngs::StdRng, Rng, SeedableRng};
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
 use tracing::{debug, info};
 
 //---------------------------------------------------------------------
@@ -158,17 +161,31 @@ impl<const N: usize> Test<N> {
                     })
                     .collect(),
                 persistent_peers_only: false,
-                discovery: discovery_config,
+                explicit_peers: vec![],
+                discovery: discovery_config.clone(),
                 idle_connection_timeout: Duration::from_secs(60),
                 transport: malachitebft_network::TransportProtocol::Quic,
+                security: Default::default(),
+                dial_timeouts: Default::default(),
+                bandwidth: Default::default(),
+                liveness: Default::default(),
+                connection_limits: Default::default(),
+                websocket: Default::default(),
                 gossipsub: malachitebft_network::GossipSubConfig::default(),
                 pubsub_protocol: malachitebft_network::PubSubProtocol::default(),
                 channel_names: malachitebft_network::ChannelNames::default(),
-                rpc_max_size: 10 * 1024 * 1024,   // 10 MiB
+                rpc_max_size: 10 * 1024 * 1024, // 10 MiB
+                rpc_max_chunked_size: None,
                 pubsub_max_size: 4 * 1024 * 1024, // 4 MiB
+                max_message_sizes: Default::default(),
+                hole_punch: Default::default(),
+                compression: Default::default(),
+                mempool: Default::default(),
                 enable_consensus: true,
                 enable_sync: false,
                 protocol_names: ProtocolNames::default(),
+                chain_ids: Vec::new(),
+                node_key_file: None,
             };
 
             // Apply custom configuration if provided
@@ -182,7 +199,7 @@ impl<const N: usize> Test<N> {
         init_logging();
         info!("Starting test with {} nodes", N);
 
-        let configs = self.generate_default_configs(self.discovery_config);
+        let configs = self.generate_default_configs(self.discovery_config.clone());
         debug!("Generated configs");
 
         let mut handles = Vec::with_capacity(N);
@@ -371,6 +388,25 @@ impl TestNode {
 // Helpers
 //---------------------------------------------------------------------
 
+/// Wait for an event matching the predicate, skipping the other ones
+pub async fn wait_for(
+    handle: &mut RecvHandle,
+    wait: Duration,
+    f: impl Fn(&Event) -> bool,
+) -> Option<Event> {
+    timeout(wait, async {
+        while let Some(event) = handle.recv().await {
+            if f(&event) {
+                return Some(event);
+            }
+        }
+        None
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
 fn init_logging() {
     use tracing_subscriber::util::SubscriberInitExt;
     use tracing_subscriber::{EnvFilter, FmtSubscriber};
@@ -77,15 +77,29 @@ fn make_config(port: u16, persistent_peers: Vec<u16>) -> Config {
         },
         idle_connection_timeout: Duration::from_secs(60),
         transport: malachitebft_network::TransportProtocol::Tcp,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
         gossipsub: GossipSubConfig::default(),
         pubsub_protocol: PubSubProtocol::default(),
         channel_names: ChannelNames::default(),
         rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
         pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
         enable_consensus: true,
         enable_sync: false,
         protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
         persistent_peers_only: false,
+        explicit_peers: vec![],
     }
 }
 
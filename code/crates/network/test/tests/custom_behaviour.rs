@@ -0,0 +1,130 @@
+//! Behaviour provided by the application, running alongside the behaviours of the network.
+
+use std::time::Duration;
+
+use libp2p::futures::StreamExt;
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::swarm::{Swarm, SwarmEvent};
+use libp2p::{StreamProtocol, SwarmBuilder};
+use malachitebft_config::TransportProtocol;
+use malachitebft_metrics::Registry;
+use malachitebft_network::behaviour::{Behaviour, NetworkEvent};
+use malachitebft_network::{
+    Config, DiscoveryConfig, Keypair, NetworkIdentity, ProtocolNames, PubSubProtocol,
+};
+use tokio::time::timeout;
+
+type Snapshots = request_response::cbor::Behaviour<String, String>;
+
+fn make_config(port: usize) -> Config {
+    Config {
+        listen_addr: TransportProtocol::Quic.multiaddr("127.0.0.1", port),
+        persistent_peers: vec![],
+        persistent_peers_only: false,
+        explicit_peers: vec![],
+        discovery: DiscoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        idle_connection_timeout: Duration::from_secs(60),
+        transport: malachitebft_network::TransportProtocol::Quic,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
+        gossipsub: malachitebft_network::GossipSubConfig::default(),
+        pubsub_protocol: PubSubProtocol::Broadcast,
+        channel_names: malachitebft_network::ChannelNames::default(),
+        rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
+        pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
+        enable_consensus: true,
+        enable_sync: false,
+        protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
+    }
+}
+
+fn make_swarm(name: &str, config: &Config) -> Swarm<Behaviour<Snapshots>> {
+    let identity = NetworkIdentity::new(name.to_string(), Keypair::generate_ed25519(), None);
+
+    let snapshots = Snapshots::new(
+        [(
+            StreamProtocol::new("/test-snapshots/1"),
+            ProtocolSupport::Full,
+        )],
+        request_response::Config::default(),
+    );
+
+    let behaviour =
+        Behaviour::new_with_metrics(config, &identity, &mut Registry::default(), snapshots)
+            .unwrap();
+
+    SwarmBuilder::with_existing_identity(identity.keypair.clone())
+        .with_tokio()
+        .with_quic()
+        .with_behaviour(|_| behaviour)
+        .unwrap()
+        .with_swarm_config(|cfg| cfg.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build()
+}
+
+/// The events of the behaviour provided by the application are emitted as
+/// `NetworkEvent::Custom`, and it shares the connection with the other behaviours
+#[tokio::test]
+async fn custom_behaviour_events_are_forwarded() {
+    let base_port = 41000;
+
+    let config1 = make_config(base_port);
+    let mut swarm1 = make_swarm("node-1", &config1);
+    swarm1.listen_on(config1.listen_addr.clone()).unwrap();
+
+    let config2 = make_config(base_port + 1);
+    let mut swarm2 = make_swarm("node-2", &config2);
+
+    let peer_id1 = *swarm1.local_peer_id();
+    swarm2.dial(config1.listen_addr.clone()).unwrap();
+
+    let request = timeout(Duration::from_secs(10), async {
+        loop {
+            tokio::select! {
+                event = swarm1.select_next_some() => {
+                    if let SwarmEvent::Behaviour(NetworkEvent::Custom(event)) = event {
+                        let event = event
+                            .downcast::<request_response::Event<String, String>>()
+                            .expect("Event should be of the custom behaviour");
+
+                        if let request_response::Event::Message {
+                            message: request_response::Message::Request { request, .. },
+                            ..
+                        } = event
+                        {
+                            return request;
+                        }
+                    }
+                }
+                event = swarm2.select_next_some() => {
+                    if let SwarmEvent::ConnectionEstablished { peer_id, .. } = event {
+                        swarm2
+                            .behaviour_mut()
+                            .custom
+                            .get_mut()
+                            .send_request(&peer_id, "snapshot at height 1".to_string());
+                    }
+                }
+            }
+        }
+    })
+    .await
+    .expect("Request of the custom behaviour should be received");
+
+    assert_eq!(request, "snapshot at height 1");
+    assert!(swarm2.is_connected(&peer_id1));
+}
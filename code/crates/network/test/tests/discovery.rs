@@ -1,7 +1,7 @@
 use std::{time::Duration, vec};
 
 use arc_malachitebft_discovery_test::{Expected, Test, TestNode};
-use malachitebft_network::{BootstrapProtocol, DiscoveryConfig, Selector};
+use malachitebft_network::{BootstrapProtocol, DiscoveryConfig, ReconnectConfig, Selector};
 
 // Ensuring that having the node's address in the bootstrap set does not cause
 // any issues.
@@ -60,6 +60,39 @@ pub async fn circular_graph() {
     test.run().await
 }
 
+// Testing the circular bootstrap sets graph with the latency selector.
+// All the nodes run locally with similar round-trip times, the selection of the peers
+// by increasing round-trip time is tested in the unit tests of the selector.
+#[tokio::test]
+pub async fn circular_graph_latency_selector() {
+    let test = Test::new(
+        [
+            TestNode::correct(0, vec![4]),
+            TestNode::correct(1, vec![0]),
+            TestNode::correct(2, vec![1]),
+            TestNode::correct(3, vec![2]),
+            TestNode::correct(4, vec![3]),
+        ],
+        [
+            Expected::Exactly(vec![1, 2, 3, 4]),
+            Expected::Exactly(vec![0, 2, 3, 4]),
+            Expected::Exactly(vec![0, 1, 3, 4]),
+            Expected::Exactly(vec![0, 1, 2, 4]),
+            Expected::Exactly(vec![0, 1, 2, 3]),
+        ],
+        Duration::from_secs(0),
+        Duration::from_secs(10),
+        DiscoveryConfig {
+            enabled: true,
+            bootstrap_protocol: BootstrapProtocol::Full,
+            selector: Selector::Latency,
+            ..Default::default()
+        },
+    );
+
+    test.run().await
+}
+
 // Testing a circular bootstrap sets graph with N nodes.
 #[tokio::test]
 pub async fn circular_graph_n() {
@@ -197,6 +230,12 @@ pub async fn multiple_persistent_peers_only_nodes() {
         DiscoveryConfig {
             enabled: false, // Disabled for explicit control
             num_inbound_peers: 10,
+            // Nodes 0 and 1 only accept each other once identified through their own dials,
+            // so let them dial each other in lockstep with the periodic timer
+            reconnect: ReconnectConfig {
+                spread_per_peer: Duration::ZERO,
+                ..Default::default()
+            },
             ..Default::default()
         },
     );
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use arc_malachitebft_discovery_test::wait_for;
+use malachitebft_config::TransportProtocol;
+use malachitebft_network::handle::{CtrlHandle, Handle, RecvHandle};
+use malachitebft_network::{
+    spawn, Config, DiscoveryConfig, Event, Keypair, LivenessConfig, NetworkIdentity, ProtocolNames,
+};
+
+fn make_config(port: usize, persistent_peers: Vec<usize>, liveness: LivenessConfig) -> Config {
+    Config {
+        listen_addr: TransportProtocol::Quic.multiaddr("127.0.0.1", port),
+        persistent_peers: persistent_peers
+            .into_iter()
+            .map(|port| TransportProtocol::Quic.multiaddr("127.0.0.1", port))
+            .collect(),
+        persistent_peers_only: false,
+        explicit_peers: vec![],
+        discovery: DiscoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        idle_connection_timeout: Duration::from_secs(60),
+        transport: malachitebft_network::TransportProtocol::Quic,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness,
+        connection_limits: Default::default(),
+        websocket: Default::default(),
+        gossipsub: malachitebft_network::GossipSubConfig::default(),
+        pubsub_protocol: malachitebft_network::PubSubProtocol::default(),
+        channel_names: malachitebft_network::ChannelNames::default(),
+        rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
+        pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
+        enable_consensus: true,
+        enable_sync: false,
+        protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
+    }
+}
+
+async fn spawn_node(name: &str, config: Config) -> (RecvHandle, CtrlHandle) {
+    let handle = spawn(
+        NetworkIdentity::new(name.to_string(), Keypair::generate_ed25519(), None),
+        config,
+        malachitebft_metrics::SharedRegistry::global().with_moniker(name.to_string()),
+    )
+    .await
+    .unwrap();
+
+    Handle::split(handle)
+}
+
+/// The connection to a peer whose pings take longer than allowed is closed
+#[tokio::test]
+async fn test_slow_peer_is_disconnected() {
+    let base_port = 44000;
+
+    let liveness = LivenessConfig {
+        max_ping_failures: Some(1),
+        max_ping_rtt: Some(Duration::from_nanos(1)),
+    };
+
+    let (mut recv1, ctrl1) = spawn_node("node-1", make_config(base_port, vec![], liveness)).await;
+    let (mut recv2, ctrl2) = spawn_node(
+        "node-2",
+        make_config(base_port + 1, vec![base_port], Default::default()),
+    )
+    .await;
+
+    assert!(
+        wait_for(&mut recv1, Duration::from_secs(10), |e| {
+            matches!(e, Event::PeerConnected(_))
+        })
+        .await
+        .is_some(),
+        "Peer should connect"
+    );
+
+    assert!(
+        wait_for(&mut recv1, Duration::from_secs(10), |e| {
+            matches!(e, Event::PeerDisconnected(_))
+        })
+        .await
+        .is_some(),
+        "Slow peer should be disconnected"
+    );
+
+    assert!(
+        wait_for(&mut recv2, Duration::from_secs(10), |e| {
+            matches!(e, Event::PeerDisconnected(_))
+        })
+        .await
+        .is_some(),
+        "Connection should be closed on both sides"
+    );
+
+    ctrl1.shutdown().await.unwrap();
+    ctrl2.shutdown().await.unwrap();
+}
@@ -0,0 +1,127 @@
+//! Transactions are gossiped on the mempool channel when enabled, within its own limits
+
+use std::time::Duration;
+
+use arc_malachitebft_discovery_test::wait_for;
+use malachitebft_config::TransportProtocol;
+use malachitebft_network::handle::{CtrlHandle, Handle, RecvHandle};
+use malachitebft_network::{
+    spawn, Bytes, Channel, Config, DiscoveryConfig, Event, GossipSubConfig, Keypair, MempoolConfig,
+    NetworkIdentity, ProtocolNames, PubSubProtocol,
+};
+
+fn make_config(port: usize, persistent_peers: Vec<usize>) -> Config {
+    Config {
+        listen_addr: TransportProtocol::Quic.multiaddr("127.0.0.1", port),
+        persistent_peers: persistent_peers
+            .into_iter()
+            .map(|port| TransportProtocol::Quic.multiaddr("127.0.0.1", port))
+            .collect(),
+        persistent_peers_only: false,
+        explicit_peers: vec![],
+        discovery: DiscoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        idle_connection_timeout: Duration::from_secs(60),
+        transport: malachitebft_network::TransportProtocol::Quic,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
+        gossipsub: GossipSubConfig::default(),
+        pubsub_protocol: PubSubProtocol::GossipSub,
+        channel_names: malachitebft_network::ChannelNames::default(),
+        rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
+        pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: MempoolConfig {
+            enabled: true,
+            max_msg_size: 1024,
+            max_msgs_per_peer: Some(100),
+        },
+        enable_consensus: true,
+        enable_sync: false,
+        protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
+    }
+}
+
+async fn spawn_node(name: &str, config: Config) -> (RecvHandle, CtrlHandle) {
+    let handle = spawn(
+        NetworkIdentity::new(name.to_string(), Keypair::generate_ed25519(), None),
+        config,
+        malachitebft_metrics::SharedRegistry::global().with_moniker(name.to_string()),
+    )
+    .await
+    .unwrap();
+
+    Handle::split(handle)
+}
+
+fn is_mempool_message(event: &Event) -> bool {
+    matches!(event, Event::MempoolMessage(..))
+}
+
+#[tokio::test]
+async fn transactions_are_gossiped_on_the_mempool_channel() {
+    let base_port = 47000;
+
+    let (mut recv1, ctrl1) = spawn_node("node-1", make_config(base_port, vec![])).await;
+    let (mut recv2, ctrl2) =
+        spawn_node("node-2", make_config(base_port + 1, vec![base_port])).await;
+
+    let connected = wait_for(&mut recv1, Duration::from_secs(10), |e| {
+        matches!(e, Event::PeerConnected(_))
+    })
+    .await;
+    assert!(connected.is_some(), "Peers should connect");
+
+    // Publish until the subscriptions have been exchanged
+    let mut received = None;
+    for i in 0..20 {
+        ctrl1
+            .publish(Channel::Mempool, Bytes::from(format!("tx-{i}")))
+            .await
+            .unwrap();
+
+        received = wait_for(&mut recv2, Duration::from_millis(500), is_mempool_message).await;
+
+        if received.is_some() {
+            break;
+        }
+    }
+
+    match received {
+        Some(Event::MempoolMessage(_, data)) => assert!(data.starts_with(b"tx-")),
+        other => panic!("Expected transactions on the mempool channel, got {other:?}"),
+    }
+
+    // Drain the transactions published while the subscriptions were exchanged
+    while wait_for(&mut recv2, Duration::from_millis(500), is_mempool_message)
+        .await
+        .is_some()
+    {}
+
+    // Messages larger than `max_msg_size` are not published
+    ctrl1
+        .publish(Channel::Mempool, Bytes::from(vec![0; 2048]))
+        .await
+        .unwrap();
+
+    let oversized = wait_for(&mut recv2, Duration::from_secs(2), is_mempool_message).await;
+    assert!(
+        oversized.is_none(),
+        "Oversized message was gossiped: {oversized:?}"
+    );
+
+    for ctrl in [ctrl1, ctrl2] {
+        ctrl.shutdown().await.unwrap();
+    }
+}
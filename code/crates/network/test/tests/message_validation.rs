@@ -0,0 +1,140 @@
+//! GossipSub messages are only forwarded to the other peers once validated by the application
+
+use std::time::Duration;
+
+use arc_malachitebft_discovery_test::wait_for;
+use malachitebft_config::TransportProtocol;
+use malachitebft_network::handle::{CtrlHandle, Handle, RecvHandle};
+use malachitebft_network::{
+    spawn, Bytes, Channel, Config, DiscoveryConfig, Event, GossipSubConfig, Keypair,
+    MessageAcceptance, NetworkIdentity, ProtocolNames, PubSubProtocol,
+};
+
+fn make_config(port: usize, persistent_peers: Vec<usize>) -> Config {
+    Config {
+        listen_addr: TransportProtocol::Quic.multiaddr("127.0.0.1", port),
+        persistent_peers: persistent_peers
+            .into_iter()
+            .map(|port| TransportProtocol::Quic.multiaddr("127.0.0.1", port))
+            .collect(),
+        persistent_peers_only: false,
+        explicit_peers: vec![],
+        discovery: DiscoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        idle_connection_timeout: Duration::from_secs(60),
+        transport: malachitebft_network::TransportProtocol::Quic,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
+        gossipsub: GossipSubConfig {
+            validate_messages: true,
+            ..Default::default()
+        },
+        pubsub_protocol: PubSubProtocol::GossipSub,
+        channel_names: malachitebft_network::ChannelNames::default(),
+        rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
+        pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
+        enable_consensus: true,
+        enable_sync: false,
+        protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
+    }
+}
+
+async fn spawn_node(name: &str, config: Config) -> (RecvHandle, CtrlHandle) {
+    let handle = spawn(
+        NetworkIdentity::new(name.to_string(), Keypair::generate_ed25519(), None),
+        config,
+        malachitebft_metrics::SharedRegistry::global().with_moniker(name.to_string()),
+    )
+    .await
+    .unwrap();
+
+    Handle::split(handle)
+}
+
+fn is_unvalidated_message(event: &Event) -> bool {
+    matches!(event, Event::UnvalidatedMessage(..))
+}
+
+/// With three nodes connected in a line, the message published by the first one
+/// only reaches the last one once the node in the middle accepts it
+#[tokio::test]
+async fn messages_are_forwarded_once_accepted() {
+    let base_port = 42000;
+
+    let (mut recv1, ctrl1) = spawn_node("node-1", make_config(base_port, vec![])).await;
+    let (mut recv2, ctrl2) =
+        spawn_node("node-2", make_config(base_port + 1, vec![base_port])).await;
+    let (mut recv3, ctrl3) =
+        spawn_node("node-3", make_config(base_port + 2, vec![base_port + 1])).await;
+
+    for node in [&mut recv1, &mut recv3] {
+        let connected = wait_for(node, Duration::from_secs(10), |e| {
+            matches!(e, Event::PeerConnected(_))
+        })
+        .await;
+
+        assert!(connected.is_some(), "Peers should connect");
+    }
+
+    // Publish until the subscriptions have been exchanged
+    let mut received = None;
+    for i in 0..20 {
+        ctrl1
+            .publish(Channel::Consensus, Bytes::from(format!("message-{i}")))
+            .await
+            .unwrap();
+
+        received = wait_for(
+            &mut recv2,
+            Duration::from_millis(500),
+            is_unvalidated_message,
+        )
+        .await;
+
+        if received.is_some() {
+            break;
+        }
+    }
+
+    let Some(Event::UnvalidatedMessage(message_id, channel, _, data)) = received else {
+        panic!("Expected a message to validate, got {received:?}");
+    };
+    assert_eq!(channel, Channel::Consensus);
+
+    // Not forwarded while pending validation
+    let forwarded = wait_for(&mut recv3, Duration::from_secs(2), is_unvalidated_message).await;
+    assert!(
+        forwarded.is_none(),
+        "Message forwarded before validation: {forwarded:?}"
+    );
+
+    ctrl2
+        .validate_message(message_id, MessageAcceptance::Accept)
+        .await
+        .unwrap();
+
+    let forwarded = wait_for(&mut recv3, Duration::from_secs(10), is_unvalidated_message).await;
+    match forwarded {
+        Some(Event::UnvalidatedMessage(_, Channel::Consensus, _, forwarded_data)) => {
+            assert_eq!(forwarded_data, data)
+        }
+        other => panic!("Expected the accepted message to be forwarded, got {other:?}"),
+    }
+
+    for ctrl in [ctrl1, ctrl2, ctrl3] {
+        ctrl.shutdown().await.unwrap();
+    }
+}
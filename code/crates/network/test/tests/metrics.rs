@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use malachitebft_config::TransportProtocol;
+use malachitebft_network::handle::Handle;
+use malachitebft_network::{
+    spawn, Config, DiscoveryConfig, Event, Keypair, NetworkIdentity, ProtocolNames,
+};
+use tokio::time::timeout;
+
+fn make_config(port: usize, persistent_peers: Vec<usize>) -> Config {
+    Config {
+        listen_addr: TransportProtocol::Tcp.multiaddr("127.0.0.1", port),
+        persistent_peers: persistent_peers
+            .into_iter()
+            .map(|port| TransportProtocol::Tcp.multiaddr("127.0.0.1", port))
+            .collect(),
+        persistent_peers_only: false,
+        explicit_peers: vec![],
+        discovery: DiscoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        idle_connection_timeout: Duration::from_secs(60),
+        transport: malachitebft_network::TransportProtocol::Tcp,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
+        gossipsub: malachitebft_network::GossipSubConfig::default(),
+        pubsub_protocol: malachitebft_network::PubSubProtocol::default(),
+        channel_names: malachitebft_network::ChannelNames::default(),
+        rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
+        pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
+        enable_consensus: true,
+        enable_sync: false,
+        protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
+    }
+}
+
+async fn spawn_node(name: &str, config: Config) -> Handle {
+    spawn(
+        NetworkIdentity::new(name.to_string(), Keypair::generate_ed25519(), None),
+        config,
+        malachitebft_metrics::SharedRegistry::global().with_moniker(name.to_string()),
+    )
+    .await
+    .unwrap()
+}
+
+/// The swarm and transport metrics of libp2p are exported along with the other metrics
+#[tokio::test]
+async fn test_swarm_metrics_are_exported() {
+    let base_port = 46000;
+
+    let mut node1 = spawn_node("node-1", make_config(base_port, vec![])).await;
+    let node2 = spawn_node(
+        "node-2",
+        make_config(base_port + 1, vec![base_port, base_port + 2]),
+    )
+    .await;
+
+    let connected = timeout(Duration::from_secs(10), async {
+        while let Some(event) = node1.recv().await {
+            if matches!(event, Event::PeerConnected(_)) {
+                return true;
+            }
+        }
+        false
+    })
+    .await
+    .unwrap_or(false);
+    assert!(connected, "Peer should connect");
+
+    // Let the dial to the missing peer fail
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let mut exported = String::new();
+    malachitebft_metrics::export(&mut exported);
+
+    // Samples of the swarm events handled by the network, not just the metric declarations
+    for sample in [
+        r#"libp2p_swarm_connections_established_total{moniker="node-1",role="Listener""#,
+        r#"libp2p_swarm_new_listen_addr_total{moniker="node-2""#,
+        r#"libp2p_swarm_outgoing_connection_error_total{moniker="node-2""#,
+        r#"libp2p_identify_received_total{moniker="node-1"} 1"#,
+        r#"libp2p_bandwidth_bytes_total{moniker="node-1""#,
+    ] {
+        assert!(exported.contains(sample), "Missing metric sample {sample}");
+    }
+
+    node1.shutdown().await.unwrap();
+    node2.shutdown().await.unwrap();
+}
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use arc_malachitebft_discovery_test::wait_for;
+use malachitebft_config::TransportProtocol;
+use malachitebft_network::handle::{CtrlHandle, Handle, RecvHandle};
+use malachitebft_network::{
+    spawn_chains, Bytes, Channel, Config, DiscoveryConfig, Event, Keypair, NetworkIdentity, PeerId,
+    ProtocolNames, PubSubProtocol,
+};
+use malachitebft_sync::RawMessage;
+use tokio::time::timeout;
+
+fn make_config(port: usize, persistent_peers: Vec<usize>) -> Config {
+    Config {
+        listen_addr: TransportProtocol::Quic.multiaddr("127.0.0.1", port),
+        persistent_peers: persistent_peers
+            .into_iter()
+            .map(|port| TransportProtocol::Quic.multiaddr("127.0.0.1", port))
+            .collect(),
+        persistent_peers_only: false,
+        explicit_peers: vec![],
+        discovery: DiscoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        idle_connection_timeout: Duration::from_secs(60),
+        transport: malachitebft_network::TransportProtocol::Quic,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
+        gossipsub: malachitebft_network::GossipSubConfig::default(),
+        pubsub_protocol: PubSubProtocol::Broadcast,
+        channel_names: malachitebft_network::ChannelNames::default(),
+        rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
+        pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
+        enable_consensus: true,
+        enable_sync: true,
+        protocol_names: ProtocolNames::default(),
+        chain_ids: vec!["chain-a".to_string(), "chain-b".to_string()],
+        node_key_file: None,
+    }
+}
+
+async fn spawn_node(name: &str, config: Config) -> (PeerId, Vec<RecvHandle>, Vec<CtrlHandle>) {
+    let handles = spawn_chains(
+        NetworkIdentity::new(name.to_string(), Keypair::generate_ed25519(), None),
+        config,
+        malachitebft_metrics::SharedRegistry::global().with_moniker(name.to_string()),
+    )
+    .await
+    .unwrap();
+
+    let peer_id = handles[0].peer_id();
+    let (recv, ctrl) = handles.into_iter().map(Handle::split).unzip();
+    (peer_id, recv, ctrl)
+}
+
+fn is_consensus_message(event: &Event) -> bool {
+    matches!(event, Event::ConsensusMessage(..))
+}
+
+/// Two nodes serving the same two chains over a single connection only deliver
+/// the messages and sync requests of each chain to the handle of that chain
+#[tokio::test]
+async fn messages_are_routed_to_their_chain() {
+    let base_port = 38000;
+
+    let (peer_id1, mut recv1, ctrl1) = spawn_node("node-1", make_config(base_port, vec![])).await;
+    let (_, mut recv2, ctrl2) =
+        spawn_node("node-2", make_config(base_port + 1, vec![base_port])).await;
+
+    assert_eq!(recv1.len(), 2);
+    assert_eq!(recv2.len(), 2);
+
+    // The connection is reported to the handles of all the chains
+    for handle in recv1.iter_mut().chain(recv2.iter_mut()) {
+        let connected = wait_for(handle, Duration::from_secs(10), |e| {
+            matches!(e, Event::PeerConnected(_))
+        })
+        .await;
+
+        assert!(connected.is_some(), "Peer should connect");
+    }
+
+    // Publish on the second chain until the broadcast subscriptions have been exchanged
+    let mut received = None;
+    for _ in 0..20 {
+        ctrl2[1]
+            .publish(Channel::Consensus, Bytes::from_static(b"chain-b"))
+            .await
+            .unwrap();
+
+        received = wait_for(
+            &mut recv1[1],
+            Duration::from_millis(500),
+            is_consensus_message,
+        )
+        .await;
+        if received.is_some() {
+            break;
+        }
+    }
+
+    match received {
+        Some(Event::ConsensusMessage(Channel::Consensus, _, data)) => {
+            assert_eq!(data.as_ref(), b"chain-b")
+        }
+        other => panic!("Expected a consensus message on the second chain, got {other:?}"),
+    }
+
+    let leaked = wait_for(
+        &mut recv1[0],
+        Duration::from_millis(500),
+        is_consensus_message,
+    )
+    .await;
+    assert!(
+        leaked.is_none(),
+        "Message leaked to the first chain: {leaked:?}"
+    );
+
+    // Sync requests and responses of the first chain
+    ctrl2[0]
+        .sync_request(peer_id1, Bytes::from_static(b"request-a"))
+        .await
+        .unwrap();
+
+    let request = wait_for(&mut recv1[0], Duration::from_secs(10), |e| {
+        matches!(e, Event::Sync(_))
+    })
+    .await;
+
+    let Some(Event::Sync(RawMessage::Request {
+        request_id, body, ..
+    })) = request
+    else {
+        panic!("Expected a sync request on the first chain, got {request:?}");
+    };
+    assert_eq!(body.as_ref(), b"request-a");
+
+    ctrl1[0]
+        .sync_reply(request_id, Bytes::from_static(b"response-a"))
+        .await
+        .unwrap();
+
+    let response = wait_for(&mut recv2[0], Duration::from_secs(10), |e| {
+        matches!(e, Event::Sync(_))
+    })
+    .await;
+
+    let Some(Event::Sync(RawMessage::Response { body, .. })) = response else {
+        panic!("Expected a sync response on the first chain, got {response:?}");
+    };
+    assert_eq!(body.as_ref(), b"response-a");
+
+    let leaked = wait_for(&mut recv1[1], Duration::from_millis(500), |e| {
+        matches!(e, Event::Sync(_))
+    })
+    .await;
+    assert!(
+        leaked.is_none(),
+        "Sync request leaked to the second chain: {leaked:?}"
+    );
+
+    // Messages sent directly to a peer on the second chain, and their replies
+    let rx_reply = ctrl2[1]
+        .send_to_peer(peer_id1, Bytes::from_static(b"message-b"))
+        .await
+        .unwrap();
+
+    let message = wait_for(&mut recv1[1], Duration::from_secs(10), |e| {
+        matches!(e, Event::PeerMessage { .. })
+    })
+    .await;
+
+    let Some(Event::PeerMessage {
+        request_id,
+        payload,
+        ..
+    }) = message
+    else {
+        panic!("Expected a peer message on the second chain, got {message:?}");
+    };
+    assert_eq!(payload.as_ref(), b"message-b");
+
+    ctrl1[1]
+        .reply_to_peer(request_id, Some(Bytes::from_static(b"reply-b")))
+        .await
+        .unwrap();
+
+    let reply = timeout(Duration::from_secs(10), rx_reply)
+        .await
+        .expect("Peer should reply")
+        .unwrap()
+        .unwrap();
+    assert_eq!(reply.as_ref(), b"reply-b");
+
+    // Dropping a message without replying fails the request right away
+    let rx_reply = ctrl2[1]
+        .send_to_peer(peer_id1, Bytes::from_static(b"dropped"))
+        .await
+        .unwrap();
+
+    let Some(Event::PeerMessage { request_id, .. }) =
+        wait_for(&mut recv1[1], Duration::from_secs(10), |e| {
+            matches!(e, Event::PeerMessage { .. })
+        })
+        .await
+    else {
+        panic!("Expected a peer message on the second chain");
+    };
+
+    ctrl1[1].reply_to_peer(request_id, None).await.unwrap();
+
+    let reply = timeout(Duration::from_secs(10), rx_reply)
+        .await
+        .expect("Request should fail")
+        .unwrap();
+    assert!(reply.is_err(), "Expected an error, got {reply:?}");
+
+    // The network stops once all the chains are shut down
+    for ctrl in ctrl1.iter().chain(ctrl2.iter()) {
+        ctrl.shutdown().await.unwrap();
+    }
+
+    for ctrl in ctrl1.into_iter().chain(ctrl2) {
+        timeout(Duration::from_secs(10), ctrl.join())
+            .await
+            .expect("Network should stop")
+            .unwrap();
+    }
+}
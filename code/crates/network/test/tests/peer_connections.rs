@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use arc_malachitebft_discovery_test::wait_for;
+use malachitebft_config::TransportProtocol;
+use malachitebft_network::handle::{CtrlHandle, Handle, RecvHandle};
+use malachitebft_network::{
+    spawn, Config, DiscoveryConfig, Event, Keypair, NetworkIdentity, PeerConnectionError,
+    PeerIdExt, ProtocolNames,
+};
+
+fn make_config(port: usize) -> Config {
+    Config {
+        listen_addr: TransportProtocol::Quic.multiaddr("127.0.0.1", port),
+        persistent_peers: vec![],
+        persistent_peers_only: false,
+        explicit_peers: vec![],
+        discovery: DiscoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        idle_connection_timeout: Duration::from_secs(60),
+        transport: malachitebft_network::TransportProtocol::Quic,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
+        gossipsub: malachitebft_network::GossipSubConfig::default(),
+        pubsub_protocol: malachitebft_network::PubSubProtocol::default(),
+        channel_names: malachitebft_network::ChannelNames::default(),
+        rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
+        pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
+        enable_consensus: true,
+        enable_sync: false,
+        protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
+    }
+}
+
+async fn spawn_node(name: &str, config: Config) -> (RecvHandle, CtrlHandle) {
+    let handle = spawn(
+        NetworkIdentity::new(name.to_string(), Keypair::generate_ed25519(), None),
+        config,
+        malachitebft_metrics::SharedRegistry::global().with_moniker(name.to_string()),
+    )
+    .await
+    .unwrap();
+
+    Handle::split(handle)
+}
+
+/// A peer dialed on request is listed among the connected peers until it is disconnected
+#[tokio::test]
+async fn test_dial_list_and_disconnect_peer() {
+    let base_port = 48000;
+
+    let (mut recv1, ctrl1) = spawn_node("node-1", make_config(base_port)).await;
+    let (_recv2, ctrl2) = spawn_node("node-2", make_config(base_port + 1)).await;
+
+    assert!(ctrl1.list_peers().await.unwrap().is_empty());
+
+    let addr = TransportProtocol::Quic.multiaddr("127.0.0.1", base_port + 1);
+    assert_eq!(ctrl1.dial_peer(addr).await.unwrap(), Ok(()));
+
+    let Some(Event::PeerConnected(peer_id)) = wait_for(&mut recv1, Duration::from_secs(10), |e| {
+        matches!(e, Event::PeerConnected(_))
+    })
+    .await
+    else {
+        panic!("Dialed peer should connect");
+    };
+
+    let peers = ctrl1.list_peers().await.unwrap();
+    assert_eq!(peers.len(), 1);
+    assert_eq!(peers[0].peer_id, peer_id.to_libp2p());
+    assert!(!peers[0].addresses.is_empty());
+
+    assert_eq!(ctrl1.disconnect_peer(peer_id).await.unwrap(), Ok(()));
+
+    assert!(
+        wait_for(&mut recv1, Duration::from_secs(10), |e| {
+            matches!(e, Event::PeerDisconnected(id) if *id == peer_id)
+        })
+        .await
+        .is_some(),
+        "Peer should be disconnected"
+    );
+
+    assert!(ctrl1.list_peers().await.unwrap().is_empty());
+    assert_eq!(
+        ctrl1.disconnect_peer(peer_id).await.unwrap(),
+        Err(PeerConnectionError::NotConnected)
+    );
+
+    ctrl1.shutdown().await.unwrap();
+    ctrl2.shutdown().await.unwrap();
+}
@@ -2,8 +2,8 @@ use std::time::Duration;
 
 use malachitebft_config::TransportProtocol;
 use malachitebft_network::{
-    spawn, Config, DiscoveryConfig, Event, Keypair, NetworkIdentity, PersistentPeerError,
-    ProtocolNames,
+    spawn, BackoffConfig, Config, DiscoveryConfig, Event, Keypair, NetworkIdentity,
+    PersistentPeerError, ProtocolNames,
 };
 use tokio::time::sleep;
 
@@ -12,20 +12,34 @@ fn make_config(port: usize) -> Config {
         listen_addr: TransportProtocol::Quic.multiaddr("127.0.0.1", port),
         persistent_peers: vec![],
         persistent_peers_only: false,
+        explicit_peers: vec![],
         discovery: DiscoveryConfig {
             enabled: false,
             ..Default::default()
         },
         idle_connection_timeout: Duration::from_secs(60),
         transport: malachitebft_network::TransportProtocol::Quic,
+        security: Default::default(),
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
         gossipsub: malachitebft_network::GossipSubConfig::default(),
         pubsub_protocol: malachitebft_network::PubSubProtocol::default(),
         channel_names: malachitebft_network::ChannelNames::default(),
         rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
         pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
         enable_consensus: true,
         enable_sync: false,
         protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
     }
 }
 
@@ -404,6 +418,452 @@ async fn test_add_remove_race_with_periodic_dial() {
     handle2.shutdown().await.unwrap();
 }
 
+/// Test that a discovery persistent peer is dialed until it comes up,
+/// and re-dialed after it is lost
+#[tokio::test]
+async fn test_discovery_persistent_peer_reconnects() {
+    init_logging();
+
+    let keypair1 = Keypair::generate_ed25519();
+    let keypair2 = Keypair::generate_ed25519();
+    let peer_id2 = keypair2.public().to_peer_id();
+    let base_port = 36000;
+
+    // Use TCP, as QUIC does not reset the connection state when reconnecting quickly
+    let make_tcp_config = |port| Config {
+        listen_addr: TransportProtocol::Tcp.multiaddr("127.0.0.1", port),
+        transport: malachitebft_network::TransportProtocol::Tcp,
+        ..make_config(port)
+    };
+
+    let spawn_node2 = |keypair: Keypair| {
+        spawn(
+            NetworkIdentity::new(
+                "node-2".to_string(),
+                keypair,
+                Some("test-address-2".to_string()),
+            ),
+            make_tcp_config(base_port + 1),
+            malachitebft_metrics::SharedRegistry::global().with_moniker("node-2".to_string()),
+        )
+    };
+
+    let node2_addr = format!(
+        "{}/p2p/{peer_id2}",
+        TransportProtocol::Tcp.multiaddr("127.0.0.1", base_port + 1)
+    )
+    .parse()
+    .unwrap();
+
+    let mut config1 = make_tcp_config(base_port);
+    config1.discovery.persistent_peers = vec![node2_addr];
+    config1.discovery.retry_backoff = BackoffConfig {
+        initial_delay: Duration::from_millis(100),
+        multiplier: 2.0,
+        max_delay: Duration::from_millis(500),
+        jitter: 0.0,
+    };
+
+    let mut handle1 = spawn(
+        NetworkIdentity::new(
+            "node-1".to_string(),
+            keypair1,
+            Some("test-address-1".to_string()),
+        ),
+        config1,
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-1".to_string()),
+    )
+    .await
+    .unwrap();
+
+    // Let node 1 fail to dial node 2 for a while
+    sleep(Duration::from_secs(2)).await;
+
+    let handle2 = spawn_node2(keypair2.clone()).await.unwrap();
+
+    let mut connected = false;
+    for _ in 0..100 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerConnected(_)) = event {
+                    connected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(connected, "Persistent peer should connect once it is up");
+
+    handle2.shutdown().await.unwrap();
+
+    let mut disconnected = false;
+    for _ in 0..100 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerDisconnected(_)) = event {
+                    disconnected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(disconnected, "Persistent peer should disconnect");
+
+    let handle2 = spawn_node2(keypair2).await.unwrap();
+
+    let mut reconnected = false;
+    for _ in 0..100 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerConnected(_)) = event {
+                    reconnected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(
+        reconnected,
+        "Persistent peer should be re-dialed after it is lost"
+    );
+
+    handle1.shutdown().await.unwrap();
+    handle2.shutdown().await.unwrap();
+}
+
+/// Test that nodes listening on WebSocket addresses connect to each other
+#[tokio::test]
+async fn test_websocket_persistent_peer() {
+    init_logging();
+
+    let keypair1 = Keypair::generate_ed25519();
+    let keypair2 = Keypair::generate_ed25519();
+    let peer_id2 = keypair2.public().to_peer_id();
+    let base_port = 37000;
+
+    let make_ws_config = |port| Config {
+        listen_addr: TransportProtocol::Ws.multiaddr("127.0.0.1", port),
+        transport: malachitebft_network::TransportProtocol::WebSocket,
+        ..make_config(port)
+    };
+
+    let node2_addr = format!(
+        "{}/p2p/{peer_id2}",
+        TransportProtocol::Ws.multiaddr("127.0.0.1", base_port + 1)
+    )
+    .parse()
+    .unwrap();
+
+    let mut config1 = make_ws_config(base_port);
+    config1.discovery.persistent_peers = vec![node2_addr];
+
+    let handle2 = spawn(
+        NetworkIdentity::new(
+            "node-2".to_string(),
+            keypair2,
+            Some("test-address-2".to_string()),
+        ),
+        make_ws_config(base_port + 1),
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-2".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let mut handle1 = spawn(
+        NetworkIdentity::new(
+            "node-1".to_string(),
+            keypair1,
+            Some("test-address-1".to_string()),
+        ),
+        config1,
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-1".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let mut connected = false;
+    for _ in 0..100 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerConnected(_)) = event {
+                    connected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(connected, "Peers should connect over WebSocket");
+
+    handle1.shutdown().await.unwrap();
+    handle2.shutdown().await.unwrap();
+}
+
+/// Test that nodes listening on in-memory addresses connect to each other
+#[tokio::test]
+async fn test_memory_persistent_peer() {
+    init_logging();
+
+    let base_port = 45000;
+
+    let make_memory_config = |port| Config {
+        listen_addr: TransportProtocol::Memory.multiaddr("127.0.0.1", port),
+        transport: malachitebft_network::TransportProtocol::Memory,
+        ..make_config(port)
+    };
+
+    let mut config1 = make_memory_config(base_port);
+    config1.persistent_peers =
+        vec![TransportProtocol::Memory.multiaddr("127.0.0.1", base_port + 1)];
+
+    let handle2 = spawn(
+        NetworkIdentity::new(
+            "node-2".to_string(),
+            Keypair::generate_ed25519(),
+            Some("test-address-2".to_string()),
+        ),
+        make_memory_config(base_port + 1),
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-2".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let mut handle1 = spawn(
+        NetworkIdentity::new(
+            "node-1".to_string(),
+            Keypair::generate_ed25519(),
+            Some("test-address-1".to_string()),
+        ),
+        config1,
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-1".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let mut connected = false;
+    for _ in 0..100 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerConnected(_)) = event {
+                    connected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(
+        connected,
+        "Peers should connect over the in-memory transport"
+    );
+
+    handle1.shutdown().await.unwrap();
+    handle2.shutdown().await.unwrap();
+}
+
+/// Test that a banned persistent peer is disconnected and cannot reconnect until unbanned
+#[tokio::test]
+async fn test_banned_peer_cannot_reconnect() {
+    init_logging();
+
+    let keypair1 = Keypair::generate_ed25519();
+    let keypair2 = Keypair::generate_ed25519();
+    let base_port = 39000;
+
+    let mut handle1 = spawn(
+        NetworkIdentity::new(
+            "node-1".to_string(),
+            keypair1,
+            Some("test-address-1".to_string()),
+        ),
+        make_config(base_port),
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-1".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let mut config2 = make_config(base_port + 1);
+    config2.persistent_peers = vec![TransportProtocol::Quic.multiaddr("127.0.0.1", base_port)];
+
+    let handle2 = spawn(
+        NetworkIdentity::new(
+            "node-2".to_string(),
+            keypair2,
+            Some("test-address-2".to_string()),
+        ),
+        config2,
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-2".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let peer_id2 = handle2.peer_id();
+
+    let mut connected = false;
+    for _ in 0..50 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerConnected(_)) = event {
+                    connected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(connected, "Persistent peer should connect");
+
+    handle1.ban_peer(peer_id2, None).await.unwrap();
+
+    let mut disconnected = false;
+    for _ in 0..50 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerDisconnected(_)) = event {
+                    disconnected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(disconnected, "Banned peer should be disconnected");
+
+    // The banned peer keeps re-dialing its persistent peer, without success
+    let mut reconnected = false;
+    for _ in 0..30 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerConnected(_)) = event {
+                    reconnected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(!reconnected, "Banned peer should not reconnect");
+
+    handle1.unban_peer(peer_id2).await.unwrap();
+
+    let node2_addr = TransportProtocol::Quic.multiaddr("127.0.0.1", base_port + 1);
+    let result = handle1.add_persistent_peer(node2_addr).await.unwrap();
+    assert_eq!(result, Ok(()));
+
+    let mut reconnected = false;
+    for _ in 0..100 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::PeerConnected(_)) = event {
+                    reconnected = true;
+                    break;
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(reconnected, "Unbanned peer should reconnect");
+
+    handle1.shutdown().await.unwrap();
+    handle2.shutdown().await.unwrap();
+}
+
+/// Test that a listen address added at runtime accepts connections,
+/// and that removing the configured one does not re-create its listener
+#[tokio::test]
+async fn test_add_and_remove_listen_addr() {
+    init_logging();
+
+    let base_port = 40000;
+
+    let mut handle1 = spawn(
+        NetworkIdentity::new(
+            "node-1".to_string(),
+            Keypair::generate_ed25519(),
+            Some("test-address-1".to_string()),
+        ),
+        make_config(base_port),
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-1".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let new_addr = TransportProtocol::Quic.multiaddr("127.0.0.1", base_port + 2);
+    handle1.add_listen_addr(new_addr.clone()).await.unwrap();
+
+    let mut listening = false;
+    for _ in 0..50 {
+        tokio::select! {
+            event = handle1.recv() => {
+                if let Some(Event::Listening(addr)) = event {
+                    if addr == new_addr {
+                        listening = true;
+                        break;
+                    }
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(listening, "Node should listen on the new address");
+
+    // Stop listening on the configured address, only the new one remains
+    let listen_addr = TransportProtocol::Quic.multiaddr("127.0.0.1", base_port);
+    handle1.remove_listen_addr(listen_addr).await.unwrap();
+
+    let mut config2 = make_config(base_port + 1);
+    config2.persistent_peers = vec![new_addr];
+
+    let handle2 = spawn(
+        NetworkIdentity::new(
+            "node-2".to_string(),
+            Keypair::generate_ed25519(),
+            Some("test-address-2".to_string()),
+        ),
+        config2,
+        malachitebft_metrics::SharedRegistry::global().with_moniker("node-2".to_string()),
+    )
+    .await
+    .unwrap();
+
+    let mut connected = false;
+    let mut relistened = false;
+    for _ in 0..50 {
+        tokio::select! {
+            event = handle1.recv() => {
+                match event {
+                    Some(Event::PeerConnected(_)) => {
+                        connected = true;
+                        break;
+                    }
+                    Some(Event::Listening(_)) => relistened = true,
+                    _ => {}
+                }
+            }
+            _ = sleep(Duration::from_millis(100)) => {}
+        }
+    }
+
+    assert!(connected, "Peer should connect on the new address");
+    assert!(!relistened, "Removed listener should not be re-created");
+
+    handle1.shutdown().await.unwrap();
+    handle2.shutdown().await.unwrap();
+}
+
 fn init_logging() {
     use tracing_subscriber::util::SubscriberInitExt;
     use tracing_subscriber::{EnvFilter, FmtSubscriber};
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use malachitebft_config::TransportProtocol;
+use malachitebft_network::handle::Handle;
+use malachitebft_network::{
+    spawn, Config, DiscoveryConfig, Event, Keypair, NetworkIdentity, ProtocolNames,
+    SecurityProtocol,
+};
+use tokio::time::timeout;
+
+fn make_config(port: usize, persistent_peers: Vec<usize>, security: SecurityProtocol) -> Config {
+    Config {
+        listen_addr: TransportProtocol::Tcp.multiaddr("127.0.0.1", port),
+        persistent_peers: persistent_peers
+            .into_iter()
+            .map(|port| TransportProtocol::Tcp.multiaddr("127.0.0.1", port))
+            .collect(),
+        persistent_peers_only: false,
+        explicit_peers: vec![],
+        discovery: DiscoveryConfig {
+            enabled: false,
+            ..Default::default()
+        },
+        idle_connection_timeout: Duration::from_secs(60),
+        transport: malachitebft_network::TransportProtocol::Tcp,
+        security,
+        dial_timeouts: Default::default(),
+        bandwidth: Default::default(),
+        liveness: Default::default(),
+        connection_limits: Default::default(),
+        websocket: Default::default(),
+        gossipsub: malachitebft_network::GossipSubConfig::default(),
+        pubsub_protocol: malachitebft_network::PubSubProtocol::default(),
+        channel_names: malachitebft_network::ChannelNames::default(),
+        rpc_max_size: 10 * 1024 * 1024,
+        rpc_max_chunked_size: None,
+        pubsub_max_size: 4 * 1024 * 1024,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: Default::default(),
+        mempool: Default::default(),
+        enable_consensus: true,
+        enable_sync: false,
+        protocol_names: ProtocolNames::default(),
+        chain_ids: Vec::new(),
+        node_key_file: None,
+    }
+}
+
+async fn spawn_node(name: &str, config: Config) -> Handle {
+    spawn(
+        NetworkIdentity::new(name.to_string(), Keypair::generate_ed25519(), None),
+        config,
+        malachitebft_metrics::SharedRegistry::global().with_moniker(name.to_string()),
+    )
+    .await
+    .unwrap()
+}
+
+async fn wait_for_connection(handle: &mut Handle, wait: Duration) -> bool {
+    timeout(wait, async {
+        while let Some(event) = handle.recv().await {
+            if matches!(event, Event::PeerConnected(_)) {
+                return true;
+            }
+        }
+        false
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Nodes supporting both Noise and TLS connect to the nodes supporting either of them,
+/// while nodes without any protocol in common cannot connect
+#[tokio::test]
+async fn test_security_protocol_negotiation() {
+    let base_port = 43000;
+
+    let mut tls = spawn_node(
+        "node-tls",
+        make_config(base_port, vec![], SecurityProtocol::Tls),
+    )
+    .await;
+
+    let mut both = spawn_node(
+        "node-both",
+        make_config(
+            base_port + 1,
+            vec![base_port],
+            SecurityProtocol::NoiseAndTls,
+        ),
+    )
+    .await;
+
+    assert!(wait_for_connection(&mut tls, Duration::from_secs(10)).await);
+    assert!(wait_for_connection(&mut both, Duration::from_secs(10)).await);
+
+    let mut noise = spawn_node(
+        "node-noise",
+        make_config(base_port + 2, vec![base_port + 1], SecurityProtocol::Noise),
+    )
+    .await;
+
+    assert!(wait_for_connection(&mut noise, Duration::from_secs(10)).await);
+    assert!(wait_for_connection(&mut both, Duration::from_secs(10)).await);
+
+    let mut noise_only = spawn_node(
+        "node-noise-only",
+        make_config(base_port + 3, vec![base_port], SecurityProtocol::Noise),
+    )
+    .await;
+
+    assert!(
+        !wait_for_connection(&mut noise_only, Duration::from_secs(3)).await,
+        "Noise node should not connect to the TLS node"
+    );
+
+    for handle in [tls, both, noise, noise_only] {
+        handle.shutdown().await.unwrap();
+    }
+}
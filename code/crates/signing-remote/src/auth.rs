@@ -0,0 +1,143 @@
+//! Mutual authentication of the node and the remote signer with a shared secret.
+//!
+//! Once connected, and before any signing request, each side proves to the other that it
+//! knows the secret, without ever sending it:
+//!
+//! 1. the node sends a random challenge,
+//! 2. the signer replies with a random challenge of its own, and a MAC of both challenges,
+//! 3. the node checks the MAC of the signer, and replies with its own MAC of both challenges,
+//! 4. the signer checks the MAC of the node.
+//!
+//! The MACs are keyed SHA3-256 hashes, bound to the side computing them,
+//! so that the MAC of one side cannot be replayed to the other.
+
+use core::fmt;
+use std::io;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha3::{Digest, Sha3_256};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::protocol::{read_frame, write_frame};
+
+/// Size of the challenges and of the MACs exchanged during the handshake
+const CHALLENGE_SIZE: usize = 32;
+
+/// Domain separation of the MACs computed by the node and by the signer
+const NODE_DOMAIN: &[u8] = b"malachitebft-remote-signer/node";
+const SIGNER_DOMAIN: &[u8] = b"malachitebft-remote-signer/signer";
+
+/// The secret shared by a node and its remote signer, authenticating them to each other
+#[derive(Clone)]
+pub struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+    /// Use the given bytes as the shared secret, which must be kept private to the node and the signer
+    pub fn new(secret: [u8; 32]) -> Self {
+        Self(secret)
+    }
+
+    /// Generate a random shared secret
+    pub fn generate() -> Self {
+        let mut secret = [0; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self(secret)
+    }
+
+    /// Decode a shared secret from its hex encoding, eg. as stored in a file
+    pub fn from_hex(hex: &str) -> Result<Self, hex::FromHexError> {
+        let mut secret = [0; 32];
+        hex::decode_to_slice(hex.trim(), &mut secret)?;
+        Ok(Self(secret))
+    }
+
+    /// Encode the shared secret as hex
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    fn mac(&self, domain: &[u8], node_challenge: &[u8], signer_challenge: &[u8]) -> Vec<u8> {
+        Sha3_256::new()
+            .chain_update(self.0)
+            .chain_update(domain)
+            .chain_update(node_challenge)
+            .chain_update(signer_challenge)
+            .finalize()
+            .to_vec()
+    }
+}
+
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedSecret(..)")
+    }
+}
+
+/// Authenticate the signer the node just connected to, and the node to the signer
+pub(crate) async fn authenticate_signer<S>(stream: &mut S, secret: &SharedSecret) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let node_challenge = challenge();
+    write_frame(stream, &node_challenge).await?;
+
+    let reply = read_frame(stream).await?;
+
+    if reply.len() != 2 * CHALLENGE_SIZE {
+        return Err(denied("malformed handshake from the signer"));
+    }
+
+    let (signer_challenge, signer_mac) = reply.split_at(CHALLENGE_SIZE);
+    let expected = secret.mac(SIGNER_DOMAIN, &node_challenge, signer_challenge);
+
+    if !constant_time_eq(signer_mac, &expected) {
+        return Err(denied("the signer does not know the shared secret"));
+    }
+
+    let node_mac = secret.mac(NODE_DOMAIN, &node_challenge, signer_challenge);
+    write_frame(stream, &node_mac).await
+}
+
+/// Authenticate the node which just connected to the signer, and the signer to the node
+pub(crate) async fn authenticate_node<S>(stream: &mut S, secret: &SharedSecret) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let node_challenge = read_frame(stream).await?;
+
+    if node_challenge.len() != CHALLENGE_SIZE {
+        return Err(denied("malformed handshake from the node"));
+    }
+
+    let signer_challenge = challenge();
+    let signer_mac = secret.mac(SIGNER_DOMAIN, &node_challenge, &signer_challenge);
+    write_frame(stream, &[signer_challenge.as_slice(), &signer_mac].concat()).await?;
+
+    let node_mac = read_frame(stream).await?;
+    let expected = secret.mac(NODE_DOMAIN, &node_challenge, &signer_challenge);
+
+    if !constant_time_eq(&node_mac, &expected) {
+        return Err(denied("the node does not know the shared secret"));
+    }
+
+    Ok(())
+}
+
+fn challenge() -> [u8; CHALLENGE_SIZE] {
+    let mut challenge = [0; CHALLENGE_SIZE];
+    OsRng.fill_bytes(&mut challenge);
+    challenge
+}
+
+fn denied(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("authentication failed: {reason}"),
+    )
+}
+
+/// Compare the MACs without leaking through timing how many of their first bytes match
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,242 @@
+use std::io;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use malachitebft_core_types::{Context, PublicKey, Signature, SignedMessage};
+use malachitebft_signing::{Error, SigningProvider, VerificationResult};
+
+use crate::auth::{authenticate_signer, SharedSecret};
+use crate::protocol::{read_frame, write_frame, SignRequest, SignResponse, SignerCodec};
+
+/// Default time to wait for the remote signer to reply to a request
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Signing provider delegating signing to a [`RemoteSigner`](crate::RemoteSigner),
+/// and verification to a local signing provider which needs no private key.
+///
+/// The connection to the signer is established on the first request, authenticating the node
+/// and the signer to each other with their [`SharedSecret`], and re-established whenever it fails. As the signer sends the same signature back for a message it already
+/// signed, a request which failed midway is safely sent again on a fresh connection.
+pub struct RemoteSigningProvider<Ctx, Codec, V> {
+    address: String,
+    secret: SharedSecret,
+    timeout: Duration,
+    codec: Codec,
+    verifier: V,
+    connection: Mutex<Option<TcpStream>>,
+    _marker: PhantomData<fn() -> Ctx>,
+}
+
+impl<Ctx, Codec, V> RemoteSigningProvider<Ctx, Codec, V>
+where
+    Ctx: Context,
+    Codec: SignerCodec<Ctx>,
+    V: SigningProvider<Ctx>,
+{
+    /// Create a provider for the signer listening at the given address, eg. `10.0.0.2:26660`,
+    /// and knowing the given secret
+    pub fn new(
+        address: impl Into<String>,
+        secret: SharedSecret,
+        codec: Codec,
+        verifier: V,
+    ) -> Self {
+        Self {
+            address: address.into(),
+            secret,
+            timeout: DEFAULT_TIMEOUT,
+            codec,
+            verifier,
+            connection: Mutex::new(None),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set how long to wait for the signer to reply to a request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn request(&self, request: SignRequest<Ctx>) -> Result<Signature<Ctx>, Error> {
+        let bytes = self
+            .codec
+            .encode(&request)
+            .map_err(|e| Error::from_source(format!("Failed to encode sign request: {e}")))?;
+
+        let reply = self.send(&bytes).await.map_err(|e| {
+            Error::from_source(format!(
+                "Failed to reach remote signer at {}: {e}",
+                self.address
+            ))
+        })?;
+
+        let response: SignResponse<Ctx> = self
+            .codec
+            .decode(reply)
+            .map_err(|e| Error::from_source(format!("Failed to decode sign response: {e}")))?;
+
+        match response {
+            SignResponse::Signature(signature) => Ok(signature),
+            SignResponse::Rejected(reason) => Err(Error::from_source(format!(
+                "Remote signer refused to sign: {reason}"
+            ))),
+        }
+    }
+
+    /// Send a request to the signer and wait for its reply,
+    /// retrying once on a fresh connection if the current one is broken
+    async fn send(&self, bytes: &[u8]) -> io::Result<Bytes> {
+        let mut connection = self.connection.lock().await;
+
+        if connection.is_some() {
+            match self.exchange(&mut connection, bytes).await {
+                Ok(reply) => return Ok(reply),
+                Err(e) => warn!(address = %self.address, "Connection to remote signer failed: {e}"),
+            }
+        }
+
+        debug!(address = %self.address, "Connecting to remote signer");
+        self.exchange(&mut connection, bytes).await
+    }
+
+    async fn exchange(
+        &self,
+        connection: &mut Option<TcpStream>,
+        bytes: &[u8],
+    ) -> io::Result<Bytes> {
+        let result = tokio::time::timeout(
+            self.timeout,
+            request(connection, &self.address, &self.secret, bytes),
+        )
+        .await
+        .unwrap_or_else(|_| {
+            Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no reply within {:?}", self.timeout),
+            ))
+        });
+
+        // Drop the connection on failure, as a reply may still be in flight
+        if result.is_err() {
+            *connection = None;
+        }
+
+        result
+    }
+}
+
+async fn request(
+    connection: &mut Option<TcpStream>,
+    address: &str,
+    secret: &SharedSecret,
+    bytes: &[u8],
+) -> io::Result<Bytes> {
+    let stream = match connection {
+        Some(stream) => stream,
+        None => {
+            let mut stream = TcpStream::connect(address).await?;
+            authenticate_signer(&mut stream, secret).await?;
+            connection.insert(stream)
+        }
+    };
+
+    write_frame(stream, bytes).await?;
+    read_frame(stream).await
+}
+
+#[async_trait]
+impl<Ctx, Codec, V> SigningProvider<Ctx> for RemoteSigningProvider<Ctx, Codec, V>
+where
+    Ctx: Context,
+    Codec: SignerCodec<Ctx>,
+    V: SigningProvider<Ctx>,
+{
+    async fn sign_bytes(&self, bytes: &[u8]) -> Result<Signature<Ctx>, Error> {
+        self.request(SignRequest::Bytes(Bytes::copy_from_slice(bytes)))
+            .await
+    }
+
+    async fn verify_signed_bytes(
+        &self,
+        bytes: &[u8],
+        signature: &Signature<Ctx>,
+        public_key: &PublicKey<Ctx>,
+    ) -> Result<VerificationResult, Error> {
+        self.verifier
+            .verify_signed_bytes(bytes, signature, public_key)
+            .await
+    }
+
+    async fn sign_vote(&self, vote: Ctx::Vote) -> Result<SignedMessage<Ctx, Ctx::Vote>, Error> {
+        let signature = self.request(SignRequest::Vote(vote.clone())).await?;
+        Ok(SignedMessage::new(vote, signature))
+    }
+
+    async fn verify_signed_vote(
+        &self,
+        vote: &Ctx::Vote,
+        signature: &Signature<Ctx>,
+        public_key: &PublicKey<Ctx>,
+    ) -> Result<VerificationResult, Error> {
+        self.verifier
+            .verify_signed_vote(vote, signature, public_key)
+            .await
+    }
+
+    async fn verify_signed_votes(
+        &self,
+        votes: &[(SignedMessage<Ctx, Ctx::Vote>, PublicKey<Ctx>)],
+    ) -> Result<Vec<VerificationResult>, Error> {
+        self.verifier.verify_signed_votes(votes).await
+    }
+
+    async fn sign_proposal(
+        &self,
+        proposal: Ctx::Proposal,
+    ) -> Result<SignedMessage<Ctx, Ctx::Proposal>, Error> {
+        let signature = self
+            .request(SignRequest::Proposal(proposal.clone()))
+            .await?;
+        Ok(SignedMessage::new(proposal, signature))
+    }
+
+    async fn verify_signed_proposal(
+        &self,
+        proposal: &Ctx::Proposal,
+        signature: &Signature<Ctx>,
+        public_key: &PublicKey<Ctx>,
+    ) -> Result<VerificationResult, Error> {
+        self.verifier
+            .verify_signed_proposal(proposal, signature, public_key)
+            .await
+    }
+
+    async fn sign_vote_extension(
+        &self,
+        extension: Ctx::Extension,
+    ) -> Result<SignedMessage<Ctx, Ctx::Extension>, Error> {
+        let signature = self
+            .request(SignRequest::VoteExtension(extension.clone()))
+            .await?;
+
+        Ok(SignedMessage::new(extension, signature))
+    }
+
+    async fn verify_signed_vote_extension(
+        &self,
+        extension: &Ctx::Extension,
+        signature: &Signature<Ctx>,
+        public_key: &PublicKey<Ctx>,
+    ) -> Result<VerificationResult, Error> {
+        self.verifier
+            .verify_signed_vote_extension(extension, signature, public_key)
+            .await
+    }
+}
@@ -0,0 +1,28 @@
+//! Remote signing for the Malachite BFT consensus engine.
+//!
+//! Instead of holding the validator key in-process, a node can delegate signing to a
+//! [`RemoteSigner`] running on a dedicated host, or in front of an HSM, through the
+//! [`RemoteSigningProvider`]. Signatures are still verified locally by the node.
+//!
+//! The remote signer keeps track of what it signed at the latest height in a state file,
+//! and refuses to sign a vote or proposal conflicting with one it already signed, even
+//! across restarts of the node or of the signer. This protects the validator from
+//! double-signing, eg. when two nodes are accidentally run with the same signer.
+//!
+//! The node and the signer exchange length-prefixed messages over TCP, encoded with a
+//! [`SignerCodec`]. They authenticate each other with a [`SharedSecret`] when connecting,
+//! and the signer serves no request of a node which failed to. The connection is not
+//! encrypted though, so the signer should only be reachable by the node, eg. over a private
+//! network or a tunnel.
+
+mod auth;
+mod client;
+mod protocol;
+mod server;
+mod state;
+
+pub use auth::SharedSecret;
+pub use client::RemoteSigningProvider;
+pub use protocol::{SignRequest, SignResponse, SignerCodec, MAX_FRAME_SIZE};
+pub use server::RemoteSigner;
+pub use state::{SignError, SignState, Step};
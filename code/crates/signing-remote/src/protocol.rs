@@ -0,0 +1,96 @@
+use std::io;
+
+use bytes::Bytes;
+use derive_where::derive_where;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use malachitebft_codec::Codec;
+use malachitebft_core_types::{Context, Signature};
+
+/// Maximum size of a message exchanged between the node and the signer
+pub const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// A request sent by the node to the remote signer
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub enum SignRequest<Ctx: Context> {
+    /// Sign a vote
+    Vote(Ctx::Vote),
+
+    /// Sign a proposal
+    Proposal(Ctx::Proposal),
+
+    /// Sign a vote extension
+    VoteExtension(Ctx::Extension),
+
+    /// Sign raw bytes, only allowed for validator proofs
+    /// (see [`ValidatorProof`](malachitebft_core_types::ValidatorProof))
+    Bytes(Bytes),
+}
+
+/// The reply of the remote signer to a [`SignRequest`]
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub enum SignResponse<Ctx: Context> {
+    /// The signature of the requested message
+    Signature(Signature<Ctx>),
+
+    /// The signer refused to sign the message, with the reason why
+    Rejected(String),
+}
+
+/// Codec for the messages exchanged between the node and the remote signer.
+///
+/// This trait is automatically implemented for any type that implements:
+/// - [`Codec<SignRequest<Ctx>>`]
+/// - [`Codec<SignResponse<Ctx>>`]
+pub trait SignerCodec<Ctx>
+where
+    Ctx: Context,
+    Self: Codec<SignRequest<Ctx>>,
+    Self: Codec<SignResponse<Ctx>>,
+{
+}
+
+impl<Ctx, C> SignerCodec<Ctx> for C
+where
+    Ctx: Context,
+    C: Codec<SignRequest<Ctx>>,
+    C: Codec<SignResponse<Ctx>>,
+{
+}
+
+/// Write a message prefixed with its length, as a 32-bit big-endian integer
+pub(crate) async fn write_frame<W>(writer: &mut W, bytes: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    if bytes.len() > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("message too large: {} bytes", bytes.len()),
+        ));
+    }
+
+    writer.write_u32(bytes.len() as u32).await?;
+    writer.write_all(bytes).await?;
+    writer.flush().await
+}
+
+/// Read a message written by [`write_frame`]
+pub(crate) async fn read_frame<R>(reader: &mut R) -> io::Result<Bytes>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = reader.read_u32().await? as usize;
+
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message too large: {len} bytes"),
+        ));
+    }
+
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes).await?;
+
+    Ok(Bytes::from(bytes))
+}
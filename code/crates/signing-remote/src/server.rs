@@ -0,0 +1,246 @@
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tracing::{debug, error, info, warn};
+
+use malachitebft_core_types::{
+    Context, Height, Proposal, Round, Signature, SigningScheme, Vote, VoteType,
+};
+use malachitebft_signing::SigningProvider;
+
+use crate::auth::{authenticate_node, SharedSecret};
+use crate::protocol::{read_frame, write_frame, SignRequest, SignResponse, SignerCodec};
+use crate::state::{SignError, SignState, Step};
+
+/// Prefix of the bytes signed for a validator proof, see `ValidatorProof::signing_bytes`
+const VALIDATOR_PROOF_SEPARATOR: &[u8] = b"PoV";
+
+/// Time given to a node to authenticate once connected
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A remote signer, signing messages with the validator key on behalf of a node.
+///
+/// The signer only serves the nodes knowing its [`SharedSecret`], only signs votes and
+/// proposals of its own validator, and refuses to sign any vote or proposal conflicting
+/// with one it already signed, see [`SignState`]. The state is persisted before any
+/// signature is sent back to the node.
+pub struct RemoteSigner<Ctx, Codec, P>
+where
+    Ctx: Context,
+{
+    address: Ctx::Address,
+    secret: SharedSecret,
+    codec: Codec,
+    signing_provider: P,
+    state_file: PathBuf,
+    state: Mutex<SignState>,
+}
+
+impl<Ctx, Codec, P> RemoteSigner<Ctx, Codec, P>
+where
+    Ctx: Context,
+    Codec: SignerCodec<Ctx>,
+    P: SigningProvider<Ctx> + 'static,
+{
+    /// Create a signer for the validator with the given address, serving the nodes
+    /// knowing the given secret, and loading its sign state from the given file if it exists
+    pub fn new(
+        address: Ctx::Address,
+        secret: SharedSecret,
+        codec: Codec,
+        signing_provider: P,
+        state_file: impl Into<PathBuf>,
+    ) -> io::Result<Self> {
+        let state_file = state_file.into();
+        let state = SignState::load(&state_file)?;
+
+        Ok(Self {
+            address,
+            secret,
+            codec,
+            signing_provider,
+            state_file,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Serve the signing requests of the nodes connecting to the given listener
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        info!(address = %listener.local_addr()?, "Remote signer is listening");
+
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            info!(%peer, "Node connected to the remote signer");
+
+            let signer = Arc::clone(&self);
+
+            tokio::spawn(async move {
+                match signer.handle_connection(stream).await {
+                    Ok(()) => info!(%peer, "Node disconnected from the remote signer"),
+                    Err(e) => warn!(%peer, "Connection to the node failed: {e}"),
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> io::Result<()> {
+        tokio::time::timeout(
+            HANDSHAKE_TIMEOUT,
+            authenticate_node(&mut stream, &self.secret),
+        )
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "authentication timed out"))??;
+
+        debug!("Node authenticated");
+
+        loop {
+            let bytes = match read_frame(&mut stream).await {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            let request: Result<SignRequest<Ctx>, _> = self.codec.decode(bytes);
+
+            let response = match request {
+                Ok(request) => self.sign(request).await,
+                Err(e) => SignResponse::Rejected(format!("Invalid request: {e}")),
+            };
+
+            let bytes = self
+                .codec
+                .encode(&response)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+
+            write_frame(&mut stream, &bytes).await?;
+        }
+    }
+
+    /// Sign the requested message, unless it conflicts with a message already signed
+    pub async fn sign(&self, request: SignRequest<Ctx>) -> SignResponse<Ctx> {
+        match self.try_sign(request).await {
+            Ok(signature) => SignResponse::Signature(signature),
+            Err(e) => {
+                error!("{e}");
+                SignResponse::Rejected(e.to_string())
+            }
+        }
+    }
+
+    async fn try_sign(&self, request: SignRequest<Ctx>) -> Result<Signature<Ctx>, SignError> {
+        match &request {
+            SignRequest::Vote(vote) => {
+                if vote.validator_address() != &self.address {
+                    return Err(SignError::Forbidden(format!(
+                        "Refusing to sign a vote of another validator: {}",
+                        vote.validator_address()
+                    )));
+                }
+
+                let step = match vote.vote_type() {
+                    VoteType::Prevote => Step::Prevote,
+                    VoteType::Precommit => Step::Precommit,
+                };
+
+                self.sign_checked(vote.height(), vote.round(), step, &request, async {
+                    let signed = self.signing_provider.sign_vote(vote.clone()).await?;
+                    Ok(signed.signature)
+                })
+                .await
+            }
+
+            SignRequest::Proposal(proposal) => {
+                if proposal.validator_address() != &self.address {
+                    return Err(SignError::Forbidden(format!(
+                        "Refusing to sign a proposal of another validator: {}",
+                        proposal.validator_address()
+                    )));
+                }
+
+                let (height, round) = (proposal.height(), proposal.round());
+
+                self.sign_checked(height, round, Step::Propose, &request, async {
+                    let signed = self
+                        .signing_provider
+                        .sign_proposal(proposal.clone())
+                        .await?;
+                    Ok(signed.signature)
+                })
+                .await
+            }
+
+            SignRequest::VoteExtension(extension) => {
+                let signed = self
+                    .signing_provider
+                    .sign_vote_extension(extension.clone())
+                    .await;
+
+                Ok(signed.map_err(signing_error)?.signature)
+            }
+
+            SignRequest::Bytes(bytes) => {
+                if !bytes.starts_with(VALIDATOR_PROOF_SEPARATOR) {
+                    return Err(SignError::Forbidden(
+                        "Refusing to sign raw bytes other than a validator proof".to_string(),
+                    ));
+                }
+
+                let signature = self.signing_provider.sign_bytes(bytes).await;
+                signature.map_err(signing_error)
+            }
+        }
+    }
+
+    /// Sign a message after checking it does not conflict with a message already signed,
+    /// and record it before returning its signature
+    async fn sign_checked(
+        &self,
+        height: Ctx::Height,
+        round: Round,
+        step: Step,
+        request: &SignRequest<Ctx>,
+        sign: impl Future<Output = Result<Signature<Ctx>, malachitebft_signing::Error>>,
+    ) -> Result<Signature<Ctx>, SignError> {
+        let (height, round) = (height.as_u64(), round.as_i64());
+
+        let request: Bytes = self
+            .codec
+            .encode(request)
+            .map_err(|e| SignError::Signing(e.to_string()))?;
+
+        // Hold the lock until the state is persisted, so that concurrent requests
+        // cannot both pass the check before either of them is recorded
+        let mut state = self.state.lock().await;
+
+        if let Some(signature) = state.check(height, round, step, &request)? {
+            debug!(%height, %round, ?step, "Already signed this message, sending the same signature");
+
+            return Ctx::SigningScheme::decode_signature(&signature)
+                .map_err(|e| SignError::Signing(format!("Invalid stored signature: {e}")));
+        }
+
+        let signature = sign.await.map_err(signing_error)?;
+        let encoded = Ctx::SigningScheme::encode_signature(&signature);
+
+        // Only update the state once persisted, so that no signature is ever sent back
+        // for a message which would be forgotten after a restart of the signer
+        let mut next = state.clone();
+        next.record(height, round, step, request.to_vec(), encoded);
+        next.save(&self.state_file)?;
+        *state = next;
+
+        debug!(%height, %round, ?step, "Signed message");
+
+        Ok(signature)
+    }
+}
+
+fn signing_error(e: malachitebft_signing::Error) -> SignError {
+    SignError::Signing(e.to_string())
+}
@@ -0,0 +1,175 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// The step at which a message is signed within a round, in the order in which they are signed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Step {
+    Propose,
+    Prevote,
+    Precommit,
+}
+
+/// Why the signer refused to sign a message
+#[derive(Debug, thiserror::Error)]
+pub enum SignError {
+    /// A message for a lower height than the last signed height
+    #[error("Refusing to sign at height {height}, already signed at height {last}")]
+    LowerHeight { height: u64, last: u64 },
+
+    /// A message for an earlier round or step than one already signed at the same height
+    #[error(
+        "Refusing to sign at height {height}, round {round}, step {step:?}: \
+         already signed at round {last_round}, step {last_step:?}"
+    )]
+    LowerStep {
+        height: u64,
+        round: i64,
+        step: Step,
+        last_round: i64,
+        last_step: Step,
+    },
+
+    /// A message differing from the one already signed at the same height, round and step
+    #[error(
+        "Refusing to double sign at height {height}, round {round}, step {step:?}: \
+         already signed a different message"
+    )]
+    DoubleSign { height: u64, round: i64, step: Step },
+
+    /// A message the signer never signs
+    #[error("{0}")]
+    Forbidden(String),
+
+    /// The signing provider failed to sign the message
+    #[error("Failed to sign: {0}")]
+    Signing(String),
+
+    /// The sign state could not be persisted
+    #[error("Failed to persist the sign state: {0}")]
+    Io(#[from] io::Error),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Signed {
+    round: i64,
+    step: Step,
+    #[serde(with = "hex")]
+    request: Vec<u8>,
+    #[serde(with = "hex")]
+    signature: Vec<u8>,
+}
+
+/// The messages signed at the latest height, used to prevent double signing.
+///
+/// Only the latest height is kept, as nothing may be signed at a lower height.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SignState {
+    height: Option<u64>,
+    signed: Vec<Signed>,
+}
+
+impl SignState {
+    /// Load the sign state from the given file, or start afresh if it does not exist
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(io::Error::other),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Atomically replace the sign state stored in the given file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+
+        let tmp = path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+
+        fs::rename(&tmp, path)
+    }
+
+    /// The latest height at which a message was signed, if any
+    pub fn height(&self) -> Option<u64> {
+        self.height
+    }
+
+    /// Check whether the given message may be signed.
+    ///
+    /// Returns the signature of the message if the very same message was already signed,
+    /// so that a node replaying its WAL after a crash gets the same signature again.
+    pub fn check(
+        &self,
+        height: u64,
+        round: i64,
+        step: Step,
+        request: &[u8],
+    ) -> Result<Option<Vec<u8>>, SignError> {
+        let Some(last) = self.height else {
+            return Ok(None);
+        };
+
+        if height < last {
+            return Err(SignError::LowerHeight { height, last });
+        }
+
+        if height > last {
+            return Ok(None);
+        }
+
+        if let Some(signed) = self
+            .signed
+            .iter()
+            .find(|s| s.round == round && s.step == step)
+        {
+            return if signed.request == request {
+                Ok(Some(signed.signature.clone()))
+            } else {
+                Err(SignError::DoubleSign {
+                    height,
+                    round,
+                    step,
+                })
+            };
+        }
+
+        match self.signed.iter().map(|s| (s.round, s.step)).max() {
+            Some((last_round, last_step)) if (round, step) < (last_round, last_step) => {
+                Err(SignError::LowerStep {
+                    height,
+                    round,
+                    step,
+                    last_round,
+                    last_step,
+                })
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Record a signed message, forgetting about the messages signed at lower heights
+    pub fn record(
+        &mut self,
+        height: u64,
+        round: i64,
+        step: Step,
+        request: Vec<u8>,
+        signature: Vec<u8>,
+    ) {
+        if self.height != Some(height) {
+            self.height = Some(height);
+            self.signed.clear();
+        }
+
+        self.signed.push(Signed {
+            round,
+            step,
+            request,
+            signature,
+        });
+    }
+}
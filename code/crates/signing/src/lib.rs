@@ -4,6 +4,7 @@
 extern crate alloc;
 
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 
 use alloc::sync::Arc;
 use async_trait::async_trait;
@@ -82,6 +83,28 @@ where
         public_key: &PublicKey<Ctx>,
     ) -> Result<VerificationResult, Error>;
 
+    /// Verify the signatures of a batch of votes, each using the public key it is paired with.
+    ///
+    /// Returns the result of the verification of each vote, in the same order as the votes.
+    /// By default, the votes are verified one at a time. Signing schemes supporting
+    /// batch verification can override this method to verify them all at once.
+    async fn verify_signed_votes(
+        &self,
+        votes: &[(SignedMessage<Ctx, Ctx::Vote>, PublicKey<Ctx>)],
+    ) -> Result<Vec<VerificationResult>, Error> {
+        let mut results = Vec::with_capacity(votes.len());
+
+        for (vote, public_key) in votes {
+            let result = self
+                .verify_signed_vote(&vote.message, &vote.signature, public_key)
+                .await?;
+
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
     /// Sign the given proposal with our private key.
     async fn sign_proposal(
         &self,
@@ -147,6 +170,13 @@ where
             .await
     }
 
+    async fn verify_signed_votes(
+        &self,
+        votes: &[(SignedMessage<Ctx, Ctx::Vote>, PublicKey<Ctx>)],
+    ) -> Result<Vec<VerificationResult>, Error> {
+        (*self).verify_signed_votes(votes).await
+    }
+
     async fn sign_proposal(
         &self,
         proposal: Ctx::Proposal,
@@ -219,6 +249,13 @@ where
             .await
     }
 
+    async fn verify_signed_votes(
+        &self,
+        votes: &[(SignedMessage<Ctx, Ctx::Vote>, PublicKey<Ctx>)],
+    ) -> Result<Vec<VerificationResult>, Error> {
+        self.as_ref().verify_signed_votes(votes).await
+    }
+
     async fn sign_proposal(
         &self,
         proposal: Ctx::Proposal,
@@ -291,6 +328,13 @@ where
             .await
     }
 
+    async fn verify_signed_votes(
+        &self,
+        votes: &[(SignedMessage<Ctx, Ctx::Vote>, PublicKey<Ctx>)],
+    ) -> Result<Vec<VerificationResult>, Error> {
+        self.as_ref().verify_signed_votes(votes).await
+    }
+
     async fn sign_proposal(
         &self,
         proposal: Ctx::Proposal,
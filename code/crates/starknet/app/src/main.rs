@@ -1,10 +1,14 @@
 use color_eyre::eyre::Context;
 
+use malachitebft_app::engine::wal::replay::Replayer;
+use malachitebft_app::types::ValuePayload;
 use malachitebft_config::{LogFormat, LogLevel};
 use malachitebft_starknet_host::codec::ProtobufCodec;
 use malachitebft_starknet_host::node::{ConfigSource, StarknetNode};
+use malachitebft_starknet_host::types::MockContext;
 use malachitebft_test::node::Node;
 use malachitebft_test_cli::args::{Args, Commands};
+use malachitebft_test_cli::keystore::Keystore;
 use malachitebft_test_cli::{logging, runtime};
 
 // Use jemalloc on Linux
@@ -51,6 +55,7 @@ pub fn main() -> color_eyre::Result<()> {
                 &config_file,
                 &args.get_genesis_file_path().unwrap(),
                 &args.get_priv_validator_key_file_path().unwrap(),
+                &Keystore::default(),
             )
             .wrap_err("Failed to run `init` command")
         }
@@ -87,6 +92,26 @@ pub fn main() -> color_eyre::Result<()> {
             cmd.run(ProtobufCodec)
                 .wrap_err("Failed to run `dump-wal` command")
         }
+
+        Commands::ReplayWal(cmd) => {
+            let _guard = logging::init(LogLevel::Info, LogFormat::Plaintext);
+
+            let node = &StarknetNode {
+                home_dir: home_dir.clone(),
+                config_source: ConfigSource::Default,
+                start_height: None,
+            };
+
+            let genesis = node.load_genesis()?;
+            let private_key = node.load_private_key(node.load_private_key_file()?);
+            let address = node.get_address(&node.get_public_key(&private_key));
+
+            // Starknet only runs in parts-only mode
+            let replayer = Replayer::new(MockContext::new(), address, ValuePayload::PartsOnly);
+
+            cmd.run(&replayer, ProtobufCodec, genesis.validator_set)
+                .wrap_err("Failed to run `replay-wal` command")
+        }
     }
 }
 
@@ -102,6 +127,7 @@ mod tests {
     use malachitebft_starknet_host::node::{ConfigSource, StarknetNode};
     use malachitebft_test_cli::args::{Args, Commands};
     use malachitebft_test_cli::cmd::init::*;
+    use malachitebft_test_cli::keystore::Keystore;
 
     #[test]
     fn running_init_creates_config_files() -> eyre::Result<()> {
@@ -122,6 +148,7 @@ mod tests {
             &args.get_config_file_path().unwrap(),
             &args.get_genesis_file_path().unwrap(),
             &args.get_priv_validator_key_file_path().unwrap(),
+            &Keystore::default(),
         )
         .expect("Failed to run init command");
 
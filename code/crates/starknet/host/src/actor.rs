@@ -210,6 +210,12 @@ impl Host {
                 value_bytes,
                 reply_to,
             } => on_process_synced_value(value_bytes, height, round, proposer, reply_to),
+
+            // Snapshots and backfilling are not supported by this application
+            HostMsg::GetSnapshots { reply_to } => Ok(reply_to.send(Vec::new())?),
+            HostMsg::GetSnapshotChunk { reply_to, .. } => Ok(reply_to.send(None)?),
+            HostMsg::RestoreSnapshot { reply_to, .. } => Ok(reply_to.send(None)?),
+            HostMsg::StoreBackfilledValues { reply_to, .. } => Ok(reply_to.send(false)?),
         }
     }
 }
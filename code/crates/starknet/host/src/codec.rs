@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bytes::Bytes;
 use prost::Message;
 
@@ -144,6 +146,9 @@ pub fn decode_sync_status(
         peer_id: decode_peer_id(peer_id)?,
         tip_height: Height::new(status.block_number, status.fork_id),
         history_min_height: Height::new(status.earliest_block_number, status.earliest_fork_id),
+        min_needed_height: status
+            .min_needed_block_number
+            .map(|block_number| Height::new(block_number, status.min_needed_fork_id)),
     })
 }
 
@@ -156,6 +161,8 @@ pub fn encode_sync_status(
         fork_id: status.tip_height.fork_id,
         earliest_block_number: status.history_min_height.block_number,
         earliest_fork_id: status.history_min_height.fork_id,
+        min_needed_block_number: status.min_needed_height.map(|height| height.block_number),
+        min_needed_fork_id: status.min_needed_height.map_or(0, |height| height.fork_id),
     })
 }
 
@@ -183,7 +190,40 @@ pub fn decode_sync_request(
             let end = value_request
                 .end_block_number
                 .map_or(start, |end| Height::new(end, value_request.fork_id));
-            sync::Request::ValueRequest(ValueRequest::new(start..=end))
+            sync::Request::ValueRequest(
+                ValueRequest::new(start..=end)
+                    .with_trace_id(sync::TraceId(value_request.trace_id))
+                    .with_certificates_only(value_request.certificates_only),
+            )
+        }
+        proto::sync::sync_request::Messages::SnapshotListRequest(_) => {
+            sync::Request::SnapshotRequest(sync::SnapshotRequest::List)
+        }
+        proto::sync::sync_request::Messages::SnapshotChunkRequest(request) => {
+            sync::Request::SnapshotRequest(sync::SnapshotRequest::Chunk {
+                height: Height::new(request.block_number, request.fork_id),
+                format: request.format,
+                index: request.index,
+            })
+        }
+        proto::sync::sync_request::Messages::ProposalPartsRequest(request) => {
+            let proposer = request.proposer.ok_or_else(|| {
+                ProtoError::missing_field::<proto::sync::ProposalPartsRequest>("proposer")
+            })?;
+
+            sync::Request::ProposalPartsRequest(sync::ProposalPartsRequest {
+                height: Height::new(request.block_number, request.fork_id),
+                round: request.round.map(Round::new).unwrap_or(Round::Nil),
+                proposer: decode_peer_id(proposer)?,
+                stream_id: request.stream_id,
+                sequences: request.sequences,
+            })
+        }
+        proto::sync::sync_request::Messages::VoteSetRequest(request) => {
+            sync::Request::VoteSetRequest(sync::VoteSetRequest::new(
+                Height::new(request.block_number, request.fork_id),
+                Round::new(request.round),
+            ))
         }
     };
 
@@ -202,10 +242,52 @@ pub fn encode_sync_request(
                         fork_id: height.fork_id,
                         block_number: height.block_number,
                         end_block_number: Some(value_request.range.end().block_number),
+                        trace_id: value_request.trace_id.0,
+                        certificates_only: value_request.certificates_only,
                     },
                 )),
             }
         }
+        sync::Request::SnapshotRequest(sync::SnapshotRequest::List) => proto::sync::SyncRequest {
+            messages: Some(proto::sync::sync_request::Messages::SnapshotListRequest(
+                proto::sync::SnapshotListRequest {},
+            )),
+        },
+        sync::Request::SnapshotRequest(sync::SnapshotRequest::Chunk {
+            height,
+            format,
+            index,
+        }) => proto::sync::SyncRequest {
+            messages: Some(proto::sync::sync_request::Messages::SnapshotChunkRequest(
+                proto::sync::SnapshotChunkRequest {
+                    block_number: height.block_number,
+                    fork_id: height.fork_id,
+                    format: *format,
+                    index: *index,
+                },
+            )),
+        },
+        sync::Request::ProposalPartsRequest(request) => proto::sync::SyncRequest {
+            messages: Some(proto::sync::sync_request::Messages::ProposalPartsRequest(
+                proto::sync::ProposalPartsRequest {
+                    block_number: request.height.block_number,
+                    fork_id: request.height.fork_id,
+                    round: request.round.as_u32(),
+                    proposer: Some(encode_peer_id(&request.proposer)?),
+                    stream_id: request.stream_id.clone(),
+                    sequences: request.sequences.clone(),
+                },
+            )),
+        },
+        sync::Request::VoteSetRequest(request) => proto::sync::SyncRequest {
+            messages: Some(proto::sync::sync_request::Messages::VoteSetRequest(
+                proto::sync::VoteSetRequest {
+                    block_number: request.height.block_number,
+                    fork_id: request.height.fork_id,
+                    round: request.round.as_u32().expect("round should not be nil"),
+                },
+            )),
+        },
     };
 
     Ok(proto)
@@ -241,13 +323,58 @@ pub fn decode_sync_response(
 
     let response = match messages {
         proto::sync::sync_response::Messages::ValueResponse(value_response) => {
-            sync::Response::ValueResponse(ValueResponse::new(
+            let mut response = ValueResponse::new(
                 Height::new(value_response.block_number, value_response.fork_id),
                 value_response
                     .values
                     .into_iter()
                     .map(decode_synced_value)
                     .collect::<Result<Vec<_>, ProtoError>>()?,
+            )
+            .with_trace_id(sync::TraceId(value_response.trace_id));
+
+            response.retry_after = value_response.retry_after_ms.map(Duration::from_millis);
+            sync::Response::ValueResponse(response)
+        }
+        proto::sync::sync_response::Messages::SnapshotListResponse(response) => {
+            sync::Response::SnapshotResponse(sync::SnapshotResponse::List(
+                response
+                    .snapshots
+                    .into_iter()
+                    .map(|snapshot| {
+                        sync::Snapshot::new(
+                            Height::new(snapshot.block_number, snapshot.fork_id),
+                            snapshot.format,
+                            snapshot.chunks,
+                            snapshot.hash,
+                        )
+                    })
+                    .collect(),
+            ))
+        }
+        proto::sync::sync_response::Messages::SnapshotChunkResponse(response) => {
+            sync::Response::SnapshotResponse(sync::SnapshotResponse::Chunk {
+                height: Height::new(response.block_number, response.fork_id),
+                format: response.format,
+                index: response.index,
+                chunk: response.chunk,
+            })
+        }
+        proto::sync::sync_response::Messages::ProposalPartsResponse(response) => {
+            sync::Response::ProposalPartsResponse(sync::ProposalPartsResponse::new(
+                Height::new(response.block_number, response.fork_id),
+                response.parts,
+            ))
+        }
+        proto::sync::sync_response::Messages::VoteSetResponse(response) => {
+            sync::Response::VoteSetResponse(sync::VoteSetResponse::new(
+                Height::new(response.block_number, response.fork_id),
+                Round::new(response.round),
+                response
+                    .votes
+                    .into_iter()
+                    .map(decode_vote)
+                    .collect::<Result<Vec<_>, _>>()?,
             ))
         }
     };
@@ -269,6 +396,67 @@ pub fn encode_sync_response(
                         .iter()
                         .map(encode_synced_value)
                         .collect::<Result<Vec<_>, _>>()?,
+                    trace_id: value_response.trace_id.0,
+                    retry_after_ms: value_response
+                        .retry_after
+                        .map(|retry_after| retry_after.as_millis() as u64),
+                },
+            )),
+        },
+        sync::Response::SnapshotResponse(sync::SnapshotResponse::List(snapshots)) => {
+            proto::sync::SyncResponse {
+                messages: Some(proto::sync::sync_response::Messages::SnapshotListResponse(
+                    proto::sync::SnapshotListResponse {
+                        snapshots: snapshots
+                            .iter()
+                            .map(|snapshot| proto::sync::Snapshot {
+                                block_number: snapshot.height.block_number,
+                                fork_id: snapshot.height.fork_id,
+                                format: snapshot.format,
+                                chunks: snapshot.chunks,
+                                hash: snapshot.hash.clone(),
+                            })
+                            .collect(),
+                    },
+                )),
+            }
+        }
+        sync::Response::SnapshotResponse(sync::SnapshotResponse::Chunk {
+            height,
+            format,
+            index,
+            chunk,
+        }) => proto::sync::SyncResponse {
+            messages: Some(proto::sync::sync_response::Messages::SnapshotChunkResponse(
+                proto::sync::SnapshotChunkResponse {
+                    block_number: height.block_number,
+                    fork_id: height.fork_id,
+                    format: *format,
+                    index: *index,
+                    chunk: chunk.clone(),
+                },
+            )),
+        },
+        sync::Response::ProposalPartsResponse(response) => proto::sync::SyncResponse {
+            messages: Some(proto::sync::sync_response::Messages::ProposalPartsResponse(
+                proto::sync::ProposalPartsResponse {
+                    block_number: response.height.block_number,
+                    fork_id: response.height.fork_id,
+                    parts: response.parts.clone(),
+                },
+            )),
+        },
+        sync::Response::VoteSetResponse(response) => proto::sync::SyncResponse {
+            messages: Some(proto::sync::sync_response::Messages::VoteSetResponse(
+                proto::sync::VoteSetResponse {
+                    block_number: response.height.block_number,
+                    fork_id: response.height.fork_id,
+                    round: response.round.as_u32().expect("round should not be nil"),
+                    votes: response
+                        .votes
+                        .iter()
+                        .map(encode_vote)
+                        .collect::<Result<Vec<_>, _>>()?,
                 },
             )),
         },
@@ -682,14 +870,10 @@ impl Codec<sync::RawDecidedValue<MockContext>> for ProtobufCodec {
     }
 }
 
-// NOTE: Will be used again in #997
-#[allow(dead_code)]
 pub(crate) fn encode_vote(vote: &SignedVote<MockContext>) -> Result<proto::Vote, ProtoError> {
     vote.message.to_proto()
 }
 
-// NOTE: Will be used again in #997
-#[allow(dead_code)]
 pub(crate) fn decode_vote(msg: proto::Vote) -> Result<SignedVote<MockContext>, ProtoError> {
     let signature = Signature::test();
     let vote = Vote::from_proto(msg)?;
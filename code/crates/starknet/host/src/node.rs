@@ -2,6 +2,7 @@
 
 use std::path::PathBuf;
 
+use bytesize::ByteSize;
 use ractor::async_trait;
 use rand::{CryptoRng, RngCore};
 use serde::{Deserialize, Serialize};
@@ -252,6 +253,15 @@ fn make_config(index: usize, total: usize, settings: MakeConfigSettings) -> Conf
             enabled: true,
             value_payload: ValuePayload::PartsOnly,
             queue_capacity: 100,
+            max_votes_per_validator_per_round: 4,
+            follower: false,
+            dev_mode: false,
+            deterministic_ordering: None,
+            decision_proof: false,
+            verification_workers: 0,
+            verification_batch_size: 64,
+            max_value_size: ByteSize::mib(64),
+            mailbox: Default::default(),
             p2p: P2pConfig {
                 protocol: PubSubProtocol::default(),
                 listen_addr: settings.transport.multiaddr("127.0.0.1", consensus_port),
@@ -285,7 +295,7 @@ fn make_config(index: usize, total: usize, settings: MakeConfigSettings) -> Conf
                         })
                         .collect()
                 },
-                discovery: settings.discovery,
+                discovery: settings.discovery.clone(),
                 persistent_peers_only: settings.persistent_peers_only,
                 ..Default::default()
             },
@@ -349,6 +359,15 @@ fn make_distributed_config(
         consensus: ConsensusConfig {
             enabled: true,
             queue_capacity: 100,
+            max_votes_per_validator_per_round: 4,
+            follower: false,
+            dev_mode: false,
+            deterministic_ordering: None,
+            decision_proof: false,
+            verification_workers: 0,
+            verification_batch_size: 64,
+            max_value_size: ByteSize::mib(64),
+            mailbox: Default::default(),
             value_payload: ValuePayload::PartsOnly,
             p2p: P2pConfig {
                 protocol: PubSubProtocol::default(),
@@ -458,6 +477,7 @@ fn test_starknet_node() {
 
     file::save_priv_validator_key(
         &node,
+        &keystore::Keystore::default(),
         &node.private_key_file(),
         &PrivateKeyFile::from(priv_keys[0].clone()),
     )
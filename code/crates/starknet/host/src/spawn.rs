@@ -13,8 +13,10 @@ use malachitebft_engine::host::HostRef;
 use malachitebft_engine::network::{Network, NetworkRef};
 use malachitebft_engine::node::{Node, NodeRef};
 use malachitebft_engine::sync::{Params as SyncParams, Sync, SyncMsg, SyncRef};
+use malachitebft_engine::util::clock::SystemClock;
 use malachitebft_engine::util::events::TxEvent;
-use malachitebft_engine::wal::{Wal, WalRef};
+use malachitebft_engine::util::mailbox::Mailbox;
+use malachitebft_engine::wal::{Wal, WalBackendKind, WalRef};
 use malachitebft_metrics::{Metrics as ConsensusMetrics, SharedRegistry};
 use malachitebft_network as gossip;
 use malachitebft_network::{ChannelNames, Keypair};
@@ -98,6 +100,7 @@ pub async fn spawn_node_actor(
         sync_port.clone(),
         consensus_metrics,
         tx_event,
+        &registry,
         &span,
     )
     .await;
@@ -109,6 +112,7 @@ pub async fn spawn_node_actor(
         consensus.clone(),
         &cfg.value_sync,
         sync_metrics,
+        &registry,
         &span,
     )
     .await;
@@ -136,9 +140,17 @@ async fn spawn_wal_actor(
     std::fs::create_dir_all(&wal_dir).unwrap();
     let wal_file = wal_dir.join("consensus.wal");
 
-    Wal::spawn(ctx, codec, wal_file, registry.clone(), span.clone())
-        .await
-        .unwrap()
+    Wal::spawn(
+        ctx,
+        codec,
+        wal_file,
+        WalBackendKind::File,
+        None,
+        registry.clone(),
+        span.clone(),
+    )
+    .await
+    .unwrap()
 }
 
 async fn spawn_sync_actor(
@@ -148,6 +160,7 @@ async fn spawn_sync_actor(
     consensus: ConsensusRef<MockContext>,
     config: &ValueSyncConfig,
     sync_metrics: sync::Metrics,
+    registry: &SharedRegistry,
     span: &tracing::Span,
 ) -> Option<SyncRef<MockContext>> {
     if !config.enabled {
@@ -157,6 +170,7 @@ async fn spawn_sync_actor(
     let params = SyncParams {
         status_update_interval: config.status_update_interval,
         request_timeout: config.request_timeout,
+        checkpoint_path: config.checkpoint_file.clone(),
     };
 
     let scoring_strategy = match config.scoring_strategy {
@@ -167,12 +181,29 @@ async fn spawn_sync_actor(
         enabled: config.enabled,
         max_request_size: config.max_request_size.as_u64() as usize,
         max_response_size: config.max_response_size.as_u64() as usize,
+        max_chunked_response_size: config.max_chunked_value_size(),
         request_timeout: config.request_timeout,
         parallel_requests: config.parallel_requests,
         scoring_strategy,
         inactive_threshold: (!config.inactive_threshold.is_zero())
             .then_some(config.inactive_threshold),
         batch_size: config.batch_size,
+        audit_window: config.audit_window,
+        advertise_min_needed_height: config.advertise_min_needed_height,
+        // Negotiated by the network layer, see `P2pConfig::compression`
+        compression: false,
+        max_in_flight_bytes: config.max_in_flight_bytes(),
+        max_inbound_requests: config.max_inbound_requests(),
+        max_inbound_requests_per_peer: config.max_inbound_requests_per_peer(),
+        max_served_bytes_per_sec: config.max_served_bytes_per_sec(),
+        certificates_only: config.certificates_only,
+        verification_queue_size: config.verification_queue_size,
+        snapshot_sync: config.snapshot_sync,
+        snapshot_min_lag: config.snapshot_min_lag,
+        snapshot_min_peers: config.snapshot_min_peers,
+        backfill: config.backfill,
+        proposal_parts_delay: config.proposal_parts_delay,
+        vote_sync: config.vote_sync,
     };
 
     let actor_ref = Sync::spawn(
@@ -184,6 +215,7 @@ async fn spawn_sync_actor(
         ProtobufCodec,
         sync_config,
         sync_metrics,
+        Mailbox::new("sync", config.mailbox, registry),
         span.clone(),
     )
     .await
@@ -204,6 +236,7 @@ async fn spawn_consensus_actor(
     sync: Arc<OutputPort<SyncMsg<MockContext>>>,
     consensus_metrics: ConsensusMetrics,
     tx_event: TxEvent<MockContext>,
+    registry: &SharedRegistry,
     span: &tracing::Span,
 ) -> ConsensusRef<MockContext> {
     let consensus_params = ConsensusParams {
@@ -211,8 +244,13 @@ async fn spawn_consensus_actor(
         threshold_params: Default::default(),
         value_payload: ValuePayload::PartsOnly,
         enabled: cfg.consensus.enabled,
+        max_votes_per_validator_per_round: (cfg.consensus.max_votes_per_validator_per_round > 0)
+            .then_some(cfg.consensus.max_votes_per_validator_per_round),
+        follower: cfg.consensus.follower,
     };
 
+    let mailbox = Mailbox::new("consensus", cfg.consensus.mailbox, registry);
+
     Consensus::spawn(
         ctx,
         consensus_params,
@@ -221,9 +259,12 @@ async fn spawn_consensus_actor(
         network,
         host,
         wal,
+        false,
         sync,
         consensus_metrics,
         tx_event,
+        Arc::new(SystemClock::new()),
+        mailbox,
         span.clone(),
     )
     .await
@@ -244,21 +285,48 @@ async fn spawn_network_actor(
     let selector = match cfg.consensus.p2p.discovery.selector {
         config::Selector::Kademlia => gossip::Selector::Kademlia,
         config::Selector::Random => gossip::Selector::Random,
+        config::Selector::Latency => gossip::Selector::Latency,
+    };
+
+    let address_family_preference = match cfg.consensus.p2p.discovery.address_family_preference {
+        config::AddressFamilyPreference::Any => gossip::AddressFamilyPreference::Any,
+        config::AddressFamilyPreference::Ipv6 => gossip::AddressFamilyPreference::Ipv6,
+        config::AddressFamilyPreference::Ipv4 => gossip::AddressFamilyPreference::Ipv4,
+    };
+
+    let security = match cfg.consensus.p2p.security {
+        config::SecurityProtocol::Noise => gossip::SecurityProtocol::Noise,
+        config::SecurityProtocol::Tls => gossip::SecurityProtocol::Tls,
+        config::SecurityProtocol::NoiseAndTls => gossip::SecurityProtocol::NoiseAndTls,
     };
 
     let config_gossip = gossip::Config {
         listen_addr: cfg.consensus.p2p.listen_addr.clone(),
         persistent_peers: cfg.consensus.p2p.persistent_peers.clone(),
         persistent_peers_only: cfg.consensus.p2p.persistent_peers_only,
+        explicit_peers: cfg.consensus.p2p.explicit_peers.clone(),
         discovery: gossip::DiscoveryConfig {
             enabled: cfg.consensus.p2p.discovery.enabled,
             persistent_peers_only: cfg.consensus.p2p.persistent_peers_only,
+            allowed_peers: (!cfg.consensus.p2p.allowed_peers.is_empty())
+                .then(|| cfg.consensus.p2p.allowed_peers.iter().copied().collect()),
+            persistent_peers: cfg.consensus.p2p.discovery.persistent_peers.clone(),
             bootstrap_protocol,
             selector,
             num_outbound_peers: cfg.consensus.p2p.discovery.num_outbound_peers,
             num_inbound_peers: cfg.consensus.p2p.discovery.num_inbound_peers,
             max_connections_per_peer: cfg.consensus.p2p.discovery.max_connections_per_peer,
             ephemeral_connection_timeout: cfg.consensus.p2p.discovery.ephemeral_connection_timeout,
+            bootstrap_timeout: cfg.consensus.p2p.discovery.bootstrap_timeout,
+            address_family_preference,
+            happy_eyeballs_delay: cfg.consensus.p2p.discovery.happy_eyeballs_delay,
+            relay_rebalance_interval: cfg.consensus.p2p.discovery.relay_rebalance_interval,
+            max_concurrent_dials: cfg.consensus.p2p.discovery.max_concurrent_dials,
+            dials_per_second: cfg.consensus.p2p.discovery.dials_per_second,
+            reconnect: gossip::ReconnectConfig {
+                spread_per_peer: cfg.consensus.p2p.discovery.reconnect.spread_per_peer,
+                max_spread: cfg.consensus.p2p.discovery.reconnect.max_spread,
+            },
             ..Default::default()
         },
         idle_connection_timeout: Duration::from_secs(15 * 60),
@@ -269,6 +337,49 @@ async fn spawn_network_actor(
                     cfg.consensus.p2p.listen_addr
                 )
             }),
+        security,
+        dial_timeouts: gossip::DialTimeouts {
+            tcp_connect: cfg.consensus.p2p.dial_timeouts.tcp_connect,
+            relay_circuit: cfg.consensus.p2p.dial_timeouts.relay_circuit,
+            dcutr_upgrade: cfg.consensus.p2p.dial_timeouts.dcutr_upgrade,
+        },
+        websocket: gossip::WebSocketConfig {
+            tls: match (
+                &cfg.consensus.p2p.websocket.tls_cert_file,
+                &cfg.consensus.p2p.websocket.tls_key_file,
+            ) {
+                (Some(cert_file), Some(key_file)) => Some(gossip::WebSocketTlsConfig {
+                    cert_file: cert_file.clone(),
+                    key_file: key_file.clone(),
+                }),
+                (None, None) => None,
+                _ => panic!(
+                    "Both `tls_cert_file` and `tls_key_file` must be set for secure WebSocket"
+                ),
+            },
+            trusted_certs: cfg.consensus.p2p.websocket.trusted_certs.clone(),
+            advertised_addrs: cfg.consensus.p2p.websocket.advertised_addrs.clone(),
+        },
+        bandwidth: gossip::BandwidthConfig {
+            max_inbound_rate: cfg
+                .consensus
+                .p2p
+                .bandwidth
+                .max_inbound_rate
+                .map(|rate| rate.as_u64()),
+            max_violations: cfg.consensus.p2p.bandwidth.max_violations,
+        },
+        liveness: gossip::LivenessConfig {
+            max_ping_failures: cfg.consensus.p2p.liveness.max_ping_failures,
+            max_ping_rtt: cfg.consensus.p2p.liveness.max_ping_rtt,
+        },
+        connection_limits: gossip::ConnectionLimitsConfig {
+            max_established_incoming: cfg.consensus.p2p.connection_limits.max_established_incoming,
+            max_established_outgoing: cfg.consensus.p2p.connection_limits.max_established_outgoing,
+            max_pending_incoming: cfg.consensus.p2p.connection_limits.max_pending_incoming,
+            max_pending_outgoing: cfg.consensus.p2p.connection_limits.max_pending_outgoing,
+            max_established_per_peer: cfg.consensus.p2p.connection_limits.max_established_per_peer,
+        },
         pubsub_protocol: match cfg.consensus.p2p.protocol {
             config::PubSubProtocol::GossipSub(_) => gossip::PubSubProtocol::GossipSub,
             config::PubSubProtocol::Broadcast => gossip::PubSubProtocol::Broadcast,
@@ -281,13 +392,45 @@ async fn spawn_network_actor(
                 mesh_outbound_min: config.mesh_outbound_min(),
                 enable_peer_scoring: config.enable_peer_scoring(),
                 enable_explicit_peering: config.enable_explicit_peering(),
+                enable_explicit_validator_peering: config.enable_explicit_validator_peering(),
                 enable_flood_publish: config.enable_flood_publish(),
+                flood_publish_votes: config.flood_publish_votes(),
+                peer_score: gossip::PeerScoreConfig {
+                    app_specific_weight: config.peer_score().app_specific_weight,
+                    ip_colocation_factor_weight: config.peer_score().ip_colocation_factor_weight,
+                    ip_colocation_factor_threshold: config
+                        .peer_score()
+                        .ip_colocation_factor_threshold,
+                    behaviour_penalty_weight: config.peer_score().behaviour_penalty_weight,
+                    behaviour_penalty_decay: config.peer_score().behaviour_penalty_decay,
+                    gossip_threshold: config.peer_score().gossip_threshold,
+                    publish_threshold: config.peer_score().publish_threshold,
+                    graylist_threshold: config.peer_score().graylist_threshold,
+                    opportunistic_graft_threshold: config
+                        .peer_score()
+                        .opportunistic_graft_threshold,
+                    reported_score_decay: config.peer_score().reported_score_decay,
+                },
+                validate_messages: config.validate_messages(),
+                history_length: config.history_length(),
+                history_gossip: config.history_gossip(),
+                duplicate_cache_time: config.duplicate_cache_time(),
             },
             config::PubSubProtocol::Broadcast => gossip::GossipSubConfig::default(),
         },
         channel_names: ChannelNames::default(),
         rpc_max_size: cfg.consensus.p2p.rpc_max_size.as_u64() as usize,
+        rpc_max_chunked_size: cfg.value_sync.max_chunked_value_size(),
         pubsub_max_size: cfg.consensus.p2p.pubsub_max_size.as_u64() as usize,
+        max_message_sizes: Default::default(),
+        hole_punch: Default::default(),
+        compression: gossip::CompressionConfig {
+            consensus: cfg.consensus.p2p.compression.consensus,
+            proposal_parts: cfg.consensus.p2p.compression.proposal_parts,
+            liveness: cfg.consensus.p2p.compression.liveness,
+            sync: cfg.consensus.p2p.compression.sync,
+        },
+        mempool: Default::default(),
         enable_consensus: cfg.consensus.enabled,
         enable_sync: true,
         protocol_names: gossip::ProtocolNames {
@@ -296,7 +439,12 @@ async fn spawn_network_actor(
             discovery_regres: cfg.consensus.p2p.protocol_names.discovery_regres.clone(),
             sync: cfg.consensus.p2p.protocol_names.sync.clone(),
             validator_proof: cfg.consensus.p2p.protocol_names.validator_proof.clone(),
+            peer_message: cfg.consensus.p2p.protocol_names.peer_message.clone(),
+            node_key: cfg.consensus.p2p.protocol_names.node_key.clone(),
         },
+        // A single chain per node, see `gossip::spawn_chains` to serve several ones
+        chain_ids: Vec::new(),
+        node_key_file: cfg.consensus.p2p.node_key_file.clone(),
     };
 
     let codec = ProtobufCodec;
@@ -306,6 +454,9 @@ async fn spawn_network_actor(
         config_gossip,
         registry.clone(),
         codec,
+        cfg.consensus.max_value_size.as_u64() as usize,
+        Mailbox::new("network", cfg.consensus.p2p.mailbox, registry),
+        cfg.consensus.p2p.vote_batching,
         span.clone(),
     )
     .await
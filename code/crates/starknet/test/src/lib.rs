@@ -5,6 +5,7 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use bytesize::ByteSize;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 
@@ -147,6 +148,15 @@ impl TestRunner {
                 enabled: true,
                 value_payload: ValuePayload::PartsOnly,
                 queue_capacity: 100,
+                max_votes_per_validator_per_round: 4,
+                follower: false,
+                dev_mode: false,
+                deterministic_ordering: None,
+                decision_proof: false,
+                verification_workers: 0,
+                verification_batch_size: 64,
+                max_value_size: ByteSize::mib(64),
+                mailbox: Default::default(),
                 p2p: P2pConfig {
                     protocol,
                     discovery: DiscoveryConfig::default(),
@@ -0,0 +1,352 @@
+//! Per-peer history of the sync requests sent to and received from peers,
+//! kept over a sliding time window so that operators can find out which peer
+//! served corrupt or slow data during a sync incident.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant, SystemTime};
+
+use derive_where::derive_where;
+use malachitebft_core_types::{Context, Height};
+use malachitebft_peer::PeerId;
+
+use crate::{InboundRequestId, RawDecidedValue, TraceId};
+
+/// Maximum number of entries kept per peer, regardless of the time window
+const MAX_ENTRIES_PER_PEER: usize = 1024;
+
+/// Whether the request was sent by us or by the peer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuditDirection {
+    /// We requested values from the peer
+    Sent,
+    /// The peer requested values from us
+    Received,
+}
+
+/// Outcome of a sync request
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuditOutcome {
+    /// All the requested values were provided
+    Complete,
+    /// Only a prefix of the requested values was provided
+    Partial,
+    /// The request was refused, or answered without any values
+    Empty,
+    /// The response did not match the request
+    InvalidResponse,
+    /// One of the provided values failed validation
+    InvalidValue,
+    /// One of the provided values could not be processed
+    ProcessingError,
+    /// No response was received before the request timed out
+    TimedOut,
+}
+
+/// A sync request sent to or received from a peer
+#[derive_where(Clone, Debug)]
+pub struct AuditEntry<Ctx: Context> {
+    /// When the request completed
+    pub at: SystemTime,
+    pub direction: AuditDirection,
+    pub trace_id: TraceId,
+    /// The requested range of heights
+    pub range: RangeInclusive<Ctx::Height>,
+    /// Number of values in the response
+    pub num_values: usize,
+    /// Total size in bytes of the values in the response
+    pub size: usize,
+    /// Time between the request and the response, if known
+    pub latency: Option<Duration>,
+    pub outcome: AuditOutcome,
+}
+
+/// History of the sync requests of each peer over the last `window`
+#[derive_where(Clone, Debug)]
+pub struct RequestAudit<Ctx: Context> {
+    window: Duration,
+    entries: BTreeMap<PeerId, VecDeque<AuditEntry<Ctx>>>,
+    /// Requests received from peers which are waiting for values from the host
+    pending_received: HashMap<InboundRequestId, (PeerId, Instant)>,
+}
+
+impl<Ctx: Context> RequestAudit<Ctx> {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: BTreeMap::new(),
+            pending_received: HashMap::new(),
+        }
+    }
+
+    /// The entries of each peer, oldest first
+    pub fn entries(&self) -> &BTreeMap<PeerId, VecDeque<AuditEntry<Ctx>>> {
+        &self.entries
+    }
+
+    /// Record a request received from a peer, to be completed with [`Self::record_served`]
+    pub fn request_received(&mut self, request_id: InboundRequestId, peer_id: PeerId) {
+        self.pending_received
+            .insert(request_id, (peer_id, Instant::now()));
+    }
+
+    /// Record the response sent to a request received from a peer,
+    /// returning the peer if the request is known
+    pub fn record_served(
+        &mut self,
+        request_id: &InboundRequestId,
+        trace_id: TraceId,
+        range: RangeInclusive<Ctx::Height>,
+        values: &[RawDecidedValue<Ctx>],
+    ) -> Option<PeerId> {
+        let (peer_id, received_at) = self.pending_received.remove(request_id)?;
+
+        // The values may have been truncated to fit in the maximum response size
+        let range_len = range.end().as_u64() - range.start().as_u64() + 1;
+        let outcome = if values.is_empty() {
+            AuditOutcome::Empty
+        } else if (values.len() as u64) < range_len {
+            AuditOutcome::Partial
+        } else {
+            AuditOutcome::Complete
+        };
+
+        self.record(
+            peer_id,
+            AuditEntry {
+                at: SystemTime::now(),
+                direction: AuditDirection::Received,
+                trace_id,
+                range,
+                num_values: values.len(),
+                size: total_size(values),
+                latency: Some(received_at.elapsed()),
+                outcome,
+            },
+        );
+
+        Some(peer_id)
+    }
+
+    /// Record a request received from a peer which was refused
+    pub fn record_refused(
+        &mut self,
+        peer_id: PeerId,
+        trace_id: TraceId,
+        range: RangeInclusive<Ctx::Height>,
+    ) {
+        self.record(
+            peer_id,
+            AuditEntry {
+                at: SystemTime::now(),
+                direction: AuditDirection::Received,
+                trace_id,
+                range,
+                num_values: 0,
+                size: 0,
+                latency: None,
+                outcome: AuditOutcome::Empty,
+            },
+        );
+    }
+
+    /// Record the response of a peer to one of our requests
+    pub fn record_response(
+        &mut self,
+        peer_id: PeerId,
+        trace_id: TraceId,
+        range: RangeInclusive<Ctx::Height>,
+        values: &[RawDecidedValue<Ctx>],
+        latency: Option<Duration>,
+        outcome: AuditOutcome,
+    ) {
+        self.record(
+            peer_id,
+            AuditEntry {
+                at: SystemTime::now(),
+                direction: AuditDirection::Sent,
+                trace_id,
+                range,
+                num_values: values.len(),
+                size: total_size(values),
+                latency,
+                outcome,
+            },
+        );
+    }
+
+    /// Record one of our requests to a peer which did not get a response
+    pub fn record_timeout(
+        &mut self,
+        peer_id: PeerId,
+        trace_id: TraceId,
+        range: RangeInclusive<Ctx::Height>,
+    ) {
+        self.record(
+            peer_id,
+            AuditEntry {
+                at: SystemTime::now(),
+                direction: AuditDirection::Sent,
+                trace_id,
+                range,
+                num_values: 0,
+                size: 0,
+                latency: None,
+                outcome: AuditOutcome::TimedOut,
+            },
+        );
+    }
+
+    /// Flag the latest response of the peer containing the given height as faulty,
+    /// eg. because the value at that height failed validation.
+    pub fn flag_value(&mut self, peer_id: PeerId, height: Ctx::Height, outcome: AuditOutcome) {
+        let entry = self.entries.get_mut(&peer_id).and_then(|entries| {
+            entries.iter_mut().rev().find(|entry| {
+                entry.direction == AuditDirection::Sent && entry.range.contains(&height)
+            })
+        });
+
+        match entry {
+            Some(entry) => entry.outcome = outcome,
+            None => self.record(
+                peer_id,
+                AuditEntry {
+                    at: SystemTime::now(),
+                    direction: AuditDirection::Sent,
+                    trace_id: TraceId::default(),
+                    range: height..=height,
+                    num_values: 0,
+                    size: 0,
+                    latency: None,
+                    outcome,
+                },
+            ),
+        }
+    }
+
+    /// Remove the entries older than the window
+    pub fn prune(&mut self) {
+        let Some(cutoff) = SystemTime::now().checked_sub(self.window) else {
+            return;
+        };
+
+        self.entries.retain(|_, entries| {
+            while entries.front().is_some_and(|entry| entry.at < cutoff) {
+                entries.pop_front();
+            }
+            !entries.is_empty()
+        });
+
+        // Requests which the host never answered
+        let window = self.window;
+        self.pending_received
+            .retain(|_, (_, received_at)| received_at.elapsed() < window);
+    }
+
+    fn record(&mut self, peer_id: PeerId, entry: AuditEntry<Ctx>) {
+        let entries = self.entries.entry(peer_id).or_default();
+
+        if entries.len() >= MAX_ENTRIES_PER_PEER {
+            entries.pop_front();
+        }
+
+        entries.push_back(entry);
+    }
+}
+
+fn total_size<Ctx: Context>(values: &[RawDecidedValue<Ctx>]) -> usize {
+    values.iter().map(|value| value.value_bytes.len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use arc_malachitebft_test::{Height, TestContext};
+
+    use super::*;
+
+    #[test]
+    fn entries_are_grouped_by_peer() {
+        let mut audit = RequestAudit::<TestContext>::new(Duration::from_secs(60));
+        let (a, b) = (PeerId::random(), PeerId::random());
+
+        audit.record_timeout(a, TraceId(1), Height::new(1)..=Height::new(5));
+        audit.record_refused(b, TraceId(2), Height::new(10)..=Height::new(20));
+        audit.record_response(
+            a,
+            TraceId(3),
+            Height::new(1)..=Height::new(5),
+            &[],
+            Some(Duration::from_millis(20)),
+            AuditOutcome::Empty,
+        );
+
+        let entries = audit.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[&a].len(), 2);
+        assert_eq!(entries[&a][0].outcome, AuditOutcome::TimedOut);
+        assert_eq!(entries[&a][1].trace_id, TraceId(3));
+        assert_eq!(entries[&b][0].direction, AuditDirection::Received);
+    }
+
+    #[test]
+    fn flag_value_marks_latest_response() {
+        let mut audit = RequestAudit::<TestContext>::new(Duration::from_secs(60));
+        let p = PeerId::random();
+
+        audit.record_response(
+            p,
+            TraceId(1),
+            Height::new(1)..=Height::new(5),
+            &[],
+            None,
+            AuditOutcome::Complete,
+        );
+        audit.flag_value(p, Height::new(3), AuditOutcome::InvalidValue);
+        assert_eq!(audit.entries()[&p][0].outcome, AuditOutcome::InvalidValue);
+
+        // No response contains the height
+        audit.flag_value(p, Height::new(8), AuditOutcome::ProcessingError);
+        assert_eq!(audit.entries()[&p].len(), 2);
+        assert_eq!(
+            audit.entries()[&p][1].range,
+            Height::new(8)..=Height::new(8)
+        );
+    }
+
+    #[test]
+    fn old_entries_are_pruned() {
+        let mut audit = RequestAudit::<TestContext>::new(Duration::ZERO);
+        let p = PeerId::random();
+
+        audit.record_timeout(p, TraceId(1), Height::new(1)..=Height::new(5));
+        audit.request_received(InboundRequestId::new(1), p);
+        std::thread::sleep(Duration::from_millis(1));
+        audit.prune();
+
+        assert!(audit.entries().is_empty());
+
+        // The pending request was dropped too
+        let served = audit.record_served(
+            &InboundRequestId::new(1),
+            TraceId(2),
+            Height::new(1)..=Height::new(1),
+            &[],
+        );
+        assert_eq!(served, None);
+        assert!(audit.entries().is_empty());
+    }
+
+    #[test]
+    fn entries_per_peer_are_bounded() {
+        let mut audit = RequestAudit::<TestContext>::new(Duration::from_secs(60));
+        let p = PeerId::random();
+
+        for i in 0..MAX_ENTRIES_PER_PEER as u64 + 10 {
+            audit.record_timeout(p, TraceId(i), Height::new(1)..=Height::new(1));
+        }
+
+        let entries = &audit.entries()[&p];
+        assert_eq!(entries.len(), MAX_ENTRIES_PER_PEER);
+        assert_eq!(entries[0].trace_id, TraceId(10));
+    }
+}
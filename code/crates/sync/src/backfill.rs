@@ -0,0 +1,110 @@
+//! Fetching the decided values below the height the node started from, eg. from genesis
+//! up to the height of the snapshot it restored its state from, for archival nodes.
+//!
+//! Backfilling has a lower priority than syncing up to the tip: a single range of values
+//! is requested at a time, and only once the node is caught up with its peers.
+//! The values are handed to the application to store, consensus not being involved.
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use derive_where::derive_where;
+
+use malachitebft_core_types::{Context, Height};
+use malachitebft_peer::PeerId;
+
+use crate::OutboundRequestId;
+
+#[derive_where(Debug)]
+pub struct Backfill<Ctx: Context> {
+    /// Ranges of heights not requested yet, in increasing order
+    pub remaining: Vec<RangeInclusive<Ctx::Height>>,
+
+    /// Requests for values not answered or stored yet, with the range requested and the peer
+    pub pending: BTreeMap<OutboundRequestId, (RangeInclusive<Ctx::Height>, PeerId)>,
+}
+
+impl<Ctx: Context> Backfill<Ctx> {
+    pub fn new(range: RangeInclusive<Ctx::Height>) -> Self {
+        Self {
+            remaining: if range.is_empty() {
+                Vec::new()
+            } else {
+                vec![range]
+            },
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Take the lowest range of at most `max_size` heights not requested yet
+    pub fn next_range(&mut self, max_size: u64) -> Option<RangeInclusive<Ctx::Height>> {
+        let range = self.remaining.first()?.clone();
+        let end = std::cmp::min(
+            *range.end(),
+            range.start().increment_by(max_size.max(1) - 1),
+        );
+
+        if end == *range.end() {
+            self.remaining.remove(0);
+        } else {
+            self.remaining[0] = end.increment()..=*range.end();
+        }
+
+        Some(*range.start()..=end)
+    }
+
+    /// Put back a range of heights which could not be fetched, to be requested again
+    pub fn give_back(&mut self, range: RangeInclusive<Ctx::Height>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let index = self
+            .remaining
+            .partition_point(|remaining| remaining.start() < range.start());
+
+        self.remaining.insert(index, range);
+    }
+
+    /// Number of heights not fetched yet, including the ones requested
+    pub fn remaining_heights(&self) -> u64 {
+        self.remaining
+            .iter()
+            .chain(self.pending.values().map(|(range, _)| range))
+            .map(|range| range.end().as_u64() - range.start().as_u64() + 1)
+            .sum()
+    }
+
+    /// Whether all the heights have been fetched
+    pub fn is_done(&self) -> bool {
+        self.remaining.is_empty() && self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arc_malachitebft_test::{Height, TestContext};
+
+    use super::*;
+
+    fn range(start: u64, end: u64) -> RangeInclusive<Height> {
+        Height::new(start)..=Height::new(end)
+    }
+
+    #[test]
+    fn next_range() {
+        let mut backfill = Backfill::<TestContext>::new(range(1, 10));
+
+        assert_eq!(backfill.next_range(4), Some(range(1, 4)));
+        assert_eq!(backfill.next_range(4), Some(range(5, 8)));
+
+        // The range could not be fetched, it is requested again before the higher ones
+        backfill.give_back(range(1, 4));
+        assert_eq!(backfill.remaining_heights(), 6);
+
+        assert_eq!(backfill.next_range(4), Some(range(1, 4)));
+        assert_eq!(backfill.next_range(4), Some(range(9, 10)));
+        assert_eq!(backfill.next_range(4), None);
+        assert!(backfill.is_done());
+    }
+}
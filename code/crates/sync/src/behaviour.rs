@@ -5,7 +5,7 @@ use libp2p::swarm::NetworkBehaviour;
 use libp2p::{PeerId, StreamProtocol};
 use thiserror::Error;
 
-use crate::rpc::Codec;
+use crate::rpc::{Codec, COMPRESSED_PROTOCOL_SUFFIX};
 use crate::types::{RawRequest, RawResponse, ResponseChannel};
 use crate::Config;
 
@@ -19,10 +19,7 @@ pub type Event = rpc::Event<RawRequest, RawResponse>;
 
 impl Behaviour {
     pub fn new(config: Config, sync_protocol: String) -> Result<Self> {
-        let protocol = [(
-            StreamProtocol::try_from_owned(sync_protocol)?,
-            ProtocolSupport::Full,
-        )];
+        let protocol = protocols(&config, StreamProtocol::try_from_owned(sync_protocol)?);
         let rpc_config = rpc::Config::default().with_request_timeout(config.request_timeout);
 
         Ok(Self {
@@ -41,6 +38,26 @@ impl Behaviour {
     }
 }
 
+/// The supported protocols, with the variant compressing the payloads listed first when
+/// compression is enabled, so that it is negotiated whenever the peer supports it too.
+fn protocols(
+    config: &Config,
+    sync_protocol: StreamProtocol,
+) -> Vec<(StreamProtocol, ProtocolSupport)> {
+    let mut protocols = Vec::with_capacity(2);
+
+    if config.compression {
+        let compressed = format!("{sync_protocol}{COMPRESSED_PROTOCOL_SUFFIX}");
+        let compressed = StreamProtocol::try_from_owned(compressed)
+            .expect("suffixed protocol name still starts with a slash");
+
+        protocols.push((compressed, ProtocolSupport::Full));
+    }
+
+    protocols.push((sync_protocol, ProtocolSupport::Full));
+    protocols
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum Error {
     #[error("Failed to send response")]
@@ -53,10 +70,7 @@ pub enum Error {
 impl Behaviour {
     pub fn with_default_protocol(config: Config) -> Self {
         // Infallible constructor using hardcoded default protocol
-        let protocol = [(
-            StreamProtocol::new("/malachitebft-sync/v1beta1"),
-            ProtocolSupport::Full,
-        )];
+        let protocol = protocols(&config, StreamProtocol::new("/malachitebft-sync/v1beta1"));
         let rpc_config = rpc::Config::default().with_request_timeout(config.request_timeout);
 
         Self {
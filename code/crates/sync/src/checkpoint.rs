@@ -0,0 +1,110 @@
+//! Sync progress persisted across restarts, so that a restarted node selects the peers
+//! which served it well before instead of learning their scores from scratch.
+//!
+//! The checkpoint is encoded as text, one entry per line:
+//!
+//! ```text
+//! tip_height 1234
+//! peer 12D3KooWAbc... 0.87
+//! ```
+
+use std::fmt::Write;
+
+use malachitebft_peer::PeerId;
+
+use crate::scoring::Score;
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum CheckpointError {
+    #[error("Invalid line {line} in sync checkpoint: {reason}")]
+    InvalidLine { line: usize, reason: String },
+
+    #[error("Missing tip height in sync checkpoint")]
+    MissingTipHeight,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    /// Height of the last value decided before the checkpoint was taken
+    pub tip_height: u64,
+
+    /// Scores of the peers values were synced from
+    pub peer_scores: Vec<(PeerId, Score)>,
+}
+
+impl Checkpoint {
+    pub fn encode(&self) -> String {
+        let mut out = format!("tip_height {}\n", self.tip_height);
+
+        for (peer_id, score) in &self.peer_scores {
+            let _ = writeln!(out, "peer {peer_id} {score}");
+        }
+
+        out
+    }
+
+    pub fn decode(data: &str) -> Result<Self, CheckpointError> {
+        let mut tip_height = None;
+        let mut peer_scores = Vec::new();
+
+        for (index, line) in data.lines().enumerate() {
+            let invalid = |reason: &str| CheckpointError::InvalidLine {
+                line: index + 1,
+                reason: reason.to_string(),
+            };
+
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+
+            match fields.as_slice() {
+                [] => continue,
+                ["tip_height", height] => {
+                    let height = height.parse().map_err(|_| invalid("invalid height"))?;
+                    tip_height = Some(height);
+                }
+                ["peer", peer_id, score] => {
+                    let peer_id = peer_id.parse().map_err(|_| invalid("invalid peer id"))?;
+                    let score = score.parse().map_err(|_| invalid("invalid score"))?;
+                    peer_scores.push((peer_id, score));
+                }
+                _ => return Err(invalid("unknown entry")),
+            }
+        }
+
+        Ok(Self {
+            tip_height: tip_height.ok_or(CheckpointError::MissingTipHeight)?,
+            peer_scores,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let checkpoint = Checkpoint {
+            tip_height: 42,
+            peer_scores: vec![(PeerId::random(), 0.75), (PeerId::random(), 0.1)],
+        };
+
+        let decoded = Checkpoint::decode(&checkpoint.encode()).unwrap();
+        assert_eq!(decoded, checkpoint);
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(
+            Checkpoint::decode("peer foo 0.5\n"),
+            Err(CheckpointError::InvalidLine {
+                line: 1,
+                reason: "invalid peer id".to_string()
+            })
+        );
+
+        assert_eq!(
+            Checkpoint::decode(""),
+            Err(CheckpointError::MissingTipHeight)
+        );
+    }
+}
@@ -4,6 +4,9 @@ use crate::scoring::Strategy;
 
 const DEFAULT_PARALLEL_REQUESTS: usize = 5;
 const DEFAULT_BATCH_SIZE: usize = 5;
+const DEFAULT_AUDIT_WINDOW: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_SNAPSHOT_MIN_LAG: u64 = 1000;
+const DEFAULT_SNAPSHOT_MIN_PEERS: usize = 2;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Config {
@@ -11,10 +14,57 @@ pub struct Config {
     pub request_timeout: Duration,
     pub max_request_size: usize,
     pub max_response_size: usize,
+    /// Maximum size of a response split into chunks of at most `max_response_size` bytes,
+    /// so that values larger than `max_response_size` can still be synced.
+    /// Responses are never chunked if `None`.
+    pub max_chunked_response_size: Option<usize>,
     pub parallel_requests: usize,
     pub scoring_strategy: Strategy,
     pub inactive_threshold: Option<Duration>,
     pub batch_size: usize,
+    /// How long requests are kept in the per-peer request audit
+    pub audit_window: Duration,
+    /// Advertise the minimum height this node still needs in its status messages,
+    /// so that serving nodes can prune their history without cutting it off.
+    pub advertise_min_needed_height: bool,
+    /// Compress the requests and responses with LZ4 when the peer supports it,
+    /// the size limits applying to the uncompressed payloads.
+    pub compression: bool,
+    /// Estimated size of the values requested and not received yet above which no new
+    /// requests are sent, until responses arrive. Unlimited if `None`.
+    pub max_in_flight_bytes: Option<usize>,
+    /// Maximum number of requests from all the peers served at the same time,
+    /// the requests above it being rejected. Unlimited if `None`.
+    pub max_inbound_requests: Option<usize>,
+    /// Maximum number of requests from a single peer served at the same time.
+    /// Unlimited if `None`.
+    pub max_inbound_requests_per_peer: Option<usize>,
+    /// Maximum number of bytes of values served per second. Unlimited if `None`.
+    pub max_served_bytes_per_sec: Option<usize>,
+    /// Request only the commit certificates of the decided values, for nodes which do not
+    /// need to re-execute the values, eg. light verification nodes.
+    pub certificates_only: bool,
+    /// Number of answered requests whose values can wait to be verified and decided
+    /// by consensus without counting against `parallel_requests`, so that the next
+    /// ranges are downloaded in the meantime.
+    pub verification_queue_size: usize,
+    /// Restore the application state from a snapshot served by the peers, instead of
+    /// syncing every value, when more than `snapshot_min_lag` heights behind them.
+    pub snapshot_sync: bool,
+    /// Minimum number of heights between the tip and a snapshot for it to be restored.
+    pub snapshot_min_lag: u64,
+    /// Minimum number of peers which must offer the same snapshot, down to its hash,
+    /// for it to be restored.
+    pub snapshot_min_peers: usize,
+    /// After restoring a snapshot, fetch the values below its height once caught up
+    /// with the peers, and hand them to the application to store.
+    pub backfill: bool,
+    /// Delay after which the parts of a proposal of the current height still missing once
+    /// the end of its stream is received are requested from the peers. Never requested if `None`.
+    pub proposal_parts_delay: Option<Duration>,
+    /// Request the votes of the round consensus is stuck in from the peers deciding
+    /// the same height, to recover from the votes missed on the gossip layer
+    pub vote_sync: bool,
 }
 
 impl Config {
@@ -40,6 +90,14 @@ impl Config {
         self
     }
 
+    pub fn with_max_chunked_response_size(
+        mut self,
+        max_chunked_response_size: Option<usize>,
+    ) -> Self {
+        self.max_chunked_response_size = max_chunked_response_size;
+        self
+    }
+
     pub fn with_parallel_requests(mut self, parallel_requests: usize) -> Self {
         self.parallel_requests = parallel_requests;
         self
@@ -59,6 +117,132 @@ impl Config {
         self.batch_size = batch_size;
         self
     }
+
+    pub fn with_audit_window(mut self, audit_window: Duration) -> Self {
+        self.audit_window = audit_window;
+        self
+    }
+
+    pub fn with_advertise_min_needed_height(mut self, advertise_min_needed_height: bool) -> Self {
+        self.advertise_min_needed_height = advertise_min_needed_height;
+        self
+    }
+
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_max_in_flight_bytes(mut self, max_in_flight_bytes: Option<usize>) -> Self {
+        self.max_in_flight_bytes = max_in_flight_bytes;
+        self
+    }
+
+    pub fn with_max_inbound_requests(mut self, max_inbound_requests: Option<usize>) -> Self {
+        self.max_inbound_requests = max_inbound_requests;
+        self
+    }
+
+    pub fn with_max_inbound_requests_per_peer(
+        mut self,
+        max_inbound_requests_per_peer: Option<usize>,
+    ) -> Self {
+        self.max_inbound_requests_per_peer = max_inbound_requests_per_peer;
+        self
+    }
+
+    pub fn with_max_served_bytes_per_sec(
+        mut self,
+        max_served_bytes_per_sec: Option<usize>,
+    ) -> Self {
+        self.max_served_bytes_per_sec = max_served_bytes_per_sec;
+        self
+    }
+
+    pub fn with_certificates_only(mut self, certificates_only: bool) -> Self {
+        self.certificates_only = certificates_only;
+        self
+    }
+
+    pub fn with_verification_queue_size(mut self, verification_queue_size: usize) -> Self {
+        self.verification_queue_size = verification_queue_size;
+        self
+    }
+
+    pub fn with_snapshot_sync(mut self, snapshot_sync: bool) -> Self {
+        self.snapshot_sync = snapshot_sync;
+        self
+    }
+
+    pub fn with_snapshot_min_lag(mut self, snapshot_min_lag: u64) -> Self {
+        self.snapshot_min_lag = snapshot_min_lag;
+        self
+    }
+
+    pub fn with_snapshot_min_peers(mut self, snapshot_min_peers: usize) -> Self {
+        self.snapshot_min_peers = snapshot_min_peers;
+        self
+    }
+
+    pub fn with_backfill(mut self, backfill: bool) -> Self {
+        self.backfill = backfill;
+        self
+    }
+
+    pub fn with_proposal_parts_delay(mut self, proposal_parts_delay: Option<Duration>) -> Self {
+        self.proposal_parts_delay = proposal_parts_delay;
+        self
+    }
+
+    pub fn with_vote_sync(mut self, vote_sync: bool) -> Self {
+        self.vote_sync = vote_sync;
+        self
+    }
+
+    /// Apply the parameters changed at runtime, ignoring a batch size of zero
+    pub fn update(&mut self, update: ConfigUpdate) {
+        if let Some(batch_size) = update.batch_size.filter(|size| *size > 0) {
+            self.batch_size = batch_size;
+        }
+
+        if let Some(parallel_requests) = update.parallel_requests {
+            self.parallel_requests = parallel_requests;
+        }
+
+        if let Some(request_timeout) = update.request_timeout {
+            self.request_timeout = request_timeout;
+        }
+    }
+}
+
+/// Parameters of the sync which can be changed on a running node,
+/// eg. to tune how fast it catches up. The parameters left unset are unchanged.
+///
+/// The new values only apply to the requests sent afterwards. The timeout of the
+/// requests at the network layer cannot be changed at runtime, so a larger
+/// `request_timeout` than the one the node started with has no effect.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigUpdate {
+    pub batch_size: Option<usize>,
+    pub parallel_requests: Option<usize>,
+    pub request_timeout: Option<Duration>,
+}
+
+impl ConfigUpdate {
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    pub fn with_parallel_requests(mut self, parallel_requests: usize) -> Self {
+        self.parallel_requests = Some(parallel_requests);
+        self
+    }
+
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
 }
 
 impl Default for Config {
@@ -68,10 +252,26 @@ impl Default for Config {
             request_timeout: Duration::from_secs(10),
             max_request_size: 1024 * 1024,       // 1 MiB
             max_response_size: 10 * 1024 * 1024, // 10 MiB
+            max_chunked_response_size: None,
             parallel_requests: DEFAULT_PARALLEL_REQUESTS,
             scoring_strategy: Strategy::default(),
             inactive_threshold: None,
             batch_size: DEFAULT_BATCH_SIZE,
+            audit_window: DEFAULT_AUDIT_WINDOW,
+            advertise_min_needed_height: false,
+            compression: false,
+            max_in_flight_bytes: None,
+            max_inbound_requests: None,
+            max_inbound_requests_per_peer: None,
+            max_served_bytes_per_sec: None,
+            certificates_only: false,
+            verification_queue_size: 0,
+            snapshot_sync: false,
+            snapshot_min_lag: DEFAULT_SNAPSHOT_MIN_LAG,
+            snapshot_min_peers: DEFAULT_SNAPSHOT_MIN_PEERS,
+            backfill: false,
+            proposal_parts_delay: None,
+            vote_sync: false,
         }
     }
 }
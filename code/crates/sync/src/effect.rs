@@ -1,12 +1,16 @@
-use std::{marker::PhantomData, ops::RangeInclusive};
+use std::marker::PhantomData;
 
 use derive_where::derive_where;
 use thiserror::Error;
 
+use bytes::Bytes;
 use malachitebft_core_types::Context;
 use malachitebft_peer::PeerId;
 
-use crate::{InboundRequestId, OutboundRequestId, ValueRequest, ValueResponse};
+use crate::{
+    InboundRequestId, OutboundRequestId, RawDecidedValue, Snapshot, SnapshotRequest, ValueRequest,
+    ValueResponse,
+};
 
 /// Provides a way to construct the appropriate [`Resume`] value to
 /// resume execution after handling an [`Effect`].
@@ -52,6 +56,7 @@ pub enum Error<Ctx: Context> {
 pub enum Resume<Ctx: Context> {
     Continue(PhantomData<Ctx>),
     ValueRequestId(Option<OutboundRequestId>),
+    SnapshotRequestId(Option<OutboundRequestId>),
 }
 
 impl<Ctx: Context> Default for Resume<Ctx> {
@@ -62,8 +67,9 @@ impl<Ctx: Context> Default for Resume<Ctx> {
 
 #[derive_where(Debug)]
 pub enum Effect<Ctx: Context> {
-    /// Broadcast our status to our direct peers
-    BroadcastStatus(Ctx::Height, resume::Continue),
+    /// Broadcast our status to our direct peers, along with
+    /// the minimum height we still need if we advertise it
+    BroadcastStatus(Ctx::Height, Option<Ctx::Height>, resume::Continue),
 
     /// Send a ValueSync request to a peer
     SendValueRequest(PeerId, ValueRequest<Ctx>, resume::ValueRequestId),
@@ -71,12 +77,8 @@ pub enum Effect<Ctx: Context> {
     /// Send a response to a ValueSync request
     SendValueResponse(InboundRequestId, ValueResponse<Ctx>, resume::Continue),
 
-    /// Retrieve a range of values from the application
-    GetDecidedValues(
-        InboundRequestId,
-        RangeInclusive<Ctx::Height>,
-        resume::Continue,
-    ),
+    /// Retrieve the range of values of a request from the application
+    GetDecidedValues(InboundRequestId, ValueRequest<Ctx>, resume::Continue),
 
     /// Tell consensus to process the sync response
     ProcessValueResponse(
@@ -85,6 +87,23 @@ pub enum Effect<Ctx: Context> {
         ValueResponse<Ctx>,
         resume::Continue,
     ),
+
+    /// Send a request for snapshots or for a chunk of a snapshot to a peer
+    SendSnapshotRequest(PeerId, SnapshotRequest<Ctx>, resume::SnapshotRequestId),
+
+    /// Retrieve the snapshots or the chunk of a snapshot requested by a peer from the application,
+    /// and send them back to the peer
+    GetSnapshotData(InboundRequestId, SnapshotRequest<Ctx>, resume::Continue),
+
+    /// Have the application restore its state from the chunks of a snapshot
+    RestoreSnapshot(Snapshot<Ctx>, Vec<Bytes>, resume::Continue),
+
+    /// Have the application store the values received in response to a backfill request
+    StoreBackfilledValues(
+        OutboundRequestId,
+        Vec<RawDecidedValue<Ctx>>,
+        resume::Continue,
+    ),
 }
 
 pub mod resume {
@@ -112,4 +131,15 @@ pub mod resume {
             Resume::ValueRequestId(value)
         }
     }
+
+    #[derive(Debug, Default)]
+    pub struct SnapshotRequestId;
+
+    impl<Ctx: Context> Resumable<Ctx> for SnapshotRequestId {
+        type Value = Option<OutboundRequestId>;
+
+        fn resume_with(self, value: Self::Value) -> Resume<Ctx> {
+            Resume::SnapshotRequestId(value)
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use std::cmp::{max, min};
-use std::collections::BTreeMap;
 use std::ops::RangeInclusive;
+use std::time::Instant;
 
 use derive_where::derive_where;
 use tracing::{debug, error, info, warn};
@@ -8,11 +8,14 @@ use tracing::{debug, error, info, warn};
 use malachitebft_core_types::utils::height::DisplayRange;
 use malachitebft_core_types::{Context, Height};
 
+use crate::audit::AuditOutcome;
 use crate::co::Co;
 use crate::scoring::SyncResult;
+use crate::snapshot::Fetch;
 use crate::{
-    perform, Effect, Error, HeightStartType, InboundRequestId, Metrics, OutboundRequestId, PeerId,
-    RawDecidedValue, Request, Resume, State, Status, ValueRequest, ValueResponse,
+    perform, Backfill, ConfigUpdate, Effect, Error, HeightStartType, InboundRequestId, Metrics,
+    OutboundRequestId, PeerId, RawDecidedValue, Request, Resume, Snapshot, SnapshotRequest,
+    SnapshotResponse, SnapshotSync, State, Status, TraceId, ValueRequest, ValueResponse,
 };
 
 #[derive_where(Debug)]
@@ -39,7 +42,7 @@ pub enum Input<Ctx: Context> {
     /// Got a response from the application to our `GetDecidedValues` request
     GotDecidedValues(
         InboundRequestId,
-        RangeInclusive<Ctx::Height>,
+        ValueRequest<Ctx>,
         Vec<RawDecidedValue<Ctx>>,
     ),
 
@@ -51,6 +54,32 @@ pub enum Input<Ctx: Context> {
 
     /// An error occurred while processing a value
     ValueProcessingError(PeerId, Ctx::Height),
+
+    /// Stop sending requests for values, while still serving the peers
+    Pause,
+
+    /// Resume sending requests for values
+    Resume,
+
+    /// A request for snapshots or for a chunk of a snapshot has been received from a peer
+    SnapshotRequest(InboundRequestId, PeerId, SnapshotRequest<Ctx>),
+
+    /// A (possibly empty or invalid) response to a snapshot request has been received
+    SnapshotResponse(OutboundRequestId, PeerId, Option<SnapshotResponse<Ctx>>),
+
+    /// The application restored its state from a snapshot, or failed to
+    SnapshotRestored(Snapshot<Ctx>, bool),
+
+    /// Fetch the values in the given range once caught up with the peers,
+    /// eg. the ones below the height the node started from
+    Backfill(RangeInclusive<Ctx::Height>),
+
+    /// The application stored the values received in response to a backfill request,
+    /// or rejected them
+    BackfillStored(OutboundRequestId, bool),
+
+    /// Change some parameters of the sync at runtime
+    UpdateConfig(ConfigUpdate),
 }
 
 pub async fn handle<Ctx>(
@@ -62,7 +91,7 @@ pub async fn handle<Ctx>(
 where
     Ctx: Context,
 {
-    match input {
+    let result = match input {
         Input::SendStatusUpdate => on_send_status_update(co, state, metrics).await,
 
         Input::Status(status) => on_status(co, state, metrics, status).await,
@@ -77,16 +106,31 @@ where
             on_value_request(co, state, metrics, request_id, peer_id, request).await
         }
 
+        Input::ValueResponse(request_id, peer_id, response) if state.is_backfill(&request_id) => {
+            on_backfill_response(co, state, metrics, request_id, peer_id, response).await
+        }
+
         Input::ValueResponse(request_id, peer_id, Some(response)) => {
             on_value_response(co, state, metrics, request_id, peer_id, response).await
         }
 
         Input::ValueResponse(request_id, peer_id, None) => {
+            if let Some((range, _)) = state.pending_requests.get(&request_id) {
+                state.audit.record_response(
+                    peer_id,
+                    TraceId::default(),
+                    range.clone(),
+                    &[],
+                    None,
+                    AuditOutcome::InvalidResponse,
+                );
+            }
+
             on_invalid_value_response(co, state, metrics, request_id, peer_id).await
         }
 
-        Input::GotDecidedValues(request_id, range, values) => {
-            on_got_decided_values(co, state, metrics, request_id, range, values).await
+        Input::GotDecidedValues(request_id, request, values) => {
+            on_got_decided_values(co, state, metrics, request_id, request, values).await
         }
 
         Input::SyncRequestTimedOut(request_id, peer_id, request) => {
@@ -98,7 +142,93 @@ where
         Input::ValueProcessingError(peer, height) => {
             on_value_processing_error(co, state, metrics, peer, height).await
         }
+
+        Input::Pause => on_pause(state).await,
+
+        Input::Resume => on_resume(co, state, metrics).await,
+
+        Input::SnapshotRequest(request_id, peer_id, request) => {
+            on_snapshot_request(co, state, metrics, request_id, peer_id, request).await
+        }
+
+        Input::SnapshotResponse(request_id, peer_id, response) => {
+            on_snapshot_response(co, state, metrics, request_id, peer_id, response).await
+        }
+
+        Input::SnapshotRestored(snapshot, restored) => {
+            on_snapshot_restored(co, state, metrics, snapshot, restored).await
+        }
+
+        Input::Backfill(range) => on_backfill(co, state, metrics, range).await,
+
+        Input::BackfillStored(request_id, stored) => {
+            on_backfill_stored(co, state, metrics, request_id, stored).await
+        }
+
+        Input::UpdateConfig(update) => on_update_config(co, state, metrics, update).await,
+    };
+
+    let (covered, uncovered) = state.coverage();
+    metrics.coverage_updated(covered, uncovered);
+
+    result
+}
+
+pub async fn on_pause<Ctx>(state: &mut State<Ctx>) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    info!(tip_height = %state.tip_height, "Pausing sync");
+
+    state.paused = true;
+
+    Ok(())
+}
+
+pub async fn on_resume<Ctx>(
+    co: Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    info!(tip_height = %state.tip_height, "Resuming sync");
+
+    state.paused = false;
+
+    if state.started {
+        request_values(&co, state, metrics).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn on_update_config<Ctx>(
+    co: Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+    update: ConfigUpdate,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    state.config.update(update);
+
+    info!(
+        batch_size = state.config.batch_size,
+        parallel_requests = state.config.parallel_requests,
+        request_timeout = ?state.config.request_timeout,
+        "Updated sync configuration"
+    );
+
+    // The ranges requested next are sized after the new batch size,
+    // and more requests may be sent in parallel
+    if state.started {
+        request_values(&co, state, metrics).await?;
     }
+
+    Ok(())
 }
 
 async fn on_value_response<Ctx>(
@@ -119,15 +249,50 @@ where
     // Check if the response is valid. A valid response starts at the
     // requested start height, has at least one value, and no more than
     // the requested range.
+    let trace_id = response.trace_id;
+
     let Some((requested_range, stored_peer_id)) = state.pending_requests.get(&request_id) else {
-        warn!(%request_id, %peer_id, "Received response for unknown request ID");
+        warn!(%request_id, %trace_id, %peer_id, "Received response for unknown request ID");
         return Ok(());
     };
 
-    if stored_peer_id != &peer_id {
-        warn!(
-            %request_id, actual_peer = %peer_id, expected_peer = %stored_peer_id,
-            "Received response from different peer than expected"
+    if let Some(retry_after) = response.retry_after.filter(|_| stored_peer_id == &peer_id) {
+        info!(
+            %request_id, %trace_id, %peer_id, ?retry_after,
+            "Peer is serving too many requests, re-requesting values from another peer"
+        );
+
+        state
+            .busy_peers
+            .insert(peer_id, Instant::now() + retry_after);
+
+        return re_request_values_from_peer_except(&co, state, metrics, request_id, Some(peer_id))
+            .await;
+    }
+
+    let trace_id_mismatch = state.is_trace_id_mismatch(&request_id, trace_id);
+
+    if stored_peer_id != &peer_id || trace_id_mismatch {
+        if trace_id_mismatch {
+            warn!(
+                %request_id, %trace_id, %peer_id,
+                "Received response echoing another trace ID than the request's"
+            );
+        } else {
+            warn!(
+                %request_id, %trace_id, actual_peer = %peer_id, expected_peer = %stored_peer_id,
+                "Received response from different peer than expected"
+            );
+        }
+
+        let requested_range = requested_range.clone();
+        state.audit.record_response(
+            peer_id,
+            trace_id,
+            requested_range,
+            &response.values,
+            None,
+            AuditOutcome::InvalidResponse,
         );
 
         return on_invalid_value_response(co, state, metrics, request_id, peer_id).await;
@@ -140,12 +305,22 @@ where
 
     if !is_valid {
         warn!(
-            %request_id, %peer_id,
+            %request_id, %trace_id, %peer_id,
             "Received request for wrong range of heights: expected {}..={} ({} values), got {}..={} ({} values)",
             requested_range.start().as_u64(), requested_range.end().as_u64(), range_len,
             start.as_u64(), end.as_u64(), response.values.len() as u64
         );
 
+        let requested_range = requested_range.clone();
+        state.audit.record_response(
+            peer_id,
+            trace_id,
+            requested_range,
+            &response.values,
+            None,
+            AuditOutcome::InvalidResponse,
+        );
+
         return on_invalid_value_response(co, state, metrics, request_id, peer_id).await;
     }
 
@@ -160,12 +335,23 @@ pub async fn on_send_status_update<Ctx>(
 where
     Ctx: Context,
 {
-    debug!(tip_height = %state.tip_height, "Broadcasting status");
+    if state.node_status().is_starting() {
+        // Our tip height is not known until consensus is done recovering
+        debug!("Node is starting, not broadcasting status");
+    } else {
+        debug!(tip_height = %state.tip_height, "Broadcasting status");
 
-    perform!(
-        co,
-        Effect::BroadcastStatus(state.tip_height, Default::default())
-    );
+        perform!(
+            co,
+            Effect::BroadcastStatus(
+                state.tip_height,
+                state.min_needed_height(),
+                Default::default()
+            )
+        );
+    }
+
+    state.audit.prune();
 
     if let Some(inactive_threshold) = state.config.inactive_threshold {
         // If we are at or above the inactive threshold, we can prune inactive peers.
@@ -201,6 +387,11 @@ where
         return Ok(());
     }
 
+    // Do not let a stalled peer hold on to the ranges requested from it
+    re_request_expired_values(&co, state, metrics).await?;
+
+    discover_snapshots(&co, state, metrics, peer_height).await?;
+
     if peer_height >= state.sync_height {
         info!(
             tip_height = %state.tip_height,
@@ -211,9 +402,11 @@ where
 
         // We are lagging behind on one of our peers at least.
         // Request values from any peer already at or above that peer's height.
-        request_values(co, state, metrics).await?;
+        request_values(&co, state, metrics).await?;
     }
 
+    request_backfill(&co, state, metrics).await?;
+
     Ok(())
 }
 
@@ -229,6 +422,7 @@ where
 {
     debug!(%height, is_restart = %start_type.is_restart(), "Consensus started new height");
 
+    let first_start = !state.started;
     state.started = true;
     state.consensus_height = height;
 
@@ -238,8 +432,9 @@ where
     // Garbage collect fully-validated requests.
     state.prune_pending_requests();
 
-    if start_type.is_restart() {
-        // Consensus is retrying the height, so we should sync starting from it.
+    if start_type.is_restart() || first_start {
+        // Consensus is retrying the height, or starting from the values it has, which may be
+        // behind a restored checkpoint, so we should sync starting from it.
         state.sync_height = height;
         // Clear pending requests, as we are restarting the height.
         state.pending_requests.clear();
@@ -248,8 +443,16 @@ where
         state.sync_height = max(state.sync_height, height);
     }
 
+    // Look for a snapshot to restore from if the peers which sent their status
+    // before consensus started are far ahead
+    if let Some(network_tip_height) = state.network_tip_height() {
+        discover_snapshots(&co, state, metrics, network_tip_height).await?;
+    }
+
     // Trigger potential requests if possible.
-    request_values(co, state, metrics).await?;
+    request_values(&co, state, metrics).await?;
+
+    request_backfill(&co, state, metrics).await?;
 
     Ok(())
 }
@@ -264,6 +467,7 @@ where
 {
     debug!(%height, "Consensus decided on new value");
 
+    state.record_decided(height, Instant::now());
     state.tip_height = height;
 
     // Garbage collect pending requests for heights up to the new tip.
@@ -283,6 +487,7 @@ where
     fields(
         peer_id = %peer_id,
         request_id = %request_id,
+        trace_id = %request.trace_id,
         range = %DisplayRange(&request.range)
     )
 )]
@@ -299,14 +504,61 @@ where
 {
     debug!("Received request for values");
 
-    if !validate_request_range::<Ctx>(&request.range, state.tip_height, state.config.batch_size) {
+    // The values below the retain height are not served, the ones above still are
+    let unpruned_range = state.unpruned_range(&request.range);
+
+    let is_valid = if state.node_status().is_starting() {
+        // Our tip height is not known until consensus is done recovering
+        debug!("Node is starting, not serving values yet");
+        false
+    } else if let Some(range) = &unpruned_range {
+        validate_request_range::<Ctx>(range, state.tip_height, state.config.batch_size)
+    } else {
+        debug!(retain_height = ?state.retain_height, "Requested values have been pruned");
+        false
+    };
+
+    let Some(unpruned_range) = unpruned_range.filter(|_| is_valid) else {
         debug!("Sending empty response to peer");
 
+        state
+            .audit
+            .record_refused(peer_id, request.trace_id, request.range.clone());
+
         perform!(
             co,
             Effect::SendValueResponse(
                 request_id.clone(),
-                ValueResponse::new(*request.range.start(), vec![]),
+                ValueResponse::new(*request.range.start(), vec![]).with_trace_id(request.trace_id),
+                Default::default()
+            )
+        );
+
+        return Ok(());
+    };
+
+    if let Err(retry_after) =
+        state
+            .throttle
+            .try_serve(&state.config, request_id.clone(), peer_id, Instant::now())
+    {
+        debug!(
+            ?retry_after,
+            in_flight = state.throttle.in_flight(),
+            "Too many requests being served, rejecting request"
+        );
+
+        state
+            .audit
+            .record_refused(peer_id, request.trace_id, request.range.clone());
+
+        perform!(
+            co,
+            Effect::SendValueResponse(
+                request_id,
+                ValueResponse::new(*request.range.start(), vec![])
+                    .with_trace_id(request.trace_id)
+                    .with_retry_after(retry_after),
                 Default::default()
             )
         );
@@ -315,20 +567,32 @@ where
     }
 
     metrics.value_request_received(request.range.start().as_u64());
+    state.audit.request_received(request_id.clone(), peer_id);
 
-    let range = clamp_request_range::<Ctx>(&request.range, state.tip_height);
-
-    if range != request.range {
+    if unpruned_range != request.range {
         debug!(
+            retain_height = ?state.retain_height,
             requested = %DisplayRange(&request.range),
+            unpruned = %DisplayRange(&unpruned_range),
+            "Some of the requested values have been pruned, serving the others"
+        );
+    }
+
+    let range = clamp_request_range::<Ctx>(&unpruned_range, state.tip_height);
+
+    if range != unpruned_range {
+        debug!(
+            requested = %DisplayRange(&unpruned_range),
             clamped = %DisplayRange(&range),
             "Clamped request range to our tip height"
         );
     }
 
+    let request = ValueRequest { range, ..request };
+
     perform!(
         co,
-        Effect::GetDecidedValues(request_id, range, Default::default())
+        Effect::GetDecidedValues(request_id, request, Default::default())
     );
 
     Ok(())
@@ -396,9 +660,14 @@ where
     Ctx: Context,
 {
     let start = response.start_height;
-    debug!(start = %start, num_values = %response.values.len(), %peer_id, "Received response from peer");
+    debug!(
+        start = %start, num_values = %response.values.len(), %peer_id, trace_id = %response.trace_id,
+        "Received response from peer"
+    );
 
-    if let Some(response_time) = metrics.value_response_received(start.as_u64()) {
+    let response_time = metrics.value_response_received(start.as_u64(), response.trace_id);
+
+    if let Some(response_time) = response_time {
         state.peer_scorer.update_score_with_metrics(
             peer_id,
             SyncResult::Success(response_time),
@@ -408,6 +677,39 @@ where
 
     let values_count = response.values.len();
 
+    let received_bytes = response
+        .values
+        .iter()
+        .map(|value| value.value_bytes.len())
+        .sum();
+    metrics.peer_response_received(peer_id, received_bytes, response_time, response.trace_id);
+
+    state.record_value_sizes(&response.values);
+
+    // The request has been answered, it is now up to consensus to process the values
+    state.request_deadlines.remove(&request_id);
+
+    if let Some((requested_range, _)) = state.pending_requests.get(&request_id) {
+        let range_len = requested_range.end().as_u64() - requested_range.start().as_u64() + 1;
+        let outcome = if values_count as u64 >= range_len {
+            AuditOutcome::Complete
+        } else if values_count == 0 {
+            AuditOutcome::Empty
+        } else {
+            AuditOutcome::Partial
+        };
+
+        let requested_range = requested_range.clone();
+        state.audit.record_response(
+            peer_id,
+            response.trace_id,
+            requested_range,
+            &response.values,
+            response_time,
+            outcome,
+        );
+    }
+
     // Tell consensus to process the response.
     perform!(
         co,
@@ -443,6 +745,11 @@ where
 
             if values_count == 0 {
                 error!(%request_id, %peer_id, "Received response contains no values");
+
+                // The peer has most likely pruned the values since its last status update,
+                // do not request them from it again
+                let start = *requested_range.start();
+                state.update_pruned_height(peer_id, start);
             } else {
                 // The response of this request only provided `response.values.len()` values,
                 // so update the pending request accordingly
@@ -474,26 +781,29 @@ where
     debug!(%request_id, %peer_id, "Received invalid response");
 
     state.peer_scorer.update_score(peer_id, SyncResult::Failure);
+    metrics.peer_invalid_response(peer_id);
 
     // We do not trust the response, so we remove the pending request and re-request
     // the whole range from another peer.
-    re_request_values_from_peer_except(co, state, metrics, request_id, Some(peer_id)).await?;
+    re_request_values_from_peer_except(&co, state, metrics, request_id, Some(peer_id)).await?;
 
     Ok(())
 }
 
 pub async fn on_got_decided_values<Ctx>(
     co: Co<Ctx>,
-    _state: &mut State<Ctx>,
+    state: &mut State<Ctx>,
     metrics: &Metrics,
     request_id: InboundRequestId,
-    range: RangeInclusive<Ctx::Height>,
+    request: ValueRequest<Ctx>,
     values: Vec<RawDecidedValue<Ctx>>,
 ) -> Result<(), Error<Ctx>>
 where
     Ctx: Context,
 {
-    info!(%request_id, range = %DisplayRange(&range), "Received {} values from host", values.len());
+    let (range, trace_id) = (request.range, request.trace_id);
+
+    info!(%request_id, %trace_id, range = %DisplayRange(&range), "Received {} values from host", values.len());
 
     let start = range.start();
     let end = range.end();
@@ -502,7 +812,7 @@ where
     let batch_size = end.as_u64() - start.as_u64() + 1;
     if batch_size != values.len() as u64 {
         warn!(
-            %request_id,
+            %request_id, %trace_id,
             "Received {} values from host, expected {batch_size}",
             values.len()
         )
@@ -513,7 +823,7 @@ where
     for value in &values {
         if value.certificate.height != height {
             error!(
-                %request_id,
+                %request_id, %trace_id,
                 "Received from host value for height {}, expected for height {height}",
                 value.certificate.height
             );
@@ -521,17 +831,27 @@ where
         height = height.increment();
     }
 
-    debug!(%request_id, range = %DisplayRange(&range), "Sending response to peer");
+    if let Some(peer_id) = state
+        .audit
+        .record_served(&request_id, trace_id, range.clone(), &values)
+    {
+        metrics.peer_range_served(peer_id);
+    }
+
+    let served_bytes = values.iter().map(|value| value.value_bytes.len()).sum();
+    state.throttle.served(&request_id, served_bytes);
+
+    debug!(%request_id, %trace_id, range = %DisplayRange(&range), "Sending response to peer");
     perform!(
         co,
         Effect::SendValueResponse(
             request_id,
-            ValueResponse::new(*start, values),
+            ValueResponse::new(*start, values).with_trace_id(trace_id),
             Default::default()
         )
     );
 
-    metrics.value_response_sent(start.as_u64());
+    metrics.value_response_sent(start.as_u64(), trace_id);
 
     Ok(())
 }
@@ -548,16 +868,59 @@ where
     Ctx: Context,
 {
     match request {
+        Request::ValueRequest(value_request) if state.is_backfill(&request_id) => {
+            info!(
+                %peer_id, trace_id = %value_request.trace_id, range = %DisplayRange(&value_request.range),
+                "Backfill request timed out"
+            );
+
+            state.peer_scorer.update_score(peer_id, SyncResult::Timeout);
+            metrics.peer_request_timed_out(peer_id);
+
+            on_backfill_response(co, state, metrics, request_id, peer_id, None).await?;
+        }
+
         Request::ValueRequest(value_request) => {
-            info!(%peer_id, range = %DisplayRange(&value_request.range), "Sync request timed out");
+            info!(
+                %peer_id, trace_id = %value_request.trace_id, range = %DisplayRange(&value_request.range),
+                "Sync request timed out"
+            );
 
             state.peer_scorer.update_score(peer_id, SyncResult::Timeout);
+            state.audit.record_timeout(
+                peer_id,
+                value_request.trace_id,
+                value_request.range.clone(),
+            );
 
             metrics.value_request_timed_out(value_request.range.start().as_u64());
+            metrics.peer_request_timed_out(peer_id);
 
-            re_request_values_from_peer_except(co, state, metrics, request_id, Some(peer_id))
+            re_request_values_from_peer_except(&co, state, metrics, request_id, Some(peer_id))
                 .await?;
         }
+
+        Request::SnapshotRequest(snapshot_request) => {
+            info!(%peer_id, request = ?snapshot_request, "Snapshot request timed out");
+
+            on_snapshot_response(co, state, metrics, request_id, peer_id, None).await?;
+        }
+
+        // Proposal parts requests are retried by the engine, they do not affect the sync state
+        Request::ProposalPartsRequest(parts_request) => {
+            debug!(
+                %peer_id, height = %parts_request.height, round = %parts_request.round,
+                "Proposal parts request timed out"
+            );
+        }
+
+        // Vote set requests are sent again by consensus while it is stuck in the round
+        Request::VoteSetRequest(vote_set_request) => {
+            debug!(
+                %peer_id, height = %vote_set_request.height, round = %vote_set_request.round,
+                "Vote set request timed out"
+            );
+        }
     };
 
     Ok(())
@@ -577,6 +940,9 @@ where
     error!(%peer_id, %height, "Received invalid value");
 
     state.peer_scorer.update_score(peer_id, SyncResult::Failure);
+    state
+        .audit
+        .flag_value(peer_id, height, AuditOutcome::InvalidValue);
 
     if let Some((request_id, stored_peer_id)) = state.get_request_id_by(height) {
         if stored_peer_id != peer_id {
@@ -585,7 +951,7 @@ where
                 "Received response from different peer than expected"
             );
         }
-        re_request_values_from_peer_except(co, state, metrics, request_id, Some(peer_id)).await?;
+        re_request_values_from_peer_except(&co, state, metrics, request_id, Some(peer_id)).await?;
     } else {
         error!(%peer_id, %height, "Received height of invalid value for unknown request");
     }
@@ -608,8 +974,12 @@ where
     // NOTE: We do not update the peer score here, as this is an internal error
     //       and not a failure from the peer's side.
 
+    state
+        .audit
+        .flag_value(peer_id, height, AuditOutcome::ProcessingError);
+
     if let Some((request_id, _)) = state.get_request_id_by(height) {
-        re_request_values_from_peer_except(co, state, metrics, request_id, None).await?;
+        re_request_values_from_peer_except(&co, state, metrics, request_id, None).await?;
     } else {
         error!(%peer_id, %height, "Received height of invalid value for unknown request");
     }
@@ -619,16 +989,26 @@ where
 
 /// Request multiple batches of values in parallel.
 async fn request_values<Ctx>(
-    co: Co<Ctx>,
+    co: &Co<Ctx>,
     state: &mut State<Ctx>,
     metrics: &Metrics,
 ) -> Result<(), Error<Ctx>>
 where
     Ctx: Context,
 {
+    if state.paused {
+        debug!("Sync is paused, skipping request for values");
+        return Ok(());
+    }
+
+    if state.snapshot.is_active() {
+        debug!("Restoring a snapshot, skipping request for values");
+        return Ok(());
+    }
+
     let max_parallel_requests = state.max_parallel_requests();
 
-    if state.pending_requests.len() >= max_parallel_requests {
+    if state.occupied_request_slots() >= max_parallel_requests {
         info!(
             max_parallel_requests,
             pending_requests = state.pending_requests.len(),
@@ -638,14 +1018,33 @@ where
         return Ok(());
     };
 
-    while state.pending_requests.len() < max_parallel_requests {
+    while state.occupied_request_slots() < max_parallel_requests {
+        // Resume once responses arrive
+        if state.is_in_flight_budget_exceeded() {
+            info!(
+                in_flight_bytes = state.in_flight_bytes(),
+                pending_requests = state.pending_requests.len(),
+                "In-flight byte budget reached, skipping request for values"
+            );
+
+            break;
+        }
+
         // Find the next uncovered range starting from current sync_height
         let initial_height = state.sync_height;
-        let range = find_next_uncovered_range_from::<Ctx>(
-            initial_height,
-            state.config.batch_size as u64,
-            &state.pending_requests,
-        );
+        let range = match state
+            .pending_requests
+            .next_uncovered_range_from(initial_height, state.config.batch_size as u64)
+        {
+            Ok(range) => range,
+            Err(e) => {
+                // The sync height moved into a range requested before, skip over the covered heights
+                warn!(error = %e, "Sync height is covered by a pending request, skipping over it");
+
+                state.sync_height = state.pending_requests.next_uncovered_height(initial_height);
+                continue;
+            }
+        };
 
         // Get a random peer that can provide the values in the range.
         let Some((peer, range)) = state.random_peer_with(&range) else {
@@ -654,7 +1053,7 @@ where
             break;
         };
 
-        send_and_track_request_to_peer(&co, state, metrics, peer, range).await?;
+        send_and_track_request_to_peer(co, state, metrics, peer, range).await?;
     }
 
     Ok(())
@@ -678,7 +1077,7 @@ where
     // from peers and hints to potential reconfiguration.
     let max_parallel_requests = state.max_parallel_requests();
 
-    if state.pending_requests.len() >= max_parallel_requests {
+    if state.occupied_request_slots() >= max_parallel_requests {
         info!(
             %max_parallel_requests,
             pending_requests = %state.pending_requests.len(),
@@ -716,13 +1115,23 @@ where
     };
 
     // Store the pending request
+    state.request_deadlines.insert(
+        request_id.clone(),
+        Instant::now() + state.config.request_timeout,
+    );
     state
         .pending_requests
-        .insert(request_id, (final_range.clone(), peer));
+        .insert(request_id, final_range.clone(), peer);
 
     // Update sync_height to the next uncovered height after this range
     let next_sync_base = final_range.end().increment();
-    state.sync_height = find_next_uncovered_height::<Ctx>(next_sync_base, &state.pending_requests);
+    state.sync_height = state.pending_requests.next_uncovered_height(next_sync_base);
+
+    if cfg!(debug_assertions) {
+        if let Err(e) = state.pending_requests.debug_validate(state.sync_height) {
+            error!(error = %e, "Pending requests are inconsistent");
+        }
+    }
 
     Ok(())
 }
@@ -757,28 +1166,60 @@ where
         return Ok(None);
     }
 
-    info!(range = %DisplayRange(&range), %peer, "Requesting sync from peer");
+    let trace_id = TraceId::random(&mut state.rng);
+
+    info!(range = %DisplayRange(&range), %peer, %trace_id, "Requesting sync from peer");
 
     // Send request to peer
+    let request = ValueRequest::new(range.clone())
+        .with_trace_id(trace_id)
+        .with_certificates_only(state.config.certificates_only);
     let Some(request_id) = perform!(
         co,
-        Effect::SendValueRequest(peer, ValueRequest::new(range.clone()), Default::default()),
+        Effect::SendValueRequest(peer, request, Default::default()),
         Resume::ValueRequestId(id) => id,
     ) else {
-        warn!(range = %DisplayRange(&range), %peer, "Failed to send sync request to peer");
+        warn!(range = %DisplayRange(&range), %peer, %trace_id, "Failed to send sync request to peer");
         return Ok(None);
     };
 
     metrics.value_request_sent(range.start().as_u64());
-    debug!(%request_id, range = %DisplayRange(&range), %peer, "Sent sync request to peer");
+    state.request_trace_ids.insert(request_id.clone(), trace_id);
+    debug!(%request_id, %trace_id, range = %DisplayRange(&range), %peer, "Sent sync request to peer");
 
     Ok(Some((request_id, range)))
 }
 
+/// Re-request from other peers the ranges of the requests which have not been answered
+/// before their deadline, eg. because their timeout was never reported.
+async fn re_request_expired_values<Ctx>(
+    co: &Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    for (request_id, range, peer_id) in state.expired_requests(Instant::now()) {
+        warn!(
+            %request_id, %peer_id, range = %DisplayRange(&range),
+            "Sync request expired before being answered"
+        );
+
+        state.peer_scorer.update_score(peer_id, SyncResult::Timeout);
+        metrics.value_request_timed_out(range.start().as_u64());
+        metrics.peer_request_timed_out(peer_id);
+
+        re_request_values_from_peer_except(co, state, metrics, request_id, Some(peer_id)).await?;
+    }
+
+    Ok(())
+}
+
 /// Remove the pending request and re-request the batch from another peer.
 /// If `except_peer_id` is provided, the request will be re-sent to a different peer than the one that sent the original request.
 async fn re_request_values_from_peer_except<Ctx>(
-    co: Co<Ctx>,
+    co: &Co<Ctx>,
     state: &mut State<Ctx>,
     metrics: &Metrics,
     request_id: OutboundRequestId,
@@ -816,389 +1257,569 @@ where
         return Ok(());
     };
 
-    send_and_track_request_to_peer(&co, state, metrics, peer, peer_range).await?;
+    send_and_track_request_to_peer(co, state, metrics, peer, peer_range).await?;
 
     Ok(())
 }
 
-/// Find the next uncovered range starting from initial_height.
-///
-/// Builds a contiguous range of the specified max_size from initial_height.
-///
-/// # Assumptions
-/// - All ranges in pending_requests are disjoint (non-overlapping)
-/// - initial_height is not covered by any pending request (maintained by caller)
-///
-/// # Panics
-/// Panics if initial_height is already covered by a pending request (indicates a bug in the logic).
-///
-/// Returns the range that should be requested.
-fn find_next_uncovered_range_from<Ctx>(
-    initial_height: Ctx::Height,
-    max_range_size: u64,
-    pending_requests: &BTreeMap<OutboundRequestId, (RangeInclusive<Ctx::Height>, PeerId)>,
-) -> RangeInclusive<Ctx::Height>
+pub async fn on_snapshot_request<Ctx>(
+    co: Co<Ctx>,
+    _state: &mut State<Ctx>,
+    _metrics: &Metrics,
+    request_id: InboundRequestId,
+    peer_id: PeerId,
+    request: SnapshotRequest<Ctx>,
+) -> Result<(), Error<Ctx>>
 where
     Ctx: Context,
 {
-    let max_batch_size = max(1, max_range_size);
+    debug!(%request_id, %peer_id, ?request, "Received snapshot request");
 
-    // Find the pending request with the smallest range.start where range.end >= initial_height
-    let next_range = pending_requests
-        .values()
-        .map(|(range, _)| range)
-        .filter(|range| *range.end() >= initial_height)
-        .min_by_key(|range| range.start());
-
-    // Start with the full max_batch_size range
-    let mut end_height = initial_height.increment_by(max_batch_size - 1);
-
-    // If there's a range in pending, constrain to that boundary
-    if let Some(range) = next_range {
-        // Check if initial_height is covered by this earliest range
-        if range.contains(&initial_height) {
-            panic!(
-                "Bug: initial_height {} is already covered by a pending request. This should never happen.",
-                initial_height.as_u64()
-            );
-        }
-
-        // Constrain to the blocking boundary
-        let boundary_end = range
-            .start()
-            .decrement()
-            .expect("range.start() should be decrementable since it's > initial_height");
-        end_height = min(end_height, boundary_end);
-    }
+    perform!(
+        co,
+        Effect::GetSnapshotData(request_id, request, Default::default())
+    );
 
-    initial_height..=end_height
+    Ok(())
 }
 
-/// Find the next height that's not covered by any pending request starting from starting_height.
-fn find_next_uncovered_height<Ctx>(
-    starting_height: Ctx::Height,
-    pending_requests: &BTreeMap<OutboundRequestId, (RangeInclusive<Ctx::Height>, PeerId)>,
-) -> Ctx::Height
+pub async fn on_snapshot_response<Ctx>(
+    co: Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+    request_id: OutboundRequestId,
+    peer_id: PeerId,
+    response: Option<SnapshotResponse<Ctx>>,
+) -> Result<(), Error<Ctx>>
 where
     Ctx: Context,
 {
-    let mut next_height = starting_height;
-    while let Some((covered_range, _)) = pending_requests
-        .values()
-        .find(|(r, _)| r.contains(&next_height))
-    {
-        next_height = covered_range.end().increment();
-    }
-    next_height
-}
+    match &mut state.snapshot {
+        SnapshotSync::Discovering(discovery) => {
+            if discovery.pending.remove(&request_id).is_none() {
+                debug!(%request_id, %peer_id, "Received response to unknown snapshot request");
+                return Ok(());
+            }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use arc_malachitebft_test::{Height, TestContext};
-    use std::collections::BTreeMap;
+            match response {
+                Some(SnapshotResponse::List(snapshots)) => {
+                    debug!(%peer_id, count = snapshots.len(), "Received snapshots offered by peer");
+                    discovery.add_offers(peer_id, snapshots);
+                }
+                _ => {
+                    warn!(%peer_id, "Failed to get the snapshots offered by peer");
+                }
+            }
 
-    type TestPendingRequests = BTreeMap<OutboundRequestId, (RangeInclusive<Height>, PeerId)>;
+            if discovery.is_complete() {
+                choose_snapshot(&co, state, metrics).await?;
+            }
+        }
 
-    // Test case structures for table-driven tests
+        SnapshotSync::Fetching(fetch) => {
+            let Some((index, _)) = fetch.pending.remove(&request_id) else {
+                debug!(%request_id, %peer_id, "Received response to unknown snapshot request");
+                return Ok(());
+            };
 
-    struct RangeTestCase {
-        name: &'static str,
-        initial_height: u64,
-        max_size: u64,
-        pending_ranges: &'static [(u64, u64)], // (start, end) pairs
-        expected_start: u64,
-        expected_end: u64,
-    }
+            if !response.is_some_and(|response| fetch.add_chunk(index, response)) {
+                warn!(%peer_id, index, "Failed to get snapshot chunk from peer, not requesting chunks from it anymore");
+                fetch.remove_provider(peer_id);
+            }
 
-    struct PanicTestCase {
-        name: &'static str,
-        initial_height: u64,
-        max_size: u64,
-        pending_ranges: &'static [(u64, u64)], // (start, end) pairs
-        expected_panic_msg: &'static str,
-    }
+            if fetch.is_complete() {
+                restore_snapshot(&co, state).await?;
+            } else {
+                request_snapshot_chunks(&co, state, metrics).await?;
 
-    struct HeightTestCase {
-        name: &'static str,
-        initial_height: u64,
-        pending_ranges: &'static [(u64, u64)], // (start, end) pairs
-        expected_height: u64,
+                // No peer was left to fetch the snapshot from
+                if let SnapshotSync::Discovering(_) = state.snapshot {
+                    choose_snapshot(&co, state, metrics).await?;
+                }
+            }
+        }
+
+        _ => {
+            debug!(%request_id, %peer_id, "Received snapshot response while not fetching a snapshot");
+        }
     }
 
-    // Tests for find_next_uncovered_range_from function
+    Ok(())
+}
 
-    #[test]
-    fn test_find_next_uncovered_range_from_table() {
-        let test_cases = [
-            RangeTestCase {
-                name: "no pending requests",
-                initial_height: 10,
-                max_size: 5,
-                pending_ranges: &[],
-                expected_start: 10,
-                expected_end: 14,
-            },
-            RangeTestCase {
-                name: "max size one",
-                initial_height: 10,
-                max_size: 1,
-                pending_ranges: &[],
-                expected_start: 10,
-                expected_end: 10,
-            },
-            RangeTestCase {
-                name: "with blocking request",
-                initial_height: 10,
-                max_size: 5,
-                pending_ranges: &[(12, 15)],
-                expected_start: 10,
-                expected_end: 11,
-            },
-            RangeTestCase {
-                name: "zero max size becomes one",
-                initial_height: 10,
-                max_size: 0, // Should be treated as 1
-                pending_ranges: &[],
-                expected_start: 10,
-                expected_end: 10,
-            },
-            RangeTestCase {
-                name: "range starts immediately after",
-                initial_height: 15,
-                max_size: 5,
-                pending_ranges: &[(16, 20)],
-                expected_start: 15,
-                expected_end: 15, // boundary_end = 16 - 1 = 15, min(19, 15) = 15
-            },
-            RangeTestCase {
-                name: "height zero with range starting at one",
-                initial_height: 0,
-                max_size: 3,
-                pending_ranges: &[(1, 5)],
-                expected_start: 0,
-                expected_end: 0, // boundary_end = 1 - 1 = 0, min(2, 0) = 0
-            },
-            RangeTestCase {
-                name: "sync height just at range end",
-                initial_height: 11,
-                max_size: 4,
-                pending_ranges: &[(5, 10)],
-                expected_start: 11,
-                expected_end: 14, // max_end = 11 + 4 - 1 = 14
-            },
-            RangeTestCase {
-                name: "fill gap between ranges",
-                initial_height: 12,
-                max_size: 6,
-                pending_ranges: &[(5, 10), (20, 25)],
-                expected_start: 12,
-                expected_end: 17, // max_end = 12 + 6 - 1 = 17, boundary_end = 20 - 1 = 19, min(17, 19) = 17
-            },
-        ];
-
-        for case in test_cases {
-            let mut pending_requests = TestPendingRequests::new();
-
-            // Setup pending requests based on test case
-            for (i, &(start, end)) in case.pending_ranges.iter().enumerate() {
-                let peer = PeerId::random();
-                pending_requests.insert(
-                    OutboundRequestId::new(format!("req{}", i + 1)),
-                    (Height::new(start)..=Height::new(end), peer),
-                );
-            }
+pub async fn on_snapshot_restored<Ctx>(
+    co: Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+    snapshot: Snapshot<Ctx>,
+    restored: bool,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    if restored {
+        state.snapshot = SnapshotSync::Done;
 
-            let result = find_next_uncovered_range_from::<TestContext>(
-                Height::new(case.initial_height),
-                case.max_size,
-                &pending_requests,
-            );
+        info!(height = %snapshot.height, "Restored snapshot");
 
-            assert_eq!(
-                result,
-                Height::new(case.expected_start)..=Height::new(case.expected_end),
-                "Test case '{}' failed",
-                case.name
-            );
+        // Consensus is then started at the height following the snapshot
+        state.tip_height = max(state.tip_height, snapshot.height);
+        state.sync_height = max(state.sync_height, snapshot.height.increment());
+        state.prune_pending_requests();
+
+        if state.config.backfill {
+            info!(height = %snapshot.height, "Backfilling the values up to the snapshot once caught up");
+            state.backfill = Some(Backfill::new(Ctx::Height::INITIAL..=snapshot.height));
+        }
+    } else {
+        warn!(height = %snapshot.height, "Application failed to restore snapshot, trying the next one offered");
+
+        state.snapshot.fall_back();
+
+        if let SnapshotSync::Discovering(_) = state.snapshot {
+            choose_snapshot(&co, state, metrics).await?;
+        } else {
+            give_up_snapshot(&co, state, metrics).await?;
         }
     }
 
-    // Panic tests for find_next_uncovered_range_from function
+    Ok(())
+}
 
-    #[test]
-    fn test_find_next_uncovered_range_from_panic_cases() {
-        let test_cases = [
-            PanicTestCase {
-                name: "sync height covered",
-                initial_height: 12,
-                max_size: 3,
-                pending_ranges: &[(10, 15)],
-                expected_panic_msg:
-                    "Bug: initial_height 12 is already covered by a pending request",
-            },
-            PanicTestCase {
-                name: "initial height equals range start",
-                initial_height: 15,
-                max_size: 5,
-                pending_ranges: &[(15, 20)],
-                expected_panic_msg:
-                    "Bug: initial_height 15 is already covered by a pending request",
-            },
-            PanicTestCase {
-                name: "sync height equals range end",
-                initial_height: 15,
-                max_size: 3,
-                pending_ranges: &[(10, 15)],
-                expected_panic_msg:
-                    "Bug: initial_height 15 is already covered by a pending request",
-            },
-            PanicTestCase {
-                name: "multiple consecutive blocks",
-                initial_height: 16,
-                max_size: 3,
-                pending_ranges: &[(10, 15), (16, 20)],
-                expected_panic_msg:
-                    "Bug: initial_height 16 is already covered by a pending request",
-            },
-            PanicTestCase {
-                name: "sync height zero with range starting at zero",
-                initial_height: 0,
-                max_size: 3,
-                pending_ranges: &[(0, 5)],
-                expected_panic_msg: "Bug: initial_height 0 is already covered by a pending request",
-            },
-        ];
-
-        for case in test_cases {
-            let mut pending_requests = TestPendingRequests::new();
-
-            // Setup pending requests based on test case
-            for (i, &(start, end)) in case.pending_ranges.iter().enumerate() {
-                let peer = PeerId::random();
-                pending_requests.insert(
-                    OutboundRequestId::new(format!("req{}", i + 1)),
-                    (Height::new(start)..=Height::new(end), peer),
-                );
-            }
+/// Start looking for a snapshot to restore from, if enabled and not done already,
+/// when a peer is far enough ahead
+async fn discover_snapshots<Ctx>(
+    co: &Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+    peer_height: Ctx::Height,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    if !state.config.snapshot_sync {
+        return Ok(());
+    }
 
-            let result = std::panic::catch_unwind(|| {
-                find_next_uncovered_range_from::<TestContext>(
-                    Height::new(case.initial_height),
-                    case.max_size,
-                    &pending_requests,
-                )
-            });
+    let min_height = state.tip_height.increment_by(state.config.snapshot_min_lag);
 
-            assert!(
-                result.is_err(),
-                "Test case '{}' should have panicked but didn't",
-                case.name
-            );
+    if peer_height < min_height {
+        return Ok(());
+    }
 
-            if let Err(panic_value) = result {
-                if let Some(panic_msg) = panic_value.downcast_ref::<String>() {
-                    assert!(
-                        panic_msg.contains(case.expected_panic_msg),
-                        "Test case '{}' panicked with wrong message. Expected: '{}', Got: '{}'",
-                        case.name,
-                        case.expected_panic_msg,
-                        panic_msg
-                    );
-                } else if let Some(panic_msg) = panic_value.downcast_ref::<&str>() {
-                    assert!(
-                        panic_msg.contains(case.expected_panic_msg),
-                        "Test case '{}' panicked with wrong message. Expected: '{}', Got: '{}'",
-                        case.name,
-                        case.expected_panic_msg,
-                        panic_msg
-                    );
-                }
-            }
+    if let SnapshotSync::Idle = state.snapshot {
+        info!(
+            tip_height = %state.tip_height, %peer_height,
+            "Peers are far ahead, looking for a snapshot to restore from"
+        );
+
+        state.snapshot = SnapshotSync::Discovering(Default::default());
+    }
+
+    // Ask every peer far enough ahead for the snapshots it can serve
+    let peers = state
+        .peers
+        .values()
+        .filter(|status| status.tip_height >= min_height)
+        .map(|status| status.peer_id)
+        .collect::<Vec<_>>();
+
+    for peer_id in peers {
+        let SnapshotSync::Discovering(discovery) = &mut state.snapshot else {
+            return Ok(());
+        };
+
+        if !discovery.asked.insert(peer_id) {
+            continue;
+        }
+
+        let request_id = perform!(
+            co,
+            Effect::SendSnapshotRequest(peer_id, SnapshotRequest::List, Default::default()),
+            Resume::SnapshotRequestId(id) => id,
+        );
+
+        if let (Some(request_id), SnapshotSync::Discovering(discovery)) =
+            (request_id, &mut state.snapshot)
+        {
+            discovery.pending.insert(request_id, peer_id);
+        }
+    }
+
+    // None of the requests could be sent
+    if let SnapshotSync::Discovering(discovery) = &state.snapshot {
+        if discovery.is_complete() {
+            choose_snapshot(co, state, metrics).await?;
         }
     }
 
-    // Tests for find_next_uncovered_height function
+    Ok(())
+}
 
-    #[test]
-    fn test_find_next_uncovered_height_table() {
-        let test_cases = [
-            HeightTestCase {
-                name: "no pending requests",
-                initial_height: 10,
-                pending_ranges: &[],
-                expected_height: 10,
-            },
-            HeightTestCase {
-                name: "starting height covered",
-                initial_height: 12,
-                pending_ranges: &[(10, 15)],
-                expected_height: 16, // Should return the height after the covered range
-            },
-            HeightTestCase {
-                name: "starting height match request start",
-                initial_height: 10,
-                pending_ranges: &[(10, 15)],
-                expected_height: 16, // Should return the height after the covered range
-            },
-            HeightTestCase {
-                name: "starting height match request end",
-                initial_height: 15,
-                pending_ranges: &[(10, 15)],
-                expected_height: 16, // Should return the height after the covered range
-            },
-            HeightTestCase {
-                name: "starting height just before request start",
-                initial_height: 9,
-                pending_ranges: &[(10, 15)],
-                expected_height: 9, // Should return the starting height
-            },
-            HeightTestCase {
-                name: "multiple consecutive ranges",
-                initial_height: 10,
-                pending_ranges: &[(10, 15), (16, 20)],
-                expected_height: 21, // Should skip over all consecutive ranges
-            },
-            HeightTestCase {
-                name: "multiple consecutive ranges with a gap",
-                initial_height: 10,
-                pending_ranges: &[(10, 15), (16, 20), (24, 30)],
-                expected_height: 21, // Should skip over consecutive ranges but stop at gap
-            },
-            HeightTestCase {
-                name: "starting height covered multiple",
-                initial_height: 12,
-                pending_ranges: &[(10, 15), (15, 20)],
-                expected_height: 21, // Should return the height after all covered ranges
-            },
-        ];
-
-        for case in test_cases {
-            let mut pending_requests = TestPendingRequests::new();
-
-            // Setup pending requests based on test case
-            for (i, &(start, end)) in case.pending_ranges.iter().enumerate() {
-                let peer = PeerId::random();
-                pending_requests.insert(
-                    OutboundRequestId::new(format!("req{}", i + 1)),
-                    (Height::new(start)..=Height::new(end), peer),
-                );
+/// Choose the snapshot to restore from among the ones offered by enough peers,
+/// falling back to the next one if none of the peers offering it can serve its chunks,
+/// or sync the values if none is left
+async fn choose_snapshot<Ctx>(
+    co: &Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    let min_height = state.tip_height.increment_by(state.config.snapshot_min_lag);
+
+    loop {
+        let SnapshotSync::Discovering(discovery) = &mut state.snapshot else {
+            return Ok(());
+        };
+
+        let Some((snapshot, providers)) =
+            discovery.take_best_offer(min_height, state.config.snapshot_min_peers)
+        else {
+            info!("No snapshot offered by enough peers, syncing values instead");
+            return give_up_snapshot(co, state, metrics).await;
+        };
+
+        info!(
+            height = %snapshot.height, format = snapshot.format, chunks = snapshot.chunks,
+            providers = providers.len(), "Fetching snapshot"
+        );
+
+        let others = std::mem::take(discovery);
+        state.snapshot = SnapshotSync::Fetching(Fetch::new(snapshot, providers, others));
+        request_snapshot_chunks(co, state, metrics).await?;
+    }
+}
+
+/// Request the missing chunks of the snapshot being fetched from the peers offering it,
+/// going back to the other snapshots offered if none of these peers is left
+async fn request_snapshot_chunks<Ctx>(
+    co: &Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    let max_parallel_requests = state.max_parallel_requests();
+
+    loop {
+        let SnapshotSync::Fetching(fetch) = &mut state.snapshot else {
+            return Ok(());
+        };
+
+        if fetch.providers.is_empty() {
+            warn!(height = %fetch.snapshot.height, "No peer left to fetch the snapshot from, trying the next one offered");
+            state.snapshot.fall_back();
+            return Ok(());
+        }
+
+        if fetch.pending.len() >= max_parallel_requests {
+            return Ok(());
+        }
+
+        let Some(index) = fetch.next_missing_chunk() else {
+            return Ok(());
+        };
+
+        let peer_id = fetch.providers[state.rng.next_u32() as usize % fetch.providers.len()];
+
+        let request = SnapshotRequest::Chunk {
+            height: fetch.snapshot.height,
+            format: fetch.snapshot.format,
+            index,
+        };
+
+        let request_id = perform!(
+            co,
+            Effect::SendSnapshotRequest(peer_id, request, Default::default()),
+            Resume::SnapshotRequestId(id) => id,
+        );
+
+        let SnapshotSync::Fetching(fetch) = &mut state.snapshot else {
+            return Ok(());
+        };
+
+        match request_id {
+            Some(request_id) => {
+                fetch.pending.insert(request_id, (index, peer_id));
             }
+            None => {
+                warn!(%peer_id, index, "Failed to send snapshot chunk request to peer");
+                fetch.remove_provider(peer_id);
+            }
+        }
+    }
+}
 
-            let result = find_next_uncovered_height::<TestContext>(
-                Height::new(case.initial_height),
-                &pending_requests,
-            );
+/// Hand the chunks of the snapshot fetched to the application to restore its state
+async fn restore_snapshot<Ctx>(co: &Co<Ctx>, state: &mut State<Ctx>) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    let SnapshotSync::Fetching(fetch) = std::mem::take(&mut state.snapshot) else {
+        return Ok(());
+    };
 
-            assert_eq!(
-                result,
-                Height::new(case.expected_height),
-                "Test case '{}' failed",
-                case.name
-            );
+    info!(height = %fetch.snapshot.height, "Fetched all the snapshot chunks, restoring snapshot");
+
+    let chunks = fetch.chunks.into_values().collect();
+    state.snapshot = SnapshotSync::Restoring(fetch.snapshot.clone(), fetch.others);
+
+    perform!(
+        co,
+        Effect::RestoreSnapshot(fetch.snapshot, chunks, Default::default())
+    );
+
+    Ok(())
+}
+
+/// Stop looking for a snapshot and sync the values instead
+async fn give_up_snapshot<Ctx>(
+    co: &Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    state.snapshot = SnapshotSync::Done;
+
+    if state.started {
+        request_values(co, state, metrics).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn on_backfill<Ctx>(
+    co: Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+    range: RangeInclusive<Ctx::Height>,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    info!(range = %DisplayRange(&range), "Backfilling values once caught up");
+
+    // Any ongoing backfill is replaced, its pending requests being ignored when answered
+    state.backfill = Some(Backfill::new(range));
+
+    if state.started {
+        request_backfill(&co, state, metrics).await?;
+    }
+
+    Ok(())
+}
+
+async fn on_backfill_response<Ctx>(
+    co: Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+    request_id: OutboundRequestId,
+    peer_id: PeerId,
+    response: Option<ValueResponse<Ctx>>,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    let Some(backfill) = &mut state.backfill else {
+        return Ok(());
+    };
+
+    let Some((range, stored_peer_id)) = backfill.pending.get(&request_id).cloned() else {
+        return Ok(());
+    };
+
+    let trace_id = response.as_ref().map(|response| response.trace_id);
+
+    // A valid response holds a prefix of the requested range, from the peer it was sent to,
+    // and echoes the trace ID of the request
+    let values = response
+        .filter(|response| {
+            stored_peer_id == peer_id
+                && !state.is_trace_id_mismatch(&request_id, response.trace_id)
+                && response.start_height == *range.start()
+                && !response.values.is_empty()
+                && response.values.len() as u64 <= range.end().as_u64() - range.start().as_u64() + 1
+                && response
+                    .values
+                    .iter()
+                    .zip(range.start().as_u64()..)
+                    .all(|(value, height)| value.certificate.height.as_u64() == height)
+        })
+        .map(|response| response.values);
+
+    let Some(values) = values else {
+        warn!(%request_id, %peer_id, range = %DisplayRange(&range), "Received invalid response to backfill request");
+
+        state.peer_scorer.update_score(peer_id, SyncResult::Failure);
+        metrics.peer_invalid_response(peer_id);
+
+        if let Some(backfill) = &mut state.backfill {
+            backfill.pending.remove(&request_id);
+            backfill.give_back(range);
         }
+
+        return request_backfill(&co, state, metrics).await;
+    };
+
+    debug!(%request_id, %peer_id, count = values.len(), "Received backfilled values from peer");
+
+    let trace_id = trace_id.unwrap_or_default();
+    let response_time = metrics.value_response_received(range.start().as_u64(), trace_id);
+
+    let received_bytes = values.iter().map(|value| value.value_bytes.len()).sum();
+    metrics.peer_response_received(peer_id, received_bytes, response_time, trace_id);
+
+    if let Some(response_time) = response_time {
+        state.peer_scorer.update_score_with_metrics(
+            peer_id,
+            SyncResult::Success(response_time),
+            &metrics.scoring,
+        );
+    }
+
+    let Some(backfill) = &mut state.backfill else {
+        return Ok(());
+    };
+
+    // Request the heights missing from a partial response again
+    let end = range.start().increment_by(values.len() as u64 - 1);
+
+    if end < *range.end() {
+        backfill.give_back(end.increment()..=*range.end());
+        backfill
+            .pending
+            .insert(request_id.clone(), (*range.start()..=end, peer_id));
     }
 
+    // The request stays pending until the values are stored by the application
+    perform!(
+        co,
+        Effect::StoreBackfilledValues(request_id, values, Default::default())
+    );
+
+    Ok(())
+}
+
+pub async fn on_backfill_stored<Ctx>(
+    co: Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+    request_id: OutboundRequestId,
+    stored: bool,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    let Some(backfill) = &mut state.backfill else {
+        return Ok(());
+    };
+
+    let Some((range, peer_id)) = backfill.pending.remove(&request_id) else {
+        return Ok(());
+    };
+
+    if stored {
+        debug!(range = %DisplayRange(&range), "Stored backfilled values");
+    } else {
+        warn!(%peer_id, range = %DisplayRange(&range), "Application rejected backfilled values, requesting them from another peer");
+
+        state.peer_scorer.update_score(peer_id, SyncResult::Failure);
+        backfill.give_back(range);
+    }
+
+    request_backfill(&co, state, metrics).await
+}
+
+/// Request the next range of values to backfill, if caught up with the peers
+/// and no other backfill request is pending
+async fn request_backfill<Ctx>(
+    co: &Co<Ctx>,
+    state: &mut State<Ctx>,
+    metrics: &Metrics,
+) -> Result<(), Error<Ctx>>
+where
+    Ctx: Context,
+{
+    let Some(backfill) = &mut state.backfill else {
+        return Ok(());
+    };
+
+    if backfill.is_done() {
+        info!("Backfilled all values");
+        state.backfill = None;
+        return Ok(());
+    }
+
+    if !backfill.pending.is_empty() || !state.is_caught_up() {
+        return Ok(());
+    }
+
+    let batch_size = state.config.batch_size as u64;
+
+    let Some(range) = state
+        .backfill
+        .as_mut()
+        .and_then(|backfill| backfill.next_range(batch_size))
+    else {
+        return Ok(());
+    };
+
+    let Some((peer, peer_range)) = state.random_peer_with(&range) else {
+        debug!(range = %DisplayRange(&range), "No peer to backfill values from");
+
+        if let Some(backfill) = &mut state.backfill {
+            backfill.give_back(range);
+        }
+
+        return Ok(());
+    };
+
+    // Put back the heights the peer cannot provide
+    if let Some(backfill) = &mut state.backfill {
+        backfill.give_back(peer_range.end().increment()..=*range.end());
+    }
+
+    let trace_id = TraceId::random(&mut state.rng);
+
+    info!(range = %DisplayRange(&peer_range), %peer, %trace_id, "Requesting backfill from peer");
+
+    let request = ValueRequest::new(peer_range.clone()).with_trace_id(trace_id);
+
+    let request_id = perform!(
+        co,
+        Effect::SendValueRequest(peer, request, Default::default()),
+        Resume::ValueRequestId(id) => id,
+    );
+
+    let Some(backfill) = &mut state.backfill else {
+        return Ok(());
+    };
+
+    match request_id {
+        Some(request_id) => {
+            metrics.value_request_sent(peer_range.start().as_u64());
+            state.request_trace_ids.insert(request_id.clone(), trace_id);
+            backfill.pending.insert(request_id, (peer_range, peer));
+        }
+        None => {
+            warn!(range = %DisplayRange(&peer_range), %peer, "Failed to send backfill request to peer");
+            backfill.give_back(peer_range);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arc_malachitebft_test::{Height, TestContext};
+
     #[test]
     fn test_validate_request_range() {
         let validate = validate_request_range::<TestContext>;
@@ -1248,4 +1869,666 @@ mod tests {
         let clamped = clamp(&range, tip_height);
         assert_eq!(clamped, tip_height..=tip_height);
     }
+
+    #[test]
+    fn test_in_flight_budget() {
+        let config = crate::Config::default()
+            .with_max_response_size(1000)
+            .with_max_in_flight_bytes(Some(1500));
+
+        let mut state = State::<TestContext>::new(Box::new(rand::rngs::OsRng), config);
+        assert!(!state.is_in_flight_budget_exceeded());
+
+        state.pending_requests.insert(
+            OutboundRequestId::new("req1"),
+            Height::new(1)..=Height::new(5),
+            PeerId::random(),
+        );
+
+        // Without an estimate of the size of the values, the response is assumed to be as large as possible
+        assert_eq!(state.in_flight_bytes(), 1000);
+        assert!(!state.is_in_flight_budget_exceeded());
+
+        state.pending_requests.insert(
+            OutboundRequestId::new("req2"),
+            Height::new(6)..=Height::new(10),
+            PeerId::random(),
+        );
+
+        assert_eq!(state.in_flight_bytes(), 2000);
+        assert!(state.is_in_flight_budget_exceeded());
+
+        state.value_size_estimate = Some(100);
+        assert_eq!(state.in_flight_bytes(), 1000);
+        assert!(!state.is_in_flight_budget_exceeded());
+
+        // A single request is always allowed
+        state.value_size_estimate = Some(1000);
+        state
+            .pending_requests
+            .remove(&OutboundRequestId::new("req2"));
+        assert!(state.is_in_flight_budget_exceeded());
+        state.pending_requests.clear();
+        assert!(!state.is_in_flight_budget_exceeded());
+    }
+
+    #[test]
+    fn test_occupied_request_slots() {
+        let config = crate::Config::default().with_verification_queue_size(1);
+        let mut state = State::<TestContext>::new(Box::new(rand::rngs::OsRng), config);
+
+        for (i, id) in ["req1", "req2", "req3"].into_iter().enumerate() {
+            let start = Height::new(i as u64 * 5 + 1);
+            let end = Height::new(i as u64 * 5 + 5);
+            state.pending_requests.insert(
+                OutboundRequestId::new(id),
+                start..=end,
+                PeerId::random(),
+            );
+            state
+                .request_deadlines
+                .insert(OutboundRequestId::new(id), Instant::now());
+        }
+
+        assert_eq!(state.occupied_request_slots(), 3);
+
+        // The first answered request waits for verification without holding a slot
+        state
+            .request_deadlines
+            .remove(&OutboundRequestId::new("req1"));
+        assert_eq!(state.occupied_request_slots(), 2);
+
+        state
+            .request_deadlines
+            .remove(&OutboundRequestId::new("req2"));
+        assert_eq!(state.occupied_request_slots(), 2);
+    }
+
+    #[test]
+    fn test_expired_requests() {
+        use std::time::Duration;
+
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let now = Instant::now();
+        let peer = PeerId::random();
+
+        for (i, deadline) in [now, now + Duration::from_secs(10)].into_iter().enumerate() {
+            let request_id = OutboundRequestId::new(format!("req{}", i + 1));
+            let start = Height::new(i as u64 * 5 + 1);
+            let range = start..=Height::new(i as u64 * 5 + 5);
+
+            state
+                .pending_requests
+                .insert(request_id.clone(), range, peer);
+            state.request_deadlines.insert(request_id, deadline);
+        }
+
+        assert_eq!(
+            state.expired_requests(now),
+            vec![(
+                OutboundRequestId::new("req1"),
+                Height::new(1)..=Height::new(5),
+                peer
+            )]
+        );
+
+        // The deadlines of the requests which are not pending anymore are dropped
+        state
+            .pending_requests
+            .remove(&OutboundRequestId::new("req1"));
+        assert_eq!(
+            state.expired_requests(now + Duration::from_secs(10)).len(),
+            1
+        );
+        assert_eq!(state.request_deadlines.len(), 1);
+    }
+
+    #[test]
+    fn test_skip_pruned_peer() {
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let peer = PeerId::random();
+        state.update_status(Status {
+            peer_id: peer,
+            tip_height: Height::new(20),
+            history_min_height: Height::new(1),
+            min_needed_height: None,
+        });
+
+        let range = Height::new(5)..=Height::new(10);
+        assert_eq!(state.random_peer_with(&range), Some((peer, range.clone())));
+
+        state.update_pruned_height(peer, Height::new(5));
+        assert_eq!(state.random_peer_with(&range), None);
+
+        // The peer can still provide the values it retains
+        let range = Height::new(6)..=Height::new(10);
+        assert_eq!(state.random_peer_with(&range), Some((peer, range.clone())));
+    }
+
+    #[test]
+    fn test_skip_busy_peer() {
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let peer = PeerId::random();
+        state.update_status(Status {
+            peer_id: peer,
+            tip_height: Height::new(20),
+            history_min_height: Height::new(1),
+            min_needed_height: None,
+        });
+
+        let range = Height::new(5)..=Height::new(10);
+
+        state
+            .busy_peers
+            .insert(peer, Instant::now() + std::time::Duration::from_secs(60));
+        assert_eq!(state.random_peer_with(&range), None);
+
+        state.busy_peers.insert(peer, Instant::now());
+        assert_eq!(state.random_peer_with(&range), Some((peer, range.clone())));
+        assert!(state.busy_peers.is_empty());
+    }
+
+    #[test]
+    fn test_restore_checkpoint() {
+        let config = crate::Config::default();
+        let mut state = State::<TestContext>::new(Box::new(rand::rngs::OsRng), config);
+
+        let peer = PeerId::random();
+        state.peer_scorer.set_score(peer, 0.9);
+        state.tip_height = Height::new(10);
+
+        let checkpoint = state.checkpoint();
+        assert_eq!(checkpoint.tip_height, 10);
+        assert_eq!(checkpoint.peer_scores, vec![(peer, 0.9)]);
+
+        let mut restored = State::<TestContext>::new(Box::new(rand::rngs::OsRng), config);
+        restored.restore(&checkpoint);
+
+        assert_eq!(restored.tip_height, Height::new(10));
+        assert_eq!(restored.sync_height, Height::new(11));
+        assert_eq!(restored.peer_scorer.get_score(&peer), 0.9);
+    }
+
+    #[test]
+    fn test_coverage() {
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let (a, b) = (PeerId::random(), PeerId::random());
+        state.tip_height = Height::new(2);
+
+        // No peer to sync from
+        assert!(state.uncovered_ranges().is_empty());
+
+        state.update_status(Status {
+            peer_id: a,
+            tip_height: Height::new(30),
+            history_min_height: Height::new(1),
+            min_needed_height: None,
+        });
+
+        for (id, start, end, peer) in [
+            ("req1", 1, 5, a),
+            ("req2", 6, 8, b),
+            ("req3", 12, 15, a),
+            ("req4", 14, 20, b),
+        ] {
+            state.pending_requests.insert(
+                OutboundRequestId::new(id),
+                Height::new(start)..=Height::new(end),
+                peer,
+            );
+        }
+
+        assert_eq!(
+            state.covered_ranges(),
+            vec![
+                Height::new(3)..=Height::new(8),
+                Height::new(12)..=Height::new(20)
+            ]
+        );
+        assert_eq!(
+            state.uncovered_ranges(),
+            vec![
+                Height::new(9)..=Height::new(11),
+                Height::new(21)..=Height::new(30)
+            ]
+        );
+        assert_eq!(state.coverage(), (15, 13));
+
+        let outstanding = state.outstanding_ranges_by_peer();
+        assert_eq!(
+            outstanding[&a],
+            vec![
+                Height::new(3)..=Height::new(5),
+                Height::new(12)..=Height::new(15)
+            ]
+        );
+        assert_eq!(
+            outstanding[&b],
+            vec![
+                Height::new(6)..=Height::new(8),
+                Height::new(14)..=Height::new(20)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_estimated_time_to_tip() {
+        use std::time::Duration;
+
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        // No peer to sync from
+        assert_eq!(state.estimated_time_to_tip(), None);
+
+        state.update_status(Status {
+            peer_id: PeerId::random(),
+            tip_height: Height::new(30),
+            history_min_height: Height::new(1),
+            min_needed_height: None,
+        });
+
+        let now = Instant::now();
+        state.record_decided(Height::new(1), now);
+        state.tip_height = Height::new(1);
+
+        // The decide rate is not known after a single decision
+        assert_eq!(state.estimated_time_to_tip(), None);
+
+        state.record_decided(Height::new(11), now + Duration::from_secs(1));
+        state.tip_height = Height::new(11);
+
+        // 19 heights left at 10 heights per second
+        assert_eq!(
+            state.estimated_time_to_tip(),
+            Some(Duration::from_secs_f64(1.9))
+        );
+
+        state.tip_height = Height::new(30);
+        assert_eq!(state.estimated_time_to_tip(), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_retain_height() {
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let range = Height::new(5)..=Height::new(10);
+        assert_eq!(state.unpruned_range(&range), Some(range.clone()));
+
+        assert!(state.set_retain_height(Height::new(6)));
+        assert_eq!(
+            state.unpruned_range(&range),
+            Some(Height::new(6)..=Height::new(10))
+        );
+        assert_eq!(
+            state.unpruned_range(&(Height::new(6)..=Height::new(10))),
+            Some(Height::new(6)..=Height::new(10))
+        );
+        assert_eq!(
+            state.unpruned_range(&(Height::new(2)..=Height::new(5))),
+            None
+        );
+
+        // The retain height never goes down
+        assert!(!state.set_retain_height(Height::new(3)));
+        assert_eq!(state.retain_height, Some(Height::new(6)));
+    }
+
+    #[test]
+    fn test_update_config() {
+        use std::time::Duration;
+
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let update = ConfigUpdate::default()
+            .with_batch_size(20)
+            .with_parallel_requests(2)
+            .with_request_timeout(Duration::from_secs(3));
+
+        state.config.update(update);
+
+        assert_eq!(state.config.batch_size, 20);
+        assert_eq!(state.max_parallel_requests(), 2);
+        assert_eq!(state.config.request_timeout, Duration::from_secs(3));
+
+        // The next range to request is sized after the new batch size
+        let range = state
+            .pending_requests
+            .next_uncovered_range_from(Height::new(1), state.config.batch_size as u64);
+        assert_eq!(range, Ok(Height::new(1)..=Height::new(20)));
+
+        // A batch size of zero is ignored, and the parameters left unset are unchanged
+        state
+            .config
+            .update(ConfigUpdate::default().with_batch_size(0));
+
+        assert_eq!(state.config.batch_size, 20);
+        assert_eq!(state.max_parallel_requests(), 2);
+    }
+
+    /// Process the input, returning the effects emitted meanwhile
+    fn emitted_effects(
+        state: &mut State<TestContext>,
+        input: Input<TestContext>,
+    ) -> Vec<Effect<TestContext>> {
+        let metrics = Metrics::new(std::time::Duration::from_secs(10));
+        emitted_effects_with(state, &metrics, input)
+    }
+
+    /// Process the input, recording the metrics, and return the effects emitted meanwhile
+    fn emitted_effects_with(
+        state: &mut State<TestContext>,
+        metrics: &Metrics,
+        input: Input<TestContext>,
+    ) -> Vec<Effect<TestContext>> {
+        fn run(
+            state: &mut State<TestContext>,
+            metrics: &Metrics,
+            input: Input<TestContext>,
+            effects: &mut Vec<Effect<TestContext>>,
+        ) -> Result<(), Error<TestContext>> {
+            crate::process!(
+                input: input,
+                state: state,
+                metrics: metrics,
+                with: effect => {
+                    let resume = match &effect {
+                        Effect::SendValueRequest(..) => {
+                            Resume::ValueRequestId(Some(OutboundRequestId::new(effects.len())))
+                        }
+                        _ => Resume::default(),
+                    };
+
+                    effects.push(effect);
+                    Ok::<_, Error<TestContext>>(resume)
+                }
+            )
+        }
+
+        let mut effects = Vec::new();
+        run(state, metrics, input, &mut effects).unwrap();
+        effects
+    }
+
+    #[test]
+    fn test_nothing_advertised_nor_served_while_starting() {
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let value_request = || {
+            Input::ValueRequest(
+                InboundRequestId::new("req"),
+                PeerId::random(),
+                ValueRequest::new(Height::new(1)..=Height::new(3)),
+            )
+        };
+
+        assert!(state.node_status().is_starting());
+        assert!(emitted_effects(&mut state, Input::SendStatusUpdate).is_empty());
+        assert!(matches!(
+            emitted_effects(&mut state, value_request()).as_slice(),
+            [Effect::SendValueResponse(_, response, _)] if response.values.is_empty()
+        ));
+
+        emitted_effects(
+            &mut state,
+            Input::StartedHeight(Height::new(5), HeightStartType::Start),
+        );
+
+        assert_eq!(state.node_status(), crate::NodeStatus::Running);
+        assert!(matches!(
+            emitted_effects(&mut state, Input::SendStatusUpdate).as_slice(),
+            [Effect::BroadcastStatus(..)]
+        ));
+        assert!(matches!(
+            emitted_effects(&mut state, value_request()).as_slice(),
+            [Effect::GetDecidedValues(..)]
+        ));
+    }
+
+    #[test]
+    fn test_only_unpruned_values_served() {
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        emitted_effects(
+            &mut state,
+            Input::StartedHeight(Height::new(5), HeightStartType::Start),
+        );
+        state.set_retain_height(Height::new(3));
+
+        let value_request = |range| {
+            Input::ValueRequest(
+                InboundRequestId::new("req"),
+                PeerId::random(),
+                ValueRequest::new(range),
+            )
+        };
+
+        // The values below the retain height are skipped, the others are served
+        assert!(matches!(
+            emitted_effects(&mut state, value_request(Height::new(1)..=Height::new(4))).as_slice(),
+            [Effect::GetDecidedValues(_, request, _)]
+                if request.range == (Height::new(3)..=Height::new(4))
+        ));
+
+        // None of the values are served if all of them have been pruned
+        assert!(matches!(
+            emitted_effects(&mut state, value_request(Height::new(1)..=Height::new(2))).as_slice(),
+            [Effect::SendValueResponse(_, response, _)] if response.values.is_empty()
+        ));
+    }
+
+    #[test]
+    fn test_request_certificates_only() {
+        for certificates_only in [false, true] {
+            let config = crate::Config::default().with_certificates_only(certificates_only);
+            let mut state = State::<TestContext>::new(Box::new(rand::rngs::OsRng), config);
+
+            let peer = PeerId::random();
+            state.update_status(Status {
+                peer_id: peer,
+                tip_height: Height::new(20),
+                history_min_height: Height::new(1),
+                min_needed_height: None,
+            });
+
+            let effects = emitted_effects(
+                &mut state,
+                Input::StartedHeight(Height::new(1), HeightStartType::Start),
+            );
+
+            let requests: Vec<_> = effects
+                .iter()
+                .filter_map(|effect| match effect {
+                    Effect::SendValueRequest(peer_id, request, _) => Some((*peer_id, request)),
+                    _ => None,
+                })
+                .collect();
+
+            assert!(!requests.is_empty());
+            assert!(requests.iter().all(|(peer_id, request)| {
+                *peer_id == peer && request.certificates_only == certificates_only
+            }));
+        }
+    }
+
+    #[test]
+    fn test_response_echoing_another_trace_id_is_dropped() {
+        use crate::scoring::metrics::PeerLabel;
+        use arc_malachitebft_test::ValueId;
+        use malachitebft_core_types::{CommitCertificate, Round};
+
+        let metrics = Metrics::new(std::time::Duration::from_secs(10));
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let peer = PeerId::random();
+        state.update_status(Status {
+            peer_id: peer,
+            tip_height: Height::new(20),
+            history_min_height: Height::new(1),
+            min_needed_height: None,
+        });
+
+        // Consensus (re)starts the height, which clears the pending requests
+        let send_request = |state: &mut State<TestContext>| {
+            let effects = emitted_effects_with(
+                state,
+                &metrics,
+                Input::StartedHeight(Height::new(1), HeightStartType::Restart),
+            );
+
+            effects
+                .into_iter()
+                .enumerate()
+                .find_map(|(i, e)| match e {
+                    Effect::SendValueRequest(_, request, _) => {
+                        Some((OutboundRequestId::new(i), request))
+                    }
+                    _ => None,
+                })
+                .expect("No value request sent")
+        };
+
+        let response_to = |request: &ValueRequest<TestContext>, trace_id| {
+            let values = (request.range.start().as_u64()..=request.range.end().as_u64())
+                .map(|height| {
+                    let certificate = CommitCertificate::new(
+                        Height::new(height),
+                        Round::new(0),
+                        ValueId::new(height),
+                        vec![],
+                    );
+                    RawDecidedValue::new(Default::default(), certificate)
+                })
+                .collect();
+
+            ValueResponse::new(*request.range.start(), values).with_trace_id(trace_id)
+        };
+
+        let (request_id, request) = send_request(&mut state);
+        assert_eq!(
+            state.request_trace_ids.get(&request_id),
+            Some(&request.trace_id)
+        );
+
+        // The response echoes another trace ID, it is dropped and the range is requested again
+        let trace_id = TraceId(request.trace_id.0.wrapping_add(1));
+        let effects = emitted_effects_with(
+            &mut state,
+            &metrics,
+            Input::ValueResponse(
+                request_id.clone(),
+                peer,
+                Some(response_to(&request, trace_id)),
+            ),
+        );
+
+        assert!(!effects
+            .iter()
+            .any(|effect| matches!(effect, Effect::ProcessValueResponse(..))));
+        assert_eq!(
+            metrics
+                .peer_invalid_responses
+                .get_or_create(&PeerLabel::new(peer))
+                .get(),
+            1
+        );
+
+        // The response echoes the trace ID of the request, it is processed
+        let (request_id, request) = send_request(&mut state);
+        let effects = emitted_effects_with(
+            &mut state,
+            &metrics,
+            Input::ValueResponse(
+                request_id,
+                peer,
+                Some(response_to(&request, request.trace_id)),
+            ),
+        );
+
+        assert!(effects
+            .iter()
+            .any(|effect| matches!(effect, Effect::ProcessValueResponse(..))));
+    }
+
+    #[test]
+    fn test_per_peer_metrics() {
+        use crate::scoring::metrics::PeerLabel;
+
+        let metrics = Metrics::new(std::time::Duration::from_secs(10));
+        let mut state =
+            State::<TestContext>::new(Box::new(rand::rngs::OsRng), crate::Config::default());
+
+        let peer = PeerId::random();
+        state.update_status(Status {
+            peer_id: peer,
+            tip_height: Height::new(20),
+            history_min_height: Height::new(1),
+            min_needed_height: None,
+        });
+
+        let effects = emitted_effects_with(
+            &mut state,
+            &metrics,
+            Input::StartedHeight(Height::new(6), HeightStartType::Start),
+        );
+
+        let Some((request_id, request)) =
+            effects.into_iter().enumerate().find_map(|(i, e)| match e {
+                Effect::SendValueRequest(_, request, _) => {
+                    Some((OutboundRequestId::new(i), request))
+                }
+                _ => None,
+            })
+        else {
+            panic!("No value request sent");
+        };
+
+        // The request sent to the peer times out
+        emitted_effects_with(
+            &mut state,
+            &metrics,
+            Input::SyncRequestTimedOut(request_id, peer, Request::ValueRequest(request)),
+        );
+
+        let label = PeerLabel::new(peer);
+        assert_eq!(metrics.peer_request_timeouts.get_or_create(&label).get(), 1);
+        assert_eq!(metrics.peer_ranges_served.get_or_create(&label).get(), 0);
+
+        // The peer requests values from us, which are served
+        let request = ValueRequest::new(Height::new(1)..=Height::new(3));
+        let request_id = InboundRequestId::new("req");
+
+        emitted_effects_with(
+            &mut state,
+            &metrics,
+            Input::ValueRequest(request_id.clone(), peer, request.clone()),
+        );
+        emitted_effects_with(
+            &mut state,
+            &metrics,
+            Input::GotDecidedValues(request_id, request, vec![]),
+        );
+
+        assert_eq!(metrics.peer_ranges_served.get_or_create(&label).get(), 1);
+        assert_eq!(
+            metrics
+                .peer_ranges_served
+                .get_or_create(&PeerLabel::new(PeerId::random()))
+                .get(),
+            0
+        );
+    }
 }
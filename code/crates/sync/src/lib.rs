@@ -12,12 +12,30 @@ pub use types::*;
 
 pub mod scoring;
 
+pub mod audit;
+pub use audit::RequestAudit;
+
+pub mod throttle;
+pub use throttle::ServeThrottle;
+
+pub mod checkpoint;
+pub use checkpoint::Checkpoint;
+
+pub mod snapshot;
+pub use snapshot::SnapshotSync;
+
+pub mod backfill;
+pub use backfill::Backfill;
+
+pub mod pending;
+pub use pending::{PendingRequests, PendingRequestsError};
+
 mod macros;
 mod rpc;
 mod ser;
 
 pub mod config;
-pub use config::Config;
+pub use config::{Config, ConfigUpdate};
 
 #[doc(hidden)]
 pub mod handle;
@@ -3,10 +3,35 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
+use malachitebft_metrics::prometheus::encoding::EncodeLabelSet;
 use malachitebft_metrics::prometheus::metrics::counter::Counter;
+use malachitebft_metrics::prometheus::metrics::exemplar::HistogramWithExemplars;
+use malachitebft_metrics::prometheus::metrics::family::Family;
 use malachitebft_metrics::prometheus::metrics::gauge::Gauge;
 use malachitebft_metrics::prometheus::metrics::histogram::{exponential_buckets, Histogram};
 use malachitebft_metrics::SharedRegistry;
+use malachitebft_peer::PeerId;
+
+use malachitebft_metrics::prometheus as prometheus_client;
+
+use crate::scoring::metrics::PeerLabel;
+use crate::TraceId;
+
+/// Exemplar of the latency histograms, linking an observation
+/// to the log lines of the request on both peers
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct TraceLabel {
+    trace_id: String,
+}
+
+impl TraceLabel {
+    /// No exemplar is recorded for the requests without a trace ID
+    fn new(trace_id: TraceId) -> Option<Self> {
+        (trace_id != TraceId::default()).then(|| Self {
+            trace_id: trace_id.to_string(),
+        })
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Metrics(Arc<Inner>);
@@ -25,8 +50,8 @@ pub struct Inner {
     value_requests_received: Counter,
     value_responses_sent: Counter,
     value_responses_received: Counter,
-    value_client_latency: Histogram,
-    value_server_latency: Histogram,
+    value_client_latency: HistogramWithExemplars<TraceLabel>,
+    value_server_latency: HistogramWithExemplars<TraceLabel>,
     value_request_timeouts: Counter,
     status_interarrival: Histogram,
     status_interarrival_normalized: Histogram, // Independent of number of peers and status update interval
@@ -44,6 +69,27 @@ pub struct Inner {
 
     /// Number of inputs in the sync input queue across all heights
     pub sync_queue_size: Gauge,
+
+    /// Number of heights above the tip requested from the peers
+    pub covered_heights: Gauge,
+
+    /// Number of heights between the tip and the highest tip of the peers not requested from any peer
+    pub uncovered_heights: Gauge,
+
+    /// Number of bytes of values received from each peer
+    pub peer_bytes_received: Family<PeerLabel, Counter>,
+
+    /// Interval of time between when a request was sent to each peer and its response was received
+    pub peer_request_latency: Family<PeerLabel, HistogramWithExemplars<TraceLabel>>,
+
+    /// Number of requests sent to each peer which timed out
+    pub peer_request_timeouts: Family<PeerLabel, Counter>,
+
+    /// Number of invalid responses received from each peer
+    pub peer_invalid_responses: Family<PeerLabel, Counter>,
+
+    /// Number of ranges of values served to each peer
+    pub peer_ranges_served: Family<PeerLabel, Counter>,
 }
 
 impl Inner {
@@ -54,8 +100,8 @@ impl Inner {
             value_requests_received: Counter::default(),
             value_responses_sent: Counter::default(),
             value_responses_received: Counter::default(),
-            value_client_latency: Histogram::new(exponential_buckets(0.1, 2.0, 20)),
-            value_server_latency: Histogram::new(exponential_buckets(0.1, 2.0, 20)),
+            value_client_latency: HistogramWithExemplars::new(exponential_buckets(0.1, 2.0, 20)),
+            value_server_latency: HistogramWithExemplars::new(exponential_buckets(0.1, 2.0, 20)),
             value_request_timeouts: Counter::default(),
             status_interarrival: Histogram::new(exponential_buckets(0.05 * t.max(1e-6), 1.15, 40)),
             status_interarrival_normalized: Histogram::new(exponential_buckets(0.05, 1.15, 40)),
@@ -67,6 +113,15 @@ impl Inner {
             scoring: crate::scoring::metrics::Metrics::new(),
             sync_queue_heights: Gauge::default(),
             sync_queue_size: Gauge::default(),
+            covered_heights: Gauge::default(),
+            uncovered_heights: Gauge::default(),
+            peer_bytes_received: Family::default(),
+            peer_request_latency: Family::new_with_constructor(|| {
+                HistogramWithExemplars::new(exponential_buckets(0.1, 2.0, 20))
+            }),
+            peer_request_timeouts: Family::default(),
+            peer_invalid_responses: Family::default(),
+            peer_ranges_served: Family::default(),
         }
     }
 }
@@ -137,6 +192,48 @@ impl Metrics {
                 metrics.sync_queue_size.clone(),
             );
 
+            registry.register(
+                "covered_heights",
+                "Number of heights above the tip requested from the peers",
+                metrics.covered_heights.clone(),
+            );
+
+            registry.register(
+                "uncovered_heights",
+                "Number of heights between the tip and the highest tip of the peers not requested from any peer",
+                metrics.uncovered_heights.clone(),
+            );
+
+            registry.register(
+                "peer_bytes_received",
+                "Number of bytes of values received from each peer",
+                metrics.peer_bytes_received.clone(),
+            );
+
+            registry.register(
+                "peer_request_latency",
+                "Interval of time between when a request was sent to each peer and its response was received",
+                metrics.peer_request_latency.clone(),
+            );
+
+            registry.register(
+                "peer_request_timeouts",
+                "Number of requests sent to each peer which timed out",
+                metrics.peer_request_timeouts.clone(),
+            );
+
+            registry.register(
+                "peer_invalid_responses",
+                "Number of invalid responses received from each peer",
+                metrics.peer_invalid_responses.clone(),
+            );
+
+            registry.register(
+                "peer_ranges_served",
+                "Number of ranges of values served to each peer",
+                metrics.peer_ranges_served.clone(),
+            );
+
             registry.register(
                 "status_interarrival",
                 "Status updates interarrival histogram (any peer)",
@@ -168,21 +265,22 @@ impl Metrics {
         self.instant_request_received.insert(height, Instant::now());
     }
 
-    pub fn value_response_sent(&self, height: u64) {
+    pub fn value_response_sent(&self, height: u64, trace_id: TraceId) {
         self.value_responses_sent.inc();
 
         if let Some((_, instant)) = self.instant_request_received.remove(&height) {
             self.value_server_latency
-                .observe(instant.elapsed().as_secs_f64());
+                .observe(instant.elapsed().as_secs_f64(), TraceLabel::new(trace_id));
         }
     }
 
-    pub fn value_response_received(&self, height: u64) -> Option<Duration> {
+    pub fn value_response_received(&self, height: u64, trace_id: TraceId) -> Option<Duration> {
         self.value_responses_received.inc();
 
         if let Some((_, instant_request_sent)) = self.instant_request_sent.remove(&height) {
             let latency = instant_request_sent.elapsed();
-            self.value_client_latency.observe(latency.as_secs_f64());
+            self.value_client_latency
+                .observe(latency.as_secs_f64(), TraceLabel::new(trace_id));
             Some(latency)
         } else {
             None
@@ -194,6 +292,44 @@ impl Metrics {
         self.instant_request_sent.remove(&height);
     }
 
+    pub fn peer_response_received(
+        &self,
+        peer_id: PeerId,
+        bytes: usize,
+        latency: Option<Duration>,
+        trace_id: TraceId,
+    ) {
+        let label = PeerLabel::new(peer_id);
+
+        self.peer_bytes_received
+            .get_or_create(&label)
+            .inc_by(bytes as u64);
+
+        if let Some(latency) = latency {
+            self.peer_request_latency
+                .get_or_create(&label)
+                .observe(latency.as_secs_f64(), TraceLabel::new(trace_id));
+        }
+    }
+
+    pub fn peer_request_timed_out(&self, peer_id: PeerId) {
+        self.peer_request_timeouts
+            .get_or_create(&PeerLabel::new(peer_id))
+            .inc();
+    }
+
+    pub fn peer_invalid_response(&self, peer_id: PeerId) {
+        self.peer_invalid_responses
+            .get_or_create(&PeerLabel::new(peer_id))
+            .inc();
+    }
+
+    pub fn peer_range_served(&self, peer_id: PeerId) {
+        self.peer_ranges_served
+            .get_or_create(&PeerLabel::new(peer_id))
+            .inc();
+    }
+
     pub fn status_received(&self, n_peers: u64) {
         self.status_total.inc();
         let now = Instant::now();
@@ -219,6 +355,11 @@ impl Metrics {
         self.sync_queue_heights.set(heights as _);
         self.sync_queue_size.set(size as _);
     }
+
+    pub fn coverage_updated(&self, covered: u64, uncovered: u64) {
+        self.covered_heights.set(covered as _);
+        self.uncovered_heights.set(uncovered as _);
+    }
 }
 
 impl Default for Metrics {
@@ -0,0 +1,674 @@
+//! Value requests sent to peers and not answered yet, along with the range of heights
+//! each of them covers.
+//!
+//! The ranges of the pending requests are kept disjoint, so that a height is never requested
+//! from two peers at once, and the next range to request starts at the first height not covered
+//! by any of them.
+
+use std::cmp::min;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use derive_where::derive_where;
+use thiserror::Error;
+
+use malachitebft_core_types::{Context, Height};
+use malachitebft_peer::PeerId;
+
+use crate::OutboundRequestId;
+
+/// Broken invariant of the pending requests
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+#[derive(Error)]
+pub enum PendingRequestsError<Ctx: Context> {
+    /// A pending request covers no height at all
+    #[error("Pending request {request_id} has an empty range of heights")]
+    EmptyRange { request_id: OutboundRequestId },
+
+    /// Two pending requests cover the same height
+    #[error("Pending requests {first} and {second} both cover height {height}")]
+    Overlap {
+        first: OutboundRequestId,
+        second: OutboundRequestId,
+        height: Ctx::Height,
+    },
+
+    /// The height the next range is requested from is covered by a pending request
+    #[error("Height {height} is already covered by pending request {request_id}")]
+    AlreadyCovered {
+        height: Ctx::Height,
+        request_id: OutboundRequestId,
+    },
+}
+
+#[derive_where(Clone, Debug, Default)]
+pub struct PendingRequests<Ctx: Context> {
+    requests: BTreeMap<OutboundRequestId, (RangeInclusive<Ctx::Height>, PeerId)>,
+}
+
+impl<Ctx: Context> PendingRequests<Ctx> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(
+        &mut self,
+        request_id: OutboundRequestId,
+        range: RangeInclusive<Ctx::Height>,
+        peer_id: PeerId,
+    ) -> Option<(RangeInclusive<Ctx::Height>, PeerId)> {
+        self.requests.insert(request_id, (range, peer_id))
+    }
+
+    pub fn remove(
+        &mut self,
+        request_id: &OutboundRequestId,
+    ) -> Option<(RangeInclusive<Ctx::Height>, PeerId)> {
+        self.requests.remove(request_id)
+    }
+
+    /// Remove the requests for heights up to the given height, included.
+    /// A request with heights above it is kept whole.
+    pub fn remove_requests_up_to(&mut self, height: Ctx::Height) {
+        self.requests.retain(|_, (range, _)| *range.end() > height);
+    }
+
+    pub fn clear(&mut self) {
+        self.requests.clear();
+    }
+
+    pub fn get(
+        &self,
+        request_id: &OutboundRequestId,
+    ) -> Option<&(RangeInclusive<Ctx::Height>, PeerId)> {
+        self.requests.get(request_id)
+    }
+
+    pub fn contains_key(&self, request_id: &OutboundRequestId) -> bool {
+        self.requests.contains_key(request_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&OutboundRequestId, &(RangeInclusive<Ctx::Height>, PeerId))> {
+        self.requests.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &OutboundRequestId> {
+        self.requests.keys()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &(RangeInclusive<Ctx::Height>, PeerId)> {
+        self.requests.values()
+    }
+
+    pub fn as_map(&self) -> &BTreeMap<OutboundRequestId, (RangeInclusive<Ctx::Height>, PeerId)> {
+        &self.requests
+    }
+
+    /// The pending request covering the given height, if any
+    pub fn covering(&self, height: Ctx::Height) -> Option<(&OutboundRequestId, PeerId)> {
+        self.requests
+            .iter()
+            .find(|(_, (range, _))| range.contains(&height))
+            .map(|(request_id, (_, peer_id))| (request_id, *peer_id))
+    }
+
+    /// Find the next range of at most `max_range_size` heights to request, starting from
+    /// `initial_height` and ending before the first pending request above it.
+    ///
+    /// Fails if `initial_height` is itself covered by a pending request,
+    /// in which case the caller should start from [`Self::next_uncovered_height`] instead.
+    pub fn next_uncovered_range_from(
+        &self,
+        initial_height: Ctx::Height,
+        max_range_size: u64,
+    ) -> Result<RangeInclusive<Ctx::Height>, PendingRequestsError<Ctx>> {
+        let max_batch_size = max_range_size.max(1);
+
+        // Find the pending request with the smallest range.start where range.end >= initial_height
+        let next_request = self
+            .requests
+            .iter()
+            .filter(|(_, (range, _))| *range.end() >= initial_height)
+            .min_by_key(|(_, (range, _))| range.start());
+
+        // Start with the full max_batch_size range
+        let mut end_height = initial_height.increment_by(max_batch_size - 1);
+
+        // If there's a range in pending, constrain to that boundary
+        if let Some((request_id, (range, _))) = next_request {
+            if range.contains(&initial_height) {
+                return Err(PendingRequestsError::AlreadyCovered {
+                    height: initial_height,
+                    request_id: request_id.clone(),
+                });
+            }
+
+            // The range starts above initial_height, so it can be decremented
+            if let Some(boundary_end) = range.start().decrement() {
+                end_height = min(end_height, boundary_end);
+            }
+        }
+
+        Ok(initial_height..=end_height)
+    }
+
+    /// Find the next height not covered by any pending request, starting from `starting_height`
+    pub fn next_uncovered_height(&self, starting_height: Ctx::Height) -> Ctx::Height {
+        let mut next_height = starting_height;
+
+        while let Some((covered_range, _)) = self
+            .requests
+            .values()
+            .find(|(range, _)| range.contains(&next_height))
+        {
+            next_height = covered_range.end().increment();
+        }
+
+        next_height
+    }
+
+    /// Check that the ranges of the pending requests are not empty and pairwise disjoint,
+    /// and that the next range can be requested from `sync_height`, ie. that it is not covered.
+    ///
+    /// This goes over all the pending requests, and is meant for debug builds and tests.
+    pub fn debug_validate(
+        &self,
+        sync_height: Ctx::Height,
+    ) -> Result<(), PendingRequestsError<Ctx>> {
+        let mut ranges = Vec::with_capacity(self.requests.len());
+
+        for (request_id, (range, _)) in &self.requests {
+            if range.is_empty() {
+                return Err(PendingRequestsError::EmptyRange {
+                    request_id: request_id.clone(),
+                });
+            }
+
+            ranges.push((request_id, range));
+        }
+
+        ranges.sort_by_key(|(_, range)| *range.start());
+
+        for pair in ranges.windows(2) {
+            let ((first, first_range), (second, second_range)) = (pair[0], pair[1]);
+
+            if second_range.start() <= first_range.end() {
+                return Err(PendingRequestsError::Overlap {
+                    first: first.clone(),
+                    second: second.clone(),
+                    height: *second_range.start(),
+                });
+            }
+        }
+
+        if let Some((request_id, _)) = self.covering(sync_height) {
+            return Err(PendingRequestsError::AlreadyCovered {
+                height: sync_height,
+                request_id: request_id.clone(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use arbtest::arbitrary::{Result, Unstructured};
+    use arbtest::arbtest;
+
+    use arc_malachitebft_test::{Height, TestContext};
+    use malachitebft_core_types::Height as _;
+
+    type TestPendingRequests = PendingRequests<TestContext>;
+
+    fn make_pending_requests(ranges: &[(u64, u64)]) -> TestPendingRequests {
+        let mut pending_requests = TestPendingRequests::new();
+
+        for (i, &(start, end)) in ranges.iter().enumerate() {
+            pending_requests.insert(
+                OutboundRequestId::new(format!("req{}", i + 1)),
+                Height::new(start)..=Height::new(end),
+                PeerId::random(),
+            );
+        }
+
+        pending_requests
+    }
+
+    struct RangeTestCase {
+        name: &'static str,
+        initial_height: u64,
+        max_size: u64,
+        pending_ranges: &'static [(u64, u64)], // (start, end) pairs
+        expected_start: u64,
+        expected_end: u64,
+    }
+
+    struct CoveredTestCase {
+        name: &'static str,
+        initial_height: u64,
+        max_size: u64,
+        pending_ranges: &'static [(u64, u64)], // (start, end) pairs
+        expected_request: &'static str,
+    }
+
+    struct HeightTestCase {
+        name: &'static str,
+        initial_height: u64,
+        pending_ranges: &'static [(u64, u64)], // (start, end) pairs
+        expected_height: u64,
+    }
+
+    #[test]
+    fn next_uncovered_range_from_table() {
+        let test_cases = [
+            RangeTestCase {
+                name: "no pending requests",
+                initial_height: 10,
+                max_size: 5,
+                pending_ranges: &[],
+                expected_start: 10,
+                expected_end: 14,
+            },
+            RangeTestCase {
+                name: "max size one",
+                initial_height: 10,
+                max_size: 1,
+                pending_ranges: &[],
+                expected_start: 10,
+                expected_end: 10,
+            },
+            RangeTestCase {
+                name: "with blocking request",
+                initial_height: 10,
+                max_size: 5,
+                pending_ranges: &[(12, 15)],
+                expected_start: 10,
+                expected_end: 11,
+            },
+            RangeTestCase {
+                name: "zero max size becomes one",
+                initial_height: 10,
+                max_size: 0, // Should be treated as 1
+                pending_ranges: &[],
+                expected_start: 10,
+                expected_end: 10,
+            },
+            RangeTestCase {
+                name: "range starts immediately after",
+                initial_height: 15,
+                max_size: 5,
+                pending_ranges: &[(16, 20)],
+                expected_start: 15,
+                expected_end: 15, // boundary_end = 16 - 1 = 15, min(19, 15) = 15
+            },
+            RangeTestCase {
+                name: "height zero with range starting at one",
+                initial_height: 0,
+                max_size: 3,
+                pending_ranges: &[(1, 5)],
+                expected_start: 0,
+                expected_end: 0, // boundary_end = 1 - 1 = 0, min(2, 0) = 0
+            },
+            RangeTestCase {
+                name: "sync height just at range end",
+                initial_height: 11,
+                max_size: 4,
+                pending_ranges: &[(5, 10)],
+                expected_start: 11,
+                expected_end: 14, // max_end = 11 + 4 - 1 = 14
+            },
+            RangeTestCase {
+                name: "fill gap between ranges",
+                initial_height: 12,
+                max_size: 6,
+                pending_ranges: &[(5, 10), (20, 25)],
+                expected_start: 12,
+                expected_end: 17, // max_end = 12 + 6 - 1 = 17, boundary_end = 20 - 1 = 19, min(17, 19) = 17
+            },
+        ];
+
+        for case in test_cases {
+            let pending_requests = make_pending_requests(case.pending_ranges);
+
+            let result = pending_requests
+                .next_uncovered_range_from(Height::new(case.initial_height), case.max_size);
+
+            assert_eq!(
+                result,
+                Ok(Height::new(case.expected_start)..=Height::new(case.expected_end)),
+                "Test case '{}' failed",
+                case.name
+            );
+        }
+    }
+
+    #[test]
+    fn next_uncovered_range_from_covered_height() {
+        let test_cases = [
+            CoveredTestCase {
+                name: "sync height covered",
+                initial_height: 12,
+                max_size: 3,
+                pending_ranges: &[(10, 15)],
+                expected_request: "req1",
+            },
+            CoveredTestCase {
+                name: "initial height equals range start",
+                initial_height: 15,
+                max_size: 5,
+                pending_ranges: &[(15, 20)],
+                expected_request: "req1",
+            },
+            CoveredTestCase {
+                name: "sync height equals range end",
+                initial_height: 15,
+                max_size: 3,
+                pending_ranges: &[(10, 15)],
+                expected_request: "req1",
+            },
+            CoveredTestCase {
+                name: "multiple consecutive blocks",
+                initial_height: 16,
+                max_size: 3,
+                pending_ranges: &[(10, 15), (16, 20)],
+                expected_request: "req2",
+            },
+            CoveredTestCase {
+                name: "sync height zero with range starting at zero",
+                initial_height: 0,
+                max_size: 3,
+                pending_ranges: &[(0, 5)],
+                expected_request: "req1",
+            },
+        ];
+
+        for case in test_cases {
+            let pending_requests = make_pending_requests(case.pending_ranges);
+
+            let result = pending_requests
+                .next_uncovered_range_from(Height::new(case.initial_height), case.max_size);
+
+            assert_eq!(
+                result,
+                Err(PendingRequestsError::AlreadyCovered {
+                    height: Height::new(case.initial_height),
+                    request_id: OutboundRequestId::new(case.expected_request),
+                }),
+                "Test case '{}' failed",
+                case.name
+            );
+        }
+    }
+
+    #[test]
+    fn next_uncovered_height_table() {
+        let test_cases = [
+            HeightTestCase {
+                name: "no pending requests",
+                initial_height: 10,
+                pending_ranges: &[],
+                expected_height: 10,
+            },
+            HeightTestCase {
+                name: "starting height covered",
+                initial_height: 12,
+                pending_ranges: &[(10, 15)],
+                expected_height: 16, // Should return the height after the covered range
+            },
+            HeightTestCase {
+                name: "starting height match request start",
+                initial_height: 10,
+                pending_ranges: &[(10, 15)],
+                expected_height: 16, // Should return the height after the covered range
+            },
+            HeightTestCase {
+                name: "starting height match request end",
+                initial_height: 15,
+                pending_ranges: &[(10, 15)],
+                expected_height: 16, // Should return the height after the covered range
+            },
+            HeightTestCase {
+                name: "starting height just before request start",
+                initial_height: 9,
+                pending_ranges: &[(10, 15)],
+                expected_height: 9, // Should return the starting height
+            },
+            HeightTestCase {
+                name: "multiple consecutive ranges",
+                initial_height: 10,
+                pending_ranges: &[(10, 15), (16, 20)],
+                expected_height: 21, // Should skip over all consecutive ranges
+            },
+            HeightTestCase {
+                name: "multiple consecutive ranges with a gap",
+                initial_height: 10,
+                pending_ranges: &[(10, 15), (16, 20), (24, 30)],
+                expected_height: 21, // Should skip over consecutive ranges but stop at gap
+            },
+            HeightTestCase {
+                name: "starting height covered multiple",
+                initial_height: 12,
+                pending_ranges: &[(10, 15), (15, 20)],
+                expected_height: 21, // Should return the height after all covered ranges
+            },
+        ];
+
+        for case in test_cases {
+            let pending_requests = make_pending_requests(case.pending_ranges);
+
+            let result = pending_requests.next_uncovered_height(Height::new(case.initial_height));
+
+            assert_eq!(
+                result,
+                Height::new(case.expected_height),
+                "Test case '{}' failed",
+                case.name
+            );
+        }
+    }
+
+    #[test]
+    fn debug_validate_table() {
+        let requests = make_pending_requests(&[(5, 10), (11, 15), (20, 20)]);
+        assert_eq!(requests.debug_validate(Height::new(16)), Ok(()));
+
+        assert_eq!(
+            requests.debug_validate(Height::new(20)),
+            Err(PendingRequestsError::AlreadyCovered {
+                height: Height::new(20),
+                request_id: OutboundRequestId::new("req3"),
+            })
+        );
+
+        let requests = make_pending_requests(&[(5, 10), (10, 15)]);
+        assert_eq!(
+            requests.debug_validate(Height::new(16)),
+            Err(PendingRequestsError::Overlap {
+                first: OutboundRequestId::new("req1"),
+                second: OutboundRequestId::new("req2"),
+                height: Height::new(10),
+            })
+        );
+
+        let requests = make_pending_requests(&[(5, 10), (15, 12)]);
+        assert_eq!(
+            requests.debug_validate(Height::new(11)),
+            Err(PendingRequestsError::EmptyRange {
+                request_id: OutboundRequestId::new("req2"),
+            })
+        );
+    }
+
+    /// An operation on the pending requests, as performed by the sync handlers
+    #[derive(Debug)]
+    enum Op {
+        /// Request the next range from the sync height, possibly only a prefix of it
+        /// if the selected peer does not have all the values
+        Request { max_size: u64, prefix: u64 },
+        /// Remove a request, eg. to re-request its range from another peer
+        Remove { index: usize },
+        /// Remove the requests up to a newly decided height
+        Decide { advance: u64 },
+    }
+
+    fn arb_op(u: &mut Unstructured) -> Result<Op> {
+        Ok(match u.int_in_range(0..=2)? {
+            0 => Op::Request {
+                max_size: u.int_in_range(0..=10)?,
+                prefix: u.int_in_range(0..=10)?,
+            },
+            1 => Op::Remove {
+                index: u.int_in_range(0..=9)?,
+            },
+            2 => Op::Decide {
+                advance: u.int_in_range(0..=8)?,
+            },
+            _ => unreachable!(),
+        })
+    }
+
+    fn arb_ops(u: &mut Unstructured) -> Result<Vec<Op>> {
+        let len = u.int_in_range(1..=100)?;
+        (0..len).map(|_| arb_op(u)).collect()
+    }
+
+    // Property: Random interleavings of requests, removals and decisions keep the pending requests
+    // disjoint, and requesting from the next uncovered height always succeeds
+    #[test]
+    fn interleavings_keep_requests_disjoint() {
+        arbtest(|u| {
+            let ops = arb_ops(u)?;
+
+            let mut requests = TestPendingRequests::new();
+            let mut tip_height = Height::new(0);
+            let mut sync_height = Height::new(1);
+            let mut next_id = 0;
+
+            for op in ops {
+                match op {
+                    Op::Request { max_size, prefix } => {
+                        let range = match requests.next_uncovered_range_from(sync_height, max_size)
+                        {
+                            Ok(range) => range,
+                            Err(PendingRequestsError::AlreadyCovered { .. }) => {
+                                sync_height = requests.next_uncovered_height(sync_height);
+                                requests
+                                    .next_uncovered_range_from(sync_height, max_size)
+                                    .expect("the next uncovered height is not covered")
+                            }
+                            Err(e) => panic!("Unexpected error: {e}"),
+                        };
+
+                        assert!(range.end().as_u64() - range.start().as_u64() < max_size.max(1));
+
+                        let end = min(*range.end(), range.start().increment_by(prefix));
+                        let range = *range.start()..=end;
+
+                        next_id += 1;
+                        let request_id = OutboundRequestId::new(next_id);
+                        requests.insert(request_id, range.clone(), PeerId::random());
+
+                        sync_height = requests.next_uncovered_height(range.end().increment());
+
+                        requests.debug_validate(sync_height).unwrap();
+                    }
+
+                    Op::Remove { index } => {
+                        let Some(request_id) = requests.keys().nth(index).cloned() else {
+                            continue;
+                        };
+
+                        let (range, _) = requests.remove(&request_id).unwrap();
+                        assert!(!requests.contains_key(&request_id));
+
+                        sync_height = min(sync_height, *range.start());
+                    }
+
+                    Op::Decide { advance } => {
+                        tip_height = tip_height.increment_by(advance);
+                        requests.remove_requests_up_to(tip_height);
+
+                        assert!(requests
+                            .values()
+                            .all(|(range, _)| *range.end() > tip_height));
+
+                        sync_height = sync_height.max(tip_height.increment());
+                    }
+                }
+
+                requests
+                    .debug_validate(requests.next_uncovered_height(sync_height))
+                    .unwrap();
+            }
+
+            Ok(())
+        });
+    }
+
+    // Property: The next range starts at the given height if it is not covered,
+    // is at most as long as requested, and does not overlap any pending request
+    #[test]
+    fn next_range_is_uncovered() {
+        arbtest(|u| {
+            let ops = arb_ops(u)?;
+
+            let mut requests = TestPendingRequests::new();
+            let mut sync_height = Height::new(1);
+
+            for (i, op) in ops.into_iter().enumerate() {
+                if let Op::Request { max_size, prefix } = op {
+                    let range = requests
+                        .next_uncovered_range_from(sync_height, max_size)
+                        .expect("the sync height is not covered");
+
+                    let end = min(*range.end(), range.start().increment_by(prefix));
+                    requests.insert(
+                        OutboundRequestId::new(i),
+                        *range.start()..=end,
+                        PeerId::random(),
+                    );
+                }
+
+                sync_height =
+                    requests.next_uncovered_height(sync_height.increment_by(i as u64 % 3));
+            }
+
+            let initial_height = Height::new(u.int_in_range(0..=100)?);
+            let max_size = u.int_in_range(0..=10)?;
+
+            match requests.next_uncovered_range_from(initial_height, max_size) {
+                Ok(range) => {
+                    assert_eq!(*range.start(), initial_height);
+                    assert!(range.end().as_u64() - range.start().as_u64() < max_size.max(1));
+
+                    for (covered, _) in requests.values() {
+                        assert!(range.end() < covered.start() || range.start() > covered.end());
+                    }
+                }
+                Err(PendingRequestsError::AlreadyCovered { height, request_id }) => {
+                    assert_eq!(height, initial_height);
+
+                    let (covered, _) = requests.get(&request_id).unwrap();
+                    assert!(covered.contains(&initial_height));
+                }
+                Err(e) => panic!("Unexpected error: {e}"),
+            }
+
+            Ok(())
+        });
+    }
+}
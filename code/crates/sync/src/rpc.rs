@@ -6,6 +6,9 @@ use libp2p::StreamProtocol;
 use crate::types::{RawRequest, RawResponse};
 use crate::Config;
 
+/// Suffix of the name of the sync protocol variant compressing the requests and responses with LZ4
+pub(crate) const COMPRESSED_PROTOCOL_SUFFIX: &str = "+lz4";
+
 #[derive(Copy, Clone)]
 pub struct Codec {
     config: Config,
@@ -15,6 +18,19 @@ impl Codec {
     pub fn new(config: Config) -> Self {
         Self { config }
     }
+
+    /// Maximum size of a response, whether chunked or not
+    fn max_total_response_size(&self) -> usize {
+        self.config
+            .max_chunked_response_size
+            .map_or(self.config.max_response_size, |max_chunked| {
+                max_chunked.max(self.config.max_response_size)
+            })
+    }
+}
+
+fn is_compressed(protocol: &StreamProtocol) -> bool {
+    protocol.as_ref().ends_with(COMPRESSED_PROTOCOL_SUFFIX)
 }
 
 #[async_trait]
@@ -24,55 +40,152 @@ impl libp2p::request_response::Codec for Codec {
     type Request = RawRequest;
     type Response = RawResponse;
 
-    async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
+    async fn read_request<T>(
+        &mut self,
+        protocol: &Self::Protocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
     where
         T: AsyncRead + Unpin + Send,
     {
-        read_length_prefixed(io, self.config.max_request_size)
-            .await
-            .map(RawRequest)
+        let max_len = self.config.max_request_size;
+
+        if is_compressed(protocol) {
+            let data = read_length_prefixed(io, max_compressed_len(max_len)).await?;
+            decompress(&data, max_len).map(RawRequest)
+        } else {
+            read_length_prefixed(io, max_len).await.map(RawRequest)
+        }
     }
 
     async fn read_response<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
     ) -> io::Result<Self::Response>
     where
         T: AsyncRead + Unpin + Send,
     {
-        read_length_prefixed(io, self.config.max_response_size)
-            .await
-            .map(RawResponse)
+        let max_len = self.config.max_response_size;
+        let max_chunked_len = self.config.max_chunked_response_size;
+
+        if is_compressed(protocol) {
+            let data = read_response(
+                io,
+                max_compressed_len(max_len),
+                max_chunked_len.map(max_compressed_len),
+            )
+            .await?;
+
+            decompress(&data, self.max_total_response_size()).map(RawResponse)
+        } else {
+            read_response(io, max_len, max_chunked_len)
+                .await
+                .map(RawResponse)
+        }
     }
 
     async fn write_request<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
         req: Self::Request,
     ) -> io::Result<()>
     where
         T: AsyncWrite + Unpin + Send,
     {
-        write_length_prefixed(io, req.0, self.config.max_request_size).await
+        let max_len = self.config.max_request_size;
+
+        if is_compressed(protocol) {
+            let data = compress(&req.0, max_len)?;
+            write_length_prefixed(io, data, max_compressed_len(max_len)).await
+        } else {
+            write_length_prefixed(io, req.0, max_len).await
+        }
     }
 
     async fn write_response<T>(
         &mut self,
-        _: &Self::Protocol,
+        protocol: &Self::Protocol,
         io: &mut T,
         res: Self::Response,
     ) -> io::Result<()>
     where
         T: AsyncWrite + Unpin + Send,
     {
-        write_length_prefixed(io, res.0, self.config.max_response_size).await
+        let max_len = self.config.max_response_size;
+        let max_chunked_len = self.config.max_chunked_response_size;
+
+        // The limits apply to the uncompressed response, the compressed one being
+        // checked against the largest size they can be compressed to
+        let (data, max_len, max_chunked_len) = if is_compressed(protocol) {
+            (
+                compress(&res.0, self.max_total_response_size())?,
+                max_compressed_len(max_len),
+                max_chunked_len.map(max_compressed_len),
+            )
+        } else {
+            (res.0, max_len, max_chunked_len)
+        };
+
+        match max_chunked_len {
+            Some(max_total_len) if data.len() > self.config.max_response_size => {
+                write_chunked(io, data, self.config.max_response_size, max_total_len).await
+            }
+            _ => write_length_prefixed(io, data, max_len).await,
+        }
     }
 }
 
 const U32_LENGTH: usize = size_of::<u32>();
 
+/// Set in the length prefix of a response split into chunks, the remaining bits holding
+/// the number of chunks. Peers which do not support chunking see a length far above
+/// any sensible limit and reject the response, as they would an oversized one.
+const CHUNKED_FLAG: u32 = 1 << 31;
+
+/// Length of the SHA3-256 hash sent along each chunk
+const CHUNK_HASH_LENGTH: usize = 32;
+
+fn chunk_hash(chunk: &[u8]) -> [u8; CHUNK_HASH_LENGTH] {
+    use sha3::Digest;
+
+    sha3::Sha3_256::digest(chunk).into()
+}
+
+/// Largest size a payload of at most `max_len` bytes can be compressed to,
+/// including the prepended uncompressed size
+fn max_compressed_len(max_len: usize) -> usize {
+    lz4_flex::block::get_maximum_output_size(max_len) + U32_LENGTH
+}
+
+/// Compress a payload of at most `max_len` bytes, prepending its uncompressed size
+fn compress(data: &[u8], max_len: usize) -> io::Result<Bytes> {
+    if data.len() > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "data too large",
+        ));
+    }
+
+    Ok(Bytes::from(lz4_flex::compress_prepend_size(data)))
+}
+
+/// Decompress a payload, rejecting it before decompressing it
+/// if its advertised uncompressed size is above `max_len`
+fn decompress(data: &[u8], max_len: usize) -> io::Result<Bytes> {
+    let (len, _) = lz4_flex::block::uncompressed_size(data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    if len > max_len {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "data too large"));
+    }
+
+    lz4_flex::decompress_size_prepended(data)
+        .map(Bytes::from)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
 async fn write_length_prefixed<T>(dst: &mut T, data: Bytes, max_len: usize) -> io::Result<()>
 where
     T: AsyncWrite + Unpin + Send,
@@ -104,6 +217,15 @@ where
     src.read_exact(&mut len_bytes).await?;
     let len = u32::from_be_bytes(len_bytes) as usize;
 
+    read_data(src, len, max_len).await
+}
+
+async fn read_data<T>(src: &mut T, len: usize, max_len: usize) -> io::Result<Bytes>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    use io::AsyncReadExt;
+
     if len > max_len {
         return Err(io::Error::new(io::ErrorKind::InvalidData, "data too large"));
     }
@@ -112,3 +234,303 @@ where
     src.read_exact(&mut data).await?;
     Ok(Bytes::from(data))
 }
+
+/// Write a response larger than `chunk_len` as a sequence of chunks of at most `chunk_len` bytes,
+/// each one prefixed by its length and its hash.
+async fn write_chunked<T>(
+    dst: &mut T,
+    data: Bytes,
+    chunk_len: usize,
+    max_total_len: usize,
+) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin + Send,
+{
+    use io::AsyncWriteExt;
+
+    let chunk_len = chunk_len.max(1);
+    let num_chunks = data.len().div_ceil(chunk_len);
+
+    if data.len() > max_total_len || num_chunks >= CHUNKED_FLAG as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "data too large",
+        ));
+    }
+
+    dst.write_all(&(CHUNKED_FLAG | num_chunks as u32).to_be_bytes())
+        .await?;
+
+    for chunk in data.chunks(chunk_len) {
+        dst.write_all(&(chunk.len() as u32).to_be_bytes()).await?;
+        dst.write_all(&chunk_hash(chunk)).await?;
+        dst.write_all(chunk).await?;
+    }
+
+    dst.flush().await?;
+
+    Ok(())
+}
+
+/// Read a response, reassembling it if it was split into chunks
+async fn read_response<T>(
+    src: &mut T,
+    max_len: usize,
+    max_chunked_len: Option<usize>,
+) -> io::Result<Bytes>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    use io::AsyncReadExt;
+
+    let mut len_bytes = [0u8; U32_LENGTH];
+    src.read_exact(&mut len_bytes).await?;
+    let prefix = u32::from_be_bytes(len_bytes);
+
+    match max_chunked_len {
+        Some(max_total_len) if prefix & CHUNKED_FLAG != 0 => {
+            let num_chunks = (prefix & !CHUNKED_FLAG) as usize;
+            read_chunks(src, num_chunks, max_len, max_total_len).await
+        }
+        _ => read_data(src, prefix as usize, max_len).await,
+    }
+}
+
+async fn read_chunks<T>(
+    src: &mut T,
+    num_chunks: usize,
+    max_chunk_len: usize,
+    max_total_len: usize,
+) -> io::Result<Bytes>
+where
+    T: AsyncRead + Unpin + Send,
+{
+    use io::AsyncReadExt;
+
+    // A peer cannot make this node read more chunks than needed to send the largest response
+    let max_chunks = max_total_len.div_ceil(max_chunk_len.max(1));
+    if num_chunks == 0 || num_chunks > max_chunks {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid number of chunks",
+        ));
+    }
+
+    let mut data = Vec::new();
+
+    for _ in 0..num_chunks {
+        let mut len_bytes = [0u8; U32_LENGTH];
+        src.read_exact(&mut len_bytes).await?;
+        let len = u32::from_be_bytes(len_bytes) as usize;
+
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "empty chunk"));
+        }
+
+        if len > max_chunk_len || data.len() + len > max_total_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "data too large"));
+        }
+
+        let mut hash = [0u8; CHUNK_HASH_LENGTH];
+        src.read_exact(&mut hash).await?;
+
+        let start = data.len();
+        data.resize(start + len, 0);
+        src.read_exact(&mut data[start..]).await?;
+
+        if chunk_hash(&data[start..]) != hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "chunk hash mismatch",
+            ));
+        }
+    }
+
+    Ok(Bytes::from(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::futures::executor::block_on;
+    use libp2p::futures::io::Cursor;
+    use libp2p::request_response::Codec as _;
+
+    use super::*;
+
+    const MAX_RESPONSE_SIZE: usize = 1000;
+
+    fn codec(max_chunked_response_size: Option<usize>) -> Codec {
+        Codec::new(
+            Config::default()
+                .with_max_response_size(MAX_RESPONSE_SIZE)
+                .with_max_chunked_response_size(max_chunked_response_size),
+        )
+    }
+
+    fn protocol() -> StreamProtocol {
+        StreamProtocol::new("/malachitebft-sync/v1beta1")
+    }
+
+    fn compressed_protocol() -> StreamProtocol {
+        StreamProtocol::new("/malachitebft-sync/v1beta1+lz4")
+    }
+
+    fn write(codec: &mut Codec, data: &[u8]) -> io::Result<Vec<u8>> {
+        write_with(&protocol(), codec, data)
+    }
+
+    fn read(codec: &mut Codec, bytes: Vec<u8>) -> io::Result<Bytes> {
+        read_with(&protocol(), codec, bytes)
+    }
+
+    fn write_with(
+        protocol: &StreamProtocol,
+        codec: &mut Codec,
+        data: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let mut buf = Cursor::new(Vec::new());
+        block_on(codec.write_response(
+            protocol,
+            &mut buf,
+            RawResponse(Bytes::copy_from_slice(data)),
+        ))?;
+        Ok(buf.into_inner())
+    }
+
+    fn read_with(
+        protocol: &StreamProtocol,
+        codec: &mut Codec,
+        bytes: Vec<u8>,
+    ) -> io::Result<Bytes> {
+        block_on(codec.read_response(protocol, &mut Cursor::new(bytes))).map(|res| res.0)
+    }
+
+    /// Pseudo-random bytes, which do not compress
+    fn random_payload(len: usize) -> Vec<u8> {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        (0..len).map(|_| rng.gen()).collect()
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn oversized_response_is_chunked() {
+        let mut codec = codec(Some(10 * MAX_RESPONSE_SIZE));
+        let data = payload(2 * MAX_RESPONSE_SIZE + 1);
+
+        let bytes = write(&mut codec, &data).unwrap();
+        assert_eq!(
+            bytes.len(),
+            U32_LENGTH + 3 * (U32_LENGTH + CHUNK_HASH_LENGTH) + data.len()
+        );
+        assert_eq!(read(&mut codec, bytes).unwrap(), data);
+
+        // Small responses are not chunked
+        let bytes = write(&mut codec, &data[..10]).unwrap();
+        assert_eq!(bytes.len(), U32_LENGTH + 10);
+        assert_eq!(read(&mut codec, bytes).unwrap(), data[..10]);
+    }
+
+    #[test]
+    fn corrupted_chunk_is_rejected() {
+        let mut codec = codec(Some(10 * MAX_RESPONSE_SIZE));
+
+        let mut bytes = write(&mut codec, &payload(2 * MAX_RESPONSE_SIZE)).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+
+        let err = read(&mut codec, bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Header of a chunked response with the given number of chunks
+    fn chunked_header(num_chunks: u32) -> Vec<u8> {
+        (CHUNKED_FLAG | num_chunks).to_be_bytes().to_vec()
+    }
+
+    #[test]
+    fn empty_chunk_is_rejected() {
+        let mut codec = codec(Some(10 * MAX_RESPONSE_SIZE));
+
+        let mut bytes = chunked_header(2);
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.extend_from_slice(&chunk_hash(&[]));
+
+        let err = read(&mut codec, bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn too_many_chunks_are_rejected() {
+        let mut codec = codec(Some(10 * MAX_RESPONSE_SIZE));
+
+        // Rejected before reading any chunk
+        for num_chunks in [0, 11, CHUNKED_FLAG - 1] {
+            let err = read(&mut codec, chunked_header(num_chunks)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+
+        // As many chunks as needed for the largest response
+        let data = payload(10 * MAX_RESPONSE_SIZE);
+        let bytes = write(&mut codec, &data).unwrap();
+        assert_eq!(read(&mut codec, bytes).unwrap(), data);
+    }
+
+    #[test]
+    fn chunked_size_limit() {
+        let data = payload(3 * MAX_RESPONSE_SIZE);
+
+        // Above the chunked limit
+        assert!(write(&mut codec(Some(2 * MAX_RESPONSE_SIZE)), &data).is_err());
+
+        // Chunking disabled
+        assert!(write(&mut codec(None), &data).is_err());
+
+        // Receiver with a lower limit, or without chunking support
+        let bytes = write(&mut codec(Some(10 * MAX_RESPONSE_SIZE)), &data).unwrap();
+        assert!(read(&mut codec(Some(2 * MAX_RESPONSE_SIZE)), bytes.clone()).is_err());
+        assert!(read(&mut codec(None), bytes).is_err());
+    }
+
+    #[test]
+    fn compressed_response() {
+        let mut receiver = codec(None);
+        let data = payload(MAX_RESPONSE_SIZE);
+
+        let bytes = write_with(&compressed_protocol(), &mut receiver, &data).unwrap();
+        assert!(bytes.len() < data.len());
+        assert_eq!(
+            read_with(&compressed_protocol(), &mut receiver, bytes).unwrap(),
+            data
+        );
+
+        // The limit applies to the uncompressed response, however well it compresses
+        let data = payload(MAX_RESPONSE_SIZE + 1);
+        assert!(write_with(&compressed_protocol(), &mut receiver, &data).is_err());
+
+        let bytes = write_with(
+            &compressed_protocol(),
+            &mut codec(Some(10 * MAX_RESPONSE_SIZE)),
+            &data,
+        )
+        .unwrap();
+        assert!(read_with(&compressed_protocol(), &mut receiver, bytes).is_err());
+    }
+
+    #[test]
+    fn incompressible_response_is_chunked() {
+        let mut codec = codec(Some(10 * MAX_RESPONSE_SIZE));
+        let data = random_payload(3 * MAX_RESPONSE_SIZE);
+
+        let bytes = write_with(&compressed_protocol(), &mut codec, &data).unwrap();
+        assert!(bytes.len() > data.len());
+        assert_eq!(
+            read_with(&compressed_protocol(), &mut codec, bytes).unwrap(),
+            data
+        );
+    }
+}
@@ -131,6 +131,11 @@ impl PeerScorer {
         new_score
     }
 
+    /// Set the score of a peer, eg. to the one it had before a restart
+    pub fn set_score(&mut self, peer_id: PeerId, score: Score) {
+        self.scores.insert(peer_id, PeerScore::new(score));
+    }
+
     /// Get the current score for a peer
     pub fn get_score(&self, peer_id: &PeerId) -> Score {
         self.scores
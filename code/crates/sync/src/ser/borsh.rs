@@ -1,9 +1,15 @@
 use {
-    crate::{RawDecidedValue, Request, Response, Status, ValueRequest, ValueResponse},
+    crate::{
+        ProposalPartsRequest, ProposalPartsResponse, RawDecidedValue, Request, Response, Snapshot,
+        SnapshotRequest, SnapshotResponse, Status, TraceId, ValueRequest, ValueResponse,
+        VoteSetRequest, VoteSetResponse,
+    },
     borsh::BorshSerialize,
-    malachitebft_core_types::{CommitCertificate, Context},
+    bytes::Bytes,
+    malachitebft_core_types::{CommitCertificate, Context, Round, Signature, SignedMessage},
     malachitebft_peer::PeerId,
     std::ops::RangeInclusive,
+    std::time::Duration,
 };
 
 impl<Ctx: Context> borsh::BorshSerialize for Status<Ctx>
@@ -14,6 +20,7 @@ where
         self.peer_id.serialize(writer)?;
         self.tip_height.serialize(writer)?;
         self.history_min_height.serialize(writer)?;
+        self.min_needed_height.serialize(writer)?;
         Ok(())
     }
 }
@@ -26,21 +33,66 @@ where
         let peer_id = PeerId::deserialize_reader(reader)?;
         let tip_height = Ctx::Height::deserialize_reader(reader)?;
         let history_min_height = Ctx::Height::deserialize_reader(reader)?;
+        let min_needed_height = Option::<Ctx::Height>::deserialize_reader(reader)?;
         Ok(Status {
             peer_id,
             tip_height,
             history_min_height,
+            min_needed_height,
         })
     }
 }
 
+// Tags distinguishing the kinds of requests and responses
+const VALUE_TAG: u8 = 0;
+const SNAPSHOT_LIST_TAG: u8 = 1;
+const SNAPSHOT_CHUNK_TAG: u8 = 2;
+const PROPOSAL_PARTS_TAG: u8 = 3;
+const VOTE_SET_TAG: u8 = 4;
+
+fn invalid_tag(tag: u8) -> borsh::io::Error {
+    borsh::io::Error::new(
+        borsh::io::ErrorKind::InvalidData,
+        format!("invalid sync message tag: {tag}"),
+    )
+}
+
 impl<Ctx: Context> borsh::BorshSerialize for Request<Ctx>
 where
     Ctx::Height: borsh::BorshSerialize,
 {
     fn serialize<W: borsh::io::Write>(&self, writer: &mut W) -> borsh::io::Result<()> {
         match self {
-            Request::ValueRequest(value_request) => value_request.range.serialize(writer),
+            Request::ValueRequest(value_request) => {
+                VALUE_TAG.serialize(writer)?;
+                value_request.range.serialize(writer)?;
+                value_request.trace_id.0.serialize(writer)?;
+                value_request.certificates_only.serialize(writer)
+            }
+            Request::SnapshotRequest(SnapshotRequest::List) => SNAPSHOT_LIST_TAG.serialize(writer),
+            Request::SnapshotRequest(SnapshotRequest::Chunk {
+                height,
+                format,
+                index,
+            }) => {
+                SNAPSHOT_CHUNK_TAG.serialize(writer)?;
+                height.serialize(writer)?;
+                format.serialize(writer)?;
+                index.serialize(writer)
+            }
+            Request::ProposalPartsRequest(request) => {
+                PROPOSAL_PARTS_TAG.serialize(writer)?;
+                request.height.serialize(writer)?;
+                request.round.as_i64().serialize(writer)?;
+                request.proposer.serialize(writer)?;
+                BorshSerialize::serialize(&request.stream_id.to_vec(), writer)?;
+                request.sequences.serialize(writer)
+            }
+            Request::VoteSetRequest(request) => {
+                VOTE_SET_TAG.serialize(writer)?;
+                request.height.serialize(writer)?;
+                request.round.as_i64().serialize(writer)
+            }
         }
     }
 }
@@ -50,29 +102,178 @@ where
     Ctx::Height: borsh::BorshDeserialize,
 {
     fn deserialize_reader<R: borsh::io::Read>(reader: &mut R) -> borsh::io::Result<Self> {
-        let range = RangeInclusive::<Ctx::Height>::deserialize_reader(reader)?;
-        Ok(Request::ValueRequest(ValueRequest::new(range)))
+        match u8::deserialize_reader(reader)? {
+            VALUE_TAG => {
+                let range = RangeInclusive::<Ctx::Height>::deserialize_reader(reader)?;
+                let trace_id = TraceId(u64::deserialize_reader(reader)?);
+                let certificates_only = bool::deserialize_reader(reader)?;
+                Ok(Request::ValueRequest(
+                    ValueRequest::new(range)
+                        .with_trace_id(trace_id)
+                        .with_certificates_only(certificates_only),
+                ))
+            }
+            SNAPSHOT_LIST_TAG => Ok(Request::SnapshotRequest(SnapshotRequest::List)),
+            SNAPSHOT_CHUNK_TAG => Ok(Request::SnapshotRequest(SnapshotRequest::Chunk {
+                height: Ctx::Height::deserialize_reader(reader)?,
+                format: u32::deserialize_reader(reader)?,
+                index: u32::deserialize_reader(reader)?,
+            })),
+            PROPOSAL_PARTS_TAG => Ok(Request::ProposalPartsRequest(ProposalPartsRequest {
+                height: Ctx::Height::deserialize_reader(reader)?,
+                round: Round::from(i64::deserialize_reader(reader)?),
+                proposer: PeerId::deserialize_reader(reader)?,
+                stream_id: Vec::<u8>::deserialize_reader(reader)?.into(),
+                sequences: Vec::<u64>::deserialize_reader(reader)?,
+            })),
+            VOTE_SET_TAG => Ok(Request::VoteSetRequest(VoteSetRequest::new(
+                Ctx::Height::deserialize_reader(reader)?,
+                Round::from(i64::deserialize_reader(reader)?),
+            ))),
+            tag => Err(invalid_tag(tag)),
+        }
     }
 }
 
 impl<Ctx: Context> borsh::BorshSerialize for Response<Ctx>
 where
+    Ctx::Height: borsh::BorshSerialize,
     ValueResponse<Ctx>: borsh::BorshSerialize,
+    VoteSetResponse<Ctx>: borsh::BorshSerialize,
 {
     fn serialize<W: borsh::io::Write>(&self, writer: &mut W) -> borsh::io::Result<()> {
         match self {
-            Response::ValueResponse(value_response) => value_response.serialize(writer),
+            Response::ValueResponse(value_response) => {
+                VALUE_TAG.serialize(writer)?;
+                value_response.serialize(writer)
+            }
+            Response::SnapshotResponse(SnapshotResponse::List(snapshots)) => {
+                SNAPSHOT_LIST_TAG.serialize(writer)?;
+                snapshots.serialize(writer)
+            }
+            Response::SnapshotResponse(SnapshotResponse::Chunk {
+                height,
+                format,
+                index,
+                chunk,
+            }) => {
+                SNAPSHOT_CHUNK_TAG.serialize(writer)?;
+                height.serialize(writer)?;
+                format.serialize(writer)?;
+                index.serialize(writer)?;
+                BorshSerialize::serialize(&chunk.to_vec(), writer)
+            }
+            Response::ProposalPartsResponse(response) => {
+                PROPOSAL_PARTS_TAG.serialize(writer)?;
+                response.height.serialize(writer)?;
+                let parts: Vec<Vec<u8>> = response.parts.iter().map(|part| part.to_vec()).collect();
+                parts.serialize(writer)
+            }
+            Response::VoteSetResponse(response) => {
+                VOTE_SET_TAG.serialize(writer)?;
+                response.serialize(writer)
+            }
         }
     }
 }
 
 impl<Ctx: Context> borsh::BorshDeserialize for Response<Ctx>
 where
+    Ctx::Height: borsh::BorshDeserialize,
     ValueResponse<Ctx>: borsh::BorshDeserialize,
+    VoteSetResponse<Ctx>: borsh::BorshDeserialize,
+{
+    fn deserialize_reader<R: borsh::io::Read>(reader: &mut R) -> borsh::io::Result<Self> {
+        match u8::deserialize_reader(reader)? {
+            VALUE_TAG => Ok(Response::ValueResponse(ValueResponse::deserialize_reader(
+                reader,
+            )?)),
+            SNAPSHOT_LIST_TAG => Ok(Response::SnapshotResponse(SnapshotResponse::List(
+                Vec::<Snapshot<Ctx>>::deserialize_reader(reader)?,
+            ))),
+            SNAPSHOT_CHUNK_TAG => Ok(Response::SnapshotResponse(SnapshotResponse::Chunk {
+                height: Ctx::Height::deserialize_reader(reader)?,
+                format: u32::deserialize_reader(reader)?,
+                index: u32::deserialize_reader(reader)?,
+                chunk: Vec::<u8>::deserialize_reader(reader)?.into(),
+            })),
+            PROPOSAL_PARTS_TAG => Ok(Response::ProposalPartsResponse(ProposalPartsResponse::new(
+                Ctx::Height::deserialize_reader(reader)?,
+                Vec::<Vec<u8>>::deserialize_reader(reader)?
+                    .into_iter()
+                    .map(Bytes::from)
+                    .collect(),
+            ))),
+            VOTE_SET_TAG => Ok(Response::VoteSetResponse(
+                VoteSetResponse::deserialize_reader(reader)?,
+            )),
+            tag => Err(invalid_tag(tag)),
+        }
+    }
+}
+
+impl<Ctx: Context> borsh::BorshSerialize for VoteSetResponse<Ctx>
+where
+    Ctx::Height: borsh::BorshSerialize,
+    Ctx::Vote: borsh::BorshSerialize,
+    Signature<Ctx>: borsh::BorshSerialize,
+{
+    fn serialize<W: borsh::io::Write>(&self, writer: &mut W) -> borsh::io::Result<()> {
+        self.height.serialize(writer)?;
+        self.round.as_i64().serialize(writer)?;
+        (self.votes.len() as u32).serialize(writer)?;
+        for vote in &self.votes {
+            vote.message.serialize(writer)?;
+            vote.signature.serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Ctx: Context> borsh::BorshDeserialize for VoteSetResponse<Ctx>
+where
+    Ctx::Height: borsh::BorshDeserialize,
+    Ctx::Vote: borsh::BorshDeserialize,
+    Signature<Ctx>: borsh::BorshDeserialize,
+{
+    fn deserialize_reader<R: borsh::io::Read>(reader: &mut R) -> borsh::io::Result<Self> {
+        let height = Ctx::Height::deserialize_reader(reader)?;
+        let round = Round::from(i64::deserialize_reader(reader)?);
+        let len = u32::deserialize_reader(reader)?;
+        let votes = (0..len)
+            .map(|_| {
+                let vote = Ctx::Vote::deserialize_reader(reader)?;
+                let signature = Signature::<Ctx>::deserialize_reader(reader)?;
+                Ok(SignedMessage::new(vote, signature))
+            })
+            .collect::<borsh::io::Result<_>>()?;
+        Ok(VoteSetResponse::new(height, round, votes))
+    }
+}
+
+impl<Ctx: Context> borsh::BorshSerialize for Snapshot<Ctx>
+where
+    Ctx::Height: borsh::BorshSerialize,
+{
+    fn serialize<W: borsh::io::Write>(&self, writer: &mut W) -> borsh::io::Result<()> {
+        self.height.serialize(writer)?;
+        self.format.serialize(writer)?;
+        self.chunks.serialize(writer)?;
+        BorshSerialize::serialize(&self.hash.to_vec(), writer)?;
+        Ok(())
+    }
+}
+
+impl<Ctx: Context> borsh::BorshDeserialize for Snapshot<Ctx>
+where
+    Ctx::Height: borsh::BorshDeserialize,
 {
     fn deserialize_reader<R: borsh::io::Read>(reader: &mut R) -> borsh::io::Result<Self> {
-        let value = ValueResponse::deserialize_reader(reader)?;
-        Ok(Response::ValueResponse(value))
+        let height = Ctx::Height::deserialize_reader(reader)?;
+        let format = u32::deserialize_reader(reader)?;
+        let chunks = u32::deserialize_reader(reader)?;
+        let hash = Vec::<u8>::deserialize_reader(reader)?;
+        Ok(Snapshot::new(height, format, chunks, hash.into()))
     }
 }
 
@@ -84,6 +285,10 @@ where
     fn serialize<W: borsh::io::Write>(&self, writer: &mut W) -> borsh::io::Result<()> {
         self.start_height.serialize(writer)?;
         self.values.serialize(writer)?;
+        self.trace_id.0.serialize(writer)?;
+        self.retry_after
+            .map(|retry_after| retry_after.as_millis() as u64)
+            .serialize(writer)?;
         Ok(())
     }
 }
@@ -96,9 +301,13 @@ where
     fn deserialize_reader<R: borsh::io::Read>(reader: &mut R) -> borsh::io::Result<Self> {
         let start_height = Ctx::Height::deserialize_reader(reader)?;
         let values = Vec::<RawDecidedValue<Ctx>>::deserialize_reader(reader)?;
+        let trace_id = TraceId(u64::deserialize_reader(reader)?);
+        let retry_after = Option::<u64>::deserialize_reader(reader)?.map(Duration::from_millis);
         Ok(ValueResponse {
             start_height,
             values,
+            trace_id,
+            retry_after,
         })
     }
 }
@@ -0,0 +1,320 @@
+//! Restoring the application state from a snapshot served by the peers, before switching
+//! to syncing the values one height at a time.
+//!
+//! When a peer advertises a tip at least `snapshot_min_lag` heights ahead, the peers that far
+//! ahead are asked for the snapshots they can serve. Once they all answered, the highest
+//! snapshot offered by at least `snapshot_min_peers` peers, down to its hash, is chosen and
+//! its chunks are fetched in parallel from the peers which offered it. The chunks are then
+//! handed to the application, which checks them against the hash of the snapshot and restores
+//! its state, after which consensus starts at the following height.
+//!
+//! If the chunks of a snapshot cannot be fetched or the application fails to restore it,
+//! the next best offer is tried. A snapshot is restored at most once, syncing the values
+//! one height at a time if none of the snapshots offered could be restored.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bytes::Bytes;
+use derive_where::derive_where;
+
+use malachitebft_core_types::Context;
+use malachitebft_peer::PeerId;
+
+use crate::{OutboundRequestId, Snapshot, SnapshotResponse};
+
+#[derive_where(Debug, Default)]
+pub enum SnapshotSync<Ctx: Context> {
+    /// No snapshot has been looked for yet
+    #[derive_where(default)]
+    Idle,
+
+    /// Asking the peers for the snapshots they can serve
+    Discovering(Discovery<Ctx>),
+
+    /// Fetching the chunks of the chosen snapshot
+    Fetching(Fetch<Ctx>),
+
+    /// Waiting for the application to restore the snapshot,
+    /// with the offers to fall back to if it fails to
+    Restoring(Snapshot<Ctx>, Discovery<Ctx>),
+
+    /// A snapshot has been restored, or none could be
+    Done,
+}
+
+impl<Ctx: Context> SnapshotSync<Ctx> {
+    /// Whether a snapshot is being looked for, fetched or restored,
+    /// in which case no values are requested from the peers
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            Self::Discovering(_) | Self::Fetching(_) | Self::Restoring(..)
+        )
+    }
+
+    /// Give up on the snapshot being fetched or restored,
+    /// going back to choosing among the other snapshots offered
+    pub fn fall_back(&mut self) {
+        *self = match std::mem::take(self) {
+            Self::Fetching(fetch) => Self::Discovering(fetch.others),
+            Self::Restoring(_, others) => Self::Discovering(others),
+            other => other,
+        };
+    }
+}
+
+#[derive_where(Debug, Default)]
+pub struct Discovery<Ctx: Context> {
+    /// Peers which have been asked for their snapshots
+    pub asked: BTreeSet<PeerId>,
+
+    /// Requests for the snapshots of the peers not answered yet
+    pub pending: BTreeMap<OutboundRequestId, PeerId>,
+
+    /// Snapshots offered by the peers, with the peers offering each of them
+    pub offers: Vec<(Snapshot<Ctx>, BTreeSet<PeerId>)>,
+}
+
+impl<Ctx: Context> Discovery<Ctx> {
+    /// Record the snapshots offered by a peer
+    pub fn add_offers(&mut self, peer_id: PeerId, snapshots: Vec<Snapshot<Ctx>>) {
+        for snapshot in snapshots {
+            if snapshot.chunks == 0 {
+                continue;
+            }
+
+            match self.offers.iter_mut().find(|(offer, _)| *offer == snapshot) {
+                Some((_, peers)) => {
+                    peers.insert(peer_id);
+                }
+                None => self.offers.push((snapshot, BTreeSet::from([peer_id]))),
+            }
+        }
+    }
+
+    /// Whether all the peers asked for their snapshots have answered
+    pub fn is_complete(&self) -> bool {
+        !self.asked.is_empty() && self.pending.is_empty()
+    }
+
+    /// Remove and return the highest snapshot at or above the given height offered by
+    /// at least `min_peers` peers, preferring the one offered by the most peers at that height
+    pub fn take_best_offer(
+        &mut self,
+        min_height: Ctx::Height,
+        min_peers: usize,
+    ) -> Option<(Snapshot<Ctx>, Vec<PeerId>)> {
+        let (index, _) = self
+            .offers
+            .iter()
+            .enumerate()
+            .filter(|(_, (snapshot, peers))| {
+                snapshot.height >= min_height && peers.len() >= min_peers
+            })
+            .max_by_key(|(_, (snapshot, peers))| (snapshot.height, peers.len()))?;
+
+        let (snapshot, peers) = self.offers.swap_remove(index);
+        Some((snapshot, peers.into_iter().collect()))
+    }
+}
+
+#[derive_where(Debug)]
+pub struct Fetch<Ctx: Context> {
+    /// The snapshot being fetched
+    pub snapshot: Snapshot<Ctx>,
+
+    /// Peers offering the snapshot which have not failed to serve a chunk of it
+    pub providers: Vec<PeerId>,
+
+    /// Chunks received so far, by index
+    pub chunks: BTreeMap<u32, Bytes>,
+
+    /// Requests for chunks not answered yet, with the index of the chunk and the peer
+    pub pending: BTreeMap<OutboundRequestId, (u32, PeerId)>,
+
+    /// The other snapshots offered, to fall back to if this one cannot be restored
+    pub others: Discovery<Ctx>,
+}
+
+impl<Ctx: Context> Fetch<Ctx> {
+    pub fn new(snapshot: Snapshot<Ctx>, providers: Vec<PeerId>, others: Discovery<Ctx>) -> Self {
+        Self {
+            snapshot,
+            providers,
+            chunks: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            others,
+        }
+    }
+
+    /// Lowest index of the chunks neither received nor requested
+    pub fn next_missing_chunk(&self) -> Option<u32> {
+        (0..self.snapshot.chunks).find(|index| {
+            !self.chunks.contains_key(index)
+                && !self.pending.values().any(|(pending, _)| pending == index)
+        })
+    }
+
+    /// Record a chunk received in response to a request for the chunk at the given index,
+    /// returning whether it was a chunk of the snapshot at that index
+    pub fn add_chunk(&mut self, index: u32, response: SnapshotResponse<Ctx>) -> bool {
+        match response {
+            SnapshotResponse::Chunk {
+                height,
+                format,
+                index: chunk_index,
+                chunk,
+            } if height == self.snapshot.height
+                && format == self.snapshot.format
+                && chunk_index == index
+                && !chunk.is_empty() =>
+            {
+                self.chunks.insert(index, chunk);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Stop requesting chunks from a peer which failed to serve one
+    pub fn remove_provider(&mut self, peer_id: PeerId) {
+        self.providers.retain(|provider| *provider != peer_id);
+    }
+
+    /// Whether all the chunks of the snapshot have been received
+    pub fn is_complete(&self) -> bool {
+        self.chunks.len() == self.snapshot.chunks as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arc_malachitebft_test::{Height, TestContext};
+
+    use super::*;
+
+    fn snapshot(height: u64, chunks: u32) -> Snapshot<TestContext> {
+        Snapshot::new(Height::new(height), 1, chunks, Bytes::from_static(b"hash"))
+    }
+
+    fn sorted(mut peers: Vec<PeerId>) -> Vec<PeerId> {
+        peers.sort();
+        peers
+    }
+
+    #[test]
+    fn best_offer() {
+        let (a, b, c) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let mut discovery = Discovery::<TestContext>::default();
+
+        discovery.add_offers(a, vec![snapshot(100, 2), snapshot(200, 3)]);
+        discovery.add_offers(b, vec![snapshot(200, 3), snapshot(300, 0)]);
+        discovery.add_offers(c, vec![snapshot(200, 4)]);
+
+        // Empty snapshots are ignored, and the one offered by the most peers is preferred
+        let (best, peers) = discovery.take_best_offer(Height::new(150), 1).unwrap();
+        assert_eq!(best, snapshot(200, 3));
+        assert_eq!(sorted(peers), sorted(vec![a, b]));
+
+        // The offer taken is not offered again
+        let (next, peers) = discovery.take_best_offer(Height::new(150), 1).unwrap();
+        assert_eq!(next, snapshot(200, 4));
+        assert_eq!(peers, vec![c]);
+
+        assert!(discovery.take_best_offer(Height::new(201), 1).is_none());
+    }
+
+    #[test]
+    fn best_offer_requires_agreeing_peers() {
+        let (a, b, c) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let mut discovery = Discovery::<TestContext>::default();
+
+        let forged = Snapshot::new(Height::new(300), 1, 3, Bytes::from_static(b"forged"));
+
+        // A single peer offering a higher snapshot, or one with a different hash, is not enough
+        discovery.add_offers(a, vec![forged.clone(), snapshot(200, 3)]);
+        discovery.add_offers(b, vec![snapshot(200, 3)]);
+        discovery.add_offers(c, vec![snapshot(300, 3), snapshot(100, 2)]);
+
+        let (best, peers) = discovery.take_best_offer(Height::new(100), 2).unwrap();
+        assert_eq!(best, snapshot(200, 3));
+        assert_eq!(sorted(peers), sorted(vec![a, b]));
+
+        // Once it is taken, no other snapshot is offered by enough peers
+        assert!(discovery.take_best_offer(Height::new(100), 2).is_none());
+        assert_eq!(discovery.offers.len(), 3);
+
+        let (best, _) = discovery.take_best_offer(Height::new(100), 1).unwrap();
+        assert_eq!(best.height, Height::new(300));
+    }
+
+    #[test]
+    fn fall_back_to_next_offer() {
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let mut discovery = Discovery::<TestContext>::default();
+
+        discovery.add_offers(a, vec![snapshot(200, 3), snapshot(100, 2)]);
+        discovery.add_offers(b, vec![snapshot(200, 3), snapshot(100, 2)]);
+
+        let (best, providers) = discovery.take_best_offer(Height::new(50), 2).unwrap();
+        assert_eq!(best, snapshot(200, 3));
+
+        let mut sync = SnapshotSync::Fetching(Fetch::new(best.clone(), providers, discovery));
+
+        // No peer left to fetch the chunks from
+        sync.fall_back();
+        let SnapshotSync::Discovering(discovery) = &mut sync else {
+            panic!("expected to go back to the other offers");
+        };
+
+        let (next, _) = discovery.take_best_offer(Height::new(50), 2).unwrap();
+        assert_eq!(next, snapshot(100, 2));
+
+        // The application failed to restore the last snapshot offered
+        let mut sync = SnapshotSync::Restoring(next, std::mem::take(discovery));
+        sync.fall_back();
+        let SnapshotSync::Discovering(discovery) = &mut sync else {
+            panic!("expected to go back to the other offers");
+        };
+        assert!(discovery.take_best_offer(Height::new(50), 1).is_none());
+
+        // Nothing to fall back from
+        let mut sync = SnapshotSync::<TestContext>::Done;
+        sync.fall_back();
+        assert!(matches!(sync, SnapshotSync::Done));
+    }
+
+    #[test]
+    fn fetch_chunks() {
+        let peer = PeerId::random();
+        let mut fetch = Fetch::new(snapshot(100, 3), vec![peer], Discovery::default());
+
+        assert_eq!(fetch.next_missing_chunk(), Some(0));
+
+        fetch
+            .pending
+            .insert(OutboundRequestId::new("req0"), (0, peer));
+        assert_eq!(fetch.next_missing_chunk(), Some(1));
+
+        let chunk = |index, chunk: &'static [u8]| SnapshotResponse::Chunk {
+            height: Height::new(100),
+            format: 1,
+            index,
+            chunk: Bytes::from_static(chunk),
+        };
+
+        // Chunks for another index or empty are rejected
+        assert!(!fetch.add_chunk(1, chunk(2, b"data")));
+        assert!(!fetch.add_chunk(1, chunk(1, b"")));
+
+        assert!(fetch.add_chunk(1, chunk(1, b"data")));
+        assert!(fetch.add_chunk(2, chunk(2, b"data")));
+        assert!(!fetch.is_complete());
+
+        fetch.pending.clear();
+        assert_eq!(fetch.next_missing_chunk(), Some(0));
+        assert!(fetch.add_chunk(0, chunk(0, b"data")));
+        assert!(fetch.is_complete());
+        assert_eq!(fetch.next_missing_chunk(), None);
+    }
+}
@@ -1,12 +1,16 @@
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::collections::{BTreeMap, HashMap};
 use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
 
 use malachitebft_core_types::{Context, Height};
 use malachitebft_peer::PeerId;
 
 use crate::scoring::{ema, PeerScorer, Strategy};
-use crate::{Config, OutboundRequestId, Status};
+use crate::{
+    Backfill, Checkpoint, Config, NodeStatus, OutboundRequestId, PendingRequests, RawDecidedValue,
+    RequestAudit, ServeThrottle, SnapshotSync, Status, TraceId,
+};
 
 pub struct State<Ctx>
 where
@@ -31,13 +35,55 @@ where
     pub sync_height: Ctx::Height,
 
     /// The requested range of heights.
-    pub pending_requests: BTreeMap<OutboundRequestId, (RangeInclusive<Ctx::Height>, PeerId)>,
+    pub pending_requests: PendingRequests<Ctx>,
+
+    /// Deadlines of the pending requests which have not been answered yet,
+    /// after which their range is requested from another peer.
+    pub request_deadlines: BTreeMap<OutboundRequestId, Instant>,
+
+    /// Trace IDs attached to the value requests sent to the peers,
+    /// which their responses are expected to echo.
+    pub request_trace_ids: BTreeMap<OutboundRequestId, TraceId>,
 
     /// The set of peers we are connected to in order to get values, certificates and votes.
     pub peers: BTreeMap<PeerId, Status<Ctx>>,
 
     /// Peer scorer for scoring peers based on their performance.
     pub peer_scorer: PeerScorer,
+
+    /// History of the requests sent to and received from each peer.
+    pub audit: RequestAudit<Ctx>,
+
+    /// Limits on the requests served to the peers.
+    pub throttle: ServeThrottle,
+
+    /// Peers which rejected a request because they were serving too many requests,
+    /// with the time after which they can be sent requests again.
+    pub busy_peers: BTreeMap<PeerId, Instant>,
+
+    /// Estimated size of a value, averaged over the values received so far,
+    /// `None` until the first response arrives.
+    pub value_size_estimate: Option<usize>,
+
+    /// Requests for values are not sent while paused, eg. during maintenance of the node.
+    pub paused: bool,
+
+    /// Number of heights decided per second, averaged over the recent decisions,
+    /// `None` until two values have been decided.
+    pub decide_rate: Option<f64>,
+
+    /// Time at which the last value was decided
+    pub last_decided_at: Option<Instant>,
+
+    /// Progress of the restoration of a snapshot served by the peers
+    pub snapshot: SnapshotSync<Ctx>,
+
+    /// Progress of the fetching of the values below the height the node started from
+    pub backfill: Option<Backfill<Ctx>>,
+
+    /// Height below which the application prunes the decided values,
+    /// which are then neither served nor advertised to the peers
+    pub retain_height: Option<Ctx::Height>,
 }
 
 impl<Ctx> State<Ctx>
@@ -54,6 +100,8 @@ where
             Strategy::Ema => PeerScorer::new(ema::ExponentialMovingAverage::default()),
         };
 
+        let audit = RequestAudit::new(config.audit_window);
+
         Self {
             rng,
             config,
@@ -61,9 +109,21 @@ where
             consensus_height: Ctx::Height::ZERO,
             tip_height: Ctx::Height::ZERO,
             sync_height: Ctx::Height::ZERO,
-            pending_requests: BTreeMap::new(),
+            pending_requests: PendingRequests::new(),
+            request_deadlines: BTreeMap::new(),
+            request_trace_ids: BTreeMap::new(),
             peers: BTreeMap::new(),
             peer_scorer,
+            audit,
+            throttle: ServeThrottle::new(),
+            busy_peers: BTreeMap::new(),
+            value_size_estimate: None,
+            paused: false,
+            decide_rate: None,
+            last_decided_at: None,
+            snapshot: SnapshotSync::Idle,
+            backfill: None,
+            retain_height: None,
         }
     }
 
@@ -73,17 +133,117 @@ where
         max(1, self.config.parallel_requests)
     }
 
+    /// Number of pending requests counting against the maximum number of parallel requests.
+    ///
+    /// Answered requests whose values are waiting for consensus to verify and decide them
+    /// only count once there are more than `verification_queue_size` of them.
+    pub fn occupied_request_slots(&self) -> usize {
+        let unanswered = self
+            .pending_requests
+            .keys()
+            .filter(|request_id| self.request_deadlines.contains_key(request_id))
+            .count();
+
+        let answered = self.pending_requests.len() - unanswered;
+
+        unanswered + answered.saturating_sub(self.config.verification_queue_size)
+    }
+
+    /// Status of the local node, which is `Starting` until consensus starts its first height
+    pub fn node_status(&self) -> NodeStatus {
+        if self.started {
+            NodeStatus::Running
+        } else {
+            NodeStatus::Starting
+        }
+    }
+
+    /// Take a checkpoint of the sync progress, to resume from after a restart
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            tip_height: self.tip_height.as_u64(),
+            peer_scores: self
+                .peer_scorer
+                .get_scores()
+                .keys()
+                .map(|peer_id| (*peer_id, self.peer_scorer.get_score(peer_id)))
+                .collect(),
+        }
+    }
+
+    /// Resume from a checkpoint taken before a restart.
+    ///
+    /// The heights are only restored until consensus starts, which then sets them
+    /// from the values it actually has.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        self.tip_height = Ctx::Height::ZERO.increment_by(checkpoint.tip_height);
+        self.sync_height = self.tip_height.increment();
+
+        for (peer_id, score) in &checkpoint.peer_scores {
+            self.peer_scorer.set_score(*peer_id, *score);
+        }
+    }
+
     pub fn update_status(&mut self, status: Status<Ctx>) {
         self.peers.insert(status.peer_id, status);
     }
 
+    /// Record that a peer does not retain the value at the given height anymore,
+    /// until its next status update advertises its actual earliest retained height.
+    pub fn update_pruned_height(&mut self, peer_id: PeerId, height: Ctx::Height) {
+        if let Some(status) = self.peers.get_mut(&peer_id) {
+            status.history_min_height = max(status.history_min_height, height.increment());
+        }
+    }
+
+    /// Minimum height still needed by the local node, if it advertises it to its peers
+    pub fn min_needed_height(&self) -> Option<Ctx::Height> {
+        self.config
+            .advertise_min_needed_height
+            .then(|| self.tip_height.increment())
+    }
+
+    /// Raise the height below which the decided values are pruned,
+    /// returning whether it changed. The retain height never goes down.
+    pub fn set_retain_height(&mut self, height: Ctx::Height) -> bool {
+        if self
+            .retain_height
+            .is_some_and(|retain_height| retain_height >= height)
+        {
+            return false;
+        }
+
+        self.retain_height = Some(height);
+        true
+    }
+
+    /// Part of the given range whose values have not been pruned,
+    /// `None` if all of them have been
+    pub fn unpruned_range(
+        &self,
+        range: &RangeInclusive<Ctx::Height>,
+    ) -> Option<RangeInclusive<Ctx::Height>> {
+        match self.retain_height {
+            Some(retain_height) if *range.end() < retain_height => None,
+            Some(retain_height) => Some(max(*range.start(), retain_height)..=*range.end()),
+            None => Some(range.clone()),
+        }
+    }
+
+    /// Lowest height still needed by any of the connected peers, `None` if there are no peers.
+    ///
+    /// Values below the low watermark can be pruned without cutting off any syncing peer.
+    pub fn low_watermark(&self) -> Option<Ctx::Height> {
+        self.peers.values().map(Status::min_needed_height).min()
+    }
+
     pub fn update_request(
         &mut self,
         request_id: OutboundRequestId,
         peer_id: PeerId,
         range: RangeInclusive<Ctx::Height>,
     ) {
-        self.pending_requests.insert(request_id, (range, peer_id));
+        self.pending_requests.insert(request_id, range, peer_id);
     }
 
     /// Filter peers to only include those that can provide the given range of values, or at least a prefix of the range.
@@ -132,7 +292,12 @@ where
         except: Option<PeerId>,
     ) -> Option<(PeerId, RangeInclusive<Ctx::Height>)> {
         // Filtered peers together with the range of heights they can provide.
-        let peers_range = Self::filter_peers_by_range(&self.peers, range, except);
+        let mut peers_range = Self::filter_peers_by_range(&self.peers, range, except);
+
+        // Skip the peers which asked to retry later
+        let now = Instant::now();
+        self.busy_peers.retain(|_, retry_at| *retry_at > now);
+        peers_range.retain(|peer_id, _| !self.busy_peers.contains_key(peer_id));
 
         // Select a peer at random.
         let peer_ids = peers_range.keys().cloned().collect::<Vec<_>>();
@@ -157,24 +322,262 @@ where
     /// Assumes a height cannot be in multiple pending requests.
     pub fn get_request_id_by(&self, height: Ctx::Height) -> Option<(OutboundRequestId, PeerId)> {
         self.pending_requests
-            .iter()
-            .find(|(_, (range, _))| range.contains(&height))
-            .map(|(request_id, (_, stored_peer_id))| (request_id.clone(), *stored_peer_id))
+            .covering(height)
+            .map(|(request_id, peer_id)| (request_id.clone(), peer_id))
     }
 
     /// Return a new range of heights, trimming from the beginning any height
     /// that is validated by consensus.
     pub fn trim_validated_heights(
-        &mut self,
+        &self,
         range: &RangeInclusive<Ctx::Height>,
     ) -> RangeInclusive<Ctx::Height> {
         let start = max(self.tip_height.increment(), *range.start());
         start..=*range.end()
     }
 
+    /// Update the rate at which heights are decided with a decision at the given height.
+    ///
+    /// Must be called before the tip height is updated.
+    pub fn record_decided(&mut self, height: Ctx::Height, now: Instant) {
+        let last_decided_at = self.last_decided_at.replace(now);
+
+        let Some(last_decided_at) = last_decided_at else {
+            return;
+        };
+
+        let elapsed = now.duration_since(last_decided_at).as_secs_f64();
+        let decided = height.as_u64().saturating_sub(self.tip_height.as_u64());
+
+        if elapsed <= 0.0 || decided == 0 {
+            return;
+        }
+
+        let rate = decided as f64 / elapsed;
+
+        self.decide_rate = Some(match self.decide_rate {
+            Some(average) => 0.8 * average + 0.2 * rate,
+            None => rate,
+        });
+    }
+
+    /// Highest tip height advertised by the peers, if any
+    pub fn network_tip_height(&self) -> Option<Ctx::Height> {
+        self.peers.values().map(|status| status.tip_height).max()
+    }
+
+    /// Whether the given request is for values to backfill
+    pub fn is_backfill(&self, request_id: &OutboundRequestId) -> bool {
+        self.backfill
+            .as_ref()
+            .is_some_and(|backfill| backfill.pending.contains_key(request_id))
+    }
+
+    /// Whether the response to the given request echoes a trace ID other than the one
+    /// sent with the request. Peers which do not echo trace IDs send a zero trace ID.
+    pub fn is_trace_id_mismatch(&self, request_id: &OutboundRequestId, trace_id: TraceId) -> bool {
+        trace_id != TraceId::default()
+            && self
+                .request_trace_ids
+                .get(request_id)
+                .is_some_and(|expected| *expected != trace_id)
+    }
+
+    /// Whether the node is done syncing up to the tip height advertised by the peers,
+    /// in which case values below the height it started from can be backfilled
+    pub fn is_caught_up(&self) -> bool {
+        !self.paused
+            && !self.snapshot.is_active()
+            && self.pending_requests.is_empty()
+            && self
+                .network_tip_height()
+                .is_none_or(|network_tip_height| self.tip_height >= network_tip_height)
+    }
+
+    /// Estimated time until the tip height advertised by the peers is decided,
+    /// `None` if there are no peers or the rate at which heights are decided is not known yet.
+    pub fn estimated_time_to_tip(&self) -> Option<Duration> {
+        let network_tip_height = self.network_tip_height()?;
+
+        let remaining = network_tip_height
+            .as_u64()
+            .saturating_sub(self.tip_height.as_u64());
+
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+
+        self.decide_rate
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| Duration::from_secs_f64(remaining as f64 / rate))
+    }
+
+    /// Ranges of heights above the tip requested from the peers, merged and sorted
+    pub fn covered_ranges(&self) -> Vec<RangeInclusive<Ctx::Height>> {
+        let mut ranges = self
+            .pending_requests
+            .values()
+            .filter(|(range, _)| *range.end() > self.tip_height)
+            .map(|(range, _)| self.trim_validated_heights(range))
+            .collect::<Vec<_>>();
+
+        ranges.sort_by_key(|range| *range.start());
+
+        let mut merged: Vec<RangeInclusive<Ctx::Height>> = Vec::with_capacity(ranges.len());
+
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if *range.start() <= last.end().increment() => {
+                    if range.end() > last.end() {
+                        *last = *last.start()..=*range.end();
+                    }
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        merged
+    }
+
+    /// Ranges of heights between the tip and the highest tip of the peers
+    /// which have not been requested from any peer, sorted
+    pub fn uncovered_ranges(&self) -> Vec<RangeInclusive<Ctx::Height>> {
+        let Some(network_tip_height) = self.network_tip_height() else {
+            return Vec::new();
+        };
+
+        let mut uncovered = Vec::new();
+        let mut next_height = self.tip_height.increment();
+
+        for range in self.covered_ranges() {
+            if next_height > network_tip_height {
+                break;
+            }
+
+            if *range.start() > next_height {
+                let end = min(range.start().decrement().unwrap(), network_tip_height);
+                uncovered.push(next_height..=end);
+            }
+
+            next_height = max(next_height, range.end().increment());
+        }
+
+        if next_height <= network_tip_height {
+            uncovered.push(next_height..=network_tip_height);
+        }
+
+        uncovered
+    }
+
+    /// Number of heights covered by the pending requests and number of heights
+    /// left uncovered below the highest tip of the peers
+    pub fn coverage(&self) -> (u64, u64) {
+        fn count<H: Height>(ranges: &[RangeInclusive<H>]) -> u64 {
+            ranges
+                .iter()
+                .map(|range| range.end().as_u64() - range.start().as_u64() + 1)
+                .sum()
+        }
+
+        (
+            count(&self.covered_ranges()),
+            count(&self.uncovered_ranges()),
+        )
+    }
+
+    /// Ranges of heights requested from each peer and not decided yet, sorted
+    pub fn outstanding_ranges_by_peer(&self) -> BTreeMap<PeerId, Vec<RangeInclusive<Ctx::Height>>> {
+        let mut outstanding = BTreeMap::<_, Vec<_>>::new();
+
+        for (range, peer_id) in self.pending_requests.values() {
+            if *range.end() > self.tip_height {
+                outstanding
+                    .entry(*peer_id)
+                    .or_default()
+                    .push(self.trim_validated_heights(range));
+            }
+        }
+
+        for ranges in outstanding.values_mut() {
+            ranges.sort_by_key(|range| *range.start());
+        }
+
+        outstanding
+    }
+
+    /// Update the estimated size of a value with the sizes of the values of a response
+    pub fn record_value_sizes(&mut self, values: &[RawDecidedValue<Ctx>]) {
+        if values.is_empty() {
+            return;
+        }
+
+        let total: usize = values.iter().map(|value| value.value_bytes.len()).sum();
+        let mean = total / values.len();
+
+        self.value_size_estimate = Some(match self.value_size_estimate {
+            Some(estimate) => (estimate + mean) / 2,
+            None => mean,
+        });
+    }
+
+    /// Estimated size of the values of the requests not answered yet.
+    ///
+    /// Until the size of the values can be estimated, each request is assumed to be answered
+    /// with a response of the maximum size.
+    pub fn in_flight_bytes(&self) -> usize {
+        self.pending_requests
+            .values()
+            .map(|(range, _)| match self.value_size_estimate {
+                Some(value_size) => {
+                    let len = range.end().as_u64() - range.start().as_u64() + 1;
+                    (len as usize).saturating_mul(value_size)
+                }
+                None => self.config.max_response_size,
+            })
+            .fold(0, usize::saturating_add)
+    }
+
+    /// Whether the in-flight byte budget is used up. A single request can always be in flight,
+    /// however large its values are expected to be.
+    pub fn is_in_flight_budget_exceeded(&self) -> bool {
+        !self.pending_requests.is_empty()
+            && self
+                .config
+                .max_in_flight_bytes
+                .is_some_and(|max_bytes| self.in_flight_bytes() >= max_bytes)
+    }
+
+    /// Remove the deadlines and trace IDs of the pending requests that have been removed,
+    /// and return the requests that have not been answered before their deadline.
+    pub fn expired_requests(
+        &mut self,
+        now: Instant,
+    ) -> Vec<(OutboundRequestId, RangeInclusive<Ctx::Height>, PeerId)> {
+        let pending_requests = &self.pending_requests;
+
+        self.request_deadlines
+            .retain(|request_id, _| pending_requests.contains_key(request_id));
+
+        let backfill = &self.backfill;
+        self.request_trace_ids.retain(|request_id, _| {
+            pending_requests.contains_key(request_id)
+                || backfill
+                    .as_ref()
+                    .is_some_and(|backfill| backfill.pending.contains_key(request_id))
+        });
+
+        self.request_deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .filter_map(|(request_id, _)| {
+                let (range, peer_id) = pending_requests.get(request_id)?;
+                Some((request_id.clone(), range.clone(), *peer_id))
+            })
+            .collect()
+    }
+
     /// Remove pending requests that are for heights that have already been validated by consensus.
     pub fn prune_pending_requests(&mut self) {
-        self.pending_requests
-            .retain(|_, (range, _)| range.end() > &self.tip_height);
+        self.pending_requests.remove_requests_up_to(self.tip_height);
     }
 }
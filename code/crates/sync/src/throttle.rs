@@ -0,0 +1,184 @@
+//! Limits on the sync requests served to the peers, so that a single aggressive syncer
+//! cannot saturate the disk and bandwidth of the node.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use malachitebft_peer::PeerId;
+
+use crate::{Config, InboundRequestId};
+
+/// Delay suggested to the peers whose request was rejected because too many requests
+/// were being served at the time
+pub const BUSY_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Tracks the requests being served and the bytes served recently
+pub struct ServeThrottle {
+    /// Requests being served, with the time they were received at
+    in_flight: HashMap<InboundRequestId, (PeerId, Instant)>,
+
+    /// Bytes which can still be served without exceeding the byte rate,
+    /// negative if the last responses went over it
+    byte_budget: f64,
+
+    /// Last time the byte budget was refilled
+    refilled_at: Instant,
+}
+
+impl ServeThrottle {
+    pub fn new() -> Self {
+        Self {
+            in_flight: HashMap::new(),
+            // Capped to one second worth of bytes on the first refill
+            byte_budget: f64::INFINITY,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Number of requests being served
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Start serving a request from a peer unless one of the limits is reached,
+    /// in which case the delay after which the peer should retry is returned.
+    pub fn try_serve(
+        &mut self,
+        config: &Config,
+        request_id: InboundRequestId,
+        peer_id: PeerId,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        // The requests the host never answered have timed out on the side of the peer by now
+        self.in_flight.retain(|_, (_, received_at)| {
+            now.duration_since(*received_at) < config.request_timeout
+        });
+
+        if config
+            .max_inbound_requests
+            .is_some_and(|max| self.in_flight.len() >= max)
+        {
+            return Err(BUSY_RETRY_AFTER);
+        }
+
+        if let Some(max) = config.max_inbound_requests_per_peer {
+            let from_peer = self
+                .in_flight
+                .values()
+                .filter(|(peer, _)| *peer == peer_id)
+                .count();
+
+            if from_peer >= max {
+                return Err(BUSY_RETRY_AFTER);
+            }
+        }
+
+        if let Some(bytes_per_sec) = config.max_served_bytes_per_sec {
+            self.refill(bytes_per_sec, now);
+
+            if self.byte_budget <= 0.0 {
+                let secs = -self.byte_budget / bytes_per_sec.max(1) as f64;
+                return Err(Duration::from_secs_f64(secs).max(Duration::from_millis(100)));
+            }
+        }
+
+        self.in_flight.insert(request_id, (peer_id, now));
+
+        Ok(())
+    }
+
+    /// Record that a response of the given size has been sent for a request
+    pub fn served(&mut self, request_id: &InboundRequestId, bytes: usize) {
+        self.in_flight.remove(request_id);
+        self.byte_budget -= bytes as f64;
+    }
+
+    /// Refill the byte budget with the bytes allowed since the last refill,
+    /// up to one second worth of bytes
+    fn refill(&mut self, bytes_per_sec: usize, now: Instant) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        let max_budget = bytes_per_sec as f64;
+
+        self.byte_budget = (self.byte_budget + elapsed * max_budget).min(max_budget);
+        self.refilled_at = now;
+    }
+}
+
+impl Default for ServeThrottle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_id(id: &str) -> InboundRequestId {
+        InboundRequestId::new(id)
+    }
+
+    #[test]
+    fn concurrency_limits() {
+        let config = Config {
+            max_inbound_requests: Some(3),
+            max_inbound_requests_per_peer: Some(2),
+            ..Default::default()
+        };
+
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let now = Instant::now();
+        let mut throttle = ServeThrottle::new();
+
+        assert!(throttle.try_serve(&config, request_id("1"), a, now).is_ok());
+        assert!(throttle.try_serve(&config, request_id("2"), a, now).is_ok());
+        assert_eq!(
+            throttle.try_serve(&config, request_id("3"), a, now),
+            Err(BUSY_RETRY_AFTER)
+        );
+
+        assert!(throttle.try_serve(&config, request_id("4"), b, now).is_ok());
+        assert_eq!(
+            throttle.try_serve(&config, request_id("5"), b, now),
+            Err(BUSY_RETRY_AFTER)
+        );
+
+        throttle.served(&request_id("1"), 0);
+        assert!(throttle.try_serve(&config, request_id("6"), b, now).is_ok());
+
+        // Requests never answered by the host are dropped once they time out
+        let later = now + config.request_timeout;
+        assert!(throttle
+            .try_serve(&config, request_id("7"), a, later)
+            .is_ok());
+        assert_eq!(throttle.in_flight(), 1);
+    }
+
+    #[test]
+    fn byte_rate_limit() {
+        let config = Config {
+            max_served_bytes_per_sec: Some(1000),
+            ..Default::default()
+        };
+
+        let peer = PeerId::random();
+        let now = Instant::now();
+        let mut throttle = ServeThrottle::new();
+
+        assert!(throttle
+            .try_serve(&config, request_id("1"), peer, now)
+            .is_ok());
+        throttle.served(&request_id("1"), 3000);
+
+        // Two seconds worth of bytes over the limit
+        assert_eq!(
+            throttle.try_serve(&config, request_id("2"), peer, now),
+            Err(Duration::from_secs(2))
+        );
+
+        let later = now + Duration::from_millis(2500);
+        assert!(throttle
+            .try_serve(&config, request_id("2"), peer, later)
+            .is_ok());
+    }
+}
@@ -1,4 +1,5 @@
-use std::{ops::RangeInclusive, sync::Arc};
+use std::time::Duration;
+use std::{fmt, ops::RangeInclusive, sync::Arc};
 
 use bytes::Bytes;
 use derive_where::derive_where;
@@ -7,7 +8,7 @@ use libp2p::request_response;
 use serde::{Deserialize, Serialize};
 
 use malachitebft_core_types::ValueResponse as CoreValueResponse;
-use malachitebft_core_types::{CommitCertificate, Context, Height};
+use malachitebft_core_types::{CommitCertificate, Context, Height, Round, SignedVote};
 
 pub use malachitebft_peer::PeerId;
 
@@ -39,6 +40,31 @@ impl HeightStartType {
     }
 }
 
+/// Status of the local node, as far as value sync is concerned
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NodeStatus {
+    /// Consensus has not started its first height yet, eg. because it is still replaying
+    /// the WAL or the application is still verifying its store. The tip height is not known
+    /// yet, so no status is advertised to peers and no values are served.
+    Starting,
+
+    /// Consensus is running, the tip height is advertised and values are served to peers
+    Running,
+}
+
+impl NodeStatus {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Starting => "starting",
+            Self::Running => "running",
+        }
+    }
+
+    pub const fn is_starting(&self) -> bool {
+        matches!(self, Self::Starting)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Display)]
 #[displaydoc("{0}")]
 pub struct InboundRequestId(Arc<str>);
@@ -59,6 +85,25 @@ impl OutboundRequestId {
     }
 }
 
+/// Identifier attached to a sync request and echoed in the corresponding response,
+/// to correlate the log lines of both peers when debugging a failed fetch.
+///
+/// A zero trace ID means that the peer did not provide one.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TraceId(pub u64);
+
+impl TraceId {
+    pub fn random(rng: &mut dyn rand::RngCore) -> Self {
+        Self(rng.next_u64())
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
 pub type ResponseChannel = request_response::ResponseChannel<RawResponse>;
 
 #[derive_where(Clone, Debug, PartialEq, Eq)]
@@ -66,26 +111,65 @@ pub struct Status<Ctx: Context> {
     pub peer_id: PeerId,
     pub tip_height: Ctx::Height,
     pub history_min_height: Ctx::Height,
+    /// Minimum height still needed by the peer, if it advertises it
+    pub min_needed_height: Option<Ctx::Height>,
+}
+
+impl<Ctx: Context> Status<Ctx> {
+    /// Minimum height still needed by the peer.
+    ///
+    /// Peers which do not advertise it are assumed to need every height above their tip.
+    pub fn min_needed_height(&self) -> Ctx::Height {
+        self.min_needed_height
+            .unwrap_or_else(|| self.tip_height.increment())
+    }
 }
 
 #[derive_where(Clone, Debug, PartialEq, Eq)]
 pub enum Request<Ctx: Context> {
     ValueRequest(ValueRequest<Ctx>),
+    SnapshotRequest(SnapshotRequest<Ctx>),
+    ProposalPartsRequest(ProposalPartsRequest<Ctx>),
+    VoteSetRequest(VoteSetRequest<Ctx>),
 }
 
 #[derive_where(Clone, Debug, PartialEq, Eq)]
 pub enum Response<Ctx: Context> {
     ValueResponse(ValueResponse<Ctx>),
+    SnapshotResponse(SnapshotResponse<Ctx>),
+    ProposalPartsResponse(ProposalPartsResponse<Ctx>),
+    VoteSetResponse(VoteSetResponse<Ctx>),
 }
 
 #[derive_where(Clone, Debug, PartialEq, Eq)]
 pub struct ValueRequest<Ctx: Context> {
     pub range: RangeInclusive<Ctx::Height>,
+
+    /// Trace ID of the request, echoed in the response
+    pub trace_id: TraceId,
+
+    /// Request only the commit certificates, the values in the response being left empty
+    pub certificates_only: bool,
 }
 
 impl<Ctx: Context> ValueRequest<Ctx> {
     pub fn new(range: RangeInclusive<Ctx::Height>) -> Self {
-        Self { range }
+        Self {
+            range,
+            trace_id: TraceId::default(),
+            certificates_only: false,
+        }
+    }
+
+    pub fn with_trace_id(self, trace_id: TraceId) -> Self {
+        Self { trace_id, ..self }
+    }
+
+    pub fn with_certificates_only(self, certificates_only: bool) -> Self {
+        Self {
+            certificates_only,
+            ..self
+        }
     }
 }
 
@@ -96,6 +180,13 @@ pub struct ValueResponse<Ctx: Context> {
 
     /// Values are sequentially ordered by height.
     pub values: Vec<RawDecidedValue<Ctx>>,
+
+    /// Trace ID of the request this is a response to
+    pub trace_id: TraceId,
+
+    /// Set when the request was rejected because the peer is serving too many requests,
+    /// to the delay after which it can be sent again
+    pub retry_after: Option<Duration>,
 }
 
 impl<Ctx: Context> ValueResponse<Ctx> {
@@ -103,6 +194,19 @@ impl<Ctx: Context> ValueResponse<Ctx> {
         Self {
             start_height,
             values,
+            trace_id: TraceId::default(),
+            retry_after: None,
+        }
+    }
+
+    pub fn with_trace_id(self, trace_id: TraceId) -> Self {
+        Self { trace_id, ..self }
+    }
+
+    pub fn with_retry_after(self, retry_after: Duration) -> Self {
+        Self {
+            retry_after: Some(retry_after),
+            ..self
         }
     }
 
@@ -115,6 +219,129 @@ impl<Ctx: Context> ValueResponse<Ctx> {
     }
 }
 
+/// A snapshot of the application state at a given height, from which a node can restore
+/// its state instead of syncing every value from genesis
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot<Ctx: Context> {
+    /// Height of the last value applied to the state in the snapshot
+    pub height: Ctx::Height,
+
+    /// Format of the snapshot, defined by the application
+    pub format: u32,
+
+    /// Number of chunks the snapshot is split into
+    pub chunks: u32,
+
+    /// Hash of the snapshot, defined and checked by the application
+    pub hash: Bytes,
+}
+
+impl<Ctx: Context> Snapshot<Ctx> {
+    pub fn new(height: Ctx::Height, format: u32, chunks: u32, hash: Bytes) -> Self {
+        Self {
+            height,
+            format,
+            chunks,
+            hash,
+        }
+    }
+}
+
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotRequest<Ctx: Context> {
+    /// Request the snapshots the peer can serve
+    List,
+
+    /// Request a chunk of a snapshot
+    Chunk {
+        height: Ctx::Height,
+        format: u32,
+        index: u32,
+    },
+}
+
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotResponse<Ctx: Context> {
+    /// The snapshots the peer can serve
+    List(Vec<Snapshot<Ctx>>),
+
+    /// A chunk of a snapshot, empty if the peer does not have it
+    Chunk {
+        height: Ctx::Height,
+        format: u32,
+        index: u32,
+        chunk: Bytes,
+    },
+}
+
+/// Request for the parts of a proposal streamed during the current height, which this node
+/// missed on the proposal parts channel, so that it can recover within the round instead of
+/// waiting for a round change or for the decided value to be synced
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub struct ProposalPartsRequest<Ctx: Context> {
+    /// Height being decided by the requesting node
+    pub height: Ctx::Height,
+
+    /// Round being decided by the requesting node
+    pub round: Round,
+
+    /// Peer which streamed the proposal, streams being identified by their sender
+    pub proposer: PeerId,
+
+    /// Identifier of the stream, as chosen by the proposer
+    pub stream_id: Bytes,
+
+    /// Sequence numbers of the missing parts
+    pub sequences: Vec<u64>,
+}
+
+/// The requested proposal parts the peer has, if it is deciding the same height
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub struct ProposalPartsResponse<Ctx: Context> {
+    pub height: Ctx::Height,
+
+    /// The parts, as the stream messages published on the proposal parts channel
+    pub parts: Vec<Bytes>,
+}
+
+impl<Ctx: Context> ProposalPartsResponse<Ctx> {
+    pub fn new(height: Ctx::Height, parts: Vec<Bytes>) -> Self {
+        Self { height, parts }
+    }
+}
+
+/// Request for the votes of a round of the height being decided, by a node stuck in that round
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub struct VoteSetRequest<Ctx: Context> {
+    pub height: Ctx::Height,
+    pub round: Round,
+}
+
+impl<Ctx: Context> VoteSetRequest<Ctx> {
+    pub fn new(height: Ctx::Height, round: Round) -> Self {
+        Self { height, round }
+    }
+}
+
+/// The prevotes and precommits of the requested round the peer received or cast,
+/// if it is deciding the same height
+#[derive_where(Clone, Debug, PartialEq, Eq)]
+pub struct VoteSetResponse<Ctx: Context> {
+    pub height: Ctx::Height,
+    pub round: Round,
+    pub votes: Vec<SignedVote<Ctx>>,
+}
+
+impl<Ctx: Context> VoteSetResponse<Ctx> {
+    pub fn new(height: Ctx::Height, round: Round, votes: Vec<SignedVote<Ctx>>) -> Self {
+        Self {
+            height,
+            round,
+            votes,
+        }
+    }
+}
+
 #[derive_where(Clone, Debug, PartialEq, Eq)]
 pub struct RawDecidedValue<Ctx: Context> {
     pub value_bytes: Bytes,
@@ -176,7 +176,7 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
 
                 // Now what's left to do is to break down the value to propose into parts,
                 // and send those parts over the network to our peers, for them to re-assemble the full value.
-                for stream_message in state.stream_proposal(proposal, pol_round) {
+                for stream_message in state.stream_proposal(proposal, pol_round)? {
                     debug!(%height, %round, "Streaming proposal part: {stream_message:?}");
 
                     channels
@@ -214,6 +214,7 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
             AppMsg::Decided {
                 certificate,
                 extensions: _,
+                proof: _,
             } => {
                 assert!(!certificate.commit_signatures.is_empty());
 
@@ -397,8 +398,9 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
                         value: proposal.value,
                     };
 
-                    for stream_message in state.stream_proposal(locally_proposed_value, valid_round)
-                    {
+                    let stream = state.stream_proposal(locally_proposed_value, valid_round)?;
+
+                    for stream_message in stream {
                         debug!(%height, %valid_round, "Publishing proposal part: {stream_message:?}");
 
                         channels
@@ -420,6 +422,33 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
                     error!("Failed to send VerifyVoteExtension reply");
                 }
             }
+
+            // This application does not take snapshots of its state,
+            // so it has none to offer to the peers which are lagging behind.
+            AppMsg::GetSnapshots { reply } => {
+                if reply.send(Vec::new()).is_err() {
+                    error!("Failed to send GetSnapshots reply");
+                }
+            }
+
+            AppMsg::GetSnapshotChunk { reply, .. } => {
+                if reply.send(None).is_err() {
+                    error!("Failed to send GetSnapshotChunk reply");
+                }
+            }
+
+            AppMsg::RestoreSnapshot { reply, .. } => {
+                if reply.send(None).is_err() {
+                    error!("Failed to send RestoreSnapshot reply");
+                }
+            }
+
+            // Nor does it restore snapshots, so it is never asked to store backfilled values.
+            AppMsg::StoreBackfilledValues { reply, .. } => {
+                if reply.send(false).is_err() {
+                    error!("Failed to send StoreBackfilledValues reply");
+                }
+            }
         }
     }
 
@@ -0,0 +1,79 @@
+//! Run a node of the test application as a separate process, for the test framework.
+//!
+//! Usage: `malachitebft-test-node <path to node spec>`
+//!
+//! See [`arc_malachitebft_test_app::process`] for how the node talks to the test framework.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use eyre::{eyre, Result};
+use tokio::io::AsyncReadExt;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, info, warn};
+
+use arc_malachitebft_test_app::process::{encode_event, NodeSpec};
+use malachitebft_test::node::{Node, NodeHandle};
+use malachitebft_test_cli::{logging, runtime};
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    let path = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .ok_or_else(|| eyre!("Usage: malachitebft-test-node <path to node spec>"))?;
+
+    let spec = NodeSpec::read(&path)?;
+
+    // This is a drop guard responsible for flushing any remaining logs and traces when the program terminates.
+    // It must be assigned to a binding that is not _, as _ will result in the guard being dropped immediately.
+    let _guard = logging::init_with_telemetry(
+        spec.config.logging.log_level,
+        spec.config.logging.log_format,
+        &spec.config.telemetry,
+        &spec.config.moniker,
+    );
+
+    let rt = runtime::build_runtime(spec.config.runtime)?;
+
+    rt.block_on(run(spec))
+}
+
+async fn run(spec: NodeSpec) -> Result<()> {
+    info!(moniker = %spec.config.moniker, "Starting node process");
+
+    let handle = spec.into_app().start().await?;
+    let mut rx_event = handle.subscribe();
+
+    // The test framework closes our standard input to shut the node down
+    let mut stdin_closed = tokio::spawn(async {
+        let mut stdin = tokio::io::stdin();
+        let mut buf = [0; 64];
+        while matches!(stdin.read(&mut buf).await, Ok(n) if n > 0) {}
+    });
+
+    loop {
+        tokio::select! {
+            _ = &mut stdin_closed => break,
+
+            event = rx_event.recv() => match event {
+                Ok(event) => match encode_event(&event) {
+                    Ok(Some(line)) => {
+                        let mut stdout = std::io::stdout().lock();
+                        writeln!(stdout, "{line}")?;
+                        stdout.flush()?;
+                    }
+                    Ok(None) => (),
+                    Err(e) => error!("Failed to encode event {event}: {e}"),
+                },
+                Err(RecvError::Lagged(n)) => warn!("Dropped {n} events"),
+                Err(RecvError::Closed) => break,
+            },
+        }
+    }
+
+    info!("Shutting down node process");
+
+    handle.shutdown().await
+}
@@ -5,8 +5,8 @@ use serde::{Deserialize, Serialize};
 use malachitebft_app_channel::app::config::NodeConfig;
 
 pub use malachitebft_app_channel::app::config::{
-    ConsensusConfig, LogFormat, LogLevel, LoggingConfig, MetricsConfig, RuntimeConfig, TestConfig,
-    ValueSyncConfig,
+    ConsensusConfig, LogFormat, LogLevel, LoggingConfig, MetricsConfig, RuntimeConfig,
+    TelemetryConfig, TestConfig, ValueSyncConfig,
 };
 
 /// Malachite configuration options
@@ -27,6 +27,10 @@ pub struct Config {
     /// Metrics configuration options
     pub metrics: MetricsConfig,
 
+    /// Trace export configuration options
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+
     /// Runtime configuration options
     pub runtime: RuntimeConfig,
 
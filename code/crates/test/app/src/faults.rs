@@ -0,0 +1,469 @@
+//! Byzantine behaviors injected in the messages a node publishes, for testing
+//! how the other nodes cope with a faulty validator.
+//!
+//! The faults are applied by a [`FaultyNetwork`] actor standing in front of the network actor
+//! of the node, which changes, drops, delays or duplicates the messages published by consensus
+//! and by the application, signing the messages it makes up with the key of the node.
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use eyre::eyre;
+use ractor::{Actor, ActorProcessingErr, ActorRef};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use malachitebft_app_channel::app::consensus::SignedConsensusMsg;
+use malachitebft_app_channel::app::engine::network::{
+    Msg as NetworkActorMsg, NetworkEvent, NetworkRef,
+};
+use malachitebft_app_channel::app::metrics::SharedRegistry;
+use malachitebft_app_channel::app::spawn::spawn_network_actor;
+use malachitebft_app_channel::app::streaming::{StreamContent, StreamId, StreamMessage};
+use malachitebft_app_channel::app::types::core::{
+    NilOrVal, Round, SignedProposal, SignedVote, VoteType,
+};
+use malachitebft_app_channel::{NetworkIdentity, NetworkMsg};
+use malachitebft_test::codec::json::JsonCodec;
+use malachitebft_test::{
+    Ed25519Provider, Height, Proposal, ProposalFin, ProposalPart, TestContext, Value, ValueId, Vote,
+};
+
+use crate::config::Config;
+
+/// A Byzantine behavior of a node
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Misbehavior {
+    /// Publish a second proposal for a different value along with each proposal of the node
+    DoublePropose,
+    /// Forget the value the node is locked on, prevoting for the value proposed
+    /// in a later round instead of nil
+    Amnesia,
+    /// Do not publish the votes of the given type, or of any type if `None`
+    WithholdVotes(Option<VoteType>),
+    /// Publish the proposals of the node after the given delay
+    DelayProposal(Duration),
+    /// Sign the proposal parts with a wrong signature, making the value they carry invalid
+    InvalidProposalParts,
+}
+
+/// A misbehavior of a node, along with the heights and rounds in which it misbehaves
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub misbehavior: Misbehavior,
+    /// Heights in which the node misbehaves, all of them if `None`
+    pub heights: Option<RangeInclusive<u64>>,
+    /// Rounds in which the node misbehaves, all of them if `None`
+    pub rounds: Option<RangeInclusive<u32>>,
+}
+
+impl Fault {
+    pub fn new(misbehavior: Misbehavior) -> Self {
+        Self {
+            misbehavior,
+            heights: None,
+            rounds: None,
+        }
+    }
+
+    pub fn double_propose() -> Self {
+        Self::new(Misbehavior::DoublePropose)
+    }
+
+    pub fn amnesia() -> Self {
+        Self::new(Misbehavior::Amnesia)
+    }
+
+    pub fn withhold_votes() -> Self {
+        Self::new(Misbehavior::WithholdVotes(None))
+    }
+
+    pub fn withhold_prevotes() -> Self {
+        Self::new(Misbehavior::WithholdVotes(Some(VoteType::Prevote)))
+    }
+
+    pub fn withhold_precommits() -> Self {
+        Self::new(Misbehavior::WithholdVotes(Some(VoteType::Precommit)))
+    }
+
+    pub fn delay_proposal(delay: Duration) -> Self {
+        Self::new(Misbehavior::DelayProposal(delay))
+    }
+
+    pub fn invalid_proposal_parts() -> Self {
+        Self::new(Misbehavior::InvalidProposalParts)
+    }
+
+    pub fn at_height(self, height: u64) -> Self {
+        self.heights(height..=height)
+    }
+
+    pub fn heights(mut self, heights: RangeInclusive<u64>) -> Self {
+        self.heights = Some(heights);
+        self
+    }
+
+    pub fn at_round(self, round: u32) -> Self {
+        self.rounds(round..=round)
+    }
+
+    pub fn rounds(mut self, rounds: RangeInclusive<u32>) -> Self {
+        self.rounds = Some(rounds);
+        self
+    }
+
+    /// Whether the fault is active in the given height and round
+    pub fn is_active(&self, height: Height, round: Round) -> bool {
+        let in_heights = self
+            .heights
+            .as_ref()
+            .is_none_or(|heights| heights.contains(&height.as_u64()));
+
+        let in_rounds = self
+            .rounds
+            .as_ref()
+            .is_none_or(|rounds| round.as_u32().is_some_and(|round| rounds.contains(&round)));
+
+        in_heights && in_rounds
+    }
+}
+
+/// Spawn the network actor of a node along with a [`FaultyNetwork`] in front of it,
+/// returning the latter and the channel through which the application sends it messages
+pub async fn spawn_faulty_network(
+    config: &Config,
+    identity: NetworkIdentity,
+    faults: Vec<Fault>,
+    signer: Ed25519Provider,
+) -> eyre::Result<(
+    NetworkRef<TestContext>,
+    mpsc::Sender<NetworkMsg<TestContext>>,
+)> {
+    let registry = SharedRegistry::global().with_moniker(&config.moniker);
+
+    let network = spawn_network_actor(
+        &config.consensus,
+        &config.value_sync,
+        identity,
+        &registry,
+        JsonCodec,
+        Default::default(),
+    )
+    .await?;
+
+    let proposals = Proposals::default();
+
+    let (observer, _) = Actor::spawn(None, ProposalObserver, Arc::clone(&proposals)).await?;
+
+    network
+        .cast(NetworkActorMsg::Subscribe(Box::new(observer.clone())))
+        .map_err(|_| eyre!("Network actor has stopped"))?;
+
+    let args = Args {
+        network,
+        observer,
+        proposals,
+    };
+
+    let (faulty, _) = Actor::spawn(None, FaultyNetwork { faults, signer }, args).await?;
+
+    let (tx_network, mut rx_network) = mpsc::channel::<NetworkMsg<TestContext>>(1);
+
+    tokio::spawn({
+        let faulty = faulty.clone();
+
+        async move {
+            while let Some(msg) = rx_network.recv().await {
+                if faulty.cast(msg.into()).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok((faulty, tx_network))
+}
+
+/// Values proposed by the peers, by height and round
+type Proposals = Arc<Mutex<HashMap<(Height, Round), ValueId>>>;
+
+/// Records the values proposed by the peers of the node
+struct ProposalObserver;
+
+struct Observed(NetworkEvent<TestContext>);
+
+impl From<NetworkEvent<TestContext>> for Observed {
+    fn from(event: NetworkEvent<TestContext>) -> Self {
+        Self(event)
+    }
+}
+
+#[async_trait]
+impl Actor for ProposalObserver {
+    type Msg = Observed;
+    type State = Proposals;
+    type Arguments = Proposals;
+
+    async fn pre_start(
+        &self,
+        _myself: ActorRef<Observed>,
+        proposals: Proposals,
+    ) -> Result<Proposals, ActorProcessingErr> {
+        Ok(proposals)
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Observed>,
+        Observed(event): Observed,
+        proposals: &mut Proposals,
+    ) -> Result<(), ActorProcessingErr> {
+        if let NetworkEvent::Proposal(_, proposal) = event {
+            let proposal = &proposal.message;
+
+            proposals
+                .lock()
+                .unwrap()
+                .insert((proposal.height, proposal.round), proposal.value.id());
+        }
+
+        Ok(())
+    }
+}
+
+/// Stands in front of the network actor of a node, applying the faults
+/// to the messages published by the node and forwarding the others as they are
+pub struct FaultyNetwork {
+    faults: Vec<Fault>,
+    signer: Ed25519Provider,
+}
+
+pub struct Args {
+    network: NetworkRef<TestContext>,
+    observer: ActorRef<Observed>,
+    proposals: Proposals,
+}
+
+pub struct State {
+    network: NetworkRef<TestContext>,
+    proposals: Proposals,
+    /// Value the node precommitted for at each height, ie. the value it is locked on
+    locks: HashMap<Height, ValueId>,
+    /// Height and round of the streams of proposal parts published by the node
+    streams: HashMap<StreamId, (Height, Round)>,
+}
+
+impl FaultyNetwork {
+    fn is_active(&self, height: Height, round: Round, f: impl Fn(&Misbehavior) -> bool) -> bool {
+        self.faults
+            .iter()
+            .any(|fault| f(&fault.misbehavior) && fault.is_active(height, round))
+    }
+
+    fn publish(
+        state: &State,
+        msg: SignedConsensusMsg<TestContext>,
+    ) -> Result<(), ActorProcessingErr> {
+        state
+            .network
+            .cast(NetworkActorMsg::PublishConsensusMsg(msg))
+            .map_err(|_| eyre!("Network actor has stopped").into())
+    }
+
+    fn on_vote(
+        &self,
+        state: &mut State,
+        vote: SignedVote<TestContext>,
+    ) -> Result<(), ActorProcessingErr> {
+        let (height, round) = (vote.message.height, vote.message.round);
+
+        if let (VoteType::Precommit, NilOrVal::Val(value_id)) =
+            (vote.message.typ, &vote.message.value)
+        {
+            state.locks.insert(height, *value_id);
+        }
+
+        let withheld = self.is_active(height, round, |m| {
+            matches!(m, Misbehavior::WithholdVotes(typ) if typ.is_none_or(|typ| typ == vote.message.typ))
+        });
+
+        if withheld {
+            warn!(%height, %round, typ = ?vote.message.typ, "Faulty node withholds its vote");
+            return Ok(());
+        }
+
+        let amnesia = vote.message.typ == VoteType::Prevote
+            && vote.message.value == NilOrVal::Nil
+            && self.is_active(height, round, |m| *m == Misbehavior::Amnesia);
+
+        if amnesia {
+            let locked = state.locks.get(&height).copied();
+            let proposed = state
+                .proposals
+                .lock()
+                .unwrap()
+                .get(&(height, round))
+                .copied();
+
+            if let (Some(locked), Some(proposed)) = (locked, proposed) {
+                if locked != proposed {
+                    warn!(%height, %round, %locked, %proposed, "Faulty node forgets its lock");
+
+                    let prevote = Vote::new_prevote(
+                        height,
+                        round,
+                        NilOrVal::Val(proposed),
+                        vote.message.validator_address,
+                    );
+
+                    let signature = self.signer.sign(&prevote.to_sign_bytes());
+                    let vote = SignedVote::new(prevote, signature);
+
+                    return Self::publish(state, SignedConsensusMsg::Vote(vote));
+                }
+            }
+        }
+
+        Self::publish(state, SignedConsensusMsg::Vote(vote))
+    }
+
+    fn on_proposal(
+        &self,
+        state: &mut State,
+        proposal: SignedProposal<TestContext>,
+    ) -> Result<(), ActorProcessingErr> {
+        let (height, round) = (proposal.message.height, proposal.message.round);
+
+        if self.is_active(height, round, |m| *m == Misbehavior::DoublePropose) {
+            let other = Proposal::new(
+                height,
+                round,
+                Value::new(proposal.message.value.value.wrapping_add(1)),
+                proposal.message.pol_round,
+                proposal.message.validator_address,
+            );
+
+            warn!(%height, %round, value = %other.value.id(), "Faulty node proposes a second value");
+
+            let signature = self.signer.sign(&other.to_sign_bytes());
+            let other = SignedProposal::new(other, signature);
+
+            Self::publish(state, SignedConsensusMsg::Proposal(other))?;
+        }
+
+        let delay = self
+            .faults
+            .iter()
+            .find_map(|fault| match fault.misbehavior {
+                Misbehavior::DelayProposal(delay) if fault.is_active(height, round) => Some(delay),
+                _ => None,
+            });
+
+        if let Some(delay) = delay {
+            warn!(%height, %round, ?delay, "Faulty node delays its proposal");
+
+            let network = state.network.clone();
+
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+
+                let msg = SignedConsensusMsg::Proposal(proposal);
+                let _ = network.cast(NetworkActorMsg::PublishConsensusMsg(msg));
+            });
+
+            return Ok(());
+        }
+
+        Self::publish(state, SignedConsensusMsg::Proposal(proposal))
+    }
+
+    fn on_proposal_part(
+        &self,
+        state: &mut State,
+        mut part: StreamMessage<ProposalPart>,
+    ) -> Result<(), ActorProcessingErr> {
+        match &mut part.content {
+            StreamContent::Data(ProposalPart::Init(init)) => {
+                state
+                    .streams
+                    .insert(part.stream_id.clone(), (init.height, init.round));
+            }
+
+            StreamContent::Data(ProposalPart::Fin(fin)) => {
+                let invalid = state
+                    .streams
+                    .get(&part.stream_id)
+                    .is_some_and(|&(height, round)| {
+                        self.is_active(height, round, |m| *m == Misbehavior::InvalidProposalParts)
+                    });
+
+                if invalid {
+                    warn!(stream_id = %part.stream_id, "Faulty node signs its proposal parts wrongly");
+                    *fin = ProposalFin::new(self.signer.sign(b"invalid proposal parts"));
+                }
+            }
+
+            StreamContent::Fin => {
+                state.streams.remove(&part.stream_id);
+            }
+
+            StreamContent::Data(ProposalPart::Data(_)) => (),
+        }
+
+        state
+            .network
+            .cast(NetworkActorMsg::PublishProposalPart(part))
+            .map_err(|_| eyre!("Network actor has stopped").into())
+    }
+}
+
+#[async_trait]
+impl Actor for FaultyNetwork {
+    type Msg = NetworkActorMsg<TestContext>;
+    type State = State;
+    type Arguments = Args;
+
+    async fn pre_start(
+        &self,
+        myself: ActorRef<Self::Msg>,
+        args: Args,
+    ) -> Result<State, ActorProcessingErr> {
+        // Stop the network actor and the observer along with this actor
+        args.network.link(myself.get_cell());
+        args.observer.link(myself.get_cell());
+
+        Ok(State {
+            network: args.network,
+            proposals: args.proposals,
+            locks: HashMap::new(),
+            streams: HashMap::new(),
+        })
+    }
+
+    async fn handle(
+        &self,
+        _myself: ActorRef<Self::Msg>,
+        msg: Self::Msg,
+        state: &mut State,
+    ) -> Result<(), ActorProcessingErr> {
+        match msg {
+            NetworkActorMsg::PublishConsensusMsg(SignedConsensusMsg::Vote(vote)) => {
+                self.on_vote(state, vote)
+            }
+
+            NetworkActorMsg::PublishConsensusMsg(SignedConsensusMsg::Proposal(proposal)) => {
+                self.on_proposal(state, proposal)
+            }
+
+            NetworkActorMsg::PublishProposalPart(part) => self.on_proposal_part(state, part),
+
+            msg => state
+                .network
+                .cast(msg)
+                .map_err(|_| eyre!("Network actor has stopped").into()),
+        }
+    }
+}
@@ -1,6 +1,8 @@
 pub mod app;
 pub mod config;
+pub mod faults;
 pub mod node;
+pub mod process;
 pub mod state;
 pub mod store;
 pub mod streaming;
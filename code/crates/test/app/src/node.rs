@@ -2,19 +2,24 @@
 
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use bytesize::ByteSize;
 use rand::{CryptoRng, Rng, RngCore};
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tracing::Instrument;
 
 use malachitebft_app_channel::app::config::*;
+use malachitebft_app_channel::app::engine::network::{node_key, LinkConditions};
 use malachitebft_app_channel::app::events::{RxEvent, TxEvent};
 use malachitebft_app_channel::app::types::codec::Codec;
 use malachitebft_app_channel::app::types::core::VotingPower;
-use malachitebft_app_channel::app::types::Keypair;
+use malachitebft_app_channel::app::types::{Keypair, PeerId};
 use malachitebft_app_channel::{
-    ConsensusContext, EngineBuilder, EngineHandle, NetworkContext, NetworkIdentity, RequestContext,
+    ConsensusContext, ConsensusRequest, EngineBuilder, EngineHandle, NetworkContext,
+    NetworkIdentity, NetworkMsg, NetworkRequest, RequestContext, SigningProvider,
     SigningProviderExt, SyncContext, WalContext,
 };
 use malachitebft_test::codec::json::JsonCodec;
@@ -29,11 +34,12 @@ use malachitebft_test::middleware::{DefaultMiddleware, Middleware};
 // Use the same types used for integration tests.
 // A real application would use its own types and context instead.
 use malachitebft_test::{
-    Address, Ed25519Provider, Genesis, Height, PrivateKey, PublicKey, TestContext, Validator,
-    ValidatorSet,
+    Address, Ed25519Provider, Ed25519Verifier, Genesis, Height, KeyRotation, PrivateKey, PublicKey,
+    TestContext, Validator, ValidatorChange, ValidatorSet,
 };
 
 use crate::config::Config;
+use crate::faults::{spawn_faulty_network, Fault};
 use crate::state::State;
 use crate::store::Store;
 
@@ -41,6 +47,75 @@ pub struct Handle {
     pub app: JoinHandle<()>,
     pub engine: EngineHandle,
     pub tx_event: TxEvent<TestContext>,
+    pub tx_network: mpsc::Sender<NetworkMsg<TestContext>>,
+    pub net_requests: mpsc::Sender<NetworkRequest>,
+    pub requests: mpsc::Sender<ConsensusRequest<TestContext>>,
+}
+
+impl Handle {
+    /// Disconnect the node from its connected peers with the given monikers,
+    /// and deny any connection to or from them for the given duration.
+    ///
+    /// Returns the number of peers banned.
+    pub async fn ban_peers(&self, monikers: &[String], duration: Duration) -> eyre::Result<usize> {
+        let peers = self.connected_peers(monikers).await?;
+
+        for &peer_id in &peers {
+            self.send_network(NetworkMsg::BanPeer(peer_id, Some(duration)))
+                .await?;
+        }
+
+        Ok(peers.len())
+    }
+
+    /// Simulate the given conditions on the links to the connected peers with the given monikers.
+    ///
+    /// Returns the number of links affected.
+    pub async fn set_link_conditions(
+        &self,
+        monikers: &[String],
+        conditions: LinkConditions,
+    ) -> eyre::Result<usize> {
+        let peers = self.connected_peers(monikers).await?;
+
+        for &peer_id in &peers {
+            self.send_network(NetworkMsg::SetLinkConditions(peer_id, Some(conditions)))
+                .await?;
+        }
+
+        Ok(peers.len())
+    }
+
+    /// Pause consensus, or resume it if `paused` is false
+    pub async fn set_paused(&self, paused: bool) -> eyre::Result<()> {
+        if paused {
+            ConsensusRequest::pause(&self.requests).await?;
+        } else {
+            ConsensusRequest::resume(&self.requests).await?;
+        }
+
+        Ok(())
+    }
+
+    /// IDs of the connected peers with the given monikers
+    async fn connected_peers(&self, monikers: &[String]) -> eyre::Result<Vec<PeerId>> {
+        let peers = NetworkRequest::list_peers(&self.net_requests)
+            .await?
+            .ok_or_else(|| eyre::eyre!("Network is not running"))?;
+
+        peers
+            .into_iter()
+            .filter(|peer| peer.moniker.as_ref().is_some_and(|m| monikers.contains(m)))
+            .map(|peer| Ok(PeerId::from_bytes(&peer.peer_id.to_bytes())?))
+            .collect()
+    }
+
+    async fn send_network(&self, msg: NetworkMsg<TestContext>) -> eyre::Result<()> {
+        self.tx_network
+            .send(msg)
+            .await
+            .map_err(|_| eyre::eyre!("Network channel is closed"))
+    }
 }
 
 #[async_trait]
@@ -55,6 +130,12 @@ impl NodeHandle<TestContext> for Handle {
         self.engine.handle.abort();
         Ok(())
     }
+
+    async fn shutdown(&self) -> eyre::Result<()> {
+        self.engine.shutdown().await?;
+        self.app.abort();
+        Ok(())
+    }
 }
 
 /// Main application struct implementing the consensus node functionality
@@ -63,17 +144,41 @@ pub struct App {
     pub home_dir: PathBuf,
     pub config: Config,
     pub validator_set: ValidatorSet,
-    pub private_key: PrivateKey,
+    /// Private key of the node, which a follower can run without
+    pub private_key: Option<PrivateKey>,
+    /// Consensus keys registered by the validators, effective from a later height
+    pub key_rotations: Vec<KeyRotation>,
+    /// Changes to the validator set, effective from a later height
+    pub validator_changes: Vec<ValidatorChange>,
+    /// Keys this node signs with from the given heights on, in place of its private key
+    pub next_private_keys: Vec<(Height, PrivateKey)>,
     pub start_height: Option<Height>,
     pub middleware: Option<Arc<dyn Middleware>>,
+    /// Byzantine behaviors of the node, see [`Fault`]
+    pub faults: Vec<Fault>,
+}
+
+/// Public key of the network identity of the node
+fn network_public_key(keypair: &Keypair) -> eyre::Result<PublicKey> {
+    let public_key = keypair
+        .public()
+        .try_into_ed25519()
+        .map_err(|e| eyre::eyre!("Network identity is not an Ed25519 key: {e}"))?;
+
+    Ok(PublicKey::from_bytes(public_key.to_bytes()))
 }
 
 impl App {
-    fn get_network_keypair(&self) -> Keypair {
+    fn get_network_keypair(&self, config: &Config) -> eyre::Result<Keypair> {
+        // Keep the same peer ID across restarts if the node key is persisted
+        if let Some(path) = &config.consensus.p2p.node_key_file {
+            return Ok(node_key::load_or_generate(path)?);
+        }
+
         // Separate network identity
         let rng = rand::thread_rng();
         let net_pk = self.generate_private_key(rng);
-        Keypair::ed25519_from_bytes(net_pk.inner().to_bytes()).unwrap()
+        Ok(Keypair::ed25519_from_bytes(net_pk.inner().to_bytes()).unwrap())
     }
 }
 
@@ -115,7 +220,9 @@ impl Node for App {
     }
 
     fn load_private_key_file(&self) -> eyre::Result<Self::PrivateKeyFile> {
-        Ok(self.private_key.clone())
+        self.private_key
+            .clone()
+            .ok_or_else(|| eyre::eyre!("The node has no private key"))
     }
 
     fn load_genesis(&self) -> eyre::Result<Self::Genesis> {
@@ -126,7 +233,11 @@ impl Node for App {
             .map(|v| (v.public_key, v.voting_power))
             .collect();
 
-        Ok(self.make_genesis(validators))
+        let mut genesis = self.make_genesis(validators);
+        genesis.key_rotations = self.key_rotations.clone();
+        genesis.validator_changes = self.validator_changes.clone();
+
+        Ok(genesis)
     }
 
     async fn start(&self) -> eyre::Result<Handle> {
@@ -142,14 +253,35 @@ impl Node for App {
 
         let ctx = TestContext::with_middleware(middleware);
 
-        let public_key = self.get_public_key(&self.private_key);
-        let address = self.get_address(&public_key);
-        let keypair = self.get_network_keypair(); // Separate network identity
+        let keypair = self.get_network_keypair(&config)?; // Separate network identity
         let genesis = self.load_genesis()?;
         let wal_path = self.get_home_dir().join("wal").join("consensus.wal");
 
-        let signing_provider = self.get_signing_provider(self.private_key.clone());
-        let identity = {
+        // A follower never signs, so it runs without any signing key.
+        // If it has no private key either, its address is derived from its network identity.
+        let follower = config.consensus.follower;
+        let public_key = match &self.private_key {
+            Some(private_key) => self.get_public_key(private_key),
+            None if follower => network_public_key(&keypair)?,
+            None => eyre::bail!("A validator requires a private key"),
+        };
+        let address = self.get_address(&public_key);
+
+        let signing_key = self.private_key.clone().filter(|_| !follower);
+        let make_signing_provider = || {
+            signing_key
+                .clone()
+                .map(|private_key| self.get_signing_provider(private_key))
+        };
+
+        let signing_provider = match make_signing_provider() {
+            Some(signing_provider) => Box::new(signing_provider) as Box<dyn SigningProvider<_>>,
+            None => Box::new(Ed25519Verifier),
+        };
+
+        let identity = if follower {
+            NetworkIdentity::new(config.moniker.clone(), keypair, None)
+        } else {
             let peer_id_bytes = keypair.public().to_peer_id().to_bytes();
             let proof = signing_provider
                 .sign_validator_proof(public_key.as_bytes().to_vec(), peer_id_bytes)
@@ -166,14 +298,29 @@ impl Node for App {
             )
         };
 
-        let (mut channels, engine_handle) = EngineBuilder::new(ctx.clone(), config.clone())
+        let builder = EngineBuilder::new(ctx.clone(), config.clone())
             .with_default_wal(WalContext::new(wal_path, ProtobufCodec))
-            .with_default_network(NetworkContext::new(identity, JsonCodec))
             .with_default_consensus(ConsensusContext::new(address, signing_provider))
             .with_default_sync(SyncContext::new(JsonCodec))
-            .with_default_request(RequestContext::new(100))
-            .build()
-            .await?;
+            .with_default_request(RequestContext::new(100));
+
+        let (mut channels, engine_handle) = if self.faults.is_empty() {
+            builder
+                .with_default_network(NetworkContext::new(identity, JsonCodec))
+                .build()
+                .await?
+        } else {
+            let signer = make_signing_provider()
+                .ok_or_else(|| eyre::eyre!("Faults can only be injected in a validator"))?;
+
+            let (network, tx_network) =
+                spawn_faulty_network(&config, identity, self.faults.clone(), signer).await?;
+
+            builder
+                .with_custom_network(network, tx_network)
+                .build()
+                .await?
+        };
 
         drop(_guard);
 
@@ -190,11 +337,32 @@ impl Node for App {
             address,
             start_height,
             store,
-            self.get_signing_provider(self.private_key.clone()),
+            make_signing_provider(),
             self.middleware.clone(),
         );
 
+        if !follower {
+            for (height, private_key) in &self.next_private_keys {
+                let registered = ConsensusRequest::register_signing_key(
+                    &channels.requests,
+                    *height,
+                    Arc::new(self.get_signing_provider(private_key.clone())),
+                )
+                .await?;
+
+                if !registered {
+                    eyre::bail!("Failed to register the signing key for height {height}");
+                }
+
+                let signing_provider = self.get_signing_provider(private_key.clone());
+                state.register_signing_provider(*height, signing_provider);
+            }
+        }
+
         let tx_event = channels.events.clone();
+        let tx_network = channels.network.clone();
+        let net_requests = channels.net_requests.clone();
+        let requests = channels.requests.clone();
 
         let app_handle = tokio::spawn(
             async move {
@@ -209,6 +377,9 @@ impl Node for App {
             app: app_handle,
             engine: engine_handle,
             tx_event,
+            tx_network,
+            net_requests,
+            requests,
         })
     }
 
@@ -226,7 +397,11 @@ impl CanMakeGenesis for App {
 
         let validator_set = ValidatorSet::new(validators);
 
-        Genesis { validator_set }
+        Genesis {
+            validator_set,
+            key_rotations: Vec::new(),
+            validator_changes: Vec::new(),
+        }
     }
 }
 
@@ -269,6 +444,15 @@ fn make_config(index: usize, total: usize, settings: MakeConfigSettings) -> Conf
             // Current test app does not support proposal-only value payload properly as Init does not include valid_round
             value_payload: ValuePayload::ProposalAndParts,
             queue_capacity: 100,
+            max_votes_per_validator_per_round: 4,
+            follower: false,
+            dev_mode: false,
+            deterministic_ordering: None,
+            decision_proof: false,
+            verification_workers: 0,
+            verification_batch_size: 64,
+            max_value_size: ByteSize::mib(64),
+            mailbox: Default::default(),
             p2p: P2pConfig {
                 protocol: PubSubProtocol::default(),
                 listen_addr: settings.transport.multiaddr("127.0.0.1", consensus_port),
@@ -311,6 +495,7 @@ fn make_config(index: usize, total: usize, settings: MakeConfigSettings) -> Conf
             enabled: true,
             listen_addr: format!("127.0.0.1:{metrics_port}").parse().unwrap(),
         },
+        telemetry: TelemetryConfig::default(),
         runtime: settings.runtime,
         value_sync: ValueSyncConfig::default(),
         logging: LoggingConfig::default(),
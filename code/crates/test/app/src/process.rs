@@ -0,0 +1,228 @@
+//! Running the test application as a separate process, see the `malachitebft-test-node` binary.
+//!
+//! The parent process writes a [`NodeSpec`] to the home directory of the node and passes its path
+//! to the binary. The node reports the events the test framework reacts to on its standard output,
+//! one per line prefixed with [`EVENT_PREFIX`], interleaved with its logs. It shuts down gracefully
+//! when its standard input is closed.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use eyre::{bail, eyre, Context as _};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
+use malachitebft_app_channel::app::consensus::{Error as ConsensusError, Role};
+use malachitebft_app_channel::app::engine::util::events::Event;
+use malachitebft_app_channel::app::types::codec::Codec;
+use malachitebft_app_channel::app::types::core::Round;
+use malachitebft_test::codec::proto::{
+    decode_commit_certificate, encode_commit_certificate, ProtobufCodec,
+};
+use malachitebft_test::{
+    proto, Address, Height, KeyRotation, PrivateKey, TestContext, ValidatorChange, ValidatorSet,
+};
+
+use crate::config::Config;
+use crate::node::App;
+
+/// Name of the binary running a node of the test application as a separate process
+pub const NODE_BINARY: &str = "malachitebft-test-node";
+
+/// Environment variable overriding the path to the node binary
+pub const NODE_BINARY_ENV: &str = "MALACHITE_TEST_NODE_BIN";
+
+/// Prefix of the lines of the standard output of the node which report an event
+pub const EVENT_PREFIX: &str = "@event ";
+
+/// Everything needed to run a node as a separate process,
+/// that is an [`App`] without its middleware and faults, which cannot be sent to another process
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeSpec {
+    pub home_dir: PathBuf,
+    pub config: Config,
+    pub validator_set: ValidatorSet,
+    pub private_key: Option<PrivateKey>,
+    pub key_rotations: Vec<KeyRotation>,
+    pub validator_changes: Vec<ValidatorChange>,
+    pub next_private_keys: Vec<(Height, PrivateKey)>,
+    pub start_height: Option<Height>,
+}
+
+impl NodeSpec {
+    /// Fails if the node misbehaves, as faults are only supported in-process
+    pub fn from_app(app: &App) -> eyre::Result<Self> {
+        if !app.faults.is_empty() {
+            bail!("Faults cannot be injected in a node running as a separate process");
+        }
+
+        Ok(Self {
+            home_dir: app.home_dir.clone(),
+            config: app.config.clone(),
+            validator_set: app.validator_set.clone(),
+            private_key: app.private_key.clone(),
+            key_rotations: app.key_rotations.clone(),
+            validator_changes: app.validator_changes.clone(),
+            next_private_keys: app.next_private_keys.clone(),
+            start_height: app.start_height,
+        })
+    }
+
+    pub fn into_app(self) -> App {
+        App {
+            home_dir: self.home_dir,
+            config: self.config,
+            validator_set: self.validator_set,
+            private_key: self.private_key,
+            key_rotations: self.key_rotations,
+            validator_changes: self.validator_changes,
+            next_private_keys: self.next_private_keys,
+            start_height: self.start_height,
+            middleware: None,
+            faults: Vec::new(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).wrap_err_with(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// Path to the node binary, built alongside the test binaries unless overridden
+/// with the [`NODE_BINARY_ENV`] environment variable
+pub fn node_binary() -> eyre::Result<PathBuf> {
+    if let Ok(path) = std::env::var(NODE_BINARY_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+
+    // Test binaries live in `target/<profile>/deps`, and the other binaries in `target/<profile>`
+    let exe = std::env::current_exe()?;
+    let dir = exe
+        .parent()
+        .and_then(|dir| {
+            if dir.ends_with("deps") {
+                dir.parent()
+            } else {
+                Some(dir)
+            }
+        })
+        .ok_or_else(|| eyre!("Cannot locate the target directory from {}", exe.display()))?;
+
+    let path = dir
+        .join(NODE_BINARY)
+        .with_extension(std::env::consts::EXE_EXTENSION);
+
+    if !path.exists() {
+        bail!(
+            "Node binary not found at {}, build it with `cargo build -p arc-malachitebft-test-app` \
+             or set {NODE_BINARY_ENV}",
+            path.display()
+        );
+    }
+
+    Ok(path)
+}
+
+/// An event, as reported by a node running as a separate process.
+/// Consensus messages and certificates are encoded with the Protobuf codec.
+#[derive(Serialize, Deserialize)]
+enum WireEvent {
+    StartedHeight(Height, bool),
+    StartedRound(Height, Option<u32>, Address, WireRole),
+    Published(Vec<u8>),
+    Received(Vec<u8>),
+    Decided(Vec<u8>),
+    WalReplayError(String),
+    WalCorrupted(String),
+}
+
+#[derive(Serialize, Deserialize)]
+enum WireRole {
+    Proposer,
+    Validator,
+    None,
+}
+
+/// Encode an event as a line of the standard output of the node,
+/// or `None` if the event is not reported to the parent process
+pub fn encode_event(event: &Event<TestContext>) -> eyre::Result<Option<String>> {
+    let wire = match event {
+        Event::StartedHeight(height, is_restart) => WireEvent::StartedHeight(*height, *is_restart),
+        Event::StartedRound(height, round, proposer, role) => {
+            let role = match role {
+                Role::Proposer => WireRole::Proposer,
+                Role::Validator => WireRole::Validator,
+                Role::None => WireRole::None,
+            };
+
+            WireEvent::StartedRound(*height, round.as_u32(), *proposer, role)
+        }
+        Event::Published(msg) => WireEvent::Published(ProtobufCodec.encode(msg)?.to_vec()),
+        Event::Received(msg) => WireEvent::Received(ProtobufCodec.encode(msg)?.to_vec()),
+        Event::Decided { commit_certificate } => {
+            WireEvent::Decided(encode_commit_certificate(commit_certificate)?.encode_to_vec())
+        }
+        Event::WalReplayError(e) => WireEvent::WalReplayError(e.to_string()),
+        Event::WalCorrupted(e) => WireEvent::WalCorrupted(e.to_string()),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(format!(
+        "{EVENT_PREFIX}{}",
+        serde_json::to_string(&wire)?
+    )))
+}
+
+/// Decode an event from a line of the standard output of the node,
+/// or `None` if the line is not an event
+pub fn decode_event(line: &str) -> Option<eyre::Result<Event<TestContext>>> {
+    let json = line.strip_prefix(EVENT_PREFIX)?;
+
+    let decode = || -> eyre::Result<Event<TestContext>> {
+        Ok(match serde_json::from_str(json)? {
+            WireEvent::StartedHeight(height, is_restart) => {
+                Event::StartedHeight(height, is_restart)
+            }
+            WireEvent::StartedRound(height, round, proposer, role) => {
+                let round = round.map_or(Round::Nil, Round::new);
+                let role = match role {
+                    WireRole::Proposer => Role::Proposer,
+                    WireRole::Validator => Role::Validator,
+                    WireRole::None => Role::None,
+                };
+
+                Event::StartedRound(height, round, proposer, role)
+            }
+            WireEvent::Published(bytes) => {
+                Event::Published(ProtobufCodec.decode(Bytes::from(bytes))?)
+            }
+            WireEvent::Received(bytes) => {
+                Event::Received(ProtobufCodec.decode(Bytes::from(bytes))?)
+            }
+            WireEvent::Decided(bytes) => {
+                let certificate = proto::CommitCertificate::decode(bytes.as_slice())?;
+
+                Event::Decided {
+                    commit_certificate: decode_commit_certificate(certificate)?,
+                }
+            }
+            // Only the message of the error is reported, which is enough to fail the test
+            WireEvent::WalReplayError(e) => Event::WalReplayError(Arc::new(
+                ConsensusError::WalCorrupted(Arc::new(io::Error::other(e))),
+            )),
+            WireEvent::WalCorrupted(e) => Event::WalCorrupted(Arc::new(io::Error::other(e))),
+        })
+    };
+
+    Some(decode())
+}
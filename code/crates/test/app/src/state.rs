@@ -1,7 +1,7 @@
 //! Internal state of the application. This is a simplified abstract to keep it simple.
 //! A regular application would have mempool implemented, a proper database and input methods like RPC.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 
@@ -46,7 +46,9 @@ pub struct State {
     pub store: Store,
     pub middleware: Option<Arc<dyn Middleware>>,
 
-    signing_provider: Ed25519Provider,
+    /// `None` for a follower, which never proposes
+    signing_provider: Option<Ed25519Provider>,
+    next_signing_providers: BTreeMap<Height, Ed25519Provider>,
     streams_map: PartStreamsMap,
     rng: StdRng,
 }
@@ -98,7 +100,7 @@ impl State {
         address: Address,
         height: Height,
         store: Store,
-        signing_provider: Ed25519Provider,
+        signing_provider: Option<Ed25519Provider>,
         middleware: Option<Arc<dyn Middleware>>,
     ) -> Self {
         Self {
@@ -108,6 +110,7 @@ impl State {
             address,
             store,
             signing_provider,
+            next_signing_providers: BTreeMap::new(),
             middleware,
             current_height: height,
             current_round: Round::new(0),
@@ -119,12 +122,29 @@ impl State {
         }
     }
 
-    /// Returns the set of validators for the given height.
+    /// Returns the set of validators for the given height,
+    /// with the voting powers and consensus keys effective at that height.
     pub fn get_validator_set(&self, height: Height) -> ValidatorSet {
         self.ctx
             .middleware()
             .get_validator_set(&self.ctx, self.current_height, height, &self.genesis)
             .unwrap_or_else(|| self.genesis.validator_set.clone())
+            .with_validator_changes(&self.genesis.validator_changes, height)
+            .with_key_rotations(&self.genesis.key_rotations, height)
+    }
+
+    /// Sign with the given provider from the given height on, see [`Genesis::key_rotations`]
+    pub fn register_signing_provider(&mut self, height: Height, signing_provider: Ed25519Provider) {
+        self.next_signing_providers.insert(height, signing_provider);
+    }
+
+    /// Returns the signing provider to sign with at the given height, if any
+    fn signing_provider_at(&self, height: Height) -> Option<&Ed25519Provider> {
+        self.next_signing_providers
+            .range(..=height)
+            .next_back()
+            .map(|(_, provider)| provider)
+            .or(self.signing_provider.as_ref())
     }
 
     /// Returns the timeouts for the given height.
@@ -210,10 +230,7 @@ impl State {
             .ok_or(SignatureVerificationError::ProposerNotFound)?;
 
         // Verify the signature
-        if !self
-            .signing_provider
-            .verify(&hash, &fin.signature, &proposer.public_key)
-        {
+        if proposer.public_key.verify(&hash, &fin.signature).is_err() {
             return Err(SignatureVerificationError::InvalidSignature);
         }
 
@@ -484,8 +501,8 @@ impl State {
         &mut self,
         value: LocallyProposedValue<TestContext>,
         pol_round: Round,
-    ) -> impl Iterator<Item = StreamMessage<ProposalPart>> {
-        let parts = self.value_to_parts(value, pol_round);
+    ) -> eyre::Result<impl Iterator<Item = StreamMessage<ProposalPart>>> {
+        let parts = self.value_to_parts(value, pol_round)?;
         let stream_id = self.stream_id();
 
         let mut msgs = Vec::with_capacity(parts.len() + 1);
@@ -503,14 +520,14 @@ impl State {
             StreamContent::Fin,
         ));
 
-        msgs.into_iter()
+        Ok(msgs.into_iter())
     }
 
     fn value_to_parts(
         &self,
         value: LocallyProposedValue<TestContext>,
         pol_round: Round,
-    ) -> Vec<ProposalPart> {
+    ) -> eyre::Result<Vec<ProposalPart>> {
         let mut hasher = sha3::Keccak256::new();
         let mut parts = Vec::new();
 
@@ -542,11 +559,15 @@ impl State {
         // Sign the hash of the proposal parts
         {
             let hash = hasher.finalize().to_vec();
-            let signature = self.signing_provider.sign(&hash);
+            let signer = self
+                .signing_provider_at(value.height)
+                .ok_or_else(|| eyre!("Cannot sign the proposal parts without a signing key"))?;
+
+            let signature = signer.sign(&hash);
             parts.push(ProposalPart::Fin(ProposalFin::new(signature)));
         }
 
-        parts
+        Ok(parts)
     }
 
     /// Re-assemble a [`ProposedValue`] from its [`ProposalParts`].
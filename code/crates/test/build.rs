@@ -3,6 +3,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "proto/consensus.proto",
         "proto/sync.proto",
         "proto/liveness.proto",
+        "proto/tendermint/types/types.proto",
+        "proto/tendermint/consensus/types.proto",
     ];
 
     for proto in protos {
@@ -14,9 +14,11 @@ use directories::BaseDirs;
 use crate::cmd::distributed_testnet::DistributedTestnetCmd;
 use crate::cmd::dump_wal::DumpWalCmd;
 use crate::cmd::init::InitCmd;
+use crate::cmd::replay_wal::ReplayWalCmd;
 use crate::cmd::start::StartCmd;
 use crate::cmd::testnet::TestnetCmd;
 use crate::error::Error;
+use crate::keystore::Keystore;
 
 const APP_FOLDER: &str = ".malachite";
 const CONFIG_FILE: &str = "config.toml";
@@ -30,6 +32,15 @@ pub struct Args {
     #[arg(long, global = true, value_name = "HOME_DIR")]
     pub home: Option<PathBuf>,
 
+    /// File holding the passphrase of the encrypted key files
+    #[arg(
+        long,
+        global = true,
+        value_name = "PASSPHRASE_FILE",
+        env = "MALACHITE_PASSPHRASE_FILE"
+    )]
+    pub passphrase_file: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -50,6 +61,9 @@ pub enum Commands {
 
     /// Dump WAL entries
     DumpWal(DumpWalCmd),
+
+    /// Replay the WAL and reconstruct the consensus state, optionally comparing it with another WAL
+    ReplayWal(ReplayWalCmd),
 }
 
 impl Default for Commands {
@@ -98,6 +112,21 @@ impl Args {
     pub fn get_priv_validator_key_file_path(&self) -> Result<PathBuf, Error> {
         Ok(self.get_config_dir()?.join(PRIV_VALIDATOR_KEY_FILE))
     }
+
+    /// get_keystore returns the keystore of the key files, encrypting them with the passphrase
+    /// read from the `--passphrase-file` parameter if given.
+    pub fn get_keystore(&self) -> Result<Keystore, Error> {
+        let Some(path) = &self.passphrase_file else {
+            return Ok(Keystore::default());
+        };
+
+        let passphrase =
+            std::fs::read_to_string(path).map_err(|_| Error::LoadFile(path.clone()))?;
+
+        Ok(Keystore::with_passphrase(
+            passphrase.trim_end_matches(['\r', '\n']),
+        ))
+    }
 }
 
 #[cfg(test)]
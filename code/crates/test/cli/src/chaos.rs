@@ -0,0 +1,109 @@
+//! Admin endpoint to toggle failure injection on a live staging node, see [`malachitebft_engine::chaos`].
+//!
+//! The endpoint is only served if the `MALACHITE_CHAOS_ADMIN_TOKEN` environment variable is set,
+//! and every request must carry that token in an `Authorization: Bearer <token>` header.
+//!
+//! - `GET /chaos` returns the current toggles
+//! - `POST /chaos` replaces the toggles, eg. `{"gossip_drop_percent": 20, "wal_write_delay_ms": 50}`
+//! - `DELETE /chaos` turns off all the toggles
+
+use std::time::Duration;
+
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use malachitebft_engine::chaos::{toggles, ChaosSettings};
+
+/// Environment variable holding the token required to use the chaos endpoint
+pub const ADMIN_TOKEN_ENV: &str = "MALACHITE_CHAOS_ADMIN_TOKEN";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Toggles {
+    wal_write_delay_ms: u64,
+    gossip_drop_percent: u8,
+    app_reply_delay_ms: u64,
+}
+
+impl From<ChaosSettings> for Toggles {
+    fn from(settings: ChaosSettings) -> Self {
+        Self {
+            wal_write_delay_ms: settings.wal_write_delay.as_millis() as u64,
+            gossip_drop_percent: settings.gossip_drop_percent,
+            app_reply_delay_ms: settings.app_reply_delay.as_millis() as u64,
+        }
+    }
+}
+
+impl From<Toggles> for ChaosSettings {
+    fn from(toggles: Toggles) -> Self {
+        Self {
+            wal_write_delay: Duration::from_millis(toggles.wal_write_delay_ms),
+            gossip_drop_percent: toggles.gossip_drop_percent,
+            app_reply_delay: Duration::from_millis(toggles.app_reply_delay_ms),
+        }
+    }
+}
+
+/// Routes of the chaos endpoint, `None` if no admin token is configured
+pub fn router() -> Option<Router> {
+    let Ok(token) = std::env::var(ADMIN_TOKEN_ENV) else {
+        warn!("Chaos endpoint disabled, {ADMIN_TOKEN_ENV} is not set");
+        return None;
+    };
+
+    if token.is_empty() {
+        warn!("Chaos endpoint disabled, {ADMIN_TOKEN_ENV} is empty");
+        return None;
+    }
+
+    let (get_token, post_token, delete_token) = (token.clone(), token.clone(), token);
+
+    let router = Router::new().route(
+        "/chaos",
+        get(move |headers: HeaderMap| async move {
+            authorize(&headers, &get_token)?;
+            Ok::<_, StatusCode>(current())
+        })
+        .post(move |headers: HeaderMap, body: String| async move {
+            authorize(&headers, &post_token)?;
+
+            let requested: Toggles =
+                serde_json::from_str(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+
+            toggles().apply(requested.into());
+            Ok::<_, StatusCode>(current())
+        })
+        .delete(move |headers: HeaderMap| async move {
+            authorize(&headers, &delete_token)?;
+
+            toggles().reset();
+            Ok::<_, StatusCode>(current())
+        }),
+    );
+
+    Some(router)
+}
+
+fn authorize(headers: &HeaderMap, token: &str) -> Result<(), StatusCode> {
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|provided| provided == token);
+
+    if authorized {
+        Ok(())
+    } else {
+        warn!("Rejected unauthorized request to the chaos endpoint");
+        Err(StatusCode::FORBIDDEN)
+    }
+}
+
+fn current() -> String {
+    let current = Toggles::from(toggles().settings());
+    serde_json::to_string(&current).unwrap_or_default()
+}
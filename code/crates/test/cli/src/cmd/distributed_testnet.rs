@@ -17,6 +17,7 @@ use malachitebft_test::traits::{
 use crate::args::Args;
 use crate::cmd::testnet::RuntimeFlavour;
 use crate::file::{save_config, save_genesis, save_priv_validator_key};
+use crate::keystore::Keystore;
 
 #[derive(Parser, Debug, Clone, PartialEq)]
 pub struct DistributedTestnetCmd {
@@ -187,12 +188,19 @@ where
 
         save_config::<N>(
             &args.get_config_file_path()?,
-            &N::make_distributed_config(i, nodes, machines.clone(), bootstrap_set_size, settings),
+            &N::make_distributed_config(
+                i,
+                nodes,
+                machines.clone(),
+                bootstrap_set_size,
+                settings.clone(),
+            ),
         )?;
 
         let priv_validator_key = node.make_private_key_file((*private_key).clone());
         save_priv_validator_key(
             node,
+            &Keystore::default(),
             &args.get_priv_validator_key_file_path()?,
             &priv_validator_key,
         )?;
@@ -16,6 +16,7 @@ use malachitebft_test::traits::{
 
 use crate::error::Error;
 use crate::file::{save_config, save_genesis, save_priv_validator_key};
+use crate::keystore::Keystore;
 use crate::new::{generate_genesis, generate_private_keys};
 
 #[derive(Parser, Debug, Clone, Default, PartialEq)]
@@ -77,6 +78,7 @@ impl InitCmd {
         config_file: &Path,
         genesis_file: &Path,
         priv_validator_key_file: &Path,
+        keystore: &Keystore,
     ) -> Result<(), Error>
     where
         N: Node + CanMakeConfig + CanMakePrivateKeyFile + CanGeneratePrivateKey + CanMakeGenesis,
@@ -111,6 +113,7 @@ impl InitCmd {
             config_file,
             genesis_file,
             priv_validator_key_file,
+            keystore,
             self.overwrite,
         )?;
 
@@ -125,6 +128,7 @@ pub fn init<N>(
     config_file: &Path,
     genesis_file: &Path,
     priv_validator_key_file: &Path,
+    keystore: &Keystore,
     overwrite: bool,
 ) -> Result<(), Error>
 where
@@ -148,7 +152,7 @@ where
         info!(file = ?priv_validator_key_file, "Saving private key");
         let private_keys = generate_private_keys(node, 1, false);
         let priv_validator_key = node.make_private_key_file(private_keys[0].clone());
-        save_priv_validator_key(node, priv_validator_key_file, &priv_validator_key)?;
+        save_priv_validator_key(node, keystore, priv_validator_key_file, &priv_validator_key)?;
     }
 
     // Save default genesis
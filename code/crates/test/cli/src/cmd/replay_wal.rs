@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use color_eyre::eyre;
+use malachitebft_core_types::{Context, Height, ValuePayload};
+use tracing::{error, info, warn};
+
+use malachitebft_app::engine::wal::replay::{diff, read_heights, ReplayedHeight, Replayer};
+use malachitebft_app::engine::wal::WalCodec;
+
+#[derive(Parser, Debug, Clone, Default, PartialEq)]
+pub struct ReplayWalCmd {
+    /// Path to the WAL to replay
+    pub wal_file: PathBuf,
+
+    /// Path to the WAL of another node, to compare the consensus states of both nodes
+    #[clap(long)]
+    pub diff: Option<PathBuf>,
+
+    /// Only replay the given height
+    #[clap(long)]
+    pub height: Option<u64>,
+}
+
+impl ReplayWalCmd {
+    /// Replay the WAL, and compare it with the WAL of another node if requested.
+    ///
+    /// The WAL of the other node is replayed from the point of view of this node,
+    /// which only affects what the driver asks for, not the consensus state it reaches.
+    pub fn run<Ctx, Codec>(
+        &self,
+        replayer: &Replayer<Ctx>,
+        codec: Codec,
+        validator_set: Ctx::ValidatorSet,
+    ) -> eyre::Result<()>
+    where
+        Ctx: Context,
+        Codec: WalCodec<Ctx>,
+    {
+        let rt = tokio::runtime::Builder::new_current_thread().build()?;
+
+        let replays = rt.block_on(self.replay(&self.wal_file, replayer, &codec, &validator_set))?;
+
+        let Some(other) = &self.diff else {
+            for replay in &replays {
+                print_replay(replay);
+            }
+
+            return Ok(());
+        };
+
+        let others = rt.block_on(self.replay(other, replayer, &codec, &validator_set))?;
+
+        for replay in &replays {
+            let height = replay.height;
+
+            let Some(other) = others.iter().find(|other| other.height == height) else {
+                warn!(%height, "Height not found in {}", other.display());
+                continue;
+            };
+
+            match diff(replay, other) {
+                None => info!(%height, "No divergence"),
+                Some(divergence) => {
+                    error!(%height, round = %divergence.round, "Divergence found");
+                    info!("- {}: {:?}", self.wal_file.display(), divergence.left);
+                    info!("- {}: {:?}", other.display(), divergence.right);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn replay<Ctx, Codec>(
+        &self,
+        path: &Path,
+        replayer: &Replayer<Ctx>,
+        codec: &Codec,
+        validator_set: &Ctx::ValidatorSet,
+    ) -> eyre::Result<Vec<ReplayedHeight<Ctx>>>
+    where
+        Ctx: Context,
+        Codec: WalCodec<Ctx>,
+    {
+        let mut replays = Vec::new();
+
+        for (sequence, entries) in read_heights(path, codec)? {
+            if self.height.is_some_and(|height| height != sequence) {
+                continue;
+            }
+
+            let height = Ctx::Height::ZERO.increment_by(sequence);
+            let replay = replayer
+                .replay(height, validator_set.clone(), entries)
+                .await;
+
+            replays.push(replay);
+        }
+
+        Ok(replays)
+    }
+}
+
+fn print_replay<Ctx: Context>(replay: &ReplayedHeight<Ctx>) {
+    info!(height = %replay.height, "WAL Replay");
+    info!("- Entries:     {}", replay.entries);
+    info!("- Transitions: {}", replay.transitions.len());
+    info!("- Decided:     {}", replay.is_decided());
+    info!("Transitions:");
+
+    for transition in &replay.transitions {
+        match transition.entry {
+            Some(idx) => info!("- #{idx}: {:?}", transition.state),
+            None => info!("- Start: {:?}", transition.state),
+        }
+
+        for output in &transition.outputs {
+            info!("  -> {output:?}");
+        }
+    }
+
+    for (idx, e) in &replay.errors {
+        match idx {
+            Some(idx) => error!("- #{idx}: {e}"),
+            None => error!("- Start: {e}"),
+        }
+    }
+}
+
+/// The value payload used by consensus, to be passed to the [`Replayer`]
+pub fn value_payload(value_payload: malachitebft_config::ValuePayload) -> ValuePayload {
+    match value_payload {
+        malachitebft_config::ValuePayload::PartsOnly => ValuePayload::PartsOnly,
+        malachitebft_config::ValuePayload::ProposalOnly => ValuePayload::ProposalOnly,
+        malachitebft_config::ValuePayload::ProposalAndParts => ValuePayload::ProposalAndParts,
+    }
+}
@@ -17,6 +17,7 @@ use malachitebft_test::traits::{
 use crate::args::Args;
 use crate::error::Error;
 use crate::file::{save_config, save_genesis, save_priv_validator_key};
+use crate::keystore::Keystore;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum RuntimeFlavour {
@@ -82,6 +83,7 @@ pub struct TestnetCmd {
     /// Possible values:
     /// - "kademlia": Kademlia-based selection, only available with the Kademlia bootstrap protocol
     /// - "random": Random selection (default)
+    /// - "latency": Lowest ping round-trip time first
     #[clap(long, default_value = "random", verbatim_doc_comment)]
     pub selector: Selector,
 
@@ -191,13 +193,14 @@ where
         // Save config
         save_config::<N>(
             &args.get_config_file_path()?,
-            &N::make_config(i, nodes, settings),
+            &N::make_config(i, nodes, settings.clone()),
         )?;
 
         // Save private key
         let priv_validator_key = node.make_private_key_file((*private_key).clone());
         save_priv_validator_key(
             node,
+            &Keystore::default(),
             &args.get_priv_validator_key_file_path()?,
             &priv_validator_key,
         )?;
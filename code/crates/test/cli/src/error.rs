@@ -2,6 +2,8 @@
 //! This low level implementation allows the developer to choose their own error handling library.
 use std::path::PathBuf;
 
+use crate::keystore::KeystoreError;
+
 /// Error messages for commands
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -29,6 +31,10 @@ pub enum Error {
     #[error("Error determining home directory path")]
     DirPath,
 
+    /// Error reading or writing a key file
+    #[error("Keystore error: {0}")]
+    Keystore(#[from] KeystoreError),
+
     /// Error joining threads
     #[error("Error joining threads")]
     Join,
@@ -6,6 +6,7 @@ use std::path::Path;
 use malachitebft_test::node::Node;
 
 use crate::error::Error;
+use crate::keystore::Keystore;
 
 /// Save configuration to file
 pub fn save_config<N: Node>(config_file: &Path, config: &N::Config) -> Result<(), Error> {
@@ -27,17 +28,16 @@ pub fn save_genesis<N: Node>(
     )
 }
 
-/// Save private_key validator key to file
+/// Save private_key validator key to file, encrypted if the keystore holds a passphrase
 pub fn save_priv_validator_key<N: Node>(
     _node: &N,
+    keystore: &Keystore,
     priv_validator_key_file: &Path,
     priv_validator_key: &N::PrivateKeyFile,
 ) -> Result<(), Error> {
-    save(
-        priv_validator_key_file,
-        &serde_json::to_string_pretty(priv_validator_key)
-            .map_err(|e| Error::ToJSON(e.to_string()))?,
-    )
+    keystore
+        .save(priv_validator_key_file, priv_validator_key)
+        .map_err(Into::into)
 }
 
 fn save(path: &Path, data: &str) -> Result<(), Error> {
@@ -0,0 +1,293 @@
+//! Keystore for the keys of the node, ie. its consensus signing key and its network keypair.
+//!
+//! Key files are either stored in plaintext, or encrypted with a passphrase in the age format,
+//! the encryption key being derived from the passphrase with scrypt. Key files encrypted by other
+//! means, eg. with a cloud KMS, are decrypted at startup by a [`KeyDecryptor`] given to the keystore.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use age::secrecy::{ExposeSecret, SecretString};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::warn;
+
+use malachitebft_app::types::Keypair;
+
+/// Header of the files encrypted in the binary age format
+const AGE_HEADER: &[u8] = b"age-encryption.org/v1\n";
+
+/// Error returned by a [`KeyDecryptor`]
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Decrypts the key files at startup, eg. by calling a cloud KMS
+pub trait KeyDecryptor: Send + Sync {
+    /// Decrypt the content of a key file
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, BoxError>;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    #[error("Failed to read key file {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("Failed to write key file {}: {source}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+
+    #[error("Key file {} is encrypted but no passphrase was given", .0.display())]
+    PassphraseRequired(PathBuf),
+
+    #[error("Failed to decrypt key file {}: {reason}", path.display())]
+    Decrypt { path: PathBuf, reason: String },
+
+    #[error("Failed to encrypt key: {0}")]
+    Encrypt(String),
+
+    #[error("Keys decrypted by a key decryptor must be encrypted with the matching service")]
+    EncryptUnsupported,
+
+    #[error("Invalid key file {}: {reason}", path.display())]
+    Invalid { path: PathBuf, reason: String },
+}
+
+/// How the key files are protected
+#[derive(Clone, Default)]
+enum Protection {
+    #[default]
+    Plaintext,
+    Passphrase(Arc<SecretString>),
+    Decryptor(Arc<dyn KeyDecryptor>),
+}
+
+/// Reads and writes the key files of the node.
+///
+/// Files encrypted with a passphrase can only be read with a keystore holding that passphrase.
+/// Plaintext files are still read by any keystore, to not break existing setups,
+/// with a warning when encryption is expected.
+#[derive(Clone, Default)]
+pub struct Keystore {
+    protection: Protection,
+}
+
+impl fmt::Debug for Keystore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protection = match self.protection {
+            Protection::Plaintext => "plaintext",
+            Protection::Passphrase(_) => "passphrase",
+            Protection::Decryptor(_) => "decryptor",
+        };
+
+        f.debug_struct("Keystore")
+            .field("protection", &protection)
+            .finish()
+    }
+}
+
+impl Keystore {
+    /// Keystore encrypting the key files with the given passphrase
+    pub fn with_passphrase(passphrase: impl Into<String>) -> Self {
+        Self {
+            protection: Protection::Passphrase(Arc::new(SecretString::from(passphrase.into()))),
+        }
+    }
+
+    /// Keystore decrypting the key files with the given decryptor
+    pub fn with_decryptor(decryptor: impl KeyDecryptor + 'static) -> Self {
+        Self {
+            protection: Protection::Decryptor(Arc::new(decryptor)),
+        }
+    }
+
+    /// Read the content of a key file, decrypting it if needed
+    pub fn read(&self, path: &Path) -> Result<Vec<u8>, KeystoreError> {
+        let content = fs::read(path).map_err(|source| KeystoreError::Read {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        let decrypt_error = |reason: String| KeystoreError::Decrypt {
+            path: path.to_owned(),
+            reason,
+        };
+
+        match &self.protection {
+            Protection::Passphrase(passphrase) if content.starts_with(AGE_HEADER) => {
+                let identity = age::scrypt::Identity::new(clone_secret(passphrase));
+                age::decrypt(&identity, &content).map_err(|e| decrypt_error(e.to_string()))
+            }
+            Protection::Plaintext if content.starts_with(AGE_HEADER) => {
+                Err(KeystoreError::PassphraseRequired(path.to_owned()))
+            }
+            Protection::Decryptor(decryptor) => decryptor
+                .decrypt(&content)
+                .map_err(|e| decrypt_error(e.to_string())),
+            Protection::Passphrase(_) => {
+                warn!(file = %path.display(), "Key file is not encrypted");
+                Ok(content)
+            }
+            Protection::Plaintext => Ok(content),
+        }
+    }
+
+    /// Write a key file, encrypting it with the passphrase of the keystore if any.
+    ///
+    /// On Unix, the file is only readable and writable by its owner.
+    pub fn write(&self, path: &Path, plaintext: &[u8]) -> Result<(), KeystoreError> {
+        let content = match &self.protection {
+            Protection::Plaintext => plaintext.to_vec(),
+            Protection::Passphrase(passphrase) => {
+                let recipient = age::scrypt::Recipient::new(clone_secret(passphrase));
+                age::encrypt(&recipient, plaintext)
+                    .map_err(|e| KeystoreError::Encrypt(e.to_string()))?
+            }
+            Protection::Decryptor(_) => return Err(KeystoreError::EncryptUnsupported),
+        };
+
+        let write_error = |source| KeystoreError::Write {
+            path: path.to_owned(),
+            source,
+        };
+
+        if let Some(parent_dir) = path.parent() {
+            fs::create_dir_all(parent_dir).map_err(write_error)?;
+        }
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(path).map_err(write_error)?;
+        file.write_all(&content).map_err(write_error)
+    }
+
+    /// Load a key file holding a JSON value, eg. the private key of the validator
+    pub fn load<T: DeserializeOwned>(&self, path: &Path) -> Result<T, KeystoreError> {
+        let content = self.read(path)?;
+
+        serde_json::from_slice(&content).map_err(|e| KeystoreError::Invalid {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Save a value, eg. the private key of the validator, as a JSON key file
+    pub fn save<T: Serialize>(&self, path: &Path, value: &T) -> Result<(), KeystoreError> {
+        let content = serde_json::to_vec_pretty(value).map_err(|e| KeystoreError::Invalid {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        })?;
+
+        self.write(path, &content)
+    }
+
+    /// Load the network keypair of the node, stored in its protobuf encoding
+    pub fn load_keypair(&self, path: &Path) -> Result<Keypair, KeystoreError> {
+        let content = self.read(path)?;
+
+        Keypair::from_protobuf_encoding(&content).map_err(|e| KeystoreError::Invalid {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Save the network keypair of the node in its protobuf encoding
+    pub fn save_keypair(&self, path: &Path, keypair: &Keypair) -> Result<(), KeystoreError> {
+        let content = keypair
+            .to_protobuf_encoding()
+            .map_err(|e| KeystoreError::Invalid {
+                path: path.to_owned(),
+                reason: e.to_string(),
+            })?;
+
+        self.write(path, &content)
+    }
+}
+
+fn clone_secret(secret: &SecretString) -> SecretString {
+    SecretString::from(secret.expose_secret().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for a KMS, the "ciphertext" being the plaintext with its bytes flipped
+    struct FlipDecryptor;
+
+    impl KeyDecryptor for FlipDecryptor {
+        fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, BoxError> {
+            Ok(ciphertext.iter().map(|byte| !byte).collect())
+        }
+    }
+
+    #[test]
+    fn passphrase_encrypted_key_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_key.json");
+        let keystore = Keystore::with_passphrase("correct horse");
+
+        keystore.save(&path, &"secret key").unwrap();
+
+        let content = fs::read(&path).unwrap();
+        assert!(content.starts_with(AGE_HEADER));
+
+        let key: String = keystore.load(&path).unwrap();
+        assert_eq!(key, "secret key");
+
+        let wrong = Keystore::with_passphrase("battery staple");
+        assert!(matches!(
+            wrong.load::<String>(&path),
+            Err(KeystoreError::Decrypt { .. })
+        ));
+
+        assert!(matches!(
+            Keystore::default().load::<String>(&path),
+            Err(KeystoreError::PassphraseRequired(_))
+        ));
+    }
+
+    #[test]
+    fn plaintext_key_is_read_by_any_keystore() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priv_validator_key.json");
+
+        Keystore::default().save(&path, &"secret key").unwrap();
+
+        let key: String = Keystore::with_passphrase("correct horse")
+            .load(&path)
+            .unwrap();
+        assert_eq!(key, "secret key");
+    }
+
+    #[test]
+    fn decryptor_decrypts_keypair() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("node_key");
+        let keypair = Keypair::generate_ed25519();
+
+        let ciphertext = keypair
+            .to_protobuf_encoding()
+            .unwrap()
+            .iter()
+            .map(|byte| !byte)
+            .collect::<Vec<_>>();
+        fs::write(&path, ciphertext).unwrap();
+
+        let keystore = Keystore::with_decryptor(FlipDecryptor);
+        let loaded = keystore.load_keypair(&path).unwrap();
+        assert_eq!(loaded.public(), keypair.public());
+
+        assert!(matches!(
+            keystore.save_keypair(&path, &keypair),
+            Err(KeystoreError::EncryptUnsupported)
+        ));
+    }
+}
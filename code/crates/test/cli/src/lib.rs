@@ -1,11 +1,15 @@
 pub mod args;
+#[cfg(feature = "chaos")]
+pub mod chaos;
 pub mod cmd;
 pub mod error;
 pub mod file;
+pub mod keystore;
 pub mod logging;
 pub mod metrics;
 pub mod new;
 pub mod runtime;
+pub mod telemetry;
 
 pub mod config {
     pub use malachitebft_config::*;
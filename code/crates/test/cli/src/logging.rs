@@ -2,15 +2,20 @@ use std::sync::OnceLock;
 
 use tracing::error;
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::layer::{Layered, SubscriberExt};
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{fmt, reload, Registry};
 
-use malachitebft_config::LogFormat;
+use malachitebft_config::{LogFormat, TelemetryConfig};
+
+use crate::telemetry::{self, TracesGuard};
 
 pub use malachitebft_config::LogLevel;
 pub use tracing_subscriber::filter::EnvFilter;
 
+/// Subscriber the export of the traces is layered on, below the formatting of the logs
+type TracedSubscriber = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+
 static RELOAD_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 static DEFAULT_LOG_LEVEL: OnceLock<String> = OnceLock::new();
 
@@ -27,6 +32,11 @@ pub fn reload(log_level: LogLevel) {
     reload_env_filter(env_filter);
 }
 
+/// Handle on the filter of the logs, to change it at runtime, once logging is initialized
+pub fn reload_handle() -> Option<reload::Handle<EnvFilter, Registry>> {
+    RELOAD_HANDLE.get().cloned()
+}
+
 fn reload_env_filter(env_filter: EnvFilter) {
     if let Some(handle) = RELOAD_HANDLE.get() {
         if let Err(e) = handle.reload(env_filter) {
@@ -37,11 +47,30 @@ fn reload_env_filter(env_filter: EnvFilter) {
     }
 }
 
+/// Drop guard flushing any remaining logs and traces when the program terminates, see [`init`]
+pub struct Guard {
+    _traces: Option<TracesGuard>,
+    _logs: WorkerGuard,
+}
+
 /// Initialize logging.
 ///
 /// Returns a drop guard responsible for flushing any remaining logs when the program terminates.
 /// The guard must be assigned to a binding that is not _, as _ will result in the guard being dropped immediately.
-pub fn init(log_level: LogLevel, log_format: LogFormat) -> WorkerGuard {
+pub fn init(log_level: LogLevel, log_format: LogFormat) -> Guard {
+    init_with_telemetry(log_level, log_format, &TelemetryConfig::default(), "")
+}
+
+/// Initialize logging, and the export of the traces if enabled in the telemetry configuration,
+/// under the given moniker unless the configuration names the service.
+///
+/// The returned guard also flushes the traces not exported yet, see [`init`].
+pub fn init_with_telemetry(
+    log_level: LogLevel,
+    log_format: LogFormat,
+    telemetry: &TelemetryConfig,
+    moniker: &str,
+) -> Guard {
     let log_level = if let Ok(rust_log) = std::env::var("RUST_LOG") {
         rust_log
     } else {
@@ -68,23 +97,39 @@ pub fn init(log_level: LogLevel, log_format: LogFormat) -> WorkerGuard {
         .with_ansi(enable_ansi())
         .with_thread_ids(false);
 
+    let (traces_layer, traces_guard, traces_error) =
+        match telemetry::layer::<TracedSubscriber>(telemetry, moniker) {
+            Ok(Some((layer, guard))) => (Some(layer), Some(guard), None),
+            Ok(None) => (None, None, None),
+            Err(e) => (None, None, Some(e)),
+        };
+
     // There must be a better way to use conditionals in the builder pattern.
     match log_format {
         LogFormat::Plaintext => {
             tracing_subscriber::registry()
                 .with(reload_filter)
+                .with(traces_layer)
                 .with(fmt_layer)
                 .init();
         }
         LogFormat::Json => {
             tracing_subscriber::registry()
                 .with(reload_filter)
+                .with(traces_layer)
                 .with(fmt_layer.json())
                 .init();
         }
     };
 
-    guard
+    if let Some(e) = traces_error {
+        error!("Failed to set up the export of the traces: {e}");
+    }
+
+    Guard {
+        _traces: traces_guard,
+        _logs: guard,
+    }
 }
 
 /// Checks if output is going to a terminal.
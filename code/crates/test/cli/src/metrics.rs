@@ -16,6 +16,13 @@ pub async fn serve(listen_addr: impl ToSocketAddrs) {
 
 async fn inner(listen_addr: impl ToSocketAddrs) -> io::Result<()> {
     let app = Router::new().route("/metrics", get(get_metrics));
+
+    #[cfg(feature = "chaos")]
+    let app = match crate::chaos::router() {
+        Some(chaos) => app.merge(chaos),
+        None => app,
+    };
+
     let listener = TcpListener::bind(listen_addr).await?;
     let local_addr = listener.local_addr()?;
 
@@ -0,0 +1,92 @@
+//! Export of the traces to an OpenTelemetry collector over OTLP, eg. Jaeger,
+//! when built with the `otel` feature.
+//!
+//! The trace context of the messages sent to peers is propagated by the engine,
+//! so that the path of a message across nodes can be followed in a single trace.
+
+use color_eyre::eyre::Result;
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use malachitebft_config::TelemetryConfig;
+
+/// Layer exporting the spans of the subscriber `S`
+pub type TracesLayer<S> = Box<dyn Layer<S> + Send + Sync>;
+
+/// Drop guard flushing the traces not exported yet
+pub struct TracesGuard {
+    #[cfg(feature = "otel")]
+    provider: opentelemetry_sdk::trace::SdkTracerProvider,
+}
+
+impl Drop for TracesGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Failed to flush the traces: {e}");
+        }
+    }
+}
+
+/// Build the layer exporting the traces, if enabled in the configuration.
+///
+/// The traces are reported under the given moniker unless the configuration names the service.
+pub fn layer<S>(
+    config: &TelemetryConfig,
+    moniker: &str,
+) -> Result<Option<(TracesLayer<S>, TracesGuard)>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    #[cfg(feature = "otel")]
+    {
+        otlp_layer(config, moniker).map(Some)
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = moniker;
+        color_eyre::eyre::bail!("Exporting the traces requires the `otel` feature")
+    }
+}
+
+#[cfg(feature = "otel")]
+fn otlp_layer<S>(config: &TelemetryConfig, moniker: &str) -> Result<(TracesLayer<S>, TracesGuard)>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider};
+    use opentelemetry_sdk::Resource;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(config.otlp_endpoint.clone())
+        .build()?;
+
+    let service_name = if config.service_name.is_empty() {
+        moniker.to_string()
+    } else {
+        config.service_name.clone()
+    };
+
+    // Export the traces started by a peer if the peer exports them, so that they are complete
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_ratio)));
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(sampler)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build();
+
+    let tracer = provider.tracer("malachitebft");
+    let layer = tracing_opentelemetry::layer().with_tracer(tracer);
+
+    Ok((Box::new(layer), TracesGuard { provider }))
+}
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use derive_where::derive_where;
+
+use malachitebft_core_consensus::SignedConsensusMsg;
+use malachitebft_core_types::{Context, NilOrVal, Proposal, Round, Value, ValueId, Vote, VoteType};
+
+/// Damage done to the WAL of a node while it is down, as a crash or a faulty disk would
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WalCorruption {
+    /// Cut the given number of bytes off the end of the WAL, as a crash in the middle
+    /// of a write would. The WAL drops the partial entry left when it is opened.
+    Truncate(u64),
+
+    /// Flip the bits of the byte at the given distance from the end of the WAL,
+    /// so that the checksum of the entry it belongs to does not match anymore
+    FlipByte(u64),
+
+    /// Append the given bytes to the WAL, as the partial write of an entry would
+    AppendGarbage(Vec<u8>),
+
+    /// Delete the WAL altogether
+    Delete,
+}
+
+impl WalCorruption {
+    /// Damage the WAL at the given path
+    pub fn apply(&self, path: &Path) -> io::Result<()> {
+        match self {
+            Self::Truncate(bytes) => {
+                let file = OpenOptions::new().write(true).open(path)?;
+                let len = file.metadata()?.len();
+                file.set_len(len.saturating_sub(*bytes))?;
+                file.sync_all()
+            }
+
+            Self::FlipByte(from_end) => {
+                let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+                let len = file.metadata()?.len();
+
+                let Some(offset) = len.checked_sub(from_end + 1) else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("WAL is only {len} bytes long"),
+                    ));
+                };
+
+                let mut byte = [0];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut byte)?;
+
+                file.seek(SeekFrom::Start(offset))?;
+                file.write_all(&[!byte[0]])?;
+                file.sync_all()
+            }
+
+            Self::AppendGarbage(bytes) => {
+                let mut file = OpenOptions::new().append(true).open(path)?;
+                file.write_all(bytes)?;
+                file.sync_all()
+            }
+
+            Self::Delete => fs::remove_file(path),
+        }
+    }
+}
+
+/// Votes and proposals published by a node, kept across its restarts
+/// to check that it never signs two different messages for the same step
+#[derive_where(Default)]
+pub(crate) struct SignedMessages<Ctx: Context> {
+    votes: BTreeMap<(Ctx::Height, Round, VoteType), NilOrVal<ValueId<Ctx>>>,
+    proposals: BTreeMap<(Ctx::Height, Round), ValueId<Ctx>>,
+}
+
+impl<Ctx: Context> SignedMessages<Ctx> {
+    /// Record a message published by the node,
+    /// failing if it published a different one for the same step before
+    pub(crate) fn record(&mut self, msg: &SignedConsensusMsg<Ctx>) -> Result<(), String> {
+        match msg {
+            SignedConsensusMsg::Vote(vote) => {
+                let key = (vote.height(), vote.round(), vote.vote_type());
+                let value = vote.value().clone();
+
+                match self.votes.get(&key) {
+                    Some(signed) if *signed != value => Err(format!(
+                        "Node double-signed a {:?} at height {}, round {}: {signed:?} and {value:?}",
+                        key.2, key.0, key.1
+                    )),
+                    Some(_) => Ok(()),
+                    None => {
+                        self.votes.insert(key, value);
+                        Ok(())
+                    }
+                }
+            }
+
+            SignedConsensusMsg::Proposal(proposal) => {
+                let key = (proposal.height(), proposal.round());
+                let value = proposal.value().id();
+
+                match self.proposals.get(&key) {
+                    Some(signed) if *signed != value => Err(format!(
+                        "Node double-signed a proposal at height {}, round {}: {signed} and {value}",
+                        key.0, key.1
+                    )),
+                    Some(_) => Ok(()),
+                    None => {
+                        self.proposals.insert(key, value);
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use axum::async_trait;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
 use tokio::task::JoinSet;
 use tokio::time::error::Elapsed;
@@ -11,11 +12,13 @@ use tracing::{debug, error, error_span, info, Instrument};
 
 use malachitebft_core_types::{Context, Height};
 
+pub use malachitebft_engine::network::LinkConditions;
 pub use malachitebft_engine::util::events::{Event, RxEvent, TxEvent};
 pub use malachitebft_test::node::{Node, NodeHandle};
 pub use malachitebft_test::traits::{
     CanGeneratePrivateKey, CanMakeConfig, CanMakeGenesis, CanMakePrivateKeyFile,
 };
+pub use malachitebft_test_app::faults::{Fault, Misbehavior};
 
 mod logging;
 use logging::init_logging;
@@ -29,6 +32,22 @@ pub use params::TestParams;
 mod expected;
 pub use expected::Expected;
 
+mod partition;
+pub use partition::Partition;
+
+mod link;
+pub use link::Link;
+
+mod crash;
+use crash::SignedMessages;
+pub use crash::WalCorruption;
+
+mod process;
+pub use process::{Container, Isolation, ProcessCommand, ProcessHandle, SpawnedNode};
+
+pub mod sim;
+pub use sim::Simulation;
+
 use node::Step;
 
 fn unique_id() -> usize {
@@ -42,6 +61,8 @@ where
     Ctx: Context,
 {
     nodes: Vec<TestNode<Ctx, S>>,
+    partitions: Vec<Partition>,
+    links: Vec<Link>,
 }
 
 impl<Ctx, S> Default for TestBuilder<Ctx, S>
@@ -49,7 +70,11 @@ where
     Ctx: Context,
 {
     fn default() -> Self {
-        Self { nodes: Vec::new() }
+        Self {
+            nodes: Vec::new(),
+            partitions: Vec::new(),
+            links: Vec::new(),
+        }
     }
 }
 
@@ -71,8 +96,36 @@ where
         self.nodes.last_mut().unwrap()
     }
 
+    /// Partition the network between two groups of nodes for the given duration,
+    /// see [`Partition`]
+    pub fn partition(
+        &mut self,
+        group_a: &[NodeId],
+        group_b: &[NodeId],
+        duration: Duration,
+    ) -> &mut Partition {
+        self.partitions
+            .push(Partition::new(group_a, group_b, duration));
+        self.partitions.last_mut().unwrap()
+    }
+
+    /// Simulate latency, jitter and loss on the links between two groups of nodes,
+    /// see [`Link`]
+    pub fn degrade_links(
+        &mut self,
+        group_a: &[NodeId],
+        group_b: &[NodeId],
+        conditions: LinkConditions,
+    ) -> &mut Link {
+        self.links.push(Link::new(group_a, group_b, conditions));
+        self.links.last_mut().unwrap()
+    }
+
     pub fn build(self) -> Test<Ctx, S> {
-        Test::new(self.nodes)
+        let mut test = Test::new(self.nodes);
+        test.partitions = self.partitions;
+        test.links = self.links;
+        test
     }
 }
 
@@ -90,6 +143,8 @@ where
 {
     pub id: usize,
     pub nodes: Vec<TestNode<Ctx, S>>,
+    pub partitions: Vec<Partition>,
+    pub links: Vec<Link>,
 }
 
 impl<Ctx, S> Test<Ctx, S>
@@ -100,6 +155,8 @@ where
         Self {
             id: unique_id(),
             nodes,
+            partitions: Vec::new(),
+            links: Vec::new(),
         }
     }
 
@@ -170,10 +227,26 @@ where
     for node in test.nodes {
         let runner = runner.clone();
 
+        let partitions = test
+            .partitions
+            .iter()
+            .filter(|partition| partition.peers_of(node.id).is_some())
+            .cloned()
+            .map(NetworkFault::Partition);
+
+        let links = test
+            .links
+            .iter()
+            .filter(|link| link.peers_of(node.id).is_some())
+            .cloned()
+            .map(NetworkFault::Link);
+
+        let faults = partitions.chain(links).collect();
+
         set.spawn(
             async move {
                 let id = node.id;
-                let result = tokio::time::timeout(timeout, run_node(runner, node)).await;
+                let result = tokio::time::timeout(timeout, run_node(runner, node, faults)).await;
                 (id, result)
             }
             .instrument(span.clone()),
@@ -196,10 +269,95 @@ where
 
     async fn spawn(&self, id: NodeId) -> eyre::Result<Self::NodeHandle>;
     async fn reset_db(&self, id: NodeId) -> eyre::Result<()>;
+
+    /// Block the connections between the node and the given peers for the given duration
+    async fn partition(
+        &self,
+        _handle: &Self::NodeHandle,
+        id: NodeId,
+        _peers: &[NodeId],
+        _duration: Duration,
+    ) -> eyre::Result<()> {
+        eyre::bail!("Network partitions are not supported by this runner (node {id})")
+    }
+
+    /// Simulate the given conditions on the links between the node and the given peers
+    async fn degrade_links(
+        &self,
+        _handle: &Self::NodeHandle,
+        id: NodeId,
+        _peers: &[NodeId],
+        _conditions: LinkConditions,
+    ) -> eyre::Result<()> {
+        eyre::bail!("Degraded links are not supported by this runner (node {id})")
+    }
+
+    /// Pause consensus on the node, or resume it if `paused` is false
+    async fn set_paused(
+        &self,
+        _handle: &Self::NodeHandle,
+        id: NodeId,
+        _paused: bool,
+    ) -> eyre::Result<()> {
+        eyre::bail!("Pausing consensus is not supported by this runner (node {id})")
+    }
+
+    /// Damage the WAL of the node, which is down
+    async fn corrupt_wal(&self, id: NodeId, _corruption: &WalCorruption) -> eyre::Result<()> {
+        eyre::bail!("WAL corruption is not supported by this runner (node {id})")
+    }
+}
+
+/// Fault injected in the network of a node when it starts a given height
+#[derive(Clone, Debug)]
+pub enum NetworkFault {
+    Partition(Partition),
+    Link(Link),
+}
+
+impl NetworkFault {
+    pub fn at_height(&self) -> u64 {
+        match self {
+            Self::Partition(partition) => partition.at_height,
+            Self::Link(link) => link.at_height,
+        }
+    }
+
+    async fn inject<Ctx, R>(
+        &self,
+        runner: &R,
+        handle: &R::NodeHandle,
+        id: NodeId,
+    ) -> eyre::Result<()>
+    where
+        Ctx: Context,
+        R: NodeRunner<Ctx>,
+    {
+        match self {
+            Self::Partition(partition) => {
+                let peers = partition.peers_of(id).unwrap_or_default();
+                info!(?peers, duration = ?partition.duration, "Partitioning node from its peers");
+                runner
+                    .partition(handle, id, peers, partition.duration)
+                    .await
+            }
+            Self::Link(link) => {
+                let peers = link.peers_of(id).unwrap_or_default();
+                info!(?peers, conditions = ?link.conditions, "Degrading the links to the peers");
+                runner
+                    .degrade_links(handle, id, peers, link.conditions)
+                    .await
+            }
+        }
+    }
 }
 
 #[tracing::instrument("node", skip_all, fields(id = %node.id))]
-pub async fn run_node<Ctx, R, S>(runner: R, mut node: TestNode<Ctx, S>) -> TestResult
+pub async fn run_node<Ctx, R, S>(
+    runner: R,
+    mut node: TestNode<Ctx, S>,
+    faults: Vec<NetworkFault>,
+) -> TestResult
 where
     Ctx: Context,
     R: NodeRunner<Ctx>,
@@ -209,28 +367,56 @@ where
 
     info!(%node.voting_power, "Spawning node");
 
-    let mut handle = runner.spawn(node.id).await.unwrap();
+    let mut handle = Arc::new(runner.spawn(node.id).await.unwrap());
 
     let mut rx_event = handle.subscribe();
-    let rx_event_monitor = handle.subscribe();
 
     let decisions = Arc::new(AtomicUsize::new(0));
     let current_height = Arc::new(AtomicUsize::new(0));
     let failure = Arc::new(Mutex::new(None));
     let is_full_node = node.is_full_node();
     let consensus_enabled = node.consensus_enabled;
+    let node_id = node.id;
+    let faults = Arc::new(Mutex::new(faults));
+    let check_double_signing = node.check_double_signing;
+    let signed = Arc::new(Mutex::new(SignedMessages::<Ctx>::default()));
+
+    let spawn_event_monitor = |handle: Arc<R::NodeHandle>| {
+        let mut rx = handle.subscribe();
 
-    let spawn_event_monitor = |mut rx: RxEvent<Ctx>| {
         tokio::spawn({
+            let runner = runner.clone();
             let decisions = Arc::clone(&decisions);
             let current_height = Arc::clone(&current_height);
             let failure = Arc::clone(&failure);
+            let faults = Arc::clone(&faults);
+            let signed = Arc::clone(&signed);
 
             async move {
                 while let Ok(event) = rx.recv().await {
                     match &event {
                         Event::StartedHeight(height, _is_restart) => {
                             current_height.store(height.as_u64() as usize, Ordering::SeqCst);
+
+                            let started = {
+                                let mut pending = faults.lock().await;
+                                let (started, pending_rest): (Vec<_>, Vec<_>) =
+                                    std::mem::take(&mut *pending)
+                                        .into_iter()
+                                        .partition(|f| f.at_height() <= height.as_u64());
+                                *pending = pending_rest;
+                                started
+                            };
+
+                            for fault in started {
+                                let injected = fault.inject::<Ctx, R>(&runner, &handle, node_id);
+
+                                if let Err(e) = injected.await {
+                                    error!("Failed to inject a network fault: {e}");
+                                    *failure.lock().await =
+                                        Some(format!("Failed to inject a network fault: {e}"));
+                                }
+                            }
                         }
                         Event::Decided { .. } => {
                             decisions.fetch_add(1, Ordering::SeqCst);
@@ -247,6 +433,12 @@ where
                                 "Node with consensus disabled unexpectedly published a consensus message: {msg:?}"
                             ));
                         }
+                        Event::Published(msg) if check_double_signing => {
+                            if let Err(e) = signed.lock().await.record(msg) {
+                                error!("{e}");
+                                *failure.lock().await = Some(e);
+                            }
+                        }
                         Event::Received(msg) if !consensus_enabled => {
                             error!("Node with consensus disabled unexpectedly received a consensus message: {msg:?}");
                             *failure.lock().await = Some(format!(
@@ -267,7 +459,7 @@ where
         })
     };
 
-    let mut event_monitor = spawn_event_monitor(rx_event_monitor);
+    let mut event_monitor = spawn_event_monitor(Arc::clone(&handle));
 
     for step in node.steps {
         if let Some(failure) = failure.lock().await.take() {
@@ -331,6 +523,68 @@ where
                     .expect("Node must stop");
             }
 
+            Step::CrashOn(predicate) => {
+                info!("Node will crash on a given event");
+
+                'inner: while let Ok(event) = rx_event.recv().await {
+                    if !predicate(&event) {
+                        continue 'inner;
+                    }
+
+                    info!("Crashing node on event: {event}");
+
+                    // The monitor may be stopped before it sees the message published last
+                    if let Event::Published(msg) = &event {
+                        if check_double_signing {
+                            if let Err(e) = signed.lock().await.record(msg) {
+                                *failure.lock().await = Some(e);
+                            }
+                        }
+                    }
+
+                    break 'inner;
+                }
+
+                event_monitor.abort();
+
+                handle
+                    .kill(Some("Test framework has crashed the node".to_string()))
+                    .await
+                    .expect("Node must stop");
+            }
+
+            Step::CorruptWal(corruption) => {
+                info!(?corruption, "Corrupting WAL");
+
+                if let Err(e) = runner.corrupt_wal(node.id, &corruption).await {
+                    return TestResult::Failure(format!("Failed to corrupt the WAL: {e}"));
+                }
+            }
+
+            Step::Shutdown => {
+                let height = current_height.load(Ordering::SeqCst);
+
+                info!("Node will shut down at height {height}");
+
+                event_monitor.abort();
+
+                handle.shutdown().await.expect("Node must shut down");
+            }
+
+            Step::Pause | Step::Resume => {
+                let paused = matches!(step, Step::Pause);
+                info!(%paused, "Pausing or resuming consensus");
+
+                if let Err(e) = runner.set_paused(&handle, node.id, paused).await {
+                    event_monitor.abort();
+                    handle.kill(Some("Test failed".to_string())).await.unwrap();
+
+                    return TestResult::Failure(format!(
+                        "Failed to pause or resume consensus: {e}"
+                    ));
+                }
+            }
+
             Step::ResetDb => {
                 info!("Resetting database");
                 runner.reset_db(node.id).await.unwrap();
@@ -342,13 +596,12 @@ where
                 sleep(after).await;
 
                 info!("Spawning node");
-                let new_handle = runner.spawn(node.id).await.unwrap();
+                let new_handle = Arc::new(runner.spawn(node.id).await.unwrap());
                 info!("Spawned");
 
                 let new_rx_event = new_handle.subscribe();
-                let new_rx_event_bg = new_handle.subscribe();
 
-                event_monitor = spawn_event_monitor(new_rx_event_bg);
+                event_monitor = spawn_event_monitor(Arc::clone(&new_handle));
                 handle = new_handle;
                 rx_event = new_rx_event;
             }
@@ -389,6 +642,67 @@ where
                 }
             }
 
+            Step::ExpectStall(duration) => {
+                info!("Expecting no decision for {duration:?}");
+
+                let deadline = sleep(duration);
+                tokio::pin!(deadline);
+
+                let decided = loop {
+                    tokio::select! {
+                        _ = &mut deadline => break None,
+                        event = rx_event.recv() => match event {
+                            Ok(Event::Decided { commit_certificate }) => {
+                                break Some(commit_certificate.height);
+                            }
+                            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => {
+                                (&mut deadline).await;
+                                break None;
+                            }
+                        },
+                    }
+                };
+
+                if let Some(height) = decided {
+                    event_monitor.abort();
+                    handle.kill(Some("Test failed".to_string())).await.unwrap();
+
+                    return TestResult::Failure(format!(
+                        "Node decided at height {height} while it was expected to stall"
+                    ));
+                }
+            }
+
+            Step::ExpectProgress(within) => {
+                info!("Expecting a decision within {within:?}");
+
+                let decided = tokio::time::timeout(within, async {
+                    loop {
+                        match rx_event.recv().await {
+                            Ok(Event::Decided { commit_certificate }) => {
+                                break Some(commit_certificate.height);
+                            }
+                            Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => break None,
+                        }
+                    }
+                })
+                .await;
+
+                match decided {
+                    Ok(Some(height)) => info!("Node decided at height {height}"),
+                    Ok(None) | Err(_) => {
+                        event_monitor.abort();
+                        handle.kill(Some("Test failed".to_string())).await.unwrap();
+
+                        return TestResult::Failure(format!(
+                            "Node did not decide within {within:?}"
+                        ));
+                    }
+                }
+            }
+
             Step::Success => {
                 break;
             }
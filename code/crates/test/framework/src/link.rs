@@ -0,0 +1,40 @@
+use malachitebft_engine::network::LinkConditions;
+
+use crate::partition::{other_group, DEFAULT_FAULT_HEIGHT};
+use crate::NodeId;
+
+/// Degraded links between two groups of nodes, eg. two regions of a wide area network.
+///
+/// When it starts its height, every node of a group simulates the given conditions on
+/// the messages it receives from the nodes of the other group, for the rest of the test.
+/// The links within a group are left as they are.
+///
+/// As for a [`Partition`](crate::Partition), only the links to the peers connected when
+/// the degradation starts are affected, and not those to a node restarted afterwards.
+#[derive(Clone, Debug)]
+pub struct Link {
+    pub groups: [Vec<NodeId>; 2],
+    pub at_height: u64,
+    pub conditions: LinkConditions,
+}
+
+impl Link {
+    pub fn new(group_a: &[NodeId], group_b: &[NodeId], conditions: LinkConditions) -> Self {
+        Self {
+            groups: [group_a.to_vec(), group_b.to_vec()],
+            at_height: DEFAULT_FAULT_HEIGHT,
+            conditions,
+        }
+    }
+
+    /// Degrade the links when the nodes start the given height
+    pub fn at_height(&mut self, height: u64) -> &mut Self {
+        self.at_height = height;
+        self
+    }
+
+    /// Nodes the links of the given node are degraded to, `None` if it is in neither group
+    pub fn peers_of(&self, id: NodeId) -> Option<&[NodeId]> {
+        other_group(&self.groups, id)
+    }
+}
@@ -7,13 +7,14 @@ use tracing::info;
 use malachitebft_app::config::NodeConfig;
 use malachitebft_core_consensus::{LocallyProposedValue, MisbehaviorEvidence, SignedConsensusMsg};
 use malachitebft_core_types::{
-    CommitCertificate, Context, Height, SignedVote, Vote, VoteType, VotingPower,
+    CommitCertificate, Context, Height, Proposal, SignedVote, Vote, VoteType, VotingPower,
 };
 use malachitebft_engine::util::events::Event;
 use malachitebft_test::middleware::{DefaultMiddleware, Middleware};
 use malachitebft_test_app::config::Config as TestConfig;
+use malachitebft_test_app::faults::Fault;
 
-use crate::Expected;
+use crate::{Container, Expected, Isolation, WalCorruption};
 
 pub type NodeId = usize;
 pub type ConfigModifier<Config> = Arc<dyn Fn(&mut Config) + Send + Sync>;
@@ -23,12 +24,19 @@ where
     Ctx: Context,
 {
     Crash(Duration),
+    CrashOn(EventPredicate<Ctx>),
+    CorruptWal(WalCorruption),
+    Shutdown,
+    Pause,
+    Resume,
     ResetDb,
     Restart(Duration),
     WaitUntil(u64),
     WaitUntilRound(u32),
     OnEvent(EventHandler<Ctx, S>),
     Expect(Expected),
+    ExpectStall(Duration),
+    ExpectProgress(Duration),
     Success,
     Fail(String),
 }
@@ -42,19 +50,32 @@ pub enum HandlerResult {
 pub type EventHandler<Ctx, S> =
     Box<dyn Fn(Event<Ctx>, &mut S) -> Result<HandlerResult, eyre::Report> + Send + Sync>;
 
+pub type EventPredicate<Ctx> = Box<dyn Fn(&Event<Ctx>) -> bool + Send + Sync>;
+
 pub struct TestNode<Ctx, State = (), Cfg = TestConfig>
 where
     Ctx: Context,
 {
     pub id: NodeId,
     pub voting_power: VotingPower,
+    /// Heights from which the node signs with a new consensus key
+    pub key_rotations: Vec<Ctx::Height>,
+    /// Voting powers of the node from the given heights on, zero taking it out of the validator set
+    pub voting_power_changes: Vec<(Ctx::Height, VotingPower)>,
     pub start_height: Ctx::Height,
     pub start_delay: Duration,
     pub steps: Vec<Step<Ctx, State>>,
     pub state: State,
     pub middleware: Arc<dyn Middleware>,
+    /// Byzantine behaviors of the node
+    pub faults: Vec<Fault>,
     pub config_modifier: ConfigModifier<Cfg>,
     pub consensus_enabled: bool,
+    /// Whether to fail the test if the node signs two different votes or proposals
+    /// for the same step, including across restarts
+    pub check_double_signing: bool,
+    /// Whether the node runs in the process of the test, in a separate process or in a container
+    pub isolation: Isolation,
 }
 
 impl<Ctx, State, Cfg> TestNode<Ctx, State, Cfg>
@@ -73,13 +94,18 @@ where
         Self {
             id,
             voting_power: 1,
+            key_rotations: vec![],
+            voting_power_changes: vec![],
             start_height: Ctx::Height::INITIAL,
             start_delay: Duration::from_secs(0),
             steps: vec![],
             state,
             middleware: Arc::new(DefaultMiddleware),
+            faults: vec![],
             config_modifier: Arc::new(|_config| {}),
             consensus_enabled: true,
+            check_double_signing: false,
+            isolation: Isolation::InProcess,
         }
     }
 
@@ -88,6 +114,27 @@ where
         self
     }
 
+    /// Make the node misbehave as described by the given fault,
+    /// on top of the faults it was given before
+    pub fn with_fault(&mut self, fault: Fault) -> &mut Self {
+        self.faults.push(fault);
+        self
+    }
+
+    /// Run the node as a separate process, talking to the other nodes over real sockets.
+    ///
+    /// Middlewares, faults, partitions and degraded links are only supported in-process.
+    pub fn run_as_process(&mut self) -> &mut Self {
+        self.isolation = Isolation::Process;
+        self
+    }
+
+    /// Run the node as a separate process inside the given container
+    pub fn run_in_container(&mut self, container: Container) -> &mut Self {
+        self.isolation = Isolation::Container(container);
+        self
+    }
+
     pub fn with_state(&mut self, state: State) -> &mut Self {
         self.state = state;
         self
@@ -98,6 +145,31 @@ where
         self
     }
 
+    /// Change the voting power of the node from the given height on,
+    /// adding it to the validator set if it is not a validator yet
+    pub fn with_voting_power_at(&mut self, height: u64, power: VotingPower) -> &mut Self {
+        self.voting_power_changes
+            .push((Ctx::Height::ZERO.increment_by(height), power));
+        self
+    }
+
+    /// Add the node to the validator set with the given voting power from the given height on
+    pub fn join_validator_set_at(&mut self, height: u64, power: VotingPower) -> &mut Self {
+        self.with_voting_power_at(height, power)
+    }
+
+    /// Remove the node from the validator set from the given height on
+    pub fn leave_validator_set_at(&mut self, height: u64) -> &mut Self {
+        self.with_voting_power_at(height, 0)
+    }
+
+    /// Register a new consensus key for the node, which it signs with from the given height on
+    pub fn rotate_key_at(&mut self, height: u64) -> &mut Self {
+        self.key_rotations
+            .push(Ctx::Height::ZERO.increment_by(height));
+        self
+    }
+
     pub fn start(&mut self) -> &mut Self {
         self.start_height = <Ctx::Height>::INITIAL;
         self
@@ -123,6 +195,68 @@ where
         self
     }
 
+    /// Gracefully shut down the node, unlike [`Self::crash`]
+    pub fn shutdown(&mut self) -> &mut Self {
+        self.steps.push(Step::Shutdown);
+        self
+    }
+
+    /// Pause consensus on the node, which keeps running, until [`Self::resume`]
+    pub fn pause(&mut self) -> &mut Self {
+        self.steps.push(Step::Pause);
+        self
+    }
+
+    /// Resume consensus on the node, see [`Self::pause`]
+    pub fn resume(&mut self) -> &mut Self {
+        self.steps.push(Step::Resume);
+        self
+    }
+
+    /// Crash the node as soon as it emits an event matching the given predicate
+    pub fn crash_on<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: Fn(&Event<Ctx>) -> bool + Send + Sync + 'static,
+    {
+        self.steps.push(Step::CrashOn(Box::new(predicate)));
+        self
+    }
+
+    /// Crash the node right after it publishes its first vote of the given type at the given height
+    pub fn crash_after_vote(&mut self, at_height: u64, vote_type: VoteType) -> &mut Self {
+        self.crash_on(move |event| {
+            matches!(
+                event,
+                Event::Published(SignedConsensusMsg::Vote(vote))
+                    if vote.height().as_u64() == at_height && vote.vote_type() == vote_type
+            )
+        })
+    }
+
+    /// Crash the node right after it publishes a proposal at the given height
+    pub fn crash_after_proposal(&mut self, at_height: u64) -> &mut Self {
+        self.crash_on(move |event| {
+            matches!(
+                event,
+                Event::Published(SignedConsensusMsg::Proposal(proposal))
+                    if proposal.height().as_u64() == at_height
+            )
+        })
+    }
+
+    /// Damage the WAL of the node, which must be down, see [`WalCorruption`]
+    pub fn corrupt_wal(&mut self, corruption: WalCorruption) -> &mut Self {
+        self.steps.push(Step::CorruptWal(corruption));
+        self
+    }
+
+    /// Fail the test if the node signs two different votes or proposals for the same step,
+    /// eg. after it lost the messages it signed before a crash
+    pub fn expect_no_double_signing(&mut self) -> &mut Self {
+        self.check_double_signing = true;
+        self
+    }
+
     pub fn reset_db(&mut self) -> &mut Self {
         self.steps.push(Step::ResetDb);
         self
@@ -170,6 +304,47 @@ where
         })
     }
 
+    /// Wait until the node votes, failing if it does so below the given height,
+    /// eg. to check that a node joining the validator set starts voting once it joined
+    pub fn expect_to_vote_from(&mut self, from_height: u64) -> &mut Self {
+        self.on_event(move |event, _| {
+            let Event::Published(SignedConsensusMsg::Vote(vote)) = event else {
+                return Ok(HandlerResult::WaitForNextEvent);
+            };
+
+            let height = vote.height();
+
+            if height.as_u64() < from_height {
+                bail!(
+                    "Unexpected vote at height {height}, expected votes from height {from_height}"
+                )
+            }
+
+            info!(%height, "Node started voting");
+
+            Ok(HandlerResult::ContinueTest)
+        })
+    }
+
+    /// Wait until the node starts the height `until_height`, failing if it votes
+    /// from the height `from_height` on, eg. to check that a node left the validator set
+    pub fn expect_not_voting(&mut self, from_height: u64, until_height: u64) -> &mut Self {
+        self.on_event(move |event, _| match event {
+            Event::Published(SignedConsensusMsg::Vote(vote))
+                if vote.height().as_u64() >= from_height =>
+            {
+                bail!(
+                    "Unexpected vote at height {}, expected no vote from height {from_height}",
+                    vote.height()
+                )
+            }
+            Event::StartedHeight(height, _) if height.as_u64() >= until_height => {
+                Ok(HandlerResult::ContinueTest)
+            }
+            _ => Ok(HandlerResult::WaitForNextEvent),
+        })
+    }
+
     pub fn expect_vote_rebroadcast(
         &mut self,
         at_height: u64,
@@ -351,6 +526,20 @@ where
         self
     }
 
+    /// Fail the test if the node decides on a value within the given duration,
+    /// eg. while it is partitioned from enough voting power to decide
+    pub fn expect_stall(&mut self, duration: Duration) -> &mut Self {
+        self.steps.push(Step::ExpectStall(duration));
+        self
+    }
+
+    /// Fail the test unless the node decides on a value within the given duration,
+    /// eg. once a partition has healed
+    pub fn expect_progress_within(&mut self, duration: Duration) -> &mut Self {
+        self.steps.push(Step::ExpectProgress(duration));
+        self
+    }
+
     pub fn success(&mut self) -> &mut Self {
         self.steps.push(Step::Success);
         self
@@ -361,8 +550,13 @@ where
         self
     }
 
+    /// Whether the node is never part of the validator set
     pub fn is_full_node(&self) -> bool {
         self.voting_power == 0
+            && self
+                .voting_power_changes
+                .iter()
+                .all(|&(_, power)| power == 0)
     }
 
     pub fn with(&mut self, f: impl FnOnce(&mut Self)) -> &mut Self {
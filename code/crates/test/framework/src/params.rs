@@ -8,6 +8,7 @@ use malachitebft_test_app::config::Config;
 #[derive(Clone, Debug)]
 pub struct TestParams {
     pub enable_value_sync: bool,
+    pub enable_vote_sync: bool,
     pub status_update_interval: Duration,
     pub consensus_enabled: bool,
     pub parallel_requests: usize,
@@ -22,6 +23,7 @@ pub struct TestParams {
     pub max_retain_blocks: usize,
     pub stable_block_times: bool,
     pub max_response_size: ByteSize,
+    pub max_chunked_value_size: ByteSize,
     pub enable_discovery: bool,
     /// Node IDs that should not be added as persistent peers for other nodes
     /// (simulates nodes that joined after initial network setup)
@@ -37,6 +39,7 @@ impl Default for TestParams {
     fn default() -> Self {
         Self {
             enable_value_sync: false,
+            enable_vote_sync: false,
             status_update_interval: Duration::from_secs(1),
             consensus_enabled: true,
             parallel_requests: 1,
@@ -51,6 +54,7 @@ impl Default for TestParams {
             max_retain_blocks: 50,
             stable_block_times: true,
             max_response_size: ByteSize::mib(1),
+            max_chunked_value_size: ByteSize::b(0),
             enable_discovery: false,
             exclude_from_persistent_peers: Vec::new(),
             shared_key_group: HashSet::new(),
@@ -62,9 +66,11 @@ impl Default for TestParams {
 impl TestParams {
     pub fn apply_to_config(&self, config: &mut Config) {
         config.value_sync.enabled = self.enable_value_sync;
+        config.value_sync.vote_sync = self.enable_vote_sync;
         config.value_sync.parallel_requests = self.parallel_requests;
         config.value_sync.batch_size = self.batch_size;
         config.value_sync.max_response_size = self.max_response_size;
+        config.value_sync.max_chunked_value_size = self.max_chunked_value_size;
         config.value_sync.status_update_interval = self.status_update_interval;
 
         config.consensus.enabled = self.consensus_enabled;
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use crate::NodeId;
+
+/// Height at which a partition, or degraded links, start by default,
+/// once the nodes are connected to each other
+pub(crate) const DEFAULT_FAULT_HEIGHT: u64 = 2;
+
+/// A network partition between two groups of nodes.
+///
+/// When it starts its height, every node of a group bans the nodes of the other group,
+/// which closes the connections between the groups and denies any new one until the
+/// partition heals, ie. once its duration elapsed.
+///
+/// Only the peers connected when the partition starts are banned, and a node restarted
+/// during the partition gets a new network identity, which is not banned by the others.
+#[derive(Clone, Debug)]
+pub struct Partition {
+    pub groups: [Vec<NodeId>; 2],
+    pub at_height: u64,
+    pub duration: Duration,
+}
+
+impl Partition {
+    pub fn new(group_a: &[NodeId], group_b: &[NodeId], duration: Duration) -> Self {
+        Self {
+            groups: [group_a.to_vec(), group_b.to_vec()],
+            at_height: DEFAULT_FAULT_HEIGHT,
+            duration,
+        }
+    }
+
+    /// Start the partition when the nodes start the given height
+    pub fn at_height(&mut self, height: u64) -> &mut Self {
+        self.at_height = height;
+        self
+    }
+
+    /// Nodes the given node is partitioned from, `None` if it is in neither group
+    pub fn peers_of(&self, id: NodeId) -> Option<&[NodeId]> {
+        other_group(&self.groups, id)
+    }
+}
+
+/// Group the given node is not in, `None` if it is in neither group
+pub(crate) fn other_group(groups: &[Vec<NodeId>; 2], id: NodeId) -> Option<&[NodeId]> {
+    let [a, b] = groups;
+
+    if a.contains(&id) {
+        Some(b)
+    } else if b.contains(&id) {
+        Some(a)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,321 @@
+//! Nodes spawned as separate processes, optionally inside a container, to exercise what
+//! the in-process harness cannot: real sockets, file descriptor limits, process crashes.
+//!
+//! The node process reports its events on its standard output, which the runner decodes
+//! into the same [`Event`]s an in-process node emits, so that the steps and handlers
+//! of a [`TestNode`](crate::TestNode) work unchanged.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use axum::async_trait;
+use eyre::{eyre, WrapErr};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn, Instrument};
+
+use malachitebft_core_types::Context;
+use malachitebft_engine::util::events::{Event, RxEvent, TxEvent};
+use malachitebft_test::node::NodeHandle;
+
+/// How long to wait for a node process to exit after asking it to shut down, before killing it
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a node runs
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Isolation {
+    /// In the process of the test, the default
+    #[default]
+    InProcess,
+
+    /// In a separate process
+    Process,
+
+    /// In a separate process inside a container
+    Container(Container),
+}
+
+impl Isolation {
+    pub fn is_in_process(&self) -> bool {
+        matches!(self, Self::InProcess)
+    }
+}
+
+/// Container a node process runs in.
+///
+/// The container shares the network of the host, so that nodes reach each other
+/// on the same addresses whether they run in a container or not.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Container {
+    /// Command of the container runtime, eg. `docker` or `podman`
+    pub runtime: String,
+
+    /// Image to run the node binary in, which must be compatible with the host binaries
+    pub image: String,
+
+    /// Additional arguments to the `run` command of the container runtime,
+    /// eg. `--ulimit nofile=256` to lower the file descriptor limit of the node
+    pub run_args: Vec<String>,
+}
+
+impl Container {
+    pub fn new(image: impl Into<String>) -> Self {
+        Self {
+            runtime: "docker".to_string(),
+            image: image.into(),
+            run_args: Vec::new(),
+        }
+    }
+
+    pub fn with_runtime(mut self, runtime: impl Into<String>) -> Self {
+        self.runtime = runtime.into();
+        self
+    }
+
+    pub fn with_run_arg(mut self, arg: impl Into<String>) -> Self {
+        self.run_args.push(arg.into());
+        self
+    }
+}
+
+/// Command spawning a node process
+#[derive(Clone, Debug)]
+pub struct ProcessCommand {
+    program: PathBuf,
+    args: Vec<OsString>,
+    envs: Vec<(String, String)>,
+    mounts: Vec<PathBuf>,
+    container: Option<Container>,
+}
+
+impl ProcessCommand {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            envs: Vec::new(),
+            mounts: Vec::new(),
+            container: None,
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Share the given directory with the container, at the same path, if the node runs in one
+    pub fn mount(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.mounts.push(dir.into());
+        self
+    }
+
+    /// Run the node according to the given isolation, which must not be in-process
+    pub fn isolated(mut self, isolation: &Isolation) -> Self {
+        self.container = match isolation {
+            Isolation::Container(container) => Some(container.clone()),
+            Isolation::InProcess | Isolation::Process => None,
+        };
+        self
+    }
+
+    fn command(&self) -> Command {
+        let mut command = match &self.container {
+            None => {
+                let mut command = Command::new(&self.program);
+                command.args(&self.args).envs(self.envs.iter().cloned());
+                command
+            }
+
+            Some(container) => {
+                let mut command = Command::new(&container.runtime);
+                command.args(["run", "--rm", "--interactive", "--network", "host"]);
+
+                let program = self.program.display();
+                command
+                    .arg("--volume")
+                    .arg(format!("{program}:{program}:ro"));
+
+                for dir in &self.mounts {
+                    let dir = dir.display();
+                    command.arg("--volume").arg(format!("{dir}:{dir}"));
+                }
+
+                for (key, value) in &self.envs {
+                    command.arg("--env").arg(format!("{key}={value}"));
+                }
+
+                command
+                    .args(&container.run_args)
+                    .arg(&container.image)
+                    .arg(&self.program)
+                    .args(&self.args);
+
+                command
+            }
+        };
+
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true);
+
+        command
+    }
+}
+
+/// Handle to a node running as a separate process
+pub struct ProcessHandle<Ctx: Context> {
+    child: Mutex<Child>,
+    stdin: Mutex<Option<ChildStdin>>,
+    tx_event: TxEvent<Ctx>,
+    reader: JoinHandle<()>,
+}
+
+impl<Ctx: Context> ProcessHandle<Ctx> {
+    /// Spawn the node process, decoding its events from the lines of its standard output.
+    ///
+    /// `decode` returns `None` for the lines which are not events, which are logged as is.
+    pub fn spawn<F>(command: &ProcessCommand, decode: F) -> eyre::Result<Self>
+    where
+        F: Fn(&str) -> Option<eyre::Result<Event<Ctx>>> + Send + 'static,
+    {
+        let mut child = command
+            .command()
+            .spawn()
+            .wrap_err_with(|| format!("Failed to spawn {}", command.program.display()))?;
+
+        let stdin = child.stdin.take();
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| eyre!("Standard output of the node process is not piped"))?;
+
+        let tx_event = TxEvent::new();
+
+        let reader = tokio::spawn({
+            let tx_event = tx_event.clone();
+
+            async move {
+                let mut lines = BufReader::new(stdout).lines();
+
+                loop {
+                    match lines.next_line().await {
+                        Ok(Some(line)) => match decode(&line) {
+                            Some(Ok(event)) => tx_event.send(|| event),
+                            Some(Err(e)) => error!("Failed to decode event of node process: {e}"),
+                            None => debug!(target: "node_process", "{line}"),
+                        },
+                        Ok(None) => break,
+                        Err(e) => {
+                            warn!("Failed to read the output of node process: {e}");
+                            break;
+                        }
+                    }
+                }
+            }
+            .in_current_span()
+        });
+
+        Ok(Self {
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            tx_event,
+            reader,
+        })
+    }
+
+    /// OS-assigned process ID of the node, unless it has exited
+    pub async fn pid(&self) -> Option<u32> {
+        self.child.lock().await.id()
+    }
+}
+
+#[async_trait]
+impl<Ctx: Context> NodeHandle<Ctx> for ProcessHandle<Ctx> {
+    fn subscribe(&self) -> RxEvent<Ctx> {
+        self.tx_event.subscribe()
+    }
+
+    /// Kill the process abruptly, as a crash would
+    async fn kill(&self, _reason: Option<String>) -> eyre::Result<()> {
+        let mut child = self.child.lock().await;
+        child.kill().await?;
+        self.reader.abort();
+        Ok(())
+    }
+
+    /// Close the standard input of the process, upon which it shuts the node down gracefully,
+    /// and kill it if it does not exit in time
+    async fn shutdown(&self) -> eyre::Result<()> {
+        drop(self.stdin.lock().await.take());
+
+        let mut child = self.child.lock().await;
+
+        match tokio::time::timeout(SHUTDOWN_TIMEOUT, child.wait()).await {
+            Ok(status) => debug!("Node process exited with {}", status?),
+            Err(_) => {
+                warn!("Node process did not exit within {SHUTDOWN_TIMEOUT:?}, killing it");
+                child.kill().await?;
+            }
+        }
+
+        self.reader.abort();
+        Ok(())
+    }
+}
+
+/// Handle to a node spawned in-process or as a separate process, see [`Isolation`]
+pub enum SpawnedNode<Ctx: Context, H> {
+    InProcess(H),
+    Process(ProcessHandle<Ctx>),
+}
+
+impl<Ctx: Context, H> SpawnedNode<Ctx, H> {
+    /// The handle to the node if it runs in-process,
+    /// for the faults which can only be injected in-process
+    pub fn in_process(&self) -> Option<&H> {
+        match self {
+            Self::InProcess(handle) => Some(handle),
+            Self::Process(_) => None,
+        }
+    }
+}
+
+#[async_trait]
+impl<Ctx, H> NodeHandle<Ctx> for SpawnedNode<Ctx, H>
+where
+    Ctx: Context,
+    H: NodeHandle<Ctx>,
+{
+    fn subscribe(&self) -> RxEvent<Ctx> {
+        match self {
+            Self::InProcess(handle) => handle.subscribe(),
+            Self::Process(handle) => handle.subscribe(),
+        }
+    }
+
+    async fn kill(&self, reason: Option<String>) -> eyre::Result<()> {
+        match self {
+            Self::InProcess(handle) => handle.kill(reason).await,
+            Self::Process(handle) => handle.kill(reason).await,
+        }
+    }
+
+    async fn shutdown(&self) -> eyre::Result<()> {
+        match self {
+            Self::InProcess(handle) => handle.shutdown().await,
+            Self::Process(handle) => handle.shutdown().await,
+        }
+    }
+}
@@ -0,0 +1,562 @@
+//! Deterministic discrete-event simulation of a network of validators.
+//!
+//! Unlike the tests run by a [`TestBuilder`](crate::TestBuilder), which spawn whole nodes
+//! talking over the network, a [`Simulation`] drives the consensus state machine of every
+//! validator from a single queue of events ordered by a virtual clock. The delay, and thus the
+//! order, in which the messages are delivered, the messages lost and the values proposed are all
+//! drawn from an RNG seeded with the seed of the run, so that a run can be replayed exactly from
+//! its seed, eg. to debug a failure found by [`Simulation::explore`].
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tracing::{debug, info};
+
+use malachitebft_core_consensus::{
+    process, Effect, Error, Input, LivenessMsg, LocallyProposedValue, Params, ProposedValue,
+    Resumable, Resume, SignedConsensusMsg, State,
+};
+use malachitebft_core_types::{
+    CommitCertificate, LinearTimeouts, Round, SignedProposal, SignedVote, Timeout, Validity,
+    ValueOrigin, ValuePayload, VotingPower,
+};
+use malachitebft_metrics::Metrics;
+use malachitebft_test::{
+    Height, PrivateKey, Signature, TestContext, Validator, ValidatorSet, Value, ValueId,
+};
+
+/// Capacity of the queue of messages for future heights of every validator
+const QUEUE_CAPACITY: usize = 1000;
+
+/// A network of validators running consensus in a simulation
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    voting_powers: Vec<VotingPower>,
+    latency: (Duration, Duration),
+    loss: f64,
+    propose_delay: Duration,
+    timeouts: LinearTimeouts,
+    max_time: Duration,
+}
+
+impl Simulation {
+    /// A network of validators with the given voting powers
+    pub fn new(voting_powers: impl IntoIterator<Item = VotingPower>) -> Self {
+        Self {
+            voting_powers: voting_powers.into_iter().collect(),
+            latency: (Duration::from_millis(10), Duration::from_millis(100)),
+            loss: 0.0,
+            propose_delay: Duration::from_millis(50),
+            timeouts: LinearTimeouts::default(),
+            max_time: Duration::from_secs(3600),
+        }
+    }
+
+    /// Deliver every message after a delay drawn uniformly between `min` and `max`
+    pub fn with_latency(mut self, min: Duration, max: Duration) -> Self {
+        self.latency = (min, max.max(min));
+        self
+    }
+
+    /// Drop every message with the given probability, between 0 and 1
+    pub fn with_loss(mut self, loss: f64) -> Self {
+        self.loss = loss.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Time taken by the proposers to build a value
+    pub fn with_propose_delay(mut self, delay: Duration) -> Self {
+        self.propose_delay = delay;
+        self
+    }
+
+    pub fn with_timeouts(mut self, timeouts: LinearTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Stop the simulation once the virtual clock reaches the given time
+    pub fn with_max_time(mut self, max_time: Duration) -> Self {
+        self.max_time = max_time;
+        self
+    }
+
+    /// Run the simulation with the given seed until every validator decided the given height,
+    /// or the virtual clock reached the maximum time
+    pub fn run(&self, seed: u64, until_height: u64) -> SimReport {
+        Runtime::new(self, seed).run(until_height)
+    }
+
+    /// Run the simulation with each of the given seeds, until the check fails for one of them.
+    ///
+    /// The seed of the failing run is returned along with the error, to replay it with [`Self::run`].
+    pub fn explore<F>(
+        &self,
+        seeds: impl IntoIterator<Item = u64>,
+        until_height: u64,
+        check: F,
+    ) -> Result<(), SimFailure>
+    where
+        F: Fn(&SimReport) -> Result<(), String>,
+    {
+        for seed in seeds {
+            let report = self.run(seed, until_height);
+
+            if let Err(error) = check(&report) {
+                return Err(SimFailure { seed, error });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A run of the simulation which failed the check of [`Simulation::explore`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimFailure {
+    pub seed: u64,
+    pub error: String,
+}
+
+impl fmt::Display for SimFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Simulation with seed {} failed: {}",
+            self.seed, self.error
+        )
+    }
+}
+
+/// A value decided by a validator
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decision {
+    pub height: Height,
+    pub round: Round,
+    pub value_id: ValueId,
+    /// Time of the virtual clock at which the value was decided
+    pub at: Duration,
+}
+
+/// Outcome of a run of the simulation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SimReport {
+    pub seed: u64,
+    /// Values decided by every validator, in the order of the validators
+    pub decisions: Vec<Vec<Decision>>,
+    /// Every event processed by the validators, in order, which is the same for every run
+    /// with the same seed
+    pub trace: Vec<String>,
+    /// Time of the virtual clock at the end of the run
+    pub elapsed: Duration,
+}
+
+impl SimReport {
+    /// Lowest height decided by all the validators
+    pub fn decided_height(&self) -> u64 {
+        self.decisions
+            .iter()
+            .map(|decisions| decisions.last().map_or(0, |d| d.height.as_u64()))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Check that the validators decided the same value at every height
+    pub fn check_agreement(&self) -> Result<(), String> {
+        let mut decided = Vec::<&Decision>::new();
+
+        for (validator, decisions) in self.decisions.iter().enumerate() {
+            for decision in decisions {
+                match decided.iter().find(|d| d.height == decision.height) {
+                    Some(other) if other.value_id != decision.value_id => {
+                        return Err(format!(
+                            "Validator {validator} decided {} at height {}, while {} was decided",
+                            decision.value_id, decision.height, other.value_id
+                        ));
+                    }
+                    Some(_) => (),
+                    None => decided.push(decision),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that every validator decided at least the given height
+    pub fn check_progress(&self, height: u64) -> Result<(), String> {
+        let decided = self.decided_height();
+
+        if decided < height {
+            return Err(format!(
+                "Validators only decided up to height {decided} in {:?}, expected {height}",
+                self.elapsed
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum SimEvent {
+    StartHeight(Height),
+    Deliver(Input<TestContext>),
+    Propose(Height, Round, Value),
+    Timeout(Height, Timeout),
+}
+
+struct Scheduled {
+    at: Duration,
+    /// Breaks the ties between events scheduled at the same time, in the order they were scheduled
+    seq: u64,
+    node: usize,
+    event: SimEvent,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+/// What consensus asked for while processing an input, applied once it is done
+enum Action {
+    Broadcast(Input<TestContext>),
+    ScheduleTimeout(Timeout),
+    CancelTimeout(Timeout),
+    CancelAllTimeouts,
+    GetValue(Height, Round),
+    Decide(CommitCertificate<TestContext>),
+}
+
+struct SimNode {
+    state: State<TestContext>,
+    timeouts: HashSet<Timeout>,
+    decisions: Vec<Decision>,
+}
+
+struct Runtime<'a> {
+    sim: &'a Simulation,
+    seed: u64,
+    rng: StdRng,
+    now: Duration,
+    seq: u64,
+    queue: BinaryHeap<Reverse<Scheduled>>,
+    validator_set: ValidatorSet,
+    nodes: Vec<SimNode>,
+    metrics: Metrics,
+    trace: Vec<String>,
+}
+
+impl<'a> Runtime<'a> {
+    fn new(sim: &'a Simulation, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let validators = sim
+            .voting_powers
+            .iter()
+            .map(|&power| Validator::new(PrivateKey::generate(&mut rng).public_key(), power))
+            .collect::<Vec<_>>();
+
+        let validator_set = ValidatorSet::new(validators.clone());
+
+        let nodes = validators
+            .iter()
+            .map(|validator| SimNode {
+                state: State::new(
+                    TestContext::new(),
+                    Height::new(1),
+                    validator_set.clone(),
+                    Params {
+                        address: validator.address,
+                        threshold_params: Default::default(),
+                        value_payload: ValuePayload::ProposalOnly,
+                        enabled: true,
+                        max_votes_per_validator_per_round: None,
+                        follower: false,
+                    },
+                    QUEUE_CAPACITY,
+                ),
+                timeouts: HashSet::new(),
+                decisions: Vec::new(),
+            })
+            .collect();
+
+        Self {
+            sim,
+            seed,
+            rng,
+            now: Duration::ZERO,
+            seq: 0,
+            queue: BinaryHeap::new(),
+            validator_set,
+            nodes,
+            metrics: Metrics::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    fn run(mut self, until_height: u64) -> SimReport {
+        info!(
+            seed = self.seed,
+            validators = self.nodes.len(),
+            "Starting simulation"
+        );
+
+        for node in 0..self.nodes.len() {
+            self.schedule(Duration::ZERO, node, SimEvent::StartHeight(Height::new(1)));
+        }
+
+        while let Some(Reverse(scheduled)) = self.queue.pop() {
+            if scheduled.at > self.sim.max_time || self.all_decided(until_height) {
+                break;
+            }
+
+            self.now = scheduled.at;
+            self.dispatch(scheduled.node, scheduled.event);
+        }
+
+        info!(seed = self.seed, elapsed = ?self.now, "Simulation done");
+
+        SimReport {
+            seed: self.seed,
+            decisions: self.nodes.into_iter().map(|node| node.decisions).collect(),
+            trace: self.trace,
+            elapsed: self.now,
+        }
+    }
+
+    fn all_decided(&self, height: u64) -> bool {
+        self.nodes.iter().all(|node| {
+            node.decisions
+                .last()
+                .is_some_and(|d| d.height.as_u64() >= height)
+        })
+    }
+
+    fn schedule(&mut self, delay: Duration, node: usize, event: SimEvent) {
+        self.seq += 1;
+
+        self.queue.push(Reverse(Scheduled {
+            at: self.now + delay,
+            seq: self.seq,
+            node,
+            event,
+        }));
+    }
+
+    fn dispatch(&mut self, node: usize, event: SimEvent) {
+        let height = self.nodes[node].state.height();
+
+        let input = match event {
+            SimEvent::StartHeight(height) => {
+                self.nodes[node].timeouts.clear();
+                Input::StartHeight(height, self.validator_set.clone(), false, None)
+            }
+            SimEvent::Deliver(input) => input,
+            SimEvent::Propose(at_height, round, value) if at_height == height => {
+                Input::Propose(LocallyProposedValue::new(height, round, value))
+            }
+            SimEvent::Timeout(at_height, timeout)
+                if at_height == height && self.nodes[node].timeouts.remove(&timeout) =>
+            {
+                Input::TimeoutElapsed(timeout)
+            }
+            // Stale value or timeout, for a past height or a cancelled timeout
+            SimEvent::Propose(..) | SimEvent::Timeout(..) => return,
+        };
+
+        debug!(node, at = ?self.now, "Processing input: {input:?}");
+        self.trace.push(format!("{:?} {node} {input:?}", self.now));
+
+        for action in self.process(node, input) {
+            self.apply(node, action);
+        }
+    }
+
+    fn process(&mut self, node: usize, input: Input<TestContext>) -> Vec<Action> {
+        let mut actions = Vec::new();
+
+        let result: Result<(), Error<TestContext>> = process!(
+            input: input,
+            state: &mut self.nodes[node].state,
+            metrics: &self.metrics,
+            with: effect => handle_effect(effect, &mut actions)
+        );
+
+        if let Err(e) = result {
+            self.trace.push(format!("{:?} {node} error: {e}", self.now));
+        }
+
+        actions
+    }
+
+    fn apply(&mut self, node: usize, action: Action) {
+        match action {
+            Action::Broadcast(input) => {
+                for peer in (0..self.nodes.len()).filter(|&peer| peer != node) {
+                    if self.sim.loss > 0.0 && self.rng.gen_bool(self.sim.loss) {
+                        continue;
+                    }
+
+                    let (min, max) = self.sim.latency;
+                    let delay = self.rng.gen_range(min..=max);
+
+                    self.schedule(delay, peer, SimEvent::Deliver(input.clone()));
+
+                    // In this simulation, proposals carry their value, which is always valid
+                    if let Input::Proposal(proposal) = &input {
+                        let value = ProposedValue {
+                            height: proposal.height,
+                            round: proposal.round,
+                            valid_round: proposal.pol_round,
+                            proposer: proposal.validator_address,
+                            value: proposal.value.clone(),
+                            validity: Validity::Valid,
+                        };
+
+                        let input = Input::ProposedValue(value, ValueOrigin::Consensus);
+                        self.schedule(delay, peer, SimEvent::Deliver(input));
+                    }
+                }
+            }
+
+            Action::ScheduleTimeout(timeout) => {
+                let height = self.nodes[node].state.height();
+                let delay = self.sim.timeouts.duration_for(timeout);
+
+                self.nodes[node].timeouts.insert(timeout);
+                self.schedule(delay, node, SimEvent::Timeout(height, timeout));
+            }
+
+            Action::CancelTimeout(timeout) => {
+                self.nodes[node].timeouts.remove(&timeout);
+            }
+
+            Action::CancelAllTimeouts => {
+                self.nodes[node].timeouts.clear();
+            }
+
+            Action::GetValue(height, round) => {
+                let value = Value::new(self.rng.gen());
+                let delay = self.sim.propose_delay;
+
+                self.schedule(delay, node, SimEvent::Propose(height, round, value));
+            }
+
+            Action::Decide(certificate) => {
+                let height = certificate.height;
+
+                self.nodes[node].decisions.push(Decision {
+                    height,
+                    round: certificate.round,
+                    value_id: certificate.value_id,
+                    at: self.now,
+                });
+
+                self.schedule(
+                    Duration::ZERO,
+                    node,
+                    SimEvent::StartHeight(height.increment()),
+                );
+            }
+        }
+    }
+}
+
+/// Handle an effect of consensus, recording what it asks for in `actions`.
+///
+/// Signatures are not checked, the validators of a simulation being all correct.
+fn handle_effect(
+    effect: Effect<TestContext>,
+    actions: &mut Vec<Action>,
+) -> Result<Resume<TestContext>, eyre::Report> {
+    let resume = match effect {
+        Effect::CancelAllTimeouts(r) => {
+            actions.push(Action::CancelAllTimeouts);
+            r.resume_with(())
+        }
+        Effect::CancelTimeout(timeout, r) => {
+            actions.push(Action::CancelTimeout(timeout));
+            r.resume_with(())
+        }
+        Effect::ScheduleTimeout(timeout, r) => {
+            actions.push(Action::ScheduleTimeout(timeout));
+            r.resume_with(())
+        }
+        Effect::PublishConsensusMsg(msg, r) => {
+            let input = match msg {
+                SignedConsensusMsg::Vote(vote) => Input::Vote(vote),
+                SignedConsensusMsg::Proposal(proposal) => Input::Proposal(proposal),
+            };
+
+            actions.push(Action::Broadcast(input));
+            r.resume_with(())
+        }
+        Effect::PublishLivenessMsg(msg, r) => {
+            let input = match msg {
+                LivenessMsg::Vote(vote) => Input::Vote(vote),
+                LivenessMsg::PolkaCertificate(certificate) => Input::PolkaCertificate(certificate),
+                LivenessMsg::SkipRoundCertificate(certificate) => {
+                    Input::RoundCertificate(certificate)
+                }
+            };
+
+            actions.push(Action::Broadcast(input));
+            r.resume_with(())
+        }
+        Effect::RepublishVote(vote, r) => {
+            actions.push(Action::Broadcast(Input::Vote(vote)));
+            r.resume_with(())
+        }
+        Effect::RepublishRoundCertificate(certificate, r) => {
+            actions.push(Action::Broadcast(Input::RoundCertificate(certificate)));
+            r.resume_with(())
+        }
+        Effect::GetValue(height, round, _timeout, r) => {
+            actions.push(Action::GetValue(height, round));
+            r.resume_with(())
+        }
+        Effect::Decide(certificate, _extensions, _proof, r) => {
+            actions.push(Action::Decide(certificate));
+            r.resume_with(())
+        }
+        Effect::SignVote(vote, r) => r.resume_with(SignedVote::new(vote, Signature::test())),
+        Effect::SignProposal(proposal, r) => {
+            r.resume_with(SignedProposal::new(proposal, Signature::test()))
+        }
+        Effect::VerifySignature(_, _, r) => r.resume_with(true),
+        Effect::VerifyCommitCertificate(_, _, _, r)
+        | Effect::VerifyPolkaCertificate(_, _, _, r)
+        | Effect::VerifyRoundCertificate(_, _, _, r) => r.resume_with(Ok(())),
+        Effect::ExtendVote(_, _, _, r) => r.resume_with(None),
+        Effect::VerifyVoteExtension(_, _, _, _, _, r) => r.resume_with(Ok(())),
+        Effect::StartRound(_, _, _, _, r)
+        | Effect::RestreamProposal(_, _, _, _, _, r)
+        | Effect::ValidSyncValue(_, _, r)
+        | Effect::InvalidSyncValue(_, _, _, r)
+        | Effect::Finalize(_, _, _, r)
+        | Effect::WalAppend(_, _, r) => r.resume_with(()),
+    };
+
+    Ok(resume)
+}
@@ -0,0 +1,281 @@
+//! Codec encoding messages as deterministic CBOR (RFC 8949, section 4.2), for the WAL,
+//! the network and sync, ie. implementing `WalCodec`, `ConsensusCodec` and `SyncCodec`.
+//! It is used in place of the other codecs by passing it to `WalContext`,
+//! `NetworkContext` or `SyncContext` when starting the engine.
+//!
+//! The messages are mapped to CBOR through the same serde representations as the JSON codec.
+//! Integers are encoded in their shortest form, all lengths are definite, and the entries
+//! of maps are sorted by the bytewise order of their encoded keys, so that a given message
+//! always has a single encoding.
+
+use bytes::Bytes;
+use ciborium::Value as CborValue;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use malachitebft_codec::{Codec, HasEncodedLen};
+use malachitebft_core_consensus::{LivenessMsg, ProposedValue, SignedConsensusMsg};
+use malachitebft_core_types::{CommitCertificate, ValidatorProof};
+use malachitebft_engine::util::streaming::StreamMessage;
+use malachitebft_sync::{Request, Response, Status};
+
+use super::json::raw::{
+    RawCommitCertificate, RawLivenessMsg, RawProposedValue, RawRequest, RawResponse,
+    RawSignedConsensusMsg, RawStatus, RawStreamMessage, RawValidatorProof,
+};
+use crate::{ProposalPart, TestContext, Value};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CborError {
+    #[error("Failed to encode to CBOR: {0}")]
+    Encode(String),
+
+    #[error("Failed to decode from CBOR: {0}")]
+    Decode(String),
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct CborCodec;
+
+/// Encode a message as deterministic CBOR
+pub fn to_canonical_vec<T: Serialize>(msg: &T) -> Result<Vec<u8>, CborError> {
+    let mut value = CborValue::serialized(msg).map_err(|e| CborError::Encode(e.to_string()))?;
+
+    canonicalize(&mut value)?;
+
+    let mut bytes = Vec::new();
+    ciborium::into_writer(&value, &mut bytes).map_err(|e| CborError::Encode(e.to_string()))?;
+    Ok(bytes)
+}
+
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CborError> {
+    ciborium::from_reader(bytes).map_err(|e| CborError::Decode(e.to_string()))
+}
+
+/// Sort the entries of all maps by their encoded keys
+fn canonicalize(value: &mut CborValue) -> Result<(), CborError> {
+    match value {
+        CborValue::Array(items) => items.iter_mut().try_for_each(canonicalize),
+        CborValue::Tag(_, inner) => canonicalize(inner),
+        CborValue::Map(entries) => {
+            let mut keyed = Vec::with_capacity(entries.len());
+
+            for (mut key, mut value) in entries.drain(..) {
+                canonicalize(&mut key)?;
+                canonicalize(&mut value)?;
+
+                let mut encoded = Vec::new();
+                ciborium::into_writer(&key, &mut encoded)
+                    .map_err(|e| CborError::Encode(e.to_string()))?;
+
+                keyed.push((encoded, key, value));
+            }
+
+            keyed.sort_by(|a, b| a.0.cmp(&b.0));
+            entries.extend(keyed.into_iter().map(|(_, key, value)| (key, value)));
+
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Codec<Value> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<Value, Self::Error> {
+        from_slice(&bytes)
+    }
+
+    fn encode(&self, msg: &Value) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(msg).map(Bytes::from)
+    }
+}
+
+impl Codec<ProposalPart> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<ProposalPart, Self::Error> {
+        from_slice(&bytes)
+    }
+
+    fn encode(&self, msg: &ProposalPart) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(msg).map(Bytes::from)
+    }
+}
+
+impl Codec<SignedConsensusMsg<TestContext>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<SignedConsensusMsg<TestContext>, Self::Error> {
+        from_slice::<RawSignedConsensusMsg>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &SignedConsensusMsg<TestContext>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawSignedConsensusMsg::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<ProposedValue<TestContext>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<ProposedValue<TestContext>, Self::Error> {
+        from_slice::<RawProposedValue>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &ProposedValue<TestContext>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawProposedValue::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<StreamMessage<ProposalPart>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<StreamMessage<ProposalPart>, Self::Error> {
+        from_slice::<RawStreamMessage>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &StreamMessage<ProposalPart>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawStreamMessage::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<LivenessMsg<TestContext>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<LivenessMsg<TestContext>, Self::Error> {
+        from_slice::<RawLivenessMsg>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &LivenessMsg<TestContext>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawLivenessMsg::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<ValidatorProof<TestContext>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<ValidatorProof<TestContext>, Self::Error> {
+        from_slice::<RawValidatorProof>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &ValidatorProof<TestContext>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawValidatorProof::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<CommitCertificate<TestContext>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<CommitCertificate<TestContext>, Self::Error> {
+        from_slice::<RawCommitCertificate>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &CommitCertificate<TestContext>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawCommitCertificate::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<Status<TestContext>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<Status<TestContext>, Self::Error> {
+        from_slice::<RawStatus>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &Status<TestContext>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawStatus::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<Request<TestContext>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<Request<TestContext>, Self::Error> {
+        from_slice::<RawRequest>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &Request<TestContext>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawRequest::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<Response<TestContext>> for CborCodec {
+    type Error = CborError;
+
+    fn decode(&self, bytes: Bytes) -> Result<Response<TestContext>, Self::Error> {
+        from_slice::<RawResponse>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &Response<TestContext>) -> Result<Bytes, Self::Error> {
+        to_canonical_vec(&RawResponse::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl HasEncodedLen<Response<TestContext>> for CborCodec {
+    fn encoded_len(
+        &self,
+        msg: &Response<TestContext>,
+    ) -> Result<usize, <Self as Codec<Response<TestContext>>>::Error> {
+        to_canonical_vec(&RawResponse::from(msg.clone())).map(|bytes| bytes.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use malachitebft_core_types::{NilOrVal, Round, SignedVote, Validity};
+    use malachitebft_signing_ed25519::Signature;
+
+    use super::*;
+    use crate::{Address, Height, ValueId, Vote};
+
+    #[test]
+    fn map_entries_are_sorted_by_encoded_key() {
+        // Shorter keys encode to smaller bytes, then keys of the same length sort bytewise
+        let map = BTreeMap::from([("bb", 1), ("a", 2), ("ab", 3)]);
+
+        let encoded = to_canonical_vec(&map).unwrap();
+
+        assert_eq!(
+            encoded,
+            [
+                0xa3, // map(3)
+                0x61, b'a', 0x02, // "a": 2
+                0x62, b'a', b'b', 0x03, // "ab": 3
+                0x62, b'b', b'b', 0x01, // "bb": 1
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_decode_wal_entries() {
+        let vote = Vote::new_precommit(
+            Height::new(1),
+            Round::new(0),
+            NilOrVal::Val(ValueId::new(42)),
+            Address::new([1; 20]),
+        );
+        let msg = SignedConsensusMsg::Vote(SignedVote::new(vote, Signature::from_bytes([2; 64])));
+
+        let encoded = CborCodec.encode(&msg).unwrap();
+        assert_eq!(CborCodec.encode(&msg).unwrap(), encoded);
+
+        let decoded: SignedConsensusMsg<TestContext> = CborCodec.decode(encoded).unwrap();
+        assert_eq!(decoded, msg);
+
+        let value = ProposedValue {
+            height: Height::new(1),
+            round: Round::new(0),
+            valid_round: Round::Nil,
+            proposer: Address::new([1; 20]),
+            value: Value::new(42),
+            validity: Validity::Valid,
+        };
+
+        let encoded = CborCodec.encode(&value).unwrap();
+        let decoded: ProposedValue<TestContext> = CborCodec.decode(encoded).unwrap();
+        assert_eq!(decoded, value);
+    }
+}
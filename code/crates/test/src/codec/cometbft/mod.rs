@@ -0,0 +1,403 @@
+//! Codec encoding proposals, votes and commit certificates with the protobuf schemas of CometBFT,
+//! so that tooling built for CometBFT (block explorers, relayers, signature verifiers)
+//! can parse the artifacts produced by Malachite without a custom decoder.
+//!
+//! - Votes are encoded as `tendermint.types.Vote`, with their signature and extension.
+//! - Proposals are encoded as `tendermint.types.Proposal`, with the value and the address of
+//!   the proposer in fields numbered from 100, which CometBFT decoders skip.
+//! - Consensus messages are wrapped in `tendermint.consensus.Message`, as on CometBFT consensus channel.
+//! - Commit certificates are encoded as `tendermint.types.Commit`.
+//!
+//! The id of the value takes the place of the hash of the block, and timestamps are never set,
+//! as Malachite has no notion of them. Signatures are the ones of Malachite, made over
+//! its own sign bytes rather than the canonical sign bytes of CometBFT.
+
+use bytes::Bytes;
+use prost::{Message, Name};
+
+use malachitebft_codec::Codec;
+use malachitebft_core_consensus::SignedConsensusMsg;
+use malachitebft_core_types::{
+    CommitCertificate, CommitSignature, NilOrVal, Round, SignedExtension, SignedProposal,
+    SignedVote, VoteType,
+};
+use malachitebft_proto::{Error as ProtoError, Protobuf};
+use malachitebft_signing_ed25519::Signature;
+
+use crate::proto::tendermint::{consensus, types};
+use crate::{Address, Height, Proposal, TestContext, Value, ValueId, Vote};
+
+#[derive(Copy, Clone, Debug)]
+pub struct CometBftCodec;
+
+impl Codec<SignedConsensusMsg<TestContext>> for CometBftCodec {
+    type Error = ProtoError;
+
+    fn decode(&self, bytes: Bytes) -> Result<SignedConsensusMsg<TestContext>, Self::Error> {
+        let msg = consensus::Message::decode(bytes)?;
+
+        match msg.sum {
+            Some(consensus::message::Sum::Proposal(msg)) => {
+                let proposal = msg.proposal.ok_or_else(|| {
+                    ProtoError::missing_field::<consensus::ProposalMessage>("proposal")
+                })?;
+
+                decode_proposal(proposal).map(SignedConsensusMsg::Proposal)
+            }
+            Some(consensus::message::Sum::Vote(msg)) => {
+                let vote = msg
+                    .vote
+                    .ok_or_else(|| ProtoError::missing_field::<consensus::VoteMessage>("vote"))?;
+
+                decode_vote(vote).map(SignedConsensusMsg::Vote)
+            }
+            None => Err(ProtoError::missing_field::<consensus::Message>("sum")),
+        }
+    }
+
+    fn encode(&self, msg: &SignedConsensusMsg<TestContext>) -> Result<Bytes, Self::Error> {
+        let sum = match msg {
+            SignedConsensusMsg::Proposal(proposal) => {
+                consensus::message::Sum::Proposal(consensus::ProposalMessage {
+                    proposal: Some(encode_proposal(proposal)?),
+                })
+            }
+            SignedConsensusMsg::Vote(vote) => {
+                consensus::message::Sum::Vote(consensus::VoteMessage {
+                    vote: Some(encode_vote(vote)),
+                })
+            }
+        };
+
+        Ok(Bytes::from(
+            consensus::Message { sum: Some(sum) }.encode_to_vec(),
+        ))
+    }
+}
+
+impl Codec<CommitCertificate<TestContext>> for CometBftCodec {
+    type Error = ProtoError;
+
+    fn decode(&self, bytes: Bytes) -> Result<CommitCertificate<TestContext>, Self::Error> {
+        decode_commit(types::Commit::decode(bytes)?)
+    }
+
+    fn encode(&self, certificate: &CommitCertificate<TestContext>) -> Result<Bytes, Self::Error> {
+        Ok(Bytes::from(encode_commit(certificate).encode_to_vec()))
+    }
+}
+
+pub fn encode_vote(vote: &SignedVote<TestContext>) -> types::Vote {
+    let typ = match vote.typ {
+        VoteType::Prevote => types::SignedMsgType::Prevote,
+        VoteType::Precommit => types::SignedMsgType::Precommit,
+    };
+
+    let (extension, extension_signature) = match &vote.extension {
+        Some(extension) => (
+            extension.message.clone(),
+            encode_signature(&extension.signature),
+        ),
+        None => (Bytes::new(), Bytes::new()),
+    };
+
+    types::Vote {
+        r#type: typ.into(),
+        height: encode_height(vote.height),
+        round: encode_round(vote.round),
+        block_id: Some(encode_block_id(&vote.value)),
+        validator_address: encode_address(&vote.validator_address),
+        validator_index: 0,
+        signature: encode_signature(&vote.signature),
+        extension,
+        extension_signature,
+    }
+}
+
+pub fn decode_vote(vote: types::Vote) -> Result<SignedVote<TestContext>, ProtoError> {
+    let typ = match vote.r#type() {
+        types::SignedMsgType::Prevote => VoteType::Prevote,
+        types::SignedMsgType::Precommit => VoteType::Precommit,
+        _ => return Err(ProtoError::invalid_data::<types::Vote>("type")),
+    };
+
+    let extension = if vote.extension_signature.is_empty() {
+        None
+    } else {
+        Some(SignedExtension::new(
+            vote.extension,
+            decode_signature::<types::Vote>(&vote.extension_signature)?,
+        ))
+    };
+
+    let message = Vote {
+        typ,
+        height: decode_height::<types::Vote>(vote.height)?,
+        round: decode_round::<types::Vote>(vote.round)?,
+        value: decode_block_id(vote.block_id)?,
+        validator_address: decode_address::<types::Vote>(&vote.validator_address)?,
+        extension,
+    };
+
+    let signature = decode_signature::<types::Vote>(&vote.signature)?;
+
+    Ok(SignedVote::new(message, signature))
+}
+
+pub fn encode_proposal(
+    proposal: &SignedProposal<TestContext>,
+) -> Result<types::Proposal, ProtoError> {
+    Ok(types::Proposal {
+        r#type: types::SignedMsgType::Proposal.into(),
+        height: encode_height(proposal.height),
+        round: encode_round(proposal.round),
+        pol_round: encode_round(proposal.pol_round),
+        block_id: Some(encode_block_id(&NilOrVal::Val(proposal.value.id()))),
+        signature: encode_signature(&proposal.signature),
+        value: proposal.value.to_bytes()?,
+        proposer_address: encode_address(&proposal.validator_address),
+    })
+}
+
+pub fn decode_proposal(
+    proposal: types::Proposal,
+) -> Result<SignedProposal<TestContext>, ProtoError> {
+    if proposal.r#type() != types::SignedMsgType::Proposal {
+        return Err(ProtoError::invalid_data::<types::Proposal>("type"));
+    }
+
+    let value = Value::from_bytes(&proposal.value)?;
+
+    // The value is carried along its id, which must be the one of the value
+    if decode_block_id(proposal.block_id)? != NilOrVal::Val(value.id()) {
+        return Err(ProtoError::invalid_data::<types::Proposal>("block_id"));
+    }
+
+    let message = Proposal::new(
+        decode_height::<types::Proposal>(proposal.height)?,
+        decode_round::<types::Proposal>(proposal.round)?,
+        value,
+        Round::from(i64::from(proposal.pol_round)),
+        decode_address::<types::Proposal>(&proposal.proposer_address)?,
+    );
+
+    let signature = decode_signature::<types::Proposal>(&proposal.signature)?;
+
+    Ok(SignedProposal::new(message, signature))
+}
+
+pub fn encode_commit(certificate: &CommitCertificate<TestContext>) -> types::Commit {
+    types::Commit {
+        height: encode_height(certificate.height),
+        round: encode_round(certificate.round),
+        block_id: Some(encode_block_id(&NilOrVal::Val(certificate.value_id))),
+        signatures: certificate
+            .commit_signatures
+            .iter()
+            .map(|sig| types::CommitSig {
+                block_id_flag: types::BlockIdFlag::Commit.into(),
+                validator_address: encode_address(&sig.address),
+                signature: encode_signature(&sig.signature),
+            })
+            .collect(),
+    }
+}
+
+pub fn decode_commit(commit: types::Commit) -> Result<CommitCertificate<TestContext>, ProtoError> {
+    let NilOrVal::Val(value_id) = decode_block_id(commit.block_id)? else {
+        return Err(ProtoError::invalid_data::<types::Commit>("block_id"));
+    };
+
+    let commit_signatures = commit
+        .signatures
+        .into_iter()
+        // Only the signatures for the block are part of a certificate,
+        // CometBFT also lists the validators which did not sign it or signed for nil
+        .filter(|sig| sig.block_id_flag() == types::BlockIdFlag::Commit)
+        .map(|sig| -> Result<CommitSignature<TestContext>, ProtoError> {
+            Ok(CommitSignature::new(
+                decode_address::<types::CommitSig>(&sig.validator_address)?,
+                decode_signature::<types::CommitSig>(&sig.signature)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CommitCertificate {
+        height: decode_height::<types::Commit>(commit.height)?,
+        round: decode_round::<types::Commit>(commit.round)?,
+        value_id,
+        commit_signatures,
+    })
+}
+
+/// A vote for nil has a `BlockID` without hash, as in CometBFT
+fn encode_block_id(value_id: &NilOrVal<ValueId>) -> types::BlockId {
+    let hash = match value_id {
+        NilOrVal::Nil => Bytes::new(),
+        NilOrVal::Val(value_id) => Bytes::copy_from_slice(&value_id.as_u64().to_be_bytes()),
+    };
+
+    types::BlockId {
+        hash,
+        part_set_header: None,
+    }
+}
+
+fn decode_block_id(block_id: Option<types::BlockId>) -> Result<NilOrVal<ValueId>, ProtoError> {
+    let hash = block_id.map(|block_id| block_id.hash).unwrap_or_default();
+
+    if hash.is_empty() {
+        return Ok(NilOrVal::Nil);
+    }
+
+    let bytes = <[u8; 8]>::try_from(hash.as_ref())
+        .map_err(|_| ProtoError::invalid_data::<types::BlockId>("hash"))?;
+
+    Ok(NilOrVal::Val(ValueId::new(u64::from_be_bytes(bytes))))
+}
+
+fn encode_height(height: Height) -> i64 {
+    i64::try_from(height.as_u64()).expect("height should fit in an int64")
+}
+
+fn decode_height<M: Name>(height: i64) -> Result<Height, ProtoError> {
+    u64::try_from(height)
+        .map(Height::new)
+        .map_err(|_| ProtoError::invalid_data::<M>("height"))
+}
+
+/// Nil is encoded as -1, as the POL round of CometBFT
+fn encode_round(round: Round) -> i32 {
+    i32::try_from(round.as_i64()).expect("round should fit in an int32")
+}
+
+fn decode_round<M: Name>(round: i32) -> Result<Round, ProtoError> {
+    u32::try_from(round)
+        .map(Round::new)
+        .map_err(|_| ProtoError::invalid_data::<M>("round"))
+}
+
+fn encode_address(address: &Address) -> Bytes {
+    Bytes::copy_from_slice(&address.into_inner())
+}
+
+fn decode_address<M: Name>(bytes: &[u8]) -> Result<Address, ProtoError> {
+    <[u8; 20]>::try_from(bytes)
+        .map(Address::new)
+        .map_err(|_| ProtoError::invalid_data::<M>("validator_address"))
+}
+
+fn encode_signature(signature: &Signature) -> Bytes {
+    Bytes::copy_from_slice(signature.to_bytes().as_ref())
+}
+
+fn decode_signature<M: Name>(bytes: &[u8]) -> Result<Signature, ProtoError> {
+    <[u8; 64]>::try_from(bytes)
+        .map(Signature::from_bytes)
+        .map_err(|_| ProtoError::invalid_data::<M>("signature"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(byte: u8) -> Signature {
+        Signature::from_bytes([byte; 64])
+    }
+
+    fn roundtrip(msg: SignedConsensusMsg<TestContext>) {
+        let encoded = CometBftCodec.encode(&msg).unwrap();
+        let decoded: SignedConsensusMsg<TestContext> = CometBftCodec.decode(encoded).unwrap();
+        assert_eq!(decoded, msg);
+    }
+
+    #[test]
+    fn vote_has_the_layout_of_cometbft() {
+        let vote = Vote::new_prevote(
+            Height::new(1),
+            Round::new(0),
+            NilOrVal::Val(ValueId::new(1)),
+            Address::new([3; 20]),
+        );
+
+        let msg = SignedConsensusMsg::Vote(SignedVote::new(vote, signature(4)));
+        let encoded = CometBftCodec.encode(&msg).unwrap();
+
+        let mut expected = vec![
+            0x32, 0x6a, // Message.vote
+            0x0a, 0x68, // VoteMessage.vote
+            0x08, 0x01, // Vote.type = SIGNED_MSG_TYPE_PREVOTE
+            0x10, 0x01, // Vote.height = 1, the round 0 being the default is omitted
+            0x22, 0x0a, 0x0a, 0x08, 0, 0, 0, 0, 0, 0, 0, 1, // Vote.block_id.hash
+            0x32, 0x14, // Vote.validator_address
+        ];
+        expected.extend([3; 20]);
+        expected.extend([0x42, 0x40]); // Vote.signature
+        expected.extend([4; 64]);
+
+        assert_eq!(encoded.as_ref(), expected.as_slice());
+    }
+
+    #[test]
+    fn encode_decode_votes() {
+        let address = Address::new([1; 20]);
+
+        let nil = Vote::new_prevote(Height::new(2), Round::new(1), NilOrVal::Nil, address);
+        roundtrip(SignedConsensusMsg::Vote(SignedVote::new(nil, signature(2))));
+
+        let mut precommit = Vote::new_precommit(
+            Height::new(2),
+            Round::new(1),
+            NilOrVal::Val(ValueId::new(7)),
+            address,
+        );
+        precommit.extension = Some(SignedExtension::new(
+            Bytes::from_static(b"extension"),
+            signature(3),
+        ));
+        roundtrip(SignedConsensusMsg::Vote(SignedVote::new(
+            precommit,
+            signature(4),
+        )));
+    }
+
+    #[test]
+    fn encode_decode_proposal() {
+        let proposal = Proposal::new(
+            Height::new(3),
+            Round::new(2),
+            Value::new(42),
+            Round::Nil,
+            Address::new([5; 20]),
+        );
+
+        roundtrip(SignedConsensusMsg::Proposal(SignedProposal::new(
+            proposal,
+            signature(6),
+        )));
+    }
+
+    #[test]
+    fn encode_decode_commit() {
+        let certificate = CommitCertificate {
+            height: Height::new(4),
+            round: Round::new(0),
+            value_id: ValueId::new(42),
+            commit_signatures: vec![
+                CommitSignature::new(Address::new([1; 20]), signature(1)),
+                CommitSignature::new(Address::new([2; 20]), signature(2)),
+            ],
+        };
+
+        let encoded = CometBftCodec.encode(&certificate).unwrap();
+
+        let commit = types::Commit::decode(encoded.clone()).unwrap();
+        assert!(commit
+            .signatures
+            .iter()
+            .all(|sig| sig.block_id_flag() == types::BlockIdFlag::Commit));
+
+        let decoded: CommitCertificate<TestContext> = CometBftCodec.decode(encoded).unwrap();
+        assert_eq!(decoded, certificate);
+    }
+}
@@ -8,12 +8,13 @@ use malachitebft_core_consensus::{LivenessMsg, SignedConsensusMsg};
 use malachitebft_engine::util::streaming::StreamMessage;
 use malachitebft_sync::{Request, Response, Status};
 
-use crate::{ProposalPart, TestContext, Value};
+use crate::{Address, ProposalPart, TestContext, ValidatorSet, Value};
 
-use malachitebft_core_types::ValidatorProof;
+use malachitebft_core_types::{CommitCertificate, ValidatorProof};
+use malachitebft_signing_remote::{SignRequest, SignResponse};
 use raw::{
-    RawLivenessMsg, RawRequest, RawResponse, RawSignedConsensusMsg, RawStatus, RawStreamMessage,
-    RawValidatorProof,
+    RawCommitCertificate, RawLivenessMsg, RawRequest, RawResponse, RawSignRequest, RawSignResponse,
+    RawSignedConsensusMsg, RawStatus, RawStreamMessage, RawValidatorProof,
 };
 
 #[derive(Copy, Clone, Debug)]
@@ -43,6 +44,42 @@ impl Codec<ProposalPart> for JsonCodec {
     }
 }
 
+impl Codec<Address> for JsonCodec {
+    type Error = serde_json::Error;
+
+    fn decode(&self, bytes: Bytes) -> Result<Address, Self::Error> {
+        serde_json::from_slice(&bytes)
+    }
+
+    fn encode(&self, msg: &Address) -> Result<Bytes, Self::Error> {
+        serde_json::to_vec(&msg).map(Bytes::from)
+    }
+}
+
+impl Codec<ValidatorSet> for JsonCodec {
+    type Error = serde_json::Error;
+
+    fn decode(&self, bytes: Bytes) -> Result<ValidatorSet, Self::Error> {
+        serde_json::from_slice(&bytes)
+    }
+
+    fn encode(&self, msg: &ValidatorSet) -> Result<Bytes, Self::Error> {
+        serde_json::to_vec(&msg).map(Bytes::from)
+    }
+}
+
+impl Codec<CommitCertificate<TestContext>> for JsonCodec {
+    type Error = serde_json::Error;
+
+    fn decode(&self, bytes: Bytes) -> Result<CommitCertificate<TestContext>, Self::Error> {
+        serde_json::from_slice::<RawCommitCertificate>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &CommitCertificate<TestContext>) -> Result<Bytes, Self::Error> {
+        serde_json::to_vec(&RawCommitCertificate::from(msg.clone())).map(Bytes::from)
+    }
+}
+
 impl Codec<SignedConsensusMsg<TestContext>> for JsonCodec {
     type Error = serde_json::Error;
 
@@ -137,3 +174,30 @@ impl Codec<ValidatorProof<TestContext>> for JsonCodec {
         serde_json::to_vec(&RawValidatorProof::from(msg.clone())).map(Bytes::from)
     }
 }
+
+impl Codec<SignRequest<TestContext>> for JsonCodec {
+    type Error = serde_json::Error;
+
+    fn decode(&self, bytes: Bytes) -> Result<SignRequest<TestContext>, Self::Error> {
+        use serde::de::Error;
+
+        let raw = serde_json::from_slice::<RawSignRequest>(&bytes)?;
+        SignRequest::try_from(raw).map_err(serde_json::Error::custom)
+    }
+
+    fn encode(&self, msg: &SignRequest<TestContext>) -> Result<Bytes, Self::Error> {
+        serde_json::to_vec(&RawSignRequest::from(msg.clone())).map(Bytes::from)
+    }
+}
+
+impl Codec<SignResponse<TestContext>> for JsonCodec {
+    type Error = serde_json::Error;
+
+    fn decode(&self, bytes: Bytes) -> Result<SignResponse<TestContext>, Self::Error> {
+        serde_json::from_slice::<RawSignResponse>(&bytes).map(Into::into)
+    }
+
+    fn encode(&self, msg: &SignResponse<TestContext>) -> Result<Bytes, Self::Error> {
+        serde_json::to_vec(&RawSignResponse::from(msg.clone())).map(Bytes::from)
+    }
+}
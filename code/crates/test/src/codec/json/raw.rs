@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bytes::Bytes;
 use ed25519_consensus::Signature;
 use serde::{Deserialize, Serialize};
@@ -11,7 +13,9 @@ use malachitebft_core_types::{
 use malachitebft_engine::util::streaming::{StreamContent, StreamMessage};
 use malachitebft_proto::Protobuf;
 use malachitebft_sync::{
-    PeerId, RawDecidedValue, Request, Response, Status, ValueRequest, ValueResponse,
+    PeerId, ProposalPartsRequest, ProposalPartsResponse, RawDecidedValue, Request, Response,
+    Snapshot, SnapshotRequest, SnapshotResponse, Status, TraceId, ValueRequest, ValueResponse,
+    VoteSetRequest, VoteSetResponse,
 };
 
 use crate::{Address, Height, Proposal, ProposalPart, TestContext, ValueId, Vote};
@@ -115,6 +119,8 @@ pub struct RawStatus {
     pub peer_id: PeerId,
     pub tip_height: Height,
     pub history_min_height: Height,
+    #[serde(default)]
+    pub min_needed_height: Option<Height>,
 }
 
 impl From<Status<TestContext>> for RawStatus {
@@ -123,6 +129,7 @@ impl From<Status<TestContext>> for RawStatus {
             peer_id: value.peer_id,
             tip_height: value.tip_height,
             history_min_height: value.history_min_height,
+            min_needed_height: value.min_needed_height,
         }
     }
 }
@@ -133,6 +140,7 @@ impl From<RawStatus> for Status<TestContext> {
             peer_id: value.peer_id,
             tip_height: value.tip_height,
             history_min_height: value.history_min_height,
+            min_needed_height: value.min_needed_height,
         }
     }
 }
@@ -141,11 +149,32 @@ impl From<RawStatus> for Status<TestContext> {
 pub struct ValueRawRequest {
     pub height: Height,
     pub end_height: Option<Height>,
+    #[serde(default)]
+    pub trace_id: u64,
+    #[serde(default)]
+    pub certificates_only: bool,
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum RawRequest {
     SyncRequest(ValueRawRequest),
+    SnapshotListRequest,
+    SnapshotChunkRequest {
+        height: Height,
+        format: u32,
+        index: u32,
+    },
+    ProposalPartsRequest {
+        height: Height,
+        round: Round,
+        proposer: PeerId,
+        stream_id: Bytes,
+        sequences: Vec<u64>,
+    },
+    VoteSetRequest {
+        height: Height,
+        round: Round,
+    },
 }
 
 impl From<Request<TestContext>> for RawRequest {
@@ -154,7 +183,30 @@ impl From<Request<TestContext>> for RawRequest {
             Request::ValueRequest(request) => Self::SyncRequest(ValueRawRequest {
                 height: *request.range.start(),
                 end_height: Some(*request.range.end()),
+                trace_id: request.trace_id.0,
+                certificates_only: request.certificates_only,
             }),
+            Request::SnapshotRequest(SnapshotRequest::List) => Self::SnapshotListRequest,
+            Request::SnapshotRequest(SnapshotRequest::Chunk {
+                height,
+                format,
+                index,
+            }) => Self::SnapshotChunkRequest {
+                height,
+                format,
+                index,
+            },
+            Request::ProposalPartsRequest(request) => Self::ProposalPartsRequest {
+                height: request.height,
+                round: request.round,
+                proposer: request.proposer,
+                stream_id: request.stream_id,
+                sequences: request.sequences,
+            },
+            Request::VoteSetRequest(request) => Self::VoteSetRequest {
+                height: request.height,
+                round: request.round,
+            },
         }
     }
 }
@@ -164,7 +216,35 @@ impl From<RawRequest> for Request<TestContext> {
         match value {
             RawRequest::SyncRequest(raw_request) => Self::ValueRequest(ValueRequest {
                 range: raw_request.height..=raw_request.end_height.unwrap_or(raw_request.height),
+                trace_id: TraceId(raw_request.trace_id),
+                certificates_only: raw_request.certificates_only,
+            }),
+            RawRequest::SnapshotListRequest => Self::SnapshotRequest(SnapshotRequest::List),
+            RawRequest::SnapshotChunkRequest {
+                height,
+                format,
+                index,
+            } => Self::SnapshotRequest(SnapshotRequest::Chunk {
+                height,
+                format,
+                index,
             }),
+            RawRequest::ProposalPartsRequest {
+                height,
+                round,
+                proposer,
+                stream_id,
+                sequences,
+            } => Self::ProposalPartsRequest(ProposalPartsRequest {
+                height,
+                round,
+                proposer,
+                stream_id,
+                sequences,
+            }),
+            RawRequest::VoteSetRequest { height, round } => {
+                Self::VoteSetRequest(VoteSetRequest::new(height, round))
+            }
         }
     }
 }
@@ -248,6 +328,10 @@ pub struct RawSyncedValue {
 pub struct ValueRawResponse {
     pub start_height: Height,
     pub value: Vec<RawSyncedValue>,
+    #[serde(default)]
+    pub trace_id: u64,
+    #[serde(default)]
+    pub retry_after_ms: Option<u64>,
 }
 
 impl From<ValueResponse<TestContext>> for ValueRawResponse {
@@ -262,6 +346,10 @@ impl From<ValueResponse<TestContext>> for ValueRawResponse {
                     certificate: value.certificate.into(),
                 })
                 .collect(),
+            trace_id: response.trace_id.0,
+            retry_after_ms: response
+                .retry_after
+                .map(|retry_after| retry_after.as_millis() as u64),
         }
     }
 }
@@ -278,19 +366,85 @@ impl From<ValueRawResponse> for ValueResponse<TestContext> {
                     certificate: value.certificate.into(),
                 })
                 .collect(),
+            trace_id: TraceId(response.trace_id),
+            retry_after: response.retry_after_ms.map(Duration::from_millis),
         }
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct RawSnapshot {
+    pub height: Height,
+    pub format: u32,
+    pub chunks: u32,
+    pub hash: Bytes,
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum RawResponse {
     ValueResponse(ValueRawResponse),
+    SnapshotListResponse(Vec<RawSnapshot>),
+    SnapshotChunkResponse {
+        height: Height,
+        format: u32,
+        index: u32,
+        chunk: Bytes,
+    },
+    ProposalPartsResponse {
+        height: Height,
+        parts: Vec<Bytes>,
+    },
+    VoteSetResponse {
+        height: Height,
+        round: Round,
+        votes: Vec<RawSignedMessage>,
+    },
 }
 
 impl From<Response<TestContext>> for RawResponse {
     fn from(value: Response<TestContext>) -> Self {
         match value {
             Response::ValueResponse(block_response) => Self::ValueResponse(block_response.into()),
+            Response::SnapshotResponse(SnapshotResponse::List(snapshots)) => {
+                Self::SnapshotListResponse(
+                    snapshots
+                        .into_iter()
+                        .map(|snapshot| RawSnapshot {
+                            height: snapshot.height,
+                            format: snapshot.format,
+                            chunks: snapshot.chunks,
+                            hash: snapshot.hash,
+                        })
+                        .collect(),
+                )
+            }
+            Response::SnapshotResponse(SnapshotResponse::Chunk {
+                height,
+                format,
+                index,
+                chunk,
+            }) => Self::SnapshotChunkResponse {
+                height,
+                format,
+                index,
+                chunk,
+            },
+            Response::ProposalPartsResponse(response) => Self::ProposalPartsResponse {
+                height: response.height,
+                parts: response.parts,
+            },
+            Response::VoteSetResponse(response) => Self::VoteSetResponse {
+                height: response.height,
+                round: response.round,
+                votes: response
+                    .votes
+                    .into_iter()
+                    .map(|vote| RawSignedMessage {
+                        message: vote.message.to_sign_bytes(),
+                        signature: *vote.signature.inner(),
+                    })
+                    .collect(),
+            },
         }
     }
 }
@@ -301,6 +455,50 @@ impl From<RawResponse> for Response<TestContext> {
             RawResponse::ValueResponse(block_raw_response) => {
                 Self::ValueResponse(block_raw_response.into())
             }
+            RawResponse::SnapshotListResponse(snapshots) => {
+                Self::SnapshotResponse(SnapshotResponse::List(
+                    snapshots
+                        .into_iter()
+                        .map(|snapshot| {
+                            Snapshot::new(
+                                snapshot.height,
+                                snapshot.format,
+                                snapshot.chunks,
+                                snapshot.hash,
+                            )
+                        })
+                        .collect(),
+                ))
+            }
+            RawResponse::SnapshotChunkResponse {
+                height,
+                format,
+                index,
+                chunk,
+            } => Self::SnapshotResponse(SnapshotResponse::Chunk {
+                height,
+                format,
+                index,
+                chunk,
+            }),
+            RawResponse::ProposalPartsResponse { height, parts } => {
+                Self::ProposalPartsResponse(ProposalPartsResponse::new(height, parts))
+            }
+            RawResponse::VoteSetResponse {
+                height,
+                round,
+                votes,
+            } => Self::VoteSetResponse(VoteSetResponse::new(
+                height,
+                round,
+                votes
+                    .into_iter()
+                    .map(|vote| SignedVote {
+                        message: Vote::from_sign_bytes(&vote.message).unwrap(),
+                        signature: vote.signature.into(),
+                    })
+                    .collect(),
+            )),
         }
     }
 }
@@ -419,6 +617,49 @@ impl From<RawLivenessMsg> for LivenessMsg<TestContext> {
     }
 }
 
+// ProposedValue
+
+use malachitebft_core_consensus::ProposedValue;
+use malachitebft_core_types::Validity;
+
+use crate::Value;
+
+#[derive(Serialize, Deserialize)]
+pub struct RawProposedValue {
+    pub height: Height,
+    pub round: Round,
+    pub valid_round: Round,
+    pub proposer: Address,
+    pub value: Value,
+    pub valid: bool,
+}
+
+impl From<ProposedValue<TestContext>> for RawProposedValue {
+    fn from(value: ProposedValue<TestContext>) -> Self {
+        Self {
+            height: value.height,
+            round: value.round,
+            valid_round: value.valid_round,
+            proposer: value.proposer,
+            value: value.value,
+            valid: value.validity.to_bool(),
+        }
+    }
+}
+
+impl From<RawProposedValue> for ProposedValue<TestContext> {
+    fn from(value: RawProposedValue) -> Self {
+        Self {
+            height: value.height,
+            round: value.round,
+            valid_round: value.valid_round,
+            proposer: value.proposer,
+            value: value.value,
+            validity: Validity::from_bool(value.valid),
+        }
+    }
+}
+
 // ValidatorProof
 
 use malachitebft_core_types::ValidatorProof;
@@ -447,3 +688,66 @@ impl From<RawValidatorProof> for ValidatorProof<TestContext> {
         ValidatorProof::new(value.public_key, value.peer_id, value.signature.into())
     }
 }
+
+// Remote signer
+
+use malachitebft_proto::Error as ProtoError;
+use malachitebft_signing_remote::{SignRequest, SignResponse};
+
+#[derive(Serialize, Deserialize)]
+pub enum RawSignRequest {
+    Vote(Bytes),
+    Proposal(Bytes),
+    VoteExtension(RawExtension),
+    Bytes(Bytes),
+}
+
+impl From<SignRequest<TestContext>> for RawSignRequest {
+    fn from(value: SignRequest<TestContext>) -> Self {
+        match value {
+            SignRequest::Vote(vote) => Self::Vote(vote.to_sign_bytes()),
+            SignRequest::Proposal(proposal) => Self::Proposal(proposal.to_sign_bytes()),
+            SignRequest::VoteExtension(data) => Self::VoteExtension(RawExtension { data }),
+            SignRequest::Bytes(bytes) => Self::Bytes(bytes),
+        }
+    }
+}
+
+impl TryFrom<RawSignRequest> for SignRequest<TestContext> {
+    type Error = ProtoError;
+
+    fn try_from(value: RawSignRequest) -> Result<Self, Self::Error> {
+        Ok(match value {
+            RawSignRequest::Vote(vote) => SignRequest::Vote(Vote::from_sign_bytes(&vote)?),
+            RawSignRequest::Proposal(proposal) => {
+                SignRequest::Proposal(Proposal::from_sign_bytes(&proposal)?)
+            }
+            RawSignRequest::VoteExtension(extension) => SignRequest::VoteExtension(extension.data),
+            RawSignRequest::Bytes(bytes) => SignRequest::Bytes(bytes),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum RawSignResponse {
+    Signature(Signature),
+    Rejected(String),
+}
+
+impl From<SignResponse<TestContext>> for RawSignResponse {
+    fn from(value: SignResponse<TestContext>) -> Self {
+        match value {
+            SignResponse::Signature(signature) => Self::Signature(*signature.inner()),
+            SignResponse::Rejected(reason) => Self::Rejected(reason),
+        }
+    }
+}
+
+impl From<RawSignResponse> for SignResponse<TestContext> {
+    fn from(value: RawSignResponse) -> Self {
+        match value {
+            RawSignResponse::Signature(signature) => SignResponse::Signature(signature.into()),
+            RawSignResponse::Rejected(reason) => SignResponse::Rejected(reason),
+        }
+    }
+}
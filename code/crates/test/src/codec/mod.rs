@@ -1,2 +1,4 @@
+pub mod cbor;
+pub mod cometbft;
 pub mod json;
 pub mod proto;
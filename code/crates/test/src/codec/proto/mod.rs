@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bytes::Bytes;
 use prost::Message;
 
@@ -329,6 +331,7 @@ impl Codec<sync::Status<TestContext>> for ProtobufCodec {
             peer_id: PeerId::from_bytes(proto_peer_id.id.as_ref()).unwrap(),
             tip_height: Height::new(proto.height),
             history_min_height: Height::new(proto.earliest_height),
+            min_needed_height: proto.min_needed_height.map(Height::new),
         })
     }
 
@@ -339,6 +342,7 @@ impl Codec<sync::Status<TestContext>> for ProtobufCodec {
             }),
             height: msg.tip_height.as_u64(),
             earliest_height: msg.history_min_height.as_u64(),
+            min_needed_height: msg.min_needed_height.map(|height| height.as_u64()),
         };
 
         Ok(Bytes::from(proto.encode_to_vec()))
@@ -359,10 +363,44 @@ impl Codec<sync::Request<TestContext>> for ProtobufCodec {
                 Some(end_height) if end_height < req.height => {
                     Err(ProtoError::invalid_data::<proto::SyncRequest>("end_height"))
                 }
-                end_height => Ok(sync::Request::ValueRequest(sync::ValueRequest::new(
-                    Height::new(req.height)..=Height::new(end_height.unwrap_or(req.height)),
-                ))),
+                end_height => Ok(sync::Request::ValueRequest(
+                    sync::ValueRequest::new(
+                        Height::new(req.height)..=Height::new(end_height.unwrap_or(req.height)),
+                    )
+                    .with_trace_id(sync::TraceId(req.trace_id))
+                    .with_certificates_only(req.certificates_only),
+                )),
             },
+            proto::sync_request::Request::SnapshotListRequest(_) => {
+                Ok(sync::Request::SnapshotRequest(sync::SnapshotRequest::List))
+            }
+            proto::sync_request::Request::SnapshotChunkRequest(req) => Ok(
+                sync::Request::SnapshotRequest(sync::SnapshotRequest::Chunk {
+                    height: Height::new(req.height),
+                    format: req.format,
+                    index: req.index,
+                }),
+            ),
+            proto::sync_request::Request::ProposalPartsRequest(req) => {
+                let proposer = req
+                    .proposer
+                    .ok_or_else(|| ProtoError::missing_field::<proto::SyncRequest>("proposer"))?;
+
+                Ok(sync::Request::ProposalPartsRequest(
+                    sync::ProposalPartsRequest {
+                        height: Height::new(req.height),
+                        round: req.round.map(Round::new).unwrap_or(Round::Nil),
+                        proposer: PeerId::from_bytes(proposer.id.as_ref()).map_err(|_| {
+                            ProtoError::invalid_data::<proto::SyncRequest>("proposer")
+                        })?,
+                        stream_id: req.stream_id,
+                        sequences: req.sequences,
+                    },
+                ))
+            }
+            proto::sync_request::Request::VoteSetRequest(req) => Ok(sync::Request::VoteSetRequest(
+                sync::VoteSetRequest::new(Height::new(req.height), Round::new(req.round)),
+            )),
         }
     }
 
@@ -373,6 +411,47 @@ impl Codec<sync::Request<TestContext>> for ProtobufCodec {
                     proto::ValueRequest {
                         height: req.range.start().as_u64(),
                         end_height: Some(req.range.end().as_u64()),
+                        trace_id: req.trace_id.0,
+                        certificates_only: req.certificates_only,
+                    },
+                )),
+            },
+            sync::Request::SnapshotRequest(sync::SnapshotRequest::List) => proto::SyncRequest {
+                request: Some(proto::sync_request::Request::SnapshotListRequest(
+                    proto::SnapshotListRequest {},
+                )),
+            },
+            sync::Request::SnapshotRequest(sync::SnapshotRequest::Chunk {
+                height,
+                format,
+                index,
+            }) => proto::SyncRequest {
+                request: Some(proto::sync_request::Request::SnapshotChunkRequest(
+                    proto::SnapshotChunkRequest {
+                        height: height.as_u64(),
+                        format: *format,
+                        index: *index,
+                    },
+                )),
+            },
+            sync::Request::ProposalPartsRequest(req) => proto::SyncRequest {
+                request: Some(proto::sync_request::Request::ProposalPartsRequest(
+                    proto::ProposalPartsRequest {
+                        height: req.height.as_u64(),
+                        round: req.round.as_u32(),
+                        proposer: Some(proto::PeerId {
+                            id: Bytes::from(req.proposer.to_bytes()),
+                        }),
+                        stream_id: req.stream_id.clone(),
+                        sequences: req.sequences.clone(),
+                    },
+                )),
+            },
+            sync::Request::VoteSetRequest(req) => proto::SyncRequest {
+                request: Some(proto::sync_request::Request::VoteSetRequest(
+                    proto::VoteSetRequest {
+                        height: req.height.as_u64(),
+                        round: req.round.as_u32().expect("round should not be nil"),
                     },
                 )),
             },
@@ -415,13 +494,58 @@ pub fn decode_sync_response(
 
     let response = match response {
         proto::sync_response::Response::ValueResponse(response) => {
-            sync::Response::ValueResponse(sync::ValueResponse::new(
+            let mut value_response = sync::ValueResponse::new(
                 Height::new(response.start_height),
                 response
                     .values
                     .into_iter()
                     .map(decode_synced_value)
                     .collect::<Result<Vec<_>, ProtoError>>()?,
+            )
+            .with_trace_id(sync::TraceId(response.trace_id));
+
+            value_response.retry_after = response.retry_after_ms.map(Duration::from_millis);
+            sync::Response::ValueResponse(value_response)
+        }
+        proto::sync_response::Response::SnapshotListResponse(response) => {
+            sync::Response::SnapshotResponse(sync::SnapshotResponse::List(
+                response
+                    .snapshots
+                    .into_iter()
+                    .map(|snapshot| {
+                        sync::Snapshot::new(
+                            Height::new(snapshot.height),
+                            snapshot.format,
+                            snapshot.chunks,
+                            snapshot.hash,
+                        )
+                    })
+                    .collect(),
+            ))
+        }
+        proto::sync_response::Response::SnapshotChunkResponse(response) => {
+            sync::Response::SnapshotResponse(sync::SnapshotResponse::Chunk {
+                height: Height::new(response.height),
+                format: response.format,
+                index: response.index,
+                chunk: response.chunk,
+            })
+        }
+        proto::sync_response::Response::ProposalPartsResponse(response) => {
+            sync::Response::ProposalPartsResponse(sync::ProposalPartsResponse::new(
+                Height::new(response.height),
+                response.parts,
+            ))
+        }
+        proto::sync_response::Response::VoteSetResponse(response) => {
+            sync::Response::VoteSetResponse(sync::VoteSetResponse::new(
+                Height::new(response.height),
+                Round::new(response.round),
+                response
+                    .votes
+                    .into_iter()
+                    .map(decode_vote)
+                    .collect::<Result<Vec<_>, _>>()?,
             ))
         }
     };
@@ -442,9 +566,66 @@ pub fn encode_sync_response(
                         .iter()
                         .map(encode_synced_value)
                         .collect::<Result<Vec<_>, _>>()?,
+                    trace_id: value_response.trace_id.0,
+                    retry_after_ms: value_response
+                        .retry_after
+                        .map(|retry_after| retry_after.as_millis() as u64),
                 })
             }),
         },
+        sync::Response::SnapshotResponse(sync::SnapshotResponse::List(snapshots)) => {
+            proto::SyncResponse {
+                response: Some(proto::sync_response::Response::SnapshotListResponse(
+                    proto::SnapshotListResponse {
+                        snapshots: snapshots
+                            .iter()
+                            .map(|snapshot| proto::Snapshot {
+                                height: snapshot.height.as_u64(),
+                                format: snapshot.format,
+                                chunks: snapshot.chunks,
+                                hash: snapshot.hash.clone(),
+                            })
+                            .collect(),
+                    },
+                )),
+            }
+        }
+        sync::Response::SnapshotResponse(sync::SnapshotResponse::Chunk {
+            height,
+            format,
+            index,
+            chunk,
+        }) => proto::SyncResponse {
+            response: Some(proto::sync_response::Response::SnapshotChunkResponse(
+                proto::SnapshotChunkResponse {
+                    height: height.as_u64(),
+                    format: *format,
+                    index: *index,
+                    chunk: chunk.clone(),
+                },
+            )),
+        },
+        sync::Response::ProposalPartsResponse(response) => proto::SyncResponse {
+            response: Some(proto::sync_response::Response::ProposalPartsResponse(
+                proto::ProposalPartsResponse {
+                    height: response.height.as_u64(),
+                    parts: response.parts.clone(),
+                },
+            )),
+        },
+        sync::Response::VoteSetResponse(response) => proto::SyncResponse {
+            response: Some(proto::sync_response::Response::VoteSetResponse(
+                proto::VoteSetResponse {
+                    height: response.height.as_u64(),
+                    round: response.round.as_u32().expect("round should not be nil"),
+                    votes: response
+                        .votes
+                        .iter()
+                        .map(encode_vote)
+                        .collect::<Result<Vec<_>, _>>()?,
+                },
+            )),
+        },
     };
 
     Ok(proto)
@@ -724,4 +905,128 @@ mod tests {
             original_sig.signature.to_bytes()
         );
     }
+
+    #[test]
+    fn test_sync_trace_id_encode_decode() {
+        let codec = ProtobufCodec;
+        let trace_id = sync::TraceId(0xdead_beef);
+
+        let request = sync::Request::ValueRequest(
+            sync::ValueRequest::new(Height::new(1)..=Height::new(5))
+                .with_trace_id(trace_id)
+                .with_certificates_only(true),
+        );
+        let encoded = Codec::<sync::Request<TestContext>>::encode(&codec, &request).unwrap();
+        let decoded = Codec::<sync::Request<TestContext>>::decode(&codec, encoded).unwrap();
+        assert_eq!(decoded, request);
+
+        let response = sync::Response::ValueResponse(
+            sync::ValueResponse::new(Height::new(1), vec![]).with_trace_id(trace_id),
+        );
+        let encoded = Codec::<sync::Response<TestContext>>::encode(&codec, &response).unwrap();
+        let decoded = Codec::<sync::Response<TestContext>>::decode(&codec, encoded).unwrap();
+        assert_eq!(decoded, response);
+    }
+
+    #[test]
+    fn test_sync_retry_after_encode_decode() {
+        let codec = ProtobufCodec;
+
+        let response = sync::Response::ValueResponse(
+            sync::ValueResponse::new(Height::new(1), vec![])
+                .with_retry_after(Duration::from_millis(1500)),
+        );
+        let encoded = Codec::<sync::Response<TestContext>>::encode(&codec, &response).unwrap();
+        let decoded = Codec::<sync::Response<TestContext>>::decode(&codec, encoded).unwrap();
+        assert_eq!(decoded, response);
+    }
+
+    #[test]
+    fn test_sync_snapshot_encode_decode() {
+        let codec = ProtobufCodec;
+
+        for request in [
+            sync::SnapshotRequest::List,
+            sync::SnapshotRequest::Chunk {
+                height: Height::new(100),
+                format: 1,
+                index: 3,
+            },
+        ] {
+            let request = sync::Request::SnapshotRequest(request);
+            let encoded = Codec::<sync::Request<TestContext>>::encode(&codec, &request).unwrap();
+            let decoded = Codec::<sync::Request<TestContext>>::decode(&codec, encoded).unwrap();
+            assert_eq!(decoded, request);
+        }
+
+        for response in [
+            sync::SnapshotResponse::List(vec![sync::Snapshot::new(
+                Height::new(100),
+                1,
+                4,
+                Bytes::from_static(b"hash"),
+            )]),
+            sync::SnapshotResponse::Chunk {
+                height: Height::new(100),
+                format: 1,
+                index: 3,
+                chunk: Bytes::from_static(b"chunk"),
+            },
+        ] {
+            let response = sync::Response::SnapshotResponse(response);
+            let encoded = Codec::<sync::Response<TestContext>>::encode(&codec, &response).unwrap();
+            let decoded = Codec::<sync::Response<TestContext>>::decode(&codec, encoded).unwrap();
+            assert_eq!(decoded, response);
+        }
+    }
+
+    #[test]
+    fn test_sync_proposal_parts_encode_decode() {
+        let codec = ProtobufCodec;
+
+        let request = sync::Request::ProposalPartsRequest(sync::ProposalPartsRequest {
+            height: Height::new(100),
+            round: Round::new(2),
+            proposer: PeerId::random(),
+            stream_id: Bytes::from_static(b"stream"),
+            sequences: vec![1, 3],
+        });
+        let encoded = Codec::<sync::Request<TestContext>>::encode(&codec, &request).unwrap();
+        let decoded = Codec::<sync::Request<TestContext>>::decode(&codec, encoded).unwrap();
+        assert_eq!(decoded, request);
+
+        let response = sync::Response::ProposalPartsResponse(sync::ProposalPartsResponse::new(
+            Height::new(100),
+            vec![Bytes::from_static(b"part-1"), Bytes::from_static(b"part-3")],
+        ));
+        let encoded = Codec::<sync::Response<TestContext>>::encode(&codec, &response).unwrap();
+        let decoded = Codec::<sync::Response<TestContext>>::decode(&codec, encoded).unwrap();
+        assert_eq!(decoded, response);
+    }
+
+    #[test]
+    fn test_sync_vote_set_encode_decode() {
+        let codec = ProtobufCodec;
+        let (height, round) = (Height::new(100), Round::new(2));
+
+        let request = sync::Request::VoteSetRequest(sync::VoteSetRequest::new(height, round));
+        let encoded = Codec::<sync::Request<TestContext>>::encode(&codec, &request).unwrap();
+        let decoded = Codec::<sync::Request<TestContext>>::decode(&codec, encoded).unwrap();
+        assert_eq!(decoded, request);
+
+        let vote = |vote: Vote| SignedVote::new(vote, Signature::from_bytes([2; 64]));
+        let address = Address::new([1; 20]);
+
+        let response = sync::Response::VoteSetResponse(sync::VoteSetResponse::new(
+            height,
+            round,
+            vec![
+                vote(Vote::new_prevote(height, round, NilOrVal::Nil, address)),
+                vote(Vote::new_precommit(height, round, NilOrVal::Nil, address)),
+            ],
+        ));
+        let encoded = Codec::<sync::Response<TestContext>>::encode(&codec, &response).unwrap();
+        let decoded = Codec::<sync::Response<TestContext>>::decode(&codec, encoded).unwrap();
+        assert_eq!(decoded, response);
+    }
 }
@@ -1,7 +1,37 @@
-use crate::ValidatorSet;
+use malachitebft_core_types::VotingPower;
 use serde::{Deserialize, Serialize};
 
+use crate::{Address, Height, PublicKey, ValidatorSet};
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Genesis {
     pub validator_set: ValidatorSet,
+
+    /// Consensus keys registered by the validators, effective from a later height
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_rotations: Vec<KeyRotation>,
+
+    /// Changes to the validator set, effective from a later height
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub validator_changes: Vec<ValidatorChange>,
+}
+
+/// A new consensus key registered by a validator, with which it signs from the given height on.
+///
+/// The validator keeps its address, only the public key its signatures are verified with changes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRotation {
+    pub address: Address,
+    pub public_key: PublicKey,
+    pub height: Height,
+}
+
+/// A new voting power for a validator, effective from the given height on.
+///
+/// A validator not in the validator set joins it, and a voting power of zero removes it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorChange {
+    pub public_key: PublicKey,
+    pub voting_power: VotingPower,
+    pub height: Height,
 }
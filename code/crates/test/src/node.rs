@@ -22,6 +22,11 @@ where
 {
     fn subscribe(&self) -> RxEvent<Ctx>;
     async fn kill(&self, reason: Option<String>) -> eyre::Result<()>;
+
+    /// Gracefully shut down the node, or kill it if the node does not support it
+    async fn shutdown(&self) -> eyre::Result<()> {
+        self.kill(Some("Node has shut down".to_string())).await
+    }
 }
 
 #[async_trait]
@@ -1,3 +1,14 @@
 #![allow(missing_docs)]
 
 include!(concat!(env!("OUT_DIR"), "/test.rs"));
+
+/// Subset of the types of CometBFT, see [`crate::codec::cometbft`]
+pub mod tendermint {
+    pub mod types {
+        include!(concat!(env!("OUT_DIR"), "/tendermint.types.rs"));
+    }
+
+    pub mod consensus {
+        include!(concat!(env!("OUT_DIR"), "/tendermint.consensus.rs"));
+    }
+}
@@ -43,7 +43,7 @@ impl Ed25519Provider {
     }
 
     pub fn verify(&self, data: &[u8], signature: &Signature, public_key: &PublicKey) -> bool {
-        public_key.verify(data, signature).is_ok()
+        Ed25519Verifier.verify(data, signature, public_key)
     }
 }
 
@@ -59,11 +59,9 @@ impl SigningProvider<TestContext> for Ed25519Provider {
         signature: &Signature,
         public_key: &PublicKey,
     ) -> Result<VerificationResult, Error> {
-        if self.verify(bytes, signature, public_key) {
-            Ok(VerificationResult::Valid)
-        } else {
-            Ok(VerificationResult::Invalid)
-        }
+        Ed25519Verifier
+            .verify_signed_bytes(bytes, signature, public_key)
+            .await
     }
 
     async fn sign_vote(&self, vote: Vote) -> Result<SignedVote<TestContext>, Error> {
@@ -77,9 +75,16 @@ impl SigningProvider<TestContext> for Ed25519Provider {
         signature: &Signature,
         public_key: &PublicKey,
     ) -> Result<VerificationResult, Error> {
-        Ok(VerificationResult::from_bool(
-            public_key.verify(&vote.to_sign_bytes(), signature).is_ok(),
-        ))
+        Ed25519Verifier
+            .verify_signed_vote(vote, signature, public_key)
+            .await
+    }
+
+    async fn verify_signed_votes(
+        &self,
+        votes: &[(SignedVote<TestContext>, PublicKey)],
+    ) -> Result<Vec<VerificationResult>, Error> {
+        Ed25519Verifier.verify_signed_votes(votes).await
     }
 
     async fn sign_proposal(
@@ -96,11 +101,9 @@ impl SigningProvider<TestContext> for Ed25519Provider {
         signature: &Signature,
         public_key: &PublicKey,
     ) -> Result<VerificationResult, Error> {
-        Ok(VerificationResult::from_bool(
-            public_key
-                .verify(&proposal.to_sign_bytes(), signature)
-                .is_ok(),
-        ))
+        Ed25519Verifier
+            .verify_signed_proposal(proposal, signature, public_key)
+            .await
     }
 
     async fn sign_vote_extension(
@@ -113,6 +116,122 @@ impl SigningProvider<TestContext> for Ed25519Provider {
         ))
     }
 
+    async fn verify_signed_vote_extension(
+        &self,
+        extension: &Bytes,
+        signature: &Signature,
+        public_key: &PublicKey,
+    ) -> Result<VerificationResult, Error> {
+        Ed25519Verifier
+            .verify_signed_vote_extension(extension, signature, public_key)
+            .await
+    }
+}
+
+/// Signing provider without any private key, which can only verify signatures,
+/// as needed by nodes running as followers. Signing always fails.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Ed25519Verifier;
+
+impl Ed25519Verifier {
+    pub fn verify(&self, data: &[u8], signature: &Signature, public_key: &PublicKey) -> bool {
+        public_key.verify(data, signature).is_ok()
+    }
+
+    fn cannot_sign() -> Error {
+        Error::from_source("no signing key, signatures can only be verified")
+    }
+}
+
+#[async_trait]
+impl SigningProvider<TestContext> for Ed25519Verifier {
+    async fn sign_bytes(&self, _bytes: &[u8]) -> Result<Signature, Error> {
+        Err(Self::cannot_sign())
+    }
+
+    async fn verify_signed_bytes(
+        &self,
+        bytes: &[u8],
+        signature: &Signature,
+        public_key: &PublicKey,
+    ) -> Result<VerificationResult, Error> {
+        Ok(VerificationResult::from_bool(
+            self.verify(bytes, signature, public_key),
+        ))
+    }
+
+    async fn sign_vote(&self, _vote: Vote) -> Result<SignedVote<TestContext>, Error> {
+        Err(Self::cannot_sign())
+    }
+
+    async fn verify_signed_vote(
+        &self,
+        vote: &Vote,
+        signature: &Signature,
+        public_key: &PublicKey,
+    ) -> Result<VerificationResult, Error> {
+        Ok(VerificationResult::from_bool(
+            public_key.verify(&vote.to_sign_bytes(), signature).is_ok(),
+        ))
+    }
+
+    async fn verify_signed_votes(
+        &self,
+        votes: &[(SignedVote<TestContext>, PublicKey)],
+    ) -> Result<Vec<VerificationResult>, Error> {
+        let sign_bytes = votes
+            .iter()
+            .map(|(vote, _)| vote.message.to_sign_bytes())
+            .collect::<Vec<_>>();
+
+        let items = votes
+            .iter()
+            .zip(&sign_bytes)
+            .map(|((vote, public_key), bytes)| (public_key, bytes.as_ref(), &vote.signature));
+
+        if Ed25519::verify_batch(rand::thread_rng(), items) {
+            return Ok(votes.iter().map(|_| VerificationResult::Valid).collect());
+        }
+
+        // At least one signature is invalid, find out which ones
+        let results = votes
+            .iter()
+            .zip(&sign_bytes)
+            .map(|((vote, public_key), bytes)| {
+                VerificationResult::from_bool(public_key.verify(bytes, &vote.signature).is_ok())
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn sign_proposal(
+        &self,
+        _proposal: Proposal,
+    ) -> Result<SignedProposal<TestContext>, Error> {
+        Err(Self::cannot_sign())
+    }
+
+    async fn verify_signed_proposal(
+        &self,
+        proposal: &Proposal,
+        signature: &Signature,
+        public_key: &PublicKey,
+    ) -> Result<VerificationResult, Error> {
+        Ok(VerificationResult::from_bool(
+            public_key
+                .verify(&proposal.to_sign_bytes(), signature)
+                .is_ok(),
+        ))
+    }
+
+    async fn sign_vote_extension(
+        &self,
+        _extension: Bytes,
+    ) -> Result<SignedExtension<TestContext>, Error> {
+        Err(Self::cannot_sign())
+    }
+
     async fn verify_signed_vote_extension(
         &self,
         extension: &Bytes,
@@ -5,7 +5,7 @@ use malachitebft_core_types::{PrivateKey, PublicKey, VotingPower};
 
 use crate::node::Node;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct MakeConfigSettings {
     pub runtime: RuntimeConfig,
     pub transport: TransportProtocol,
@@ -1,11 +1,12 @@
 use core::slice;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use malachitebft_core_types::VotingPower;
 use serde::{Deserialize, Serialize};
 
 use crate::signing::PublicKey;
-use crate::{Address, TestContext};
+use crate::{Address, Height, KeyRotation, TestContext, ValidatorChange};
 
 /// A validator is a public key and voting power
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -117,6 +118,67 @@ impl ValidatorSet {
     pub fn get_keys(&self) -> Vec<PublicKey> {
         self.validators.iter().map(|v| v.public_key).collect()
     }
+
+    /// The validator set with the public keys of the validators which rotated their key
+    /// at or below the given height replaced by the latest key they registered.
+    pub fn with_key_rotations(&self, rotations: &[KeyRotation], height: Height) -> Self {
+        let latest_key = |address: &Address| {
+            rotations
+                .iter()
+                .filter(|r| &r.address == address && r.height <= height)
+                .max_by_key(|r| r.height)
+                .map(|r| r.public_key)
+        };
+
+        let validators = self.validators.iter().map(|v| Validator {
+            public_key: latest_key(&v.address).unwrap_or(v.public_key),
+            ..v.clone()
+        });
+
+        Self::new(validators)
+    }
+
+    /// The validator set with the changes effective at or below the given height applied,
+    /// the latest change of every validator giving its voting power.
+    ///
+    /// # Panics
+    /// If the changes remove every validator.
+    pub fn with_validator_changes(&self, changes: &[ValidatorChange], height: Height) -> Self {
+        let mut latest = BTreeMap::<Address, &ValidatorChange>::new();
+
+        for change in changes.iter().filter(|c| c.height <= height) {
+            let address = Address::from_public_key(&change.public_key);
+
+            match latest.get(&address) {
+                Some(other) if other.height > change.height => (),
+                _ => {
+                    latest.insert(address, change);
+                }
+            }
+        }
+
+        if latest.is_empty() {
+            return self.clone();
+        }
+
+        let mut validators = self
+            .validators
+            .iter()
+            .filter(|v| !latest.contains_key(&v.address))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        validators.extend(
+            latest
+                .values()
+                .filter(|c| c.voting_power > 0)
+                .map(|c| Validator::new(c.public_key, c.voting_power)),
+        );
+
+        validators.sort();
+
+        Self::new(validators)
+    }
 }
 
 impl malachitebft_core_types::ValidatorSet<TestContext> for ValidatorSet {
@@ -161,4 +223,74 @@ mod tests {
         let vs = ValidatorSet::new(vec![v1, v2, v3]);
         assert_eq!(vs.total_voting_power(), 6);
     }
+
+    #[test]
+    fn key_rotations() {
+        let mut rng = StdRng::seed_from_u64(0x42);
+
+        let sk1 = PrivateKey::generate(&mut rng);
+        let sk2 = PrivateKey::generate(&mut rng);
+        let next1 = PrivateKey::generate(&mut rng).public_key();
+        let last1 = PrivateKey::generate(&mut rng).public_key();
+
+        let v1 = Validator::new(sk1.public_key(), 1);
+        let v2 = Validator::new(sk2.public_key(), 2);
+        let vs = ValidatorSet::new(vec![v1.clone(), v2.clone()]);
+
+        let rotation = |public_key, height| KeyRotation {
+            address: v1.address,
+            public_key,
+            height: Height::new(height),
+        };
+
+        let rotations = [rotation(last1, 5), rotation(next1, 3)];
+        let key_at = |height| {
+            let vs = vs.with_key_rotations(&rotations, Height::new(height));
+            assert_eq!(vs.get_by_address(&v2.address), Some(&v2));
+            vs.get_by_address(&v1.address).unwrap().public_key
+        };
+
+        assert_eq!(key_at(2), sk1.public_key());
+        assert_eq!(key_at(3), next1);
+        assert_eq!(key_at(4), next1);
+        assert_eq!(key_at(5), last1);
+    }
+
+    #[test]
+    fn validator_changes() {
+        let mut rng = StdRng::seed_from_u64(0x42);
+
+        let sk1 = PrivateKey::generate(&mut rng);
+        let sk2 = PrivateKey::generate(&mut rng);
+        let sk3 = PrivateKey::generate(&mut rng);
+
+        let v1 = Validator::new(sk1.public_key(), 1);
+        let v2 = Validator::new(sk2.public_key(), 2);
+        let vs = ValidatorSet::new(vec![v1.clone(), v2.clone()]);
+
+        let change = |sk: &PrivateKey, voting_power, height| ValidatorChange {
+            public_key: sk.public_key(),
+            voting_power,
+            height: Height::new(height),
+        };
+
+        let changes = [
+            change(&sk3, 3, 2),
+            change(&sk1, 5, 3),
+            change(&sk2, 0, 4),
+            change(&sk3, 0, 6),
+        ];
+
+        let power_at = |height| {
+            let vs = vs.with_validator_changes(&changes, Height::new(height));
+            assert!(vs.validators.windows(2).all(|w| w[0] < w[1]));
+            vs.total_voting_power()
+        };
+
+        assert_eq!(power_at(1), 3);
+        assert_eq!(power_at(2), 6);
+        assert_eq!(power_at(3), 10);
+        assert_eq!(power_at(4), 8);
+        assert_eq!(power_at(6), 5);
+    }
 }
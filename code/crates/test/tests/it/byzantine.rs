@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use malachitebft_test_framework::{HandlerResult, TestParams};
+
+use crate::{Fault, TestBuilder};
+
+const TARGET_TIME: Duration = Duration::from_secs(1);
+
+#[tokio::test]
+pub async fn double_proposal_is_detected() {
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_fault(Fault::double_propose())
+        .start()
+        .success();
+
+    test.add_node().start().success();
+
+    // Holds more than 2/3 of the voting power, so consensus progresses
+    // whatever the faulty node proposes
+    test.add_node()
+        .with_voting_power(5)
+        .start()
+        .on_finalized(|_c, evidence, _s| {
+            let result = if evidence.proposals.is_empty() {
+                HandlerResult::WaitForNextEvent
+            } else {
+                HandlerResult::ContinueTest
+            };
+            Ok(result)
+        })
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(15),
+            TestParams {
+                target_time: Some(TARGET_TIME),
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+pub async fn withheld_votes_from_one_node() {
+    const FINAL_HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_fault(Fault::withhold_votes())
+        .start()
+        .success();
+
+    for _ in 0..3 {
+        test.add_node().start().wait_until(FINAL_HEIGHT).success();
+    }
+
+    test.build().run(Duration::from_secs(30)).await
+}
+
+#[tokio::test]
+pub async fn withheld_precommits_in_first_rounds() {
+    const FINAL_HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    // Without the precommits of the two nodes, no value is decided in rounds 0 and 1
+    for _ in 0..2 {
+        test.add_node()
+            .with_fault(Fault::withhold_precommits().heights(2..=3).rounds(0..=1))
+            .start()
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    }
+
+    for _ in 0..2 {
+        test.add_node().start().wait_until(FINAL_HEIGHT).success();
+    }
+
+    test.build().run(Duration::from_secs(60)).await
+}
+
+#[tokio::test]
+pub async fn delayed_proposals() {
+    const FINAL_HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_fault(Fault::delay_proposal(Duration::from_secs(5)))
+        .start()
+        .wait_until(FINAL_HEIGHT)
+        .success();
+
+    for _ in 0..3 {
+        test.add_node().start().wait_until(FINAL_HEIGHT).success();
+    }
+
+    test.build().run(Duration::from_secs(60)).await
+}
+
+#[tokio::test]
+pub async fn invalid_proposal_parts() {
+    const FINAL_HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_fault(Fault::invalid_proposal_parts())
+        .start()
+        .wait_until(FINAL_HEIGHT)
+        .success();
+
+    for _ in 0..3 {
+        test.add_node().start().wait_until(FINAL_HEIGHT).success();
+    }
+
+    test.build().run(Duration::from_secs(60)).await
+}
+
+#[tokio::test]
+pub async fn amnesia_after_first_round() {
+    const FINAL_HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_fault(Fault::amnesia().rounds(1..=u32::MAX))
+        .start()
+        .wait_until(FINAL_HEIGHT)
+        .success();
+
+    for _ in 0..3 {
+        test.add_node().start().wait_until(FINAL_HEIGHT).success();
+    }
+
+    test.build().run(Duration::from_secs(60)).await
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use malachitebft_core_types::{NilOrVal, TimeoutKind};
+use malachitebft_engine::util::events::Event;
+
+use crate::{HandlerResult, TestBuilder};
+
+#[tokio::test]
+pub async fn quorums_are_reported_as_they_are_reached() {
+    const HEIGHT: u64 = 3;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..3 {
+        test.add_node()
+            .start()
+            .on_event(|event, _| match event {
+                Event::PolkaReached(height, _, NilOrVal::Val(_)) if height.as_u64() == 1 => {
+                    Ok(HandlerResult::ContinueTest)
+                }
+                _ => Ok(HandlerResult::WaitForNextEvent),
+            })
+            .on_event(|event, _| match event {
+                Event::PrecommitQuorumReached(height, _, Some(_)) if height.as_u64() == 1 => {
+                    Ok(HandlerResult::ContinueTest)
+                }
+                _ => Ok(HandlerResult::WaitForNextEvent),
+            })
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    test.build().run(Duration::from_secs(30)).await
+}
+
+#[tokio::test]
+pub async fn timeouts_are_reported_when_they_fire() {
+    const HEIGHT: u64 = 3;
+
+    let mut test = TestBuilder::<()>::new();
+
+    // One of the nodes is down, so that the others time out waiting for its proposal
+    // at the height it is the proposer of
+    test.add_node().with_voting_power(1).success();
+
+    for _ in 0..2 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .on_event(|event, _| match event {
+                Event::TimeoutElapsed(_, timeout) if timeout.kind == TimeoutKind::Propose => {
+                    Ok(HandlerResult::ContinueTest)
+                }
+                _ => Ok(HandlerResult::WaitForNextEvent),
+            })
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    test.build().run(Duration::from_secs(60)).await
+}
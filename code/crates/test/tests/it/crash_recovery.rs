@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use malachitebft_core_consensus::SignedConsensusMsg;
+use malachitebft_core_types::VoteType;
+use malachitebft_engine::util::events::Event;
+
+use crate::{HandlerResult, TestBuilder, TestParams, WalCorruption};
+
+const CRASH_HEIGHT: u64 = 3;
+const FINAL_HEIGHT: u64 = 6;
+
+async fn run_crash_recovery_test(crashing: impl FnOnce(&mut TestBuilder<()>)) {
+    run_crash_recovery_test_until(FINAL_HEIGHT, crashing).await
+}
+
+async fn run_crash_recovery_test_until(
+    final_height: u64,
+    crashing: impl FnOnce(&mut TestBuilder<()>),
+) {
+    let mut test = TestBuilder::<()>::new();
+
+    crashing(&mut test);
+
+    for _ in 0..3 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(final_height)
+            .success();
+    }
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(60),
+            TestParams {
+                enable_value_sync: false,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+pub async fn crash_after_prevote() {
+    run_crash_recovery_test(|test| {
+        test.add_node()
+            .with_voting_power(10)
+            .expect_no_double_signing()
+            .start()
+            .crash_after_vote(CRASH_HEIGHT, VoteType::Prevote)
+            .restart_after(Duration::from_secs(2))
+            .expect_wal_replay(CRASH_HEIGHT)
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    })
+    .await
+}
+
+#[tokio::test]
+pub async fn crash_after_precommit() {
+    run_crash_recovery_test(|test| {
+        test.add_node()
+            .with_voting_power(10)
+            .expect_no_double_signing()
+            .start()
+            .crash_after_vote(CRASH_HEIGHT, VoteType::Precommit)
+            .restart_after(Duration::from_secs(2))
+            .expect_wal_replay(CRASH_HEIGHT)
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    })
+    .await
+}
+
+#[tokio::test]
+pub async fn crash_after_proposal() {
+    run_crash_recovery_test_until(FINAL_HEIGHT + 3, |test| {
+        // The proposer changes with every height, so the node proposes at one of the next four
+        test.add_node()
+            .with_voting_power(10)
+            .expect_no_double_signing()
+            .start()
+            .wait_until(CRASH_HEIGHT)
+            .crash_on(|event| matches!(event, Event::Published(SignedConsensusMsg::Proposal(_))))
+            .restart_after(Duration::from_secs(2))
+            .wait_until(FINAL_HEIGHT + 3)
+            .success();
+    })
+    .await
+}
+
+#[tokio::test]
+pub async fn torn_wal_write() {
+    run_crash_recovery_test(|test| {
+        test.add_node()
+            .with_voting_power(10)
+            .expect_no_double_signing()
+            .start()
+            .crash_after_vote(CRASH_HEIGHT, VoteType::Precommit)
+            .corrupt_wal(WalCorruption::Truncate(5))
+            .restart_after(Duration::from_secs(2))
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    })
+    .await
+}
+
+#[tokio::test]
+pub async fn partial_wal_entry() {
+    run_crash_recovery_test(|test| {
+        test.add_node()
+            .with_voting_power(10)
+            .expect_no_double_signing()
+            .start()
+            .crash_after_vote(CRASH_HEIGHT, VoteType::Precommit)
+            .corrupt_wal(WalCorruption::AppendGarbage(vec![0xff; 7]))
+            .restart_after(Duration::from_secs(2))
+            .expect_wal_replay(CRASH_HEIGHT)
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    })
+    .await
+}
+
+#[tokio::test]
+pub async fn corrupted_wal_entry() {
+    run_crash_recovery_test(|test| {
+        // The node stops replaying its WAL at the corrupted entry, which the WAL drops,
+        // so that it replays the entries before it once restarted again
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .crash_after_vote(CRASH_HEIGHT, VoteType::Precommit)
+            .corrupt_wal(WalCorruption::FlipByte(0))
+            .restart_after(Duration::from_secs(2))
+            .on_event(|event, _| match event {
+                Event::WalCorrupted(_) => Ok(HandlerResult::ContinueTest),
+                _ => Ok(HandlerResult::WaitForNextEvent),
+            })
+            .crash()
+            .restart_after(Duration::from_secs(2))
+            .expect_wal_replay(CRASH_HEIGHT)
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    })
+    .await
+}
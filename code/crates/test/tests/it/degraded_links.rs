@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use crate::{LinkConditions, TestBuilder};
+
+#[tokio::test]
+pub async fn decide_over_wan_links() {
+    const FINAL_HEIGHT: u64 = 6;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..4 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    }
+
+    // Two regions, with a slow link between them
+    test.degrade_links(
+        &[1, 2],
+        &[3, 4],
+        LinkConditions::default()
+            .with_latency(Duration::from_millis(150))
+            .with_jitter(Duration::from_millis(50)),
+    );
+
+    test.build().run(Duration::from_secs(60)).await
+}
+
+#[tokio::test]
+pub async fn decide_over_lossy_links() {
+    const FINAL_HEIGHT: u64 = 6;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..4 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    }
+
+    // Messages lost on the links of the first node are recovered from the other peers,
+    // or by rebroadcasting the votes when consensus is stuck in a round
+    test.degrade_links(
+        &[1],
+        &[2, 3, 4],
+        LinkConditions::default()
+            .with_latency(Duration::from_millis(20))
+            .with_loss(0.2),
+    );
+
+    test.build().run(Duration::from_secs(60)).await
+}
@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use crate::TestBuilder;
+
+#[tokio::test]
+pub async fn all_correct_nodes() {
+    const HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..3 {
+        test.add_node()
+            .add_config_modifier(|config| {
+                config.consensus.deterministic_ordering = Some(Duration::from_millis(10))
+            })
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    test.build().run(Duration::from_secs(30)).await
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use crate::TestBuilder;
+
+#[tokio::test]
+pub async fn single_validator() {
+    const HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .add_config_modifier(|config| config.consensus.dev_mode = true)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build().run(Duration::from_secs(30)).await
+}
+
+#[tokio::test]
+pub async fn two_validators_one_restarting() {
+    const HEIGHT: u64 = 8;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .add_config_modifier(|config| config.consensus.dev_mode = true)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    // Both validators are needed for a quorum, so the first one stalls while the second one
+    // is down, and both resume deciding once it is back
+    test.add_node()
+        .add_config_modifier(|config| config.consensus.dev_mode = true)
+        .start()
+        .wait_until(3)
+        .crash()
+        .restart_after(Duration::from_secs(5))
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build().run(Duration::from_secs(60)).await
+}
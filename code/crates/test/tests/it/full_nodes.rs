@@ -1,5 +1,7 @@
 use std::time::Duration;
 
+use malachitebft_config::ValuePayload;
+
 use crate::{TestBuilder, TestParams};
 
 #[tokio::test]
@@ -179,3 +181,93 @@ pub async fn mixed_validator_and_full_node_failures() {
         )
         .await
 }
+
+#[tokio::test]
+pub async fn follower_in_validator_set() {
+    const HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_voting_power(20)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+    test.add_node()
+        .with_voting_power(20)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+    test.add_node()
+        .with_voting_power(20)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    // Add a follower which is part of the validator set but has no signing key,
+    // any attempt at signing would make it fail
+    test.add_node()
+        .with_voting_power(10)
+        .add_config_modifier(|config| {
+            config.consensus.follower = true;
+        })
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(30),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+pub async fn follower_in_parts_only_network() {
+    const HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_voting_power(20)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+    test.add_node()
+        .with_voting_power(20)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+    test.add_node()
+        .with_voting_power(20)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    // In parts-only mode, the follower derives the proposals from the proposal parts
+    // it receives, which it must do without a signing key
+    test.add_node()
+        .full_node()
+        .add_config_modifier(|config| {
+            config.consensus.follower = true;
+        })
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    // Without value sync, the follower can only decide by following consensus
+    test.build()
+        .run_with_params(
+            Duration::from_secs(30),
+            TestParams {
+                value_payload: ValuePayload::PartsOnly,
+                enable_value_sync: false,
+                ..Default::default()
+            },
+        )
+        .await
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use crate::{TestBuilder, TestParams};
+
+#[tokio::test]
+pub async fn rotate_keys() {
+    const HEIGHT: u64 = 8;
+
+    let mut test = TestBuilder::<()>::new();
+
+    // Every validator holds enough voting power to halt consensus
+    // if its votes were not accepted after it rotated its key
+    test.add_node()
+        .with_voting_power(10)
+        .rotate_key_at(3)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .rotate_key_at(3)
+        .rotate_key_at(6)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build().run(Duration::from_secs(30)).await
+}
+
+#[tokio::test]
+pub async fn rotate_key_then_restart() {
+    const HEIGHT: u64 = 8;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_voting_power(10)
+        .rotate_key_at(3)
+        .start()
+        .wait_until(5)
+        .crash()
+        // The node is started with its initial key and registers the new one again,
+        // which is effective at the height it restarts from
+        .restart_after(Duration::from_secs(1))
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(30),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+pub async fn sync_across_key_rotation() {
+    const HEIGHT: u64 = 10;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_voting_power(10)
+        .rotate_key_at(2)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .rotate_key_at(4)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    // Syncs the heights decided before and after the rotations,
+    // whose certificates are signed with the keys effective at each height
+    test.add_node()
+        .with_voting_power(5)
+        .start_after(1, Duration::from_secs(5))
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(60),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
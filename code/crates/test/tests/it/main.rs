@@ -1,18 +1,31 @@
+mod byzantine;
+mod consensus_events;
+mod crash_recovery;
+mod degraded_links;
+mod deterministic_ordering;
+mod dev_mode;
 mod equivocation;
 mod finalization;
 mod full_nodes;
+mod key_rotation;
 mod liveness;
+mod max_value_size;
 mod middlewares;
 mod n3f0;
 mod n3f0_consensus_mode;
 mod n3f0_pubsub_protocol;
 mod n3f1;
+mod partition;
+mod pause;
 mod persistent_peers_only;
+mod process;
 mod reset;
+mod simulation;
 mod timeout_updates;
 mod validator_set;
 mod validity_change_on_restart;
 mod value_sync;
+mod verification_workers;
 mod vote_rebroadcast;
 mod wal;
 
@@ -23,6 +36,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use bytesize::ByteSize;
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 use tempfile::TempDir;
@@ -30,15 +44,22 @@ use tempfile::TempDir;
 use malachitebft_signing_ed25519::PrivateKey;
 use malachitebft_test_app::config::Config;
 use malachitebft_test_app::node::{App, Handle};
+use malachitebft_test_app::process::{decode_event, node_binary, NodeSpec};
 use malachitebft_test_framework::HasTestRunner;
-use malachitebft_test_framework::{ConfigModifier, NodeRunner, TestNode};
+use malachitebft_test_framework::{
+    ConfigModifier, Isolation, NodeRunner, ProcessCommand, ProcessHandle, SpawnedNode, TestNode,
+};
 
 pub use malachitebft_test_framework::TestBuilder as GenTestBuilder;
-pub use malachitebft_test_framework::{HandlerResult, NodeId, TestParams};
+pub use malachitebft_test_framework::{
+    Container, Fault, HandlerResult, LinkConditions, NodeId, TestParams, WalCorruption,
+};
 
 use arc_malachitebft_test::middleware::Middleware;
 use arc_malachitebft_test::node::Node;
-use arc_malachitebft_test::{Height, TestContext, Validator, ValidatorSet};
+use arc_malachitebft_test::{
+    Address, Height, KeyRotation, TestContext, Validator, ValidatorChange, ValidatorSet,
+};
 
 pub type TestBuilder<S> = GenTestBuilder<TestContext, S>;
 
@@ -52,6 +73,9 @@ pub struct TestRunner {
     pub params: TestParams,
     pub nodes_info: HashMap<NodeId, NodeInfo>,
     pub private_keys: HashMap<NodeId, PrivateKey>,
+    pub next_private_keys: HashMap<NodeId, Vec<(Height, PrivateKey)>>,
+    pub key_rotations: Vec<KeyRotation>,
+    pub validator_changes: Vec<ValidatorChange>,
     pub validator_set: ValidatorSet,
     pub consensus_base_port: usize,
     pub mempool_base_port: usize,
@@ -69,7 +93,9 @@ pub struct NodeInfo {
     start_height: Height,
     home_dir: PathBuf,
     middleware: Arc<dyn Middleware>,
+    faults: Vec<Fault>,
     config_modifier: ConfigModifier<Config>,
+    isolation: Isolation,
 }
 
 fn global_slot() -> Option<usize> {
@@ -90,7 +116,7 @@ const PORTS_PER_SLOT: usize = 200; // ample space for 20 nodes
 
 #[async_trait]
 impl NodeRunner<TestContext> for TestRunner {
-    type NodeHandle = Handle;
+    type NodeHandle = SpawnedNode<TestContext, Handle>;
 
     fn new<S>(id: usize, nodes: &[TestNode<TestContext, S>], params: TestParams) -> Self {
         // Check if the NEXTEST_TEST_GLOBAL_SLOT environment variable is set.
@@ -112,6 +138,8 @@ impl NodeRunner<TestContext> for TestRunner {
 
         let (validators, private_keys) = make_validators(nodes, &params);
         let validator_set = ValidatorSet::new(validators);
+        let (key_rotations, next_private_keys) = make_key_rotations(nodes, &private_keys);
+        let validator_changes = make_validator_changes(nodes, &private_keys);
 
         let nodes_info = nodes
             .iter()
@@ -122,7 +150,9 @@ impl NodeRunner<TestContext> for TestRunner {
                         start_height: node.start_height,
                         home_dir: temp_dir(node.id),
                         middleware: Arc::clone(&node.middleware),
+                        faults: node.faults.clone(),
                         config_modifier: Arc::clone(&node.config_modifier),
+                        isolation: node.isolation.clone(),
                     },
                 )
             })
@@ -133,6 +163,9 @@ impl NodeRunner<TestContext> for TestRunner {
             params,
             nodes_info,
             private_keys,
+            next_private_keys,
+            key_rotations,
+            validator_changes,
             validator_set,
             consensus_base_port: base_port,
             mempool_base_port: base_port + 100,
@@ -140,17 +173,41 @@ impl NodeRunner<TestContext> for TestRunner {
         }
     }
 
-    async fn spawn(&self, id: NodeId) -> eyre::Result<Handle> {
+    async fn spawn(&self, id: NodeId) -> eyre::Result<Self::NodeHandle> {
+        let config = self.generate_config(id);
+
+        // A follower runs without any private key
+        let private_key = (!config.consensus.follower).then(|| self.private_keys[&id].clone());
+
         let app = App {
-            config: self.generate_config(id),
+            config,
             home_dir: self.nodes_info[&id].home_dir.clone(),
             validator_set: self.validator_set.clone(),
-            private_key: self.private_keys[&id].clone(),
+            private_key,
+            key_rotations: self.key_rotations.clone(),
+            validator_changes: self.validator_changes.clone(),
+            next_private_keys: self.next_private_keys.get(&id).cloned().unwrap_or_default(),
             start_height: Some(self.nodes_info[&id].start_height),
             middleware: Some(Arc::clone(&self.nodes_info[&id].middleware)),
+            faults: self.nodes_info[&id].faults.clone(),
         };
 
-        app.start().await
+        let isolation = &self.nodes_info[&id].isolation;
+
+        if isolation.is_in_process() {
+            return Ok(SpawnedNode::InProcess(app.start().await?));
+        }
+
+        let spec_path = app.home_dir.join("node.json");
+        NodeSpec::from_app(&app)?.write(&spec_path)?;
+
+        let command = ProcessCommand::new(node_binary()?)
+            .arg(&spec_path)
+            .mount(&app.home_dir)
+            .isolated(isolation);
+
+        let handle = ProcessHandle::spawn(&command, decode_event)?;
+        Ok(SpawnedNode::Process(handle))
     }
 
     async fn reset_db(&self, id: NodeId) -> eyre::Result<()> {
@@ -159,6 +216,74 @@ impl NodeRunner<TestContext> for TestRunner {
         std::fs::create_dir_all(&db_dir)?;
         Ok(())
     }
+
+    async fn corrupt_wal(&self, id: NodeId, corruption: &WalCorruption) -> eyre::Result<()> {
+        let wal_path = self.nodes_info[&id]
+            .home_dir
+            .join("wal")
+            .join("consensus.wal");
+        corruption.apply(&wal_path)?;
+        Ok(())
+    }
+
+    async fn partition(
+        &self,
+        handle: &Self::NodeHandle,
+        id: NodeId,
+        peers: &[NodeId],
+        duration: Duration,
+    ) -> eyre::Result<()> {
+        let Some(handle) = handle.in_process() else {
+            eyre::bail!("Network partitions are only supported in-process (node {id})");
+        };
+
+        let monikers = peers.iter().map(|&peer| moniker(peer)).collect::<Vec<_>>();
+        let banned = handle.ban_peers(&monikers, duration).await?;
+
+        if banned < peers.len() {
+            tracing::warn!(%id, ?peers, %banned, "Some peers were not connected when the partition started");
+        }
+
+        Ok(())
+    }
+
+    async fn set_paused(
+        &self,
+        handle: &Self::NodeHandle,
+        id: NodeId,
+        paused: bool,
+    ) -> eyre::Result<()> {
+        let Some(handle) = handle.in_process() else {
+            eyre::bail!("Pausing consensus is only supported in-process (node {id})");
+        };
+
+        handle.set_paused(paused).await
+    }
+
+    async fn degrade_links(
+        &self,
+        handle: &Self::NodeHandle,
+        id: NodeId,
+        peers: &[NodeId],
+        conditions: LinkConditions,
+    ) -> eyre::Result<()> {
+        let Some(handle) = handle.in_process() else {
+            eyre::bail!("Degraded links are only supported in-process (node {id})");
+        };
+
+        let monikers = peers.iter().map(|&peer| moniker(peer)).collect::<Vec<_>>();
+        let degraded = handle.set_link_conditions(&monikers, conditions).await?;
+
+        if degraded < peers.len() {
+            tracing::warn!(%id, ?peers, %degraded, "Some peers were not connected when the links were degraded");
+        }
+
+        Ok(())
+    }
+}
+
+fn moniker(node: NodeId) -> String {
+    format!("node-{node}")
 }
 
 impl TestRunner {
@@ -182,13 +307,22 @@ impl TestRunner {
         let i = node - 1;
 
         Config {
-            moniker: format!("node-{node}"),
+            moniker: moniker(node),
             logging: LoggingConfig::default(),
             consensus: ConsensusConfig {
                 enabled: true,
                 // Current test app does not support proposal-only value payload properly as Init does not include valid_round
                 value_payload: ValuePayload::ProposalAndParts,
                 queue_capacity: 100,
+                max_votes_per_validator_per_round: 4,
+                follower: false,
+                dev_mode: false,
+                deterministic_ordering: None,
+                decision_proof: false,
+                verification_workers: 0,
+                verification_batch_size: 64,
+                max_value_size: ByteSize::mib(64),
+                mailbox: Default::default(),
                 p2p: P2pConfig {
                     protocol,
                     discovery: DiscoveryConfig::default(),
@@ -221,6 +355,7 @@ impl TestRunner {
                     .parse()
                     .unwrap(),
             },
+            telemetry: TelemetryConfig::default(),
             runtime: RuntimeConfig::single_threaded(),
             test: TestConfig::default(),
         }
@@ -237,6 +372,57 @@ fn transport_from_env(default: TransportProtocol) -> TransportProtocol {
     }
 }
 
+fn make_key_rotations<S>(
+    nodes: &[TestNode<TestContext, S>],
+    private_keys: &HashMap<NodeId, PrivateKey>,
+) -> (Vec<KeyRotation>, HashMap<NodeId, Vec<(Height, PrivateKey)>>) {
+    let mut rng = StdRng::seed_from_u64(0x43);
+
+    let mut key_rotations = Vec::new();
+    let mut next_private_keys = HashMap::<_, Vec<_>>::new();
+
+    for node in nodes {
+        let address = Address::from_public_key(&private_keys[&node.id].public_key());
+
+        for &height in &node.key_rotations {
+            let sk = PrivateKey::generate(&mut rng);
+
+            key_rotations.push(KeyRotation {
+                address,
+                public_key: sk.public_key(),
+                height,
+            });
+
+            next_private_keys
+                .entry(node.id)
+                .or_default()
+                .push((height, sk));
+        }
+    }
+
+    (key_rotations, next_private_keys)
+}
+
+fn make_validator_changes<S>(
+    nodes: &[TestNode<TestContext, S>],
+    private_keys: &HashMap<NodeId, PrivateKey>,
+) -> Vec<ValidatorChange> {
+    nodes
+        .iter()
+        .flat_map(|node| {
+            let public_key = private_keys[&node.id].public_key();
+
+            node.voting_power_changes
+                .iter()
+                .map(move |&(height, voting_power)| ValidatorChange {
+                    public_key,
+                    voting_power,
+                    height,
+                })
+        })
+        .collect()
+}
+
 fn make_validators<S>(
     nodes: &[TestNode<TestContext, S>],
     params: &TestParams,
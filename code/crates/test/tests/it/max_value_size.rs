@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use bytesize::ByteSize;
+
+use crate::{TestBuilder, TestParams};
+
+#[tokio::test]
+pub async fn oversized_values_are_rejected() {
+    const HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..3 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    // Drops the parts of every value proposed by the other validators,
+    // so it can only catch up with them through value sync
+    test.add_node()
+        .with_voting_power(5)
+        .add_config_modifier(|config| config.consensus.max_value_size = ByteSize::b(1))
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(60),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use crate::middlewares::PrevoteNil;
+use crate::{TestBuilder, TestParams};
+
+#[tokio::test]
+pub async fn even_split_stalls_then_recovers() {
+    const FINAL_HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    // Neither half holds more than 2/3 of the voting power,
+    // so no node decides until the partition heals
+    for _ in 0..4 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(2)
+            .expect_stall(Duration::from_secs(5))
+            .expect_progress_within(Duration::from_secs(30))
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    }
+
+    test.partition(&[1, 2], &[3, 4], Duration::from_secs(10));
+
+    test.build().run(Duration::from_secs(60)).await
+}
+
+#[tokio::test]
+pub async fn isolated_node_catches_up_after_healing() {
+    const FINAL_HEIGHT: u64 = 6;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..3 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(2)
+            .expect_progress_within(Duration::from_secs(10))
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    }
+
+    // Cut off from the others, the node stalls while they keep deciding,
+    // then syncs the heights it missed once the partition heals
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(2)
+        .expect_stall(Duration::from_secs(5))
+        .wait_until(FINAL_HEIGHT)
+        .success();
+
+    test.partition(&[1, 2, 3], &[4], Duration::from_secs(10));
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(60),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+pub async fn isolated_node_catches_up_on_rounds_through_vote_sync() {
+    const FINAL_HEIGHT: u64 = 4;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..2 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(2)
+            .expect_stall(Duration::from_secs(5))
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    }
+
+    // Without the isolated node, the others hold enough voting power to move on to the next
+    // rounds of height 2, but not to decide it, as this node prevotes nil
+    test.add_node()
+        .with_voting_power(10)
+        .with_middleware(PrevoteNil::when(|height, _, _| height.as_u64() == 2))
+        .start()
+        .wait_until(2)
+        .expect_stall(Duration::from_secs(5))
+        .wait_until(FINAL_HEIGHT)
+        .success();
+
+    // Cut off from the others, the node stays in the first round of height 2,
+    // which none of its peers decides: there is no value to sync, only the votes
+    // of the rounds it missed, requested once the partition heals
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(2)
+        .expect_stall(Duration::from_secs(5))
+        .wait_until(FINAL_HEIGHT)
+        .success();
+
+    test.partition(&[1, 2, 3], &[4], Duration::from_secs(15));
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(90),
+            TestParams {
+                enable_value_sync: true,
+                enable_vote_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::{TestBuilder, TestParams};
+
+#[tokio::test]
+pub async fn paused_node_stalls_then_catches_up() {
+    const FINAL_HEIGHT: u64 = 8;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..3 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(FINAL_HEIGHT)
+            .success();
+    }
+
+    // While paused, the node keeps running but decides nothing, while the others carry on
+    // without it. Once resumed, it processes the timeouts and sync responses it held,
+    // and catches up with them.
+    test.add_node()
+        .with_voting_power(5)
+        .start()
+        .wait_until(3)
+        .pause()
+        .expect_stall(Duration::from_secs(5))
+        .resume()
+        .expect_progress_within(Duration::from_secs(20))
+        .wait_until(FINAL_HEIGHT)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(60),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use crate::{Container, TestBuilder, TestParams};
+
+const HEIGHT: u64 = 5;
+
+#[tokio::test]
+pub async fn process_nodes_decide_with_in_process_nodes() {
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node().start().wait_until(HEIGHT).success();
+    test.add_node().start().wait_until(HEIGHT).success();
+
+    test.add_node()
+        .run_as_process()
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .run_as_process()
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build().run(Duration::from_secs(60)).await
+}
+
+#[tokio::test]
+pub async fn killed_process_node_recovers() {
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(HEIGHT * 2)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(HEIGHT * 2)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(HEIGHT * 2)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .run_as_process()
+        .start()
+        .wait_until(HEIGHT)
+        .crash()
+        .restart_after(Duration::from_secs(2))
+        .wait_until(HEIGHT * 2)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(60),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+#[ignore = "requires docker and an image able to run the node binary"]
+pub async fn container_nodes_decide() {
+    let image = std::env::var("MALACHITE_TEST_NODE_IMAGE").unwrap_or("debian:bookworm".into());
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node().start().wait_until(HEIGHT).success();
+    test.add_node().start().wait_until(HEIGHT).success();
+
+    test.add_node()
+        .run_in_container(Container::new(&image))
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .run_in_container(Container::new(&image).with_run_arg("--ulimit=nofile=1024"))
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build().run(Duration::from_secs(120)).await
+}
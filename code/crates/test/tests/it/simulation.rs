@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use malachitebft_test_framework::Simulation;
+
+const FINAL_HEIGHT: u64 = 5;
+
+fn simulation() -> Simulation {
+    Simulation::new([10, 10, 10, 10])
+        .with_latency(Duration::from_millis(10), Duration::from_millis(500))
+        .with_max_time(Duration::from_secs(600))
+}
+
+#[test]
+fn same_seed_replays_the_same_run() {
+    let sim = simulation();
+
+    let first = sim.run(42, FINAL_HEIGHT);
+    let second = sim.run(42, FINAL_HEIGHT);
+
+    assert_eq!(first.decided_height(), FINAL_HEIGHT);
+    assert_eq!(first, second);
+
+    let other = sim.run(43, FINAL_HEIGHT);
+    assert_ne!(first.trace, other.trace);
+}
+
+#[test]
+fn explore_seeds() {
+    let result = simulation().explore(0..20, FINAL_HEIGHT, |report| {
+        report.check_agreement()?;
+        report.check_progress(FINAL_HEIGHT)
+    });
+
+    if let Err(failure) = result {
+        panic!("{failure}");
+    }
+}
+
+#[test]
+fn explore_seeds_with_message_loss() {
+    let sim = simulation().with_loss(0.1);
+
+    let result = sim.explore(0..10, FINAL_HEIGHT, |report| {
+        report.check_agreement()?;
+        report.check_progress(FINAL_HEIGHT)
+    });
+
+    if let Err(failure) = result {
+        panic!("{failure}");
+    }
+}
@@ -2,7 +2,7 @@ use std::time::Duration;
 
 use arc_malachitebft_test::middleware::RotateValidators;
 
-use crate::TestBuilder;
+use crate::{TestBuilder, TestParams};
 
 #[tokio::test]
 async fn rotate_validator_set() {
@@ -24,3 +24,119 @@ async fn rotate_validator_set() {
 
     test.build().run(Duration::from_secs(50)).await
 }
+
+#[tokio::test]
+async fn validator_joins_mid_test() {
+    const JOIN_HEIGHT: u64 = 4;
+    const HEIGHT: u64 = 8;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..3 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    test.add_node()
+        .full_node()
+        .join_validator_set_at(JOIN_HEIGHT, 10)
+        .start()
+        .expect_to_vote_from(JOIN_HEIGHT)
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build().run(Duration::from_secs(60)).await
+}
+
+#[tokio::test]
+async fn late_validator_syncs_then_votes() {
+    const JOIN_HEIGHT: u64 = 6;
+    const HEIGHT: u64 = 10;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..3 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    // Starts once the others decided a few heights, which it syncs before joining
+    test.add_node()
+        .full_node()
+        .join_validator_set_at(JOIN_HEIGHT, 10)
+        .start_after(1, Duration::from_secs(5))
+        .expect_to_vote_from(JOIN_HEIGHT)
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(60),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+async fn validator_leaves_mid_test() {
+    const LEAVE_HEIGHT: u64 = 4;
+    const HEIGHT: u64 = 8;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_voting_power(10)
+        .leave_validator_set_at(LEAVE_HEIGHT)
+        .start()
+        .expect_not_voting(LEAVE_HEIGHT, HEIGHT)
+        .success();
+
+    for _ in 0..3 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    test.build().run(Duration::from_secs(60)).await
+}
+
+#[tokio::test]
+async fn voting_power_changes_mid_test() {
+    const HEIGHT: u64 = 10;
+
+    let mut test = TestBuilder::<()>::new();
+
+    // From height 4, nodes 1 and 2 hold more than 2/3 of the voting power,
+    // then nodes 3 and 4 take it back from height 7
+    for _ in 0..2 {
+        test.add_node()
+            .with_voting_power(10)
+            .with_voting_power_at(4, 40)
+            .with_voting_power_at(7, 10)
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    for _ in 0..2 {
+        test.add_node()
+            .with_voting_power(10)
+            .with_voting_power_at(7, 40)
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    test.build().run(Duration::from_secs(60)).await
+}
@@ -801,6 +801,52 @@ pub async fn response_size_limit_exceeded() {
         .await
 }
 
+#[tokio::test]
+pub async fn oversized_values_are_chunked() {
+    const HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    test.add_node()
+        .with_voting_power(5)
+        .start()
+        .wait_until(2)
+        .crash()
+        .reset_db()
+        .restart_after(Duration::from_secs(5))
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(60),
+            TestParams {
+                enable_value_sync: true,
+                // Values are around ~900 bytes, so none of them fits in a response on its own,
+                // node 3 is only able to sync if the values are split into chunks.
+                max_response_size: ByteSize::b(500),
+                rpc_max_size: ByteSize::b(500),
+                max_chunked_value_size: ByteSize::kib(4),
+                batch_size: 2,
+                parallel_requests: 1,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
 #[tokio::test]
 pub async fn status_update_on_decision() {
     const HEIGHT: u64 = 10;
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::TestBuilder;
+
+#[tokio::test]
+pub async fn all_correct_nodes() {
+    const HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..3 {
+        test.add_node()
+            .add_config_modifier(|config| {
+                config.consensus.verification_workers = 2;
+                config.consensus.verification_batch_size = 8;
+            })
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    test.build().run(Duration::from_secs(30)).await
+}
+
+#[tokio::test]
+pub async fn mixed_verification_modes() {
+    const HEIGHT: u64 = 5;
+
+    let mut test = TestBuilder::<()>::new();
+
+    test.add_node()
+        .add_config_modifier(|config| config.consensus.verification_workers = 4)
+        .start()
+        .wait_until(HEIGHT)
+        .success();
+
+    for _ in 0..2 {
+        test.add_node().start().wait_until(HEIGHT).success();
+    }
+
+    test.build().run(Duration::from_secs(30)).await
+}
@@ -445,3 +445,38 @@ async fn multi_rounds_1() {
 async fn multi_rounds_2() {
     test_multi_rounds(3, Duration::from_secs(10)).await
 }
+
+#[tokio::test]
+async fn restart_after_graceful_shutdown() {
+    const HEIGHT: u64 = 6;
+
+    let mut test = TestBuilder::<()>::new();
+
+    for _ in 0..2 {
+        test.add_node()
+            .with_voting_power(10)
+            .start()
+            .wait_until(HEIGHT)
+            .success();
+    }
+
+    // Holds enough voting power to halt consensus if its WAL were not consistent on restart
+    test.add_node()
+        .with_voting_power(10)
+        .start()
+        .wait_until(3)
+        .shutdown()
+        .restart_after(Duration::from_secs(1))
+        .wait_until(HEIGHT)
+        .success();
+
+    test.build()
+        .run_with_params(
+            Duration::from_secs(30),
+            TestParams {
+                enable_value_sync: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
@@ -1,5 +1,5 @@
 use futures::executor::block_on;
-use malachitebft_core_types::CommitCertificate;
+use malachitebft_core_types::{CommitCertificate, DecisionProof};
 use malachitebft_signing::SigningProviderExt;
 
 use super::{make_validators, types::*, CertificateBuilder, CertificateTest, DEFAULT_SEED};
@@ -469,3 +469,33 @@ fn commit_certificate_quorum_boundary_just_above_two_thirds_sufficient() {
         .with_votes(0..2, VoteType::Precommit)
         .expect_valid();
 }
+
+/// Tests the bitmap of signers in the decision proof built from a commit certificate.
+#[test]
+fn decision_proof_signers_bitmap() {
+    let (validators, _) = make_validators([10; 10], DEFAULT_SEED);
+    let validator_set = ValidatorSet::new(validators);
+
+    let signed = [0, 3, 8, 9];
+    let certificate = CommitCertificate::<TestContext> {
+        height: Height::new(1),
+        round: Round::new(2),
+        value_id: ValueId::new(42),
+        commit_signatures: signed
+            .iter()
+            .map(|&i| {
+                let address = validator_set.get_by_index(i).unwrap().address;
+                CommitSignature::new(address, Signature::test())
+            })
+            .collect(),
+    };
+
+    let proposer = validator_set.get_by_index(1).unwrap().address;
+    let proof = DecisionProof::new(&certificate, &validator_set, proposer);
+
+    assert_eq!(proof.round, Round::new(2));
+    assert_eq!(proof.proposer, proposer);
+    assert_eq!(proof.signers, vec![0b0000_1001, 0b0000_0011]);
+    assert_eq!(proof.num_signers(), signed.len());
+    assert!((0..12).all(|i| proof.has_signed(i) == signed.contains(&i)));
+}
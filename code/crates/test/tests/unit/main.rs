@@ -1,2 +1,4 @@
 mod certificates;
+mod remote_signer;
 mod sync;
+mod wal_replay;
@@ -0,0 +1,189 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
+
+use arc_malachitebft_test::codec::json::JsonCodec;
+use arc_malachitebft_test::{
+    utils, Address, Ed25519Provider, Ed25519Verifier, Height, PublicKey, TestContext, Value,
+};
+use malachitebft_core_types::{Context, NilOrVal, Round, SignedVote};
+use malachitebft_signing::SigningProvider;
+use malachitebft_signing_remote::{
+    RemoteSigner, RemoteSigningProvider, SharedSecret, SignRequest, SignResponse,
+};
+
+type Signer = RemoteSigner<TestContext, JsonCodec, Ed25519Provider>;
+type Client = RemoteSigningProvider<TestContext, JsonCodec, Ed25519Verifier>;
+
+struct Setup {
+    ctx: TestContext,
+    address: Address,
+    public_key: PublicKey,
+    other: Address,
+    secret: SharedSecret,
+    signer: Arc<Signer>,
+}
+
+fn setup(state_file: &Path) -> Setup {
+    let [(validator, private_key), (other, _)] = utils::validators::make_validators([10, 10]);
+    let secret = SharedSecret::generate();
+
+    let signer = RemoteSigner::new(
+        validator.address,
+        secret.clone(),
+        JsonCodec,
+        Ed25519Provider::new(private_key),
+        state_file,
+    )
+    .unwrap();
+
+    Setup {
+        ctx: TestContext::new(),
+        address: validator.address,
+        public_key: validator.public_key,
+        other: other.address,
+        secret,
+        signer: Arc::new(signer),
+    }
+}
+
+impl Setup {
+    async fn client(&self) -> Client {
+        self.client_with_secret(self.secret.clone()).await
+    }
+
+    async fn client_with_secret(&self, secret: SharedSecret) -> Client {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(Arc::clone(&self.signer).serve(listener));
+
+        RemoteSigningProvider::new(address.to_string(), secret, JsonCodec, Ed25519Verifier)
+    }
+
+    fn prevote(&self, height: u64, round: u32, value: u64) -> SignRequest<TestContext> {
+        let value_id = NilOrVal::Val(Value::new(value).id());
+
+        SignRequest::Vote(self.ctx.new_prevote(
+            Height::new(height),
+            Round::new(round),
+            value_id,
+            self.address,
+        ))
+    }
+
+    async fn sign(&self, request: SignRequest<TestContext>) -> Option<SignResponse<TestContext>> {
+        match self.signer.sign(request).await {
+            SignResponse::Rejected(_) => None,
+            response => Some(response),
+        }
+    }
+}
+
+#[tokio::test]
+async fn signs_over_tcp() {
+    let dir = tempfile::tempdir().unwrap();
+    let setup = setup(&dir.path().join("sign_state.json"));
+    let client = setup.client().await;
+
+    let SignRequest::Vote(vote) = setup.prevote(1, 0, 1) else {
+        unreachable!()
+    };
+
+    let signed: SignedVote<TestContext> = client.sign_vote(vote).await.unwrap();
+    let result = client
+        .verify_signed_vote(&signed.message, &signed.signature, &setup.public_key)
+        .await
+        .unwrap();
+
+    assert!(result.is_valid());
+}
+
+#[tokio::test]
+async fn refuses_nodes_without_the_shared_secret() {
+    let dir = tempfile::tempdir().unwrap();
+    let setup = setup(&dir.path().join("sign_state.json"));
+    let client = setup.client_with_secret(SharedSecret::generate()).await;
+
+    let SignRequest::Vote(vote) = setup.prevote(1, 0, 1) else {
+        unreachable!()
+    };
+
+    assert!(client.sign_vote(vote).await.is_err());
+}
+
+#[tokio::test]
+async fn same_message_gets_same_signature() {
+    let dir = tempfile::tempdir().unwrap();
+    let setup = setup(&dir.path().join("sign_state.json"));
+
+    let first = setup.sign(setup.prevote(1, 0, 1)).await;
+    let second = setup.sign(setup.prevote(1, 0, 1)).await;
+
+    assert!(first.is_some());
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn refuses_to_double_sign() {
+    let dir = tempfile::tempdir().unwrap();
+    let setup = setup(&dir.path().join("sign_state.json"));
+
+    assert!(setup.sign(setup.prevote(1, 0, 1)).await.is_some());
+    assert!(setup.sign(setup.prevote(1, 0, 2)).await.is_none());
+
+    // A different value may be voted for in a later round
+    assert!(setup.sign(setup.prevote(1, 1, 2)).await.is_some());
+}
+
+#[tokio::test]
+async fn refuses_to_sign_lower_height_or_round() {
+    let dir = tempfile::tempdir().unwrap();
+    let setup = setup(&dir.path().join("sign_state.json"));
+
+    assert!(setup.sign(setup.prevote(2, 1, 1)).await.is_some());
+    assert!(setup.sign(setup.prevote(2, 0, 1)).await.is_none());
+    assert!(setup.sign(setup.prevote(1, 3, 1)).await.is_none());
+}
+
+#[tokio::test]
+async fn refuses_to_sign_for_another_validator() {
+    let dir = tempfile::tempdir().unwrap();
+    let setup = setup(&dir.path().join("sign_state.json"));
+
+    let vote = setup
+        .ctx
+        .new_prevote(Height::new(1), Round::new(0), NilOrVal::Nil, setup.other);
+
+    let proposal = setup.ctx.new_proposal(
+        Height::new(1),
+        Round::new(0),
+        Value::new(1),
+        Round::Nil,
+        setup.other,
+    );
+
+    assert!(setup.sign(SignRequest::Vote(vote)).await.is_none());
+    assert!(setup.sign(SignRequest::Proposal(proposal)).await.is_none());
+    assert!(setup
+        .sign(SignRequest::Bytes(b"anything".to_vec().into()))
+        .await
+        .is_none());
+}
+
+#[tokio::test]
+async fn state_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let state_file = dir.path().join("sign_state.json");
+
+    let first = {
+        let setup = setup(&state_file);
+        setup.sign(setup.prevote(1, 0, 1)).await
+    };
+
+    let setup = setup(&state_file);
+
+    assert_eq!(setup.sign(setup.prevote(1, 0, 1)).await, first);
+    assert!(setup.sign(setup.prevote(1, 0, 2)).await.is_none());
+}
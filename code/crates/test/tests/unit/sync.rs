@@ -1,5 +1,5 @@
 use arc_malachitebft_test::{Height, TestContext};
-use malachitebft_sync::{PeerId, State, Status};
+use malachitebft_sync::{Config, PeerId, State, Status};
 use std::collections::BTreeMap;
 
 #[test]
@@ -85,6 +85,7 @@ fn filter_peers_by_range_test() {
                     peer_id: *peer_id,
                     tip_height: Height::new(*max),
                     history_min_height: Height::new(*min),
+                    min_needed_height: None,
                 },
             );
         }
@@ -139,3 +140,37 @@ fn filter_peers_by_range_test() {
         }
     }
 }
+
+#[test]
+fn low_watermark_test() {
+    let status = |tip: u64, min_needed: Option<u64>| Status::<TestContext> {
+        peer_id: PeerId::random(),
+        tip_height: Height::new(tip),
+        history_min_height: Height::new(1),
+        min_needed_height: min_needed.map(Height::new),
+    };
+
+    let rng = Box::new(rand::rngs::OsRng);
+    let mut state = State::<TestContext>::new(rng, Config::default());
+
+    // No peers, no low watermark
+    assert_eq!(state.low_watermark(), None);
+
+    // Peers which do not advertise the height they need are assumed to need every height above their tip
+    state.update_status(status(20, None));
+    assert_eq!(state.low_watermark(), Some(Height::new(21)));
+
+    // A syncing peer holds back the low watermark
+    state.update_status(status(20, Some(5)));
+    assert_eq!(state.low_watermark(), Some(Height::new(5)));
+
+    state.update_status(status(10, Some(11)));
+    assert_eq!(state.low_watermark(), Some(Height::new(5)));
+
+    // The local node advertises the height above its tip only if configured to
+    assert_eq!(state.min_needed_height(), None);
+
+    state.config = Config::default().with_advertise_min_needed_height(true);
+    state.tip_height = Height::new(7);
+    assert_eq!(state.min_needed_height(), Some(Height::new(8)));
+}
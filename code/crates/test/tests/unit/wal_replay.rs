@@ -0,0 +1,135 @@
+use futures::executor::block_on;
+
+use arc_malachitebft_test::{
+    utils, Ed25519Provider, Height, TestContext, Validator, ValidatorSet, Value,
+};
+use malachitebft_core_consensus::{ProposedValue, SignedConsensusMsg, ValuePayload};
+use malachitebft_core_types::{Context, NilOrVal, Round, Validity, VoteType};
+use malachitebft_engine::wal::replay::{diff, ReplayedHeight, Replayer};
+use malachitebft_engine::wal::WalEntry;
+use malachitebft_signing::SigningProvider;
+
+const SEED: u64 = 0xfeedbeef;
+
+struct Setup {
+    ctx: TestContext,
+    height: Height,
+    validators: [Validator; 4],
+    signers: [Ed25519Provider; 4],
+    validator_set: ValidatorSet,
+}
+
+fn setup() -> Setup {
+    let (validators, private_keys): (Vec<_>, Vec<_>) =
+        utils::validators::make_validators_seeded([10, 10, 10, 10], SEED)
+            .into_iter()
+            .map(|(v, pk)| (v, Ed25519Provider::new(pk)))
+            .unzip();
+
+    Setup {
+        ctx: TestContext::new(),
+        height: Height::new(1),
+        validator_set: ValidatorSet::new(validators.clone()),
+        validators: validators.try_into().unwrap(),
+        signers: private_keys.try_into().unwrap(),
+    }
+}
+
+impl Setup {
+    fn proposed_value(&self, value: Value) -> WalEntry<TestContext> {
+        let proposer = self
+            .ctx
+            .select_proposer(&self.validator_set, self.height, Round::new(0));
+
+        WalEntry::ProposedValue(ProposedValue {
+            height: self.height,
+            round: Round::new(0),
+            valid_round: Round::Nil,
+            proposer: proposer.address,
+            value,
+            validity: Validity::Valid,
+        })
+    }
+
+    fn votes(&self, vote_type: VoteType, value: Option<&Value>) -> Vec<WalEntry<TestContext>> {
+        let value_id = match value {
+            Some(value) => NilOrVal::Val(value.id()),
+            None => NilOrVal::Nil,
+        };
+
+        let (ctx, height, round) = (&self.ctx, self.height, Round::new(0));
+
+        self.validators
+            .iter()
+            .zip(&self.signers)
+            .map(|(validator, signer)| {
+                let address = validator.address;
+
+                let vote = match vote_type {
+                    VoteType::Prevote => ctx.new_prevote(height, round, value_id, address),
+                    VoteType::Precommit => ctx.new_precommit(height, round, value_id, address),
+                };
+
+                let signed = block_on(signer.sign_vote(vote)).unwrap();
+                WalEntry::ConsensusMsg(SignedConsensusMsg::Vote(signed))
+            })
+            .collect()
+    }
+
+    fn replay(&self, entries: Vec<WalEntry<TestContext>>) -> ReplayedHeight<TestContext> {
+        let replayer = Replayer::new(
+            self.ctx.clone(),
+            self.validators[0].address,
+            ValuePayload::PartsOnly,
+        );
+
+        let entries = entries.into_iter().map(Ok);
+        block_on(replayer.replay(self.height, self.validator_set.clone(), entries))
+    }
+}
+
+#[test]
+fn replay_decides_height() {
+    let setup = setup();
+    let value = Value::new(42);
+
+    let mut entries = vec![setup.proposed_value(value.clone())];
+    entries.extend(setup.votes(VoteType::Prevote, Some(&value)));
+    entries.extend(setup.votes(VoteType::Precommit, Some(&value)));
+
+    let replay = setup.replay(entries.clone());
+
+    assert!(replay.errors.is_empty(), "{:?}", replay.errors);
+    assert_eq!(replay.entries, entries.len());
+    assert!(replay.is_decided());
+
+    let state = replay.final_state().unwrap();
+    assert_eq!(state.decided, Some((Round::new(0), value.id())));
+
+    // Replaying the same entries yields the same transitions
+    let again = setup.replay(entries);
+    assert!(diff(&replay, &again).is_none());
+}
+
+#[test]
+fn diff_finds_divergence() {
+    let setup = setup();
+    let value = Value::new(42);
+
+    let mut decided = vec![setup.proposed_value(value.clone())];
+    decided.extend(setup.votes(VoteType::Prevote, Some(&value)));
+    decided.extend(setup.votes(VoteType::Precommit, Some(&value)));
+
+    // The value never made it to the other node, which prevoted for nil
+    let nil = setup.votes(VoteType::Prevote, None);
+
+    let left = setup.replay(decided);
+    let right = setup.replay(nil);
+
+    assert!(!right.is_decided());
+
+    let divergence = diff(&left, &right).expect("replays should diverge");
+    assert_eq!(divergence.round, Round::new(0));
+    assert!(divergence.left.unwrap().decided.is_some());
+    assert!(divergence.right.unwrap().decided.is_none());
+}
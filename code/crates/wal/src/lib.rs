@@ -7,8 +7,10 @@ mod storage;
 mod version;
 
 pub mod log;
+pub mod segmented;
 
 pub use file::{Log, LogEntry, LogIter};
+pub use segmented::{SegmentOptions, SegmentedLog};
 pub use storage::Storage;
 pub use version::Version;
 
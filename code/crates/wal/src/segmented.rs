@@ -0,0 +1,417 @@
+//! Write-Ahead Log (WAL) split into several segment files.
+//!
+//! Entries are appended to the last segment of the current sequence, and a new segment
+//! is started once it grows past [`SegmentOptions::max_segment_size`]. When the log is
+//! reset to a new sequence, the segments of the previous sequences are kept for
+//! [`SegmentOptions::retain_sequences`] sequences, then deleted.
+//!
+//! # Layout on disk
+//!
+//! For a log at `path/to/consensus.wal`, segment `n` of sequence `s` is stored at
+//! `path/to/consensus.wal.<s>.<n>`, each segment being a regular [`Log`].
+//!
+//! # Crash safety
+//!
+//! - Segments are only ever appended to while they are the last one, and a segment
+//!   is flushed before the next one is created, so only the last segment may end
+//!   with a partial entry, which is dropped when opening the log.
+//! - A segment is created with the sequence number of the log before any entry is
+//!   appended to it. A segment left empty by a crash is reset to the expected
+//!   sequence when opening the log.
+//! - When resetting the log, the segments of the new sequence are created before
+//!   the segments of the older sequences are deleted, which is done again
+//!   when opening the log in case the node crashed in between.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::log::constants::ENTRY_HEADER_SIZE;
+use crate::Log;
+
+type Entries<'a> = Box<dyn Iterator<Item = io::Result<Vec<u8>>> + 'a>;
+
+/// Options for a [`SegmentedLog`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SegmentOptions {
+    /// Size in bytes past which a new segment is started
+    pub max_segment_size: u64,
+
+    /// Number of sequences before the current one whose segments are kept
+    pub retain_sequences: u64,
+}
+
+impl Default for SegmentOptions {
+    fn default() -> Self {
+        Self {
+            max_segment_size: 64 * 1024 * 1024,
+            retain_sequences: 0,
+        }
+    }
+}
+
+/// Write-Ahead Log (WAL) split into several segment files, see the [module docs](self)
+#[derive(Debug)]
+pub struct SegmentedLog {
+    path: PathBuf,
+    options: SegmentOptions,
+    sequence: u64,
+    /// Segments of the current sequence, ordered by index, never empty
+    segments: Vec<Log>,
+}
+
+impl SegmentedLog {
+    /// Opens the segmented log at the given path, creating it if it does not exist.
+    ///
+    /// The segments of the latest sequence found on disk make up the log,
+    /// the segments of sequences which are too old to be retained are deleted.
+    pub fn open(path: impl AsRef<Path>, options: SegmentOptions) -> io::Result<Self> {
+        if options.max_segment_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Maximum segment size must be greater than zero",
+            ));
+        }
+
+        let path = path.as_ref().to_owned();
+        let on_disk = list_segments(&path)?;
+
+        let Some((&sequence, indices)) = on_disk.iter().next_back() else {
+            let segment = create_segment(&path, 0, 0)?;
+
+            return Ok(Self {
+                path,
+                options,
+                sequence: 0,
+                segments: vec![segment],
+            });
+        };
+
+        let mut segments = Vec::with_capacity(indices.len());
+
+        for (position, &index) in indices.iter().enumerate() {
+            // Segments are numbered without gaps, anything after a gap was never
+            // acknowledged as the previous segment was still being written to
+            if index != position as u64 {
+                for &index in &indices[position..] {
+                    fs::remove_file(segment_path(&path, sequence, index))?;
+                }
+
+                break;
+            }
+
+            let mut segment = Log::open(segment_path(&path, sequence, index))?;
+
+            if segment.sequence() != sequence {
+                if !segment.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Segment {index} of sequence {sequence} has entries for sequence {}",
+                            segment.sequence()
+                        ),
+                    ));
+                }
+
+                // Created right before a crash, before its header was updated
+                segment.reset(sequence)?;
+            }
+
+            segments.push(segment);
+        }
+
+        let mut log = Self {
+            path,
+            options,
+            sequence,
+            segments,
+        };
+
+        log.compact()?;
+
+        Ok(log)
+    }
+
+    /// Path of the log, from which the paths of the segments are derived
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Options of the log
+    pub fn options(&self) -> SegmentOptions {
+        self.options
+    }
+
+    /// The current sequence number
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Number of segments of the current sequence
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Number of entries for the current sequence
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(|s| s.len()).sum()
+    }
+
+    /// Whether there are no entries for the current sequence
+    pub fn is_empty(&self) -> bool {
+        self.segments.iter().all(|s| s.is_empty())
+    }
+
+    /// Size in bytes of the segments of the current sequence
+    pub fn size_bytes(&self) -> io::Result<u64> {
+        self.segments.iter().map(|s| s.size_bytes()).sum()
+    }
+
+    /// Appends an entry to the last segment, starting a new segment first
+    /// if the entry would make the last one grow past the maximum segment size.
+    pub fn append(&mut self, data: impl AsRef<[u8]>) -> io::Result<()> {
+        let data = data.as_ref();
+        let last = self.last_segment();
+
+        let entry_size = ENTRY_HEADER_SIZE + data.len() as u64;
+
+        if !last.is_empty() && last.size_bytes()? + entry_size > self.options.max_segment_size {
+            self.rotate()?;
+        }
+
+        self.last_segment_mut().append(data)
+    }
+
+    /// Syncs the last segment to disk, the previous ones were synced when rotated
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_segment_mut().flush()
+    }
+
+    /// Removes all entries and starts over with the given sequence number,
+    /// deleting the segments of the sequences which are too old to be retained.
+    pub fn reset(&mut self, sequence: u64) -> io::Result<()> {
+        // Drop the segments we are about to delete, to release their lock
+        self.segments.clear();
+
+        let result = self.start_sequence(sequence);
+
+        if result.is_err() && self.segments.is_empty() {
+            // Fall back to whatever is left on disk, so that the log stays usable
+            let path = self.path.clone();
+            *self = Self::open(path, self.options)?;
+        }
+
+        result
+    }
+
+    fn start_sequence(&mut self, sequence: u64) -> io::Result<()> {
+        // Delete the segments which would otherwise be taken for the latest ones on restart
+        for (&seq, indices) in list_segments(&self.path)?.range(sequence..) {
+            for &index in indices {
+                fs::remove_file(segment_path(&self.path, seq, index))?;
+            }
+        }
+
+        sync_parent(&self.path)?;
+
+        self.segments.push(create_segment(&self.path, sequence, 0)?);
+        self.sequence = sequence;
+
+        self.compact()
+    }
+
+    /// Removes all entries from the given index onwards
+    pub fn truncate(&mut self, from_entry: u64) -> io::Result<()> {
+        let mut remaining = from_entry;
+        let mut keep = self.segments.len();
+
+        for (position, segment) in self.segments.iter_mut().enumerate() {
+            let len = segment.len() as u64;
+
+            if remaining < len {
+                segment.truncate(remaining)?;
+
+                // Delete the segment if it is left empty, unless it is the first one
+                keep = if remaining == 0 && position > 0 {
+                    position
+                } else {
+                    position + 1
+                };
+
+                break;
+            }
+
+            remaining -= len;
+        }
+
+        // Delete the segments following the one which was truncated
+        if keep < self.segments.len() {
+            let removed = self.segments.len() - keep;
+            self.segments.truncate(keep);
+
+            for index in keep..keep + removed {
+                fs::remove_file(segment_path(&self.path, self.sequence, index as u64))?;
+            }
+
+            sync_parent(&self.path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an iterator over the entries of all segments of the current sequence
+    pub fn iter(&mut self) -> impl Iterator<Item = io::Result<Vec<u8>>> + '_ {
+        self.segments.iter_mut().flat_map(|segment| {
+            let entries: Entries<'_> = match segment.iter() {
+                Ok(iter) => Box::new(iter),
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
+
+            entries
+        })
+    }
+
+    /// Sequences of the retained segments, older than the current one, oldest first
+    pub fn retained_sequences(&self) -> io::Result<Vec<u64>> {
+        Ok(list_segments(&self.path)?
+            .into_keys()
+            .filter(|&seq| seq < self.sequence)
+            .collect())
+    }
+
+    /// Opens the segments of a retained sequence, eg. for inspection
+    pub fn open_retained(&self, sequence: u64) -> io::Result<Vec<Log>> {
+        let segments = list_segments(&self.path)?;
+
+        let indices = segments.get(&sequence).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No segments retained for sequence {sequence}"),
+            )
+        })?;
+
+        indices
+            .iter()
+            .map(|&index| Log::open(segment_path(&self.path, sequence, index)))
+            .collect()
+    }
+
+    /// Flush the last segment and start a new one
+    fn rotate(&mut self) -> io::Result<()> {
+        self.last_segment_mut().flush()?;
+
+        let index = self.segments.len() as u64;
+        let segment = create_segment(&self.path, self.sequence, index)?;
+        self.segments.push(segment);
+
+        Ok(())
+    }
+
+    /// Delete the segments of the sequences which are too old to be retained
+    fn compact(&mut self) -> io::Result<()> {
+        let min_sequence = self.sequence.saturating_sub(self.options.retain_sequences);
+
+        let segments = list_segments(&self.path)?;
+        let expired = segments.range(..min_sequence);
+
+        let mut deleted = false;
+
+        for (&seq, indices) in expired {
+            for &index in indices {
+                fs::remove_file(segment_path(&self.path, seq, index))?;
+                deleted = true;
+            }
+        }
+
+        if deleted {
+            sync_parent(&self.path)?;
+        }
+
+        Ok(())
+    }
+
+    fn last_segment(&self) -> &Log {
+        self.segments.last().expect("log always has a segment")
+    }
+
+    fn last_segment_mut(&mut self) -> &mut Log {
+        self.segments.last_mut().expect("log always has a segment")
+    }
+}
+
+/// Path of the segment `index` of sequence `sequence` of the log at `path`
+pub fn segment_path(path: &Path, sequence: u64, index: u64) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".{sequence}.{index}"));
+    path.with_file_name(name)
+}
+
+/// Create a segment and write the sequence number in its header before it is used
+fn create_segment(path: &Path, sequence: u64, index: u64) -> io::Result<Log> {
+    let mut segment = Log::open(segment_path(path, sequence, index))?;
+
+    if segment.sequence() != sequence || !segment.is_empty() {
+        segment.reset(sequence)?;
+    }
+
+    sync_parent(path)?;
+
+    Ok(segment)
+}
+
+/// List the segments of the log at `path` found on disk, by sequence and index
+fn list_segments(path: &Path) -> io::Result<BTreeMap<u64, Vec<u64>>> {
+    let dir = parent_dir(path);
+    let prefix = format!(
+        "{}.",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    );
+
+    let mut segments = BTreeMap::<u64, Vec<u64>>::new();
+
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+
+        let Some(suffix) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+
+        let Some((sequence, index)) = suffix.split_once('.') else {
+            continue;
+        };
+
+        let (Ok(sequence), Ok(index)) = (sequence.parse(), index.parse()) else {
+            continue;
+        };
+
+        segments.entry(sequence).or_default().push(index);
+    }
+
+    for indices in segments.values_mut() {
+        indices.sort_unstable();
+    }
+
+    Ok(segments)
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Sync the directory containing the segments, so that their creation
+/// and deletion survive a crash
+fn sync_parent(path: &Path) -> io::Result<()> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            fs::File::open(parent_dir(path))?.sync_all()
+        } else {
+            let _ = path;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use testdir::{NumberedDir, NumberedDirBuilder};
+
+use arc_malachitebft_wal::segmented::segment_path;
+use arc_malachitebft_wal::{Log, SegmentOptions, SegmentedLog};
+
+static TESTDIR: LazyLock<NumberedDir> =
+    LazyLock::new(|| NumberedDirBuilder::new("wal".to_string()).create().unwrap());
+
+macro_rules! testwal {
+    () => {{
+        let module_path = ::std::module_path!();
+        let test_name = ::testdir::private::extract_test_name(&module_path);
+        let subdir_path = ::std::path::Path::new(&module_path.replace("::", "/")).join(&test_name);
+        TESTDIR.create_subdir(subdir_path).unwrap().join("wal.log")
+    }};
+}
+
+const OPTIONS: SegmentOptions = SegmentOptions {
+    max_segment_size: 128,
+    retain_sequences: 0,
+};
+
+fn entry(i: usize) -> Vec<u8> {
+    format!("entry #{i:04} with some padding").into_bytes()
+}
+
+fn setup_wal(path: &Path, options: SegmentOptions, count: usize) -> io::Result<SegmentedLog> {
+    let mut wal = SegmentedLog::open(path, options)?;
+
+    for i in 0..count {
+        wal.append(entry(i))?;
+    }
+
+    wal.flush()?;
+
+    Ok(wal)
+}
+
+fn read_all(wal: &mut SegmentedLog) -> io::Result<Vec<Vec<u8>>> {
+    wal.iter().collect()
+}
+
+#[test]
+fn new_segmented_wal() -> io::Result<()> {
+    let path = testwal!();
+    let wal = SegmentedLog::open(&path, OPTIONS)?;
+
+    assert_eq!(wal.sequence(), 0);
+    assert_eq!(wal.segment_count(), 1);
+    assert!(wal.is_empty());
+    assert!(segment_path(&path, 0, 0).exists());
+
+    Ok(())
+}
+
+#[test]
+fn rotate_when_segment_is_full() -> io::Result<()> {
+    let path = testwal!();
+
+    let wal = setup_wal(&path, OPTIONS, 20)?;
+    let segments = wal.segment_count();
+
+    assert!(segments > 1, "expected the log to be rotated");
+    assert_eq!(wal.len(), 20);
+    drop(wal);
+
+    for index in 0..segments as u64 {
+        let size = fs::metadata(segment_path(&path, 0, index))?.len();
+        assert!(size <= OPTIONS.max_segment_size);
+    }
+
+    let mut wal = SegmentedLog::open(&path, OPTIONS)?;
+    assert_eq!(wal.segment_count(), segments);
+    assert_eq!(wal.len(), 20);
+
+    let entries = read_all(&mut wal)?;
+    assert_eq!(entries, (0..20).map(entry).collect::<Vec<_>>());
+
+    Ok(())
+}
+
+#[test]
+fn entry_larger_than_segment() -> io::Result<()> {
+    let path = testwal!();
+    let mut wal = SegmentedLog::open(&path, OPTIONS)?;
+
+    let large = vec![42; 4 * OPTIONS.max_segment_size as usize];
+    wal.append(&large)?;
+    wal.append(entry(0))?;
+    wal.flush()?;
+
+    assert_eq!(wal.segment_count(), 2);
+    assert_eq!(read_all(&mut wal)?, vec![large, entry(0)]);
+
+    Ok(())
+}
+
+#[test]
+fn reset_deletes_expired_sequences() -> io::Result<()> {
+    let path = testwal!();
+
+    let options = SegmentOptions {
+        retain_sequences: 2,
+        ..OPTIONS
+    };
+
+    let mut wal = setup_wal(&path, options, 10)?;
+
+    for sequence in 1..=5 {
+        wal.reset(sequence)?;
+
+        for i in 0..10 {
+            wal.append(entry(i))?;
+        }
+
+        wal.flush()?;
+    }
+
+    assert_eq!(wal.sequence(), 5);
+    assert_eq!(wal.retained_sequences()?, vec![3, 4]);
+
+    for sequence in 0..3 {
+        assert!(!segment_path(&path, sequence, 0).exists());
+    }
+
+    let retained = wal.open_retained(4)?;
+    let count = retained.iter().map(|segment| segment.len()).sum::<usize>();
+    assert_eq!(count, 10);
+
+    Ok(())
+}
+
+#[test]
+fn reset_to_same_sequence() -> io::Result<()> {
+    let path = testwal!();
+
+    let mut wal = setup_wal(&path, OPTIONS, 20)?;
+    assert!(wal.segment_count() > 1);
+
+    wal.reset(0)?;
+
+    assert_eq!(wal.sequence(), 0);
+    assert_eq!(wal.segment_count(), 1);
+    assert!(wal.is_empty());
+    assert!(!segment_path(&path, 0, 1).exists());
+
+    Ok(())
+}
+
+#[test]
+fn truncate_across_segments() -> io::Result<()> {
+    let path = testwal!();
+
+    let mut wal = setup_wal(&path, OPTIONS, 20)?;
+    let segments = wal.segment_count();
+
+    wal.truncate(5)?;
+
+    assert_eq!(wal.len(), 5);
+    assert!(wal.segment_count() < segments);
+    assert_eq!(read_all(&mut wal)?, (0..5).map(entry).collect::<Vec<_>>());
+
+    drop(wal);
+
+    let mut wal = SegmentedLog::open(&path, OPTIONS)?;
+    assert_eq!(wal.len(), 5);
+    assert_eq!(read_all(&mut wal)?, (0..5).map(entry).collect::<Vec<_>>());
+
+    Ok(())
+}
+
+#[test]
+fn crash_safety_partial_write_in_last_segment() -> io::Result<()> {
+    let path = testwal!();
+
+    let wal = setup_wal(&path, OPTIONS, 20)?;
+    let last = wal.segment_count() as u64 - 1;
+    drop(wal);
+
+    // Simulate a crash in the middle of writing an entry: its header claims
+    // more data than what made it to disk
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(segment_path(&path, 0, last))?;
+
+    file.write_all(&[0])?;
+    file.write_all(&1000_u64.to_be_bytes())?;
+    file.write_all(&[0xAB; 10])?;
+    file.sync_all()?;
+    drop(file);
+
+    let mut wal = SegmentedLog::open(&path, OPTIONS)?;
+    assert_eq!(wal.len(), 20);
+    assert_eq!(read_all(&mut wal)?, (0..20).map(entry).collect::<Vec<_>>());
+
+    wal.append(entry(20))?;
+    assert_eq!(wal.len(), 21);
+
+    Ok(())
+}
+
+#[test]
+fn crash_safety_during_rotation() -> io::Result<()> {
+    let path = testwal!();
+
+    let mut wal = setup_wal(&path, OPTIONS, 20)?;
+    wal.reset(7)?;
+
+    for i in 0..20 {
+        wal.append(entry(i))?;
+    }
+
+    wal.flush()?;
+
+    let next = wal.segment_count() as u64;
+    drop(wal);
+
+    // Simulate a crash right after the next segment was created,
+    // before its header was updated with the sequence of the log
+    let segment = Log::open(segment_path(&path, 7, next))?;
+    assert_eq!(segment.sequence(), 0);
+    drop(segment);
+
+    let mut wal = SegmentedLog::open(&path, OPTIONS)?;
+    assert_eq!(wal.sequence(), 7);
+    assert_eq!(wal.segment_count() as u64, next + 1);
+    assert_eq!(read_all(&mut wal)?, (0..20).map(entry).collect::<Vec<_>>());
+
+    wal.append(entry(20))?;
+    wal.flush()?;
+    drop(wal);
+
+    let segment = Log::open(segment_path(&path, 7, next))?;
+    assert_eq!(segment.sequence(), 7);
+    assert_eq!(segment.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn crash_safety_during_reset() -> io::Result<()> {
+    let path = testwal!();
+
+    let wal = setup_wal(&path, OPTIONS, 20)?;
+    let segments = wal.segment_count() as u64;
+    drop(wal);
+
+    // Simulate a crash after the first segment of the next sequence was created,
+    // but before the segments of the previous sequence were deleted
+    let mut segment = Log::open(segment_path(&path, 1, 0))?;
+    segment.reset(1)?;
+    drop(segment);
+
+    let wal = SegmentedLog::open(&path, OPTIONS)?;
+    assert_eq!(wal.sequence(), 1);
+    assert!(wal.is_empty());
+
+    for index in 0..segments {
+        assert!(!segment_path(&path, 0, index).exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn crash_safety_segment_after_gap() -> io::Result<()> {
+    let path = testwal!();
+
+    let wal = setup_wal(&path, OPTIONS, 20)?;
+    let segments = wal.segment_count() as u64;
+    drop(wal);
+
+    // A segment which does not directly follow the last one cannot have been
+    // written to by the log, drop it
+    let mut stray = Log::open(segment_path(&path, 0, segments + 1))?;
+    stray.append(entry(1000))?;
+    stray.flush()?;
+    drop(stray);
+
+    let mut wal = SegmentedLog::open(&path, OPTIONS)?;
+    assert_eq!(wal.segment_count() as u64, segments);
+    assert_eq!(read_all(&mut wal)?, (0..20).map(entry).collect::<Vec<_>>());
+    assert!(!segment_path(&path, 0, segments + 1).exists());
+
+    Ok(())
+}
+
+#[test]
+fn invalid_options() {
+    let path = testwal!();
+
+    let options = SegmentOptions {
+        max_segment_size: 0,
+        ..OPTIONS
+    };
+
+    let result = SegmentedLog::open(path, options);
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
@@ -12,7 +12,7 @@ use malachitebft_app_channel::app::types::core::{Height as _, Round, Validity};
 use malachitebft_app_channel::app::types::sync::RawDecidedValue;
 use malachitebft_app_channel::app::types::{LocallyProposedValue, ProposedValue};
 use malachitebft_app_channel::{
-    AppMsg, Channels, ConsensusRequest, ConsensusRequestError, NetworkMsg,
+    AppMsg, Channels, ConsensusRequest, ConsensusRequestError, NetworkMsg, SyncRequest,
 };
 use malachitebft_test::{Height, TestContext};
 
@@ -48,7 +48,25 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
         monitor_state(channels.requests.clone());
     }
 
-    while let Some(msg) = channels.consensus.recv().await {
+    loop {
+        let msg = tokio::select! {
+            msg = channels.consensus.recv() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+
+            // Peers lagging behind may ask us for the parts of a recent proposal
+            Some(message) = channels.peer_messages.recv() => {
+                let reply = state.serve_proposal_parts(message.peer_id, &message.payload);
+
+                if let Some(reply) = reply {
+                    let _ = message.reply.send(reply);
+                }
+
+                continue;
+            }
+        };
+
         match msg {
             // The first message to handle is the `ConsensusReady` message, signaling to the app
             // that Malachite is ready to start consensus
@@ -244,6 +262,7 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
             AppMsg::Decided {
                 certificate,
                 extensions: _,
+                proof: _,
             } => {
                 info!(
                     height = %certificate.height,
@@ -275,6 +294,17 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
                 // When that happens, we store the decided value in our store
                 match state.commit(certificate, extensions).await {
                     Ok(_) => {
+                        // Keep the store bounded by pruning the oldest decided values in the
+                        // background, once sync no longer serves them to the peers
+                        let retain_height = state.retain_height();
+
+                        match SyncRequest::set_retain_height(&channels.sync_requests, retain_height)
+                            .await
+                        {
+                            Ok(_) => state.store.prune_decided(retain_height),
+                            Err(e) => error!("Failed to set the retain height of sync: {e}"),
+                        }
+
                         // Sleep a bit to slow down the app.
                         sleep(Duration::from_millis(500)).await;
 
@@ -403,6 +433,23 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
                 };
                 info!(%height, %proposal_round, "Restreaming existing propos*al...");
 
+                // The parts of recent proposals are cached, in which case
+                // they can be re-streamed without going through the store
+                if let Some(stream) =
+                    state.restream_cached_proposal(height, round, valid_round, value_id)
+                {
+                    for stream_message in stream {
+                        info!(%height, %valid_round, "Publishing proposal part: {stream_message:?}");
+
+                        channels
+                            .network
+                            .send(NetworkMsg::PublishProposalPart(stream_message))
+                            .await?;
+                    }
+
+                    continue;
+                }
+
                 let proposal = state
                     .store
                     .get_undecided_proposal(height, proposal_round, value_id)
@@ -426,6 +473,33 @@ pub async fn run(state: &mut State, channels: &mut Channels<TestContext>) -> eyr
                     }
                 }
             }
+
+            // This application does not take snapshots of its state,
+            // so it has none to offer to the peers which are lagging behind.
+            AppMsg::GetSnapshots { reply } => {
+                if reply.send(Vec::new()).is_err() {
+                    error!("Failed to send GetSnapshots reply");
+                }
+            }
+
+            AppMsg::GetSnapshotChunk { reply, .. } => {
+                if reply.send(None).is_err() {
+                    error!("Failed to send GetSnapshotChunk reply");
+                }
+            }
+
+            AppMsg::RestoreSnapshot { reply, .. } => {
+                if reply.send(None).is_err() {
+                    error!("Failed to send RestoreSnapshot reply");
+                }
+            }
+
+            // Nor does it restore snapshots, so it is never asked to store backfilled values.
+            AppMsg::StoreBackfilledValues { reply, .. } => {
+                if reply.send(false).is_err() {
+                    error!("Failed to send StoreBackfilledValues reply");
+                }
+            }
         }
     }
 
@@ -5,11 +5,13 @@ use eyre::{eyre, Result};
 use malachitebft_test::node::Node;
 use tracing::info;
 
+use malachitebft_app_channel::app::engine::wal::replay::Replayer;
 use malachitebft_test::codec::proto::ProtobufCodec;
-use malachitebft_test::Height;
+use malachitebft_test::{Height, TestContext};
 use malachitebft_test_cli::args::{Args, Commands};
 use malachitebft_test_cli::cmd::dump_wal::DumpWalCmd;
 use malachitebft_test_cli::cmd::init::InitCmd;
+use malachitebft_test_cli::cmd::replay_wal::{self, ReplayWalCmd};
 use malachitebft_test_cli::cmd::start::StartCmd;
 use malachitebft_test_cli::cmd::testnet::TestnetCmd;
 use malachitebft_test_cli::config::{LogFormat, LogLevel};
@@ -19,6 +21,7 @@ mod app;
 mod config;
 mod metrics;
 mod node;
+mod part_cache;
 mod state;
 mod store;
 mod streaming;
@@ -45,6 +48,7 @@ fn main() -> Result<()> {
         Commands::Init(cmd) => init(&args, cmd),
         Commands::Testnet(cmd) => testnet(&args, cmd),
         Commands::DumpWal(cmd) => dump_wal(&args, cmd),
+        Commands::ReplayWal(cmd) => replay_wal(&args, cmd),
         Commands::DistributedTestnet(_) => unimplemented!(),
     }
 }
@@ -56,6 +60,7 @@ fn start(args: &Args, cmd: &StartCmd) -> Result<()> {
         config_file: args.get_config_file_path()?,
         genesis_file: args.get_genesis_file_path()?,
         private_key_file: args.get_priv_validator_key_file_path()?,
+        keystore: args.get_keystore()?,
         start_height: cmd.start_height.map(Height::new),
     };
 
@@ -85,6 +90,7 @@ fn init(args: &Args, cmd: &InitCmd) -> Result<()> {
         config_file: args.get_config_file_path()?,
         genesis_file: args.get_genesis_file_path()?,
         private_key_file: args.get_priv_validator_key_file_path()?,
+        keystore: args.get_keystore()?,
         start_height: None,
     };
 
@@ -93,6 +99,7 @@ fn init(args: &Args, cmd: &InitCmd) -> Result<()> {
         &args.get_config_file_path()?,
         &args.get_genesis_file_path()?,
         &args.get_priv_validator_key_file_path()?,
+        &app.keystore,
     )
     .map_err(|error| eyre!("Failed to run init command {error:?}"))
 }
@@ -108,6 +115,7 @@ fn testnet(args: &Args, cmd: &TestnetCmd) -> Result<()> {
         config_file: args.get_config_file_path()?,
         genesis_file: args.get_genesis_file_path()?,
         private_key_file: args.get_priv_validator_key_file_path()?,
+        keystore: args.get_keystore()?,
         start_height: Some(Height::new(1)), // We always start at height 1
     };
 
@@ -123,3 +131,34 @@ fn dump_wal(_args: &Args, cmd: &DumpWalCmd) -> Result<()> {
     cmd.run(ProtobufCodec)
         .map_err(|error| eyre!("Failed to run dump-wal command {:?}", error))
 }
+
+fn replay_wal(args: &Args, cmd: &ReplayWalCmd) -> Result<()> {
+    // This is a drop guard responsible for flushing any remaining logs when the program terminates.
+    // It must be assigned to a binding that is not _, as _ will result in the guard being dropped immediately.
+    let _guard = logging::init(LogLevel::Info, LogFormat::Plaintext);
+
+    let app = App {
+        home_dir: args.get_home_dir()?,
+        config_file: args.get_config_file_path()?,
+        genesis_file: args.get_genesis_file_path()?,
+        private_key_file: args.get_priv_validator_key_file_path()?,
+        keystore: args.get_keystore()?,
+        start_height: None,
+    };
+
+    let config: Config = app.load_config()?;
+    let genesis = app.load_genesis()?;
+    let private_key = app.load_private_key(app.load_private_key_file()?);
+    let address = app.get_address(&app.get_public_key(&private_key));
+    let signing_provider = app.get_signing_provider(private_key);
+
+    let replayer = Replayer::new(
+        TestContext::new(),
+        address,
+        replay_wal::value_payload(config.consensus.value_payload),
+        &signing_provider,
+    );
+
+    cmd.run(&replayer, ProtobufCodec, genesis.validator_set)
+        .map_err(|error| eyre!("Failed to run replay-wal command {:?}", error))
+}
@@ -4,6 +4,7 @@
 use std::path::PathBuf;
 
 use async_trait::async_trait;
+use bytesize::ByteSize;
 use rand::{CryptoRng, RngCore};
 use tokio::task::JoinHandle;
 use tracing::Instrument;
@@ -14,8 +15,8 @@ use malachitebft_app_channel::app::types::codec::Codec;
 use malachitebft_app_channel::app::types::core::{Height as _, VotingPower};
 use malachitebft_app_channel::app::types::Keypair;
 use malachitebft_app_channel::{
-    ConsensusContext, EngineBuilder, EngineHandle, NetworkContext, NetworkIdentity, RequestContext,
-    SigningProviderExt, SyncContext, WalContext,
+    ConsensusContext, EngineBuilder, EngineHandle, LogFilter, NetworkContext, NetworkIdentity,
+    RequestContext, SigningProviderExt, SyncContext, WalContext,
 };
 use malachitebft_test::node::{Node, NodeHandle};
 use malachitebft_test::traits::{
@@ -29,6 +30,8 @@ use malachitebft_test::{
     Address, Ed25519Provider, Genesis, Height, PrivateKey, PublicKey, TestContext, Validator,
     ValidatorSet,
 };
+use malachitebft_test_cli::keystore::Keystore;
+use malachitebft_test_cli::logging;
 use malachitebft_test_cli::metrics;
 
 use crate::config::{load_config, Config, ValidatorRotationConfig};
@@ -43,6 +46,8 @@ pub struct App {
     pub config_file: PathBuf,
     pub genesis_file: PathBuf,
     pub private_key_file: PathBuf,
+    /// Keystore of the private key file, decrypting it if needed
+    pub keystore: Keystore,
     pub start_height: Option<Height>,
 }
 
@@ -100,8 +105,9 @@ impl Node for App {
     }
 
     fn load_private_key_file(&self) -> eyre::Result<Self::PrivateKeyFile> {
-        let private_key = std::fs::read_to_string(&self.private_key_file)?;
-        serde_json::from_str(&private_key).map_err(Into::into)
+        self.keystore
+            .load(&self.private_key_file)
+            .map_err(Into::into)
     }
 
     fn get_signing_provider(&self, private_key: PrivateKey) -> Self::SigningProvider {
@@ -146,12 +152,18 @@ impl Node for App {
             )
         };
 
+        let mut request_ctx = RequestContext::new(100);
+
+        if let Some(handle) = logging::reload_handle() {
+            request_ctx = request_ctx.with_log_filter(LogFilter::new(handle));
+        }
+
         let (mut channels, engine_handle) = EngineBuilder::new(ctx.clone(), config.clone())
             .with_default_wal(WalContext::new(wal_path, ProtobufCodec))
             .with_default_network(NetworkContext::new(identity, ProtobufCodec))
             .with_default_consensus(ConsensusContext::new(address, signing_provider))
             .with_default_sync(SyncContext::new(ProtobufCodec))
-            .with_default_request(RequestContext::new(100))
+            .with_default_request(request_ctx)
             .build()
             .await?;
 
@@ -256,6 +268,15 @@ fn make_config(index: usize, total: usize, settings: MakeConfigSettings) -> Conf
             // Current channel app does not support parts-only value payload properly as Init does not include valid_round
             value_payload: ValuePayload::ProposalAndParts,
             queue_capacity: 100,
+            max_votes_per_validator_per_round: 4,
+            follower: false,
+            dev_mode: false,
+            deterministic_ordering: None,
+            decision_proof: false,
+            verification_workers: 0,
+            verification_batch_size: 64,
+            max_value_size: ByteSize::mib(64),
+            mailbox: Default::default(),
             p2p: P2pConfig {
                 protocol: PubSubProtocol::default(),
                 listen_addr: settings.transport.multiaddr("127.0.0.1", consensus_port),
@@ -0,0 +1,96 @@
+//! Bounded cache of the proposal parts of the recently assembled proposals, whether built
+//! by this node or received from the proposer.
+//!
+//! It is used both to re-propose a locked value in a later round without reading it back
+//! from the store and splitting it into parts again, and to serve the parts of a proposal
+//! to the peers lagging behind which ask for them.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use malachitebft_app_channel::app::types::core::Round;
+use malachitebft_test::{Height, ValueId};
+
+use crate::streaming::ProposalParts;
+
+type Key = (Height, Round, ValueId);
+
+/// Request sent by a peer for the parts of a proposal, encoded in JSON.
+/// The reply holds the [`ProposalParts`] in JSON, or is dropped if they are not in the cache.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposalPartsRequest {
+    pub height: Height,
+    pub round: Round,
+    pub value_id: ValueId,
+}
+
+pub struct PartCache {
+    capacity: usize,
+    entries: BTreeMap<Key, ProposalParts>,
+}
+
+impl PartCache {
+    /// Cache holding the parts of at most `capacity` proposals
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Cache the parts of the proposal with the given value id,
+    /// evicting the ones for the lowest heights and rounds when full
+    pub fn insert(&mut self, value_id: ValueId, parts: ProposalParts) {
+        self.entries
+            .insert((parts.height, parts.round, value_id), parts);
+
+        while self.entries.len() > self.capacity {
+            self.entries.pop_first();
+        }
+    }
+
+    /// The parts of the proposal for the given height, round and value id, if cached
+    pub fn get(&self, height: Height, round: Round, value_id: ValueId) -> Option<&ProposalParts> {
+        self.entries.get(&(height, round, value_id))
+    }
+
+    /// Evict the parts of the proposals for the heights lower than `min_height`
+    pub fn prune(&mut self, min_height: Height) {
+        self.entries
+            .retain(|(height, _, _), _| *height >= min_height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use malachitebft_test::{Address, ProposalData, ProposalPart};
+
+    use super::*;
+
+    fn parts(height: u64, round: u32) -> ProposalParts {
+        ProposalParts {
+            height: Height::new(height),
+            round: Round::new(round),
+            proposer: Address::new([0; 20]),
+            parts: vec![ProposalPart::Data(ProposalData::new(height))],
+        }
+    }
+
+    #[test]
+    fn lowest_proposals_are_evicted_first() {
+        let mut cache = PartCache::new(2);
+        let value_id = ValueId::new(42);
+
+        cache.insert(value_id, parts(2, 0));
+        cache.insert(value_id, parts(1, 1));
+        cache.insert(value_id, parts(2, 1));
+
+        assert!(cache.get(Height::new(1), Round::new(1), value_id).is_none());
+        assert!(cache.get(Height::new(2), Round::new(0), value_id).is_some());
+        assert!(cache.get(Height::new(2), Round::new(1), value_id).is_some());
+
+        cache.prune(Height::new(3));
+        assert!(cache.get(Height::new(2), Round::new(1), value_id).is_none());
+    }
+}
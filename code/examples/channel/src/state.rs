@@ -23,16 +23,20 @@ use malachitebft_app_channel::app::types::{LocallyProposedValue, PeerId};
 use malachitebft_test::codec::proto::ProtobufCodec;
 use malachitebft_test::{
     Address, Ed25519Provider, Genesis, Height, LinearTimeouts, ProposalData, ProposalFin,
-    ProposalInit, ProposalPart, TestContext, ValidatorSet, Value,
+    ProposalInit, ProposalPart, TestContext, ValidatorSet, Value, ValueId,
 };
 
 use crate::config::ValidatorRotationConfig;
+use crate::part_cache::{PartCache, ProposalPartsRequest};
 use crate::store::{DecidedValue, Store};
 use crate::streaming::{PartStreamsMap, ProposalParts};
 
 /// Number of historical values to keep in the store
 const HISTORY_LENGTH: u64 = 1000;
 
+/// Number of recently assembled proposals whose parts are kept in the cache
+const PART_CACHE_CAPACITY: usize = 32;
+
 /// Represents the internal state of the application node
 /// Contains information about current height, round, proposals and blocks
 pub struct State {
@@ -43,6 +47,7 @@ pub struct State {
     address: Address,
     vote_extensions: HashMap<Height, VoteExtensions<TestContext>>,
     streams_map: PartStreamsMap,
+    part_cache: PartCache,
     rng: StdRng,
     validator_rotation: ValidatorRotationConfig,
 
@@ -125,6 +130,7 @@ impl State {
             store,
             vote_extensions: HashMap::new(),
             streams_map: PartStreamsMap::new(),
+            part_cache: PartCache::new(PART_CACHE_CAPACITY),
             rng: StdRng::seed_from_u64(seed_from_address(&address, std::process::id() as u64)),
             validator_rotation,
         }
@@ -251,7 +257,8 @@ impl State {
         match self.validate_proposal_parts(&parts) {
             Ok(()) => {
                 // Validation passed - assemble and store as undecided
-                let value = Self::assemble_value_from_parts(parts)?;
+                let value = Self::assemble_value_from_parts(parts.clone())?;
+                self.part_cache.insert(value.value.id(), parts);
                 info!(%value.height, %value.round, %value.proposer, "Storing validated proposal as undecided");
                 self.store.store_undecided_proposal(value.clone()).await?;
                 Ok(Some(value))
@@ -305,9 +312,10 @@ impl State {
             .store_decided_value(&certificate, proposal.value)
             .await?;
 
-        // Prune the store, keep the last HISTORY_LENGTH decided values, remove all undecided proposals for the decided height
-        let retain_height = Height::new(height.as_u64().saturating_sub(HISTORY_LENGTH));
-        self.store.prune(height, retain_height).await?;
+        // Prune the store of all the undecided proposals for the decided height,
+        // the decided values are pruned in the background, see `retain_height`
+        self.store.prune(height).await?;
+        self.part_cache.prune(height.increment());
 
         // Move to next height
         self.current_height = self.current_height.increment();
@@ -316,6 +324,15 @@ impl State {
         Ok(())
     }
 
+    /// Height below which the decided values are pruned, keeping the last HISTORY_LENGTH ones
+    pub fn retain_height(&self) -> Height {
+        Height::new(
+            self.current_height
+                .as_u64()
+                .saturating_sub(HISTORY_LENGTH + 1),
+        )
+    }
+
     /// Retrieves a previously built proposal value for the given height and round.
     /// Called by the consensus engine to re-use a previously built value.
     /// There should be at most one proposal for a given height and round when the proposer is not byzantine.
@@ -423,7 +440,92 @@ impl State {
         value: LocallyProposedValue<TestContext>,
         pol_round: Round,
     ) -> impl Iterator<Item = StreamMessage<ProposalPart>> {
-        let parts = self.value_to_parts(value, pol_round);
+        let factors = factor_value(value.value.clone());
+        let parts = self.make_parts(value.height, value.round, pol_round, factors);
+
+        self.part_cache.insert(
+            value.value.id(),
+            ProposalParts {
+                height: value.height,
+                round: value.round,
+                proposer: self.address,
+                parts: parts.clone(),
+            },
+        );
+
+        self.stream_parts(parts)
+    }
+
+    /// Re-stream a proposal whose parts are in the cache in a later round,
+    /// re-using its data parts rather than splitting the value into parts again.
+    /// Returns `None` if the parts of the proposal are not in the cache.
+    pub fn restream_cached_proposal(
+        &mut self,
+        height: Height,
+        round: Round,
+        valid_round: Round,
+        value_id: ValueId,
+    ) -> Option<impl Iterator<Item = StreamMessage<ProposalPart>>> {
+        let proposal_round = if valid_round == Round::Nil {
+            round
+        } else {
+            valid_round
+        };
+
+        let cached = self.part_cache.get(height, proposal_round, value_id)?;
+
+        let factors = cached
+            .parts
+            .iter()
+            .filter_map(|part| part.as_data())
+            .map(|data| data.factor)
+            .collect();
+
+        let parts = self.make_parts(height, round, valid_round, factors);
+
+        self.part_cache.insert(
+            value_id,
+            ProposalParts {
+                height,
+                round,
+                proposer: self.address,
+                parts: parts.clone(),
+            },
+        );
+
+        Some(self.stream_parts(parts))
+    }
+
+    /// Serve the parts of a cached proposal to a peer asking for them, see [`ProposalPartsRequest`].
+    /// Returns `None` if the request is invalid or the parts are not in the cache.
+    pub fn serve_proposal_parts(&self, from: PeerId, request: &[u8]) -> Option<Bytes> {
+        let request: ProposalPartsRequest = match serde_json::from_slice(request) {
+            Ok(request) => request,
+            Err(e) => {
+                debug!(%from, "Invalid proposal parts request: {e}");
+                return None;
+            }
+        };
+
+        let Some(parts) = self
+            .part_cache
+            .get(request.height, request.round, request.value_id)
+        else {
+            debug!(
+                %from, height = %request.height, round = %request.round,
+                "Proposal parts requested by peer are not in the cache"
+            );
+            return None;
+        };
+
+        serde_json::to_vec(parts).ok().map(Bytes::from)
+    }
+
+    /// Split the parts of a proposal into the messages of a new stream
+    fn stream_parts(
+        &self,
+        parts: Vec<ProposalPart>,
+    ) -> impl Iterator<Item = StreamMessage<ProposalPart>> {
         let stream_id = self.stream_id();
 
         let mut msgs = Vec::with_capacity(parts.len() + 1);
@@ -451,10 +553,12 @@ impl State {
         StreamId::new(bytes.into())
     }
 
-    fn value_to_parts(
+    fn make_parts(
         &self,
-        value: LocallyProposedValue<TestContext>,
+        height: Height,
+        round: Round,
         pol_round: Round,
+        factors: Vec<u64>,
     ) -> Vec<ProposalPart> {
         let mut hasher = sha3::Keccak256::new();
         let mut parts = Vec::new();
@@ -463,20 +567,20 @@ impl State {
         // Include metadata about the proposal
         {
             parts.push(ProposalPart::Init(ProposalInit::new(
-                value.height,
-                value.round,
+                height,
+                round,
                 pol_round,
                 self.address,
             )));
 
-            hasher.update(value.height.as_u64().to_be_bytes().as_slice());
-            hasher.update(value.round.as_i64().to_be_bytes().as_slice());
+            hasher.update(height.as_u64().to_be_bytes().as_slice());
+            hasher.update(round.as_i64().to_be_bytes().as_slice());
         }
 
         // Data
         // Include each prime factor of the value as a separate proposal part
         {
-            for factor in factor_value(value.value) {
+            for factor in factors {
                 parts.push(ProposalPart::Data(ProposalData::new(factor)));
 
                 hasher.update(factor.to_be_bytes().as_slice());
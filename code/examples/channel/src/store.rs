@@ -7,6 +7,7 @@ use bytes::Bytes;
 use prost::Message;
 use redb::ReadableTable;
 use thiserror::Error;
+use tracing::{debug, error};
 
 use malachitebft_app_channel::app::types::codec::Codec;
 use malachitebft_app_channel::app::types::core::{CommitCertificate, Round};
@@ -356,7 +357,7 @@ impl Db {
         ValueId::new(u64::from_be_bytes(bytes))
     }
 
-    fn prune(&self, current_height: Height, retain_height: Height) -> Result<(), StoreError> {
+    fn prune(&self, current_height: Height) -> Result<(), StoreError> {
         let start = Instant::now();
 
         let tx = self.db.begin_write().unwrap();
@@ -369,7 +370,21 @@ impl Db {
             // Remove all pending proposals with height <= current_height
             let mut pending = tx.open_table(PENDING_PROPOSAL_PARTS_TABLE)?;
             pending.retain(|k, _| k.0 > current_height)?;
+        };
+
+        tx.commit()?;
 
+        self.metrics.observe_delete_time(start.elapsed());
+
+        Ok(())
+    }
+
+    fn prune_decided(&self, retain_height: Height) -> Result<(), StoreError> {
+        let start = Instant::now();
+
+        let tx = self.db.begin_write().unwrap();
+
+        {
             // Prune decided values and certificates up to the retain height
             let mut decided = tx.open_table(DECIDED_VALUES_TABLE)?;
             let mut certificates = tx.open_table(CERTIFICATES_TABLE)?;
@@ -577,13 +592,27 @@ impl Store {
 
     /// Prunes the store by removing all undecided proposals and decided values up to the retain height.
     /// Called by the application to clean up old data and free up space. This is done when a new value is committed.
-    pub async fn prune(
-        &self,
-        current_height: Height,
-        retain_height: Height,
-    ) -> Result<(), StoreError> {
+    pub async fn prune(&self, current_height: Height) -> Result<(), StoreError> {
+        let db = Arc::clone(&self.db);
+        tokio::task::spawn_blocking(move || db.prune(current_height)).await?
+    }
+
+    /// Deletes the decided values and their certificates below the retain height in the
+    /// background, without waiting for the deletion to complete.
+    /// Sync should be told about the retain height beforehand, so that these values are
+    /// no longer served to the peers.
+    pub fn prune_decided(&self, retain_height: Height) {
         let db = Arc::clone(&self.db);
-        tokio::task::spawn_blocking(move || db.prune(current_height, retain_height)).await?
+
+        tokio::spawn(async move {
+            let result = tokio::task::spawn_blocking(move || db.prune_decided(retain_height)).await;
+
+            match result {
+                Ok(Ok(())) => debug!(%retain_height, "Pruned decided values"),
+                Ok(Err(e)) => error!(%retain_height, "Failed to prune decided values: {e}"),
+                Err(e) => error!(%retain_height, "Pruning task of decided values failed: {e}"),
+            }
+        });
     }
 
     /// Retrieves an undecided proposal by its value ID.
@@ -0,0 +1,23 @@
+//! Decoding of the consensus messages, liveness messages and proposal parts gossiped by peers
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use malachitebft_core_consensus::{LivenessMsg, ProposedValue, SignedConsensusMsg};
+use malachitebft_engine::util::streaming::StreamMessage;
+use malachitebft_fuzz::decode_and_encode;
+use malachitebft_test::codec::json::JsonCodec;
+use malachitebft_test::codec::proto::ProtobufCodec;
+use malachitebft_test::{ProposalPart, TestContext};
+
+fuzz_target!(|data: &[u8]| {
+    decode_and_encode::<SignedConsensusMsg<TestContext>, _>(&ProtobufCodec, data);
+    decode_and_encode::<LivenessMsg<TestContext>, _>(&ProtobufCodec, data);
+    decode_and_encode::<StreamMessage<ProposalPart>, _>(&ProtobufCodec, data);
+    decode_and_encode::<ProposedValue<TestContext>, _>(&ProtobufCodec, data);
+
+    decode_and_encode::<SignedConsensusMsg<TestContext>, _>(&JsonCodec, data);
+    decode_and_encode::<LivenessMsg<TestContext>, _>(&JsonCodec, data);
+    decode_and_encode::<StreamMessage<ProposalPart>, _>(&JsonCodec, data);
+});
@@ -0,0 +1,13 @@
+//! Decoding of the discovery protocol requests and responses,
+//! with the CBOR encoding of the request-response behaviour
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use malachitebft_discovery::{Request, Response};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = cbor4ii::serde::from_slice::<Request>(data);
+    let _ = cbor4ii::serde::from_slice::<Response>(data);
+});
@@ -0,0 +1,21 @@
+//! Decoding of the status updates, requests and responses exchanged by the sync protocol
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use malachitebft_fuzz::decode_and_encode;
+use malachitebft_sync::{Request, Response, Status};
+use malachitebft_test::codec::json::JsonCodec;
+use malachitebft_test::codec::proto::ProtobufCodec;
+use malachitebft_test::TestContext;
+
+fuzz_target!(|data: &[u8]| {
+    decode_and_encode::<Status<TestContext>, _>(&ProtobufCodec, data);
+    decode_and_encode::<Request<TestContext>, _>(&ProtobufCodec, data);
+    decode_and_encode::<Response<TestContext>, _>(&ProtobufCodec, data);
+
+    decode_and_encode::<Status<TestContext>, _>(&JsonCodec, data);
+    decode_and_encode::<Request<TestContext>, _>(&JsonCodec, data);
+    decode_and_encode::<Response<TestContext>, _>(&JsonCodec, data);
+});
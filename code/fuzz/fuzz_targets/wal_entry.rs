@@ -0,0 +1,15 @@
+//! Decoding of the entries read back from a WAL, which may have been damaged on disk
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use malachitebft_engine::wal::{decode_entry, encode_entry};
+use malachitebft_test::codec::proto::ProtobufCodec;
+use malachitebft_test::TestContext;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(entry) = decode_entry::<TestContext, _, _>(&ProtobufCodec, data) {
+        let _ = encode_entry(&entry, &ProtobufCodec, Vec::new());
+    }
+});
@@ -0,0 +1,97 @@
+//! Write the initial corpus of every fuzz target, made of well-formed messages,
+//! to `corpus/<target>/` in the current directory.
+//!
+//! Usage: `cargo run --bin generate_corpus -- [<count per target>] [<seed>]`
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use malachitebft_codec::Codec;
+use malachitebft_fuzz::samples::Samples;
+use malachitebft_test::codec::proto::ProtobufCodec;
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let count = args
+        .next()
+        .map_or(Ok(64), |arg| arg.parse())
+        .map_err(invalid_input)?;
+    let seed = args
+        .next()
+        .map_or(Ok(0), |arg| arg.parse())
+        .map_err(invalid_input)?;
+
+    let mut samples = Samples::new(StdRng::seed_from_u64(seed));
+
+    for i in 0..count {
+        write(
+            "consensus_msg",
+            i,
+            &ProtobufCodec.encode(&samples.consensus_msg()),
+        )?;
+        write(
+            "consensus_msg",
+            count + i,
+            &ProtobufCodec.encode(&samples.liveness_msg()),
+        )?;
+        write(
+            "consensus_msg",
+            2 * count + i,
+            &ProtobufCodec.encode(&samples.proposal_part()),
+        )?;
+        write(
+            "consensus_msg",
+            3 * count + i,
+            &ProtobufCodec.encode(&samples.proposed_value()),
+        )?;
+
+        write("sync_msg", i, &ProtobufCodec.encode(&samples.sync_status()))?;
+        write(
+            "sync_msg",
+            count + i,
+            &ProtobufCodec.encode(&samples.sync_request()),
+        )?;
+        write(
+            "sync_msg",
+            2 * count + i,
+            &ProtobufCodec.encode(&samples.sync_response()),
+        )?;
+
+        write("wal_entry", i, &samples.encoded_wal_entry())?;
+
+        write("discovery_msg", i, &cbor(&samples.discovery_request()))?;
+        write(
+            "discovery_msg",
+            count + i,
+            &cbor(&samples.discovery_response()),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write<B, E>(target: &str, index: usize, bytes: &Result<B, E>) -> io::Result<()>
+where
+    B: AsRef<[u8]>,
+    E: std::fmt::Debug,
+{
+    let bytes = bytes
+        .as_ref()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}")))?;
+
+    let dir = Path::new("corpus").join(target);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(format!("{index:06}")), bytes)
+}
+
+fn cbor<T: serde::Serialize>(msg: &T) -> Result<Vec<u8>, String> {
+    cbor4ii::serde::to_vec(Vec::new(), msg).map_err(|e| e.to_string())
+}
+
+fn invalid_input(e: std::num::ParseIntError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
@@ -0,0 +1,21 @@
+//! Fuzz targets for the decoders of the messages a node receives from its peers
+//! and reads back from its WAL.
+//!
+//! Whatever the input, decoding must either succeed or return an error,
+//! never panic nor allocate memory out of proportion to the input size.
+
+use bytes::Bytes;
+
+use malachitebft_codec::Codec;
+
+pub mod samples;
+
+/// Decode a message from the given bytes and, if they are well-formed, encode it back
+pub fn decode_and_encode<T, C>(codec: &C, data: &[u8])
+where
+    C: Codec<T>,
+{
+    if let Ok(msg) = codec.decode(Bytes::copy_from_slice(data)) {
+        let _ = codec.encode(&msg);
+    }
+}
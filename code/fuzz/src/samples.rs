@@ -0,0 +1,342 @@
+//! Well-formed messages of every kind, from which the initial corpus of the fuzz targets is built.
+//!
+//! Starting from valid messages lets the fuzzer reach the decoding of nested fields
+//! right away, instead of having to discover the message structure from random bytes.
+
+use std::io;
+
+use bytes::Bytes;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore};
+
+use malachitebft_codec::Codec;
+use malachitebft_core_consensus::{LivenessMsg, ProposedValue, SignedConsensusMsg, WalEntry};
+use malachitebft_core_types::{
+    CommitCertificate, Height as _, NilOrVal, PolkaCertificate, Round, SignedMessage, SignedVote,
+    Timeout, TimeoutKind, Validity,
+};
+use malachitebft_discovery::{DisconnectReason, NodeRole, PeerCapabilities, RelayLoad};
+use malachitebft_engine::util::streaming::{StreamContent, StreamId, StreamMessage};
+use malachitebft_engine::wal::encode_entry;
+use malachitebft_peer::PeerId;
+use malachitebft_sync::{self as sync, RawDecidedValue, TraceId, ValueRequest, ValueResponse};
+use malachitebft_test::codec::proto::ProtobufCodec;
+use malachitebft_test::utils::validators::make_validators_seeded;
+use malachitebft_test::{
+    Address, Height, PrivateKey, Proposal, ProposalData, ProposalFin, ProposalInit, ProposalPart,
+    TestContext, Value, ValueId, Vote,
+};
+
+/// Builds random, but well-formed and properly signed, messages
+pub struct Samples {
+    rng: StdRng,
+    keys: Vec<(Address, PrivateKey)>,
+}
+
+impl Samples {
+    pub fn new(mut rng: StdRng) -> Self {
+        let keys = make_validators_seeded([1, 1, 1, 1], rng.next_u64())
+            .into_iter()
+            .map(|(validator, key)| (validator.address, key))
+            .collect();
+
+        Self { rng, keys }
+    }
+
+    fn height(&mut self) -> Height {
+        Height::new(self.rng.gen_range(1..1000))
+    }
+
+    fn round(&mut self) -> Round {
+        Round::new(self.rng.gen_range(0..10))
+    }
+
+    fn pol_round(&mut self) -> Round {
+        if self.rng.gen_bool(0.5) {
+            Round::Nil
+        } else {
+            self.round()
+        }
+    }
+
+    fn value(&mut self) -> Value {
+        Value::new(self.rng.next_u64())
+    }
+
+    fn signer(&mut self) -> (Address, PrivateKey) {
+        let index = self.rng.gen_range(0..self.keys.len());
+        self.keys[index].clone()
+    }
+
+    fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; self.rng.gen_range(0..=max_len)];
+        self.rng.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    fn vote(
+        &mut self,
+        height: Height,
+        round: Round,
+        value_id: NilOrVal<ValueId>,
+    ) -> SignedVote<TestContext> {
+        let (address, key) = self.signer();
+
+        let vote = if self.rng.gen_bool(0.5) {
+            Vote::new_prevote(height, round, value_id, address)
+        } else {
+            Vote::new_precommit(height, round, value_id, address)
+        };
+
+        let signature = key.sign(&vote.to_sign_bytes());
+        SignedMessage::new(vote, signature)
+    }
+
+    fn random_vote(&mut self) -> SignedVote<TestContext> {
+        let (height, round) = (self.height(), self.round());
+
+        let value_id = if self.rng.gen_bool(0.5) {
+            NilOrVal::Val(ValueId::new(self.rng.next_u64()))
+        } else {
+            NilOrVal::Nil
+        };
+
+        self.vote(height, round, value_id)
+    }
+
+    /// Signed votes of the given type for the same value, as certificates are made of
+    fn votes_for(
+        &mut self,
+        height: Height,
+        round: Round,
+        value_id: ValueId,
+        precommits: bool,
+    ) -> Vec<SignedVote<TestContext>> {
+        self.keys
+            .clone()
+            .into_iter()
+            .map(|(address, key)| {
+                let vote = if precommits {
+                    Vote::new_precommit(height, round, NilOrVal::Val(value_id), address)
+                } else {
+                    Vote::new_prevote(height, round, NilOrVal::Val(value_id), address)
+                };
+
+                let signature = key.sign(&vote.to_sign_bytes());
+                SignedMessage::new(vote, signature)
+            })
+            .collect()
+    }
+
+    pub fn consensus_msg(&mut self) -> SignedConsensusMsg<TestContext> {
+        if self.rng.gen_bool(0.5) {
+            return SignedConsensusMsg::Vote(self.random_vote());
+        }
+
+        let (height, round, pol_round) = (self.height(), self.round(), self.pol_round());
+        let value = self.value();
+        let (address, key) = self.signer();
+
+        let proposal = Proposal::new(height, round, value, pol_round, address);
+        let signature = key.sign(&proposal.to_sign_bytes());
+
+        SignedConsensusMsg::Proposal(SignedMessage::new(proposal, signature))
+    }
+
+    pub fn liveness_msg(&mut self) -> LivenessMsg<TestContext> {
+        let (height, round) = (self.height(), self.round());
+        let value_id = ValueId::new(self.rng.next_u64());
+
+        match self.rng.gen_range(0..2) {
+            0 => LivenessMsg::Vote(self.random_vote()),
+            _ => {
+                let votes = self.votes_for(height, round, value_id, false);
+                LivenessMsg::PolkaCertificate(PolkaCertificate::new(height, round, value_id, votes))
+            }
+        }
+    }
+
+    pub fn proposal_part(&mut self) -> StreamMessage<ProposalPart> {
+        let stream_id = StreamId::new(Bytes::from(self.bytes(16)));
+        let sequence = self.rng.gen_range(0..10);
+
+        let content = match self.rng.gen_range(0..4) {
+            0 => {
+                let (height, round, pol_round) = (self.height(), self.round(), self.pol_round());
+                let (address, _) = self.signer();
+
+                StreamContent::Data(ProposalPart::Init(ProposalInit::new(
+                    height, round, pol_round, address,
+                )))
+            }
+            1 => StreamContent::Data(ProposalPart::Data(ProposalData::new(self.rng.next_u64()))),
+            2 => {
+                let (_, key) = self.signer();
+                let signature = key.sign(&self.bytes(32));
+
+                StreamContent::Data(ProposalPart::Fin(ProposalFin::new(signature)))
+            }
+            _ => StreamContent::Fin,
+        };
+
+        StreamMessage::new(stream_id, sequence, content)
+    }
+
+    pub fn proposed_value(&mut self) -> ProposedValue<TestContext> {
+        let (height, round, valid_round) = (self.height(), self.round(), self.pol_round());
+        let (proposer, _) = self.signer();
+
+        ProposedValue {
+            height,
+            round,
+            valid_round,
+            proposer,
+            value: self.value(),
+            validity: if self.rng.gen_bool(0.9) {
+                Validity::Valid
+            } else {
+                Validity::Invalid
+            },
+        }
+    }
+
+    pub fn sync_status(&mut self) -> sync::Status<TestContext> {
+        let history_min_height = self.height();
+        let tip_height = history_min_height.increment_by(self.rng.gen_range(0..100));
+
+        sync::Status {
+            peer_id: PeerId::random(),
+            tip_height,
+            history_min_height,
+            min_needed_height: self.rng.gen_bool(0.5).then(|| tip_height.increment()),
+        }
+    }
+
+    pub fn sync_request(&mut self) -> sync::Request<TestContext> {
+        let start = self.height();
+        let end = start.increment_by(self.rng.gen_range(0..10));
+
+        sync::Request::ValueRequest(ValueRequest {
+            range: start..=end,
+            trace_id: TraceId(self.rng.next_u64()),
+            certificates_only: self.rng.gen_bool(0.2),
+        })
+    }
+
+    pub fn sync_response(&mut self) -> sync::Response<TestContext> {
+        let start_height = self.height();
+        let count = self.rng.gen_range(0..4);
+
+        let values = (0..count)
+            .map(|offset| {
+                let height = start_height.increment_by(offset);
+                let round = self.round();
+                let value = self.value();
+                let votes = self.votes_for(height, round, value.id(), true);
+
+                let value_bytes = Codec::<Value>::encode(&ProtobufCodec, &value).unwrap();
+                let certificate = CommitCertificate::new(height, round, value.id(), votes);
+
+                RawDecidedValue::new(value_bytes, certificate)
+            })
+            .collect();
+
+        let response =
+            ValueResponse::new(start_height, values).with_trace_id(TraceId(self.rng.next_u64()));
+
+        sync::Response::ValueResponse(response)
+    }
+
+    pub fn wal_entry(&mut self) -> WalEntry<TestContext> {
+        match self.rng.gen_range(0..3) {
+            0 => WalEntry::ConsensusMsg(self.consensus_msg()),
+            1 => {
+                let kind = match self.rng.gen_range(0..3) {
+                    0 => TimeoutKind::Propose,
+                    1 => TimeoutKind::Prevote,
+                    _ => TimeoutKind::Precommit,
+                };
+
+                WalEntry::Timeout(Timeout::new(self.round(), kind))
+            }
+            _ => WalEntry::ProposedValue(self.proposed_value()),
+        }
+    }
+
+    /// A WAL entry, encoded as the WAL writes it to disk
+    pub fn encoded_wal_entry(&mut self) -> io::Result<Vec<u8>> {
+        let entry = self.wal_entry();
+
+        let mut buf = Vec::new();
+        encode_entry(&entry, &ProtobufCodec, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn peer_records(&mut self) -> Vec<Vec<u8>> {
+        (0..self.rng.gen_range(0..4))
+            .map(|_| self.bytes(256))
+            .collect()
+    }
+
+    fn relay_load(&mut self) -> RelayLoad {
+        let max_circuits = self.rng.gen_range(1..100);
+
+        RelayLoad {
+            circuits: self.rng.gen_range(0..=max_circuits),
+            max_circuits,
+        }
+    }
+
+    fn peer_capabilities(&mut self) -> PeerCapabilities {
+        let role = match self.rng.gen_range(0..4) {
+            0 => None,
+            1 => Some(NodeRole::Validator),
+            2 => Some(NodeRole::FullNode),
+            _ => Some(NodeRole::Relay),
+        };
+
+        PeerCapabilities {
+            role,
+            chain_id: self.rng.gen_bool(0.5).then(|| "test-chain".to_string()),
+            retained_height: self.rng.gen_bool(0.5).then(|| self.rng.gen()),
+            relay_capacity: self.rng.gen_bool(0.5).then(|| self.rng.gen_range(1..100)),
+        }
+    }
+
+    pub fn discovery_request(&mut self) -> malachitebft_discovery::Request {
+        use malachitebft_discovery::Request;
+
+        match self.rng.gen_range(0..4) {
+            0 => Request::Peers(self.peer_records()),
+            1 => Request::Connect(self.peer_capabilities()),
+            2 => {
+                let reason = match self.rng.gen_range(0..6) {
+                    0 => DisconnectReason::EphemeralTimeout,
+                    1 => DisconnectReason::PeerLimit,
+                    2 => DisconnectReason::NotAllowed,
+                    3 => DisconnectReason::Evicted,
+                    4 => DisconnectReason::Banned,
+                    _ => DisconnectReason::Shutdown,
+                };
+
+                Request::Disconnect(reason)
+            }
+            _ => Request::RelayLoad(),
+        }
+    }
+
+    pub fn discovery_response(&mut self) -> malachitebft_discovery::Response {
+        use malachitebft_discovery::Response;
+
+        match self.rng.gen_range(0..5) {
+            0 => Response::Peers(self.peer_records()),
+            1 => Response::Connect(self.rng.gen_bool(0.5), self.peer_capabilities()),
+            2 => Response::Disconnect(),
+            3 => {
+                let records = self.peer_records();
+                Response::PeersWithRelayLoad(records, self.relay_load())
+            }
+            _ => Response::RelayLoad(self.rng.gen_bool(0.5).then(|| self.relay_load())),
+        }
+    }
+}